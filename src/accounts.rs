@@ -2,9 +2,11 @@
 //! Provides user registration, login, session management, and profiles
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use anyhow::Result;
@@ -16,7 +18,8 @@ use argon2::password_hash::{rand_core::OsRng, SaltString};
 pub struct User {
     pub user_id: Uuid,
     pub username: String,
-    #[serde(skip_serializing)]
+    /// Argon2 hash; saved with the account so it can still log in after a restart.
+    /// Users are never sent over the network
     pub password_hash: String,
     pub email: String,
     pub created_at: DateTime<Utc>,
@@ -65,13 +68,24 @@ pub struct UserStats {
     pub average_accuracy: f64,
     pub best_accuracy: f64,
     pub songs_played: HashMap<String, SongStats>,
+    /// Totals per season, keyed by season id
+    #[serde(default)]
+    pub seasons: HashMap<String, SeasonTotals>,
+}
+
+/// A user's competitive totals within one season
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeasonTotals {
+    pub total_games: u32,
+    pub total_score: u64,
+    pub average_accuracy: f64,
 }
 
 /// Statistics for a specific song
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SongStats {
     pub plays: u32,
-    pub high_score: u32,
+    pub high_score: i64,
     pub best_combo: u32,
     pub best_accuracy: f64,
     pub grade_counts: HashMap<String, u32>,
@@ -89,6 +103,48 @@ impl Default for SongStats {
     }
 }
 
+impl SongStats {
+    /// Best grade this song has been played with
+    pub fn best_grade(&self) -> Option<&'static str> {
+        GRADE_ORDER
+            .iter()
+            .copied()
+            .find(|grade| self.grade_counts.get(*grade).is_some_and(|count| *count > 0))
+            .map(|grade| if grade == "AAA" { "SS+" } else { grade })
+    }
+
+    /// Best result on the song, for comparisons
+    pub fn record(&self) -> SongRecord {
+        SongRecord {
+            best_score: self.high_score,
+            best_accuracy: self.best_accuracy,
+            grade: self.best_grade().unwrap_or("-").to_string(),
+        }
+    }
+}
+
+/// Grade names, best first. "AAA" is what SS+ was called in older saves
+pub const GRADE_ORDER: [&str; 9] = ["SS+", "AAA", "SS", "S", "A", "B", "C", "D", "F"];
+
+/// A player's best result on one song
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SongRecord {
+    pub best_score: i64,
+    pub best_accuracy: f64,
+    pub grade: String,
+}
+
+/// Per-song records a user shares with other players
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProfileRecords {
+    Public {
+        username: String,
+        songs: HashMap<String, SongRecord>,
+    },
+    /// The user's profile is not public
+    Private { username: String },
+}
+
 impl Default for UserStats {
     fn default() -> Self {
         Self {
@@ -103,6 +159,7 @@ impl Default for UserStats {
             average_accuracy: 0.0,
             best_accuracy: 0.0,
             songs_played: HashMap::new(),
+            seasons: HashMap::new(),
         }
     }
 }
@@ -136,7 +193,10 @@ impl User {
     pub fn new(username: String, password: &str, email: String) -> Result<Self> {
         let salt = SaltString::generate(&mut OsRng);
         let argon2 = Argon2::default();
-        let password_hash = argon2.hash_password(password.as_bytes(), &salt)?.to_string();
+        let password_hash = argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))?
+            .to_string();
 
         Ok(Self {
             user_id: Uuid::new_v4(),
@@ -157,15 +217,16 @@ impl User {
 
     /// Verify password
     pub fn verify_password(&self, password: &str) -> Result<bool> {
-        let parsed_hash = PasswordHash::new(&self.password_hash)?;
+        let parsed_hash = PasswordHash::new(&self.password_hash)
+            .map_err(|e| anyhow::anyhow!("Stored password hash is invalid: {}", e))?;
         let argon2 = Argon2::default();
         Ok(argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok())
     }
 
     /// Update user stats after a game
-    pub fn update_stats(&mut self, score: u32, combo: u32, accuracy: f64, song_name: String, play_time: u64) {
+    pub fn update_stats(&mut self, score: i64, combo: u32, accuracy: f64, song_name: String, play_time: u64) {
         self.stats.total_games += 1;
-        self.stats.total_score += score as u64;
+        self.stats.total_score = self.stats.total_score.saturating_add_signed(score);
         self.stats.highest_combo = self.stats.highest_combo.max(combo);
         self.stats.play_time_seconds += play_time;
 
@@ -182,6 +243,15 @@ impl User {
         song_stats.best_accuracy = song_stats.best_accuracy.max(accuracy);
     }
 
+    /// Add a game to a season's totals
+    pub fn update_season(&mut self, season: &str, score: i64, accuracy: f64) {
+        let totals = self.stats.seasons.entry(season.to_string()).or_default();
+        totals.total_games += 1;
+        totals.total_score = totals.total_score.saturating_add_signed(score);
+        let total_acc = totals.average_accuracy * (totals.total_games - 1) as f64;
+        totals.average_accuracy = (total_acc + accuracy) / totals.total_games as f64;
+    }
+
     /// Update hit statistics
     pub fn update_hits(&mut self, perfect: u32, good: u32, ok: u32, misses: u32) {
         self.stats.perfect_hits += perfect;
@@ -202,6 +272,49 @@ impl User {
     }
 }
 
+/// A finished play as the account stats record it
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub song_name: String,
+    pub score: i64,
+    pub max_combo: u32,
+    /// Accuracy percentage
+    pub accuracy: f64,
+    /// Perfect, good, ok and missed hits
+    pub hits: [u32; 4],
+    /// Seconds played
+    pub play_time: u64,
+    /// Practice run (autoplay or no fail), kept out of the competitive stats
+    pub practice: bool,
+    /// Id of the season the game counts toward; None keeps it off every season board
+    pub season: Option<String>,
+}
+
+/// Sessions older than this are purged even if their expiry says otherwise
+pub const MAX_SESSION_AGE_DAYS: i64 = 90;
+
+/// How often a running server cleans up stored account data
+pub const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Files account data is stored in, under the data path
+const USERS_FILE: &str = "users.json";
+const SESSIONS_FILE: &str = "sessions.json";
+const FRIENDS_FILE: &str = "friends.json";
+const FRIEND_REQUESTS_FILE: &str = "friend_requests.json";
+
+/// Wrong passwords in a row that lock an account
+pub const MAX_FAILED_LOGINS: u32 = 5;
+
+/// How long a locked account refuses to log in (minutes)
+pub const LOGIN_LOCKOUT_MINUTES: i64 = 15;
+
+/// Wrong passwords given for an account since it last logged in
+#[derive(Debug, Clone, Default)]
+struct LoginFailures {
+    count: u32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
 /// Session information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -233,6 +346,84 @@ impl Session {
     pub fn is_expired(&self) -> bool {
         Utc::now() > self.expires_at
     }
+
+    /// Whether the session should be dropped from storage: expired, or older than
+    /// `MAX_SESSION_AGE_DAYS` however far away its expiry is
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        now > self.expires_at
+            || now - self.created_at > chrono::Duration::days(MAX_SESSION_AGE_DAYS)
+    }
+}
+
+/// What a cleanup of account storage removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub sessions_removed: usize,
+    /// Friend entries of, or pointing at, users that no longer exist
+    pub friends_removed: usize,
+    pub requests_removed: usize,
+}
+
+impl MaintenanceReport {
+    /// Whether anything was removed
+    pub fn is_empty(&self) -> bool {
+        self.sessions_removed == 0 && self.friends_removed == 0 && self.requests_removed == 0
+    }
+}
+
+/// One stored file and how many entries it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredFile {
+    pub name: &'static str,
+    /// Size on disk; 0 if the file hasn't been written yet
+    pub bytes: u64,
+    pub entries: usize,
+}
+
+/// Size of the account data on disk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    pub files: Vec<StoredFile>,
+}
+
+impl StorageStats {
+    /// Size of all files together
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.bytes).sum()
+    }
+
+    /// One line summary, e.g. "Storage: 12.4 KB (users 3, sessions 5, ...)"
+    pub fn summary(&self) -> String {
+        let entries: Vec<String> = self.files.iter()
+            .map(|file| format!("{} {}", file.name.trim_end_matches(".json"), file.entries))
+            .collect();
+        format!("Storage: {} ({})", format_bytes(self.total_bytes()), entries.join(", "))
+    }
+}
+
+/// Byte count as "512 B", "12.4 KB" or "3.1 MB"
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Write a file by writing a temporary file next to it and renaming it into place,
+/// so a crash mid-write leaves the old file intact rather than a truncated one
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Serialize a value as pretty JSON into a file atomically
+fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    write_atomic(path, &serde_json::to_string_pretty(value)?)
 }
 
 /// Friend relationship
@@ -251,6 +442,29 @@ pub enum FriendStatus {
     Blocked,
 }
 
+impl FriendStatus {
+    /// Name shown next to a friend
+    pub fn label(&self) -> &'static str {
+        match self {
+            FriendStatus::Pending => "Pending",
+            FriendStatus::Accepted => "Friend",
+            FriendStatus::Blocked => "Blocked",
+        }
+    }
+}
+
+/// A user's friends list split into what a friends screen shows
+#[derive(Debug, Clone, Default)]
+pub struct FriendsSnapshot {
+    /// Accepted friends, by username
+    pub friends: Vec<Friend>,
+    /// Requests this user sent that haven't been answered
+    pub outgoing: Vec<Friend>,
+    /// Requests sent to this user, oldest first
+    pub incoming: Vec<Friend>,
+    pub blocked: Vec<Friend>,
+}
+
 /// Account manager for handling users, sessions, and friends
 #[derive(Debug, Clone)]
 pub struct AccountManager {
@@ -258,7 +472,11 @@ pub struct AccountManager {
     username_to_id: Arc<RwLock<HashMap<String, Uuid>>>,
     sessions: Arc<RwLock<HashMap<String, Session>>>,
     friends: Arc<RwLock<HashMap<Uuid, Vec<Friend>>>>,
-    leaderboard: Arc<RwLock<Vec<LeaderboardEntry>>>,
+    /// Pending requests keyed by the user they were sent to; `friend_id` is the requester
+    friend_requests: Arc<RwLock<HashMap<Uuid, Vec<Friend>>>>,
+    /// Kept in memory only, so a restart lifts every lock
+    login_failures: Arc<RwLock<HashMap<Uuid, LoginFailures>>>,
+    leaderboard: Arc<RwLock<Leaderboard>>,
     data_path: PathBuf,
 }
 
@@ -273,6 +491,185 @@ pub struct LeaderboardEntry {
     pub total_games: u32,
 }
 
+impl LeaderboardEntry {
+    /// Build an unranked entry from a user's current stats
+    fn from_user(user: &User) -> Self {
+        Self {
+            user_id: user.user_id,
+            username: user.username.clone(),
+            rank: 0,
+            total_score: user.stats.total_score,
+            average_accuracy: user.stats.average_accuracy,
+            total_games: user.stats.total_games,
+        }
+    }
+
+    /// Build an unranked entry from a user's totals in one season, if they played in it
+    fn from_season(user: &User, season: &str) -> Option<Self> {
+        let totals = user.stats.seasons.get(season)?;
+        Some(Self {
+            user_id: user.user_id,
+            username: user.username.clone(),
+            rank: 0,
+            total_score: totals.total_score,
+            average_accuracy: totals.average_accuracy,
+            total_games: totals.total_games,
+        })
+    }
+
+    /// Sort key: highest score first, ties broken by user id so order is stable
+    fn sort_key(&self) -> (Reverse<u64>, Uuid) {
+        (Reverse(self.total_score), self.user_id)
+    }
+}
+
+/// Which players a leaderboard view ranks, one per leaderboard tab
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LeaderboardScope {
+    /// Everyone
+    Global,
+    /// Players whose profile lists this country
+    Country(String),
+    /// This player and their accepted friends
+    Friends(Uuid),
+}
+
+/// One page of the leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardPage {
+    /// Entries on this page with their ranks filled in
+    pub entries: Vec<LeaderboardEntry>,
+    /// Offset of the first entry
+    pub offset: usize,
+    /// Total number of ranked players
+    pub total_count: usize,
+}
+
+/// Leaderboard kept sorted as stats change, so reads never re-sort.
+/// Ranks are positions in the sorted list and are filled in when entries are read.
+#[derive(Debug, Default)]
+pub struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+    /// Current score per user, used to find a user's entry by binary search
+    scores: HashMap<Uuid, u64>,
+}
+
+impl Leaderboard {
+    /// Build from a set of entries
+    pub fn from_entries(mut entries: Vec<LeaderboardEntry>) -> Self {
+        entries.sort_by_key(|e| e.sort_key());
+        let scores = entries.iter().map(|e| (e.user_id, e.total_score)).collect();
+        Self { entries, scores }
+    }
+
+    /// Number of ranked players
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no players are ranked
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index of a user's entry
+    fn position(&self, user_id: Uuid) -> Option<usize> {
+        let score = *self.scores.get(&user_id)?;
+        self.entries
+            .binary_search_by_key(&(Reverse(score), user_id), |e| e.sort_key())
+            .ok()
+    }
+
+    /// Insert or move a user's entry to its new place
+    pub fn upsert(&mut self, entry: LeaderboardEntry) {
+        if let Some(index) = self.position(entry.user_id) {
+            self.entries.remove(index);
+        }
+        let index = self
+            .entries
+            .binary_search_by_key(&entry.sort_key(), |e| e.sort_key())
+            .unwrap_or_else(|i| i);
+        self.scores.insert(entry.user_id, entry.total_score);
+        self.entries.insert(index, entry);
+    }
+
+    /// Remove a user's entry
+    pub fn remove(&mut self, user_id: Uuid) -> Option<LeaderboardEntry> {
+        let index = self.position(user_id)?;
+        self.scores.remove(&user_id);
+        Some(self.entries.remove(index))
+    }
+
+    /// 1-based rank of a user
+    pub fn rank_of(&self, user_id: Uuid) -> Option<u32> {
+        self.position(user_id).map(|index| index as u32 + 1)
+    }
+
+    /// Entries `offset..offset + count` with ranks filled in
+    pub fn page(&self, offset: usize, count: usize) -> LeaderboardPage {
+        let start = offset.min(self.entries.len());
+        let end = start.saturating_add(count).min(self.entries.len());
+        let entries = self.entries[start..end]
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| LeaderboardEntry {
+                rank: (start + i + 1) as u32,
+                ..entry.clone()
+            })
+            .collect();
+
+        LeaderboardPage {
+            entries,
+            offset: start,
+            total_count: self.entries.len(),
+        }
+    }
+
+    /// Offset of the page of `page_size` entries that contains a user
+    pub fn page_offset_of(&self, user_id: Uuid, page_size: usize) -> Option<usize> {
+        let index = self.position(user_id)?;
+        let page_size = page_size.max(1);
+        Some(index / page_size * page_size)
+    }
+}
+
+/// The `entries` (sorted best first) that belong to `members`, or all of them without
+/// members, ranked among themselves so their rows read 1, 2, 3...
+fn ranked_within(
+    entries: Vec<LeaderboardEntry>,
+    members: Option<&HashSet<Uuid>>,
+) -> Vec<LeaderboardEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| members.is_none_or(|members| members.contains(&entry.user_id)))
+        .enumerate()
+        .map(|(i, entry)| LeaderboardEntry {
+            rank: i as u32 + 1,
+            ..entry
+        })
+        .collect()
+}
+
+/// Check a registration form before any account is created: every field filled in,
+/// an email with an '@' and the password typed the same twice
+pub fn validate_registration(
+    username: &str,
+    email: &str,
+    password: &str,
+    confirm_password: &str,
+) -> Result<()> {
+    if username.trim().is_empty() || email.trim().is_empty() || password.is_empty() {
+        return Err(anyhow::anyhow!("Fill in every field"));
+    }
+    if !email.contains('@') {
+        return Err(anyhow::anyhow!("Email address needs an '@'"));
+    }
+    if password != confirm_password {
+        return Err(anyhow::anyhow!("Passwords don't match"));
+    }
+    Ok(())
+}
+
 impl AccountManager {
     /// Create a new account manager
     pub fn new(data_path: PathBuf) -> Self {
@@ -281,29 +678,39 @@ impl AccountManager {
             username_to_id: Arc::new(RwLock::new(HashMap::new())),
             sessions: Arc::new(RwLock::new(HashMap::new())),
             friends: Arc::new(RwLock::new(HashMap::new())),
-            leaderboard: Arc::new(RwLock::new(Vec::new())),
+            friend_requests: Arc::new(RwLock::new(HashMap::new())),
+            login_failures: Arc::new(RwLock::new(HashMap::new())),
+            leaderboard: Arc::new(RwLock::new(Leaderboard::default())),
             data_path,
         }
     }
 
     /// Register a new user
     pub async fn register(&self, username: String, password: String, email: String) -> Result<Uuid> {
-        // Check if username already exists
+        self.register_sync(username, password, email)
+    }
+
+    /// Register without awaiting, for callers outside the async runtime. Refuses
+    /// empty fields, an email without an '@' and a taken username
+    pub fn register_sync(&self, username: String, password: String, email: String) -> Result<Uuid> {
+        validate_registration(&username, &email, &password, &password)?;
+
+        // Hash the password before locking, it's the slow part
+        let user = User::new(username.clone(), &password, email)?;
+        let user_id = user.user_id;
+
+        // The check and the insert share one write lock, so two registrations
+        // racing for a name can't both get it
         {
-            let username_map = self.username_to_id.read().unwrap();
+            let mut username_map = self.username_to_id.write().unwrap();
             if username_map.contains_key(&username) {
                 return Err(anyhow::anyhow!("Username already exists"));
             }
+            self.leaderboard.write().unwrap().upsert(LeaderboardEntry::from_user(&user));
+            self.users.write().unwrap().insert(user_id, user);
+            username_map.insert(username, user_id);
         }
 
-        // Create new user
-        let user = User::new(username.clone(), &password, email)?;
-        let user_id = user.user_id;
-
-        // Store user
-        self.users.write().unwrap().insert(user_id, user.clone());
-        self.username_to_id.write().unwrap().insert(username, user_id);
-
         // Save to disk
         self.save_data()?;
 
@@ -312,6 +719,20 @@ impl AccountManager {
 
     /// Login user
     pub async fn login(&self, username: String, password: String, ip_address: Option<String>) -> Result<Session> {
+        self.login_sync(username, password, ip_address)
+    }
+
+    /// Login without awaiting, for callers outside the async runtime. Fails with
+    /// "User not found", "Invalid password" or, while the account is locked,
+    /// "Account locked, ..."
+    pub fn login_sync(&self, username: String, password: String, ip_address: Option<String>) -> Result<Session> {
+        self.login_at(username, password, ip_address, Utc::now())
+    }
+
+    /// Login as of `now`. Each wrong password counts toward `MAX_FAILED_LOGINS`, and
+    /// the last one locks the account for `LOGIN_LOCKOUT_MINUTES`, during which even
+    /// the right password is refused. A successful login clears the count
+    fn login_at(&self, username: String, password: String, ip_address: Option<String>, now: DateTime<Utc>) -> Result<Session> {
         // Find user
         let user_id = {
             let username_map = self.username_to_id.read().unwrap();
@@ -320,14 +741,37 @@ impl AccountManager {
                 .ok_or_else(|| anyhow::anyhow!("User not found"))?
         };
 
+        {
+            let mut failures = self.login_failures.write().unwrap();
+            if let Some(locked_until) = failures.get(&user_id).and_then(|f| f.locked_until) {
+                if now < locked_until {
+                    let minutes = ((locked_until - now).num_seconds() + 59) / 60;
+                    return Err(anyhow::anyhow!("Account locked, try again in {} minutes", minutes));
+                }
+                failures.remove(&user_id);
+            }
+        }
+
         let mut users = self.users.write().unwrap();
         let user = users.get_mut(&user_id)
             .ok_or_else(|| anyhow::anyhow!("User not found"))?;
 
         // Verify password
         if !user.verify_password(&password)? {
+            let mut failures = self.login_failures.write().unwrap();
+            let failure = failures.entry(user_id).or_default();
+            failure.count += 1;
+            if failure.count >= MAX_FAILED_LOGINS {
+                failure.locked_until = Some(now + chrono::Duration::minutes(LOGIN_LOCKOUT_MINUTES));
+                return Err(anyhow::anyhow!(
+                    "Account locked after {} wrong passwords, try again in {} minutes",
+                    MAX_FAILED_LOGINS,
+                    LOGIN_LOCKOUT_MINUTES
+                ));
+            }
             return Err(anyhow::anyhow!("Invalid password"));
         }
+        self.login_failures.write().unwrap().remove(&user_id);
 
         // Update last login
         user.update_last_login();
@@ -360,6 +804,11 @@ impl AccountManager {
 
     /// Get user by ID
     pub async fn get_user(&self, user_id: Uuid) -> Option<User> {
+        self.user_snapshot(user_id)
+    }
+
+    /// A user as they are now, without awaiting
+    pub fn user_snapshot(&self, user_id: Uuid) -> Option<User> {
         self.users.read().unwrap().get(&user_id).cloned()
     }
 
@@ -382,48 +831,223 @@ impl AccountManager {
         }
     }
 
-    /// Send friend request
-    pub async fn send_friend_request(&self, requester_id: Uuid, target_username: String) -> Result<()> {
+    /// Username of a user
+    fn username_of(&self, user_id: Uuid) -> Result<String> {
+        self.users.read().unwrap()
+            .get(&user_id)
+            .map(|user| user.username.clone())
+            .ok_or_else(|| anyhow::anyhow!("User not found"))
+    }
+
+    /// Send a friend request. The target sees it in `get_pending_requests`; the requester
+    /// keeps a Pending entry in their own list until it is answered. Returns the entry
+    /// as the target sees it, for notifying them if they are online. A request to
+    /// someone who already asked you accepts theirs instead
+    pub async fn send_friend_request(&self, requester_id: Uuid, target_username: String) -> Result<Friend> {
+        self.send_friend_request_sync(requester_id, target_username)
+    }
+
+    /// Send a friend request without awaiting, for callers outside the async runtime.
+    /// Fails with "User not found" for an unknown username
+    pub fn send_friend_request_sync(&self, requester_id: Uuid, target_username: String) -> Result<Friend> {
         let target_id = {
             let username_map = self.username_to_id.read().unwrap();
             username_map.get(&target_username)
                 .copied()
                 .ok_or_else(|| anyhow::anyhow!("User not found"))?
         };
+        if target_id == requester_id {
+            return Err(anyhow::anyhow!("You can't add yourself"));
+        }
+        let requester_name = self.username_of(requester_id)?;
+        let accepts_requests = self.users.read().unwrap()
+            .get(&target_id)
+            .is_some_and(|user| user.settings.allow_friend_requests);
+
+        if self.has_pending_request(requester_id, target_id) {
+            self.accept_friend_request_sync(requester_id, target_id)?;
+            return Ok(Friend {
+                friend_id: requester_id,
+                username: requester_name,
+                status: FriendStatus::Accepted,
+                added_at: Utc::now(),
+            });
+        }
+
+        {
+            let friends = self.friends.read().unwrap();
+            let existing = friends.get(&requester_id)
+                .and_then(|list| list.iter().find(|f| f.friend_id == target_id));
+            match existing.map(|f| &f.status) {
+                Some(FriendStatus::Accepted) => return Err(anyhow::anyhow!("Already friends")),
+                Some(FriendStatus::Pending) => return Err(anyhow::anyhow!("Request already sent")),
+                _ => {}
+            }
+            let blocked = friends.get(&target_id).is_some_and(|list| {
+                list.iter().any(|f| {
+                    f.friend_id == requester_id && matches!(f.status, FriendStatus::Blocked)
+                })
+            });
+            // Blocks look the same as a closed inbox to the requester
+            if blocked || !accepts_requests {
+                return Err(anyhow::anyhow!("{} isn't accepting friend requests", target_username));
+            }
+        }
 
-        let mut friends = self.friends.write().unwrap();
-        friends.entry(requester_id).or_insert_with(Vec::new).push(Friend {
+        let now = Utc::now();
+        self.friends.write().unwrap().entry(requester_id).or_default().push(Friend {
             friend_id: target_id,
             username: target_username,
             status: FriendStatus::Pending,
-            added_at: Utc::now(),
+            added_at: now,
         });
+        let incoming = Friend {
+            friend_id: requester_id,
+            username: requester_name,
+            status: FriendStatus::Pending,
+            added_at: now,
+        };
+        self.friend_requests.write().unwrap().entry(target_id).or_default().push(incoming.clone());
 
-        Ok(())
+        self.save_data()?;
+        Ok(incoming)
     }
 
-    /// Accept friend request
+    /// Accept the pending request from `friend_id` to `user_id`; both sides become Accepted
     pub async fn accept_friend_request(&self, user_id: Uuid, friend_id: Uuid) -> Result<()> {
-        let mut friends = self.friends.write().unwrap();
+        self.accept_friend_request_sync(user_id, friend_id)
+    }
 
-        // Update requester's friend list
-        if let Some(friend_list) = friends.get_mut(&user_id) {
-            if let Some(friend) = friend_list.iter_mut().find(|f| f.friend_id == friend_id) {
-                friend.status = FriendStatus::Accepted;
-            }
+    /// Accept a request without awaiting, for callers outside the async runtime
+    pub fn accept_friend_request_sync(&self, user_id: Uuid, friend_id: Uuid) -> Result<()> {
+        let recipient_name = self.username_of(user_id)?;
+        let request = self.take_pending_request(user_id, friend_id)
+            .ok_or_else(|| anyhow::anyhow!("No pending request from that user"))?;
+
+        {
+            let mut friends = self.friends.write().unwrap();
+
+            // The requester's side; their outgoing entry went with the request
+            let list = friends.entry(friend_id).or_default();
+            list.retain(|f| f.friend_id != user_id);
+            list.push(Friend {
+                friend_id: user_id,
+                username: recipient_name,
+                status: FriendStatus::Accepted,
+                added_at: Utc::now(),
+            });
+
+            // The recipient's side, replacing any earlier block
+            let list = friends.entry(user_id).or_default();
+            list.retain(|f| f.friend_id != friend_id);
+            list.push(Friend {
+                friend_id,
+                username: request.username,
+                status: FriendStatus::Accepted,
+                added_at: Utc::now(),
+            });
         }
 
-        // Add to friend's list
-        let target_user = self.users.read().unwrap().get(&friend_id)
-            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
-        friends.entry(friend_id).or_insert_with(Vec::new).push(Friend {
-            friend_id: user_id,
-            username: target_user.username.clone(),
-            status: FriendStatus::Accepted,
-            added_at: Utc::now(),
-        });
+        self.save_data()
+    }
 
-        Ok(())
+    /// Pending friend requests sent to `user_id`, oldest first
+    pub async fn get_pending_requests(&self, user_id: Uuid) -> Vec<Friend> {
+        self.pending_requests_snapshot(user_id)
+    }
+
+    /// Pending friend requests sent to `user_id` without awaiting, oldest first
+    pub fn pending_requests_snapshot(&self, user_id: Uuid) -> Vec<Friend> {
+        self.friend_requests.read().unwrap()
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Whether `requester_id` has a pending request to `user_id`
+    fn has_pending_request(&self, user_id: Uuid, requester_id: Uuid) -> bool {
+        self.friend_requests.read().unwrap()
+            .get(&user_id)
+            .is_some_and(|list| list.iter().any(|f| f.friend_id == requester_id))
+    }
+
+    /// Remove a pending request from the recipient's list and the requester's outgoing entry
+    fn take_pending_request(&self, user_id: Uuid, requester_id: Uuid) -> Option<Friend> {
+        let request = {
+            let mut requests = self.friend_requests.write().unwrap();
+            let list = requests.get_mut(&user_id)?;
+            let index = list.iter().position(|f| f.friend_id == requester_id)?;
+            list.remove(index)
+        };
+        if let Some(list) = self.friends.write().unwrap().get_mut(&requester_id) {
+            list.retain(|f| !(f.friend_id == user_id && matches!(f.status, FriendStatus::Pending)));
+        }
+        Some(request)
+    }
+
+    /// Decline the pending request from `requester_id` to `user_id`, optionally blocking
+    /// them so they can't ask again. Without a block they may send a new request later
+    pub async fn decline_friend_request(&self, user_id: Uuid, requester_id: Uuid, block: bool) -> Result<()> {
+        self.decline_friend_request_sync(user_id, requester_id, block)
+    }
+
+    /// Decline a request without awaiting, for callers outside the async runtime
+    pub fn decline_friend_request_sync(&self, user_id: Uuid, requester_id: Uuid, block: bool) -> Result<()> {
+        let request = self.take_pending_request(user_id, requester_id)
+            .ok_or_else(|| anyhow::anyhow!("No pending request from that user"))?;
+        if block {
+            let mut friends = self.friends.write().unwrap();
+            let list = friends.entry(user_id).or_default();
+            list.retain(|f| f.friend_id != requester_id);
+            list.push(Friend {
+                friend_id: requester_id,
+                username: request.username,
+                status: FriendStatus::Blocked,
+                added_at: Utc::now(),
+            });
+        }
+        self.save_data()
+    }
+
+    /// Decline every pending request to `user_id` from a muted user. Returns how many were declined
+    pub async fn decline_muted_requests(&self, user_id: Uuid, muted: &HashSet<Uuid>) -> usize {
+        self.decline_muted_requests_sync(user_id, muted)
+    }
+
+    /// Decline muted users' requests without awaiting, for callers outside the async runtime
+    pub fn decline_muted_requests_sync(&self, user_id: Uuid, muted: &HashSet<Uuid>) -> usize {
+        self.pending_requests_snapshot(user_id)
+            .into_iter()
+            .filter(|request| muted.contains(&request.friend_id))
+            .filter(|request| self.decline_friend_request_sync(user_id, request.friend_id, false).is_ok())
+            .count()
+    }
+
+    /// Song records of `target_id` as seen by `viewer_id`; hidden unless the profile is public
+    pub async fn get_profile_records(&self, viewer_id: Uuid, target_id: Uuid) -> Result<ProfileRecords> {
+        self.profile_records_snapshot(viewer_id, target_id)
+    }
+
+    /// Song records of `target_id` as seen by `viewer_id`, without awaiting
+    pub fn profile_records_snapshot(&self, viewer_id: Uuid, target_id: Uuid) -> Result<ProfileRecords> {
+        let users = self.users.read().unwrap();
+        let target = users.get(&target_id).ok_or_else(|| anyhow::anyhow!("User not found"))?;
+
+        if viewer_id != target_id && !target.settings.public_profile {
+            return Ok(ProfileRecords::Private {
+                username: target.username.clone(),
+            });
+        }
+
+        Ok(ProfileRecords::Public {
+            username: target.username.clone(),
+            songs: target
+                .stats
+                .songs_played
+                .iter()
+                .map(|(song, stats)| (song.clone(), stats.record()))
+                .collect(),
+        })
     }
 
     /// Get friends list
@@ -434,50 +1058,298 @@ impl AccountManager {
             .unwrap_or_default()
     }
 
-    /// Update leaderboard
+    /// Friends list and incoming requests of a user at this moment, without awaiting
+    pub fn friends_snapshot(&self, user_id: Uuid) -> FriendsSnapshot {
+        let mut snapshot = FriendsSnapshot {
+            incoming: self.pending_requests_snapshot(user_id),
+            ..Default::default()
+        };
+        let friends = self.friends.read().unwrap();
+        for friend in friends.get(&user_id).into_iter().flatten() {
+            match friend.status {
+                FriendStatus::Accepted => snapshot.friends.push(friend.clone()),
+                FriendStatus::Pending => snapshot.outgoing.push(friend.clone()),
+                FriendStatus::Blocked => snapshot.blocked.push(friend.clone()),
+            }
+        }
+        snapshot.friends.sort_by_key(|friend| friend.username.to_lowercase());
+        snapshot
+    }
+
+    /// Rebuild the leaderboard from every user (used after loading from disk)
     pub async fn update_leaderboard(&self) {
+        self.rebuild_leaderboard();
+    }
+
+    /// Rebuild the leaderboard without awaiting
+    fn rebuild_leaderboard(&self) {
         let users = self.users.read().unwrap();
-        let mut entries: Vec<LeaderboardEntry> = users.values()
-            .map(|user| LeaderboardEntry {
-                user_id: user.user_id,
-                username: user.username.clone(),
-                rank: 0,
-                total_score: user.stats.total_score,
-                average_accuracy: user.stats.average_accuracy,
-                total_games: user.stats.total_games,
-            })
-            .collect();
+        let entries = users.values().map(LeaderboardEntry::from_user).collect();
+        *self.leaderboard.write().unwrap() = Leaderboard::from_entries(entries);
+    }
 
-        // Sort by total score
-        entries.sort_by(|a, b| b.total_score.cmp(&a.total_score));
+    /// Record a finished game and move the player's leaderboard entry in place
+    pub async fn record_game(&self, user_id: Uuid, record: GameRecord) -> Result<bool> {
+        self.record_game_sync(user_id, record)
+    }
 
-        // Assign ranks
-        for (idx, entry) in entries.iter_mut().enumerate() {
-            entry.rank = (idx + 1) as u32;
+    /// Record a finished game without awaiting, for callers outside the async runtime.
+    /// Practice runs are left out of the competitive stats; returns whether the game
+    /// was counted
+    pub fn record_game_sync(&self, user_id: Uuid, record: GameRecord) -> Result<bool> {
+        if record.practice {
+            return Ok(false);
         }
 
-        *self.leaderboard.write().unwrap() = entries;
+        let entry = {
+            let mut users = self.users.write().unwrap();
+            let user = users.get_mut(&user_id)
+                .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+            let [perfect, good, ok, misses] = record.hits;
+            if let Some(season) = &record.season {
+                user.update_season(season, record.score, record.accuracy);
+            }
+            user.update_stats(record.score, record.max_combo, record.accuracy, record.song_name, record.play_time);
+            user.update_hits(perfect, good, ok, misses);
+            LeaderboardEntry::from_user(user)
+        };
+
+        self.leaderboard.write().unwrap().upsert(entry);
+        self.save_data()?;
+        Ok(true)
     }
 
-    /// Get leaderboard
+    /// Get the top of the leaderboard
     pub async fn get_leaderboard(&self, limit: usize) -> Vec<LeaderboardEntry> {
+        self.leaderboard.read().unwrap().page(0, limit).entries
+    }
+
+    /// Top of the leaderboard without awaiting, for callers outside the async runtime.
+    /// Locks the leaderboard directly, like the async getters do
+    pub fn leaderboard_snapshot(&self, limit: usize) -> Vec<LeaderboardEntry> {
+        self.leaderboard.read().unwrap().page(0, limit).entries
+    }
+
+    /// Rows `offset..offset + count` of a scope's board, all time or in one season,
+    /// with the board's player count for paging
+    pub fn board_page(
+        &self,
+        scope: &LeaderboardScope,
+        season: Option<&str>,
+        offset: usize,
+        count: usize,
+    ) -> LeaderboardPage {
+        if let (LeaderboardScope::Global, None) = (scope, season) {
+            return self.leaderboard.read().unwrap().page(offset, count);
+        }
+        let entries = self.ranked_board(scope, season);
+        let offset = offset.min(entries.len());
+        LeaderboardPage {
+            total_count: entries.len(),
+            entries: entries.into_iter().skip(offset).take(count).collect(),
+            offset,
+        }
+    }
+
+    /// A user's 1-based rank on a scope's board, all time or in one season
+    pub fn board_rank(
+        &self,
+        scope: &LeaderboardScope,
+        season: Option<&str>,
+        user_id: Uuid,
+    ) -> Option<u32> {
+        if let (LeaderboardScope::Global, None) = (scope, season) {
+            return self.leaderboard.read().unwrap().rank_of(user_id);
+        }
+        self.ranked_board(scope, season)
+            .iter()
+            .find(|entry| entry.user_id == user_id)
+            .map(|entry| entry.rank)
+    }
+
+    /// Every row of a scope's board, ranked among themselves. Season boards are read far
+    /// less often than they change, so they're sorted on each read
+    fn ranked_board(&self, scope: &LeaderboardScope, season: Option<&str>) -> Vec<LeaderboardEntry> {
+        let entries = match season {
+            Some(season) => self.season_board(season),
+            None => {
+                let leaderboard = self.leaderboard.read().unwrap();
+                leaderboard.page(0, leaderboard.len()).entries
+            }
+        };
+        ranked_within(entries, self.scope_members(scope).as_ref())
+    }
+
+    /// A user's 1-based rank among everyone who played in a season
+    pub fn season_rank(&self, user_id: Uuid, season: &str) -> Option<u32> {
+        self.season_board(season)
+            .iter()
+            .position(|entry| entry.user_id == user_id)
+            .map(|index| index as u32 + 1)
+    }
+
+    /// Ids of the seasons anyone has played in, sorted
+    pub fn ranked_seasons(&self) -> Vec<String> {
+        let users = self.users.read().unwrap();
+        let mut seasons: Vec<String> = users.values()
+            .flat_map(|user| user.stats.seasons.keys().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        seasons.sort();
+        seasons
+    }
+
+    /// Unranked entries of everyone who played in a season, best first
+    fn season_board(&self, season: &str) -> Vec<LeaderboardEntry> {
+        let users = self.users.read().unwrap();
+        let mut entries: Vec<LeaderboardEntry> = users.values()
+            .filter_map(|user| LeaderboardEntry::from_season(user, season))
+            .collect();
+        entries.sort_by_key(|entry| entry.sort_key());
+        entries
+    }
+
+    /// Players a scope ranks, None when it ranks everyone
+    fn scope_members(&self, scope: &LeaderboardScope) -> Option<HashSet<Uuid>> {
+        let members = match scope {
+            LeaderboardScope::Global => return None,
+            LeaderboardScope::Country(country) => self
+                .users
+                .read()
+                .unwrap()
+                .values()
+                .filter(|user| user.profile.country.eq_ignore_ascii_case(country))
+                .map(|user| user.user_id)
+                .collect(),
+            LeaderboardScope::Friends(user_id) => self
+                .friends
+                .read()
+                .unwrap()
+                .get(user_id)
+                .into_iter()
+                .flatten()
+                .filter(|friend| matches!(friend.status, FriendStatus::Accepted))
+                .map(|friend| friend.friend_id)
+                .chain(std::iter::once(*user_id))
+                .collect(),
+        };
+        Some(members)
+    }
+
+    /// Get `count` leaderboard entries starting at `offset`, plus the total player count
+    pub async fn get_leaderboard_page(&self, offset: usize, count: usize) -> LeaderboardPage {
+        self.leaderboard.read().unwrap().page(offset, count)
+    }
+
+    /// Get the total number of ranked players
+    pub async fn leaderboard_total_count(&self) -> usize {
+        self.leaderboard.read().unwrap().len()
+    }
+
+    /// Get a user's 1-based global rank
+    pub async fn get_user_rank(&self, user_id: Uuid) -> Option<u32> {
+        self.leaderboard.read().unwrap().rank_of(user_id)
+    }
+
+    /// Get the page of `page_size` entries containing a user ("jump to my rank")
+    pub async fn get_leaderboard_page_for_user(
+        &self,
+        user_id: Uuid,
+        page_size: usize,
+    ) -> Option<LeaderboardPage> {
         let leaderboard = self.leaderboard.read().unwrap();
-        leaderboard.iter().take(limit).cloned().collect()
+        let offset = leaderboard.page_offset_of(user_id, page_size)?;
+        Some(leaderboard.page(offset, page_size))
     }
 
-    /// Save data to disk
-    fn save_data(&self) -> Result<()> {
+    /// Save data to disk. Each file is replaced atomically
+    pub fn save_data(&self) -> Result<()> {
         std::fs::create_dir_all(&self.data_path)?;
 
+        save_json(&self.data_path.join(USERS_FILE), &*self.users.read().unwrap())?;
+        save_json(&self.data_path.join(SESSIONS_FILE), &*self.sessions.read().unwrap())?;
+        save_json(&self.data_path.join(FRIENDS_FILE), &*self.friends.read().unwrap())?;
+        save_json(
+            &self.data_path.join(FRIEND_REQUESTS_FILE),
+            &*self.friend_requests.read().unwrap(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Drop stale sessions (see `Session::is_stale`), except the one with the `keep`
+    /// token: the session in use locally is never purged. Returns how many were removed
+    pub fn purge_sessions(&self, keep: Option<&str>) -> usize {
+        let now = Utc::now();
+        let mut sessions = self.sessions.write().unwrap();
+        let before = sessions.len();
+        sessions.retain(|token, session| Some(token.as_str()) == keep || !session.is_stale(now));
+        before - sessions.len()
+    }
+
+    /// Remove friend lists and pending requests of users that no longer exist, and
+    /// entries in them pointing at such users. Returns (friends, requests) removed
+    pub fn compact_friends(&self) -> (usize, usize) {
         let users = self.users.read().unwrap();
-        let users_json = serde_json::to_string_pretty(&*users)?;
-        std::fs::write(self.data_path.join("users.json"), users_json)?;
+        let compact = |lists: &mut HashMap<Uuid, Vec<Friend>>| {
+            let before: usize = lists.values().map(Vec::len).sum();
+            lists.retain(|owner, _| users.contains_key(owner));
+            for list in lists.values_mut() {
+                list.retain(|friend| users.contains_key(&friend.friend_id));
+            }
+            lists.retain(|_, list| !list.is_empty());
+            before - lists.values().map(Vec::len).sum::<usize>()
+        };
+        let friends_removed = compact(&mut self.friends.write().unwrap());
+        let requests_removed = compact(&mut self.friend_requests.write().unwrap());
+        (friends_removed, requests_removed)
+    }
 
-        let sessions = self.sessions.read().unwrap();
-        let sessions_json = serde_json::to_string_pretty(&*sessions)?;
-        std::fs::write(self.data_path.join("sessions.json"), sessions_json)?;
+    /// Purge stale sessions and dangling friend entries, saving if anything was
+    /// removed. Blocks on disk I/O, so async callers should run it with
+    /// `tokio::task::spawn_blocking`
+    pub fn run_maintenance(&self, keep: Option<&str>) -> Result<MaintenanceReport> {
+        let sessions_removed = self.purge_sessions(keep);
+        let (friends_removed, requests_removed) = self.compact_friends();
+        let report = MaintenanceReport { sessions_removed, friends_removed, requests_removed };
+        if !report.is_empty() {
+            self.save_data()?;
+        }
+        Ok(report)
+    }
 
-        Ok(())
+    /// Size of each stored file and how many entries it holds now
+    pub fn storage_stats(&self) -> StorageStats {
+        let size = |name: &str| {
+            std::fs::metadata(self.data_path.join(name))
+                .map(|metadata| metadata.len())
+                .unwrap_or(0)
+        };
+        let count = |lists: &HashMap<Uuid, Vec<Friend>>| lists.values().map(Vec::len).sum();
+        let files = vec![
+            StoredFile {
+                name: USERS_FILE,
+                bytes: size(USERS_FILE),
+                entries: self.users.read().unwrap().len(),
+            },
+            StoredFile {
+                name: SESSIONS_FILE,
+                bytes: size(SESSIONS_FILE),
+                entries: self.sessions.read().unwrap().len(),
+            },
+            StoredFile {
+                name: FRIENDS_FILE,
+                bytes: size(FRIENDS_FILE),
+                entries: count(&self.friends.read().unwrap()),
+            },
+            StoredFile {
+                name: FRIEND_REQUESTS_FILE,
+                bytes: size(FRIEND_REQUESTS_FILE),
+                entries: count(&self.friend_requests.read().unwrap()),
+            },
+        ];
+        StorageStats { files }
     }
 
     /// Load data from disk
@@ -487,7 +1359,7 @@ impl AccountManager {
         }
 
         // Load users
-        let users_path = self.data_path.join("users.json");
+        let users_path = self.data_path.join(USERS_FILE);
         if users_path.exists() {
             let users_json = std::fs::read_to_string(users_path)?;
             let users: HashMap<Uuid, User> = serde_json::from_str(&users_json)?;
@@ -501,21 +1373,583 @@ impl AccountManager {
         }
 
         // Load sessions
-        let sessions_path = self.data_path.join("sessions.json");
+        let sessions_path = self.data_path.join(SESSIONS_FILE);
         if sessions_path.exists() {
             let sessions_json = std::fs::read_to_string(sessions_path)?;
             let sessions: HashMap<String, Session> = serde_json::from_str(&sessions_json)?;
             *self.sessions.write().unwrap() = sessions;
         }
 
-        // Update leaderboard
-        tokio::spawn({
-            let this = self.clone();
-            async move {
-                this.update_leaderboard().await;
-            }
-        });
+        // Load friends and pending requests
+        let friends_path = self.data_path.join(FRIENDS_FILE);
+        if friends_path.exists() {
+            let friends_json = std::fs::read_to_string(friends_path)?;
+            *self.friends.write().unwrap() = serde_json::from_str(&friends_json)?;
+        }
+        let requests_path = self.data_path.join(FRIEND_REQUESTS_FILE);
+        if requests_path.exists() {
+            let requests_json = std::fs::read_to_string(requests_path)?;
+            *self.friend_requests.write().unwrap() = serde_json::from_str(&requests_json)?;
+        }
+
+        // Drop what went stale while the server was down
+        self.run_maintenance(None)?;
+
+        // Rebuilt in place, so a client without an async runtime can load too
+        self.rebuild_leaderboard();
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    /// A manager saving into an empty folder of its own
+    fn manager(test: &str) -> AccountManager {
+        AccountManager::new(scratch_dir(&format!("accounts-{}", test)))
+    }
+
+    fn register(manager: &AccountManager, username: &str) -> Uuid {
+        manager
+            .register_sync(username.to_string(), "hunter22".to_string(), format!("{}@example.com", username))
+            .unwrap()
+    }
+
+    fn login(manager: &AccountManager, username: &str, password: &str) -> Result<Session> {
+        manager.login_sync(username.to_string(), password.to_string(), None)
+    }
+
+    #[test]
+    fn registration_needs_every_field_an_email_and_matching_passwords() {
+        let error = |username, email, password, confirm| {
+            validate_registration(username, email, password, confirm).unwrap_err().to_string()
+        };
+        assert!(validate_registration("alice", "alice@example.com", "hunter22", "hunter22").is_ok());
+        assert_eq!(error("  ", "alice@example.com", "hunter22", "hunter22"), "Fill in every field");
+        assert_eq!(error("alice", "", "hunter22", "hunter22"), "Fill in every field");
+        assert_eq!(error("alice", "alice@example.com", "", ""), "Fill in every field");
+        assert_eq!(error("alice", "alice.example.com", "hunter22", "hunter22"), "Email address needs an '@'");
+        assert_eq!(error("alice", "alice@example.com", "hunter22", "hunter23"), "Passwords don't match");
+    }
+
+    #[test]
+    fn usernames_are_taken_once() {
+        let manager = manager("duplicate");
+        register(&manager, "alice");
+        let again = manager.register_sync("alice".to_string(), "other".to_string(), "a@b.c".to_string());
+        assert_eq!(again.unwrap_err().to_string(), "Username already exists");
+        let invalid = manager.register_sync("bob".to_string(), "other".to_string(), "no-at".to_string());
+        assert_eq!(invalid.unwrap_err().to_string(), "Email address needs an '@'");
+        assert_eq!(manager.leaderboard_snapshot(10).len(), 1);
+    }
+
+    #[test]
+    fn racing_registrations_take_a_username_once() {
+        let manager = manager("duplicate-race");
+        let results: Vec<Result<Uuid>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        manager.register_sync(
+                            "alice".to_string(),
+                            "hunter22".to_string(),
+                            "alice@example.com".to_string(),
+                        )
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert_eq!(manager.leaderboard_snapshot(10).len(), 1);
+    }
+
+    fn friend_names(friends: &[Friend]) -> Vec<&str> {
+        friends.iter().map(|friend| friend.username.as_str()).collect()
+    }
+
+    #[test]
+    fn accepted_requests_list_both_players_as_friends() {
+        let manager = manager("friends");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+
+        let error = manager.send_friend_request_sync(alice, "nobody".to_string()).unwrap_err();
+        assert_eq!(error.to_string(), "User not found");
+        manager.send_friend_request_sync(alice, "bob".to_string()).unwrap();
+        assert_eq!(friend_names(&manager.friends_snapshot(alice).outgoing), ["bob"]);
+        assert_eq!(friend_names(&manager.friends_snapshot(bob).incoming), ["alice"]);
+
+        manager.accept_friend_request_sync(bob, alice).unwrap();
+        let (alice_side, bob_side) = (manager.friends_snapshot(alice), manager.friends_snapshot(bob));
+        assert_eq!(friend_names(&alice_side.friends), ["bob"]);
+        assert!(alice_side.outgoing.is_empty());
+        assert_eq!(friend_names(&bob_side.friends), ["alice"]);
+        assert!(bob_side.incoming.is_empty());
+    }
+
+    #[test]
+    fn asking_back_accepts_and_blocking_stops_new_requests() {
+        let manager = manager("friend-requests");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+        let carol = register(&manager, "carol");
+
+        manager.send_friend_request_sync(alice, "bob".to_string()).unwrap();
+        manager.send_friend_request_sync(bob, "alice".to_string()).unwrap();
+        assert_eq!(friend_names(&manager.friends_snapshot(alice).friends), ["bob"]);
+
+        manager.send_friend_request_sync(carol, "alice".to_string()).unwrap();
+        manager.decline_friend_request_sync(alice, carol, true).unwrap();
+        assert_eq!(friend_names(&manager.friends_snapshot(alice).blocked), ["carol"]);
+        assert!(manager.friends_snapshot(carol).outgoing.is_empty());
+        let error = manager.send_friend_request_sync(carol, "alice".to_string()).unwrap_err();
+        assert_eq!(error.to_string(), "alice isn't accepting friend requests");
+    }
+
+    #[tokio::test]
+    async fn a_declined_request_can_be_sent_again_and_survives_a_reload() {
+        let manager = manager("decline-and-retry");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+
+        manager.send_friend_request(alice, "bob".to_string()).await.unwrap();
+        let error = manager.send_friend_request(alice, "bob".to_string()).await.unwrap_err();
+        assert_eq!(error.to_string(), "Request already sent");
+        assert_eq!(friend_names(&manager.get_pending_requests(bob).await), ["alice"]);
+        // Only the recipient sees it as incoming
+        assert!(manager.get_pending_requests(alice).await.is_empty());
+
+        manager.decline_friend_request(bob, alice, false).await.unwrap();
+        let (alice_side, bob_side) = (manager.friends_snapshot(alice), manager.friends_snapshot(bob));
+        assert!(alice_side.outgoing.is_empty() && alice_side.friends.is_empty());
+        assert!(bob_side.incoming.is_empty() && bob_side.blocked.is_empty());
+        let error = manager.decline_friend_request(bob, alice, false).await.unwrap_err();
+        assert_eq!(error.to_string(), "No pending request from that user");
+
+        // Without a block, asking again works and the request is kept on disk
+        manager.send_friend_request(alice, "bob".to_string()).await.unwrap();
+        let reloaded = AccountManager::new(manager.data_path.clone());
+        reloaded.load_data().unwrap();
+        assert_eq!(friend_names(&reloaded.get_pending_requests(bob).await), ["alice"]);
+        assert_eq!(friend_names(&reloaded.friends_snapshot(alice).outgoing), ["bob"]);
+
+        reloaded.accept_friend_request(bob, alice).await.unwrap();
+        let reloaded_again = AccountManager::new(manager.data_path.clone());
+        reloaded_again.load_data().unwrap();
+        assert_eq!(friend_names(&reloaded_again.friends_snapshot(alice).friends), ["bob"]);
+        assert_eq!(friend_names(&reloaded_again.friends_snapshot(bob).friends), ["alice"]);
+        assert!(reloaded_again.get_pending_requests(bob).await.is_empty());
+    }
+
+    fn play(manager: &AccountManager, user_id: Uuid, score: i64) {
+        play_in(manager, user_id, score, None);
+    }
+
+    fn play_in(manager: &AccountManager, user_id: Uuid, score: i64, season: Option<&str>) {
+        let record = GameRecord {
+            song_name: "song".to_string(),
+            score,
+            max_combo: 10,
+            accuracy: 90.0,
+            hits: [10, 0, 0, 0],
+            play_time: 60,
+            practice: false,
+            season: season.map(str::to_string),
+        };
+        assert!(manager.record_game_sync(user_id, record).unwrap());
+    }
+
+    #[test]
+    fn season_boards_rank_only_that_seasons_games() {
+        let manager = manager("seasons");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+        let carol = register(&manager, "carol");
+        play_in(&manager, alice, 500, Some("2026-Q3"));
+        play_in(&manager, bob, 100, Some("2026-Q3"));
+        play_in(&manager, bob, 300, Some("2026-Q4"));
+        play_in(&manager, carol, 200, Some("2026-Q4"));
+        play(&manager, carol, 1000);
+
+        let names = |scope: LeaderboardScope, season: &str| -> Vec<(u32, String, u64)> {
+            manager
+                .board_page(&scope, Some(season), 0, 10)
+                .entries
+                .into_iter()
+                .map(|entry| (entry.rank, entry.username, entry.total_score))
+                .collect()
+        };
+        assert_eq!(
+            names(LeaderboardScope::Global, "2026-Q4"),
+            vec![(1, "bob".to_string(), 300), (2, "carol".to_string(), 200)]
+        );
+        assert_eq!(
+            names(LeaderboardScope::Global, "2026-Q3"),
+            vec![(1, "alice".to_string(), 500), (2, "bob".to_string(), 100)]
+        );
+        assert!(names(LeaderboardScope::Friends(alice), "2026-Q4").is_empty());
+        assert!(names(LeaderboardScope::Global, "2026-Q2").is_empty());
+
+        assert_eq!(manager.season_rank(carol, "2026-Q4"), Some(2));
+        assert_eq!(manager.season_rank(alice, "2026-Q4"), None);
+        assert_eq!(manager.ranked_seasons(), ["2026-Q3", "2026-Q4"]);
+        // The lifetime board still counts every game
+        assert_eq!(manager.leaderboard_snapshot(1)[0].username, "carol");
+
+        let reloaded = AccountManager::new(manager.data_path.clone());
+        reloaded.load_data().unwrap();
+        assert_eq!(reloaded.season_rank(bob, "2026-Q4"), Some(1));
+    }
+
+    #[test]
+    fn scoped_boards_rank_their_own_players() {
+        let manager = manager("scopes");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+        let carol = register(&manager, "carol");
+        play(&manager, alice, 100);
+        play(&manager, bob, 300);
+        play(&manager, carol, 200);
+        manager.users.write().unwrap().get_mut(&carol).unwrap().profile.country = "NZ".to_string();
+        manager.send_friend_request_sync(alice, "carol".to_string()).unwrap();
+        manager.accept_friend_request_sync(carol, alice).unwrap();
+
+        let names = |scope: LeaderboardScope| -> Vec<(u32, String)> {
+            manager
+                .board_page(&scope, None, 0, 10)
+                .entries
+                .into_iter()
+                .map(|entry| (entry.rank, entry.username))
+                .collect()
+        };
+        assert_eq!(
+            names(LeaderboardScope::Global),
+            vec![(1, "bob".to_string()), (2, "carol".to_string()), (3, "alice".to_string())]
+        );
+        assert_eq!(names(LeaderboardScope::Country("nz".to_string())), vec![(1, "carol".to_string())]);
+        assert_eq!(
+            names(LeaderboardScope::Friends(alice)),
+            vec![(1, "carol".to_string()), (2, "alice".to_string())]
+        );
+        assert_eq!(names(LeaderboardScope::Friends(carol)), names(LeaderboardScope::Friends(alice)));
+        assert_eq!(manager.board_page(&LeaderboardScope::Global, None, 0, 1).entries.len(), 1);
+    }
+
+    #[test]
+    fn board_pages_count_every_player_and_rank_within_the_scope() {
+        let manager = manager("board-pages");
+        let players: Vec<Uuid> = (0..5)
+            .map(|i| register(&manager, &format!("player{}", i)))
+            .collect();
+        for (i, &player) in players.iter().enumerate() {
+            play(&manager, player, 1000 * (i as i64 + 1));
+            play_in(&manager, player, 100 * (5 - i as i64), Some("2026-Q4"));
+        }
+        manager.send_friend_request_sync(players[0], "player2".to_string()).unwrap();
+        manager.accept_friend_request_sync(players[2], players[0]).unwrap();
+
+        let global = manager.board_page(&LeaderboardScope::Global, None, 2, 2);
+        assert_eq!((global.offset, global.total_count), (2, 5));
+        let ranks: Vec<u32> = global.entries.iter().map(|entry| entry.rank).collect();
+        assert_eq!(ranks, [3, 4]);
+        assert_eq!(global.entries[0].user_id, players[2]);
+        assert_eq!(manager.board_rank(&LeaderboardScope::Global, None, players[4]), Some(1));
+
+        let season = manager.board_page(&LeaderboardScope::Global, Some("2026-Q4"), 4, 2);
+        assert_eq!((season.offset, season.total_count), (4, 5));
+        assert_eq!(season.entries[0].user_id, players[4]);
+        assert_eq!(manager.board_rank(&LeaderboardScope::Global, Some("2026-Q4"), players[0]), Some(1));
+
+        // Past the end the page is empty but still knows the player count
+        let friends = LeaderboardScope::Friends(players[0]);
+        let past_end = manager.board_page(&friends, None, 10, 2);
+        assert!(past_end.entries.is_empty());
+        assert_eq!((past_end.offset, past_end.total_count), (2, 2));
+        assert_eq!(manager.board_rank(&friends, None, players[0]), Some(2));
+        assert_eq!(manager.board_rank(&friends, None, players[1]), None);
+    }
+
+    #[test]
+    fn scores_past_u32_are_stored_whole() {
+        let manager = manager("big-scores");
+        let alice = register(&manager, "alice");
+        let score = u32::MAX as i64 * 3;
+        play_in(&manager, alice, score, Some("2026-Q4"));
+
+        let user = manager.user_snapshot(alice).unwrap();
+        assert_eq!(user.stats.songs_played["song"].high_score, score);
+        assert_eq!(user.stats.total_score, score as u64);
+        assert_eq!(user.stats.seasons["2026-Q4"].total_score, score as u64);
+        assert_eq!(manager.board_page(&LeaderboardScope::Global, None, 0, 1).entries[0].total_score, score as u64);
+    }
+
+    #[test]
+    fn login_checks_the_username_and_password() {
+        let manager = manager("login");
+        let user_id = register(&manager, "alice");
+
+        assert_eq!(login(&manager, "alice", "hunter22").unwrap().user_id, user_id);
+        assert_eq!(login(&manager, "alice", "hunter2").unwrap_err().to_string(), "Invalid password");
+        assert_eq!(login(&manager, "bob", "hunter22").unwrap_err().to_string(), "User not found");
+    }
+
+    #[test]
+    fn wrong_passwords_lock_the_account_for_a_while() {
+        let manager = manager("lockout");
+        register(&manager, "alice");
+        let now = Utc::now();
+        let attempt = |password: &str, at: DateTime<Utc>| {
+            manager.login_at("alice".to_string(), password.to_string(), None, at)
+        };
+
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert_eq!(attempt("wrong", now).unwrap_err().to_string(), "Invalid password");
+        }
+        assert!(attempt("wrong", now).unwrap_err().to_string().starts_with("Account locked"));
+        // The right password is refused too until the lock runs out
+        let error = attempt("hunter22", now).unwrap_err().to_string();
+        assert_eq!(error, format!("Account locked, try again in {} minutes", LOGIN_LOCKOUT_MINUTES));
+
+        let later = now + chrono::Duration::minutes(LOGIN_LOCKOUT_MINUTES) + chrono::Duration::seconds(1);
+        assert!(attempt("hunter22", later).is_ok());
+    }
+
+    #[test]
+    fn a_successful_login_clears_earlier_failures() {
+        let manager = manager("clear-failures");
+        register(&manager, "alice");
+
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert!(login(&manager, "alice", "wrong").is_err());
+        }
+        assert!(login(&manager, "alice", "hunter22").is_ok());
+        assert_eq!(login(&manager, "alice", "wrong").unwrap_err().to_string(), "Invalid password");
+    }
+
+    #[tokio::test]
+    async fn expired_sessions_are_refused() {
+        let manager = manager("expiry");
+        let user_id = register(&manager, "alice");
+        let session = login(&manager, "alice", "hunter22").unwrap();
+        assert_eq!(manager.validate_session(&session.token).await.unwrap(), user_id);
+
+        manager.sessions.write().unwrap().get_mut(&session.token).unwrap().expires_at =
+            Utc::now() - chrono::Duration::seconds(1);
+        assert_eq!(manager.validate_session(&session.token).await.unwrap_err().to_string(), "Session expired");
+
+        manager.logout(session.token.clone()).await.unwrap();
+        assert_eq!(manager.validate_session(&session.token).await.unwrap_err().to_string(), "Invalid session");
+    }
+
+    #[tokio::test]
+    async fn friend_requests_from_muted_users_are_declined() {
+        let manager = manager("muted-requests");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+        let carol = register(&manager, "carol");
+        manager.send_friend_request_sync(bob, "alice".to_string()).unwrap();
+        manager.send_friend_request_sync(carol, "alice".to_string()).unwrap();
+
+        let muted: HashSet<Uuid> = [bob].into_iter().collect();
+        assert_eq!(manager.decline_muted_requests(alice, &muted).await, 1);
+        let incoming: Vec<Uuid> = manager.friends_snapshot(alice).incoming.iter().map(|f| f.friend_id).collect();
+        assert_eq!(incoming, vec![carol]);
+        assert_eq!(manager.decline_muted_requests(alice, &muted).await, 0);
+    }
+
+    #[test]
+    fn accounts_can_log_in_after_a_reload() {
+        let manager = manager("reload");
+        let user_id = register(&manager, "alice");
+
+        let reloaded = AccountManager::new(manager.data_path.clone());
+        reloaded.load_data().unwrap();
+        assert_eq!(login(&reloaded, "alice", "hunter22").unwrap().user_id, user_id);
+        assert_eq!(reloaded.leaderboard_snapshot(10).len(), 1);
+    }
+
+    #[test]
+    fn profile_records_stay_hidden_behind_a_private_profile() {
+        let manager = manager("records");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+        play(&manager, bob, 1000);
+
+        match manager.profile_records_snapshot(alice, bob).unwrap() {
+            ProfileRecords::Public { username, songs } => {
+                assert_eq!(username, "bob");
+                assert_eq!(songs["song"].best_score, 1000);
+            }
+            other => panic!("expected public records, got {:?}", other),
+        }
+
+        manager.users.write().unwrap().get_mut(&bob).unwrap().settings.public_profile = false;
+        assert!(matches!(
+            manager.profile_records_snapshot(alice, bob).unwrap(),
+            ProfileRecords::Private { .. }
+        ));
+        // Players always see their own
+        assert!(matches!(
+            manager.profile_records_snapshot(bob, bob).unwrap(),
+            ProfileRecords::Public { .. }
+        ));
+    }
+
+    /// A session of `user_id` created and expiring this many days from now
+    fn session(user_id: Uuid, token: &str, created_days: i64, expires_days: i64) -> (String, Session) {
+        let now = Utc::now();
+        let session = Session {
+            token: token.to_string(),
+            created_at: now + chrono::Duration::days(created_days),
+            expires_at: now + chrono::Duration::days(expires_days),
+            ..Session::new(user_id, None)
+        };
+        (token.to_string(), session)
+    }
+
+    fn tokens(manager: &AccountManager) -> Vec<String> {
+        let mut tokens: Vec<String> = manager.sessions.read().unwrap().keys().cloned().collect();
+        tokens.sort();
+        tokens
+    }
+
+    #[test]
+    fn loading_purges_stale_sessions_and_dangling_friends() {
+        let manager = manager("maintenance");
+        let alice = register(&manager, "alice");
+        let bob = register(&manager, "bob");
+        let deleted = Uuid::new_v4();
+        let friend = |friend_id: Uuid, status: FriendStatus| Friend {
+            friend_id,
+            username: "someone".to_string(),
+            status,
+            added_at: Utc::now(),
+        };
+
+        let sessions: HashMap<String, Session> = [
+            session(alice, "fresh", -1, 29),
+            session(alice, "expired", -31, -1),
+            session(bob, "too-old", -(MAX_SESSION_AGE_DAYS + 1), 10),
+            session(bob, "old-enough", -(MAX_SESSION_AGE_DAYS - 1), 10),
+            session(deleted, "expired-long-ago", -400, -370),
+        ]
+        .into_iter()
+        .collect();
+        let friends: HashMap<Uuid, Vec<Friend>> = [
+            (alice, vec![friend(bob, FriendStatus::Accepted), friend(deleted, FriendStatus::Accepted)]),
+            (deleted, vec![friend(alice, FriendStatus::Blocked)]),
+        ]
+        .into_iter()
+        .collect();
+        let requests: HashMap<Uuid, Vec<Friend>> =
+            [(bob, vec![friend(deleted, FriendStatus::Pending)])].into_iter().collect();
+        let write = |name: &str, json: String| std::fs::write(manager.data_path.join(name), json).unwrap();
+        write(SESSIONS_FILE, serde_json::to_string(&sessions).unwrap());
+        write(FRIENDS_FILE, serde_json::to_string(&friends).unwrap());
+        write(FRIEND_REQUESTS_FILE, serde_json::to_string(&requests).unwrap());
+
+        let reloaded = AccountManager::new(manager.data_path.clone());
+        reloaded.load_data().unwrap();
+        assert_eq!(tokens(&reloaded), ["fresh", "old-enough"]);
+        assert_eq!(friend_names(&reloaded.friends_snapshot(alice).friends), ["someone"]);
+        assert!(reloaded.friends.read().unwrap().get(&deleted).is_none());
+        assert!(reloaded.pending_requests_snapshot(bob).is_empty());
+
+        // The cleaned files were written back, with no temporary files left over
+        let saved = std::fs::read_to_string(manager.data_path.join(SESSIONS_FILE)).unwrap();
+        let saved: HashMap<String, Session> = serde_json::from_str(&saved).unwrap();
+        assert_eq!(saved.len(), 2);
+        assert!(saved.contains_key("fresh") && saved.contains_key("old-enough"));
+        let leftovers = std::fs::read_dir(&manager.data_path)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().is_some_and(|ext| ext == "tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let stats = reloaded.storage_stats();
+        let entries: Vec<(&str, usize)> = stats.files.iter().map(|file| (file.name, file.entries)).collect();
+        assert_eq!(entries, [(USERS_FILE, 2), (SESSIONS_FILE, 2), (FRIENDS_FILE, 1), (FRIEND_REQUESTS_FILE, 0)]);
+        assert!(stats.files.iter().all(|file| file.bytes > 0));
+    }
+
+    #[test]
+    fn maintenance_keeps_the_session_in_use() {
+        let manager = manager("maintenance-keep");
+        let alice = register(&manager, "alice");
+        manager.sessions.write().unwrap().extend([
+            session(alice, "active", -31, -1),
+            session(alice, "other", -31, -1),
+        ]);
+
+        let report = manager.run_maintenance(Some("active")).unwrap();
+        assert_eq!(report, MaintenanceReport { sessions_removed: 1, ..Default::default() });
+        assert_eq!(tokens(&manager), ["active"]);
+        assert!(manager.run_maintenance(Some("active")).unwrap().is_empty());
+        assert_eq!(manager.purge_sessions(None), 1);
+    }
+
+    fn board_entry(total_score: u64) -> LeaderboardEntry {
+        LeaderboardEntry {
+            user_id: Uuid::new_v4(),
+            username: format!("player{}", total_score),
+            rank: 0,
+            total_score,
+            average_accuracy: 90.0,
+            total_games: 1,
+        }
+    }
+
+    #[test]
+    fn pages_carry_their_ranks_and_the_total() {
+        let board = Leaderboard::from_entries((1..=25).map(|i| board_entry(i * 100)).collect());
+        let page = board.page(10, 10);
+        assert_eq!(page.offset, 10);
+        assert_eq!(page.total_count, 25);
+        assert_eq!(page.entries.iter().map(|e| e.rank).collect::<Vec<_>>(), (11..=20).collect::<Vec<_>>());
+        assert_eq!(page.entries[0].total_score, 1500);
+
+        // A page past the end is empty rather than an error
+        assert_eq!(board.page(20, 10).entries.len(), 5);
+        assert!(board.page(100, 10).entries.is_empty());
+    }
+
+    #[test]
+    fn upserts_move_a_player_to_their_new_rank() {
+        let entries: Vec<LeaderboardEntry> = (1..=5).map(|i| board_entry(i * 100)).collect();
+        let last = entries[0].clone();
+        let mut board = Leaderboard::from_entries(entries);
+        assert_eq!(board.rank_of(last.user_id), Some(5));
+
+        board.upsert(LeaderboardEntry { total_score: 1000, ..last.clone() });
+        assert_eq!(board.rank_of(last.user_id), Some(1));
+        assert_eq!(board.len(), 5);
+        assert_eq!(board.page_offset_of(last.user_id, 2), Some(0));
+
+        assert!(board.remove(last.user_id).is_some());
+        assert_eq!(board.rank_of(last.user_id), None);
+        assert_eq!(board.len(), 4);
+    }
+
+    #[test]
+    fn fifty_thousand_players_update_and_query_within_milliseconds() {
+        let entries: Vec<LeaderboardEntry> = (0..50_000).map(|i| board_entry(i * 7 % 100_003)).collect();
+        let players: Vec<LeaderboardEntry> = entries.iter().step_by(50).cloned().collect();
+        let mut board = Leaderboard::from_entries(entries);
+
+        let start = std::time::Instant::now();
+        for (i, player) in players.iter().enumerate() {
+            board.upsert(LeaderboardEntry { total_score: player.total_score + i as u64 * 13, ..player.clone() });
+            let rank = board.rank_of(player.user_id).unwrap();
+            let offset = board.page_offset_of(player.user_id, 50).unwrap();
+            let page = board.page(offset, 50);
+            assert!(page.entries.iter().any(|e| e.user_id == player.user_id && e.rank == rank));
+        }
+        let per_update = start.elapsed() / players.len() as u32;
+        assert_eq!(board.len(), 50_000);
+        assert!(per_update < std::time::Duration::from_millis(3), "{:?} per update", per_update);
+    }
+}
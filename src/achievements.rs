@@ -0,0 +1,558 @@
+// src/achievements.rs
+
+use crate::analytics::{AchievementCategory, Analytics, Grade};
+use crate::config::GameConfig;
+use crate::event_bus::{BusEvent, EventBus};
+use crate::score_filter::speed_percent;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+
+/// Bundled achievement definitions
+pub const ACHIEVEMENTS_PATH: &str = "src/assets/achievements.json";
+
+/// What has to happen to unlock an achievement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "condition_type", content = "data")]
+pub enum AchievementCondition {
+    TotalGames {
+        count: u32,
+    },
+    TotalScore {
+        score: u64,
+    },
+    PerfectGame,
+    FullCombo {
+        combo: u32,
+    },
+    Accuracy {
+        min_accuracy: f64,
+    },
+    FirstBlood,
+    /// Total time spent playing
+    CumulativePlayTime {
+        seconds: u64,
+    },
+    /// Distinct songs cleared without a miss
+    SongsFullCombod {
+        count: u32,
+    },
+    /// Any run reaching a grade
+    Grade {
+        grade: Grade,
+    },
+    /// Consecutive sessions, all at or above an accuracy
+    AccuracyStreak {
+        min_accuracy: f64,
+        sessions: u32,
+    },
+    /// Distinct songs played
+    DistinctSongs {
+        count: u32,
+    },
+    /// Any song full combo'd at or above a playback speed, e.g. 1.5 for Double Time
+    SpeedFullCombo {
+        speed: f32,
+    },
+}
+
+impl AchievementCondition {
+    /// Progress toward a threshold condition, None for conditions met in a single run
+    pub fn progress(&self, stats: &AchievementStats) -> Option<AchievementProgress> {
+        let (current, target) = match self {
+            AchievementCondition::TotalGames { count } => (stats.total_games as u64, *count as u64),
+            AchievementCondition::TotalScore { score } => (stats.total_score, *score),
+            AchievementCondition::FullCombo { combo } => {
+                (stats.highest_combo as u64, *combo as u64)
+            }
+            AchievementCondition::CumulativePlayTime { seconds } => {
+                (stats.play_time_seconds, *seconds)
+            }
+            AchievementCondition::SongsFullCombod { count } => {
+                (stats.songs_full_combo as u64, *count as u64)
+            }
+            AchievementCondition::AccuracyStreak {
+                min_accuracy,
+                sessions,
+            } => (
+                stats.accuracy_streak(*min_accuracy) as u64,
+                *sessions as u64,
+            ),
+            AchievementCondition::DistinctSongs { count } => {
+                (stats.songs_played as u64, *count as u64)
+            }
+            _ => return None,
+        };
+        Some(AchievementProgress {
+            current: current.min(target),
+            target,
+        })
+    }
+
+    /// Whether the stats meet the condition
+    pub fn is_met(&self, stats: &AchievementStats) -> bool {
+        if let Some(progress) = self.progress(stats) {
+            return progress.is_complete();
+        }
+        match self {
+            AchievementCondition::PerfectGame => stats.perfect_games > 0,
+            AchievementCondition::Accuracy { min_accuracy } => stats.best_accuracy >= *min_accuracy,
+            AchievementCondition::FirstBlood => stats.multiplayer_wins > 0,
+            AchievementCondition::Grade { grade } => stats.grades.contains(grade),
+            // Compared in hundredths, as speeds are stored
+            AchievementCondition::SpeedFullCombo { speed } => {
+                speed_percent(stats.fastest_full_combo) >= speed_percent(*speed)
+            }
+            _ => false,
+        }
+    }
+
+    /// Progress as shown under a locked achievement, e.g. "37/100"
+    pub fn progress_label(&self, progress: AchievementProgress) -> String {
+        match self {
+            AchievementCondition::CumulativePlayTime { .. } => {
+                format!("{}/{} min", progress.current / 60, progress.target / 60)
+            }
+            AchievementCondition::AccuracyStreak { .. } => {
+                format!("{}/{} in a row", progress.current, progress.target)
+            }
+            _ => format!("{}/{}", progress.current, progress.target),
+        }
+    }
+
+    /// Reject thresholds that could never be shown as progress
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            AchievementCondition::TotalGames { count: 0 }
+            | AchievementCondition::FullCombo { combo: 0 }
+            | AchievementCondition::SongsFullCombod { count: 0 }
+            | AchievementCondition::TotalScore { score: 0 }
+            | AchievementCondition::CumulativePlayTime { seconds: 0 }
+            | AchievementCondition::AccuracyStreak { sessions: 0, .. }
+            | AchievementCondition::DistinctSongs { count: 0 } => {
+                Err("threshold must be above zero".to_string())
+            }
+            AchievementCondition::Accuracy { min_accuracy }
+            | AchievementCondition::AccuracyStreak { min_accuracy, .. }
+                if !(0.0..=100.0).contains(min_accuracy) =>
+            {
+                Err("accuracy must be between 0 and 100".to_string())
+            }
+            AchievementCondition::SpeedFullCombo { speed } if *speed <= 0.0 => {
+                Err("speed must be above zero".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How far a threshold condition has come
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AchievementProgress {
+    pub current: u64,
+    pub target: u64,
+}
+
+impl AchievementProgress {
+    /// Share of the threshold reached, 0-1
+    pub fn fraction(&self) -> f32 {
+        if self.target == 0 {
+            1.0
+        } else {
+            self.current as f32 / self.target as f32
+        }
+    }
+
+    /// Whether the threshold is reached
+    pub fn is_complete(&self) -> bool {
+        self.current >= self.target
+    }
+}
+
+/// How rare an achievement is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AchievementRarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+/// One achievement from the definitions file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AchievementDefinition {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub category: AchievementCategory,
+    #[serde(default)]
+    pub rarity: AchievementRarity,
+    pub condition: AchievementCondition,
+}
+
+/// Player statistics achievements are checked against, built from local analytics
+/// (`Analytics::achievement_stats`) or a server account
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AchievementStats {
+    pub total_games: u32,
+    pub total_score: u64,
+    pub highest_combo: u32,
+    pub best_accuracy: f64,
+    pub play_time_seconds: u64,
+    /// Runs with perfect accuracy and no misses
+    pub perfect_games: u32,
+    /// Distinct songs full combo'd
+    pub songs_full_combo: u32,
+    /// Distinct songs played
+    pub songs_played: u32,
+    /// Accuracy of each session counting toward streaks, oldest first
+    pub session_accuracies: Vec<f64>,
+    /// Highest playback speed a song was full combo'd at, 0 if none
+    pub fastest_full_combo: f32,
+    /// Grades reached at least once
+    pub grades: Vec<Grade>,
+    pub multiplayer_wins: u32,
+}
+
+impl AchievementStats {
+    /// Most consecutive sessions at or above an accuracy
+    pub fn accuracy_streak(&self, min_accuracy: f64) -> u32 {
+        let mut longest = 0;
+        let mut current = 0;
+        for &accuracy in &self.session_accuracies {
+            if accuracy >= min_accuracy {
+                current += 1;
+                longest = longest.max(current);
+            } else {
+                current = 0;
+            }
+        }
+        longest
+    }
+}
+
+/// Achievement definitions loaded at startup
+#[derive(Debug, Clone, Resource)]
+pub struct AchievementDefinitions {
+    pub definitions: Vec<AchievementDefinition>,
+}
+
+impl AchievementDefinitions {
+    /// Load the bundled definitions, falling back to the built-in set if the file is
+    /// missing or invalid
+    pub fn load() -> Self {
+        let json = match fs::read_to_string(ACHIEVEMENTS_PATH) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!(
+                    "Failed to read {}: {}, using built-in achievements",
+                    ACHIEVEMENTS_PATH, e
+                );
+                return Self::builtin();
+            }
+        };
+        match Self::from_json(&json) {
+            Ok((definitions, warnings)) => {
+                for warning in warnings {
+                    warn!("{}: {}", ACHIEVEMENTS_PATH, warning);
+                }
+                definitions
+            }
+            Err(e) => {
+                warn!(
+                    "Invalid {}: {}, using built-in achievements",
+                    ACHIEVEMENTS_PATH, e
+                );
+                Self::builtin()
+            }
+        }
+    }
+
+    /// Parse and validate definitions. Duplicate ids keep the first entry and are
+    /// reported as warnings; anything else wrong rejects the whole file
+    pub fn from_json(json: &str) -> Result<(Self, Vec<String>), String> {
+        let parsed: Vec<AchievementDefinition> =
+            serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        let mut seen = HashSet::new();
+        let mut warnings = Vec::new();
+        let mut definitions = Vec::new();
+        for definition in parsed {
+            if definition.id.trim().is_empty() || definition.name.trim().is_empty() {
+                return Err("every achievement needs an id and a name".to_string());
+            }
+            definition
+                .condition
+                .validate()
+                .map_err(|e| format!("achievement \"{}\": {}", definition.id, e))?;
+            if !seen.insert(definition.id.clone()) {
+                warnings.push(format!(
+                    "duplicate achievement id \"{}\" ignored",
+                    definition.id
+                ));
+                continue;
+            }
+            definitions.push(definition);
+        }
+
+        if definitions.is_empty() {
+            return Err("no achievements defined".to_string());
+        }
+        Ok((Self { definitions }, warnings))
+    }
+
+    /// Minimal set compiled into the game
+    pub fn builtin() -> Self {
+        let definition =
+            |id: &str, name: &str, description: &str, category, condition| AchievementDefinition {
+                id: id.to_string(),
+                name: name.to_string(),
+                description: description.to_string(),
+                category,
+                rarity: AchievementRarity::Common,
+                condition,
+            };
+        Self {
+            definitions: vec![
+                definition(
+                    "first_game",
+                    "First Steps",
+                    "Play your first game",
+                    AchievementCategory::Special,
+                    AchievementCondition::TotalGames { count: 1 },
+                ),
+                definition(
+                    "full_combo",
+                    "Full Combo",
+                    "Complete a song without misses",
+                    AchievementCategory::Streak,
+                    AchievementCondition::SongsFullCombod { count: 1 },
+                ),
+                definition(
+                    "ss_grade",
+                    "SS Rank",
+                    "Get an SS grade",
+                    AchievementCategory::Score,
+                    AchievementCondition::Grade { grade: Grade::SS },
+                ),
+            ],
+        }
+    }
+
+    /// Definition by id
+    pub fn get(&self, id: &str) -> Option<&AchievementDefinition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.id == id)
+    }
+
+    /// Locked achievements whose conditions the stats now meet
+    pub fn newly_met(
+        &self,
+        stats: &AchievementStats,
+        is_unlocked: impl Fn(&str) -> bool,
+    ) -> Vec<&AchievementDefinition> {
+        self.definitions
+            .iter()
+            .filter(|definition| !is_unlocked(&definition.id))
+            .filter(|definition| definition.condition.is_met(stats))
+            .collect()
+    }
+
+    /// Locked achievements with their progress, closest to unlocking first
+    pub fn locked_by_progress(
+        &self,
+        stats: &AchievementStats,
+        is_unlocked: impl Fn(&str) -> bool,
+    ) -> Vec<(&AchievementDefinition, Option<AchievementProgress>)> {
+        let mut locked: Vec<_> = self
+            .definitions
+            .iter()
+            .filter(|definition| !is_unlocked(&definition.id))
+            .map(|definition| (definition, definition.condition.progress(stats)))
+            .collect();
+        let fraction = |progress: &Option<AchievementProgress>| {
+            progress.map_or(0.0, |progress| progress.fraction())
+        };
+        locked.sort_by(|a, b| fraction(&b.1).total_cmp(&fraction(&a.1)));
+        locked
+    }
+}
+
+/// Unlock achievements whose conditions the latest analytics meet
+pub fn unlock_achievements(
+    mut analytics: ResMut<Analytics>,
+    definitions: Res<AchievementDefinitions>,
+    config: Res<GameConfig>,
+    mut bus: ResMut<EventBus>,
+) {
+    if !analytics.is_changed() && !definitions.is_changed() {
+        return;
+    }
+    if !config.records_analytics() {
+        return;
+    }
+
+    let stats = analytics.achievement_stats();
+    let pending: Vec<AchievementDefinition> = definitions
+        .newly_met(&stats, |id| analytics.has_achievement(id))
+        .into_iter()
+        .cloned()
+        .collect();
+    if pending.is_empty() {
+        return;
+    }
+    for definition in &pending {
+        analytics.unlock_achievement(definition);
+        bus.emit(BusEvent::AchievementUnlocked {
+            name: definition.name.clone(),
+            category: definition.category,
+        });
+    }
+    analytics.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition_json(id: &str, condition: &str) -> String {
+        format!(
+            r#"{{"id":"{}","name":"Name","description":"","category":"Special","condition":{}}}"#,
+            id, condition
+        )
+    }
+
+    const TEN_GAMES: &str = r#"{"condition_type":"TotalGames","data":{"count":10}}"#;
+
+    #[test]
+    fn the_bundled_definitions_load_without_warnings() {
+        let json = fs::read_to_string(ACHIEVEMENTS_PATH).unwrap();
+        let (definitions, warnings) = AchievementDefinitions::from_json(&json).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(
+            definitions.definitions.len() > AchievementDefinitions::builtin().definitions.len()
+        );
+        assert_eq!(
+            AchievementDefinitions::load().definitions,
+            definitions.definitions
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_keep_the_first_and_warn() {
+        let json = format!(
+            "[{},{},{}]",
+            definition_json("games", TEN_GAMES),
+            definition_json("games", r#"{"condition_type":"PerfectGame"}"#),
+            definition_json("perfect", r#"{"condition_type":"PerfectGame"}"#)
+        );
+        let (definitions, warnings) = AchievementDefinitions::from_json(&json).unwrap();
+        assert_eq!(definitions.definitions.len(), 2);
+        assert_eq!(
+            definitions.get("games").unwrap().condition,
+            AchievementCondition::TotalGames { count: 10 }
+        );
+        assert_eq!(
+            warnings,
+            vec!["duplicate achievement id \"games\" ignored".to_string()]
+        );
+    }
+
+    #[test]
+    fn invalid_files_are_rejected_for_the_builtin_set() {
+        let zero = definition_json(
+            "zero",
+            r#"{"condition_type":"TotalGames","data":{"count":0}}"#,
+        );
+        assert_eq!(
+            AchievementDefinitions::from_json(&format!("[{}]", zero)).unwrap_err(),
+            "achievement \"zero\": threshold must be above zero"
+        );
+        let unnamed = definition_json(" ", TEN_GAMES);
+        assert!(AchievementDefinitions::from_json(&format!("[{}]", unnamed)).is_err());
+        assert_eq!(
+            AchievementDefinitions::from_json("[]").unwrap_err(),
+            "no achievements defined"
+        );
+        assert!(AchievementDefinitions::from_json("{").is_err());
+        assert!(!AchievementDefinitions::builtin().definitions.is_empty());
+    }
+
+    #[test]
+    fn threshold_progress_counts_toward_the_target() {
+        let stats = AchievementStats {
+            total_games: 37,
+            play_time_seconds: 150 * 60,
+            session_accuracies: vec![99.0, 96.0, 80.0, 97.0, 98.0, 99.5],
+            ..Default::default()
+        };
+
+        let games = AchievementCondition::TotalGames { count: 100 };
+        let progress = games.progress(&stats).unwrap();
+        assert_eq!(
+            progress,
+            AchievementProgress {
+                current: 37,
+                target: 100
+            }
+        );
+        assert_eq!(games.progress_label(progress), "37/100");
+        assert!(!games.is_met(&stats));
+
+        let time = AchievementCondition::CumulativePlayTime { seconds: 120 * 60 };
+        let progress = time.progress(&stats).unwrap();
+        assert_eq!(progress.current, progress.target);
+        assert_eq!(time.progress_label(progress), "120/120 min");
+        assert!(time.is_met(&stats));
+
+        let streak = AchievementCondition::AccuracyStreak {
+            min_accuracy: 95.0,
+            sessions: 5,
+        };
+        assert_eq!(
+            streak.progress_label(streak.progress(&stats).unwrap()),
+            "3/5 in a row"
+        );
+        assert_eq!(AchievementCondition::PerfectGame.progress(&stats), None);
+    }
+
+    #[test]
+    fn locked_achievements_sort_closest_to_unlocking_first() {
+        let json = format!(
+            "[{},{},{},{}]",
+            definition_json(
+                "hundred",
+                r#"{"condition_type":"TotalGames","data":{"count":100}}"#
+            ),
+            definition_json("perfect", r#"{"condition_type":"PerfectGame"}"#),
+            definition_json("ten", TEN_GAMES),
+            definition_json(
+                "first",
+                r#"{"condition_type":"TotalGames","data":{"count":1}}"#
+            )
+        );
+        let (definitions, _) = AchievementDefinitions::from_json(&json).unwrap();
+        let stats = AchievementStats {
+            total_games: 8,
+            ..Default::default()
+        };
+
+        let locked: Vec<&str> = definitions
+            .locked_by_progress(&stats, |id| id == "first")
+            .into_iter()
+            .map(|(definition, _)| definition.id.as_str())
+            .collect();
+        assert_eq!(locked, vec!["ten", "hundred", "perfect"]);
+
+        let met: Vec<&str> = definitions
+            .newly_met(&stats, |_| false)
+            .into_iter()
+            .map(|d| d.id.as_str())
+            .collect();
+        assert_eq!(met, vec!["first"]);
+    }
+}
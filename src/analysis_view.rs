@@ -0,0 +1,367 @@
+// src/analysis_view.rs
+
+use crate::constants::{GOOD_WINDOW, NEON_PINK, OKAY_WINDOW, PERFECT_WINDOW};
+use crate::simulation::{step_seconds, InputEvent};
+use crate::structs::{
+    GameAssets, GameCircle, GameStateResource, VisualizingData, VisualizingState,
+};
+use crate::ui::UiElement;
+use bevy::prelude::*;
+
+/// Playfield scale while the analysis view is on
+pub const ANALYSIS_ZOOM: f32 = 0.7;
+
+/// How far past the playhead upcoming objects are shown as ghosts (seconds)
+pub const GHOST_LOOKAHEAD: f64 = 5.0;
+
+/// Song time the input strip shows before and after the playhead (seconds)
+pub const STRIP_PAST: f64 = 2.0;
+pub const STRIP_AHEAD: f64 = 1.0;
+
+/// Height of the input strip (pixels, before the zoom)
+const STRIP_HEIGHT: f32 = 60.0;
+
+/// Judgment windows drawn on the strip, widest first so narrower ones sit on top
+const STRIP_WINDOWS: [(f64, (f32, f32, f32)); 3] = [
+    (OKAY_WINDOW, (1.0, 1.0, 0.0)),
+    (GOOD_WINDOW, (0.0, 0.75, 1.0)),
+    (PERFECT_WINDOW, (0.0, 1.0, 0.5)),
+];
+
+/// Whether the analysis view may be shown. Only plays the game makes itself (Auto
+/// or practice autoplay) outside multiplayer can be watched this way; during live
+/// play the ghosts would be a five second preview of the map
+pub fn analysis_view_allowed(state: &VisualizingState, multiplayer: bool) -> bool {
+    !multiplayer && (state.game_settings.is_auto() || state.config.practice.autoplay)
+}
+
+/// An upcoming object drawn faintly before it normally appears
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ghost {
+    pub position: Vec2,
+    pub radius: f32,
+    pub spawn_time: f64,
+    pub hit_time: f64,
+    /// Hit time as "m:ss.mmm", formatted once when the view is built
+    pub label: String,
+}
+
+/// Overview of the playfield for watching: ghosts of the next objects and a strip
+/// plotting inputs against the judgment windows. Built when the view is switched on
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisView {
+    /// Ordered by hit time so a frame only looks at the ghosts around the song time
+    ghosts: Vec<Ghost>,
+}
+
+impl AnalysisView {
+    /// Precompute the ghosts of a map from its circles
+    pub fn new(circles: &[GameCircle]) -> Self {
+        let mut ghosts: Vec<Ghost> = circles
+            .iter()
+            .map(|circle| Ghost {
+                position: circle.position,
+                radius: circle.max_radius,
+                spawn_time: circle.spawn_time,
+                hit_time: circle.hit_time,
+                label: format_song_time(circle.hit_time),
+            })
+            .collect();
+        ghosts.sort_by(|a, b| a.hit_time.total_cmp(&b.hit_time));
+        Self { ghosts }
+    }
+
+    /// Ghosts hit within `GHOST_LOOKAHEAD` after a song time that aren't on screen yet
+    pub fn ghosts(&self, time: f64) -> impl Iterator<Item = &Ghost> {
+        let start = self.ghosts.partition_point(|ghost| ghost.hit_time <= time);
+        let end = self
+            .ghosts
+            .partition_point(|ghost| ghost.hit_time <= time + GHOST_LOOKAHEAD);
+        self.ghosts[start..end]
+            .iter()
+            .filter(move |ghost| ghost.spawn_time > time)
+    }
+
+    /// Hit times inside the strip's time range, for its judgment windows
+    fn strip_targets(&self, time: f64) -> impl Iterator<Item = f64> + '_ {
+        let start = self
+            .ghosts
+            .partition_point(|ghost| ghost.hit_time < time - STRIP_PAST - OKAY_WINDOW);
+        let end = self
+            .ghosts
+            .partition_point(|ghost| ghost.hit_time <= time + STRIP_AHEAD + OKAY_WINDOW);
+        self.ghosts[start..end].iter().map(|ghost| ghost.hit_time)
+    }
+}
+
+/// Song time as "m:ss.mmm"
+fn format_song_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Switch the analysis view with V and zoom the camera to match. Outside a watched
+/// play the key does nothing and the view is forced off, so it can never be used
+/// as a preview during live play
+pub fn handle_analysis_view_toggle(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut visualizing_data: ResMut<VisualizingData>,
+    game_state: Res<GameStateResource>,
+    mut cameras: Query<&mut OrthographicProjection, With<Camera2d>>,
+) {
+    if !analysis_view_allowed(&visualizing_data.state, game_state.multiplayer) {
+        visualizing_data.analysis = None;
+    } else if keyboard.just_pressed(KeyCode::KeyV) {
+        visualizing_data.analysis = match visualizing_data.analysis {
+            Some(_) => None,
+            None => Some(AnalysisView::new(&visualizing_data.state.circles)),
+        };
+    }
+
+    let scale = if visualizing_data.analysis.is_some() {
+        1.0 / ANALYSIS_ZOOM
+    } else {
+        1.0
+    };
+    for mut projection in &mut cameras {
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+/// Put the camera back to normal framing on the way out of a play
+pub fn reset_analysis_zoom(mut cameras: Query<&mut OrthographicProjection, With<Camera2d>>) {
+    for mut projection in &mut cameras {
+        projection.scale = 1.0;
+    }
+}
+
+/// Draw the ghosts and the input strip. `screen` is the window size; the strip runs
+/// along the bottom of the zoomed out view
+pub fn draw_analysis_view(
+    commands: &mut Commands,
+    view: &AnalysisView,
+    inputs: &[InputEvent],
+    elapsed: f64,
+    screen: Vec2,
+    assets: &GameAssets,
+) {
+    for ghost in view.ghosts(elapsed) {
+        // Fade in as the ghost nears its normal appearance
+        let lead = ((ghost.spawn_time - elapsed) / GHOST_LOOKAHEAD) as f32;
+        let alpha = 0.08 + 0.12 * (1.0 - lead.clamp(0.0, 1.0));
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, alpha),
+                custom_size: Some(Vec2::splat(ghost.radius * 2.0)),
+                ..default()
+            },
+            Transform::from_xyz(ghost.position.x, ghost.position.y, 0.01),
+            UiElement,
+        ));
+        commands.spawn((
+            Text2d::new(ghost.label.clone()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, alpha * 2.5)),
+            Transform::from_xyz(ghost.position.x, ghost.position.y, 0.02),
+            UiElement,
+        ));
+    }
+
+    draw_input_strip(commands, view, inputs, elapsed, screen / ANALYSIS_ZOOM);
+}
+
+/// Strip of the last `STRIP_PAST` and next `STRIP_AHEAD` seconds: a judgment window
+/// band around each hit time, a tick per input and a line at the playhead
+fn draw_input_strip(
+    commands: &mut Commands,
+    view: &AnalysisView,
+    inputs: &[InputEvent],
+    elapsed: f64,
+    view_size: Vec2,
+) {
+    let width = view_size.x - 40.0;
+    let center_y = -view_size.y / 2.0 + STRIP_HEIGHT / 2.0 + 20.0;
+    let span = STRIP_PAST + STRIP_AHEAD;
+    let to_x = |time: f64| -width / 2.0 + ((time - elapsed + STRIP_PAST) / span) as f32 * width;
+
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(0.0, 0.0, 0.0, 0.6),
+            custom_size: Some(Vec2::new(width, STRIP_HEIGHT)),
+            ..default()
+        },
+        Transform::from_xyz(0.0, center_y, 0.6),
+        UiElement,
+    ));
+
+    let left = elapsed - STRIP_PAST;
+    let right = elapsed + STRIP_AHEAD;
+    for hit_time in view.strip_targets(elapsed) {
+        for (layer, (window, (r, g, b))) in STRIP_WINDOWS.iter().enumerate() {
+            let start = (hit_time - window).max(left);
+            let end = (hit_time + window).min(right);
+            if end <= start {
+                continue;
+            }
+            let (x0, x1) = (to_x(start), to_x(end));
+            commands.spawn((
+                Sprite {
+                    color: Color::srgba(*r, *g, *b, 0.25),
+                    custom_size: Some(Vec2::new(x1 - x0, STRIP_HEIGHT * 0.6)),
+                    ..default()
+                },
+                Transform::from_xyz((x0 + x1) / 2.0, center_y, 0.61 + layer as f32 * 0.01),
+                UiElement,
+            ));
+        }
+    }
+
+    // Inputs are in time order, so only the tail can be on the strip; releases only
+    // matter to sliders
+    let first = inputs.partition_point(|input| step_seconds(input.time_ms) < left);
+    for input in inputs[first..].iter().filter(|input| !input.release) {
+        let time = step_seconds(input.time_ms);
+        if time > right {
+            break;
+        }
+        commands.spawn((
+            Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::new(2.0, STRIP_HEIGHT * 0.9)),
+                ..default()
+            },
+            Transform::from_xyz(to_x(time), center_y, 0.65),
+            UiElement,
+        ));
+    }
+
+    commands.spawn((
+        Sprite {
+            color: NEON_PINK,
+            custom_size: Some(Vec2::new(2.0, STRIP_HEIGHT)),
+            ..default()
+        },
+        Transform::from_xyz(to_x(elapsed), center_y, 0.66),
+        UiElement,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::gamemode::Modifier;
+
+    fn circle(hit_time: f64) -> GameCircle {
+        GameCircle {
+            position: Vec2::new(hit_time as f32 * 10.0, 0.0),
+            spawn_time: hit_time - 1.5,
+            hit_time,
+            max_radius: 60.0,
+            hit: false,
+            missed: false,
+            kind: Default::default(),
+            new_combo: false,
+            hitsound: Default::default(),
+        }
+    }
+
+    /// A play of five circles, one a second
+    fn visualizing(config: GameConfig) -> VisualizingData {
+        let circles = (1..=5).map(|i| circle(i as f64)).collect();
+        VisualizingData {
+            state: VisualizingState::new(Vec::new(), circles, config, "test".into()),
+            clock: Default::default(),
+            song_clock: crate::simulation::SongClock::new(1.0, None),
+            show_debug: false,
+            latency: Default::default(),
+            analysis: None,
+            energy: Vec::new(),
+            attempt: 1,
+            loops: 0,
+            pause_menu: None,
+        }
+    }
+
+    /// Press V once with the play set up as given, returning whether the view is on
+    /// and the camera's scale
+    fn press_v(world: &mut World) -> (bool, f32) {
+        let mut keyboard = world.resource_mut::<ButtonInput<KeyCode>>();
+        keyboard.release(KeyCode::KeyV);
+        keyboard.clear();
+        keyboard.press(KeyCode::KeyV);
+        let toggle = world.register_system(handle_analysis_view_toggle);
+        world.run_system(toggle).unwrap();
+        let on = world.resource::<VisualizingData>().analysis.is_some();
+        let scale = world.query::<&OrthographicProjection>().single(world).scale;
+        (on, scale)
+    }
+
+    fn world(config: GameConfig, multiplayer: bool) -> World {
+        let mut world = World::new();
+        world.insert_resource(ButtonInput::<KeyCode>::default());
+        world.insert_resource(visualizing(config));
+        world.insert_resource(GameStateResource {
+            multiplayer,
+            ..Default::default()
+        });
+        world.spawn((Camera2d, OrthographicProjection::default_2d()));
+        world
+    }
+
+    #[test]
+    fn the_view_is_refused_during_live_play() {
+        let mut live = world(GameConfig::default(), false);
+        assert_eq!(press_v(&mut live), (false, 1.0));
+
+        // A view left on from a watched play is switched off
+        live.resource_mut::<VisualizingData>().analysis = Some(AnalysisView::default());
+        assert_eq!(press_v(&mut live), (false, 1.0));
+
+        let mut auto = GameConfig::default();
+        auto.game_settings.modifiers = vec![Modifier::Auto];
+        let mut multiplayer = world(auto, true);
+        assert_eq!(press_v(&mut multiplayer), (false, 1.0));
+    }
+
+    #[test]
+    fn watched_plays_toggle_the_zoomed_out_view() {
+        let mut auto = GameConfig::default();
+        auto.game_settings.modifiers = vec![Modifier::Auto];
+        let mut practice = GameConfig::default();
+        practice.practice.autoplay = true;
+
+        for config in [auto, practice] {
+            let mut world = world(config, false);
+            assert_eq!(press_v(&mut world), (true, 1.0 / ANALYSIS_ZOOM));
+            assert_eq!(press_v(&mut world), (false, 1.0));
+        }
+    }
+
+    #[test]
+    fn ghosts_are_the_objects_due_soon_that_are_not_on_screen_yet() {
+        let circles: Vec<GameCircle> = [6.0, 1.0, 3.0, 2.0, 7.0].map(circle).to_vec();
+        let view = AnalysisView::new(&circles);
+        let times = |time| {
+            view.ghosts(time)
+                .map(|ghost| ghost.hit_time)
+                .collect::<Vec<_>>()
+        };
+        // At 1.0s the circle at 2.0 is already showing; 7.0 is past the lookahead
+        assert_eq!(times(1.0), [3.0, 6.0]);
+        assert_eq!(times(2.5), [6.0, 7.0]);
+        assert!(times(7.0).is_empty());
+        assert_eq!(view.ghosts(2.5).next().unwrap().label, "0:06.000");
+    }
+}
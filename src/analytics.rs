@@ -1,12 +1,20 @@
 // src/analytics.rs
 
+use crate::achievements::{AchievementDefinition, AchievementStats};
+use crate::precision::PrecisionMap;
+use crate::score_filter::{mods_label, PlaySignature};
+use crate::seasons::{Season, SeasonStats};
+use crate::session_details::{evict_details, SessionDetails, SESSION_DETAILS_DIR};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+/// Where analytics were stored before local profiles; migrated into the shared profile
+pub const ANALYTICS_PATH: &str = "analytics.json";
+
 /// Analytics data for tracking player performance
 #[derive(Debug, Clone, Serialize, Deserialize, Resource)]
 pub struct Analytics {
@@ -25,11 +33,48 @@ pub struct Analytics {
     /// Overall accuracy history
     pub accuracy_history: Vec<f32>,
     /// Best scores per song
-    pub best_scores: HashMap<String, i32>,
+    pub best_scores: HashMap<String, i64>,
     /// Achievements unlocked
     pub achievements: Vec<Achievement>,
     /// Last updated timestamp
     pub last_updated: SystemTime,
+    /// Play activity bucketed by day (days since the Unix epoch)
+    #[serde(default)]
+    pub daily_activity: BTreeMap<u64, DailyActivity>,
+    /// Where hits landed and how early or late, over every recorded session
+    #[serde(default)]
+    pub precision: PrecisionMap,
+    /// Plays per season, keyed by season id
+    #[serde(default)]
+    pub seasons: BTreeMap<String, SeasonStats>,
+    /// File these analytics are loaded from and saved to, in the profile's folder
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Seconds in a day, used for bucketing sessions by date
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Play activity for a single day
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DailyActivity {
+    /// Number of sessions played
+    pub sessions: u32,
+    /// Total play time in seconds
+    pub play_time_seconds: u64,
+    /// Distinct songs played that day
+    pub songs: Vec<String>,
+}
+
+impl DailyActivity {
+    /// Add a session to this day's bucket
+    pub fn record(&mut self, session: &GameSession) {
+        self.sessions += 1;
+        self.play_time_seconds += session.duration_seconds;
+        if !self.songs.contains(&session.song_name) {
+            self.songs.push(session.song_name.clone());
+        }
+    }
 }
 
 /// Hit statistics for tracking different hit types
@@ -58,14 +103,19 @@ impl HitStats {
 
     /// Get accuracy percentage (0.0 - 100.0)
     pub fn accuracy(&self) -> f32 {
-        let total = self.total();
+        self.exact_accuracy() as f32
+    }
+
+    /// Accuracy percentage from the integer judgment weights. Only all-perfect hits
+    /// reach 100, however long the map; an f32 would round 99.999999% up to it
+    pub fn exact_accuracy(&self) -> f64 {
+        let total = self.total() as u64;
         if total == 0 {
             return 0.0;
         }
         let weighted_score =
-            (self.perfect as f32 * 300.0) + (self.good as f32 * 100.0) + (self.okay as f32 * 50.0);
-        let max_score = total as f32 * 300.0;
-        (weighted_score / max_score) * 100.0
+            self.perfect as u64 * 300 + self.good as u64 * 100 + self.okay as u64 * 50;
+        weighted_score as f64 * 100.0 / (total * 300) as f64
     }
 
     /// Get hit rate percentage (non-misses)
@@ -85,20 +135,93 @@ impl HitStats {
         self.misses += session.misses;
     }
 
-    /// Get grade based on accuracy
-    pub fn grade(&self) -> Grade {
-        let accuracy = self.accuracy();
-        if accuracy >= 100.0 && self.misses == 0 {
-            Grade::AAA
-        } else if accuracy >= 95.0 {
+    /// Remove hits that were previously added
+    pub fn remove_session(&mut self, session: &HitStats) {
+        self.perfect = self.perfect.saturating_sub(session.perfect);
+        self.good = self.good.saturating_sub(session.good);
+        self.okay = self.okay.saturating_sub(session.okay);
+        self.misses = self.misses.saturating_sub(session.misses);
+    }
+
+    /// Whether every judgment was perfect
+    pub fn all_perfect(&self) -> bool {
+        self.perfect > 0 && self.perfect == self.total()
+    }
+
+    /// Grade the hits earn under a scoring system's rules, from the exact accuracy.
+    /// SS+ is also checked against the judgments, so it always means all perfect
+    pub fn grade(&self, rules: &GradeRules) -> Grade {
+        let grade = match rules.accuracy_grade(self.exact_accuracy()) {
+            Grade::SSPlus if !self.all_perfect() => Grade::SS,
+            grade => grade,
+        };
+        if self.misses > 0 && grade.rank() < rules.best_with_misses.rank() {
+            rules.best_with_misses
+        } else {
+            grade
+        }
+    }
+}
+
+/// Lowest accuracy for each grade under Score V1; shared by the results screen and
+/// the live gauge
+pub const GRADE_SS_ACCURACY: f32 = 95.0;
+pub const GRADE_S_ACCURACY: f32 = 90.0;
+pub const GRADE_A_ACCURACY: f32 = 80.0;
+pub const GRADE_B_ACCURACY: f32 = 70.0;
+pub const GRADE_C_ACCURACY: f32 = 60.0;
+
+/// Grade cutoffs of a scoring system. SS+ always takes every judgment perfect,
+/// which with 300/100/50 weighting is 100% accuracy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradeRules {
+    /// Lowest accuracy for SS, S, A, B and C (percent)
+    pub ss_accuracy: f32,
+    pub s_accuracy: f32,
+    pub a_accuracy: f32,
+    pub b_accuracy: f32,
+    pub c_accuracy: f32,
+    /// Best grade a play with any misses can get
+    pub best_with_misses: Grade,
+}
+
+impl GradeRules {
+    /// Score V1, used for solo play: the accuracy cutoffs, with SS and above needing
+    /// no misses
+    pub const SCORE_V1: GradeRules = GradeRules {
+        ss_accuracy: GRADE_SS_ACCURACY,
+        s_accuracy: GRADE_S_ACCURACY,
+        a_accuracy: GRADE_A_ACCURACY,
+        b_accuracy: GRADE_B_ACCURACY,
+        c_accuracy: GRADE_C_ACCURACY,
+        best_with_misses: Grade::S,
+    };
+
+    /// Score V2: stricter cutoffs and S and above need no misses. SS needs 100%
+    /// accuracy and SS+ also needs every judgment perfect, which with the
+    /// 300/100/50 weights comes to the same plays
+    pub const SCORE_V2: GradeRules = GradeRules {
+        ss_accuracy: 100.0,
+        s_accuracy: 95.0,
+        a_accuracy: 90.0,
+        b_accuracy: 80.0,
+        c_accuracy: 70.0,
+        best_with_misses: Grade::A,
+    };
+
+    /// Grade an accuracy percentage earns before misses are counted
+    pub fn accuracy_grade(&self, accuracy: f64) -> Grade {
+        if accuracy >= 100.0 {
+            Grade::SSPlus
+        } else if accuracy >= self.ss_accuracy as f64 {
             Grade::SS
-        } else if accuracy >= 90.0 {
+        } else if accuracy >= self.s_accuracy as f64 {
             Grade::S
-        } else if accuracy >= 80.0 {
+        } else if accuracy >= self.a_accuracy as f64 {
             Grade::A
-        } else if accuracy >= 70.0 {
+        } else if accuracy >= self.b_accuracy as f64 {
             Grade::B
-        } else if accuracy >= 60.0 {
+        } else if accuracy >= self.c_accuracy as f64 {
             Grade::C
         } else {
             Grade::D
@@ -109,7 +232,10 @@ impl HitStats {
 /// Performance grade
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Grade {
-    AAA,
+    /// Every judgment perfect. Saved as "SS+"; files from before the tier was
+    /// renamed say "AAA"
+    #[serde(rename = "SS+", alias = "AAA")]
+    SSPlus,
     SS,
     S,
     A,
@@ -120,10 +246,44 @@ pub enum Grade {
 }
 
 impl Grade {
+    /// Score V1 grade for an accuracy percentage, when the misses aren't known
+    pub fn from_accuracy(accuracy: f32) -> Grade {
+        GradeRules::SCORE_V1.accuracy_grade(accuracy as f64)
+    }
+
+    /// Grade named by `as_str`, also taking the old "AAA" name of SS+
+    pub fn from_name(name: &str) -> Option<Grade> {
+        match name {
+            "SS+" | "AAA" => Some(Grade::SSPlus),
+            "SS" => Some(Grade::SS),
+            "S" => Some(Grade::S),
+            "A" => Some(Grade::A),
+            "B" => Some(Grade::B),
+            "C" => Some(Grade::C),
+            "D" => Some(Grade::D),
+            "F" => Some(Grade::F),
+            _ => None,
+        }
+    }
+
+    /// Position from the best grade down, SS+ being 0
+    pub fn rank(&self) -> u8 {
+        match self {
+            Grade::SSPlus => 0,
+            Grade::SS => 1,
+            Grade::S => 2,
+            Grade::A => 3,
+            Grade::B => 4,
+            Grade::C => 5,
+            Grade::D => 6,
+            Grade::F => 7,
+        }
+    }
+
     /// Get grade as string
     pub fn as_str(&self) -> &'static str {
         match self {
-            Grade::AAA => "AAA",
+            Grade::SSPlus => "SS+",
             Grade::SS => "SS",
             Grade::S => "S",
             Grade::A => "A",
@@ -137,14 +297,14 @@ impl Grade {
     /// Get grade color
     pub fn color(&self) -> (f32, f32, f32) {
         match self {
-            Grade::AAA => (1.0, 0.9, 0.3), // Platinum/Gold mix
-            Grade::SS => (1.0, 0.84, 0.0), // Gold
-            Grade::S => (1.0, 0.5, 0.0),   // Orange
-            Grade::A => (0.0, 1.0, 0.0),   // Green
-            Grade::B => (0.0, 0.5, 1.0),   // Blue
-            Grade::C => (0.5, 0.0, 1.0),   // Purple
-            Grade::D => (1.0, 0.0, 0.5),   // Pink
-            Grade::F => (1.0, 0.0, 0.0),   // Red
+            Grade::SSPlus => (0.85, 0.95, 1.0), // Platinum
+            Grade::SS => (1.0, 0.84, 0.0),      // Gold
+            Grade::S => (1.0, 0.5, 0.0),        // Orange
+            Grade::A => (0.0, 1.0, 0.0),        // Green
+            Grade::B => (0.0, 0.5, 1.0),        // Blue
+            Grade::C => (0.5, 0.0, 1.0),        // Purple
+            Grade::D => (1.0, 0.0, 0.5),        // Pink
+            Grade::F => (1.0, 0.0, 0.0),        // Red
         }
     }
 }
@@ -157,7 +317,7 @@ pub struct SongStats {
     /// Number of times played
     pub play_count: u32,
     /// Best score achieved
-    pub best_score: i32,
+    pub best_score: i64,
     /// Best accuracy achieved
     pub best_accuracy: f32,
     /// Total hits for this song
@@ -166,8 +326,20 @@ pub struct SongStats {
     pub average_score: f32,
     /// Total play time in seconds
     pub total_play_time_seconds: u64,
+    /// Best completion reached, kept so the song list needn't scan sessions
+    #[serde(default)]
+    pub completion: CompletionStatus,
+    /// Star rating of the map last played for this song
+    #[serde(default)]
+    pub star_rating: Option<f32>,
+    /// Accuracy of the latest plays, oldest first, at most `SONG_ACCURACY_HISTORY`
+    #[serde(default)]
+    pub accuracy_history: Vec<f32>,
 }
 
+/// Plays kept in each song's accuracy history
+pub const SONG_ACCURACY_HISTORY: usize = 30;
+
 impl SongStats {
     /// Create new song stats
     pub fn new(song_name: String) -> Self {
@@ -179,6 +351,9 @@ impl SongStats {
             total_hits: HitStats::new(),
             average_score: 0.0,
             total_play_time_seconds: 0,
+            completion: CompletionStatus::Unplayed,
+            star_rating: None,
+            accuracy_history: Vec::new(),
         }
     }
 
@@ -188,18 +363,94 @@ impl SongStats {
         self.total_play_time_seconds += session.duration_seconds;
         self.total_hits.add_session(&session.hits);
 
-        if session.score > self.best_score {
-            self.best_score = session.score;
-        }
+        if session.counts_for_bests() {
+            if session.score > self.best_score {
+                self.best_score = session.score;
+            }
 
-        let session_accuracy = session.hits.accuracy();
-        if session_accuracy > self.best_accuracy {
-            self.best_accuracy = session_accuracy;
+            let session_accuracy = session.hits.accuracy();
+            if session_accuracy > self.best_accuracy {
+                self.best_accuracy = session_accuracy;
+            }
         }
 
         // Update average score
         let total_score = self.average_score * (self.play_count - 1) as f32;
         self.average_score = (total_score + session.score as f32) / self.play_count as f32;
+
+        self.completion = self.completion.max(CompletionStatus::from_session(session));
+        if session.star_rating.is_some() {
+            self.star_rating = session.star_rating;
+        }
+        self.accuracy_history.push(session.accuracy);
+        if self.accuracy_history.len() > SONG_ACCURACY_HISTORY {
+            self.accuracy_history.remove(0);
+        }
+    }
+}
+
+/// How far a song has been completed, in increasing order
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum CompletionStatus {
+    #[default]
+    Unplayed,
+    /// Played at least once without reaching the end
+    Played,
+    /// Finished without failing
+    Cleared,
+    /// Finished without a miss
+    FullCombo,
+    /// Finished without a miss at grade SS or better
+    SS,
+}
+
+impl CompletionStatus {
+    /// Status a single session earns; practice and unranked runs earn nothing
+    pub fn from_session(session: &GameSession) -> CompletionStatus {
+        if !session.counts_for_completion() {
+            CompletionStatus::Unplayed
+        } else if !session.completed {
+            CompletionStatus::Played
+        } else if !session.full_combo {
+            CompletionStatus::Cleared
+        } else if matches!(session.grade, Grade::SS | Grade::SSPlus) {
+            CompletionStatus::SS
+        } else {
+            CompletionStatus::FullCombo
+        }
+    }
+
+    /// Best status across a song's sessions
+    pub fn derive<'a>(sessions: impl IntoIterator<Item = &'a GameSession>) -> CompletionStatus {
+        sessions
+            .into_iter()
+            .map(CompletionStatus::from_session)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Short tag shown next to a song
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CompletionStatus::Unplayed => "NEW",
+            CompletionStatus::Played => "--",
+            CompletionStatus::Cleared => "CL",
+            CompletionStatus::FullCombo => "FC",
+            CompletionStatus::SS => "SS",
+        }
+    }
+
+    /// Get status color
+    pub fn color(&self) -> (f32, f32, f32) {
+        match self {
+            CompletionStatus::Unplayed => (0.0, 0.75, 1.0),
+            CompletionStatus::Played => (0.5, 0.5, 0.5),
+            CompletionStatus::Cleared => (0.0, 1.0, 0.0),
+            CompletionStatus::FullCombo => (1.0, 0.5, 0.0),
+            CompletionStatus::SS => (1.0, 0.84, 0.0),
+        }
     }
 }
 
@@ -211,7 +462,7 @@ pub struct GameSession {
     /// Song name
     pub song_name: String,
     /// Score achieved
-    pub score: i32,
+    pub score: i64,
     /// Hit statistics
     pub hits: HitStats,
     /// Duration in seconds
@@ -222,10 +473,65 @@ pub struct GameSession {
     pub grade: Grade,
     /// Whether it was a full combo (no misses)
     pub full_combo: bool,
+    /// Longest combo of the play, 0 for sessions recorded before it was tracked
+    #[serde(default)]
+    pub max_combo: u32,
     /// Whether practice mode was enabled
     pub practice_mode: bool,
     /// Playback speed if in practice mode
     pub playback_speed: Option<f32>,
+    /// Quick retries before this run in the same sitting
+    #[serde(default)]
+    pub retries: u32,
+    /// Whether the song played to the end without failing or quitting
+    #[serde(default)]
+    pub completed: bool,
+    /// Whether assisting modifiers (auto, no fail) were on
+    #[serde(default)]
+    pub unranked: bool,
+    /// Seconds of intro skipped, 0 if the intro was played
+    #[serde(default)]
+    pub skipped_intro_seconds: f64,
+    /// Star rating of the map played
+    #[serde(default)]
+    pub star_rating: Option<f32>,
+    /// Modifiers and speed the score was set with
+    #[serde(default)]
+    pub signature: PlaySignature,
+    /// Score multiplier of the difficulty and modifiers, already applied to `score`
+    #[serde(default = "default_score_multiplier")]
+    pub score_multiplier: f32,
+    /// Seed the procedural map was generated from
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Replay file of the play, if it was saved
+    #[serde(default)]
+    pub replay_file: Option<String>,
+    /// Player (1 or 2) of a local versus match; None for solo plays
+    #[serde(default)]
+    pub local_versus: Option<u8>,
+    /// Season active when the session was played; older sessions fall back to their
+    /// calendar quarter
+    #[serde(default)]
+    pub season: Option<String>,
+    /// Details file of the session (judgment log, hit timings); None if none was
+    /// written or it was evicted
+    #[serde(default)]
+    pub details_file: Option<String>,
+    /// Pinned sessions stay in the recent list and keep their details file
+    #[serde(default)]
+    pub pinned: bool,
+    /// Hit positions and timing of the play, merged into the lifetime map and not kept
+    /// per session
+    #[serde(skip)]
+    pub precision: Option<PrecisionMap>,
+    /// Details of the play until they are written to `details_file`
+    #[serde(skip)]
+    pub details: Option<SessionDetails>,
+}
+
+fn default_score_multiplier() -> f32 {
+    1.0
 }
 
 impl GameSession {
@@ -243,12 +549,59 @@ impl GameSession {
             accuracy: 0.0,
             grade: Grade::F,
             full_combo: false,
+            max_combo: 0,
             practice_mode: false,
             playback_speed: None,
+            retries: 0,
+            completed: false,
+            unranked: false,
+            skipped_intro_seconds: 0.0,
+            star_rating: None,
+            signature: PlaySignature::default(),
+            score_multiplier: 1.0,
+            seed: None,
+            replay_file: None,
+            local_versus: None,
+            season: None,
+            details_file: None,
+            pinned: false,
+            precision: None,
+            details: None,
         }
     }
 }
 
+impl GameSession {
+    /// Day this session was played (days since the Unix epoch)
+    pub fn day(&self) -> u64 {
+        self.session_id / SECONDS_PER_DAY
+    }
+
+    /// Id of the season this session counts towards
+    pub fn season_id(&self) -> String {
+        self.season
+            .clone()
+            .unwrap_or_else(|| Season::quarter_of(self.session_id as i64).id)
+    }
+
+    /// Whether the run can raise a song's completion status
+    pub fn counts_for_completion(&self) -> bool {
+        !self.practice_mode && !self.unranked && self.counts_for_bests()
+    }
+
+    /// Whether the run can set a best score; local versus matches are played keyboard
+    /// only and kept out of bests and leaderboards
+    pub fn counts_for_bests(&self) -> bool {
+        self.local_versus.is_none()
+    }
+
+    /// Whether the session counts toward streak and speed achievements: played to the
+    /// end, without practice or assisting modifiers
+    pub fn counts_for_streaks(&self) -> bool {
+        self.completed && !self.practice_mode && !self.unranked
+    }
+}
+
 /// Achievement structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Achievement {
@@ -265,7 +618,7 @@ pub struct Achievement {
 }
 
 /// Achievement categories
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum AchievementCategory {
     Accuracy,
     Score,
@@ -295,7 +648,11 @@ pub struct ActiveSession {
     /// Current hit stats
     pub hits: HitStats,
     /// Current score
-    pub score: i32,
+    pub score: i64,
+    /// Hits since the last miss
+    pub combo: u32,
+    /// Longest combo so far
+    pub max_combo: u32,
     /// Song name
     pub song_name: String,
     /// Whether practice mode is enabled
@@ -304,6 +661,93 @@ pub struct ActiveSession {
     pub playback_speed: f32,
     /// Hit timings for precision analysis (in milliseconds)
     pub hit_timings: Vec<f32>,
+    /// Per-judgment log for the results scrubber
+    pub judgment_log: JudgmentLog,
+    /// Quick retries before this run in the same sitting
+    pub retries: u32,
+    /// Whether the song played to the end without failing or quitting
+    pub completed: bool,
+    /// Whether assisting modifiers (auto, no fail) were on
+    pub unranked: bool,
+    /// Seconds of intro skipped, 0 if the intro was played
+    pub skipped_intro_seconds: f64,
+    /// Star rating of the map being played
+    pub star_rating: Option<f32>,
+    /// Modifiers and speed the run is played with
+    pub signature: PlaySignature,
+    /// Score multiplier of the difficulty and modifiers, applied to each hit's points
+    pub score_multiplier: f32,
+    /// Seed the map being played was generated from
+    pub seed: Option<u64>,
+    /// Hit positions and timing, None when not tracked
+    pub precision: Option<PrecisionMap>,
+    /// Player (1 or 2) of a local versus match; None for solo plays
+    pub local_versus: Option<u8>,
+}
+
+/// Maximum judgments kept for the results scrubber
+pub const MAX_JUDGMENT_LOG: usize = 4000;
+
+/// Judgment given to a single object
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JudgmentKind {
+    Perfect,
+    Good,
+    Okay,
+    Miss,
+}
+
+impl JudgmentKind {
+    /// Judgment for the points awarded by a hit
+    pub fn from_points(points: i32) -> Self {
+        match points {
+            300 => JudgmentKind::Perfect,
+            100 => JudgmentKind::Good,
+            50 => JudgmentKind::Okay,
+            _ => JudgmentKind::Miss,
+        }
+    }
+
+    /// Get judgment color
+    pub fn color(&self) -> (f32, f32, f32) {
+        match self {
+            JudgmentKind::Perfect => (0.0, 1.0, 0.5),
+            JudgmentKind::Good => (0.0, 0.75, 1.0),
+            JudgmentKind::Okay => (1.0, 1.0, 0.0),
+            JudgmentKind::Miss => (1.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// A judgment on one object
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct JudgmentEntry {
+    /// Song time of the object (seconds)
+    pub time: f64,
+    /// Object position on the playfield
+    pub position: Vec2,
+    /// Judgment received
+    pub kind: JudgmentKind,
+}
+
+/// Bounded log of judgments for a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JudgmentLog {
+    /// Recorded judgments in play order
+    pub entries: Vec<JudgmentEntry>,
+    /// Set once the cap was reached; positions beyond it were dropped
+    pub truncated: bool,
+}
+
+impl JudgmentLog {
+    /// Record a judgment, dropping it once the cap is reached
+    pub fn push(&mut self, entry: JudgmentEntry) {
+        if self.entries.len() < MAX_JUDGMENT_LOG {
+            self.entries.push(entry);
+        } else {
+            self.truncated = true;
+        }
+    }
 }
 
 impl ActiveSession {
@@ -313,29 +757,48 @@ impl ActiveSession {
             start_time: std::time::Instant::now(),
             hits: HitStats::new(),
             score: 0,
+            combo: 0,
+            max_combo: 0,
             song_name,
             practice_mode,
             playback_speed,
             hit_timings: Vec::new(),
+            judgment_log: JudgmentLog::default(),
+            retries: 0,
+            completed: false,
+            unranked: false,
+            skipped_intro_seconds: 0.0,
+            star_rating: None,
+            signature: PlaySignature::default(),
+            score_multiplier: 1.0,
+            seed: None,
+            precision: None,
+            local_versus: None,
         }
     }
 
     /// Record a hit
     pub fn record_hit(&mut self, points: i32, timing_ms: f32) {
-        self.score += points;
+        self.score += (points as f32 * self.score_multiplier) as i64;
         self.hit_timings.push(timing_ms);
 
         match points {
             300 => self.hits.perfect += 1,
             100 => self.hits.good += 1,
             50 => self.hits.okay += 1,
-            _ => self.hits.misses += 1,
+            _ => {
+                self.record_miss();
+                return;
+            }
         }
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
     }
 
     /// Record a miss
     pub fn record_miss(&mut self) {
         self.hits.misses += 1;
+        self.combo = 0;
     }
 
     /// Finish the session and create a GameSession
@@ -343,6 +806,10 @@ impl ActiveSession {
         let duration = self.start_time.elapsed().as_secs();
         let accuracy = self.hits.accuracy();
         let full_combo = self.hits.misses == 0;
+        let details = SessionDetails {
+            judgments: self.judgment_log,
+            hit_timings: self.hit_timings,
+        };
 
         GameSession {
             session_id: SystemTime::now()
@@ -354,14 +821,32 @@ impl ActiveSession {
             hits: self.hits.clone(),
             duration_seconds: duration,
             accuracy,
-            grade: self.hits.grade(),
+            grade: self.hits.grade(&GradeRules::SCORE_V1),
             full_combo,
+            max_combo: self.max_combo,
             practice_mode: self.practice_mode,
             playback_speed: if self.practice_mode {
                 Some(self.playback_speed)
             } else {
                 None
             },
+            retries: self.retries,
+            completed: self.completed,
+            unranked: self.unranked,
+            skipped_intro_seconds: self.skipped_intro_seconds,
+            star_rating: self.star_rating,
+            signature: self.signature,
+            score_multiplier: self.score_multiplier,
+            seed: self.seed,
+            replay_file: None,
+            local_versus: self.local_versus,
+            // Tagged when recorded, from the season calendar
+            season: None,
+            // Written when recorded, into the profile's details folder
+            details_file: None,
+            pinned: false,
+            precision: self.precision,
+            details: (!details.is_empty()).then_some(details),
         }
     }
 
@@ -402,30 +887,87 @@ impl Default for Analytics {
             best_scores: HashMap::new(),
             achievements: Vec::new(),
             last_updated: SystemTime::now(),
+            daily_activity: BTreeMap::new(),
+            precision: PrecisionMap::default(),
+            seasons: BTreeMap::new(),
+            path: PathBuf::from(ANALYTICS_PATH),
         }
     }
 }
 
 impl Analytics {
     /// Load analytics from file or create default
-    pub fn load() -> Self {
-        let analytics_path = "analytics.json";
-        if Path::new(analytics_path).exists() {
-            match fs::read_to_string(analytics_path) {
-                Ok(contents) => match serde_json::from_str(&contents) {
-                    Ok(analytics) => analytics,
+    pub fn load(analytics_path: PathBuf) -> Self {
+        if analytics_path.exists() {
+            match fs::read_to_string(&analytics_path) {
+                Ok(contents) => match serde_json::from_str::<Analytics>(&contents) {
+                    Ok(mut analytics) => {
+                        analytics.path = analytics_path;
+                        // Older files have no day buckets; derive them from recent sessions
+                        if analytics.daily_activity.is_empty() {
+                            for session in &analytics.recent_sessions {
+                                analytics
+                                    .daily_activity
+                                    .entry(session.day())
+                                    .or_default()
+                                    .record(session);
+                            }
+                        }
+                        // Nor season totals; recent sessions are all they can come from
+                        if analytics.seasons.is_empty() {
+                            for session in &analytics.recent_sessions {
+                                analytics
+                                    .seasons
+                                    .entry(session.season_id())
+                                    .or_default()
+                                    .record(session);
+                            }
+                        }
+                        // Older sessions have no signature; practice ones know their speed
+                        for session in &mut analytics.recent_sessions {
+                            if session.signature == PlaySignature::default() {
+                                if let Some(speed) = session.playback_speed {
+                                    session.signature = PlaySignature::new(&[], speed);
+                                }
+                            }
+                        }
+                        // Nor per-song accuracy; seed it from the song's recent sessions
+                        for (song_name, stats) in analytics.song_stats.iter_mut() {
+                            if stats.accuracy_history.is_empty() {
+                                let accuracies: Vec<f32> = analytics
+                                    .recent_sessions
+                                    .iter()
+                                    .filter(|session| &session.song_name == song_name)
+                                    .map(|session| session.accuracy)
+                                    .collect();
+                                let start = accuracies.len().saturating_sub(SONG_ACCURACY_HISTORY);
+                                stats.accuracy_history = accuracies[start..].to_vec();
+                            }
+                        }
+                        analytics.refresh_completion();
+                        analytics
+                    }
                     Err(e) => {
-                        eprintln!("Failed to parse analytics: {}, using default", e);
-                        Self::default()
+                        warn!("Failed to parse analytics: {}, using default", e);
+                        Self {
+                            path: analytics_path,
+                            ..Self::default()
+                        }
                     }
                 },
                 Err(e) => {
-                    eprintln!("Failed to read analytics: {}, using default", e);
-                    Self::default()
+                    warn!("Failed to read analytics: {}, using default", e);
+                    Self {
+                        path: analytics_path,
+                        ..Self::default()
+                    }
                 }
             }
         } else {
-            let analytics = Self::default();
+            let analytics = Self {
+                path: analytics_path,
+                ..Self::default()
+            };
             analytics.save();
             analytics
         }
@@ -433,25 +975,61 @@ impl Analytics {
 
     /// Save analytics to file
     pub fn save(&self) {
-        let analytics_path = "analytics.json";
         match serde_json::to_string_pretty(self) {
             Ok(json) => {
-                if let Err(e) = fs::write(analytics_path, json) {
-                    eprintln!("Failed to save analytics: {}", e);
+                if let Err(e) = fs::write(&self.path, json) {
+                    error!("Failed to save analytics: {}", e);
                 }
             }
             Err(e) => {
-                eprintln!("Failed to serialize analytics: {}", e);
+                error!("Failed to serialize analytics: {}", e);
             }
         }
     }
 
+    /// Write the recent sessions to a CSV file, one row each, oldest first
+    pub fn export_csv(&self, path: &Path) -> Result<(), String> {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for session in &self.recent_sessions {
+            csv.push_str(&csv_row(session));
+            csv.push('\n');
+        }
+        fs::write(path, csv).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Write all analytics to a JSON file, in the same form as `analytics.json`
+    pub fn export_json(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize analytics: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Export to a CSV and a JSON file in `exports/`, named after the current time.
+    /// Returns the CSV path
+    pub fn export(&self) -> Result<PathBuf, String> {
+        fs::create_dir_all(EXPORTS_DIR)
+            .map_err(|e| format!("Failed to create {}: {}", EXPORTS_DIR, e))?;
+        let stamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let base = Path::new(EXPORTS_DIR).join(format!("analytics-{}", stamp));
+        let csv_path = base.with_extension("csv");
+        self.export_csv(&csv_path)?;
+        self.export_json(&base.with_extension("json"))?;
+        Ok(csv_path)
+    }
+
     /// Add a completed game session
     pub fn add_session(&mut self, session: GameSession) {
         self.total_games_played += 1;
         self.total_play_time_seconds += session.duration_seconds;
         self.total_hits.add_session(&session.hits);
         self.accuracy_history.push(session.accuracy);
+        if let Some(precision) = &session.precision {
+            self.precision.merge(precision);
+        }
 
         // Keep only last 100 accuracy values
         if self.accuracy_history.len() > 100 {
@@ -466,119 +1044,273 @@ impl Analytics {
         song_stats.update(&session);
 
         // Update best score
-        if session.score > *self.best_scores.get(&session.song_name).unwrap_or(&0) {
+        if session.counts_for_bests()
+            && session.score > *self.best_scores.get(&session.song_name).unwrap_or(&0)
+        {
             self.best_scores
                 .insert(session.song_name.clone(), session.score);
         }
 
+        // Update the day bucket
+        self.daily_activity
+            .entry(session.day())
+            .or_default()
+            .record(&session);
+        self.seasons
+            .entry(session.season_id())
+            .or_default()
+            .record(&session);
+
         // Add to recent sessions
         self.recent_sessions.push(session);
 
-        // Keep only last 50 sessions
+        // Keep only last 50 sessions, dropping the oldest unpinned one
         if self.recent_sessions.len() > 50 {
-            self.recent_sessions.remove(0);
+            let oldest = self
+                .recent_sessions
+                .iter()
+                .position(|session| !session.pinned)
+                .unwrap_or(0);
+            let dropped = self.recent_sessions.remove(oldest);
+            remove_details_file(&dropped);
+        }
+
+        self.last_updated = SystemTime::now();
+        self.save();
+    }
+
+    /// Raise each song's stored completion to what its recent sessions show, for files
+    /// written before completion was tracked
+    pub fn refresh_completion(&mut self) {
+        for (song_name, stats) in self.song_stats.iter_mut() {
+            let derived = CompletionStatus::derive(
+                self.recent_sessions
+                    .iter()
+                    .filter(|session| &session.song_name == song_name),
+            );
+            stats.completion = stats.completion.max(derived);
+        }
+    }
+
+    /// Completion status of a song
+    pub fn completion(&self, song_name: &str) -> CompletionStatus {
+        self.song_stats
+            .get(song_name)
+            .map(|stats| stats.completion)
+            .unwrap_or_default()
+    }
+
+    /// Remove everything recorded for one song: its stats, best score and recent sessions.
+    /// Totals are reduced by the song's own stats so every other song is untouched;
+    /// the accuracy history is rebuilt from the remaining sessions. Achievements and the
+    /// lifetime precision map stay.
+    pub fn reset_song(&mut self, song_name: &str) -> bool {
+        let stats = self.song_stats.remove(song_name);
+        let had_best = self.best_scores.remove(song_name).is_some();
+        let removed: Vec<GameSession> = self
+            .recent_sessions
+            .iter()
+            .filter(|session| session.song_name == song_name)
+            .cloned()
+            .collect();
+        if stats.is_none() && !had_best && removed.is_empty() {
+            return false;
+        }
+        self.recent_sessions.retain(|session| session.song_name != song_name);
+        for session in &removed {
+            remove_details_file(session);
         }
 
-        // Check for achievements
-        self.check_achievements();
+        if let Some(stats) = stats {
+            self.total_games_played = self.total_games_played.saturating_sub(stats.play_count);
+            self.total_play_time_seconds = self
+                .total_play_time_seconds
+                .saturating_sub(stats.total_play_time_seconds);
+            self.total_hits.remove_session(&stats.total_hits);
+        }
+
+        // Day buckets only know the sessions still in the recent list
+        for session in &removed {
+            if let Some(activity) = self.daily_activity.get_mut(&session.day()) {
+                activity.sessions = activity.sessions.saturating_sub(1);
+                activity.play_time_seconds = activity
+                    .play_time_seconds
+                    .saturating_sub(session.duration_seconds);
+            }
+        }
+        for activity in self.daily_activity.values_mut() {
+            activity.songs.retain(|song| song != song_name);
+        }
+        self.daily_activity
+            .retain(|_, activity| activity.sessions > 0 || !activity.songs.is_empty());
+        for season in self.seasons.values_mut() {
+            season.songs.remove(song_name);
+        }
+        self.seasons.retain(|_, season| !season.songs.is_empty());
+
+        self.accuracy_history = self
+            .recent_sessions
+            .iter()
+            .map(|session| session.accuracy)
+            .collect();
 
         self.last_updated = SystemTime::now();
         self.save();
+        true
     }
 
-    /// Check and unlock achievements
-    fn check_achievements(&mut self) {
-        let achievements_to_check = vec![
-            (
-                "first_game",
-                "First Steps",
-                "Play your first game",
-                AchievementCategory::Special,
-                1u32,
-            ),
-            (
-                "ten_games",
-                "Getting Started",
-                "Play 10 games",
-                AchievementCategory::Special,
-                10u32,
-            ),
-            (
-                "hundred_games",
-                "Rhythm Master",
-                "Play 100 games",
-                AchievementCategory::Special,
-                100u32,
-            ),
-            (
-                "perfect_accuracy",
-                "Perfect",
-                "Achieve 100% accuracy",
-                AchievementCategory::Accuracy,
-                0u32,
-            ),
-            (
-                "aaa_grade",
-                "AAA Rank",
-                "Get an AAA grade (perfect score, no misses)",
-                AchievementCategory::Score,
-                0u32,
-            ),
-            (
-                "ss_grade",
-                "SS Rank",
-                "Get an SS grade",
-                AchievementCategory::Score,
-                0u32,
-            ),
-            (
-                "full_combo",
-                "Full Combo",
-                "Complete a song without misses",
-                AchievementCategory::Streak,
-                0u32,
-            ),
-        ];
+    /// Back up the analytics file next to it, then wipe all analytics except the player id.
+    /// Returns the backup path, or None if there was no file to back up
+    pub fn wipe_all(&mut self) -> Result<Option<String>, String> {
+        let backup = if self.path.exists() {
+            let stamp = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let backup_path = self
+                .path
+                .with_file_name(format!("analytics.backup-{}.json", stamp));
+            fs::copy(&self.path, &backup_path)
+                .map_err(|e| format!("Failed to back up analytics: {}", e))?;
+            Some(backup_path.display().to_string())
+        } else {
+            None
+        };
+
+        // Session details aren't part of the backup; they go with the rest
+        let details_dir = self.details_dir();
+        if details_dir.exists() {
+            fs::remove_dir_all(&details_dir)
+                .map_err(|e| format!("Failed to delete session details: {}", e))?;
+        }
 
-        for (id, name, desc, category, threshold) in achievements_to_check {
-            if !self.has_achievement(id) {
-                let should_unlock = match id {
-                    "first_game" | "ten_games" | "hundred_games" => {
-                        self.total_games_played >= threshold
-                    }
-                    "perfect_accuracy" => self.accuracy_history.iter().any(|&a| a >= 100.0),
-                    "aaa_grade" => self.recent_sessions.iter().any(|s| s.grade == Grade::AAA),
-                    "ss_grade" => self.recent_sessions.iter().any(|s| s.grade == Grade::SS),
-                    "full_combo" => self.recent_sessions.iter().any(|s| s.full_combo),
-                    _ => false,
-                };
-
-                if should_unlock {
-                    self.unlock_achievement(id, name, desc, category);
-                }
+        *self = Self {
+            player_id: self.player_id.clone(),
+            path: self.path.clone(),
+            ..Self::default()
+        };
+        self.save();
+        Ok(backup)
+    }
+
+    /// Folder the session details files are kept in, next to the analytics file
+    pub fn details_dir(&self) -> PathBuf {
+        self.path.with_file_name(SESSION_DETAILS_DIR)
+    }
+
+    /// Write a session's details to its own file before it is added, keeping only the
+    /// path on the session
+    pub fn write_details(&self, session: &mut GameSession) {
+        let Some(details) = session.details.take() else {
+            return;
+        };
+        match details.save(&self.details_dir(), session) {
+            Ok(path) => session.details_file = Some(path),
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    /// Delete the oldest unpinned details files until the folder fits in `cap_mb`,
+    /// saving if any session lost its file. Returns how many files were deleted
+    pub fn evict_details(&mut self, cap_mb: u32) -> usize {
+        let cap_bytes = cap_mb as u64 * 1024 * 1024;
+        let evicted = evict_details(&self.details_dir(), cap_bytes, &self.recent_sessions);
+        let mut cleared = false;
+        for session in &mut self.recent_sessions {
+            if session
+                .details_file
+                .as_ref()
+                .is_some_and(|file| evicted.contains(file))
+            {
+                session.details_file = None;
+                cleared = true;
             }
         }
+        if cleared {
+            self.save();
+        }
+        evicted.len()
+    }
+
+    /// Pin or unpin a session. Returns whether it is pinned now, None if not found
+    pub fn toggle_pin(&mut self, session_id: u64, local_versus: Option<u8>) -> Option<bool> {
+        let session = self.recent_sessions.iter_mut().find(|session| {
+            session.session_id == session_id && session.local_versus == local_versus
+        })?;
+        session.pinned = !session.pinned;
+        let pinned = session.pinned;
+        self.save();
+        Some(pinned)
+    }
+
+    /// Statistics achievement conditions are checked against
+    pub fn achievement_stats(&self) -> AchievementStats {
+        let mut grades: Vec<Grade> = Vec::new();
+        for session in &self.recent_sessions {
+            if !grades.contains(&session.grade) {
+                grades.push(session.grade);
+            }
+        }
+        AchievementStats {
+            total_games: self.total_games_played,
+            total_score: self
+                .recent_sessions
+                .iter()
+                .map(|session| session.score.max(0) as u64)
+                .sum(),
+            // Sessions from before combo was stored count a full combo as one streak
+            highest_combo: self
+                .recent_sessions
+                .iter()
+                .map(|session| match session.full_combo {
+                    true => session.max_combo.max(session.hits.total()),
+                    false => session.max_combo,
+                })
+                .max()
+                .unwrap_or(0),
+            best_accuracy: self.accuracy_history.iter().copied().fold(0.0f32, f32::max) as f64,
+            play_time_seconds: self.total_play_time_seconds,
+            perfect_games: self
+                .recent_sessions
+                .iter()
+                .filter(|session| session.full_combo && session.accuracy >= 100.0)
+                .count() as u32,
+            songs_full_combo: self
+                .song_stats
+                .values()
+                .filter(|stats| stats.completion >= CompletionStatus::FullCombo)
+                .count() as u32,
+            songs_played: self.song_stats.len() as u32,
+            session_accuracies: self
+                .recent_sessions
+                .iter()
+                .filter(|session| session.counts_for_streaks())
+                .map(|session| session.accuracy as f64)
+                .collect(),
+            fastest_full_combo: self
+                .recent_sessions
+                .iter()
+                .filter(|session| session.full_combo && session.counts_for_streaks())
+                .map(|session| session.signature.effective_speed())
+                .fold(0.0, f32::max),
+            grades,
+            multiplayer_wins: 0,
+        }
     }
 
     /// Check if player has an achievement
-    fn has_achievement(&self, id: &str) -> bool {
+    pub fn has_achievement(&self, id: &str) -> bool {
         self.achievements.iter().any(|a| a.id == id)
     }
 
     /// Unlock an achievement
-    fn unlock_achievement(
-        &mut self,
-        id: &str,
-        name: &str,
-        description: &str,
-        category: AchievementCategory,
-    ) {
+    pub fn unlock_achievement(&mut self, definition: &AchievementDefinition) {
         self.achievements.push(Achievement {
-            id: id.to_string(),
-            name: name.to_string(),
-            description: description.to_string(),
+            id: definition.id.clone(),
+            name: definition.name.clone(),
+            description: definition.description.clone(),
             unlocked_at: SystemTime::now(),
-            category,
+            category: definition.category,
         });
     }
 
@@ -602,33 +1334,65 @@ impl Analytics {
     fn get_best_grade(&self) -> Option<Grade> {
         self.recent_sessions
             .iter()
-            .map(|s| s.grade.clone())
-            .min_by_key(|g| match g {
-                Grade::AAA => 0,
-                Grade::SS => 1,
-                Grade::S => 2,
-                Grade::A => 3,
-                Grade::B => 4,
-                Grade::C => 5,
-                Grade::D => 6,
-                Grade::F => 7,
-            })
+            .map(|s| s.grade)
+            .min_by_key(Grade::rank)
     }
 
-    /// Get accuracy trend (last 10 sessions)
-    pub fn get_accuracy_trend(&self) -> Vec<f32> {
-        self.accuracy_history
+    /// Accuracy of the last `count` sessions, oldest first
+    pub fn get_accuracy_trend(&self, count: usize) -> Vec<f32> {
+        let start = self.accuracy_history.len().saturating_sub(count);
+        self.accuracy_history[start..].to_vec()
+    }
+
+    /// Recent sessions played on a given day
+    pub fn sessions_on_day(&self, day: u64) -> Vec<&GameSession> {
+        self.recent_sessions
             .iter()
-            .rev()
-            .take(10)
-            .cloned()
+            .filter(|session| session.day() == day)
             .collect()
     }
 
+    /// Sessions listed in the Sessions view, newest first: those of a day, or the
+    /// recent ones, within a season if one is picked
+    pub fn listed_sessions(&self, day: Option<u64>, season: Option<&Season>) -> Vec<&GameSession> {
+        let mut sessions = match day {
+            Some(day) => self.sessions_on_day(day),
+            None => self.recent_sessions.iter().rev().collect(),
+        };
+        if let Some(season) = season {
+            sessions.retain(|session| session.season_id() == season.id);
+        }
+        sessions
+    }
+
+    /// Songs with recorded stats, sorted by name
+    pub fn song_names(&self) -> Vec<&String> {
+        let mut names: Vec<_> = self.song_stats.keys().collect();
+        names.sort();
+        names
+    }
+
+    /// Songs listed in the Songs view: all of them, or those played in a season
+    pub fn listed_songs(&self, season: Option<&Season>) -> Vec<&String> {
+        match season {
+            Some(season) => self
+                .seasons
+                .get(&season.id)
+                .map(|stats| stats.songs.keys().collect())
+                .unwrap_or_default(),
+            None => self.song_names(),
+        }
+    }
+
+    /// Stats of a season, if anything was played in it
+    pub fn season_stats(&self, season: &Season) -> Option<&SeasonStats> {
+        self.seasons.get(&season.id)
+    }
+
     /// Get most played songs
     pub fn get_most_played_songs(&self, limit: usize) -> Vec<(&String, &SongStats)> {
         let mut songs: Vec<_> = self.song_stats.iter().collect();
-        songs.sort_by(|a, b| b.1.play_count.cmp(&a.1.play_count));
+        songs.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.play_count));
         songs.into_iter().take(limit).collect()
     }
 }
@@ -680,6 +1444,31 @@ pub struct AnalyticsState {
     pub scroll_y: f32,
     /// Selected session index
     pub selected_session: Option<usize>,
+    /// Day highlighted in the history heatmap (days since the Unix epoch)
+    pub history_cursor: u64,
+    /// Metric used to color the history heatmap
+    pub history_metric: HistoryMetric,
+    /// Day the session list is filtered to, if opened from the heatmap
+    pub selected_day: Option<u64>,
+    /// Highlighted row in the Songs view
+    pub song_cursor: usize,
+    /// Song whose data will be reset once the player confirms
+    pub pending_song_reset: Option<String>,
+    /// Result of the last action, such as a replay export
+    pub notice: Option<String>,
+    /// Season every view is narrowed to; None for lifetime
+    pub season: Option<Season>,
+    /// Highlighted row in the Past Seasons view
+    pub season_cursor: usize,
+    /// Details file of the selected session and what was read from it; loaded when
+    /// the session is selected, None inside if it couldn't be read
+    pub session_details: Option<(String, Option<SessionDetails>)>,
+}
+
+impl Default for AnalyticsState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AnalyticsState {
@@ -690,10 +1479,120 @@ impl AnalyticsState {
             selected_song: None,
             scroll_y: 0.0,
             selected_session: None,
+            history_cursor: today(),
+            history_metric: HistoryMetric::PlayTime,
+            selected_day: None,
+            song_cursor: 0,
+            pending_song_reset: None,
+            notice: None,
+            season: None,
+            season_cursor: 0,
+            session_details: None,
+        }
+    }
+
+    /// Follow the selected session's details file: read it when a session with one is
+    /// selected, and drop what was read once none is
+    pub fn follow_session_details(&mut self, details_file: Option<&str>) {
+        let cached = self.session_details.as_ref().map(|(path, _)| path.as_str());
+        match details_file {
+            Some(file) if cached != Some(file) => {
+                let details = SessionDetails::load(Path::new(file))
+                    .map_err(|e| warn!("{}", e))
+                    .ok();
+                self.session_details = Some((file.to_string(), details));
+            }
+            None if cached.is_some() => self.session_details = None,
+            _ => {}
+        }
+    }
+}
+
+/// Metric shown by the history heatmap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryMetric {
+    PlayTime,
+    Sessions,
+}
+
+impl HistoryMetric {
+    /// Value of this metric for a day
+    pub fn value(&self, activity: &DailyActivity) -> f32 {
+        match self {
+            HistoryMetric::PlayTime => activity.play_time_seconds as f32,
+            HistoryMetric::Sessions => activity.sessions as f32,
+        }
+    }
+
+    /// Toggle between metrics
+    pub fn toggle(&self) -> HistoryMetric {
+        match self {
+            HistoryMetric::PlayTime => HistoryMetric::Sessions,
+            HistoryMetric::Sessions => HistoryMetric::PlayTime,
+        }
+    }
+
+    /// Display name
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoryMetric::PlayTime => "Play Time",
+            HistoryMetric::Sessions => "Sessions",
         }
     }
 }
 
+/// Folder analytics exports are written to
+pub const EXPORTS_DIR: &str = "exports";
+
+/// Columns of the sessions CSV export
+pub const CSV_HEADER: &str =
+    "session_id,song,score,accuracy,grade,full_combo,max_combo,practice_mode,playback_speed,mods";
+
+/// A field quoted if it holds a comma, quote or line break, quotes doubled
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One session as a CSV row, in `CSV_HEADER` order. The speed includes Double Time
+/// and Half Time
+fn csv_row(session: &GameSession) -> String {
+    [
+        session.session_id.to_string(),
+        csv_field(&session.song_name),
+        session.score.to_string(),
+        format!("{:.2}", session.accuracy),
+        session.grade.as_str().to_string(),
+        session.full_combo.to_string(),
+        session.max_combo.to_string(),
+        session.practice_mode.to_string(),
+        format!("{:.2}", session.signature.effective_speed()),
+        csv_field(&mods_label(&session.signature.modifiers)),
+    ]
+    .join(",")
+}
+
+/// Delete the details file of a session leaving the analytics
+fn remove_details_file(session: &GameSession) {
+    if let Some(file) = &session.details_file {
+        if let Err(e) = fs::remove_file(file) {
+            warn!("Failed to delete session details {}: {}", file, e);
+        }
+    }
+}
+
+/// Current day (days since the Unix epoch)
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / SECONDS_PER_DAY
+}
+
 /// Analytics view tabs
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AnalyticsView {
@@ -702,6 +1601,10 @@ pub enum AnalyticsView {
     Sessions,
     Achievements,
     Trends,
+    History,
+    Replays,
+    Precision,
+    Seasons,
 }
 
 impl AnalyticsView {
@@ -713,6 +1616,10 @@ impl AnalyticsView {
             (AnalyticsView::Sessions, "Sessions"),
             (AnalyticsView::Achievements, "Achievements"),
             (AnalyticsView::Trends, "Trends"),
+            (AnalyticsView::History, "History"),
+            (AnalyticsView::Replays, "Replays"),
+            (AnalyticsView::Precision, "Precision"),
+            (AnalyticsView::Seasons, "Past Seasons"),
         ]
     }
 
@@ -723,18 +1630,565 @@ impl AnalyticsView {
             AnalyticsView::Songs => AnalyticsView::Sessions,
             AnalyticsView::Sessions => AnalyticsView::Achievements,
             AnalyticsView::Achievements => AnalyticsView::Trends,
-            AnalyticsView::Trends => AnalyticsView::Overview,
+            AnalyticsView::Trends => AnalyticsView::History,
+            AnalyticsView::History => AnalyticsView::Replays,
+            AnalyticsView::Replays => AnalyticsView::Precision,
+            AnalyticsView::Precision => AnalyticsView::Seasons,
+            AnalyticsView::Seasons => AnalyticsView::Overview,
         }
     }
 
     /// Get previous view
     pub fn previous(&self) -> AnalyticsView {
         match self {
-            AnalyticsView::Overview => AnalyticsView::Trends,
+            AnalyticsView::Overview => AnalyticsView::Seasons,
             AnalyticsView::Songs => AnalyticsView::Overview,
             AnalyticsView::Sessions => AnalyticsView::Songs,
             AnalyticsView::Achievements => AnalyticsView::Sessions,
             AnalyticsView::Trends => AnalyticsView::Achievements,
+            AnalyticsView::History => AnalyticsView::Trends,
+            AnalyticsView::Replays => AnalyticsView::History,
+            AnalyticsView::Precision => AnalyticsView::Replays,
+            AnalyticsView::Seasons => AnalyticsView::Precision,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn hits(perfect: u32, good: u32, okay: u32, misses: u32) -> HitStats {
+        HitStats {
+            perfect,
+            good,
+            okay,
+            misses,
+        }
+    }
+
+    #[test]
+    fn rank_orders_every_grade_best_first() {
+        let grades = [
+            Grade::SSPlus,
+            Grade::SS,
+            Grade::S,
+            Grade::A,
+            Grade::B,
+            Grade::C,
+            Grade::D,
+            Grade::F,
+        ];
+        for (expected, grade) in grades.iter().enumerate() {
+            assert_eq!(grade.rank() as usize, expected, "{:?}", grade);
+        }
+    }
+
+    #[test]
+    fn score_v1_accuracy_boundaries() {
+        let rules = GradeRules::SCORE_V1;
+        let cases = [
+            (100.0, Grade::SSPlus),
+            (99.99, Grade::SS),
+            (95.0, Grade::SS),
+            (94.99, Grade::S),
+            (90.0, Grade::S),
+            (89.99, Grade::A),
+            (80.0, Grade::A),
+            (79.99, Grade::B),
+            (70.0, Grade::B),
+            (69.99, Grade::C),
+            (60.0, Grade::C),
+            (59.99, Grade::D),
+            (0.0, Grade::D),
+        ];
+        for (accuracy, grade) in cases {
+            assert_eq!(rules.accuracy_grade(accuracy), grade, "{}%", accuracy);
+        }
+    }
+
+    #[test]
+    fn score_v2_accuracy_boundaries() {
+        let rules = GradeRules::SCORE_V2;
+        let cases = [
+            (100.0, Grade::SSPlus),
+            (99.99, Grade::S),
+            (99.5, Grade::S),
+            (95.0, Grade::S),
+            (94.99, Grade::A),
+            (90.0, Grade::A),
+            (89.99, Grade::B),
+            (80.0, Grade::B),
+            (79.99, Grade::C),
+            (70.0, Grade::C),
+            (69.99, Grade::D),
+        ];
+        for (accuracy, grade) in cases {
+            assert_eq!(rules.accuracy_grade(accuracy), grade, "{}%", accuracy);
+        }
+    }
+
+    #[test]
+    fn ss_plus_needs_every_judgment_perfect() {
+        for rules in [GradeRules::SCORE_V1, GradeRules::SCORE_V2] {
+            assert_eq!(hits(500, 0, 0, 0).grade(&rules), Grade::SSPlus);
+        }
+        assert_eq!(hits(499, 1, 0, 0).grade(&GradeRules::SCORE_V1), Grade::SS);
+        assert_eq!(hits(0, 0, 0, 0).grade(&GradeRules::SCORE_V1), Grade::D);
+    }
+
+    #[test]
+    fn accuracy_is_exact_on_long_maps() {
+        // One good in fifty million would round to 100% in f32
+        let long = hits(50_000_000, 1, 0, 0);
+        assert!(long.exact_accuracy() < 100.0);
+        assert_eq!(long.grade(&GradeRules::SCORE_V1), Grade::SS);
+        assert_eq!(long.grade(&GradeRules::SCORE_V2), Grade::S);
+        assert_eq!(hits(500, 0, 0, 0).exact_accuracy(), 100.0);
+    }
+
+    #[test]
+    fn misses_cap_the_grade() {
+        // 99.8% accuracy with one miss
+        let one_miss = hits(499, 0, 0, 1);
+        assert_eq!(one_miss.grade(&GradeRules::SCORE_V1), Grade::S);
+        assert_eq!(one_miss.grade(&GradeRules::SCORE_V2), Grade::A);
+        // Already below the cap, so the miss changes nothing: 70% is V1's B cutoff
+        // and V2's C cutoff
+        let low = hits(70, 0, 0, 30);
+        for rules in [GradeRules::SCORE_V1, GradeRules::SCORE_V2] {
+            assert_eq!(low.grade(&rules), rules.accuracy_grade(70.0));
+        }
+        assert_eq!(low.grade(&GradeRules::SCORE_V1), Grade::B);
+        assert_eq!(low.grade(&GradeRules::SCORE_V2), Grade::C);
+    }
+
+    #[test]
+    fn score_v2_keeps_ss_for_full_accuracy() {
+        // 99.5% accuracy without misses: SS under V1, S under V2
+        let near = hits(397, 3, 0, 0);
+        assert!((near.accuracy() - 99.5).abs() < 0.001);
+        assert_eq!(near.grade(&GradeRules::SCORE_V1), Grade::SS);
+        assert_eq!(near.grade(&GradeRules::SCORE_V2), Grade::S);
+        // One good in 500 is still 99.87%, short of V2's SS
+        assert_eq!(hits(499, 1, 0, 0).grade(&GradeRules::SCORE_V2), Grade::S);
+        // SS takes exactly 100%, which only all-perfect hits reach
+        assert_eq!(GradeRules::SCORE_V2.accuracy_grade(99.999_999), Grade::S);
+        assert_eq!(hits(1_000, 0, 0, 0).grade(&GradeRules::SCORE_V2), Grade::SSPlus);
+    }
+
+    #[test]
+    fn grade_reads_old_and_new_names() {
+        let old: Grade = serde_json::from_str("\"AAA\"").unwrap();
+        assert_eq!(old, Grade::SSPlus);
+        assert_eq!(serde_json::to_string(&Grade::SSPlus).unwrap(), "\"SS+\"");
+        let saved: Grade = serde_json::from_str("\"SS+\"").unwrap();
+        assert_eq!(saved, Grade::SSPlus);
+        assert_eq!(serde_json::from_str::<Grade>("\"SS\"").unwrap(), Grade::SS);
+        assert_eq!(Grade::from_name("AAA"), Some(Grade::SSPlus));
+        let name = Grade::SSPlus.as_str();
+        assert_eq!(Grade::from_name(name), Some(Grade::SSPlus));
+        assert_eq!(Grade::from_name("Z"), None);
+    }
+
+    fn session(song: &str, score: i64, duration_seconds: u64, hits: HitStats) -> GameSession {
+        GameSession {
+            score,
+            duration_seconds,
+            accuracy: hits.accuracy(),
+            completed: true,
+            hits,
+            ..GameSession::new(song.to_string())
+        }
+    }
+
+    /// Analytics saving into an empty folder of its own
+    fn analytics(test: &str) -> Analytics {
+        Analytics::load(scratch_dir(&format!("analytics-{}", test)).join("analytics.json"))
+    }
+
+    #[test]
+    fn resetting_one_song_leaves_every_other_song_untouched() {
+        let mut analytics = analytics("reset-song");
+        analytics.add_session(session("a", 500, 60, hits(10, 2, 1, 1)));
+        analytics.add_session(session("b", 900, 90, hits(20, 0, 0, 0)));
+        analytics.add_session(session("a", 700, 60, hits(12, 1, 0, 0)));
+        analytics.add_session(session("c", 300, 30, hits(5, 5, 5, 5)));
+        let stats_of = |analytics: &Analytics, song: &str| {
+            serde_json::to_value(&analytics.song_stats[song]).unwrap()
+        };
+        let (b, c) = (stats_of(&analytics, "b"), stats_of(&analytics, "c"));
+
+        assert!(analytics.reset_song("a"));
+        assert!(!analytics.reset_song("a"));
+
+        assert!(!analytics.song_stats.contains_key("a"));
+        assert_eq!(stats_of(&analytics, "b"), b);
+        assert_eq!(stats_of(&analytics, "c"), c);
+        assert_eq!(analytics.best_scores.get("b"), Some(&900));
+        assert_eq!(analytics.best_scores.get("c"), Some(&300));
+        assert!(!analytics.best_scores.contains_key("a"));
+
+        // Aggregates are what b and c alone would give
+        assert_eq!(analytics.total_games_played, 2);
+        assert_eq!(analytics.total_play_time_seconds, 120);
+        assert_eq!(
+            analytics.total_hits.total(),
+            hits(20, 0, 0, 0).total() + hits(5, 5, 5, 5).total()
+        );
+        assert_eq!(analytics.recent_sessions.len(), 2);
+        assert_eq!(analytics.accuracy_history.len(), 2);
+        let today = analytics.recent_sessions[0].day();
+        assert_eq!(analytics.daily_activity[&today].sessions, 2);
+        assert!(!analytics.daily_activity[&today]
+            .songs
+            .contains(&"a".to_string()));
+    }
+
+    #[test]
+    fn wiping_backs_up_the_file_and_keeps_the_player_id() {
+        let mut analytics = analytics("wipe");
+        analytics.add_session(session("a", 500, 60, hits(10, 0, 0, 0)));
+        let player_id = analytics.player_id.clone();
+
+        let backup = analytics.wipe_all().unwrap().unwrap();
+        let backed_up: Analytics =
+            serde_json::from_str(&fs::read_to_string(&backup).unwrap()).unwrap();
+        assert_eq!(backed_up.total_games_played, 1);
+        assert_eq!(analytics.player_id, player_id);
+        assert_eq!(analytics.total_games_played, 0);
+        assert!(analytics.song_stats.is_empty());
+    }
+
+    /// Details of a play with a judgment per second, `misses` of them misses
+    fn details(judgments: usize, misses: usize) -> SessionDetails {
+        SessionDetails {
+            judgments: JudgmentLog {
+                entries: (0..judgments)
+                    .map(|i| JudgmentEntry {
+                        time: i as f64,
+                        position: Vec2::ZERO,
+                        kind: if i < misses {
+                            JudgmentKind::Miss
+                        } else {
+                            JudgmentKind::Perfect
+                        },
+                    })
+                    .collect(),
+                truncated: false,
+            },
+            hit_timings: vec![4.0; judgments - misses],
+        }
+    }
+
+    /// Record a session, writing its details first like a finished play
+    fn record(analytics: &mut Analytics, id: u64, details: Option<SessionDetails>) {
+        let mut session = GameSession {
+            session_id: id,
+            details,
+            ..session("song", 100, 60, hits(10, 0, 0, 0))
+        };
+        analytics.write_details(&mut session);
+        analytics.add_session(session);
+    }
+
+    #[test]
+    fn session_details_stay_on_disk_until_the_session_is_opened() {
+        let mut analytics = analytics("details");
+        record(&mut analytics, 1000, Some(details(30, 2)));
+        let stored = fs::read_to_string(&analytics.path).unwrap();
+        assert!(!stored.contains("hit_timings"));
+
+        let reloaded = Analytics::load(analytics.path.clone());
+        let file = reloaded.recent_sessions[0].details_file.clone().unwrap();
+        assert!(file.ends_with("1000.json"));
+        let mut state = AnalyticsState::new();
+        state.follow_session_details(Some(&file));
+        let (path, loaded) = state.session_details.clone().unwrap();
+        assert_eq!(path, file);
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.judgments.entries.len(), 30);
+        assert_eq!(loaded.hit_timings.len(), 28);
+
+        // What was read is kept while the session stays selected
+        fs::remove_file(&file).unwrap();
+        state.follow_session_details(Some(&file));
+        assert!(state.session_details.as_ref().unwrap().1.is_some());
+        state.follow_session_details(None);
+        assert!(state.session_details.is_none());
+        // A file gone missing opens as no details rather than an error
+        state.follow_session_details(Some(&file));
+        assert!(state.session_details.as_ref().unwrap().1.is_none());
+    }
+
+    #[test]
+    fn pinned_sessions_survive_the_recent_trim_and_eviction() {
+        let mut analytics = analytics("pins");
+        for id in 0..3 {
+            record(&mut analytics, 1000 + id, Some(details(200, 0)));
+        }
+        assert_eq!(analytics.toggle_pin(1000, None), Some(true));
+        let files: Vec<String> = analytics
+            .recent_sessions
+            .iter()
+            .map(|session| session.details_file.clone().unwrap())
+            .collect();
+        for id in 0..50 {
+            record(&mut analytics, 2000 + id, None);
+        }
+
+        assert_eq!(analytics.recent_sessions.len(), 50);
+        assert_eq!(analytics.recent_sessions[0].session_id, 1000);
+        assert!(Path::new(&files[0]).exists());
+        assert!(!Path::new(&files[1]).exists());
+        assert!(!Path::new(&files[2]).exists());
+
+        record(&mut analytics, 3000, Some(details(200, 0)));
+        assert_eq!(analytics.evict_details(0), 1);
+        assert_eq!(
+            analytics.recent_sessions[0].details_file.as_ref(),
+            Some(&files[0])
+        );
+        assert!(analytics
+            .recent_sessions
+            .last()
+            .unwrap()
+            .details_file
+            .is_none());
+        assert!(Path::new(&files[0]).exists());
+    }
+
+    /// Ids of the bundled achievements the analytics now meet but hasn't unlocked
+    fn newly_met(analytics: &Analytics) -> Vec<String> {
+        crate::achievements::AchievementDefinitions::load()
+            .newly_met(&analytics.achievement_stats(), |id| {
+                analytics.has_achievement(id)
+            })
+            .into_iter()
+            .map(|definition| definition.id.clone())
+            .collect()
+    }
+
+    fn at_accuracy(song: &str, accuracy: f32) -> GameSession {
+        GameSession {
+            accuracy,
+            ..session(song, 500, 60, hits(10, 0, 0, 0))
+        }
+    }
+
+    #[test]
+    fn a_300_combo_fires_without_a_full_combo() {
+        let mut analytics = analytics("combo-300");
+        analytics.add_session(GameSession {
+            max_combo: 299,
+            ..session("a", 500, 60, hits(299, 0, 0, 1))
+        });
+        assert!(!newly_met(&analytics).contains(&"combo_300".to_string()));
+
+        analytics.add_session(GameSession {
+            max_combo: 300,
+            ..session("a", 500, 60, hits(300, 0, 0, 4))
+        });
+        assert!(newly_met(&analytics).contains(&"combo_300".to_string()));
+    }
+
+    #[test]
+    fn the_accuracy_streak_needs_five_finished_sessions_in_a_row() {
+        let mut analytics = analytics("accuracy-streak");
+        for accuracy in [96.0, 97.0, 99.0, 95.0, 90.0, 98.0, 96.0, 97.0, 99.5] {
+            analytics.add_session(at_accuracy("a", accuracy));
+        }
+        // Practice, unranked and unfinished plays neither count nor break the streak
+        analytics.add_session(GameSession {
+            practice_mode: true,
+            ..at_accuracy("a", 99.0)
+        });
+        analytics.add_session(GameSession {
+            unranked: true,
+            ..at_accuracy("a", 40.0)
+        });
+        analytics.add_session(GameSession {
+            completed: false,
+            ..at_accuracy("a", 20.0)
+        });
+        assert_eq!(analytics.achievement_stats().accuracy_streak(95.0), 4);
+        assert!(!newly_met(&analytics).contains(&"accuracy_streak_5".to_string()));
+
+        analytics.add_session(at_accuracy("a", 95.5));
+        assert!(newly_met(&analytics).contains(&"accuracy_streak_5".to_string()));
+    }
+
+    #[test]
+    fn ten_distinct_songs_fire_however_often_each_was_played() {
+        let mut analytics = analytics("distinct-songs");
+        for round in 0..3 {
+            for song in 0..9 {
+                analytics.add_session(session(
+                    &format!("song {}", song),
+                    round,
+                    60,
+                    hits(5, 0, 0, 0),
+                ));
+            }
+        }
+        assert!(!newly_met(&analytics).contains(&"ten_songs".to_string()));
+
+        analytics.add_session(session("song 9", 100, 60, hits(5, 0, 0, 0)));
+        assert!(newly_met(&analytics).contains(&"ten_songs".to_string()));
+    }
+
+    #[test]
+    fn a_speed_full_combo_counts_double_time_but_not_practice() {
+        let full_combo = |modifiers: &[crate::gamemode::Modifier], speed: f32| GameSession {
+            full_combo: true,
+            signature: PlaySignature::new(modifiers, speed),
+            ..session("a", 500, 60, hits(50, 0, 0, 0))
+        };
+        let mut analytics = analytics("speed-full-combo");
+        analytics.add_session(full_combo(&[crate::gamemode::Modifier::HalfTime], 1.75));
+        analytics.add_session(GameSession {
+            practice_mode: true,
+            ..full_combo(&[], 2.0)
+        });
+        analytics.add_session(GameSession {
+            full_combo: false,
+            ..full_combo(&[], 1.5)
+        });
+        assert!(!newly_met(&analytics).contains(&"speed_full_combo".to_string()));
+
+        analytics.add_session(full_combo(&[crate::gamemode::Modifier::DoubleTime], 1.0));
+        assert_eq!(analytics.achievement_stats().fastest_full_combo, 1.5);
+        assert!(newly_met(&analytics).contains(&"speed_full_combo".to_string()));
+    }
+
+    #[test]
+    fn the_csv_export_quotes_song_names_and_counts_speed_mods() {
+        let mut analytics = analytics("export");
+        analytics.add_session(GameSession {
+            session_id: 42,
+            accuracy: 97.5,
+            grade: Grade::S,
+            full_combo: true,
+            max_combo: 120,
+            signature: PlaySignature::new(
+                &[
+                    crate::gamemode::Modifier::HardRock,
+                    crate::gamemode::Modifier::DoubleTime,
+                ],
+                1.0,
+            ),
+            ..session("Say \"Hi\", World", 12345, 90, hits(120, 0, 0, 0))
+        });
+        analytics.add_session(GameSession {
+            session_id: 43,
+            practice_mode: true,
+            playback_speed: Some(0.75),
+            signature: PlaySignature::new(&[], 0.75),
+            ..session("plain", 10, 30, hits(1, 0, 0, 0))
+        });
+
+        let dir = analytics.path.parent().unwrap().to_path_buf();
+        let csv_path = dir.join("export.csv");
+        analytics.export_csv(&csv_path).unwrap();
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            r#"42,"Say ""Hi"", World",12345,97.50,S,true,120,false,1.50,DT+HR"#
+        );
+        assert!(lines[2].starts_with("43,plain,10,"), "{}", lines[2]);
+        assert!(lines[2].ends_with(",0,true,0.75,No mod"), "{}", lines[2]);
+        assert_eq!(lines.len(), 3);
+
+        let json_path = dir.join("export.json");
+        analytics.export_json(&json_path).unwrap();
+        let exported: Analytics =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(exported.total_games_played, 2);
+        assert_eq!(exported.recent_sessions[0].song_name, "Say \"Hi\", World");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// A run of one song with the given outcome
+    fn run(completed: bool, full_combo: bool, grade: Grade) -> GameSession {
+        GameSession {
+            completed,
+            full_combo,
+            grade,
+            ..GameSession::new("song".to_string())
+        }
+    }
+
+    #[test]
+    fn each_run_earns_the_status_its_outcome_reaches() {
+        let cases = [
+            (run(false, false, Grade::F), CompletionStatus::Played),
+            // A failed run stays Played however well it was going
+            (run(false, true, Grade::SS), CompletionStatus::Played),
+            (run(true, false, Grade::A), CompletionStatus::Cleared),
+            (run(true, true, Grade::S), CompletionStatus::FullCombo),
+            (run(true, true, Grade::SS), CompletionStatus::SS),
+            (run(true, true, Grade::SSPlus), CompletionStatus::SS),
+        ];
+        for (session, status) in cases {
+            assert_eq!(
+                CompletionStatus::from_session(&session),
+                status,
+                "{:?}",
+                session.grade
+            );
+        }
+    }
+
+    #[test]
+    fn practice_unranked_and_versus_runs_earn_nothing() {
+        let practice = GameSession {
+            practice_mode: true,
+            ..run(true, true, Grade::SS)
+        };
+        let unranked = GameSession {
+            unranked: true,
+            ..run(true, true, Grade::SS)
+        };
+        let versus = GameSession {
+            local_versus: Some(1),
+            ..run(true, true, Grade::SS)
+        };
+        for session in [&practice, &unranked, &versus] {
+            assert_eq!(
+                CompletionStatus::from_session(session),
+                CompletionStatus::Unplayed
+            );
+        }
+        assert_eq!(
+            CompletionStatus::derive([&run(false, false, Grade::F), &practice, &versus]),
+            CompletionStatus::Played
+        );
+    }
+
+    #[test]
+    fn a_song_keeps_the_best_status_of_its_runs() {
+        assert_eq!(CompletionStatus::derive(&[]), CompletionStatus::Unplayed);
+
+        let failed = run(false, false, Grade::F);
+        let cleared = run(true, false, Grade::B);
+        let full_combo = run(true, true, Grade::S);
+        assert_eq!(
+            CompletionStatus::derive([&cleared, &failed]),
+            CompletionStatus::Cleared
+        );
+        assert_eq!(
+            CompletionStatus::derive([&failed, &full_combo, &cleared]),
+            CompletionStatus::FullCombo
+        );
+
+        // The cached status moves up with each run and never down
+        let mut stats = SongStats::new("song".to_string());
+        for session in [&full_combo, &failed, &cleared] {
+            stats.update(session);
         }
+        assert_eq!(stats.completion, CompletionStatus::FullCombo);
     }
 }
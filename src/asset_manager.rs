@@ -0,0 +1,400 @@
+// src/asset_manager.rs
+
+use crate::structs::GameAssets;
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+/// Font every screen is drawn with once loaded
+pub const UI_FONT_PATH: &str = "fonts/teknaf.otf";
+
+/// Per-song images (covers, backgrounds) kept loaded; the least recently used go first
+pub const SONG_IMAGE_CAPACITY: usize = 12;
+
+/// Side of the placeholder checkerboard, in squares
+const CHECKER_SQUARES: u32 = 8;
+
+/// Where an asset is in loading
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AssetStatus {
+    #[default]
+    NotLoaded,
+    Loading,
+    Ready,
+    Failed(String),
+}
+
+impl AssetStatus {
+    fn from_load_state(state: Option<LoadState>) -> Self {
+        match state {
+            None | Some(LoadState::NotLoaded) => AssetStatus::NotLoaded,
+            Some(LoadState::Loading) => AssetStatus::Loading,
+            Some(LoadState::Loaded) => AssetStatus::Ready,
+            Some(LoadState::Failed(error)) => AssetStatus::Failed(error.to_string()),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        *self == AssetStatus::Ready
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self, AssetStatus::Failed(_))
+    }
+}
+
+/// An asset loading in the background and its last known status
+pub struct TrackedAsset<A: Asset> {
+    pub path: String,
+    handle: Handle<A>,
+    pub status: AssetStatus,
+}
+
+impl<A: Asset> TrackedAsset<A> {
+    /// Start loading; the asset server does the work off the main thread
+    fn load(path: &str, asset_server: &AssetServer) -> Self {
+        Self {
+            path: path.to_string(),
+            handle: asset_server.load(path.to_string()),
+            status: AssetStatus::Loading,
+        }
+    }
+
+    /// Refresh the status from the asset server. True when it changed
+    fn poll(&mut self, asset_server: &AssetServer) -> bool {
+        self.update(AssetStatus::from_load_state(
+            asset_server.get_load_state(self.handle.id()),
+        ))
+    }
+
+    /// Take a newly reported status. True when it changed
+    fn update(&mut self, status: AssetStatus) -> bool {
+        if status == self.status {
+            return false;
+        }
+        self.status = status;
+        true
+    }
+
+    /// Load a failed asset again. True when a retry was started
+    fn retry(&mut self, asset_server: &AssetServer) -> bool {
+        if !self.begin_retry() {
+            return false;
+        }
+        asset_server.reload(self.path.clone());
+        true
+    }
+
+    /// Move a failed asset back to loading. True if it had failed
+    fn begin_retry(&mut self) -> bool {
+        if !self.status.is_failed() {
+            return false;
+        }
+        self.status = AssetStatus::Loading;
+        true
+    }
+
+    /// The asset once ready, the placeholder until then
+    pub fn get_or(&self, placeholder: &Handle<A>) -> Handle<A> {
+        if self.status.is_ready() {
+            self.handle.clone()
+        } else {
+            placeholder.clone()
+        }
+    }
+}
+
+/// Per-song images by path, most recently used last. Dropping an entry drops its
+/// handle, which lets Bevy free the image
+#[derive(Default)]
+struct SongImageCache {
+    entries: Vec<TrackedAsset<Image>>,
+}
+
+impl SongImageCache {
+    /// The entry for a path, loading it with `load` if needed and marking it most
+    /// recently used
+    fn touch(
+        &mut self,
+        path: &str,
+        load: impl FnOnce(&str) -> TrackedAsset<Image>,
+    ) -> &TrackedAsset<Image> {
+        let entry = match self.entries.iter().position(|entry| entry.path == path) {
+            Some(index) => self.entries.remove(index),
+            None => load(path),
+        };
+        self.entries.push(entry);
+        let excess = self.entries.len().saturating_sub(SONG_IMAGE_CAPACITY);
+        self.entries.drain(..excess);
+        self.entries.last().expect("entry was just pushed")
+    }
+}
+
+/// Loading state of the game's assets, with placeholders to draw while they load
+#[derive(Resource)]
+pub struct AssetManager {
+    pub font: TrackedAsset<Font>,
+    /// Bevy's built-in font, drawn until the UI font is ready or if it fails
+    pub fallback_font: Handle<Font>,
+    /// Drawn in place of images that aren't ready
+    pub checkerboard: Handle<Image>,
+    song_images: SongImageCache,
+}
+
+impl AssetManager {
+    /// Start loading the boot assets
+    pub fn start(asset_server: &AssetServer, images: &mut Assets<Image>) -> Self {
+        Self {
+            font: TrackedAsset::load(UI_FONT_PATH, asset_server),
+            fallback_font: Handle::default(),
+            checkerboard: images.add(checkerboard_image()),
+            song_images: SongImageCache::default(),
+        }
+    }
+
+    /// The UI font, or the fallback until it's ready
+    pub fn ui_font(&self) -> Handle<Font> {
+        self.font.get_or(&self.fallback_font)
+    }
+
+    /// A song's image, loaded on first use; the checkerboard until it's ready
+    pub fn song_image(&mut self, path: &str, asset_server: &AssetServer) -> Handle<Image> {
+        let checkerboard = self.checkerboard.clone();
+        self.song_images
+            .touch(&absolute_path(path), |path| {
+                TrackedAsset::load(path, asset_server)
+            })
+            .get_or(&checkerboard)
+    }
+
+    /// Boot assets ready and in total
+    pub fn progress(&self) -> (usize, usize) {
+        (usize::from(self.font.status.is_ready()), 1)
+    }
+
+    /// Boot assets that failed, with the reason
+    pub fn failed(&self) -> Vec<(&str, &str)> {
+        match &self.font.status {
+            AssetStatus::Failed(reason) => vec![(self.font.path.as_str(), reason.as_str())],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Load every failed asset again. Returns how many were retried
+    pub fn retry_failed(&mut self, asset_server: &AssetServer) -> usize {
+        let mut retried = usize::from(self.font.retry(asset_server));
+        for entry in &mut self.song_images.entries {
+            retried += usize::from(entry.retry(asset_server));
+        }
+        retried
+    }
+
+    /// One line for the boot screen, e.g. "Loading assets 0/1"; None once all are ready
+    pub fn status_line(&self) -> Option<String> {
+        let failed = self.failed();
+        if let Some((path, reason)) = failed.first() {
+            return Some(format!(
+                "Could not load {} ({}) - R: retry",
+                path,
+                first_line(reason)
+            ));
+        }
+        let (ready, total) = self.progress();
+        (ready < total).then(|| format!("Loading assets {}/{}", ready, total))
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+/// Song files are paths on disk rather than under the asset folder, so they are
+/// handed to the asset server in full
+fn absolute_path(path: &str) -> String {
+    std::env::current_dir()
+        .map(|dir| dir.join(path).to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Magenta and black squares, the usual "missing texture"
+fn checkerboard_image() -> Image {
+    let mut data = Vec::with_capacity((CHECKER_SQUARES * CHECKER_SQUARES * 4) as usize);
+    for y in 0..CHECKER_SQUARES {
+        for x in 0..CHECKER_SQUARES {
+            let pixel = if (x + y) % 2 == 0 {
+                [255, 0, 255, 255]
+            } else {
+                [0, 0, 0, 255]
+            };
+            data.extend_from_slice(&pixel);
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: CHECKER_SQUARES,
+            height: CHECKER_SQUARES,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    // Keep the squares sharp when stretched
+    image.sampler = bevy::image::ImageSampler::nearest();
+    image
+}
+
+/// Track loads as they finish. When the UI font becomes ready, every text already
+/// drawn with the fallback switches over, so screens don't wait for a redraw
+pub fn poll_asset_loads(
+    mut manager: ResMut<AssetManager>,
+    asset_server: Res<AssetServer>,
+    mut game_assets: ResMut<GameAssets>,
+    mut texts: Query<&mut TextFont>,
+) {
+    let tracked = manager.bypass_change_detection();
+    let mut changed = tracked.font.poll(&asset_server);
+    for entry in &mut tracked.song_images.entries {
+        changed |= entry.poll(&asset_server);
+    }
+    if !changed {
+        return;
+    }
+    if manager.font.status.is_failed() {
+        error!(
+            "Failed to load {}: {:?}",
+            manager.font.path, manager.font.status
+        );
+    }
+    manager.set_changed();
+
+    let font = manager.ui_font();
+    if game_assets.cyberpunk_font == font {
+        return;
+    }
+    let previous = std::mem::replace(&mut game_assets.cyberpunk_font, font.clone());
+    for mut text in texts.iter_mut().filter(|text| text.font == previous) {
+        text.font = font.clone();
+    }
+}
+
+/// Retry failed loads when asked to from the boot screen
+pub fn retry_failed_assets(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut manager: ResMut<AssetManager>,
+    asset_server: Res<AssetServer>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyR) && !manager.failed().is_empty() {
+        manager.retry_failed(&asset_server);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An asset as if the asset server had just started loading it
+    fn loading<A: Asset>(path: &str) -> TrackedAsset<A> {
+        TrackedAsset {
+            path: path.to_string(),
+            handle: Handle::weak_from_u128(path.len() as u128 + 1),
+            status: AssetStatus::Loading,
+        }
+    }
+
+    fn manager() -> AssetManager {
+        AssetManager {
+            font: loading(UI_FONT_PATH),
+            fallback_font: Handle::default(),
+            checkerboard: Handle::weak_from_u128(99),
+            song_images: SongImageCache::default(),
+        }
+    }
+
+    #[test]
+    fn a_failed_load_succeeds_on_retry() {
+        let mut manager = manager();
+        assert_eq!(manager.ui_font(), manager.fallback_font);
+        assert_eq!(manager.status_line().as_deref(), Some("Loading assets 0/1"));
+        assert!(!manager.font.begin_retry());
+
+        assert!(manager
+            .font
+            .update(AssetStatus::Failed("not found\nat teknaf.otf".to_string())));
+        assert!(!manager
+            .font
+            .update(AssetStatus::Failed("not found\nat teknaf.otf".to_string())));
+        assert_eq!(
+            manager.failed(),
+            [(UI_FONT_PATH, "not found\nat teknaf.otf")]
+        );
+        assert_eq!(
+            manager.status_line().as_deref(),
+            Some("Could not load fonts/teknaf.otf (not found) - R: retry")
+        );
+        assert_eq!(manager.ui_font(), manager.fallback_font);
+
+        assert!(manager.font.begin_retry());
+        assert_eq!(manager.font.status, AssetStatus::Loading);
+        assert!(manager.failed().is_empty());
+        assert!(manager.font.update(AssetStatus::Ready));
+        assert_eq!(manager.progress(), (1, 1));
+        assert_eq!(manager.status_line(), None);
+        assert_ne!(manager.ui_font(), manager.fallback_font);
+        assert!(!manager.font.begin_retry());
+    }
+
+    #[test]
+    fn images_show_the_placeholder_until_ready() {
+        let placeholder = Handle::weak_from_u128(7);
+        let mut image: TrackedAsset<Image> = loading("cover.png");
+        assert_eq!(image.get_or(&placeholder), placeholder);
+        image.update(AssetStatus::Failed("bad".to_string()));
+        assert_eq!(image.get_or(&placeholder), placeholder);
+        image.begin_retry();
+        image.update(AssetStatus::Ready);
+        assert_eq!(image.get_or(&placeholder), image.handle);
+    }
+
+    #[test]
+    fn the_song_image_cache_drops_the_least_recently_used() {
+        let mut cache = SongImageCache::default();
+        let mut loads = 0;
+        let mut touch = |cache: &mut SongImageCache, path: &str| {
+            cache.touch(path, |path| {
+                loads += 1;
+                loading(path)
+            });
+        };
+
+        for i in 0..SONG_IMAGE_CAPACITY {
+            touch(&mut cache, &format!("song{}.png", i));
+        }
+        // Using the oldest again keeps it over the next oldest
+        touch(&mut cache, "song0.png");
+        touch(&mut cache, "new.png");
+        let paths: Vec<&str> = cache
+            .entries
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect();
+        assert_eq!(paths.len(), SONG_IMAGE_CAPACITY);
+        assert!(!paths.contains(&"song1.png"));
+        assert_eq!(paths[paths.len() - 2..], ["song0.png", "new.png"]);
+
+        // Only new paths are loaded; an evicted one loads again
+        touch(&mut cache, "song1.png");
+        assert_eq!(loads, SONG_IMAGE_CAPACITY + 2);
+    }
+
+    #[test]
+    fn the_checkerboard_alternates_magenta_and_black() {
+        let image = checkerboard_image();
+        let size = CHECKER_SQUARES as usize;
+        assert_eq!(image.data.len(), size * size * 4);
+        assert_eq!(image.data[..8], [255, 0, 255, 255, 0, 0, 0, 255]);
+        assert_eq!(image.data[size * 4..size * 4 + 4], [0, 0, 0, 255]);
+    }
+}
@@ -1,20 +1,138 @@
 use aubio::{Onset, OnsetMode};
-use biquad::{Biquad, Coefficients, DirectForm1, ToHertz, Type as FilterType, Q_BUTTERWORTH_F32};
+use biquad::{Biquad, Coefficients, DirectForm1, ToHertz, Type as FilterType};
+use rodio::cpal::FromSample;
 use rodio::{Decoder, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::BufReader;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-/// Read an audio file and find the times of the kick beats
-pub fn gather_beats(path: &str) -> Vec<f64> {
+/// Length of each energy bucket in `SongAnalysis::energy` (seconds)
+pub const ENERGY_RESOLUTION: f64 = 0.05;
+
+/// Beats plus a coarse loudness curve for a song
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SongAnalysis {
+    /// Kick beat times in seconds
+    pub beats: Vec<f64>,
+    /// Normalized RMS energy (0.0 - 1.0) per `ENERGY_RESOLUTION` bucket
+    pub energy: Vec<f32>,
+}
+
+/// Speed against accuracy tradeoff of beat detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DetectionQuality {
+    /// Coarse hop and the plain energy onset function, for long mixes
+    Fast,
+    #[default]
+    Balanced,
+    /// Fine hop, spectral flux onsets and a tempo grid cleanup pass
+    Accurate,
+}
+
+impl DetectionQuality {
+    /// All modes, fastest first
+    pub fn all() -> Vec<DetectionQuality> {
+        vec![
+            DetectionQuality::Fast,
+            DetectionQuality::Balanced,
+            DetectionQuality::Accurate,
+        ]
+    }
+
+    /// Get display name for the mode
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            DetectionQuality::Fast => "Fast",
+            DetectionQuality::Balanced => "Balanced",
+            DetectionQuality::Accurate => "Accurate",
+        }
+    }
+
+    /// Mode by name, ignoring case
+    pub fn from_name(name: &str) -> Option<DetectionQuality> {
+        Self::all()
+            .into_iter()
+            .find(|quality| quality.display_name().eq_ignore_ascii_case(name))
+    }
+
+    /// The mode `direction` steps away, wrapping around; 0 counts as forward
+    pub fn cycled(&self, direction: i32) -> DetectionQuality {
+        let all = Self::all();
+        let index = all.iter().position(|quality| quality == self).unwrap_or(0) as i32;
+        let step = if direction < 0 { -1 } else { 1 };
+        all[(index + step).rem_euclid(all.len() as i32) as usize]
+    }
+
+    /// Detector configuration for the mode
+    pub fn params(&self) -> DetectionParams {
+        match self {
+            DetectionQuality::Fast => DetectionParams {
+                buffer_size: 2048,
+                hop_size: 1024,
+                onset_mode: OnsetMode::Energy,
+                threshold: 0.4,
+                min_gap: 0.15,
+                tempo_grid: false,
+            },
+            DetectionQuality::Balanced => DetectionParams {
+                buffer_size: 1024,
+                hop_size: 512,
+                onset_mode: OnsetMode::Energy,
+                threshold: 0.4,
+                min_gap: 0.15,
+                tempo_grid: false,
+            },
+            DetectionQuality::Accurate => DetectionParams {
+                buffer_size: 1024,
+                hop_size: 256,
+                onset_mode: OnsetMode::SpecFlux,
+                threshold: 0.3,
+                min_gap: 0.1,
+                tempo_grid: true,
+            },
+        }
+    }
+}
+
+/// Onset detector settings behind a `DetectionQuality`
+#[derive(Debug, Clone, Copy)]
+pub struct DetectionParams {
+    /// Analysis window (samples)
+    pub buffer_size: usize,
+    /// Step between windows (samples); smaller finds onset times more precisely
+    pub hop_size: usize,
+    pub onset_mode: OnsetMode,
+    /// Peak picking threshold; lower catches softer kicks
+    pub threshold: f32,
+    /// Onsets closer than this to the previous one are dropped (seconds)
+    pub min_gap: f64,
+    /// Snap onsets to a fitted tempo grid and drop doubles, see `snap_to_tempo_grid`
+    pub tempo_grid: bool,
+}
+
+/// Onsets further than this from the fitted grid are left where they are (seconds)
+const TEMPO_GRID_TOLERANCE: f64 = 0.035;
+
+/// Read an audio file and find the kick beats and energy curve
+pub fn analyze_song(path: &str, quality: DetectionQuality) -> SongAnalysis {
     println!("Loading audio file: {}", path);
+    try_analyze_song(path, quality).expect("Failed to analyze audio file")
+}
+
+/// Like `analyze_song`, but reports files that can't be opened or decoded
+pub fn try_analyze_song(path: &str, quality: DetectionQuality) -> Result<SongAnalysis, String> {
     // Open the file
-    let file = File::open(path).expect("Failed to open audio file");
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
 
     // Create a reader that buffers the file
     let reader = BufReader::new(file);
 
     // Decode the audio from the reader
-    let decoder = Decoder::new(reader).expect("Failed to decode audio");
+    let decoder = Decoder::new(reader).map_err(|e| format!("Failed to decode {}: {}", path, e))?;
 
     // Get the sample rate of the audio
     let sample_rate = decoder.sample_rate();
@@ -22,15 +140,34 @@ pub fn gather_beats(path: &str) -> Vec<f64> {
     // Collect all of the samples from the audio
     let samples: Vec<f32> = decoder.convert_samples().collect();
 
-    // Find the kick beats in the samples
-    let beats = detect_kick_beats(&samples, sample_rate);
-    beats
+    // Find the kick beats and the loudness curve in the samples
+    Ok(SongAnalysis {
+        beats: detect_kick_beats(&samples, sample_rate, quality.params()),
+        energy: energy_curve(&samples, sample_rate),
+    })
+}
+
+/// Compute normalized RMS energy per bucket
+fn energy_curve(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let bucket_size = ((sample_rate as f64 * ENERGY_RESOLUTION) as usize).max(1);
+    let mut energy: Vec<f32> = samples
+        .chunks(bucket_size)
+        .map(|chunk| (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt())
+        .collect();
+
+    let max = energy.iter().cloned().fold(0.0f32, f32::max);
+    if max > 0.0 {
+        for value in &mut energy {
+            *value /= max;
+        }
+    }
+    energy
 }
 
 /// Find the kick beats in a set of samples
-fn detect_kick_beats(samples: &[f32], sample_rate: u32) -> Vec<f64> {
-    let buffer_size = 1024;
-    let hop_size = 512;
+fn detect_kick_beats(samples: &[f32], sample_rate: u32, params: DetectionParams) -> Vec<f64> {
+    let buffer_size = params.buffer_size;
+    let hop_size = params.hop_size;
 
     // Lower the cutoff frequency to capture the bass drum more effectively
     let cutoff_freq = 120.0; // Adjust this based on the bass frequency range
@@ -54,14 +191,14 @@ fn detect_kick_beats(samples: &[f32], sample_rate: u32) -> Vec<f64> {
     }
 
     // Use Energy mode instead of RMS (since Rms doesn't exist in your library)
-    let mut onset = Onset::new(OnsetMode::Energy, buffer_size, hop_size, sample_rate).unwrap();
+    let mut onset = Onset::new(params.onset_mode, buffer_size, hop_size, sample_rate).unwrap();
 
-    onset.set_threshold(0.4); // Lower the threshold to catch softer bass hits
+    onset.set_threshold(params.threshold); // Lower the threshold to catch softer bass hits
     onset.set_silence(-60.0); // Adjust for quieter kicks
 
-    // Pre-allocate beats vector with estimated capacity
+    // Pre-allocate onsets vector with estimated capacity
     let estimated_beats = samples.len() / (hop_size * 2); // Estimate beat count
-    let mut beats = Vec::with_capacity(estimated_beats);
+    let mut onsets = Vec::with_capacity(estimated_beats);
     let mut buffer = vec![0.0; buffer_size];
     let mut position = 0;
 
@@ -70,16 +207,728 @@ fn detect_kick_beats(samples: &[f32], sample_rate: u32) -> Vec<f64> {
 
         // Check for an onset
         if onset.do_result(&buffer).unwrap() > 0.0 {
-            let onset_time = onset.get_last_s();
+            onsets.push(onset.get_last_s() as f64);
+        }
+
+        position += hop_size;
+    }
+
+    clean_onsets(&onsets, params)
+}
+
+/// Post-process detected onsets: drop any closer than `min_gap` to the previous
+/// kept one, then snap to the tempo grid if the mode asks for it
+pub fn clean_onsets(onsets: &[f64], params: DetectionParams) -> Vec<f64> {
+    let mut beats: Vec<f64> = Vec::with_capacity(onsets.len());
+    for &onset_time in onsets {
+        if beats.last().is_none_or(|last| onset_time - last > params.min_gap) {
+            beats.push(onset_time);
+        }
+    }
+
+    if params.tempo_grid {
+        snap_to_tempo_grid(&beats)
+    } else {
+        beats
+    }
+}
+
+/// Snap onsets to a tempo grid fitted to them and drop the doubles that leaves.
+/// The grid runs at half the median gap so off-beat kicks survive; onsets far
+/// from every grid line stay where they are, so tempo changes aren't forced onto
+/// the wrong grid
+pub fn snap_to_tempo_grid(beats: &[f64]) -> Vec<f64> {
+    let mut gaps: Vec<f64> = beats
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|gap| *gap > 0.0)
+        .collect();
+    if gaps.len() < 2 {
+        return beats.to_vec();
+    }
+    gaps.sort_by(|a, b| a.total_cmp(b));
+    let step = gaps[gaps.len() / 2] / 2.0;
+
+    // Grid phase from the circular mean of where each onset falls within a step
+    let tau = std::f64::consts::TAU;
+    let (sin, cos) = beats.iter().fold((0.0, 0.0), |(sin, cos), beat| {
+        let angle = tau * (beat / step).fract();
+        (sin + angle.sin(), cos + angle.cos())
+    });
+    let offset = f64::atan2(sin, cos).rem_euclid(tau) / tau * step;
+
+    let mut snapped: Vec<f64> = Vec::with_capacity(beats.len());
+    for &beat in beats {
+        let grid = offset + ((beat - offset) / step).round() * step;
+        let time = if (grid - beat).abs() <= TEMPO_GRID_TOLERANCE {
+            grid.max(0.0)
+        } else {
+            beat
+        };
+        // Onsets landing on the same grid line, or within half a step, are one kick
+        if snapped.last().is_some_and(|last| time - last < step * 0.5) {
+            continue;
+        }
+        snapped.push(time);
+    }
+    snapped
+}
+
+/// Length of the intro-skip whoosh (seconds)
+const WHOOSH_DURATION: f32 = 0.35;
+
+/// Sample rate the whoosh is synthesized at
+const WHOOSH_SAMPLE_RATE: u32 = 44_100;
+
+/// Restart a song on the sink from a song time. rodio can't seek a playing decoder,
+/// so the file is decoded again and skipped ahead; the returned position reads in
+/// song time from the start of the file
+pub fn play_from(sink: &rodio::Sink, path: &str, seconds: f64) -> Result<PlaybackPosition, String> {
+    let (source, position) = CountingSource::new(decode_from(path, seconds)?);
+    position.set_seconds(seconds);
 
-            // Post-processing: Ignore beats too close together (e.g., less than 150 ms apart)
-            if beats.is_empty() || (onset_time as f64 - beats.last().unwrap()) > 0.15 {
-                beats.push(onset_time as f64);
+    sink.stop();
+    sink.append(source);
+    sink.play();
+    Ok(position)
+}
+
+/// Like `play_from`, at a playback speed. Changing the sink speed shifts the pitch with
+/// it; with `preserve_pitch` the song is time-stretched instead and the sink left at 1x
+pub fn play_song(
+    sink: &rodio::Sink,
+    path: &str,
+    seconds: f64,
+    speed: f32,
+    preserve_pitch: bool,
+) -> Result<PlaybackPosition, String> {
+    if !preserve_pitch || speed == 1.0 {
+        sink.set_speed(speed);
+        return play_from(sink, path, seconds);
+    }
+    let (source, position) = TimeStretch::new(decode_from(path, seconds)?, speed);
+    position.set_seconds(seconds);
+
+    sink.stop();
+    sink.set_speed(1.0);
+    sink.append(source);
+    sink.play();
+    Ok(position)
+}
+
+/// Length of a song in seconds. Formats that don't report it are decoded through
+/// to count their frames
+pub fn song_duration(path: &str) -> Option<f64> {
+    let decoder = decode_from(path, 0.0).ok()?;
+    if let Some(duration) = decoder.total_duration() {
+        return Some(duration.as_secs_f64());
+    }
+    let channels = decoder.channels().max(1) as f64;
+    let sample_rate = decoder.sample_rate().max(1) as f64;
+    Some(decoder.count() as f64 / channels / sample_rate)
+}
+
+/// A song decoded from a song time on
+fn decode_from(
+    path: &str,
+    seconds: f64,
+) -> Result<rodio::source::SkipDuration<Decoder<BufReader<File>>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to decode {}: {}", path, e))?;
+    Ok(decoder.skip_duration(Duration::from_secs_f64(seconds.max(0.0))))
+}
+
+/// Short swept-noise whoosh played when the intro is skipped. Synthesized so no
+/// sound asset has to ship with the game
+pub fn whoosh() -> rodio::buffer::SamplesBuffer<f32> {
+    let count = (WHOOSH_DURATION * WHOOSH_SAMPLE_RATE as f32) as usize;
+    let mut seed: u32 = 0x2545_f491;
+    let mut filtered = 0.0f32;
+    let samples = (0..count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            // Cheap LCG noise, low-passed with a cutoff that rises through the sweep
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0;
+            filtered += (noise - filtered) * (0.02 + 0.3 * t);
+            let envelope = (std::f32::consts::PI * t).sin();
+            filtered * envelope * 0.8
+        })
+        .collect::<Vec<_>>();
+    rodio::buffer::SamplesBuffer::new(1, WHOOSH_SAMPLE_RATE, samples)
+}
+
+/// Shared handle to how far the audio output has read into a `CountingSource`
+#[derive(Debug, Clone)]
+pub struct PlaybackPosition {
+    samples: Arc<AtomicU64>,
+    finished: Arc<AtomicBool>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl PlaybackPosition {
+    /// Song time in seconds of the last frame handed to the output
+    pub fn seconds(&self) -> f64 {
+        let frames = self.samples.load(Ordering::Relaxed) / self.channels.max(1) as u64;
+        frames as f64 / self.sample_rate.max(1) as f64
+    }
+
+    /// Whether the output has started reading the source
+    pub fn started(&self) -> bool {
+        self.samples.load(Ordering::Relaxed) > 0
+    }
+
+    /// Whether the source has run out of samples
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Move the position to a song time, for sources that were seeked or looped
+    pub fn set_seconds(&self, seconds: f64) {
+        let frames = (seconds.max(0.0) * self.sample_rate as f64) as u64;
+        self.samples
+            .store(frames * self.channels.max(1) as u64, Ordering::Relaxed);
+        self.finished.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Source wrapper that counts the samples the output consumes, so the game can
+/// read the true audio position instead of trusting the wall clock
+pub struct CountingSource<S> {
+    inner: S,
+    position: PlaybackPosition,
+}
+
+impl<S: Source> CountingSource<S>
+where
+    S::Item: rodio::Sample,
+{
+    /// Wrap a source; the returned handle tracks its position
+    pub fn new(inner: S) -> (Self, PlaybackPosition) {
+        let position = PlaybackPosition {
+            samples: Arc::new(AtomicU64::new(0)),
+            finished: Arc::new(AtomicBool::new(false)),
+            sample_rate: inner.sample_rate(),
+            channels: inner.channels(),
+        };
+        let source = Self {
+            inner,
+            position: position.clone(),
+        };
+        (source, position)
+    }
+}
+
+impl<S: Source> Iterator for CountingSource<S>
+where
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let sample = self.inner.next();
+        match sample {
+            Some(_) => {
+                self.position.samples.fetch_add(1, Ordering::Relaxed);
             }
+            None => self.position.finished.store(true, Ordering::Relaxed),
         }
+        sample
+    }
 
-        position += hop_size;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source> Source for CountingSource<S>
+where
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+/// Frames in each window `TimeStretch` overlaps (about 46 ms at 44.1 kHz)
+const STRETCH_WINDOW: usize = 2048;
+
+/// How far `TimeStretch` may move a window to line its waveform up with the previous
+/// one (frames)
+const STRETCH_TOLERANCE: usize = 256;
+
+/// Candidate offsets and points compared when lining windows up are this many frames
+/// apart; finer costs more for little audible gain
+const STRETCH_SEARCH_STEP: usize = 2;
+
+/// Source that changes a song's speed without changing its pitch (WSOLA). Windows of
+/// the input are read `speed` times further apart than they are written, each moved
+/// slightly so its waveform continues the previous one, and overlap-added under a
+/// Hann window. Reports the song position like `CountingSource`
+pub struct TimeStretch<S> {
+    inner: S,
+    channels: usize,
+    sample_rate: u32,
+    speed: f64,
+    /// Decoded input, interleaved, starting at frame `input_start`
+    input: Vec<f32>,
+    input_start: usize,
+    exhausted: bool,
+    /// Where the next window would be read without lining up (input frames)
+    next_read: f64,
+    /// Where the previous window was read
+    last_read: Option<usize>,
+    /// Overlap-add buffer, one window long
+    mix: Vec<f32>,
+    /// Finished output waiting to be played
+    ready: VecDeque<f32>,
+    window: Vec<f32>,
+    done: bool,
+    position: PlaybackPosition,
+    /// Output frames handed out, and the song samples counted for them
+    emitted: u64,
+    counted: u64,
+}
+
+impl<S: Source> TimeStretch<S>
+where
+    S::Item: rodio::Sample,
+    f32: FromSample<S::Item>,
+{
+    /// Wrap a source to play at `speed` (0.25 - 2.0 in practice); the returned handle
+    /// tracks the song position
+    pub fn new(inner: S, speed: f32) -> (Self, PlaybackPosition) {
+        let channels = inner.channels().max(1) as usize;
+        let position = PlaybackPosition {
+            samples: Arc::new(AtomicU64::new(0)),
+            finished: Arc::new(AtomicBool::new(false)),
+            sample_rate: inner.sample_rate(),
+            channels: channels as u16,
+        };
+        // Periodic Hann: windows half a window apart sum to exactly one
+        let window = (0..STRETCH_WINDOW)
+            .map(|i| {
+                let phase = i as f32 / STRETCH_WINDOW as f32;
+                0.5 - 0.5 * (2.0 * std::f32::consts::PI * phase).cos()
+            })
+            .collect();
+        let source = Self {
+            sample_rate: inner.sample_rate(),
+            inner,
+            channels,
+            speed: speed.max(0.01) as f64,
+            input: Vec::new(),
+            input_start: 0,
+            exhausted: false,
+            next_read: 0.0,
+            last_read: None,
+            mix: vec![0.0; STRETCH_WINDOW * channels],
+            ready: VecDeque::new(),
+            window,
+            done: false,
+            position: position.clone(),
+            emitted: 0,
+            counted: 0,
+        };
+        (source, position)
     }
 
-    beats
+    /// Frame after the last decoded one
+    fn input_end(&self) -> usize {
+        self.input_start + self.input.len() / self.channels
+    }
+
+    /// Decode until `frame` is available or the source runs out
+    fn fill_to(&mut self, frame: usize) {
+        while !self.exhausted && self.input_end() < frame {
+            match self.inner.next() {
+                Some(sample) => self.input.push(f32::from_sample_(sample)),
+                None => self.exhausted = true,
+            }
+        }
+    }
+
+    /// Input sample, silence outside what is decoded
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        if frame < self.input_start || frame >= self.input_end() {
+            return 0.0;
+        }
+        self.input[(frame - self.input_start) * self.channels + channel]
+    }
+
+    /// All channels of an input frame mixed down, for lining windows up
+    fn mono(&self, frame: usize) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    /// Offset near `nominal` whose waveform best continues the input from `target`
+    fn aligned(&self, target: usize, nominal: usize) -> usize {
+        let overlap = STRETCH_WINDOW / 2;
+        let lowest = nominal
+            .saturating_sub(STRETCH_TOLERANCE)
+            .max(self.input_start);
+        let mut best = (nominal, f32::MIN);
+        for candidate in (lowest..=nominal + STRETCH_TOLERANCE).step_by(STRETCH_SEARCH_STEP) {
+            let score: f32 = (0..overlap)
+                .step_by(STRETCH_SEARCH_STEP)
+                .map(|i| self.mono(target + i) * self.mono(candidate + i))
+                .sum();
+            if score > best.1 {
+                best = (candidate, score);
+            }
+        }
+        best.0
+    }
+
+    /// Overlap-add the next window and move half a window to `ready`. Returns false
+    /// once the input has run out
+    fn step(&mut self) -> bool {
+        let hop = STRETCH_WINDOW / 2;
+        let nominal = self.next_read.round() as usize;
+        self.fill_to(nominal + STRETCH_TOLERANCE + STRETCH_WINDOW);
+        if nominal >= self.input_end() {
+            return false;
+        }
+
+        let start = match self.last_read {
+            Some(last) => self.aligned(last + hop, nominal),
+            None => nominal,
+        };
+        for i in 0..STRETCH_WINDOW {
+            for c in 0..self.channels {
+                self.mix[i * self.channels + c] += self.sample(start + i, c) * self.window[i];
+            }
+        }
+        self.ready.extend(self.mix.drain(..hop * self.channels));
+        self.mix.resize(STRETCH_WINDOW * self.channels, 0.0);
+        self.last_read = Some(start);
+        self.next_read += hop as f64 * self.speed;
+
+        // Drop input neither the next window nor its alignment target can reach
+        let keep_from =
+            (start + hop).min((self.next_read as usize).saturating_sub(STRETCH_TOLERANCE));
+        if keep_from > self.input_start {
+            let drop = ((keep_from - self.input_start) * self.channels).min(self.input.len());
+            self.input.drain(..drop);
+            self.input_start = keep_from;
+        }
+        true
+    }
+}
+
+impl<S: Source> Iterator for TimeStretch<S>
+where
+    S::Item: rodio::Sample,
+    f32: FromSample<S::Item>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.ready.is_empty() && !self.done && !self.step() {
+            // The last window's tail is all that is left
+            let hop = STRETCH_WINDOW / 2;
+            self.ready.extend(self.mix.drain(..hop * self.channels));
+            self.done = true;
+        }
+        let Some(sample) = self.ready.pop_front() else {
+            self.position.finished.store(true, Ordering::Relaxed);
+            return None;
+        };
+        self.emitted += 1;
+        if self.emitted.is_multiple_of(self.channels as u64) {
+            // Output frames map back to song frames at the speed
+            let frames = self.emitted / self.channels as u64;
+            let song = (frames as f64 * self.speed) as u64 * self.channels as u64;
+            self.position
+                .samples
+                .fetch_add(song - self.counted, Ordering::Relaxed);
+            self.counted = song;
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source> Source for TimeStretch<S>
+where
+    S::Item: rodio::Sample,
+    f32: FromSample<S::Item>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner
+            .total_duration()
+            .map(|duration| duration.div_f64(self.speed))
+    }
+}
+
+/// Fade at each end of a song clip, so previews and loops don't click (seconds)
+pub const CLIP_EDGE_FADE: f64 = 1.0;
+
+/// Gains of the outgoing and incoming song `progress` (0-1) of the way through a
+/// crossfade. Equal power, so loudness holds steady and both are never at full volume
+pub fn crossfade_gains(progress: f32) -> (f32, f32) {
+    let angle = progress.clamp(0.0, 1.0) * std::f32::consts::FRAC_PI_2;
+    (angle.cos(), angle.sin())
+}
+
+/// Gain `elapsed` seconds into a clip `length` seconds long that fades in and out
+/// over `edge` seconds at each end
+pub fn clip_gain(elapsed: f64, length: f64, edge: f64) -> f32 {
+    if edge <= 0.0 {
+        return 1.0;
+    }
+    let fade_in = elapsed / edge;
+    let fade_out = (length - elapsed) / edge;
+    fade_in.min(fade_out).clamp(0.0, 1.0) as f32
+}
+
+/// A section of a song as decoded for `song_clip`
+pub type SongClip = ClipEnvelope<
+    rodio::source::TakeDuration<rodio::source::SkipDuration<Decoder<BufReader<File>>>>,
+>;
+
+/// Section of a song starting at `start`, `length` seconds long or to the end of the
+/// song, faded in and out at its edges
+pub fn song_clip(path: &str, start: f64, length: Option<f64>) -> Result<SongClip, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to decode {}: {}", path, e))?;
+    let length = length.map_or(Duration::MAX, |seconds| {
+        Duration::from_secs_f64(seconds.max(0.0))
+    });
+    let source = decoder
+        .skip_duration(Duration::from_secs_f64(start.max(0.0)))
+        .take_duration(length);
+    Ok(ClipEnvelope::new(source, length.as_secs_f64()))
+}
+
+/// Source wrapper applying `clip_gain` across a clip of known length
+pub struct ClipEnvelope<S> {
+    inner: S,
+    length: f64,
+    samples: u64,
+}
+
+impl<S: Source> ClipEnvelope<S>
+where
+    S::Item: rodio::Sample,
+{
+    pub fn new(inner: S, length: f64) -> Self {
+        Self {
+            inner,
+            length,
+            samples: 0,
+        }
+    }
+}
+
+impl<S: Source> Iterator for ClipEnvelope<S>
+where
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let sample = self.inner.next()?;
+        let frames = self.samples / self.inner.channels().max(1) as u64;
+        let elapsed = frames as f64 / self.inner.sample_rate().max(1) as f64;
+        self.samples += 1;
+        let gain = clip_gain(elapsed, self.length, CLIP_EDGE_FADE);
+        Some(rodio::Sample::amplify(sample, gain))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<S: Source> Source for ClipEnvelope<S>
+where
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kick every 0.8s (75 BPM)
+    fn kicks(count: usize) -> Vec<f64> {
+        (0..count).map(|i| 1.0 + i as f64 * 0.8).collect()
+    }
+
+    fn jitter(i: usize) -> f64 {
+        [0.0, 0.0, 0.0, 0.003, 0.0, 0.0][i % 6]
+    }
+
+    /// Onsets of a noisy click track: every kick, a few 3 ms late, plus a second
+    /// spurious onset shortly after every fifth kick, as a ringing kick gives
+    fn noisy_onsets(kicks: &[f64]) -> Vec<f64> {
+        let mut onsets = Vec::new();
+        for (i, kick) in kicks.iter().enumerate() {
+            onsets.push(kick + jitter(i));
+            if i % 5 == 0 {
+                onsets.push(kick + 0.17 + jitter(i + 1));
+            }
+        }
+        onsets
+    }
+
+    fn false_positives(beats: &[f64], kicks: &[f64]) -> usize {
+        beats
+            .iter()
+            .filter(|beat| !kicks.iter().any(|kick| (*beat - kick).abs() < 0.02))
+            .count()
+    }
+
+    #[test]
+    fn accurate_detection_finds_fewer_false_beats_than_fast() {
+        let kicks = kicks(40);
+        let onsets = noisy_onsets(&kicks);
+
+        let fast = clean_onsets(&onsets, DetectionQuality::Fast.params());
+        let accurate = clean_onsets(&onsets, DetectionQuality::Accurate.params());
+
+        assert_eq!(false_positives(&fast, &kicks), 8);
+        assert_eq!(false_positives(&accurate, &kicks), 0);
+        // Every kick survives, within 10 ms of where it was played
+        assert_eq!(accurate.len(), kicks.len());
+        for (beat, kick) in accurate.iter().zip(&kicks) {
+            assert!((beat - kick).abs() < 0.01, "{} vs {}", beat, kick);
+        }
+    }
+
+    #[test]
+    fn the_tempo_grid_keeps_off_beat_kicks() {
+        // Kicks on the beat and the half beat at 120 BPM, a few milliseconds off
+        let beats = [0.5, 1.003, 1.25, 1.497, 2.0, 2.502, 2.75, 3.0];
+        let snapped = snap_to_tempo_grid(&beats);
+        assert_eq!(snapped.len(), beats.len());
+        for (snapped, beat) in snapped.iter().zip(beats) {
+            assert!((snapped - beat).abs() <= 0.005);
+        }
+    }
+
+    #[test]
+    fn onsets_far_from_the_grid_stay_put() {
+        let beats = [0.5, 1.0, 1.5, 2.0, 2.5, 2.83, 3.0];
+        let snapped = snap_to_tempo_grid(&beats);
+        assert!(snapped.contains(&2.83));
+        assert_eq!(snap_to_tempo_grid(&[1.0, 1.5]), vec![1.0, 1.5]);
+    }
+
+    #[test]
+    fn detection_modes_cycle_and_parse_by_name() {
+        assert_eq!(DetectionQuality::Accurate.cycled(1), DetectionQuality::Fast);
+        assert_eq!(
+            DetectionQuality::Fast.cycled(-1),
+            DetectionQuality::Accurate
+        );
+        for quality in DetectionQuality::all() {
+            assert_eq!(
+                DetectionQuality::from_name(quality.display_name()),
+                Some(quality)
+            );
+        }
+    }
+
+    /// Three seconds of mono silence with a short 440 Hz beep on the beat at one second
+    fn beep_track() -> rodio::buffer::SamplesBuffer<f32> {
+        let rate = 44_100;
+        let samples: Vec<f32> = (0..3 * rate)
+            .map(|i| {
+                if (rate..rate + 441).contains(&i) {
+                    0.8 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / rate as f32).sin()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        rodio::buffer::SamplesBuffer::new(1, rate as u32, samples)
+    }
+
+    #[test]
+    fn practice_speed_moves_the_beat_in_wall_time_but_not_in_song_time() {
+        for speed in [0.5f32, 0.75, 1.5] {
+            let (stretched, position) = TimeStretch::new(beep_track(), speed);
+            let mut output = 0usize;
+            let mut beat = None;
+            for sample in stretched {
+                output += 1;
+                if beat.is_none() && sample.abs() > 0.1 {
+                    beat = Some((output as f64 / 44_100.0, position.seconds()));
+                }
+            }
+            let (wall, song) = beat.expect("the beep survives stretching");
+            // The beat is heard at its song time divided by the speed...
+            assert!(
+                (wall - 1.0 / speed as f64).abs() < 0.05,
+                "{}x: beat heard at {:.3}s",
+                speed,
+                wall
+            );
+            // ...while the position the game judges against still reads one second
+            assert!(
+                (song - 1.0).abs() < 0.05,
+                "{}x: beat at song time {:.3}s",
+                speed,
+                song
+            );
+            let length = output as f64 / 44_100.0;
+            assert!(
+                (length - 3.0 / speed as f64).abs() < 0.1,
+                "{}x: {:.3}s",
+                speed,
+                length
+            );
+            // The last window's faded tail can run a few milliseconds past the end
+            assert!(
+                (position.seconds() - 3.0).abs() < 0.1,
+                "{}x: ended at {:.3}s",
+                speed,
+                position.seconds()
+            );
+            assert!(position.finished());
+        }
+    }
 }
@@ -0,0 +1,111 @@
+// src/beat_cache.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::audio::{DetectionQuality, SongAnalysis};
+use crate::hashing::fnv1a;
+
+/// Folder holding one analysis file per song and detection mode
+pub const BEAT_CACHE_DIR: &str = "cache/beats";
+
+/// The audio file an analysis was made from; a cached analysis is only used while
+/// the file still has the same size and modification time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub len: u64,
+    pub modified_secs: u64,
+}
+
+impl FileStamp {
+    /// Stamp of a file as it is now, None if it can't be read
+    pub fn of(path: &str) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified_secs = metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Some(Self {
+            len: metadata.len(),
+            modified_secs,
+        })
+    }
+}
+
+/// An analysis as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedAnalysis {
+    song_path: String,
+    quality: DetectionQuality,
+    stamp: FileStamp,
+    analysis: SongAnalysis,
+}
+
+/// Cache file in `dir` of a song analyzed in a mode
+fn cache_path(dir: &Path, song_path: &str, quality: DetectionQuality) -> PathBuf {
+    let key = format!("{}|{}", song_path, quality.display_name());
+    dir.join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+}
+
+/// Cached analysis of a song, if there is one for this mode and the file hasn't
+/// changed since
+pub fn load_cached(song_path: &str, quality: DetectionQuality) -> Option<SongAnalysis> {
+    load_cached_in(Path::new(BEAT_CACHE_DIR), song_path, quality)
+}
+
+/// `load_cached` from a cache kept in `dir`
+pub fn load_cached_in(
+    dir: &Path,
+    song_path: &str,
+    quality: DetectionQuality,
+) -> Option<SongAnalysis> {
+    let stamp = FileStamp::of(song_path)?;
+    let contents = fs::read_to_string(cache_path(dir, song_path, quality)).ok()?;
+    let cached: CachedAnalysis = serde_json::from_str(&contents).ok()?;
+    (cached.song_path == song_path && cached.quality == quality && cached.stamp == stamp)
+        .then_some(cached.analysis)
+}
+
+/// Whether a song has a usable cached analysis for this mode
+pub fn is_cached(song_path: &str, quality: DetectionQuality) -> bool {
+    is_cached_in(Path::new(BEAT_CACHE_DIR), song_path, quality)
+}
+
+/// `is_cached` for a cache kept in `dir`
+pub fn is_cached_in(dir: &Path, song_path: &str, quality: DetectionQuality) -> bool {
+    load_cached_in(dir, song_path, quality).is_some()
+}
+
+/// Store a song's analysis for the next time it is loaded
+pub fn store(
+    song_path: &str,
+    quality: DetectionQuality,
+    analysis: &SongAnalysis,
+) -> Result<(), String> {
+    store_in(Path::new(BEAT_CACHE_DIR), song_path, quality, analysis)
+}
+
+/// `store` into a cache kept in `dir`
+pub fn store_in(
+    dir: &Path,
+    song_path: &str,
+    quality: DetectionQuality,
+    analysis: &SongAnalysis,
+) -> Result<(), String> {
+    let stamp = FileStamp::of(song_path).ok_or_else(|| format!("Couldn't read {}", song_path))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create beat cache folder: {}", e))?;
+    let cached = CachedAnalysis {
+        song_path: song_path.to_string(),
+        quality,
+        stamp,
+        analysis: analysis.clone(),
+    };
+    let json = serde_json::to_string(&cached)
+        .map_err(|e| format!("Failed to serialize beat analysis: {}", e))?;
+    fs::write(cache_path(dir, song_path, quality), json)
+        .map_err(|e| format!("Failed to save beat analysis: {}", e))
+}
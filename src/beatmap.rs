@@ -1,9 +1,13 @@
 // src/beatmap.rs
 
+use crate::difficulty::{content_hash, DifficultySummary};
+use crate::gamemode::{GameSettings, HARD_ROCK_CS_SCALE, HARD_ROCK_MAP_SCALE};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
+use std::ops::Range;
 use std::path::Path;
 
 /// Unique identifier for hit objects
@@ -12,6 +16,27 @@ pub type HitObjectId = u64;
 /// Beatmap file format version
 pub const BEATMAP_FORMAT_VERSION: u32 = 1;
 
+/// Slowest slider velocity an inherited timing point may set
+pub const MIN_SLIDER_VELOCITY: f64 = 0.1;
+
+/// Fastest slider velocity an inherited timing point may set
+pub const MAX_SLIDER_VELOCITY: f64 = 4.0;
+
+/// Most slider ticks per beat used in gameplay, whatever the map's tick rate says
+pub const MAX_SLIDER_TICK_RATE: f64 = 8.0;
+
+/// Times closer than this to the midpoint between two ticks count as ambiguous (seconds)
+pub const TICK_AMBIGUITY_EPSILON: f64 = 0.0005;
+
+/// Time kiai effects take to fade in after a section starts and out after it ends (seconds)
+pub const KIAI_RAMP: f64 = 0.5;
+
+/// Slowest playback rate a practice hint should suggest
+pub const MIN_PRACTICE_RATE: f32 = 0.25;
+
+/// Fastest playback rate a practice hint should suggest; hints are for slowing down
+pub const MAX_PRACTICE_RATE: f32 = 1.0;
+
 /// A complete beatmap containing all metadata, timing, and hit objects
 #[derive(Debug, Clone, Serialize, Deserialize, Resource)]
 pub struct Beatmap {
@@ -35,6 +60,13 @@ pub struct Beatmap {
     pub preview_time: f64,
     /// Tags for searching/categorization
     pub tags: Vec<String>,
+    /// Kiai (chorus) sections, sorted by start time
+    #[serde(default)]
+    pub kiai_sections: Vec<KiaiSection>,
+    /// Sections the mapper suggests practicing slowed down, sorted by start time.
+    /// Only the practice menu reads them
+    #[serde(default)]
+    pub practice_hints: Vec<PracticeHint>,
 }
 
 impl Default for Beatmap {
@@ -50,6 +82,8 @@ impl Default for Beatmap {
             audio_path: String::new(),
             preview_time: 0.0,
             tags: Vec::new(),
+            kiai_sections: Vec::new(),
+            practice_hints: Vec::new(),
         }
     }
 }
@@ -102,10 +136,29 @@ impl Beatmap {
         }
     }
 
-    /// Add a hit object
+    /// Add a hit object after any others at the same time, where a sort would put it
     pub fn add_hit_object(&mut self, object: HitObject) {
-        self.hit_objects.push(object);
-        self.sort_hit_objects();
+        let position = self
+            .hit_objects
+            .partition_point(|obj| obj.time <= object.time);
+        self.hit_objects.insert(position, object);
+    }
+
+    /// Add a hit object, finding its place through the index instead of the objects
+    pub fn insert_indexed(&mut self, index: &mut HitObjectIndex, object: HitObject) {
+        let end_time = self.object_end_time(&object);
+        let position = index.insert(object.time, object.id, end_time);
+        self.hit_objects.insert(position, object);
+    }
+
+    /// Remove a hit object by ID, finding it through the index
+    pub fn remove_indexed(
+        &mut self,
+        index: &mut HitObjectIndex,
+        id: HitObjectId,
+    ) -> Option<HitObject> {
+        let position = index.remove(id)?;
+        Some(self.hit_objects.remove(position))
     }
 
     /// Remove a hit object by ID
@@ -117,6 +170,22 @@ impl Beatmap {
         }
     }
 
+    /// Number of an object within its combo, counting from 1
+    pub fn combo_number(&self, id: HitObjectId) -> Option<u32> {
+        let mut number = 0;
+        for (index, obj) in self.hit_objects.iter().enumerate() {
+            number = if index == 0 || obj.new_combo {
+                1
+            } else {
+                number + 1
+            };
+            if obj.id == id {
+                return Some(number);
+            }
+        }
+        None
+    }
+
     /// Sort hit objects by time
     pub fn sort_hit_objects(&mut self) {
         self.hit_objects.sort_by(|a, b| {
@@ -126,11 +195,12 @@ impl Beatmap {
         });
     }
 
-    /// Get BPM at a specific time
+    /// Get BPM at a specific time (inherited points do not change BPM)
     pub fn get_bpm_at(&self, time: f64) -> f64 {
         self.timing_points
             .iter()
             .rev()
+            .filter(|tp| !tp.inherited)
             .find(|tp| tp.time <= time)
             .map(|tp| tp.bpm)
             .unwrap_or(120.0)
@@ -148,7 +218,7 @@ impl Beatmap {
         let mut last_time = 0.0;
         let mut last_bpm = 120.0;
 
-        for tp in &self.timing_points {
+        for tp in self.timing_points.iter().filter(|tp| !tp.inherited) {
             if tp.time > time {
                 break;
             }
@@ -167,7 +237,7 @@ impl Beatmap {
         let mut last_beat = 0.0;
         let mut last_bpm = 120.0;
 
-        for tp in &self.timing_points {
+        for tp in self.timing_points.iter().filter(|tp| !tp.inherited) {
             let tp_beat = last_beat + (tp.time - time) / (60.0 / last_bpm);
             if tp_beat > beat {
                 break;
@@ -187,6 +257,291 @@ impl Beatmap {
         self.beat_to_time(snapped_beat)
     }
 
+    /// Find the nearest tick at `divisor` for a time, measured from the governing
+    /// uninherited timing point. Returns the tick time and whether the time sat
+    /// exactly between two ticks.
+    pub fn nearest_tick(&self, time: f64, divisor: u32) -> (f64, bool) {
+        let red_points: Vec<&TimingPoint> =
+            self.timing_points.iter().filter(|tp| !tp.inherited).collect();
+        let index = red_points
+            .iter()
+            .rposition(|tp| tp.time <= time)
+            .unwrap_or(0);
+        let (origin, bpm) = red_points
+            .get(index)
+            .map(|tp| (tp.time, tp.bpm))
+            .unwrap_or((0.0, 120.0));
+
+        let tick_length = 60.0 / bpm / divisor.max(1) as f64;
+        let lower = origin + ((time - origin) / tick_length).floor() * tick_length;
+        let mut upper = lower + tick_length;
+
+        // The next timing point restarts the grid, so it is always a valid tick
+        if let Some(next) = red_points.get(index + 1) {
+            if upper > next.time {
+                upper = next.time;
+            }
+        }
+
+        let to_lower = time - lower;
+        let to_upper = upper - time;
+        if (to_lower - to_upper).abs() < TICK_AMBIGUITY_EPSILON {
+            (lower, true)
+        } else if to_lower < to_upper {
+            (lower, false)
+        } else {
+            (upper, false)
+        }
+    }
+
+    /// Slider velocity multiplier at a time: the latest inherited point since the
+    /// last uninherited point, or 1.0 (an uninherited point resets SV)
+    pub fn slider_velocity_at(&self, time: f64) -> f64 {
+        self.timing_points
+            .iter()
+            .rev()
+            .find(|tp| tp.time <= time)
+            .filter(|tp| tp.inherited)
+            .map(|tp| tp.slider_velocity)
+            .unwrap_or(1.0)
+    }
+
+    /// Pixels a slider travels per beat at a time (base multiplier × SV × per-slider velocity)
+    pub fn slider_pixels_per_beat(&self, time: f64, velocity: f64) -> f64 {
+        self.settings.slider_multiplier * 100.0 * self.slider_velocity_at(time) * velocity.max(0.01)
+    }
+
+    /// Duration of a single slider span in seconds
+    pub fn slider_span_duration(&self, time: f64, pixel_length: f64, velocity: f64) -> f64 {
+        pixel_length / self.slider_pixels_per_beat(time, velocity) * self.get_beat_length_at(time)
+    }
+
+    /// Pixel length a slider needs for a span to last `duration` seconds
+    pub fn slider_length_for_duration(&self, time: f64, duration: f64, velocity: f64) -> f64 {
+        duration / self.get_beat_length_at(time) * self.slider_pixels_per_beat(time, velocity)
+    }
+
+    /// Sort timing points by time, uninherited before inherited at the same time
+    pub fn sort_timing_points(&mut self) {
+        self.timing_points.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.inherited.cmp(&b.inherited))
+        });
+    }
+
+    /// Add an inherited point at a time, replacing one already there
+    pub fn set_slider_velocity_point(&mut self, time: f64, slider_velocity: f64) {
+        self.timing_points
+            .retain(|tp| !(tp.inherited && (tp.time - time).abs() < TICK_AMBIGUITY_EPSILON));
+        self.timing_points
+            .push(TimingPoint::inherited_at(time, slider_velocity));
+        self.sort_timing_points();
+    }
+
+    /// Time at which a hit object ends (its start time for circles)
+    pub fn object_end_time(&self, object: &HitObject) -> f64 {
+        match &object.kind {
+            HitObjectKind::Circle => object.time,
+            HitObjectKind::Slider {
+                repeats,
+                pixel_length,
+                velocity,
+                ..
+            } => {
+                object.time
+                    + self.slider_span_duration(object.time, *pixel_length, *velocity)
+                        * (*repeats as f64 + 1.0)
+            }
+            HitObjectKind::Spinner { end_time } => *end_time,
+        }
+    }
+
+    /// Times of a slider's body ticks, `slider_tick_rate` per beat from its start, ending
+    /// with the slider end. Empty for other objects
+    pub fn slider_tick_times(&self, object: &HitObject) -> Vec<f64> {
+        if !matches!(object.kind, HitObjectKind::Slider { .. }) {
+            return Vec::new();
+        }
+        let end = self.object_end_time(object);
+        let tick_rate = self
+            .settings
+            .slider_tick_rate
+            .clamp(0.1, MAX_SLIDER_TICK_RATE);
+        let interval = self.get_beat_length_at(object.time) / tick_rate;
+        // A tick right before the end would be scored twice in a row
+        let last_tick = end - interval * 0.1;
+        let mut ticks: Vec<f64> = (1..)
+            .map(|n| object.time + interval * n as f64)
+            .take_while(|&time| time < last_tick)
+            .collect();
+        ticks.push(end);
+        ticks
+    }
+
+    /// Index of the kiai section containing a time
+    pub fn kiai_at(&self, time: f64) -> Option<usize> {
+        self.kiai_sections.iter().position(|section| section.contains(time))
+    }
+
+    /// Add a kiai section, refusing ones that are empty or overlap another section
+    pub fn add_kiai_section(&mut self, section: KiaiSection) -> Result<(), String> {
+        if section.end <= section.start {
+            return Err("Kiai section must end after it starts".to_string());
+        }
+        if self.kiai_sections.iter().any(|other| other.overlaps(&section)) {
+            return Err("Kiai section overlaps an existing one".to_string());
+        }
+        self.kiai_sections.push(section);
+        self.kiai_sections
+            .sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(())
+    }
+
+    /// Index of the practice hint containing a time
+    pub fn practice_hint_at(&self, time: f64) -> Option<usize> {
+        self.practice_hints
+            .iter()
+            .position(|hint| hint.contains(time))
+    }
+
+    /// Add a practice hint, refusing empty ones. Overlaps and unusual rates are only
+    /// warned about (see `practice_hint_warnings`)
+    pub fn add_practice_hint(&mut self, hint: PracticeHint) -> Result<(), String> {
+        if hint.end <= hint.start {
+            return Err("Practice hint must end after it starts".to_string());
+        }
+        self.practice_hints.push(hint);
+        self.sort_practice_hints();
+        Ok(())
+    }
+
+    /// Keep practice hints in start time order
+    pub fn sort_practice_hints(&mut self) {
+        self.practice_hints
+            .sort_by(|a, b| a.start.total_cmp(&b.start));
+    }
+
+    /// Problems with the practice hints that don't stop the map from loading or
+    /// playing: overlapping sections and rates outside 0.25x-1.0x
+    pub fn practice_hint_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for (i, hint) in self.practice_hints.iter().enumerate() {
+            if !(MIN_PRACTICE_RATE..=MAX_PRACTICE_RATE).contains(&hint.rate) {
+                warnings.push(format!(
+                    "Practice hint \"{}\" suggests {:.2}x, outside {:.2}x-{:.2}x",
+                    hint.label, hint.rate, MIN_PRACTICE_RATE, MAX_PRACTICE_RATE
+                ));
+            }
+            for other in &self.practice_hints[i + 1..] {
+                if hint.overlaps(other) {
+                    warnings.push(format!(
+                        "Practice hints \"{}\" and \"{}\" overlap",
+                        hint.label, other.label
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Kiai sections from the kiai flags on timing points, as used by .osu files.
+    /// A section left open at the last point runs to the end of the map.
+    pub fn kiai_from_timing_points(&self) -> Vec<KiaiSection> {
+        let mut points: Vec<&TimingPoint> = self.timing_points.iter().collect();
+        points.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut sections = Vec::new();
+        let mut open: Option<f64> = None;
+        for point in points {
+            match (open, point.kiai) {
+                (None, true) => open = Some(point.time),
+                (Some(start), false) => {
+                    if point.time > start {
+                        sections.push(KiaiSection { start, end: point.time });
+                    }
+                    open = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = open {
+            let end = self.get_duration();
+            if end > start {
+                sections.push(KiaiSection { start, end });
+            }
+        }
+        sections
+    }
+
+    /// Check the hit objects for problems that would break playback or editing
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        for section in &self.kiai_sections {
+            if section.end <= section.start {
+                errors.push(format!(
+                    "Kiai section at {:.3}s ends before it starts",
+                    section.start
+                ));
+            }
+        }
+        for pair in self.kiai_sections.windows(2) {
+            if pair[0].overlaps(&pair[1]) {
+                errors.push(format!(
+                    "Kiai sections at {:.3}s and {:.3}s overlap",
+                    pair[0].start, pair[1].start
+                ));
+            }
+        }
+
+        for pair in self.hit_objects.windows(2) {
+            if pair[1].time < pair[0].time {
+                errors.push(format!(
+                    "Object {} at {:.3}s is before object {} at {:.3}s",
+                    pair[1].id, pair[1].time, pair[0].id, pair[0].time
+                ));
+            }
+        }
+
+        for object in &self.hit_objects {
+            if !object.time.is_finite() || object.time < 0.0 {
+                errors.push(format!("Object {} has invalid time {}", object.id, object.time));
+            }
+            match &object.kind {
+                HitObjectKind::Circle => {}
+                HitObjectKind::Slider {
+                    control_points,
+                    pixel_length,
+                    velocity,
+                    ..
+                } => {
+                    if control_points.len() < 2 {
+                        errors.push(format!("Slider {} needs at least 2 control points", object.id));
+                    }
+                    if !pixel_length.is_finite() || *pixel_length <= 0.0 {
+                        errors.push(format!("Slider {} has non-positive length", object.id));
+                    }
+                    if !velocity.is_finite() || *velocity <= 0.0 {
+                        errors.push(format!("Slider {} has non-positive velocity", object.id));
+                    }
+                }
+                HitObjectKind::Spinner { end_time } => {
+                    if !end_time.is_finite() || *end_time <= object.time {
+                        errors.push(format!("Spinner {} ends before it starts", object.id));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Get hit objects in a time range
     pub fn get_hit_objects_in_range(&self, start: f64, end: f64) -> Vec<&HitObject> {
         self.hit_objects
@@ -221,7 +576,7 @@ impl Beatmap {
 }
 
 /// Beatmap metadata information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BeatmapMetadata {
     /// Song title
     pub title: String,
@@ -262,8 +617,12 @@ pub struct TimingPoint {
     pub bpm: f64,
     /// Time signature numerator (beats per measure)
     pub meter: u32,
-    /// Whether this is an inherited (uninherited) timing point
+    /// Whether this is an inherited (green-line) point. Inherited points only set
+    /// `slider_velocity`; their `bpm` and `meter` are ignored.
     pub inherited: bool,
+    /// Slider velocity multiplier set by an inherited point (1.0 on uninherited points)
+    #[serde(default = "default_slider_velocity")]
+    pub slider_velocity: f64,
     /// Volume percentage (0-100)
     pub volume: u32,
     /// Kiai mode (special section with effects)
@@ -277,14 +636,53 @@ impl Default for TimingPoint {
             bpm: 120.0,
             meter: 4,
             inherited: false,
+            slider_velocity: 1.0,
             volume: 100,
             kiai: false,
         }
     }
 }
 
+fn default_slider_velocity() -> f64 {
+    1.0
+}
+
+impl TimingPoint {
+    /// Inherited point changing slider velocity at a time
+    pub fn inherited_at(time: f64, slider_velocity: f64) -> Self {
+        Self {
+            time,
+            inherited: true,
+            slider_velocity: slider_velocity.clamp(MIN_SLIDER_VELOCITY, MAX_SLIDER_VELOCITY),
+            ..Default::default()
+        }
+    }
+
+    /// Timing point from an .osu `[TimingPoints]` time, beat length and effects bits.
+    /// Negative beat lengths are green lines: SV = -100 / beat length. Effects bit 0 is kiai.
+    pub fn from_osu(time_ms: f64, beat_length: f64, meter: u32, uninherited: bool, effects: u32) -> Self {
+        let kiai = effects & 1 != 0;
+        let point = if uninherited && beat_length > 0.0 {
+            Self {
+                time: time_ms / 1000.0,
+                bpm: 60_000.0 / beat_length,
+                meter: meter.max(1),
+                ..Default::default()
+            }
+        } else {
+            let slider_velocity = if beat_length < 0.0 {
+                -100.0 / beat_length
+            } else {
+                1.0
+            };
+            Self::inherited_at(time_ms / 1000.0, slider_velocity)
+        };
+        Self { kiai, ..point }
+    }
+}
+
 /// A single hit object in the beatmap
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct HitObject {
     /// Unique identifier
     pub id: HitObjectId,
@@ -305,7 +703,7 @@ pub struct HitObject {
 }
 
 /// Type of hit object
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum HitObjectKind {
     /// Standard circle
     Circle,
@@ -327,8 +725,22 @@ pub enum HitObjectKind {
     },
 }
 
+impl HitObjectKind {
+    /// Where the cursor ends up after the object, given its start position
+    pub fn end_position(&self, start: Vec2) -> Vec2 {
+        match self {
+            HitObjectKind::Slider {
+                control_points,
+                repeats,
+                ..
+            } if repeats % 2 == 0 => control_points.last().copied().unwrap_or(start),
+            _ => start,
+        }
+    }
+}
+
 /// Hitsound types
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum Hitsound {
     #[default]
     Normal,
@@ -337,8 +749,20 @@ pub enum Hitsound {
     Clap,
 }
 
+impl Hitsound {
+    /// The hitsound after this one, wrapping back to Normal
+    pub fn next(&self) -> Hitsound {
+        match self {
+            Hitsound::Normal => Hitsound::Whistle,
+            Hitsound::Whistle => Hitsound::Finish,
+            Hitsound::Finish => Hitsound::Clap,
+            Hitsound::Clap => Hitsound::Normal,
+        }
+    }
+}
+
 /// Sample set for hitsounds
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SampleSet {
     pub normal_set: u32,
     pub addition_set: u32,
@@ -347,8 +771,18 @@ pub struct SampleSet {
     pub filename: Option<String>,
 }
 
+/// Name of an osu! sample bank number (0 follows the timing point)
+pub fn sample_bank_name(bank: u32) -> &'static str {
+    match bank {
+        1 => "normal",
+        2 => "soft",
+        3 => "drum",
+        _ => "auto",
+    }
+}
+
 /// Beatmap difficulty settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BeatmapSettings {
     /// Circle size (CS) - affects circle radius
     pub circle_size: f32,
@@ -381,6 +815,18 @@ impl Default for BeatmapSettings {
 }
 
 impl BeatmapSettings {
+    /// Settings as played with Hard Rock or Easy
+    pub fn with_modifiers(&self, game_settings: &GameSettings) -> Self {
+        Self {
+            circle_size: game_settings.scale_map_setting(self.circle_size, HARD_ROCK_CS_SCALE),
+            approach_rate: game_settings.scale_map_setting(self.approach_rate, HARD_ROCK_MAP_SCALE),
+            overall_difficulty: game_settings
+                .scale_map_setting(self.overall_difficulty, HARD_ROCK_MAP_SCALE),
+            hp_drain: game_settings.scale_map_setting(self.hp_drain, HARD_ROCK_MAP_SCALE),
+            ..self.clone()
+        }
+    }
+
     /// Get circle radius in pixels based on circle size
     pub fn get_circle_radius(&self) -> f32 {
         // CS 5 = 50px radius, lower CS = larger circles
@@ -416,6 +862,90 @@ pub struct TimingWindows {
     pub okay: f64,
 }
 
+/// Section of a beatmap where gameplay effects are intensified (chorus, drop)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KiaiSection {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+}
+
+impl KiaiSection {
+    /// Whether a time falls inside the section (the end is exclusive)
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.start && time < self.end
+    }
+
+    /// Whether two sections share any time; sections that only touch don't overlap
+    pub fn overlaps(&self, other: &KiaiSection) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Section a mapper suggests practicing at a slower playback rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PracticeHint {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// Suggested playback rate, e.g. 0.75
+    pub rate: f32,
+    pub label: String,
+}
+
+impl PracticeHint {
+    /// Whether a time falls inside the section (the end is exclusive)
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.start && time < self.end
+    }
+
+    /// Whether two hints share any time; hints that only touch don't overlap
+    pub fn overlaps(&self, other: &PracticeHint) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// Label, rate and range, e.g. "Stream 0.75x (32.00s - 48.50s)"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {:.2}x ({:.2}s - {:.2}s)",
+            self.label, self.rate, self.start, self.end
+        )
+    }
+}
+
+/// Kiai effect strength (0.0 - 1.0) at a time. Effects ramp in over `KIAI_RAMP`
+/// after a section starts and back out over `KIAI_RAMP` after it ends; sections
+/// that touch or overlap count as one so there is no dip at the seam.
+pub fn kiai_intensity(time: f64, sections: &[KiaiSection]) -> f32 {
+    let mut sorted = sections.to_vec();
+    sorted.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut merged: Vec<KiaiSection> = Vec::new();
+    for section in sorted {
+        match merged.last_mut() {
+            Some(last) if section.start <= last.end => last.end = last.end.max(section.end),
+            _ => merged.push(section),
+        }
+    }
+
+    merged
+        .iter()
+        .map(|section| {
+            if time < section.start {
+                0.0
+            } else if time < section.end {
+                ((time - section.start) / KIAI_RAMP).min(1.0)
+            } else {
+                // Fade out from whatever level the section reached
+                let peak = ((section.end - section.start) / KIAI_RAMP).min(1.0);
+                (peak * (1.0 - (time - section.end) / KIAI_RAMP)).max(0.0)
+            }
+        })
+        .fold(0.0, f64::max) as f32
+}
+
 /// Bookmark for quick navigation in editor
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bookmark {
@@ -433,6 +963,87 @@ pub struct ObjectStats {
     pub spinners: usize,
 }
 
+/// Time-sorted (time, id) index of a beatmap's objects. It lists them in the same
+/// order as `hit_objects`, so a position in one is the same object's position in the
+/// other, and finding where an object goes or the objects in a time window is a
+/// binary search
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HitObjectIndex {
+    entries: Vec<(f64, HitObjectId)>,
+    /// Start time of each object by id
+    times: HashMap<HitObjectId, f64>,
+    /// Longest object duration since the index was built. It never shrinks, so after
+    /// a long object goes window queries only start a little earlier than needed
+    longest: f64,
+}
+
+impl HitObjectIndex {
+    /// Index a beatmap's objects, which must be sorted by time
+    pub fn of(beatmap: &Beatmap) -> Self {
+        let objects = &beatmap.hit_objects;
+        Self {
+            entries: objects.iter().map(|obj| (obj.time, obj.id)).collect(),
+            times: objects.iter().map(|obj| (obj.id, obj.time)).collect(),
+            longest: objects
+                .iter()
+                .map(|obj| beatmap.object_end_time(obj) - obj.time)
+                .fold(0.0, f64::max),
+        }
+    }
+
+    /// Add an object after any others at the same time, returning its position
+    pub fn insert(&mut self, time: f64, id: HitObjectId, end_time: f64) -> usize {
+        let position = self.entries.partition_point(|(t, _)| *t <= time);
+        self.entries.insert(position, (time, id));
+        self.times.insert(id, time);
+        self.longest = self.longest.max(end_time - time);
+        position
+    }
+
+    /// Drop an object, returning the position it had
+    pub fn remove(&mut self, id: HitObjectId) -> Option<usize> {
+        let position = self.position(id)?;
+        self.entries.remove(position);
+        self.times.remove(&id);
+        Some(position)
+    }
+
+    /// Position of an object, searching only the objects at its time
+    pub fn position(&self, id: HitObjectId) -> Option<usize> {
+        let time = *self.times.get(&id)?;
+        let first = self.entries.partition_point(|(t, _)| *t < time);
+        self.entries[first..]
+            .iter()
+            .take_while(|(t, _)| *t == time)
+            .position(|(_, entry)| *entry == id)
+            .map(|offset| first + offset)
+    }
+
+    /// Positions of the objects starting between `start` and `end`, inclusive
+    pub fn starting_between(&self, start: f64, end: f64) -> Range<usize> {
+        let first = self.entries.partition_point(|(t, _)| *t < start);
+        let last = self.entries.partition_point(|(t, _)| *t <= end);
+        first..last.max(first)
+    }
+
+    /// Positions of the objects that may be in progress between `start` and `end`:
+    /// every object that is, and possibly a few that ended a little before `start`
+    pub fn overlapping(&self, start: f64, end: f64) -> Range<usize> {
+        self.starting_between(start - self.longest, end)
+    }
+
+    /// Whether the index lists exactly these objects, in this order
+    pub fn matches(&self, objects: &[HitObject]) -> bool {
+        self.entries.len() == objects.len()
+            && self.times.len() == objects.len()
+            && self
+                .entries
+                .iter()
+                .zip(objects)
+                .all(|(&(time, id), obj)| time == obj.time && id == obj.id)
+    }
+}
+
 /// Asset manager for beatmaps
 #[derive(Debug, Clone, Resource)]
 pub struct BeatmapAssets {
@@ -442,6 +1053,11 @@ pub struct BeatmapAssets {
     pub current_beatmap: Option<String>,
     /// Beatmaps directory
     pub beatmaps_dir: String,
+    /// Difficulty summaries keyed by beatmap content hash
+    pub difficulty_cache: HashMap<u64, DifficultySummary>,
+    /// Index of the current beatmap's objects; None after an edit that went around
+    /// it, until it is rebuilt
+    object_index: Option<HitObjectIndex>,
 }
 
 impl Default for BeatmapAssets {
@@ -450,6 +1066,8 @@ impl Default for BeatmapAssets {
             beatmaps: HashMap::new(),
             current_beatmap: None,
             beatmaps_dir: "src/assets/beatmaps".to_string(),
+            difficulty_cache: HashMap::new(),
+            object_index: None,
         }
     }
 }
@@ -461,12 +1079,15 @@ impl BeatmapAssets {
             beatmaps: HashMap::new(),
             current_beatmap: None,
             beatmaps_dir,
+            difficulty_cache: HashMap::new(),
+            object_index: None,
         }
     }
 
     /// Load all beatmaps from the beatmaps directory
     pub fn load_all(&mut self) -> Result<usize, String> {
         self.beatmaps.clear();
+        self.object_index = None;
 
         let path = Path::new(&self.beatmaps_dir);
         if !path.exists() {
@@ -482,6 +1103,7 @@ impl BeatmapAssets {
                 if path.extension().map(|e| e == "json").unwrap_or(false) {
                     let path_str = path.to_string_lossy().to_string();
                     if let Ok(beatmap) = Beatmap::load_from_file(&path_str) {
+                        self.cache_difficulty(&beatmap);
                         self.beatmaps.insert(path_str, beatmap);
                         count += 1;
                     }
@@ -492,6 +1114,20 @@ impl BeatmapAssets {
         Ok(count)
     }
 
+    /// Compute and cache a beatmap's difficulty unless its contents are already cached
+    pub fn cache_difficulty(&mut self, beatmap: &Beatmap) -> DifficultySummary {
+        *self
+            .difficulty_cache
+            .entry(content_hash(beatmap))
+            .or_insert_with(|| DifficultySummary::for_beatmap(beatmap))
+    }
+
+    /// Cached difficulty of a loaded beatmap
+    pub fn difficulty(&self, path: &str) -> Option<DifficultySummary> {
+        let beatmap = self.beatmaps.get(path)?;
+        self.difficulty_cache.get(&content_hash(beatmap)).copied()
+    }
+
     /// Get a beatmap by path
     pub fn get(&self, path: &str) -> Option<&Beatmap> {
         self.beatmaps.get(path)
@@ -499,6 +1135,9 @@ impl BeatmapAssets {
 
     /// Get mutable reference to a beatmap
     pub fn get_mut(&mut self, path: &str) -> Option<&mut Beatmap> {
+        if self.current_beatmap.as_deref() == Some(path) {
+            self.object_index = None;
+        }
         self.beatmaps.get_mut(path)
     }
 
@@ -509,23 +1148,70 @@ impl BeatmapAssets {
             .and_then(|p| self.beatmaps.get(p))
     }
 
-    /// Get mutable current beatmap
+    /// Get mutable current beatmap. Edits through it may reorder the objects, so the
+    /// object index is rebuilt afterwards
     pub fn current_mut(&mut self) -> Option<&mut Beatmap> {
+        self.object_index = None;
         self.current_beatmap
             .as_ref()
             .and_then(|p| self.beatmaps.get_mut(p))
     }
 
+    /// Mutable current beatmap with its object index, for edits that keep the index
+    /// up to date themselves
+    pub fn current_indexed_mut(&mut self) -> Option<(&mut Beatmap, &mut HitObjectIndex)> {
+        let beatmap = self
+            .current_beatmap
+            .as_ref()
+            .and_then(|p| self.beatmaps.get_mut(p))?;
+        let index = self
+            .object_index
+            .get_or_insert_with(|| HitObjectIndex::of(beatmap));
+        Some((beatmap, index))
+    }
+
+    /// Object index of the current beatmap, built on the spot if an edit went around it
+    pub fn object_index(&self) -> Option<Cow<'_, HitObjectIndex>> {
+        match &self.object_index {
+            Some(index) => Some(Cow::Borrowed(index)),
+            None => self
+                .current()
+                .map(|beatmap| Cow::Owned(HitObjectIndex::of(beatmap))),
+        }
+    }
+
+    /// Rebuild the object index if an edit went around it
+    pub fn refresh_object_index(&mut self) {
+        if self.object_index.is_none() {
+            let index = self.current().map(HitObjectIndex::of);
+            self.object_index = index;
+        }
+    }
+
     /// Set current beatmap
     pub fn set_current(&mut self, path: Option<String>) {
+        self.object_index = None;
         self.current_beatmap = path;
     }
 
     /// Add or update a beatmap
     pub fn add(&mut self, path: String, beatmap: Beatmap) {
+        self.cache_difficulty(&beatmap);
+        if self.current_beatmap.as_deref() == Some(path.as_str()) {
+            self.object_index = None;
+        }
         self.beatmaps.insert(path, beatmap);
     }
 
+    /// Drop a beatmap from the library, clearing it as current if it was
+    pub fn remove(&mut self, path: &str) -> Option<Beatmap> {
+        if self.current_beatmap.as_deref() == Some(path) {
+            self.current_beatmap = None;
+            self.object_index = None;
+        }
+        self.beatmaps.remove(path)
+    }
+
     /// Save a beatmap to file
     pub fn save(&self, path: &str) -> Result<(), String> {
         if let Some(beatmap) = self.beatmaps.get(path) {
@@ -536,6 +1222,80 @@ impl BeatmapAssets {
         }
     }
 
+    /// The other beatmaps in a beatmap's set, by path: those sharing its set id, or its
+    /// audio file when it has no set id
+    pub fn set_members(&self, path: &str) -> Vec<(&String, &Beatmap)> {
+        let Some(beatmap) = self.beatmaps.get(path) else {
+            return Vec::new();
+        };
+        let has_audio = Path::new(&beatmap.audio_path).is_file();
+        let mut members: Vec<(&String, &Beatmap)> = self
+            .beatmaps
+            .iter()
+            .filter(|(other_path, _)| other_path.as_str() != path)
+            .filter(|(_, other)| match beatmap.metadata.set_id {
+                Some(set_id) => other.metadata.set_id == Some(set_id),
+                None => {
+                    has_audio
+                        && other.metadata.set_id.is_none()
+                        && other.audio_path == beatmap.audio_path
+                }
+            })
+            .collect();
+        members.sort_by(|a, b| a.0.cmp(b.0));
+        members
+    }
+
+    /// Beatmaps made for a song file, matched by audio file name, in path order
+    fn beatmaps_for_song(&self, song_path: &str) -> Vec<&Beatmap> {
+        let file_name = |path: &str| {
+            Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+        };
+        let Some(song) = file_name(song_path) else {
+            return Vec::new();
+        };
+        let mut beatmaps: Vec<(&String, &Beatmap)> = self
+            .beatmaps
+            .iter()
+            .filter(|(_, beatmap)| file_name(&beatmap.audio_path).as_ref() == Some(&song))
+            .collect();
+        beatmaps.sort_by(|a, b| a.0.cmp(b.0));
+        beatmaps.into_iter().map(|(_, beatmap)| beatmap).collect()
+    }
+
+    /// Practice hints of the beatmaps made for a song file, in start time order
+    pub fn practice_hints_for_song(&self, song_path: &str) -> Vec<PracticeHint> {
+        let mut hints: Vec<PracticeHint> = Vec::new();
+        for beatmap in self.beatmaps_for_song(song_path) {
+            for hint in &beatmap.practice_hints {
+                // Difficulties of a set often share the same hints
+                if !hints.contains(hint) {
+                    hints.push(hint.clone());
+                }
+            }
+        }
+        hints.sort_by(|a, b| a.start.total_cmp(&b.start));
+        hints
+    }
+
+    /// Preview point set by the first beatmap made for a song file that has one
+    pub fn preview_time_for_song(&self, song_path: &str) -> Option<f64> {
+        self.beatmaps_for_song(song_path)
+            .into_iter()
+            .map(|beatmap| beatmap.preview_time)
+            .find(|time| *time > 0.0)
+    }
+
+    /// Difficulty names used by the other beatmaps in a beatmap's set
+    pub fn sibling_versions(&self, path: &str) -> Vec<String> {
+        self.set_members(path)
+            .into_iter()
+            .map(|(_, other)| other.metadata.version.clone())
+            .collect()
+    }
+
     /// Get all beatmap paths
     pub fn get_all_paths(&self) -> Vec<&String> {
         self.beatmaps.keys().collect()
@@ -557,7 +1317,7 @@ impl BeatmapAssets {
 
     /// Get beatmaps sorted by creation time (newest first)
     pub fn get_recent(&self, limit: usize) -> Vec<&Beatmap> {
-        let mut beatmaps: Vec<_> = self.beatmaps.values().collect();
+        let beatmaps: Vec<_> = self.beatmaps.values().collect();
         // For now, just return in arbitrary order
         // In production, would sort by file modification time
         beatmaps.into_iter().take(limit).collect()
@@ -598,6 +1358,8 @@ pub enum EditorTool {
     Slider,
     Spinner,
     Delete,
+    /// Picks up one object's hitsound and stamps it onto others
+    HitsoundCopier,
 }
 
 impl EditorTool {
@@ -608,6 +1370,7 @@ impl EditorTool {
             EditorTool::Slider => "Slider",
             EditorTool::Spinner => "Spinner",
             EditorTool::Delete => "Delete",
+            EditorTool::HitsoundCopier => "Hitsound",
         }
     }
 
@@ -618,16 +1381,18 @@ impl EditorTool {
             EditorTool::Slider,
             EditorTool::Spinner,
             EditorTool::Delete,
+            EditorTool::HitsoundCopier,
         ]
     }
 }
 
 /// Beat snap divisors
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum BeatDivisor {
     One = 1,
     Two = 2,
     Three = 3,
+    #[default]
     Four = 4,
     Six = 6,
     Eight = 8,
@@ -658,8 +1423,345 @@ impl BeatDivisor {
     }
 }
 
-impl Default for BeatDivisor {
-    fn default() -> Self {
-        BeatDivisor::Four
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn red(time: f64, bpm: f64) -> TimingPoint {
+        TimingPoint {
+            time,
+            bpm,
+            ..Default::default()
+        }
+    }
+
+    fn green(time: f64) -> TimingPoint {
+        TimingPoint {
+            time,
+            bpm: 999.0,
+            inherited: true,
+            slider_velocity: 2.0,
+            ..Default::default()
+        }
+    }
+
+    fn beatmap(timing_points: Vec<TimingPoint>) -> Beatmap {
+        Beatmap {
+            timing_points,
+            ..Default::default()
+        }
+    }
+
+    fn assert_tick(actual: (f64, bool), expected: (f64, bool)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && actual.1 == expected.1,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn nearest_tick_rounds_to_the_closer_tick() {
+        // 120 BPM: a beat every 0.5s
+        let map = beatmap(vec![red(0.0, 120.0)]);
+        assert_tick(map.nearest_tick(0.74, 1), (0.5, false));
+        assert_tick(map.nearest_tick(0.76, 1), (1.0, false));
+        assert_tick(map.nearest_tick(1.0, 1), (1.0, false));
+        assert_tick(map.nearest_tick(0.13, 4), (0.125, false));
+    }
+
+    #[test]
+    fn nearest_tick_flags_times_halfway_between_ticks() {
+        let map = beatmap(vec![red(0.0, 120.0)]);
+        assert_tick(map.nearest_tick(0.75, 1), (0.5, true));
+        // Within the ambiguity epsilon still counts as halfway
+        assert_tick(map.nearest_tick(0.7502, 1), (0.5, true));
+        // 1/16 at 120 BPM is 31.25ms, so 15.625ms sits between two ticks
+        assert_tick(map.nearest_tick(0.015625, 16), (0.0, true));
+    }
+
+    #[test]
+    fn nearest_tick_treats_divisor_zero_as_whole_beats() {
+        let map = beatmap(vec![red(0.0, 120.0)]);
+        assert_eq!(map.nearest_tick(0.6, 0), map.nearest_tick(0.6, 1));
+    }
+
+    #[test]
+    fn nearest_tick_follows_the_governing_timing_point() {
+        // 150 BPM from 10.2s: a beat every 0.4s measured from there
+        let map = beatmap(vec![red(0.0, 120.0), red(10.2, 150.0)]);
+        assert_tick(map.nearest_tick(10.2, 1), (10.2, false));
+        assert_tick(map.nearest_tick(10.55, 1), (10.6, false));
+        assert_tick(map.nearest_tick(10.75, 2), (10.8, false));
+    }
+
+    #[test]
+    fn nearest_tick_snaps_to_the_next_timing_point() {
+        // The 120 BPM grid's next tick after 10.0 would be 10.5, but the grid restarts
+        // at 10.2, which is closer
+        let map = beatmap(vec![red(0.0, 120.0), red(10.2, 150.0)]);
+        assert_tick(map.nearest_tick(10.15, 1), (10.2, false));
+        assert_tick(map.nearest_tick(10.05, 1), (10.0, false));
+    }
+
+    #[test]
+    fn nearest_tick_ignores_inherited_points() {
+        let plain = beatmap(vec![red(0.0, 120.0)]);
+        let with_green = beatmap(vec![red(0.0, 120.0), green(0.3)]);
+        for time in [0.1, 0.3, 0.6, 0.9] {
+            let expected = plain.nearest_tick(time, 2);
+            assert_eq!(with_green.nearest_tick(time, 2), expected);
+        }
+    }
+
+    #[test]
+    fn nearest_tick_extends_the_first_grid_backwards() {
+        let map = beatmap(vec![red(1.0, 120.0)]);
+        assert_tick(map.nearest_tick(0.3, 1), (0.5, false));
+        assert_tick(map.nearest_tick(0.1, 1), (0.0, false));
+    }
+
+    fn kiai(start: f64, end: f64) -> KiaiSection {
+        KiaiSection { start, end }
+    }
+
+    fn assert_intensity(time: f64, sections: &[KiaiSection], expected: f32) {
+        let actual = kiai_intensity(time, sections);
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "at {}s expected {}, got {}",
+            time,
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn kiai_ramps_in_and_fades_out() {
+        let sections = [kiai(10.0, 20.0)];
+        assert_intensity(9.99, &sections, 0.0);
+        assert_intensity(10.0, &sections, 0.0);
+        assert_intensity(10.25, &sections, 0.5);
+        assert_intensity(15.0, &sections, 1.0);
+        assert_intensity(20.0, &sections, 1.0);
+        assert_intensity(20.25, &sections, 0.5);
+        assert_intensity(20.5, &sections, 0.0);
+        assert_intensity(30.0, &[], 0.0);
+    }
+
+    #[test]
+    fn touching_and_overlapping_kiai_sections_have_no_dip_at_the_seam() {
+        let touching = [kiai(14.0, 20.0), kiai(10.0, 14.0)];
+        let overlapping = [kiai(10.0, 16.0), kiai(12.0, 20.0)];
+        for sections in [&touching[..], &overlapping[..]] {
+            for time in [13.99, 14.0, 14.01, 16.0, 19.99] {
+                assert_intensity(time, sections, 1.0);
+            }
+            assert_intensity(20.25, sections, 0.5);
+        }
+    }
+
+    #[test]
+    fn a_short_kiai_fades_from_the_level_it_reached() {
+        let sections = [kiai(10.0, 10.25)];
+        assert_intensity(10.25, &sections, 0.5);
+        assert_intensity(10.5, &sections, 0.25);
+        assert_intensity(10.75, &sections, 0.0);
+
+        // A section starting inside another's fade counts the stronger of the two
+        let close = [kiai(10.0, 12.0), kiai(12.25, 14.0)];
+        assert_intensity(12.25, &close, 0.5);
+        assert_intensity(12.375, &close, 0.25);
+        assert_intensity(12.5, &close, 0.5);
+    }
+
+    #[test]
+    fn kiai_sections_come_from_the_timing_point_flags() {
+        let kiai_at = |time: f64, kiai: bool| TimingPoint {
+            kiai,
+            ..green(time)
+        };
+        let mut map = beatmap(vec![
+            red(0.0, 120.0),
+            kiai_at(10.0, true),
+            kiai_at(12.0, true),
+            kiai_at(20.0, false),
+            kiai_at(30.0, true),
+        ]);
+        map.hit_objects.push(HitObject {
+            id: 1,
+            time: 40.0,
+            position: Vec2::ZERO,
+            kind: HitObjectKind::Circle,
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Normal,
+            sample_set: None,
+        });
+        let sections = map.kiai_from_timing_points();
+        assert_eq!(sections[0], kiai(10.0, 20.0));
+        assert_eq!(sections[1].start, 30.0);
+        assert_eq!(sections.len(), 2);
+    }
+
+    fn hint(start: f64, end: f64, rate: f32, label: &str) -> PracticeHint {
+        PracticeHint {
+            start,
+            end,
+            rate,
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn practice_hints_round_trip_through_the_file() {
+        let mut map = beatmap(vec![red(0.0, 120.0)]);
+        map.add_practice_hint(hint(40.0, 48.5, 0.75, "Stream"))
+            .unwrap();
+        map.add_practice_hint(hint(10.0, 20.0, 0.5, "Jumps"))
+            .unwrap();
+        assert_eq!(map.practice_hints[0].label, "Jumps");
+
+        let path = scratch_dir("beatmap-hints").join("song.json");
+        let path = path.to_string_lossy().to_string();
+        map.save_to_file(&path).unwrap();
+        let loaded = Beatmap::load_from_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.practice_hints, map.practice_hints);
+        assert_eq!(loaded.practice_hint_at(45.0), Some(1));
+        assert_eq!(loaded.practice_hint_at(48.5), None);
+
+        // Maps saved before hints existed load without any
+        let mut json = serde_json::to_value(&map).unwrap();
+        json.as_object_mut().unwrap().remove("practice_hints");
+        let old: Beatmap = serde_json::from_value(json).unwrap();
+        assert!(old.practice_hints.is_empty());
+    }
+
+    #[test]
+    fn practice_hints_warn_about_overlaps_and_odd_rates() {
+        let mut map = beatmap(Vec::new());
+        assert_eq!(
+            map.add_practice_hint(hint(5.0, 5.0, 0.75, "Empty")),
+            Err("Practice hint must end after it starts".to_string())
+        );
+        map.add_practice_hint(hint(0.0, 10.0, 0.75, "Intro"))
+            .unwrap();
+        map.add_practice_hint(hint(10.0, 20.0, 0.25, "Touching"))
+            .unwrap();
+        assert!(map.practice_hint_warnings().is_empty());
+
+        map.add_practice_hint(hint(15.0, 25.0, 1.5, "Fast"))
+            .unwrap();
+        assert_eq!(
+            map.practice_hint_warnings(),
+            [
+                "Practice hints \"Touching\" and \"Fast\" overlap",
+                "Practice hint \"Fast\" suggests 1.50x, outside 0.25x-1.00x"
+            ]
+        );
+    }
+
+    /// A circle, or a spinner when `length` is positive
+    fn object(id: HitObjectId, time: f64, length: f64) -> HitObject {
+        HitObject {
+            id,
+            time,
+            position: Vec2::ZERO,
+            kind: if length > 0.0 {
+                HitObjectKind::Spinner {
+                    end_time: time + length,
+                }
+            } else {
+                HitObjectKind::Circle
+            },
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Normal,
+            sample_set: None,
+        }
+    }
+
+    #[test]
+    fn the_object_index_matches_a_sorted_copy_after_random_edits() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut map = beatmap(vec![red(0.0, 120.0)]);
+            let mut index = HitObjectIndex::of(&map);
+            let mut next_id = 1;
+            for _ in 0..300 {
+                if map.hit_objects.is_empty() || rng.gen_bool(0.6) {
+                    // Quarter second steps so plenty of objects share a time
+                    let time = rng.gen_range(0..80) as f64 * 0.25;
+                    let length = if rng.gen_bool(0.1) {
+                        rng.gen_range(1.0..4.0)
+                    } else {
+                        0.0
+                    };
+                    map.insert_indexed(&mut index, object(next_id, time, length));
+                    next_id += 1;
+                } else {
+                    let victim = map.hit_objects[rng.gen_range(0..map.hit_objects.len())].id;
+                    assert_eq!(
+                        map.remove_indexed(&mut index, victim).map(|obj| obj.id),
+                        Some(victim)
+                    );
+                    assert_eq!(map.remove_indexed(&mut index, victim), None);
+                }
+
+                let mut sorted = map.hit_objects.clone();
+                sorted.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+                assert_eq!(sorted, map.hit_objects, "seed {}", seed);
+                assert!(index.matches(&sorted), "seed {}", seed);
+                for (position, obj) in map.hit_objects.iter().enumerate() {
+                    assert_eq!(index.position(obj.id), Some(position));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn index_windows_cover_every_object_in_progress() {
+        let mut map = beatmap(vec![red(0.0, 120.0)]);
+        for (id, (time, length)) in [
+            (1.0, 0.0),
+            (2.0, 6.0),
+            (3.0, 0.0),
+            (3.0, 0.0),
+            (9.0, 0.0),
+            (12.0, 1.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            map.add_hit_object(object(id as HitObjectId, time, length));
+        }
+        let index = HitObjectIndex::of(&map);
+        let ids = |range: Range<usize>| -> Vec<HitObjectId> {
+            map.hit_objects[range].iter().map(|obj| obj.id).collect()
+        };
+
+        assert_eq!(ids(index.starting_between(3.0, 9.0)), [2, 3, 4]);
+        assert_eq!(
+            ids(index.starting_between(4.0, 8.0)),
+            Vec::<HitObjectId>::new()
+        );
+        assert_eq!(index.starting_between(20.0, 10.0), 6..6);
+        // The spinner from 2 to 8 is still going at 7
+        let overlapping = ids(index.overlapping(7.0, 10.0));
+        assert!(overlapping.contains(&1) && overlapping.contains(&4));
+        for start in [0.0, 2.5, 5.0, 7.5, 10.0, 12.5] {
+            let range = index.overlapping(start, start + 1.0);
+            for (position, obj) in map.hit_objects.iter().enumerate() {
+                if map.object_end_time(obj) >= start && obj.time <= start + 1.0 {
+                    assert!(range.contains(&position), "{} missing at {}", obj.id, start);
+                }
+            }
+        }
     }
 }
@@ -5,15 +5,10 @@ use anyhow::Result;
 use std::sync::Arc;
 use tokio::signal;
 
-mod network;
-mod accounts;
-mod multiplayer;
-mod community;
-
-use network::GameServer;
-use accounts::AccountManager;
-use multiplayer::GameCoordinator;
-use community::CommunityManager;
+use yum_osu::network::GameServer;
+use yum_osu::accounts::{AccountManager, MAINTENANCE_INTERVAL};
+use yum_osu::multiplayer::GameCoordinator;
+use yum_osu::community::CommunityManager;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -24,7 +19,9 @@ async fn main() -> Result<()> {
     println!("Initializing managers...");
     let account_manager = Arc::new(AccountManager::new(std::path::PathBuf::from("data")));
     let game_coordinator = Arc::new(GameCoordinator::new());
-    let community_manager = Arc::new(CommunityManager::new());
+    let community_manager = Arc::new(
+        CommunityManager::new().with_chat_archive(std::path::PathBuf::from("data").join("chat")),
+    );
 
     // Load existing data
     println!("Loading data...");
@@ -33,9 +30,52 @@ async fn main() -> Result<()> {
         println!("Starting with fresh state...");
     }
 
+    // Clean up stale sessions and dangling friend entries every hour, off the
+    // async workers since it writes to disk
+    let maintenance_handle = tokio::spawn({
+        let account_manager = account_manager.clone();
+        async move {
+            let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+            // The first tick fires at once, and load_data has just cleaned up
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let account_manager = account_manager.clone();
+                match tokio::task::spawn_blocking(move || account_manager.run_maintenance(None))
+                    .await
+                {
+                    Ok(Ok(report)) if !report.is_empty() => println!(
+                        "Cleaned up {} sessions, {} friends, {} friend requests",
+                        report.sessions_removed, report.friends_removed, report.requests_removed
+                    ),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => eprintln!("Maintenance error: {}", e),
+                    Err(e) => eprintln!("Maintenance task failed: {}", e),
+                }
+            }
+        }
+    });
+
+    // Award walkovers for tournament matches nobody turned up to
+    let forfeit_handle = tokio::spawn({
+        let community_manager = community_manager.clone();
+        async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                let updates = community_manager.resolve_forfeits(chrono::Utc::now()).await;
+                if !updates.is_empty() {
+                    println!("Advanced {} tournament brackets after forfeits", updates.len());
+                }
+            }
+        }
+    });
+
     // Create game server
     println!("Starting game server...");
-    let game_server = GameServer::new();
+    let game_server = GameServer::new()
+        .with_coordinator(game_coordinator.clone())
+        .with_community(community_manager.clone());
 
     // Server address
     let addr = "0.0.0.0:8080";
@@ -59,8 +99,15 @@ async fn main() -> Result<()> {
     }
 
     // Save data before shutdown
+    maintenance_handle.abort();
+    forfeit_handle.abort();
     println!("Saving data...");
-    let _ = account_manager.load_data();
+    if let Err(e) = account_manager.save_data() {
+        eprintln!("Could not save data: {}", e);
+    }
+    let mut storage = account_manager.storage_stats();
+    storage.files.extend(community_manager.chat_storage());
+    println!("{}", storage.summary());
 
     println!("Goodbye! 👋");
     Ok(())
@@ -0,0 +1,493 @@
+//! Offline bot opponents for practicing the multiplayer format alone.
+//!
+//! A bot's whole run is decided up front by a pure function of the seed, its
+//! profile and the beat times, so a match always plays out the same way. During
+//! the match the judgments are released as the song clock reaches them, as the
+//! same hit and miss events a real client sends.
+
+use crate::analytics::HitStats;
+use crate::multiplayer::GameEvent;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Tag shown before a bot's name on scoreboards and results
+pub const BOT_TAG: &str = "[BOT]";
+
+/// Width of the window local note density is measured over (seconds)
+pub const DENSITY_WINDOW: f64 = 1.0;
+
+/// Notes per second a bot handles without extra misses
+pub const COMFORT_DENSITY: f64 = 3.0;
+
+/// Extra miss chance per note per second above the comfort density, relative to the base chance
+pub const DENSITY_MISS_FACTOR: f64 = 0.5;
+
+/// Highest miss chance of any single note
+pub const MAX_MISS_CHANCE: f64 = 0.95;
+
+/// Seconds after a beat a miss is reported, when a client's hit window closes
+pub const MISS_DELAY: f64 = 0.5;
+
+/// Share of non-perfect hits that are 100s rather than 50s
+pub const GOOD_HIT_SHARE: f64 = 0.8;
+
+/// Accuracy an adaptive bot aims for when the player has no recent sessions
+pub const ADAPTIVE_FALLBACK_ACCURACY: f64 = 90.0;
+
+/// Most bots a local room takes besides the player
+pub const MAX_ROOM_BOTS: usize = 3;
+
+/// Accuracy value of a non-perfect hit on average (100s and 50s as a share of 300)
+const OFF_HIT_VALUE: f64 = GOOD_HIT_SHARE / 3.0 + (1.0 - GOOD_HIT_SHARE) / 6.0;
+
+/// How well a bot plays
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BotProfile {
+    Easy,
+    Medium,
+    Hard,
+    /// Plays around the player's recent accuracy
+    Adaptive { target_accuracy: f64 },
+}
+
+impl BotProfile {
+    /// Profiles offered when adding a bot, the adaptive one tuned to the player's
+    /// recent session accuracies
+    pub fn choices(recent_accuracies: &[f32]) -> Vec<BotProfile> {
+        vec![
+            BotProfile::Easy,
+            BotProfile::Medium,
+            BotProfile::Hard,
+            BotProfile::adaptive(recent_accuracies),
+        ]
+    }
+
+    /// Adaptive profile aiming for the mean of recent session accuracies
+    pub fn adaptive(recent_accuracies: &[f32]) -> BotProfile {
+        let target_accuracy = if recent_accuracies.is_empty() {
+            ADAPTIVE_FALLBACK_ACCURACY
+        } else {
+            recent_accuracies.iter().map(|a| *a as f64).sum::<f64>() / recent_accuracies.len() as f64
+        };
+        BotProfile::Adaptive { target_accuracy }
+    }
+
+    /// Get display name for the profile
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            BotProfile::Easy => "Easy",
+            BotProfile::Medium => "Medium",
+            BotProfile::Hard => "Hard",
+            BotProfile::Adaptive { .. } => "Adaptive",
+        }
+    }
+
+    /// Accuracy the bot aims for on average (percent)
+    pub fn target_accuracy(&self) -> f64 {
+        match self {
+            BotProfile::Easy => 80.0,
+            BotProfile::Medium => 90.0,
+            BotProfile::Hard => 96.0,
+            BotProfile::Adaptive { target_accuracy } => target_accuracy.clamp(50.0, 99.0),
+        }
+    }
+
+    /// Skill parameters derived from the target accuracy
+    pub fn skill(&self) -> BotSkill {
+        let target_accuracy = self.target_accuracy();
+        let shortfall = (100.0 - target_accuracy) / 100.0;
+        BotSkill {
+            target_accuracy,
+            accuracy_spread: shortfall * 15.0,
+            base_miss_chance: shortfall * 0.3,
+            reaction_jitter: (0.02 + shortfall * 0.3).min(0.09),
+        }
+    }
+}
+
+/// Parameters of a bot's play
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BotSkill {
+    /// Mean accuracy across matches (percent)
+    pub target_accuracy: f64,
+    /// Standard deviation of a single match's accuracy around the target
+    pub accuracy_spread: f64,
+    /// Miss chance of a note at or below the comfort density
+    pub base_miss_chance: f64,
+    /// Largest timing offset of a perfect hit (seconds)
+    pub reaction_jitter: f64,
+}
+
+impl BotSkill {
+    /// Miss chance of a note at a local density (notes per second)
+    pub fn miss_chance(&self, density: f64) -> f64 {
+        let overload = (density - COMFORT_DENSITY).max(0.0);
+        (self.base_miss_chance * (1.0 + DENSITY_MISS_FACTOR * overload)).clamp(0.0, MAX_MISS_CHANCE)
+    }
+}
+
+/// One judged note of a bot's run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BotJudgment {
+    /// Index of the note in the beat timeline
+    pub circle_id: u32,
+    /// Song time the judgment is reported at
+    pub time: f64,
+    /// 300, 100 or 50, or 0 for a miss
+    pub score: u16,
+}
+
+/// Notes per second within half a density window of `time`; `beat_times` must be sorted
+pub fn local_density(beat_times: &[f64], time: f64) -> f64 {
+    let start = beat_times.partition_point(|t| *t < time - DENSITY_WINDOW / 2.0);
+    let end = beat_times.partition_point(|t| *t <= time + DENSITY_WINDOW / 2.0);
+    (end - start) as f64 / DENSITY_WINDOW
+}
+
+/// Decide a bot's judgment for every beat, sorted by report time.
+///
+/// The match's accuracy is drawn around the profile target, misses land where the
+/// notes are densest, and the share of perfect hits is set so the expected accuracy
+/// of the run equals the drawn one.
+pub fn simulate_run(seed: u64, profile: BotProfile, beat_times: &[f64]) -> Vec<BotJudgment> {
+    if beat_times.is_empty() {
+        return Vec::new();
+    }
+    let skill = profile.skill();
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let form = skill.target_accuracy + standard_normal(&mut rng) * skill.accuracy_spread;
+    let accuracy = form.clamp(0.0, 100.0) / 100.0;
+
+    let miss_chances: Vec<f64> = beat_times
+        .iter()
+        .map(|time| skill.miss_chance(local_density(beat_times, *time)))
+        .collect();
+    let hit_share = 1.0 - miss_chances.iter().sum::<f64>() / beat_times.len() as f64;
+    let hit_value = if hit_share > 0.0 { accuracy / hit_share } else { 0.0 };
+    let perfect_chance = ((hit_value - OFF_HIT_VALUE) / (1.0 - OFF_HIT_VALUE)).clamp(0.0, 1.0);
+
+    let mut judgments: Vec<BotJudgment> = beat_times
+        .iter()
+        .zip(miss_chances)
+        .enumerate()
+        .map(|(index, (time, miss_chance))| {
+            let sign = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+            let (score, offset) = if rng.gen_bool(miss_chance) {
+                (0, MISS_DELAY)
+            } else if rng.gen_bool(perfect_chance) {
+                (300, sign * rng.gen_range(0.0..=skill.reaction_jitter))
+            } else if rng.gen_bool(GOOD_HIT_SHARE) {
+                (100, sign * rng.gen_range(0.1..0.3))
+            } else {
+                (50, sign * rng.gen_range(0.3..MISS_DELAY))
+            };
+            BotJudgment {
+                circle_id: index as u32,
+                time: time + offset,
+                score,
+            }
+        })
+        .collect();
+    judgments.sort_by(|a, b| a.time.total_cmp(&b.time));
+    judgments
+}
+
+/// Accuracy percentage of a set of judgments, scored like a real player's hits
+pub fn run_accuracy(judgments: &[BotJudgment]) -> f64 {
+    if judgments.is_empty() {
+        return 100.0;
+    }
+    let points: f64 = judgments.iter().map(|j| j.score as f64).sum();
+    points / (judgments.len() as f64 * 300.0) * 100.0
+}
+
+/// Standard normal sample (Box-Muller)
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// A bot playing one match
+#[derive(Debug, Clone)]
+pub struct BotPlayer {
+    pub user_id: Uuid,
+    pub profile: BotProfile,
+    judgments: Vec<BotJudgment>,
+    released: usize,
+    finished: bool,
+}
+
+impl BotPlayer {
+    /// Plan the bot's run; the bot's id is mixed into the match seed so bots with the
+    /// same profile still play differently
+    pub fn new(user_id: Uuid, profile: BotProfile, seed: u64, beat_times: &[f64]) -> Self {
+        let seed = seed ^ user_id.as_u128() as u64;
+        Self {
+            user_id,
+            profile,
+            judgments: simulate_run(seed, profile, beat_times),
+            released: 0,
+            finished: false,
+        }
+    }
+
+    /// Hit and miss events the song clock has reached since the last call, followed by
+    /// the finish event once every note is judged
+    pub fn events_until(&mut self, now: f64) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        while let Some(judgment) = self.judgments.get(self.released) {
+            if judgment.time > now {
+                break;
+            }
+            events.push(match judgment.score {
+                0 => GameEvent::Miss {
+                    player_id: self.user_id,
+                    circle_id: judgment.circle_id,
+                    timestamp: judgment.time,
+                },
+                score => GameEvent::Hit {
+                    player_id: self.user_id,
+                    circle_id: judgment.circle_id,
+                    score,
+                    timestamp: judgment.time,
+                },
+            });
+            self.released += 1;
+        }
+
+        if !self.finished && self.released == self.judgments.len() {
+            self.finished = true;
+            events.push(GameEvent::GameFinished {
+                player_id: self.user_id,
+                final_score: self.final_score(),
+                final_accuracy: run_accuracy(&self.judgments),
+                timestamp: now,
+            });
+        }
+        events
+    }
+
+    /// Score of the whole run
+    pub fn final_score(&self) -> u32 {
+        self.judgments.iter().map(|j| j.score as u32).sum()
+    }
+
+    /// Whether the finish event was sent
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// A bot in a local room, playing the same map alongside the player
+#[derive(Debug, Clone)]
+pub struct RoomBot {
+    pub player: BotPlayer,
+    /// Score so far, scaled by the play's modifiers like the player's
+    pub score: i64,
+    /// Judgments so far
+    pub hits: HitStats,
+    score_multiplier: f32,
+}
+
+impl RoomBot {
+    /// Bot in room slot `slot`; the slot picks the bot's id, so each seat plays its
+    /// own run of the same seed
+    pub fn new(
+        slot: usize,
+        profile: BotProfile,
+        seed: u64,
+        hit_times: &[f64],
+        score_multiplier: f32,
+    ) -> Self {
+        let user_id = Uuid::from_u128(slot as u128 + 1);
+        Self {
+            player: BotPlayer::new(user_id, profile, seed, hit_times),
+            score: 0,
+            hits: HitStats::new(),
+            score_multiplier,
+        }
+    }
+
+    /// Name shown on the scoreboard and results, "[BOT] Hard"
+    pub fn name(&self) -> String {
+        format!("{} {}", BOT_TAG, self.player.profile.display_name())
+    }
+
+    /// Count the judgments the song clock has reached
+    pub fn advance(&mut self, now: f64) {
+        for event in self.player.events_until(now) {
+            match event {
+                GameEvent::Hit { score, .. } => {
+                    self.score += (score as f32 * self.score_multiplier) as i64;
+                    match score {
+                        300 => self.hits.perfect += 1,
+                        100 => self.hits.good += 1,
+                        _ => self.hits.okay += 1,
+                    }
+                }
+                GameEvent::Miss { .. } => self.hits.misses += 1,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// One row of a local room's ranking
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub name: String,
+    pub score: i64,
+    pub accuracy: f32,
+    pub bot: bool,
+}
+
+/// The player and the room's bots ranked by score, then accuracy
+pub fn room_standings(player: Standing, bots: &[RoomBot]) -> Vec<Standing> {
+    let mut standings = vec![player];
+    standings.extend(bots.iter().map(|bot| Standing {
+        name: bot.name(),
+        score: bot.score,
+        accuracy: bot.hits.accuracy(),
+        bot: true,
+    }));
+    standings.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.accuracy.total_cmp(&a.accuracy))
+    });
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A three minute song at 2.5 notes per second with a dense stream in the middle
+    fn beat_times() -> Vec<f64> {
+        let mut times: Vec<f64> = (0..450).map(|i| 1.0 + i as f64 * 0.4).collect();
+        times.extend((0..80).map(|i| 60.0 + i as f64 * 0.125));
+        times.sort_by(f64::total_cmp);
+        times
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_run() {
+        let times = beat_times();
+        let run = simulate_run(7, BotProfile::Medium, &times);
+        assert_eq!(run, simulate_run(7, BotProfile::Medium, &times));
+        assert_ne!(run, simulate_run(8, BotProfile::Medium, &times));
+        assert_eq!(run.len(), times.len());
+        assert!(run.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+
+    #[test]
+    fn final_accuracy_lands_near_the_profile_target() {
+        let times = beat_times();
+        let profiles = [
+            BotProfile::Easy,
+            BotProfile::Medium,
+            BotProfile::Hard,
+            BotProfile::adaptive(&[70.0, 74.0]),
+        ];
+        for profile in profiles {
+            let runs = 200;
+            let mean = (0..runs)
+                .map(|seed| run_accuracy(&simulate_run(seed, profile, &times)))
+                .sum::<f64>()
+                / runs as f64;
+            let target = profile.target_accuracy();
+            assert!(
+                (mean - target).abs() < 2.0,
+                "{} averaged {mean:.2}% against {target}%",
+                profile.display_name()
+            );
+        }
+    }
+
+    #[test]
+    fn misses_gather_where_notes_are_dense() {
+        let times = beat_times();
+        let (mut stream, mut stream_misses, mut misses) = (0, 0, 0);
+        for seed in 0..50 {
+            for judgment in simulate_run(seed, BotProfile::Easy, &times) {
+                let time = times[judgment.circle_id as usize];
+                let in_stream = (60.0..70.0).contains(&time);
+                stream += in_stream as u32;
+                if judgment.score == 0 {
+                    misses += 1;
+                    stream_misses += in_stream as u32;
+                }
+            }
+        }
+        let stream_rate = stream_misses as f64 / stream as f64;
+        let overall_rate = misses as f64 / (50 * times.len()) as f64;
+        assert!(stream_rate > overall_rate * 1.5);
+    }
+
+    #[test]
+    fn adaptive_bots_follow_recent_accuracy() {
+        assert_eq!(BotProfile::adaptive(&[80.0, 90.0]).target_accuracy(), 85.0);
+        assert_eq!(
+            BotProfile::adaptive(&[]).target_accuracy(),
+            ADAPTIVE_FALLBACK_ACCURACY
+        );
+        assert_eq!(BotProfile::adaptive(&[20.0]).target_accuracy(), 50.0);
+    }
+
+    #[test]
+    fn room_bots_count_their_run_as_the_clock_reaches_it() {
+        let times = beat_times();
+        let mut bot = RoomBot::new(0, BotProfile::Hard, 5, &times, 1.5);
+        bot.advance(30.0);
+        let halfway = bot.hits.total();
+        assert!(halfway > 0 && halfway < times.len() as u32);
+
+        bot.advance(f64::INFINITY);
+        assert!(bot.player.is_finished());
+        assert_eq!(bot.hits.total(), times.len() as u32);
+        assert_eq!(bot.score, (bot.player.final_score() as f32 * 1.5) as i64);
+        assert_eq!(bot.name(), "[BOT] Hard");
+    }
+
+    #[test]
+    fn each_room_seat_plays_its_own_run() {
+        let times = beat_times();
+        let mut seats: Vec<RoomBot> = (0..MAX_ROOM_BOTS)
+            .map(|slot| RoomBot::new(slot, BotProfile::Medium, 5, &times, 1.0))
+            .collect();
+        for bot in &mut seats {
+            bot.advance(f64::INFINITY);
+        }
+        assert_ne!(seats[0].score, seats[1].score);
+
+        let mut again = RoomBot::new(1, BotProfile::Medium, 5, &times, 1.0);
+        again.advance(f64::INFINITY);
+        assert_eq!(again.score, seats[1].score);
+    }
+
+    #[test]
+    fn the_room_ranks_by_score_then_accuracy() {
+        let times = beat_times();
+        let mut bots = vec![
+            RoomBot::new(0, BotProfile::Easy, 2, &times, 1.0),
+            RoomBot::new(1, BotProfile::Hard, 2, &times, 1.0),
+        ];
+        for bot in &mut bots {
+            bot.advance(f64::INFINITY);
+        }
+        let player = Standing {
+            name: "You".to_string(),
+            score: bots[1].score,
+            accuracy: 100.0,
+            bot: false,
+        };
+        let standings = room_standings(player, &bots);
+        let names: Vec<&str> = standings.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["You", "[BOT] Hard", "[BOT] Easy"]);
+        assert!(!standings[0].bot && standings[1].bot);
+    }
+}
@@ -0,0 +1,432 @@
+//! Tournament bracket generation and progression.
+//!
+//! Players are seeded in registration order. The first round places them so the
+//! top seeds can only meet late, with byes going to the top seeds when the field
+//! isn't a power of two. Each later round pairs the winners of neighbouring
+//! slots, so the same results always produce the same bracket.
+
+use crate::community::{EliminationType, Match, MatchOutcome, ScoringType};
+use crate::multiplayer::PlayerGameState;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+/// Minutes after a match's scheduled time a player has to join its room before
+/// forfeiting
+pub const FORFEIT_GRACE_MINUTES: i64 = 10;
+
+/// Seed index (0 for the top seed) at each slot of a bracket of `size` players, a
+/// power of two. Neighbouring slots play each other in the first round, and seeds
+/// 1 and 2 land in opposite halves
+pub fn seed_positions(size: usize) -> Vec<usize> {
+    let mut positions = vec![0];
+    while positions.len() < size {
+        let width = positions.len() * 2;
+        positions = positions
+            .iter()
+            .flat_map(|&seed| [seed, width - 1 - seed])
+            .collect();
+    }
+    positions
+}
+
+/// Song a round is played on, cycling through the pool
+fn round_song(song_pool: &[String], round: u32) -> String {
+    if song_pool.is_empty() {
+        return String::new();
+    }
+    song_pool[(round as usize - 1) % song_pool.len()].clone()
+}
+
+fn new_match(
+    tournament_id: Uuid,
+    round: u32,
+    slot: u32,
+    player1_id: Uuid,
+    player2_id: Option<Uuid>,
+    song: String,
+    scheduled_at: DateTime<Utc>,
+) -> Match {
+    let mut game_match = Match {
+        match_id: Uuid::new_v4(),
+        tournament_id,
+        player1_id,
+        player2_id,
+        player1_score: 0,
+        player2_score: 0,
+        winner_id: None,
+        song,
+        scheduled_at,
+        completed_at: None,
+        round,
+        slot,
+        checked_in: Vec::new(),
+        outcome: MatchOutcome::Pending,
+    };
+    // A player without an opponent goes straight through
+    if player2_id.is_none() {
+        game_match.winner_id = Some(player1_id);
+        game_match.completed_at = Some(scheduled_at);
+        game_match.outcome = MatchOutcome::Bye;
+    }
+    game_match
+}
+
+fn check_supported(elimination: &EliminationType) -> Result<()> {
+    match elimination {
+        EliminationType::SingleElimination => Ok(()),
+        other => Err(anyhow::anyhow!(
+            "{:?} brackets are not supported yet",
+            other
+        )),
+    }
+}
+
+/// Round 1 of a bracket for players in seed order. Byes are decided matches
+pub fn first_round(
+    tournament_id: Uuid,
+    players: &[Uuid],
+    elimination: &EliminationType,
+    song_pool: &[String],
+    scheduled_at: DateTime<Utc>,
+) -> Result<Vec<Match>> {
+    check_supported(elimination)?;
+    if players.len() < 2 {
+        return Err(anyhow::anyhow!("A bracket needs at least 2 players"));
+    }
+
+    let positions = seed_positions(players.len().next_power_of_two());
+    Ok(positions
+        .chunks(2)
+        .enumerate()
+        .map(|(slot, pair)| {
+            // The better seed of the pair is always player 1
+            let (high, low) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+            new_match(
+                tournament_id,
+                1,
+                slot as u32,
+                players[high],
+                players.get(low).copied(),
+                round_song(song_pool, 1),
+                scheduled_at,
+            )
+        })
+        .collect())
+}
+
+/// Whether a match has a result the bracket can move on from
+pub fn is_decided(game_match: &Match) -> bool {
+    game_match.completed_at.is_some() && game_match.winner_id.is_some()
+}
+
+/// The round after a decided one, pairing the winners of slots 2k and 2k+1 in slot
+/// k. None when the round was the final
+pub fn next_round(
+    tournament_id: Uuid,
+    round: &[Match],
+    elimination: &EliminationType,
+    song_pool: &[String],
+    scheduled_at: DateTime<Utc>,
+) -> Result<Option<Vec<Match>>> {
+    check_supported(elimination)?;
+    if round.len() <= 1 {
+        return Ok(None);
+    }
+    let mut ordered: Vec<&Match> = round.iter().collect();
+    ordered.sort_by_key(|game_match| game_match.slot);
+    let winners: Vec<Uuid> = ordered
+        .iter()
+        .map(|game_match| {
+            game_match
+                .winner_id
+                .filter(|_| is_decided(game_match))
+                .ok_or_else(|| anyhow::anyhow!("Round {} isn't finished", game_match.round))
+        })
+        .collect::<Result<_>>()?;
+
+    let number = ordered[0].round + 1;
+    Ok(Some(
+        winners
+            .chunks(2)
+            .enumerate()
+            .map(|(slot, pair)| {
+                new_match(
+                    tournament_id,
+                    number,
+                    slot as u32,
+                    pair[0],
+                    pair.get(1).copied(),
+                    round_song(song_pool, number),
+                    scheduled_at,
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// A player's result as a match score: the score, or accuracy in hundredths of a
+/// percent for accuracy matches. Invalidated results score nothing
+pub fn match_score(player: &PlayerGameState, scoring_type: ScoringType) -> u32 {
+    if player.result_invalid {
+        return 0;
+    }
+    match scoring_type {
+        ScoringType::ScoreV1 | ScoringType::ScoreV2 => player.score,
+        ScoringType::Accuracy => (player.accuracy * 100.0).round() as u32,
+    }
+}
+
+/// Winner of a tie on match score: better accuracy (better score in accuracy
+/// matches), then the better seed
+pub fn break_tie(
+    game_match: &Match,
+    player1: &PlayerGameState,
+    player2: &PlayerGameState,
+    scoring_type: ScoringType,
+) -> Uuid {
+    let player2_better = match scoring_type {
+        ScoringType::Accuracy => player2.score > player1.score,
+        ScoringType::ScoreV1 | ScoringType::ScoreV2 => player2.accuracy > player1.accuracy,
+    };
+    match game_match.player2_id {
+        Some(player2_id) if player2_better => player2_id,
+        _ => game_match.player1_id,
+    }
+}
+
+/// Winner by walkover of an unplayed match whose grace window has run out: the only
+/// player who joined its room, or the better seed when neither did so the bracket
+/// never has an empty slot. None while the window is open or once both showed up
+pub fn walkover_winner(game_match: &Match, now: DateTime<Utc>) -> Option<Uuid> {
+    if game_match.completed_at.is_some()
+        || now < game_match.scheduled_at + Duration::minutes(FORFEIT_GRACE_MINUTES)
+    {
+        return None;
+    }
+    let player2_id = game_match.player2_id?;
+    let player1_here = game_match.checked_in.contains(&game_match.player1_id);
+    let player2_here = game_match.checked_in.contains(&player2_id);
+    match (player1_here, player2_here) {
+        (true, true) => None,
+        (false, true) => Some(player2_id),
+        _ => Some(game_match.player1_id),
+    }
+}
+
+/// Matches of a tournament grouped by round, each round in slot order
+pub fn rounds(matches: &[Match]) -> Vec<Vec<Match>> {
+    let last = matches
+        .iter()
+        .map(|game_match| game_match.round)
+        .max()
+        .unwrap_or(0);
+    (1..=last)
+        .map(|round| {
+            let mut games: Vec<Match> = matches
+                .iter()
+                .filter(|game_match| game_match.round == round)
+                .cloned()
+                .collect();
+            games.sort_by_key(|game_match| game_match.slot);
+            games
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn players(count: usize) -> Vec<Uuid> {
+        (0..count).map(|_| Uuid::new_v4()).collect()
+    }
+
+    fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    /// Seed index of a player, for readable assertions
+    fn seed(players: &[Uuid], id: Uuid) -> usize {
+        players.iter().position(|player| *player == id).unwrap()
+    }
+
+    /// (player 1, player 2) seeds of each match in a round
+    fn pairings(players: &[Uuid], round: &[Match]) -> Vec<(usize, Option<usize>)> {
+        round
+            .iter()
+            .map(|game_match| {
+                let player2 = game_match.player2_id.map(|id| seed(players, id));
+                (seed(players, game_match.player1_id), player2)
+            })
+            .collect()
+    }
+
+    /// Decide every open match of a round, the lower seed number winning
+    fn play(players: &[Uuid], round: &mut [Match]) {
+        for game_match in round.iter_mut().filter(|m| !is_decided(m)) {
+            let player2 = game_match.player2_id.unwrap();
+            let winner = if seed(players, game_match.player1_id) < seed(players, player2) {
+                game_match.player1_id
+            } else {
+                player2
+            };
+            game_match.winner_id = Some(winner);
+            game_match.completed_at = Some(start());
+            game_match.outcome = MatchOutcome::Played;
+        }
+    }
+
+    /// Every round of a bracket the top seeds always win
+    fn run_bracket(players: &[Uuid]) -> Vec<Vec<Match>> {
+        let id = Uuid::new_v4();
+        let elimination = EliminationType::SingleElimination;
+        let mut round = first_round(id, players, &elimination, &[], start()).unwrap();
+        let mut rounds = Vec::new();
+        loop {
+            play(players, &mut round);
+            let next = next_round(id, &round, &elimination, &[], start()).unwrap();
+            rounds.push(round);
+            match next {
+                Some(next) => round = next,
+                None => return rounds,
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_one_and_two_sit_in_opposite_halves() {
+        assert_eq!(seed_positions(2), [0, 1]);
+        assert_eq!(seed_positions(4), [0, 3, 1, 2]);
+        assert_eq!(seed_positions(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+    }
+
+    #[test]
+    fn eight_players_play_three_full_rounds() {
+        let players = players(8);
+        let rounds = run_bracket(&players);
+        assert_eq!(rounds.len(), 3);
+        assert_eq!(
+            pairings(&players, &rounds[0]),
+            [(0, Some(7)), (3, Some(4)), (1, Some(6)), (2, Some(5))]
+        );
+        assert_eq!(pairings(&players, &rounds[1]), [(0, Some(3)), (1, Some(2))]);
+        assert_eq!(pairings(&players, &rounds[2]), [(0, Some(1))]);
+        assert_eq!(rounds[2][0].winner_id, Some(players[0]));
+        assert!(rounds
+            .iter()
+            .flatten()
+            .all(|m| m.outcome == MatchOutcome::Played));
+    }
+
+    #[test]
+    fn five_players_give_the_top_three_seeds_byes() {
+        let players = players(5);
+        let rounds = run_bracket(&players);
+        assert_eq!(rounds.len(), 3);
+        assert_eq!(
+            pairings(&players, &rounds[0]),
+            [(0, None), (3, Some(4)), (1, None), (2, None)]
+        );
+        let byes: Vec<&Match> = rounds[0]
+            .iter()
+            .filter(|m| m.outcome == MatchOutcome::Bye)
+            .collect();
+        assert_eq!(byes.len(), 3);
+        assert!(byes
+            .iter()
+            .all(|m| is_decided(m) && m.winner_id == Some(m.player1_id)));
+        assert_eq!(pairings(&players, &rounds[1]), [(0, Some(3)), (1, Some(2))]);
+        assert_eq!(rounds[2][0].winner_id, Some(players[0]));
+    }
+
+    #[test]
+    fn nine_players_need_a_fourth_round() {
+        let players = players(9);
+        let rounds = run_bracket(&players);
+        assert_eq!(
+            rounds.iter().map(Vec::len).collect::<Vec<_>>(),
+            [8, 4, 2, 1]
+        );
+        // Only the bottom two seeds play in the first round
+        let played: Vec<_> = pairings(&players, &rounds[0])
+            .into_iter()
+            .filter(|(_, player2)| player2.is_some())
+            .collect();
+        assert_eq!(played, [(7, Some(8))]);
+        assert_eq!(pairings(&players, &rounds[1])[0], (0, Some(7)));
+        assert_eq!(rounds[3][0].winner_id, Some(players[0]));
+    }
+
+    #[test]
+    fn the_same_players_always_draw_the_same_bracket() {
+        let players = players(9);
+        let draw = |rounds: Vec<Vec<Match>>| -> Vec<Vec<(usize, Option<usize>)>> {
+            rounds
+                .iter()
+                .map(|round| pairings(&players, round))
+                .collect()
+        };
+        assert_eq!(draw(run_bracket(&players)), draw(run_bracket(&players)));
+    }
+
+    #[test]
+    fn a_no_show_forfeits_after_the_grace_window() {
+        let players = players(8);
+        let id = Uuid::new_v4();
+        let elimination = EliminationType::SingleElimination;
+        let mut round = first_round(id, &players, &elimination, &[], start()).unwrap();
+        let grace = Duration::minutes(FORFEIT_GRACE_MINUTES);
+
+        // Seed 1 never joins the room; seed 8 does
+        round[0].checked_in.push(players[7]);
+        assert_eq!(
+            walkover_winner(&round[0], start() + grace - Duration::seconds(1)),
+            None
+        );
+        assert_eq!(
+            walkover_winner(&round[0], start() + grace),
+            Some(players[7])
+        );
+        // Neither showing up sends the better seed through; both showing up is a game
+        assert_eq!(
+            walkover_winner(&round[1], start() + grace),
+            Some(players[3])
+        );
+        round[2].checked_in.extend([players[1], players[6]]);
+        assert_eq!(walkover_winner(&round[2], start() + grace), None);
+
+        round[0].winner_id = walkover_winner(&round[0], start() + grace);
+        round[0].completed_at = Some(start() + grace);
+        assert_eq!(walkover_winner(&round[0], start() + grace), None);
+        assert!(next_round(id, &round, &elimination, &[], start()).is_err());
+
+        play(&players, &mut round);
+        let next = next_round(id, &round, &elimination, &[], start())
+            .unwrap()
+            .unwrap();
+        assert_eq!(pairings(&players, &next), [(7, Some(3)), (1, Some(2))]);
+    }
+
+    #[test]
+    fn other_elimination_types_are_refused() {
+        let players = players(4);
+        for elimination in [
+            EliminationType::DoubleElimination,
+            EliminationType::RoundRobin,
+            EliminationType::Swiss,
+        ] {
+            assert!(first_round(Uuid::new_v4(), &players, &elimination, &[], start()).is_err());
+        }
+        let single = EliminationType::SingleElimination;
+        assert!(first_round(Uuid::new_v4(), &players[..1], &single, &[], start()).is_err());
+    }
+
+    #[test]
+    fn rounds_cycle_through_the_song_pool() {
+        let pool = vec!["a".to_string(), "b".to_string()];
+        let songs: Vec<String> = (1..=3).map(|round| round_song(&pool, round)).collect();
+        assert_eq!(songs, ["a", "b", "a"]);
+        assert_eq!(round_song(&[], 1), "");
+    }
+}
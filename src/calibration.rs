@@ -0,0 +1,260 @@
+// src/calibration.rs
+
+use bevy::prelude::*;
+use std::time::Instant;
+
+use crate::beatmap::Hitsound;
+use crate::config::{key_label, GameConfig, AUDIO_OFFSET_LIMIT_MS};
+use crate::constants::{NEON_GREEN, NEON_PINK, NEON_YELLOW};
+use crate::editor_audio::TickScheduler;
+use crate::structs::{GameAssets, GameAudioSink};
+use crate::ui::UiElement;
+use crate::AppState;
+
+/// Metronome beat length (seconds, 120 BPM)
+pub const CALIBRATION_BEAT: f64 = 0.5;
+
+/// Beats per bar; the first click of each bar is pitched higher
+const BEATS_PER_BAR: i64 = 4;
+
+/// Clicks played before taps count, to find the pulse
+const LEAD_IN_BEATS: i64 = 4;
+
+/// Taps averaged into the measured offset
+pub const CALIBRATION_TAPS: usize = 16;
+
+/// Taps further than this from the nearest click are stray presses (ms)
+const STRAY_TAP_MS: f64 = 200.0;
+
+/// Metronome and counted taps of the calibration screen
+#[derive(Debug, Default, Resource)]
+pub struct Calibration {
+    /// When the metronome started
+    started: Option<Instant>,
+    scheduler: TickScheduler,
+    /// How late (positive) or early each counted tap was (ms)
+    pub taps: Vec<f32>,
+    /// Offset saved at the end of this run
+    pub saved: Option<i32>,
+}
+
+impl Calibration {
+    /// Seconds since the metronome started
+    fn now(&self) -> f64 {
+        self.started
+            .map_or(0.0, |started| started.elapsed().as_secs_f64())
+    }
+
+    /// Start over from the lead-in
+    fn restart(&mut self) {
+        *self = Self {
+            started: Some(Instant::now()),
+            ..default()
+        };
+    }
+
+    /// Whether enough taps were counted
+    pub fn is_done(&self) -> bool {
+        self.taps.len() >= CALIBRATION_TAPS
+    }
+
+    /// Count a tap at metronome time `now` against the nearest click. Taps during the
+    /// lead-in, far from any click or after the run is done are ignored
+    pub fn tap(&mut self, now: f64) -> bool {
+        let beat = (now / CALIBRATION_BEAT).round() as i64;
+        let offset_ms = (now - beat as f64 * CALIBRATION_BEAT) * 1000.0;
+        if beat <= LEAD_IN_BEATS || offset_ms.abs() > STRAY_TAP_MS || self.is_done() {
+            return false;
+        }
+        self.taps.push(offset_ms as f32);
+        true
+    }
+
+    /// Mean and standard deviation of the taps so far (ms)
+    pub fn tap_stats(&self) -> Option<(f32, f32)> {
+        if self.taps.is_empty() {
+            return None;
+        }
+        let count = self.taps.len() as f32;
+        let mean = self.taps.iter().sum::<f32>() / count;
+        let variance = self.taps.iter().map(|&t| (t - mean).powi(2)).sum::<f32>() / count;
+        Some((mean, variance.sqrt()))
+    }
+
+    /// Offset the taps call for: their mean, clamped to the allowed range
+    pub fn measured_offset(&self) -> Option<i32> {
+        let (mean, _) = self.tap_stats()?;
+        Some((mean.round() as i32).clamp(-AUDIO_OFFSET_LIMIT_MS, AUDIO_OFFSET_LIMIT_MS))
+    }
+}
+
+/// Clicks between two metronome times, accenting the first of each bar
+fn clicks_between(from: f64, until: f64) -> impl Iterator<Item = (f64, Hitsound)> {
+    let first = (from / CALIBRATION_BEAT).floor() as i64 + 1;
+    let last = (until / CALIBRATION_BEAT).floor() as i64;
+    (first.max(1)..=last).map(|beat| {
+        let hitsound = if beat % BEATS_PER_BAR == 1 {
+            Hitsound::Whistle
+        } else {
+            Hitsound::Normal
+        };
+        (beat as f64 * CALIBRATION_BEAT, hitsound)
+    })
+}
+
+/// Start the metronome on the effects sink at the effects volume
+pub fn enter_calibration(
+    mut calibration: ResMut<Calibration>,
+    audio_sink: Res<GameAudioSink>,
+    config: Res<GameConfig>,
+) {
+    audio_sink.effects.stop();
+    audio_sink
+        .effects
+        .set_volume(config.audio.master_volume * config.audio.effects_volume);
+    calibration.restart();
+}
+
+/// Silence the metronome and hand the effects sink back at full volume
+pub fn exit_calibration(audio_sink: Res<GameAudioSink>) {
+    audio_sink.effects.stop();
+    audio_sink.effects.set_volume(1.0);
+}
+
+/// Keep the clicks queued, count hit key taps and save the offset once enough are in.
+/// Enter starts over, ESC goes back to Settings
+pub fn update_calibration(
+    mut calibration: ResMut<Calibration>,
+    mut config: ResMut<GameConfig>,
+    audio_sink: Res<GameAudioSink>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Settings);
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Enter) {
+        calibration.restart();
+        return;
+    }
+
+    // Queuing clicks isn't worth a redraw
+    let now = calibration.now();
+    let state = calibration.bypass_change_detection();
+    let (from, until) = state.scheduler.window(now);
+    state
+        .scheduler
+        .queue(&audio_sink.effects, clicks_between(from, until), now, 1.0);
+
+    let hit_keys = [
+        config.key_bindings.primary_hit_key(),
+        config.key_bindings.secondary_hit_key(),
+    ];
+    if !keyboard.any_just_pressed(hit_keys) || !calibration.tap(now) {
+        return;
+    }
+    if calibration.is_done() {
+        if let Some(offset) = calibration.measured_offset() {
+            config.audio.audio_offset_ms = offset;
+            config.save();
+            calibration.saved = Some(offset);
+            info!("Audio offset calibrated to {:+} ms", offset);
+        }
+    }
+}
+
+/// Marker for the calibration screen's entities
+#[derive(Component)]
+pub struct CalibrationElement;
+
+/// Draw the instructions, the taps counted and the measured offset
+pub fn draw_calibration(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    calibration: Res<Calibration>,
+    config: Res<GameConfig>,
+    existing: Query<Entity, With<CalibrationElement>>,
+) {
+    if !calibration.is_changed() && !existing.is_empty() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+    let dim = Color::srgba(1.0, 1.0, 1.0, 0.6);
+
+    let keys = &config.key_bindings;
+    let (status, status_color) = match (calibration.saved, calibration.tap_stats()) {
+        (Some(offset), _) => (format!("Saved audio offset: {:+} ms", offset), NEON_GREEN),
+        (None, Some((mean, spread))) => (
+            format!("Average: {:+.1} ms (spread {:.1} ms)", mean, spread),
+            NEON_YELLOW,
+        ),
+        (None, None) => ("Listen for the pulse...".to_string(), NEON_YELLOW),
+    };
+    let lines = [
+        ("Offset Calibration".to_string(), 28.0, NEON_PINK),
+        (
+            format!(
+                "Tap {} or {} on every click, by ear rather than by eye",
+                key_label(&keys.primary_hit),
+                key_label(&keys.secondary_hit)
+            ),
+            16.0,
+            Color::WHITE,
+        ),
+        (
+            format!(
+                "The first {} clicks are a lead-in; taps far from a click don't count",
+                LEAD_IN_BEATS
+            ),
+            14.0,
+            dim,
+        ),
+        (
+            format!("Taps: {}/{}", calibration.taps.len(), CALIBRATION_TAPS),
+            20.0,
+            Color::WHITE,
+        ),
+        (status, 20.0, status_color),
+        (
+            format!(
+                "Current offset: {:+} ms (limit ±{} ms)",
+                config.audio.offset_ms(),
+                AUDIO_OFFSET_LIMIT_MS
+            ),
+            14.0,
+            dim,
+        ),
+    ];
+    let top = window.height() / 2.0 - 80.0;
+    for (i, (text, size, color)) in lines.into_iter().enumerate() {
+        commands.spawn((
+            Text2d::new(text),
+            font(size),
+            TextColor(color),
+            Transform::from_xyz(0.0, top - i as f32 * 50.0, 1.0),
+            UiElement,
+            CalibrationElement,
+        ));
+    }
+
+    commands.spawn((
+        Text2d::new("Enter: start over | ESC: back"),
+        font(12.0),
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+        Transform::from_xyz(0.0, -window.height() / 2.0 + 40.0, 1.0),
+        UiElement,
+        CalibrationElement,
+    ));
+}
@@ -0,0 +1,289 @@
+// src/cli.rs
+
+use crate::audio::{try_analyze_song, DetectionQuality, SongAnalysis, ENERGY_RESOLUTION};
+use crate::beatmap::Beatmap;
+use crate::map_comments::MapComments;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Usage shown for `help` and unknown subcommands
+const USAGE: &str = "\
+Usage: yum-osu [COMMAND]
+
+Without a command the game starts. Commands run without a window or audio device:
+  analyze <audio> [fast|balanced|accurate]
+                       Detect beats and print BPM, beat count and loudness as JSON
+  validate <beatmap>   Check a beatmap, exiting nonzero if it has errors
+  convert <map.osu> [-o out.json]
+  import-osu <map.osu> [out.json]
+                       Convert an osu! beatmap, saving it next to the .osu file
+                       unless an output path is given
+  export <beatmap> [-o out.ymz] [--with-comments]
+                       Package a beatmap with its audio and background as .ymz,
+                       and its review comments too if asked
+  export-osu <beatmap> [out.osu]
+                       Write a beatmap in osu!'s .osu format; copy the audio file
+                       next to it for osu! to find
+  help                 Show this message
+
+  --editor-bench [--headless]
+                       Start the game on a generated 10,000 object map, script
+                       editor operations and print frame times, exiting nonzero
+                       over budget. Headless runs need no window, GPU or audio
+                       device and only time the game's own work each frame";
+
+/// Flag starting the game in the editor benchmark instead of the menu
+pub const EDITOR_BENCH_FLAG: &str = "--editor-bench";
+
+/// Flag running the editor benchmark without a window
+pub const HEADLESS_FLAG: &str = "--headless";
+
+/// Flag packaging a beatmap's review comments into its .ymz export
+pub const WITH_COMMENTS_FLAG: &str = "--with-comments";
+
+/// BPM range a detected tempo is folded into by doubling or halving
+const BPM_RANGE: (f64, f64) = (70.0, 200.0);
+
+/// Result of `yum-osu analyze`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnalyzeReport {
+    pub path: String,
+    /// Beat detection mode used
+    pub quality: DetectionQuality,
+    /// Tempo estimated from the median gap between kick beats, None with too few beats
+    pub bpm: Option<f64>,
+    pub beat_count: usize,
+    pub duration_seconds: f64,
+    /// Average loudness relative to the loudest moment, 0-1
+    pub mean_loudness: f32,
+}
+
+impl AnalyzeReport {
+    /// Summarize an analysis of the song at `path`
+    pub fn from_analysis(path: &str, quality: DetectionQuality, analysis: &SongAnalysis) -> Self {
+        let mean_loudness = if analysis.energy.is_empty() {
+            0.0
+        } else {
+            analysis.energy.iter().sum::<f32>() / analysis.energy.len() as f32
+        };
+        Self {
+            path: path.to_string(),
+            quality,
+            bpm: estimate_bpm(&analysis.beats),
+            beat_count: analysis.beats.len(),
+            duration_seconds: analysis.energy.len() as f64 * ENERGY_RESOLUTION,
+            mean_loudness,
+        }
+    }
+}
+
+/// Tempo from the median gap between beats, folded into `BPM_RANGE`
+pub fn estimate_bpm(beats: &[f64]) -> Option<f64> {
+    let mut gaps: Vec<f64> = beats
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .filter(|gap| *gap > 0.0)
+        .collect();
+    if gaps.is_empty() {
+        return None;
+    }
+    gaps.sort_by(|a, b| a.total_cmp(b));
+
+    let mut bpm = 60.0 / gaps[gaps.len() / 2];
+    while bpm < BPM_RANGE.0 {
+        bpm *= 2.0;
+    }
+    while bpm > BPM_RANGE.1 {
+        bpm /= 2.0;
+    }
+    Some(bpm)
+}
+
+/// Analyze an audio file
+pub fn analyze(path: &str, quality: DetectionQuality) -> Result<AnalyzeReport, String> {
+    let analysis = try_analyze_song(path, quality)?;
+    Ok(AnalyzeReport::from_analysis(path, quality, &analysis))
+}
+
+/// Load and check a beatmap. The outer error means it couldn't be read; the inner
+/// list holds the problems found in it
+pub fn validate(path: &str) -> Result<Vec<String>, String> {
+    let beatmap = Beatmap::load_from_file(path)?;
+    Ok(beatmap.validate().err().unwrap_or_default())
+}
+
+/// Convert an .osu file into a beatmap saved as JSON at `out`, with its audio path
+/// made relative to where the .osu file is, as osu! reads it. Returns the beatmap's
+/// validation problems, which don't stop the import
+pub fn import_osu(path: &str, out: &str) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("couldn't read {} ({})", path, e))?;
+    let mut beatmap = Beatmap::from_osu_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if let Some(folder) = Path::new(path).parent() {
+        beatmap.audio_path = folder
+            .join(&beatmap.audio_path)
+            .to_string_lossy()
+            .into_owned();
+    }
+    beatmap.save_to_file(out)?;
+    Ok(beatmap.validate().err().unwrap_or_default())
+}
+
+/// Write a beatmap as an .osu file at `out`
+pub fn export_osu(path: &str, out: &str) -> Result<(), String> {
+    let beatmap = Beatmap::load_from_file(path)?;
+    fs::write(out, beatmap.to_osu_string()).map_err(|e| format!("couldn't write {} ({})", out, e))
+}
+
+/// Package a beatmap as a .ymz file at `out`, its audio and background looked up
+/// relative to the beatmap's folder. With `with_comments` its review comments sidecar
+/// goes in too, if it has one
+pub fn export_ymz(path: &str, out: &str, with_comments: bool) -> Result<(), String> {
+    let beatmap = Beatmap::load_from_file(path)?;
+    let folder = Path::new(path).parent().unwrap_or(Path::new(""));
+    let comments = if with_comments {
+        Some(MapComments::load(path)?).filter(|comments| !comments.comments.is_empty())
+    } else {
+        None
+    };
+    let bytes = beatmap.to_ymz_bytes(folder, comments.as_ref())?;
+    fs::write(out, bytes).map_err(|e| format!("couldn't write {} ({})", out, e))
+}
+
+/// `out` if given, otherwise `path` with another extension
+fn output_path(path: &str, out: Option<&String>, extension: &str) -> String {
+    out.cloned().unwrap_or_else(|| {
+        Path::new(path)
+            .with_extension(extension)
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+/// The output path of `-o <path>`, None without the flag and an error for anything
+/// else
+fn output_flag(rest: &[String]) -> Result<Option<&String>, String> {
+    match rest {
+        [] => Ok(None),
+        [flag, out] if flag == "-o" => Ok(Some(out)),
+        [flag] if flag == "-o" => Err("-o needs an output path".to_string()),
+        [other, ..] => Err(format!("unexpected argument \"{}\"", other)),
+    }
+}
+
+/// Run a subcommand if one was given. Returns the exit code, or None to start the
+/// game
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?;
+    let code = match (command.as_str(), &args[1..]) {
+        (EDITOR_BENCH_FLAG, []) => return None,
+        (EDITOR_BENCH_FLAG, [flag]) if flag == HEADLESS_FLAG => return None,
+        ("analyze", [path]) => print_analysis(path, DetectionQuality::default()),
+        ("analyze", [path, mode]) => match DetectionQuality::from_name(mode) {
+            Some(quality) => print_analysis(path, quality),
+            None => fail(&format!("unknown detection mode \"{}\"", mode)),
+        },
+        ("validate", [path]) => match validate(path) {
+            Ok(errors) if errors.is_empty() => {
+                println!("{}: OK", path);
+                0
+            }
+            Ok(errors) => {
+                for error in &errors {
+                    eprintln!("{}: {}", path, error);
+                }
+                eprintln!("{}: {} error(s)", path, errors.len());
+                1
+            }
+            Err(e) => fail(&e),
+        },
+        ("import-osu", [path, rest @ ..]) if rest.len() <= 1 => {
+            print_import(path, &output_path(path, rest.first(), "json"))
+        }
+        ("convert", [path, rest @ ..]) => match output_flag(rest) {
+            Ok(out) => print_import(path, &output_path(path, out, "json")),
+            Err(e) => usage_error(&e),
+        },
+        ("export-osu", [path, rest @ ..]) if rest.len() <= 1 => {
+            let out = output_path(path, rest.first(), "osu");
+            match export_osu(path, &out) {
+                Ok(()) => {
+                    println!("{}: exported to {}", path, out);
+                    0
+                }
+                Err(e) => fail(&e),
+            }
+        }
+        ("export", [path, rest @ ..]) => {
+            let with_comments = rest.iter().any(|arg| arg == WITH_COMMENTS_FLAG);
+            let rest: Vec<String> = rest
+                .iter()
+                .filter(|arg| *arg != WITH_COMMENTS_FLAG)
+                .cloned()
+                .collect();
+            match output_flag(&rest) {
+                Ok(out) => {
+                    let out = output_path(path, out, "ymz");
+                    match export_ymz(path, &out, with_comments) {
+                        Ok(()) => {
+                            println!("{}: packaged to {}", path, out);
+                            0
+                        }
+                        Err(e) => fail(&e),
+                    }
+                }
+                Err(e) => usage_error(&e),
+            }
+        }
+        ("help" | "--help" | "-h", _) => {
+            println!("{}", USAGE);
+            0
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    };
+    Some(code)
+}
+
+/// Analyze a file and print the report as JSON, returning the exit code
+fn print_analysis(path: &str, quality: DetectionQuality) -> i32 {
+    match analyze(path, quality) {
+        Ok(report) => match serde_json::to_string_pretty(&report) {
+            Ok(json) => {
+                println!("{}", json);
+                0
+            }
+            Err(e) => fail(&e.to_string()),
+        },
+        Err(e) => fail(&e),
+    }
+}
+
+/// Convert an .osu file and print where it went and the problems found in it,
+/// returning the exit code
+fn print_import(path: &str, out: &str) -> i32 {
+    match import_osu(path, out) {
+        Ok(problems) => {
+            for problem in &problems {
+                eprintln!("{}: {}", out, problem);
+            }
+            println!("{}: imported to {}", path, out);
+            0
+        }
+        Err(e) => fail(&e),
+    }
+}
+
+/// Report bad arguments with the usage and return the usage exit code
+fn usage_error(message: &str) -> i32 {
+    eprintln!("yum-osu: {}\n\n{}", message, USAGE);
+    2
+}
+
+/// Report an error to stderr and return the failure exit code
+fn fail(message: &str) -> i32 {
+    eprintln!("yum-osu: {}", message);
+    1
+}
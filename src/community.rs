@@ -2,15 +2,23 @@
 //! Provides leaderboards, friends system, chat, and profiles
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use anyhow::Result;
 
-use crate::accounts::{User, UserProfile, UserStats, LeaderboardEntry, Friend, FriendStatus};
+use crate::accounts::{UserStats, Friend, FriendStatus, ProfileRecords, SongRecord, StoredFile};
+use crate::achievements::{AchievementCondition, AchievementDefinitions, AchievementProgress, AchievementRarity, AchievementStats};
+use crate::analytics::{Analytics, Grade, GradeRules};
+use crate::bracket;
+use crate::multiplayer::MultiplayerGameState;
+use crate::network::{GameClient, NetworkMessage};
 
 /// Chat message
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub message_id: Uuid,
     pub sender_id: Uuid,
@@ -21,6 +29,22 @@ pub struct ChatMessage {
     pub recipient_id: Option<Uuid>, // Some for direct messages
 }
 
+/// Messages kept in memory per chat room; older ones go to the room's archive file.
+/// Pinned messages are kept apart and don't count
+pub const CHAT_HISTORY_LIMIT: usize = 100;
+
+/// Name of the public room a chat panel opens in
+pub const LOBBY_ROOM_NAME: &str = "Lobby";
+
+/// Most messages a room can have pinned at once
+pub const MAX_PINNED_MESSAGES: usize = 3;
+
+/// Longest slow mode a host can set (seconds)
+pub const MAX_SLOW_MODE_SECONDS: u32 = 300;
+
+/// Longest timeout a host can give (minutes)
+pub const MAX_TIMEOUT_MINUTES: u32 = 24 * 60;
+
 /// Lobby chat room
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatRoom {
@@ -30,6 +54,167 @@ pub struct ChatRoom {
     pub members: Vec<Uuid>,
     pub messages: Vec<ChatMessage>,
     pub created_at: DateTime<Utc>,
+    /// Room host or tournament organizer allowed to moderate; None for unmoderated rooms
+    #[serde(default)]
+    pub host_id: Option<Uuid>,
+    #[serde(default)]
+    pub settings: ChatRoomSettings,
+    /// When each member last sent a message, for slow mode
+    #[serde(skip)]
+    last_sent: HashMap<Uuid, DateTime<Utc>>,
+}
+
+/// Moderation state of a room, broadcast with the room
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatRoomSettings {
+    /// Minimum seconds between messages from one member, 0 for off
+    pub slow_mode_seconds: u32,
+    /// Members muted in this room and until when
+    pub timeouts: HashMap<Uuid, DateTime<Utc>>,
+    /// Messages shown at the top of the chat panel, oldest pin first
+    pub pinned: Vec<ChatMessage>,
+}
+
+/// Something the host does to moderate a room's chat
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChatModAction {
+    /// Set slow mode, 0 to turn it off
+    SlowMode { seconds: u32 },
+    /// Mute a member for some minutes, 0 to lift an existing timeout
+    Timeout { member_id: Uuid, minutes: u32 },
+    Pin { message_id: Uuid },
+    Unpin { message_id: Uuid },
+}
+
+impl ChatRoom {
+    /// Create an empty room
+    pub fn new(name: String, room_type: ChatRoomType, members: Vec<Uuid>, host_id: Option<Uuid>) -> Self {
+        Self {
+            room_id: Uuid::new_v4(),
+            name,
+            room_type,
+            members,
+            messages: Vec::new(),
+            created_at: Utc::now(),
+            host_id,
+            settings: ChatRoomSettings::default(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Whether a user moderates the room
+    pub fn is_host(&self, user_id: Uuid) -> bool {
+        self.host_id == Some(user_id)
+    }
+
+    /// Time left on a member's timeout
+    pub fn timeout_remaining(&self, user_id: Uuid, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.settings.timeouts.get(&user_id)
+            .map(|until| *until - now)
+            .filter(|remaining| *remaining > chrono::Duration::zero())
+    }
+
+    /// Time a member still has to wait under slow mode
+    pub fn slow_mode_remaining(&self, user_id: Uuid, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        if self.settings.slow_mode_seconds == 0 {
+            return None;
+        }
+        let last = self.last_sent.get(&user_id)?;
+        let remaining = *last + chrono::Duration::seconds(self.settings.slow_mode_seconds as i64) - now;
+        Some(remaining).filter(|remaining| *remaining > chrono::Duration::zero())
+    }
+
+    /// Check a member may send now. The host is exempt from slow mode and timeouts
+    pub fn check_can_send(&self, user_id: Uuid, now: DateTime<Utc>) -> Result<()> {
+        if self.is_host(user_id) {
+            return Ok(());
+        }
+        if let Some(remaining) = self.timeout_remaining(user_id, now) {
+            let minutes = (remaining.num_seconds() + 59) / 60;
+            return Err(anyhow::anyhow!("You are timed out in this room for {} more minute(s)", minutes));
+        }
+        if let Some(remaining) = self.slow_mode_remaining(user_id, now) {
+            let seconds = (remaining.num_milliseconds() + 999) / 1000;
+            return Err(anyhow::anyhow!("Slow mode is on: wait {}s before sending again", seconds));
+        }
+        Ok(())
+    }
+
+    /// Add a message if its sender may send. Returns the oldest history pushed past the
+    /// cap, for the caller to archive
+    pub fn post(&mut self, message: ChatMessage, now: DateTime<Utc>) -> Result<Vec<ChatMessage>> {
+        self.check_can_send(message.sender_id, now)?;
+        self.settings.timeouts.retain(|_, until| *until > now);
+        self.last_sent.insert(message.sender_id, now);
+        self.messages.push(message);
+        let excess = self.messages.len().saturating_sub(CHAT_HISTORY_LIMIT);
+        Ok(self.messages.drain(..excess).collect())
+    }
+
+    /// Apply a moderation action (host only)
+    pub fn moderate(&mut self, user_id: Uuid, action: ChatModAction, now: DateTime<Utc>) -> Result<()> {
+        if !self.is_host(user_id) {
+            return Err(anyhow::anyhow!("Only the host can moderate this room"));
+        }
+        match action {
+            ChatModAction::SlowMode { seconds } => {
+                self.settings.slow_mode_seconds = seconds.min(MAX_SLOW_MODE_SECONDS);
+            }
+            ChatModAction::Timeout { member_id, minutes: 0 } => {
+                self.settings.timeouts.remove(&member_id);
+            }
+            ChatModAction::Timeout { member_id, minutes } => {
+                if self.is_host(member_id) {
+                    return Err(anyhow::anyhow!("The host can't be timed out"));
+                }
+                if !self.members.contains(&member_id) {
+                    return Err(anyhow::anyhow!("Not a member of this room"));
+                }
+                let until = now + chrono::Duration::minutes(minutes.min(MAX_TIMEOUT_MINUTES) as i64);
+                self.settings.timeouts.insert(member_id, until);
+            }
+            ChatModAction::Pin { message_id } => {
+                if self.settings.pinned.iter().any(|m| m.message_id == message_id) {
+                    return Ok(());
+                }
+                if self.settings.pinned.len() >= MAX_PINNED_MESSAGES {
+                    return Err(anyhow::anyhow!("At most {} messages can be pinned; unpin one first", MAX_PINNED_MESSAGES));
+                }
+                let message = self.messages.iter()
+                    .find(|m| m.message_id == message_id)
+                    .ok_or_else(|| anyhow::anyhow!("Message not found"))?;
+                // Pins keep their own copy so trimming the history never drops them
+                self.settings.pinned.push(message.clone());
+            }
+            ChatModAction::Unpin { message_id } => {
+                self.settings.pinned.retain(|m| m.message_id != message_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Lines shown above the messages: pinned messages, then notices for slow mode
+    /// and the viewer's own timeout
+    pub fn panel_header(&self, viewer: Uuid, now: DateTime<Utc>) -> Vec<String> {
+        let mut lines: Vec<String> = self.settings.pinned.iter()
+            .map(|m| format!("📌 {}: {}", m.sender_name, m.content))
+            .collect();
+        if self.settings.slow_mode_seconds > 0 {
+            lines.push(format!("Slow mode: one message every {}s", self.settings.slow_mode_seconds));
+        }
+        if let Some(remaining) = self.timeout_remaining(viewer, now) {
+            lines.push(format!("You are timed out for {} more minute(s)", (remaining.num_seconds() + 59) / 60));
+        }
+        lines
+    }
+
+    /// Moderation controls to show a viewer; empty for everyone but the host
+    pub fn moderation_controls(&self, viewer: Uuid) -> Vec<&'static str> {
+        if !self.is_host(viewer) {
+            return Vec::new();
+        }
+        vec!["Slow mode", "Time out member", "Pin message", "Unpin message"]
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,26 +236,6 @@ pub struct Achievement {
     pub condition: AchievementCondition,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum AchievementRarity {
-    Common,
-    Uncommon,
-    Rare,
-    Epic,
-    Legendary,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "condition_type", content = "data")]
-pub enum AchievementCondition {
-    TotalGames { count: u32 },
-    TotalScore { score: u64 },
-    PerfectGame,
-    FullCombo { combo: u32 },
-    Accuracy { min_accuracy: f64 },
-    FirstBlood,
-}
-
 /// User achievement progress
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserAchievement {
@@ -94,7 +259,7 @@ pub struct Tournament {
     pub rules: TournamentRules,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TournamentStatus {
     Registration,
     InProgress,
@@ -102,6 +267,18 @@ pub enum TournamentStatus {
     Cancelled,
 }
 
+impl TournamentStatus {
+    /// Name shown on a tournament's details
+    pub fn label(&self) -> &'static str {
+        match self {
+            TournamentStatus::Registration => "Registration open",
+            TournamentStatus::InProgress => "In progress",
+            TournamentStatus::Completed => "Completed",
+            TournamentStatus::Cancelled => "Cancelled",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TournamentRules {
     pub song_pool: Vec<String>,
@@ -109,13 +286,23 @@ pub struct TournamentRules {
     pub elimination_type: EliminationType,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ScoringType {
     ScoreV1,
     ScoreV2,
     Accuracy,
 }
 
+impl ScoringType {
+    /// Grade cutoffs results are graded with; accuracy matches use the Score V1 ones
+    pub fn grade_rules(&self) -> GradeRules {
+        match self {
+            ScoringType::ScoreV2 => GradeRules::SCORE_V2,
+            ScoringType::ScoreV1 | ScoringType::Accuracy => GradeRules::SCORE_V1,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EliminationType {
     SingleElimination,
@@ -129,24 +316,122 @@ pub enum EliminationType {
 pub struct Match {
     pub match_id: Uuid,
     pub tournament_id: Uuid,
+    /// The better seed of the two
     pub player1_id: Uuid,
-    pub player2_id: Uuid,
+    /// None for a bye
+    pub player2_id: Option<Uuid>,
     pub player1_score: u32,
     pub player2_score: u32,
     pub winner_id: Option<Uuid>,
     pub song: String,
     pub scheduled_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
+    /// Bracket round, 1 for the first
+    #[serde(default)]
+    pub round: u32,
+    /// Position in the round; slots 2k and 2k+1 feed slot k of the next round
+    #[serde(default)]
+    pub slot: u32,
+    /// Players who joined the match's room, for the no-show rule
+    #[serde(default)]
+    pub checked_in: Vec<Uuid>,
+    #[serde(default)]
+    pub outcome: MatchOutcome,
+}
+
+impl Match {
+    /// Whether a player is in this match
+    pub fn has_player(&self, player_id: Uuid) -> bool {
+        self.player1_id == player_id || self.player2_id == Some(player_id)
+    }
+}
+
+/// How a match was decided
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum MatchOutcome {
+    #[default]
+    Pending,
+    /// Played in a multiplayer room, or scored by hand
+    Played,
+    /// No opponent; player 1 went through
+    Bye,
+    /// Decided after the grace window; these players never showed up
+    Walkover { no_shows: Vec<Uuid> },
+}
+
+/// Chat history that fell out of memory, one JSON line per message in `<room_id>.jsonl`,
+/// oldest first. Read back a page at a time when a client scrolls up
+#[derive(Debug, Clone)]
+pub struct ChatArchive {
+    dir: PathBuf,
+}
+
+impl ChatArchive {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn room_path(&self, room_id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", room_id))
+    }
+
+    /// Append messages to the end of a room's archive
+    pub fn append(&self, room_id: Uuid, messages: &[ChatMessage]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(self.room_path(room_id))?;
+        for message in messages {
+            writeln!(file, "{}", serde_json::to_string(message)?)?;
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` archived messages, newest first, skipping the newest `skip`
+    pub fn page(&self, room_id: Uuid, skip: usize, limit: usize) -> Result<Vec<ChatMessage>> {
+        let contents = match std::fs::read_to_string(self.room_path(room_id)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        contents.lines().rev()
+            .skip(skip)
+            .take(limit)
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    }
+
+    /// Archived messages and their size on disk across all rooms
+    pub fn stats(&self) -> StoredFile {
+        let mut stats = StoredFile { name: "chat", bytes: 0, entries: 0 };
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return stats;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().is_some_and(|ext| ext == "jsonl") {
+                if let Ok(contents) = std::fs::read_to_string(&path) {
+                    stats.bytes += contents.len() as u64;
+                    stats.entries += contents.lines().count();
+                }
+            }
+        }
+        stats
+    }
 }
 
 /// Community manager
 #[derive(Debug, Clone)]
 pub struct CommunityManager {
     chat_rooms: Arc<RwLock<HashMap<Uuid, ChatRoom>>>,
+    /// Where history past `CHAT_HISTORY_LIMIT` goes; None drops it
+    chat_archive: Option<ChatArchive>,
     achievements: Arc<RwLock<HashMap<String, Achievement>>>,
     user_achievements: Arc<RwLock<HashMap<Uuid, HashMap<String, UserAchievement>>>>,
     tournaments: Arc<RwLock<HashMap<Uuid, Tournament>>>,
     matches: Arc<RwLock<HashMap<Uuid, Match>>>,
+    /// Ratings of downloaded maps by map id, one per account
+    map_ratings: Arc<RwLock<HashMap<String, HashMap<Uuid, MapRating>>>>,
 }
 
 impl CommunityManager {
@@ -154,123 +439,155 @@ impl CommunityManager {
     pub fn new() -> Self {
         Self {
             chat_rooms: Arc::new(RwLock::new(HashMap::new())),
+            chat_archive: None,
             achievements: Arc::new(RwLock::new(Self::init_achievements())),
             user_achievements: Arc::new(RwLock::new(HashMap::new())),
             tournaments: Arc::new(RwLock::new(HashMap::new())),
             matches: Arc::new(RwLock::new(HashMap::new())),
+            map_ratings: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    /// Initialize default achievements
-    fn init_achievements() -> HashMap<String, Achievement> {
-        let mut achievements = HashMap::new();
-
-        achievements.insert("first_game".to_string(), Achievement {
-            achievement_id: "first_game".to_string(),
-            name: "First Steps".to_string(),
-            description: "Complete your first game".to_string(),
-            icon_url: Some("achievements/first_game.png".to_string()),
-            rarity: AchievementRarity::Common,
-            condition: AchievementCondition::TotalGames { count: 1 },
-        });
-
-        achievements.insert("hundred_games".to_string(), Achievement {
-            achievement_id: "hundred_games".to_string(),
-            name: "Century Club".to_string(),
-            description: "Complete 100 games".to_string(),
-            icon_url: Some("achievements/hundred_games.png".to_string()),
-            rarity: AchievementRarity::Rare,
-            condition: AchievementCondition::TotalGames { count: 100 },
-        });
-
-        achievements.insert("million_score".to_string(), Achievement {
-            achievement_id: "million_score".to_string(),
-            name: "Millionaire".to_string(),
-            description: "Reach 1,000,000 total score".to_string(),
-            icon_url: Some("achievements/million_score.png".to_string()),
-            rarity: AchievementRarity::Epic,
-            condition: AchievementCondition::TotalScore { score: 1_000_000 },
-        });
-
-        achievements.insert("perfect_game".to_string(), Achievement {
-            achievement_id: "perfect_game".to_string(),
-            name: "Perfectionist".to_string(),
-            description: "Complete a song with no misses and perfect accuracy".to_string(),
-            icon_url: Some("achievements/perfect_game.png".to_string()),
-            rarity: AchievementRarity::Epic,
-            condition: AchievementCondition::PerfectGame,
-        });
-
-        achievements.insert("full_combo_100".to_string(), Achievement {
-            achievement_id: "full_combo_100".to_string(),
-            name: "Unstoppable".to_string(),
-            description: "Achieve a 100x combo".to_string(),
-            icon_url: Some("achievements/full_combo_100.png".to_string()),
-            rarity: AchievementRarity::Rare,
-            condition: AchievementCondition::FullCombo { combo: 100 },
-        });
-
-        achievements.insert("accuracy_95".to_string(), Achievement {
-            achievement_id: "accuracy_95".to_string(),
-            name: "Precision Master".to_string(),
-            description: "Achieve 95% accuracy in a game".to_string(),
-            icon_url: Some("achievements/accuracy_95.png".to_string()),
-            rarity: AchievementRarity::Uncommon,
-            condition: AchievementCondition::Accuracy { min_accuracy: 95.0 },
-        });
+    /// Keep chat history past `CHAT_HISTORY_LIMIT` in per-room files under `dir`
+    pub fn with_chat_archive(mut self, dir: PathBuf) -> Self {
+        self.chat_archive = Some(ChatArchive::new(dir));
+        self
+    }
 
-        achievements
+    /// Build the achievement catalog from the shared definitions file
+    fn init_achievements() -> HashMap<String, Achievement> {
+        AchievementDefinitions::load()
+            .definitions
+            .into_iter()
+            .map(|definition| {
+                let achievement = Achievement {
+                    icon_url: Some(format!("achievements/{}.png", definition.id)),
+                    achievement_id: definition.id.clone(),
+                    name: definition.name,
+                    description: definition.description,
+                    rarity: definition.rarity,
+                    condition: definition.condition,
+                };
+                (definition.id, achievement)
+            })
+            .collect()
     }
 
-    /// Create a chat room
-    pub async fn create_chat_room(&self, name: String, room_type: ChatRoomType, members: Vec<Uuid>) -> Uuid {
-        let room_id = Uuid::new_v4();
-        let room = ChatRoom {
-            room_id,
-            name,
-            room_type,
-            members,
-            messages: Vec::new(),
-            created_at: Utc::now(),
-        };
+    /// Create a chat room, moderated by `host_id` (the room host or tournament organizer) if given
+    pub async fn create_chat_room(&self, name: String, room_type: ChatRoomType, members: Vec<Uuid>, host_id: Option<Uuid>) -> Uuid {
+        let room = ChatRoom::new(name, room_type, members, host_id);
+        let room_id = room.room_id;
         self.chat_rooms.write().unwrap().insert(room_id, room);
         room_id
     }
 
-    /// Send a message to a chat room
+    /// The public lobby room, created the first time it is asked for
+    pub fn lobby_room(&self) -> Uuid {
+        let mut rooms = self.chat_rooms.write().unwrap();
+        let existing = rooms.values()
+            .find(|room| matches!(room.room_type, ChatRoomType::Public) && room.name == LOBBY_ROOM_NAME);
+        if let Some(room) = existing {
+            return room.room_id;
+        }
+        let room = ChatRoom::new(LOBBY_ROOM_NAME.to_string(), ChatRoomType::Public, Vec::new(), None);
+        let room_id = room.room_id;
+        rooms.insert(room_id, room);
+        room_id
+    }
+
+    /// Send a message to a chat room. Slow mode and timeouts are enforced here; the
+    /// error is meant for the sender's chat panel
     pub async fn send_message(&self, room_id: Uuid, sender_id: Uuid, sender_name: String, content: String) -> Result<()> {
+        self.send_message_sync(room_id, sender_id, sender_name, content)
+    }
+
+    /// Send a message without awaiting, for callers outside the async runtime. Blank
+    /// messages are refused
+    pub fn send_message_sync(&self, room_id: Uuid, sender_id: Uuid, sender_name: String, content: String) -> Result<()> {
+        let content = content.trim().to_string();
+        if content.is_empty() {
+            return Err(anyhow::anyhow!("Message is empty"));
+        }
         let mut rooms = self.chat_rooms.write().unwrap();
         if let Some(room) = rooms.get_mut(&room_id) {
+            let now = Utc::now();
             let message = ChatMessage {
                 message_id: Uuid::new_v4(),
                 sender_id,
                 sender_name,
                 content,
-                timestamp: Utc::now(),
+                timestamp: now,
                 room_id: Some(room_id),
                 recipient_id: None,
             };
-            room.messages.push(message);
+            let spilled = room.post(message, now)?;
+            if let Some(archive) = &self.chat_archive {
+                // History is best effort; the message itself went through
+                if let Err(e) = archive.append(room_id, &spilled) {
+                    eprintln!("Failed to archive chat history for room {}: {}", room_id, e);
+                }
+            }
             Ok(())
         } else {
             Err(anyhow::anyhow!("Chat room not found"))
         }
     }
 
+    /// Apply a host's moderation action, returning the settings to broadcast with the room
+    pub async fn moderate_chat(&self, room_id: Uuid, user_id: Uuid, action: ChatModAction) -> Result<ChatRoomSettings> {
+        self.moderate_chat_sync(room_id, user_id, action)
+    }
+
+    /// Apply a moderation action without awaiting, for callers outside the async runtime
+    pub fn moderate_chat_sync(&self, room_id: Uuid, user_id: Uuid, action: ChatModAction) -> Result<ChatRoomSettings> {
+        let mut rooms = self.chat_rooms.write().unwrap();
+        let room = rooms.get_mut(&room_id).ok_or_else(|| anyhow::anyhow!("Chat room not found"))?;
+        room.moderate(user_id, action, Utc::now())?;
+        Ok(room.settings.clone())
+    }
+
+    /// Get a room's host and moderation settings
+    pub async fn get_chat_room_state(&self, room_id: Uuid) -> Option<(Option<Uuid>, ChatRoomSettings)> {
+        self.chat_rooms.read().unwrap()
+            .get(&room_id)
+            .map(|room| (room.host_id, room.settings.clone()))
+    }
+
+    /// A room as it is at this moment, for a chat panel to show its settings
+    pub fn chat_room_snapshot(&self, room_id: Uuid) -> Option<ChatRoom> {
+        self.chat_rooms.read().unwrap().get(&room_id).cloned()
+    }
+
+    /// Open a tournament's lobby chat, moderated by its organizer. The room shares the
+    /// tournament's id, and players joining the tournament become members
+    pub fn open_tournament_chat(&self, tournament_id: Uuid, name: String, organizer_id: Uuid) -> Uuid {
+        let mut room = ChatRoom::new(name, ChatRoomType::Lobby, vec![organizer_id], Some(organizer_id));
+        room.room_id = tournament_id;
+        self.chat_rooms.write().unwrap().insert(tournament_id, room);
+        tournament_id
+    }
+
+    /// Lobby rooms a player is a member of, by name
+    pub fn member_lobby_rooms(&self, user_id: Uuid) -> Vec<(Uuid, String)> {
+        let rooms = self.chat_rooms.read().unwrap();
+        let mut joined: Vec<&ChatRoom> = rooms.values()
+            .filter(|room| matches!(room.room_type, ChatRoomType::Lobby) && room.members.contains(&user_id))
+            .collect();
+        joined.sort_by_key(|room| room.created_at);
+        joined.into_iter().map(|room| (room.room_id, room.name.clone())).collect()
+    }
+
     /// Send a direct message
     pub async fn send_direct_message(&self, sender_id: Uuid, sender_name: String, recipient_id: Uuid, content: String) {
         // Create a direct chat room if it doesn't exist
         let mut rooms = self.chat_rooms.write().unwrap();
-        let room_id = Uuid::new_v4();
-
-        let room = ChatRoom {
-            room_id,
-            name: format!("DM: {}", recipient_id),
-            room_type: ChatRoomType::Direct,
-            members: vec![sender_id, recipient_id],
-            messages: Vec::new(),
-            created_at: Utc::now(),
-        };
+        let mut room = ChatRoom::new(
+            format!("DM: {}", recipient_id),
+            ChatRoomType::Direct,
+            vec![sender_id, recipient_id],
+            None,
+        );
+        let room_id = room.room_id;
 
         let message = ChatMessage {
             message_id: Uuid::new_v4(),
@@ -296,6 +613,59 @@ impl CommunityManager {
         }
     }
 
+    /// The newest `limit` messages of a room at this moment, oldest first as a chat
+    /// panel lists them, without awaiting
+    pub fn messages_snapshot(&self, room_id: Uuid, limit: usize) -> Vec<ChatMessage> {
+        let rooms = self.chat_rooms.read().unwrap();
+        rooms.get(&room_id).map_or_else(Vec::new, |room| {
+            let start = room.messages.len().saturating_sub(limit);
+            room.messages[start..].to_vec()
+        })
+    }
+
+    /// Page further back through a room's history, newest first, for a client that
+    /// already has the newest `loaded` messages. Pages past the in-memory history are
+    /// read from the room's archive
+    pub async fn get_older_messages(&self, room_id: Uuid, loaded: usize, limit: usize) -> Result<Vec<ChatMessage>> {
+        let (mut page, in_memory) = {
+            let rooms = self.chat_rooms.read().unwrap();
+            let room = rooms.get(&room_id).ok_or_else(|| anyhow::anyhow!("Chat room not found"))?;
+            let page: Vec<ChatMessage> = room.messages.iter().rev().skip(loaded).take(limit).cloned().collect();
+            (page, room.messages.len())
+        };
+        if page.len() < limit {
+            if let Some(archive) = &self.chat_archive {
+                let skip = loaded.saturating_sub(in_memory);
+                page.extend(archive.page(room_id, skip, limit - page.len())?);
+            }
+        }
+        Ok(page)
+    }
+
+    /// Archived chat history on disk, for the storage summary
+    pub fn chat_storage(&self) -> Option<StoredFile> {
+        self.chat_archive.as_ref().map(ChatArchive::stats)
+    }
+
+    /// Record a player's rating of a map, replacing their earlier one, and return the
+    /// map's new aggregate to broadcast
+    pub fn rate_map(&self, rating: MapRating) -> Result<MapRatingSummary> {
+        if !(1..=5).contains(&rating.stars) {
+            return Err(anyhow::anyhow!("Ratings are 1 to 5 stars"));
+        }
+        let mut ratings = self.map_ratings.write().unwrap();
+        let map = ratings.entry(rating.map_id.clone()).or_default();
+        let map_id = rating.map_id.clone();
+        map.insert(rating.user_id, rating);
+        Ok(MapRatingSummary::of(&map_id, map.values()))
+    }
+
+    /// Aggregate rating of a map
+    pub fn map_rating_summary(&self, map_id: &str) -> MapRatingSummary {
+        let ratings = self.map_ratings.read().unwrap();
+        MapRatingSummary::of(map_id, ratings.get(map_id).into_iter().flat_map(|map| map.values()))
+    }
+
     /// Get all available achievements
     pub fn get_all_achievements(&self) -> Vec<Achievement> {
         self.achievements.read().unwrap().values().cloned().collect()
@@ -309,13 +679,31 @@ impl CommunityManager {
             .unwrap_or_default()
     }
 
+    /// Locked achievements for a profile with their progress, closest to unlocking first
+    pub fn locked_achievements(&self, user_id: Uuid, stats: &UserStats) -> Vec<(Achievement, Option<AchievementProgress>)> {
+        let achievement_stats = account_achievement_stats(stats);
+        let user_map = self.get_user_achievements(user_id);
+        let mut locked: Vec<_> = self.achievements.read().unwrap()
+            .values()
+            .filter(|achievement| {
+                user_map.get(&achievement.achievement_id)
+                    .is_none_or(|user_ach| user_ach.unlocked_at.is_none())
+            })
+            .map(|achievement| (achievement.clone(), achievement.condition.progress(&achievement_stats)))
+            .collect();
+        let fraction = |progress: &Option<AchievementProgress>| progress.map_or(0.0, |p| p.fraction());
+        locked.sort_by(|a, b| fraction(&b.1).total_cmp(&fraction(&a.1)));
+        locked
+    }
+
     /// Check and unlock achievements based on user stats
     pub async fn check_achievements(&self, user_id: Uuid, stats: &UserStats) -> Vec<String> {
         let mut unlocked = Vec::new();
+        let achievement_stats = account_achievement_stats(stats);
         let mut user_achievements = self.user_achievements.write().unwrap();
 
         // Get or create user's achievement map
-        let user_map = user_achievements.entry(user_id).or_insert_with(HashMap::new);
+        let user_map = user_achievements.entry(user_id).or_default();
 
         // Check each achievement
         for (achievement_id, achievement) in self.achievements.read().unwrap().iter() {
@@ -327,21 +715,19 @@ impl CommunityManager {
             }
 
             // Check achievement condition
-            let unlocked = match &achievement.condition {
-                AchievementCondition::TotalGames { count } => stats.total_games >= *count,
-                AchievementCondition::TotalScore { score } => stats.total_score >= *score,
-                AchievementCondition::PerfectGame => stats.misses == 0 && stats.average_accuracy == 100.0,
-                AchievementCondition::FullCombo { combo } => stats.highest_combo >= *combo,
-                AchievementCondition::Accuracy { min_accuracy } => stats.best_accuracy >= *min_accuracy,
-            };
-
-            if unlocked {
+            if achievement.condition.is_met(&achievement_stats) {
                 user_map.insert(achievement_id.clone(), UserAchievement {
                     achievement_id: achievement_id.clone(),
                     unlocked_at: Some(Utc::now()),
                     progress: 100.0,
                 });
                 unlocked.push(achievement.name.clone());
+            } else if let Some(progress) = achievement.condition.progress(&achievement_stats) {
+                user_map.insert(achievement_id.clone(), UserAchievement {
+                    achievement_id: achievement_id.clone(),
+                    unlocked_at: None,
+                    progress: progress.fraction() as f64 * 100.0,
+                });
             }
         }
 
@@ -356,6 +742,18 @@ impl CommunityManager {
         max_players: u32,
         starts_at: DateTime<Utc>,
         rules: TournamentRules
+    ) -> Uuid {
+        self.create_tournament_sync(name, description, max_players, starts_at, rules)
+    }
+
+    /// Create a tournament without awaiting, open for registration
+    pub fn create_tournament_sync(
+        &self,
+        name: String,
+        description: String,
+        max_players: u32,
+        starts_at: DateTime<Utc>,
+        rules: TournamentRules
     ) -> Uuid {
         let tournament_id = Uuid::new_v4();
         let tournament = Tournament {
@@ -376,6 +774,12 @@ impl CommunityManager {
 
     /// Join a tournament
     pub async fn join_tournament(&self, tournament_id: Uuid, player_id: Uuid) -> Result<()> {
+        self.join_tournament_sync(tournament_id, player_id)
+    }
+
+    /// Join a tournament without awaiting, for callers outside the async runtime. Fails
+    /// with "Tournament is full" or "Player already registered" for a panel to show
+    pub fn join_tournament_sync(&self, tournament_id: Uuid, player_id: Uuid) -> Result<()> {
         let mut tournaments = self.tournaments.write().unwrap();
         if let Some(tournament) = tournaments.get_mut(&tournament_id) {
             if tournament.status != TournamentStatus::Registration {
@@ -389,21 +793,47 @@ impl CommunityManager {
             }
 
             tournament.players.push(player_id);
+            if let Some(room) = self.chat_rooms.write().unwrap().get_mut(&tournament_id) {
+                if !room.members.contains(&player_id) {
+                    room.members.push(player_id);
+                }
+            }
             Ok(())
         } else {
             Err(anyhow::anyhow!("Tournament not found"))
         }
     }
 
-    /// Start a tournament
-    pub async fn start_tournament(&self, tournament_id: Uuid) -> Result<()> {
+    /// Start a tournament and draw the first round of its bracket, seeded in
+    /// registration order
+    pub async fn start_tournament(&self, tournament_id: Uuid) -> Result<NetworkMessage> {
+        self.start_tournament_sync(tournament_id)?;
+        // Every first round has at least one real match, so nothing advances yet
+        Ok(self.bracket_update(tournament_id))
+    }
+
+    /// Start a tournament without awaiting; `get_bracket` has the drawn first round
+    pub fn start_tournament_sync(&self, tournament_id: Uuid) -> Result<()> {
         let mut tournaments = self.tournaments.write().unwrap();
-        if let Some(tournament) = tournaments.get_mut(&tournament_id) {
-            tournament.status = TournamentStatus::InProgress;
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Tournament not found"))
+        let tournament = tournaments.get_mut(&tournament_id)
+            .ok_or_else(|| anyhow::anyhow!("Tournament not found"))?;
+        if tournament.status != TournamentStatus::Registration {
+            return Err(anyhow::anyhow!("Tournament has already started"));
+        }
+        let first_round = bracket::first_round(
+            tournament_id,
+            &tournament.players,
+            &tournament.rules.elimination_type,
+            &tournament.rules.song_pool,
+            tournament.starts_at.max(Utc::now()),
+        )?;
+        tournament.status = TournamentStatus::InProgress;
+
+        let mut matches = self.matches.write().unwrap();
+        for game_match in first_round {
+            matches.insert(game_match.match_id, game_match);
         }
+        Ok(())
     }
 
     /// Create a match
@@ -420,13 +850,17 @@ impl CommunityManager {
             match_id,
             tournament_id,
             player1_id,
-            player2_id,
+            player2_id: Some(player2_id),
             player1_score: 0,
             player2_score: 0,
             winner_id: None,
             song,
             scheduled_at,
             completed_at: None,
+            round: 0,
+            slot: 0,
+            checked_in: Vec::new(),
+            outcome: MatchOutcome::Pending,
         };
         self.matches.write().unwrap().insert(match_id, game_match);
         match_id
@@ -443,7 +877,7 @@ impl CommunityManager {
             if player1_score > player2_score {
                 game_match.winner_id = Some(game_match.player1_id);
             } else if player2_score > player1_score {
-                game_match.winner_id = Some(game_match.player2_id);
+                game_match.winner_id = game_match.player2_id;
             }
 
             Ok(())
@@ -457,6 +891,9 @@ impl CommunityManager {
         let mut matches = self.matches.write().unwrap();
         if let Some(game_match) = matches.get_mut(&match_id) {
             game_match.completed_at = Some(Utc::now());
+            if game_match.outcome == MatchOutcome::Pending {
+                game_match.outcome = MatchOutcome::Played;
+            }
             Ok(())
         } else {
             Err(anyhow::anyhow!("Match not found"))
@@ -465,28 +902,200 @@ impl CommunityManager {
 
     /// Get tournament info
     pub async fn get_tournament(&self, tournament_id: Uuid) -> Option<Tournament> {
-        self.tournaments.read().await.get(&tournament_id).cloned()
+        self.tournament_snapshot(tournament_id)
+    }
+
+    /// A tournament as it is now, without awaiting
+    pub fn tournament_snapshot(&self, tournament_id: Uuid) -> Option<Tournament> {
+        self.tournaments.read().unwrap().get(&tournament_id).cloned()
     }
 
     /// Get all active tournaments
     pub async fn get_active_tournaments(&self) -> Vec<Tournament> {
-        self.tournaments.read().await.values()
+        self.active_tournaments_snapshot()
+    }
+
+    /// Tournaments in registration or in progress, soonest start first, without awaiting
+    pub fn active_tournaments_snapshot(&self) -> Vec<Tournament> {
+        let mut active: Vec<Tournament> = self.tournaments.read().unwrap().values()
             .filter(|t| t.status == TournamentStatus::Registration || t.status == TournamentStatus::InProgress)
             .cloned()
-            .collect()
+            .collect();
+        active.sort_by_key(|t| t.starts_at);
+        active
     }
 
     /// Get match info
     pub async fn get_match(&self, match_id: Uuid) -> Option<Match> {
-        self.matches.read().await.get(&match_id).cloned()
+        self.matches.read().unwrap().get(&match_id).cloned()
     }
 
     /// Get player's matches
     pub async fn get_player_matches(&self, player_id: Uuid) -> Vec<Match> {
-        self.matches.read().await.values()
-            .filter(|m| m.player1_id == player_id || m.player2_id == player_id)
+        self.player_matches_snapshot(player_id)
+    }
+
+    /// A player's matches in every tournament, soonest first, without awaiting
+    pub fn player_matches_snapshot(&self, player_id: Uuid) -> Vec<Match> {
+        let mut matches: Vec<Match> = self.matches.read().unwrap().values()
+            .filter(|m| m.has_player(player_id))
             .cloned()
-            .collect()
+            .collect();
+        matches.sort_by_key(|m| m.scheduled_at);
+        matches
+    }
+
+    /// Mark a player as present for a match, when they join its room
+    pub async fn check_in(&self, match_id: Uuid, player_id: Uuid) -> Result<()> {
+        let mut matches = self.matches.write().unwrap();
+        let game_match = matches.get_mut(&match_id)
+            .ok_or_else(|| anyhow::anyhow!("Match not found"))?;
+        if !game_match.has_player(player_id) {
+            return Err(anyhow::anyhow!("Player is not in this match"));
+        }
+        if !game_match.checked_in.contains(&player_id) {
+            game_match.checked_in.push(player_id);
+        }
+        Ok(())
+    }
+
+    /// Feed an ended multiplayer game into its tournament match, scored by the
+    /// tournament's rules, and advance the bracket. None for games that aren't
+    /// tournament matches or whose match was already decided (e.g. by walkover)
+    pub async fn record_multiplayer_result(&self, game: &MultiplayerGameState) -> Result<Option<NetworkMessage>> {
+        let Some(match_id) = game.match_id else {
+            return Ok(None);
+        };
+        let tournament_id = {
+            let tournaments = self.tournaments.read().unwrap();
+            let mut matches = self.matches.write().unwrap();
+            let game_match = matches.get_mut(&match_id)
+                .ok_or_else(|| anyhow::anyhow!("Match not found"))?;
+            if bracket::is_decided(game_match) {
+                return Ok(None);
+            }
+            let scoring_type = tournaments.get(&game_match.tournament_id)
+                .map(|t| t.rules.scoring_type)
+                .ok_or_else(|| anyhow::anyhow!("Tournament not found"))?;
+
+            // A player who left before the end has no result and loses
+            let player1 = game.players.get(&game_match.player1_id);
+            let player2 = game_match.player2_id.and_then(|id| game.players.get(&id));
+            let score = |player: Option<_>| player.map_or(0, |p| bracket::match_score(p, scoring_type));
+            let (player1_score, player2_score) = (score(player1), score(player2));
+            let winner_id = match (player1, player2) {
+                (None, None) => return Err(anyhow::anyhow!("Neither player has a result")),
+                (Some(_), None) => game_match.player1_id,
+                (None, Some(_)) => game_match.player2_id.unwrap_or(game_match.player1_id),
+                (Some(p1), Some(p2)) if player1_score == player2_score => {
+                    bracket::break_tie(game_match, p1, p2, scoring_type)
+                }
+                (Some(_), Some(_)) if player1_score > player2_score => game_match.player1_id,
+                (Some(_), Some(_)) => game_match.player2_id.unwrap_or(game_match.player1_id),
+            };
+
+            game_match.player1_score = player1_score;
+            game_match.player2_score = player2_score;
+            game_match.winner_id = Some(winner_id);
+            game_match.completed_at = Some(Utc::now());
+            game_match.outcome = MatchOutcome::Played;
+            game_match.tournament_id
+        };
+        self.advance_tournament(tournament_id).await.map(Some)
+    }
+
+    /// Move a tournament on once its latest round is decided: draw the next round, or
+    /// complete the tournament after the final. Returns the bracket either way
+    pub async fn advance_tournament(&self, tournament_id: Uuid) -> Result<NetworkMessage> {
+        {
+            let mut tournaments = self.tournaments.write().unwrap();
+            let tournament = tournaments.get_mut(&tournament_id)
+                .ok_or_else(|| anyhow::anyhow!("Tournament not found"))?;
+            if tournament.status != TournamentStatus::InProgress {
+                return Err(anyhow::anyhow!("Tournament is not in progress"));
+            }
+
+            let mut matches = self.matches.write().unwrap();
+            let own: Vec<Match> = matches.values()
+                .filter(|m| m.tournament_id == tournament_id)
+                .cloned()
+                .collect();
+            let latest = bracket::rounds(&own).pop().unwrap_or_default();
+            if !latest.is_empty() && latest.iter().all(bracket::is_decided) {
+                match bracket::next_round(
+                    tournament_id,
+                    &latest,
+                    &tournament.rules.elimination_type,
+                    &tournament.rules.song_pool,
+                    Utc::now(),
+                )? {
+                    Some(next) => {
+                        for game_match in next {
+                            matches.insert(game_match.match_id, game_match);
+                        }
+                    }
+                    None => {
+                        tournament.status = TournamentStatus::Completed;
+                        tournament.ends_at = Some(Utc::now());
+                    }
+                }
+            }
+        }
+        Ok(self.bracket_update(tournament_id))
+    }
+
+    /// Decide unplayed matches whose check-in window has run out, then advance their
+    /// tournaments. Returns the updated brackets
+    pub async fn resolve_forfeits(&self, now: DateTime<Utc>) -> Vec<NetworkMessage> {
+        let mut affected = HashSet::new();
+        {
+            let tournaments = self.tournaments.read().unwrap();
+            let mut matches = self.matches.write().unwrap();
+            for game_match in matches.values_mut() {
+                let in_progress = tournaments.get(&game_match.tournament_id)
+                    .is_some_and(|t| t.status == TournamentStatus::InProgress);
+                if !in_progress {
+                    continue;
+                }
+                let Some(winner_id) = bracket::walkover_winner(game_match, now) else {
+                    continue;
+                };
+                let no_shows = [Some(game_match.player1_id), game_match.player2_id]
+                    .into_iter()
+                    .flatten()
+                    .filter(|id| !game_match.checked_in.contains(id))
+                    .collect();
+                game_match.winner_id = Some(winner_id);
+                game_match.completed_at = Some(now);
+                game_match.outcome = MatchOutcome::Walkover { no_shows };
+                affected.insert(game_match.tournament_id);
+            }
+        }
+
+        let mut updates = Vec::new();
+        for tournament_id in affected {
+            match self.advance_tournament(tournament_id).await {
+                Ok(update) => updates.push(update),
+                Err(e) => eprintln!("Could not advance tournament {}: {}", tournament_id, e),
+            }
+        }
+        updates
+    }
+
+    /// A tournament's matches grouped by round
+    pub fn get_bracket(&self, tournament_id: Uuid) -> Vec<Vec<Match>> {
+        let own: Vec<Match> = self.matches.read().unwrap().values()
+            .filter(|m| m.tournament_id == tournament_id)
+            .cloned()
+            .collect();
+        bracket::rounds(&own)
+    }
+
+    fn bracket_update(&self, tournament_id: Uuid) -> NetworkMessage {
+        NetworkMessage::BracketUpdate {
+            tournament_id,
+            rounds: self.get_bracket(tournament_id),
+        }
     }
 }
 
@@ -495,3 +1104,1140 @@ impl Default for CommunityManager {
         Self::new()
     }
 }
+
+/// Achievement stats of a server account. Full combos per song and session history
+/// aren't tracked server-side, so full combo, streak and speed conditions never
+/// progress here
+pub fn account_achievement_stats(stats: &UserStats) -> AchievementStats {
+    let mut grades: Vec<Grade> = Vec::new();
+    for song in stats.songs_played.values() {
+        for (grade, count) in &song.grade_counts {
+            if let Some(grade) = Grade::from_name(grade) {
+                if *count > 0 && !grades.contains(&grade) {
+                    grades.push(grade);
+                }
+            }
+        }
+    }
+    AchievementStats {
+        total_games: stats.total_games,
+        total_score: stats.total_score,
+        highest_combo: stats.highest_combo,
+        best_accuracy: stats.best_accuracy,
+        play_time_seconds: stats.play_time_seconds,
+        perfect_games: if stats.misses == 0 && stats.average_accuracy == 100.0 && stats.total_games > 0 { 1 } else { 0 },
+        songs_full_combo: 0,
+        songs_played: stats.songs_played.len() as u32,
+        session_accuracies: Vec::new(),
+        fastest_full_combo: 0.0,
+        grades,
+        multiplayer_wins: 0,
+    }
+}
+
+/// Where filed reports are kept until the server has them
+pub const REPORTS_PATH: &str = "reports.json";
+
+/// Why a user is being reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Cheating,
+    InappropriateName,
+    Other,
+}
+
+impl ReportReason {
+    /// All reasons in menu order
+    pub fn all() -> Vec<ReportReason> {
+        vec![
+            ReportReason::Spam,
+            ReportReason::Harassment,
+            ReportReason::Cheating,
+            ReportReason::InappropriateName,
+            ReportReason::Other,
+        ]
+    }
+
+    /// Get display name for the reason
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ReportReason::Spam => "Spam",
+            ReportReason::Harassment => "Harassment",
+            ReportReason::Cheating => "Cheating",
+            ReportReason::InappropriateName => "Inappropriate name",
+            ReportReason::Other => "Other",
+        }
+    }
+}
+
+/// A report against a user, optionally pointing at chat messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserReport {
+    pub report_id: Uuid,
+    pub reporter_id: Uuid,
+    pub target_id: Uuid,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub message_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserReport {
+    /// Report a user from their profile
+    pub fn new(reporter_id: Uuid, target_id: Uuid, reason: ReportReason, details: Option<String>) -> Self {
+        Self {
+            report_id: Uuid::new_v4(),
+            reporter_id,
+            target_id,
+            reason,
+            details: details.filter(|d| !d.trim().is_empty()),
+            message_ids: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Report the sender of a chat message, attaching the message
+    pub fn for_message(reporter_id: Uuid, message: &ChatMessage, reason: ReportReason, details: Option<String>) -> Self {
+        let mut report = Self::new(reporter_id, message.sender_id, reason, details);
+        report.message_ids.push(message.message_id);
+        report
+    }
+}
+
+/// Reports waiting to be sent, persisted so they survive going offline
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportQueue {
+    pub pending: Vec<UserReport>,
+}
+
+impl ReportQueue {
+    /// Load queued reports, starting empty if there are none
+    pub fn load() -> Self {
+        std::fs::read_to_string(REPORTS_PATH)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save queued reports to disk
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(REPORTS_PATH, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Queue a report and try to send it right away
+    pub fn submit(&mut self, report: UserReport, client: Option<&GameClient>) -> Result<()> {
+        self.pending.push(report);
+        if let Some(client) = client {
+            self.flush(client);
+        }
+        self.save()
+    }
+
+    /// Send every queued report; ones that fail stay queued. Returns how many were sent
+    pub fn flush(&mut self, client: &GameClient) -> usize {
+        let before = self.pending.len();
+        self.pending.retain(|report| {
+            client
+                .send(NetworkMessage::Report { report: report.clone() })
+                .is_err()
+        });
+        before - self.pending.len()
+    }
+}
+
+/// Where ratings waiting to be sent are kept
+pub const RATINGS_PATH: &str = "ratings.json";
+
+/// Quick tag a player can add to a map rating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MapTag {
+    Fun,
+    Overmapped,
+    TimingOff,
+}
+
+impl MapTag {
+    pub const ALL: [MapTag; 3] = [MapTag::Fun, MapTag::Overmapped, MapTag::TimingOff];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MapTag::Fun => "fun",
+            MapTag::Overmapped => "overmapped",
+            MapTag::TimingOff => "timing off",
+        }
+    }
+}
+
+/// A player's rating of a downloaded community map, with how their play went
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapRating {
+    /// Archive/source id the map was downloaded under
+    pub map_id: String,
+    pub user_id: Uuid,
+    /// 1 to 5
+    pub stars: u8,
+    pub tags: Vec<MapTag>,
+    pub accuracy: f64,
+    pub max_combo: u32,
+    pub completed: bool,
+    pub rated_at: DateTime<Utc>,
+}
+
+impl MapRating {
+    /// Rate a map after a play; stars are clamped to 1-5 and tags deduplicated
+    pub fn new(map_id: String, user_id: Uuid, stars: u8, tags: &[MapTag], accuracy: f64, max_combo: u32, completed: bool) -> Self {
+        let mut tags = tags.to_vec();
+        tags.sort();
+        tags.dedup();
+        Self {
+            map_id,
+            user_id,
+            stars: stars.clamp(1, 5),
+            tags,
+            accuracy,
+            max_combo,
+            completed,
+            rated_at: Utc::now(),
+        }
+    }
+
+    /// One rating per map per account: a rating with the same key replaces the old one
+    pub fn dedup_key(&self) -> (&str, Uuid) {
+        (&self.map_id, self.user_id)
+    }
+}
+
+/// Aggregate rating of a map, shown on its entry in the map browser
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapRatingSummary {
+    pub map_id: String,
+    /// Mean stars, 0 if unrated
+    pub average: f32,
+    pub count: usize,
+    pub tag_counts: BTreeMap<MapTag, usize>,
+}
+
+impl MapRatingSummary {
+    /// Summarize the ratings of one map
+    pub fn of<'a>(map_id: &str, ratings: impl IntoIterator<Item = &'a MapRating>) -> Self {
+        let mut summary = Self { map_id: map_id.to_string(), ..Self::default() };
+        let mut stars = 0u32;
+        for rating in ratings {
+            stars += rating.stars as u32;
+            summary.count += 1;
+            for tag in &rating.tags {
+                *summary.tag_counts.entry(*tag).or_default() += 1;
+            }
+        }
+        if summary.count > 0 {
+            summary.average = stars as f32 / summary.count as f32;
+        }
+        summary
+    }
+
+    /// e.g. "4.2 stars (31) | fun 12, overmapped 3"
+    pub fn label(&self) -> String {
+        if self.count == 0 {
+            return "Not rated yet".to_string();
+        }
+        let mut label = format!("{:.1} stars ({})", self.average, self.count);
+        let tags: Vec<String> = self.tag_counts.iter()
+            .map(|(tag, count)| format!("{} {}", tag.label(), count))
+            .collect();
+        if !tags.is_empty() {
+            label.push_str(&format!(" | {}", tags.join(", ")));
+        }
+        label
+    }
+}
+
+/// Ratings waiting for the server, persisted so they survive going offline like reports.
+/// A rating stays queued until the server answers with the map's new aggregate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatingQueue {
+    pub pending: Vec<MapRating>,
+    /// Where the queue is saved
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl RatingQueue {
+    /// Load queued ratings, starting empty if there are none
+    pub fn load() -> Self {
+        Self::load_from(PathBuf::from(RATINGS_PATH))
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        let pending = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<RatingQueue>(&contents).ok())
+            .map(|queue| queue.pending)
+            .unwrap_or_default();
+        Self { pending, path }
+    }
+
+    /// Save queued ratings to disk
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Queue a rating, replacing a queued one for the same map and account, and try to
+    /// send it right away
+    pub fn submit(&mut self, rating: MapRating, client: Option<&GameClient>) -> Result<()> {
+        self.pending.retain(|queued| queued.dedup_key() != rating.dedup_key());
+        self.pending.push(rating);
+        if let Some(client) = client {
+            self.flush(client);
+        }
+        self.save()
+    }
+
+    /// Send every queued rating. They stay queued until acknowledged, since a message
+    /// handed to the client can still be lost with the connection. Returns how many were sent
+    pub fn flush(&self, client: &GameClient) -> usize {
+        self.pending
+            .iter()
+            .filter(|rating| client.send(NetworkMessage::RateMap { rating: (*rating).clone() }).is_ok())
+            .count()
+    }
+
+    /// The server stored our rating of `summary.map_id`; drop it from the queue.
+    /// Returns whether anything was waiting for that map
+    pub fn acknowledge(&mut self, summary: &MapRatingSummary) -> Result<bool> {
+        let before = self.pending.len();
+        self.pending.retain(|rating| rating.map_id != summary.map_id);
+        if self.pending.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+}
+
+/// A row in a chat panel after muted users are filtered out
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatLine<'a> {
+    Message(&'a ChatMessage),
+    /// A run of consecutive messages from muted users
+    Hidden(usize),
+}
+
+impl ChatLine<'_> {
+    /// Text for a collapsed run of hidden messages
+    pub fn hidden_label(count: usize) -> String {
+        if count == 1 {
+            "1 hidden message".to_string()
+        } else {
+            format!("{} hidden messages", count)
+        }
+    }
+}
+
+/// Chat panel rows for a message history, collapsing muted senders.
+/// The history itself is left untouched so unmuting shows the messages again.
+pub fn visible_chat_lines<'a>(messages: &'a [ChatMessage], muted: &HashSet<Uuid>) -> Vec<ChatLine<'a>> {
+    let mut lines = Vec::new();
+    for message in messages {
+        if muted.contains(&message.sender_id) {
+            match lines.last_mut() {
+                Some(ChatLine::Hidden(count)) => *count += 1,
+                _ => lines.push(ChatLine::Hidden(1)),
+            }
+        } else {
+            lines.push(ChatLine::Message(message));
+        }
+    }
+    lines
+}
+
+/// Pending friend requests as the client sees them, for the Friends button badge and
+/// the toast shown when one arrives while online
+#[derive(Debug, Clone, Default)]
+pub struct FriendInbox {
+    pub pending: Vec<Friend>,
+    /// Toast for the latest request, until it's shown
+    toast: Option<String>,
+}
+
+impl FriendInbox {
+    /// Apply a friend request message from the server. Returns true if it changed the inbox
+    pub fn handle_message(&mut self, message: &NetworkMessage) -> bool {
+        match message {
+            NetworkMessage::FriendRequests { pending } => {
+                self.pending = pending.clone();
+                true
+            }
+            NetworkMessage::FriendRequestReceived { request } => {
+                if matches!(request.status, FriendStatus::Accepted) {
+                    self.toast = Some(format!("{} accepted your friend request", request.username));
+                } else if !self.pending.iter().any(|f| f.friend_id == request.friend_id) {
+                    self.toast = Some(format!("Friend request from {}", request.username));
+                    self.pending.push(request.clone());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Answer a request and drop it from the inbox
+    pub fn answer(&mut self, client: &GameClient, requester_id: Uuid, accept: bool, block: bool) -> Result<()> {
+        client.send(NetworkMessage::FriendRequestAnswer { requester_id, accept, block })?;
+        self.pending.retain(|f| f.friend_id != requester_id);
+        Ok(())
+    }
+
+    /// Badge text for the Friends button; None when nothing is pending
+    pub fn badge(&self) -> Option<String> {
+        match self.pending.len() {
+            0 => None,
+            n if n > 99 => Some("99+".to_string()),
+            n => Some(n.to_string()),
+        }
+    }
+
+    /// Take the toast for the latest request, if it hasn't been shown yet
+    pub fn take_toast(&mut self) -> Option<String> {
+        self.toast.take()
+    }
+}
+
+/// Last fetched comparison per friend, for viewing offline
+pub const COMPARISON_CACHE_PATH: &str = "comparisons.json";
+
+/// Rows visible at once in the comparison table
+pub const COMPARISON_VISIBLE_ROWS: usize = 12;
+
+/// Song records from local analytics, used for your side and for imported friend files
+pub fn analytics_records(analytics: &Analytics) -> HashMap<String, SongRecord> {
+    analytics
+        .song_stats
+        .iter()
+        .map(|(song, stats)| {
+            let record = SongRecord {
+                best_score: stats.best_score,
+                best_accuracy: stats.best_accuracy as f64,
+                grade: Grade::from_accuracy(stats.best_accuracy).as_str().to_string(),
+            };
+            (song.clone(), record)
+        })
+        .collect()
+}
+
+/// Song records from an analytics file a friend exported
+pub fn import_analytics_records(path: &str) -> Result<HashMap<String, SongRecord>> {
+    let contents = std::fs::read_to_string(path)?;
+    let analytics: Analytics = serde_json::from_str(&contents)?;
+    Ok(analytics_records(&analytics))
+}
+
+/// Who is ahead on a shared song
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Tie,
+}
+
+/// Both players' best results on one song
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongComparison {
+    pub song: String,
+    pub you: SongRecord,
+    pub them: SongRecord,
+}
+
+impl SongComparison {
+    /// Your best score minus theirs
+    pub fn score_gap(&self) -> i64 {
+        self.you.best_score.saturating_sub(self.them.best_score)
+    }
+
+    /// Result by best score, with accuracy breaking ties
+    pub fn outcome(&self) -> Outcome {
+        let accuracy_gap = self.you.best_accuracy - self.them.best_accuracy;
+        match self.score_gap() {
+            gap if gap > 0 => Outcome::Win,
+            gap if gap < 0 => Outcome::Loss,
+            _ if accuracy_gap > 0.0 => Outcome::Win,
+            _ if accuracy_gap < 0.0 => Outcome::Loss,
+            _ => Outcome::Tie,
+        }
+    }
+}
+
+/// Sort order of the comparison table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ComparisonSort {
+    /// Alphabetical by song
+    #[default]
+    Song,
+    /// Largest score difference either way first
+    BiggestGap,
+    /// Songs where the friend leads by the most first
+    BiggestDeficit,
+}
+
+impl ComparisonSort {
+    /// Next sort order when cycling
+    pub fn next(&self) -> ComparisonSort {
+        match self {
+            ComparisonSort::Song => ComparisonSort::BiggestGap,
+            ComparisonSort::BiggestGap => ComparisonSort::BiggestDeficit,
+            ComparisonSort::BiggestDeficit => ComparisonSort::Song,
+        }
+    }
+
+    /// Get display name for the sort order
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ComparisonSort::Song => "Song",
+            ComparisonSort::BiggestGap => "Biggest gap",
+            ComparisonSort::BiggestDeficit => "Where they lead",
+        }
+    }
+}
+
+/// Head-to-head comparison with a friend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FriendComparison {
+    pub friend_id: Uuid,
+    pub friend_name: String,
+    /// Songs both players have played
+    pub shared: Vec<SongComparison>,
+    /// Songs only you have played
+    pub only_you: Vec<String>,
+    /// Songs only the friend has played
+    pub only_them: Vec<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl FriendComparison {
+    /// Compare your records against a friend's
+    pub fn build(
+        friend_id: Uuid,
+        friend_name: String,
+        yours: &HashMap<String, SongRecord>,
+        theirs: &HashMap<String, SongRecord>,
+    ) -> Self {
+        let mut shared = Vec::new();
+        let mut only_you = Vec::new();
+        for (song, you) in yours {
+            match theirs.get(song) {
+                Some(them) => shared.push(SongComparison {
+                    song: song.clone(),
+                    you: you.clone(),
+                    them: them.clone(),
+                }),
+                None => only_you.push(song.clone()),
+            }
+        }
+        let mut only_them: Vec<String> = theirs
+            .keys()
+            .filter(|song| !yours.contains_key(*song))
+            .cloned()
+            .collect();
+
+        only_you.sort();
+        only_them.sort();
+        let mut comparison = Self {
+            friend_id,
+            friend_name,
+            shared,
+            only_you,
+            only_them,
+            fetched_at: Utc::now(),
+        };
+        comparison.sort(ComparisonSort::Song);
+        comparison
+    }
+
+    /// Wins, losses and ties over the shared songs
+    pub fn tally(&self) -> (usize, usize, usize) {
+        self.shared.iter().fold((0, 0, 0), |(wins, losses, ties), row| match row.outcome() {
+            Outcome::Win => (wins + 1, losses, ties),
+            Outcome::Loss => (wins, losses + 1, ties),
+            Outcome::Tie => (wins, losses, ties + 1),
+        })
+    }
+
+    /// Reorder the shared songs
+    pub fn sort(&mut self, sort: ComparisonSort) {
+        match sort {
+            ComparisonSort::Song => self.shared.sort_by(|a, b| a.song.cmp(&b.song)),
+            ComparisonSort::BiggestGap => self
+                .shared
+                .sort_by_key(|row| (std::cmp::Reverse(row.score_gap().abs()), row.song.clone())),
+            ComparisonSort::BiggestDeficit => self
+                .shared
+                .sort_by_key(|row| (row.score_gap(), row.song.clone())),
+        }
+    }
+}
+
+/// Comparisons fetched earlier, keyed by friend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComparisonCache {
+    pub comparisons: HashMap<Uuid, FriendComparison>,
+}
+
+impl ComparisonCache {
+    /// Load cached comparisons, starting empty if there are none
+    pub fn load() -> Self {
+        std::fs::read_to_string(COMPARISON_CACHE_PATH)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save cached comparisons to disk
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(COMPARISON_CACHE_PATH, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Replace the cached comparison for a friend
+    pub fn store(&mut self, comparison: FriendComparison) -> Result<()> {
+        self.comparisons.insert(comparison.friend_id, comparison);
+        self.save()
+    }
+
+    /// Forget a friend's comparison, e.g. after they made their profile private
+    pub fn forget(&mut self, friend_id: Uuid) -> Result<()> {
+        if self.comparisons.remove(&friend_id).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+}
+
+/// What the comparison screen is showing
+#[derive(Debug, Clone)]
+pub enum ComparisonStatus {
+    /// Waiting for the server; shows the cached comparison meanwhile, if any
+    Loading(Option<FriendComparison>),
+    Ready(FriendComparison),
+    /// The friend's profile is not public
+    Private,
+    /// Fetch failed and nothing is cached
+    Failed(String),
+}
+
+/// Lists on the comparison screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComparisonTab {
+    #[default]
+    Shared,
+    OnlyYou,
+    OnlyThem,
+}
+
+impl ComparisonTab {
+    /// Next tab when cycling
+    pub fn next(&self) -> ComparisonTab {
+        match self {
+            ComparisonTab::Shared => ComparisonTab::OnlyYou,
+            ComparisonTab::OnlyYou => ComparisonTab::OnlyThem,
+            ComparisonTab::OnlyThem => ComparisonTab::Shared,
+        }
+    }
+
+    /// Get display name for the tab
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ComparisonTab::Shared => "Head to head",
+            ComparisonTab::OnlyYou => "Only you",
+            ComparisonTab::OnlyThem => "Only them",
+        }
+    }
+}
+
+/// Comparison screen state opened from a friend's profile
+#[derive(Debug, Clone)]
+pub struct ComparisonView {
+    pub friend_id: Uuid,
+    pub friend_name: String,
+    pub status: ComparisonStatus,
+    pub tab: ComparisonTab,
+    pub sort: ComparisonSort,
+    /// Selected row in the current tab
+    pub cursor: usize,
+    /// First visible row in the current tab
+    pub scroll: usize,
+}
+
+impl ComparisonView {
+    /// Open the comparison and request fresh data. Offline, the cached comparison is shown
+    pub fn open(friend: &Friend, cache: &ComparisonCache, client: Option<&GameClient>) -> Self {
+        let cached = cache.comparisons.get(&friend.friend_id).cloned();
+        let requested = client.is_some_and(|client| {
+            client
+                .send(NetworkMessage::CompareRequest { friend_id: friend.friend_id })
+                .is_ok()
+        });
+        let status = match (requested, cached) {
+            (true, cached) => ComparisonStatus::Loading(cached),
+            (false, Some(cached)) => ComparisonStatus::Ready(cached),
+            (false, None) => ComparisonStatus::Failed("Offline and no saved comparison".to_string()),
+        };
+
+        Self {
+            friend_id: friend.friend_id,
+            friend_name: friend.username.clone(),
+            status,
+            tab: ComparisonTab::default(),
+            sort: ComparisonSort::default(),
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    /// Compare against an analytics file the friend exported, for offline use
+    pub fn open_imported(friend: &Friend, yours: &HashMap<String, SongRecord>, path: &str) -> Self {
+        let status = match import_analytics_records(path) {
+            Ok(theirs) => ComparisonStatus::Ready(FriendComparison::build(
+                friend.friend_id,
+                friend.username.clone(),
+                yours,
+                &theirs,
+            )),
+            Err(e) => ComparisonStatus::Failed(format!("Could not import {}: {}", path, e)),
+        };
+        Self {
+            friend_id: friend.friend_id,
+            friend_name: friend.username.clone(),
+            status,
+            tab: ComparisonTab::default(),
+            sort: ComparisonSort::default(),
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    /// Apply the server's answer, caching a fresh comparison or dropping a now private one
+    pub fn receive(
+        &mut self,
+        records: ProfileRecords,
+        yours: &HashMap<String, SongRecord>,
+        cache: &mut ComparisonCache,
+    ) -> Result<()> {
+        match records {
+            ProfileRecords::Public { username, songs } => {
+                self.friend_name = username.clone();
+                let mut comparison = FriendComparison::build(self.friend_id, username, yours, &songs);
+                cache.store(comparison.clone())?;
+                comparison.sort(self.sort);
+                self.status = ComparisonStatus::Ready(comparison);
+            }
+            ProfileRecords::Private { .. } => {
+                cache.forget(self.friend_id)?;
+                self.status = ComparisonStatus::Private;
+            }
+        }
+        self.cursor = 0;
+        self.scroll = 0;
+        Ok(())
+    }
+
+    /// The fetch failed; keep showing the cached comparison if there is one
+    pub fn fetch_failed(&mut self, message: String) {
+        self.status = match std::mem::replace(&mut self.status, ComparisonStatus::Private) {
+            ComparisonStatus::Loading(Some(cached)) => ComparisonStatus::Ready(cached),
+            ComparisonStatus::Loading(None) => ComparisonStatus::Failed(message),
+            other => other,
+        };
+    }
+
+    /// Comparison being shown, cached or fresh
+    pub fn comparison(&self) -> Option<&FriendComparison> {
+        match &self.status {
+            ComparisonStatus::Ready(comparison) | ComparisonStatus::Loading(Some(comparison)) => Some(comparison),
+            _ => None,
+        }
+    }
+
+    /// Text shown instead of the table, if any
+    pub fn placeholder(&self) -> Option<String> {
+        match &self.status {
+            ComparisonStatus::Loading(None) => Some("Loading comparison...".to_string()),
+            ComparisonStatus::Private => Some(format!("{}'s profile is private", self.friend_name)),
+            ComparisonStatus::Failed(message) => Some(message.clone()),
+            _ => None,
+        }
+    }
+
+    /// Rows in the current tab
+    pub fn row_count(&self) -> usize {
+        self.comparison().map_or(0, |comparison| match self.tab {
+            ComparisonTab::Shared => comparison.shared.len(),
+            ComparisonTab::OnlyYou => comparison.only_you.len(),
+            ComparisonTab::OnlyThem => comparison.only_them.len(),
+        })
+    }
+
+    /// Move the selection, scrolling to keep it visible
+    pub fn move_cursor(&mut self, delta: i32) {
+        let count = self.row_count();
+        if count == 0 {
+            return;
+        }
+        self.cursor = (self.cursor as i32 + delta).clamp(0, count as i32 - 1) as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + COMPARISON_VISIBLE_ROWS {
+            self.scroll = self.cursor + 1 - COMPARISON_VISIBLE_ROWS;
+        }
+    }
+
+    /// Range of rows currently on screen
+    pub fn visible_range(&self) -> std::ops::Range<usize> {
+        let count = self.row_count();
+        self.scroll.min(count)..(self.scroll + COMPARISON_VISIBLE_ROWS).min(count)
+    }
+
+    /// Switch to the next list
+    pub fn next_tab(&mut self) {
+        self.tab = self.tab.next();
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    /// Cycle the sort order of the shared songs
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        let sort = self.sort;
+        match &mut self.status {
+            ComparisonStatus::Ready(comparison) | ComparisonStatus::Loading(Some(comparison)) => {
+                comparison.sort(sort);
+            }
+            _ => {}
+        }
+        self.cursor = 0;
+        self.scroll = 0;
+    }
+
+    /// Tally line shown above the table
+    pub fn tally_label(&self) -> Option<String> {
+        self.comparison().map(|comparison| {
+            let (wins, losses, ties) = comparison.tally();
+            format!("You {} - {} {} ({} tied)", wins, losses, self.friend_name, ties)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::test_support::scratch_dir;
+
+    fn message(sender_id: Uuid, content: &str) -> ChatMessage {
+        ChatMessage {
+            message_id: Uuid::new_v4(),
+            sender_id,
+            sender_name: "player".to_string(),
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            room_id: None,
+            recipient_id: None,
+        }
+    }
+
+    fn shown(lines: &[ChatLine]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| match line {
+                ChatLine::Message(message) => message.content.clone(),
+                ChatLine::Hidden(count) => ChatLine::hidden_label(*count),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn muting_and_unmuting_changes_an_open_chat_panel_straight_away() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let history = vec![
+            message(alice, "hi"),
+            message(bob, "spam"),
+            message(bob, "more spam"),
+            message(alice, "bye"),
+            message(bob, "last"),
+        ];
+        let mut config = GameConfig::default();
+
+        assert_eq!(visible_chat_lines(&history, &config.muted_users).len(), 5);
+
+        assert!(config.mute_user(bob));
+        assert!(!config.mute_user(bob));
+        assert_eq!(
+            shown(&visible_chat_lines(&history, &config.muted_users)),
+            vec!["hi", "2 hidden messages", "bye", "1 hidden message"]
+        );
+        assert_eq!(history.len(), 5);
+
+        assert!(config.unmute_user(bob));
+        assert_eq!(
+            shown(&visible_chat_lines(&history, &config.muted_users)),
+            vec!["hi", "spam", "more spam", "bye", "last"]
+        );
+    }
+
+    #[test]
+    fn message_reports_carry_the_sender_and_message() {
+        let reporter = Uuid::new_v4();
+        let offending = message(Uuid::new_v4(), "spam");
+        let report = UserReport::for_message(reporter, &offending, ReportReason::Spam, Some("  ".to_string()));
+
+        assert_eq!(report.target_id, offending.sender_id);
+        assert_eq!(report.message_ids, vec![offending.message_id]);
+        assert_eq!(report.details, None);
+
+        let profile = UserReport::new(reporter, offending.sender_id, ReportReason::Other, Some("rude name".to_string()));
+        assert!(profile.message_ids.is_empty());
+        assert_eq!(profile.details.as_deref(), Some("rude name"));
+    }
+
+    #[test]
+    fn map_ratings_clamp_stars_and_key_on_map_and_account() {
+        let player = Uuid::new_v4();
+        let tags = [MapTag::TimingOff, MapTag::Fun, MapTag::Fun];
+        let rating = MapRating::new("1234".to_string(), player, 9, &tags, 97.5, 300, true);
+        assert_eq!(rating.stars, 5);
+        assert_eq!(rating.tags, vec![MapTag::Fun, MapTag::TimingOff]);
+        assert_eq!(MapRating::new("1234".to_string(), player, 0, &[], 0.0, 0, false).stars, 1);
+
+        let again = MapRating::new("1234".to_string(), player, 2, &[], 80.0, 50, false);
+        assert_eq!(rating.dedup_key(), again.dedup_key());
+        let other_map = MapRating::new("5678".to_string(), player, 2, &[], 80.0, 50, false);
+        assert_ne!(rating.dedup_key(), other_map.dedup_key());
+        let other_player = MapRating::new("1234".to_string(), Uuid::new_v4(), 2, &[], 80.0, 50, false);
+        assert_ne!(rating.dedup_key(), other_player.dedup_key());
+    }
+
+    #[test]
+    fn queued_ratings_wait_for_the_server_to_acknowledge_them() {
+        let path = scratch_dir("rating-queue").join(RATINGS_PATH);
+        let mut queue = RatingQueue::load_from(path.clone());
+        let player = Uuid::new_v4();
+        let rate = |map: &str, stars| MapRating::new(map.to_string(), player, stars, &[], 95.0, 100, true);
+
+        // Offline: queued and saved
+        queue.submit(rate("1234", 3), None).unwrap();
+        queue.submit(rate("1234", 4), None).unwrap();
+        assert_eq!(queue.pending.len(), 1);
+        assert_eq!(queue.pending[0].stars, 4);
+        assert_eq!(RatingQueue::load_from(path.clone()).pending, queue.pending);
+
+        // Handing them to the client isn't delivery
+        let client = GameClient::new();
+        queue.submit(rate("5678", 5), Some(&client)).unwrap();
+        assert_eq!(queue.flush(&client), 2);
+        assert_eq!(queue.pending.len(), 2);
+
+        let stored = MapRatingSummary::of("1234", &queue.pending[..1]);
+        assert!(queue.acknowledge(&stored).unwrap());
+        assert!(!queue.acknowledge(&stored).unwrap());
+        assert_eq!(queue.pending.len(), 1);
+        assert_eq!(queue.pending[0].map_id, "5678");
+        assert_eq!(RatingQueue::load_from(path).pending, queue.pending);
+    }
+
+    #[tokio::test]
+    async fn chat_history_past_the_cap_pages_back_from_the_archive() {
+        let dir = scratch_dir("chat-archive");
+        let manager = CommunityManager::new().with_chat_archive(dir.clone());
+        let sender = Uuid::new_v4();
+        let room = manager.create_chat_room("room".to_string(), ChatRoomType::Public, vec![sender], None).await;
+        for i in 0..130 {
+            manager.send_message(room, sender, "player".to_string(), format!("message {}", i)).await.unwrap();
+        }
+
+        assert_eq!(manager.chat_room_snapshot(room).unwrap().messages.len(), CHAT_HISTORY_LIMIT);
+        let stored = manager.chat_storage().unwrap();
+        assert_eq!(stored.entries, 30);
+
+        let contents = |page: Vec<ChatMessage>| page.into_iter().map(|message| message.content).collect::<Vec<_>>();
+        let newest = contents(manager.get_older_messages(room, 0, 3).await.unwrap());
+        assert_eq!(newest, ["message 129", "message 128", "message 127"]);
+        // A page straddling memory and the archive reads on without a gap
+        let straddling = contents(manager.get_older_messages(room, 98, 4).await.unwrap());
+        assert_eq!(straddling, ["message 31", "message 30", "message 29", "message 28"]);
+        let oldest = contents(manager.get_older_messages(room, 125, 10).await.unwrap());
+        assert_eq!(oldest, ["message 4", "message 3", "message 2", "message 1", "message 0"]);
+        assert!(manager.get_older_messages(room, 130, 10).await.unwrap().is_empty());
+    }
+
+    /// Public room moderated by `host` with `members` in it
+    fn moderated_room(host: Uuid, members: &[Uuid]) -> ChatRoom {
+        let mut everyone = vec![host];
+        everyone.extend_from_slice(members);
+        ChatRoom::new(
+            "Room".to_string(),
+            ChatRoomType::Public,
+            everyone,
+            Some(host),
+        )
+    }
+
+    fn seconds(n: i64) -> chrono::Duration {
+        chrono::Duration::seconds(n)
+    }
+
+    #[test]
+    fn slow_mode_allows_one_message_per_member_per_window() {
+        let (host, alice, bob) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut room = moderated_room(host, &[alice, bob]);
+        let start = Utc::now();
+        assert!(room
+            .moderate(alice, ChatModAction::SlowMode { seconds: 10 }, start)
+            .is_err());
+        room.moderate(host, ChatModAction::SlowMode { seconds: 10 }, start)
+            .unwrap();
+
+        room.post(message(alice, "first"), start).unwrap();
+        let error = room
+            .post(
+                message(alice, "too soon"),
+                start + chrono::Duration::milliseconds(9_500),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Slow mode is on: wait 1s before sending again"
+        );
+        // Each member has their own window, and the host has none
+        room.post(message(bob, "other member"), start + seconds(5))
+            .unwrap();
+        room.post(message(host, "host"), start).unwrap();
+        room.post(message(host, "host again"), start).unwrap();
+        // The window ends exactly slow_mode_seconds after the last message
+        room.post(message(alice, "on time"), start + seconds(10))
+            .unwrap();
+        assert!(room
+            .post(message(alice, "again"), start + seconds(19))
+            .is_err());
+
+        room.moderate(host, ChatModAction::SlowMode { seconds: 1_000 }, start)
+            .unwrap();
+        assert_eq!(room.settings.slow_mode_seconds, MAX_SLOW_MODE_SECONDS);
+        room.moderate(host, ChatModAction::SlowMode { seconds: 0 }, start)
+            .unwrap();
+        room.post(message(alice, "slow mode off"), start + seconds(10))
+            .unwrap();
+        assert_eq!(room.messages.len(), 6);
+    }
+
+    #[test]
+    fn timeouts_expire_after_their_minutes_or_when_lifted() {
+        let (host, alice, stranger) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut room = moderated_room(host, &[alice]);
+        let start = Utc::now();
+        let timeout = |member_id, minutes| ChatModAction::Timeout { member_id, minutes };
+        assert!(room.moderate(host, timeout(host, 5), start).is_err());
+        assert!(room.moderate(host, timeout(stranger, 5), start).is_err());
+        room.moderate(host, timeout(alice, 5), start).unwrap();
+
+        let almost = start + chrono::Duration::minutes(5) - seconds(1);
+        let error = room.post(message(alice, "muted"), almost).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "You are timed out in this room for 1 more minute(s)"
+        );
+        assert_eq!(
+            room.panel_header(alice, start),
+            ["You are timed out for 5 more minute(s)"]
+        );
+        assert!(room.panel_header(host, start).is_empty());
+
+        // Expired timeouts let the member back in and are dropped on the next post
+        let expired = start + chrono::Duration::minutes(5);
+        room.post(message(alice, "back"), expired).unwrap();
+        assert!(room.settings.timeouts.is_empty());
+
+        room.moderate(host, timeout(alice, 60), expired).unwrap();
+        assert!(room
+            .post(message(alice, "muted"), expired + seconds(1))
+            .is_err());
+        room.moderate(host, timeout(alice, 0), expired).unwrap();
+        room.post(message(alice, "lifted"), expired + seconds(1))
+            .unwrap();
+    }
+
+    #[test]
+    fn pinned_messages_survive_the_history_cap() {
+        let (host, alice) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut room = moderated_room(host, &[alice]);
+        let now = Utc::now();
+        let rules = message(host, "be nice");
+        let rules_id = rules.message_id;
+        room.post(rules, now).unwrap();
+        room.moderate(
+            host,
+            ChatModAction::Pin {
+                message_id: rules_id,
+            },
+            now,
+        )
+        .unwrap();
+        // Pinning twice keeps one copy
+        room.moderate(
+            host,
+            ChatModAction::Pin {
+                message_id: rules_id,
+            },
+            now,
+        )
+        .unwrap();
+
+        let mut archived = Vec::new();
+        for i in 0..CHAT_HISTORY_LIMIT + 20 {
+            archived.extend(
+                room.post(message(alice, &format!("message {}", i)), now)
+                    .unwrap(),
+            );
+        }
+        assert_eq!(room.messages.len(), CHAT_HISTORY_LIMIT);
+        assert!(archived.iter().any(|m| m.message_id == rules_id));
+        assert!(room.messages.iter().all(|m| m.message_id != rules_id));
+        assert_eq!(room.settings.pinned.len(), 1);
+        assert_eq!(room.panel_header(alice, now), ["📌 player: be nice"]);
+
+        // A trimmed message can't be newly pinned, and the pin limit holds
+        let trimmed = archived[1].message_id;
+        assert!(room
+            .moderate(
+                host,
+                ChatModAction::Pin {
+                    message_id: trimmed
+                },
+                now
+            )
+            .is_err());
+        let more: Vec<Uuid> = room.messages[..MAX_PINNED_MESSAGES - 1]
+            .iter()
+            .map(|m| m.message_id)
+            .collect();
+        for message_id in more {
+            room.moderate(host, ChatModAction::Pin { message_id }, now)
+                .unwrap();
+        }
+        let extra = room.messages[MAX_PINNED_MESSAGES].message_id;
+        assert!(room
+            .moderate(host, ChatModAction::Pin { message_id: extra }, now)
+            .is_err());
+
+        room.moderate(
+            host,
+            ChatModAction::Unpin {
+                message_id: rules_id,
+            },
+            now,
+        )
+        .unwrap();
+        assert_eq!(room.settings.pinned.len(), MAX_PINNED_MESSAGES - 1);
+    }
+}
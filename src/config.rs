@@ -1,15 +1,32 @@
 // src/config.rs
 
-use bevy::input::keyboard::KeyCode;
+use bevy::input::keyboard::{KeyCode, NativeKeyCode};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use uuid::Uuid;
 
-use crate::gamemode::{Difficulty, GameMode, GameSettings, Modifier};
+use crate::audio::DetectionQuality;
+use crate::gamemode::GameSettings;
+use crate::generator::GenerationSettings;
+use crate::hud::{HudElement, HudLayout};
+use crate::key_conflicts::PendingBinding;
+use crate::library_scan::LibraryScan;
+use crate::logging::LogVerbosity;
+use crate::online::AccountMaintenance;
+use crate::performance::PerformancePreset;
+use crate::profiles::{profile_dir, resolve_profile, ProfileSettings, DEFAULT_PROFILE};
+use crate::session_details::{DetailsUsage, DEFAULT_DETAILS_CAP_MB, SESSION_DETAILS_DIR};
+use crate::settings_registry::SettingsSearch;
+use crate::text_input::TextInput;
+use crate::theme_presets::{list_presets, ThemePreset, ThemePresetEntry};
 
-/// Game configuration settings for customization
-#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+/// Game configuration settings for customization.
+/// Stored on disk as a portable section (follows the player) and a machine section
+/// (stays with this computer), see `PortableConfig` and `MachineConfig`.
+#[derive(Debug, Clone, Resource)]
 pub struct GameConfig {
     /// Key bindings for gameplay
     pub key_bindings: KeyBindings,
@@ -23,10 +40,134 @@ pub struct GameConfig {
     pub game_settings: GameSettings,
     /// Whether to save analytics
     pub save_analytics: bool,
+    /// Whether analytics record where hits land and how early or late they are
+    pub track_hit_precision: bool,
+    /// Procedural map generation thresholds
+    pub generation: GenerationSettings,
+    /// Id of the newest news entry the player has opened
+    pub last_seen_news_id: Option<String>,
+    /// Users whose chat, friend requests and presence are hidden
+    pub muted_users: HashSet<Uuid>,
+    /// Gameplay HUD element placement
+    pub hud_layout: HudLayout,
+    /// Name credited as the creator of new beatmaps
+    pub display_name: Option<String>,
+    /// Display settings
+    pub display: DisplayConfig,
+    /// Extra directories scanned for songs
+    pub song_directories: Vec<String>,
+    /// Last practice settings used per song
+    pub practice_songs: SongPracticeStore,
+    /// Play without recording anything to analytics
+    pub incognito: bool,
+    /// Lowest severity written to the log file
+    pub log_verbosity: LogVerbosity,
+    /// Local profile whose key bindings, practice settings, HUD layout and analytics
+    /// are loaded
+    pub active_profile: String,
+    /// Disk space session details may use before the oldest are evicted (megabytes)
+    pub session_details_cap_mb: u32,
+}
+
+/// Settings that follow the player between machines (profiles, sync)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortableConfig {
+    /// Key bindings for gameplay
+    pub key_bindings: KeyBindings,
+    /// Visual theme settings
+    pub theme: ThemeConfig,
+    /// Practice mode defaults
+    pub practice: PracticeConfig,
+    /// Game settings (mode, difficulty, modifiers)
+    pub game_settings: GameSettings,
+    /// Whether to save analytics
+    pub save_analytics: bool,
+    /// Whether analytics record where hits land and how early or late they are
+    pub track_hit_precision: bool,
+    /// Procedural map generation thresholds
+    pub generation: GenerationSettings,
+    /// Id of the newest news entry the player has opened
+    pub last_seen_news_id: Option<String>,
+    /// Users whose chat, friend requests and presence are hidden
+    pub muted_users: HashSet<Uuid>,
+    /// Gameplay HUD element placement
+    pub hud_layout: HudLayout,
+    /// Name credited as the creator of new beatmaps
+    pub display_name: Option<String>,
+}
+
+/// Settings tied to this machine's hardware and file layout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MachineConfig {
+    /// Audio device and volume settings
+    pub audio: AudioConfig,
+    /// Display settings
+    pub display: DisplayConfig,
+    /// Extra directories scanned for songs
+    pub song_directories: Vec<String>,
+    /// Last practice settings used per song (song paths are machine-specific)
+    pub practice_songs: SongPracticeStore,
+    /// Play without recording anything to analytics (e.g. while streaming)
+    pub incognito: bool,
+    /// Lowest severity written to the log file
+    pub log_verbosity: LogVerbosity,
+    /// Local profile in use on this machine
+    pub active_profile: String,
+    /// Disk space session details may use before the oldest are evicted (megabytes)
+    pub session_details_cap_mb: u32,
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        GameConfig::default().machine()
+    }
+}
+
+/// On-disk layout of config.json
+#[derive(Serialize)]
+struct ConfigFile {
+    portable: PortableConfig,
+    machine: MachineConfig,
+}
+
+/// Display configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Run fullscreen
+    pub fullscreen: bool,
+    /// Enable vsync
+    pub vsync: bool,
+    /// Cosmetic extras drawn; None until the first-run benchmark picks one
+    pub performance_preset: Option<PerformancePreset>,
+    /// Don't wait for vsync during gameplay, so presses are read sooner at the cost of
+    /// possible tearing
+    pub reduced_input_latency: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            vsync: true,
+            performance_preset: None,
+            reduced_input_latency: false,
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Preset in use, Quality before the benchmark has run
+    pub fn performance_preset(&self) -> PerformancePreset {
+        self.performance_preset.unwrap_or_default()
+    }
 }
 
 /// Key bindings configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct KeyBindings {
     /// Primary hit key
     pub primary_hit: String,
@@ -42,6 +183,14 @@ pub struct KeyBindings {
     pub navigate_down: String,
     /// Select/confirm
     pub select: String,
+    /// Restart the song during gameplay
+    pub quick_retry: String,
+    /// Skip a long intro during gameplay
+    pub skip_intro: String,
+    /// Player two's primary hit key in local versus
+    pub versus_primary_hit: String,
+    /// Player two's secondary hit key in local versus
+    pub versus_secondary_hit: String,
 }
 
 impl Default for KeyBindings {
@@ -54,6 +203,10 @@ impl Default for KeyBindings {
             navigate_up: "ArrowUp".to_string(),
             navigate_down: "ArrowDown".to_string(),
             select: "Enter".to_string(),
+            quick_retry: "Backquote".to_string(),
+            skip_intro: "Space".to_string(),
+            versus_primary_hit: "KeyK".to_string(),
+            versus_secondary_hit: "KeyL".to_string(),
         }
     }
 }
@@ -93,6 +246,61 @@ impl KeyBindings {
     pub fn select_key(&self) -> KeyCode {
         string_to_keycode(&self.select)
     }
+
+    /// Get the quick retry key as KeyCode
+    pub fn quick_retry_key(&self) -> KeyCode {
+        string_to_keycode(&self.quick_retry)
+    }
+
+    /// Get the skip intro key as KeyCode
+    pub fn skip_intro_key(&self) -> KeyCode {
+        string_to_keycode(&self.skip_intro)
+    }
+
+    /// Get player two's primary hit key as KeyCode
+    pub fn versus_primary_hit_key(&self) -> KeyCode {
+        string_to_keycode(&self.versus_primary_hit)
+    }
+
+    /// Get player two's secondary hit key as KeyCode
+    pub fn versus_secondary_hit_key(&self) -> KeyCode {
+        string_to_keycode(&self.versus_secondary_hit)
+    }
+
+    /// Key name of a binding, "" if unbound
+    pub fn get(&self, binding: KeyBindingType) -> &str {
+        match binding {
+            KeyBindingType::PrimaryHit => &self.primary_hit,
+            KeyBindingType::SecondaryHit => &self.secondary_hit,
+            KeyBindingType::Pause => &self.pause,
+            KeyBindingType::Exit => &self.exit,
+            KeyBindingType::NavigateUp => &self.navigate_up,
+            KeyBindingType::NavigateDown => &self.navigate_down,
+            KeyBindingType::Select => &self.select,
+            KeyBindingType::QuickRetry => &self.quick_retry,
+            KeyBindingType::SkipIntro => &self.skip_intro,
+            KeyBindingType::VersusPrimaryHit => &self.versus_primary_hit,
+            KeyBindingType::VersusSecondaryHit => &self.versus_secondary_hit,
+        }
+    }
+
+    /// Bind a key by name; "" leaves the binding unbound
+    pub fn set(&mut self, binding: KeyBindingType, key: &str) {
+        let slot = match binding {
+            KeyBindingType::PrimaryHit => &mut self.primary_hit,
+            KeyBindingType::SecondaryHit => &mut self.secondary_hit,
+            KeyBindingType::Pause => &mut self.pause,
+            KeyBindingType::Exit => &mut self.exit,
+            KeyBindingType::NavigateUp => &mut self.navigate_up,
+            KeyBindingType::NavigateDown => &mut self.navigate_down,
+            KeyBindingType::Select => &mut self.select,
+            KeyBindingType::QuickRetry => &mut self.quick_retry,
+            KeyBindingType::SkipIntro => &mut self.skip_intro,
+            KeyBindingType::VersusPrimaryHit => &mut self.versus_primary_hit,
+            KeyBindingType::VersusSecondaryHit => &mut self.versus_secondary_hit,
+        };
+        *slot = key.to_string();
+    }
 }
 
 /// Convert a string to a KeyCode
@@ -184,10 +392,28 @@ fn string_to_keycode(s: &str) -> KeyCode {
         "ControlRight" => KeyCode::ControlRight,
         "AltRight" => KeyCode::AltRight,
         "SuperRight" => KeyCode::SuperRight,
+        // Cleared by "assign anyway"; never reported as pressed
+        "" => KeyCode::Unidentified(NativeKeyCode::Unidentified),
         _ => KeyCode::KeyA,
     }
 }
 
+/// Whether a key name can be picked in the Key Bindings tab
+pub fn is_bindable_key(name: &str) -> bool {
+    get_available_keys().iter().any(|(key, _)| *key == name)
+}
+
+/// Key name as shown to players, "KeyA" as "A"
+pub fn key_label(name: &str) -> String {
+    if name.is_empty() {
+        return "Unbound".to_string();
+    }
+    get_available_keys()
+        .into_iter()
+        .find(|(key, _)| *key == name)
+        .map_or_else(|| name.to_string(), |(_, label)| label.to_string())
+}
+
 /// Get all available keys for binding
 pub fn get_available_keys() -> Vec<(&'static str, &'static str)> {
     vec![
@@ -261,7 +487,8 @@ pub fn get_available_keys() -> Vec<(&'static str, &'static str)> {
 }
 
 /// Visual theme configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ThemeConfig {
     /// Primary accent color (hex string)
     pub primary_color: String,
@@ -277,6 +504,16 @@ pub struct ThemeConfig {
     pub particles_enabled: bool,
     /// Enable screen shake on hit
     pub screen_shake: bool,
+    /// Fraction of the approach window over which Hidden fades circles out
+    pub hidden_fade_fraction: f32,
+    /// Turn off pulsing and kiai effects
+    pub reduced_motion: bool,
+    /// Dots leading from each object to the next in its combo
+    pub follow_points: bool,
+    /// Glow at the hit position, colored by the judgment
+    pub hit_lighting: bool,
+    /// Bar at the bottom of the screen marking how early or late recent hits were
+    pub show_hit_error_bar: bool,
 }
 
 impl Default for ThemeConfig {
@@ -289,12 +526,17 @@ impl Default for ThemeConfig {
             circle_size: 1.0,
             particles_enabled: true,
             screen_shake: true,
+            hidden_fade_fraction: 0.4,
+            reduced_motion: false,
+            follow_points: true,
+            hit_lighting: true,
+            show_hit_error_bar: true,
         }
     }
 }
 
 /// Background style options
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BackgroundStyle {
     Cyberpunk,
     Dark,
@@ -314,8 +556,15 @@ impl BackgroundStyle {
     }
 }
 
+/// Largest audio offset either way (ms)
+pub const AUDIO_OFFSET_LIMIT_MS: i32 = 300;
+
+/// Change per Left/Right press on the audio offset (ms)
+pub const AUDIO_OFFSET_STEP_MS: i32 = 5;
+
 /// Audio configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AudioConfig {
     /// Master volume (0.0 - 1.0)
     pub master_volume: f32,
@@ -327,6 +576,15 @@ pub struct AudioConfig {
     pub visualizer_enabled: bool,
     /// Audio buffer size
     pub buffer_size: usize,
+    /// Output device name (None uses the system default)
+    pub output_device: Option<String>,
+    /// Beat detection mode used when loading a song
+    pub detection_quality: DetectionQuality,
+    /// Play a shuffled rotation of song previews softly on the main menu
+    pub menu_music: bool,
+    /// How late the player hears the music and so hits (ms, negative when early).
+    /// Judgments run this far behind the song; set by the calibration screen
+    pub audio_offset_ms: i32,
 }
 
 impl Default for AudioConfig {
@@ -337,38 +595,149 @@ impl Default for AudioConfig {
             effects_volume: 1.0,
             visualizer_enabled: true,
             buffer_size: 1024,
+            output_device: None,
+            detection_quality: DetectionQuality::default(),
+            menu_music: false,
+            audio_offset_ms: 0,
         }
     }
 }
 
+impl AudioConfig {
+    /// Audio offset clamped to `AUDIO_OFFSET_LIMIT_MS`, so a bad calibration or a
+    /// hand-edited config can't make songs unplayable
+    pub fn offset_ms(&self) -> i32 {
+        self.audio_offset_ms
+            .clamp(-AUDIO_OFFSET_LIMIT_MS, AUDIO_OFFSET_LIMIT_MS)
+    }
+}
+
 /// Practice mode configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PracticeConfig {
     /// Playback speed multiplier (0.25 - 2.0)
     pub playback_speed: f32,
+    /// Time-stretch the song at other speeds instead of letting the pitch follow
+    pub preserve_pitch: bool,
     /// Enable no-fail mode
     pub no_fail: bool,
     /// Enable autoplay
     pub autoplay: bool,
     /// Enable hit sounds
     pub hit_sounds: bool,
+    /// Click track on the song's beats
+    pub metronome: MetronomeMode,
     /// Loop section start time (in seconds, None if not looping)
     pub loop_start: Option<f64>,
     /// Loop section end time (in seconds, None if not looping)
     pub loop_end: Option<f64>,
+    /// Remember the last practice settings for each song
+    pub remember_per_song: bool,
 }
 
 impl Default for PracticeConfig {
     fn default() -> Self {
         Self {
             playback_speed: 1.0,
+            preserve_pitch: false,
             no_fail: false,
             autoplay: false,
             hit_sounds: true,
+            metronome: MetronomeMode::Off,
             loop_start: None,
             loop_end: None,
+            remember_per_song: false,
+        }
+    }
+}
+
+impl PracticeConfig {
+    /// Section to loop as (start, end), if both ends are set and it isn't empty
+    pub fn loop_section(&self) -> Option<(f64, f64)> {
+        match (self.loop_start, self.loop_end) {
+            (Some(start), Some(end)) if end > start => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    /// Step the playback speed through the practice menu's speed options
+    pub fn cycle_speed(&mut self, direction: i32) {
+        let options = crate::structs::PracticeMenuState::speed_options();
+        let current = options
+            .iter()
+            .position(|(speed, _)| *speed == self.playback_speed)
+            .unwrap_or(3) as i32;
+        let next = (current + direction).clamp(0, options.len() as i32 - 1);
+        self.playback_speed = options[next as usize].0;
+    }
+}
+
+/// What the practice metronome does on each beat
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MetronomeMode {
+    #[default]
+    Off,
+    /// A click through the effects output
+    Click,
+    /// A click and a flash around the screen edge
+    ClickAndPulse,
+}
+
+impl MetronomeMode {
+    /// All modes, in menu order
+    pub fn all() -> Vec<MetronomeMode> {
+        vec![
+            MetronomeMode::Off,
+            MetronomeMode::Click,
+            MetronomeMode::ClickAndPulse,
+        ]
+    }
+
+    /// Get display name for the mode
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MetronomeMode::Off => "OFF",
+            MetronomeMode::Click => "Click",
+            MetronomeMode::ClickAndPulse => "Click + Pulse",
         }
     }
+
+    /// The mode `direction` steps away, wrapping around; 0 counts as forward
+    pub fn cycled(&self, direction: i32) -> MetronomeMode {
+        let all = Self::all();
+        let index = all.iter().position(|mode| mode == self).unwrap_or(0) as i32;
+        let step = if direction < 0 { -1 } else { 1 };
+        all[(index + step).rem_euclid(all.len() as i32) as usize]
+    }
+
+    /// Whether the screen edge flashes with the clicks
+    pub fn pulses(&self) -> bool {
+        *self == MetronomeMode::ClickAndPulse
+    }
+}
+
+/// Last practice settings used for each song, keyed by song path
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SongPracticeStore {
+    /// Practice settings per song path
+    pub songs: HashMap<String, PracticeConfig>,
+}
+
+impl SongPracticeStore {
+    /// Practice settings for a song, falling back to the global defaults
+    pub fn get_or_default(&self, song_path: &str, defaults: &PracticeConfig) -> PracticeConfig {
+        self.songs
+            .get(song_path)
+            .cloned()
+            .unwrap_or_else(|| defaults.clone())
+    }
+
+    /// Store the settings last used for a song
+    pub fn remember(&mut self, song_path: &str, settings: PracticeConfig) {
+        self.songs.insert(song_path.to_string(), settings);
+    }
 }
 
 impl Default for GameConfig {
@@ -380,7 +749,43 @@ impl Default for GameConfig {
             practice: PracticeConfig::default(),
             game_settings: GameSettings::default(),
             save_analytics: true,
+            track_hit_precision: true,
+            generation: GenerationSettings::default(),
+            last_seen_news_id: None,
+            muted_users: HashSet::new(),
+            hud_layout: HudLayout::default(),
+            display_name: None,
+            display: DisplayConfig::default(),
+            song_directories: Vec::new(),
+            practice_songs: SongPracticeStore::default(),
+            incognito: false,
+            log_verbosity: LogVerbosity::default(),
+            active_profile: DEFAULT_PROFILE.to_string(),
+            session_details_cap_mb: DEFAULT_DETAILS_CAP_MB,
+        }
+    }
+}
+
+impl Default for PortableConfig {
+    fn default() -> Self {
+        GameConfig::default().portable()
+    }
+}
+
+impl Serialize for GameConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ConfigFile {
+            portable: self.portable(),
+            machine: self.machine(),
         }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GameConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        Ok(Self::from_json_value(value))
     }
 }
 
@@ -393,12 +798,12 @@ impl GameConfig {
                 Ok(contents) => match serde_json::from_str(&contents) {
                     Ok(config) => config,
                     Err(e) => {
-                        eprintln!("Failed to parse config: {}, using default", e);
+                        warn!("Failed to parse config: {}, using default", e);
                         Self::default()
                     }
                 },
                 Err(e) => {
-                    eprintln!("Failed to read config: {}, using default", e);
+                    warn!("Failed to read config: {}, using default", e);
                     Self::default()
                 }
             }
@@ -409,19 +814,167 @@ impl GameConfig {
         }
     }
 
-    /// Save configuration to file
+    /// Save configuration to file, and the active profile's settings to its folder
     pub fn save(&self) {
         let config_path = "config.json";
         match serde_json::to_string_pretty(self) {
             Ok(json) => {
                 if let Err(e) = fs::write(config_path, json) {
-                    eprintln!("Failed to save config: {}", e);
+                    error!("Failed to save config: {}", e);
                 }
             }
             Err(e) => {
-                eprintln!("Failed to serialize config: {}", e);
+                error!("Failed to serialize config: {}", e);
             }
         }
+        if let Err(e) = ProfileSettings::from_config(self).save(&self.active_profile) {
+            error!("{}", e);
+        }
+    }
+
+    /// Load the active profile's settings over this config, falling back to the
+    /// shared profile when its folder is gone
+    pub fn load_active_profile(&mut self) {
+        self.active_profile = resolve_profile(&self.active_profile);
+        ProfileSettings::load(&self.active_profile).apply(self);
+    }
+
+    /// Build a config from config.json contents, migrating the old flat layout.
+    /// A missing or broken section falls back to defaults for that section only.
+    pub fn from_json_value(value: serde_json::Value) -> Self {
+        let is_split = value.get("portable").is_some() || value.get("machine").is_some();
+
+        let (portable, machine) = if is_split {
+            (
+                section_or_default(value.get("portable"), "portable"),
+                section_or_default(value.get("machine"), "machine"),
+            )
+        } else {
+            // Flat layout from before the split: both sections read from the top level
+            (
+                section_or_default(Some(&value), "portable"),
+                section_or_default(Some(&value), "machine"),
+            )
+        };
+
+        Self::from_sections(portable, machine)
+    }
+
+    /// Combine the two sections into a config
+    pub fn from_sections(portable: PortableConfig, machine: MachineConfig) -> Self {
+        Self {
+            key_bindings: portable.key_bindings,
+            theme: portable.theme,
+            audio: machine.audio,
+            practice: portable.practice,
+            game_settings: portable.game_settings,
+            save_analytics: portable.save_analytics,
+            track_hit_precision: portable.track_hit_precision,
+            generation: portable.generation,
+            last_seen_news_id: portable.last_seen_news_id,
+            muted_users: portable.muted_users,
+            hud_layout: portable.hud_layout,
+            display_name: portable.display_name,
+            display: machine.display,
+            song_directories: machine.song_directories,
+            practice_songs: machine.practice_songs,
+            incognito: machine.incognito,
+            log_verbosity: machine.log_verbosity,
+            active_profile: machine.active_profile,
+            session_details_cap_mb: machine.session_details_cap_mb,
+        }
+    }
+
+    /// Settings that follow the player
+    pub fn portable(&self) -> PortableConfig {
+        PortableConfig {
+            key_bindings: self.key_bindings.clone(),
+            theme: self.theme.clone(),
+            practice: self.practice.clone(),
+            game_settings: self.game_settings.clone(),
+            save_analytics: self.save_analytics,
+            track_hit_precision: self.track_hit_precision,
+            generation: self.generation.clone(),
+            last_seen_news_id: self.last_seen_news_id.clone(),
+            muted_users: self.muted_users.clone(),
+            hud_layout: self.hud_layout.clone(),
+            display_name: self.display_name.clone(),
+        }
+    }
+
+    /// Settings that stay on this machine
+    pub fn machine(&self) -> MachineConfig {
+        MachineConfig {
+            audio: self.audio.clone(),
+            display: self.display.clone(),
+            song_directories: self.song_directories.clone(),
+            practice_songs: self.practice_songs.clone(),
+            incognito: self.incognito,
+            log_verbosity: self.log_verbosity,
+            active_profile: self.active_profile.clone(),
+            session_details_cap_mb: self.session_details_cap_mb,
+        }
+    }
+
+    /// Apply synced settings from a profile, leaving machine settings untouched
+    pub fn merge_portable(&mut self, portable: PortableConfig) {
+        *self = Self::from_sections(portable, self.machine());
+    }
+
+    /// Practice settings to pre-fill the practice menu with for a song
+    pub fn practice_for_song(&self, song_path: &str) -> PracticeConfig {
+        if self.practice.remember_per_song {
+            let mut settings = self.practice_songs.get_or_default(song_path, &self.practice);
+            settings.remember_per_song = true;
+            settings
+        } else {
+            self.practice.clone()
+        }
+    }
+
+    /// Restore practice defaults; remembered per-song settings are kept
+    pub fn reset_practice_defaults(&mut self) {
+        self.practice = PracticeConfig::default();
+    }
+
+    /// Name to pre-fill as a beatmap's creator, falling back to the OS account name
+    pub fn creator_name(&self) -> String {
+        self.display_name
+            .clone()
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .unwrap_or_default()
+    }
+
+    /// Volume songs play at: master times music, each clamped to 0.0 - 1.0. A master
+    /// volume of 0.0 mutes
+    pub fn effective_music_volume(&self) -> f32 {
+        self.audio.master_volume.clamp(0.0, 1.0) * self.audio.music_volume.clamp(0.0, 1.0)
+    }
+
+    /// Volume hitsounds and other effects play at, clamped like the music volume
+    pub fn effective_effects_volume(&self) -> f32 {
+        self.audio.master_volume.clamp(0.0, 1.0) * self.audio.effects_volume.clamp(0.0, 1.0)
+    }
+
+    /// Whether finished sessions should be written to analytics
+    pub fn records_analytics(&self) -> bool {
+        self.save_analytics && !self.incognito
+    }
+
+    /// Whether a user is muted
+    pub fn is_muted(&self, user_id: Uuid) -> bool {
+        self.muted_users.contains(&user_id)
+    }
+
+    /// Mute a user; returns false if they were already muted
+    pub fn mute_user(&mut self, user_id: Uuid) -> bool {
+        self.muted_users.insert(user_id)
+    }
+
+    /// Unmute a user; returns false if they were not muted
+    pub fn unmute_user(&mut self, user_id: Uuid) -> bool {
+        self.muted_users.remove(&user_id)
     }
 
     /// Reset to default configuration
@@ -431,6 +984,20 @@ impl GameConfig {
     }
 }
 
+/// Deserialize one config section, using defaults if it is missing or invalid
+fn section_or_default<T: for<'de> Deserialize<'de> + Default>(
+    value: Option<&serde_json::Value>,
+    name: &str,
+) -> T {
+    match value {
+        Some(value) => serde_json::from_value(value.clone()).unwrap_or_else(|e| {
+            warn!("Failed to parse {} config: {}, using default", name, e);
+            T::default()
+        }),
+        None => T::default(),
+    }
+}
+
 /// Settings menu state
 #[derive(Debug, Clone, Resource)]
 pub struct SettingsState {
@@ -438,10 +1005,390 @@ pub struct SettingsState {
     pub current_tab: SettingsTab,
     /// Whether we're waiting for a key input
     pub waiting_for_key: Option<KeyBindingType>,
+    /// Captured key that clashes with another binding, waiting for swap / assign / cancel
+    pub pending_binding: Option<PendingBinding>,
     /// Selected item index for keyboard navigation
     pub selected_index: usize,
     /// Scroll position for settings menu
     pub scroll_y: f32,
+    /// Text typed so far while confirming analytics deletion
+    pub delete_confirmation: Option<TextInput>,
+    /// Result of the last data action, shown under the rows
+    pub notice: Option<String>,
+    /// Presets offered in the Theme tab
+    pub theme_presets: Vec<ThemePresetEntry>,
+    /// Preset picked in the Theme tab
+    pub theme_preset_index: usize,
+    /// Imported theme waiting for confirmation, shown next to the current one
+    pub theme_preview: Option<ThemePreset>,
+    /// Beatmap file and the audio path typed so far while locating its audio
+    pub locate_audio: Option<(String, TextInput)>,
+    /// Beatmap file waiting for a second Delete press before it is removed
+    pub pending_removal: Option<String>,
+    /// Search across every tab's settings, open while typing a query
+    pub search: Option<SettingsSearch>,
+}
+
+/// Rows of the Practice settings tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PracticeSetting {
+    PlaybackSpeed,
+    PreservePitch,
+    NoFail,
+    Autoplay,
+    HitSounds,
+    Metronome,
+    RememberPerSong,
+    ResetDefaults,
+}
+
+impl PracticeSetting {
+    /// Label with the current value
+    pub fn label(&self, practice: &PracticeConfig) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            PracticeSetting::PlaybackSpeed => {
+                format!("Default Speed: {:.2}x", practice.playback_speed)
+            }
+            PracticeSetting::PreservePitch => {
+                format!("Preserve Pitch: {}", on_off(practice.preserve_pitch))
+            }
+            PracticeSetting::NoFail => format!("Default No Fail: {}", on_off(practice.no_fail)),
+            PracticeSetting::Autoplay => format!("Default Autoplay: {}", on_off(practice.autoplay)),
+            PracticeSetting::HitSounds => format!("Hit Sounds: {}", on_off(practice.hit_sounds)),
+            PracticeSetting::Metronome => {
+                format!("Metronome: {}", practice.metronome.display_name())
+            }
+            PracticeSetting::RememberPerSong => format!(
+                "Remember Settings Per Song: {}",
+                on_off(practice.remember_per_song)
+            ),
+            PracticeSetting::ResetDefaults => "Reset Practice Defaults".to_string(),
+        }
+    }
+
+    /// Change the value with Left/Right (`-1`/`1`) or Enter (`0`)
+    pub fn adjust(&self, config: &mut GameConfig, direction: i32) {
+        let practice = &mut config.practice;
+        match self {
+            PracticeSetting::PlaybackSpeed => {
+                practice.cycle_speed(if direction == 0 { 1 } else { direction })
+            }
+            PracticeSetting::PreservePitch => practice.preserve_pitch = !practice.preserve_pitch,
+            PracticeSetting::NoFail => practice.no_fail = !practice.no_fail,
+            PracticeSetting::Autoplay => practice.autoplay = !practice.autoplay,
+            PracticeSetting::HitSounds => practice.hit_sounds = !practice.hit_sounds,
+            PracticeSetting::Metronome => practice.metronome = practice.metronome.cycled(direction),
+            PracticeSetting::RememberPerSong => {
+                practice.remember_per_song = !practice.remember_per_song
+            }
+            PracticeSetting::ResetDefaults => {
+                if direction == 0 {
+                    config.reset_practice_defaults();
+                }
+            }
+        }
+    }
+}
+
+/// Word that must be typed to delete all local analytics
+pub const DELETE_ANALYTICS_CONFIRMATION: &str = "DELETE";
+
+/// Data and privacy rows of the General tab, after the generation rows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataSetting {
+    Incognito,
+    HitPrecision,
+    DeleteAnalytics,
+    LibraryHealth,
+    LogVerbosity,
+    SessionDetails,
+    /// Size of the stored accounts, sessions and friend lists
+    AccountStorage,
+    /// Clean up stale sessions and dangling friend entries now rather than within the hour
+    AccountCleanup,
+}
+
+impl DataSetting {
+    /// Label with the current value
+    pub fn label(
+        &self,
+        config: &GameConfig,
+        library: &LibraryScan,
+        maintenance: &AccountMaintenance,
+    ) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            DataSetting::Incognito => format!(
+                "Incognito (record nothing): {}",
+                if config.incognito { "ON" } else { "OFF" }
+            ),
+            DataSetting::HitPrecision => format!(
+                "Record Hit Precision: {}",
+                on_off(config.track_hit_precision)
+            ),
+            DataSetting::DeleteAnalytics => "Delete All Local Analytics...".to_string(),
+            DataSetting::LibraryHealth => format!("Beatmap Library: {}", library.status()),
+            DataSetting::LogVerbosity => {
+                format!("Log Level: {}", config.log_verbosity.display_name())
+            }
+            DataSetting::SessionDetails => {
+                let dir = profile_dir(&config.active_profile).join(SESSION_DETAILS_DIR);
+                format!(
+                    "Session Details: {}",
+                    DetailsUsage::of(&dir).summary(config.session_details_cap_mb)
+                )
+            }
+            DataSetting::AccountStorage => format!("Account {}", maintenance.storage.summary()),
+            DataSetting::AccountCleanup if maintenance.is_running() => {
+                "Cleaning Up Account Data...".to_string()
+            }
+            DataSetting::AccountCleanup => "Clean Up Account Data Now".to_string(),
+        }
+    }
+}
+
+/// Rows of the Theme tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThemeSetting {
+    Preset,
+    FollowPoints,
+    HitLighting,
+    HitErrorBar,
+    Export,
+}
+
+impl ThemeSetting {
+    /// Label with the current value
+    pub fn label(&self, state: &SettingsState, theme: &ThemeConfig) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            ThemeSetting::Preset => format!(
+                "Preset: {}",
+                state
+                    .theme_presets
+                    .get(state.theme_preset_index)
+                    .map_or("-", |entry| entry.name.as_str())
+            ),
+            ThemeSetting::FollowPoints => format!("Follow Points: {}", on_off(theme.follow_points)),
+            ThemeSetting::HitLighting => format!("Hit Lighting: {}", on_off(theme.hit_lighting)),
+            ThemeSetting::HitErrorBar => {
+                format!("Hit Error Bar: {}", on_off(theme.show_hit_error_bar))
+            }
+            ThemeSetting::Export => "Export Current Theme".to_string(),
+        }
+    }
+}
+
+/// Rows of the Gameplay tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameplaySetting {
+    HudLayout,
+    ReducedMotion,
+    AccuracyReadout,
+    AccuracyBar,
+    LargeScores,
+}
+
+impl GameplaySetting {
+    /// Label with the current value
+    pub fn label(&self, config: &GameConfig) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            GameplaySetting::HudLayout => "Edit HUD layout...".to_string(),
+            GameplaySetting::ReducedMotion => {
+                format!("Reduced Motion: {}", on_off(config.theme.reduced_motion))
+            }
+            GameplaySetting::AccuracyReadout => format!(
+                "Accuracy Readout: {}",
+                on_off(config.hud_layout.get(HudElement::Accuracy).visible)
+            ),
+            GameplaySetting::AccuracyBar => {
+                format!("Accuracy Bar: {}", on_off(config.hud_layout.accuracy_bar))
+            }
+            GameplaySetting::LargeScores => {
+                format!("Large Scores: {}", config.hud_layout.score_style.label())
+            }
+        }
+    }
+
+    /// Toggle the value with Left/Right or Enter. The HUD layout row opens an editor
+    /// instead, which the caller handles
+    pub fn adjust(&self, config: &mut GameConfig) {
+        match self {
+            GameplaySetting::HudLayout => {}
+            GameplaySetting::ReducedMotion => {
+                config.theme.reduced_motion = !config.theme.reduced_motion
+            }
+            GameplaySetting::AccuracyReadout => {
+                config.hud_layout.toggle_visible(HudElement::Accuracy)
+            }
+            GameplaySetting::AccuracyBar => {
+                config.hud_layout.accuracy_bar = !config.hud_layout.accuracy_bar
+            }
+            GameplaySetting::LargeScores => {
+                config.hud_layout.score_style = config.hud_layout.score_style.toggled()
+            }
+        }
+    }
+}
+
+/// Rows of the Audio tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioSetting {
+    MasterVolume,
+    MusicVolume,
+    DetectionQuality,
+    MenuMusic,
+    AudioOffset,
+    /// Opens the offset calibration screen
+    CalibrateOffset,
+    /// Opens the library analysis screen
+    AnalyzeLibrary,
+}
+
+impl AudioSetting {
+    /// Label with the current value
+    pub fn label(&self, audio: &AudioConfig) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            AudioSetting::MasterVolume => {
+                format!("Master Volume: {:.0}%", audio.master_volume * 100.0)
+            }
+            AudioSetting::MusicVolume => {
+                format!("Music Volume: {:.0}%", audio.music_volume * 100.0)
+            }
+            AudioSetting::DetectionQuality => {
+                format!("Beat Detection: {}", audio.detection_quality.display_name())
+            }
+            AudioSetting::MenuMusic => format!("Menu Music: {}", on_off(audio.menu_music)),
+            AudioSetting::AudioOffset => format!("Audio Offset: {:+} ms", audio.offset_ms()),
+            AudioSetting::CalibrateOffset => "Calibrate Offset...".to_string(),
+            AudioSetting::AnalyzeLibrary => "Analyze Entire Library...".to_string(),
+        }
+    }
+
+    /// Cycle the value with Left/Right (`-1`/`1`) or Enter (`0`)
+    pub fn adjust(&self, audio: &mut AudioConfig, direction: i32) {
+        let step = |volume: f32| (volume + direction as f32 * 0.05).clamp(0.0, 1.0);
+        match self {
+            AudioSetting::MasterVolume => audio.master_volume = step(audio.master_volume),
+            AudioSetting::MusicVolume => audio.music_volume = step(audio.music_volume),
+            AudioSetting::DetectionQuality => {
+                audio.detection_quality = audio.detection_quality.cycled(direction)
+            }
+            AudioSetting::MenuMusic => audio.menu_music = !audio.menu_music,
+            AudioSetting::AudioOffset => {
+                audio.audio_offset_ms = (audio.offset_ms() + direction * AUDIO_OFFSET_STEP_MS)
+                    .clamp(-AUDIO_OFFSET_LIMIT_MS, AUDIO_OFFSET_LIMIT_MS)
+            }
+            AudioSetting::CalibrateOffset | AudioSetting::AnalyzeLibrary => {}
+        }
+    }
+}
+
+/// Rows of the Display tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplaySetting {
+    PerformancePreset,
+    ReducedInputLatency,
+}
+
+impl DisplaySetting {
+    /// Label with the current value
+    pub fn label(&self, display: &DisplayConfig) -> String {
+        match self {
+            DisplaySetting::PerformancePreset => format!(
+                "Performance: {}",
+                display.performance_preset().display_name()
+            ),
+            DisplaySetting::ReducedInputLatency => format!(
+                "Reduced Input Latency: {}",
+                if display.reduced_input_latency {
+                    "ON"
+                } else {
+                    "OFF"
+                }
+            ),
+        }
+    }
+
+    /// Cycle the value with Left/Right (`-1`/`1`) or Enter (`0`)
+    pub fn adjust(&self, display: &mut DisplayConfig, direction: i32) {
+        match self {
+            DisplaySetting::PerformancePreset => {
+                display.performance_preset = Some(display.performance_preset().cycled(direction))
+            }
+            DisplaySetting::ReducedInputLatency => {
+                display.reduced_input_latency = !display.reduced_input_latency
+            }
+        }
+    }
+}
+
+/// Map generation thresholds editable from the General tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GenerationSetting {
+    SpinnerMinGap,
+    SpinnerMinEnergy,
+    StreamMaxInterval,
+    StreamMinBeats,
+    SliderMaxBeats,
+}
+
+impl GenerationSetting {
+    /// Label with the current value
+    pub fn label(&self, settings: &GenerationSettings) -> String {
+        match self {
+            GenerationSetting::SpinnerMinGap => {
+                format!("Spinner Min Gap: {:.1}s", settings.spinner_min_gap)
+            }
+            GenerationSetting::SpinnerMinEnergy => {
+                format!("Spinner Min Energy: {:.0}%", settings.spinner_min_energy * 100.0)
+            }
+            GenerationSetting::StreamMaxInterval => {
+                format!("Stream Max Interval: {:.0}ms", settings.stream_max_interval * 1000.0)
+            }
+            GenerationSetting::StreamMinBeats => {
+                format!("Stream Min Beats: {}", settings.stream_min_beats)
+            }
+            GenerationSetting::SliderMaxBeats => {
+                format!("Slider Max Beats: {}", settings.slider_max_beats)
+            }
+        }
+    }
+
+    /// Step the value up (`1`) or down (`-1`), clamped to a sane range
+    pub fn adjust(&self, settings: &mut GenerationSettings, direction: i32) {
+        let step = direction as f64;
+        match self {
+            GenerationSetting::SpinnerMinGap => {
+                settings.spinner_min_gap = (settings.spinner_min_gap + step * 0.5).clamp(1.5, 10.0);
+            }
+            GenerationSetting::SpinnerMinEnergy => {
+                settings.spinner_min_energy =
+                    (settings.spinner_min_energy + direction as f32 * 0.05).clamp(0.0, 1.0);
+            }
+            GenerationSetting::StreamMaxInterval => {
+                settings.stream_max_interval =
+                    (settings.stream_max_interval + step * 0.01).clamp(0.05, 0.5);
+            }
+            GenerationSetting::StreamMinBeats => {
+                settings.stream_min_beats =
+                    (settings.stream_min_beats as i32 + direction).clamp(2, 16) as usize;
+            }
+            GenerationSetting::SliderMaxBeats => {
+                settings.slider_max_beats =
+                    (settings.slider_max_beats as i32 + direction).clamp(2, 16) as usize;
+            }
+        }
+    }
+}
+
+impl Default for SettingsState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SettingsState {
@@ -450,8 +1397,17 @@ impl SettingsState {
         Self {
             current_tab: SettingsTab::General,
             waiting_for_key: None,
+            pending_binding: None,
             selected_index: 0,
             scroll_y: 0.0,
+            delete_confirmation: None,
+            notice: None,
+            theme_presets: list_presets(),
+            theme_preset_index: 0,
+            theme_preview: None,
+            locate_audio: None,
+            pending_removal: None,
+            search: None,
         }
     }
 }
@@ -460,9 +1416,11 @@ impl SettingsState {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SettingsTab {
     General,
+    Gameplay,
     KeyBindings,
     Theme,
     Audio,
+    Display,
     Practice,
 }
 
@@ -471,20 +1429,37 @@ impl SettingsTab {
     pub fn all() -> Vec<(SettingsTab, &'static str)> {
         vec![
             (SettingsTab::General, "General"),
+            (SettingsTab::Gameplay, "Gameplay"),
             (SettingsTab::KeyBindings, "Key Bindings"),
             (SettingsTab::Theme, "Theme"),
             (SettingsTab::Audio, "Audio"),
+            (SettingsTab::Display, "Display"),
             (SettingsTab::Practice, "Practice"),
         ]
     }
 
+    /// Display name of the tab
+    pub fn name(&self) -> &'static str {
+        Self::all()
+            .into_iter()
+            .find(|(tab, _)| tab == self)
+            .map_or("", |(_, name)| name)
+    }
+
+    /// Whether this tab holds machine-local settings that never sync
+    pub fn is_machine_local(&self) -> bool {
+        matches!(self, SettingsTab::Audio | SettingsTab::Display)
+    }
+
     /// Get the next tab
     pub fn next(&self) -> SettingsTab {
         match self {
-            SettingsTab::General => SettingsTab::KeyBindings,
+            SettingsTab::General => SettingsTab::Gameplay,
+            SettingsTab::Gameplay => SettingsTab::KeyBindings,
             SettingsTab::KeyBindings => SettingsTab::Theme,
             SettingsTab::Theme => SettingsTab::Audio,
-            SettingsTab::Audio => SettingsTab::Practice,
+            SettingsTab::Audio => SettingsTab::Display,
+            SettingsTab::Display => SettingsTab::Practice,
             SettingsTab::Practice => SettingsTab::General,
         }
     }
@@ -493,10 +1468,12 @@ impl SettingsTab {
     pub fn previous(&self) -> SettingsTab {
         match self {
             SettingsTab::General => SettingsTab::Practice,
-            SettingsTab::KeyBindings => SettingsTab::General,
+            SettingsTab::Gameplay => SettingsTab::General,
+            SettingsTab::KeyBindings => SettingsTab::Gameplay,
             SettingsTab::Theme => SettingsTab::KeyBindings,
             SettingsTab::Audio => SettingsTab::Theme,
-            SettingsTab::Practice => SettingsTab::Audio,
+            SettingsTab::Display => SettingsTab::Audio,
+            SettingsTab::Practice => SettingsTab::Display,
         }
     }
 }
@@ -507,9 +1484,14 @@ pub enum KeyBindingType {
     PrimaryHit,
     SecondaryHit,
     Pause,
+    Exit,
     NavigateUp,
     NavigateDown,
     Select,
+    QuickRetry,
+    SkipIntro,
+    VersusPrimaryHit,
+    VersusSecondaryHit,
 }
 
 impl KeyBindingType {
@@ -519,9 +1501,14 @@ impl KeyBindingType {
             KeyBindingType::PrimaryHit => "Primary Hit",
             KeyBindingType::SecondaryHit => "Secondary Hit",
             KeyBindingType::Pause => "Pause",
+            KeyBindingType::Exit => "Exit Song",
             KeyBindingType::NavigateUp => "Navigate Up",
             KeyBindingType::NavigateDown => "Navigate Down",
             KeyBindingType::Select => "Select / Confirm",
+            KeyBindingType::QuickRetry => "Quick Retry",
+            KeyBindingType::SkipIntro => "Skip Intro",
+            KeyBindingType::VersusPrimaryHit => "Versus P2 Primary Hit",
+            KeyBindingType::VersusSecondaryHit => "Versus P2 Secondary Hit",
         }
     }
 
@@ -531,9 +1518,189 @@ impl KeyBindingType {
             KeyBindingType::PrimaryHit,
             KeyBindingType::SecondaryHit,
             KeyBindingType::Pause,
+            KeyBindingType::Exit,
             KeyBindingType::NavigateUp,
             KeyBindingType::NavigateDown,
             KeyBindingType::Select,
+            KeyBindingType::QuickRetry,
+            KeyBindingType::SkipIntro,
+            KeyBindingType::VersusPrimaryHit,
+            KeyBindingType::VersusSecondaryHit,
         ]
     }
+
+    /// Whether the binding is read during play; the rest are only read in menus
+    pub fn in_gameplay(&self) -> bool {
+        !matches!(
+            self,
+            KeyBindingType::NavigateUp | KeyBindingType::NavigateDown | KeyBindingType::Select
+        )
+    }
+
+    /// Row label in the Key Bindings tab
+    pub fn label(&self, config: &GameConfig, state: &SettingsState) -> String {
+        let key = if state.waiting_for_key == Some(*self) {
+            "press a key...".to_string()
+        } else {
+            key_label(config.key_bindings.get(*self))
+        };
+        format!("{}: {}", self.display_name(), key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn flat_configs_migrate_into_both_sections() {
+        let config = GameConfig::from_json_value(json!({
+            "key_bindings": { "primary_hit": "KeyZ", "secondary_hit": "KeyX" },
+            "audio": { "master_volume": 0.4 },
+            "song_directories": ["/music"],
+            "save_analytics": false,
+        }));
+        assert_eq!(config.key_bindings.primary_hit, "KeyZ");
+        assert_eq!(config.key_bindings.secondary_hit, "KeyX");
+        assert_eq!(config.audio.master_volume, 0.4);
+        assert_eq!(config.song_directories, vec!["/music".to_string()]);
+        assert!(!config.save_analytics);
+    }
+
+    #[test]
+    fn split_configs_round_trip() {
+        let mut config = GameConfig::default();
+        config.key_bindings.primary_hit = "KeyJ".to_string();
+        config.audio.music_volume = 0.25;
+        let value = serde_json::to_value(&config).unwrap();
+        assert!(value.get("portable").is_some() && value.get("machine").is_some());
+
+        let loaded = GameConfig::from_json_value(value);
+        assert_eq!(loaded.key_bindings.primary_hit, "KeyJ");
+        assert_eq!(loaded.audio.music_volume, 0.25);
+    }
+
+    #[test]
+    fn a_broken_section_only_resets_itself() {
+        let mut config = GameConfig::default();
+        config.audio.master_volume = 0.3;
+        let mut value = serde_json::to_value(&config).unwrap();
+        value["portable"] = json!("not a section");
+
+        let loaded = GameConfig::from_json_value(value);
+        assert_eq!(loaded.audio.master_volume, 0.3);
+        assert_eq!(
+            loaded.key_bindings.primary_hit,
+            KeyBindings::default().primary_hit
+        );
+    }
+
+    #[test]
+    fn merging_synced_settings_keeps_the_machine_ones() {
+        let mut config = GameConfig::default();
+        config.audio.master_volume = 0.3;
+        config.song_directories = vec!["/music".to_string()];
+
+        let mut synced = GameConfig::default().portable();
+        synced.key_bindings.primary_hit = "KeyQ".to_string();
+        config.merge_portable(synced);
+
+        assert_eq!(config.key_bindings.primary_hit, "KeyQ");
+        assert_eq!(config.audio.master_volume, 0.3);
+        assert_eq!(config.song_directories, vec!["/music".to_string()]);
+    }
+
+    #[test]
+    fn songs_without_saved_practice_use_the_global_defaults() {
+        let mut config = GameConfig::default();
+        config.practice.playback_speed = 0.75;
+        config.practice.remember_per_song = true;
+        assert_eq!(config.practice_for_song("a.mp3").playback_speed, 0.75);
+
+        let mut saved = config.practice.clone();
+        saved.playback_speed = 0.5;
+        saved.loop_start = Some(10.0);
+        saved.loop_end = Some(20.0);
+        config.practice_songs.remember("a.mp3", saved);
+        let practice = config.practice_for_song("a.mp3");
+        assert_eq!(practice.playback_speed, 0.5);
+        assert_eq!(
+            (practice.loop_start, practice.loop_end),
+            (Some(10.0), Some(20.0))
+        );
+        assert_eq!(config.practice_for_song("b.mp3").loop_start, None);
+
+        // Turning remembering off goes back to the defaults for every song
+        config.practice.remember_per_song = false;
+        assert_eq!(config.practice_for_song("a.mp3").playback_speed, 0.75);
+    }
+
+    #[test]
+    fn per_song_practice_survives_a_save_and_load() {
+        let mut config = GameConfig::default();
+        let saved = PracticeConfig {
+            playback_speed: 1.25,
+            loop_start: Some(3.5),
+            ..PracticeConfig::default()
+        };
+        config.practice_songs.remember("a.mp3", saved);
+
+        let loaded = GameConfig::from_json_value(serde_json::to_value(&config).unwrap());
+        let practice = loaded
+            .practice_songs
+            .get_or_default("a.mp3", &PracticeConfig::default());
+        assert_eq!(practice.playback_speed, 1.25);
+        assert_eq!(practice.loop_start, Some(3.5));
+    }
+
+    #[test]
+    fn resetting_practice_defaults_keeps_per_song_settings() {
+        let mut config = GameConfig::default();
+        config.practice.playback_speed = 0.5;
+        let saved = PracticeConfig {
+            playback_speed: 0.75,
+            ..PracticeConfig::default()
+        };
+        config.practice_songs.remember("a.mp3", saved);
+
+        config.reset_practice_defaults();
+        assert_eq!(
+            config.practice.playback_speed,
+            PracticeConfig::default().playback_speed
+        );
+        assert_eq!(config.practice_songs.songs["a.mp3"].playback_speed, 0.75);
+    }
+
+    #[test]
+    fn incognito_stops_analytics_recording() {
+        let config = GameConfig {
+            incognito: true,
+            ..Default::default()
+        };
+        assert!(!config.records_analytics());
+        assert!(GameConfig::default().records_analytics());
+    }
+
+    #[test]
+    fn effective_volumes_multiply_master_and_channel() {
+        let mut config = GameConfig::default();
+        config.audio.master_volume = 0.5;
+        config.audio.music_volume = 0.8;
+        config.audio.effects_volume = 0.25;
+        assert!((config.effective_music_volume() - 0.4).abs() < 1e-6);
+        assert!((config.effective_effects_volume() - 0.125).abs() < 1e-6);
+
+        // A muted master silences everything, whatever the channels say
+        config.audio.master_volume = 0.0;
+        assert_eq!(config.effective_music_volume(), 0.0);
+        assert_eq!(config.effective_effects_volume(), 0.0);
+
+        // Hand-edited values out of range are clamped
+        config.audio.master_volume = 3.0;
+        config.audio.music_volume = -1.0;
+        config.audio.effects_volume = 1.5;
+        assert_eq!(config.effective_music_volume(), 0.0);
+        assert_eq!(config.effective_effects_volume(), 1.0);
+    }
 }
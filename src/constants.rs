@@ -8,6 +8,11 @@ pub const SHRINK_TIME: f64 = 1.5; // Time it takes for a circle to shrink
 pub const CIRCLE_MAX_RADIUS: f32 = 100.0; // Maximum radius of circles
 pub const OUTLINE_THICKNESS: f32 = 2.0; // Thickness of the circle outline
 
+// Judgment windows, seconds either side of the hit time
+pub const PERFECT_WINDOW: f64 = 0.08; // 300 points
+pub const GOOD_WINDOW: f64 = 0.2; // 100 points
+pub const OKAY_WINDOW: f64 = 0.35; // 50 points
+
 // Score display styling
 pub const SCORE_FONT_SIZE: f32 = 40.0; // Size of the score font
 
@@ -15,16 +20,30 @@ pub const SCORE_FONT_SIZE: f32 = 40.0; // Size of the score font
 pub const OUTLINE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.5); // Semi-transparent white outline
 pub const DARK_BACKGROUND: Color = Color::srgba(0.05, 0.05, 0.1, 1.0); // Dark background to enhance neon colors
 
-// Positioning for score display
-pub const DRAW_SCORE_X: f32 = 20.0; // X position for score
-pub const DRAW_SCORE_Y: f32 = 40.0; // Y position for score
-
 // Song selection and entry heights
 pub const SONG_ENTRY_HEIGHT: f32 = 40.0; // Height of each song entry
+pub const DIFFICULTY_CHART_RADIUS: f32 = 24.0; // Radius of the difficulty radar chart in beatmap lists
 pub const FONT_SIZE: u16 = 30; // General font size for text
 
 // Countdown behavior
 pub const COUNTDOWN_DURATION: f64 = 5.0; // Countdown before game starts
+pub const DETECTION_OVERRIDE_WINDOW: f64 = 1.0; // Seconds the loading screen offers a detection mode change
+pub const QUICK_RETRY_COOLDOWN: f64 = 0.5; // Seconds into an attempt before quick retry works again
+pub const INTRO_SKIP_THRESHOLD: f64 = 6.0; // Wait before the first circle needed to offer an intro skip
+pub const INTRO_SKIP_LEAD_IN: f64 = 1.5; // Seconds left before the first circle after skipping
+
+// Health. Only misses and slider breaks drain it, so a play without either never fails;
+// at the default HP drain of 5 ten misses in a row fail a full bar, and a miss takes
+// about three perfects to win back
+pub const HP_MISS_BASE: f32 = 0.04; // Health a miss costs at HP drain 0
+pub const HP_MISS_PER_DRAIN: f32 = 0.012; // Extra health a miss costs per point of HP drain
+pub const HP_PERFECT_RECOVERY: f32 = 0.03; // Health a perfect gives back
+pub const HP_GOOD_RECOVERY: f32 = 0.015; // Health a good gives back
+pub const NO_FAIL_HP_FLOOR: f32 = 0.05; // Health never drops below this when the play can't fail
+
+// Slider bodies, scored on top of the head's judgment
+pub const SLIDER_TICK_SCORE: i32 = 10; // Points per body tick passed while holding
+pub const SLIDER_END_SCORE: i32 = 30; // Points for holding to the slider end
 
 // Cyberpunk neon colors
 pub const NEON_PINK: Color = Color::srgba(1.0, 0.07, 0.58, 1.0); // Neon pink for active UI elements
@@ -53,6 +72,7 @@ pub const WARNING_COLOR: Color = NEON_YELLOW;
 pub const ERROR_COLOR: Color = NEON_ORANGE;
 
 // Grade colors
+pub const GRADE_SS_PLUS_COLOR: Color = Color::srgba(0.85, 0.95, 1.0, 1.0);
 pub const GRADE_SS_COLOR: Color = Color::srgba(1.0, 0.84, 0.0, 1.0);
 pub const GRADE_S_COLOR: Color = Color::srgba(1.0, 0.5, 0.0, 1.0);
 pub const GRADE_A_COLOR: Color = NEON_GREEN;
@@ -61,6 +81,10 @@ pub const GRADE_C_COLOR: Color = NEON_PURPLE;
 pub const GRADE_D_COLOR: Color = NEON_PINK;
 pub const GRADE_F_COLOR: Color = Color::srgba(1.0, 0.0, 0.0, 1.0);
 
+// Editor timing point colors
+pub const UNINHERITED_POINT_COLOR: Color = Color::srgba(1.0, 0.2, 0.2, 1.0); // Red lines change BPM
+pub const INHERITED_POINT_COLOR: Color = NEON_GREEN; // Green lines change slider velocity
+
 // Practice mode constants
 pub const MIN_PLAYBACK_SPEED: f32 = 0.25;
 pub const MAX_PLAYBACK_SPEED: f32 = 2.0;
@@ -73,6 +97,11 @@ pub const COMBO_MILESTONES: [u32; 5] = [10, 25, 50, 100, 200];
 pub const PULSE_SPEED: f32 = 2.0;
 pub const GLOW_INTENSITY: f32 = 0.5;
 
+// Kiai effect strength at full intensity
+pub const KIAI_GLOW_BOOST: f32 = 0.6; // Extra circle outline glow
+pub const KIAI_BACKGROUND_ALPHA: f32 = 0.12; // Opacity of the background wash
+pub const KIAI_MILESTONE_BOOST: f32 = 0.5; // Extra size of combo milestone text
+
 /// Window configuration for Bevy
 pub fn window_config() -> WindowPlugin {
     WindowPlugin {
@@ -90,6 +119,7 @@ pub fn window_config() -> WindowPlugin {
 /// Get grade color based on grade string
 pub fn get_grade_color(grade: &str) -> Color {
     match grade {
+        "SS+" | "AAA" => GRADE_SS_PLUS_COLOR,
         "SS" => GRADE_SS_COLOR,
         "S" => GRADE_S_COLOR,
         "A" => GRADE_A_COLOR,
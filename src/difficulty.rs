@@ -0,0 +1,281 @@
+//! Beatmap difficulty: star rating and the sub-metrics it is built from.
+//!
+//! Every metric is on the same rough 0-10 scale as the star rating so the
+//! difficulty picker can chart them against each other.
+
+use crate::beatmap::Beatmap;
+use crate::hashing::fnv1a;
+use serde::{Deserialize, Serialize};
+
+/// Length of the windows stamina is measured over (seconds)
+pub const STAMINA_WINDOW: f64 = 30.0;
+
+/// Shortest gap between objects used for aim strain; stacked chords would otherwise explode it
+pub const MIN_OBJECT_GAP: f64 = 0.025;
+
+/// Cursor speed (osu! pixels per second) that counts as one point of aim strain
+pub const AIM_PIXELS_PER_POINT: f64 = 100.0;
+
+/// Highest value any metric is charted at
+pub const METRIC_SCALE_MAX: f32 = 10.0;
+
+/// Tag marking a beatmap that was generated from audio rather than mapped
+pub const GENERATED_TAG: &str = "generated";
+
+/// Sub-metrics of a beatmap's difficulty
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct DifficultyMetrics {
+    /// Aim strain from spacing between consecutive objects
+    pub aim: f32,
+    /// Speed strain from the overall note rate
+    pub speed: f32,
+    /// Share of objects that are sliders
+    pub slider_density: f32,
+    /// Note rate sustained over the densest stamina window
+    pub stamina: f32,
+}
+
+impl DifficultyMetrics {
+    /// Compute every metric for a beatmap
+    pub fn calculate(beatmap: &Beatmap) -> Self {
+        Self {
+            aim: aim_strain(beatmap),
+            speed: speed_strain(beatmap),
+            slider_density: slider_density(beatmap),
+            stamina: stamina(beatmap),
+        }
+    }
+
+    /// Metrics in chart order with their labels
+    pub fn axes(&self) -> [(&'static str, f32); 4] {
+        [
+            ("Aim", self.aim),
+            ("Speed", self.speed),
+            ("Sliders", self.slider_density),
+            ("Stamina", self.stamina),
+        ]
+    }
+
+    /// Star rating built from the metrics
+    pub fn star_rating(&self) -> f32 {
+        let stars = self.aim * 0.4 + self.speed * 0.3 + self.stamina * 0.25 + self.slider_density * 0.05;
+        stars.clamp(0.0, METRIC_SCALE_MAX)
+    }
+}
+
+/// What the difficulty picker can show for a beatmap
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DifficultySummary {
+    /// Hand-made map: star rating plus the full radar chart
+    Detailed(DifficultyMetrics),
+    /// Generated map: only the star rating is meaningful
+    StarsOnly(f32),
+}
+
+impl DifficultySummary {
+    /// Summarize a beatmap, dropping the breakdown for generated maps
+    pub fn for_beatmap(beatmap: &Beatmap) -> Self {
+        let metrics = DifficultyMetrics::calculate(beatmap);
+        if is_generated(beatmap) {
+            DifficultySummary::StarsOnly(metrics.star_rating())
+        } else {
+            DifficultySummary::Detailed(metrics)
+        }
+    }
+
+    /// Star rating regardless of detail level
+    pub fn star_rating(&self) -> f32 {
+        match self {
+            DifficultySummary::Detailed(metrics) => metrics.star_rating(),
+            DifficultySummary::StarsOnly(stars) => *stars,
+        }
+    }
+
+    /// Breakdown for the radar chart, if there is one
+    pub fn metrics(&self) -> Option<&DifficultyMetrics> {
+        match self {
+            DifficultySummary::Detailed(metrics) => Some(metrics),
+            DifficultySummary::StarsOnly(_) => None,
+        }
+    }
+}
+
+/// Whether the beatmap came from the procedural generator
+pub fn is_generated(beatmap: &Beatmap) -> bool {
+    beatmap.tags.iter().any(|tag| tag == GENERATED_TAG)
+}
+
+/// Hash of the beatmap contents, used to key cached difficulty
+pub fn content_hash(beatmap: &Beatmap) -> u64 {
+    fnv1a(&serde_json::to_vec(beatmap).unwrap_or_default())
+}
+
+/// Mean cursor speed between consecutive objects
+pub fn aim_strain(beatmap: &Beatmap) -> f32 {
+    let objects = &beatmap.hit_objects;
+    if objects.len() < 2 {
+        return 0.0;
+    }
+
+    let mut total_speed = 0.0;
+    for pair in objects.windows(2) {
+        let start = pair[0].kind.end_position(pair[0].position);
+        let gap = (pair[1].time - beatmap.object_end_time(&pair[0])).max(MIN_OBJECT_GAP);
+        total_speed += start.distance(pair[1].position) as f64 / gap;
+    }
+
+    let mean_speed = total_speed / (objects.len() - 1) as f64;
+    ((mean_speed / AIM_PIXELS_PER_POINT) as f32).min(METRIC_SCALE_MAX)
+}
+
+/// Objects per second over the drain time
+pub fn speed_strain(beatmap: &Beatmap) -> f32 {
+    let objects = &beatmap.hit_objects;
+    if objects.len() < 2 {
+        return 0.0;
+    }
+
+    let drain = objects[objects.len() - 1].time - objects[0].time;
+    if drain <= 0.0 {
+        return 0.0;
+    }
+    (((objects.len() - 1) as f64 / drain) as f32).min(METRIC_SCALE_MAX)
+}
+
+/// Share of objects that are sliders, on the metric scale
+pub fn slider_density(beatmap: &Beatmap) -> f32 {
+    let stats = beatmap.get_object_stats();
+    if stats.total == 0 {
+        return 0.0;
+    }
+    stats.sliders as f32 / stats.total as f32 * METRIC_SCALE_MAX
+}
+
+/// Objects per second in the densest stamina window
+pub fn stamina(beatmap: &Beatmap) -> f32 {
+    let times: Vec<f64> = beatmap.hit_objects.iter().map(|object| object.time).collect();
+    if times.is_empty() {
+        return 0.0;
+    }
+
+    // Shorter maps are judged over their whole length
+    let window = STAMINA_WINDOW.min((times[times.len() - 1] - times[0]).max(1.0));
+
+    let mut densest = 0;
+    let mut start = 0;
+    for end in 0..times.len() {
+        while times[end] - times[start] > window {
+            start += 1;
+        }
+        densest = densest.max(end - start + 1);
+    }
+
+    ((densest as f64 / window) as f32).min(METRIC_SCALE_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beatmap::{HitObject, HitObjectKind, Hitsound, TimingPoint};
+    use bevy::math::Vec2;
+
+    fn circle(id: u64, time: f64, x: f32) -> HitObject {
+        HitObject {
+            id,
+            time,
+            position: Vec2::new(x, 192.0),
+            kind: HitObjectKind::Circle,
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Normal,
+            sample_set: None,
+        }
+    }
+
+    /// Circles `gap` seconds apart, alternating `spacing` pixels left and right
+    fn pattern(count: usize, gap: f64, spacing: f32) -> Beatmap {
+        Beatmap {
+            timing_points: vec![TimingPoint::default()],
+            hit_objects: (0..count)
+                .map(|i| circle(i as u64 + 1, i as f64 * gap, (i % 2) as f32 * spacing))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn close(actual: f32, expected: f32) -> bool {
+        (actual - expected).abs() < 0.05
+    }
+
+    #[test]
+    fn aim_follows_spacing_and_speed_follows_note_rate() {
+        let stream = pattern(41, 0.25, 50.0);
+        let jumps = pattern(41, 0.25, 150.0);
+
+        assert!(close(speed_strain(&stream), 4.0));
+        assert!(close(speed_strain(&jumps), 4.0));
+        assert!(close(aim_strain(&stream), 2.0));
+        assert!(close(aim_strain(&jumps), 6.0));
+    }
+
+    #[test]
+    fn slider_density_is_the_slider_share() {
+        let mut map = pattern(10, 1.0, 100.0);
+        for object in map.hit_objects.iter_mut().step_by(2) {
+            object.kind = HitObjectKind::Slider {
+                control_points: vec![object.position, object.position + Vec2::new(50.0, 0.0)],
+                repeats: 0,
+                pixel_length: 50.0,
+                velocity: 1.0,
+            };
+        }
+        assert!(close(slider_density(&map), 5.0));
+        assert_eq!(slider_density(&pattern(10, 1.0, 100.0)), 0.0);
+    }
+
+    #[test]
+    fn stamina_measures_the_densest_window() {
+        // Thirty seconds at four notes a second, then thirty at one
+        let mut map = pattern(121, 0.25, 0.0);
+        let burst_end = map.hit_objects.len() as u64;
+        map.hit_objects
+            .extend((1..=30).map(|i| circle(burst_end + i, 30.0 + i as f64, 0.0)));
+
+        assert!(close(stamina(&map), 121.0 / 30.0));
+        assert!(stamina(&map) > speed_strain(&map));
+    }
+
+    #[test]
+    fn empty_and_single_object_maps_score_zero() {
+        for map in [pattern(0, 1.0, 0.0), pattern(1, 1.0, 0.0)] {
+            assert_eq!(aim_strain(&map), 0.0);
+            assert_eq!(speed_strain(&map), 0.0);
+        }
+        assert_eq!(stamina(&pattern(0, 1.0, 0.0)), 0.0);
+        assert_eq!(
+            DifficultyMetrics::calculate(&pattern(0, 1.0, 0.0)).star_rating(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn generated_maps_only_get_a_star_rating() {
+        let mut map = pattern(41, 0.25, 150.0);
+        let detailed = DifficultySummary::for_beatmap(&map);
+        assert!(detailed.metrics().is_some());
+
+        map.tags.push(GENERATED_TAG.to_string());
+        let summary = DifficultySummary::for_beatmap(&map);
+        assert!(summary.metrics().is_none());
+        assert_eq!(summary.star_rating(), detailed.star_rating());
+    }
+
+    #[test]
+    fn the_content_hash_changes_with_the_map() {
+        let map = pattern(8, 0.5, 100.0);
+        let mut edited = map.clone();
+        assert_eq!(content_hash(&map), content_hash(&edited));
+        edited.hit_objects[3].time += 0.001;
+        assert_ne!(content_hash(&map), content_hash(&edited));
+    }
+}
@@ -1,14 +1,17 @@
 // src/editor.rs
 
 use crate::beatmap::{
-    BeatDivisor, Beatmap, BeatmapAssets, BeatmapSettings, EditorTool, HitObject, HitObjectId,
-    HitObjectKind, Hitsound, TimingPoint,
+    sample_bank_name, BeatDivisor, Beatmap, BeatmapMetadata, BeatmapSettings, EditorTool,
+    HitObject, HitObjectId, HitObjectIndex, HitObjectKind, Hitsound, KiaiSection, PracticeHint,
+    SampleSet, TimingPoint, MAX_SLIDER_VELOCITY, MIN_SLIDER_VELOCITY,
 };
-use crate::constants::*;
-use crate::structs::GameAssets;
-use crate::ui::UiElement;
+use crate::map_comments::{MapComment, MapComments, MAX_COMMENT_LENGTH};
+use crate::text_input::TextInput;
 use bevy::prelude::*;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 /// Editor state resource
 #[derive(Debug, Clone, Resource)]
@@ -46,9 +49,9 @@ pub struct EditorState {
     /// Clipboard for copy/paste
     pub clipboard: Vec<HitObject>,
     /// Undo history
-    pub undo_stack: Vec<EditorAction>,
+    pub undo_stack: Vec<HistoryEntry>,
     /// Redo history
-    pub redo_stack: Vec<EditorAction>,
+    pub redo_stack: Vec<HistoryEntry>,
     /// Maximum undo history size
     pub max_undo_size: usize,
     /// Is in editor test mode
@@ -67,6 +70,55 @@ pub struct EditorState {
     pub show_settings: bool,
     /// Audio file duration (if known)
     pub audio_duration: Option<f64>,
+    /// Also snap slider durations to tick multiples when resnapping
+    pub resnap_slider_duration: bool,
+    /// Resnap waiting for confirmation
+    pub pending_resnap: Option<ResnapPlan>,
+    /// Slider velocity used for new inherited timing points
+    pub slider_velocity_input: f64,
+    /// Copied rhythm: beat offsets of the copied objects from the first one
+    pub rhythm_clipboard: Vec<f64>,
+    /// Mirror copies flip positions horizontally
+    pub mirror_flip_x: bool,
+    /// Mirror copies flip positions vertically
+    pub mirror_flip_y: bool,
+    /// Start of a kiai section waiting for its end to be placed
+    pub kiai_start: Option<f64>,
+    /// Start of a practice hint waiting for its end to be placed
+    pub hint_start: Option<f64>,
+    /// Practice hint shown with its fields in the Practice panel
+    pub selected_hint: Option<usize>,
+    /// Practice hint field being typed in the Practice panel; captures the keyboard
+    pub hint_edit: Option<HintEdit>,
+    /// Property field being typed in the Properties panel; captures the keyboard
+    pub property_edit: Option<PropertyEdit>,
+    /// Show and edit positions as 0-1 fractions of the playfield instead of pixels
+    pub property_normalized: bool,
+    /// Metadata field being typed in the Metadata panel; captures the keyboard
+    pub metadata_edit: Option<MetadataEdit>,
+    /// The beatmap has changes that have not been saved
+    pub dirty: bool,
+    /// Ctrl+O was pressed with unsaved changes; a second press reloads and drops them
+    pub pending_reload: bool,
+    /// ESC was pressed on a beatmap that failed validation; a second press leaves
+    /// without saving
+    pub pending_exit: bool,
+    /// Timeline object under the cursor, shown in a tooltip
+    pub timeline_hover: Option<HitObjectId>,
+    /// Object or slider end being dragged on the timeline
+    pub timeline_drag: Option<TimelineDrag>,
+    /// Metadata propagation to the rest of the set waiting for confirmation
+    pub pending_propagation: Option<PropagationPlan>,
+    /// Review comments of the beatmap, from its sidecar file
+    pub comments: MapComments,
+    /// Comment selected in the Comments panel
+    pub selected_comment: Option<usize>,
+    /// Comment being written in the Comments panel; captures the keyboard
+    pub comment_draft: Option<CommentDraft>,
+    /// Playfield object under the cursor, auditioned after a short hover
+    pub playfield_hover: Option<HitObjectId>,
+    /// Hitsound the copier tool picked up, stamped onto the objects clicked next
+    pub hitsound_pickup: Option<HitsoundPickup>,
 }
 
 impl Default for EditorState {
@@ -99,6 +151,30 @@ impl Default for EditorState {
             show_timing: false,
             show_settings: false,
             audio_duration: None,
+            resnap_slider_duration: false,
+            pending_resnap: None,
+            slider_velocity_input: 1.0,
+            rhythm_clipboard: Vec::new(),
+            mirror_flip_x: true,
+            mirror_flip_y: false,
+            kiai_start: None,
+            hint_start: None,
+            selected_hint: None,
+            hint_edit: None,
+            property_edit: None,
+            property_normalized: false,
+            metadata_edit: None,
+            dirty: false,
+            pending_reload: false,
+            pending_exit: false,
+            timeline_hover: None,
+            timeline_drag: None,
+            pending_propagation: None,
+            comments: MapComments::default(),
+            selected_comment: None,
+            comment_draft: None,
+            playfield_hover: None,
+            hitsound_pickup: None,
         }
     }
 }
@@ -191,14 +267,18 @@ impl EditorState {
     }
 
     /// Delete selected objects and return the action for undo
-    pub fn delete_selected(&mut self, beatmap: &mut Beatmap) -> Option<EditorAction> {
+    pub fn delete_selected(
+        &mut self,
+        beatmap: &mut Beatmap,
+        index: &mut HitObjectIndex,
+    ) -> Option<EditorAction> {
         if self.selected_objects.is_empty() {
             return None;
         }
 
         let mut deleted = Vec::new();
         for id in &self.selected_objects {
-            if let Some(obj) = beatmap.remove_hit_object(*id) {
+            if let Some(obj) = beatmap.remove_indexed(index, *id) {
                 deleted.push(obj);
             }
         }
@@ -211,8 +291,14 @@ impl EditorState {
         }
     }
 
-    /// Add an object and return the action for undo
-    pub fn add_object(&mut self, beatmap: &mut Beatmap, position: Vec2) -> Option<EditorAction> {
+    /// Add an object and return the action for undo. Placing objects while the song
+    /// plays goes through the index, so it never resorts the map
+    pub fn add_object(
+        &mut self,
+        beatmap: &mut Beatmap,
+        index: &mut HitObjectIndex,
+        position: Vec2,
+    ) -> Option<EditorAction> {
         let time = if self.snap_enabled {
             beatmap.snap_time(self.current_time, self.beat_divisor.value())
         } else {
@@ -245,7 +331,7 @@ impl EditorState {
             sample_set: None,
         };
 
-        beatmap.add_hit_object(object.clone());
+        beatmap.insert_indexed(index, object.clone());
         self.select_object(id, false);
 
         Some(EditorAction::AddObject { object })
@@ -253,19 +339,50 @@ impl EditorState {
 
     /// Record an action for undo
     pub fn record_action(&mut self, action: EditorAction) {
-        self.undo_stack.push(action);
+        let label = action.label();
+        self.record_labeled_action(action, label);
+    }
+
+    /// Record an action for undo under a custom history label
+    pub fn record_labeled_action(&mut self, action: EditorAction, label: String) {
+        self.undo_stack.push(HistoryEntry { label, action });
         if self.undo_stack.len() > self.max_undo_size {
             self.undo_stack.remove(0);
         }
         // Clear redo stack on new action
         self.redo_stack.clear();
+        self.dirty = true;
+        // A new edit calls off a reload or exit waiting to drop the changes
+        self.pending_reload = false;
+        self.pending_exit = false;
+        // and a resnap preview, which would put the edited objects back
+        self.pending_resnap = None;
+    }
+
+    /// Start over on a beatmap just read from disk: the history, the selection and
+    /// anything half done refer to the old copy
+    pub fn reset_for_reload(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.selected_objects.clear();
+        self.pending_resnap = None;
+        self.timeline_drag = None;
+        self.property_edit = None;
+        self.pending_reload = false;
+        self.pending_exit = false;
+        self.dirty = false;
     }
 
     /// Undo last action
     pub fn undo(&mut self, beatmap: &mut Beatmap) -> bool {
-        if let Some(action) = self.undo_stack.pop() {
-            let inverse = action.undo(beatmap);
-            self.redo_stack.push(inverse);
+        if let Some(entry) = self.undo_stack.pop() {
+            let inverse = entry.action.undo(beatmap);
+            self.redo_stack.push(HistoryEntry {
+                label: entry.label,
+                action: inverse,
+            });
+            self.dirty = true;
+            self.pending_resnap = None;
             true
         } else {
             false
@@ -274,30 +391,58 @@ impl EditorState {
 
     /// Redo last undone action
     pub fn redo(&mut self, beatmap: &mut Beatmap) -> bool {
-        if let Some(action) = self.redo_stack.pop() {
-            let inverse = action.undo(beatmap);
-            self.undo_stack.push(inverse);
+        if let Some(entry) = self.redo_stack.pop() {
+            let inverse = entry.action.undo(beatmap);
+            self.undo_stack.push(HistoryEntry {
+                label: entry.label,
+                action: inverse,
+            });
+            self.dirty = true;
+            self.pending_resnap = None;
             true
         } else {
             false
         }
     }
 
+    /// History labels oldest-first, covering both applied and undone steps
+    pub fn history_labels(&self) -> Vec<&str> {
+        self.undo_stack
+            .iter()
+            .chain(self.redo_stack.iter().rev())
+            .map(|entry| entry.label.as_str())
+            .collect()
+    }
+
+    /// Undo or redo until exactly `applied` history steps are applied
+    pub fn jump_to_history(&mut self, beatmap: &mut Beatmap, applied: usize) {
+        while self.undo_stack.len() > applied {
+            if !self.undo(beatmap) {
+                break;
+            }
+        }
+        while self.undo_stack.len() < applied {
+            if !self.redo(beatmap) {
+                break;
+            }
+        }
+    }
+
     /// Get selected objects from beatmap
-    pub fn get_selected_objects(&self, beatmap: &Beatmap) -> Vec<&HitObject> {
+    pub fn get_selected_objects<'a>(&self, beatmap: &'a Beatmap) -> Vec<&'a HitObject> {
+        let selected: HashSet<HitObjectId> = self.selected_objects.iter().copied().collect();
         beatmap
             .hit_objects
             .iter()
-            .filter(|obj| self.selected_objects.contains(&obj.id))
+            .filter(|obj| selected.contains(&obj.id))
             .collect()
     }
 
     /// Copy selected objects to clipboard
     pub fn copy_selected(&mut self, beatmap: &Beatmap) {
-        self.clipboard = beatmap
-            .hit_objects
-            .iter()
-            .filter(|obj| self.selected_objects.contains(&obj.id))
+        self.clipboard = self
+            .get_selected_objects(beatmap)
+            .into_iter()
             .cloned()
             .collect();
     }
@@ -329,6 +474,687 @@ impl EditorState {
         actions
     }
 
+    /// Snap a time to the current divisor if snapping is on
+    fn snapped(&self, beatmap: &Beatmap, time: f64) -> f64 {
+        if self.snap_enabled {
+            beatmap.snap_time(time, self.beat_divisor.value())
+        } else {
+            time
+        }
+    }
+
+    /// Selected objects sorted by time
+    fn selection_by_time(&self, beatmap: &Beatmap) -> Vec<HitObject> {
+        let mut selection: Vec<HitObject> =
+            self.get_selected_objects(beatmap).into_iter().cloned().collect();
+        selection.sort_by(|a, b| a.time.total_cmp(&b.time));
+        selection
+    }
+
+    /// Append a copy of the selection starting at the playhead, optionally mirrored.
+    /// Copies that would overlap an existing object are skipped.
+    pub fn mirror_selection(&mut self, beatmap: &mut Beatmap) -> BatchOutcome {
+        let selection = self.selection_by_time(beatmap);
+        let Some(first) = selection.first() else {
+            return BatchOutcome::default();
+        };
+
+        let target = self.snapped(beatmap, self.current_time);
+        let beat_offset = beatmap.time_to_beat(target) - beatmap.time_to_beat(first.time);
+        let flip = Vec2::new(
+            if self.mirror_flip_x { -1.0 } else { 1.0 },
+            if self.mirror_flip_y { -1.0 } else { 1.0 },
+        );
+
+        let mut created = Vec::new();
+        let mut skipped = 0;
+        for obj in &selection {
+            let time = self.snapped(
+                beatmap,
+                beatmap.beat_to_time(beatmap.time_to_beat(obj.time) + beat_offset),
+            );
+            let shift = time - obj.time;
+            let kind = match &obj.kind {
+                HitObjectKind::Circle => HitObjectKind::Circle,
+                HitObjectKind::Slider {
+                    control_points,
+                    repeats,
+                    pixel_length,
+                    velocity,
+                } => HitObjectKind::Slider {
+                    control_points: control_points.iter().map(|p| *p * flip).collect(),
+                    repeats: *repeats,
+                    pixel_length: *pixel_length,
+                    velocity: *velocity,
+                },
+                HitObjectKind::Spinner { end_time } => HitObjectKind::Spinner {
+                    end_time: end_time + shift,
+                },
+            };
+            let copy = HitObject {
+                id: beatmap.generate_hit_object_id(),
+                time,
+                position: obj.position * flip,
+                kind,
+                new_combo: obj.new_combo,
+                combo_index: obj.combo_index,
+                hitsound: obj.hitsound,
+                sample_set: obj.sample_set.clone(),
+            };
+
+            if !time_range_free(beatmap, copy.time, beatmap.object_end_time(&copy), &[]) {
+                skipped += 1;
+                continue;
+            }
+            beatmap.add_hit_object(copy.clone());
+            created.push(copy);
+        }
+
+        if created.is_empty() {
+            return BatchOutcome {
+                skipped,
+                ..Default::default()
+            };
+        }
+
+        self.selected_objects = created.iter().map(|obj| obj.id).collect();
+        BatchOutcome {
+            applied: created.len(),
+            skipped,
+            action: Some(EditorAction::AddObjects { objects: created }),
+        }
+    }
+
+    /// Copy only the timing of the selection, as beat offsets from its first object
+    pub fn copy_rhythm(&mut self, beatmap: &Beatmap) -> usize {
+        let selection = self.selection_by_time(beatmap);
+        let Some(first) = selection.first() else {
+            return 0;
+        };
+        let first_beat = beatmap.time_to_beat(first.time);
+        self.rhythm_clipboard = selection
+            .iter()
+            .map(|obj| beatmap.time_to_beat(obj.time) - first_beat)
+            .collect();
+        self.rhythm_clipboard.len()
+    }
+
+    /// Re-time the selected objects to the copied rhythm, anchored at the first selected
+    /// object. Positions are never touched; objects whose new time would overlap an
+    /// unselected object keep their old time.
+    pub fn paste_rhythm(&mut self, beatmap: &mut Beatmap) -> BatchOutcome {
+        let targets = self.selection_by_time(beatmap);
+        let Some(first) = targets.first() else {
+            return BatchOutcome::default();
+        };
+        if self.rhythm_clipboard.is_empty() {
+            return BatchOutcome::default();
+        }
+
+        let anchor_beat = beatmap.time_to_beat(first.time);
+        let target_ids: Vec<HitObjectId> = targets.iter().map(|obj| obj.id).collect();
+        let mut changes = Vec::new();
+        let mut skipped = targets.len().saturating_sub(self.rhythm_clipboard.len());
+
+        for (obj, beat) in targets.iter().zip(&self.rhythm_clipboard) {
+            let new_time = self.snapped(beatmap, beatmap.beat_to_time(anchor_beat + beat));
+            let shift = new_time - obj.time;
+            let new_kind = match &obj.kind {
+                HitObjectKind::Spinner { end_time } => HitObjectKind::Spinner {
+                    end_time: end_time + shift,
+                },
+                kind => kind.clone(),
+            };
+            let end = beatmap.object_end_time(obj) + shift;
+            if !time_range_free(beatmap, new_time, end, &target_ids) {
+                skipped += 1;
+                continue;
+            }
+            if shift.abs() > f64::EPSILON {
+                changes.push(ResnapChange {
+                    id: obj.id,
+                    old_time: obj.time,
+                    new_time,
+                    old_kind: obj.kind.clone(),
+                    new_kind,
+                });
+            }
+        }
+
+        for change in &changes {
+            if let Some(obj) = beatmap.hit_objects.iter_mut().find(|o| o.id == change.id) {
+                obj.time = change.new_time;
+                obj.kind = change.new_kind.clone();
+            }
+        }
+        beatmap.sort_hit_objects();
+
+        BatchOutcome {
+            applied: changes.len(),
+            skipped,
+            action: (!changes.is_empty()).then_some(EditorAction::ResnapObjects { changes }),
+        }
+    }
+
+    /// Toggle kiai at the playhead: inside a section removes it, otherwise the first
+    /// press marks a start and the second closes the section there
+    pub fn toggle_kiai(&mut self, beatmap: &mut Beatmap) -> KiaiToggle {
+        let time = self.snapped(beatmap, self.current_time);
+        let old_sections = beatmap.kiai_sections.clone();
+
+        let Some(start) = self.kiai_start.take() else {
+            if let Some(index) = beatmap.kiai_at(time) {
+                let removed = beatmap.kiai_sections.remove(index);
+                return KiaiToggle::Removed(
+                    removed,
+                    EditorAction::ModifyKiai {
+                        old_sections,
+                        new_sections: beatmap.kiai_sections.clone(),
+                    },
+                );
+            }
+            self.kiai_start = Some(time);
+            return KiaiToggle::Started(time);
+        };
+
+        let section = KiaiSection {
+            start: start.min(time),
+            end: start.max(time),
+        };
+        match beatmap.add_kiai_section(section) {
+            Ok(()) => KiaiToggle::Added(
+                section,
+                EditorAction::ModifyKiai {
+                    old_sections,
+                    new_sections: beatmap.kiai_sections.clone(),
+                },
+            ),
+            Err(reason) => KiaiToggle::Rejected(reason),
+        }
+    }
+
+    /// Toggle a practice hint at the playhead, like kiai: inside a hint removes it,
+    /// otherwise the first press marks a start and the second adds the hint
+    pub fn toggle_practice_hint(&mut self, beatmap: &mut Beatmap) -> HintToggle {
+        let time = self.snapped(beatmap, self.current_time);
+        let old_hints = beatmap.practice_hints.clone();
+
+        let Some(start) = self.hint_start.take() else {
+            if let Some(index) = beatmap.practice_hint_at(time) {
+                let removed = beatmap.practice_hints.remove(index);
+                self.selected_hint = None;
+                return HintToggle::Removed(
+                    removed,
+                    EditorAction::ModifyPracticeHints {
+                        old_hints,
+                        new_hints: beatmap.practice_hints.clone(),
+                    },
+                );
+            }
+            self.hint_start = Some(time);
+            return HintToggle::Started(time);
+        };
+
+        let hint = PracticeHint {
+            start: start.min(time),
+            end: start.max(time),
+            rate: DEFAULT_HINT_RATE,
+            label: format!("Section {}", beatmap.practice_hints.len() + 1),
+        };
+        match beatmap.add_practice_hint(hint.clone()) {
+            Ok(()) => {
+                self.selected_hint = beatmap.practice_hints.iter().position(|h| *h == hint);
+                HintToggle::Added(
+                    hint,
+                    EditorAction::ModifyPracticeHints {
+                        old_hints,
+                        new_hints: beatmap.practice_hints.clone(),
+                    },
+                )
+            }
+            Err(reason) => HintToggle::Rejected(reason),
+        }
+    }
+
+    /// Start typing into a field of the selected practice hint
+    pub fn begin_hint_edit(&mut self, beatmap: &Beatmap, field: HintField) {
+        let Some(hint) = self
+            .selected_hint
+            .and_then(|index| beatmap.practice_hints.get(index))
+        else {
+            return;
+        };
+        let input = TextInput::new(field.value(hint));
+        self.hint_edit = Some(HintEdit {
+            field,
+            input: if field == HintField::Label {
+                input
+            } else {
+                input.numeric()
+            },
+        });
+    }
+
+    /// Apply the typed value to the selected practice hint. Nothing changes if the
+    /// value is invalid
+    pub fn commit_hint_edit(
+        &mut self,
+        beatmap: &mut Beatmap,
+    ) -> Result<Option<EditorAction>, String> {
+        let Some(edit) = self.hint_edit.take() else {
+            return Ok(None);
+        };
+        let Some(index) = self
+            .selected_hint
+            .filter(|&index| index < beatmap.practice_hints.len())
+        else {
+            return Ok(None);
+        };
+        let text = edit.input.text().trim();
+        let mut hint = beatmap.practice_hints[index].clone();
+        match edit.field {
+            HintField::Start => hint.start = parse_time(text)?,
+            HintField::End => hint.end = parse_time(text)?,
+            HintField::Rate => {
+                let rate = edit.input.number()?;
+                if rate <= 0.0 {
+                    return Err("Rate must be above 0".to_string());
+                }
+                hint.rate = rate as f32;
+            }
+            HintField::Label => {
+                if text.is_empty() {
+                    return Err("Label can't be empty".to_string());
+                }
+                hint.label = text.to_string();
+            }
+        }
+        if hint.end <= hint.start {
+            return Err("Practice hint must end after it starts".to_string());
+        }
+        if hint == beatmap.practice_hints[index] {
+            return Ok(None);
+        }
+
+        let old_hints = beatmap.practice_hints.clone();
+        beatmap.practice_hints[index] = hint.clone();
+        beatmap.sort_practice_hints();
+        self.selected_hint = beatmap.practice_hints.iter().position(|h| *h == hint);
+        Ok(Some(EditorAction::ModifyPracticeHints {
+            old_hints,
+            new_hints: beatmap.practice_hints.clone(),
+        }))
+    }
+
+    /// Load the review comments of the current beatmap. A sidecar that can't be read
+    /// leaves no comments and is reported
+    pub fn load_comments(&mut self) -> Result<(), String> {
+        self.comments = MapComments::default();
+        self.selected_comment = None;
+        self.comment_draft = None;
+        match &self.current_beatmap_path {
+            Some(path) => {
+                self.comments = MapComments::load(path)?;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Start writing a comment at the playhead
+    pub fn begin_comment(&mut self) {
+        self.comment_draft = Some(CommentDraft {
+            time: self.current_time,
+            input: TextInput::default().with_max_len(MAX_COMMENT_LENGTH),
+        });
+    }
+
+    /// Add the comment being written and save the sidecar. Comments are review notes
+    /// rather than map data, so they bypass undo and are saved right away. An empty
+    /// comment leaves the field open
+    pub fn commit_comment(&mut self, author: String) -> Result<(), String> {
+        let Some(draft) = &self.comment_draft else {
+            return Ok(());
+        };
+        let text = draft.input.text().trim().to_string();
+        if text.is_empty() {
+            return Err("Comment can't be empty".to_string());
+        }
+        let time = draft.time;
+        self.comment_draft = None;
+        let index = self.comments.add(MapComment {
+            time,
+            author,
+            text,
+            resolved: false,
+        });
+        self.selected_comment = Some(index);
+        self.save_comments()
+    }
+
+    /// Resolve or reopen the selected comment and save; returns whether it is now
+    /// resolved
+    pub fn toggle_comment_resolved(&mut self) -> Result<Option<bool>, String> {
+        let Some(resolved) = self
+            .selected_comment
+            .and_then(|index| self.comments.toggle_resolved(index))
+        else {
+            return Ok(None);
+        };
+        self.save_comments()?;
+        Ok(Some(resolved))
+    }
+
+    /// Delete the selected comment and save
+    pub fn delete_comment(&mut self) -> Result<Option<MapComment>, String> {
+        let Some(comment) = self
+            .selected_comment
+            .take()
+            .and_then(|index| self.comments.remove(index))
+        else {
+            return Ok(None);
+        };
+        self.save_comments()?;
+        Ok(Some(comment))
+    }
+
+    /// Select a comment and move the playhead to its time
+    pub fn jump_to_comment(&mut self, index: usize) {
+        let Some(time) = self.comments.comments.get(index).map(|c| c.time) else {
+            return;
+        };
+        self.selected_comment = Some(index);
+        self.seek_to(time);
+    }
+
+    /// Comment whose timeline marker is under the cursor, at `timeline_x` and `offset_y`
+    /// above the timeline's center line
+    pub fn comment_marker_at(
+        &self,
+        timeline_x: f32,
+        offset_y: f32,
+        timeline_height: f32,
+    ) -> Option<usize> {
+        if (offset_y - comment_marker_y(timeline_height)).abs() > COMMENT_MARKER_SIZE {
+            return None;
+        }
+        let time = timeline_pos_to_time(timeline_x, self.timeline_zoom, self.timeline_scroll);
+        let tolerance = (COMMENT_MARKER_SIZE / self.timeline_zoom) as f64;
+        self.comments.marker_at(time, tolerance)
+    }
+
+    fn save_comments(&self) -> Result<(), String> {
+        match &self.current_beatmap_path {
+            Some(path) => self.comments.save(path),
+            None => Err("No beatmap file to keep comments next to".to_string()),
+        }
+    }
+
+    /// Property fields shared by every selected object
+    pub fn property_fields(&self, beatmap: &Beatmap) -> Vec<ObjectField> {
+        let selection = self.selection_by_time(beatmap);
+        if selection.is_empty() {
+            return Vec::new();
+        }
+        ObjectField::all()
+            .into_iter()
+            .filter(|field| selection.iter().all(|obj| field.applies_to(&obj.kind)))
+            .collect()
+    }
+
+    /// Value of a field across the selection, or "—" where the objects differ
+    pub fn shared_property(&self, beatmap: &Beatmap, field: ObjectField) -> String {
+        let mut values = self
+            .selection_by_time(beatmap)
+            .into_iter()
+            .filter_map(|obj| field.value(&obj, self.property_normalized));
+        match values.next() {
+            Some(first) if values.all(|value| value == first) => first,
+            Some(_) => "—".to_string(),
+            None => String::new(),
+        }
+    }
+
+    /// Start typing into a field, pre-filled with the shared value
+    pub fn begin_property_edit(&mut self, beatmap: &Beatmap, field: ObjectField) {
+        let value = self.shared_property(beatmap, field);
+        let text = if value == "—" { String::new() } else { value };
+        self.property_edit = Some(PropertyEdit {
+            field,
+            input: TextInput::new(text).numeric(),
+        });
+    }
+
+    /// Flip a toggle field on every selected object: new combo turns on unless all
+    /// have it, hitsounds cycle from the first object's
+    pub fn toggle_property(
+        &mut self,
+        beatmap: &mut Beatmap,
+        field: ObjectField,
+    ) -> Option<EditorAction> {
+        let selection = self.selection_by_time(beatmap);
+        match field {
+            ObjectField::NewCombo => {
+                let value = !selection.iter().all(|obj| obj.new_combo);
+                self.edit_selected(beatmap, |obj| {
+                    obj.new_combo = value;
+                    Ok(())
+                })
+                .ok()
+                .flatten()
+            }
+            ObjectField::Hitsound => {
+                let value = selection.first()?.hitsound.next();
+                self.edit_selected(beatmap, |obj| {
+                    obj.hitsound = value;
+                    Ok(())
+                })
+                .ok()
+                .flatten()
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse the typed value and apply it to every selected object. Nothing changes
+    /// if the value is invalid for any of them
+    pub fn commit_property_edit(
+        &mut self,
+        beatmap: &mut Beatmap,
+    ) -> Result<Option<EditorAction>, String> {
+        let Some(edit) = self.property_edit.take() else {
+            return Ok(None);
+        };
+        let text = edit.input.text().trim();
+        let number = || edit.input.number();
+        let half = Vec2::new(PLAYFIELD_WIDTH, PLAYFIELD_HEIGHT) / 2.0;
+        let normalized = self.property_normalized;
+        // Typed coordinates to playfield pixels, rejecting anything off the playfield
+        let coordinate = |value: f64, half: f32| -> Result<f32, String> {
+            let pixels = if normalized {
+                (value as f32 * 2.0 - 1.0) * half
+            } else {
+                value as f32
+            };
+            if pixels.abs() > half + f32::EPSILON {
+                Err("Position is outside the playfield".to_string())
+            } else {
+                Ok(pixels)
+            }
+        };
+
+        match edit.field {
+            ObjectField::Time => {
+                let time = parse_time(text)?;
+                let time = self.snapped(beatmap, time).max(0.0);
+                self.edit_selected(beatmap, |obj| {
+                    let shift = time - obj.time;
+                    if let HitObjectKind::Spinner { end_time } = &mut obj.kind {
+                        *end_time += shift;
+                    }
+                    obj.time = time;
+                    Ok(())
+                })
+            }
+            ObjectField::X => {
+                let x = coordinate(number()?, half.x)?;
+                self.edit_selected(beatmap, |obj| {
+                    obj.position.x = x;
+                    Ok(())
+                })
+            }
+            ObjectField::Y => {
+                let y = coordinate(number()?, half.y)?;
+                self.edit_selected(beatmap, |obj| {
+                    obj.position.y = y;
+                    Ok(())
+                })
+            }
+            ObjectField::Repeats => {
+                let value: u32 = text
+                    .parse()
+                    .map_err(|_| format!("\"{}\" is not a whole number", text))?;
+                self.edit_selected(beatmap, |obj| {
+                    if let HitObjectKind::Slider { repeats, .. } = &mut obj.kind {
+                        *repeats = value;
+                    }
+                    Ok(())
+                })
+            }
+            ObjectField::PixelLength => {
+                let value = number()?;
+                if value <= 0.0 {
+                    return Err("Length must be positive".to_string());
+                }
+                self.edit_selected(beatmap, |obj| {
+                    if let HitObjectKind::Slider { pixel_length, .. } = &mut obj.kind {
+                        *pixel_length = value;
+                    }
+                    Ok(())
+                })
+            }
+            ObjectField::EndTime => {
+                let value = self.snapped(beatmap, parse_time(text)?);
+                self.edit_selected(beatmap, |obj| {
+                    if value <= obj.time {
+                        return Err("Spinner must end after it starts".to_string());
+                    }
+                    if let HitObjectKind::Spinner { end_time } = &mut obj.kind {
+                        *end_time = value;
+                    }
+                    Ok(())
+                })
+            }
+            ObjectField::NewCombo | ObjectField::Hitsound => Ok(None),
+        }
+    }
+
+    /// Start typing into a metadata field, pre-filled with its value
+    pub fn begin_metadata_edit(&mut self, beatmap: &Beatmap, field: MetadataField) {
+        let input = TextInput::new(field.value(beatmap));
+        self.metadata_edit = Some(MetadataEdit {
+            field,
+            input: if field == MetadataField::PreviewTime {
+                input.numeric()
+            } else {
+                input
+            },
+        });
+    }
+
+    /// Validate the typed metadata value and apply it. Nothing changes if the value
+    /// is invalid; `taken_versions` are the difficulty names used elsewhere in the set
+    pub fn commit_metadata_edit(
+        &mut self,
+        beatmap: &mut Beatmap,
+        taken_versions: &[String],
+    ) -> Result<Option<EditorAction>, String> {
+        let Some(edit) = self.metadata_edit.take() else {
+            return Ok(None);
+        };
+        let text = edit.input.text().trim().to_string();
+        let mut info = MetadataSnapshot::of(beatmap);
+        match edit.field {
+            MetadataField::Title => info.metadata.title = text,
+            MetadataField::Artist => info.metadata.artist = text,
+            MetadataField::Creator => info.metadata.creator = text,
+            MetadataField::Version => info.metadata.version = text,
+            MetadataField::PreviewTime => info.preview_time = parse_time(&text)?,
+            MetadataField::AudioFile => return Ok(None),
+        }
+
+        let problem = info
+            .problems(taken_versions, self.audio_duration)
+            .into_iter()
+            .find(|(field, _)| *field == edit.field);
+        match problem {
+            Some((_, message)) => Err(message),
+            None => Ok(apply_metadata(beatmap, info)),
+        }
+    }
+
+    /// Move the preview point to the playhead
+    pub fn set_preview_from_playhead(&self, beatmap: &mut Beatmap) -> Option<EditorAction> {
+        let mut info = MetadataSnapshot::of(beatmap);
+        info.preview_time = self.current_time;
+        apply_metadata(beatmap, info)
+    }
+
+    /// Switch to the next audio file in the beatmap's folder
+    pub fn cycle_audio_file(
+        &self,
+        beatmap: &mut Beatmap,
+        beatmap_path: &str,
+    ) -> Option<EditorAction> {
+        let choices = audio_choices(&beatmap.audio_path, beatmap_path);
+        let next = choices
+            .iter()
+            .position(|choice| *choice == beatmap.audio_path)
+            .map_or(0, |i| (i + 1) % choices.len());
+        let mut info = MetadataSnapshot::of(beatmap);
+        info.audio_path = choices.get(next)?.clone();
+        apply_metadata(beatmap, info)
+    }
+
+    /// Copy another difficulty's CS/AR/OD/HP and slider settings into this one
+    pub fn copy_difficulty_settings(
+        &self,
+        beatmap: &mut Beatmap,
+        source: &BeatmapSettings,
+    ) -> Option<EditorAction> {
+        if beatmap.settings == *source {
+            return None;
+        }
+        let old_settings = std::mem::replace(&mut beatmap.settings, source.clone());
+        Some(EditorAction::ModifySettings {
+            old_settings,
+            new_settings: source.clone(),
+        })
+    }
+
+    /// Apply a change to copies of the selected objects and swap them in only if it
+    /// succeeds for all of them
+    fn edit_selected(
+        &self,
+        beatmap: &mut Beatmap,
+        mut change: impl FnMut(&mut HitObject) -> Result<(), String>,
+    ) -> Result<Option<EditorAction>, String> {
+        let old_objects = self.selection_by_time(beatmap);
+        let mut new_objects = old_objects.clone();
+        for obj in &mut new_objects {
+            change(obj)?;
+        }
+        if old_objects.is_empty() {
+            return Ok(None);
+        }
+
+        replace_objects(beatmap, &new_objects);
+        Ok(Some(EditorAction::ReplaceObjects {
+            old_objects,
+            new_objects,
+        }))
+    }
+
     /// Set tool
     pub fn set_tool(&mut self, tool: EditorTool) {
         self.current_tool = tool;
@@ -336,6 +1162,47 @@ impl EditorState {
         if tool != EditorTool::Select {
             self.selected_objects.clear();
         }
+        if tool != EditorTool::HitsoundCopier {
+            self.hitsound_pickup = None;
+        }
+    }
+
+    /// Click on an object with the hitsound copier. The first click (or a Shift+click)
+    /// picks up its hitsound and sample set, later clicks stamp them onto the object
+    pub fn copier_click(
+        &mut self,
+        beatmap: &mut Beatmap,
+        id: HitObjectId,
+        pick_up: bool,
+    ) -> Option<CopierClick> {
+        let object = beatmap.hit_objects.iter().find(|obj| obj.id == id)?;
+        let carried = match &self.hitsound_pickup {
+            Some(pickup) if !pick_up => pickup.clone(),
+            _ => {
+                let pickup = HitsoundPickup::of(object);
+                self.hitsound_pickup = Some(pickup.clone());
+                return Some(CopierClick::PickedUp(pickup));
+            }
+        };
+        if HitsoundPickup::of(object) == carried {
+            return Some(CopierClick::Unchanged);
+        }
+
+        let old_object = object.clone();
+        let mut new_object = old_object.clone();
+        new_object.hitsound = carried.hitsound;
+        new_object.sample_set = carried.sample_set;
+        replace_objects(beatmap, std::slice::from_ref(&new_object));
+        Some(CopierClick::Stamped(EditorAction::ReplaceObjects {
+            old_objects: vec![old_object],
+            new_objects: vec![new_object],
+        }))
+    }
+
+    /// Put the hitsound copier down and go back to selecting
+    pub fn drop_copier(&mut self) {
+        self.hitsound_pickup = None;
+        self.current_tool = EditorTool::Select;
     }
 
     /// Toggle grid snapping
@@ -343,6 +1210,179 @@ impl EditorState {
         self.snap_enabled = !self.snap_enabled;
     }
 
+    /// Plan a resnap of the selection (or every object if nothing is selected)
+    pub fn plan_resnap(&self, beatmap: &Beatmap, divisor: u32) -> ResnapPlan {
+        let mut plan = ResnapPlan {
+            divisor,
+            changes: Vec::new(),
+            ambiguous: Vec::new(),
+            max_shift: 0.0,
+        };
+
+        for obj in &beatmap.hit_objects {
+            if !self.selected_objects.is_empty() && !self.selected_objects.contains(&obj.id) {
+                continue;
+            }
+
+            let (new_time, ambiguous) = beatmap.nearest_tick(obj.time, divisor);
+            if ambiguous {
+                plan.ambiguous.push(obj.id);
+                continue;
+            }
+
+            let new_kind = match &obj.kind {
+                HitObjectKind::Circle => HitObjectKind::Circle,
+                HitObjectKind::Slider {
+                    control_points,
+                    repeats,
+                    pixel_length,
+                    velocity,
+                } => {
+                    let mut pixel_length = *pixel_length;
+                    if self.resnap_slider_duration {
+                        let span = beatmap.slider_span_duration(obj.time, pixel_length, *velocity);
+                        let tick = beatmap.get_beat_length_at(new_time) / divisor.max(1) as f64;
+                        let snapped = ((span / tick).round() * tick).max(tick);
+                        if span > 0.0 {
+                            pixel_length *= snapped / span;
+                        }
+                    }
+                    HitObjectKind::Slider {
+                        control_points: control_points.clone(),
+                        repeats: *repeats,
+                        pixel_length,
+                        velocity: *velocity,
+                    }
+                }
+                HitObjectKind::Spinner { end_time } => {
+                    // Keep the spinner at least one tick long after snapping both ends
+                    let tick = beatmap.get_beat_length_at(new_time) / divisor.max(1) as f64;
+                    let (snapped_end, _) = beatmap.nearest_tick(*end_time, divisor);
+                    HitObjectKind::Spinner {
+                        end_time: snapped_end.max(new_time + tick),
+                    }
+                }
+            };
+
+            let shift = (new_time - obj.time).abs();
+            let kind_changed = match (&obj.kind, &new_kind) {
+                (
+                    HitObjectKind::Slider { pixel_length: a, .. },
+                    HitObjectKind::Slider { pixel_length: b, .. },
+                ) => (a - b).abs() > f64::EPSILON,
+                (HitObjectKind::Spinner { end_time: a }, HitObjectKind::Spinner { end_time: b }) => {
+                    (a - b).abs() > f64::EPSILON
+                }
+                _ => false,
+            };
+
+            if shift > f64::EPSILON || kind_changed {
+                plan.max_shift = plan.max_shift.max(shift);
+                plan.changes.push(ResnapChange {
+                    id: obj.id,
+                    old_time: obj.time,
+                    new_time,
+                    old_kind: obj.kind.clone(),
+                    new_kind,
+                });
+            }
+        }
+
+        plan
+    }
+
+    /// Apply a resnap plan as a single undoable action and select ambiguous objects
+    pub fn apply_resnap(&mut self, beatmap: &mut Beatmap, plan: ResnapPlan) -> Option<EditorAction> {
+        for change in &plan.changes {
+            if let Some(obj) = beatmap.hit_objects.iter_mut().find(|o| o.id == change.id) {
+                obj.time = change.new_time;
+                obj.kind = change.new_kind.clone();
+            }
+        }
+        beatmap.sort_hit_objects();
+
+        // Leave ambiguous objects selected for manual review
+        self.selected_objects = plan.ambiguous;
+
+        if plan.changes.is_empty() {
+            None
+        } else {
+            Some(EditorAction::ResnapObjects {
+                changes: plan.changes,
+            })
+        }
+    }
+
+    /// Step the SV used for new inherited points by `delta`
+    pub fn adjust_slider_velocity_input(&mut self, delta: f64) {
+        let value = ((self.slider_velocity_input + delta) * 100.0).round() / 100.0;
+        self.slider_velocity_input = value.clamp(MIN_SLIDER_VELOCITY, MAX_SLIDER_VELOCITY);
+    }
+
+    /// Add an inherited point at the playhead with the current SV input. Sliders it
+    /// affects keep their duration, so their bodies grow or shrink with the new SV.
+    pub fn add_slider_velocity_point(&mut self, beatmap: &mut Beatmap) -> Option<EditorAction> {
+        let old_points = beatmap.timing_points.clone();
+        let durations: Vec<(HitObjectId, f64)> = beatmap
+            .hit_objects
+            .iter()
+            .filter_map(|obj| match &obj.kind {
+                HitObjectKind::Slider {
+                    pixel_length,
+                    velocity,
+                    ..
+                } => Some((obj.id, beatmap.slider_span_duration(obj.time, *pixel_length, *velocity))),
+                _ => None,
+            })
+            .collect();
+
+        beatmap.set_slider_velocity_point(self.current_time, self.slider_velocity_input);
+
+        let mut sliders = Vec::new();
+        for (id, duration) in durations {
+            let Some(obj) = beatmap.hit_objects.iter().find(|o| o.id == id) else {
+                continue;
+            };
+            let HitObjectKind::Slider {
+                control_points,
+                repeats,
+                pixel_length,
+                velocity,
+            } = &obj.kind
+            else {
+                continue;
+            };
+
+            let new_length = beatmap.slider_length_for_duration(obj.time, duration, *velocity);
+            if (new_length - pixel_length).abs() > 1e-6 {
+                sliders.push(ResnapChange {
+                    id,
+                    old_time: obj.time,
+                    new_time: obj.time,
+                    old_kind: obj.kind.clone(),
+                    new_kind: HitObjectKind::Slider {
+                        control_points: control_points.clone(),
+                        repeats: *repeats,
+                        pixel_length: new_length,
+                        velocity: *velocity,
+                    },
+                });
+            }
+        }
+
+        for change in &sliders {
+            if let Some(obj) = beatmap.hit_objects.iter_mut().find(|o| o.id == change.id) {
+                obj.kind = change.new_kind.clone();
+            }
+        }
+
+        Some(EditorAction::ChangeSliderVelocity {
+            old_points,
+            new_points: beatmap.timing_points.clone(),
+            sliders,
+        })
+    }
+
     /// Get the object under a position at the current time
     pub fn get_object_at_position(
         &self,
@@ -362,28 +1402,409 @@ impl EditorState {
             })
             .map(|obj| obj.id)
     }
-}
 
-/// Editor actions for undo/redo
-#[derive(Debug, Clone)]
-pub enum EditorAction {
-    AddObject {
-        object: HitObject,
-    },
-    DeleteObjects {
-        objects: Vec<HitObject>,
-    },
-    MoveObjects {
-        moves: Vec<ObjectMove>,
-    },
-    ModifyTiming {
-        old_points: Vec<TimingPoint>,
-        new_points: Vec<TimingPoint>,
-    },
-    ModifySettings {
-        old_settings: BeatmapSettings,
+    /// Object under a playfield position at the current time, looked up through the
+    /// object index so hovering stays cheap on long maps
+    pub fn object_under_cursor(
+        &self,
+        beatmap: &Beatmap,
+        index: &HitObjectIndex,
+        position: Vec2,
+        tolerance: f32,
+    ) -> Option<HitObjectId> {
+        let range = index.starting_between(self.current_time - 0.1, self.current_time + 0.1);
+        beatmap.hit_objects[range]
+            .iter()
+            .find(|obj| obj.position.distance(position) < tolerance)
+            .map(|obj| obj.id)
+    }
+
+    /// Timeline object under a point, with `timeline_x` measured from the timeline's left
+    /// edge and `offset_y` from its center line. Ticks stay at least
+    /// `TIMELINE_MIN_HIT_WIDTH` wide at any zoom, and the right end of a slider bar grabs
+    /// its duration
+    pub fn timeline_object_at(
+        &self,
+        beatmap: &Beatmap,
+        timeline_x: f32,
+        offset_y: f32,
+    ) -> Option<TimelineGrab> {
+        if offset_y.abs() > TIMELINE_HIT_HEIGHT / 2.0 {
+            return None;
+        }
+        let zoom = self.timeline_zoom;
+        let scroll = self.timeline_scroll;
+        let half = TIMELINE_MIN_HIT_WIDTH / 2.0;
+        // Later objects are drawn on top, so they win ties
+        beatmap
+            .hit_objects
+            .iter()
+            .rev()
+            .filter_map(|obj| {
+                let start = time_to_timeline_pos(obj.time, zoom, scroll);
+                let end = match obj.kind {
+                    HitObjectKind::Slider { .. } => {
+                        time_to_timeline_pos(beatmap.object_end_time(obj), zoom, scroll)
+                    }
+                    _ => start,
+                };
+                if timeline_x < start - half || timeline_x > end.max(start) + half {
+                    return None;
+                }
+                if end - start > TIMELINE_MIN_HIT_WIDTH && (timeline_x - end).abs() <= half {
+                    return Some((TimelineGrab::SliderEnd(obj.id), 0.0));
+                }
+                Some((TimelineGrab::Object(obj.id), (timeline_x - start).abs()))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(grab, _)| grab)
+    }
+
+    /// Press on a timeline object: select it (Shift adds to the selection) and start a drag.
+    /// Pressing a member of the selection keeps the selection so it drags as a group
+    pub fn begin_timeline_drag(
+        &mut self,
+        beatmap: &Beatmap,
+        grab: TimelineGrab,
+        cursor_time: f64,
+        add_to_selection: bool,
+    ) {
+        let Some(obj) = beatmap.hit_objects.iter().find(|o| o.id == grab.id()) else {
+            return;
+        };
+        if add_to_selection || !self.selected_objects.contains(&obj.id) {
+            self.select_object(obj.id, add_to_selection);
+        }
+        let original_time = match grab {
+            TimelineGrab::Object(_) => obj.time,
+            TimelineGrab::SliderEnd(_) => beatmap.object_end_time(obj),
+        };
+        self.timeline_drag = Some(TimelineDrag {
+            grab,
+            anchor_time: cursor_time,
+            original_time,
+            candidate_time: original_time,
+        });
+    }
+
+    /// Follow the cursor with the drag, snapping the candidate time to the beat divisor
+    pub fn update_timeline_drag(&mut self, beatmap: &Beatmap, cursor_time: f64) {
+        let Some(drag) = self.timeline_drag else {
+            return;
+        };
+        let candidate = self.snapped(beatmap, drag.original_time + cursor_time - drag.anchor_time);
+        let candidate = match drag.grab {
+            TimelineGrab::Object(_) => {
+                // Keep the earliest selected object from moving before the song starts
+                let earliest = self
+                    .get_selected_objects(beatmap)
+                    .iter()
+                    .map(|obj| obj.time)
+                    .fold(drag.original_time, f64::min);
+                candidate.max(drag.original_time - earliest)
+            }
+            TimelineGrab::SliderEnd(id) => {
+                let start = beatmap
+                    .hit_objects
+                    .iter()
+                    .find(|obj| obj.id == id)
+                    .map_or(0.0, |obj| obj.time);
+                if candidate > start + OVERLAP_EPSILON {
+                    candidate
+                } else {
+                    drag.candidate_time
+                }
+            }
+        };
+        if candidate != drag.candidate_time {
+            self.timeline_drag = Some(TimelineDrag {
+                candidate_time: candidate,
+                ..drag
+            });
+        }
+    }
+
+    /// Release the drag, moving the selection in time or changing the grabbed slider's
+    /// duration. Nothing is recorded if the drag ends where it started
+    pub fn finish_timeline_drag(&mut self, beatmap: &mut Beatmap) -> Option<EditorAction> {
+        let drag = self.timeline_drag.take()?;
+        let shift = drag.shift();
+        if shift.abs() < OVERLAP_EPSILON {
+            return None;
+        }
+        match drag.grab {
+            TimelineGrab::Object(_) => {
+                let moves: Vec<ObjectMove> = self
+                    .selection_by_time(beatmap)
+                    .iter()
+                    .map(|obj| ObjectMove {
+                        id: obj.id,
+                        old_position: obj.position,
+                        new_position: obj.position,
+                        old_time: obj.time,
+                        new_time: obj.time + shift,
+                    })
+                    .collect();
+                apply_moves(beatmap, &moves);
+                Some(EditorAction::MoveObjects { moves })
+            }
+            TimelineGrab::SliderEnd(id) => {
+                let old_object = beatmap.hit_objects.iter().find(|o| o.id == id)?.clone();
+                let mut new_object = old_object.clone();
+                if let HitObjectKind::Slider {
+                    repeats,
+                    pixel_length,
+                    velocity,
+                    ..
+                } = &mut new_object.kind
+                {
+                    let span = (drag.candidate_time - new_object.time) / (*repeats as f64 + 1.0);
+                    *pixel_length =
+                        beatmap.slider_length_for_duration(new_object.time, span, *velocity);
+                }
+                replace_objects(beatmap, std::slice::from_ref(&new_object));
+                Some(EditorAction::ReplaceObjects {
+                    old_objects: vec![old_object],
+                    new_objects: vec![new_object],
+                })
+            }
+        }
+    }
+
+    /// Tooltip for the hovered timeline object, or the candidate time of the one being
+    /// dragged
+    pub fn timeline_tooltip(&self, beatmap: &Beatmap) -> Option<(HitObjectId, String)> {
+        if let Some(drag) = self.timeline_drag {
+            let text = match drag.grab {
+                TimelineGrab::Object(_) => format!(
+                    "{} → {}",
+                    format_time(drag.original_time),
+                    format_time(drag.candidate_time)
+                ),
+                TimelineGrab::SliderEnd(_) => format!(
+                    "slider end {} → {}",
+                    format_time(drag.original_time),
+                    format_time(drag.candidate_time)
+                ),
+            };
+            return Some((drag.grab.id(), text));
+        }
+        let id = self.timeline_hover?;
+        let obj = beatmap.hit_objects.iter().find(|o| o.id == id)?;
+        let combo = beatmap.combo_number(id).unwrap_or(0);
+        Some((
+            id,
+            format!(
+                "{} @ {}, combo {}",
+                kind_name(&obj.kind),
+                format_time(obj.time),
+                combo
+            ),
+        ))
+    }
+}
+
+/// Hitsound flags and sample set carried by the hitsound copier
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitsoundPickup {
+    pub hitsound: Hitsound,
+    pub sample_set: Option<SampleSet>,
+}
+
+impl HitsoundPickup {
+    /// The hitsound an object has
+    pub fn of(object: &HitObject) -> Self {
+        Self {
+            hitsound: object.hitsound,
+            sample_set: object.sample_set.clone(),
+        }
+    }
+
+    /// Flags and sample set for the status bar, e.g. "Whistle, soft/drum #2"
+    pub fn summary(&self) -> String {
+        match &self.sample_set {
+            Some(set) => {
+                let mut text = format!(
+                    "{:?}, {}/{}",
+                    self.hitsound,
+                    sample_bank_name(set.normal_set),
+                    sample_bank_name(set.addition_set)
+                );
+                if set.index > 0 {
+                    text.push_str(&format!(" #{}", set.index));
+                }
+                if let Some(file) = &set.filename {
+                    text.push_str(&format!(" ({})", file));
+                }
+                text
+            }
+            None => format!("{:?}, default sample set", self.hitsound),
+        }
+    }
+}
+
+/// What a click with the hitsound copier did
+#[derive(Debug, Clone)]
+pub enum CopierClick {
+    /// Picked up the clicked object's hitsound
+    PickedUp(HitsoundPickup),
+    /// Stamped the carried hitsound onto the clicked object
+    Stamped(EditorAction),
+    /// The object already had the carried hitsound
+    Unchanged,
+}
+
+/// Volume an object's hitsound plays at (percent): its own sample volume, or the
+/// timing point's in effect when that is 0
+pub fn hitsound_volume(beatmap: &Beatmap, object: &HitObject) -> u32 {
+    match &object.sample_set {
+        Some(set) if set.volume > 0 => set.volume.min(100),
+        _ => beatmap
+            .timing_points
+            .iter()
+            .filter(|point| point.time <= object.time)
+            .max_by(|a, b| a.time.total_cmp(&b.time))
+            .map_or(100, |point| point.volume.min(100)),
+    }
+}
+
+/// Status bar description of an object's effective hitsound
+pub fn hitsound_description(beatmap: &Beatmap, object: &HitObject) -> String {
+    format!(
+        "{} @ {}: {}, {}% volume",
+        kind_name(&object.kind),
+        format_time(object.time),
+        HitsoundPickup::of(object).summary(),
+        hitsound_volume(beatmap, object)
+    )
+}
+
+/// Smallest clickable width of a timeline object, so ticks stay reachable when zoomed out
+pub const TIMELINE_MIN_HIT_WIDTH: f32 = 8.0;
+
+/// Height around the timeline's center line that picks objects; above and below it scrubs
+pub const TIMELINE_HIT_HEIGHT: f32 = 24.0;
+
+/// What a press on the timeline grabbed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineGrab {
+    /// An object's tick or slider bar, dragged to retime it
+    Object(HitObjectId),
+    /// A slider bar's right end, dragged to change its duration
+    SliderEnd(HitObjectId),
+}
+
+impl TimelineGrab {
+    /// The grabbed object
+    pub fn id(&self) -> HitObjectId {
+        match self {
+            TimelineGrab::Object(id) | TimelineGrab::SliderEnd(id) => *id,
+        }
+    }
+}
+
+/// Drag on the timeline in progress; the beatmap is only changed on release
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelineDrag {
+    pub grab: TimelineGrab,
+    /// Cursor time when the drag started
+    pub anchor_time: f64,
+    /// Start time of the grabbed object, or end time of the grabbed slider
+    pub original_time: f64,
+    /// Snapped time the grab lands on if released now
+    pub candidate_time: f64,
+}
+
+impl TimelineDrag {
+    /// How far the grab has moved (seconds)
+    pub fn shift(&self) -> f64 {
+        self.candidate_time - self.original_time
+    }
+}
+
+/// Gap below which two objects count as overlapping (seconds)
+pub const OVERLAP_EPSILON: f64 = 0.001;
+
+/// Whether `[start, end]` is clear of every object not listed in `ignore`
+pub fn time_range_free(beatmap: &Beatmap, start: f64, end: f64, ignore: &[HitObjectId]) -> bool {
+    !beatmap.hit_objects.iter().any(|obj| {
+        !ignore.contains(&obj.id)
+            && start <= beatmap.object_end_time(obj) + OVERLAP_EPSILON
+            && end + OVERLAP_EPSILON >= obj.time
+    })
+}
+
+/// Result of a batched edit such as mirroring or pasting a rhythm
+#[derive(Debug, Clone, Default)]
+pub struct BatchOutcome {
+    /// Single undoable action covering the whole batch
+    pub action: Option<EditorAction>,
+    /// Objects created or re-timed
+    pub applied: usize,
+    /// Objects skipped because they would overlap others
+    pub skipped: usize,
+}
+
+/// Editor actions for undo/redo
+#[derive(Debug, Clone)]
+pub enum EditorAction {
+    AddObject {
+        object: HitObject,
+    },
+    /// Several objects added in one step
+    AddObjects {
+        objects: Vec<HitObject>,
+    },
+    DeleteObjects {
+        objects: Vec<HitObject>,
+    },
+    MoveObjects {
+        moves: Vec<ObjectMove>,
+    },
+    ModifyTiming {
+        old_points: Vec<TimingPoint>,
+        new_points: Vec<TimingPoint>,
+    },
+    ModifySettings {
+        old_settings: BeatmapSettings,
         new_settings: BeatmapSettings,
     },
+    ResnapObjects {
+        changes: Vec<ResnapChange>,
+    },
+    /// Timing change from an SV edit, with the slider lengths it rescaled
+    ChangeSliderVelocity {
+        old_points: Vec<TimingPoint>,
+        new_points: Vec<TimingPoint>,
+        sliders: Vec<ResnapChange>,
+    },
+    ModifyKiai {
+        old_sections: Vec<KiaiSection>,
+        new_sections: Vec<KiaiSection>,
+    },
+    ModifyPracticeHints {
+        old_hints: Vec<PracticeHint>,
+        new_hints: Vec<PracticeHint>,
+    },
+    /// Objects edited in place from the Properties panel
+    ReplaceObjects {
+        old_objects: Vec<HitObject>,
+        new_objects: Vec<HitObject>,
+    },
+    /// Metadata, audio file or preview point edited from the Metadata panel
+    ModifyMetadata {
+        old_info: Box<MetadataSnapshot>,
+        new_info: Box<MetadataSnapshot>,
+    },
+}
+
+/// Recorded action with a label for the history panel
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    /// Human-readable description, e.g. "Move 3 objects"
+    pub label: String,
+    /// Action that reverts this step (or re-applies it on the redo stack)
+    pub action: EditorAction,
 }
 
 /// Object move data for undo
@@ -396,7 +1817,132 @@ pub struct ObjectMove {
     pub new_time: f64,
 }
 
+/// Time and shape change of a single object during a resnap
+#[derive(Debug, Clone)]
+pub struct ResnapChange {
+    pub id: HitObjectId,
+    pub old_time: f64,
+    pub new_time: f64,
+    pub old_kind: HitObjectKind,
+    pub new_kind: HitObjectKind,
+}
+
+/// Preview of a resnap before it is applied
+#[derive(Debug, Clone)]
+pub struct ResnapPlan {
+    /// Beat divisor being snapped to
+    pub divisor: u32,
+    /// Objects that will move
+    pub changes: Vec<ResnapChange>,
+    /// Objects equidistant between two ticks, left untouched
+    pub ambiguous: Vec<HitObjectId>,
+    /// Largest time shift in seconds
+    pub max_shift: f64,
+}
+
+impl ResnapPlan {
+    /// Human-readable summary for the status bar
+    pub fn summary(&self) -> String {
+        format!(
+            "Resnap to 1/{}: {} objects will move, max shift {:.0} ms, {} objects ambiguous",
+            self.divisor,
+            self.changes.len(),
+            self.max_shift * 1000.0,
+            self.ambiguous.len()
+        )
+    }
+}
+
+/// Name of a hit object kind for history labels
+fn kind_name(kind: &HitObjectKind) -> &'static str {
+    match kind {
+        HitObjectKind::Circle => "circle",
+        HitObjectKind::Slider { .. } => "slider",
+        HitObjectKind::Spinner { .. } => "spinner",
+    }
+}
+
+/// "1 object" / "3 objects"
+fn count_objects(count: usize) -> String {
+    if count == 1 {
+        "1 object".to_string()
+    } else {
+        format!("{} objects", count)
+    }
+}
+
 impl EditorAction {
+    /// Short description for the history panel
+    pub fn label(&self) -> String {
+        match self {
+            EditorAction::AddObject { object } => format!(
+                "Add {} @ {}",
+                kind_name(&object.kind),
+                format_time(object.time)
+            ),
+            EditorAction::AddObjects { objects } => format!("Add {}", count_objects(objects.len())),
+            EditorAction::DeleteObjects { objects } => match objects.as_slice() {
+                [object] => format!(
+                    "Delete {} @ {}",
+                    kind_name(&object.kind),
+                    format_time(object.time)
+                ),
+                _ => format!("Delete {}", count_objects(objects.len())),
+            },
+            EditorAction::MoveObjects { moves } => format!("Move {}", count_objects(moves.len())),
+            EditorAction::ModifyTiming { .. } => "Edit timing points".to_string(),
+            EditorAction::ModifySettings {
+                old_settings,
+                new_settings,
+            } => {
+                let fields = [
+                    ("CS", old_settings.circle_size, new_settings.circle_size),
+                    ("AR", old_settings.approach_rate, new_settings.approach_rate),
+                    ("OD", old_settings.overall_difficulty, new_settings.overall_difficulty),
+                    ("HP", old_settings.hp_drain, new_settings.hp_drain),
+                ];
+                let changes: Vec<String> = fields
+                    .iter()
+                    .filter(|(_, old, new)| (old - new).abs() > f32::EPSILON)
+                    .map(|(name, old, new)| format!("{} {}→{}", name, old, new))
+                    .collect();
+                if changes.is_empty() {
+                    "Change settings".to_string()
+                } else {
+                    format!("Change {}", changes.join(", "))
+                }
+            }
+            EditorAction::ResnapObjects { changes } => {
+                format!("Resnap {}", count_objects(changes.len()))
+            }
+            EditorAction::ChangeSliderVelocity { sliders, .. } => {
+                format!("Change SV ({} rescaled)", count_objects(sliders.len()))
+            }
+            EditorAction::ModifyKiai {
+                old_sections,
+                new_sections,
+            } => {
+                if new_sections.len() >= old_sections.len() {
+                    "Add kiai section".to_string()
+                } else {
+                    "Remove kiai section".to_string()
+                }
+            }
+            EditorAction::ModifyPracticeHints {
+                old_hints,
+                new_hints,
+            } => match new_hints.len().cmp(&old_hints.len()) {
+                std::cmp::Ordering::Greater => "Add practice hint".to_string(),
+                std::cmp::Ordering::Less => "Remove practice hint".to_string(),
+                std::cmp::Ordering::Equal => "Edit practice hint".to_string(),
+            },
+            EditorAction::ReplaceObjects { new_objects, .. } => {
+                format!("Edit {}", count_objects(new_objects.len()))
+            }
+            EditorAction::ModifyMetadata { .. } => "Edit metadata".to_string(),
+        }
+    }
+
     /// Undo the action and return the inverse action
     pub fn undo(self, beatmap: &mut Beatmap) -> EditorAction {
         match self {
@@ -406,31 +1952,30 @@ impl EditorAction {
                     objects: vec![object],
                 }
             }
+            EditorAction::AddObjects { objects } => {
+                for obj in &objects {
+                    beatmap.remove_hit_object(obj.id);
+                }
+                EditorAction::DeleteObjects { objects }
+            }
             EditorAction::DeleteObjects { objects } => {
                 for obj in &objects {
                     beatmap.add_hit_object(obj.clone());
                 }
-                EditorAction::AddObject {
-                    object: objects.into_iter().next().unwrap(),
-                }
+                EditorAction::AddObjects { objects }
             }
             EditorAction::MoveObjects { moves } => {
                 let inverse_moves: Vec<_> = moves
                     .iter()
-                    .map(|m| {
-                        if let Some(obj) = beatmap.hit_objects.iter_mut().find(|o| o.id == m.id) {
-                            obj.position = m.old_position;
-                            obj.time = m.old_time;
-                        }
-                        ObjectMove {
-                            id: m.id,
-                            old_position: m.new_position,
-                            new_position: m.old_position,
-                            old_time: m.new_time,
-                            new_time: m.old_time,
-                        }
+                    .map(|m| ObjectMove {
+                        id: m.id,
+                        old_position: m.new_position,
+                        new_position: m.old_position,
+                        old_time: m.new_time,
+                        new_time: m.old_time,
                     })
                     .collect();
+                apply_moves(beatmap, &inverse_moves);
                 EditorAction::MoveObjects {
                     moves: inverse_moves,
                 }
@@ -451,8 +1996,679 @@ impl EditorAction {
                     new_settings: old_settings,
                 }
             }
+            EditorAction::ResnapObjects { changes } => {
+                let inverse_changes: Vec<_> = changes
+                    .into_iter()
+                    .map(|c| {
+                        if let Some(obj) = beatmap.hit_objects.iter_mut().find(|o| o.id == c.id) {
+                            obj.time = c.old_time;
+                            obj.kind = c.old_kind.clone();
+                        }
+                        ResnapChange {
+                            id: c.id,
+                            old_time: c.new_time,
+                            new_time: c.old_time,
+                            old_kind: c.new_kind,
+                            new_kind: c.old_kind,
+                        }
+                    })
+                    .collect();
+                beatmap.sort_hit_objects();
+                EditorAction::ResnapObjects {
+                    changes: inverse_changes,
+                }
+            }
+            EditorAction::ChangeSliderVelocity {
+                old_points,
+                new_points,
+                sliders,
+            } => {
+                beatmap.timing_points = old_points.clone();
+                let inverse_sliders = sliders
+                    .into_iter()
+                    .map(|c| {
+                        if let Some(obj) = beatmap.hit_objects.iter_mut().find(|o| o.id == c.id) {
+                            obj.kind = c.old_kind.clone();
+                        }
+                        ResnapChange {
+                            id: c.id,
+                            old_time: c.new_time,
+                            new_time: c.old_time,
+                            old_kind: c.new_kind,
+                            new_kind: c.old_kind,
+                        }
+                    })
+                    .collect();
+                EditorAction::ChangeSliderVelocity {
+                    old_points: new_points,
+                    new_points: old_points,
+                    sliders: inverse_sliders,
+                }
+            }
+            EditorAction::ModifyKiai { old_sections, .. } => {
+                let current = std::mem::replace(&mut beatmap.kiai_sections, old_sections.clone());
+                EditorAction::ModifyKiai {
+                    old_sections: current,
+                    new_sections: old_sections,
+                }
+            }
+            EditorAction::ModifyPracticeHints { old_hints, .. } => {
+                let current = std::mem::replace(&mut beatmap.practice_hints, old_hints.clone());
+                EditorAction::ModifyPracticeHints {
+                    old_hints: current,
+                    new_hints: old_hints,
+                }
+            }
+            EditorAction::ReplaceObjects {
+                old_objects,
+                new_objects,
+            } => {
+                replace_objects(beatmap, &old_objects);
+                EditorAction::ReplaceObjects {
+                    old_objects: new_objects,
+                    new_objects: old_objects,
+                }
+            }
+            EditorAction::ModifyMetadata { old_info, new_info } => {
+                old_info.clone().apply_to(beatmap);
+                EditorAction::ModifyMetadata {
+                    old_info: new_info,
+                    new_info: old_info,
+                }
+            }
+        }
+    }
+}
+
+/// Result of toggling kiai at the playhead
+#[derive(Debug, Clone)]
+pub enum KiaiToggle {
+    /// Start marked; the next toggle closes the section
+    Started(f64),
+    Added(KiaiSection, EditorAction),
+    Removed(KiaiSection, EditorAction),
+    /// The section was not added
+    Rejected(String),
+}
+
+/// Rate new practice hints suggest until edited
+pub const DEFAULT_HINT_RATE: f32 = 0.75;
+
+/// Result of toggling a practice hint at the playhead
+#[derive(Debug, Clone)]
+pub enum HintToggle {
+    /// Start marked; the next toggle closes the hint
+    Started(f64),
+    Added(PracticeHint, EditorAction),
+    Removed(PracticeHint, EditorAction),
+    /// The hint was not added
+    Rejected(String),
+}
+
+/// Editable field of a practice hint in the Practice panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintField {
+    Label,
+    Start,
+    End,
+    Rate,
+}
+
+impl HintField {
+    /// All fields in panel order
+    pub fn all() -> [HintField; 4] {
+        [
+            HintField::Label,
+            HintField::Start,
+            HintField::End,
+            HintField::Rate,
+        ]
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            HintField::Label => "Label",
+            HintField::Start => "Start",
+            HintField::End => "End",
+            HintField::Rate => "Rate",
+        }
+    }
+
+    /// The field's value as shown and edited in the panel
+    pub fn value(&self, hint: &PracticeHint) -> String {
+        match self {
+            HintField::Label => hint.label.clone(),
+            HintField::Start => format_time(hint.start),
+            HintField::End => format_time(hint.end),
+            HintField::Rate => format!("{:.2}", hint.rate),
+        }
+    }
+}
+
+/// Text typed into a practice hint field, applied on Enter
+#[derive(Debug, Clone)]
+pub struct HintEdit {
+    pub field: HintField,
+    pub input: TextInput,
+}
+
+/// Comment being written at a song time, added on Enter
+#[derive(Debug, Clone)]
+pub struct CommentDraft {
+    pub time: f64,
+    pub input: TextInput,
+}
+
+/// Size of a comment marker on the timeline
+pub const COMMENT_MARKER_SIZE: f32 = 8.0;
+
+/// Height of comment markers above the timeline's center line, just under the practice
+/// hint bands
+pub fn comment_marker_y(timeline_height: f32) -> f32 {
+    timeline_height / 2.0 - 18.0
+}
+
+/// Editable property of the selected objects in the Properties panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectField {
+    Time,
+    X,
+    Y,
+    NewCombo,
+    Hitsound,
+    Repeats,
+    PixelLength,
+    EndTime,
+}
+
+impl ObjectField {
+    /// All fields in panel order
+    pub fn all() -> Vec<ObjectField> {
+        vec![
+            ObjectField::Time,
+            ObjectField::X,
+            ObjectField::Y,
+            ObjectField::NewCombo,
+            ObjectField::Hitsound,
+            ObjectField::Repeats,
+            ObjectField::PixelLength,
+            ObjectField::EndTime,
+        ]
+    }
+
+    /// Get display name for the field
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ObjectField::Time => "Time",
+            ObjectField::X => "X",
+            ObjectField::Y => "Y",
+            ObjectField::NewCombo => "New combo",
+            ObjectField::Hitsound => "Hitsound",
+            ObjectField::Repeats => "Repeats",
+            ObjectField::PixelLength => "Length",
+            ObjectField::EndTime => "End time",
+        }
+    }
+
+    /// Whether clicking the field flips it instead of opening a text entry
+    pub fn is_toggle(&self) -> bool {
+        matches!(self, ObjectField::NewCombo | ObjectField::Hitsound)
+    }
+
+    /// Whether objects of this kind have the field
+    pub fn applies_to(&self, kind: &HitObjectKind) -> bool {
+        match self {
+            ObjectField::Repeats | ObjectField::PixelLength => {
+                matches!(kind, HitObjectKind::Slider { .. })
+            }
+            ObjectField::EndTime => matches!(kind, HitObjectKind::Spinner { .. }),
+            _ => true,
+        }
+    }
+
+    /// The field's value on one object, as shown and edited in the panel
+    pub fn value(&self, obj: &HitObject, normalized: bool) -> Option<String> {
+        let coordinate = |value: f32, size: f32| {
+            if normalized {
+                format!("{:.3}", value / size + 0.5)
+            } else {
+                format!("{:.1}", value)
+            }
+        };
+        match (self, &obj.kind) {
+            (ObjectField::Time, _) => Some(format_time(obj.time)),
+            (ObjectField::X, _) => Some(coordinate(obj.position.x, PLAYFIELD_WIDTH)),
+            (ObjectField::Y, _) => Some(coordinate(obj.position.y, PLAYFIELD_HEIGHT)),
+            (ObjectField::NewCombo, _) => {
+                Some(if obj.new_combo { "on" } else { "off" }.to_string())
+            }
+            (ObjectField::Hitsound, _) => Some(format!("{:?}", obj.hitsound)),
+            (ObjectField::Repeats, HitObjectKind::Slider { repeats, .. }) => {
+                Some(repeats.to_string())
+            }
+            (ObjectField::PixelLength, HitObjectKind::Slider { pixel_length, .. }) => {
+                Some(format!("{:.1}", pixel_length))
+            }
+            (ObjectField::EndTime, HitObjectKind::Spinner { end_time }) => {
+                Some(format_time(*end_time))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Text typed into a Properties field, applied on Enter
+#[derive(Debug, Clone)]
+pub struct PropertyEdit {
+    pub field: ObjectField,
+    pub input: TextInput,
+}
+
+/// Parse a time typed as seconds ("83.250") or minutes:seconds ("1:23.25", "1:23")
+pub fn parse_time(text: &str) -> Result<f64, String> {
+    let text = text.trim();
+    let invalid = || format!("\"{}\" is not a time (use 83.250 or 1:23.250)", text);
+    let seconds = match text.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: u32 = minutes.trim().parse().map_err(|_| invalid())?;
+            let seconds: f64 = seconds.trim().parse().map_err(|_| invalid())?;
+            if !(0.0..60.0).contains(&seconds) {
+                return Err(invalid());
+            }
+            minutes as f64 * 60.0 + seconds
+        }
+        None => text.parse().map_err(|_| invalid())?,
+    };
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err("Time must not be negative".to_string());
+    }
+    Ok(seconds)
+}
+
+/// Format seconds as mm:ss.mmm
+pub fn format_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Seconds of audio played by the Metadata panel's preview button
+pub const PREVIEW_CLIP_SECONDS: f64 = 10.0;
+
+/// Editable field of the Metadata panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Title,
+    Artist,
+    Creator,
+    Version,
+    AudioFile,
+    PreviewTime,
+}
+
+impl MetadataField {
+    /// All fields in panel order
+    pub fn all() -> Vec<MetadataField> {
+        vec![
+            MetadataField::Title,
+            MetadataField::Artist,
+            MetadataField::Creator,
+            MetadataField::Version,
+            MetadataField::AudioFile,
+            MetadataField::PreviewTime,
+        ]
+    }
+
+    /// Get display name for the field
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            MetadataField::Title => "Title",
+            MetadataField::Artist => "Artist",
+            MetadataField::Creator => "Creator",
+            MetadataField::Version => "Version",
+            MetadataField::AudioFile => "Audio",
+            MetadataField::PreviewTime => "Preview",
+        }
+    }
+
+    /// Whether the field is typed into; the audio file is picked instead
+    pub fn is_typed(&self) -> bool {
+        *self != MetadataField::AudioFile
+    }
+
+    /// The field's value as shown and edited in the panel
+    pub fn value(&self, beatmap: &Beatmap) -> String {
+        let meta = &beatmap.metadata;
+        match self {
+            MetadataField::Title => meta.title.clone(),
+            MetadataField::Artist => meta.artist.clone(),
+            MetadataField::Creator => meta.creator.clone(),
+            MetadataField::Version => meta.version.clone(),
+            MetadataField::AudioFile => Path::new(&beatmap.audio_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "(none)".to_string()),
+            MetadataField::PreviewTime => format_time(beatmap.preview_time),
+        }
+    }
+}
+
+/// Text typed into a Metadata field, applied on Enter
+#[derive(Debug, Clone)]
+pub struct MetadataEdit {
+    pub field: MetadataField,
+    pub input: TextInput,
+}
+
+/// Everything the Metadata panel edits, swapped as a whole on undo
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataSnapshot {
+    pub metadata: BeatmapMetadata,
+    pub audio_path: String,
+    pub preview_time: f64,
+}
+
+impl MetadataSnapshot {
+    /// Copy the editable metadata out of a beatmap
+    pub fn of(beatmap: &Beatmap) -> Self {
+        Self {
+            metadata: beatmap.metadata.clone(),
+            audio_path: beatmap.audio_path.clone(),
+            preview_time: beatmap.preview_time,
+        }
+    }
+
+    /// Write the metadata back into a beatmap
+    pub fn apply_to(self, beatmap: &mut Beatmap) {
+        beatmap.metadata = self.metadata;
+        beatmap.audio_path = self.audio_path;
+        beatmap.preview_time = self.preview_time;
+    }
+
+    /// Problems with the metadata, by field. `taken_versions` are the difficulty names
+    /// used by the rest of the beatmap set
+    pub fn problems(
+        &self,
+        taken_versions: &[String],
+        audio_duration: Option<f64>,
+    ) -> Vec<(MetadataField, String)> {
+        let meta = &self.metadata;
+        let mut problems = Vec::new();
+        if meta.title.trim().is_empty() {
+            problems.push((MetadataField::Title, "Title is required".to_string()));
+        }
+        if meta.artist.trim().is_empty() {
+            problems.push((MetadataField::Artist, "Artist is required".to_string()));
+        }
+        let version = meta.version.trim();
+        if version.is_empty() {
+            problems.push((MetadataField::Version, "Version is required".to_string()));
+        } else if taken_versions
+            .iter()
+            .any(|taken| taken.trim().eq_ignore_ascii_case(version))
+        {
+            problems.push((
+                MetadataField::Version,
+                format!("\"{}\" is already a difficulty of this song", version),
+            ));
+        }
+        if !Path::new(&self.audio_path).is_file() {
+            problems.push((MetadataField::AudioFile, "Audio file not found".to_string()));
+        }
+        if let Some(duration) = audio_duration {
+            if self.preview_time > duration {
+                problems.push((
+                    MetadataField::PreviewTime,
+                    format!(
+                        "Preview is past the end of the song ({})",
+                        format_time(duration)
+                    ),
+                ));
+            }
+        }
+        problems
+    }
+}
+
+/// Metadata every difficulty of a set has in common. The difficulty name and
+/// settings stay per difficulty
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedMetadata {
+    pub title: String,
+    pub artist: String,
+    pub source: Option<String>,
+    pub tags: Vec<String>,
+    pub audio_path: String,
+    pub background_path: Option<String>,
+}
+
+impl SharedMetadata {
+    /// Copy the shared fields out of a beatmap
+    pub fn of(beatmap: &Beatmap) -> Self {
+        Self {
+            title: beatmap.metadata.title.clone(),
+            artist: beatmap.metadata.artist.clone(),
+            source: beatmap.metadata.source.clone(),
+            tags: beatmap.tags.clone(),
+            audio_path: beatmap.audio_path.clone(),
+            background_path: beatmap.background_path.clone(),
+        }
+    }
+
+    /// Write the shared fields into a beatmap
+    pub fn apply_to(&self, beatmap: &mut Beatmap) {
+        beatmap.metadata.title = self.title.clone();
+        beatmap.metadata.artist = self.artist.clone();
+        beatmap.metadata.source = self.source.clone();
+        beatmap.tags = self.tags.clone();
+        beatmap.audio_path = self.audio_path.clone();
+        beatmap.background_path = self.background_path.clone();
+    }
+
+    /// "Field: old → new" for each shared field that differs in `other`
+    pub fn diff(&self, other: &Beatmap) -> Vec<String> {
+        let theirs = SharedMetadata::of(other);
+        let optional = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        let fields = [
+            ("Title", theirs.title, self.title.clone()),
+            ("Artist", theirs.artist, self.artist.clone()),
+            ("Source", optional(&theirs.source), optional(&self.source)),
+            ("Tags", theirs.tags.join(", "), self.tags.join(", ")),
+            ("Audio", theirs.audio_path, self.audio_path.clone()),
+            (
+                "Background",
+                optional(&theirs.background_path),
+                optional(&self.background_path),
+            ),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, old, new)| old != new)
+            .map(|(name, old, new)| format!("{}: {} → {}", name, old, new))
+            .collect()
+    }
+}
+
+/// One file a metadata propagation will rewrite
+#[derive(Debug, Clone)]
+pub struct PropagationTarget {
+    pub path: String,
+    /// Difficulty name of the file, for the confirmation list
+    pub version: String,
+    /// Fields that will change, see `SharedMetadata::diff`
+    pub changes: Vec<String>,
+}
+
+/// Metadata propagation to the rest of a set, shown before anything is written
+#[derive(Debug, Clone)]
+pub struct PropagationPlan {
+    pub shared: SharedMetadata,
+    pub targets: Vec<PropagationTarget>,
+    /// Set members that already match
+    pub unchanged: usize,
+    /// Files that couldn't be read, with the reason
+    pub errors: Vec<(String, String)>,
+}
+
+impl PropagationPlan {
+    /// Read each other difficulty of the set in the beatmap's folder and work out what
+    /// the shared fields would change. Unreadable files are listed, not fatal
+    pub fn new(beatmap: &Beatmap, beatmap_path: &str, set_paths: &[String]) -> Self {
+        let shared = SharedMetadata::of(beatmap);
+        let folder = Path::new(beatmap_path).parent();
+        let mut plan = PropagationPlan {
+            shared,
+            targets: Vec::new(),
+            unchanged: 0,
+            errors: Vec::new(),
+        };
+
+        for path in set_paths {
+            if path == beatmap_path || Path::new(path).parent() != folder {
+                continue;
+            }
+            match Beatmap::load_from_file(path) {
+                Ok(other) => {
+                    let changes = plan.shared.diff(&other);
+                    if changes.is_empty() {
+                        plan.unchanged += 1;
+                    } else {
+                        plan.targets.push(PropagationTarget {
+                            path: path.clone(),
+                            version: other.metadata.version,
+                            changes,
+                        });
+                    }
+                }
+                Err(e) => plan.errors.push((path.clone(), e)),
+            }
+        }
+        plan
+    }
+
+    /// Human-readable summary for the status bar
+    pub fn summary(&self) -> String {
+        let mut text = format!(
+            "Propagate metadata: {} files will change, {} already match",
+            self.targets.len(),
+            self.unchanged
+        );
+        if !self.errors.is_empty() {
+            text.push_str(&format!(", {} unreadable", self.errors.len()));
+        }
+        text
+    }
+
+    /// Write the shared fields into every target file. Each file is re-read first so
+    /// its own fields are kept as they are on disk; a file that fails is reported and
+    /// the rest are still written. Returns the paths written
+    pub fn apply(&self) -> (Vec<String>, Vec<(String, String)>) {
+        let mut written = Vec::new();
+        let mut errors = self.errors.clone();
+        for target in &self.targets {
+            let result = Beatmap::load_from_file(&target.path).and_then(|mut other| {
+                self.shared.apply_to(&mut other);
+                other.save_to_file(&target.path)
+            });
+            match result {
+                Ok(()) => written.push(target.path.clone()),
+                Err(e) => errors.push((target.path.clone(), e)),
+            }
+        }
+        (written, errors)
+    }
+}
+
+/// Save a beatmap as JSON, refusing one that fails validation so a broken map never
+/// replaces the file. The error lists the problems found
+pub fn save_beatmap(path: &str, beatmap: &Beatmap) -> Result<(), String> {
+    if let Err(errors) = beatmap.validate() {
+        return Err(format!(
+            "{} problem(s): {}",
+            errors.len(),
+            errors.join("; ")
+        ));
+    }
+    beatmap.save_to_file(path)
+}
+
+/// Load a beatmap from JSON with its hit objects and timing points in time order
+pub fn load_beatmap(path: &str) -> Result<Beatmap, String> {
+    let mut beatmap = Beatmap::load_from_file(path)?;
+    beatmap.sort_hit_objects();
+    beatmap.sort_timing_points();
+    Ok(beatmap)
+}
+
+/// Audio files the beatmap can switch to: those next to its current audio file, or
+/// next to the beatmap file when it has none
+pub fn audio_choices(audio_path: &str, beatmap_path: &str) -> Vec<String> {
+    let audio = Path::new(audio_path);
+    let folder = if audio.is_dir() {
+        Some(audio)
+    } else if !audio_path.is_empty() {
+        audio.parent()
+    } else {
+        Path::new(beatmap_path).parent()
+    };
+    let Some(Ok(entries)) = folder.map(fs::read_dir) else {
+        return Vec::new();
+    };
+
+    let mut choices: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| ext == "mp3" || ext == "ogg" || ext == "wav")
+        })
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    choices.sort();
+    choices
+}
+
+/// Swap in edited metadata as an undoable action, or nothing if it is unchanged
+fn apply_metadata(beatmap: &mut Beatmap, new_info: MetadataSnapshot) -> Option<EditorAction> {
+    let old_info = MetadataSnapshot::of(beatmap);
+    if old_info == new_info {
+        return None;
+    }
+    new_info.clone().apply_to(beatmap);
+    Some(EditorAction::ModifyMetadata {
+        old_info: Box::new(old_info),
+        new_info: Box::new(new_info),
+    })
+}
+
+/// Move objects to their new positions and times; spinners keep their length
+fn apply_moves(beatmap: &mut Beatmap, moves: &[ObjectMove]) {
+    let moves: HashMap<HitObjectId, &ObjectMove> = moves.iter().map(|m| (m.id, m)).collect();
+    for obj in &mut beatmap.hit_objects {
+        if let Some(m) = moves.get(&obj.id) {
+            if let HitObjectKind::Spinner { end_time } = &mut obj.kind {
+                *end_time += m.new_time - obj.time;
+            }
+            obj.position = m.new_position;
+            obj.time = m.new_time;
         }
     }
+    beatmap.sort_hit_objects();
+}
+
+/// Swap objects in the beatmap for the versions with the same ids
+fn replace_objects(beatmap: &mut Beatmap, objects: &[HitObject]) {
+    for object in objects {
+        if let Some(slot) = beatmap.hit_objects.iter_mut().find(|o| o.id == object.id) {
+            *slot = object.clone();
+        }
+    }
+    beatmap.sort_hit_objects();
 }
 
 /// Editor UI state
@@ -476,7 +2692,7 @@ pub struct EditorUIState {
     pub right_panel_tab: EditorRightTab,
     /// Hover info
     pub hover_info: Option<String>,
-    /// Status message
+    /// Status message and when it expires
     pub status_message: Option<(String, Instant)>,
 }
 
@@ -498,15 +2714,16 @@ impl Default for EditorUIState {
 }
 
 impl EditorUIState {
-    /// Show a status message
+    /// Show a status message for `duration_secs` seconds
     pub fn show_status(&mut self, message: String, duration_secs: u64) {
-        self.status_message = Some((message, Instant::now()));
+        let expires = Instant::now() + Duration::from_secs(duration_secs);
+        self.status_message = Some((message, expires));
     }
 
-    /// Check if status message has expired
-    pub fn update_status(&mut self, duration_secs: u64) {
-        if let Some((_, start)) = &self.status_message {
-            if start.elapsed().as_secs() > duration_secs {
+    /// Clear the status message once it has expired
+    pub fn update_status(&mut self) {
+        if let Some((_, expires)) = &self.status_message {
+            if Instant::now() >= *expires {
                 self.status_message = None;
             }
         }
@@ -519,6 +2736,9 @@ pub enum EditorLeftTab {
     Tools,
     Timing,
     Bookmarks,
+    Practice,
+    Comments,
+    History,
 }
 
 /// Right panel tabs
@@ -562,9 +2782,9 @@ pub const TIMELINE_WAVEFORM_HEIGHT: f32 = 60.0;
 
 /// Get beat line opacity based on beat importance
 pub fn get_beat_line_opacity(beat_index: usize) -> f32 {
-    if beat_index % 16 == 0 {
+    if beat_index.is_multiple_of(16) {
         1.0 // Measure line
-    } else if beat_index % 4 == 0 {
+    } else if beat_index.is_multiple_of(4) {
         0.7 // Beat line
     } else {
         0.3 // Sub-beat line
@@ -580,3 +2800,721 @@ pub fn time_to_timeline_pos(time: f64, zoom: f32, scroll: f32) -> f32 {
 pub fn timeline_pos_to_time(pos: f32, zoom: f32, scroll: f32) -> f64 {
     ((pos - scroll) / zoom) as f64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn circle(id: HitObjectId, time: f64) -> HitObject {
+        HitObject {
+            id,
+            time,
+            position: Vec2::ZERO,
+            kind: HitObjectKind::Circle,
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Normal,
+            sample_set: None,
+        }
+    }
+
+    /// 120 BPM, so 1/4 ticks are 0.125s apart
+    fn beatmap(objects: Vec<HitObject>) -> Beatmap {
+        Beatmap {
+            timing_points: vec![TimingPoint::default()],
+            hit_objects: objects,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn resnap_moves_off_grid_objects_and_leaves_ambiguous_ones() {
+        let map = beatmap(vec![circle(1, 0.13), circle(2, 0.25), circle(3, 0.3125)]);
+        let plan = EditorState::default().plan_resnap(&map, 4);
+
+        assert_eq!(plan.changes.len(), 1);
+        assert_eq!(plan.changes[0].id, 1);
+        assert!((plan.changes[0].new_time - 0.125).abs() < 1e-9);
+        assert!((plan.max_shift - 0.005).abs() < 1e-9);
+        assert_eq!(plan.ambiguous, vec![3]);
+    }
+
+    #[test]
+    fn resnap_only_plans_the_selection() {
+        let map = beatmap(vec![circle(1, 0.13), circle(2, 0.38)]);
+        let editor = EditorState {
+            selected_objects: vec![2],
+            ..Default::default()
+        };
+        let plan = editor.plan_resnap(&map, 4);
+
+        assert_eq!(plan.changes.len(), 1);
+        assert_eq!(plan.changes[0].id, 2);
+        assert!((plan.changes[0].new_time - 0.375).abs() < 1e-9);
+    }
+
+    #[test]
+    fn edits_and_undo_drop_a_pending_resnap() {
+        let mut map = beatmap(vec![circle(1, 0.13)]);
+        let mut editor = EditorState::default();
+
+        editor.pending_resnap = Some(editor.plan_resnap(&map, 4));
+        let object = circle(2, 0.5);
+        map.add_hit_object(object.clone());
+        editor.record_action(EditorAction::AddObject { object });
+        assert!(editor.pending_resnap.is_none());
+
+        editor.pending_resnap = Some(editor.plan_resnap(&map, 4));
+        assert!(editor.undo(&mut map));
+        assert!(editor.pending_resnap.is_none());
+
+        editor.pending_resnap = Some(editor.plan_resnap(&map, 4));
+        assert!(editor.redo(&mut map));
+        assert!(editor.pending_resnap.is_none());
+    }
+
+    /// Ten recorded circle additions, one per beat
+    fn history_of_ten() -> (EditorState, Beatmap) {
+        let mut map = beatmap(Vec::new());
+        let mut editor = EditorState::default();
+        for id in 1..=10 {
+            let object = circle(id, id as f64 * 0.5);
+            map.add_hit_object(object.clone());
+            editor.record_action(EditorAction::AddObject { object });
+        }
+        (editor, map)
+    }
+
+    fn object_ids(map: &Beatmap) -> Vec<HitObjectId> {
+        map.hit_objects.iter().map(|object| object.id).collect()
+    }
+
+    #[test]
+    fn jumping_through_history_matches_pressing_undo_and_redo() {
+        let (mut jumped, mut jumped_map) = history_of_ten();
+        jumped.jump_to_history(&mut jumped_map, 5);
+        jumped.jump_to_history(&mut jumped_map, 8);
+
+        let (mut pressed, mut pressed_map) = history_of_ten();
+        for _ in 0..5 {
+            assert!(pressed.undo(&mut pressed_map));
+        }
+        for _ in 0..3 {
+            assert!(pressed.redo(&mut pressed_map));
+        }
+
+        assert_eq!(object_ids(&jumped_map), object_ids(&pressed_map));
+        assert_eq!(object_ids(&jumped_map), (1..=8).collect::<Vec<_>>());
+        assert_eq!(jumped.undo_stack.len(), pressed.undo_stack.len());
+        assert_eq!(jumped.redo_stack.len(), pressed.redo_stack.len());
+        assert_eq!(jumped.history_labels(), pressed.history_labels());
+        assert_eq!(jumped.history_labels().len(), 10);
+
+        // The remaining redo steps still replay the rest of the history
+        jumped.jump_to_history(&mut jumped_map, 99);
+        assert_eq!(object_ids(&jumped_map), (1..=10).collect::<Vec<_>>());
+        assert!(jumped.redo_stack.is_empty());
+    }
+
+    fn circle_at(id: HitObjectId, time: f64, x: f32, y: f32) -> HitObject {
+        HitObject {
+            position: Vec2::new(x, y),
+            ..circle(id, time)
+        }
+    }
+
+    #[test]
+    fn mirroring_flips_positions_and_keeps_the_rhythm() {
+        let originals = vec![
+            circle_at(1, 1.0, 100.0, 50.0),
+            circle_at(2, 1.25, -40.0, 20.0),
+            circle_at(3, 2.0, 0.0, -120.0),
+        ];
+        let mut map = beatmap(originals.clone());
+        let mut editor = EditorState {
+            selected_objects: vec![1, 2, 3],
+            current_time: 5.0,
+            mirror_flip_x: true,
+            mirror_flip_y: false,
+            ..Default::default()
+        };
+
+        let outcome = editor.mirror_selection(&mut map);
+        assert_eq!((outcome.applied, outcome.skipped), (3, 0));
+        assert_eq!(map.hit_objects.len(), 6);
+        for original in &originals {
+            let copy = map
+                .hit_objects
+                .iter()
+                .find(|obj| (obj.time - (original.time + 4.0)).abs() < 1e-9)
+                .unwrap();
+            assert_eq!(
+                copy.position,
+                Vec2::new(-original.position.x, original.position.y)
+            );
+        }
+        assert_eq!(&map.hit_objects[..3], &originals[..]);
+
+        // Flipping the copies again lands exactly on the originals' shapes
+        editor.current_time = 9.0;
+        editor.mirror_flip_y = true;
+        let again = editor.mirror_selection(&mut map);
+        let twice: Vec<Vec2> = map.hit_objects[6..]
+            .iter()
+            .map(|obj| obj.position)
+            .collect();
+        let expected: Vec<Vec2> = originals
+            .iter()
+            .map(|obj| Vec2::new(obj.position.x, -obj.position.y))
+            .collect();
+        assert_eq!(twice, expected);
+
+        editor.record_action(again.action.unwrap());
+        assert!(editor.undo(&mut map));
+        assert_eq!(map.hit_objects.len(), 6);
+    }
+
+    #[test]
+    fn mirroring_skips_copies_that_would_overlap() {
+        let mut map = beatmap(vec![
+            circle_at(1, 1.0, 10.0, 0.0),
+            circle_at(2, 1.5, 20.0, 0.0),
+            circle(3, 3.5),
+        ]);
+        let mut editor = EditorState {
+            selected_objects: vec![1, 2],
+            current_time: 3.0,
+            ..Default::default()
+        };
+
+        let outcome = editor.mirror_selection(&mut map);
+        assert_eq!((outcome.applied, outcome.skipped), (1, 1));
+        assert_eq!(map.hit_objects.len(), 4);
+    }
+
+    #[test]
+    fn pasting_a_rhythm_retimes_without_moving() {
+        let mut map = beatmap(vec![
+            circle_at(1, 1.0, 0.0, 0.0),
+            circle_at(2, 1.25, 0.0, 0.0),
+            circle_at(3, 2.0, 0.0, 0.0),
+            circle_at(4, 4.0, 12.5, -33.25),
+            circle_at(5, 4.5, 101.0, 7.75),
+            circle_at(6, 5.5, -64.0, 80.0),
+        ]);
+        let mut editor = EditorState {
+            selected_objects: vec![1, 2, 3],
+            ..Default::default()
+        };
+        assert_eq!(editor.copy_rhythm(&map), 3);
+
+        let positions: Vec<Vec2> = map.hit_objects[3..]
+            .iter()
+            .map(|obj| obj.position)
+            .collect();
+        editor.selected_objects = vec![4, 5, 6];
+        let outcome = editor.paste_rhythm(&mut map);
+        assert_eq!((outcome.applied, outcome.skipped), (2, 0));
+
+        let retimed: Vec<(f64, Vec2)> = map.hit_objects[3..]
+            .iter()
+            .map(|obj| (obj.time, obj.position))
+            .collect();
+        assert_eq!(
+            retimed,
+            vec![
+                (4.0, positions[0]),
+                (4.25, positions[1]),
+                (5.0, positions[2])
+            ]
+        );
+
+        editor.record_action(outcome.action.unwrap());
+        assert!(editor.undo(&mut map));
+        let times: Vec<f64> = map.hit_objects[3..].iter().map(|obj| obj.time).collect();
+        assert_eq!(times, vec![4.0, 4.5, 5.5]);
+    }
+
+    #[test]
+    fn times_parse_as_seconds_or_minutes_and_seconds() {
+        assert_eq!(parse_time("83.250"), Ok(83.25));
+        assert_eq!(parse_time("1:23.25"), Ok(83.25));
+        assert_eq!(parse_time("1:23"), Ok(83.0));
+        assert_eq!(parse_time(" 0:00.5 "), Ok(0.5));
+        assert!(parse_time("1:60").is_err());
+        assert!(parse_time("-1").is_err());
+        assert!(parse_time("1:-5").is_err());
+        assert!(parse_time("abc").is_err());
+        assert!(parse_time("").is_err());
+        assert_eq!(parse_time(&format_time(83.25)), Ok(83.25));
+    }
+
+    fn type_property(editor: &mut EditorState, field: ObjectField, text: &str) {
+        editor.property_edit = Some(PropertyEdit {
+            field,
+            input: TextInput::new(text).numeric(),
+        });
+    }
+
+    #[test]
+    fn property_edits_apply_to_the_whole_selection() {
+        let mut map = beatmap(vec![
+            circle_at(1, 1.0, 10.0, 5.0),
+            circle_at(2, 2.0, 20.0, 5.0),
+        ]);
+        let mut editor = EditorState {
+            selected_objects: vec![1, 2],
+            ..Default::default()
+        };
+        assert_eq!(editor.shared_property(&map, ObjectField::X), "—");
+        assert_eq!(editor.shared_property(&map, ObjectField::Y), "5.0");
+
+        type_property(&mut editor, ObjectField::X, "-64");
+        let action = editor.commit_property_edit(&mut map).unwrap().unwrap();
+        assert_eq!(editor.shared_property(&map, ObjectField::X), "-64.0");
+
+        editor.record_action(action);
+        assert!(editor.undo(&mut map));
+        let xs: Vec<f32> = map.hit_objects.iter().map(|obj| obj.position.x).collect();
+        assert_eq!(xs, vec![10.0, 20.0]);
+    }
+
+    #[test]
+    fn invalid_property_edits_change_nothing() {
+        let mut map = beatmap(vec![circle_at(1, 1.0, 10.0, 5.0)]);
+        let before = map.hit_objects.clone();
+        let mut editor = EditorState {
+            selected_objects: vec![1],
+            ..Default::default()
+        };
+
+        type_property(&mut editor, ObjectField::X, "400");
+        assert_eq!(
+            editor.commit_property_edit(&mut map).unwrap_err(),
+            "Position is outside the playfield"
+        );
+        type_property(&mut editor, ObjectField::Time, "-2");
+        assert!(editor.commit_property_edit(&mut map).is_err());
+        assert_eq!(map.hit_objects, before);
+    }
+
+    #[test]
+    fn history_labels_summarize_each_step() {
+        assert_eq!(
+            EditorAction::AddObject {
+                object: circle(1, 83.456)
+            }
+            .label(),
+            "Add circle @ 01:23.456"
+        );
+        let objects: Vec<HitObject> = (1..=12).map(|id| circle(id, id as f64)).collect();
+        assert_eq!(
+            EditorAction::DeleteObjects { objects }.label(),
+            "Delete 12 objects"
+        );
+        let moves = (1..=3)
+            .map(|id| ObjectMove {
+                id,
+                old_position: Vec2::ZERO,
+                new_position: Vec2::ONE,
+                old_time: 1.0,
+                new_time: 1.0,
+            })
+            .collect();
+        assert_eq!(
+            EditorAction::MoveObjects { moves }.label(),
+            "Move 3 objects"
+        );
+        let old_settings = BeatmapSettings {
+            approach_rate: 5.0,
+            ..Default::default()
+        };
+        let new_settings = BeatmapSettings {
+            approach_rate: 7.0,
+            ..old_settings.clone()
+        };
+        assert_eq!(
+            EditorAction::ModifySettings {
+                old_settings,
+                new_settings
+            }
+            .label(),
+            "Change AR 5→7"
+        );
+    }
+
+    #[test]
+    fn copying_difficulty_settings_is_one_undoable_step() {
+        let mut map = beatmap(Vec::new());
+        let mut editor = EditorState::default();
+        let hard = BeatmapSettings {
+            circle_size: 5.0,
+            approach_rate: 9.5,
+            slider_multiplier: 2.0,
+            ..Default::default()
+        };
+
+        let action = editor.copy_difficulty_settings(&mut map, &hard).unwrap();
+        editor.record_action(action);
+        assert_eq!(map.settings, hard);
+        assert!(editor.copy_difficulty_settings(&mut map, &hard).is_none());
+
+        assert!(editor.undo(&mut map));
+        assert_eq!(map.settings, BeatmapSettings::default());
+    }
+
+    /// A difficulty of the set in `folder`, saved to disk
+    fn difficulty(folder: &Path, version: &str, approach_rate: f32) -> (String, Beatmap) {
+        let mut map = beatmap(vec![circle(1, 1.0)]);
+        map.metadata.title = "Old Title".to_string();
+        map.metadata.artist = "Old Artist".to_string();
+        map.metadata.version = version.to_string();
+        map.settings.approach_rate = approach_rate;
+        map.audio_path = "song.mp3".to_string();
+        let path = folder
+            .join(format!("{}.json", version))
+            .to_string_lossy()
+            .to_string();
+        map.save_to_file(&path).unwrap();
+        (path, map)
+    }
+
+    #[test]
+    fn propagating_metadata_rewrites_shared_fields_only() {
+        let folder = scratch_dir("editor-propagate");
+        let (easy_path, mut easy) = difficulty(&folder, "Easy", 5.0);
+        let (hard_path, _) = difficulty(&folder, "Hard", 9.0);
+        let (same_path, _) = difficulty(&folder, "Same", 7.0);
+        let broken_path = folder.join("Broken.json").to_string_lossy().to_string();
+        std::fs::write(&broken_path, "not a beatmap").unwrap();
+
+        easy.metadata.title = "New Title".to_string();
+        easy.metadata.source = Some("Game".to_string());
+        easy.tags = vec!["electronic".to_string()];
+        easy.background_path = Some("bg.png".to_string());
+        let mut same = Beatmap::load_from_file(&same_path).unwrap();
+        SharedMetadata::of(&easy).apply_to(&mut same);
+        same.save_to_file(&same_path).unwrap();
+
+        let set = [
+            easy_path.clone(),
+            hard_path.clone(),
+            same_path,
+            broken_path.clone(),
+        ];
+        let plan = PropagationPlan::new(&easy, &easy_path, &set);
+        assert_eq!(plan.targets.len(), 1);
+        assert_eq!(plan.targets[0].version, "Hard");
+        assert_eq!(
+            plan.targets[0].changes,
+            [
+                "Title: Old Title → New Title",
+                "Source: - → Game",
+                "Tags:  → electronic",
+                "Background: - → bg.png"
+            ]
+        );
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.errors.len(), 1);
+        assert_eq!(plan.errors[0].0, broken_path);
+
+        let (written, errors) = plan.apply();
+        assert_eq!(written, std::slice::from_ref(&hard_path));
+        assert_eq!(errors.len(), 1);
+        let hard = Beatmap::load_from_file(&hard_path).unwrap();
+        assert_eq!(SharedMetadata::of(&hard), SharedMetadata::of(&easy));
+        assert_eq!(hard.metadata.version, "Hard");
+        assert_eq!(hard.settings.approach_rate, 9.0);
+        assert_eq!(hard.hit_objects.len(), 1);
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+
+    #[test]
+    fn toggling_practice_hints_is_undoable() {
+        let mut map = beatmap(Vec::new());
+        let mut editor = EditorState {
+            current_time: 12.0,
+            ..Default::default()
+        };
+
+        assert!(
+            matches!(editor.toggle_practice_hint(&mut map), HintToggle::Started(t) if t == 12.0)
+        );
+        editor.current_time = 10.0;
+        let HintToggle::Added(hint, action) = editor.toggle_practice_hint(&mut map) else {
+            panic!("expected the hint to be added");
+        };
+        assert_eq!(
+            (hint.start, hint.end, hint.rate),
+            (10.0, 12.0, DEFAULT_HINT_RATE)
+        );
+        assert_eq!(action.label(), "Add practice hint");
+        editor.record_action(action);
+        assert_eq!(editor.selected_hint, Some(0));
+
+        editor.current_time = 11.0;
+        let HintToggle::Removed(_, action) = editor.toggle_practice_hint(&mut map) else {
+            panic!("expected the hint to be removed");
+        };
+        editor.record_action(action);
+        assert!(map.practice_hints.is_empty());
+
+        assert!(editor.undo(&mut map));
+        assert_eq!(map.practice_hints, [hint]);
+        assert!(editor.undo(&mut map));
+        assert!(map.practice_hints.is_empty());
+    }
+
+    fn type_hint_field(
+        editor: &mut EditorState,
+        map: &mut Beatmap,
+        field: HintField,
+        text: &str,
+    ) -> Result<Option<EditorAction>, String> {
+        editor.begin_hint_edit(map, field);
+        editor.hint_edit.as_mut().unwrap().input.set_text(text);
+        editor.commit_hint_edit(map)
+    }
+
+    #[test]
+    fn hint_edits_keep_the_section_valid() {
+        let mut map = beatmap(Vec::new());
+        map.add_practice_hint(PracticeHint {
+            start: 10.0,
+            end: 20.0,
+            rate: 0.75,
+            label: "Stream".to_string(),
+        })
+        .unwrap();
+        let mut editor = EditorState {
+            selected_hint: Some(0),
+            ..Default::default()
+        };
+
+        let action = type_hint_field(&mut editor, &mut map, HintField::Rate, "0.5").unwrap();
+        assert_eq!(action.unwrap().label(), "Edit practice hint");
+        assert_eq!(map.practice_hints[0].rate, 0.5);
+        assert!(
+            type_hint_field(&mut editor, &mut map, HintField::Rate, "0.50")
+                .unwrap()
+                .is_none()
+        );
+
+        let before = map.practice_hints.clone();
+        assert!(type_hint_field(&mut editor, &mut map, HintField::End, "0:05").is_err());
+        assert!(type_hint_field(&mut editor, &mut map, HintField::Rate, "0").is_err());
+        assert!(type_hint_field(&mut editor, &mut map, HintField::Label, "  ").is_err());
+        assert_eq!(map.practice_hints, before);
+        assert!(editor.hint_edit.is_none());
+    }
+
+    #[test]
+    fn clicking_a_comment_marker_jumps_to_its_time() {
+        let dir = scratch_dir("editor-comments");
+        let path = dir.join("song.json").to_string_lossy().into_owned();
+        let mut editor = EditorState {
+            current_beatmap_path: Some(path.clone()),
+            timeline_zoom: 100.0,
+            timeline_scroll: -500.0,
+            ..Default::default()
+        };
+
+        for (time, text) in [(12.0, "late start"), (8.0, "too dense")] {
+            editor.current_time = time;
+            editor.begin_comment();
+            editor.comment_draft.as_mut().unwrap().input.set_text(text);
+            editor.commit_comment("reviewer".to_string()).unwrap();
+        }
+        editor.current_time = 0.0;
+
+        // 8s sits at x = 300 on the timeline
+        let marker_y = comment_marker_y(80.0);
+        assert_eq!(
+            editor.comment_marker_at(300.0 + COMMENT_MARKER_SIZE, marker_y, 80.0),
+            Some(0)
+        );
+        assert_eq!(editor.comment_marker_at(300.0, marker_y + 20.0, 80.0), None);
+        assert_eq!(editor.comment_marker_at(500.0, marker_y, 80.0), None);
+        let index = editor.comment_marker_at(700.0, marker_y, 80.0).unwrap();
+        editor.jump_to_comment(index);
+        assert_eq!(editor.current_time, 12.0);
+        assert_eq!(editor.selected_comment, Some(1));
+
+        // Edits are saved straight away
+        editor.toggle_comment_resolved().unwrap();
+        editor.load_comments().unwrap();
+        let texts: Vec<(&str, bool)> = editor
+            .comments
+            .comments
+            .iter()
+            .map(|c| (c.text.as_str(), c.resolved))
+            .collect();
+        assert_eq!(texts, [("too dense", false), ("late start", true)]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn soft_whistle() -> HitsoundPickup {
+        HitsoundPickup {
+            hitsound: Hitsound::Whistle,
+            sample_set: Some(SampleSet {
+                normal_set: 2,
+                addition_set: 2,
+                index: 1,
+                volume: 60,
+                filename: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn the_copier_picks_up_then_stamps_one_undo_step_at_a_time() {
+        let mut source = circle(1, 0.5);
+        source.hitsound = Hitsound::Whistle;
+        source.sample_set = soft_whistle().sample_set;
+        let mut map = beatmap(vec![source, circle(2, 1.0), circle(3, 1.5)]);
+        let mut editor = EditorState::default();
+        editor.set_tool(EditorTool::HitsoundCopier);
+
+        assert!(matches!(
+            editor.copier_click(&mut map, 1, false),
+            Some(CopierClick::PickedUp(pickup)) if pickup == soft_whistle()
+        ));
+        assert!(editor.undo_stack.is_empty());
+
+        for id in [2, 3] {
+            let Some(CopierClick::Stamped(action)) = editor.copier_click(&mut map, id, false)
+            else {
+                panic!("object {} wasn't stamped", id);
+            };
+            editor.record_labeled_action(action, format!("Stamp {}", id));
+        }
+        assert!(map
+            .hit_objects
+            .iter()
+            .all(|obj| HitsoundPickup::of(obj) == soft_whistle()));
+        assert_eq!(editor.undo_stack.len(), 2);
+        assert!(matches!(
+            editor.copier_click(&mut map, 2, false),
+            Some(CopierClick::Unchanged)
+        ));
+        assert_eq!(editor.copier_click(&mut map, 99, false).map(|_| ()), None);
+        assert_eq!(editor.hitsound_pickup, Some(soft_whistle()));
+
+        assert!(editor.undo(&mut map));
+        assert_eq!(map.hit_objects[2].hitsound, Hitsound::Normal);
+        assert_eq!(map.hit_objects[1].hitsound, Hitsound::Whistle);
+        assert!(editor.undo(&mut map));
+        assert_eq!(map.hit_objects[1].sample_set, None);
+    }
+
+    #[test]
+    fn shift_click_picks_up_a_new_source_and_leaving_drops_it() {
+        let mut clap = circle(2, 1.0);
+        clap.hitsound = Hitsound::Clap;
+        let mut map = beatmap(vec![circle(1, 0.5), clap]);
+        let mut editor = EditorState::default();
+        editor.set_tool(EditorTool::HitsoundCopier);
+
+        editor.copier_click(&mut map, 1, false);
+        assert!(matches!(
+            editor.copier_click(&mut map, 2, true),
+            Some(CopierClick::PickedUp(pickup)) if pickup.hitsound == Hitsound::Clap
+        ));
+        assert_eq!(map.hit_objects[0].hitsound, Hitsound::Normal);
+        assert!(editor.undo_stack.is_empty());
+
+        editor.drop_copier();
+        assert_eq!(editor.hitsound_pickup, None);
+        assert_eq!(editor.current_tool, EditorTool::Select);
+
+        editor.set_tool(EditorTool::HitsoundCopier);
+        editor.copier_click(&mut map, 2, false);
+        editor.set_tool(EditorTool::Circle);
+        assert_eq!(editor.hitsound_pickup, None);
+    }
+
+    #[test]
+    fn saved_beatmaps_load_back_with_their_objects_in_time_order() {
+        let path = scratch_dir("editor-round-trip")
+            .join("song.json")
+            .to_string_lossy()
+            .to_string();
+        let slider = HitObject {
+            kind: HitObjectKind::Slider {
+                control_points: vec![Vec2::ZERO, Vec2::new(100.0, 50.0)],
+                repeats: 1,
+                pixel_length: 120.0,
+                velocity: 1.0,
+            },
+            position: Vec2::new(-40.0, 25.0),
+            new_combo: true,
+            hitsound: Hitsound::Clap,
+            ..circle(2, 1.0)
+        };
+        let spinner = HitObject {
+            kind: HitObjectKind::Spinner { end_time: 4.0 },
+            ..circle(3, 2.5)
+        };
+        let map = beatmap(vec![circle(1, 0.5), slider, spinner]);
+        save_beatmap(&path, &map).unwrap();
+        let loaded = load_beatmap(&path).unwrap();
+        assert_eq!(loaded.hit_objects, map.hit_objects);
+
+        // A file written out of order comes back sorted
+        let mut shuffled = map.clone();
+        shuffled.hit_objects.reverse();
+        shuffled.save_to_file(&path).unwrap();
+        assert_eq!(load_beatmap(&path).unwrap().hit_objects, map.hit_objects);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn saving_a_broken_beatmap_lists_the_problems_and_keeps_the_file() {
+        let path = scratch_dir("editor-broken-save")
+            .join("song.json")
+            .to_string_lossy()
+            .to_string();
+        let good = beatmap(vec![circle(1, 0.5)]);
+        save_beatmap(&path, &good).unwrap();
+
+        let broken = beatmap(vec![circle(1, 0.5), circle(2, -1.0)]);
+        let error = save_beatmap(&path, &broken).unwrap_err();
+        assert!(error.starts_with("2 problem(s): "), "{}", error);
+        assert!(error.contains("Object 2 has invalid time"), "{}", error);
+        assert_eq!(load_beatmap(&path).unwrap().hit_objects, good.hit_objects);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn reloading_clears_the_history_and_the_selection() {
+        let mut map = beatmap(vec![circle(1, 0.5)]);
+        let mut editor = EditorState::default();
+        for object in [circle(2, 1.0), circle(3, 1.5)] {
+            map.add_hit_object(object.clone());
+            editor.record_action(EditorAction::AddObject { object });
+        }
+        editor.undo(&mut map);
+        assert!(!editor.undo_stack.is_empty() && !editor.redo_stack.is_empty());
+        editor.selected_objects = vec![1];
+        editor.pending_reload = true;
+
+        editor.reset_for_reload();
+        assert!(editor.undo_stack.is_empty());
+        assert!(editor.redo_stack.is_empty());
+        assert!(editor.selected_objects.is_empty());
+        assert!(!editor.dirty && !editor.pending_reload && !editor.pending_exit);
+    }
+
+    #[test]
+    fn status_messages_last_as_long_as_asked() {
+        let mut ui = EditorUIState::default();
+        ui.show_status("Beatmap saved".to_string(), 5);
+        ui.update_status();
+        assert!(ui.status_message.is_some());
+
+        ui.show_status("Copied".to_string(), 0);
+        ui.update_status();
+        assert!(ui.status_message.is_none());
+    }
+}
@@ -0,0 +1,406 @@
+// src/editor_audio.rs
+
+use crate::audio::play_from;
+use crate::beatmap::{BeatmapAssets, HitObjectId, Hitsound};
+use crate::config::GameConfig;
+use crate::editor::{hitsound_description, hitsound_volume, EditorState, EditorUIState};
+use crate::structs::GameAudioSink;
+use bevy::prelude::*;
+use rodio::buffer::SamplesBuffer;
+use rodio::source::Zero;
+use rodio::{Sink, Source};
+use std::time::{Duration, Instant};
+
+/// Change per click on an editor volume control
+pub const EDITOR_VOLUME_STEP: f32 = 0.1;
+
+/// How far past the playhead object ticks are queued (seconds of song time)
+pub const TICK_LOOKAHEAD: f64 = 0.1;
+
+/// Sample rate ticks and the silence between them are synthesized at
+const TICK_SAMPLE_RATE: u32 = 44_100;
+
+/// How long the cursor rests on an object before its hitsound is auditioned (seconds)
+pub const AUDITION_HOVER_SECS: f32 = 0.3;
+
+/// Shortest gap between two auditions (seconds), so moving back and forth along a
+/// stream doesn't machine-gun samples
+pub const AUDITION_MIN_GAP_SECS: f32 = 0.25;
+
+/// Editor-only mix of the song and object ticks. Kept while the game runs, across
+/// editor visits, but never written to the config
+#[derive(Debug, Clone, Resource)]
+pub struct EditorAudio {
+    /// Song volume (0.0 - 1.0), scaled by the master volume
+    pub music_volume: f32,
+    /// Object tick volume (0.0 - 1.0), scaled by the master volume
+    pub tick_volume: f32,
+    pub music_muted: bool,
+    pub ticks_muted: bool,
+}
+
+impl Default for EditorAudio {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.0,
+            tick_volume: 1.0,
+            music_muted: false,
+            ticks_muted: false,
+        }
+    }
+}
+
+impl EditorAudio {
+    /// Song output volume; never louder than the master volume
+    pub fn music_output(&self, master_volume: f32) -> f32 {
+        if self.music_muted {
+            0.0
+        } else {
+            self.music_volume.clamp(0.0, 1.0) * master_volume
+        }
+    }
+
+    /// Tick output volume; never louder than the master volume
+    pub fn tick_output(&self, master_volume: f32) -> f32 {
+        if self.ticks_muted {
+            0.0
+        } else {
+            self.tick_volume.clamp(0.0, 1.0) * master_volume
+        }
+    }
+
+    /// Step a volume by `EDITOR_VOLUME_STEP` in `direction`
+    pub fn adjust(volume: &mut f32, direction: f32) {
+        *volume = ((*volume + direction * EDITOR_VOLUME_STEP) * 10.0).round() / 10.0;
+        *volume = volume.clamp(0.0, 1.0);
+    }
+}
+
+/// Short synthesized tick for an object's hitsound; additions get their own pitch
+/// and length so they can be told apart while mapping
+pub fn tick_sample(hitsound: Hitsound) -> SamplesBuffer<f32> {
+    let (frequency, duration) = match hitsound {
+        Hitsound::Normal => (1_200.0, 0.03),
+        Hitsound::Whistle => (2_400.0, 0.06),
+        Hitsound::Finish => (600.0, 0.12),
+        Hitsound::Clap => (0.0, 0.04),
+    };
+    let count = (duration * TICK_SAMPLE_RATE as f32) as usize;
+    let mut seed: u32 = 0x9e37_79b9;
+    let samples = (0..count)
+        .map(|i| {
+            let t = i as f32 / TICK_SAMPLE_RATE as f32;
+            let envelope = 1.0 - i as f32 / count as f32;
+            let wave = if frequency > 0.0 {
+                (std::f32::consts::TAU * frequency * t).sin()
+            } else {
+                // Clap is a noise burst
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+            };
+            wave * envelope * envelope * 0.6
+        })
+        .collect::<Vec<_>>();
+    SamplesBuffer::new(1, TICK_SAMPLE_RATE, samples)
+}
+
+/// Queues object ticks on a sink a little ahead of the playhead. Silence pads the
+/// queue so each tick starts at its song time, stretched by the playback speed,
+/// which keeps ticks on time however late in the frame they were queued
+#[derive(Debug, Default)]
+pub struct TickScheduler {
+    /// Song time up to which objects have been queued
+    cursor: f64,
+    /// Song time the sink's queue runs until
+    queued_until: f64,
+}
+
+impl TickScheduler {
+    /// Start queuing from the playhead; objects before it never tick
+    pub fn reset(&mut self, time: f64) {
+        self.cursor = time;
+        self.queued_until = time;
+    }
+
+    /// Song times the next `queue` call at `now` ticks objects between
+    pub fn window(&self, now: f64) -> (f64, f64) {
+        (self.cursor, now + TICK_LOOKAHEAD)
+    }
+
+    /// Queue ticks for objects between the last call and `TICK_LOOKAHEAD` past `now`
+    pub fn queue(
+        &mut self,
+        sink: &Sink,
+        objects: impl Iterator<Item = (f64, Hitsound)>,
+        now: f64,
+        speed: f32,
+    ) {
+        let horizon = now + TICK_LOOKAHEAD;
+        let mut due: Vec<(f64, Hitsound)> = objects
+            .filter(|(time, _)| *time > self.cursor && *time <= horizon)
+            .collect();
+        due.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.cursor = self.cursor.max(horizon);
+
+        let speed = speed.max(0.01) as f64;
+        if sink.empty() {
+            self.queued_until = now;
+        }
+        for (time, hitsound) in due {
+            // A tick still sounding swallows one that lands on top of it
+            if time < self.queued_until {
+                continue;
+            }
+            let gap = Duration::from_secs_f64((time - self.queued_until) / speed);
+            sink.append(Zero::<f32>::new(1, TICK_SAMPLE_RATE).take_duration(gap));
+            let tick = tick_sample(hitsound);
+            let length = tick.total_duration().unwrap_or_default().as_secs_f64();
+            sink.append(tick);
+            self.queued_until = time + length * speed;
+        }
+    }
+}
+
+/// Hover timer behind hitsound auditioning
+#[derive(Debug, Default)]
+pub struct HitsoundAudition {
+    /// Object under the cursor and when the cursor reached it
+    hovered: Option<(HitObjectId, Instant)>,
+    /// The hovered object was already auditioned; it plays again only after the
+    /// cursor leaves and comes back
+    played: bool,
+    last_played: Option<Instant>,
+}
+
+impl HitsoundAudition {
+    /// Follow the hovered object. Returns it once, when it has been hovered for
+    /// `AUDITION_HOVER_SECS` and the last audition was long enough ago
+    pub fn update(&mut self, hover: Option<HitObjectId>, now: Instant) -> Option<HitObjectId> {
+        if self.hovered.map(|(id, _)| id) != hover {
+            self.hovered = hover.map(|id| (id, now));
+            self.played = false;
+        }
+        let (id, since) = self.hovered?;
+        if self.played || now.duration_since(since).as_secs_f32() < AUDITION_HOVER_SECS {
+            return None;
+        }
+        if self
+            .last_played
+            .is_some_and(|last| now.duration_since(last).as_secs_f32() < AUDITION_MIN_GAP_SECS)
+        {
+            return None;
+        }
+        self.played = true;
+        self.last_played = Some(now);
+        Some(id)
+    }
+}
+
+/// Play the hitsound of the object resting under the cursor, in the playfield or on
+/// the timeline, at the tick volume and show its flags in the status bar. Reads the
+/// beatmap only, so auditioning never touches the map or the undo history
+pub fn audition_hitsounds(
+    editor_state: Res<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    editor_audio: Res<EditorAudio>,
+    beatmap_assets: Res<BeatmapAssets>,
+    audio_sink: Res<GameAudioSink>,
+    mut audition: Local<HitsoundAudition>,
+) {
+    // Playback ticks every object already
+    let hover = if editor_state.is_playing {
+        None
+    } else {
+        editor_state.playfield_hover.or(editor_state.timeline_hover)
+    };
+    let Some(id) = audition.update(hover, Instant::now()) else {
+        return;
+    };
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+    let Some(object) = beatmap.hit_objects.iter().find(|obj| obj.id == id) else {
+        return;
+    };
+
+    if !editor_audio.ticks_muted {
+        let volume = hitsound_volume(beatmap, object) as f32 / 100.0;
+        audio_sink
+            .effects
+            .append(tick_sample(object.hitsound).amplify(volume));
+    }
+    editor_ui.show_status(hitsound_description(beatmap, object), 3);
+}
+
+/// Play the song and object ticks during editor playback. Starting, pausing or
+/// seeking restarts both from the playhead, so scrubbing never fires ticks
+#[allow(clippy::too_many_arguments)]
+pub fn sync_editor_audio(
+    editor_state: Res<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    editor_audio: Res<EditorAudio>,
+    beatmap_assets: Res<BeatmapAssets>,
+    audio_sink: Res<GameAudioSink>,
+    config: Res<GameConfig>,
+    mut scheduler: Local<TickScheduler>,
+    mut last_start: Local<Option<Instant>>,
+) {
+    let master = config.audio.master_volume;
+    audio_sink
+        .sink
+        .set_volume(editor_audio.music_output(master));
+    audio_sink
+        .effects
+        .set_volume(editor_audio.tick_output(master));
+
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+
+    if editor_state.playback_start != *last_start {
+        *last_start = editor_state.playback_start;
+        audio_sink.effects.stop();
+        if editor_state.is_playing {
+            scheduler.reset(editor_state.current_time);
+            match play_from(
+                &audio_sink.sink,
+                &beatmap.audio_path,
+                editor_state.current_time,
+            ) {
+                Ok(_) => audio_sink.sink.set_speed(editor_state.playback_speed),
+                Err(e) => editor_ui.show_status(e, 3),
+            }
+        } else {
+            audio_sink.sink.stop();
+        }
+    }
+
+    if !editor_state.is_playing {
+        return;
+    }
+    if editor_audio.ticks_muted {
+        // Keep the cursor moving so unmuting doesn't catch up on skipped objects
+        scheduler.reset(editor_state.current_time);
+    } else if let Some(index) = beatmap_assets.object_index() {
+        // Only the objects the index puts in the window, not the whole map every frame
+        let (from, until) = scheduler.window(editor_state.current_time);
+        scheduler.queue(
+            &audio_sink.effects,
+            beatmap.hit_objects[index.starting_between(from, until)]
+                .iter()
+                .map(|object| (object.time, object.hitsound)),
+            editor_state.current_time,
+            editor_state.playback_speed,
+        );
+    }
+}
+
+/// Silence the editor's audio on the way out and hand the sinks back at full volume
+pub fn stop_editor_audio(audio_sink: Res<GameAudioSink>) {
+    audio_sink.sink.stop();
+    audio_sink.effects.stop();
+    audio_sink.sink.set_volume(1.0);
+    audio_sink.effects.set_volume(1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beatmap::{Beatmap, HitObject, HitObjectKind};
+    use crate::editor::EditorAction;
+
+    fn after(start: Instant, seconds: f32) -> Instant {
+        start + Duration::from_secs_f32(seconds)
+    }
+
+    #[test]
+    fn an_object_plays_once_after_resting_on_it() {
+        let start = Instant::now();
+        let mut audition = HitsoundAudition::default();
+        assert_eq!(audition.update(Some(1), start), None);
+        assert_eq!(audition.update(Some(1), after(start, 0.2)), None);
+        assert_eq!(audition.update(Some(1), after(start, 0.31)), Some(1));
+        assert_eq!(audition.update(Some(1), after(start, 2.0)), None);
+
+        // Leaving and coming back plays it again
+        assert_eq!(audition.update(None, after(start, 2.1)), None);
+        assert_eq!(audition.update(Some(1), after(start, 2.2)), None);
+        assert_eq!(audition.update(Some(1), after(start, 2.55)), Some(1));
+    }
+
+    #[test]
+    fn sweeping_across_a_stream_stays_quiet() {
+        let start = Instant::now();
+        let mut audition = HitsoundAudition::default();
+        // A new object every 50 ms never rests long enough to play
+        for step in 0..40 {
+            let id = step as HitObjectId;
+            assert_eq!(
+                audition.update(Some(id), after(start, step as f32 * 0.05)),
+                None
+            );
+        }
+
+        // Resting on each object in turn plays each of them once
+        let mut audition = HitsoundAudition::default();
+        let mut played = Vec::new();
+        for step in 0..60 {
+            let id = step / 10;
+            played.extend(audition.update(Some(id), after(start, step as f32 * 0.05)));
+        }
+        assert_eq!(played, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn auditioning_leaves_the_map_and_the_undo_stack_alone() {
+        let mut beatmap = Beatmap::default();
+        beatmap.hit_objects.push(HitObject {
+            id: 7,
+            time: 1.0,
+            position: Vec2::ZERO,
+            kind: HitObjectKind::Circle,
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Clap,
+            sample_set: None,
+        });
+        let before = serde_json::to_string(&beatmap).unwrap();
+        let mut assets = BeatmapAssets::default();
+        assets.beatmaps.insert("map".to_string(), beatmap);
+        assets.current_beatmap = Some("map".to_string());
+
+        let mut editor_state = EditorState {
+            playfield_hover: Some(7),
+            ..Default::default()
+        };
+        editor_state.record_action(EditorAction::AddObject {
+            object: assets.current().unwrap().hit_objects[0].clone(),
+        });
+        let mut world = World::new();
+        world.insert_resource(editor_state);
+        world.insert_resource(EditorUIState::default());
+        world.insert_resource(EditorAudio::default());
+        world.insert_resource(assets);
+        world.insert_resource(GameAudioSink {
+            sink: Sink::new_idle().0,
+            effects: Sink::new_idle().0,
+        });
+        let audition = world.register_system(audition_hitsounds);
+
+        world.run_system(audition).unwrap();
+        std::thread::sleep(Duration::from_secs_f32(AUDITION_HOVER_SECS + 0.05));
+        world.run_system(audition).unwrap();
+
+        let status = world.resource::<EditorUIState>().status_message.clone();
+        assert!(status.is_some_and(|(message, _)| message.contains("Clap")));
+        assert_eq!(world.resource::<GameAudioSink>().effects.len(), 1);
+        let assets = world.resource::<BeatmapAssets>();
+        assert_eq!(
+            serde_json::to_string(assets.current().unwrap()).unwrap(),
+            before
+        );
+        let editor_state = world.resource::<EditorState>();
+        assert_eq!(editor_state.undo_stack.len(), 1);
+        assert!(editor_state.redo_stack.is_empty());
+    }
+}
@@ -0,0 +1,522 @@
+// src/editor_bench.rs
+
+use crate::beatmap::{
+    Beatmap, BeatmapAssets, EditorTool, HitObject, HitObjectId, HitObjectKind, Hitsound,
+    TimingPoint,
+};
+use crate::editor::{EditorState, TimelineGrab};
+use crate::AppState;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+/// Objects in the synthetic benchmark map
+pub const BENCH_OBJECTS: usize = 10_000;
+
+/// Objects box selected, moved and restored by the script
+pub const BENCH_SELECTION: usize = 2_000;
+
+/// 95th percentile frame time the benchmark must stay under (milliseconds)
+pub const FRAME_BUDGET_MS: f64 = 8.0;
+
+/// Tempo of the synthetic map; objects sit on quarter beats
+const BENCH_BPM: f64 = 180.0;
+
+/// Frames a sweep (scroll, zoom) is spread over
+const SWEEP_FRAMES: u32 = 600;
+
+/// Frames a discrete step lasts, leaving the editor time to settle after it
+const SETTLE_FRAMES: u32 = 30;
+
+/// Timeline zoom reached halfway through the zoom sweep, relative to the start
+const ZOOM_RANGE: f32 = 8.0;
+
+/// Path the synthetic map is registered under; it is never saved
+const BENCH_PATH: &str = "editor_bench.json";
+
+/// Share of frames in the placement step that add an object; the rest delete one
+const PLACE_CHANCE: f64 = 0.6;
+
+/// Scripted editor operations, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchStep {
+    /// Let the editor build its UI around the map
+    Open,
+    /// Scroll the timeline and playhead from the start of the map to the end
+    Scroll,
+    /// Select a run of objects, as a box select over the timeline would
+    BoxSelect,
+    /// Drag the selection a beat later on the timeline
+    Move,
+    Undo,
+    /// Zoom the timeline in and back out
+    Zoom,
+    /// Sweep the playhead as playback would, adding an object at it or deleting a
+    /// random one every frame
+    Place,
+}
+
+impl BenchStep {
+    const SCRIPT: [BenchStep; 7] = [
+        BenchStep::Open,
+        BenchStep::Scroll,
+        BenchStep::BoxSelect,
+        BenchStep::Move,
+        BenchStep::Undo,
+        BenchStep::Zoom,
+        BenchStep::Place,
+    ];
+
+    fn frames(self) -> u32 {
+        match self {
+            BenchStep::Scroll | BenchStep::Zoom | BenchStep::Place => SWEEP_FRAMES,
+            _ => SETTLE_FRAMES,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BenchStep::Open => "open",
+            BenchStep::Scroll => "scroll",
+            BenchStep::BoxSelect => "box select",
+            BenchStep::Move => "move",
+            BenchStep::Undo => "undo",
+            BenchStep::Zoom => "zoom",
+            BenchStep::Place => "place",
+        }
+    }
+}
+
+/// A map of `count` objects on quarter beats: mostly circles in a spiral, with a
+/// short slider every tenth object and a spinner every five hundredth
+pub fn synthetic_beatmap(count: usize) -> Beatmap {
+    let mut beatmap = Beatmap::new(
+        "Editor Benchmark".to_string(),
+        "yum-osu".to_string(),
+        String::new(),
+    );
+    beatmap.timing_points = vec![TimingPoint {
+        bpm: BENCH_BPM,
+        ..Default::default()
+    }];
+
+    let step = 60.0 / BENCH_BPM / 4.0;
+    beatmap.hit_objects = (0..count)
+        .map(|index| {
+            let time = index as f64 * step;
+            let angle = index as f32 * 0.7;
+            let distance = 60.0 + (index % 7) as f32 * 30.0;
+            let position = Vec2::new(angle.cos(), angle.sin()) * distance;
+            let kind = if index % 500 == 499 {
+                HitObjectKind::Spinner {
+                    end_time: time + step * 0.9,
+                }
+            } else if index % 10 == 5 {
+                HitObjectKind::Slider {
+                    control_points: vec![position, position + Vec2::new(40.0, 30.0)],
+                    repeats: 0,
+                    pixel_length: 50.0,
+                    velocity: 1.0,
+                }
+            } else {
+                HitObjectKind::Circle
+            };
+            HitObject {
+                id: index as HitObjectId + 1,
+                time,
+                position,
+                kind,
+                new_combo: index % 8 == 0,
+                combo_index: (index % 8) as u32 + 1,
+                hitsound: Hitsound::Normal,
+                sample_set: None,
+            }
+        })
+        .collect();
+    beatmap
+}
+
+/// Frame times of one step
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepStats {
+    pub name: &'static str,
+    pub frames: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl StepStats {
+    fn from_times(name: &'static str, times: &[f64]) -> Self {
+        let mut sorted = times.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        Self {
+            name,
+            frames: sorted.len(),
+            p50_ms: percentile(&sorted, 0.5),
+            p95_ms: percentile(&sorted, 0.95),
+            max_ms: sorted.last().copied().unwrap_or(0.0),
+        }
+    }
+
+    /// Whether the 95th percentile frame time stays within `FRAME_BUDGET_MS`
+    pub fn within_budget(&self) -> bool {
+        self.p95_ms <= FRAME_BUDGET_MS
+    }
+}
+
+/// Value at a fraction of the way through sorted samples; 0 when there are none
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+    sorted[index]
+}
+
+/// Summary printed when the script finishes
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub steps: Vec<StepStats>,
+    /// Every step but Open, whose frames include building the editor UI
+    pub overall: StepStats,
+    /// Edits in the placement step after which the object index didn't list the same
+    /// objects as a fresh sort of the map
+    pub index_mismatches: usize,
+}
+
+impl BenchReport {
+    fn from_samples(samples: &[(BenchStep, f64)], index_mismatches: usize) -> Self {
+        let steps = BenchStep::SCRIPT
+            .iter()
+            .map(|step| {
+                let times: Vec<f64> = samples
+                    .iter()
+                    .filter(|(sampled, _)| sampled == step)
+                    .map(|(_, time)| *time)
+                    .collect();
+                StepStats::from_times(step.name(), &times)
+            })
+            .collect();
+        let measured: Vec<f64> = samples
+            .iter()
+            .filter(|(step, _)| *step != BenchStep::Open)
+            .map(|(_, time)| *time)
+            .collect();
+        Self {
+            steps,
+            overall: StepStats::from_times("overall", &measured),
+            index_mismatches,
+        }
+    }
+
+    /// Steps whose frames count against the budget; Open is left out as it builds
+    /// the editor UI
+    fn measured_steps(&self) -> impl Iterator<Item = &StepStats> {
+        self.steps
+            .iter()
+            .filter(|stats| stats.name != BenchStep::Open.name())
+    }
+
+    /// Whether the p95 frame time held the budget overall and in every measured step,
+    /// and the object index stayed correct
+    pub fn passed(&self) -> bool {
+        self.overall.within_budget()
+            && self.measured_steps().all(StepStats::within_budget)
+            && self.index_mismatches == 0
+    }
+
+    /// Table of frame times per step and the verdict
+    pub fn summary(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "Editor benchmark: {} objects, {} selected",
+                BENCH_OBJECTS, BENCH_SELECTION
+            ),
+            format!(
+                "{:<12} {:>6} {:>9} {:>9} {:>9} {:>7}",
+                "step", "frames", "p50 ms", "p95 ms", "max ms", "budget"
+            ),
+        ];
+        for stats in self.steps.iter().chain(std::iter::once(&self.overall)) {
+            let verdict = if stats.name == BenchStep::Open.name() {
+                "-"
+            } else if stats.within_budget() {
+                "ok"
+            } else {
+                "OVER"
+            };
+            lines.push(format!(
+                "{:<12} {:>6} {:>9.2} {:>9.2} {:>9.2} {:>7}",
+                stats.name, stats.frames, stats.p50_ms, stats.p95_ms, stats.max_ms, verdict
+            ));
+        }
+        lines.push(format!(
+            "Object index mismatches: {}",
+            self.index_mismatches
+        ));
+        lines.push(format!(
+            "{}: p95 {:.2} ms against a {:.1} ms budget",
+            if self.passed() { "PASS" } else { "FAIL" },
+            self.overall.p95_ms,
+            FRAME_BUDGET_MS
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Running editor benchmark, present only when started with `--editor-bench`
+#[derive(Resource, Default)]
+pub struct EditorBench {
+    /// Index into `BenchStep::SCRIPT`
+    step: usize,
+    /// Frames spent in the current step
+    frame: u32,
+    last_frame: Option<Instant>,
+    samples: Vec<(BenchStep, f64)>,
+    /// Ids of the box selection, picked when the step runs
+    selection: Vec<HitObjectId>,
+    index_mismatches: usize,
+}
+
+/// Register the synthetic map and open it in the editor
+pub fn start_editor_bench(
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    beatmap_assets.add(BENCH_PATH.to_string(), synthetic_beatmap(BENCH_OBJECTS));
+    beatmap_assets.set_current(Some(BENCH_PATH.to_string()));
+    next_state.set(AppState::BeatmapEditor);
+}
+
+/// Time the last frame and drive the script one frame further. Prints the report
+/// and exits when the script is done, with a failing exit code over budget
+pub fn run_editor_bench(
+    mut bench: ResMut<EditorBench>,
+    mut editor_state: ResMut<EditorState>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    windows: Query<&Window>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let now = Instant::now();
+    let step = BenchStep::SCRIPT[bench.step];
+    if let Some(last_frame) = bench.last_frame {
+        let elapsed = now.duration_since(last_frame).as_secs_f64() * 1000.0;
+        bench.samples.push((step, elapsed));
+    }
+    bench.last_frame = Some(now);
+
+    // Only steps that edit the map take it mutably, so the others don't mark it changed
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+    let frame = bench.frame;
+    let progress = frame as f32 / (step.frames() - 1) as f32;
+    let screen_w = windows.get_single().map_or(1280.0, |window| window.width());
+
+    match step {
+        BenchStep::Open => {}
+        BenchStep::Scroll => {
+            let map_end = beatmap.hit_objects.last().map_or(0.0, |obj| obj.time);
+            let time = map_end * progress as f64;
+            editor_state.seek_to(time);
+            center_timeline_on(&mut editor_state, time, screen_w);
+        }
+        BenchStep::BoxSelect if frame == 0 => {
+            let start = beatmap.hit_objects.len().saturating_sub(BENCH_SELECTION) / 2;
+            bench.selection = beatmap.hit_objects[start..]
+                .iter()
+                .take(BENCH_SELECTION)
+                .map(|obj| obj.id)
+                .collect();
+            editor_state.selected_objects = bench.selection.clone();
+            if let Some(first) = beatmap.hit_objects.get(start) {
+                let time = first.time;
+                editor_state.seek_to(time);
+                center_timeline_on(&mut editor_state, time, screen_w);
+            }
+        }
+        BenchStep::Move => {
+            // Drag the first selected object a beat later over the step's frames
+            let Some(&first) = bench.selection.first() else {
+                return;
+            };
+            let Some(grab_time) = beatmap
+                .hit_objects
+                .iter()
+                .find(|obj| obj.id == first)
+                .map(|obj| obj.time)
+            else {
+                return;
+            };
+            let beat = beatmap.get_beat_length_at(grab_time);
+            if frame == 0 {
+                editor_state.begin_timeline_drag(
+                    beatmap,
+                    TimelineGrab::Object(first),
+                    grab_time,
+                    false,
+                );
+            } else if frame + 1 < step.frames() {
+                editor_state.update_timeline_drag(beatmap, grab_time + beat * progress as f64);
+            } else if let Some(beatmap) = beatmap_assets.current_mut() {
+                if let Some(action) = editor_state.finish_timeline_drag(beatmap) {
+                    editor_state.record_action(action);
+                }
+            }
+        }
+        BenchStep::Undo if frame == 0 => {
+            if let Some(beatmap) = beatmap_assets.current_mut() {
+                editor_state.undo(beatmap);
+            }
+        }
+        BenchStep::Zoom => {
+            // In for the first half, back out for the second
+            let rate = ZOOM_RANGE.powf(2.0 / step.frames() as f32);
+            if frame < step.frames() / 2 {
+                editor_state.timeline_zoom *= rate;
+            } else {
+                editor_state.timeline_zoom /= rate;
+            }
+            let time = editor_state.current_time;
+            center_timeline_on(&mut editor_state, time, screen_w);
+        }
+        BenchStep::Place => {
+            let map_end = beatmap.hit_objects.last().map_or(0.0, |obj| obj.time);
+            let time = map_end * progress as f64;
+            editor_state.seek_to(time);
+            center_timeline_on(&mut editor_state, time, screen_w);
+
+            let Some((beatmap, index)) = beatmap_assets.current_indexed_mut() else {
+                return;
+            };
+            let mut rng = StdRng::seed_from_u64(frame as u64);
+            let action = if rng.gen_bool(PLACE_CHANCE) {
+                let tool = editor_state.current_tool;
+                editor_state.current_tool = EditorTool::Circle;
+                let position =
+                    Vec2::new(rng.gen_range(-200.0..200.0), rng.gen_range(-150.0..150.0));
+                let action = editor_state.add_object(beatmap, index, position);
+                editor_state.current_tool = tool;
+                action
+            } else if beatmap.hit_objects.is_empty() {
+                None
+            } else {
+                let target = rng.gen_range(0..beatmap.hit_objects.len());
+                editor_state.selected_objects = vec![beatmap.hit_objects[target].id];
+                editor_state.delete_selected(beatmap, index)
+            };
+            if let Some(action) = action {
+                editor_state.record_action(action);
+            }
+            // The objects must still be sorted by time and the index list them in order,
+            // just as a fresh sort and rebuild would
+            let objects = &beatmap.hit_objects;
+            let sorted = objects.windows(2).all(|pair| pair[0].time <= pair[1].time);
+            if !sorted || !index.matches(objects) {
+                bench.index_mismatches += 1;
+            }
+        }
+        BenchStep::BoxSelect | BenchStep::Undo => {}
+    }
+
+    bench.frame += 1;
+    if bench.frame < step.frames() {
+        return;
+    }
+    bench.frame = 0;
+    bench.step += 1;
+    if bench.step < BenchStep::SCRIPT.len() {
+        return;
+    }
+
+    let report = BenchReport::from_samples(&bench.samples, bench.index_mismatches);
+    println!("{}", report.summary());
+    app_exit.send(if report.passed() {
+        AppExit::Success
+    } else {
+        AppExit::from_code(1)
+    });
+}
+
+/// Scroll the timeline so a time sits under the playhead in the middle of the screen
+fn center_timeline_on(editor_state: &mut EditorState, time: f64, screen_w: f32) {
+    editor_state.timeline_scroll = screen_w / 2.0 - time as f32 * editor_state.timeline_zoom;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Samples giving every step `frames` frames of `ms`
+    fn samples(ms: f64, frames: usize) -> Vec<(BenchStep, f64)> {
+        BenchStep::SCRIPT
+            .iter()
+            .flat_map(|&step| std::iter::repeat_n((step, ms), frames))
+            .collect()
+    }
+
+    #[test]
+    fn percentiles_pick_from_sorted_times() {
+        let times: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        let stats = StepStats::from_times("scroll", &times);
+        assert_eq!(stats.frames, 100);
+        assert_eq!(stats.p50_ms, 51.0);
+        assert_eq!(stats.p95_ms, 95.0);
+        assert_eq!(stats.max_ms, 100.0);
+        assert_eq!(StepStats::from_times("empty", &[]).p95_ms, 0.0);
+    }
+
+    #[test]
+    fn p95_is_checked_against_the_budget() {
+        let fast = BenchReport::from_samples(&samples(FRAME_BUDGET_MS, 20), 0);
+        assert!(fast.passed());
+        assert!(fast.summary().starts_with("Editor benchmark"));
+        assert!(fast.summary().ends_with(&format!(
+            "PASS: p95 {:.2} ms against a {:.1} ms budget",
+            FRAME_BUDGET_MS, FRAME_BUDGET_MS
+        )));
+
+        let slow = BenchReport::from_samples(&samples(FRAME_BUDGET_MS + 0.5, 20), 0);
+        assert!(!slow.overall.within_budget());
+        assert!(!slow.passed());
+        assert!(slow.summary().contains("FAIL"));
+    }
+
+    #[test]
+    fn one_step_over_budget_fails_the_run() {
+        // A tenth of the zoom frames are slow, a small share of the whole run
+        let mut samples = samples(1.0, 100);
+        for sample in samples
+            .iter_mut()
+            .filter(|(step, _)| *step == BenchStep::Zoom)
+            .take(10)
+        {
+            sample.1 = FRAME_BUDGET_MS * 2.0;
+        }
+        let report = BenchReport::from_samples(&samples, 0);
+        assert!(report.overall.within_budget());
+        assert!(!report.passed());
+        let summary = report.summary();
+        let zoom_row = summary
+            .lines()
+            .find(|line| line.starts_with("zoom"))
+            .unwrap();
+        assert!(zoom_row.ends_with("OVER"));
+    }
+
+    #[test]
+    fn opening_is_left_out_of_the_budget() {
+        let mut samples = samples(1.0, 20);
+        for sample in samples
+            .iter_mut()
+            .filter(|(step, _)| *step == BenchStep::Open)
+        {
+            sample.1 = 500.0;
+        }
+        let report = BenchReport::from_samples(&samples, 0);
+        assert!(report.passed());
+        assert!(!BenchReport::from_samples(&samples, 1).passed());
+    }
+}
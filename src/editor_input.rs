@@ -1,21 +1,30 @@
 // src/editor_input.rs
 
-use crate::beatmap::{BeatDivisor, BeatmapAssets, EditorTool};
-use crate::constants::*;
+use crate::audio::song_clip;
+use crate::beatmap::{BeatDivisor, Beatmap, BeatmapAssets, EditorTool};
+use crate::config::GameConfig;
 use crate::editor::{
-    screen_to_grid, snap_to_grid, EditorAction, EditorLeftTab, EditorRightTab, EditorState,
-    EditorUIState,
+    format_time, load_beatmap, save_beatmap, snap_to_grid, CopierClick, EditorAction,
+    EditorLeftTab, EditorRightTab, EditorState, EditorUIState, HintToggle, KiaiToggle,
+    MetadataField, PropagationPlan, PREVIEW_CLIP_SECONDS,
 };
+use crate::editor_audio::EditorAudio;
 use crate::editor_ui::*;
+use crate::structs::GameAudioSink;
+use crate::text_input::{pressed_keys, Clipboard, FocusId, Modifiers, TextFocus};
+use bevy::input::keyboard::KeyboardInput;
 use bevy::prelude::*;
 use bevy::window::Window;
+use rodio::Sink;
 
 /// Handle editor input
+#[allow(clippy::too_many_arguments)]
 pub fn handle_editor_input(
     mut editor_state: ResMut<EditorState>,
     mut editor_ui: ResMut<EditorUIState>,
     mut beatmap_assets: ResMut<BeatmapAssets>,
     mut next_state: ResMut<NextState<crate::AppState>>,
+    mut text_focus: ResMut<TextFocus>,
     keyboard: Res<ButtonInput<KeyCode>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window>,
@@ -27,13 +36,101 @@ pub fn handle_editor_input(
         editor_state.update_current_time();
     }
 
-    // ESC to exit editor
-    if keyboard.just_pressed(KeyCode::Escape) {
-        // Save current beatmap before exiting
-        if let Some(path) = &editor_state.current_beatmap_path {
-            if let Err(e) = beatmap_assets.save(path) {
-                eprintln!("Failed to save beatmap: {}", e);
+    // A focused panel field takes the keyboard (see handle_property_input,
+    // handle_metadata_input, handle_practice_hint_input and handle_comment_input)
+    text_focus.set(
+        FocusId::EditorProperty,
+        editor_state.property_edit.is_some(),
+    );
+    text_focus.set(
+        FocusId::EditorMetadata,
+        editor_state.metadata_edit.is_some(),
+    );
+    text_focus.set(
+        FocusId::EditorPracticeHint,
+        editor_state.hint_edit.is_some(),
+    );
+    text_focus.set(FocusId::EditorComment, editor_state.comment_draft.is_some());
+    if text_focus.any() {
+        editor_ui.update_status();
+        return;
+    }
+
+    // Pending resnap: Enter applies, ESC cancels
+    if editor_state.pending_resnap.is_some() {
+        if keyboard.just_pressed(KeyCode::Enter) {
+            if let (Some(plan), Some(beatmap)) =
+                (editor_state.pending_resnap.take(), beatmap_assets.current_mut())
+            {
+                let moved = plan.changes.len();
+                let ambiguous = plan.ambiguous.len();
+                if let Some(action) = editor_state.apply_resnap(beatmap, plan) {
+                    editor_state.record_action(action);
+                }
+                editor_ui.show_status(
+                    format!(
+                        "Resnapped {} objects, {} ambiguous left selected",
+                        moved, ambiguous
+                    ),
+                    3,
+                );
             }
+            return;
+        }
+        if keyboard.just_pressed(KeyCode::Escape) {
+            editor_state.pending_resnap = None;
+            editor_ui.show_status("Resnap cancelled".to_string(), 3);
+            return;
+        }
+    }
+
+    // Pending metadata propagation: Enter writes the files, ESC cancels
+    if editor_state.pending_propagation.is_some() {
+        if keyboard.just_pressed(KeyCode::Enter) {
+            if let Some(plan) = editor_state.pending_propagation.take() {
+                let (written, errors) = plan.apply();
+                // Keep the loaded copies in step with the files
+                for path in &written {
+                    if let Some(other) = beatmap_assets.get_mut(path) {
+                        plan.shared.apply_to(other);
+                    }
+                }
+                for (path, error) in &errors {
+                    error!("Failed to propagate metadata to {}: {}", path, error);
+                }
+                let mut status = format!("Propagated metadata to {} files", written.len());
+                if !errors.is_empty() {
+                    status.push_str(&format!(", {} failed (see log)", errors.len()));
+                }
+                editor_ui.show_status(status, 3);
+            }
+            return;
+        }
+        if keyboard.just_pressed(KeyCode::Escape) {
+            editor_state.pending_propagation = None;
+            editor_ui.show_status("Propagation cancelled".to_string(), 3);
+            return;
+        }
+    }
+
+    // ESC puts the hitsound copier down before it exits the editor
+    if keyboard.just_pressed(KeyCode::Escape)
+        && editor_state.current_tool == EditorTool::HitsoundCopier
+    {
+        editor_state.drop_copier();
+        editor_ui.show_status("Hitsound copier dropped".to_string(), 3);
+        return;
+    }
+
+    // ESC to exit editor, saving first. A map that fails validation isn't written;
+    // a second ESC leaves it unsaved
+    if keyboard.just_pressed(KeyCode::Escape) {
+        if !editor_state.pending_exit
+            && !save_edited_beatmap(&mut editor_state, &mut editor_ui, &beatmap_assets)
+            && editor_state.current_beatmap_path.is_some()
+        {
+            editor_state.pending_exit = true;
+            return;
         }
         next_state.set(crate::AppState::Menu);
         return;
@@ -87,49 +184,219 @@ pub fn handle_editor_input(
         editor_state.show_grid = !editor_state.show_grid;
     }
 
+    // Waveform toggle
+    if keyboard.just_pressed(KeyCode::KeyW) {
+        editor_state.show_waveform = !editor_state.show_waveform;
+    }
+
     // New combo toggle
     if keyboard.just_pressed(KeyCode::KeyQ) {
         editor_state.new_combo_mode = !editor_state.new_combo_mode;
     }
 
+    // Slider length resnap toggle
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        editor_state.resnap_slider_duration = !editor_state.resnap_slider_duration;
+    }
+
+    // Slider velocity for new inherited points
+    if keyboard.just_pressed(KeyCode::BracketLeft) || keyboard.just_pressed(KeyCode::BracketRight) {
+        let delta = if keyboard.just_pressed(KeyCode::BracketRight) { 0.1 } else { -0.1 };
+        editor_state.adjust_slider_velocity_input(delta);
+        editor_ui.show_status(
+            format!("SV {:.2}x | I to add at playhead", editor_state.slider_velocity_input),
+            3,
+        );
+    }
+
+    // Add an inherited (SV) timing point at the playhead
+    if keyboard.just_pressed(KeyCode::KeyI) {
+        if let Some(beatmap) = beatmap_assets.current_mut() {
+            if let Some(action) = editor_state.add_slider_velocity_point(beatmap) {
+                editor_state.record_action(action);
+            }
+            editor_ui.show_status(
+                format!(
+                    "Added SV {:.2}x at {:.3}s",
+                    editor_state.slider_velocity_input, editor_state.current_time
+                ),
+                3,
+            );
+        }
+    }
+
+    // Resnap selection (or everything) to the current divisor, previewed first
+    if (keyboard.pressed(KeyCode::ControlLeft) || keyboard.pressed(KeyCode::ControlRight))
+        && (keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight))
+        && keyboard.just_pressed(KeyCode::KeyR)
+    {
+        if let Some(beatmap) = beatmap_assets.current() {
+            let plan = editor_state.plan_resnap(beatmap, editor_state.beat_divisor.value());
+            editor_ui.show_status(
+                format!("{} | ENTER to apply, ESC to cancel", plan.summary()),
+                30,
+            );
+            editor_state.pending_resnap = Some(plan);
+        }
+    }
+
     // Undo/Redo
-    if keyboard.pressed(KeyCode::ControlLeft) || keyboard.pressed(KeyCode::ControlRight) {
-        if keyboard.just_pressed(KeyCode::KeyZ) {
-            if keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight) {
-                // Redo
-                if let Some(beatmap) = beatmap_assets.current_mut() {
-                    editor_state.redo(beatmap);
-                }
-            } else {
-                // Undo
-                if let Some(beatmap) = beatmap_assets.current_mut() {
-                    editor_state.undo(beatmap);
-                }
+    if (keyboard.pressed(KeyCode::ControlLeft) || keyboard.pressed(KeyCode::ControlRight))
+        && keyboard.just_pressed(KeyCode::KeyZ)
+    {
+        if keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight) {
+            // Redo
+            if let Some(beatmap) = beatmap_assets.current_mut() {
+                editor_state.redo(beatmap);
+            }
+        } else {
+            // Undo
+            if let Some(beatmap) = beatmap_assets.current_mut() {
+                editor_state.undo(beatmap);
             }
         }
     }
 
-    // Copy/Paste
+    // Copy/Paste (with Shift: rhythm only)
+    let shift_held = keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight);
     if keyboard.pressed(KeyCode::ControlLeft) || keyboard.pressed(KeyCode::ControlRight) {
         if keyboard.just_pressed(KeyCode::KeyC) {
             if let Some(beatmap) = beatmap_assets.current() {
-                editor_state.copy_selected(beatmap);
+                if shift_held {
+                    let count = editor_state.copy_rhythm(beatmap);
+                    editor_ui.show_status(format!("Copied rhythm of {} objects", count), 3);
+                } else {
+                    editor_state.copy_selected(beatmap);
+                }
             }
         }
         if keyboard.just_pressed(KeyCode::KeyV) {
             if let Some(beatmap) = beatmap_assets.current_mut() {
-                let actions = editor_state.paste(beatmap);
-                for action in actions {
-                    editor_state.record_action(action);
+                if shift_held {
+                    let outcome = editor_state.paste_rhythm(beatmap);
+                    if let Some(action) = outcome.action {
+                        editor_state.record_labeled_action(
+                            action,
+                            format!("Paste rhythm onto {} objects", outcome.applied),
+                        );
+                    }
+                    editor_ui.show_status(
+                        format!(
+                            "Re-timed {} objects, skipped {} (overlap or no rhythm left)",
+                            outcome.applied, outcome.skipped
+                        ),
+                        3,
+                    );
+                } else {
+                    let actions = editor_state.paste(beatmap);
+                    for action in actions {
+                        editor_state.record_action(action);
+                    }
                 }
             }
         }
     }
 
+    // Hitsound copier: H picks it up or puts it down
+    let flip_x_pressed = keyboard.just_pressed(KeyCode::KeyH) && shift_held;
+    if keyboard.just_pressed(KeyCode::KeyH) && !shift_held {
+        if editor_state.current_tool == EditorTool::HitsoundCopier {
+            editor_state.drop_copier();
+            editor_ui.show_status("Hitsound copier dropped".to_string(), 3);
+        } else {
+            editor_state.set_tool(EditorTool::HitsoundCopier);
+            editor_ui.show_status(
+                "Hitsound copier: click an object to pick it up, ESC to drop".to_string(),
+                3,
+            );
+        }
+    }
+
+    // Mirror flip toggles and mirror-to-playhead
+    if flip_x_pressed {
+        editor_state.mirror_flip_x = !editor_state.mirror_flip_x;
+    }
+    if keyboard.just_pressed(KeyCode::KeyJ) {
+        editor_state.mirror_flip_y = !editor_state.mirror_flip_y;
+    }
+    if flip_x_pressed || keyboard.just_pressed(KeyCode::KeyJ) {
+        editor_ui.show_status(
+            format!(
+                "Mirror: horizontal {} | vertical {}",
+                if editor_state.mirror_flip_x { "on" } else { "off" },
+                if editor_state.mirror_flip_y { "on" } else { "off" }
+            ),
+            3,
+        );
+    }
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        if let Some(beatmap) = beatmap_assets.current_mut() {
+            let outcome = editor_state.mirror_selection(beatmap);
+            if let Some(action) = outcome.action {
+                editor_state.record_labeled_action(
+                    action,
+                    format!("Mirror {} objects", outcome.applied),
+                );
+            }
+            editor_ui.show_status(
+                format!(
+                    "Mirrored {} objects to the playhead, skipped {} overlapping",
+                    outcome.applied, outcome.skipped
+                ),
+                3,
+            );
+        }
+    }
+
+    // Kiai sections: first press marks the start, second the end; inside a section removes it
+    if keyboard.just_pressed(KeyCode::KeyK) {
+        if let Some(beatmap) = beatmap_assets.current_mut() {
+            let message = match editor_state.toggle_kiai(beatmap) {
+                KiaiToggle::Started(time) => {
+                    format!("Kiai start at {:.3}s, press K again at the end", time)
+                }
+                KiaiToggle::Added(section, action) => {
+                    editor_state.record_action(action);
+                    format!("Added kiai {:.3}s - {:.3}s", section.start, section.end)
+                }
+                KiaiToggle::Removed(section, action) => {
+                    editor_state.record_action(action);
+                    format!("Removed kiai {:.3}s - {:.3}s", section.start, section.end)
+                }
+                KiaiToggle::Rejected(reason) => reason,
+            };
+            editor_ui.show_status(message, 3);
+        }
+    }
+
+    // Practice hints toggle like kiai sections
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        if let Some(beatmap) = beatmap_assets.current_mut() {
+            let message = match editor_state.toggle_practice_hint(beatmap) {
+                HintToggle::Started(time) => {
+                    format!(
+                        "Practice hint start at {:.3}s, press P again at the end",
+                        time
+                    )
+                }
+                HintToggle::Added(hint, action) => {
+                    editor_state.record_action(action);
+                    format!("Added practice hint {}", hint.summary())
+                }
+                HintToggle::Removed(hint, action) => {
+                    editor_state.record_action(action);
+                    format!("Removed practice hint {}", hint.summary())
+                }
+                HintToggle::Rejected(reason) => reason,
+            };
+            editor_ui.show_status(message, 3);
+        }
+    }
+
     // Delete selected
     if keyboard.just_pressed(KeyCode::Delete) {
-        if let Some(beatmap) = beatmap_assets.current_mut() {
-            if let Some(action) = editor_state.delete_selected(beatmap) {
+        if let Some((beatmap, index)) = beatmap_assets.current_indexed_mut() {
+            if let Some(action) = editor_state.delete_selected(beatmap, index) {
                 editor_state.record_action(action);
             }
         }
@@ -180,46 +447,71 @@ pub fn handle_editor_input(
 
         let in_playfield = !in_toolbar && !in_timeline && !in_left_panel && !in_right_panel;
 
+        // Object under the cursor, auditioned by audition_hitsounds
+        let hover = match (in_playfield, beatmap_assets.current()) {
+            (true, Some(beatmap)) => beatmap_assets.object_index().and_then(|index| {
+                editor_state.object_under_cursor(
+                    beatmap,
+                    &index,
+                    Vec2::new(world_x, world_y),
+                    25.0 * editor_state.playfield_zoom,
+                )
+            }),
+            _ => None,
+        };
+        if editor_state.playfield_hover != hover {
+            editor_state.playfield_hover = hover;
+        }
+
         // Handle left click
         if mouse_input.just_pressed(MouseButton::Left) {
             if in_playfield {
                 handle_playfield_click(
                     &mut editor_state,
+                    &mut editor_ui,
                     beatmap_assets.as_mut(),
                     world_x,
                     world_y,
+                    shift_held,
                 );
             } else if in_timeline {
                 handle_timeline_click(
                     &mut editor_state,
-                    &editor_ui,
+                    &mut editor_ui,
                     beatmap_assets.current(),
                     screen_w,
+                    screen_h,
                     world_x,
+                    world_y,
                 );
             }
         }
 
         // Handle right click (context menu / cancel)
-        if mouse_input.just_pressed(MouseButton::Right) {
-            if in_playfield && editor_state.current_tool == EditorTool::Select {
-                editor_state.deselect_all();
-            }
+        if mouse_input.just_pressed(MouseButton::Right)
+            && in_playfield
+            && editor_state.current_tool == EditorTool::Select
+        {
+            editor_state.deselect_all();
         }
     }
 
-    // Update UI state
-    editor_ui.update_status(3);
+    // Update UI state (keep the resnap and propagation previews up until answered)
+    if editor_state.pending_resnap.is_none() && editor_state.pending_propagation.is_none() {
+        editor_ui.update_status();
+    }
 }
 
 /// Handle clicking on the playfield
 fn handle_playfield_click(
     editor_state: &mut EditorState,
+    editor_ui: &mut EditorUIState,
     beatmap_assets: &mut BeatmapAssets,
     world_x: f32,
     world_y: f32,
+    shift_held: bool,
 ) {
-    if let Some(beatmap) = beatmap_assets.current_mut() {
+    if let Some((beatmap, index)) = beatmap_assets.current_indexed_mut() {
         match editor_state.current_tool {
             EditorTool::Select => {
                 // Try to select an object
@@ -246,7 +538,7 @@ fn handle_playfield_click(
                     );
                 }
 
-                if let Some(action) = editor_state.add_object(beatmap, position) {
+                if let Some(action) = editor_state.add_object(beatmap, index, position) {
                     editor_state.record_action(action);
                 }
             }
@@ -257,27 +549,89 @@ fn handle_playfield_click(
 
                 if let Some(id) = editor_state.get_object_at_position(beatmap, click_pos, tolerance)
                 {
-                    if let Some(obj) = beatmap.remove_hit_object(id) {
+                    if let Some(obj) = beatmap.remove_indexed(index, id) {
                         editor_state
                             .record_action(EditorAction::DeleteObjects { objects: vec![obj] });
                     }
                     editor_state.selected_objects.retain(|&x| x != id);
                 }
             }
+            EditorTool::HitsoundCopier => {
+                let click_pos = Vec2::new(world_x, world_y);
+                let tolerance = 25.0 * editor_state.playfield_zoom;
+
+                if let Some(id) = editor_state.get_object_at_position(beatmap, click_pos, tolerance)
+                {
+                    copier_click(editor_state, editor_ui, beatmap, id, shift_held);
+                }
+            }
         }
     }
 }
 
-/// Handle clicking on the timeline
+/// Hitsound copier click on an object, in the playfield or on the timeline. Each
+/// stamp is its own undo step
+fn copier_click(
+    editor_state: &mut EditorState,
+    editor_ui: &mut EditorUIState,
+    beatmap: &mut Beatmap,
+    id: crate::beatmap::HitObjectId,
+    pick_up: bool,
+) {
+    let message = match editor_state.copier_click(beatmap, id, pick_up) {
+        Some(CopierClick::PickedUp(pickup)) => format!(
+            "Picked up {} | click objects to stamp, Shift+click to pick up another",
+            pickup.summary()
+        ),
+        Some(CopierClick::Stamped(action)) => {
+            let label = match &action {
+                EditorAction::ReplaceObjects { new_objects, .. } => new_objects
+                    .first()
+                    .map(|obj| format!("Stamp hitsound @ {}", format_time(obj.time)))
+                    .unwrap_or_else(|| action.label()),
+                _ => action.label(),
+            };
+            editor_state.record_labeled_action(action, label.clone());
+            label
+        }
+        Some(CopierClick::Unchanged) => "Object already has that hitsound".to_string(),
+        None => return,
+    };
+    editor_ui.show_status(message, 3);
+}
+
+/// Handle clicking on empty timeline space or a comment marker; presses on objects are
+/// left to `handle_timeline_input`
 fn handle_timeline_click(
     editor_state: &mut EditorState,
-    editor_ui: &EditorUIState,
+    editor_ui: &mut EditorUIState,
     beatmap: Option<&crate::beatmap::Beatmap>,
     screen_w: f32,
+    screen_h: f32,
     world_x: f32,
+    world_y: f32,
 ) {
     // Convert screen x to timeline position
     let timeline_x = world_x + screen_w / 2.0;
+    let offset_y = world_y - timeline_center_y(editor_ui, screen_h);
+
+    // A comment marker jumps to its comment
+    if let Some(index) =
+        editor_state.comment_marker_at(timeline_x, offset_y, editor_ui.timeline_height)
+    {
+        editor_state.jump_to_comment(index);
+        editor_ui.left_panel_tab = EditorLeftTab::Comments;
+        show_comment(editor_state, editor_ui, index);
+        return;
+    }
+    if let Some(bm) = beatmap {
+        if editor_state
+            .timeline_object_at(bm, timeline_x, offset_y)
+            .is_some()
+        {
+            return;
+        }
+    }
     let time = crate::editor::timeline_pos_to_time(
         timeline_x,
         editor_state.timeline_zoom,
@@ -299,6 +653,7 @@ fn handle_timeline_click(
 }
 
 /// Handle editor interactions with UI elements
+#[allow(clippy::too_many_arguments)]
 pub fn handle_editor_ui_interactions(
     mut editor_state: ResMut<EditorState>,
     mut editor_ui: ResMut<EditorUIState>,
@@ -306,7 +661,6 @@ pub fn handle_editor_ui_interactions(
     playback_buttons: Query<(&Transform, &PlaybackButton), Without<Text2d>>,
     left_tabs: Query<(&Transform, &LeftPanelTab), Without<Text2d>>,
     right_tabs: Query<(&Transform, &RightPanelTab), Without<Text2d>>,
-    timeline_objects: Query<(&Transform, &TimelineObject), Without<Text2d>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window>,
 ) {
@@ -341,9 +695,7 @@ pub fn handle_editor_ui_interactions(
                 match button {
                     PlaybackButton::PlayPause => editor_state.toggle_playback(),
                     PlaybackButton::Previous => {
-                        if let Some(beatmap) = &editor_state.current_beatmap_path {
-                            // Need to access beatmap, skipping for now
-                        }
+                        // Need to access beatmap, skipping for now
                     }
                     PlaybackButton::Next => {
                         // Similar
@@ -376,27 +728,90 @@ pub fn handle_editor_ui_interactions(
                 editor_ui.right_panel_tab = tab.tab;
             }
         }
+    }
+}
 
-        // Check for timeline object clicks
-        for (transform, obj) in timeline_objects.iter() {
-            let obj_rect =
-                Rect::from_center_size(transform.translation.truncate(), Vec2::new(8.0, 20.0));
+/// Y of the timeline's center line
+fn timeline_center_y(editor_ui: &EditorUIState, screen_h: f32) -> f32 {
+    -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0
+}
 
-            if obj_rect.contains(Vec2::new(world_x, world_y))
-                && mouse_input.just_pressed(MouseButton::Left)
-            {
-                editor_state.select_object(obj.id, false);
+/// Hover, select and drag objects on the timeline. Dragging a tick or slider bar retimes
+/// the selection and dragging a slider's right end changes its duration, both recorded
+/// on release
+pub fn handle_timeline_input(
+    mut editor_state: ResMut<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window>,
+) {
+    if editor_state.timeline_drag.is_some() && !mouse_input.pressed(MouseButton::Left) {
+        if let Some(beatmap) = beatmap_assets.current_mut() {
+            if let Some(action) = editor_state.finish_timeline_drag(beatmap) {
+                editor_state.record_action(action);
             }
         }
+        editor_state.timeline_drag = None;
+        return;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+    let Some(cursor_pos) = window.cursor_position() else {
+        if editor_state.timeline_hover.is_some() {
+            editor_state.timeline_hover = None;
+        }
+        return;
+    };
+    let timeline_x = cursor_pos.x;
+    let world_y = window.height() / 2.0 - cursor_pos.y;
+    let offset_y = world_y - timeline_center_y(&editor_ui, window.height());
+    let cursor_time = crate::editor::timeline_pos_to_time(
+        timeline_x,
+        editor_state.timeline_zoom,
+        editor_state.timeline_scroll,
+    );
+
+    if editor_state.timeline_drag.is_some() {
+        editor_state.update_timeline_drag(beatmap, cursor_time);
+        return;
+    }
+
+    let grab = editor_state.timeline_object_at(beatmap, timeline_x, offset_y);
+    let hover = grab.map(|grab| grab.id());
+    if editor_state.timeline_hover != hover {
+        editor_state.timeline_hover = hover;
+    }
+    let Some(grab) = grab else {
+        return;
+    };
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let shift_held = keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight);
+    if editor_state.current_tool == EditorTool::HitsoundCopier {
+        if let Some(beatmap) = beatmap_assets.current_mut() {
+            copier_click(
+                &mut editor_state,
+                &mut editor_ui,
+                beatmap,
+                grab.id(),
+                shift_held,
+            );
+        }
+        return;
     }
+    editor_state.begin_timeline_drag(beatmap, grab, cursor_time, shift_held);
 }
 
 /// Update editor (called every frame)
-pub fn update_editor(
-    mut editor_state: ResMut<EditorState>,
-    mut editor_ui: ResMut<EditorUIState>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-) {
+pub fn update_editor(keyboard: Res<ButtonInput<KeyCode>>) {
     // Auto-save indicator or periodic tasks could go here
 
     // Check for shift key for multi-select
@@ -404,24 +819,720 @@ pub fn update_editor(
         keyboard.pressed(KeyCode::ShiftLeft) || keyboard.pressed(KeyCode::ShiftRight);
 }
 
-/// Save beatmap shortcut
-pub fn handle_save_shortcut(
-    editor_state: Res<EditorState>,
+/// Rebuild the object index once after an edit that went around it, without marking
+/// the beatmaps changed a second time
+pub fn refresh_object_index(mut beatmap_assets: ResMut<BeatmapAssets>) {
+    beatmap_assets
+        .bypass_change_detection()
+        .refresh_object_index();
+}
+
+/// Step or mute the editor's music and tick volumes from the toolbar
+pub fn handle_editor_audio_clicks(
+    mut editor_audio: ResMut<EditorAudio>,
+    buttons: Query<(&Transform, &EditorAudioButton)>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let world = Vec2::new(
+        cursor_pos.x - window.width() / 2.0,
+        window.height() / 2.0 - cursor_pos.y,
+    );
+
+    let clicked = buttons.iter().find(|(transform, _)| {
+        Rect::from_center_size(
+            transform.translation.truncate(),
+            Vec2::splat(EDITOR_AUDIO_BUTTON_SIZE),
+        )
+        .contains(world)
+    });
+    let Some((_, button)) = clicked else {
+        return;
+    };
+    let audio = &mut *editor_audio;
+    match button {
+        EditorAudioButton::MusicDown => EditorAudio::adjust(&mut audio.music_volume, -1.0),
+        EditorAudioButton::MusicUp => EditorAudio::adjust(&mut audio.music_volume, 1.0),
+        EditorAudioButton::MusicMute => audio.music_muted = !audio.music_muted,
+        EditorAudioButton::TicksDown => EditorAudio::adjust(&mut audio.tick_volume, -1.0),
+        EditorAudioButton::TicksUp => EditorAudio::adjust(&mut audio.tick_volume, 1.0),
+        EditorAudioButton::TicksMute => audio.ticks_muted = !audio.ticks_muted,
+    }
+}
+
+/// Jump to a history step when its row is clicked
+pub fn handle_history_clicks(
+    mut editor_state: ResMut<EditorState>,
+    editor_ui: Res<EditorUIState>,
     mut beatmap_assets: ResMut<BeatmapAssets>,
+    rows: Query<(&Transform, &HistoryRow)>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let world = Vec2::new(
+        cursor_pos.x - window.width() / 2.0,
+        window.height() / 2.0 - cursor_pos.y,
+    );
+
+    let clicked = rows.iter().find(|(transform, _)| {
+        Rect::from_center_size(
+            transform.translation.truncate(),
+            Vec2::new(editor_ui.left_panel_width, HISTORY_ROW_HEIGHT),
+        )
+        .contains(world)
+    });
+
+    if let (Some((_, row)), Some(beatmap)) = (clicked, beatmap_assets.current_mut()) {
+        if row.applied != editor_state.undo_stack.len() {
+            editor_state.jump_to_history(beatmap, row.applied);
+        }
+    }
+}
+
+/// Properties panel: clicking a row starts typing into it (or flips a toggle), typed
+/// text goes to the focused field, Enter applies, Tab applies and moves on, ESC cancels
+#[allow(clippy::too_many_arguments)]
+pub fn handle_property_input(
+    mut editor_state: ResMut<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    rows: Query<(&Transform, &PropertyRow)>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut clipboard: NonSendMut<Clipboard>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
 ) {
-    if (keyboard.pressed(KeyCode::ControlLeft) || keyboard.pressed(KeyCode::ControlRight))
-        && keyboard.just_pressed(KeyCode::KeyS)
+    // Drain events every frame so keys pressed before a field opens don't land in it
+    let typed = pressed_keys(&mut key_events);
+
+    if !editor_ui.right_panel_visible || editor_ui.right_panel_tab != EditorRightTab::Properties {
+        if editor_state.property_edit.is_some() {
+            editor_state.property_edit = None;
+        }
+        return;
+    }
+    let Some(beatmap) = beatmap_assets.current_mut() else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        let Ok(window) = windows.get_single() else {
+            return;
+        };
+        let Some(cursor_pos) = window.cursor_position() else {
+            return;
+        };
+        let world = Vec2::new(
+            cursor_pos.x - window.width() / 2.0,
+            window.height() / 2.0 - cursor_pos.y,
+        );
+        let clicked = rows.iter().find(|(transform, _)| {
+            Rect::from_center_size(
+                transform.translation.truncate(),
+                Vec2::new(editor_ui.right_panel_width, PROPERTY_ROW_HEIGHT),
+            )
+            .contains(world)
+        });
+
+        match clicked.map(|(_, row)| *row) {
+            Some(PropertyRow::Units) => {
+                editor_state.property_normalized = !editor_state.property_normalized;
+                editor_state.property_edit = None;
+            }
+            Some(PropertyRow::Field(field)) if field.is_toggle() => {
+                editor_state.property_edit = None;
+                if let Some(action) = editor_state.toggle_property(beatmap, field) {
+                    record_panel_edit(
+                        &mut editor_state,
+                        &mut editor_ui,
+                        action,
+                        field.display_name(),
+                    );
+                }
+            }
+            Some(PropertyRow::Field(field)) => editor_state.begin_property_edit(beatmap, field),
+            None => {}
+        }
+        return;
+    }
+
+    let Some(edit) = editor_state.property_edit.as_mut() else {
+        return;
+    };
+    edit.input
+        .handle_keys(&typed, Modifiers::from_keyboard(&keyboard), &mut clipboard);
+    let field = edit.field;
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        editor_state.property_edit = None;
+        editor_ui.show_status("Edit cancelled".to_string(), 3);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        commit_property(&mut editor_state, &mut editor_ui, beatmap);
+    } else if keyboard.just_pressed(KeyCode::Tab)
+        && commit_property(&mut editor_state, &mut editor_ui, beatmap)
     {
-        if let Some(path) = &editor_state.current_beatmap_path {
-            match beatmap_assets.save(path) {
-                Ok(_) => {
-                    println!("Beatmap saved successfully!");
+        let fields: Vec<_> = editor_state
+            .property_fields(beatmap)
+            .into_iter()
+            .filter(|field| !field.is_toggle())
+            .collect();
+        let next = fields
+            .iter()
+            .position(|f| *f == field)
+            .map(|i| fields[(i + 1) % fields.len()]);
+        if let Some(next) = next.or(fields.first().copied()) {
+            editor_state.begin_property_edit(beatmap, next);
+        }
+    }
+}
+
+/// Practice panel: clicking a hint selects it and seeks to its start, clicking one of
+/// its fields starts typing into it. Enter applies, ESC cancels
+#[allow(clippy::too_many_arguments)]
+pub fn handle_practice_hint_input(
+    mut editor_state: ResMut<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    rows: Query<(&Transform, &PracticeHintRow)>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut clipboard: NonSendMut<Clipboard>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    // Drain events every frame so keys pressed before a field opens don't land in it
+    let typed = pressed_keys(&mut key_events);
+
+    if !editor_ui.left_panel_visible || editor_ui.left_panel_tab != EditorLeftTab::Practice {
+        if editor_state.hint_edit.is_some() {
+            editor_state.hint_edit = None;
+        }
+        return;
+    }
+    let Some(beatmap) = beatmap_assets.current_mut() else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        let Ok(window) = windows.get_single() else {
+            return;
+        };
+        let Some(cursor_pos) = window.cursor_position() else {
+            return;
+        };
+        let world = Vec2::new(
+            cursor_pos.x - window.width() / 2.0,
+            window.height() / 2.0 - cursor_pos.y,
+        );
+        let clicked = rows.iter().find(|(transform, _)| {
+            Rect::from_center_size(
+                transform.translation.truncate(),
+                Vec2::new(editor_ui.left_panel_width, PRACTICE_ROW_HEIGHT),
+            )
+            .contains(world)
+        });
+
+        match clicked.map(|(_, row)| *row) {
+            Some(PracticeHintRow::Hint(index)) => {
+                editor_state.hint_edit = None;
+                editor_state.selected_hint = Some(index);
+                if let Some(hint) = beatmap.practice_hints.get(index) {
+                    let start = hint.start;
+                    editor_state.seek_to(start);
+                }
+            }
+            Some(PracticeHintRow::Field(field)) => editor_state.begin_hint_edit(beatmap, field),
+            None => {}
+        }
+        return;
+    }
+
+    let Some(edit) = editor_state.hint_edit.as_mut() else {
+        return;
+    };
+    edit.input
+        .handle_keys(&typed, Modifiers::from_keyboard(&keyboard), &mut clipboard);
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        editor_state.hint_edit = None;
+        editor_ui.show_status("Edit cancelled".to_string(), 3);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        let Some(edit) = editor_state.hint_edit.clone() else {
+            return;
+        };
+        match editor_state.commit_hint_edit(beatmap) {
+            Ok(Some(action)) => {
+                record_panel_edit(
+                    &mut editor_state,
+                    &mut editor_ui,
+                    action,
+                    edit.field.display_name(),
+                );
+            }
+            Ok(None) => {}
+            Err(e) => {
+                // Keep the typed text open so it can be corrected
+                editor_ui.show_status(e, 3);
+                editor_state.hint_edit = Some(edit);
+            }
+        }
+    }
+}
+
+/// Comments panel: clicking a comment selects it and seeks to its time, the rows under
+/// it resolve or delete it, and the top row starts a comment at the playhead. Every
+/// change is saved to the sidecar right away
+#[allow(clippy::too_many_arguments)]
+pub fn handle_comment_input(
+    mut editor_state: ResMut<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    config: Res<GameConfig>,
+    rows: Query<(&Transform, &CommentRow)>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut clipboard: NonSendMut<Clipboard>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    // Drain events every frame so keys pressed before the field opens don't land in it
+    let typed = pressed_keys(&mut key_events);
+
+    if !editor_ui.left_panel_visible || editor_ui.left_panel_tab != EditorLeftTab::Comments {
+        if editor_state.comment_draft.is_some() {
+            editor_state.comment_draft = None;
+        }
+        return;
+    }
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        let Ok(window) = windows.get_single() else {
+            return;
+        };
+        let Some(cursor_pos) = window.cursor_position() else {
+            return;
+        };
+        let world = Vec2::new(
+            cursor_pos.x - window.width() / 2.0,
+            window.height() / 2.0 - cursor_pos.y,
+        );
+        let clicked = rows.iter().find(|(transform, _)| {
+            Rect::from_center_size(
+                transform.translation.truncate(),
+                Vec2::new(editor_ui.left_panel_width, PRACTICE_ROW_HEIGHT),
+            )
+            .contains(world)
+        });
+
+        let result = match clicked.map(|(_, row)| *row) {
+            Some(CommentRow::Add) => {
+                if editor_state.comment_draft.is_none() {
+                    editor_state.begin_comment();
+                }
+                Ok(())
+            }
+            Some(CommentRow::Comment(index)) => {
+                editor_state.comment_draft = None;
+                editor_state.jump_to_comment(index);
+                show_comment(&editor_state, &mut editor_ui, index);
+                Ok(())
+            }
+            Some(CommentRow::Resolve) => {
+                editor_state
+                    .toggle_comment_resolved()
+                    .map(|resolved| match resolved {
+                        Some(true) => editor_ui.show_status("Comment resolved".to_string(), 3),
+                        Some(false) => editor_ui.show_status("Comment reopened".to_string(), 3),
+                        None => {}
+                    })
+            }
+            Some(CommentRow::Delete) => editor_state.delete_comment().map(|deleted| {
+                if deleted.is_some() {
+                    editor_ui.show_status("Comment deleted".to_string(), 3);
+                }
+            }),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            editor_ui.show_status(e, 3);
+        }
+        return;
+    }
+
+    let Some(draft) = editor_state.comment_draft.as_mut() else {
+        return;
+    };
+    draft
+        .input
+        .handle_keys(&typed, Modifiers::from_keyboard(&keyboard), &mut clipboard);
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        editor_state.comment_draft = None;
+        editor_ui.show_status("Comment cancelled".to_string(), 3);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        let Some(time) = editor_state.comment_draft.as_ref().map(|draft| draft.time) else {
+            return;
+        };
+        let author = Some(config.creator_name())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| "Anonymous".to_string());
+        match editor_state.commit_comment(author) {
+            Ok(()) => editor_ui.show_status(format!("Comment added at {}", format_time(time)), 3),
+            Err(e) => editor_ui.show_status(e, 3),
+        }
+    }
+}
+
+/// Show a comment in full in the status bar
+fn show_comment(editor_state: &EditorState, editor_ui: &mut EditorUIState, index: usize) {
+    if let Some(comment) = editor_state.comments.comments.get(index) {
+        editor_ui.show_status(
+            format!(
+                "{} {}: {}",
+                format_time(comment.time),
+                comment.author,
+                comment.text
+            ),
+            5,
+        );
+    }
+}
+
+/// Apply the focused Properties field. On a bad value the field stays open with the
+/// typed text so it can be corrected; returns whether it was applied
+fn commit_property(
+    editor_state: &mut EditorState,
+    editor_ui: &mut EditorUIState,
+    beatmap: &mut Beatmap,
+) -> bool {
+    let Some(edit) = editor_state.property_edit.clone() else {
+        return false;
+    };
+    match editor_state.commit_property_edit(beatmap) {
+        Ok(Some(action)) => {
+            record_panel_edit(editor_state, editor_ui, action, edit.field.display_name());
+            true
+        }
+        Ok(None) => true,
+        Err(e) => {
+            editor_ui.show_status(e, 3);
+            editor_state.property_edit = Some(edit);
+            false
+        }
+    }
+}
+
+/// Metadata panel: clicking a field starts typing into it, the audio row picks the next
+/// file in the beatmap's folder, and the preview rows set or play the preview point.
+/// Enter applies, Tab applies and moves on, ESC cancels. The set rows copy another
+/// difficulty's settings or preview a metadata propagation to the set
+#[allow(clippy::too_many_arguments)]
+pub fn handle_metadata_input(
+    mut editor_state: ResMut<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    audio_sink: Res<GameAudioSink>,
+    config: Res<GameConfig>,
+    rows: Query<(&Transform, &MetadataRow)>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut clipboard: NonSendMut<Clipboard>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    // Drain events every frame so keys pressed before a field opens don't land in it
+    let typed = pressed_keys(&mut key_events);
+
+    if !editor_ui.right_panel_visible || editor_ui.right_panel_tab != EditorRightTab::Metadata {
+        if editor_state.metadata_edit.is_some() {
+            editor_state.metadata_edit = None;
+        }
+        return;
+    }
+    let Some(path) = beatmap_assets.current_beatmap.clone() else {
+        return;
+    };
+    let taken_versions = beatmap_assets.sibling_versions(&path);
+    let set_members: Vec<_> = beatmap_assets
+        .set_members(&path)
+        .into_iter()
+        .map(|(other_path, other)| {
+            (
+                other_path.clone(),
+                other.metadata.version.clone(),
+                other.settings.clone(),
+            )
+        })
+        .collect();
+    let Some(beatmap) = beatmap_assets.current_mut() else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        let Ok(window) = windows.get_single() else {
+            return;
+        };
+        let Some(cursor_pos) = window.cursor_position() else {
+            return;
+        };
+        let world = Vec2::new(
+            cursor_pos.x - window.width() / 2.0,
+            window.height() / 2.0 - cursor_pos.y,
+        );
+        let clicked = rows.iter().find(|(transform, _)| {
+            Rect::from_center_size(
+                transform.translation.truncate(),
+                Vec2::new(editor_ui.right_panel_width, METADATA_ROW_HEIGHT),
+            )
+            .contains(world)
+        });
+        let Some(row) = clicked.map(|(_, row)| *row) else {
+            return;
+        };
+
+        editor_state.metadata_edit = None;
+        editor_state.pending_propagation = None;
+        match row {
+            MetadataRow::Field(MetadataField::AudioFile) => {
+                match editor_state.cycle_audio_file(beatmap, &path) {
+                    Some(action) => record_panel_edit(
+                        &mut editor_state,
+                        &mut editor_ui,
+                        action,
+                        MetadataField::AudioFile.display_name(),
+                    ),
+                    None => editor_ui.show_status(
+                        "No other audio files in the beatmap's folder".to_string(),
+                        3,
+                    ),
+                }
+            }
+            MetadataRow::Field(field) => {
+                editor_state.begin_metadata_edit(beatmap, field);
+                if let Some(edit) = editor_state.metadata_edit.as_mut() {
+                    if field == MetadataField::Creator && edit.input.is_empty() {
+                        edit.input.set_text(config.creator_name());
+                    }
+                }
+            }
+            MetadataRow::PreviewFromPlayhead => {
+                if let Some(action) = editor_state.set_preview_from_playhead(beatmap) {
+                    record_panel_edit(
+                        &mut editor_state,
+                        &mut editor_ui,
+                        action,
+                        MetadataField::PreviewTime.display_name(),
+                    );
+                }
+            }
+            MetadataRow::PlayPreview => match play_preview(&audio_sink.sink, beatmap) {
+                Ok(()) => editor_ui.show_status(
+                    format!("Previewing from {}", format_time(beatmap.preview_time)),
+                    3,
+                ),
+                Err(e) => editor_ui.show_status(e, 3),
+            },
+            MetadataRow::CopySettingsFrom(index) => {
+                let Some((_, version, settings)) = set_members.get(index) else {
+                    return;
+                };
+                match editor_state.copy_difficulty_settings(beatmap, settings) {
+                    Some(action) => record_panel_edit(
+                        &mut editor_state,
+                        &mut editor_ui,
+                        action,
+                        &format!("copied from {}", version),
+                    ),
+                    None => editor_ui.show_status(format!("Settings already match {}", version), 3),
                 }
-                Err(e) => {
-                    eprintln!("Failed to save beatmap: {}", e);
+            }
+            MetadataRow::PropagateToSet => {
+                let paths: Vec<String> = set_members.iter().map(|(p, _, _)| p.clone()).collect();
+                let plan = PropagationPlan::new(beatmap, &path, &paths);
+                editor_ui.show_status(plan.summary(), 3);
+                if !plan.targets.is_empty() {
+                    editor_state.pending_propagation = Some(plan);
                 }
             }
         }
+        return;
+    }
+
+    let Some(edit) = editor_state.metadata_edit.as_mut() else {
+        return;
+    };
+    edit.input
+        .handle_keys(&typed, Modifiers::from_keyboard(&keyboard), &mut clipboard);
+    let field = edit.field;
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        editor_state.metadata_edit = None;
+        editor_ui.show_status("Edit cancelled".to_string(), 3);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        commit_metadata(&mut editor_state, &mut editor_ui, beatmap, &taken_versions);
+    } else if keyboard.just_pressed(KeyCode::Tab)
+        && commit_metadata(&mut editor_state, &mut editor_ui, beatmap, &taken_versions)
+    {
+        let fields: Vec<_> = MetadataField::all()
+            .into_iter()
+            .filter(|field| field.is_typed())
+            .collect();
+        let next = fields
+            .iter()
+            .position(|f| *f == field)
+            .map_or(fields[0], |i| fields[(i + 1) % fields.len()]);
+        editor_state.begin_metadata_edit(beatmap, next);
+    }
+}
+
+/// Apply the focused Metadata field. On a bad value the field stays open with the
+/// typed text and the problem is shown; returns whether it was applied
+fn commit_metadata(
+    editor_state: &mut EditorState,
+    editor_ui: &mut EditorUIState,
+    beatmap: &mut Beatmap,
+    taken_versions: &[String],
+) -> bool {
+    let Some(edit) = editor_state.metadata_edit.clone() else {
+        return false;
+    };
+    match editor_state.commit_metadata_edit(beatmap, taken_versions) {
+        Ok(Some(action)) => {
+            record_panel_edit(editor_state, editor_ui, action, edit.field.display_name());
+            true
+        }
+        Ok(None) => true,
+        Err(e) => {
+            editor_ui.show_status(e, 3);
+            editor_state.metadata_edit = Some(edit);
+            false
+        }
+    }
+}
+
+/// Play a short clip of the beatmap's audio from its preview point, faded at its ends
+/// the way song selection previews it
+fn play_preview(sink: &Sink, beatmap: &Beatmap) -> Result<(), String> {
+    let clip = song_clip(
+        &beatmap.audio_path,
+        beatmap.preview_time,
+        Some(PREVIEW_CLIP_SECONDS),
+    )?;
+    sink.stop();
+    sink.set_speed(1.0);
+    sink.append(clip);
+    sink.play();
+    Ok(())
+}
+
+/// Record a panel edit for undo and report it in the status bar
+fn record_panel_edit(
+    editor_state: &mut EditorState,
+    editor_ui: &mut EditorUIState,
+    action: EditorAction,
+    field: &str,
+) {
+    let label = format!("{}: {}", action.label(), field);
+    editor_ui.show_status(label.clone(), 3);
+    editor_state.record_labeled_action(action, label);
+}
+
+/// Save the beatmap being edited, refusing one that fails validation. Reports the
+/// outcome in the status bar; returns whether it was written
+fn save_edited_beatmap(
+    editor_state: &mut EditorState,
+    editor_ui: &mut EditorUIState,
+    beatmap_assets: &BeatmapAssets,
+) -> bool {
+    let Some(path) = editor_state.current_beatmap_path.clone() else {
+        return false;
+    };
+    let Some(beatmap) = beatmap_assets.get(&path) else {
+        editor_ui.show_status("Not saved: the beatmap isn't loaded".to_string(), 5);
+        return false;
+    };
+    match save_beatmap(&path, beatmap) {
+        Ok(()) => {
+            editor_state.dirty = false;
+            editor_state.pending_exit = false;
+            editor_ui.show_status("Beatmap saved".to_string(), 3);
+            true
+        }
+        Err(e) => {
+            error!("Failed to save beatmap: {}", e);
+            let hint = if editor_state.pending_exit {
+                ""
+            } else {
+                " (ESC again leaves without saving)"
+            };
+            editor_ui.show_status(format!("Not saved: {}{}", e, hint), 8);
+            false
+        }
+    }
+}
+
+/// Save (Ctrl+S) and reload from disk (Ctrl+O) shortcuts. Reloading over unsaved
+/// changes needs a second Ctrl+O
+pub fn handle_save_shortcut(
+    mut editor_state: ResMut<EditorState>,
+    mut editor_ui: ResMut<EditorUIState>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    text_focus: Res<TextFocus>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+) {
+    if text_focus.any()
+        || !(keyboard.pressed(KeyCode::ControlLeft) || keyboard.pressed(KeyCode::ControlRight))
+    {
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        save_edited_beatmap(&mut editor_state, &mut editor_ui, &beatmap_assets);
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyO) {
+        let Some(path) = editor_state.current_beatmap_path.clone() else {
+            return;
+        };
+        if editor_state.dirty && !editor_state.pending_reload {
+            editor_state.pending_reload = true;
+            editor_ui.show_status(
+                "Unsaved changes - Ctrl+O again to reload from disk and drop them".to_string(),
+                5,
+            );
+            return;
+        }
+        match load_beatmap(&path) {
+            Ok(beatmap) => {
+                let count = beatmap.hit_objects.len();
+                beatmap_assets.add(path, beatmap);
+                editor_state.reset_for_reload();
+                editor_ui.show_status(format!("Reloaded {} objects from disk", count), 3);
+            }
+            Err(e) => {
+                error!("Failed to load beatmap: {}", e);
+                editor_state.pending_reload = false;
+                editor_ui.show_status(format!("Couldn't reload: {}", e), 5);
+            }
+        }
     }
 }
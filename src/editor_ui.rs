@@ -1,15 +1,21 @@
 // src/editor_ui.rs
 
-use crate::beatmap::{BeatDivisor, Beatmap, EditorTool, HitObjectKind};
+use crate::beatmap::{Beatmap, EditorTool, HitObject, HitObjectKind, KiaiSection};
 use crate::constants::*;
 use crate::editor::{
-    grid_to_screen, snap_to_grid, EditorAction, EditorLeftTab, EditorRightTab, EditorState,
-    EditorUIState,
+    comment_marker_y, format_time, EditorLeftTab, EditorRightTab, EditorState, EditorUIState,
+    HintField, MetadataField, MetadataSnapshot, ObjectField, TimelineGrab, COMMENT_MARKER_SIZE,
+    PREVIEW_CLIP_SECONDS, TIMELINE_WAVEFORM_HEIGHT,
 };
+use crate::editor_audio::EditorAudio;
+use crate::guidelines::GuidelineReport;
 use crate::structs::GameAssets;
+use crate::text_input::add_input_spans;
 use crate::ui::UiElement;
+use crate::waveform::EditorWaveform;
 use bevy::prelude::*;
 use bevy::window::Window;
+use std::collections::{HashMap, HashSet};
 
 /// Setup the editor UI
 pub fn setup_editor_ui(
@@ -75,13 +81,13 @@ pub fn setup_editor_ui(
     );
 
     // Playfield grid
-    spawn_playfield_grid(&mut commands, &assets, &editor_state, screen_w, screen_h);
+    spawn_playfield_grid(&mut commands, &editor_state);
 
     // Status bar
     spawn_status_bar(
         &mut commands,
         &assets,
-        &editor_state,
+        &editor_ui,
         beatmap_assets.current(),
         screen_w,
         screen_h,
@@ -140,7 +146,7 @@ fn spawn_toolbar(
                 font_size: 10.0,
                 ..default()
             },
-            TextColor(Color::WHITE.into()),
+            TextColor(Color::WHITE),
             Transform::from_xyz(x, toolbar_y - button_size / 2.0 - 10.0, 0.3),
             UiElement,
         ));
@@ -203,7 +209,7 @@ fn spawn_playback_controls(
             font_size: 16.0,
             ..default()
         },
-        TextColor(Color::WHITE.into()),
+        TextColor(Color::WHITE),
         Transform::from_xyz(center_x, y, 0.3),
         UiElement,
     ));
@@ -251,7 +257,7 @@ fn spawn_divisor_selector(
             font_size: 14.0,
             ..default()
         },
-        TextColor(NEON_CYAN.into()),
+        TextColor(NEON_CYAN),
         Transform::from_xyz(x, y, 0.2),
         UiElement,
         BeatDivisorDisplay,
@@ -266,7 +272,7 @@ fn spawn_divisor_selector(
     let snap_color = if editor_state.snap_enabled {
         NEON_GREEN
     } else {
-        Color::GRAY
+        Color::srgb(0.5, 0.5, 0.5)
     };
     commands.spawn((
         Text2d::new(snap_text),
@@ -275,7 +281,7 @@ fn spawn_divisor_selector(
             font_size: 12.0,
             ..default()
         },
-        TextColor(snap_color.into()),
+        TextColor(snap_color),
         Transform::from_xyz(x, y - 15.0, 0.2),
         UiElement,
         SnapToggleButton,
@@ -307,10 +313,13 @@ fn spawn_left_panel(
     ));
 
     // Tab buttons
-    let tabs = vec![
+    let tabs = [
         (EditorLeftTab::Tools, "Tools"),
         (EditorLeftTab::Timing, "Timing"),
         (EditorLeftTab::Bookmarks, "Bookmarks"),
+        (EditorLeftTab::Practice, "Practice"),
+        (EditorLeftTab::Comments, "Comments"),
+        (EditorLeftTab::History, "History"),
     ];
 
     let tab_width = editor_ui.left_panel_width / tabs.len() as f32;
@@ -342,7 +351,7 @@ fn spawn_left_panel(
                 font_size: 10.0,
                 ..default()
             },
-            TextColor(Color::WHITE.into()),
+            TextColor(Color::WHITE),
             Transform::from_xyz(tab_x, tab_y, 0.3),
             UiElement,
         ));
@@ -353,12 +362,11 @@ fn spawn_left_panel(
         EditorLeftTab::Tools => {
             spawn_tools_panel(commands, assets, panel_x, panel_y, editor_ui, editor_state)
         }
-        EditorLeftTab::Timing => {
-            spawn_timing_panel(commands, assets, panel_x, panel_y, editor_ui, editor_state)
-        }
-        EditorLeftTab::Bookmarks => {
-            spawn_bookmarks_panel(commands, assets, panel_x, panel_y, editor_ui)
-        }
+        EditorLeftTab::Timing => spawn_timing_panel(commands, assets, panel_x, panel_y),
+        EditorLeftTab::Bookmarks => spawn_bookmarks_panel(commands, assets, panel_x, panel_y),
+        EditorLeftTab::Practice => spawn_practice_panel(commands, assets, panel_x, panel_y),
+        EditorLeftTab::Comments => spawn_comments_panel(commands, assets, panel_x, panel_y),
+        EditorLeftTab::History => spawn_history_panel(commands, assets, panel_x, panel_y),
     }
 }
 
@@ -377,7 +385,7 @@ fn spawn_tools_panel(
     let combo_color = if editor_state.new_combo_mode {
         NEON_GREEN
     } else {
-        Color::GRAY
+        Color::srgb(0.5, 0.5, 0.5)
     };
     commands.spawn((
         Text2d::new("New Combo (Q)"),
@@ -386,7 +394,7 @@ fn spawn_tools_panel(
             font_size: 12.0,
             ..default()
         },
-        TextColor(combo_color.into()),
+        TextColor(combo_color),
         Transform::from_xyz(panel_x, start_y, 0.2),
         UiElement,
         NewComboToggle,
@@ -400,7 +408,7 @@ fn spawn_tools_panel(
             font_size: 12.0,
             ..default()
         },
-        TextColor(NEON_CYAN.into()),
+        TextColor(NEON_CYAN),
         Transform::from_xyz(panel_x, start_y - 30.0, 0.2),
         UiElement,
     ));
@@ -413,7 +421,7 @@ fn spawn_tools_panel(
             font_size: 12.0,
             ..default()
         },
-        TextColor(Color::WHITE.into()),
+        TextColor(Color::WHITE),
         Transform::from_xyz(panel_x, start_y - 60.0, 0.2),
         UiElement,
     ));
@@ -421,7 +429,7 @@ fn spawn_tools_panel(
     let grid_toggle_color = if editor_state.show_grid {
         NEON_GREEN
     } else {
-        Color::GRAY
+        Color::srgb(0.5, 0.5, 0.5)
     };
     commands.spawn((
         Text2d::new("Show Grid (G)"),
@@ -430,22 +438,33 @@ fn spawn_tools_panel(
             font_size: 12.0,
             ..default()
         },
-        TextColor(grid_toggle_color.into()),
+        TextColor(grid_toggle_color),
         Transform::from_xyz(panel_x, start_y - 85.0, 0.2),
         UiElement,
         GridToggle,
     ));
+
+    let resnap_color = if editor_state.resnap_slider_duration {
+        NEON_GREEN
+    } else {
+        Color::srgb(0.5, 0.5, 0.5)
+    };
+    commands.spawn((
+        Text2d::new("Resnap Slider Lengths (L)"),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(resnap_color),
+        Transform::from_xyz(panel_x, start_y - 110.0, 0.2),
+        UiElement,
+        ResnapDurationToggle,
+    ));
 }
 
 /// Spawn timing panel content
-fn spawn_timing_panel(
-    commands: &mut Commands,
-    assets: &GameAssets,
-    panel_x: f32,
-    panel_y: f32,
-    editor_ui: &EditorUIState,
-    editor_state: &EditorState,
-) {
+fn spawn_timing_panel(commands: &mut Commands, assets: &GameAssets, panel_x: f32, panel_y: f32) {
     commands.spawn((
         Text2d::new("Timing Points"),
         TextFont {
@@ -453,20 +472,14 @@ fn spawn_timing_panel(
             font_size: 14.0,
             ..default()
         },
-        TextColor(NEON_PINK.into()),
+        TextColor(NEON_PINK),
         Transform::from_xyz(panel_x, panel_y + 80.0, 0.2),
         UiElement,
     ));
 }
 
 /// Spawn bookmarks panel content
-fn spawn_bookmarks_panel(
-    commands: &mut Commands,
-    assets: &GameAssets,
-    panel_x: f32,
-    panel_y: f32,
-    editor_ui: &EditorUIState,
-) {
+fn spawn_bookmarks_panel(commands: &mut Commands, assets: &GameAssets, panel_x: f32, panel_y: f32) {
     commands.spawn((
         Text2d::new("Bookmarks"),
         TextFont {
@@ -474,7 +487,7 @@ fn spawn_bookmarks_panel(
             font_size: 14.0,
             ..default()
         },
-        TextColor(NEON_PINK.into()),
+        TextColor(NEON_PINK),
         Transform::from_xyz(panel_x, panel_y + 80.0, 0.2),
         UiElement,
     ));
@@ -507,7 +520,7 @@ fn spawn_right_panel(
     ));
 
     // Tab buttons
-    let tabs = vec![
+    let tabs = [
         (EditorRightTab::Properties, "Properties"),
         (EditorRightTab::Settings, "Settings"),
         (EditorRightTab::Metadata, "Metadata"),
@@ -542,7 +555,7 @@ fn spawn_right_panel(
                 font_size: 10.0,
                 ..default()
             },
-            TextColor(Color::WHITE.into()),
+            TextColor(Color::WHITE),
             Transform::from_xyz(tab_x, tab_y, 0.3),
             UiElement,
         ));
@@ -563,9 +576,8 @@ fn spawn_right_panel(
             EditorRightTab::Settings => {
                 spawn_settings_panel(commands, assets, panel_x, panel_y, beatmap, editor_ui)
             }
-            EditorRightTab::Metadata => {
-                spawn_metadata_panel(commands, assets, panel_x, panel_y, beatmap, editor_ui)
-            }
+            // Drawn by draw_editor_metadata so edits show up immediately
+            EditorRightTab::Metadata => {}
         }
     }
 }
@@ -591,7 +603,7 @@ fn spawn_properties_panel(
             font_size: 12.0,
             ..default()
         },
-        TextColor(Color::WHITE.into()),
+        TextColor(Color::WHITE),
         Transform::from_xyz(panel_x, start_y, 0.2),
         UiElement,
     ));
@@ -606,7 +618,7 @@ fn spawn_properties_panel(
             font_size: 10.0,
             ..default()
         },
-        TextColor(Color::srgba(0.7, 0.7, 0.7, 1.0).into()),
+        TextColor(Color::srgba(0.7, 0.7, 0.7, 1.0)),
         Transform::from_xyz(panel_x, start_y - 15.0, 0.2),
         UiElement,
     ));
@@ -622,7 +634,7 @@ fn spawn_properties_panel(
             font_size: 12.0,
             ..default()
         },
-        TextColor(Color::WHITE.into()),
+        TextColor(Color::WHITE),
         Transform::from_xyz(panel_x, start_y - 40.0, 0.2),
         UiElement,
     ));
@@ -636,7 +648,7 @@ fn spawn_properties_panel(
                 font_size: 12.0,
                 ..default()
             },
-            TextColor(NEON_GREEN.into()),
+            TextColor(NEON_GREEN),
             Transform::from_xyz(panel_x, start_y - 70.0, 0.2),
             UiElement,
         ));
@@ -655,7 +667,7 @@ fn spawn_settings_panel(
     let start_y = panel_y + editor_ui.right_panel_width / 2.0 - 50.0;
     let settings = &beatmap.settings;
 
-    let settings_text = vec![
+    let settings_text = [
         format!("Circle Size (CS): {:.1}", settings.circle_size),
         format!("Approach Rate (AR): {:.1}", settings.approach_rate),
         format!(
@@ -674,48 +686,59 @@ fn spawn_settings_panel(
                 font_size: 11.0,
                 ..default()
             },
-            TextColor(Color::WHITE.into()),
+            TextColor(Color::WHITE),
             Transform::from_xyz(panel_x, start_y - i as f32 * 20.0, 0.2),
             UiElement,
         ));
     }
 }
 
-/// Spawn metadata panel
-fn spawn_metadata_panel(
-    commands: &mut Commands,
-    assets: &GameAssets,
-    panel_x: f32,
-    panel_y: f32,
-    beatmap: &Beatmap,
-    editor_ui: &EditorUIState,
-) {
-    let start_y = panel_y + editor_ui.right_panel_width / 2.0 - 50.0;
-    let meta = &beatmap.metadata;
-
-    let meta_text = vec![
-        format!("Title: {}", meta.title),
-        format!("Artist: {}", meta.artist),
-        format!("Creator: {}", meta.creator),
-        format!("Version: {}", meta.version),
-    ];
+/// Spawn practice panel header; hints are drawn by `draw_editor_practice_hints`
+fn spawn_practice_panel(commands: &mut Commands, assets: &GameAssets, panel_x: f32, panel_y: f32) {
+    commands.spawn((
+        Text2d::new("Practice Hints"),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(NEON_PINK),
+        Transform::from_xyz(panel_x, panel_y + 80.0, 0.2),
+        UiElement,
+    ));
+}
 
-    for (i, text) in meta_text.iter().enumerate() {
-        commands.spawn((
-            Text2d::new(text.clone()),
-            TextFont {
-                font: assets.cyberpunk_font.clone(),
-                font_size: 11.0,
-                ..default()
-            },
-            TextColor(Color::WHITE.into()),
-            Transform::from_xyz(panel_x, start_y - i as f32 * 20.0, 0.2),
-            UiElement,
-        ));
-    }
+/// Spawn comments panel header; comments are drawn by `draw_editor_comments`
+fn spawn_comments_panel(commands: &mut Commands, assets: &GameAssets, panel_x: f32, panel_y: f32) {
+    commands.spawn((
+        Text2d::new("Review Comments"),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(NEON_PINK),
+        Transform::from_xyz(panel_x, panel_y + 80.0, 0.2),
+        UiElement,
+    ));
 }
 
 /// Spawn timeline
+/// Spawn history panel header; rows are drawn by `draw_editor_history`
+fn spawn_history_panel(commands: &mut Commands, assets: &GameAssets, panel_x: f32, panel_y: f32) {
+    commands.spawn((
+        Text2d::new("History"),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(NEON_PINK),
+        Transform::from_xyz(panel_x, panel_y + 80.0, 0.2),
+        UiElement,
+    ));
+}
+
 fn spawn_timeline(
     commands: &mut Commands,
     assets: &GameAssets,
@@ -775,33 +798,32 @@ fn spawn_timeline(
             }
         }
 
-        // Draw hit objects on timeline
-        for obj in &beatmap.hit_objects {
-            if obj.time >= visible_start && obj.time <= visible_end {
-                let x =
-                    crate::editor::time_to_timeline_pos(obj.time, zoom, scroll) - screen_w / 2.0;
-
-                let color = match obj.kind {
-                    HitObjectKind::Circle => NEON_BLUE,
-                    HitObjectKind::Slider { .. } => NEON_PURPLE,
-                    HitObjectKind::Spinner { .. } => NEON_YELLOW,
-                };
-
-                let is_selected = editor_state.selected_objects.contains(&obj.id);
-                let height = if is_selected { 20.0 } else { 14.0 };
-                let z = if is_selected { 0.25 } else { 0.2 };
-
-                commands.spawn((
-                    Sprite {
-                        color,
-                        custom_size: Some(Vec2::new(4.0, height)),
-                        ..default()
-                    },
-                    Transform::from_xyz(x, timeline_y, z),
-                    UiElement,
-                    TimelineObject { id: obj.id },
-                ));
+        // Kiai sections as bright bands behind the objects
+        let kiai_color = Color::srgba(1.0, 0.84, 0.0, 0.2);
+        let pending_kiai = editor_state.kiai_start.map(|start| KiaiSection {
+            start: start.min(editor_state.current_time),
+            end: start.max(editor_state.current_time),
+        });
+        for (section, color) in beatmap
+            .kiai_sections
+            .iter()
+            .map(|section| (section, kiai_color))
+            .chain(pending_kiai.iter().map(|section| (section, kiai_color.with_alpha(0.1))))
+        {
+            if section.end < visible_start || section.start > visible_end {
+                continue;
             }
+            let left = crate::editor::time_to_timeline_pos(section.start.max(visible_start), zoom, scroll);
+            let right = crate::editor::time_to_timeline_pos(section.end.min(visible_end), zoom, scroll);
+            commands.spawn((
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::new((right - left).max(1.0), editor_ui.timeline_height)),
+                    ..default()
+                },
+                Transform::from_xyz((left + right) / 2.0 - screen_w / 2.0, timeline_y, 0.12),
+                UiElement,
+            ));
         }
     }
 
@@ -831,7 +853,7 @@ fn spawn_timeline(
             font_size: 14.0,
             ..default()
         },
-        TextColor(NEON_PINK.into()),
+        TextColor(NEON_PINK),
         Transform::from_xyz(
             0.0,
             timeline_y + editor_ui.timeline_height / 2.0 + 10.0,
@@ -843,13 +865,7 @@ fn spawn_timeline(
 }
 
 /// Spawn playfield grid
-fn spawn_playfield_grid(
-    commands: &mut Commands,
-    assets: &GameAssets,
-    editor_state: &EditorState,
-    screen_w: f32,
-    screen_h: f32,
-) {
+fn spawn_playfield_grid(commands: &mut Commands, editor_state: &EditorState) {
     if !editor_state.show_grid {
         return;
     }
@@ -909,7 +925,7 @@ fn spawn_playfield_grid(
 fn spawn_status_bar(
     commands: &mut Commands,
     assets: &GameAssets,
-    editor_state: &EditorState,
+    editor_ui: &EditorUIState,
     beatmap: Option<&Beatmap>,
     screen_w: f32,
     screen_h: f32,
@@ -930,7 +946,7 @@ fn spawn_status_bar(
     ));
 
     // Status message
-    let status_text = if let Some((msg, _)) = &editor_state.status_message {
+    let status_text = if let Some((msg, _)) = &editor_ui.status_message {
         msg.clone()
     } else if let Some(beatmap) = beatmap {
         format!(
@@ -951,7 +967,7 @@ fn spawn_status_bar(
             font_size: 10.0,
             ..default()
         },
-        TextColor(Color::srgba(0.7, 0.7, 0.7, 1.0).into()),
+        TextColor(Color::srgba(0.7, 0.7, 0.7, 1.0)),
         Transform::from_xyz(-screen_w / 2.0 + 10.0, bar_y, 0.2),
         UiElement,
         StatusText,
@@ -965,186 +981,1834 @@ fn spawn_status_bar(
             font_size: 10.0,
             ..default()
         },
-        TextColor(Color::srgba(0.5, 0.5, 0.5, 1.0).into()),
+        TextColor(Color::srgba(0.5, 0.5, 0.5, 1.0)),
         Transform::from_xyz(screen_w / 2.0 - 100.0, bar_y, 0.2),
         UiElement,
     ));
 }
 
-/// Render hit objects in the playfield
+/// Seconds an object stays on the playfield after its hit time, fading out
+const FADE_OUT_TIME: f64 = 0.2;
+
+/// Render hit objects in the playfield. Entities are kept from frame to frame: only
+/// objects entering or leaving the approach window are spawned or despawned, and an
+/// object is respawned only when it, its selection or the zoom changes. Otherwise the
+/// fade and approach circle are updated in place
 pub fn render_editor_hit_objects(
     mut commands: Commands,
     assets: Res<GameAssets>,
     editor_state: Res<EditorState>,
     beatmap_assets: Res<crate::beatmap::BeatmapAssets>,
+    mut drawn: Query<DrawnHitObject>,
 ) {
-    if let Some(beatmap) = beatmap_assets.current() {
-        let approach_time = beatmap.settings.get_approach_time();
-        let current_time = editor_state.current_time;
-
-        for obj in &beatmap.hit_objects {
-            // Check if object is visible (within approach window)
-            let time_diff = obj.time - current_time;
-            if time_diff < -0.2 || time_diff > approach_time {
-                continue;
-            }
-
-            let is_selected = editor_state.selected_objects.contains(&obj.id);
-            let alpha = if time_diff < 0.0 {
-                1.0 - ((-time_diff) / 0.2) as f32
-            } else {
-                1.0
-            };
+    let Some(beatmap) = beatmap_assets.current() else {
+        for (entity, ..) in drawn.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    let approach_time = beatmap.settings.get_approach_time();
+    let current_time = editor_state.current_time;
+    let zoom = editor_state.playfield_zoom;
 
-            let color = match obj.kind {
-                HitObjectKind::Circle => {
-                    if is_selected {
-                        NEON_GREEN
-                    } else if obj.new_combo {
-                        NEON_PINK
-                    } else {
-                        NEON_BLUE
-                    }
-                }
-                HitObjectKind::Slider { .. } => {
-                    if is_selected {
-                        NEON_GREEN
-                    } else {
-                        NEON_PURPLE
-                    }
-                }
-                HitObjectKind::Spinner { .. } => {
-                    if is_selected {
-                        NEON_GREEN
-                    } else {
-                        NEON_YELLOW
-                    }
-                }
-            };
+    // The index lists objects in the same order, so the visible ones are a contiguous run
+    let Some(index) = beatmap_assets.object_index() else {
+        return;
+    };
+    let range = index.starting_between(current_time - FADE_OUT_TIME, current_time + approach_time);
+    let visible: HashMap<HitObjectId, &HitObject> = beatmap.hit_objects[range.clone()]
+        .iter()
+        .map(|obj| (obj.id, obj))
+        .collect();
+    let selected: HashSet<HitObjectId> = editor_state.selected_objects.iter().copied().collect();
+
+    // Objects whose entities still match what they were spawned from
+    let kept: HashSet<HitObjectId> = drawn
+        .iter()
+        .filter_map(|(_, part, snapshot, ..)| {
+            let snapshot = snapshot?;
+            let obj = visible.get(&part.id)?;
+            (snapshot.object == **obj
+                && snapshot.selected == selected.contains(&obj.id)
+                && snapshot.zoom == zoom)
+                .then_some(part.id)
+        })
+        .collect();
+
+    for (entity, part, _, mut transform, sprite) in drawn.iter_mut() {
+        let Some(obj) = visible.get(&part.id).filter(|_| kept.contains(&part.id)) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        let (alpha, approach) = object_fade(obj.time - current_time, approach_time);
+        let Some(mut sprite) = sprite else {
+            continue;
+        };
+        match part.part {
+            ObjectPart::Body => sprite.color.set_alpha(alpha),
+            ObjectPart::SliderBody => sprite.color.set_alpha(alpha * 0.35),
+            ObjectPart::Approach => {
+                let approach = approach.unwrap_or(0.0);
+                sprite.color.set_alpha(approach * 0.3);
+                transform.scale = Vec3::splat(1.0 + approach * 2.0);
+            }
+            ObjectPart::Selection | ObjectPart::ComboNumber => {}
+        }
+    }
 
-            let radius = 20.0 * editor_state.playfield_zoom;
+    for obj in &beatmap.hit_objects[range] {
+        if !kept.contains(&obj.id) {
+            let is_selected = selected.contains(&obj.id);
+            let fade = object_fade(obj.time - current_time, approach_time);
+            spawn_editor_hit_object(&mut commands, &assets, obj, is_selected, zoom, fade);
+        }
+    }
+}
 
-            // Draw approach circle
-            if time_diff > 0.0 {
-                let approach_scale = (time_diff / approach_time) as f32;
-                let approach_radius = radius * (1.0 + approach_scale * 2.0);
+/// Alpha of an object and, before its hit time, how far out its approach circle is
+/// (1 when it appears, 0 at the hit time)
+fn object_fade(time_diff: f64, approach_time: f64) -> (f32, Option<f32>) {
+    let alpha = if time_diff < 0.0 {
+        1.0 - ((-time_diff) / FADE_OUT_TIME) as f32
+    } else {
+        1.0
+    };
+    let approach = (time_diff > 0.0).then(|| (time_diff / approach_time) as f32);
+    (alpha, approach)
+}
 
-                commands.spawn((
-                    Sprite {
-                        color: Color::srgba(
-                            color.to_linear().red,
-                            color.to_linear().green,
-                            color.to_linear().blue,
-                            approach_scale * 0.3,
-                        ),
-                        custom_size: Some(Vec2::new(approach_radius * 2.0, approach_radius * 2.0)),
-                        ..default()
-                    },
-                    Transform::from_xyz(obj.position.x, obj.position.y, 0.1),
-                    UiElement,
-                ));
+/// Spawn the entities of one playfield object: body, slider path, approach circle,
+/// selection ring and combo number
+fn spawn_editor_hit_object(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    obj: &HitObject,
+    is_selected: bool,
+    zoom: f32,
+    (alpha, approach): (f32, Option<f32>),
+) {
+    let color = match obj.kind {
+        _ if is_selected => NEON_GREEN,
+        HitObjectKind::Circle if obj.new_combo => NEON_PINK,
+        HitObjectKind::Circle => NEON_BLUE,
+        HitObjectKind::Slider { .. } => NEON_PURPLE,
+        HitObjectKind::Spinner { .. } => NEON_YELLOW,
+    };
+    let radius = 20.0 * zoom;
+    let part = |part| EditorHitObject { id: obj.id, part };
+
+    // Draw slider body along its path, cut to the pixel length so SV edits show
+    if let HitObjectKind::Slider {
+        control_points,
+        pixel_length,
+        ..
+    } = &obj.kind
+    {
+        let mut remaining = *pixel_length as f32;
+        for pair in control_points.windows(2) {
+            if remaining <= 0.0 {
+                break;
             }
-
-            // Draw object
+            let segment = pair[1] - pair[0];
+            let length = segment.length().min(remaining);
+            if length <= 0.0 {
+                continue;
+            }
+            remaining -= length;
+            let direction = segment.normalize();
+            let center = pair[0] + direction * length / 2.0;
             commands.spawn((
                 Sprite {
-                    color: Color::srgba(
-                        color.to_linear().red,
-                        color.to_linear().green,
-                        color.to_linear().blue,
-                        alpha,
-                    ),
-                    custom_size: Some(Vec2::new(radius * 2.0, radius * 2.0)),
+                    color: color.with_alpha(alpha * 0.35),
+                    custom_size: Some(Vec2::new(length, radius * 2.0)),
                     ..default()
                 },
-                Transform::from_xyz(obj.position.x, obj.position.y, 0.2),
+                Transform::from_xyz(center.x, center.y, 0.12)
+                    .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x))),
                 UiElement,
-                EditorHitObject { id: obj.id },
+                part(ObjectPart::SliderBody),
             ));
-
-            // Draw selection indicator
-            if is_selected {
-                commands.spawn((
-                    Sprite {
-                        color: Color::srgba(0.0, 1.0, 0.5, 0.5),
-                        custom_size: Some(Vec2::new(radius * 2.5, radius * 2.5)),
-                        ..default()
-                    },
-                    Transform::from_xyz(obj.position.x, obj.position.y, 0.15),
-                    UiElement,
-                ));
-            }
-
-            // Draw combo number
-            if obj.combo_index > 0 {
-                commands.spawn((
-                    Text2d::new(obj.combo_index.to_string()),
-                    TextFont {
-                        font: assets.cyberpunk_font.clone(),
-                        font_size: 12.0 * editor_state.playfield_zoom,
-                        ..default()
-                    },
-                    TextColor(Color::WHITE.into()),
-                    Transform::from_xyz(obj.position.x, obj.position.y, 0.3),
-                    UiElement,
-                ));
-            }
         }
     }
-}
-
-// Component markers
-#[derive(Component)]
-pub struct EditorToolbar;
 
-#[derive(Component)]
-pub struct ToolButton {
-    pub tool: EditorTool,
-}
+    // Approach circle, sized by its scale so it can shrink without respawning
+    let approach = approach.unwrap_or(0.0);
+    commands.spawn((
+        Sprite {
+            color: color.with_alpha(approach * 0.3),
+            custom_size: Some(Vec2::splat(radius * 2.0)),
+            ..default()
+        },
+        Transform::from_xyz(obj.position.x, obj.position.y, 0.1)
+            .with_scale(Vec3::splat(1.0 + approach * 2.0)),
+        UiElement,
+        part(ObjectPart::Approach),
+    ));
 
-#[derive(Component)]
-pub enum PlaybackButton {
-    PlayPause,
-    Previous,
-    Next,
-    Stop,
-}
+    // Draw object
+    commands.spawn((
+        Sprite {
+            color: color.with_alpha(alpha),
+            custom_size: Some(Vec2::splat(radius * 2.0)),
+            ..default()
+        },
+        Transform::from_xyz(obj.position.x, obj.position.y, 0.2),
+        UiElement,
+        part(ObjectPart::Body),
+        DrawnObject {
+            object: obj.clone(),
+            selected: is_selected,
+            zoom,
+        },
+    ));
 
-#[derive(Component)]
-pub struct BeatDivisorDisplay;
+    // Draw selection indicator
+    if is_selected {
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(0.0, 1.0, 0.5, 0.5),
+                custom_size: Some(Vec2::splat(radius * 2.5)),
+                ..default()
+            },
+            Transform::from_xyz(obj.position.x, obj.position.y, 0.15),
+            UiElement,
+            part(ObjectPart::Selection),
+        ));
+    }
 
-#[derive(Component)]
-pub struct SnapToggleButton;
+    // Draw combo number
+    if obj.combo_index > 0 {
+        commands.spawn((
+            Text2d::new(obj.combo_index.to_string()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 12.0 * zoom,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(obj.position.x, obj.position.y, 0.3),
+            UiElement,
+            part(ObjectPart::ComboNumber),
+        ));
+    }
+}
 
-#[derive(Component)]
-pub struct LeftPanel;
+/// Height of an unselected object tick on the timeline
+const TIMELINE_TICK_HEIGHT: f32 = 14.0;
 
-#[derive(Component)]
-pub struct LeftPanelTab {
-    pub tab: EditorLeftTab,
+/// Where an object's tick (and, for sliders, bar and end handle) sit on the timeline
+struct TickLayout {
+    x: f32,
+    /// Right edge of a slider's bar
+    slider_end: Option<f32>,
+    color: Color,
+    height: f32,
+    z: f32,
 }
 
-#[derive(Component)]
-pub struct NewComboToggle;
+/// Draw hit objects on the timeline, sliders as bars spanning their duration, with a
+/// tooltip for the hovered or dragged object. Dragged objects are drawn at their
+/// candidate times. Ticks of objects that stay in view are moved rather than
+/// respawned, so scrolling and zooming only spawn what scrolls into view
+#[allow(clippy::too_many_arguments)]
+pub fn draw_editor_timeline_objects(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    beatmap_assets: Res<crate::beatmap::BeatmapAssets>,
+    mut existing: Query<(Entity, &TimelineObject, &mut Transform, &mut Sprite)>,
+    tooltips: Query<Entity, With<TimelineTooltip>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() && !beatmap_assets.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
 
-#[derive(Component)]
-pub struct GridToggle;
+    for entity in tooltips.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 
-#[derive(Component)]
-pub struct RightPanel;
+    let screen_w = window.width();
+    let screen_h = window.height();
+    let timeline_y = -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0;
+    let zoom = editor_state.timeline_zoom;
+    let scroll = editor_state.timeline_scroll;
+    let visible_start = crate::editor::timeline_pos_to_time(0.0, zoom, scroll);
+    let visible_end = crate::editor::timeline_pos_to_time(screen_w, zoom, scroll);
+    let to_x = |time: f64| crate::editor::time_to_timeline_pos(time, zoom, scroll) - screen_w / 2.0;
+
+    let drag = editor_state.timeline_drag;
+    let selected: HashSet<HitObjectId> = editor_state.selected_objects.iter().copied().collect();
+    let beatmap = beatmap_assets.current();
+    let tooltip = beatmap.and_then(|beatmap| editor_state.timeline_tooltip(beatmap));
+    let mut tooltip_x = None;
+    let mut layouts: HashMap<HitObjectId, TickLayout> = HashMap::new();
+
+    // Only objects the index places near the visible span, widened by a drag's shift
+    let reach = drag.map_or(0.0, |drag| drag.shift().abs());
+    let nearby = beatmap
+        .zip(beatmap_assets.object_index())
+        .map_or(&[][..], |(beatmap, index)| {
+            &beatmap.hit_objects[index.overlapping(visible_start - reach, visible_end + reach)]
+        });
+    for obj in nearby {
+        let is_selected = selected.contains(&obj.id);
+        let mut start = obj.time;
+        let mut end = beatmap.map_or(start, |beatmap| beatmap.object_end_time(obj));
+        match drag.map(|drag| (drag.grab, drag.shift())) {
+            Some((TimelineGrab::Object(_), shift)) if is_selected => {
+                start += shift;
+                end += shift;
+            }
+            Some((TimelineGrab::SliderEnd(id), shift)) if id == obj.id => end += shift,
+            _ => {}
+        }
+        if end < visible_start || start > visible_end {
+            continue;
+        }
 
-#[derive(Component)]
-pub struct RightPanelTab {
-    pub tab: EditorRightTab,
-}
+        let x = to_x(start);
+        let layout = TickLayout {
+            x,
+            slider_end: matches!(obj.kind, HitObjectKind::Slider { .. }).then(|| to_x(end)),
+            color: match obj.kind {
+                HitObjectKind::Circle => NEON_BLUE,
+                HitObjectKind::Slider { .. } => NEON_PURPLE,
+                HitObjectKind::Spinner { .. } => NEON_YELLOW,
+            },
+            height: if is_selected {
+                TIMELINE_TICK_HEIGHT + 6.0
+            } else {
+                TIMELINE_TICK_HEIGHT
+            },
+            z: if is_selected { 0.25 } else { 0.2 },
+        };
 
-#[derive(Component)]
-pub struct Timeline;
+        if let Some((id, _)) = &tooltip {
+            if *id == obj.id {
+                tooltip_x = Some(match drag {
+                    Some(drag) if matches!(drag.grab, TimelineGrab::SliderEnd(_)) => to_x(end),
+                    _ => x,
+                });
+            }
+        }
+        layouts.insert(obj.id, layout);
+    }
 
-#[derive(Component)]
-pub struct TimelineObject {
-    pub id: HitObjectId,
-}
+    // Move the parts of objects still in view, despawn the rest
+    let mut present: HashSet<(HitObjectId, TimelinePart)> = HashSet::new();
+    for (entity, object, mut transform, mut sprite) in existing.iter_mut() {
+        let Some(layout) = layouts.get(&object.id) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        if object.part != TimelinePart::Tick && layout.slider_end.is_none() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let (translation, sprite_part) = timeline_part(object.part, layout, timeline_y);
+        transform.translation = translation;
+        sprite.color = sprite_part.color;
+        sprite.custom_size = sprite_part.custom_size;
+        present.insert((object.id, object.part));
+    }
+
+    // Spawn the parts of objects that came into view
+    for (id, layout) in &layouts {
+        let parts: &[TimelinePart] = if layout.slider_end.is_some() {
+            &[
+                TimelinePart::Tick,
+                TimelinePart::SliderBar,
+                TimelinePart::SliderHandle,
+            ]
+        } else {
+            &[TimelinePart::Tick]
+        };
+        for &part in parts {
+            if present.contains(&(*id, part)) {
+                continue;
+            }
+            let (translation, sprite) = timeline_part(part, layout, timeline_y);
+            commands.spawn((
+                sprite,
+                Transform::from_translation(translation),
+                UiElement,
+                TimelineObject { id: *id, part },
+            ));
+        }
+    }
+
+    if let (Some((_, text)), Some(x)) = (tooltip, tooltip_x) {
+        let half_w = screen_w / 2.0 - 80.0;
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 12.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(x.clamp(-half_w, half_w), timeline_y + 24.0, 0.4),
+            UiElement,
+            TimelineTooltip,
+        ));
+    }
+}
+
+/// Position and sprite of one part of an object on the timeline. Slider parts are
+/// only asked for when the layout has a slider end
+fn timeline_part(part: TimelinePart, layout: &TickLayout, timeline_y: f32) -> (Vec3, Sprite) {
+    let right = layout.slider_end.unwrap_or(layout.x);
+    let (x, z, color, size) = match part {
+        TimelinePart::Tick => (
+            layout.x,
+            layout.z,
+            layout.color,
+            Vec2::new(4.0, layout.height),
+        ),
+        TimelinePart::SliderBar => (
+            (layout.x + right) / 2.0,
+            layout.z - 0.01,
+            layout.color.with_alpha(0.45),
+            Vec2::new((right - layout.x).max(4.0), layout.height * 0.6),
+        ),
+        // Right-edge handle for dragging the duration
+        TimelinePart::SliderHandle => {
+            (right, layout.z, layout.color, Vec2::new(2.0, layout.height))
+        }
+    };
+    let sprite = Sprite {
+        color,
+        custom_size: Some(size),
+        ..default()
+    };
+    (Vec3::new(x, timeline_y, z), sprite)
+}
+
+/// Marker for timing point markers and the Timing panel list, rebuilt on change
+#[derive(Component)]
+pub struct TimingElement;
+
+/// Draw timing points as red (BPM) and green (SV) markers on the timeline and list
+/// them in the Timing panel
+pub fn draw_editor_timing(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    beatmap_assets: Res<crate::beatmap::BeatmapAssets>,
+    existing: Query<Entity, With<TimingElement>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() && !beatmap_assets.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+    let screen_w = window.width();
+    let screen_h = window.height();
+
+    // Timeline markers
+    let timeline_y = -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0;
+    let zoom = editor_state.timeline_zoom;
+    let scroll = editor_state.timeline_scroll;
+    for point in &beatmap.timing_points {
+        let x = crate::editor::time_to_timeline_pos(point.time, zoom, scroll) - screen_w / 2.0;
+        if x < -screen_w / 2.0 || x > screen_w / 2.0 {
+            continue;
+        }
+
+        let color = if point.inherited {
+            INHERITED_POINT_COLOR
+        } else {
+            UNINHERITED_POINT_COLOR
+        };
+        commands.spawn((
+            Sprite {
+                color,
+                custom_size: Some(Vec2::new(2.0, editor_ui.timeline_height)),
+                ..default()
+            },
+            Transform::from_xyz(x, timeline_y, 0.17),
+            UiElement,
+            TimingElement,
+        ));
+
+        if point.inherited {
+            commands.spawn((
+                Text2d::new(format!("{:.2}x", point.slider_velocity)),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 9.0,
+                    ..default()
+                },
+                TextColor(color),
+                Transform::from_xyz(x + 14.0, timeline_y + editor_ui.timeline_height / 2.0 - 8.0, 0.3),
+                UiElement,
+                TimingElement,
+            ));
+        }
+    }
+
+    // Timing panel list
+    if !editor_ui.left_panel_visible || editor_ui.left_panel_tab != EditorLeftTab::Timing {
+        return;
+    }
+    let panel_x = -screen_h / 2.0 + editor_ui.left_panel_width / 2.0;
+    let mut y = 55.0;
+    for point in beatmap.timing_points.iter().take(12) {
+        let (label, color) = if point.inherited {
+            (
+                format!("{:>8.3}s  SV {:.2}x", point.time, point.slider_velocity),
+                INHERITED_POINT_COLOR,
+            )
+        } else {
+            (
+                format!("{:>8.3}s  {:.1} BPM {}/4", point.time, point.bpm, point.meter),
+                UNINHERITED_POINT_COLOR,
+            )
+        };
+        commands.spawn((
+            Text2d::new(label),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 10.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(panel_x, y, 0.3),
+            UiElement,
+            TimingElement,
+        ));
+        y -= 16.0;
+    }
+
+    commands.spawn((
+        Text2d::new(format!(
+            "New SV: {:.2}x  ([ / ] adjust, I add)",
+            editor_state.slider_velocity_input
+        )),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 10.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+        Transform::from_xyz(panel_x, y - 10.0, 0.3),
+        UiElement,
+        TimingElement,
+    ));
+}
+
+/// Color of practice hint bands and labels
+pub const PRACTICE_HINT_COLOR: Color = Color::srgba(0.0, 0.9, 1.0, 1.0);
+
+/// Color of practice hint warnings
+pub const PRACTICE_WARNING_COLOR: Color = Color::srgba(1.0, 0.6, 0.0, 1.0);
+
+/// Height of one row in the Practice panel
+pub const PRACTICE_ROW_HEIGHT: f32 = 16.0;
+
+/// Most hints listed in the Practice panel at once
+pub const PRACTICE_VISIBLE_HINTS: usize = 8;
+
+/// Marker for practice hint bands and the Practice panel contents, rebuilt on change
+#[derive(Component)]
+pub struct PracticeHintElement;
+
+/// A clickable row in the Practice panel
+#[derive(Component, Clone, Copy)]
+pub enum PracticeHintRow {
+    /// Selects the hint at this index of `Beatmap::practice_hints`
+    Hint(usize),
+    /// Edits a field of the selected hint
+    Field(HintField),
+}
+
+/// Draw practice hints as labelled bands along the top of the timeline, and list
+/// them with the selected hint's fields and any warnings in the Practice panel
+pub fn draw_editor_practice_hints(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    beatmap_assets: Res<crate::beatmap::BeatmapAssets>,
+    existing: Query<Entity, With<PracticeHintElement>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() && !beatmap_assets.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+    let screen_w = window.width();
+    let screen_h = window.height();
+
+    // Timeline bands, with the hint being placed drawn fainter
+    let timeline_y = -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0;
+    let band_y = timeline_y + editor_ui.timeline_height / 2.0 - 5.0;
+    let zoom = editor_state.timeline_zoom;
+    let scroll = editor_state.timeline_scroll;
+    let visible_start = crate::editor::timeline_pos_to_time(0.0, zoom, scroll);
+    let visible_end = crate::editor::timeline_pos_to_time(screen_w, zoom, scroll);
+    let pending = editor_state.hint_start.map(|start| {
+        (
+            start.min(editor_state.current_time),
+            start.max(editor_state.current_time),
+            String::new(),
+            0.2,
+        )
+    });
+    for (start, end, label, alpha) in beatmap
+        .practice_hints
+        .iter()
+        .map(|hint| {
+            let label = format!("{} {:.2}x", hint.label, hint.rate);
+            (hint.start, hint.end, label, 0.5)
+        })
+        .chain(pending)
+    {
+        if end < visible_start || start > visible_end {
+            continue;
+        }
+        let left = crate::editor::time_to_timeline_pos(start.max(visible_start), zoom, scroll);
+        let right = crate::editor::time_to_timeline_pos(end.min(visible_end), zoom, scroll);
+        commands.spawn((
+            Sprite {
+                color: PRACTICE_HINT_COLOR.with_alpha(alpha),
+                custom_size: Some(Vec2::new((right - left).max(1.0), 6.0)),
+                ..default()
+            },
+            Transform::from_xyz((left + right) / 2.0 - screen_w / 2.0, band_y, 0.16),
+            UiElement,
+            PracticeHintElement,
+        ));
+        if !label.is_empty() {
+            commands.spawn((
+                Text2d::new(label),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 9.0,
+                    ..default()
+                },
+                TextColor(PRACTICE_HINT_COLOR),
+                Transform::from_xyz((left + right) / 2.0 - screen_w / 2.0, band_y - 10.0, 0.3),
+                UiElement,
+                PracticeHintElement,
+            ));
+        }
+    }
+
+    if !editor_ui.left_panel_visible || editor_ui.left_panel_tab != EditorLeftTab::Practice {
+        return;
+    }
+    let panel_x = -screen_h / 2.0 + editor_ui.left_panel_width / 2.0;
+    let mut y = 55.0;
+    let mut rows: Vec<(PracticeHintRow, String, bool)> = beatmap
+        .practice_hints
+        .iter()
+        .enumerate()
+        .take(PRACTICE_VISIBLE_HINTS)
+        .map(|(index, hint)| {
+            (
+                PracticeHintRow::Hint(index),
+                hint.summary(),
+                editor_state.selected_hint == Some(index),
+            )
+        })
+        .collect();
+    if let Some(hint) = editor_state
+        .selected_hint
+        .and_then(|index| beatmap.practice_hints.get(index))
+    {
+        for field in HintField::all() {
+            let editing = matches!(&editor_state.hint_edit, Some(edit) if edit.field == field);
+            let value = if editing {
+                String::new()
+            } else {
+                field.value(hint)
+            };
+            rows.push((
+                PracticeHintRow::Field(field),
+                format!("  {}: {}", field.display_name(), value),
+                editing,
+            ));
+        }
+    }
+
+    for (row, label, highlighted) in rows {
+        let background = if highlighted {
+            Color::srgba(1.0, 0.08, 0.58, 0.35)
+        } else {
+            Color::srgba(1.0, 1.0, 1.0, 0.04)
+        };
+        let input = match (&editor_state.hint_edit, row) {
+            (Some(edit), PracticeHintRow::Field(field)) if edit.field == field => Some(&edit.input),
+            _ => None,
+        };
+        commands
+            .spawn((
+                Sprite {
+                    color: background,
+                    custom_size: Some(Vec2::new(
+                        editor_ui.left_panel_width - 20.0,
+                        PRACTICE_ROW_HEIGHT - 2.0,
+                    )),
+                    ..default()
+                },
+                Transform::from_xyz(panel_x, y, 0.25),
+                UiElement,
+                PracticeHintElement,
+                row,
+            ))
+            .with_children(|parent| {
+                let font = TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                };
+                let mut text = parent.spawn((
+                    Text2d::new(label),
+                    font.clone(),
+                    TextColor(Color::WHITE),
+                    Transform::from_xyz(0.0, 0.0, 0.05),
+                ));
+                if let Some(input) = input {
+                    add_input_spans(&mut text, input, true, &font, Color::WHITE);
+                }
+            });
+        y -= PRACTICE_ROW_HEIGHT;
+    }
+
+    let warnings = beatmap.practice_hint_warnings();
+    let help = if editor_state.hint_edit.is_some() {
+        "Enter apply, ESC cancel"
+    } else if beatmap.practice_hints.is_empty() {
+        "P at the playhead: start, then end"
+    } else {
+        "P add/remove, click a hint to edit"
+    };
+    for (text, color) in warnings
+        .iter()
+        .map(|warning| (warning.as_str(), PRACTICE_WARNING_COLOR))
+        .chain(std::iter::once((help, Color::srgba(1.0, 1.0, 1.0, 0.6))))
+    {
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 9.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(panel_x, y - 4.0, 0.3),
+            UiElement,
+            PracticeHintElement,
+        ));
+        y -= PRACTICE_ROW_HEIGHT;
+    }
+}
+
+/// Color of comment markers and open comments; resolved ones are drawn faded
+pub const COMMENT_COLOR: Color = Color::srgba(1.0, 0.85, 0.2, 1.0);
+
+/// Most comments listed in the Comments panel at once, scrolled to keep the selected
+/// one in view
+pub const COMMENTS_VISIBLE: usize = 10;
+
+/// Characters of a comment's text shown in its row
+const COMMENT_PREVIEW_CHARS: usize = 22;
+
+/// Marker for comment markers and the Comments panel contents, rebuilt on change
+#[derive(Component)]
+pub struct CommentElement;
+
+/// A clickable row in the Comments panel
+#[derive(Component, Clone, Copy)]
+pub enum CommentRow {
+    /// Starts writing a comment at the playhead
+    Add,
+    /// Selects the comment at this index of `MapComments::comments` and jumps to it
+    Comment(usize),
+    /// Resolves or reopens the selected comment
+    Resolve,
+    /// Deletes the selected comment
+    Delete,
+}
+
+/// Draw comment markers on the timeline, and list the comments in the Comments panel
+/// with actions for the selected one
+pub fn draw_editor_comments(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    existing: Query<Entity, With<CommentElement>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let screen_w = window.width();
+    let screen_h = window.height();
+    let comments = &editor_state.comments.comments;
+
+    // Diamonds along the top of the timeline, the comment being written drawn fainter
+    let timeline_y = -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0;
+    let marker_y = timeline_y + comment_marker_y(editor_ui.timeline_height);
+    let zoom = editor_state.timeline_zoom;
+    let scroll = editor_state.timeline_scroll;
+    let markers = comments
+        .iter()
+        .enumerate()
+        .map(|(index, comment)| {
+            let alpha = if comment.resolved { 0.35 } else { 1.0 };
+            let selected = editor_state.selected_comment == Some(index);
+            (comment.time, alpha, selected)
+        })
+        .chain(
+            editor_state
+                .comment_draft
+                .as_ref()
+                .map(|draft| (draft.time, 0.5, false)),
+        );
+    for (time, alpha, selected) in markers {
+        let x = crate::editor::time_to_timeline_pos(time, zoom, scroll);
+        if !(0.0..=screen_w).contains(&x) {
+            continue;
+        }
+        let size = if selected {
+            COMMENT_MARKER_SIZE + 4.0
+        } else {
+            COMMENT_MARKER_SIZE
+        };
+        commands.spawn((
+            Sprite {
+                color: COMMENT_COLOR.with_alpha(alpha),
+                custom_size: Some(Vec2::splat(size)),
+                ..default()
+            },
+            Transform::from_xyz(x - screen_w / 2.0, marker_y, 0.3)
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            UiElement,
+            CommentElement,
+        ));
+    }
+
+    if !editor_ui.left_panel_visible || editor_ui.left_panel_tab != EditorLeftTab::Comments {
+        return;
+    }
+    let panel_x = -screen_h / 2.0 + editor_ui.left_panel_width / 2.0;
+    let mut y = 55.0;
+
+    let add_label = match &editor_state.comment_draft {
+        Some(draft) => format!("@ {}: ", format_time(draft.time)),
+        None => "+ Comment at playhead".to_string(),
+    };
+    let mut rows = vec![(
+        CommentRow::Add,
+        add_label,
+        Color::WHITE,
+        editor_state.comment_draft.is_some(),
+    )];
+    let first = editor_state
+        .selected_comment
+        .map_or(0, |index| (index + 1).saturating_sub(COMMENTS_VISIBLE));
+    for (index, comment) in comments
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(COMMENTS_VISIBLE)
+    {
+        let mut text: String = comment.text.chars().take(COMMENT_PREVIEW_CHARS).collect();
+        if comment.text.chars().count() > COMMENT_PREVIEW_CHARS {
+            text.push_str("...");
+        }
+        let color = if comment.resolved {
+            Color::srgba(1.0, 1.0, 1.0, 0.4)
+        } else {
+            COMMENT_COLOR
+        };
+        rows.push((
+            CommentRow::Comment(index),
+            format!(
+                "{} {} {}: {}",
+                if comment.resolved { "[x]" } else { "[ ]" },
+                format_time(comment.time),
+                comment.author,
+                text
+            ),
+            color,
+            editor_state.selected_comment == Some(index),
+        ));
+    }
+    if let Some(comment) = editor_state
+        .selected_comment
+        .and_then(|index| comments.get(index))
+    {
+        let resolve = if comment.resolved {
+            "  Reopen"
+        } else {
+            "  Resolve"
+        };
+        for (row, label) in [
+            (CommentRow::Resolve, resolve),
+            (CommentRow::Delete, "  Delete"),
+        ] {
+            rows.push((row, label.to_string(), Color::WHITE, false));
+        }
+    }
+
+    for (row, label, color, highlighted) in rows {
+        let background = if highlighted {
+            Color::srgba(1.0, 0.08, 0.58, 0.35)
+        } else {
+            Color::srgba(1.0, 1.0, 1.0, 0.04)
+        };
+        let input = match (&editor_state.comment_draft, row) {
+            (Some(draft), CommentRow::Add) => Some(&draft.input),
+            _ => None,
+        };
+        commands
+            .spawn((
+                Sprite {
+                    color: background,
+                    custom_size: Some(Vec2::new(
+                        editor_ui.left_panel_width - 20.0,
+                        PRACTICE_ROW_HEIGHT - 2.0,
+                    )),
+                    ..default()
+                },
+                Transform::from_xyz(panel_x, y, 0.25),
+                UiElement,
+                CommentElement,
+                row,
+            ))
+            .with_children(|parent| {
+                let font = TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                };
+                let mut text = parent.spawn((
+                    Text2d::new(label),
+                    font.clone(),
+                    TextColor(color),
+                    Transform::from_xyz(0.0, 0.0, 0.05),
+                ));
+                if let Some(input) = input {
+                    add_input_spans(&mut text, input, true, &font, Color::WHITE);
+                }
+            });
+        y -= PRACTICE_ROW_HEIGHT;
+    }
+
+    let open = editor_state.comments.open_count();
+    let help = if editor_state.comment_draft.is_some() {
+        "Enter add, ESC cancel".to_string()
+    } else {
+        format!(
+            "{} open, {} resolved | click a comment or marker",
+            open,
+            comments.len() - open
+        )
+    };
+    commands.spawn((
+        Text2d::new(help),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 9.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+        Transform::from_xyz(panel_x, y - 4.0, 0.3),
+        UiElement,
+        CommentElement,
+    ));
+}
+
+/// Height of one row in the Properties panel field list
+pub const PROPERTY_ROW_HEIGHT: f32 = 18.0;
+
+/// Marker for the Properties panel field list, rebuilt on change
+#[derive(Component)]
+pub struct PropertyElement;
+
+/// A clickable row in the Properties panel
+#[derive(Component, Clone, Copy)]
+pub enum PropertyRow {
+    /// Edits or toggles a field of the selected objects
+    Field(ObjectField),
+    /// Switches positions between pixels and normalized values
+    Units,
+}
+
+/// List the selected objects' fields in the Properties panel, with the field being
+/// typed in highlighted
+pub fn draw_editor_properties(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    beatmap_assets: Res<crate::beatmap::BeatmapAssets>,
+    existing: Query<Entity, With<PropertyElement>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() && !beatmap_assets.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !editor_ui.right_panel_visible || editor_ui.right_panel_tab != EditorRightTab::Properties {
+        return;
+    }
+    let Some(beatmap) = beatmap_assets.current() else {
+        return;
+    };
+    let fields = editor_state.property_fields(beatmap);
+    if fields.is_empty() {
+        return;
+    }
+
+    let panel_x = window.width() / 2.0 - editor_ui.right_panel_width / 2.0;
+    let mut y = editor_ui.right_panel_width / 2.0 - 145.0;
+    let units = if editor_state.property_normalized {
+        "normalized"
+    } else {
+        "px"
+    };
+    let mut rows = vec![(PropertyRow::Units, format!("Units: {}", units), false)];
+    for field in fields {
+        let (value, editing) = match &editor_state.property_edit {
+            Some(edit) if edit.field == field => (String::new(), true),
+            _ => (editor_state.shared_property(beatmap, field), false),
+        };
+        rows.push((
+            PropertyRow::Field(field),
+            format!("{}: {}", field.display_name(), value),
+            editing,
+        ));
+    }
+
+    for (row, label, editing) in rows {
+        let background = if editing {
+            Color::srgba(1.0, 0.08, 0.58, 0.35)
+        } else {
+            Color::srgba(1.0, 1.0, 1.0, 0.04)
+        };
+        // The field being typed into is drawn with its cursor and selection
+        let input = match (&editor_state.property_edit, row) {
+            (Some(edit), PropertyRow::Field(field)) if edit.field == field => Some(&edit.input),
+            _ => None,
+        };
+        commands
+            .spawn((
+                Sprite {
+                    color: background,
+                    custom_size: Some(Vec2::new(
+                        editor_ui.right_panel_width - 20.0,
+                        PROPERTY_ROW_HEIGHT - 2.0,
+                    )),
+                    ..default()
+                },
+                Transform::from_xyz(panel_x, y, 0.25),
+                UiElement,
+                PropertyElement,
+                row,
+            ))
+            .with_children(|parent| {
+                let font = TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                };
+                let mut text = parent.spawn((
+                    Text2d::new(label),
+                    font.clone(),
+                    TextColor(Color::WHITE),
+                    Transform::from_xyz(0.0, 0.0, 0.05),
+                ));
+                if let Some(input) = input {
+                    add_input_spans(&mut text, input, true, &font, Color::WHITE);
+                }
+            });
+        y -= PROPERTY_ROW_HEIGHT;
+    }
+
+    let hint = if editor_state.property_edit.is_some() {
+        "Enter apply, Tab next, ESC cancel"
+    } else {
+        "Click a field to edit"
+    };
+    commands.spawn((
+        Text2d::new(hint),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 9.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+        Transform::from_xyz(panel_x, y - 4.0, 0.3),
+        UiElement,
+        PropertyElement,
+    ));
+}
+
+/// Height of one row in the Metadata panel
+pub const METADATA_ROW_HEIGHT: f32 = 18.0;
+
+/// Color of metadata validation problems
+pub const METADATA_ERROR_COLOR: Color = Color::srgba(1.0, 0.25, 0.25, 1.0);
+
+/// Color of difficulty guideline warnings, which never block saving
+pub const GUIDELINE_COLOR: Color = Color::srgba(1.0, 0.65, 0.15, 1.0);
+
+/// Guideline warnings listed in the Metadata panel; the rest are counted
+pub const GUIDELINE_LISTED: usize = 8;
+
+/// Marker for the Metadata panel contents, rebuilt on change
+#[derive(Component)]
+pub struct MetadataElement;
+
+/// A clickable row in the Metadata panel
+#[derive(Component, Clone, Copy)]
+pub enum MetadataRow {
+    /// Edits a metadata field, or picks the next audio file
+    Field(MetadataField),
+    /// Moves the preview point to the playhead
+    PreviewFromPlayhead,
+    /// Plays a clip from the preview point
+    PlayPreview,
+    /// Copies the difficulty settings of the set member at this index of
+    /// `BeatmapAssets::set_members`
+    CopySettingsFrom(usize),
+    /// Shows what propagating the shared metadata to the set would change
+    PropagateToSet,
+}
+
+/// Draw the Metadata panel: editable fields with their problems underneath in red,
+/// the preview buttons, and every problem again in the validation list followed by the
+/// difficulty guideline warnings
+#[allow(clippy::too_many_arguments)]
+pub fn draw_editor_metadata(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    beatmap_assets: Res<crate::beatmap::BeatmapAssets>,
+    guidelines: Res<GuidelineReport>,
+    existing: Query<Entity, With<MetadataElement>>,
+) {
+    if !editor_state.is_changed()
+        && !editor_ui.is_changed()
+        && !beatmap_assets.is_changed()
+        && !guidelines.is_changed()
+    {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !editor_ui.right_panel_visible || editor_ui.right_panel_tab != EditorRightTab::Metadata {
+        return;
+    }
+    let (Some(path), Some(beatmap)) = (&beatmap_assets.current_beatmap, beatmap_assets.current())
+    else {
+        return;
+    };
+    let problems = MetadataSnapshot::of(beatmap).problems(
+        &beatmap_assets.sibling_versions(path),
+        editor_state.audio_duration,
+    );
+
+    let panel_x = window.width() / 2.0 - editor_ui.right_panel_width / 2.0;
+    let mut y = editor_ui.right_panel_width / 2.0 - 50.0;
+    let note = |commands: &mut Commands, text: String, color: Color, y: f32| {
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 9.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(panel_x, y, 0.3),
+            UiElement,
+            MetadataElement,
+        ));
+    };
+
+    let mut rows = Vec::new();
+    for field in MetadataField::all() {
+        let (value, editing) = match &editor_state.metadata_edit {
+            Some(edit) if edit.field == field => (String::new(), true),
+            _ => (field.value(beatmap), false),
+        };
+        rows.push((
+            MetadataRow::Field(field),
+            format!("{}: {}", field.display_name(), value),
+            editing,
+        ));
+    }
+    rows.push((
+        MetadataRow::PreviewFromPlayhead,
+        "Set preview from playhead".to_string(),
+        false,
+    ));
+    rows.push((
+        MetadataRow::PlayPreview,
+        format!("Play preview ({}s)", PREVIEW_CLIP_SECONDS),
+        false,
+    ));
+    let set_members = beatmap_assets.set_members(path);
+    for (i, (_, other)) in set_members.iter().enumerate() {
+        rows.push((
+            MetadataRow::CopySettingsFrom(i),
+            format!("Copy difficulty settings from {}", other.metadata.version),
+            false,
+        ));
+    }
+    if !set_members.is_empty() {
+        rows.push((
+            MetadataRow::PropagateToSet,
+            "Propagate metadata to set".to_string(),
+            editor_state.pending_propagation.is_some(),
+        ));
+    }
+
+    for (row, label, editing) in rows {
+        let background = if editing {
+            Color::srgba(1.0, 0.08, 0.58, 0.35)
+        } else {
+            Color::srgba(1.0, 1.0, 1.0, 0.04)
+        };
+        // The field being typed into is drawn with its cursor and selection
+        let input = match (&editor_state.metadata_edit, row) {
+            (Some(edit), MetadataRow::Field(field)) if edit.field == field => Some(&edit.input),
+            _ => None,
+        };
+        commands
+            .spawn((
+                Sprite {
+                    color: background,
+                    custom_size: Some(Vec2::new(
+                        editor_ui.right_panel_width - 20.0,
+                        METADATA_ROW_HEIGHT - 2.0,
+                    )),
+                    ..default()
+                },
+                Transform::from_xyz(panel_x, y, 0.25),
+                UiElement,
+                MetadataElement,
+                row,
+            ))
+            .with_children(|parent| {
+                let font = TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 10.0,
+                    ..default()
+                };
+                let mut text = parent.spawn((
+                    Text2d::new(label),
+                    font.clone(),
+                    TextColor(Color::WHITE),
+                    Transform::from_xyz(0.0, 0.0, 0.05),
+                ));
+                if let Some(input) = input {
+                    add_input_spans(&mut text, input, true, &font, Color::WHITE);
+                }
+            });
+        y -= METADATA_ROW_HEIGHT;
+
+        if let MetadataRow::Field(field) = row {
+            for (_, message) in problems.iter().filter(|(f, _)| *f == field) {
+                note(
+                    &mut commands,
+                    message.clone(),
+                    METADATA_ERROR_COLOR,
+                    y + 3.0,
+                );
+                y -= 12.0;
+            }
+        }
+    }
+
+    // What a pending propagation will change, file by file
+    if let Some(plan) = &editor_state.pending_propagation {
+        y -= 8.0;
+        for target in &plan.targets {
+            note(
+                &mut commands,
+                format!("{} ({})", target.version, target.path),
+                NEON_CYAN,
+                y,
+            );
+            for change in &target.changes {
+                y -= 12.0;
+                note(
+                    &mut commands,
+                    change.clone(),
+                    Color::srgba(1.0, 1.0, 1.0, 0.8),
+                    y,
+                );
+            }
+            y -= 14.0;
+        }
+        for (path, error) in &plan.errors {
+            note(
+                &mut commands,
+                format!("{}: {}", path, error),
+                METADATA_ERROR_COLOR,
+                y,
+            );
+            y -= 12.0;
+        }
+    }
+
+    // Validation list
+    y -= 8.0;
+    if problems.is_empty() {
+        note(&mut commands, "No problems".to_string(), SUCCESS_COLOR, y);
+    } else {
+        note(
+            &mut commands,
+            format!("Problems ({})", problems.len()),
+            METADATA_ERROR_COLOR,
+            y,
+        );
+        for (field, message) in &problems {
+            y -= 12.0;
+            note(
+                &mut commands,
+                format!("{}: {}", field.display_name(), message),
+                METADATA_ERROR_COLOR,
+                y,
+            );
+        }
+    }
+
+    // Guideline warnings for the tier the difficulty name matches
+    y -= 16.0;
+    match &guidelines.tier {
+        None => note(
+            &mut commands,
+            format!("Guidelines: no tier matches \"{}\"", guidelines.version),
+            Color::srgba(1.0, 1.0, 1.0, 0.6),
+            y,
+        ),
+        Some(tier) if guidelines.violations.is_empty() => note(
+            &mut commands,
+            format!("Guidelines ({}): within limits", tier),
+            SUCCESS_COLOR,
+            y,
+        ),
+        Some(tier) => {
+            note(
+                &mut commands,
+                format!("Guidelines ({}): {}", tier, guidelines.violations.len()),
+                GUIDELINE_COLOR,
+                y,
+            );
+            for violation in guidelines.violations.iter().take(GUIDELINE_LISTED) {
+                y -= 12.0;
+                let text = match violation.span {
+                    Some((start, _)) => format!("{} {}", format_time(start), violation.message),
+                    None => violation.message.clone(),
+                };
+                note(&mut commands, text, GUIDELINE_COLOR, y);
+            }
+            let hidden = guidelines.violations.len().saturating_sub(GUIDELINE_LISTED);
+            if hidden > 0 {
+                y -= 12.0;
+                note(
+                    &mut commands,
+                    format!("...and {} more", hidden),
+                    GUIDELINE_COLOR,
+                    y,
+                );
+            }
+        }
+    }
+
+    y -= 20.0;
+    let hint = if editor_state.metadata_edit.is_some() {
+        "Enter apply, Tab next, ESC cancel"
+    } else if editor_state.pending_propagation.is_some() {
+        "Enter write these files, ESC cancel"
+    } else if editor_state.dirty {
+        "Unsaved changes (Ctrl+S to save)"
+    } else {
+        "Click a field to edit"
+    };
+    note(
+        &mut commands,
+        hint.to_string(),
+        Color::srgba(1.0, 1.0, 1.0, 0.6),
+        y,
+    );
+}
+
+/// Height of the difficulty strip along the top edge of the timeline
+pub const GUIDELINE_STRIP_HEIGHT: f32 = 8.0;
+
+/// Distance between hatching stripes in the difficulty strip
+const GUIDELINE_HATCH_SPACING: f32 = 8.0;
+
+/// Marker for the difficulty strip, rebuilt on change
+#[derive(Component)]
+pub struct GuidelineElement;
+
+/// Draw the difficulty strip just above the timeline, hatching the regions that go past
+/// the difficulty's guidelines
+pub fn draw_editor_guidelines(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    guidelines: Res<GuidelineReport>,
+    existing: Query<Entity, With<GuidelineElement>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() && !guidelines.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let screen_w = window.width();
+    let screen_h = window.height();
+    let timeline_y = -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0;
+    let strip_y = timeline_y + editor_ui.timeline_height / 2.0 + GUIDELINE_STRIP_HEIGHT / 2.0;
+    let zoom = editor_state.timeline_zoom;
+    let scroll = editor_state.timeline_scroll;
+    let visible_start = crate::editor::timeline_pos_to_time(0.0, zoom, scroll);
+    let visible_end = crate::editor::timeline_pos_to_time(screen_w, zoom, scroll);
+
+    for (start, end) in guidelines.violations.iter().filter_map(|v| v.span) {
+        if end < visible_start || start > visible_end {
+            continue;
+        }
+        let left = crate::editor::time_to_timeline_pos(start.max(visible_start), zoom, scroll);
+        let right = crate::editor::time_to_timeline_pos(end.min(visible_end), zoom, scroll);
+        let width = (right - left).max(2.0);
+        commands.spawn((
+            Sprite {
+                color: GUIDELINE_COLOR.with_alpha(0.2),
+                custom_size: Some(Vec2::new(width, GUIDELINE_STRIP_HEIGHT)),
+                ..default()
+            },
+            Transform::from_xyz(left + width / 2.0 - screen_w / 2.0, strip_y, 0.3),
+            UiElement,
+            GuidelineElement,
+        ));
+        // Diagonal stripes, fixed to the screen so they don't crawl while scrolling
+        let first = (left / GUIDELINE_HATCH_SPACING).ceil() * GUIDELINE_HATCH_SPACING;
+        let stripes = ((left + width - first) / GUIDELINE_HATCH_SPACING).max(0.0) as usize + 1;
+        for stripe in 0..stripes {
+            let x = first + stripe as f32 * GUIDELINE_HATCH_SPACING;
+            if x > left + width {
+                break;
+            }
+            commands.spawn((
+                Sprite {
+                    color: GUIDELINE_COLOR.with_alpha(0.8),
+                    custom_size: Some(Vec2::new(1.5, GUIDELINE_STRIP_HEIGHT * 1.2)),
+                    ..default()
+                },
+                Transform::from_xyz(x - screen_w / 2.0, strip_y, 0.31)
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+                UiElement,
+                GuidelineElement,
+            ));
+        }
+    }
+}
+
+/// Width of one waveform bar on the timeline (pixels)
+pub const WAVEFORM_COLUMN_WIDTH: f32 = 2.0;
+
+/// Draw the song's waveform across the timeline, behind the beat lines. Bars are reused
+/// from the last draw, since playback changes the view every frame
+pub fn draw_editor_waveform(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    waveform: Res<EditorWaveform>,
+    mut existing: Query<(Entity, &mut Transform, &mut Sprite), With<TimelineWaveform>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() && !waveform.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    let screen_w = window.width();
+    let screen_h = window.height();
+    let timeline_y = -screen_h / 2.0 + editor_ui.timeline_height / 2.0 + 20.0;
+    let half_height = TIMELINE_WAVEFORM_HEIGHT.min(editor_ui.timeline_height) / 2.0;
+    let zoom = editor_state.timeline_zoom;
+    let scroll = editor_state.timeline_scroll;
+    let columns = (screen_w / WAVEFORM_COLUMN_WIDTH).ceil() as usize;
+    let span_w = columns as f32 * WAVEFORM_COLUMN_WIDTH;
+    let visible_start = crate::editor::timeline_pos_to_time(0.0, zoom, scroll);
+    let visible_end = crate::editor::timeline_pos_to_time(span_w, zoom, scroll);
+
+    // Center and height of each bar
+    let mut bars = Vec::new();
+    if let (true, Some(peaks)) = (editor_state.show_waveform, waveform.peaks.as_ref()) {
+        let column_peaks = peaks.columns(visible_start, visible_end, columns);
+        for (column, peak) in column_peaks.into_iter().enumerate() {
+            let Some((low, high)) = peak.map(|peak| peaks.normalized(peak)) else {
+                continue;
+            };
+            let x = (column as f32 + 0.5) * WAVEFORM_COLUMN_WIDTH - screen_w / 2.0;
+            let y = timeline_y + (low + high) / 2.0 * half_height;
+            bars.push((Vec3::new(x, y, 0.11), ((high - low) * half_height).max(1.0)));
+        }
+    }
+
+    let mut bars = bars.into_iter();
+    for (entity, mut transform, mut sprite) in existing.iter_mut() {
+        match bars.next() {
+            Some((position, height)) => {
+                transform.translation = position;
+                sprite.custom_size = Some(Vec2::new(WAVEFORM_COLUMN_WIDTH, height));
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for (position, height) in bars {
+        commands.spawn((
+            Sprite {
+                color: NEON_BLUE.with_alpha(0.35),
+                custom_size: Some(Vec2::new(WAVEFORM_COLUMN_WIDTH, height)),
+                ..default()
+            },
+            Transform::from_translation(position),
+            UiElement,
+            TimelineWaveform,
+        ));
+    }
+}
+
+/// Most history rows drawn at once; long histories show a window around the current step
+pub const HISTORY_VISIBLE_ROWS: usize = 14;
+
+/// Height of one history row
+pub const HISTORY_ROW_HEIGHT: f32 = 16.0;
+
+/// A clickable history row; clicking jumps to the state with `applied` steps applied
+#[derive(Component)]
+pub struct HistoryRow {
+    pub applied: usize,
+}
+
+/// Draw the history list newest-first with the current step highlighted
+pub fn draw_editor_history(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_state: Res<EditorState>,
+    editor_ui: Res<EditorUIState>,
+    existing: Query<Entity, With<HistoryRow>>,
+) {
+    if !editor_state.is_changed() && !editor_ui.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    if !editor_ui.left_panel_visible || editor_ui.left_panel_tab != EditorLeftTab::History {
+        return;
+    }
+
+    let labels = editor_state.history_labels();
+    let current = editor_state.undo_stack.len();
+    let total = labels.len();
+
+    // Rows are states newest-first: `total` steps applied down to the initial state
+    let newest_first: Vec<usize> = (0..=total).rev().collect();
+    let current_row = total - current;
+    let start = current_row
+        .saturating_sub(HISTORY_VISIBLE_ROWS / 2)
+        .min(newest_first.len().saturating_sub(HISTORY_VISIBLE_ROWS));
+    let end = (start + HISTORY_VISIBLE_ROWS).min(newest_first.len());
+
+    let panel_x = -window.height() / 2.0 + editor_ui.left_panel_width / 2.0;
+    let mut y = 55.0;
+    for &applied in &newest_first[start..end] {
+        let label = if applied == 0 {
+            "Initial state".to_string()
+        } else {
+            labels[applied - 1].to_string()
+        };
+        let color = if applied == current {
+            NEON_PINK
+        } else if applied > current {
+            // Undone steps that can still be redone
+            Color::srgba(1.0, 1.0, 1.0, 0.35)
+        } else {
+            Color::WHITE
+        };
+
+        commands.spawn((
+            Text2d::new(label),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 10.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(panel_x, y, 0.3),
+            UiElement,
+            HistoryRow { applied },
+        ));
+        y -= HISTORY_ROW_HEIGHT;
+    }
+}
+
+/// Size of the editor volume buttons
+pub const EDITOR_AUDIO_BUTTON_SIZE: f32 = 16.0;
+
+/// A volume or mute button in the toolbar's audio controls
+#[derive(Component, Clone, Copy)]
+pub enum EditorAudioButton {
+    MusicDown,
+    MusicUp,
+    MusicMute,
+    TicksDown,
+    TicksUp,
+    TicksMute,
+}
+
+/// Part of the toolbar's audio controls, redrawn when the editor mix changes
+#[derive(Component)]
+pub struct EditorAudioElement;
+
+/// Draw the music and object tick volume controls in the toolbar
+pub fn draw_editor_audio(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    editor_ui: Res<EditorUIState>,
+    editor_audio: Res<EditorAudio>,
+    existing: Query<Entity, With<EditorAudioElement>>,
+) {
+    if !editor_audio.is_changed() && !editor_ui.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let toolbar_y = window.height() / 2.0 - editor_ui.toolbar_height / 2.0;
+    let x = window.width() / 2.0 - 330.0;
+    let rows = [
+        (
+            "Music",
+            editor_audio.music_volume,
+            editor_audio.music_muted,
+            [
+                EditorAudioButton::MusicDown,
+                EditorAudioButton::MusicUp,
+                EditorAudioButton::MusicMute,
+            ],
+        ),
+        (
+            "Ticks",
+            editor_audio.tick_volume,
+            editor_audio.ticks_muted,
+            [
+                EditorAudioButton::TicksDown,
+                EditorAudioButton::TicksUp,
+                EditorAudioButton::TicksMute,
+            ],
+        ),
+    ];
+    for (row, (name, volume, muted, buttons)) in rows.into_iter().enumerate() {
+        let y = toolbar_y + 10.0 - row as f32 * 20.0;
+        let (label, color) = if muted {
+            (format!("{} MUTED", name), Color::srgb(0.5, 0.5, 0.5))
+        } else {
+            (format!("{} {:.0}%", name, volume * 100.0), NEON_CYAN)
+        };
+        commands.spawn((
+            Text2d::new(label),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 11.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(x - 30.0, y, 0.3),
+            UiElement,
+            EditorAudioElement,
+        ));
+
+        for (i, button) in buttons.into_iter().enumerate() {
+            let button_x = x + 25.0 + i as f32 * (EDITOR_AUDIO_BUTTON_SIZE + 4.0);
+            let (text, color) = match button {
+                EditorAudioButton::MusicDown | EditorAudioButton::TicksDown => ("-", NEON_BLUE),
+                EditorAudioButton::MusicUp | EditorAudioButton::TicksUp => ("+", NEON_BLUE),
+                _ if muted => ("M", NEON_PINK),
+                _ => ("M", Color::srgba(0.15, 0.15, 0.2, 1.0)),
+            };
+            commands.spawn((
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(EDITOR_AUDIO_BUTTON_SIZE)),
+                    ..default()
+                },
+                Transform::from_xyz(button_x, y, 0.2),
+                UiElement,
+                EditorAudioElement,
+                button,
+            ));
+            commands.spawn((
+                Text2d::new(text),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 11.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                Transform::from_xyz(button_x, y, 0.3),
+                UiElement,
+                EditorAudioElement,
+            ));
+        }
+    }
+}
+
+// Component markers
+#[derive(Component)]
+pub struct EditorToolbar;
+
+#[derive(Component)]
+pub struct ToolButton {
+    pub tool: EditorTool,
+}
+
+#[derive(Component)]
+pub enum PlaybackButton {
+    PlayPause,
+    Previous,
+    Next,
+    Stop,
+}
+
+#[derive(Component)]
+pub struct BeatDivisorDisplay;
+
+#[derive(Component)]
+pub struct SnapToggleButton;
+
+#[derive(Component)]
+pub struct LeftPanel;
+
+#[derive(Component)]
+pub struct LeftPanelTab {
+    pub tab: EditorLeftTab,
+}
+
+#[derive(Component)]
+pub struct NewComboToggle;
+
+#[derive(Component)]
+pub struct GridToggle;
+
+#[derive(Component)]
+pub struct ResnapDurationToggle;
+
+#[derive(Component)]
+pub struct RightPanel;
+
+#[derive(Component)]
+pub struct RightPanelTab {
+    pub tab: EditorRightTab,
+}
+
+#[derive(Component)]
+pub struct Timeline;
+
+/// Timeline tick, slider bar or slider end handle of an object, moved on change
+#[derive(Component)]
+pub struct TimelineObject {
+    pub id: HitObjectId,
+    pub part: TimelinePart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimelinePart {
+    Tick,
+    SliderBar,
+    SliderHandle,
+}
+
+/// Tooltip of the hovered or dragged timeline object, rebuilt on change
+#[derive(Component)]
+pub struct TimelineTooltip;
+
+/// One bar of the song's waveform on the timeline, moved on change
+#[derive(Component)]
+pub struct TimelineWaveform;
 
 #[derive(Component)]
 pub struct Playhead;
@@ -1161,10 +2825,39 @@ pub struct StatusBar;
 #[derive(Component)]
 pub struct StatusText;
 
+/// One of the entities an object is drawn with on the playfield
 #[derive(Component)]
 pub struct EditorHitObject {
     pub id: HitObjectId,
+    pub part: ObjectPart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectPart {
+    Body,
+    SliderBody,
+    Approach,
+    Selection,
+    ComboNumber,
 }
 
+/// What an object's entities were spawned from, kept on its body so they are only
+/// respawned when the object, its selection or the zoom changes
+#[derive(Component)]
+pub struct DrawnObject {
+    object: HitObject,
+    selected: bool,
+    zoom: f32,
+}
+
+/// A drawn object's body as `render_editor_hit_objects` reads and moves it
+type DrawnHitObject = (
+    Entity,
+    &'static EditorHitObject,
+    Option<&'static DrawnObject>,
+    &'static mut Transform,
+    Option<&'static mut Sprite>,
+);
+
 // Type alias for HitObjectId
 use crate::beatmap::HitObjectId;
@@ -0,0 +1,534 @@
+// src/effects.rs
+
+use crate::analytics::JudgmentKind;
+use crate::constants::{GOOD_WINDOW, OKAY_WINDOW, PERFECT_WINDOW};
+use crate::hud::{HudElement, HudLayout};
+use crate::structs::{GameCircle, GameCircleKind};
+use crate::ui::UiElement;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Distance between follow point dots (pixels)
+pub const FOLLOW_POINT_SPACING: f32 = 32.0;
+
+/// Objects closer than this (edge to edge) aren't connected (pixels)
+pub const FOLLOW_POINT_MIN_DISTANCE: f32 = 48.0;
+
+/// Objects further apart in time than this aren't connected, e.g. across breaks (seconds)
+pub const FOLLOW_POINT_MAX_GAP: f64 = 2.0;
+
+/// Share of the gap over which the dots appear one after another, start to end
+pub const FOLLOW_POINT_LEAD: f64 = 0.5;
+
+/// Size of a follow point dot (pixels)
+const FOLLOW_POINT_SIZE: f32 = 6.0;
+
+/// How long a hit light glows (seconds)
+pub const HIT_LIGHT_DURATION: f64 = 0.25;
+
+/// Most hit lights alive at once; the oldest slot is reused when full
+pub const MAX_HIT_LIGHTS: usize = 32;
+
+/// Largest glow size of a hit light, as a multiple of the circle radius
+const HIT_LIGHT_SCALE: f32 = 2.5;
+
+/// How long a hit stays on the hit-error bar (seconds)
+pub const HIT_ERROR_DURATION: f64 = 1.0;
+
+/// Hits the hit-error bar remembers; its mean marker averages them
+pub const MAX_HIT_ERRORS: usize = 32;
+
+/// Height of a hit's tick on the hit-error bar (pixels)
+const HIT_ERROR_TICK_HEIGHT: f32 = 18.0;
+
+/// One dot of the trail between two consecutive objects in a combo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FollowPoint {
+    pub position: Vec2,
+    /// Song time the dot appears
+    pub appear_time: f64,
+    /// Song time the dot is gone; never after the next object's hit time
+    pub expire_time: f64,
+    /// Index of the object the trail leads to
+    pub target: usize,
+}
+
+/// Follow points for a map, ordered by appear time so a frame only looks at the
+/// dots around the current song time
+#[derive(Debug, Clone, Default)]
+pub struct FollowPoints {
+    pub points: Vec<FollowPoint>,
+    /// Longest time any dot is visible
+    max_lifetime: f64,
+}
+
+impl FollowPoints {
+    /// Precompute the follow points of a map from its circles in hit order
+    pub fn generate(circles: &[GameCircle]) -> Self {
+        let mut points: Vec<FollowPoint> = circles
+            .windows(2)
+            .enumerate()
+            .flat_map(|(index, pair)| follow_points_between(&pair[0], &pair[1], index + 1))
+            .collect();
+        points.sort_by(|a, b| a.appear_time.total_cmp(&b.appear_time));
+        let max_lifetime = points
+            .iter()
+            .map(|point| point.expire_time - point.appear_time)
+            .fold(0.0, f64::max);
+        Self {
+            points,
+            max_lifetime,
+        }
+    }
+
+    /// Dots visible at a song time
+    pub fn visible(&self, time: f64) -> impl Iterator<Item = &FollowPoint> {
+        let end = self
+            .points
+            .partition_point(|point| point.appear_time <= time);
+        let start = self
+            .points
+            .partition_point(|point| point.appear_time < time - self.max_lifetime);
+        self.points[start..end]
+            .iter()
+            .filter(move |point| point.expire_time > time)
+    }
+}
+
+/// Where the cursor leaves an object and when
+fn object_exit(circle: &GameCircle) -> (Vec2, f64) {
+    match &circle.kind {
+        GameCircleKind::Slider {
+            path,
+            end_time,
+            repeats,
+            ..
+        } => {
+            let position = if repeats % 2 == 0 {
+                path.last().copied().unwrap_or(circle.position)
+            } else {
+                circle.position
+            };
+            (position, *end_time)
+        }
+        GameCircleKind::Spinner { end_time } => (circle.position, *end_time),
+        GameCircleKind::Circle => (circle.position, circle.hit_time),
+    }
+}
+
+/// Dots from `from` to `to`. Nothing connects across a new combo, to or from a
+/// spinner, between objects too close together, or between objects that overlap
+/// or are too far apart in time
+pub fn follow_points_between(
+    from: &GameCircle,
+    to: &GameCircle,
+    target: usize,
+) -> Vec<FollowPoint> {
+    if to.new_combo
+        || matches!(from.kind, GameCircleKind::Spinner { .. })
+        || matches!(to.kind, GameCircleKind::Spinner { .. })
+    {
+        return Vec::new();
+    }
+
+    let (start, start_time) = object_exit(from);
+    let gap = to.hit_time - start_time;
+    if gap <= 0.0 || gap > FOLLOW_POINT_MAX_GAP {
+        return Vec::new();
+    }
+
+    // Dots run between the circle edges, not the centers
+    let delta = to.position - start;
+    let length = delta.length() - from.max_radius - to.max_radius;
+    if length < FOLLOW_POINT_MIN_DISTANCE {
+        return Vec::new();
+    }
+    let direction = delta.normalize();
+    let first = start + direction * from.max_radius;
+
+    let count = (length / FOLLOW_POINT_SPACING) as usize;
+    let spacing = length / (count + 1) as f32;
+    (1..=count)
+        .map(|i| {
+            let fraction = i as f64 / (count + 1) as f64;
+            let appear_time = start_time + gap * FOLLOW_POINT_LEAD * fraction;
+            FollowPoint {
+                position: first + direction * spacing * i as f32,
+                appear_time,
+                expire_time: (appear_time + gap * FOLLOW_POINT_LEAD).min(to.hit_time),
+                target,
+            }
+        })
+        .collect()
+}
+
+/// Brief glow where an object was hit, colored by the judgment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitLight {
+    pub position: Vec2,
+    pub spawn_time: f64,
+    pub color: (f32, f32, f32),
+    /// Radius of the circle that was hit
+    pub radius: f32,
+}
+
+/// Fixed pool of hit lights; the oldest light is reused once the pool is full
+#[derive(Debug, Clone, Default)]
+pub struct HitLights {
+    lights: Vec<HitLight>,
+    next: usize,
+}
+
+/// Color of a hit judgment, the same as its floating text; misses have none
+fn judgment_color(kind: JudgmentKind) -> Option<(f32, f32, f32)> {
+    match kind {
+        JudgmentKind::Perfect => Some((0.0, 1.0, 0.5)),
+        JudgmentKind::Good => Some((0.0, 0.75, 1.0)),
+        JudgmentKind::Okay => Some((1.0, 1.0, 0.0)),
+        JudgmentKind::Miss => None,
+    }
+}
+
+impl HitLights {
+    /// Light up a hit
+    pub fn spawn(&mut self, position: Vec2, radius: f32, kind: JudgmentKind, time: f64) {
+        let Some(color) = judgment_color(kind) else {
+            return;
+        };
+        let light = HitLight {
+            position,
+            spawn_time: time,
+            color,
+            radius,
+        };
+        if self.lights.len() < MAX_HIT_LIGHTS {
+            self.lights.push(light);
+        } else {
+            self.lights[self.next] = light;
+            self.next = (self.next + 1) % MAX_HIT_LIGHTS;
+        }
+    }
+
+    /// Lights still glowing at a song time
+    pub fn active(&self, time: f64) -> impl Iterator<Item = &HitLight> {
+        self.lights
+            .iter()
+            .filter(move |light| (0.0..HIT_LIGHT_DURATION).contains(&(time - light.spawn_time)))
+    }
+}
+
+/// A hit shown on the hit-error bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitError {
+    /// How early (negative) or late the hit was (ms)
+    pub offset_ms: f32,
+    /// Song time of the hit
+    pub time: f64,
+    pub color: (f32, f32, f32),
+}
+
+/// The last `MAX_HIT_ERRORS` hits, for the hit-error bar
+#[derive(Debug, Clone, Default)]
+pub struct HitErrors {
+    hits: VecDeque<HitError>,
+}
+
+impl HitErrors {
+    /// Remember a hit; misses have no timing and aren't shown
+    pub fn record(&mut self, offset_ms: f32, kind: JudgmentKind, time: f64) {
+        let Some(color) = judgment_color(kind) else {
+            return;
+        };
+        if self.hits.len() == MAX_HIT_ERRORS {
+            self.hits.pop_front();
+        }
+        self.hits.push_back(HitError {
+            offset_ms,
+            time,
+            color,
+        });
+    }
+
+    /// Forget every hit, e.g. when a practice loop rewinds
+    pub fn clear(&mut self) {
+        self.hits.clear();
+    }
+
+    /// Mean offset of the remembered hits (ms)
+    pub fn mean(&self) -> Option<f32> {
+        if self.hits.is_empty() {
+            return None;
+        }
+        Some(self.hits.iter().map(|hit| hit.offset_ms).sum::<f32>() / self.hits.len() as f32)
+    }
+
+    /// Sample hits for the layout editor preview, all still showing at time 0
+    pub fn preview() -> Self {
+        let mut errors = HitErrors::default();
+        for (offset_ms, kind, time) in [
+            (-140.0, JudgmentKind::Good, -0.8),
+            (-35.0, JudgmentKind::Perfect, -0.6),
+            (12.0, JudgmentKind::Perfect, -0.4),
+            (58.0, JudgmentKind::Perfect, -0.2),
+            (-8.0, JudgmentKind::Perfect, 0.0),
+        ] {
+            errors.record(offset_ms, kind, time);
+        }
+        errors
+    }
+
+    /// Hits still fading on the bar at a song time
+    pub fn active(&self, time: f64) -> impl Iterator<Item = &HitError> {
+        self.hits
+            .iter()
+            .filter(move |hit| (0.0..HIT_ERROR_DURATION).contains(&(time - hit.time)))
+    }
+}
+
+/// Draw the hit-error bar where the HUD layout puts it: the timing windows, a tick
+/// for each recent hit that fades out (early on the left, late on the right) and a
+/// marker at their mean. `window_mult` scales the windows as Hard Rock and Easy do,
+/// `alpha` dims it (hidden in the layout editor). Returns the widest window, which
+/// the rest of the bar hangs off
+pub fn draw_hit_error_bar(
+    commands: &mut Commands,
+    errors: &HitErrors,
+    elapsed: f64,
+    window_mult: f64,
+    layout: &HudLayout,
+    screen: Vec2,
+    alpha: f32,
+) -> Entity {
+    let position = layout.position(HudElement::HitErrorBar, screen);
+    let scale = layout.get(HudElement::HitErrorBar).scale;
+    let tick_height = HIT_ERROR_TICK_HEIGHT * scale;
+    let okay_ms = (OKAY_WINDOW * window_mult) as f32 * 1000.0;
+    let pixels_per_ms = layout.size(HudElement::HitErrorBar).x / (okay_ms * 2.0);
+    let x_of = |offset_ms: f32| offset_ms.clamp(-okay_ms, okay_ms) * pixels_per_ms;
+    let window_sprite = |window: f64, (r, g, b): (f32, f32, f32)| Sprite {
+        color: Color::srgba(r, g, b, 0.35 * alpha),
+        custom_size: Some(Vec2::new(
+            (window * window_mult) as f32 * 2000.0 * pixels_per_ms,
+            6.0 * scale,
+        )),
+        ..default()
+    };
+
+    commands
+        .spawn((
+            window_sprite(OKAY_WINDOW, (1.0, 1.0, 0.0)),
+            Transform::from_xyz(position.x, position.y, 0.9),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            // Narrower windows on top of the wider ones
+            for (i, (window, color)) in [
+                (GOOD_WINDOW, (0.0, 0.75, 1.0)),
+                (PERFECT_WINDOW, (0.0, 1.0, 0.5)),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                parent.spawn((
+                    window_sprite(window, color),
+                    Transform::from_xyz(0.0, 0.0, 0.001 * (i + 1) as f32),
+                    UiElement,
+                ));
+            }
+            parent.spawn((
+                Sprite {
+                    color: Color::WHITE.with_alpha(alpha),
+                    custom_size: Some(Vec2::new(2.0 * scale, tick_height + 6.0 * scale)),
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 0.0, 0.01),
+                UiElement,
+            ));
+
+            for hit in errors.active(elapsed) {
+                let fade = 1.0 - ((elapsed - hit.time) / HIT_ERROR_DURATION) as f32;
+                let (r, g, b) = hit.color;
+                parent.spawn((
+                    Sprite {
+                        color: Color::srgba(r, g, b, fade.clamp(0.0, 1.0) * 0.9 * alpha),
+                        custom_size: Some(Vec2::new(3.0 * scale, tick_height)),
+                        ..default()
+                    },
+                    Transform::from_xyz(x_of(hit.offset_ms), 0.0, 0.02),
+                    UiElement,
+                ));
+            }
+
+            if let Some(mean) = errors.mean() {
+                parent.spawn((
+                    Sprite {
+                        color: Color::WHITE.with_alpha(alpha),
+                        custom_size: Some(Vec2::new(8.0, 6.0) * scale),
+                        ..default()
+                    },
+                    Transform::from_xyz(x_of(mean), tick_height / 2.0 + 6.0 * scale, 0.03),
+                    UiElement,
+                ));
+            }
+        })
+        .id()
+}
+
+/// Draw the follow point dots visible at `elapsed`. With reduced motion the dots
+/// hold a steady alpha instead of fading
+pub fn draw_follow_points(
+    commands: &mut Commands,
+    follow_points: &FollowPoints,
+    elapsed: f64,
+    reduced_motion: bool,
+) {
+    for point in follow_points.visible(elapsed) {
+        let alpha = if reduced_motion {
+            0.6
+        } else {
+            let life = (elapsed - point.appear_time) / (point.expire_time - point.appear_time);
+            // Quick fade in, slow fade out
+            (life * 5.0).min(1.0 - life) as f32 * 0.8
+        };
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, alpha.clamp(0.0, 1.0)),
+                custom_size: Some(Vec2::splat(FOLLOW_POINT_SIZE)),
+                ..default()
+            },
+            Transform::from_xyz(point.position.x, point.position.y, 0.05),
+            UiElement,
+        ));
+    }
+}
+
+/// Draw the hit lights glowing at `elapsed` as a few layered, fading sprites
+pub fn draw_hit_lights(commands: &mut Commands, hit_lights: &HitLights, elapsed: f64) {
+    for light in hit_lights.active(elapsed) {
+        let progress = ((elapsed - light.spawn_time) / HIT_LIGHT_DURATION) as f32;
+        let size = light.radius * 2.0 * (1.0 + (HIT_LIGHT_SCALE - 1.0) * progress);
+        let (r, g, b) = light.color;
+        for layer in 1..=3 {
+            let scale = layer as f32 / 3.0;
+            commands.spawn((
+                Sprite {
+                    color: Color::srgba(r, g, b, (1.0 - progress) * 0.15),
+                    custom_size: Some(Vec2::splat(size * scale)),
+                    ..default()
+                },
+                Transform::from_xyz(light.position.x, light.position.y, 0.04),
+                UiElement,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(x: f32, hit_time: f64, new_combo: bool) -> GameCircle {
+        GameCircle {
+            position: Vec2::new(x, 0.0),
+            spawn_time: hit_time - 1.0,
+            hit_time,
+            max_radius: 40.0,
+            hit: false,
+            missed: false,
+            kind: GameCircleKind::Circle,
+            new_combo,
+            hitsound: Default::default(),
+        }
+    }
+
+    #[test]
+    fn dots_run_edge_to_edge_and_are_gone_by_the_next_hit() {
+        // 320px apart, 240px between the edges: seven dots 30px apart
+        let points = follow_points_between(&circle(0.0, 1.0, true), &circle(320.0, 2.0, false), 1);
+        assert_eq!(points.len(), 7);
+        for (i, point) in points.iter().enumerate() {
+            assert!((point.position.x - (40.0 + 30.0 * (i + 1) as f32)).abs() < 1e-3);
+            assert!(point.appear_time > 1.0 && point.appear_time < 1.0 + FOLLOW_POINT_LEAD);
+            assert!(point.expire_time <= 2.0);
+            assert_eq!(point.target, 1);
+        }
+        assert!(points
+            .windows(2)
+            .all(|pair| pair[0].appear_time < pair[1].appear_time));
+    }
+
+    #[test]
+    fn nothing_connects_across_a_new_combo() {
+        let circles = [
+            circle(0.0, 1.0, true),
+            circle(300.0, 1.5, false),
+            circle(600.0, 2.0, true),
+            circle(900.0, 2.5, false),
+        ];
+        let points = FollowPoints::generate(&circles);
+        let mut targets: Vec<usize> = points.points.iter().map(|point| point.target).collect();
+        targets.dedup();
+        assert_eq!(targets, vec![1, 3]);
+    }
+
+    #[test]
+    fn overlapping_close_and_distant_objects_are_not_connected() {
+        let from = circle(0.0, 1.0, false);
+        // Stacked on top of each other
+        assert!(follow_points_between(&from, &circle(0.0, 1.5, false), 1).is_empty());
+        // Edges closer than the minimum distance
+        assert!(follow_points_between(&from, &circle(120.0, 1.5, false), 1).is_empty());
+        // Overlapping in time
+        assert!(follow_points_between(&from, &circle(400.0, 1.0, false), 1).is_empty());
+        // Across a break
+        let later = circle(400.0, 1.0 + FOLLOW_POINT_MAX_GAP + 0.1, false);
+        assert!(follow_points_between(&from, &later, 1).is_empty());
+        // To a spinner
+        let spinner = GameCircle {
+            kind: GameCircleKind::Spinner { end_time: 3.0 },
+            ..circle(400.0, 1.5, false)
+        };
+        assert!(follow_points_between(&from, &spinner, 1).is_empty());
+    }
+
+    #[test]
+    fn visible_dots_are_the_ones_alive_at_the_time() {
+        let circles = [
+            circle(0.0, 1.0, true),
+            circle(320.0, 2.0, false),
+            circle(0.0, 3.0, false),
+        ];
+        let points = FollowPoints::generate(&circles);
+        for time in [0.5, 1.1, 1.6, 2.0, 2.3, 3.5] {
+            let visible: Vec<&FollowPoint> = points.visible(time).collect();
+            let expected: Vec<&FollowPoint> = points
+                .points
+                .iter()
+                .filter(|point| point.appear_time <= time && point.expire_time > time)
+                .collect();
+            assert_eq!(visible, expected, "at {}s", time);
+        }
+    }
+
+    #[test]
+    fn hit_lights_reuse_the_oldest_slot() {
+        let mut lights = HitLights::default();
+        lights.spawn(Vec2::ZERO, 40.0, JudgmentKind::Miss, 0.0);
+        assert_eq!(lights.active(0.0).count(), 0);
+
+        for i in 0..MAX_HIT_LIGHTS + 2 {
+            lights.spawn(Vec2::new(i as f32, 0.0), 40.0, JudgmentKind::Perfect, 1.0);
+        }
+        assert_eq!(lights.active(1.0).count(), MAX_HIT_LIGHTS);
+        let xs: Vec<f32> = lights
+            .active(1.0)
+            .map(|light| light.position.x)
+            .take(3)
+            .collect();
+        assert_eq!(
+            xs,
+            vec![MAX_HIT_LIGHTS as f32, (MAX_HIT_LIGHTS + 1) as f32, 2.0]
+        );
+        assert_eq!(lights.active(1.0 + HIT_LIGHT_DURATION).count(), 0);
+    }
+}
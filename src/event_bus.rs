@@ -0,0 +1,253 @@
+// src/event_bus.rs
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::analytics::{AchievementCategory, GameSession, JudgmentKind};
+use crate::replay::Replay;
+use crate::AppState;
+
+/// Events held between dispatches; past this the oldest are dropped
+pub const BUS_CAPACITY: usize = 1024;
+
+/// Something that happened in gameplay or the menus, for whoever subscribed
+#[derive(Debug, Clone)]
+pub enum BusEvent {
+    /// A play started after the countdown, including quick retries
+    SessionStarted {
+        song: String,
+        attempt: u32,
+    },
+    /// An object was judged, at the object's song time
+    JudgmentMade {
+        kind: JudgmentKind,
+        time: f64,
+    },
+    /// A miss ended a combo of this length
+    ComboBroken {
+        combo: u32,
+    },
+    /// A play ended, whether finished, failed or quit
+    SessionFinished(Box<FinishedSession>),
+    ScreenChanged {
+        from: AppState,
+        to: AppState,
+    },
+    AchievementUnlocked {
+        name: String,
+        category: AchievementCategory,
+    },
+}
+
+/// Analytics of a play that ended, with its replay if one was kept
+#[derive(Debug, Clone)]
+pub struct FinishedSession {
+    pub session: GameSession,
+    pub replay: Option<Replay>,
+}
+
+/// Handles one event with access to every resource. Events it emits are delivered
+/// on the next dispatch
+pub type Handler = fn(&BusEvent, &mut World);
+
+#[derive(Clone, Copy)]
+struct Subscriber {
+    name: &'static str,
+    handler: Handler,
+}
+
+/// Queue of events emitted this frame and the subscribers they go to
+#[derive(Resource, Default)]
+pub struct EventBus {
+    queue: VecDeque<BusEvent>,
+    subscribers: Vec<Subscriber>,
+    /// Events dropped since the last dispatch because the queue was full
+    dropped: usize,
+}
+
+impl EventBus {
+    /// Add a subscriber; subscribers get each event in the order they were added
+    pub fn subscribe(&mut self, name: &'static str, handler: Handler) {
+        self.subscribers.push(Subscriber { name, handler });
+    }
+
+    /// Queue an event for the next dispatch
+    pub fn emit(&mut self, event: BusEvent) {
+        if self.queue.len() >= BUS_CAPACITY {
+            self.queue.pop_front();
+            self.dropped += 1;
+        }
+        self.queue.push_back(event);
+    }
+
+    /// Queue events in order
+    pub fn emit_all(&mut self, events: impl IntoIterator<Item = BusEvent>) {
+        for event in events {
+            self.emit(event);
+        }
+    }
+}
+
+/// Deliver the queued events, in the order they were emitted, each to every
+/// subscriber in the order they subscribed. A subscriber that panics is reported
+/// and the event still reaches the rest. Runs after the frame's gameplay
+pub fn dispatch_bus_events(world: &mut World) {
+    let (events, subscribers) = {
+        let mut bus = world.resource_mut::<EventBus>();
+        if bus.dropped > 0 {
+            warn!("Event bus full, dropped {} events", bus.dropped);
+            bus.dropped = 0;
+        }
+        if bus.queue.is_empty() {
+            return;
+        }
+        (std::mem::take(&mut bus.queue), bus.subscribers.clone())
+    };
+
+    for event in &events {
+        for subscriber in &subscribers {
+            let delivered = catch_unwind(AssertUnwindSafe(|| (subscriber.handler)(event, world)));
+            if delivered.is_err() {
+                error!(
+                    "Event subscriber {} panicked on {:?}",
+                    subscriber.name, event
+                );
+            }
+        }
+    }
+}
+
+/// Emit `ScreenChanged` whenever the app moves to another state
+pub fn emit_screen_changes(
+    state: Res<State<AppState>>,
+    mut previous: Local<Option<AppState>>,
+    mut bus: ResMut<EventBus>,
+) {
+    let current = *state.get();
+    match previous.replace(current) {
+        Some(from) if from != current => bus.emit(BusEvent::ScreenChanged { from, to: current }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What the subscribers saw, in delivery order
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn combo(event: &BusEvent) -> u32 {
+        match event {
+            BusEvent::ComboBroken { combo } => *combo,
+            _ => 0,
+        }
+    }
+
+    fn first(event: &BusEvent, world: &mut World) {
+        world
+            .resource_mut::<Log>()
+            .0
+            .push(format!("first {}", combo(event)));
+    }
+
+    fn second(event: &BusEvent, world: &mut World) {
+        world
+            .resource_mut::<Log>()
+            .0
+            .push(format!("second {}", combo(event)));
+    }
+
+    fn panics_on_two(event: &BusEvent, _world: &mut World) {
+        if combo(event) == 2 {
+            panic!("subscriber failed");
+        }
+    }
+
+    /// Answers each broken combo with a bigger one
+    fn echo(event: &BusEvent, world: &mut World) {
+        if combo(event) < 10 {
+            let combo = combo(event) + 10;
+            world
+                .resource_mut::<EventBus>()
+                .emit(BusEvent::ComboBroken { combo });
+        }
+    }
+
+    fn world(subscribers: &[(&'static str, Handler)]) -> World {
+        let mut world = World::new();
+        let mut bus = EventBus::default();
+        for (name, handler) in subscribers {
+            bus.subscribe(name, *handler);
+        }
+        world.insert_resource(bus);
+        world.init_resource::<Log>();
+        world
+    }
+
+    fn emit(world: &mut World, combos: &[u32]) {
+        world
+            .resource_mut::<EventBus>()
+            .emit_all(combos.iter().map(|&combo| BusEvent::ComboBroken { combo }));
+    }
+
+    fn log(world: &World) -> Vec<&str> {
+        world
+            .resource::<Log>()
+            .0
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn events_reach_subscribers_in_order() {
+        let mut world = world(&[("first", first), ("second", second)]);
+        emit(&mut world, &[1, 2]);
+        dispatch_bus_events(&mut world);
+        assert_eq!(log(&world), ["first 1", "second 1", "first 2", "second 2"]);
+
+        // Each event is delivered once
+        dispatch_bus_events(&mut world);
+        assert_eq!(log(&world).len(), 4);
+    }
+
+    #[test]
+    fn a_panicking_subscriber_does_not_starve_the_others() {
+        let mut world = world(&[
+            ("first", first),
+            ("panics", panics_on_two),
+            ("second", second),
+        ]);
+        emit(&mut world, &[1, 2, 3]);
+        dispatch_bus_events(&mut world);
+        assert_eq!(
+            log(&world),
+            ["first 1", "second 1", "first 2", "second 2", "first 3", "second 3"]
+        );
+    }
+
+    #[test]
+    fn events_emitted_while_dispatching_wait_for_the_next_dispatch() {
+        let mut world = world(&[("echo", echo), ("first", first)]);
+        emit(&mut world, &[1, 2]);
+        dispatch_bus_events(&mut world);
+        assert_eq!(log(&world), ["first 1", "first 2"]);
+        dispatch_bus_events(&mut world);
+        assert_eq!(log(&world), ["first 1", "first 2", "first 11", "first 12"]);
+    }
+
+    #[test]
+    fn a_full_queue_drops_the_oldest_events() {
+        let mut world = world(&[("first", first)]);
+        let combos: Vec<u32> = (0..BUS_CAPACITY as u32 + 2).collect();
+        emit(&mut world, &combos);
+        assert_eq!(world.resource::<EventBus>().dropped, 2);
+        dispatch_bus_events(&mut world);
+        assert_eq!(log(&world).len(), BUS_CAPACITY);
+        assert_eq!(log(&world)[0], "first 2");
+        assert_eq!(world.resource::<EventBus>().dropped, 0);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::constants::*;
 use crate::gamemode::{GameSettings, Modifier};
-use crate::structs::{FloatingText, GameCircle, VisualizingState};
+use crate::beatmap::{Beatmap, HitObjectKind};
+use crate::performance::RenderGates;
+use crate::structs::{FloatingText, GameCircle, GameCircleKind, VisualizingState};
+use crate::visibility::{flashlight_alpha, object_visibility, FadeParams, VisibleObject};
 use bevy::prelude::*;
-use rand::Rng;
 
 /// Component marker for game circles
 #[derive(Component)]
@@ -10,57 +12,116 @@ pub struct CircleComponent {
     pub circle_index: usize,
 }
 
-/// Initialize circles for a game with animations
+/// Initialize circles for a game from a generated beatmap
 pub fn initialize_circles(
-    beats: &[f64],
-    rng: &mut impl Rng,
-    spawn_radius: f32,
-    center: Vec2,
+    beatmap: &Beatmap,
     shrink_time: f64,
     delay: f64,
     config: &crate::config::GameConfig,
 ) -> Vec<GameCircle> {
     let game_settings = &config.game_settings;
-    let mut circles = Vec::with_capacity(beats.len());
-
-    // Apply difficulty multipliers
-    let circle_size_mult = game_settings.difficulty.circle_size_multiplier();
-    let shrink_time_mult = game_settings.difficulty.shrink_time_multiplier();
-
-    for &beat_time in beats {
-        let (angle, distance) = if game_settings.randomize_positions() {
-            (
-                rng.gen_range(0.0..std::f32::consts::TAU),
-                rng.gen_range(0.0..spawn_radius),
-            )
-        } else {
-            (
-                rng.gen_range(0.0..std::f32::consts::TAU),
-                rng.gen_range(0.0..spawn_radius),
-            )
-        };
+    let mut circles = Vec::with_capacity(beatmap.hit_objects.len());
 
-        let position = Vec2::new(
-            center.x + distance * angle.cos(),
-            center.y + distance * angle.sin(),
-        );
+    // Apply difficulty multipliers; Hard Rock and Easy also change the circle size
+    let circle_size_mult = game_settings.circle_size_multiplier();
+    let shrink_time_mult = game_settings.difficulty.shrink_time_multiplier() as f64;
 
+    for (index, object) in beatmap.hit_objects.iter().enumerate() {
         let adjusted_shrink_time = shrink_time * shrink_time_mult;
         let max_radius = CIRCLE_MAX_RADIUS * circle_size_mult * config.theme.circle_size;
 
+        let kind = match &object.kind {
+            HitObjectKind::Circle => GameCircleKind::Circle,
+            HitObjectKind::Slider {
+                control_points,
+                repeats,
+                pixel_length,
+                ..
+            } => GameCircleKind::Slider {
+                path: slider_path(control_points, *pixel_length as f32),
+                end_time: beatmap.object_end_time(object) + delay,
+                repeats: *repeats,
+                ticks: beatmap
+                    .slider_tick_times(object)
+                    .into_iter()
+                    .map(|tick| tick + delay)
+                    .collect(),
+                ticks_done: 0,
+                broken: false,
+            },
+            HitObjectKind::Spinner { end_time } => GameCircleKind::Spinner {
+                end_time: end_time + delay,
+            },
+        };
+
         circles.push(GameCircle {
-            position,
-            spawn_time: beat_time - adjusted_shrink_time + delay,
-            hit_time: beat_time + delay,
+            position: object.position,
+            spawn_time: object.time - adjusted_shrink_time + delay,
+            hit_time: object.time + delay,
             max_radius,
             hit: false,
             missed: false,
+            kind,
+            new_combo: index == 0 || object.new_combo,
+            hitsound: object.hitsound,
         });
     }
 
     circles
 }
 
+/// A slider's control points cut to its pixel length, as the editor draws it
+fn slider_path(control_points: &[Vec2], pixel_length: f32) -> Vec<Vec2> {
+    let mut path: Vec<Vec2> = control_points.iter().take(1).copied().collect();
+    let mut remaining = pixel_length;
+    for pair in control_points.windows(2) {
+        let length = pair[0].distance(pair[1]);
+        if length >= remaining {
+            path.push(pair[0].move_towards(pair[1], remaining));
+            break;
+        }
+        path.push(pair[1]);
+        remaining -= length;
+    }
+    path
+}
+
+/// Point a share (0.0 - 1.0) of the way along a path of straight segments
+fn point_along(path: &[Vec2], share: f32) -> Vec2 {
+    let total: f32 = path.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+    let mut remaining = total * share.clamp(0.0, 1.0);
+    for pair in path.windows(2) {
+        let length = pair[0].distance(pair[1]);
+        if remaining <= length {
+            return pair[0].move_towards(pair[1], remaining);
+        }
+        remaining -= length;
+    }
+    path.last().copied().unwrap_or_default()
+}
+
+/// Where a slider's follow ball is at a song time. It runs along the path and back
+/// on each repeat; other objects stay where they are
+pub fn slider_ball_position(circle: &GameCircle, elapsed: f64) -> Vec2 {
+    let GameCircleKind::Slider {
+        path,
+        end_time,
+        repeats,
+        ..
+    } = &circle.kind
+    else {
+        return circle.position;
+    };
+    let duration = end_time - circle.hit_time;
+    if duration <= 0.0 {
+        return circle.position;
+    }
+    let spans = ((elapsed - circle.hit_time) / duration).clamp(0.0, 1.0) * (*repeats as f64 + 1.0);
+    let span = (spans.floor() as u32).min(*repeats);
+    let along = (spans - span as f64) as f32;
+    point_along(path, if span % 2 == 0 { along } else { 1.0 - along })
+}
+
 /// Calculate the spawn radius based on the screen size
 pub fn calculate_spawn_radius(width: f32, height: f32) -> f32 {
     width.min(height) / 2.0 - 100.0
@@ -76,13 +137,16 @@ pub fn circle_radius(circle: &GameCircle, elapsed: f64, shrink_time: f64) -> Opt
     }
 }
 
-/// Calculate score from timing difference, applying modifiers and game settings
+/// The judgment (300/100/50/0) of a hit; the score multiplier is applied where the
+/// points are added to the score
 pub fn calculate_score_from_timing(time_difference: f64, game_settings: &GameSettings) -> i32 {
-    let base_score = if time_difference < 0.08 {
+    // Hard Rock tightens the windows and Easy widens them
+    let window_mult = game_settings.timing_window_multiplier();
+    let base_score = if time_difference < PERFECT_WINDOW * window_mult {
         300
-    } else if time_difference < 0.2 {
+    } else if time_difference < GOOD_WINDOW * window_mult {
         100
-    } else if time_difference < 0.35 {
+    } else if time_difference < OKAY_WINDOW * window_mult {
         50
     } else {
         0
@@ -93,9 +157,7 @@ pub fn calculate_score_from_timing(time_difference: f64, game_settings: &GameSet
         return 0;
     }
 
-    // Apply score multiplier
-    let multiplier = game_settings.score_multiplier();
-    (base_score as f32 * multiplier) as i32
+    base_score
 }
 
 /// Legacy version for backward compatibility
@@ -106,7 +168,7 @@ pub fn calculate_score_from_timing_legacy(time_difference: f64) -> i32 {
 /// Handle missed circles and animate a "Miss" text
 /// Returns true if the game should end (e.g., survival mode with no lives)
 pub fn handle_missed_circles(
-    circles: &mut Vec<GameCircle>,
+    circles: &mut [GameCircle],
     elapsed: f64,
     vis_state: &mut VisualizingState,
     shrink_time: f64,
@@ -132,13 +194,21 @@ pub fn handle_missed_circles(
                     spawn_time: elapsed,
                     duration: 1.5,
                     color: (1.0, 0.5, 0.0),
+                    scale: 1.0,
                 });
             }
 
+            vis_state.change_hp(-hp_miss_penalty(vis_state.hp_drain));
+
             // Only record miss if not in no-fail mode
             if !vis_state.no_fail && !vis_state.game_settings.has_modifier(Modifier::NoFail) {
                 vis_state.record_miss();
             }
+            vis_state.log_judgment(
+                circle.hit_time,
+                circle.position,
+                crate::analytics::JudgmentKind::Miss,
+            );
 
             vis_state.floating_texts.push(FloatingText {
                 text: "Miss".to_string(),
@@ -146,6 +216,7 @@ pub fn handle_missed_circles(
                 spawn_time: elapsed,
                 duration: 1.0,
                 color: (1.0, 0.0, 0.0),
+                scale: 1.0,
             });
         }
     }
@@ -153,24 +224,186 @@ pub fn handle_missed_circles(
     should_end_game
 }
 
+/// Score the bodies of sliders whose head was hit. Each tick and the end passed with
+/// a hit key held adds to score and combo; letting go before the end breaks the
+/// combo, and the rest of the body scores nothing
+pub fn update_sliders(
+    circles: &mut [GameCircle],
+    elapsed: f64,
+    vis_state: &mut VisualizingState,
+    holding: bool,
+) {
+    for circle in circles.iter_mut().filter(|c| c.hit) {
+        let ball = slider_ball_position(circle, elapsed);
+        let GameCircleKind::Slider {
+            ticks,
+            ticks_done,
+            broken,
+            ..
+        } = &mut circle.kind
+        else {
+            continue;
+        };
+        // The body starts at the head's hit time, however early the head was hit
+        if *broken || *ticks_done == ticks.len() || elapsed < circle.hit_time {
+            continue;
+        }
+
+        if !holding {
+            *broken = true;
+            vis_state.change_hp(-hp_miss_penalty(vis_state.hp_drain));
+            // Breaks are left out of the combo in no-fail, like misses
+            if !vis_state.no_fail && !vis_state.game_settings.has_modifier(Modifier::NoFail) {
+                vis_state.record_slider_break();
+            }
+            vis_state.floating_texts.push(FloatingText {
+                text: "Slider break".to_string(),
+                position: ball,
+                spawn_time: elapsed,
+                duration: 1.0,
+                color: (1.0, 0.3, 0.0),
+                scale: 0.8,
+            });
+            continue;
+        }
+
+        while ticks.get(*ticks_done).is_some_and(|&tick| tick <= elapsed) {
+            *ticks_done += 1;
+            let points = if *ticks_done == ticks.len() {
+                SLIDER_END_SCORE
+            } else {
+                SLIDER_TICK_SCORE
+            };
+            vis_state.record_slider_tick(points);
+        }
+    }
+}
+
+/// Health a miss costs on a map with `hp_drain` (0 - 10)
+pub fn hp_miss_penalty(hp_drain: f32) -> f32 {
+    HP_MISS_BASE + HP_MISS_PER_DRAIN * hp_drain.clamp(0.0, 10.0)
+}
+
+/// Health a hit worth `points` gives back; okays give nothing and a zero-point hit
+/// costs as much as a miss
+pub fn hp_change_for_hit(points: i32, hp_drain: f32) -> f32 {
+    match points {
+        300 => HP_PERFECT_RECOVERY,
+        100 => HP_GOOD_RECOVERY,
+        0 => -hp_miss_penalty(hp_drain),
+        _ => 0.0,
+    }
+}
+
 /// Score calculation based on the hit time and elapsed time (legacy)
 pub fn calculate_score(hit_time: f64, current_time: f64) -> i32 {
     let time_difference = (current_time - hit_time).abs();
-    calculate_score_from_timing(time_difference)
+    calculate_score_from_timing_legacy(time_difference)
 }
 
-/// Draw circles in Bevy
+/// Draw slider bodies from when they appear until they end, and the follow ball once
+/// the head was hit. Heads are drawn with the circles by `draw_circles_bevy`.
+/// `flashlight` is the Flashlight center, None without the mod
+pub fn draw_sliders(
+    commands: &mut Commands,
+    circles: &[GameCircle],
+    elapsed: f64,
+    shrink_time: f64,
+    game_settings: &GameSettings,
+    theme: &crate::config::ThemeConfig,
+    flashlight: Option<Vec2>,
+) {
+    let hidden = !game_settings.show_approach_circles();
+    let fade = FadeParams::from_theme(theme);
+
+    for circle in circles {
+        let GameCircleKind::Slider { path, end_time, .. } = &circle.kind else {
+            continue;
+        };
+        if circle.missed || elapsed < circle.spawn_time || elapsed >= *end_time {
+            continue;
+        }
+        let visibility = object_visibility(
+            VisibleObject::Slider,
+            circle.hit_time - elapsed,
+            shrink_time,
+            hidden,
+            &fade,
+        );
+
+        // Body along the path, under the head; Flashlight lights it segment by segment
+        for pair in path.windows(2) {
+            let segment = pair[1] - pair[0];
+            let length = segment.length();
+            let center = pair[0] + segment / 2.0;
+            let alpha = visibility.body_alpha * flashlight_alpha(center, flashlight);
+            if length <= 0.0 || alpha <= 0.0 {
+                continue;
+            }
+            commands.spawn((
+                Sprite {
+                    color: Color::srgba(0.0, 0.75, 1.0, alpha * 0.5),
+                    custom_size: Some(Vec2::new(length, circle.max_radius)),
+                    ..default()
+                },
+                Transform::from_xyz(center.x, center.y, 0.05)
+                    .with_rotation(Quat::from_rotation_z(segment.y.atan2(segment.x))),
+                crate::ui::UiElement,
+            ));
+        }
+
+        // Follow ball, dimmed once the hold was broken
+        if circle.hit && elapsed >= circle.hit_time {
+            let ball = slider_ball_position(circle, elapsed);
+            let alpha = visibility.follow_ball_alpha * flashlight_alpha(ball, flashlight);
+            let color = if circle.slider_broken() {
+                Color::srgba(1.0, 0.3, 0.0, alpha * 0.4)
+            } else {
+                Color::srgba(1.0, 0.84, 0.0, alpha)
+            };
+            commands.spawn((
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(circle.max_radius * 0.8)),
+                    ..default()
+                },
+                Transform::from_xyz(ball.x, ball.y, 0.25),
+                crate::ui::UiElement,
+            ));
+        }
+    }
+}
+
+/// Draw circles in Bevy. Under Flashlight (`flashlight` is its center) only circles
+/// near the center are drawn, approach ring included
+#[allow(clippy::too_many_arguments)]
 pub fn draw_circles_bevy(
     commands: &mut Commands,
     circles: &[GameCircle],
     elapsed: f64,
     shrink_time: f64,
     game_settings: &GameSettings,
+    theme: &crate::config::ThemeConfig,
+    kiai: f32,
+    gates: &RenderGates,
+    flashlight: Option<Vec2>,
 ) {
     // Pre-compute pulse intensity once
-    let pulse_intensity = 0.5 + (elapsed.sin() as f32) * 0.5;
+    let pulse_intensity = if theme.reduced_motion || !gates.pulse {
+        0.75
+    } else {
+        0.5 + (elapsed.sin() as f32) * 0.5
+    };
+    // Kiai strengthens the outline glow
+    let glow = if gates.glow {
+        1.0 + KIAI_GLOW_BOOST * kiai
+    } else {
+        1.0
+    };
+    let outline_thickness = OUTLINE_THICKNESS * glow;
 
-    let show_approach = game_settings.show_approach_circles();
+    let hidden = !game_settings.show_approach_circles();
+    let fade = FadeParams::from_theme(theme);
 
     for circle in circles {
         let time_since_spawn = elapsed - circle.spawn_time;
@@ -185,30 +418,46 @@ pub fn draw_circles_bevy(
                 continue;
             }
 
-            // Pre-compute alpha
-            let alpha = 0.6 - scale * 0.5;
+            let object = match circle.kind {
+                GameCircleKind::Circle => VisibleObject::Circle,
+                GameCircleKind::Slider { .. } => VisibleObject::Slider,
+                GameCircleKind::Spinner { .. } => VisibleObject::Spinner,
+            };
+            let visibility =
+                object_visibility(object, circle.hit_time - elapsed, shrink_time, hidden, &fade);
+            // Spinners fill the middle of the screen and stay lit
+            let visibility = match object {
+                VisibleObject::Spinner => visibility,
+                _ => visibility.dimmed(flashlight_alpha(circle.position, flashlight)),
+            };
+            if visibility.body_alpha <= 0.0 && visibility.approach_alpha <= 0.0 {
+                continue;
+            }
 
-            // Draw outline circle (pulsing effect)
-            commands.spawn((
-                Sprite {
-                    color: Color::srgba(
-                        OUTLINE_COLOR.to_linear().red,
-                        OUTLINE_COLOR.to_linear().green,
-                        OUTLINE_COLOR.to_linear().blue,
-                        pulse_intensity,
-                    ),
-                    custom_size: Some(Vec2::new(
-                        (radius + OUTLINE_THICKNESS) * 2.0,
-                        (radius + OUTLINE_THICKNESS) * 2.0,
-                    )),
-                    ..default()
-                },
-                Transform::from_xyz(circle.position.x, circle.position.y, 0.3),
-                crate::ui::UiElement,
-            ));
+            // Draw outline circle (pulsing effect); flat circles without glow
+            if gates.glow {
+                commands.spawn((
+                    Sprite {
+                        color: Color::srgba(
+                            OUTLINE_COLOR.to_linear().red,
+                            OUTLINE_COLOR.to_linear().green,
+                            OUTLINE_COLOR.to_linear().blue,
+                            (pulse_intensity * glow).min(1.0)
+                                * (visibility.body_alpha / 0.6).min(1.0),
+                        ),
+                        custom_size: Some(Vec2::new(
+                            (radius + outline_thickness) * 2.0,
+                            (radius + outline_thickness) * 2.0,
+                        )),
+                        ..default()
+                    },
+                    Transform::from_xyz(circle.position.x, circle.position.y, 0.3),
+                    crate::ui::UiElement,
+                ));
+            }
 
             // Draw main circle
-            let color = Color::srgba(0.0, 0.75, 1.0, alpha);
+            let color = Color::srgba(0.0, 0.75, 1.0, visibility.body_alpha);
             commands.spawn((
                 Sprite {
                     color,
@@ -220,8 +469,8 @@ pub fn draw_circles_bevy(
             ));
 
             // Draw approach circle (outline) only if not hidden
-            if show_approach {
-                let approach_alpha = 0.3 + pulse_intensity * 0.3;
+            if visibility.approach_alpha > 0.0 {
+                let approach_alpha = (0.3 + pulse_intensity * 0.3) * visibility.approach_alpha;
                 commands.spawn((
                     Sprite {
                         color: Color::srgba(
@@ -241,56 +490,98 @@ pub fn draw_circles_bevy(
     }
 }
 
-            // Pre-compute alpha
-            let alpha = 0.6 - scale * 0.5;
-
-            // Draw outline circle (pulsing effect)
-            commands.spawn((
-                Sprite {
-                    color: Color::srgba(
-                        OUTLINE_COLOR.to_linear().red,
-                        OUTLINE_COLOR.to_linear().green,
-                        OUTLINE_COLOR.to_linear().blue,
-                        pulse_intensity,
-                    ),
-                    custom_size: Some(Vec2::new(
-                        (radius + OUTLINE_THICKNESS) * 2.0,
-                        (radius + OUTLINE_THICKNESS) * 2.0,
-                    )),
-                    ..default()
-                },
-                Transform::from_xyz(circle.position.x, circle.position.y, 0.3),
-                crate::ui::UiElement,
-            ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+
+    /// A slider from 1.0 s to 2.0 s with ticks at 1.25, 1.5 and 1.75 and its end
+    fn slider(hit: bool) -> GameCircle {
+        GameCircle {
+            position: Vec2::ZERO,
+            spawn_time: 0.0,
+            hit_time: 1.0,
+            max_radius: 50.0,
+            hit,
+            missed: false,
+            kind: GameCircleKind::Slider {
+                path: vec![Vec2::ZERO, Vec2::new(200.0, 0.0)],
+                end_time: 2.0,
+                repeats: 0,
+                ticks: vec![1.25, 1.5, 1.75, 2.0],
+                ticks_done: 0,
+                broken: false,
+            },
+            new_combo: false,
+            hitsound: Default::default(),
+        }
+    }
 
-            // Draw main circle
-            let color = Color::srgba(0.0, 0.75, 1.0, alpha);
-            commands.spawn((
-                Sprite {
-                    color,
-                    custom_size: Some(Vec2::new(radius * 2.0, radius * 2.0)),
-                    ..default()
-                },
-                Transform::from_xyz(circle.position.x, circle.position.y, 0.2),
-                crate::ui::UiElement,
-            ));
+    fn state() -> VisualizingState {
+        VisualizingState::new(Vec::new(), Vec::new(), GameConfig::default(), "test".into())
+    }
 
-            // Draw approach circle (outline)
-            let approach_alpha = 0.3 + pulse_intensity * 0.3;
-            commands.spawn((
-                Sprite {
-                    color: Color::srgba(
-                        OUTLINE_COLOR.to_linear().red,
-                        OUTLINE_COLOR.to_linear().green,
-                        OUTLINE_COLOR.to_linear().blue,
-                        approach_alpha,
-                    ),
-                    custom_size: Some(Vec2::new(radius * 2.0, radius * 2.0)),
-                    ..default()
-                },
-                Transform::from_xyz(circle.position.x, circle.position.y, 0.1),
-                crate::ui::UiElement,
-            ));
+    fn ticks_done(circle: &GameCircle) -> usize {
+        match circle.kind {
+            GameCircleKind::Slider { ticks_done, .. } => ticks_done,
+            _ => unreachable!(),
         }
     }
+
+    #[test]
+    fn holding_collects_each_tick_and_the_end() {
+        let mut circles = [slider(true)];
+        let mut state = state();
+        state.combo = 1;
+
+        update_sliders(&mut circles, 1.6, &mut state, true);
+        assert_eq!(ticks_done(&circles[0]), 2);
+        assert_eq!(state.combo, 3);
+        assert_eq!(state.score, 2 * SLIDER_TICK_SCORE as i64);
+
+        update_sliders(&mut circles, 2.0, &mut state, true);
+        assert_eq!(ticks_done(&circles[0]), 4);
+        assert_eq!(state.combo, 5);
+        assert_eq!(state.score, (3 * SLIDER_TICK_SCORE + SLIDER_END_SCORE) as i64);
+        assert!(!circles[0].slider_broken());
+
+        // Finished, so letting go afterwards breaks nothing
+        update_sliders(&mut circles, 2.1, &mut state, false);
+        assert!(!circles[0].slider_broken());
+        assert_eq!(state.combo, 5);
+    }
+
+    #[test]
+    fn letting_go_early_breaks_the_combo_and_stops_scoring() {
+        let mut circles = [slider(true)];
+        let mut state = state();
+        state.combo = 1;
+
+        update_sliders(&mut circles, 1.3, &mut state, true);
+        assert_eq!(state.combo, 2);
+        update_sliders(&mut circles, 1.4, &mut state, false);
+        assert!(circles[0].slider_broken());
+        assert_eq!(state.combo, 0);
+        assert_eq!(state.max_combo, 2);
+        assert_eq!(state.hits.misses, 0);
+
+        // Holding again doesn't pick up the rest of the body
+        update_sliders(&mut circles, 2.0, &mut state, true);
+        assert_eq!(ticks_done(&circles[0]), 1);
+        assert_eq!(state.score, SLIDER_TICK_SCORE as i64);
+    }
+
+    #[test]
+    fn a_slider_whose_head_was_missed_scores_no_body() {
+        let mut circles = [slider(false)];
+        let mut state = state();
+        state.combo = 4;
+
+        update_sliders(&mut circles, 1.5, &mut state, true);
+        update_sliders(&mut circles, 1.6, &mut state, false);
+        assert_eq!(ticks_done(&circles[0]), 0);
+        assert!(!circles[0].slider_broken());
+        assert_eq!(state.score, 0);
+        assert_eq!(state.combo, 4);
+    }
 }
@@ -4,9 +4,10 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Game mode types that affect how the game is played
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum GameMode {
     /// Standard mode - classic gameplay with scoring
+    #[default]
     Standard,
     /// Time Attack - score as many points as possible within a time limit
     TimeAttack { time_limit_seconds: u32 },
@@ -66,18 +67,13 @@ impl fmt::Display for GameMode {
     }
 }
 
-impl Default for GameMode {
-    fn default() -> Self {
-        GameMode::Standard
-    }
-}
-
 /// Difficulty settings that affect circle behavior
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Difficulty {
     /// Easy mode - larger circles, slower approach
     Easy,
     /// Normal mode - standard gameplay
+    #[default]
     Normal,
     /// Hard mode - smaller circles, faster approach
     Hard,
@@ -161,12 +157,6 @@ impl fmt::Display for Difficulty {
     }
 }
 
-impl Default for Difficulty {
-    fn default() -> Self {
-        Difficulty::Normal
-    }
-}
-
 /// Game modifiers that change gameplay mechanics
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub enum Modifier {
@@ -176,7 +166,7 @@ pub enum Modifier {
     PerfectOnly,
     /// Hidden - approach circles are invisible
     Hidden,
-    /// Flash - circles flash visible/invisible
+    /// Flashlight - only objects near the cursor are visible
     Flash,
     /// No Fail - game doesn't end on miss
     NoFail,
@@ -203,7 +193,7 @@ impl Modifier {
             (Modifier::SuddenDeath, "Sudden Death"),
             (Modifier::PerfectOnly, "Perfect Only"),
             (Modifier::Hidden, "Hidden"),
-            (Modifier::Flash, "Flash"),
+            (Modifier::Flash, "Flashlight"),
             (Modifier::NoFail, "No Fail"),
             (Modifier::Auto, "Auto"),
             (Modifier::Relaxed, "Relaxed"),
@@ -221,7 +211,7 @@ impl Modifier {
             Modifier::SuddenDeath => "Sudden Death",
             Modifier::PerfectOnly => "Perfect Only",
             Modifier::Hidden => "Hidden",
-            Modifier::Flash => "Flash",
+            Modifier::Flash => "Flashlight",
             Modifier::NoFail => "No Fail",
             Modifier::Auto => "Auto",
             Modifier::Relaxed => "Relaxed",
@@ -239,7 +229,7 @@ impl Modifier {
             Modifier::SuddenDeath => "One miss ends the game",
             Modifier::PerfectOnly => "Only perfect hits count",
             Modifier::Hidden => "Approach circles are invisible",
-            Modifier::Flash => "Circles flash visible/invisible",
+            Modifier::Flash => "Only objects near the cursor are visible",
             Modifier::NoFail => "Game doesn't end on miss",
             Modifier::Auto => "Game plays itself",
             Modifier::Relaxed => "No timing judgment",
@@ -251,6 +241,43 @@ impl Modifier {
         }
     }
 
+    /// Fixed number for the modifier in hashes and on the wire. Unlike the names it
+    /// never changes, so append new modifiers with the next free number
+    pub fn wire_id(&self) -> u8 {
+        match self {
+            Modifier::SuddenDeath => 0,
+            Modifier::PerfectOnly => 1,
+            Modifier::Hidden => 2,
+            Modifier::Flash => 3,
+            Modifier::NoFail => 4,
+            Modifier::Auto => 5,
+            Modifier::Relaxed => 6,
+            Modifier::Randomize => 7,
+            Modifier::DoubleTime => 8,
+            Modifier::HalfTime => 9,
+            Modifier::HardRock => 10,
+            Modifier::EasyMod => 11,
+        }
+    }
+
+    /// Two-letter tag shown where space is short, e.g. "HD+DT"
+    pub fn acronym(&self) -> &'static str {
+        match self {
+            Modifier::SuddenDeath => "SD",
+            Modifier::PerfectOnly => "PF",
+            Modifier::Hidden => "HD",
+            Modifier::Flash => "FL",
+            Modifier::NoFail => "NF",
+            Modifier::Auto => "AT",
+            Modifier::Relaxed => "RX",
+            Modifier::Randomize => "RD",
+            Modifier::DoubleTime => "DT",
+            Modifier::HalfTime => "HT",
+            Modifier::HardRock => "HR",
+            Modifier::EasyMod => "EZ",
+        }
+    }
+
     /// Get score multiplier for this modifier
     pub fn score_multiplier(&self) -> f32 {
         match self {
@@ -269,6 +296,15 @@ impl Modifier {
         }
     }
 
+    /// Playback speed the modifier plays the song at
+    pub fn playback_speed(&self) -> f32 {
+        match self {
+            Modifier::DoubleTime => 1.5,
+            Modifier::HalfTime => 0.75,
+            _ => 1.0,
+        }
+    }
+
     /// Check if modifier conflicts with another modifier
     pub fn conflicts_with(&self, other: &Modifier) -> bool {
         match self {
@@ -292,6 +328,18 @@ impl fmt::Display for Modifier {
     }
 }
 
+/// Hard Rock's scaling of a map's circle size (CS)
+pub const HARD_ROCK_CS_SCALE: f32 = 1.3;
+
+/// Hard Rock's scaling of a map's approach rate, overall difficulty and HP drain
+pub const HARD_ROCK_MAP_SCALE: f32 = 1.4;
+
+/// Easy's scaling of every map setting
+pub const EASY_MAP_SCALE: f32 = 0.5;
+
+/// Map settings run from 0 to 10
+pub const MAX_MAP_SETTING: f32 = 10.0;
+
 /// Game settings configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameSettings {
@@ -348,6 +396,17 @@ impl GameSettings {
         self.modifiers.retain(|m| *m != modifier);
     }
 
+    /// Turn a modifier off, or on in place of the ones it conflicts with
+    pub fn toggle_modifier(&mut self, modifier: Modifier) {
+        if self.has_modifier(modifier) {
+            self.remove_modifier(modifier);
+        } else {
+            self.modifiers
+                .retain(|m| !modifier.conflicts_with(m) && !m.conflicts_with(&modifier));
+            self.modifiers.push(modifier);
+        }
+    }
+
     /// Calculate total score multiplier
     pub fn score_multiplier(&self) -> f32 {
         let difficulty_mult = self.difficulty.score_multiplier();
@@ -366,15 +425,40 @@ impl GameSettings {
 
     /// Get the effective playback speed
     pub fn playback_speed(&self) -> f32 {
-        if self.has_modifier(Modifier::DoubleTime) {
-            1.5
-        } else if self.has_modifier(Modifier::HalfTime) {
-            0.75
+        self.modifiers
+            .iter()
+            .map(Modifier::playback_speed)
+            .product()
+    }
+
+    /// Hard Rock's or Easy's factor, 1.0 with neither
+    fn map_modifier(&self, hard_rock: f32, easy: f32) -> f32 {
+        if self.has_modifier(Modifier::HardRock) {
+            hard_rock
+        } else if self.has_modifier(Modifier::EasyMod) {
+            easy
         } else {
             1.0
         }
     }
 
+    /// Circle size multiplier of the difficulty, with Hard Rock's smaller or Easy's
+    /// larger circles
+    pub fn circle_size_multiplier(&self) -> f32 {
+        self.difficulty.circle_size_multiplier() * self.map_modifier(0.85, 1.25)
+    }
+
+    /// Timing window multiplier: Hard Rock's are tighter, Easy's wider
+    pub fn timing_window_multiplier(&self) -> f64 {
+        self.map_modifier(0.8, 1.35) as f64
+    }
+
+    /// A map setting (CS, AR, OD or HP) raised by Hard Rock, at most to
+    /// `MAX_MAP_SETTING`, or lowered by Easy
+    pub fn scale_map_setting(&self, value: f32, hard_rock_scale: f32) -> f32 {
+        (value * self.map_modifier(hard_rock_scale, EASY_MAP_SCALE)).min(MAX_MAP_SETTING)
+    }
+
     /// Check if the game should end on miss
     pub fn end_on_miss(&self) -> bool {
         self.has_modifier(Modifier::SuddenDeath)
@@ -407,3 +491,113 @@ impl GameSettings {
         self.has_modifier(Modifier::Randomize)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beatmap::BeatmapSettings;
+    use crate::game::calculate_score_from_timing;
+
+    fn with(modifiers: &[Modifier]) -> GameSettings {
+        GameSettings {
+            modifiers: modifiers.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn hard_rock_raises_map_settings_up_to_ten_and_easy_halves_them() {
+        let map = BeatmapSettings {
+            circle_size: 4.0,
+            approach_rate: 8.0,
+            overall_difficulty: 6.0,
+            hp_drain: 5.0,
+            ..Default::default()
+        };
+
+        let hard_rock = map.with_modifiers(&with(&[Modifier::HardRock]));
+        assert_close(hard_rock.circle_size, 5.2);
+        assert_close(hard_rock.approach_rate, MAX_MAP_SETTING);
+        assert_close(hard_rock.overall_difficulty, 8.4);
+        assert_close(hard_rock.hp_drain, 7.0);
+        assert_eq!(hard_rock.slider_multiplier, map.slider_multiplier);
+
+        let easy = map.with_modifiers(&with(&[Modifier::EasyMod]));
+        assert_close(easy.circle_size, 2.0);
+        assert_close(easy.approach_rate, 4.0);
+        assert_close(easy.overall_difficulty, 3.0);
+        assert_close(easy.hp_drain, 2.5);
+
+        let plain = map.with_modifiers(&with(&[Modifier::DoubleTime, Modifier::Hidden]));
+        assert_close(plain.circle_size, 4.0);
+        assert_close(plain.approach_rate, 8.0);
+    }
+
+    #[test]
+    fn hard_rock_tightens_and_easy_widens_the_timing_windows() {
+        let normal = GameSettings::default();
+        let hard_rock = with(&[Modifier::HardRock]);
+        let easy = with(&[Modifier::EasyMod]);
+
+        // 70 ms is a perfect, but outside Hard Rock's 64 ms window
+        assert_eq!(calculate_score_from_timing(0.07, &normal), 300);
+        assert_eq!(calculate_score_from_timing(0.07, &hard_rock), 100);
+        // 100 ms is inside Easy's 108 ms window
+        assert_eq!(calculate_score_from_timing(0.1, &normal), 100);
+        assert_eq!(calculate_score_from_timing(0.1, &easy), 300);
+        // 400 ms misses unless the windows are widened
+        assert_eq!(calculate_score_from_timing(0.4, &normal), 0);
+        assert_eq!(calculate_score_from_timing(0.4, &easy), 50);
+        assert_eq!(calculate_score_from_timing(0.3, &hard_rock), 0);
+
+        assert_close(hard_rock.circle_size_multiplier(), 0.85);
+        assert_close(easy.circle_size_multiplier(), 1.25);
+    }
+
+    #[test]
+    fn toggling_a_modifier_replaces_the_ones_it_conflicts_with() {
+        let mut settings = GameSettings::default();
+        settings.toggle_modifier(Modifier::DoubleTime);
+        settings.toggle_modifier(Modifier::HardRock);
+        assert_close(settings.playback_speed(), 1.5);
+
+        settings.toggle_modifier(Modifier::HalfTime);
+        settings.toggle_modifier(Modifier::EasyMod);
+        assert_eq!(settings.modifiers, [Modifier::HalfTime, Modifier::EasyMod]);
+        assert_close(settings.playback_speed(), 0.75);
+
+        settings.toggle_modifier(Modifier::HalfTime);
+        assert_eq!(settings.modifiers, [Modifier::EasyMod]);
+        assert_close(settings.playback_speed(), 1.0);
+    }
+
+    #[test]
+    fn hits_score_with_the_multiplier_of_every_modifier() {
+        let settings = GameSettings {
+            difficulty: Difficulty::Hard,
+            ..with(&[Modifier::DoubleTime, Modifier::HardRock])
+        };
+        assert_close(settings.score_multiplier(), 3.375);
+
+        let config = crate::config::GameConfig {
+            game_settings: settings,
+            ..Default::default()
+        };
+        let mut state =
+            crate::structs::VisualizingState::new(Vec::new(), Vec::new(), config, "test".into());
+        state.record_hit(300, 0.0);
+        state.record_hit(100, 0.0);
+        assert_eq!(state.score, 1012 + 337);
+        assert_eq!(state.hits.perfect, 1);
+        assert_eq!(state.hits.good, 1);
+    }
+}
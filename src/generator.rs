@@ -0,0 +1,370 @@
+// src/generator.rs
+
+use crate::audio::ENERGY_RESOLUTION;
+use crate::beatmap::{Beatmap, HitObject, HitObjectId, HitObjectKind, Hitsound};
+use crate::difficulty::GENERATED_TAG;
+use crate::hashing::Fnv1a;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Thresholds that decide which object type the procedural generator produces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationSettings {
+    /// Gaps between beats at least this long (seconds) may become spinners
+    pub spinner_min_gap: f64,
+    /// Average energy (0.0 - 1.0) a gap needs to count as a sustained sound
+    pub spinner_min_energy: f32,
+    /// Beats closer together than this (seconds) count as a stream
+    pub stream_max_interval: f64,
+    /// Allowed deviation between intervals (seconds) for a stream to be "even"
+    pub stream_max_jitter: f64,
+    /// Minimum beats in a stream before it becomes a slider
+    pub stream_min_beats: usize,
+    /// Maximum beats covered by one slider; longer streams are split
+    pub slider_max_beats: usize,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            spinner_min_gap: 3.0,
+            spinner_min_energy: 0.3,
+            stream_max_interval: 0.2,
+            stream_max_jitter: 0.03,
+            stream_min_beats: 4,
+            slider_max_beats: 5,
+        }
+    }
+}
+
+/// Object type chosen for a section of the beat sequence
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeneratedKind {
+    Circle,
+    /// Slider covering several evenly spaced beats, one span per interval
+    Slider { end_time: f64, repeats: u32 },
+    Spinner { end_time: f64 },
+}
+
+/// A generated object before it is placed on the playfield
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedObject {
+    pub time: f64,
+    pub kind: GeneratedKind,
+}
+
+/// Seconds kept clear between a spinner and the beats around it
+const SPINNER_MARGIN: f64 = 0.5;
+
+/// Decide object types for a beat sequence. Pure and deterministic.
+pub fn classify_beats(
+    beats: &[f64],
+    energy: &[f32],
+    settings: &GenerationSettings,
+) -> Vec<GeneratedObject> {
+    let mut objects = Vec::with_capacity(beats.len());
+    let mut i = 0;
+
+    while i < beats.len() {
+        // Evenly spaced fast run starting here?
+        let stream_len = stream_length(beats, i, settings);
+        if stream_len >= settings.stream_min_beats.max(2) {
+            let mut start = i;
+            let end = i + stream_len;
+            while start < end - 1 {
+                let chunk_end = (start + settings.slider_max_beats.max(2)).min(end) - 1;
+                let spans = (chunk_end - start) as u32;
+                if spans == 0 {
+                    break;
+                }
+                objects.push(GeneratedObject {
+                    time: beats[start],
+                    kind: GeneratedKind::Slider {
+                        end_time: beats[chunk_end],
+                        repeats: spans - 1,
+                    },
+                });
+                start = chunk_end + 1;
+            }
+            // A single beat left over after the last slider stays a circle
+            if start == end - 1 {
+                objects.push(GeneratedObject {
+                    time: beats[start],
+                    kind: GeneratedKind::Circle,
+                });
+            }
+            i = end;
+        } else {
+            objects.push(GeneratedObject {
+                time: beats[i],
+                kind: GeneratedKind::Circle,
+            });
+            i += 1;
+        }
+
+        // Long sustained gap after the last object becomes a spinner
+        if i < beats.len() && i > 0 {
+            let gap_start = beats[i - 1];
+            let gap_end = beats[i];
+            if gap_end - gap_start >= settings.spinner_min_gap
+                && average_energy(energy, gap_start, gap_end) >= settings.spinner_min_energy
+            {
+                let start = gap_start + SPINNER_MARGIN;
+                let end = gap_end - SPINNER_MARGIN;
+                if end > start {
+                    objects.push(GeneratedObject {
+                        time: start,
+                        kind: GeneratedKind::Spinner { end_time: end },
+                    });
+                }
+            }
+        }
+    }
+
+    objects
+}
+
+/// Number of beats in the evenly spaced fast run starting at `start` (1 if none)
+fn stream_length(beats: &[f64], start: usize, settings: &GenerationSettings) -> usize {
+    if start + 1 >= beats.len() {
+        return 1;
+    }
+    let first_interval = beats[start + 1] - beats[start];
+    if first_interval > settings.stream_max_interval {
+        return 1;
+    }
+
+    let mut len = 2;
+    while start + len < beats.len() {
+        let interval = beats[start + len] - beats[start + len - 1];
+        if (interval - first_interval).abs() > settings.stream_max_jitter {
+            break;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// Average energy between two times
+fn average_energy(energy: &[f32], start: f64, end: f64) -> f32 {
+    let first = (start / ENERGY_RESOLUTION).floor().max(0.0) as usize;
+    let last = ((end / ENERGY_RESOLUTION).ceil() as usize).min(energy.len());
+    if first >= last {
+        return 0.0;
+    }
+    energy[first..last].iter().sum::<f32>() / (last - first) as f32
+}
+
+/// Build a playable beatmap from generated objects, placing them on the playfield
+pub fn generate_beatmap(
+    song_path: &str,
+    objects: &[GeneratedObject],
+    rng: &mut impl Rng,
+    spawn_radius: f32,
+    center: Vec2,
+) -> Beatmap {
+    let mut beatmap = Beatmap::new(String::new(), String::new(), song_path.to_string());
+    beatmap.tags.push(GENERATED_TAG.to_string());
+    let slider_length = (spawn_radius * 0.5).max(40.0);
+
+    for (index, object) in objects.iter().enumerate() {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let distance = rng.gen_range(0.0..spawn_radius.max(1.0));
+        let position = center + Vec2::new(angle.cos(), angle.sin()) * distance;
+
+        let (position, kind) = match object.kind {
+            GeneratedKind::Circle => (position, HitObjectKind::Circle),
+            GeneratedKind::Slider { end_time, repeats } => {
+                // Point the slider back toward the center so it stays on screen
+                let direction = (center - position).normalize_or(Vec2::X);
+                let bend = direction.perp() * rng.gen_range(-0.3..0.3) * slider_length;
+
+                // Pick the velocity that makes the spans fill the stream exactly
+                let span_duration = (end_time - object.time) / (repeats as f64 + 1.0);
+                let unit_span = beatmap.slider_span_duration(object.time, slider_length as f64, 1.0);
+                let velocity = unit_span / span_duration.max(0.001);
+
+                (
+                    position,
+                    HitObjectKind::Slider {
+                        control_points: vec![
+                            position,
+                            position + direction * slider_length * 0.5 + bend,
+                            position + direction * slider_length,
+                        ],
+                        repeats,
+                        pixel_length: slider_length as f64,
+                        velocity,
+                    },
+                )
+            }
+            GeneratedKind::Spinner { end_time } => (center, HitObjectKind::Spinner { end_time }),
+        };
+
+        beatmap.hit_objects.push(HitObject {
+            id: index as HitObjectId + 1,
+            time: object.time,
+            position,
+            kind,
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Normal,
+            sample_set: None,
+        });
+    }
+
+    beatmap.sort_hit_objects();
+    beatmap
+}
+
+/// Seed for generation derived from the song file contents and the settings,
+/// so the same file and settings always produce the same map
+pub fn song_seed(path: &str, settings: &GenerationSettings) -> u64 {
+    let mut hasher = Fnv1a::new();
+
+    match fs::read(path) {
+        Ok(contents) => hasher.write(&contents),
+        Err(_) => hasher.write(path.as_bytes()),
+    }
+    if let Ok(json) = serde_json::to_vec(settings) {
+        hasher.write(&json);
+    }
+    hasher.finish()
+}
+
+/// Seed as 16 hex digits, the form shown on the results screen and pasted back
+pub fn format_seed(seed: u64) -> String {
+    format!("{:016x}", seed)
+}
+
+/// Parse a pasted seed: hex digits, with or without a leading "0x"
+pub fn parse_seed(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    u64::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loud energy for `seconds` of audio
+    fn loud(seconds: f64) -> Vec<f32> {
+        vec![1.0; (seconds / ENERGY_RESOLUTION) as usize + 1]
+    }
+
+    fn kinds(objects: &[GeneratedObject]) -> Vec<&'static str> {
+        objects
+            .iter()
+            .map(|object| match object.kind {
+                GeneratedKind::Circle => "circle",
+                GeneratedKind::Slider { .. } => "slider",
+                GeneratedKind::Spinner { .. } => "spinner",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spaced_beats_stay_circles() {
+        let beats = [1.0, 1.5, 2.0, 2.5];
+        let objects = classify_beats(&beats, &loud(3.0), &GenerationSettings::default());
+        assert_eq!(kinds(&objects), ["circle"; 4]);
+    }
+
+    #[test]
+    fn an_even_stream_becomes_sliders_with_a_leftover_circle() {
+        // Seven beats 0.1s apart: a five beat slider, then two beats for a second one
+        let beats: Vec<f64> = (0..7).map(|i| 1.0 + i as f64 * 0.1).collect();
+        let objects = classify_beats(&beats, &loud(2.0), &GenerationSettings::default());
+        assert_eq!(kinds(&objects), ["slider", "slider"]);
+        assert_eq!(
+            objects[0].kind,
+            GeneratedKind::Slider {
+                end_time: beats[4],
+                repeats: 3
+            }
+        );
+
+        // Six beats: a five beat slider and a lone circle
+        let objects = classify_beats(&beats[..6], &loud(2.0), &GenerationSettings::default());
+        assert_eq!(kinds(&objects), ["slider", "circle"]);
+        assert_eq!(objects[1].time, beats[5]);
+    }
+
+    #[test]
+    fn uneven_fast_beats_are_not_a_stream() {
+        let beats = [1.0, 1.1, 1.3, 1.35, 1.5];
+        let objects = classify_beats(&beats, &loud(2.0), &GenerationSettings::default());
+        assert!(kinds(&objects).iter().all(|kind| *kind != "slider"));
+    }
+
+    #[test]
+    fn a_long_loud_gap_becomes_a_spinner_clear_of_its_beats() {
+        let beats = [1.0, 6.0];
+        let objects = classify_beats(&beats, &loud(7.0), &GenerationSettings::default());
+        assert_eq!(kinds(&objects), ["circle", "spinner", "circle"]);
+        assert_eq!(
+            objects[1],
+            GeneratedObject {
+                time: 1.0 + SPINNER_MARGIN,
+                kind: GeneratedKind::Spinner {
+                    end_time: 6.0 - SPINNER_MARGIN
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn a_quiet_gap_stays_empty() {
+        let beats = [1.0, 6.0];
+        let quiet = vec![0.0; loud(7.0).len()];
+        let objects = classify_beats(&beats, &quiet, &GenerationSettings::default());
+        assert_eq!(kinds(&objects), ["circle", "circle"]);
+    }
+
+    /// Circles of the map generated for `beats` from `seed`, as a play would start
+    fn circles(beats: &[f64], seed: u64) -> Vec<(Vec2, f64)> {
+        use rand::SeedableRng;
+        let objects = classify_beats(beats, &loud(12.0), &GenerationSettings::default());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let beatmap = generate_beatmap(
+            "song.mp3",
+            &objects,
+            &mut rng,
+            300.0,
+            Vec2::new(640.0, 360.0),
+        );
+        crate::game::initialize_circles(&beatmap, 1.0, 3.0, &crate::config::GameConfig::default())
+            .iter()
+            .map(|circle| (circle.position, circle.hit_time))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_lays_out_the_same_circles() {
+        let beats: Vec<f64> = (0..40).map(|i| 1.0 + i as f64 * 0.25).collect();
+        let first = circles(&beats, 42);
+        assert_eq!(first.len(), beats.len());
+        assert_eq!(first, circles(&beats, 42));
+        assert_ne!(first, circles(&beats, 43));
+    }
+
+    #[test]
+    fn seeds_parse_back_from_their_text() {
+        let seed = 0x00ab_cdef_1234_5678;
+        assert_eq!(parse_seed(&format_seed(seed)), Some(seed));
+        assert_eq!(parse_seed(" 0xFF "), Some(255));
+        assert_eq!(parse_seed(""), None);
+        assert_eq!(parse_seed("12345678901234567"), None);
+        assert_eq!(parse_seed("xyz"), None);
+    }
+}
@@ -0,0 +1,489 @@
+// src/guidelines.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+
+use crate::beatmap::{Beatmap, BeatmapAssets};
+use crate::difficulty::DifficultyMetrics;
+use crate::osu_format::OSU_SCALE;
+
+/// Guidelines shipped with the game, used unless the player provides their own
+const BUNDLED_GUIDELINES: &str = include_str!("assets/guidelines.json");
+
+/// Player's own guidelines next to config.json; replaces the bundled file entirely
+pub const USER_GUIDELINES_PATH: &str = "guidelines.json";
+
+/// Objects this close together, in quarter beats, count as part of a stream. A little
+/// over one so rounded object times still chain
+const STREAM_GAP_QUARTERS: f64 = 1.05;
+
+/// Jumps between objects further apart than this many beats are never checked for
+/// spacing; a long gap gives the player time to move
+const SPACING_MAX_BEATS: f64 = 2.0;
+
+/// Shortest difficulty-name word matched with a typo, so "ez" never fuzzily matches "ex"
+const FUZZY_MIN_LENGTH: usize = 4;
+
+/// Seconds without edits before the map is checked again, so placing notes along with
+/// the song doesn't recheck the whole map on each one
+const CHECK_DEBOUNCE: f32 = 0.5;
+
+/// Limits a difficulty named for a tier is expected to keep to. Missing limits aren't
+/// checked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyTier {
+    pub name: String,
+    /// Other difficulty names meaning this tier, lowercase
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub min_stars: f32,
+    pub max_stars: f32,
+    /// Notes per second over any window of `Guidelines::nps_window`
+    pub max_nps: Option<f64>,
+    /// Objects in one run of quarter-beat (or closer) notes
+    pub max_stream: Option<usize>,
+    /// Distance jumped between consecutive objects, in osu! pixels
+    pub max_spacing: Option<f32>,
+}
+
+/// Per-tier difficulty guidelines checked in the editor. Advisory only; nothing here
+/// stops a map from being saved
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Guidelines {
+    /// Seconds each notes-per-second window spans
+    pub nps_window: f64,
+    pub tiers: Vec<DifficultyTier>,
+}
+
+impl Guidelines {
+    /// The player's guidelines if they have a readable file, the bundled ones otherwise
+    pub fn load() -> Self {
+        match fs::read_to_string(USER_GUIDELINES_PATH) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(guidelines) => return guidelines,
+                Err(e) => warn!(
+                    "Failed to parse {}: {}, using the bundled guidelines",
+                    USER_GUIDELINES_PATH, e
+                ),
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => warn!(
+                "Failed to read {}: {}, using the bundled guidelines",
+                USER_GUIDELINES_PATH, e
+            ),
+        }
+        Self::bundled()
+    }
+
+    fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_GUIDELINES).unwrap_or_else(|e| {
+            error!("Bundled guidelines are invalid: {}", e);
+            Self {
+                nps_window: 2.0,
+                tiers: Vec::new(),
+            }
+        })
+    }
+
+    /// Check a beatmap against the tier its difficulty name matches
+    pub fn check(&self, beatmap: &Beatmap) -> GuidelineReport {
+        let version = beatmap.metadata.version.clone();
+        let Some(tier) = match_tier(&version, &self.tiers) else {
+            return GuidelineReport {
+                version,
+                ..default()
+            };
+        };
+
+        let mut violations = Vec::new();
+        let stars = DifficultyMetrics::calculate(beatmap).star_rating();
+        if stars < tier.min_stars || stars > tier.max_stars {
+            violations.push(GuidelineViolation {
+                span: None,
+                message: format!(
+                    "{:.2} stars (expected {:.1}-{:.1})",
+                    stars, tier.min_stars, tier.max_stars
+                ),
+            });
+        }
+
+        let objects = &beatmap.hit_objects;
+        if let Some(max_nps) = tier.max_nps {
+            let times: Vec<f64> = objects.iter().map(|object| object.time).collect();
+            let dense = nps_windows(&times, self.nps_window)
+                .into_iter()
+                .zip(&times)
+                .filter(|(nps, _)| *nps > max_nps)
+                .map(|(nps, &start)| (start, start + self.nps_window, nps))
+                .collect();
+            for (start, end, nps) in merge_spans(dense) {
+                violations.push(GuidelineViolation {
+                    span: Some((start, end)),
+                    message: format!("{:.1} notes/s (max {:.1})", nps, max_nps),
+                });
+            }
+        }
+
+        if let Some(max_stream) = tier.max_stream {
+            for (first, last) in stream_runs(beatmap) {
+                let length = last - first + 1;
+                if length > max_stream {
+                    violations.push(GuidelineViolation {
+                        span: Some((objects[first].time, objects[last].time)),
+                        message: format!("{}-note stream (max {})", length, max_stream),
+                    });
+                }
+            }
+        }
+
+        if let Some(max_spacing) = tier.max_spacing {
+            let jumps = objects
+                .windows(2)
+                .filter_map(|pair| {
+                    let end = beatmap.object_end_time(&pair[0]);
+                    let beats = (pair[1].time - end) / beatmap.get_beat_length_at(end);
+                    let from = pair[0].kind.end_position(pair[0].position);
+                    let distance = from.distance(pair[1].position) * OSU_SCALE;
+                    let jumped = beats <= SPACING_MAX_BEATS && distance > max_spacing;
+                    jumped.then_some((end, pair[1].time, distance as f64))
+                })
+                .collect();
+            for (start, end, distance) in merge_spans(jumps) {
+                violations.push(GuidelineViolation {
+                    span: Some((start, end)),
+                    message: format!("{:.0}px jump (max {:.0}px)", distance, max_spacing),
+                });
+            }
+        }
+
+        violations.sort_by(|a, b| {
+            let start = |violation: &GuidelineViolation| violation.span.map_or(-1.0, |s| s.0);
+            start(a).total_cmp(&start(b))
+        });
+        GuidelineReport {
+            version,
+            tier: Some(tier.name.clone()),
+            violations,
+        }
+    }
+}
+
+/// A place a difficulty goes past its tier's limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuidelineViolation {
+    /// Song time range (seconds) it covers; None when it's about the whole map
+    pub span: Option<(f64, f64)>,
+    /// What was measured and the limit exceeded
+    pub message: String,
+}
+
+/// Guideline check of the beatmap being edited, redone whenever it changes
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct GuidelineReport {
+    /// Difficulty name checked
+    pub version: String,
+    /// Tier it matched, None when no tier fits the name
+    pub tier: Option<String>,
+    pub violations: Vec<GuidelineViolation>,
+}
+
+/// Tier a difficulty name refers to. Words are tried from the last, since names like
+/// "Someone's Insane" end in the tier; each word must equal a tier name or alias, or
+/// be one typo away for longer words
+pub fn match_tier<'a>(version: &str, tiers: &'a [DifficultyTier]) -> Option<&'a DifficultyTier> {
+    let version = version.to_lowercase();
+    let words: Vec<&str> = version
+        .split(|c: char| !(c.is_alphanumeric() || c == '+'))
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    for word in words.iter().rev() {
+        if let Some(tier) = tiers
+            .iter()
+            .find(|tier| tier_names(tier).any(|name| name == *word))
+        {
+            return Some(tier);
+        }
+    }
+    words
+        .iter()
+        .rev()
+        .filter(|word| word.chars().count() >= FUZZY_MIN_LENGTH)
+        .find_map(|word| {
+            tiers.iter().find(|tier| {
+                tier_names(tier).any(|name| {
+                    name.chars().count() >= FUZZY_MIN_LENGTH && edit_distance(word, &name) <= 1
+                })
+            })
+        })
+}
+
+/// A tier's name and aliases, lowercase
+fn tier_names(tier: &DifficultyTier) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(tier.name.to_lowercase())
+        .chain(tier.aliases.iter().map(|alias| alias.to_lowercase()))
+}
+
+/// Edits (insertions, deletions, substitutions, swaps of neighbours) turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Notes per second in the window of `window` seconds starting at each time. Times must
+/// be sorted
+pub fn nps_windows(times: &[f64], window: f64) -> Vec<f64> {
+    if window <= 0.0 {
+        return vec![0.0; times.len()];
+    }
+    let mut end = 0;
+    times
+        .iter()
+        .enumerate()
+        .map(|(start, &time)| {
+            end = end.max(start);
+            while end < times.len() && times[end] < time + window {
+                end += 1;
+            }
+            (end - start) as f64 / window
+        })
+        .collect()
+}
+
+/// First and last index of each run of objects a quarter beat or less apart
+fn stream_runs(beatmap: &Beatmap) -> Vec<(usize, usize)> {
+    let objects = &beatmap.hit_objects;
+    let mut runs = Vec::new();
+    let mut first = 0;
+    for i in 1..=objects.len() {
+        let chained = i < objects.len() && {
+            let gap = objects[i].time - objects[i - 1].time;
+            gap <= beatmap.get_beat_length_at(objects[i - 1].time) / 4.0 * STREAM_GAP_QUARTERS
+        };
+        if !chained {
+            if i - first > 1 {
+                runs.push((first, i - 1));
+            }
+            first = i;
+        }
+    }
+    runs
+}
+
+/// Join overlapping (start, end, value) spans sorted by start, keeping the largest value
+fn merge_spans(spans: Vec<(f64, f64, f64)>) -> Vec<(f64, f64, f64)> {
+    let mut merged: Vec<(f64, f64, f64)> = Vec::new();
+    for (start, end, value) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => {
+                last.1 = last.1.max(end);
+                last.2 = last.2.max(value);
+            }
+            _ => merged.push((start, end, value)),
+        }
+    }
+    merged
+}
+
+/// Guideline check of the current beatmap, run on a worker thread once edits settle
+#[derive(Resource, Default)]
+pub struct GuidelineCheck {
+    /// Seconds until the next check starts, while one is due
+    due_in: Option<f32>,
+    /// Check in flight. Dropped when the map changes again, so an outdated report
+    /// never replaces a newer one
+    receiver: Option<Mutex<Receiver<GuidelineReport>>>,
+}
+
+/// Re-check the current beatmap in the background a moment after it or the guidelines
+/// last changed
+pub fn refresh_guideline_report(
+    time: Res<Time>,
+    beatmap_assets: Res<BeatmapAssets>,
+    guidelines: Res<Guidelines>,
+    mut check: ResMut<GuidelineCheck>,
+    mut report: ResMut<GuidelineReport>,
+) {
+    if beatmap_assets.is_changed() || guidelines.is_changed() {
+        check.due_in = Some(CHECK_DEBOUNCE);
+        check.receiver = None;
+    }
+
+    if let Some(due_in) = check.due_in {
+        let due_in = due_in - time.delta_secs();
+        if due_in > 0.0 {
+            check.due_in = Some(due_in);
+        } else {
+            let beatmap = beatmap_assets.current().cloned();
+            let guidelines = guidelines.clone();
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                let checked = beatmap
+                    .map(|beatmap| guidelines.check(&beatmap))
+                    .unwrap_or_default();
+                let _ = sender.send(checked);
+            });
+            check.due_in = None;
+            check.receiver = Some(Mutex::new(receiver));
+        }
+    }
+
+    let Some(receiver) = check.receiver.as_ref() else {
+        return;
+    };
+    let received = match receiver.lock() {
+        Ok(receiver) => receiver.try_recv(),
+        Err(_) => Err(mpsc::TryRecvError::Disconnected),
+    };
+    match received {
+        Ok(checked) => {
+            check.receiver = None;
+            report.set_if_neq(checked);
+        }
+        Err(mpsc::TryRecvError::Empty) => {}
+        Err(mpsc::TryRecvError::Disconnected) => {
+            warn!("Guideline check stopped without a report");
+            check.receiver = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beatmap::{HitObject, HitObjectKind, Hitsound};
+
+    fn tier(name: &str, aliases: &[&str]) -> DifficultyTier {
+        DifficultyTier {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            min_stars: 0.0,
+            max_stars: 100.0,
+            max_nps: None,
+            max_stream: None,
+            max_spacing: None,
+        }
+    }
+
+    fn tiers() -> Vec<DifficultyTier> {
+        vec![
+            tier("Easy", &["ez", "beginner"]),
+            tier("Normal", &["medium"]),
+            tier("Insane", &[]),
+        ]
+    }
+
+    fn matched(version: &str) -> Option<String> {
+        match_tier(version, &tiers()).map(|tier| tier.name.clone())
+    }
+
+    /// Circles at the given times and x positions; 120 BPM, so a quarter beat is 0.125s
+    fn beatmap(objects: &[(f64, f32)]) -> Beatmap {
+        Beatmap {
+            hit_objects: objects
+                .iter()
+                .enumerate()
+                .map(|(i, &(time, x))| HitObject {
+                    id: i as u64 + 1,
+                    time,
+                    position: Vec2::new(x, 0.0),
+                    kind: HitObjectKind::Circle,
+                    new_combo: false,
+                    combo_index: 0,
+                    hitsound: Hitsound::Normal,
+                    sample_set: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn tiers_match_by_name_or_alias_from_the_last_word() {
+        assert_eq!(matched("Easy").as_deref(), Some("Easy"));
+        assert_eq!(matched("Someone's EZ").as_deref(), Some("Easy"));
+        assert_eq!(matched("Beginner").as_deref(), Some("Easy"));
+        assert_eq!(matched("Easy Medium").as_deref(), Some("Normal"));
+        assert_eq!(matched("Expert"), None);
+        assert_eq!(matched(""), None);
+    }
+
+    #[test]
+    fn long_names_match_with_one_typo() {
+        assert_eq!(matched("Insnae").as_deref(), Some("Insane"));
+        assert_eq!(matched("Insanr").as_deref(), Some("Insane"));
+        assert_eq!(matched("Nromal's Mediun").as_deref(), Some("Normal"));
+        assert_eq!(matched("Insaaane"), None);
+        // Short words need an exact match
+        assert_eq!(matched("ex"), None);
+        // An exact match on an earlier word wins over a typo on the last one
+        assert_eq!(matched("Easy Insnae").as_deref(), Some("Easy"));
+    }
+
+    #[test]
+    fn nps_windows_count_notes_starting_at_each_time() {
+        let times = [0.0, 0.5, 1.0, 1.5, 4.0];
+        assert_eq!(nps_windows(&times, 2.0), vec![2.0, 1.5, 1.0, 0.5, 0.5]);
+        assert_eq!(nps_windows(&times, 0.0), vec![0.0; 5]);
+        assert!(nps_windows(&[], 2.0).is_empty());
+    }
+
+    #[test]
+    fn stream_runs_chain_quarter_beat_gaps() {
+        let map = beatmap(&[
+            (0.0, 0.0),
+            (0.125, 0.0),
+            (0.25, 0.0),
+            (1.0, 0.0),
+            (1.25, 0.0),
+            (2.0, 0.0),
+            (2.1, 0.0),
+        ]);
+        assert_eq!(stream_runs(&map), vec![(0, 2), (5, 6)]);
+        assert!(stream_runs(&beatmap(&[(0.0, 0.0)])).is_empty());
+    }
+
+    #[test]
+    fn spacing_is_measured_in_osu_pixels() {
+        let mut easy = tier("Easy", &[]);
+        easy.max_spacing = Some(160.0);
+        let guidelines = Guidelines {
+            nps_window: 2.0,
+            tiers: vec![easy],
+        };
+
+        let mut map = beatmap(&[(0.0, 0.0), (0.5, 190.0)]);
+        map.metadata.version = "Easy".to_string();
+        assert!(guidelines.check(&map).violations.is_empty());
+
+        map.hit_objects[1].position.x = 250.0;
+        let report = guidelines.check(&map);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].message, "200px jump (max 160px)");
+        assert_eq!(report.violations[0].span, Some((0.0, 0.5)));
+    }
+}
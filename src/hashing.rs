@@ -0,0 +1,62 @@
+// src/hashing.rs
+
+/// 64-bit FNV-1a, the stable hash behind cache keys, seeds and digests. Unlike
+/// `DefaultHasher` its output never changes between builds, so it is safe to
+/// persist or compare across machines
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0100_0000_01b3;
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Fnv1a(OFFSET_BASIS)
+    }
+
+    /// Feed more bytes. Feeding in pieces hashes the same as feeding them joined
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a::new()
+    }
+}
+
+/// FNV-1a of a single byte string
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_reference_vectors() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn pieces_hash_like_the_joined_bytes() {
+        let mut hasher = Fnv1a::new();
+        hasher.write(b"foo");
+        hasher.write(b"");
+        hasher.write(b"bar");
+        assert_eq!(hasher.finish(), fnv1a(b"foobar"));
+    }
+}
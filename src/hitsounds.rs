@@ -0,0 +1,101 @@
+// src/hitsounds.rs
+
+use bevy::prelude::*;
+use rodio::buffer::SamplesBuffer;
+use rodio::source::Buffered;
+use rodio::{Decoder, OutputStreamHandle, Sink, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::beatmap::Hitsound;
+use crate::editor_audio::tick_sample;
+
+/// Folder searched for custom hitsound samples, e.g. `whistle.wav`
+pub const HITSOUND_DIR: &str = "src/assets/hitsounds";
+
+/// Sample file extensions tried, in order
+const SAMPLE_EXTENSIONS: [&str; 3] = ["wav", "ogg", "mp3"];
+
+/// Hitsounds that can ring at once; further hits while all are busy stay silent
+/// rather than play late
+pub const HITSOUND_VOICES: usize = 8;
+
+/// File stem of a hitsound's sample
+fn sample_name(hitsound: Hitsound) -> &'static str {
+    match hitsound {
+        Hitsound::Normal => "normal",
+        Hitsound::Whistle => "whistle",
+        Hitsound::Finish => "finish",
+        Hitsound::Clap => "clap",
+    }
+}
+
+/// Decode a sample file whole, so playing it never touches the disk
+fn decode_sample(path: &Path) -> Result<SamplesBuffer<f32>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+    let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+    let samples: Vec<f32> = decoder.convert_samples().collect();
+    Ok(SamplesBuffer::new(channels, sample_rate, samples))
+}
+
+/// A hitsound's sample: the file in `HITSOUND_DIR` if there is one, otherwise the
+/// editor's synthesized tick, so the game still sounds without shipped samples
+fn load_sample(hitsound: Hitsound) -> SamplesBuffer<f32> {
+    let name = sample_name(hitsound);
+    let found = SAMPLE_EXTENSIONS
+        .iter()
+        .map(|ext| Path::new(HITSOUND_DIR).join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file());
+    match found.map(|path| decode_sample(&path)) {
+        Some(Ok(sample)) => sample,
+        Some(Err(e)) => {
+            warn!("Using the built-in {} hitsound: {}", name, e);
+            tick_sample(hitsound)
+        }
+        None => tick_sample(hitsound),
+    }
+}
+
+/// Hitsound samples decoded at startup and a pool of sinks to play them on. Each
+/// sound gets an idle sink of its own, so hits close together overlap instead of
+/// queuing behind or cutting off each other
+#[derive(Resource)]
+pub struct HitsoundPlayer {
+    /// Normal, whistle, finish and clap, in that order. Buffered so each hit plays a
+    /// cheap clone sharing the decoded samples
+    samples: [Buffered<SamplesBuffer<f32>>; 4],
+    voices: Vec<Sink>,
+}
+
+impl HitsoundPlayer {
+    /// Load the samples and open the voices on the game's output
+    pub fn new(stream_handle: &OutputStreamHandle) -> Result<Self, String> {
+        let voices = (0..HITSOUND_VOICES)
+            .map(|_| Sink::try_new(stream_handle))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to open hitsound output: {}", e))?;
+        Ok(Self {
+            samples: [
+                Hitsound::Normal,
+                Hitsound::Whistle,
+                Hitsound::Finish,
+                Hitsound::Clap,
+            ]
+            .map(|hitsound| load_sample(hitsound).buffered()),
+            voices,
+        })
+    }
+
+    /// Play hitsounds at `volume` (0.0 - 1.0), each on an idle voice
+    pub fn play(&self, hitsounds: impl IntoIterator<Item = Hitsound>, volume: f32) {
+        let idle = self.voices.iter().filter(|voice| voice.empty());
+        for (voice, hitsound) in idle.zip(hitsounds) {
+            voice.set_volume(volume);
+            voice.append(self.samples[hitsound as usize].clone());
+            voice.play();
+        }
+    }
+}
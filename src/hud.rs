@@ -0,0 +1,452 @@
+// src/hud.rs
+
+use crate::analytics::Grade;
+use crate::score_format::ScoreStyle;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Smallest scale a HUD element can be shrunk to
+pub const HUD_MIN_SCALE: f32 = 0.5;
+
+/// Largest scale a HUD element can be grown to
+pub const HUD_MAX_SCALE: f32 = 2.0;
+
+/// Scale change per key press in the layout editor
+pub const HUD_SCALE_STEP: f32 = 0.1;
+
+/// Seconds the accuracy readout takes to ease to a new value
+pub const ACCURACY_TWEEN_SECONDS: f64 = 0.2;
+
+/// Elements of the gameplay HUD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum HudElement {
+    Score,
+    Combo,
+    MaxCombo,
+    Accuracy,
+    Lives,
+    Health,
+    HitErrorBar,
+}
+
+impl HudElement {
+    /// All HUD elements in editor order
+    pub fn all() -> Vec<HudElement> {
+        vec![
+            HudElement::Score,
+            HudElement::Combo,
+            HudElement::MaxCombo,
+            HudElement::Accuracy,
+            HudElement::Lives,
+            HudElement::Health,
+            HudElement::HitErrorBar,
+        ]
+    }
+
+    /// Get display name for the element
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            HudElement::Score => "Score",
+            HudElement::Combo => "Combo",
+            HudElement::MaxCombo => "Max Combo",
+            HudElement::Accuracy => "Accuracy",
+            HudElement::Lives => "Lives",
+            HudElement::Health => "Health",
+            HudElement::HitErrorBar => "Hit Error Bar",
+        }
+    }
+
+    /// Approximate on-screen size at scale 1, used for clamping and picking
+    pub fn base_size(&self) -> Vec2 {
+        match self {
+            HudElement::Score => Vec2::new(220.0, 36.0),
+            HudElement::Combo => Vec2::new(100.0, 44.0),
+            HudElement::MaxCombo => Vec2::new(170.0, 24.0),
+            HudElement::Accuracy => Vec2::new(140.0, 36.0),
+            HudElement::Lives => Vec2::new(110.0, 26.0),
+            HudElement::Health => Vec2::new(320.0, 12.0),
+            // The ends of the bar are the edges of the Okay window
+            HudElement::HitErrorBar => Vec2::new(300.0, 36.0),
+        }
+    }
+}
+
+/// Screen point a HUD element is positioned relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HudAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl HudAnchor {
+    /// All anchors
+    pub fn all() -> Vec<HudAnchor> {
+        vec![
+            HudAnchor::TopLeft,
+            HudAnchor::Top,
+            HudAnchor::TopRight,
+            HudAnchor::Left,
+            HudAnchor::Center,
+            HudAnchor::Right,
+            HudAnchor::BottomLeft,
+            HudAnchor::Bottom,
+            HudAnchor::BottomRight,
+        ]
+    }
+
+    /// World position of the anchor on a screen of the given size (origin at center)
+    pub fn point(&self, screen: Vec2) -> Vec2 {
+        let half = screen / 2.0;
+        let (x, y) = match self {
+            HudAnchor::TopLeft => (-1.0, 1.0),
+            HudAnchor::Top => (0.0, 1.0),
+            HudAnchor::TopRight => (1.0, 1.0),
+            HudAnchor::Left => (-1.0, 0.0),
+            HudAnchor::Center => (0.0, 0.0),
+            HudAnchor::Right => (1.0, 0.0),
+            HudAnchor::BottomLeft => (-1.0, -1.0),
+            HudAnchor::Bottom => (0.0, -1.0),
+            HudAnchor::BottomRight => (1.0, -1.0),
+        };
+        Vec2::new(half.x * x, half.y * y)
+    }
+
+    /// Anchor closest to a world position
+    pub fn nearest(position: Vec2, screen: Vec2) -> HudAnchor {
+        HudAnchor::all()
+            .into_iter()
+            .min_by(|a, b| {
+                a.point(screen)
+                    .distance_squared(position)
+                    .total_cmp(&b.point(screen).distance_squared(position))
+            })
+            .unwrap_or(HudAnchor::Center)
+    }
+}
+
+/// Placement of one HUD element
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HudElementLayout {
+    /// Screen point the offset is measured from
+    pub anchor: HudAnchor,
+    /// Offset of the element's center from the anchor
+    pub offset: Vec2,
+    /// Size multiplier
+    pub scale: f32,
+    /// Whether the element is drawn during gameplay
+    pub visible: bool,
+}
+
+impl HudElementLayout {
+    fn at(anchor: HudAnchor, x: f32, y: f32) -> Self {
+        Self {
+            anchor,
+            offset: Vec2::new(x, y),
+            scale: 1.0,
+            visible: true,
+        }
+    }
+}
+
+/// Positions of every HUD element, stored in the config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudLayout {
+    pub elements: BTreeMap<HudElement, HudElementLayout>,
+    /// Draw a grade-colored bar under the accuracy readout
+    pub accuracy_bar: bool,
+    /// How the score is written on the HUD, results and analytics
+    pub score_style: ScoreStyle,
+}
+
+impl Default for HudLayout {
+    fn default() -> Self {
+        let elements = HudElement::all()
+            .into_iter()
+            .map(|element| (element, Self::default_for(element)))
+            .collect();
+        Self {
+            elements,
+            accuracy_bar: true,
+            score_style: ScoreStyle::default(),
+        }
+    }
+}
+
+impl HudLayout {
+    /// Default placement of an element
+    pub fn default_for(element: HudElement) -> HudElementLayout {
+        match element {
+            HudElement::Score => HudElementLayout::at(HudAnchor::TopRight, -130.0, -30.0),
+            HudElement::Combo => HudElementLayout::at(HudAnchor::BottomLeft, 70.0, 40.0),
+            HudElement::MaxCombo => HudElementLayout::at(HudAnchor::TopRight, -105.0, -62.0),
+            HudElement::Accuracy => HudElementLayout::at(HudAnchor::BottomLeft, 85.0, 92.0),
+            HudElement::Lives => HudElementLayout::at(HudAnchor::TopLeft, 75.0, -30.0),
+            HudElement::Health => HudElementLayout::at(HudAnchor::Top, 0.0, -20.0),
+            HudElement::HitErrorBar => HudElementLayout::at(HudAnchor::Bottom, 0.0, 30.0),
+        }
+    }
+
+    /// Placement of an element, falling back to its default
+    pub fn get(&self, element: HudElement) -> HudElementLayout {
+        self.elements
+            .get(&element)
+            .copied()
+            .unwrap_or_else(|| Self::default_for(element))
+    }
+
+    /// Mutable placement of an element
+    pub fn get_mut(&mut self, element: HudElement) -> &mut HudElementLayout {
+        self.elements
+            .entry(element)
+            .or_insert_with(|| Self::default_for(element))
+    }
+
+    /// On-screen size of an element at its current scale
+    pub fn size(&self, element: HudElement) -> Vec2 {
+        element.base_size() * self.get(element).scale
+    }
+
+    /// World position of an element's center, kept fully on screen so layouts
+    /// saved at another resolution or aspect ratio stay reachable
+    pub fn position(&self, element: HudElement, screen: Vec2) -> Vec2 {
+        let layout = self.get(element);
+        clamp_on_screen(
+            layout.anchor.point(screen) + layout.offset,
+            self.size(element),
+            screen,
+        )
+    }
+
+    /// Move an element's center to a world position, re-anchoring it to the nearest anchor
+    pub fn move_to(&mut self, element: HudElement, position: Vec2, screen: Vec2) {
+        let position = clamp_on_screen(position, self.size(element), screen);
+        let anchor = HudAnchor::nearest(position, screen);
+        let layout = self.get_mut(element);
+        layout.anchor = anchor;
+        layout.offset = position - anchor.point(screen);
+    }
+
+    /// Change an element's scale by `delta`, within the allowed range
+    pub fn adjust_scale(&mut self, element: HudElement, delta: f32) {
+        let layout = self.get_mut(element);
+        layout.scale = (layout.scale + delta).clamp(HUD_MIN_SCALE, HUD_MAX_SCALE);
+    }
+
+    /// Show or hide an element
+    pub fn toggle_visible(&mut self, element: HudElement) {
+        let layout = self.get_mut(element);
+        layout.visible = !layout.visible;
+    }
+
+    /// Topmost element under a world position
+    pub fn element_at(&self, position: Vec2, screen: Vec2) -> Option<HudElement> {
+        HudElement::all().into_iter().rev().find(|element| {
+            let center = self.position(*element, screen);
+            let half = self.size(*element) / 2.0;
+            (position - center).abs().cmple(half).all()
+        })
+    }
+}
+
+/// Clamp a box's center so the whole box stays on screen
+pub fn clamp_on_screen(center: Vec2, size: Vec2, screen: Vec2) -> Vec2 {
+    let limit = ((screen - size) / 2.0).max(Vec2::ZERO);
+    center.clamp(-limit, limit)
+}
+
+/// Values shown by the HUD
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HudValues {
+    pub score: i64,
+    pub combo: u32,
+    pub max_combo: u32,
+    /// Accuracy percentage, if any hits have been judged
+    pub accuracy: Option<f32>,
+    /// Remaining lives in survival mode
+    pub lives: Option<u32>,
+    /// Health (0.0 - 1.0), None outside gameplay
+    pub health: Option<f32>,
+    /// Grade the current accuracy earns, for coloring the readout
+    pub grade: Option<Grade>,
+    /// Draw the hit-error bar with sample hits; in gameplay it shows the live ones
+    pub hit_error_preview: bool,
+}
+
+impl HudValues {
+    /// Sample values for the layout editor preview
+    pub fn preview() -> Self {
+        Self {
+            score: 123_450,
+            combo: 64,
+            max_combo: 128,
+            accuracy: Some(97.25),
+            lives: Some(3),
+            health: Some(0.8),
+            grade: Some(Grade::SS),
+            hit_error_preview: true,
+        }
+    }
+}
+
+/// Accuracy readout that eases to each new value instead of jumping
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AccuracyGauge {
+    from: f32,
+    target: f32,
+    changed_at: f64,
+    grade: Option<Grade>,
+}
+
+impl AccuracyGauge {
+    /// Ease toward a new accuracy; called once per judgment, not every frame
+    pub fn set(&mut self, accuracy: f32, grade: Grade, now: f64) {
+        // The first judgment snaps instead of sweeping up from zero
+        self.from = if self.grade.is_some() {
+            self.value(now)
+        } else {
+            accuracy
+        };
+        self.target = accuracy;
+        self.changed_at = now;
+        self.grade = Some(grade);
+    }
+
+    /// Accuracy to show at a song time, or None before the first judgment
+    pub fn displayed(&self, now: f64) -> Option<f32> {
+        self.grade.map(|_| self.value(now))
+    }
+
+    /// Grade of the latest accuracy
+    pub fn grade(&self) -> Option<Grade> {
+        self.grade
+    }
+
+    fn value(&self, now: f64) -> f32 {
+        let t = ((now - self.changed_at) / ACCURACY_TWEEN_SECONDS).clamp(0.0, 1.0) as f32;
+        let eased = t * t * (3.0 - 2.0 * t);
+        self.from + (self.target - self.from) * eased
+    }
+}
+
+/// HUD layout editor state
+#[derive(Debug, Clone, Default, Resource)]
+pub struct HudEditorState {
+    /// Index into `HudElement::all()` of the selected element
+    pub selected: usize,
+    /// Element being dragged and the cursor's offset from its center
+    pub dragging: Option<(HudElement, Vec2)>,
+}
+
+impl HudEditorState {
+    /// The selected element
+    pub fn selected_element(&self) -> HudElement {
+        let elements = HudElement::all();
+        elements[self.selected.min(elements.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::{JudgmentKind, GRADE_SS_ACCURACY, GRADE_S_ACCURACY};
+    use crate::config::GameConfig;
+    use crate::structs::VisualizingState;
+
+    #[test]
+    fn the_gauge_eases_to_each_new_accuracy() {
+        let mut gauge = AccuracyGauge::default();
+        assert_eq!(gauge.displayed(0.0), None);
+
+        // The first judgment snaps
+        gauge.set(100.0, Grade::SSPlus, 1.0);
+        assert_eq!(gauge.displayed(1.0), Some(100.0));
+
+        gauge.set(90.0, Grade::S, 2.0);
+        assert_eq!(gauge.displayed(2.0), Some(100.0));
+        let halfway = gauge.displayed(2.0 + ACCURACY_TWEEN_SECONDS / 2.0).unwrap();
+        assert!((halfway - 95.0).abs() < 1e-4);
+        assert_eq!(gauge.displayed(2.0 + ACCURACY_TWEEN_SECONDS), Some(90.0));
+        assert_eq!(gauge.displayed(10.0), Some(90.0));
+
+        // A judgment mid-tween eases on from where the readout is
+        gauge.set(80.0, Grade::A, 2.0 + ACCURACY_TWEEN_SECONDS / 2.0);
+        assert!(
+            (gauge.displayed(2.0 + ACCURACY_TWEEN_SECONDS / 2.0).unwrap() - halfway).abs() < 1e-4
+        );
+    }
+
+    #[test]
+    fn the_live_grade_matches_the_results_screen() {
+        let mut state =
+            VisualizingState::new(Vec::new(), Vec::new(), GameConfig::default(), "test".into());
+        let judgments = [300, 300, 300, 100, 300, 0, 50, 300, 100, 0, 0, 300];
+
+        for (i, points) in judgments.into_iter().enumerate() {
+            let kind = match points {
+                300 => JudgmentKind::Perfect,
+                100 => JudgmentKind::Good,
+                50 => JudgmentKind::Okay,
+                _ => JudgmentKind::Miss,
+            };
+            if points > 0 {
+                state.record_hit(points, 0.0);
+            } else {
+                state.record_miss();
+            }
+            let now = i as f64;
+            state.log_judgment(now, Vec2::ZERO, kind);
+
+            let results = state.active_session.clone().unwrap().finish();
+            assert_eq!(
+                state.accuracy_gauge.grade(),
+                Some(results.grade),
+                "after judgment {}",
+                i
+            );
+            assert_eq!(
+                state.accuracy_gauge.displayed(now + ACCURACY_TWEEN_SECONDS),
+                Some(results.accuracy)
+            );
+        }
+    }
+
+    #[test]
+    fn the_gauge_ticks_sit_where_the_grade_changes() {
+        for threshold in [GRADE_S_ACCURACY, GRADE_SS_ACCURACY] {
+            assert_ne!(
+                Grade::from_accuracy(threshold),
+                Grade::from_accuracy(threshold - 0.01),
+                "{}%",
+                threshold
+            );
+        }
+    }
+
+    #[test]
+    fn the_hit_error_bar_defaults_to_its_old_spot() {
+        let screen = Vec2::new(1280.0, 720.0);
+        let layout = HudLayout::default();
+        assert_eq!(
+            layout.position(HudElement::HitErrorBar, screen),
+            Vec2::new(0.0, -screen.y / 2.0 + 30.0)
+        );
+        assert_eq!(layout.size(HudElement::HitErrorBar).x, 300.0);
+
+        // Layouts saved before the bar could be moved get the default
+        let mut saved = layout.clone();
+        saved.elements.remove(&HudElement::HitErrorBar);
+        assert_eq!(
+            saved.get(HudElement::HitErrorBar),
+            HudLayout::default_for(HudElement::HitErrorBar)
+        );
+    }
+}
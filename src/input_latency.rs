@@ -0,0 +1,117 @@
+// src/input_latency.rs
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::config::GameConfig;
+
+/// Presses the debug overlay's latency figures are taken over
+const LATENCY_SAMPLES: usize = 240;
+
+/// Upper bounds on recent input-to-judgment latencies (milliseconds), for the debug
+/// overlay (F3). Presses carry no timestamp of their own, so each one is bounded by
+/// how long the frame that judged it could have kept it waiting
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    samples: VecDeque<f32>,
+    /// Presses measured this play, including ones that fell out of the window
+    pub presses: u32,
+}
+
+impl LatencyStats {
+    /// Record the latency bound of a press that was just judged
+    pub fn record(&mut self, latency_ms: f32) {
+        if self.samples.len() == LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency_ms);
+        self.presses += 1;
+    }
+
+    /// Bound at `fraction` (0..=1) through the recent samples, None before any press
+    pub fn percentile(&self, fraction: f32) -> Option<f32> {
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f32::total_cmp);
+        let index = ((sorted.len() - 1) as f32 * fraction.clamp(0.0, 1.0)).round() as usize;
+        Some(sorted[index])
+    }
+
+    /// Debug overlay line with the distribution of recent bounds
+    pub fn summary_line(&self) -> String {
+        match (
+            self.percentile(0.5),
+            self.percentile(0.95),
+            self.percentile(1.0),
+        ) {
+            (Some(p50), Some(p95), Some(max)) => format!(
+                "Input to judgment, frame-bound upper limit: p50 {:.1} ms, p95 {:.1} ms, max {:.1} ms ({} presses)",
+                p50, p95, max, self.presses
+            ),
+            _ => "Input to judgment: no presses yet".to_string(),
+        }
+    }
+}
+
+/// Longest a press read this frame can have waited before being judged (milliseconds).
+/// A press lands somewhere after the previous frame's input read, so this is the last
+/// frame interval plus the time spent in this frame up to now. Every press judged in
+/// the same frame gets the same bound; it is not a measurement of any one press
+pub fn press_latency_bound_ms(time: &Time<Real>) -> f32 {
+    let into_frame = time
+        .last_update()
+        .map(|start| Instant::now().saturating_duration_since(start))
+        .unwrap_or_default();
+    (time.delta() + into_frame).as_secs_f32() * 1000.0
+}
+
+/// Present mode the window had before gameplay switched vsync off
+#[derive(Resource, Default)]
+pub struct SavedPresentMode(Option<PresentMode>);
+
+/// Stop waiting for vsync during gameplay when reduced input latency is on, so input
+/// is read as soon as the previous frame is handed off rather than a refresh later
+pub fn apply_reduced_latency(
+    config: Res<GameConfig>,
+    mut saved: ResMut<SavedPresentMode>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !config.display.reduced_input_latency {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    saved.0 = Some(window.present_mode);
+    window.present_mode = PresentMode::AutoNoVsync;
+}
+
+/// Put back the present mode gameplay replaced
+pub fn restore_present_mode(
+    mut saved: ResMut<SavedPresentMode>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Some(mode) = saved.0.take() else {
+        return;
+    };
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.present_mode = mode;
+    }
+}
+
+/// Debug overlay line with the frame interval and whether frames wait for vsync
+pub fn present_mode_line(window: &Window, time: &Time<Real>) -> String {
+    let waits = matches!(
+        window.present_mode,
+        PresentMode::AutoVsync | PresentMode::Fifo | PresentMode::FifoRelaxed
+    );
+    format!(
+        "Frame: {:.1} ms, vsync {}",
+        time.delta_secs() * 1000.0,
+        if waits { "on" } else { "off" }
+    )
+}
@@ -0,0 +1,376 @@
+// src/key_conflicts.rs
+
+use bevy::prelude::*;
+
+use crate::config::{is_bindable_key, key_label, GameConfig, KeyBindingType, KeyBindings};
+use crate::toasts::Toasts;
+
+/// Keys the game handles itself, with what they do. Exit and Pause may still use
+/// Escape, which they do by default
+pub const RESERVED_KEYS: [(&str, &str); 3] = [
+    ("Escape", "leaves menus and screens"),
+    ("F3", "toggles the debug overlay"),
+    ("F4", "opens the log viewer"),
+];
+
+/// Why a key can't simply be given to a binding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyConflict {
+    /// Another binding read on the same screens has the key
+    Binding(KeyBindingType),
+    /// The game handles the key itself; says what it does
+    Reserved(&'static str),
+}
+
+impl KeyConflict {
+    /// Reserved keys and the quick-retry key get the stronger warning: pressing them
+    /// by accident leaves or restarts the song
+    pub fn is_strong(&self) -> bool {
+        matches!(
+            self,
+            KeyConflict::Reserved(_) | KeyConflict::Binding(KeyBindingType::QuickRetry)
+        )
+    }
+
+    /// e.g. "A is already Pause"
+    pub fn describe(&self, key: &str) -> String {
+        match self {
+            KeyConflict::Binding(other) => {
+                format!("{} is already {}", key_label(key), other.display_name())
+            }
+            KeyConflict::Reserved(reason) => {
+                format!("{} is reserved: it {}", key_label(key), reason)
+            }
+        }
+    }
+}
+
+/// How the other bindings are settled when a clashing key is assigned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    /// The other binding takes this binding's old key
+    Swap,
+    /// The other bindings are left unbound
+    Clear,
+}
+
+/// Whether two bindings may share a key: they are never read on the same screen, or
+/// they are Exit and Pause, which both default to Escape
+fn may_share(a: KeyBindingType, b: KeyBindingType) -> bool {
+    a.in_gameplay() != b.in_gameplay()
+        || matches!(
+            (a, b),
+            (KeyBindingType::Exit, KeyBindingType::Pause)
+                | (KeyBindingType::Pause, KeyBindingType::Exit)
+        )
+}
+
+/// What a reserved key does, if `binding` isn't allowed to use it
+fn reserved_reason(binding: KeyBindingType, key: &str) -> Option<&'static str> {
+    if key == "Escape" && matches!(binding, KeyBindingType::Exit | KeyBindingType::Pause) {
+        return None;
+    }
+    RESERVED_KEYS
+        .iter()
+        .find(|(reserved, _)| *reserved == key)
+        .map(|(_, reason)| *reason)
+}
+
+/// Everything standing in the way of giving `key` to `binding`
+pub fn conflicts_for(
+    bindings: &KeyBindings,
+    binding: KeyBindingType,
+    key: &str,
+) -> Vec<KeyConflict> {
+    if key.is_empty() {
+        return Vec::new();
+    }
+    let reserved = reserved_reason(binding, key).map(KeyConflict::Reserved);
+    let taken = KeyBindingType::all()
+        .into_iter()
+        .filter(|other| *other != binding && !may_share(binding, *other))
+        .filter(|other| bindings.get(*other) == key)
+        .map(KeyConflict::Binding);
+    reserved.into_iter().chain(taken).collect()
+}
+
+/// Bindings with `key` given to `binding`, the bindings it clashes with settled by
+/// `resolution`. Swapping hands every clashing binding the old key, so offer it only
+/// when there is one (see `PendingBinding::can_swap`)
+pub fn assign(
+    bindings: &KeyBindings,
+    binding: KeyBindingType,
+    key: &str,
+    resolution: Resolution,
+) -> KeyBindings {
+    let old_key = bindings.get(binding).to_string();
+    let mut assigned = bindings.clone();
+    for conflict in conflicts_for(bindings, binding, key) {
+        if let KeyConflict::Binding(other) = conflict {
+            match resolution {
+                Resolution::Swap => assigned.set(other, &old_key),
+                Resolution::Clear => assigned.set(other, ""),
+            }
+        }
+    }
+    assigned.set(binding, key);
+    assigned
+}
+
+/// Problems in a whole set of bindings, e.g. from a hand-edited config.json
+pub fn validate(bindings: &KeyBindings) -> Vec<String> {
+    let all = KeyBindingType::all();
+    let mut problems = Vec::new();
+    for (i, &binding) in all.iter().enumerate() {
+        let key = bindings.get(binding);
+        if key.is_empty() {
+            continue;
+        }
+        if let Some(reason) = reserved_reason(binding, key) {
+            problems.push(format!(
+                "{} uses {}, which {}",
+                binding.display_name(),
+                key_label(key),
+                reason
+            ));
+        } else if !is_bindable_key(key) {
+            problems.push(format!(
+                "{} is set to unknown key \"{}\"",
+                binding.display_name(),
+                key
+            ));
+        }
+        for &other in &all[i + 1..] {
+            if !may_share(binding, other) && bindings.get(other) == key {
+                problems.push(format!(
+                    "{} and {} both use {}",
+                    binding.display_name(),
+                    other.display_name(),
+                    key_label(key)
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// A captured key that clashes, waiting for the player to swap, assign anyway or cancel
+#[derive(Debug, Clone)]
+pub struct PendingBinding {
+    pub binding: KeyBindingType,
+    pub key: String,
+    pub conflicts: Vec<KeyConflict>,
+}
+
+impl PendingBinding {
+    /// Swapping only makes sense with a single other binding and no reserved key
+    pub fn can_swap(&self) -> bool {
+        matches!(self.conflicts.as_slice(), [KeyConflict::Binding(_)])
+    }
+
+    /// Whether any conflict gets the stronger warning
+    pub fn is_strong(&self) -> bool {
+        self.conflicts.iter().any(KeyConflict::is_strong)
+    }
+
+    /// Warning and the choices on offer, shown under the rows
+    pub fn prompt(&self, bindings: &KeyBindings) -> String {
+        let warnings: Vec<String> = self
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.describe(&self.key))
+            .collect();
+        let others: Vec<&str> = self
+            .conflicts
+            .iter()
+            .filter_map(|conflict| match conflict {
+                KeyConflict::Binding(other) => Some(other.display_name()),
+                KeyConflict::Reserved(_) => None,
+            })
+            .collect();
+        let mut choices = Vec::new();
+        if let (true, [other]) = (self.can_swap(), others.as_slice()) {
+            choices.push(format!(
+                "S: swap ({} gets {})",
+                other,
+                key_label(bindings.get(self.binding))
+            ));
+        }
+        choices.push(if others.is_empty() {
+            "Enter: assign anyway".to_string()
+        } else {
+            format!("Enter: assign anyway (clears {})", others.join(", "))
+        });
+        choices.push("ESC: cancel".to_string());
+        let warning = if self.is_strong() { "Warning! " } else { "" };
+        format!(
+            "{}{}. {}",
+            warning,
+            warnings.join("; "),
+            choices.join(" | ")
+        )
+    }
+}
+
+/// Check the bindings at launch and after a profile switch, the only times a
+/// hand-edited file is read, and point at the settings screen if they clash
+pub fn warn_key_binding_conflicts(
+    config: Res<GameConfig>,
+    mut checked_profile: Local<Option<String>>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if checked_profile.as_deref() == Some(config.active_profile.as_str()) {
+        return;
+    }
+    *checked_profile = Some(config.active_profile.clone());
+
+    let problems = validate(&config.key_bindings);
+    for problem in &problems {
+        warn!("Key bindings: {}", problem);
+    }
+    if let Some(first) = problems.first() {
+        let more = match problems.len() {
+            1 => String::new(),
+            count => format!(" (+{} more)", count - 1),
+        };
+        toasts.push(format!(
+            "Key bindings: {}{} - fix them in Settings > Key Bindings",
+            first, more
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_defaults_have_no_conflicts() {
+        assert_eq!(validate(&KeyBindings::default()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn swapping_hands_the_other_binding_the_old_key() {
+        let bindings = KeyBindings::default();
+        let conflicts = conflicts_for(&bindings, KeyBindingType::PrimaryHit, "KeyS");
+        assert_eq!(
+            conflicts,
+            [KeyConflict::Binding(KeyBindingType::SecondaryHit)]
+        );
+
+        let swapped = assign(
+            &bindings,
+            KeyBindingType::PrimaryHit,
+            "KeyS",
+            Resolution::Swap,
+        );
+        assert_eq!(swapped.primary_hit, "KeyS");
+        assert_eq!(swapped.secondary_hit, "KeyA");
+        assert!(validate(&swapped).is_empty());
+    }
+
+    #[test]
+    fn assigning_anyway_clears_every_other_binding_with_the_key() {
+        let bindings = KeyBindings {
+            skip_intro: "KeyK".to_string(),
+            ..KeyBindings::default()
+        };
+        let conflicts = conflicts_for(&bindings, KeyBindingType::PrimaryHit, "KeyK");
+        assert_eq!(conflicts.len(), 2);
+
+        let cleared = assign(
+            &bindings,
+            KeyBindingType::PrimaryHit,
+            "KeyK",
+            Resolution::Clear,
+        );
+        assert_eq!(cleared.primary_hit, "KeyK");
+        assert_eq!(cleared.skip_intro, "");
+        assert_eq!(cleared.versus_primary_hit, "");
+        assert_eq!(cleared.secondary_hit, "KeyS");
+        assert!(validate(&cleared).is_empty());
+    }
+
+    #[test]
+    fn menu_and_gameplay_bindings_may_share_keys() {
+        let bindings = KeyBindings::default();
+        assert!(conflicts_for(&bindings, KeyBindingType::Select, "Space").is_empty());
+        assert!(conflicts_for(&bindings, KeyBindingType::PrimaryHit, "ArrowUp").is_empty());
+        assert!(conflicts_for(&bindings, KeyBindingType::Pause, "Escape").is_empty());
+        assert_eq!(
+            conflicts_for(&bindings, KeyBindingType::NavigateUp, "ArrowDown"),
+            [KeyConflict::Binding(KeyBindingType::NavigateDown)]
+        );
+    }
+
+    #[test]
+    fn reserved_keys_get_the_stronger_warning() {
+        let bindings = KeyBindings::default();
+        let escape = PendingBinding {
+            binding: KeyBindingType::PrimaryHit,
+            key: "Escape".to_string(),
+            conflicts: conflicts_for(&bindings, KeyBindingType::PrimaryHit, "Escape"),
+        };
+        assert_eq!(
+            escape.conflicts[1..],
+            [
+                KeyConflict::Binding(KeyBindingType::Pause),
+                KeyConflict::Binding(KeyBindingType::Exit)
+            ]
+        );
+        assert!(matches!(escape.conflicts[0], KeyConflict::Reserved(_)));
+        assert!(escape.is_strong());
+        assert!(!escape.can_swap());
+        let prompt = escape.prompt(&bindings);
+        assert!(
+            prompt.starts_with("Warning! Escape is reserved"),
+            "{}",
+            prompt
+        );
+        assert!(prompt.contains("Enter: assign anyway (clears Pause, Exit Song)"));
+        assert!(!prompt.contains("S: swap"));
+
+        let retry = PendingBinding {
+            binding: KeyBindingType::SkipIntro,
+            key: bindings.quick_retry.clone(),
+            conflicts: conflicts_for(&bindings, KeyBindingType::SkipIntro, &bindings.quick_retry),
+        };
+        assert!(retry.is_strong());
+        assert!(retry.can_swap());
+        assert!(retry
+            .prompt(&bindings)
+            .contains("S: swap (Quick Retry gets "));
+
+        let plain = PendingBinding {
+            binding: KeyBindingType::PrimaryHit,
+            key: "KeyS".to_string(),
+            conflicts: conflicts_for(&bindings, KeyBindingType::PrimaryHit, "KeyS"),
+        };
+        assert!(!plain.is_strong());
+        assert_eq!(
+            plain.prompt(&bindings),
+            "S is already Secondary Hit. S: swap (Secondary Hit gets A) | Enter: assign anyway (clears Secondary Hit) | ESC: cancel"
+        );
+    }
+
+    #[test]
+    fn validation_reports_hand_edited_problems() {
+        let bindings = KeyBindings {
+            secondary_hit: "KeyA".to_string(),
+            quick_retry: "F3".to_string(),
+            skip_intro: "NotAKey".to_string(),
+            ..KeyBindings::default()
+        };
+        let problems = validate(&bindings);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems
+            .iter()
+            .any(|problem| problem.contains("Primary Hit and Secondary Hit both use A")));
+        assert!(problems
+            .iter()
+            .any(|problem| problem.starts_with("Quick Retry uses F3")));
+        assert!(problems
+            .iter()
+            .any(|problem| problem.contains("unknown key \"NotAKey\"")));
+    }
+}
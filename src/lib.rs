@@ -0,0 +1,99 @@
+//! Game and server code shared by the yum-osu and server binaries
+
+use bevy::prelude::*;
+
+pub mod accounts;
+pub mod achievements;
+pub mod analysis_view;
+pub mod analytics;
+pub mod asset_manager;
+pub mod audio;
+pub mod beat_cache;
+pub mod beatmap;
+pub mod bots;
+pub mod bracket;
+pub mod calibration;
+pub mod cli;
+pub mod community;
+pub mod config;
+pub mod constants;
+pub mod difficulty;
+pub mod editor;
+pub mod editor_audio;
+pub mod editor_bench;
+pub mod editor_input;
+pub mod editor_ui;
+pub mod effects;
+pub mod event_bus;
+pub mod game;
+pub mod gamemode;
+pub mod generator;
+pub mod guidelines;
+pub mod hashing;
+pub mod hitsounds;
+pub mod hud;
+pub mod input_latency;
+pub mod key_conflicts;
+pub mod library_analysis;
+pub mod library_scan;
+pub mod logging;
+pub mod map_comments;
+pub mod menu_music;
+pub mod metronome;
+pub mod multiplayer;
+pub mod network;
+pub mod news;
+pub mod online;
+pub mod osu_format;
+pub mod performance;
+pub mod precision;
+pub mod profiles;
+pub mod recommend;
+pub mod replay;
+pub mod score_filter;
+pub mod score_format;
+pub mod seasons;
+pub mod session_details;
+pub mod settings_registry;
+pub mod simulation;
+pub mod structs;
+#[cfg(test)]
+mod test_support;
+pub mod text_input;
+pub mod theme_presets;
+pub mod toasts;
+pub mod ui;
+pub mod versus;
+pub mod visibility;
+pub mod waveform;
+pub mod ymz_format;
+
+/// Application states
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum AppState {
+    /// Until the startup setup has loaded the assets and config. Bevy enters the
+    /// initial state before Startup, so no screen can be it
+    #[default]
+    Boot,
+    Menu,
+    SongSelection,
+    PracticeMenu,
+    Playing,
+    Loading,
+    ReadyToPlay,
+    Visualizing,
+    End,
+    Failed,
+    Settings,
+    Analytics,
+    BeatmapEditor,
+    BeatmapSelection,
+    HudEditor,
+    ProfilePicker,
+    Account,
+    Online,
+    Versus,
+    VersusResults,
+    LibraryAnalysis,
+    Calibration,
+}
@@ -0,0 +1,721 @@
+// src/library_analysis.rs
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::audio::{try_analyze_song, DetectionQuality, SongAnalysis};
+use crate::beat_cache::{self, BEAT_CACHE_DIR};
+use crate::config::GameConfig;
+use crate::constants::*;
+use crate::structs::GameAssets;
+use crate::ui::{load_songs_from_assets, UiElement};
+use crate::AppState;
+
+/// How often a paused or held worker checks whether it may carry on
+const PAUSE_POLL: Duration = Duration::from_millis(250);
+
+/// Pause each worker takes between songs, so the analysis stays in the background
+const WORKER_BREATHER: Duration = Duration::from_millis(50);
+
+/// Songs listed at once on the progress screen
+const LISTED_SONGS: usize = 14;
+
+/// Audio files picked up from the song directories
+const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "ogg", "wav"];
+
+/// Every song the game can play: the bundled music folder plus the extra directories
+pub fn library_songs(config: &GameConfig) -> Vec<String> {
+    let mut songs = load_songs_from_assets();
+    for dir in &config.song_directories {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Couldn't read song directory {}: {}", dir, e);
+                continue;
+            }
+        };
+        songs.extend(
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension().is_some_and(|ext| {
+                        AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+                    })
+                })
+                .map(|path| path.to_string_lossy().into_owned()),
+        );
+    }
+    songs.sort();
+    songs.dedup();
+    songs
+}
+
+/// Workers to analyze with: one per core, leaving one for the game
+pub fn worker_count() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |cores| cores.get().saturating_sub(1))
+        .max(1)
+}
+
+/// What a worker did with one song
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SongOutcome {
+    Analyzed,
+    /// Already had a valid cache entry
+    Skipped,
+}
+
+/// Where a song is in the run
+#[derive(Debug, Clone, PartialEq)]
+pub enum SongStatus {
+    Queued,
+    Analyzing,
+    Done(SongOutcome),
+    Failed(String),
+}
+
+impl SongStatus {
+    fn tag(&self) -> String {
+        match self {
+            SongStatus::Queued => "queued".to_string(),
+            SongStatus::Analyzing => "analyzing...".to_string(),
+            SongStatus::Done(SongOutcome::Analyzed) => "done".to_string(),
+            SongStatus::Done(SongOutcome::Skipped) => "cached".to_string(),
+            SongStatus::Failed(error) => format!("failed: {}", error),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            SongStatus::Queued => Color::srgba(1.0, 1.0, 1.0, 0.5),
+            SongStatus::Analyzing => NEON_CYAN,
+            SongStatus::Done(SongOutcome::Analyzed) => NEON_GREEN,
+            SongStatus::Done(SongOutcome::Skipped) => Color::srgba(1.0, 1.0, 1.0, 0.7),
+            SongStatus::Failed(_) => NEON_ORANGE,
+        }
+    }
+}
+
+/// Flags shared between the game and the workers
+#[derive(Debug, Clone, Default)]
+pub struct PoolControl {
+    /// Stop taking new songs; songs being analyzed still finish
+    pub cancel: Arc<AtomicBool>,
+    /// Paused by the player
+    pub paused: Arc<AtomicBool>,
+    /// Held by the game while a song is loading or playing
+    pub held: Arc<AtomicBool>,
+}
+
+impl PoolControl {
+    /// Wait while paused or held. Returns false once cancelled
+    fn wait_for_turn(&self) -> bool {
+        while (self.paused.load(Ordering::Relaxed) || self.held.load(Ordering::Relaxed))
+            && !self.cancel.load(Ordering::Relaxed)
+        {
+            std::thread::sleep(PAUSE_POLL);
+        }
+        !self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress reported by the workers, by index into the song list they were given
+#[derive(Debug)]
+pub enum PoolMessage {
+    Started(usize),
+    Finished(usize, Result<SongOutcome, String>),
+}
+
+/// Run `analyze` over `songs` on `workers` threads. Each worker takes the next song
+/// off a shared queue, so one slow song never holds the others up. Results arrive on
+/// the returned channel, which disconnects once every worker has stopped. A panic
+/// while analyzing fails that song only
+pub fn run_pool<F>(
+    songs: Vec<String>,
+    workers: usize,
+    analyze: F,
+    control: PoolControl,
+) -> Receiver<PoolMessage>
+where
+    F: Fn(&str) -> Result<SongOutcome, String> + Send + Sync + 'static,
+{
+    let queue: VecDeque<(usize, String)> = songs.into_iter().enumerate().collect();
+    let queue = Arc::new(Mutex::new(queue));
+    let analyze = Arc::new(analyze);
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..workers.max(1) {
+        let (queue, analyze, control, sender) = (
+            queue.clone(),
+            analyze.clone(),
+            control.clone(),
+            sender.clone(),
+        );
+        std::thread::spawn(move || loop {
+            if !control.wait_for_turn() {
+                return;
+            }
+            let next = queue.lock().ok().and_then(|mut queue| queue.pop_front());
+            let Some((index, song)) = next else {
+                return;
+            };
+            if sender.send(PoolMessage::Started(index)).is_err() {
+                return;
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| analyze(&song)))
+                .unwrap_or_else(|_| Err("analysis crashed".to_string()));
+            if sender.send(PoolMessage::Finished(index, result)).is_err() {
+                return;
+            }
+            std::thread::sleep(WORKER_BREATHER);
+        });
+    }
+    receiver
+}
+
+/// Analyze a song into the beat cache unless it already has a valid entry
+fn analyze_into_cache(song: &str, quality: DetectionQuality) -> Result<SongOutcome, String> {
+    analyze_into(Path::new(BEAT_CACHE_DIR), song, quality, try_analyze_song)
+}
+
+/// `analyze_into_cache` with the cache in `dir` and beat detection done by `analyze`
+fn analyze_into<F>(
+    dir: &Path,
+    song: &str,
+    quality: DetectionQuality,
+    analyze: F,
+) -> Result<SongOutcome, String>
+where
+    F: Fn(&str, DetectionQuality) -> Result<SongAnalysis, String>,
+{
+    if beat_cache::is_cached_in(dir, song, quality) {
+        return Ok(SongOutcome::Skipped);
+    }
+    let analysis = analyze(song, quality)?;
+    beat_cache::store_in(dir, song, quality, &analysis)?;
+    Ok(SongOutcome::Analyzed)
+}
+
+/// Beat detection over the whole library ahead of time, started from Settings > Audio.
+/// It runs on a pool of workers in the background, so menus stay usable, and holds
+/// while a song is loading or playing
+#[derive(Resource, Default)]
+pub struct LibraryAnalysis {
+    /// Every song in the library with its status, sorted by path
+    pub songs: Vec<(String, SongStatus)>,
+    pub quality: DetectionQuality,
+    pub workers: usize,
+    /// Stopped before every song was done
+    pub cancelled: bool,
+    /// Time spent running (not paused or held), for the estimate
+    active: Duration,
+    control: PoolControl,
+    receiver: Option<Mutex<Receiver<PoolMessage>>>,
+}
+
+impl LibraryAnalysis {
+    /// Start over on every song in the library with the configured detection mode
+    pub fn start(&mut self, config: &GameConfig) {
+        self.cancel();
+        self.quality = config.audio.detection_quality;
+        self.workers = worker_count();
+        self.cancelled = false;
+        self.active = Duration::ZERO;
+        self.control = PoolControl::default();
+
+        let songs = library_songs(config);
+        self.songs = songs
+            .iter()
+            .map(|song| (song.clone(), SongStatus::Queued))
+            .collect();
+        let quality = self.quality;
+        let receiver = run_pool(
+            songs,
+            self.workers,
+            move |song| analyze_into_cache(song, quality),
+            self.control.clone(),
+        );
+        self.receiver = Some(Mutex::new(receiver));
+        info!(
+            "Analyzing {} songs on {} workers",
+            self.songs.len(),
+            self.workers
+        );
+    }
+
+    /// Stop taking new songs; what was finished stays in the cache
+    pub fn cancel(&mut self) {
+        if self.is_running() {
+            self.control.cancel.store(true, Ordering::Relaxed);
+            self.receiver = None;
+            self.cancelled = true;
+            for (_, status) in &mut self.songs {
+                if *status == SongStatus::Analyzing {
+                    *status = SongStatus::Queued;
+                }
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.control
+            .paused
+            .store(!self.is_paused(), Ordering::Relaxed);
+    }
+
+    /// Songs with each outcome: (analyzed, failed, skipped, left)
+    pub fn counts(&self) -> (usize, usize, usize, usize) {
+        let mut counts = (0, 0, 0, 0);
+        for (_, status) in &self.songs {
+            match status {
+                SongStatus::Done(SongOutcome::Analyzed) => counts.0 += 1,
+                SongStatus::Failed(_) => counts.1 += 1,
+                SongStatus::Done(SongOutcome::Skipped) => counts.2 += 1,
+                SongStatus::Queued | SongStatus::Analyzing => counts.3 += 1,
+            }
+        }
+        counts
+    }
+
+    /// Time left at the pace of the songs analyzed so far, None before the first
+    pub fn remaining(&self) -> Option<Duration> {
+        let (analyzed, _, _, left) = self.counts();
+        (analyzed > 0).then(|| self.active.mul_f64(left as f64 / analyzed as f64))
+    }
+
+    /// e.g. "Analyzed 40, failed 2, skipped 100"
+    pub fn summary(&self) -> String {
+        let (analyzed, failed, skipped, _) = self.counts();
+        format!(
+            "Analyzed {}, failed {}, skipped {}",
+            analyzed, failed, skipped
+        )
+    }
+
+    /// Status line for the progress screen
+    pub fn status(&self) -> String {
+        let (analyzed, failed, skipped, left) = self.counts();
+        let done = analyzed + failed + skipped;
+        if !self.is_running() {
+            let ending = if self.cancelled {
+                "Cancelled"
+            } else {
+                "Finished"
+            };
+            return format!("{}: {}", ending, self.summary());
+        }
+        let state = if self.is_paused() {
+            "Paused".to_string()
+        } else if self.control.held.load(Ordering::Relaxed) {
+            "Waiting for the song to end".to_string()
+        } else {
+            match self.remaining() {
+                Some(remaining) => format!("about {} left", format_duration(remaining)),
+                None => "estimating time left...".to_string(),
+            }
+        };
+        format!(
+            "{}/{} songs ({} left) on {} workers - {}",
+            done,
+            self.songs.len(),
+            left,
+            self.workers,
+            state
+        )
+    }
+}
+
+/// e.g. "1h 05m", "4m 30s"
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Pick up worker results, and hold the workers while a song is loading or playing
+/// so they don't cause audio glitches
+pub fn poll_library_analysis(
+    mut analysis: ResMut<LibraryAnalysis>,
+    state: Res<State<AppState>>,
+    time: Res<Time<Real>>,
+) {
+    let busy = matches!(
+        state.get(),
+        AppState::Loading
+            | AppState::ReadyToPlay
+            | AppState::Playing
+            | AppState::Visualizing
+            | AppState::Versus
+    );
+    // Only mark the analysis changed (and redraw its screen) when results arrived
+    let run = analysis.bypass_change_detection();
+    let was_busy = run.control.held.swap(busy, Ordering::Relaxed);
+    let Some(receiver) = run.receiver.as_ref() else {
+        return;
+    };
+    if !busy && !run.control.paused.load(Ordering::Relaxed) {
+        run.active += time.delta();
+    }
+
+    let mut messages = Vec::new();
+    let mut finished = false;
+    match receiver.lock() {
+        Ok(receiver) => loop {
+            match receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        },
+        Err(_) => finished = true,
+    }
+    if messages.is_empty() && !finished && was_busy == busy {
+        return;
+    }
+
+    for message in messages {
+        match message {
+            PoolMessage::Started(index) => analysis.songs[index].1 = SongStatus::Analyzing,
+            PoolMessage::Finished(index, Ok(outcome)) => {
+                analysis.songs[index].1 = SongStatus::Done(outcome)
+            }
+            PoolMessage::Finished(index, Err(e)) => {
+                warn!(
+                    "Beat detection failed for {}: {}",
+                    analysis.songs[index].0, e
+                );
+                analysis.songs[index].1 = SongStatus::Failed(e);
+            }
+        }
+    }
+    if finished {
+        analysis.receiver = None;
+        info!("Library analysis finished: {}", analysis.summary());
+    }
+    analysis.set_changed();
+}
+
+/// Opening the screen starts a run unless one is already going
+pub fn enter_library_analysis(config: Res<GameConfig>, mut analysis: ResMut<LibraryAnalysis>) {
+    if !analysis.is_running() {
+        analysis.start(&config);
+    }
+}
+
+/// Marker for the progress screen's entities
+#[derive(Component)]
+pub struct LibraryAnalysisElement;
+
+/// P pauses or resumes, C cancels, Enter starts again once stopped; ESC goes back to
+/// Settings and leaves the analysis running
+pub fn handle_library_analysis(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
+    mut analysis: ResMut<LibraryAnalysis>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Settings);
+    } else if keyboard.just_pressed(KeyCode::KeyP) && analysis.is_running() {
+        analysis.toggle_pause();
+    } else if keyboard.just_pressed(KeyCode::KeyC) {
+        analysis.cancel();
+    } else if keyboard.just_pressed(KeyCode::Enter) && !analysis.is_running() {
+        analysis.start(&config);
+    }
+}
+
+/// Draw the run's status and the songs around the ones being analyzed
+pub fn draw_library_analysis(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    analysis: Res<LibraryAnalysis>,
+    existing: Query<Entity, With<LibraryAnalysisElement>>,
+) {
+    if !analysis.is_changed() && !existing.is_empty() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+
+    let top = window.height() / 2.0 - 60.0;
+    commands.spawn((
+        Text2d::new(format!(
+            "Analyze Library ({})",
+            analysis.quality.display_name()
+        )),
+        font(28.0),
+        TextColor(NEON_PINK),
+        Transform::from_xyz(0.0, top, 1.0),
+        UiElement,
+        LibraryAnalysisElement,
+    ));
+    commands.spawn((
+        Text2d::new(analysis.status()),
+        font(16.0),
+        TextColor(NEON_YELLOW),
+        Transform::from_xyz(0.0, top - 40.0, 1.0),
+        UiElement,
+        LibraryAnalysisElement,
+    ));
+
+    // Keep the songs being worked on in view
+    let open = analysis
+        .songs
+        .iter()
+        .position(|(_, status)| matches!(status, SongStatus::Queued | SongStatus::Analyzing))
+        .unwrap_or(analysis.songs.len());
+    let start = open
+        .saturating_sub(LISTED_SONGS / 2)
+        .min(analysis.songs.len().saturating_sub(LISTED_SONGS));
+    for (i, (song, status)) in analysis
+        .songs
+        .iter()
+        .skip(start)
+        .take(LISTED_SONGS)
+        .enumerate()
+    {
+        let name = std::path::Path::new(song)
+            .file_name()
+            .map_or_else(|| song.clone(), |name| name.to_string_lossy().into_owned());
+        commands.spawn((
+            Text2d::new(format!("{} - {}", name, status.tag())),
+            font(14.0),
+            TextColor(status.color()),
+            Transform::from_xyz(0.0, top - 90.0 - i as f32 * 22.0, 1.0),
+            UiElement,
+            LibraryAnalysisElement,
+        ));
+    }
+
+    let help = if analysis.is_running() {
+        "P: pause / resume | C: cancel | ESC: back (keeps running)"
+    } else {
+        "Enter: analyze again | ESC: back"
+    };
+    commands.spawn((
+        Text2d::new(help),
+        font(12.0),
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+        Transform::from_xyz(0.0, -window.height() / 2.0 + 40.0, 1.0),
+        UiElement,
+        LibraryAnalysisElement,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Instant;
+    use crate::test_support::scratch_dir;
+
+    fn names(songs: &[&str]) -> Vec<String> {
+        songs.iter().map(|song| song.to_string()).collect()
+    }
+
+    /// Every message of a run, waiting for its workers to stop
+    fn drain(receiver: Receiver<PoolMessage>) -> Vec<PoolMessage> {
+        receiver.iter().collect()
+    }
+
+    fn finished(messages: &[PoolMessage]) -> HashMap<usize, Result<SongOutcome, String>> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                PoolMessage::Finished(index, result) => Some((*index, result.clone())),
+                PoolMessage::Started(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_song_runs_once_and_failures_stay_with_their_song() {
+        let songs = names(&["a", "bad", "crash", "b", "c", "d", "e"]);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let (now, most) = (running.clone(), most_running.clone());
+        let stub = move |song: &str| {
+            let count = now.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(count, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(20));
+            now.fetch_sub(1, Ordering::SeqCst);
+            match song {
+                "bad" => Err("unreadable".to_string()),
+                "crash" => panic!("decoder blew up"),
+                _ => Ok(SongOutcome::Analyzed),
+            }
+        };
+
+        let messages = drain(run_pool(songs.clone(), 3, stub, PoolControl::default()));
+
+        let started = messages
+            .iter()
+            .filter(|message| matches!(message, PoolMessage::Started(_)))
+            .count();
+        assert_eq!(started, songs.len());
+        let results = finished(&messages);
+        assert_eq!(results.len(), songs.len());
+        assert_eq!(results[&1], Err("unreadable".to_string()));
+        assert_eq!(results[&2], Err("analysis crashed".to_string()));
+        for index in [0, 3, 4, 5, 6] {
+            assert_eq!(results[&index], Ok(SongOutcome::Analyzed));
+        }
+        assert!(most_running.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn cancelling_finishes_the_song_in_hand_and_takes_no_more() {
+        let control = PoolControl::default();
+        let stub = |_: &str| {
+            std::thread::sleep(Duration::from_millis(50));
+            Ok(SongOutcome::Analyzed)
+        };
+        let receiver = run_pool(names(&["a", "b", "c", "d"]), 1, stub, control.clone());
+
+        assert!(matches!(receiver.recv().unwrap(), PoolMessage::Started(0)));
+        control.cancel.store(true, Ordering::Relaxed);
+        let rest = drain(receiver);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(finished(&rest)[&0], Ok(SongOutcome::Analyzed));
+    }
+
+    #[test]
+    fn a_paused_or_held_pool_waits_to_start() {
+        let control = PoolControl::default();
+        control.paused.store(true, Ordering::Relaxed);
+        control.held.store(true, Ordering::Relaxed);
+        let stub = |_: &str| Ok(SongOutcome::Analyzed);
+        let receiver = run_pool(names(&["a", "b"]), 2, stub, control.clone());
+
+        let wait = PAUSE_POLL + Duration::from_millis(100);
+        assert!(receiver.recv_timeout(wait).is_err());
+        control.paused.store(false, Ordering::Relaxed);
+        assert!(receiver.recv_timeout(wait).is_err());
+        control.held.store(false, Ordering::Relaxed);
+        let started = Instant::now();
+        assert_eq!(finished(&drain(receiver)).len(), 2);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn songs_with_a_valid_cache_entry_are_skipped() {
+        let dir = scratch_dir("library-analysis");
+        let cache = dir.join("cache");
+        let song = dir.join("song.ogg");
+        fs::write(&song, b"first take").unwrap();
+        let song = song.to_string_lossy().into_owned();
+
+        let calls = AtomicUsize::new(0);
+        let stub = |_: &str, _: DetectionQuality| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(SongAnalysis {
+                beats: vec![0.5, 1.0],
+                energy: Vec::new(),
+            })
+        };
+        let fast = DetectionQuality::Fast;
+        assert_eq!(
+            analyze_into(&cache, &song, fast, stub),
+            Ok(SongOutcome::Analyzed)
+        );
+        assert_eq!(
+            analyze_into(&cache, &song, fast, stub),
+            Ok(SongOutcome::Skipped)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            beat_cache::load_cached_in(&cache, &song, fast)
+                .unwrap()
+                .beats,
+            [0.5, 1.0]
+        );
+
+        // Another detection mode has an entry of its own
+        let accurate = DetectionQuality::Accurate;
+        assert_eq!(
+            analyze_into(&cache, &song, accurate, stub),
+            Ok(SongOutcome::Analyzed)
+        );
+        // And a changed file is analyzed again
+        fs::write(&song, b"second, longer take").unwrap();
+        assert_eq!(
+            analyze_into(&cache, &song, fast, stub),
+            Ok(SongOutcome::Analyzed)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let failing = |_: &str, _: DetectionQuality| Err("no audio".to_string());
+        let other = dir.join("other.ogg");
+        fs::write(&other, b"x").unwrap();
+        let other = other.to_string_lossy().into_owned();
+        assert_eq!(
+            analyze_into(&cache, &other, fast, failing),
+            Err("no audio".to_string())
+        );
+        assert!(!beat_cache::is_cached_in(&cache, &other, fast));
+    }
+
+    #[test]
+    fn the_estimate_follows_the_pace_so_far() {
+        let mut analysis = LibraryAnalysis {
+            songs: vec![
+                ("a".to_string(), SongStatus::Done(SongOutcome::Analyzed)),
+                ("b".to_string(), SongStatus::Done(SongOutcome::Skipped)),
+                ("c".to_string(), SongStatus::Failed("bad".to_string())),
+                ("d".to_string(), SongStatus::Done(SongOutcome::Analyzed)),
+                ("e".to_string(), SongStatus::Analyzing),
+                ("f".to_string(), SongStatus::Queued),
+            ],
+            active: Duration::from_secs(40),
+            ..Default::default()
+        };
+
+        assert_eq!(analysis.counts(), (2, 1, 1, 2));
+        assert_eq!(analysis.remaining(), Some(Duration::from_secs(40)));
+        assert_eq!(analysis.summary(), "Analyzed 2, failed 1, skipped 1");
+        assert_eq!(
+            analysis.status(),
+            "Finished: Analyzed 2, failed 1, skipped 1"
+        );
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 05m");
+        assert_eq!(format_duration(Duration::from_secs(270)), "4m 30s");
+
+        analysis.songs.truncate(3);
+        analysis.songs.push(("d".to_string(), SongStatus::Queued));
+        analysis.songs[0].1 = SongStatus::Queued;
+        assert_eq!(analysis.remaining(), None);
+    }
+}
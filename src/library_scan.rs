@@ -0,0 +1,540 @@
+// src/library_scan.rs
+
+use crate::beatmap::{Beatmap, BeatmapAssets};
+use crate::difficulty::content_hash;
+use crate::AppState;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Pause between beatmap files, so the scan is a trickle of disk reads
+const SCAN_THROTTLE: Duration = Duration::from_millis(20);
+
+/// How often a paused scan checks whether it may carry on
+const PAUSE_POLL: Duration = Duration::from_millis(250);
+
+/// Issues listed as rows in Settings > General; the rest are counted
+pub const MAX_LISTED_ISSUES: usize = 8;
+
+/// What is wrong with a beatmap file
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// Not a readable beatmap, so the library leaves it out
+    ParseFailed(String),
+    /// The audio file it plays isn't there
+    MissingAudio(String),
+    /// Its background image isn't there
+    MissingBackground(String),
+    /// It loads but fails validation
+    Invalid(Vec<String>),
+    /// The file no longer matches the copy the library loaded, so cached difficulty
+    /// and edits are out of date
+    Stale,
+}
+
+impl IssueKind {
+    /// Short tag for list entries
+    pub fn tag(&self) -> &'static str {
+        match self {
+            IssueKind::ParseFailed(_) => "failed to parse",
+            IssueKind::MissingAudio(_) => "missing audio",
+            IssueKind::MissingBackground(_) => "missing background",
+            IssueKind::Invalid(_) => "invalid",
+            IssueKind::Stale => "changed on disk",
+        }
+    }
+
+    /// Tag with the detail, e.g. "missing audio: song.mp3"
+    pub fn describe(&self) -> String {
+        match self {
+            IssueKind::ParseFailed(error) => format!("{}: {}", self.tag(), error),
+            IssueKind::MissingAudio(path) if path.is_empty() => "no audio file set".to_string(),
+            IssueKind::MissingAudio(path) | IssueKind::MissingBackground(path) => {
+                format!("{}: {}", self.tag(), path)
+            }
+            IssueKind::Invalid(errors) => match errors.as_slice() {
+                [only] => format!("{}: {}", self.tag(), only),
+                [first, rest @ ..] => format!("{}: {} (+{} more)", self.tag(), first, rest.len()),
+                [] => self.tag().to_string(),
+            },
+            IssueKind::Stale => self.tag().to_string(),
+        }
+    }
+
+    /// Order issues are counted in the summary
+    fn rank(&self) -> usize {
+        match self {
+            IssueKind::MissingAudio(_) => 0,
+            IssueKind::ParseFailed(_) => 1,
+            IssueKind::MissingBackground(_) => 2,
+            IssueKind::Invalid(_) => 3,
+            IssueKind::Stale => 4,
+        }
+    }
+}
+
+/// A problem found in one beatmap file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryIssue {
+    /// Beatmap file, as the library keys it
+    pub path: String,
+    pub kind: IssueKind,
+}
+
+impl LibraryIssue {
+    /// List row text, e.g. "map.json - missing audio: song.mp3"
+    pub fn label(&self) -> String {
+        let name = Path::new(&self.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.clone());
+        format!("{} - {}", name, self.kind.describe())
+    }
+}
+
+/// Check one beatmap file: it parses, its audio and background exist, it passes
+/// validation and, when the library loaded it, it still matches that copy
+pub fn check_beatmap(path: &str, loaded_hash: Option<u64>) -> Vec<LibraryIssue> {
+    let issue = |kind| LibraryIssue {
+        path: path.to_string(),
+        kind,
+    };
+    let beatmap = match Beatmap::load_from_file(path) {
+        Ok(beatmap) => beatmap,
+        Err(e) => return vec![issue(IssueKind::ParseFailed(e))],
+    };
+
+    let mut issues = Vec::new();
+    if !Path::new(&beatmap.audio_path).is_file() {
+        issues.push(issue(IssueKind::MissingAudio(beatmap.audio_path.clone())));
+    }
+    if let Some(background) = beatmap.background_path.as_ref().filter(|p| !p.is_empty()) {
+        if !Path::new(background).is_file() {
+            issues.push(issue(IssueKind::MissingBackground(background.clone())));
+        }
+    }
+    if let Err(errors) = beatmap.validate() {
+        issues.push(issue(IssueKind::Invalid(errors)));
+    }
+    if loaded_hash.is_some_and(|hash| hash != content_hash(&beatmap)) {
+        issues.push(issue(IssueKind::Stale));
+    }
+    issues
+}
+
+/// Beatmap files in a directory, sorted
+fn beatmap_files(dir: &str) -> Vec<String> {
+    let mut paths: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .map(|path| path.to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+/// Results of a library scan so far
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryHealth {
+    /// Grouped by file, in scan order
+    pub issues: Vec<LibraryIssue>,
+    /// Files checked
+    pub checked: usize,
+    /// Files the scan found
+    pub total: usize,
+}
+
+impl LibraryHealth {
+    /// Counts of broken maps by issue, e.g. "3 maps have missing audio, 1 failed to parse"
+    pub fn summary(&self) -> String {
+        if self.issues.is_empty() {
+            return format!("all {} beatmaps OK", self.checked);
+        }
+        let mut counts = [0usize; 5];
+        for issue in &self.issues {
+            counts[issue.kind.rank()] += 1;
+        }
+        let phrases = [
+            ("map has missing audio", "maps have missing audio"),
+            ("failed to parse", "failed to parse"),
+            (
+                "map has a missing background",
+                "maps have missing backgrounds",
+            ),
+            ("map fails validation", "maps fail validation"),
+            ("map changed on disk", "maps changed on disk"),
+        ];
+        counts
+            .iter()
+            .zip(phrases)
+            .filter(|(count, _)| **count > 0)
+            .map(|(count, (one, many))| {
+                format!("{} {}", count, if *count == 1 { one } else { many })
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Tags of a file's issues for the beatmap list, e.g. "missing audio, invalid"
+    pub fn tags_for(&self, path: &str) -> Option<String> {
+        let tags: Vec<&str> = self
+            .issues
+            .iter()
+            .filter(|issue| issue.path == path)
+            .map(|issue| issue.kind.tag())
+            .collect();
+        (!tags.is_empty()).then(|| tags.join(", "))
+    }
+
+    /// Files that failed to parse, which the library doesn't list
+    pub fn unparsed(&self) -> impl Iterator<Item = &LibraryIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| matches!(issue.kind, IssueKind::ParseFailed(_)))
+    }
+
+    /// Swap a file's issues for a fresh check, keeping its place in the list
+    fn replace(&mut self, path: &str, issues: Vec<LibraryIssue>) {
+        let at = self
+            .issues
+            .iter()
+            .position(|issue| issue.path == path)
+            .unwrap_or(self.issues.len());
+        self.issues.retain(|issue| issue.path != path);
+        let at = at.min(self.issues.len());
+        self.issues.splice(at..at, issues);
+    }
+}
+
+/// Progress reported by the scan thread
+enum ScanMessage {
+    Started(usize),
+    Checked(String, Vec<LibraryIssue>),
+}
+
+/// Background integrity scan of the beatmap library. It runs on a worker thread,
+/// one file at a time with a pause in between, holds while a song is loading or
+/// playing and can be cancelled from Settings
+#[derive(Resource, Default)]
+pub struct LibraryScan {
+    pub health: LibraryHealth,
+    /// Stopped from Settings before it checked every file
+    pub cancelled: bool,
+    cancel: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    receiver: Option<Mutex<Receiver<ScanMessage>>>,
+}
+
+impl LibraryScan {
+    /// Start scanning the library's directory
+    pub fn start(beatmap_assets: &BeatmapAssets) -> Self {
+        let mut scan = Self::default();
+        scan.restart(beatmap_assets);
+        scan
+    }
+
+    /// Stop any scan in flight and check every file again
+    pub fn restart(&mut self, beatmap_assets: &BeatmapAssets) {
+        self.cancel.store(true, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(self.paused.load(Ordering::Relaxed)));
+        let loaded: HashMap<String, u64> = beatmap_assets
+            .beatmaps
+            .iter()
+            .map(|(path, beatmap)| (path.clone(), content_hash(beatmap)))
+            .collect();
+        let dir = beatmap_assets.beatmaps_dir.clone();
+
+        let (sender, receiver) = mpsc::channel();
+        let (thread_cancel, thread_paused) = (cancel.clone(), paused.clone());
+        std::thread::spawn(move || {
+            let paths = beatmap_files(&dir);
+            if sender.send(ScanMessage::Started(paths.len())).is_err() {
+                return;
+            }
+            for path in paths {
+                while thread_paused.load(Ordering::Relaxed)
+                    && !thread_cancel.load(Ordering::Relaxed)
+                {
+                    std::thread::sleep(PAUSE_POLL);
+                }
+                if thread_cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let issues = check_beatmap(&path, loaded.get(&path).copied());
+                if sender.send(ScanMessage::Checked(path, issues)).is_err() {
+                    return;
+                }
+                std::thread::sleep(SCAN_THROTTLE);
+            }
+        });
+
+        self.health = LibraryHealth::default();
+        self.cancelled = false;
+        self.cancel = cancel;
+        self.paused = paused;
+        self.receiver = Some(Mutex::new(receiver));
+    }
+
+    /// Stop the scan, keeping what it found so far
+    pub fn cancel(&mut self) {
+        if self.is_running() {
+            self.cancel.store(true, Ordering::Relaxed);
+            self.receiver = None;
+            self.cancelled = true;
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Check one file again after fixing it; `loaded` is the library's copy, if any
+    pub fn recheck(&mut self, path: &str, loaded: Option<&Beatmap>) {
+        let issues = check_beatmap(path, loaded.map(content_hash));
+        self.health.replace(path, issues);
+    }
+
+    /// Load a file into the library again, as importing it would, and recheck it
+    pub fn reload(&mut self, path: &str, beatmap_assets: &mut BeatmapAssets) -> Result<(), String> {
+        let result = Beatmap::load_from_file(path)
+            .map(|beatmap| beatmap_assets.add(path.to_string(), beatmap));
+        self.recheck(path, beatmap_assets.get(path));
+        result
+    }
+
+    /// Point a beatmap at an audio file and save it, then reload and recheck it
+    pub fn relink_audio(
+        &mut self,
+        path: &str,
+        audio_path: &str,
+        beatmap_assets: &mut BeatmapAssets,
+    ) -> Result<(), String> {
+        if !Path::new(audio_path).is_file() {
+            return Err(format!("No audio file at {}", audio_path));
+        }
+        let mut beatmap = Beatmap::load_from_file(path)?;
+        beatmap.audio_path = audio_path.to_string();
+        beatmap.save_to_file(path)?;
+        self.reload(path, beatmap_assets)
+    }
+
+    /// Delete a beatmap file and drop it from the library and the report
+    pub fn remove(&mut self, path: &str, beatmap_assets: &mut BeatmapAssets) -> Result<(), String> {
+        fs::remove_file(path).map_err(|e| format!("Couldn't remove {}: {}", path, e))?;
+        beatmap_assets.remove(path);
+        self.health.replace(path, Vec::new());
+        self.health.checked = self.health.checked.saturating_sub(1);
+        self.health.total = self.health.total.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Settings row text
+    pub fn status(&self) -> String {
+        let health = &self.health;
+        if self.is_running() {
+            format!("checking {}/{}...", health.checked, health.total)
+        } else if self.cancelled {
+            format!(
+                "cancelled after {}/{}, {}",
+                health.checked,
+                health.total,
+                health.summary()
+            )
+        } else {
+            health.summary()
+        }
+    }
+}
+
+/// Pick up scan results, and hold the scan while a song is loading or playing
+pub fn poll_library_scan(mut scan: ResMut<LibraryScan>, state: Res<State<AppState>>) {
+    let busy = matches!(
+        state.get(),
+        AppState::Loading
+            | AppState::ReadyToPlay
+            | AppState::Playing
+            | AppState::Visualizing
+            | AppState::Versus
+    );
+    // Only mark the scan changed (and redraw Settings) when results arrived
+    let scan_state = scan.bypass_change_detection();
+    scan_state.paused.store(busy, Ordering::Relaxed);
+    let Some(receiver) = scan_state.receiver.as_ref() else {
+        return;
+    };
+
+    let mut messages = Vec::new();
+    let mut finished = false;
+    match receiver.lock() {
+        Ok(receiver) => loop {
+            match receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        },
+        Err(_) => finished = true,
+    }
+    if messages.is_empty() && !finished {
+        return;
+    }
+
+    for message in messages {
+        match message {
+            ScanMessage::Started(total) => scan.health.total = total,
+            ScanMessage::Checked(path, issues) => {
+                scan.health.checked += 1;
+                scan.health.replace(&path, issues);
+            }
+        }
+    }
+    if finished {
+        scan.receiver = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beatmap::KiaiSection;
+    use crate::test_support::scratch_dir;
+    use std::path::PathBuf;
+
+    /// A folder with a song, a background and a beatmap using both, all valid
+    fn fixture(name: &str) -> (PathBuf, String, Beatmap) {
+        let dir = scratch_dir(name);
+        let audio = dir.join("song.mp3");
+        let background = dir.join("bg.png");
+        fs::write(&audio, b"audio").unwrap();
+        fs::write(&background, b"image").unwrap();
+
+        let mut beatmap = Beatmap::new(
+            "Song".to_string(),
+            "Artist".to_string(),
+            audio.to_string_lossy().into_owned(),
+        );
+        beatmap.background_path = Some(background.to_string_lossy().into_owned());
+        let path = dir.join("map.json").to_string_lossy().into_owned();
+        beatmap.save_to_file(&path).unwrap();
+        (dir, path, beatmap)
+    }
+
+    fn kinds(issues: &[LibraryIssue]) -> Vec<&'static str> {
+        issues.iter().map(|issue| issue.kind.tag()).collect()
+    }
+
+    #[test]
+    fn a_sound_map_has_no_issues() {
+        let (_dir, path, beatmap) = fixture("scan-ok");
+        assert!(check_beatmap(&path, Some(content_hash(&beatmap))).is_empty());
+        assert!(check_beatmap(&path, None).is_empty());
+    }
+
+    #[test]
+    fn unreadable_files_fail_to_parse() {
+        let (_dir, path, _) = fixture("scan-parse");
+        fs::write(&path, "{ not a beatmap").unwrap();
+        let issues = check_beatmap(&path, None);
+        assert!(matches!(
+            issues.as_slice(),
+            [LibraryIssue {
+                kind: IssueKind::ParseFailed(_),
+                ..
+            }]
+        ));
+        assert_eq!(issues[0].path, path);
+    }
+
+    #[test]
+    fn maps_failing_validation_are_invalid() {
+        let (_dir, path, mut beatmap) = fixture("scan-invalid");
+        beatmap.kiai_sections.push(KiaiSection {
+            start: 5.0,
+            end: 2.0,
+        });
+        beatmap.save_to_file(&path).unwrap();
+        let issues = check_beatmap(&path, None);
+        match issues.as_slice() {
+            [LibraryIssue {
+                kind: IssueKind::Invalid(errors),
+                ..
+            }] => assert!(errors[0].contains("ends before it starts")),
+            other => panic!("expected one validation issue, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn files_changed_since_loading_are_stale() {
+        let (_dir, path, mut beatmap) = fixture("scan-stale");
+        let loaded = content_hash(&beatmap);
+        beatmap.metadata.title = "Edited elsewhere".to_string();
+        beatmap.save_to_file(&path).unwrap();
+        assert_eq!(
+            kinds(&check_beatmap(&path, Some(loaded))),
+            ["changed on disk"]
+        );
+        // Nothing to be stale against when the library never loaded it
+        assert!(check_beatmap(&path, None).is_empty());
+    }
+
+    #[test]
+    fn missing_audio_is_reported_with_its_path() {
+        let (dir, path, beatmap) = fixture("scan-audio");
+        fs::remove_file(dir.join("song.mp3")).unwrap();
+        let issues = check_beatmap(&path, None);
+        assert_eq!(
+            issues.iter().map(|issue| &issue.kind).collect::<Vec<_>>(),
+            [&IssueKind::MissingAudio(beatmap.audio_path.clone())]
+        );
+        assert!(issues[0].label().starts_with("map.json - missing audio: "));
+    }
+
+    #[test]
+    fn missing_backgrounds_are_reported_but_unset_ones_are_not() {
+        let (dir, path, mut beatmap) = fixture("scan-background");
+        fs::remove_file(dir.join("bg.png")).unwrap();
+        assert_eq!(kinds(&check_beatmap(&path, None)), ["missing background"]);
+
+        beatmap.background_path = Some(String::new());
+        beatmap.save_to_file(&path).unwrap();
+        assert!(check_beatmap(&path, None).is_empty());
+    }
+
+    #[test]
+    fn the_summary_counts_maps_by_issue() {
+        let issue = |path: &str, kind| LibraryIssue {
+            path: path.to_string(),
+            kind,
+        };
+        let health = LibraryHealth {
+            issues: vec![
+                issue("a.json", IssueKind::Stale),
+                issue("b.json", IssueKind::MissingAudio("b.mp3".to_string())),
+                issue("c.json", IssueKind::MissingAudio("c.mp3".to_string())),
+                issue("c.json", IssueKind::Invalid(vec!["bad".to_string()])),
+            ],
+            checked: 3,
+            total: 3,
+        };
+        assert_eq!(
+            health.summary(),
+            "2 maps have missing audio, 1 map fails validation, 1 map changed on disk"
+        );
+        assert_eq!(health.tags_for("c.json").unwrap(), "missing audio, invalid");
+        assert_eq!(health.tags_for("d.json"), None);
+    }
+}
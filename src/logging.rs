@@ -0,0 +1,717 @@
+// src/logging.rs
+
+use bevy::log::tracing_subscriber::layer::{Context, Layer};
+use bevy::log::{BoxedLayer, Level, LogPlugin, DEFAULT_FILTER};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::tracing::field::{Field, Visit};
+use bevy::utils::tracing::{Event, Subscriber};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::GameConfig;
+use crate::constants::*;
+use crate::structs::GameAssets;
+use crate::text_input::Clipboard;
+
+/// Folder the log files are written to, next to config.json
+pub const LOG_DIR: &str = "logs";
+
+/// Name of the current log file; rotated ones are numbered, `yum-osu.1.log` newest
+const LOG_FILE_STEM: &str = "yum-osu";
+
+/// Size a log file grows to before it is rotated (bytes)
+pub const LOG_FILE_LIMIT: u64 = 1024 * 1024;
+
+/// Log files kept, the current one included
+pub const LOG_FILES_KEPT: usize = 5;
+
+/// Warnings and errors kept in memory for the log viewer
+pub const LOG_RING_CAPACITY: usize = 200;
+
+/// Lines the log viewer's copy button puts on the clipboard
+pub const COPY_LINES: usize = 50;
+
+/// Lines the log viewer shows at once, newest at the bottom
+const VIEWER_LINES: usize = 20;
+
+/// Longest line the log viewer draws before cutting it off, in characters
+const VIEWER_LINE_CHARS: usize = 110;
+
+/// How long a panic waits for its log line to reach the file
+const PANIC_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Lowest severity written to the log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogVerbosity {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogVerbosity {
+    /// All verbosities, quietest first
+    pub fn all() -> Vec<LogVerbosity> {
+        vec![
+            LogVerbosity::Error,
+            LogVerbosity::Warn,
+            LogVerbosity::Info,
+            LogVerbosity::Debug,
+        ]
+    }
+
+    /// Get display name for the verbosity
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            LogVerbosity::Error => "Errors",
+            LogVerbosity::Warn => "Warnings",
+            LogVerbosity::Info => "Info",
+            LogVerbosity::Debug => "Debug",
+        }
+    }
+
+    /// The verbosity `direction` steps away, wrapping around; 0 counts as forward
+    pub fn cycled(&self, direction: i32) -> LogVerbosity {
+        let all = Self::all();
+        let index = all
+            .iter()
+            .position(|verbosity| verbosity == self)
+            .unwrap_or(0) as i32;
+        let step = if direction < 0 { -1 } else { 1 };
+        all[(index + step).rem_euclid(all.len() as i32) as usize]
+    }
+
+    /// Most verbose level let through. Levels order from ERROR up to TRACE
+    fn max_level(&self) -> Level {
+        match self {
+            LogVerbosity::Error => Level::ERROR,
+            LogVerbosity::Warn => Level::WARN,
+            LogVerbosity::Info => Level::INFO,
+            LogVerbosity::Debug => Level::DEBUG,
+        }
+    }
+
+    /// Whether lines of a level are written
+    pub fn allows(&self, level: Level) -> bool {
+        level <= self.max_level()
+    }
+}
+
+/// Verbosity set in the config, read by the capture layer on every line
+static VERBOSITY: AtomicU8 = AtomicU8::new(LogVerbosity::Info as u8);
+
+fn verbosity() -> LogVerbosity {
+    let all = LogVerbosity::all();
+    let index = VERBOSITY.load(Ordering::Relaxed) as usize;
+    all.get(index).copied().unwrap_or_default()
+}
+
+/// Follow the verbosity picked in settings
+pub fn refresh_log_verbosity(config: Res<GameConfig>) {
+    if config.is_changed() {
+        VERBOSITY.store(config.log_verbosity as u8, Ordering::Relaxed);
+    }
+}
+
+/// One logged line
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    /// Wall clock time, to the millisecond
+    pub time: String,
+    pub level: Level,
+    /// Module the line came from
+    pub target: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}: {}",
+            self.time, self.level, self.target, self.message
+        )
+    }
+}
+
+/// The most recent lines, oldest dropped first once full
+#[derive(Debug, Clone, Default)]
+pub struct LogRing {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+    /// Lines ever pushed, so viewers can tell when something new arrived
+    pushed: u64,
+}
+
+impl LogRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+            pushed: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: LogLine) {
+        self.pushed += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        while self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Lines held, oldest first
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &LogLine> {
+        self.lines.iter()
+    }
+}
+
+/// Log file that moves aside once it reaches a size limit. `yum-osu.log` is written to;
+/// rotating renames it to `yum-osu.1.log`, that one to `yum-osu.2.log` and so on, and
+/// drops the oldest beyond the number kept
+pub struct RotatingFile {
+    dir: PathBuf,
+    limit: u64,
+    kept: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn new(dir: impl Into<PathBuf>, limit: u64, kept: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            limit,
+            kept: kept.max(1),
+            file: None,
+            size: 0,
+        }
+    }
+
+    /// Path of the file `index` rotations old; 0 is the one being written
+    pub fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join(format!("{}.log", LOG_FILE_STEM))
+        } else {
+            self.dir.join(format!("{}.{}.log", LOG_FILE_STEM, index))
+        }
+    }
+
+    /// Append a line, rotating first if it would take the file past the limit. A
+    /// single line longer than the limit still gets a file of its own
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let length = line.len() as u64 + 1;
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if self.size > 0 && self.size + length > self.limit {
+            self.rotate()?;
+        }
+        if let Some(file) = self.file.as_mut() {
+            writeln!(file, "{}", line)?;
+            self.size += length;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn open(&mut self) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(0))?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+        let oldest = self.path(self.kept - 1);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for index in (0..self.kept - 1).rev() {
+            let from = self.path(index);
+            if from.exists() {
+                fs::rename(from, self.path(index + 1))?;
+            }
+        }
+        self.open()
+    }
+}
+
+enum LogMessage {
+    Line(String),
+    /// Flush the file, then answer
+    Flush(mpsc::Sender<()>),
+}
+
+/// Where captured lines go: the writer thread and the viewer's ring buffer
+struct LogSink {
+    sender: Mutex<mpsc::Sender<LogMessage>>,
+    ring: Mutex<LogRing>,
+}
+
+static LOG_SINK: OnceLock<LogSink> = OnceLock::new();
+
+/// Start the thread writing the log file, so logging never waits on the disk. Lines
+/// logged before this only reach the console
+pub fn init() {
+    let (sender, receiver) = mpsc::channel();
+    let spawned = thread::Builder::new()
+        .name("log-writer".to_string())
+        .spawn(move || {
+            let mut file = RotatingFile::new(LOG_DIR, LOG_FILE_LIMIT, LOG_FILES_KEPT);
+            let mut reported = false;
+            for message in receiver {
+                match message {
+                    LogMessage::Line(line) => {
+                        // The log can't report its own failures, so say it once on the console
+                        if let Err(e) = file.write_line(&line) {
+                            if !reported {
+                                eprintln!("Failed to write the log file: {}", e);
+                                reported = true;
+                            }
+                        }
+                    }
+                    LogMessage::Flush(done) => {
+                        let _ = file.flush();
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        eprintln!("Failed to start the log writer: {}", e);
+        return;
+    }
+    let _ = LOG_SINK.set(LogSink {
+        sender: Mutex::new(sender),
+        ring: Mutex::new(LogRing::new(LOG_RING_CAPACITY)),
+    });
+}
+
+/// Wait up to `timeout` for every line sent so far to reach the file
+pub fn flush(timeout: Duration) {
+    let Some(sink) = LOG_SINK.get() else {
+        return;
+    };
+    let (done, finished) = mpsc::channel();
+    let sent = sink
+        .sender
+        .lock()
+        .is_ok_and(|sender| sender.send(LogMessage::Flush(done)).is_ok());
+    if sent {
+        let _ = finished.recv_timeout(timeout);
+    }
+}
+
+/// Log panics and wait for them to reach the file before the previous handler reports
+/// the crash
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        error!(target: "panic", "{}", info);
+        flush(PANIC_FLUSH_TIMEOUT);
+        previous(info);
+    }));
+}
+
+/// Collects an event's message and any other fields
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Tracing layer sending every line the verbosity allows to the log file, and
+/// warnings and errors to the viewer's ring buffer
+struct LogCapture;
+
+impl<S: Subscriber> Layer<S> for LogCapture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(sink) = LOG_SINK.get() else {
+            return;
+        };
+        let metadata = event.metadata();
+        let level = *metadata.level();
+        if !verbosity().allows(level) {
+            return;
+        }
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        let line = LogLine {
+            time: chrono::Local::now().format("%H:%M:%S%.3f").to_string(),
+            level,
+            target: metadata.target().to_string(),
+            message: message.0,
+        };
+        if let Ok(sender) = sink.sender.lock() {
+            let _ = sender.send(LogMessage::Line(line.to_string()));
+        }
+        if level <= Level::WARN {
+            if let Ok(mut ring) = sink.ring.lock() {
+                ring.push(line);
+            }
+        }
+    }
+}
+
+/// Bevy's log plugin with the capture layer added. The game's own debug lines are let
+/// through to it; the verbosity setting decides what is kept
+pub fn log_plugin() -> LogPlugin {
+    LogPlugin {
+        filter: format!("{},yum_osu=debug", DEFAULT_FILTER),
+        custom_layer: |_| Some(Box::new(LogCapture) as BoxedLayer),
+        ..default()
+    }
+}
+
+/// Lines of the viewer's ring buffer passing a filter, oldest first
+fn ring_lines(errors_only: bool) -> Vec<LogLine> {
+    let Some(ring) = LOG_SINK.get().and_then(|sink| sink.ring.lock().ok()) else {
+        return Vec::new();
+    };
+    ring.lines()
+        .filter(|line| !errors_only || line.level == Level::ERROR)
+        .cloned()
+        .collect()
+}
+
+fn ring_pushed() -> u64 {
+    LOG_SINK
+        .get()
+        .and_then(|sink| sink.ring.lock().ok())
+        .map_or(0, |ring| ring.pushed)
+}
+
+/// Recent warnings and errors over any screen, opened with F4 next to the F3 overlay
+#[derive(Resource, Default)]
+pub struct LogViewer {
+    pub open: bool,
+    /// Hide warnings
+    pub errors_only: bool,
+    /// Result of the last copy
+    pub notice: Option<String>,
+    /// Ring buffer count when last drawn
+    drawn: Option<u64>,
+}
+
+/// A clickable button of the log viewer
+#[derive(Component, Clone, Copy)]
+pub enum LogViewerButton {
+    Filter,
+    Copy,
+}
+
+/// Marker for log viewer entities, which outlive screen changes
+#[derive(Component)]
+pub struct LogViewerElement;
+
+const VIEWER_WIDTH: f32 = 900.0;
+const VIEWER_LINE_HEIGHT: f32 = 16.0;
+const VIEWER_BUTTON_SIZE: Vec2 = Vec2::new(170.0, 24.0);
+
+/// F4 opens and closes the viewer; its buttons change the filter and copy lines
+pub fn handle_log_viewer(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    buttons: Query<(&Transform, &LogViewerButton)>,
+    mut viewer: ResMut<LogViewer>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    if keyboard.just_pressed(KeyCode::F4) {
+        viewer.open = !viewer.open;
+        viewer.notice = None;
+    }
+    if !viewer.open || !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+    let cursor = Vec2::new(
+        cursor.x - window.width() / 2.0,
+        window.height() / 2.0 - cursor.y,
+    );
+    let clicked = buttons.iter().find(|(transform, _)| {
+        Rect::from_center_size(transform.translation.truncate(), VIEWER_BUTTON_SIZE)
+            .contains(cursor)
+    });
+    match clicked.map(|(_, button)| *button) {
+        Some(LogViewerButton::Filter) => {
+            viewer.errors_only = !viewer.errors_only;
+            viewer.notice = None;
+        }
+        Some(LogViewerButton::Copy) => {
+            let lines = ring_lines(viewer.errors_only);
+            let start = lines.len().saturating_sub(COPY_LINES);
+            let text = lines[start..]
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            clipboard.set(text);
+            viewer.notice = Some(format!("Copied {} lines", lines.len() - start));
+        }
+        None => {}
+    }
+}
+
+/// Draw the log viewer over the current screen, rebuilt when it or the log changes
+pub fn draw_log_viewer(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    mut viewer: ResMut<LogViewer>,
+    existing: Query<Entity, With<LogViewerElement>>,
+) {
+    let pushed = ring_pushed();
+    let current = viewer.open.then_some(pushed);
+    if !viewer.is_changed() && viewer.drawn == current {
+        return;
+    }
+    viewer.bypass_change_detection().drawn = current;
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !viewer.open {
+        return;
+    }
+
+    let height = (VIEWER_LINES as f32 + 4.0) * VIEWER_LINE_HEIGHT;
+    let top = window.height() / 2.0 - 20.0;
+    let left = -VIEWER_WIDTH / 2.0 + 12.0;
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(0.02, 0.02, 0.06, 0.92),
+            custom_size: Some(Vec2::new(VIEWER_WIDTH, height)),
+            ..default()
+        },
+        Transform::from_xyz(0.0, top - height / 2.0, 40.0),
+        LogViewerElement,
+    ));
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+
+    let title = match &viewer.notice {
+        Some(notice) => format!("Log (F4 to close) - {}", notice),
+        None => "Log (F4 to close)".to_string(),
+    };
+    commands.spawn((
+        Text2d::new(title),
+        font(14.0),
+        TextColor(NEON_CYAN),
+        Anchor::CenterLeft,
+        Transform::from_xyz(left, top - VIEWER_LINE_HEIGHT, 40.1),
+        LogViewerElement,
+    ));
+    let filter = if viewer.errors_only {
+        "Showing: Errors"
+    } else {
+        "Showing: Warnings+"
+    };
+    let buttons = [
+        (LogViewerButton::Filter, filter.to_string()),
+        (LogViewerButton::Copy, format!("Copy last {}", COPY_LINES)),
+    ];
+    for (i, (button, label)) in buttons.into_iter().enumerate() {
+        let from_right = (1 - i) as f32 * (VIEWER_BUTTON_SIZE.x + 10.0);
+        let x = VIEWER_WIDTH / 2.0 - 12.0 - VIEWER_BUTTON_SIZE.x / 2.0 - from_right;
+        commands
+            .spawn((
+                Sprite {
+                    color: NEON_PURPLE.with_alpha(0.5),
+                    custom_size: Some(VIEWER_BUTTON_SIZE),
+                    ..default()
+                },
+                Transform::from_xyz(x, top - VIEWER_LINE_HEIGHT, 40.1),
+                LogViewerElement,
+                button,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text2d::new(label),
+                    font(12.0),
+                    TextColor(Color::WHITE),
+                    Transform::from_xyz(0.0, 0.0, 0.01),
+                ));
+            });
+    }
+
+    let lines = ring_lines(viewer.errors_only);
+    if lines.is_empty() {
+        commands.spawn((
+            Text2d::new("Nothing logged"),
+            font(12.0),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Anchor::CenterLeft,
+            Transform::from_xyz(left, top - 3.0 * VIEWER_LINE_HEIGHT, 40.1),
+            LogViewerElement,
+        ));
+    }
+    let start = lines.len().saturating_sub(VIEWER_LINES);
+    for (i, line) in lines[start..].iter().enumerate() {
+        let color = if line.level == Level::ERROR {
+            ERROR_COLOR
+        } else {
+            WARNING_COLOR
+        };
+        let mut text = line.to_string();
+        if text.chars().count() > VIEWER_LINE_CHARS {
+            text = text.chars().take(VIEWER_LINE_CHARS - 3).collect::<String>() + "...";
+        }
+        commands.spawn((
+            Text2d::new(text),
+            font(11.0),
+            TextColor(color),
+            Anchor::CenterLeft,
+            Transform::from_xyz(left, top - (3.0 + i as f32) * VIEWER_LINE_HEIGHT, 40.1),
+            LogViewerElement,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn line(message: &str) -> LogLine {
+        LogLine {
+            time: "12:00:00.000".to_string(),
+            level: Level::WARN,
+            target: "yum_osu::test".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// An empty log folder of the test's own
+    fn log_dir(test: &str) -> PathBuf {
+        scratch_dir(&format!("logging-{}", test))
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn the_ring_drops_the_oldest_lines_once_full() {
+        let mut ring = LogRing::new(3);
+        for i in 0..5 {
+            ring.push(line(&i.to_string()));
+        }
+        let messages: Vec<&str> = ring.lines().map(|line| line.message.as_str()).collect();
+        assert_eq!(messages, ["2", "3", "4"]);
+        assert_eq!(ring.pushed, 5);
+
+        let mut empty = LogRing::new(0);
+        empty.push(line("lost"));
+        assert_eq!(empty.lines().count(), 0);
+        assert_eq!(empty.pushed, 1);
+    }
+
+    #[test]
+    fn rotation_shifts_files_and_keeps_only_the_limit() {
+        let dir = log_dir("rotate");
+        // Each line is 9 bytes with its newline, so two fit under the limit
+        let mut file = RotatingFile::new(&dir, 20, 3);
+        for i in 0..9 {
+            file.write_line(&format!("line {:03}", i)).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(read(file.path(0)), "line 008\n");
+        assert_eq!(read(file.path(1)), "line 006\nline 007\n");
+        assert_eq!(read(file.path(2)), "line 004\nline 005\n");
+        assert!(!file.path(3).exists());
+    }
+
+    #[test]
+    fn an_oversized_line_gets_a_file_of_its_own() {
+        let dir = log_dir("oversized");
+        let mut file = RotatingFile::new(&dir, 10, 5);
+        let long = "x".repeat(30);
+        file.write_line("short").unwrap();
+        file.write_line(&long).unwrap();
+        file.write_line("after").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(read(file.path(2)), "short\n");
+        assert_eq!(read(file.path(1)), format!("{}\n", long));
+        assert_eq!(read(file.path(0)), "after\n");
+    }
+
+    #[test]
+    fn reopening_counts_what_the_file_already_holds() {
+        let dir = log_dir("reopen");
+        let mut first = RotatingFile::new(&dir, 20, 2);
+        first.write_line("line 001").unwrap();
+        first.write_line("line 002").unwrap();
+        first.flush().unwrap();
+        drop(first);
+
+        let mut second = RotatingFile::new(&dir, 20, 2);
+        second.write_line("line 003").unwrap();
+        second.flush().unwrap();
+
+        assert_eq!(read(second.path(1)), "line 001\nline 002\n");
+        assert_eq!(read(second.path(0)), "line 003\n");
+    }
+
+    #[test]
+    fn verbosity_lets_through_its_level_and_anything_more_severe() {
+        assert!(LogVerbosity::Warn.allows(Level::ERROR));
+        assert!(LogVerbosity::Warn.allows(Level::WARN));
+        assert!(!LogVerbosity::Warn.allows(Level::INFO));
+        assert!(LogVerbosity::Debug.allows(Level::DEBUG));
+        assert!(!LogVerbosity::Debug.allows(Level::TRACE));
+        assert!(!LogVerbosity::Error.allows(Level::WARN));
+
+        assert_eq!(LogVerbosity::Error.cycled(-1), LogVerbosity::Debug);
+        assert_eq!(LogVerbosity::Debug.cycled(1), LogVerbosity::Error);
+        assert_eq!(LogVerbosity::Info.cycled(0), LogVerbosity::Debug);
+    }
+}
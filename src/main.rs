@@ -1,63 +1,233 @@
-mod analytics;
-mod audio;
-mod beatmap;
-mod config;
-mod constants;
-mod editor;
-mod editor_input;
-mod editor_ui;
-mod game;
-mod gamemode;
-mod structs;
-mod ui;
-
-use crate::analytics::{Analytics, AnalyticsState};
-use crate::audio::gather_beats;
-use crate::beatmap::BeatmapAssets;
-use crate::config::{GameConfig, SettingsState};
-use crate::constants::*;
-use crate::editor::{EditorState, EditorUIState};
-use crate::editor_input::{handle_editor_input, handle_editor_ui_interactions, handle_save_shortcut, update_editor};
-use crate::editor_ui::{render_editor_hit_objects, setup_editor_ui};
-use crate::game::*;
-use crate::structs::*;
-use crate::ui::*;
-
+use yum_osu::achievements::{unlock_achievements, AchievementDefinitions};
+use yum_osu::analysis_view::{
+    draw_analysis_view, handle_analysis_view_toggle, reset_analysis_zoom, ANALYSIS_ZOOM,
+};
+use yum_osu::analytics::{Analytics, AnalyticsState, AnalyticsView};
+use yum_osu::asset_manager::{poll_asset_loads, retry_failed_assets, AssetManager};
+use yum_osu::audio::{analyze_song, play_song, song_duration, whoosh};
+use yum_osu::beatmap::BeatmapAssets;
+use yum_osu::bots::{room_standings, BotProfile, RoomBot, Standing, MAX_ROOM_BOTS};
+use yum_osu::calibration::{
+    draw_calibration, enter_calibration, exit_calibration, update_calibration, Calibration,
+};
+use yum_osu::community::{ChatModAction, ReportQueue, ReportReason, COMPARISON_VISIBLE_ROWS};
+use yum_osu::config::{
+    is_bindable_key, key_label, AudioSetting, DataSetting, GameConfig, GameplaySetting,
+    PracticeConfig, SettingsState, SettingsTab, ThemeSetting, DELETE_ANALYTICS_CONFIRMATION,
+};
+use yum_osu::constants::*;
+use yum_osu::difficulty::DifficultyMetrics;
+use yum_osu::editor::{EditorState, EditorUIState};
+use yum_osu::editor_audio::{
+    audition_hitsounds, stop_editor_audio, sync_editor_audio, EditorAudio,
+};
+use yum_osu::editor_bench::{run_editor_bench, start_editor_bench, EditorBench};
+use yum_osu::editor_input::{
+    handle_comment_input, handle_editor_audio_clicks, handle_editor_input,
+    handle_editor_ui_interactions, handle_history_clicks, handle_metadata_input,
+    handle_practice_hint_input, handle_property_input, handle_save_shortcut, handle_timeline_input,
+    refresh_object_index, update_editor,
+};
+use yum_osu::editor_ui::{
+    draw_editor_audio, draw_editor_comments, draw_editor_guidelines, draw_editor_history,
+    draw_editor_metadata, draw_editor_practice_hints, draw_editor_properties,
+    draw_editor_timeline_objects, draw_editor_timing, draw_editor_waveform,
+    render_editor_hit_objects, setup_editor_ui,
+};
+use yum_osu::effects::{draw_follow_points, draw_hit_error_bar, draw_hit_lights};
+use yum_osu::event_bus::{
+    dispatch_bus_events, emit_screen_changes, BusEvent, EventBus, FinishedSession,
+};
+use yum_osu::game::*;
+use yum_osu::generator::{classify_beats, format_seed, generate_beatmap, parse_seed, song_seed};
+use yum_osu::guidelines::{refresh_guideline_report, GuidelineCheck, GuidelineReport, Guidelines};
+use yum_osu::hitsounds::HitsoundPlayer;
+use yum_osu::hud::{HudEditorState, HudElement, HudLayout, HUD_SCALE_STEP};
+use yum_osu::input_latency::{
+    apply_reduced_latency, present_mode_line, press_latency_bound_ms, restore_present_mode,
+    SavedPresentMode,
+};
+use yum_osu::key_conflicts::{
+    assign, conflicts_for, warn_key_binding_conflicts, PendingBinding, Resolution,
+};
+use yum_osu::library_analysis::{
+    draw_library_analysis, enter_library_analysis, handle_library_analysis, poll_library_analysis,
+    LibraryAnalysis,
+};
+use yum_osu::library_scan::{
+    poll_library_scan, IssueKind, LibraryIssue, LibraryScan, MAX_LISTED_ISSUES,
+};
+use yum_osu::logging::{draw_log_viewer, handle_log_viewer, refresh_log_verbosity, LogViewer};
+use yum_osu::menu_music::{
+    fade_out_menu_music, play_failed_music, play_results_music, play_selection_music,
+    preview_hovered_song, stop_menu_music, update_menu_music, MenuMusic,
+};
+use yum_osu::metronome::{
+    draw_metronome_pulse, enter_metronome, exit_metronome, update_metronome, Metronome,
+};
+use yum_osu::news::{poll_news_fetch, season_calendar, NewsState};
+use yum_osu::online::{
+    AccountField, AccountFormState, OnlineHubState, OnlineServices, OnlineTab,
+    CHAT_SLOW_MODE_STEPS, CHAT_TIMEOUT_MINUTES, MAX_USERNAME_LEN,
+};
+use yum_osu::performance::{
+    refresh_render_gates, run_performance_benchmark, PerformanceBenchmark, RenderGates,
+};
+use yum_osu::profiles::{
+    analytics_path, create_profile, delete_profile, list_profiles, migrate_flat_files,
+    switch_profile, ProfilePickerState, MAX_PROFILE_NAME,
+};
+use yum_osu::recommend::{
+    recommend, AnalyticsSummary, LibrarySong, NextRecommendation, SessionResult,
+};
+use yum_osu::replay::{
+    export_replay, save_session_replay, watch_replays, Replay, ReplayLibrary, ReplayMeta,
+};
+use yum_osu::seasons::{roll_over_seasons, SeasonArchive, SeasonCalendar};
+use yum_osu::session_details::cycled_cap;
+use yum_osu::settings_registry::{tab_entries, tab_index, SettingRow, SettingsSearch};
+use yum_osu::simulation::{judgment_ms, step_seconds, InputEvent, JudgmentClock, SongClock};
+use yum_osu::structs::*;
+use yum_osu::text_input::{
+    blink_text_cursors, clear_text_focus, pressed_keys, Clipboard, FocusId, Modifiers, TextFocus,
+    TextInput,
+};
+use yum_osu::theme_presets::{export_theme, list_presets};
+use yum_osu::toasts::{draw_toasts, toast_events, Toasts};
+use yum_osu::ui::*;
+use yum_osu::versus::{
+    draw_versus_vote, exit_versus, next_versus_opponent, recent_solo_accuracies, render_versus,
+    setup_versus_results_ui, update_versus, update_versus_results, VersusData,
+};
+use yum_osu::waveform::{refresh_waveform_peaks, EditorWaveform};
+use yum_osu::{beat_cache, cli, logging, AppState};
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::app::{PluginGroupBuilder, ScheduleRunnerPlugin};
 use bevy::prelude::*;
-use bevy::window::WindowCloseRequested;
-use rodio::{Decoder, OutputStream, Sink};
-use std::time::Instant;
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::window::{PresentMode, WindowCloseRequested};
+use bevy::winit::WinitPlugin;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rodio::{OutputStream, Sink};
+use std::time::{Duration, Instant};
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(window_config()))
+    // Batch subcommands run headless, before any window or audio device is opened
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+    logging::init();
+    logging::install_panic_hook();
+    let editor_bench = args.first().is_some_and(|arg| arg == cli::EDITOR_BENCH_FLAG);
+    let headless = editor_bench && args.get(1).is_some_and(|arg| arg == cli::HEADLESS_FLAG);
+
+    let mut window = window_config();
+    if editor_bench {
+        // Frame times only mean something when vsync isn't holding frames back
+        if let Some(primary) = window.primary_window.as_mut() {
+            primary.present_mode = PresentMode::AutoNoVsync;
+        }
+    }
+
+    let mut app = App::new();
+    app.add_plugins(default_plugins(window, headless))
         .init_state::<AppState>()
         .init_resource::<GameStateResource>()
         .init_resource::<GameTime>()
         .init_resource::<SettingsState>()
+        .init_resource::<LibraryAnalysis>()
+        .init_resource::<Calibration>()
+        .init_resource::<Metronome>()
         .init_resource::<AnalyticsState>()
+        .init_resource::<ReplayLibrary>()
         .init_resource::<PracticeMenuState>()
         .init_resource::<EditorState>()
         .init_resource::<EditorUIState>()
+        .init_resource::<EditorAudio>()
         .init_resource::<BeatmapAssets>()
+        .init_resource::<HudEditorState>()
+        .init_resource::<TextFocus>()
+        .init_resource::<Toasts>()
+        .init_resource::<ProfilePickerState>()
+        .init_resource::<AccountFormState>()
+        .init_resource::<OnlineHubState>()
+        .init_resource::<LogViewer>()
+        .init_resource::<SeasonArchive>()
+        .init_resource::<SavedPresentMode>()
+        .insert_non_send_resource(Clipboard::default())
         .add_event::<GameEvent>()
         .add_systems(Startup, setup)
-        .add_systems(Update, (handle_window_close, update_game_time))
+        .add_systems(
+            Update,
+            (
+                handle_window_close,
+                update_game_time,
+                poll_news_fetch,
+                roll_over_seasons,
+                warn_key_binding_conflicts,
+                poll_library_scan,
+                poll_library_analysis,
+                poll_asset_loads,
+                unlock_achievements,
+                update_friend_inbox,
+                run_account_maintenance,
+                clear_text_focus.run_if(state_changed::<AppState>),
+                emit_screen_changes,
+                draw_toasts,
+                refresh_render_gates,
+                update_menu_music,
+                refresh_log_verbosity,
+                (handle_log_viewer, draw_log_viewer).chain(),
+            ),
+        )
+        // After Update so cursors spawned by this frame's redraws blink too
+        .add_systems(PostUpdate, blink_text_cursors)
+        // After the frame's gameplay so subscribers see everything it emitted
+        .add_systems(PostUpdate, dispatch_bus_events)
         // Menu state systems
         .add_systems(OnEnter(AppState::Menu), (enter_menu, setup_menu_ui))
         .add_systems(
             Update,
-            (update_menu, handle_menu_interactions).run_if(in_state(AppState::Menu)),
+            (
+                update_menu,
+                handle_menu_interactions,
+                (handle_news_panel, draw_news_panel).chain(),
+                (retry_failed_assets, draw_asset_status).chain(),
+            )
+                .run_if(in_state(AppState::Menu)),
+        )
+        .add_systems(
+            Update,
+            run_performance_benchmark
+                .run_if(resource_exists::<PerformanceBenchmark>)
+                .run_if(in_state(AppState::Menu)),
         )
         .add_systems(OnExit(AppState::Menu), (exit_menu, cleanup_ui))
         // Song selection state systems
         .add_systems(
             OnEnter(AppState::SongSelection),
-            (enter_song_selection, setup_song_selection_ui),
+            (
+                enter_song_selection,
+                setup_song_selection_ui,
+                play_selection_music,
+            ),
         )
         .add_systems(
             Update,
-            (update_song_selection, handle_song_selection)
+            (
+                update_song_selection,
+                handle_song_keyboard,
+                handle_leaderboard_chips,
+                draw_song_list,
+                draw_song_leaderboard,
+                handle_song_selection,
+                preview_hovered_song,
+            )
+                .chain()
                 .run_if(in_state(AppState::SongSelection)),
         )
         .add_systems(OnExit(AppState::SongSelection), cleanup_ui)
@@ -68,50 +238,178 @@ fn main() {
         )
         .add_systems(
             Update,
-            update_practice_menu.run_if(in_state(AppState::PracticeMenu)),
+            (update_practice_menu, draw_practice_menu)
+                .chain()
+                .run_if(in_state(AppState::PracticeMenu)),
         )
         .add_systems(OnExit(AppState::PracticeMenu), cleanup_ui)
+        // Playing state systems
+        .add_systems(OnEnter(AppState::Playing), enter_playing)
         // Loading state systems
         .add_systems(
             OnEnter(AppState::Loading),
-            (enter_loading, setup_loading_ui),
+            (enter_loading, setup_loading_ui, fade_out_menu_music),
         )
         .add_systems(Update, update_loading.run_if(in_state(AppState::Loading)))
         .add_systems(OnExit(AppState::Loading), cleanup_ui)
         // ReadyToPlay state systems
         .add_systems(
             OnEnter(AppState::ReadyToPlay),
-            (enter_ready_to_play, setup_ready_ui),
+            (enter_ready_to_play, setup_ready_ui, stop_menu_music),
         )
         .add_systems(
             Update,
             (update_ready_to_play, update_countdown).run_if(in_state(AppState::ReadyToPlay)),
         )
         .add_systems(OnExit(AppState::ReadyToPlay), cleanup_ui)
-        // Visualizing state systems
-        .add_systems(OnEnter(AppState::Visualizing), enter_visualizing)
+        // Visualizing state systems. Input is judged at the top of the frame, so
+        // everything drawn in Update already shows this frame's judgments
+        .add_systems(
+            OnEnter(AppState::Visualizing),
+            (enter_visualizing, apply_reduced_latency, enter_metronome),
+        )
+        .add_systems(
+            PreUpdate,
+            update_visualizing
+                .after(bevy::input::InputSystem)
+                .run_if(in_state(AppState::Visualizing)),
+        )
         .add_systems(
             Update,
             (
-                update_visualizing,
+                handle_analysis_view_toggle,
                 render_game_circles,
                 render_game_floating_texts,
-                render_game_score,
+                render_game_hud,
+                (update_metronome, draw_metronome_pulse).chain(),
             )
                 .run_if(in_state(AppState::Visualizing)),
         )
-        .add_systems(OnExit(AppState::Visualizing), exit_visualizing)
+        .add_systems(
+            OnExit(AppState::Visualizing),
+            (
+                exit_visualizing,
+                reset_analysis_zoom,
+                restore_present_mode,
+                exit_metronome,
+                cleanup_ui,
+            ),
+        )
+        // Local versus state systems
+        .add_systems(
+            Update,
+            (update_versus, render_versus)
+                .chain()
+                .run_if(in_state(AppState::Versus)),
+        )
+        .add_systems(OnExit(AppState::Versus), (exit_versus, cleanup_ui))
+        .add_systems(OnEnter(AppState::VersusResults), setup_versus_results_ui)
+        .add_systems(
+            Update,
+            (update_versus_results, draw_versus_vote)
+                .chain()
+                .run_if(in_state(AppState::VersusResults)),
+        )
+        .add_systems(OnExit(AppState::VersusResults), cleanup_ui)
+        // Library analysis state systems
+        .add_systems(OnEnter(AppState::LibraryAnalysis), enter_library_analysis)
+        .add_systems(
+            Update,
+            (handle_library_analysis, draw_library_analysis)
+                .chain()
+                .run_if(in_state(AppState::LibraryAnalysis)),
+        )
+        .add_systems(OnExit(AppState::LibraryAnalysis), cleanup_ui)
+        // Offset calibration state systems
+        .add_systems(OnEnter(AppState::Calibration), enter_calibration)
+        .add_systems(
+            Update,
+            (update_calibration, draw_calibration)
+                .chain()
+                .run_if(in_state(AppState::Calibration)),
+        )
+        .add_systems(OnExit(AppState::Calibration), (exit_calibration, cleanup_ui))
         // End state systems
-        .add_systems(OnEnter(AppState::End), (enter_end, setup_end_ui))
-        .add_systems(Update, update_end.run_if(in_state(AppState::End)))
+        .add_systems(
+            OnEnter(AppState::End),
+            (enter_end, setup_end_ui, play_results_music).chain(),
+        )
+        .add_systems(
+            Update,
+            (update_end, draw_results_scrubber, draw_results_notice)
+                .chain()
+                .run_if(in_state(AppState::End)),
+        )
         .add_systems(OnExit(AppState::End), cleanup_ui)
+        // Failed state systems
+        .add_systems(
+            OnEnter(AppState::Failed),
+            (setup_failed_ui, play_failed_music),
+        )
+        .add_systems(
+            Update,
+            (update_failed, draw_failed_menu)
+                .chain()
+                .run_if(in_state(AppState::Failed)),
+        )
+        .add_systems(OnExit(AppState::Failed), cleanup_ui)
         // Settings state systems
         .add_systems(
             OnEnter(AppState::Settings),
             (enter_settings, setup_settings_ui),
         )
-        .add_systems(Update, update_settings.run_if(in_state(AppState::Settings)))
+        .add_systems(
+            Update,
+            (update_settings, draw_settings_tabs)
+                .chain()
+                .run_if(in_state(AppState::Settings)),
+        )
         .add_systems(OnExit(AppState::Settings), cleanup_ui)
+        // HUD layout editor state systems
+        .add_systems(
+            OnEnter(AppState::HudEditor),
+            (enter_hud_editor, setup_hud_editor_ui),
+        )
+        .add_systems(
+            Update,
+            (update_hud_editor, draw_hud_editor)
+                .chain()
+                .run_if(in_state(AppState::HudEditor)),
+        )
+        .add_systems(OnExit(AppState::HudEditor), cleanup_ui)
+        // Profile picker state systems
+        .add_systems(
+            OnEnter(AppState::ProfilePicker),
+            (enter_profile_picker, setup_profile_picker_ui),
+        )
+        .add_systems(
+            Update,
+            (update_profile_picker, draw_profile_picker)
+                .chain()
+                .run_if(in_state(AppState::ProfilePicker)),
+        )
+        .add_systems(OnExit(AppState::ProfilePicker), cleanup_ui)
+        // Account sign-in state systems
+        .add_systems(
+            OnEnter(AppState::Account),
+            (enter_account, setup_account_ui),
+        )
+        .add_systems(
+            Update,
+            (update_account, draw_account)
+                .chain()
+                .run_if(in_state(AppState::Account)),
+        )
+        .add_systems(OnExit(AppState::Account), cleanup_ui)
+        // Online hub state systems
+        .add_systems(OnEnter(AppState::Online), (enter_online, setup_online_ui))
+        .add_systems(
+            Update,
+            (update_online, draw_online)
+                .chain()
+                .run_if(in_state(AppState::Online)),
+        )
+        .add_systems(OnExit(AppState::Online), cleanup_ui)
         // Analytics state systems
         .add_systems(
             OnEnter(AppState::Analytics),
@@ -119,26 +417,57 @@ fn main() {
         )
         .add_systems(
             Update,
-            update_analytics.run_if(in_state(AppState::Analytics)),
+            (watch_replays, update_analytics, draw_analytics_view)
+                .chain()
+                .run_if(in_state(AppState::Analytics)),
         )
         .add_systems(OnExit(AppState::Analytics), cleanup_ui)
         // Beatmap editor state systems
         .add_systems(
             OnEnter(AppState::BeatmapEditor),
-            (enter_beatmap_editor, setup_editor_ui),
+            (enter_beatmap_editor, setup_editor_ui, stop_menu_music),
         )
         .add_systems(
             Update,
             (
-                handle_editor_input,
+                (
+                    handle_editor_input,
+                    handle_property_input,
+                    handle_metadata_input,
+                    handle_practice_hint_input,
+                    handle_comment_input,
+                    refresh_object_index,
+                )
+                    .chain(),
                 handle_editor_ui_interactions,
                 handle_save_shortcut,
                 update_editor,
                 render_editor_hit_objects,
+                draw_editor_timing,
+                draw_editor_practice_hints,
+                draw_editor_comments,
+                draw_editor_properties,
+                (
+                    refresh_guideline_report,
+                    draw_editor_metadata,
+                    draw_editor_guidelines,
+                )
+                    .chain(),
+                (handle_timeline_input, draw_editor_timeline_objects).chain(),
+                (refresh_waveform_peaks, draw_editor_waveform).chain(),
+                (handle_history_clicks, draw_editor_history).chain(),
+                (handle_editor_audio_clicks, draw_editor_audio).chain(),
+                sync_editor_audio.after(handle_editor_input),
+                audition_hitsounds
+                    .after(handle_editor_input)
+                    .after(handle_timeline_input),
             )
                 .run_if(in_state(AppState::BeatmapEditor)),
         )
-        .add_systems(OnExit(AppState::BeatmapEditor), cleanup_ui)
+        .add_systems(
+            OnExit(AppState::BeatmapEditor),
+            (cleanup_ui, stop_editor_audio),
+        )
         // Beatmap selection state systems
         .add_systems(
             OnEnter(AppState::BeatmapSelection),
@@ -146,31 +475,50 @@ fn main() {
         )
         .add_systems(
             Update,
-            (update_beatmap_selection, handle_beatmap_selection)
+            (
+                update_beatmap_selection,
+                (handle_beatmap_selection, draw_beatmap_backdrop).chain(),
+            )
                 .run_if(in_state(AppState::BeatmapSelection)),
         )
-        .add_systems(OnExit(AppState::BeatmapSelection), cleanup_ui)
-        .run();
+        .add_systems(OnExit(AppState::BeatmapSelection), cleanup_ui);
+
+    if editor_bench {
+        app.init_resource::<EditorBench>()
+            // After setup has inserted the loaded beatmaps, or they'd replace the bench map
+            .add_systems(Startup, start_editor_bench.after(setup))
+            .add_systems(
+                Update,
+                run_editor_bench
+                    .before(render_editor_hit_objects)
+                    .before(draw_editor_timeline_objects)
+                    .run_if(in_state(AppState::BeatmapEditor)),
+            );
+    }
+    app.run();
 }
 
-/// Application states
-#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
-pub enum AppState {
-    #[default]
-    Menu,
-    SongSelection,
-    PracticeMenu,
-    Playing,
-    Loading,
-    ReadyToPlay,
-    Visualizing,
-    End,
-    Settings,
-    Analytics,
-    BeatmapEditor,
-    BeatmapSelection,
+/// Bevy's default plugins. A headless run has nothing to draw to, so it goes without
+/// winit and the GPU and runs frames back to back
+fn default_plugins(window: WindowPlugin, headless: bool) -> PluginGroupBuilder {
+    let plugins = DefaultPlugins.set(window).set(logging::log_plugin());
+    if !headless {
+        return plugins;
+    }
+    plugins
+        .set(RenderPlugin {
+            render_creation: WgpuSettings {
+                backends: None,
+                ..default()
+            }
+            .into(),
+            ..default()
+        })
+        .disable::<WinitPlugin>()
+        .add(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
 }
 
+
 /// Game events for communication between systems
 #[derive(Event)]
 pub enum GameEvent {
@@ -184,34 +532,95 @@ pub enum GameEvent {
 }
 
 /// Setup system - runs once at startup
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Load font
-    let font_handle: Handle<Font> = asset_server.load("fonts/teknaf.otf");
-
-    // Insert resources
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // Start loading assets; screens draw with placeholders until they're ready
+    let asset_manager = AssetManager::start(&asset_server, &mut images);
     commands.insert_resource(GameAssets {
-        cyberpunk_font: font_handle,
+        cyberpunk_font: asset_manager.ui_font(),
     });
+    commands.insert_resource(asset_manager);
 
     // Load configuration
-    let config = GameConfig::load();
+    let mut config = GameConfig::load();
+    // Files from before local profiles become the shared profile
+    if let Err(e) = migrate_flat_files(&config) {
+        error!("{}", e);
+    }
+    config.load_active_profile();
     commands.insert_resource(config.clone());
+    commands.insert_resource(RenderGates::new(config.display.performance_preset()));
+    // No preset picked yet, so time a heavy scene on the menu and pick one
+    if config.display.performance_preset.is_none() {
+        commands.init_resource::<PerformanceBenchmark>();
+    }
 
-    // Load analytics
-    let analytics = Analytics::load();
+    // Load the active profile's analytics
+    let analytics = Analytics::load(analytics_path(&config.active_profile));
     commands.insert_resource(analytics);
+    // Whoever is at a shared machine picks their profile first
+    if list_profiles().len() > 1 {
+        next_state.set(AppState::ProfilePicker);
+    } else {
+        next_state.set(AppState::Menu);
+    }
+    commands.insert_resource(OnlineServices::load());
+    commands.insert_resource(AchievementDefinitions::load());
+    commands.insert_resource(Guidelines::load());
+    commands.insert_resource(GuidelineReport::default());
+    commands.insert_resource(GuidelineCheck::default());
+    commands.insert_resource(EditorWaveform::default());
+
+    // Subscribers are called in this order for every event
+    let mut bus = EventBus::default();
+    bus.subscribe("analytics", record_finished_session);
+    bus.subscribe("account", record_account_game);
+    bus.subscribe("toasts", toast_events);
+    commands.insert_resource(bus);
+
+    // Show cached news and fetch the latest feed in the background. Seasons announced
+    // in the cached feed apply until the fetched one replaces them
+    let news = NewsState::start_fetch();
+    commands.insert_resource(season_calendar(&news));
+    commands.insert_resource(news);
 
     // Initialize beatmap assets
     let mut beatmap_assets = BeatmapAssets::default();
     if let Err(e) = beatmap_assets.load_all() {
-        eprintln!("Failed to load beatmaps: {}", e);
+        error!("Failed to load beatmaps: {}", e);
     }
+    // Check the library for broken files in the background
+    commands.insert_resource(LibraryScan::start(&beatmap_assets));
     commands.insert_resource(beatmap_assets);
 
-    // Setup audio
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+    // Setup audio. Without an output device (a headless benchmark) the sinks are
+    // idle and play nowhere
+    let Ok((_stream, stream_handle)) = OutputStream::try_default() else {
+        error!("No audio output device, playing silently");
+        commands.insert_resource(GameAudioSink {
+            sink: Sink::new_idle().0,
+            effects: Sink::new_idle().0,
+        });
+        commands.insert_resource(MenuMusic::new([Sink::new_idle().0, Sink::new_idle().0]));
+        commands.spawn(Camera2d);
+        return;
+    };
     let sink = Sink::try_new(&stream_handle).unwrap();
-    commands.insert_resource(GameAudioSink { sink });
+    let effects = Sink::try_new(&stream_handle).unwrap();
+    commands.insert_resource(GameAudioSink { sink, effects });
+    match HitsoundPlayer::new(&stream_handle) {
+        Ok(player) => commands.insert_resource(player),
+        Err(e) => error!("Hitsounds disabled: {}", e),
+    }
+    // Menu music crossfades between two sinks of its own
+    commands.insert_resource(MenuMusic::new([
+        Sink::try_new(&stream_handle).unwrap(),
+        Sink::try_new(&stream_handle).unwrap(),
+    ]));
     // Note: _stream must be kept alive, we'll store it in a resource
     commands.insert_resource(AudioStream(_stream));
 
@@ -221,6 +630,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
 
 /// Resource to hold audio stream (must be kept alive)
 /// Safety: OutputStream is not Send/Sync due to raw pointers, but we only use it on the main thread
+#[derive(Resource)]
 pub struct AudioStream(#[allow(dead_code)] OutputStream);
 
 // SAFETY: We promise to only use AudioStream on the main thread
@@ -317,13 +727,111 @@ fn enter_song_selection(
     *selection_state = SongSelectionState::new();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_song_selection(
     mut next_state: ResMut<NextState<AppState>>,
+    mut selection_state: ResMut<SongSelectionState>,
+    mut game_state: ResMut<GameStateResource>,
+    mut key_events: EventReader<KeyboardInput>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    mut text_focus: ResMut<TextFocus>,
+    mut clipboard: NonSendMut<Clipboard>,
+    mut config: ResMut<GameConfig>,
+    analytics: Res<Analytics>,
 ) {
+    let keys = pressed_keys(&mut key_events);
+    let modifiers = Modifiers::from_keyboard(&keyboard);
+    text_focus.set(FocusId::SongSearch, selection_state.query.is_some());
+
+    // The search box captures typing; the navigate and select keys still work on
+    // the matches, ESC closes it and shows every song again
+    if selection_state.query.is_some() {
+        if keyboard.just_pressed(KeyCode::Escape) {
+            selection_state.query = None;
+            selection_state.selected_index = 0;
+            selection_state.scroll_pos = 0;
+            return;
+        }
+        // Only mark the state changed (and redraw the list) when the query changed
+        let state = selection_state.bypass_change_detection();
+        let Some(query) = state.query.as_mut() else {
+            return;
+        };
+        if query.handle_keys(&keys, modifiers, &mut clipboard) {
+            selection_state.selected_index = 0;
+            selection_state.scroll_pos = 0;
+        }
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Slash)
+        || (modifiers.ctrl && keyboard.just_pressed(KeyCode::KeyF))
+    {
+        selection_state.query = Some(TextInput::default());
+        return;
+    }
+
     if keyboard.just_pressed(KeyCode::Escape) {
         next_state.set(AppState::Menu);
     }
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        selection_state.filter = selection_state.filter.next();
+    }
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        selection_state.sort = selection_state.sort.next();
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        game_state.local_versus = !game_state.local_versus;
+    }
+    // Player two of a versus match: a second player, or a bot to practice against
+    if game_state.local_versus && keyboard.just_pressed(KeyCode::KeyB) {
+        game_state.versus_bot = next_versus_opponent(
+            game_state.versus_bot,
+            &recent_solo_accuracies(&analytics),
+        );
+    }
+    // Outside versus, B seats another bot in the local room, Shift+B changes the
+    // newest bot's profile and Ctrl+B lets it go
+    if !game_state.local_versus && keyboard.just_pressed(KeyCode::KeyB) {
+        let choices = BotProfile::choices(&recent_solo_accuracies(&analytics));
+        if modifiers.ctrl {
+            game_state.room_bots.pop();
+        } else if modifiers.shift {
+            if let Some(newest) = game_state.room_bots.last_mut() {
+                let index = choices
+                    .iter()
+                    .position(|choice| choice.display_name() == newest.display_name())
+                    .map_or(0, |index| (index + 1) % choices.len());
+                *newest = choices[index];
+            }
+        } else if game_state.room_bots.len() < MAX_ROOM_BOTS {
+            game_state.room_bots.push(choices[0]);
+        }
+    }
+
+    // Modifiers stay on for the next plays, like the rest of the game settings
+    for (key, _, modifier) in SONG_SELECT_MODS {
+        if keyboard.just_pressed(key) {
+            config.game_settings.toggle_modifier(modifier);
+            config.save();
+        }
+    }
+
+    // A pasted seed (shared by a friend with the same file) lays out the next play
+    if modifiers.ctrl && keyboard.just_pressed(KeyCode::KeyV) {
+        match parse_seed(&clipboard.get()) {
+            Some(seed) => {
+                game_state.seed_override = Some(seed);
+                selection_state.seed_notice = None;
+            }
+            None => {
+                selection_state.seed_notice =
+                    Some("The clipboard doesn't hold a layout seed".to_string());
+            }
+        }
+    }
+    if keyboard.just_pressed(KeyCode::Backspace) && game_state.seed_override.is_some() {
+        game_state.seed_override = None;
+    }
 }
 
 // ==================== PRACTICE MENU STATE ====================
@@ -331,17 +839,146 @@ fn update_song_selection(
 fn enter_practice_menu(
     mut game_state: ResMut<GameStateResource>,
     mut practice_state: ResMut<PracticeMenuState>,
+    config: Res<GameConfig>,
+    beatmap_assets: Res<BeatmapAssets>,
 ) {
     game_state.songs = load_songs_from_assets();
-    *practice_state = PracticeMenuState::new();
+    let song = game_state.songs.first().cloned();
+    let practice = match &song {
+        Some(path) => config.practice_for_song(path),
+        None => config.practice.clone(),
+    };
+    let hints = song
+        .as_deref()
+        .map(|path| beatmap_assets.practice_hints_for_song(path))
+        .unwrap_or_default();
+    let song_length = song.as_deref().and_then(song_duration);
+    *practice_state = PracticeMenuState::from_config(song, &practice);
+    practice_state.hints = hints;
+    practice_state.song_length = song_length;
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_practice_menu(
     mut next_state: ResMut<NextState<AppState>>,
+    mut practice_state: ResMut<PracticeMenuState>,
+    mut game_state: ResMut<GameStateResource>,
+    mut config: ResMut<GameConfig>,
+    beatmap_assets: Res<BeatmapAssets>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
 ) {
     if keyboard.just_pressed(KeyCode::Escape) {
         next_state.set(AppState::Menu);
+        return;
+    }
+
+    // Loop markers are dragged on the timeline. Pressing elsewhere on it moves the
+    // nearest marker there, or starts a loop at that point when there is none
+    if mouse.just_released(MouseButton::Left) {
+        practice_state.dragging = None;
+    }
+    if let (Some(length), Ok(window)) = (practice_state.song_length, windows.get_single()) {
+        let timeline = practice_timeline(window.width(), window.height());
+        let cursor = window
+            .cursor_position()
+            .map(|c| Vec2::new(c.x - window.width() / 2.0, window.height() / 2.0 - c.y));
+        let time_at =
+            |x: f32| ((x - timeline.min.x) / timeline.width()).clamp(0.0, 1.0) as f64 * length;
+        if let Some(cursor) = cursor {
+            let time = time_at(cursor.x);
+            if mouse.just_pressed(MouseButton::Left) && timeline.inflate(12.0).contains(cursor) {
+                let marker = match (practice_state.loop_start, practice_state.loop_end) {
+                    (Some(start), Some(end)) if (time - start).abs() <= (time - end).abs() => {
+                        LoopMarker::Start
+                    }
+                    (Some(_), Some(_)) => LoopMarker::End,
+                    _ => {
+                        practice_state.move_loop_marker(LoopMarker::Start, time);
+                        practice_state.move_loop_marker(LoopMarker::End, time + MIN_LOOP_LENGTH);
+                        LoopMarker::End
+                    }
+                };
+                practice_state.dragging = Some(marker);
+            }
+            let current = match practice_state.dragging {
+                Some(LoopMarker::Start) => practice_state.loop_start,
+                Some(LoopMarker::End) => practice_state.loop_end,
+                None => None,
+            };
+            if let Some(marker) = practice_state.dragging {
+                if mouse.pressed(MouseButton::Left) && current != Some(time) {
+                    practice_state.move_loop_marker(marker, time);
+                }
+            }
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        practice_state.selected_index = practice_state.selected_index.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        practice_state.selected_index =
+            (practice_state.selected_index + 1).min(PRACTICE_MENU_ROWS - 1);
+    }
+
+    let direction = if keyboard.just_pressed(KeyCode::ArrowRight) {
+        1
+    } else if keyboard.just_pressed(KeyCode::ArrowLeft) {
+        -1
+    } else {
+        0
+    };
+    let confirm = keyboard.just_pressed(KeyCode::Enter);
+
+    match practice_state.selected_index {
+        0 if direction != 0 && !game_state.songs.is_empty() => {
+            // Switching songs pre-fills that song's remembered settings
+            let count = game_state.songs.len() as i32;
+            let current = practice_state
+                .selected_song
+                .as_ref()
+                .and_then(|song| game_state.songs.iter().position(|s| s == song))
+                .unwrap_or(0) as i32;
+            let song = game_state.songs[(current + direction).rem_euclid(count) as usize].clone();
+            let practice = config.practice_for_song(&song);
+            let hints = beatmap_assets.practice_hints_for_song(&song);
+            let song_length = song_duration(&song);
+            let index = practice_state.selected_index;
+            *practice_state = PracticeMenuState::from_config(Some(song), &practice);
+            practice_state.hints = hints;
+            practice_state.song_length = song_length;
+            practice_state.selected_index = index;
+        }
+        1 if direction > 0 => practice_state.next_speed(),
+        1 if direction < 0 => practice_state.previous_speed(),
+        2 if direction != 0 || confirm => {
+            practice_state.preserve_pitch = !practice_state.preserve_pitch
+        }
+        3 if direction != 0 || confirm => practice_state.no_fail = !practice_state.no_fail,
+        4 if direction != 0 || confirm => practice_state.autoplay = !practice_state.autoplay,
+        5 if direction != 0 || confirm => practice_state.hit_sounds = !practice_state.hit_sounds,
+        6 if direction != 0 || confirm => {
+            practice_state.metronome = practice_state.metronome.cycled(direction)
+        }
+        7 if direction != 0 => practice_state.cycle_hint(direction),
+        8 if confirm => practice_state.clear_loop(),
+        9 if confirm => {
+            let Some(song) = practice_state.selected_song.clone() else {
+                return;
+            };
+            let practice = practice_state.to_config(config.practice.remember_per_song);
+            if config.practice.remember_per_song {
+                config.practice_songs.remember(&song, practice.clone());
+                config.save();
+            }
+            game_state.selected_song = song;
+            game_state.practice = Some(practice);
+            game_state.local_versus = false;
+            next_state.set(AppState::Playing);
+        }
+        _ => {}
     }
 }
 
@@ -349,13 +986,24 @@ fn update_practice_menu(
 
 fn enter_playing(
     mut commands: Commands,
-    game_state: Res<GameStateResource>,
+    mut game_state: ResMut<GameStateResource>,
+    config: Res<GameConfig>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
+    // Seeded from the file and settings so a song always generates the same map,
+    // unless a layout is being replayed or was pasted in
+    let seed = game_state
+        .seed_override
+        .take()
+        .unwrap_or_else(|| song_seed(&game_state.selected_song, &config.generation));
     commands.insert_resource(LoadingData {
         beats: None,
+        energy: Vec::new(),
         start_time: Instant::now(),
         song_path: game_state.selected_song.clone(),
+        quality: config.audio.detection_quality,
+        analyzing: false,
+        seed,
     });
 
     // Transition to loading state
@@ -372,18 +1020,52 @@ fn update_loading(
     mut commands: Commands,
     mut loading_data: ResMut<LoadingData>,
     mut next_state: ResMut<NextState<AppState>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut loading_text: Query<&mut Text2d, With<LoadingText>>,
 ) {
+    if loading_data.beats.is_none() && !loading_data.analyzing {
+        // Offer a different detection mode for this song before analysis starts
+        if keyboard.just_pressed(KeyCode::Tab) {
+            loading_data.quality = loading_data.quality.cycled(1);
+        }
+        let elapsed = loading_data.start_time.elapsed().as_secs_f64();
+        let status = if elapsed < DETECTION_OVERRIDE_WINDOW {
+            format!(
+                "Loading...\nBeat detection: {} (TAB to change)",
+                loading_data.quality.display_name()
+            )
+        } else {
+            loading_data.analyzing = true;
+            format!("Analyzing beats ({})...", loading_data.quality.display_name())
+        };
+        for mut text in loading_text.iter_mut() {
+            text.0 = status.clone();
+        }
+        return;
+    }
+
     // Load beats synchronously (we're in a loading screen, so this is fine)
     if loading_data.beats.is_none() {
-        let beats = gather_beats(&loading_data.song_path);
-        loading_data.beats = Some(beats);
+        let (path, quality) = (&loading_data.song_path, loading_data.quality);
+        let analysis = beat_cache::load_cached(path, quality).unwrap_or_else(|| {
+            let analysis = analyze_song(path, quality);
+            if let Err(e) = beat_cache::store(path, quality, &analysis) {
+                warn!("Couldn't cache beats for {}: {}", path, e);
+            }
+            analysis
+        });
+        loading_data.beats = Some(analysis.beats);
+        loading_data.energy = analysis.energy;
     }
 
     // Once we have beats, transition to ready
     if let Some(ref beats) = loading_data.beats {
         commands.insert_resource(ReadyToPlayData {
             beats: beats.clone(),
+            energy: std::mem::take(&mut loading_data.energy),
             ready_time: Instant::now(),
+            attempt: 1,
+            seed: loading_data.seed,
         });
 
         commands.remove_resource::<LoadingData>();
@@ -397,56 +1079,151 @@ fn enter_ready_to_play() {
     // Setup countdown
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_ready_to_play(
     mut commands: Commands,
     ready_data: Res<ReadyToPlayData>,
     mut next_state: ResMut<NextState<AppState>>,
-    mut audio_sink: ResMut<GameAudioSink>,
+    audio_sink: ResMut<GameAudioSink>,
     config: Res<GameConfig>,
     windows: Query<&Window>,
     game_state: Res<GameStateResource>,
+    mut bus: ResMut<EventBus>,
 ) {
     let elapsed = ready_data.ready_time.elapsed().as_secs_f32();
 
     if elapsed >= COUNTDOWN_DURATION as f32 {
-        // Load and start audio playback
-        if let Ok(file) = std::fs::File::open(&game_state.selected_song) {
-            let reader = std::io::BufReader::new(file);
-            if let Ok(source) = Decoder::new(reader) {
-                audio_sink.sink.append(source);
-                audio_sink.sink.play();
-            }
-        }
+        // Practice speed and Double Time / Half Time apply to the audio as well as the
+        // circles. Circle times stay in song time; the song clock runs at the speed
+        let (playback_speed, preserve_pitch) = game_state.playback(&config.game_settings);
+        // A practice loop starts the song at the loop
+        let start_at = game_state
+            .practice
+            .as_ref()
+            .and_then(|practice| practice.loop_section())
+            .map_or(0.0, |(start, _)| start);
+
+        // Load and start audio playback, counting what the output consumes
+        audio_sink.sink.set_volume(config.effective_music_volume());
+        let playback = play_song(
+            &audio_sink.sink,
+            &game_state.selected_song,
+            start_at,
+            playback_speed,
+            preserve_pitch,
+        )
+        .map_err(|e| error!("Failed to start song: {}", e))
+        .ok();
 
         // Initialize visualization state
         if let Ok(window) = windows.get_single() {
             let width = window.width();
             let height = window.height();
-            let mut rng = rand::thread_rng();
+            let mut rng = StdRng::seed_from_u64(ready_data.seed);
 
-            let spawn_radius = calculate_spawn_radius(width, height);
-            let center = Vec2::new(width / 2.0, height / 2.0);
+            // A versus layout fits half the screen; each player's side is placed at draw time
+            let (spawn_radius, center) = if game_state.local_versus {
+                (calculate_spawn_radius(width / 2.0, height), Vec2::ZERO)
+            } else {
+                (calculate_spawn_radius(width, height), Vec2::new(width / 2.0, height / 2.0))
+            };
 
-            let circles = initialize_circles(
-                &ready_data.beats,
+            let generated = classify_beats(&ready_data.beats, &ready_data.energy, &config.generation);
+            let beatmap = generate_beatmap(
+                &game_state.selected_song,
+                &generated,
                 &mut rng,
                 spawn_radius,
                 center,
+            );
+            if let Err(errors) = beatmap.validate() {
+                warn!("Generated map failed validation: {}", errors.join("; "));
+            }
+
+            let circles = initialize_circles(
+                &beatmap,
                 SHRINK_TIME,
                 COUNTDOWN_DURATION,
                 &config,
             );
 
-            let vis_state = VisualizingState::new(
+            if game_state.local_versus {
+                bus.emit(BusEvent::SessionStarted {
+                    song: game_state.selected_song.clone(),
+                    attempt: ready_data.attempt,
+                });
+                commands.insert_resource(VersusData::new(
+                    circles,
+                    &config,
+                    &game_state.selected_song,
+                    Some(DifficultyMetrics::calculate(&beatmap).star_rating()),
+                    ready_data.seed,
+                    SongClock::new(playback_speed, playback),
+                    game_state.versus_bot,
+                ));
+                commands.remove_resource::<ReadyToPlayData>();
+                next_state.set(AppState::Versus);
+                return;
+            }
+
+            // Practice settings only apply to plays started from the practice menu
+            let mut play_config = config.clone();
+            play_config.practice = game_state.practice.clone().unwrap_or_default();
+
+            let mut vis_state = VisualizingState::new(
                 ready_data.beats.clone(),
                 circles,
-                config.clone(),
+                play_config,
                 game_state.selected_song.clone(),
             );
+            vis_state.kiai_sections = beatmap.kiai_sections.clone();
+            vis_state.hp_drain = beatmap.settings.with_modifiers(&config.game_settings).hp_drain;
+            vis_state.star_rating = Some(DifficultyMetrics::calculate(&beatmap).star_rating());
+            vis_state.seed = ready_data.seed;
+            // The local room's bots play the same objects; practice plays stay solo
+            if game_state.practice.is_none() {
+                let hit_times: Vec<f64> = vis_state
+                    .circles
+                    .iter()
+                    .map(|circle| circle.hit_time)
+                    .collect();
+                let multiplier = vis_state.game_settings.score_multiplier();
+                vis_state.room = game_state
+                    .room_bots
+                    .iter()
+                    .enumerate()
+                    .map(|(slot, profile)| {
+                        RoomBot::new(slot, *profile, ready_data.seed, &hit_times, multiplier)
+                    })
+                    .collect();
+            }
+            if let Some(session) = vis_state.active_session.as_mut() {
+                session.retries = ready_data.attempt - 1;
+                session.star_rating = vis_state.star_rating;
+                session.seed = Some(ready_data.seed);
+            }
 
+            bus.emit(BusEvent::SessionStarted {
+                song: game_state.selected_song.clone(),
+                attempt: ready_data.attempt,
+            });
+            let mut clock = JudgmentClock::default();
+            let mut song_clock = SongClock::new(playback_speed, playback.clone());
+            if start_at > 0.0 {
+                clock.skip_to(judgment_ms(start_at, config.audio.offset_ms()));
+                song_clock.seek(start_at, playback);
+            }
             commands.insert_resource(VisualizingData {
                 state: vis_state,
-                start_time: Instant::now(),
+                clock,
+                song_clock,
+                show_debug: false,
+                latency: Default::default(),
+                analysis: None,
+                energy: ready_data.energy.clone(),
+                attempt: ready_data.attempt,
+                loops: 0,
+                pause_menu: None,
             });
         }
 
@@ -461,22 +1238,106 @@ fn enter_visualizing() {
     // Setup visualization
 }
 
+/// Stop the song and record the abandoned play, without a replay
+fn quit_play(visualizing_data: &VisualizingData, audio_sink: &GameAudioSink, bus: &mut EventBus) {
+    audio_sink.sink.stop();
+
+    if let Some(session) = visualizing_data.state.finish_session() {
+        bus.emit(BusEvent::SessionFinished(Box::new(FinishedSession {
+            session,
+            replay: None,
+        })));
+    }
+}
+
+/// Stop the song and go back to the countdown without the menu round-trip. The
+/// abandoned attempt is not recorded; the finishing run records how many retries it took
+fn restart_play(
+    visualizing_data: &mut VisualizingData,
+    audio_sink: &GameAudioSink,
+    commands: &mut Commands,
+) {
+    audio_sink.sink.stop();
+    commands.insert_resource(retry_data(visualizing_data));
+}
+
+/// Countdown of the next attempt at the play in progress, on the same map
+fn retry_data(visualizing_data: &mut VisualizingData) -> ReadyToPlayData {
+    ReadyToPlayData {
+        beats: visualizing_data.state.beats.clone(),
+        energy: std::mem::take(&mut visualizing_data.energy),
+        ready_time: Instant::now(),
+        attempt: visualizing_data.attempt + 1,
+        seed: visualizing_data.state.seed,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn update_visualizing(
     mut visualizing_data: ResMut<VisualizingData>,
     mut next_state: ResMut<NextState<AppState>>,
-    mut audio_sink: ResMut<GameAudioSink>,
+    audio_sink: ResMut<GameAudioSink>,
     keyboard: Res<ButtonInput<KeyCode>>,
     config: Res<GameConfig>,
-    mut analytics: ResMut<Analytics>,
+    mut bus: ResMut<EventBus>,
+    hitsound_player: Option<Res<HitsoundPlayer>>,
     windows: Query<&Window>,
+    time: Res<Time>,
+    real_time: Res<Time<Real>>,
+    game_state: Res<GameStateResource>,
     mut commands: Commands,
 ) {
-    let base_elapsed = visualizing_data.start_time.elapsed().as_secs_f64();
-    let elapsed = if visualizing_data.state.playback_speed != 1.0 {
-        base_elapsed * visualizing_data.state.playback_speed as f64
-    } else {
-        base_elapsed
-    };
+    if keyboard.just_pressed(KeyCode::F3) {
+        visualizing_data.show_debug = !visualizing_data.show_debug;
+    }
+
+    // Nothing advances while paused; only the menu takes input
+    if let Some(selected) = visualizing_data.pause_menu {
+        let keys = &config.key_bindings;
+        let choice = if keyboard.just_pressed(keys.pause_key()) {
+            Some(PauseOption::Resume)
+        } else if keyboard.just_pressed(keys.select_key()) {
+            Some(selected)
+        } else {
+            None
+        };
+        match choice {
+            Some(PauseOption::Resume) => {
+                audio_sink.sink.play();
+                visualizing_data.song_clock.resume();
+                visualizing_data.pause_menu = None;
+            }
+            Some(PauseOption::Restart) => {
+                restart_play(&mut visualizing_data, &audio_sink, &mut commands);
+                next_state.set(AppState::ReadyToPlay);
+            }
+            Some(PauseOption::Quit) => {
+                quit_play(&visualizing_data, &audio_sink, &mut bus);
+                next_state.set(AppState::Menu);
+            }
+            None if keyboard.just_pressed(keys.navigate_up_key()) => {
+                visualizing_data.pause_menu = Some(selected.moved(-1));
+            }
+            None if keyboard.just_pressed(keys.navigate_down_key()) => {
+                visualizing_data.pause_menu = Some(selected.moved(1));
+            }
+            None => {}
+        }
+        return;
+    }
+
+    // Pause before the exit check, as both default to Escape. A multiplayer room
+    // keeps playing, so there the key still leaves
+    if keyboard.just_pressed(config.key_bindings.pause_key()) && !game_state.multiplayer {
+        audio_sink.sink.pause();
+        visualizing_data.song_clock.pause();
+        visualizing_data.pause_menu = Some(PauseOption::Resume);
+        return;
+    }
+
+    // Follow the audio so the circles stay on the music over long songs
+    visualizing_data.song_clock.sync(time.delta_secs_f64());
+    let elapsed = visualizing_data.song_clock.now();
 
     // Get mouse position for hit detection
     let mut mouse_pos = Vec2::ZERO;
@@ -487,65 +1348,199 @@ fn update_visualizing(
                 cursor_pos.x - window.width() / 2.0,
                 window.height() / 2.0 - cursor_pos.y,
             );
+            // The zoomed out view shows more of the playfield per pixel
+            if visualizing_data.analysis.is_some() {
+                mouse_pos /= ANALYSIS_ZOOM;
+            }
+            visualizing_data.state.cursor = mouse_pos;
         }
     }
 
-    // Check for key presses
-    let key_pressed = keyboard.just_pressed(config.key_bindings.primary_hit_key())
-        || keyboard.just_pressed(config.key_bindings.secondary_hit_key());
-
-    // Handle key hits with mouse position
+    // Check for key presses, and for the last held hit key being let go
+    let hit_keys = [
+        config.key_bindings.primary_hit_key(),
+        config.key_bindings.secondary_hit_key(),
+    ];
+    let key_pressed = keyboard.any_just_pressed(hit_keys);
+    let key_released = keyboard.any_just_released(hit_keys) && !keyboard.any_pressed(hit_keys);
+
+    // Inputs are stamped on the simulation grid, shifted by the audio offset, and
+    // judged by the fixed-step clock
+    let offset_ms = config.audio.offset_ms();
+    let now_ms = judgment_ms(elapsed, offset_ms);
+    let data = &mut *visualizing_data;
     if key_pressed {
-        handle_key_hits_with_mouse(
-            &mut visualizing_data.state.circles,
-            elapsed,
-            &mut visualizing_data.state,
-            SHRINK_TIME,
-            &config,
-            mouse_pos,
-        );
+        data.clock.push_input(InputEvent {
+            time_ms: now_ms,
+            position: mouse_pos,
+            release: false,
+        });
+    }
+    if key_released {
+        data.clock.push_input(InputEvent {
+            time_ms: now_ms,
+            position: mouse_pos,
+            release: true,
+        });
+    }
+    let should_end_game = data.clock.advance(&mut data.state, now_ms, SHRINK_TIME);
+    if key_pressed {
+        data.latency.record(press_latency_bound_ms(&real_time));
+    }
+    bus.emit_all(data.state.bus_events.drain(..));
+    for bot in &mut data.state.room {
+        bot.advance(elapsed);
+    }
+    match hitsound_player.as_ref() {
+        Some(player) => player.play(
+            data.state.hitsounds.drain(..),
+            config.effective_effects_volume(),
+        ),
+        None => data.state.hitsounds.clear(),
     }
 
-    // Handle missed circles
-    let should_end_game = handle_missed_circles(
-        &mut visualizing_data.state.circles,
-        elapsed,
-        &mut visualizing_data.state,
-        SHRINK_TIME,
-    );
-
-    // Check if game should end due to survival mode
+    // Out of lives or health: the failed screen offers a retry. The play isn't
+    // finished here, so nothing is recorded unless the player quits from there
     if should_end_game {
         audio_sink.sink.stop();
+        let retry = (!game_state.multiplayer).then(|| retry_data(&mut visualizing_data));
+        let state = &mut visualizing_data.state;
+        commands.insert_resource(FailedData {
+            song_name: state.song_name.clone(),
+            ended_at: elapsed,
+            progress: state.progress(),
+            score: state.score,
+            session: state.active_session.take(),
+            retry,
+            selected: FailedOption::Retry,
+        });
+        next_state.set(AppState::Failed);
+        return;
+    }
+
+    // Check for exit
+    if keyboard.just_pressed(config.key_bindings.exit_key()) {
+        quit_play(&visualizing_data, &audio_sink, &mut bus);
+        next_state.set(AppState::Menu);
+        return;
+    }
+
+    // Quick retry
+    if keyboard.just_pressed(config.key_bindings.quick_retry_key())
+        && !game_state.multiplayer
+        && visualizing_data.song_clock.now() >= QUICK_RETRY_COOLDOWN
+    {
+        restart_play(&mut visualizing_data, &audio_sink, &mut commands);
+        next_state.set(AppState::ReadyToPlay);
+        return;
+    }
 
-        if let Some(session) = visualizing_data.state.finish_session() {
-            if config.save_analytics {
-                analytics.add_session(session);
+    // Skip a long quiet intro. In multiplayer the whole room has to agree through its
+    // skip vote, so the key only works solo
+    if keyboard.just_pressed(config.key_bindings.skip_intro_key()) && !game_state.multiplayer {
+        if let Some(target) = visualizing_data.state.intro_skip_target(elapsed) {
+            let (speed, preserve_pitch) = game_state.playback(&config.game_settings);
+            match play_song(
+                &audio_sink.sink,
+                &game_state.selected_song,
+                target,
+                speed,
+                preserve_pitch,
+            ) {
+                Ok(position) => {
+                    let data = &mut *visualizing_data;
+                    data.song_clock.seek(target, Some(position));
+                    data.clock.skip_to(judgment_ms(target, offset_ms));
+                    data.state.record_intro_skip(target - elapsed);
+                    audio_sink.effects.append(whoosh());
+                }
+                Err(e) => error!("Failed to skip intro: {}", e),
             }
         }
+    }
 
-        // Create end state with survival info
-        let active_session = visualizing_data.state.finish_session();
+    // Wrap a practice loop back to its start, bringing its circles back. A loop
+    // reaching the end of the song wraps when the song runs out
+    let practice = &visualizing_data.state.config.practice;
+    let loop_section = practice
+        .loop_section()
+        .filter(|&(_, end)| elapsed >= end || audio_sink.sink.empty());
+    if let Some((loop_start, _)) = loop_section {
+        let (speed, preserve_pitch) = game_state.playback(&config.game_settings);
+        match play_song(
+            &audio_sink.sink,
+            &game_state.selected_song,
+            loop_start,
+            speed,
+            preserve_pitch,
+        ) {
+            Ok(position) => {
+                let data = &mut *visualizing_data;
+                data.song_clock.seek(loop_start, Some(position));
+                data.clock.rewind_to(judgment_ms(loop_start, offset_ms));
+                data.state.rewind_loop(loop_start);
+                data.loops += 1;
+            }
+            Err(e) => error!("Failed to loop the section: {}", e),
+        }
+        return;
+    }
 
-        let end_state = EndState {
-            score: visualizing_data.state.score,
-            max_combo: visualizing_data.state.max_combo,
+    // Check if music has ended
+    if audio_sink.sink.empty() {
+        visualizing_data.state.mark_completed();
+        let judgment_log = visualizing_data.state.judgment_log();
+        let precision = visualizing_data.state.precision();
+        let active_session = visualizing_data.state.finish_session();
+        let accuracy = active_session.as_ref().map_or(0.0, |session| session.accuracy);
+        // A looped play's inputs went back in time, which a replay can't show
+        let replay =
+            (visualizing_data.loops == 0).then(|| play_replay(&visualizing_data, accuracy));
+        // Bots finish their runs, misses still pending after the last note included
+        let state = &mut visualizing_data.state;
+        for bot in &mut state.room {
+            bot.advance(f64::INFINITY);
+        }
+        let room = if state.room.is_empty() {
+            Vec::new()
+        } else {
+            let player = Standing {
+                name: "You".to_string(),
+                score: state.score,
+                accuracy: state.hits.accuracy(),
+                bot: false,
+            };
+            room_standings(player, &state.room)
+        };
+
+        let end_state = EndState {
+            score: visualizing_data.state.score,
+            max_combo: visualizing_data.state.max_combo,
             hits: if let Some(ref session) = active_session {
                 session.hits.clone()
             } else {
-                crate::analytics::HitStats::new()
-            },
-            accuracy: if let Some(ref session) = active_session {
-                session.accuracy
-            } else {
-                0.0
+                yum_osu::analytics::HitStats::new()
             },
+            accuracy,
             grade: if let Some(ref session) = active_session {
-                session.grade.clone()
+                session.grade
             } else {
-                crate::analytics::Grade::F
+                yum_osu::analytics::Grade::F
             },
-            full_combo: false, // Can't be full combo if you ran out of lives
+            full_combo: visualizing_data.state.max_combo > 0
+                && visualizing_data
+                    .state
+                    .circles
+                    .iter()
+                    .all(|c| c.hit || c.missed)
+                && visualizing_data
+                    .state
+                    .circles
+                    .iter()
+                    .filter(|c| c.missed)
+                    .count()
+                    == 0
+                && !visualizing_data.state.circles.iter().any(GameCircle::slider_broken),
             song_name: visualizing_data.state.song_name.clone(),
             practice_mode: visualizing_data.state.practice_mode,
             playback_speed: visualizing_data.state.playback_speed,
@@ -554,128 +1549,1471 @@ fn update_visualizing(
             game_mode: visualizing_data.state.game_settings.mode,
             difficulty: visualizing_data.state.game_settings.difficulty,
             modifiers: visualizing_data.state.game_settings.modifiers.clone(),
+            score_multiplier: visualizing_data.state.game_settings.score_multiplier(),
+            judgment_log,
+            incognito: config.incognito,
+            star_rating: visualizing_data.state.star_rating,
+            ended_at: elapsed,
+            seed: Some(visualizing_data.state.seed),
+            replay,
+            precision,
+            room,
         };
 
-        if config.save_analytics {
-            if let Some(session) = active_session {
-                analytics.add_session(session);
-            }
+        if let Some(session) = active_session {
+            bus.emit(BusEvent::SessionFinished(Box::new(FinishedSession {
+                session,
+                replay: end_state.replay.clone(),
+            })));
         }
 
         commands.insert_resource(EndData { state: end_state });
         next_state.set(AppState::End);
+    }
+}
+
+/// Replay of the play that just ended
+fn play_replay(data: &VisualizingData, accuracy: f32) -> Replay {
+    let state = &data.state;
+    let meta = ReplayMeta {
+        song: state.song_name.clone(),
+        seed: Some(state.seed),
+        score: state.score,
+        accuracy,
+        max_combo: state.max_combo,
+        playback_speed: state.playback_speed,
+        recorded_at: std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    Replay::new(meta, &data.clock.input_log)
+}
+
+/// Event bus subscriber recording finished plays, keeping their replays on disk so
+/// they can be exported later. Nothing is recorded while analytics are off
+fn record_finished_session(event: &BusEvent, world: &mut World) {
+    let BusEvent::SessionFinished(finished) = event else {
+        return;
+    };
+    if !world.resource::<GameConfig>().records_analytics() {
         return;
     }
+    let mut session = finished.session.clone();
+    let season = world
+        .resource::<SeasonCalendar>()
+        .season_at(session.session_id as i64);
+    session.season = Some(season.id);
+    if let Some(replay) = &finished.replay {
+        match save_session_replay(replay, session.session_id) {
+            Ok(path) => session.replay_file = Some(path),
+            Err(e) => error!("Failed to save replay: {}", e),
+        }
+    }
+    let cap_mb = world.resource::<GameConfig>().session_details_cap_mb;
+    let mut analytics = world.resource_mut::<Analytics>();
+    analytics.write_details(&mut session);
+    analytics.add_session(session);
+    let evicted = analytics.evict_details(cap_mb);
+    if evicted > 0 {
+        info!("Evicted {} session details to stay under {} MB", evicted, cap_mb);
+    }
+}
 
-    // Check for exit
-    if keyboard.just_pressed(config.key_bindings.exit_key()) {
-        audio_sink.sink.stop();
+/// Count a finished play toward the signed-in account's stats and leaderboard row
+fn record_account_game(event: &BusEvent, world: &mut World) {
+    let BusEvent::SessionFinished(finished) = event else {
+        return;
+    };
+    let online = world.resource::<OnlineServices>();
+    let calendar = world.resource::<SeasonCalendar>();
+    if let Err(e) = online.record_play(&finished.session, calendar) {
+        error!("Failed to record the play to your account: {}", e);
+    }
+}
+
+fn exit_visualizing(mut commands: Commands) {
+    commands.remove_resource::<VisualizingData>();
+}
 
-        if let Some(session) = visualizing_data.state.finish_session() {
-            if config.save_analytics {
-                analytics.add_session(session);
+// ==================== FAILED STATE ====================
+
+/// Move through the failed screen's options. Retry plays the same map again; quitting
+/// records the failed play and goes back to the menu
+fn update_failed(
+    mut commands: Commands,
+    mut failed: ResMut<FailedData>,
+    mut next_state: ResMut<NextState<AppState>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
+    mut bus: ResMut<EventBus>,
+) {
+    let keys = &config.key_bindings;
+    let options = failed.options();
+    let choice = if keyboard.just_pressed(keys.select_key()) {
+        Some(failed.selected)
+    } else if keyboard.just_pressed(keys.quick_retry_key()) {
+        options.first().copied()
+    } else if keyboard.just_pressed(keys.exit_key()) {
+        Some(FailedOption::Quit)
+    } else {
+        None
+    };
+
+    match choice {
+        Some(FailedOption::Retry) => {
+            if let Some(retry) = failed.retry.take() {
+                commands.insert_resource(ReadyToPlayData {
+                    ready_time: Instant::now(),
+                    ..retry
+                });
+                next_state.set(AppState::ReadyToPlay);
             }
         }
+        Some(FailedOption::Quit) => {
+            if let Some(session) = failed.session.take() {
+                bus.emit(BusEvent::SessionFinished(Box::new(FinishedSession {
+                    session: session.finish(),
+                    replay: None,
+                })));
+            }
+            next_state.set(AppState::Menu);
+        }
+        None => {
+            let step = if keyboard.just_pressed(keys.navigate_up_key()) {
+                -1
+            } else if keyboard.just_pressed(keys.navigate_down_key()) {
+                1
+            } else {
+                return;
+            };
+            let index = options
+                .iter()
+                .position(|option| *option == failed.selected)
+                .unwrap_or(0) as i32;
+            failed.selected = options[(index + step).rem_euclid(options.len() as i32) as usize];
+        }
+    }
+}
 
+// ==================== END STATE ====================
+
+fn enter_end(
+    mut commands: Commands,
+    end_data: Res<EndData>,
+    game_state: Res<GameStateResource>,
+    analytics: Res<Analytics>,
+) {
+    commands.insert_resource(ResultsScrubber::default());
+    commands.insert_resource(ResultsNotice::default());
+
+    // Suggest what to play next after a solo play that just ended
+    let mut next = None;
+    if !game_state.multiplayer && end_data.state.star_rating.is_some() {
+        let songs = if game_state.songs.is_empty() {
+            load_songs_from_assets()
+        } else {
+            game_state.songs.clone()
+        };
+        let library = LibrarySong::from_analytics(&songs, &analytics);
+        let result = SessionResult::from_end_state(&end_data.state);
+        let summary = AnalyticsSummary::from_analytics(&analytics);
+        next = recommend(&result, &library, &summary).into_iter().next();
+    }
+    commands.insert_resource(NextRecommendation(next));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_end(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut scrubber: ResMut<ResultsScrubber>,
+    mut game_state: ResMut<GameStateResource>,
+    recommendation: Res<NextRecommendation>,
+    end_data: Res<EndData>,
+    mut notice: ResMut<ResultsNotice>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) || keyboard.just_pressed(KeyCode::Enter) {
         next_state.set(AppState::Menu);
         return;
     }
 
-    // Check if music has ended
-    if audio_sink.sink.empty() {
-        let active_session = visualizing_data.state.finish_session();
+    if keyboard.just_pressed(KeyCode::KeyE) {
+        if let Some(replay) = &end_data.state.replay {
+            notice.0 = Some(match export_replay(replay) {
+                Ok(path) => format!("Replay exported to {}", path.display()),
+                Err(e) => format!("Couldn't export replay: {}", e),
+            });
+        }
+    }
 
-        let end_state = EndState {
-            score: visualizing_data.state.score,
-            max_combo: visualizing_data.state.max_combo,
-            hits: if let Some(ref session) = active_session {
-                session.hits.clone()
+    // Play the song again on the identical layout, or copy the seed to share it
+    if let Some(seed) = end_data.state.seed {
+        if keyboard.just_pressed(KeyCode::KeyL) && !game_state.multiplayer {
+            game_state.selected_song = end_data.state.song_name.clone();
+            game_state.seed_override = Some(seed);
+            next_state.set(AppState::Playing);
+            return;
+        }
+        if keyboard.just_pressed(KeyCode::KeyC) {
+            clipboard.set(format_seed(seed));
+        }
+    }
+
+    let duration = scrubber_duration(&end_data.state);
+    let has_scrubber = end_data.state.judgment_log.is_some() && duration > 0.0;
+
+    if has_scrubber {
+        if keyboard.just_pressed(KeyCode::ArrowLeft) {
+            scrubber.cursor_time = (scrubber.cursor_time - 1.0).max(0.0);
+        }
+        if keyboard.just_pressed(KeyCode::ArrowRight) {
+            scrubber.cursor_time = (scrubber.cursor_time + 1.0).min(duration);
+        }
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let cursor = window.cursor_position().map(|pos| {
+        Vec2::new(pos.x - window.width() / 2.0, window.height() / 2.0 - pos.y)
+    });
+
+    // Clicking the strip scrubs; clicking anywhere else continues
+    let (strip_y, strip_w) = scrubber_strip(window.width(), window.height());
+    let on_strip = cursor.is_some_and(|c| {
+        has_scrubber && (c.y - strip_y).abs() < 20.0 && c.x.abs() <= strip_w / 2.0
+    });
+
+    // The recommendation card starts its song, slowed down for a practice retry
+    let card = recommendation_card(window.width(), window.height());
+    if let Some(next) = &recommendation.0 {
+        if mouse_input.just_pressed(MouseButton::Left) && cursor.is_some_and(|c| card.contains(c)) {
+            game_state.selected_song = next.song.clone();
+            game_state.practice = (next.playback_speed < 1.0).then(|| PracticeConfig {
+                playback_speed: next.playback_speed,
+                ..PracticeConfig::default()
+            });
+            next_state.set(AppState::Playing);
+            return;
+        }
+    }
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        if on_strip {
+            scrubber.dragging = true;
+        } else {
+            next_state.set(AppState::Menu);
+            return;
+        }
+    }
+    if mouse_input.just_released(MouseButton::Left) {
+        scrubber.dragging = false;
+    }
+
+    if scrubber.dragging {
+        if let Some(c) = cursor {
+            let fraction = ((c.x + strip_w / 2.0) / strip_w).clamp(0.0, 1.0) as f64;
+            let time = fraction * duration;
+            if (time - scrubber.cursor_time).abs() > f64::EPSILON {
+                scrubber.cursor_time = time;
+            }
+        }
+    }
+}
+
+// ==================== SETTINGS STATE ====================
+
+fn enter_settings(mut settings_state: ResMut<SettingsState>) {
+    *settings_state = SettingsState::new();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_settings(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut settings_state: ResMut<SettingsState>,
+    mut config: ResMut<GameConfig>,
+    mut analytics: ResMut<Analytics>,
+    mut library: ResMut<LibraryScan>,
+    mut beatmap_assets: ResMut<BeatmapAssets>,
+    mut online: ResMut<OnlineServices>,
+    mut key_events: EventReader<KeyboardInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut text_focus: ResMut<TextFocus>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    let keys = pressed_keys(&mut key_events);
+
+    // A key binding being set takes the next key pressed
+    if let Some(binding) = settings_state.waiting_for_key {
+        let Some(&code) = keyboard.get_just_pressed().next() else {
+            return;
+        };
+        settings_state.waiting_for_key = None;
+        let key = format!("{:?}", code);
+        if !is_bindable_key(&key) {
+            settings_state.notice = Some(format!("{} can't be bound", key));
+            return;
+        }
+        let conflicts = conflicts_for(&config.key_bindings, binding, &key);
+        if conflicts.is_empty() {
+            config.key_bindings.set(binding, &key);
+            settings_state.notice =
+                Some(format!("{} set to {}", binding.display_name(), key_label(&key)));
+        } else {
+            settings_state.pending_binding = Some(PendingBinding { binding, key, conflicts });
+        }
+        return;
+    }
+
+    // A captured key that clashes waits for swap, assign anyway or cancel
+    if let Some(pending) = settings_state.pending_binding.clone() {
+        let resolution = if keyboard.just_pressed(KeyCode::KeyS) && pending.can_swap() {
+            Some(Resolution::Swap)
+        } else if keyboard.just_pressed(KeyCode::Enter) {
+            Some(Resolution::Clear)
+        } else {
+            None
+        };
+        if let Some(resolution) = resolution {
+            config.key_bindings =
+                assign(&config.key_bindings, pending.binding, &pending.key, resolution);
+            settings_state.pending_binding = None;
+            settings_state.notice = Some(format!(
+                "{} set to {}",
+                pending.binding.display_name(),
+                key_label(&pending.key)
+            ));
+        } else if keyboard.just_pressed(KeyCode::Escape) {
+            settings_state.pending_binding = None;
+            settings_state.notice =
+                Some(format!("{} unchanged", pending.binding.display_name()));
+        }
+        return;
+    }
+
+    text_focus.set(FocusId::SettingsSearch, settings_state.search.is_some());
+    text_focus.set(
+        FocusId::DeleteConfirmation,
+        settings_state.delete_confirmation.is_some(),
+    );
+    text_focus.set(FocusId::LocateAudio, settings_state.locate_audio.is_some());
+
+    // Typed confirmation for deleting all analytics captures every key
+    if settings_state.delete_confirmation.is_some() {
+        let modifiers = Modifiers::from_keyboard(&keyboard);
+        // Only mark the state changed (and redraw the tab) when the field changed
+        let state = settings_state.bypass_change_detection();
+        let Some(input) = state.delete_confirmation.as_mut() else {
+            return;
+        };
+        let edited = input.handle_keys(&keys, modifiers, &mut clipboard);
+        let typed = input.text().to_uppercase();
+
+        if keyboard.just_pressed(KeyCode::Escape) {
+            settings_state.delete_confirmation = None;
+            settings_state.notice = Some("Deletion cancelled".to_string());
+        } else if keyboard.just_pressed(KeyCode::Enter) {
+            settings_state.delete_confirmation = None;
+            settings_state.notice = Some(if typed == DELETE_ANALYTICS_CONFIRMATION {
+                match analytics.wipe_all() {
+                    Ok(Some(backup)) => format!("Analytics deleted, backup saved to {}", backup),
+                    Ok(None) => "Analytics deleted".to_string(),
+                    Err(e) => e,
+                }
             } else {
-                crate::analytics::HitStats::new()
-            },
-            accuracy: if let Some(ref session) = active_session {
-                session.accuracy
+                "Confirmation did not match, nothing was deleted".to_string()
+            });
+        } else if edited {
+            settings_state.set_changed();
+        }
+        return;
+    }
+
+    // Path prompt for a beatmap's missing audio captures every key
+    if settings_state.locate_audio.is_some() {
+        let modifiers = Modifiers::from_keyboard(&keyboard);
+        let state = settings_state.bypass_change_detection();
+        let Some((_, input)) = state.locate_audio.as_mut() else {
+            return;
+        };
+        let edited = input.handle_keys(&keys, modifiers, &mut clipboard);
+
+        if keyboard.just_pressed(KeyCode::Escape) {
+            settings_state.locate_audio = None;
+        } else if keyboard.just_pressed(KeyCode::Enter) {
+            if let Some((path, input)) = settings_state.locate_audio.take() {
+                let audio_path = input.text().trim();
+                settings_state.notice =
+                    Some(match library.relink_audio(&path, audio_path, &mut beatmap_assets) {
+                        Ok(()) => format!("{} now plays {}", path, audio_path),
+                        Err(e) => e,
+                    });
+            }
+        } else if edited {
+            settings_state.set_changed();
+        }
+        return;
+    }
+
+    // An imported theme is previewed next to the current one until confirmed
+    if settings_state.theme_preview.is_some() {
+        if keyboard.just_pressed(KeyCode::Enter) {
+            if let Some(preset) = settings_state.theme_preview.take() {
+                preset.apply(&mut config.theme);
+                settings_state.notice = Some(format!("Applied theme \"{}\"", preset.name));
+            }
+        } else if keyboard.just_pressed(KeyCode::Escape) {
+            settings_state.theme_preview = None;
+            settings_state.notice = Some("Theme import cancelled".to_string());
+        }
+        return;
+    }
+
+    // Search across every tab captures typing; arrows pick and adjust results instead
+    if settings_state.search.is_some() {
+        let modifiers = Modifiers::from_keyboard(&keyboard);
+        let keys: Vec<Key> = keys
+            .into_iter()
+            .filter(|key| !matches!(key, Key::ArrowLeft | Key::ArrowRight))
+            .collect();
+        let state = settings_state.bypass_change_detection();
+        let Some(search) = state.search.as_mut() else {
+            return;
+        };
+        if search.input.handle_keys(&keys, modifiers, &mut clipboard) {
+            search.selected = 0;
+            settings_state.set_changed();
+        }
+        let Some(search) = &settings_state.search else {
+            return;
+        };
+        let results = search.results();
+        let picked = results.get(search.selected).copied();
+        let last = results.len().saturating_sub(1);
+
+        if keyboard.just_pressed(KeyCode::Escape) {
+            settings_state.search = None;
+        } else if keyboard.just_pressed(KeyCode::Tab) {
+            // Go to the picked setting's tab with its row selected
+            if let Some(entry) = picked {
+                settings_state.current_tab = entry.tab;
+                settings_state.selected_index = tab_index(entry);
+                settings_state.search = None;
+            }
+        } else if keyboard.just_pressed(KeyCode::ArrowUp) {
+            if let Some(search) = settings_state.search.as_mut() {
+                search.selected = search.selected.saturating_sub(1);
+            }
+        } else if keyboard.just_pressed(KeyCode::ArrowDown) {
+            if let Some(search) = settings_state.search.as_mut() {
+                search.selected = (search.selected + 1).min(last);
+            }
+        } else if let (Some(entry), Some(direction)) = (picked, adjust_direction(&keyboard)) {
+            apply_setting(
+                entry.row,
+                direction,
+                &mut next_state,
+                &mut settings_state,
+                &mut config,
+                &mut library,
+                &beatmap_assets,
+                &mut online,
+            );
+        }
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Slash)
+        || (Modifiers::from_keyboard(&keyboard).ctrl && keyboard.just_pressed(KeyCode::KeyF))
+    {
+        settings_state.notice = None;
+        settings_state.search = Some(SettingsSearch::default());
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        config.save();
+        next_state.set(AppState::Menu);
+    }
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        settings_state.current_tab = if keyboard.pressed(KeyCode::ShiftLeft) {
+            settings_state.current_tab.previous()
+        } else {
+            settings_state.current_tab.next()
+        };
+        settings_state.selected_index = 0;
+    }
+
+    let rows: Vec<SettingRow> = tab_entries(settings_state.current_tab)
+        .map(|entry| entry.row)
+        .collect();
+    let listed_issues = match settings_state.current_tab {
+        SettingsTab::General => library.health.issues.len().min(MAX_LISTED_ISSUES),
+        _ => 0,
+    };
+    let row_count = rows.len() + listed_issues;
+    if row_count == 0 {
+        return;
+    }
+    // Fixed or removed library issues take their rows with them
+    if settings_state.selected_index >= row_count {
+        settings_state.selected_index = row_count - 1;
+    }
+
+    // Library issue listed under the General rows, if one is selected
+    let issue = match settings_state.current_tab {
+        SettingsTab::General => settings_state
+            .selected_index
+            .checked_sub(rows.len())
+            .and_then(|index| library.health.issues.get(index).cloned()),
+        _ => None,
+    };
+    if let Some(issue) = &issue {
+        if keyboard.just_pressed(KeyCode::Delete) {
+            if settings_state.pending_removal.as_ref() == Some(&issue.path) {
+                settings_state.pending_removal = None;
+                let result = library.remove(&issue.path, &mut beatmap_assets);
+                settings_state.notice = Some(match result {
+                    Ok(()) => format!("Removed {}", issue.path),
+                    Err(e) => e,
+                });
             } else {
-                0.0
-            },
-            grade: if let Some(ref session) = active_session {
-                session.grade.clone()
+                settings_state.pending_removal = Some(issue.path.clone());
+                settings_state.notice =
+                    Some(format!("Press Delete again to remove {}", issue.path));
+            }
+            return;
+        }
+        if keyboard.just_pressed(KeyCode::KeyR) {
+            settings_state.notice = Some(match library.reload(&issue.path, &mut beatmap_assets) {
+                Ok(()) => format!("Reloaded {}", issue.path),
+                Err(e) => e,
+            });
+            return;
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        settings_state.selected_index = settings_state.selected_index.saturating_sub(1);
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        settings_state.selected_index = (settings_state.selected_index + 1).min(row_count - 1);
+    }
+
+    let Some(direction) = adjust_direction(&keyboard) else {
+        return;
+    };
+
+    match rows.get(settings_state.selected_index) {
+        Some(&row) => apply_setting(
+            row,
+            direction,
+            &mut next_state,
+            &mut settings_state,
+            &mut config,
+            &mut library,
+            &beatmap_assets,
+            &mut online,
+        ),
+        // Enter on a listed issue: locate missing audio, otherwise reload
+        None if direction == 0 => match issue {
+            Some(LibraryIssue {
+                path,
+                kind: IssueKind::MissingAudio(audio_path),
+            }) => {
+                settings_state.notice = None;
+                settings_state.locate_audio = Some((path, TextInput::new(audio_path)));
+            }
+            Some(issue) => {
+                settings_state.notice =
+                    Some(match library.reload(&issue.path, &mut beatmap_assets) {
+                        Ok(()) => format!("Reloaded {}", issue.path),
+                        Err(e) => e,
+                    });
+            }
+            None => {}
+        },
+        None => {}
+    }
+}
+
+/// Right (`1`), Left (`-1`) or Enter (`0`) pressed on a settings row
+fn adjust_direction(keyboard: &ButtonInput<KeyCode>) -> Option<i32> {
+    if keyboard.just_pressed(KeyCode::ArrowRight) {
+        Some(1)
+    } else if keyboard.just_pressed(KeyCode::ArrowLeft) {
+        Some(-1)
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Change a setting from its tab or from search results
+#[allow(clippy::too_many_arguments)]
+fn apply_setting(
+    row: SettingRow,
+    direction: i32,
+    next_state: &mut NextState<AppState>,
+    settings_state: &mut SettingsState,
+    config: &mut GameConfig,
+    library: &mut LibraryScan,
+    beatmap_assets: &BeatmapAssets,
+    online: &mut OnlineServices,
+) {
+    match row {
+        SettingRow::Generation(setting) if direction != 0 => {
+            setting.adjust(&mut config.generation, direction);
+        }
+        SettingRow::Data(DataSetting::Incognito) => config.incognito = !config.incognito,
+        SettingRow::Data(DataSetting::HitPrecision) => {
+            config.track_hit_precision = !config.track_hit_precision
+        }
+        SettingRow::Data(DataSetting::DeleteAnalytics) if direction == 0 => {
+            settings_state.notice = None;
+            settings_state.delete_confirmation = Some(TextInput::default());
+        }
+        SettingRow::Data(DataSetting::LibraryHealth) if direction == 0 => {
+            if library.is_running() {
+                library.cancel();
             } else {
-                crate::analytics::Grade::F
-            },
-            full_combo: visualizing_data.state.max_combo > 0
-                && visualizing_data
-                    .state
-                    .circles
-                    .iter()
-                    .all(|c| c.hit || c.missed)
-                && visualizing_data
-                    .state
-                    .circles
+                library.restart(beatmap_assets);
+            }
+        }
+        SettingRow::Data(DataSetting::LogVerbosity) => {
+            config.log_verbosity = config.log_verbosity.cycled(direction)
+        }
+        SettingRow::Data(DataSetting::SessionDetails) => {
+            config.session_details_cap_mb = cycled_cap(config.session_details_cap_mb, direction)
+        }
+        SettingRow::Data(DataSetting::AccountStorage) if direction == 0 => {
+            online.read_storage();
+        }
+        SettingRow::Data(DataSetting::AccountCleanup) if direction == 0 => {
+            online.start_maintenance(true);
+        }
+        SettingRow::Gameplay(GameplaySetting::HudLayout) if direction == 0 => {
+            next_state.set(AppState::HudEditor);
+        }
+        SettingRow::Gameplay(setting) => setting.adjust(config),
+        SettingRow::KeyBinding(binding) if direction == 0 => {
+            settings_state.notice = None;
+            settings_state.waiting_for_key = Some(binding);
+        }
+        SettingRow::Theme(ThemeSetting::Preset) if direction != 0 => {
+            let count = settings_state.theme_presets.len() as i32;
+            if count > 0 {
+                let next = settings_state.theme_preset_index as i32 + direction;
+                settings_state.theme_preset_index = next.rem_euclid(count) as usize;
+            }
+        }
+        SettingRow::Theme(ThemeSetting::Preset) => {
+            let entry = settings_state
+                .theme_presets
+                .get(settings_state.theme_preset_index)
+                .cloned();
+            if let Some(entry) = entry {
+                match entry.load() {
+                    Ok(preset) => {
+                        settings_state.notice = None;
+                        settings_state.theme_preview = Some(preset);
+                    }
+                    Err(e) => {
+                        settings_state.notice =
+                            Some(format!("Couldn't import {}: {}", entry.name, e));
+                    }
+                }
+            }
+        }
+        SettingRow::Theme(ThemeSetting::FollowPoints) => {
+            config.theme.follow_points = !config.theme.follow_points
+        }
+        SettingRow::Theme(ThemeSetting::HitLighting) => {
+            config.theme.hit_lighting = !config.theme.hit_lighting
+        }
+        SettingRow::Theme(ThemeSetting::HitErrorBar) => {
+            config.theme.show_hit_error_bar = !config.theme.show_hit_error_bar
+        }
+        SettingRow::Theme(ThemeSetting::Export) if direction == 0 => {
+            settings_state.notice = Some(match export_theme(&config.theme, "Custom Theme") {
+                Ok(path) => format!("Theme exported to {}", path.display()),
+                Err(e) => format!("Couldn't export theme: {}", e),
+            });
+            settings_state.theme_presets = list_presets();
+        }
+        SettingRow::Practice(setting) => setting.adjust(config, direction),
+        SettingRow::Audio(AudioSetting::AnalyzeLibrary) if direction == 0 => {
+            next_state.set(AppState::LibraryAnalysis);
+        }
+        SettingRow::Audio(AudioSetting::CalibrateOffset) if direction == 0 => {
+            next_state.set(AppState::Calibration);
+        }
+        SettingRow::Audio(setting) => setting.adjust(&mut config.audio, direction),
+        SettingRow::Display(setting) => setting.adjust(&mut config.display, direction),
+        _ => {}
+    }
+}
+
+// ==================== HUD EDITOR STATE ====================
+
+fn enter_hud_editor(mut editor_state: ResMut<HudEditorState>) {
+    *editor_state = HudEditorState::default();
+}
+
+fn update_hud_editor(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut editor_state: ResMut<HudEditorState>,
+    mut config: ResMut<GameConfig>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) {
+        config.save();
+        next_state.set(AppState::Settings);
+        return;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let screen = Vec2::new(window.width(), window.height());
+    let element_count = HudElement::all().len();
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        editor_state.selected = if keyboard.pressed(KeyCode::ShiftLeft) {
+            (editor_state.selected + element_count - 1) % element_count
+        } else {
+            (editor_state.selected + 1) % element_count
+        };
+    }
+
+    let selected = editor_state.selected_element();
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        config.hud_layout.toggle_visible(selected);
+    }
+    if keyboard.just_pressed(KeyCode::Equal) || keyboard.just_pressed(KeyCode::NumpadAdd) {
+        config.hud_layout.adjust_scale(selected, HUD_SCALE_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::Minus) || keyboard.just_pressed(KeyCode::NumpadSubtract) {
+        config.hud_layout.adjust_scale(selected, -HUD_SCALE_STEP);
+    }
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        config.hud_layout = HudLayout::default();
+    }
+
+    // Drag elements with the mouse
+    let cursor = window.cursor_position().map(|cursor_pos| {
+        Vec2::new(
+            cursor_pos.x - window.width() / 2.0,
+            window.height() / 2.0 - cursor_pos.y,
+        )
+    });
+    if let Some(cursor) = cursor {
+        if mouse.just_pressed(MouseButton::Left) {
+            if let Some(element) = config.hud_layout.element_at(cursor, screen) {
+                let grab = cursor - config.hud_layout.position(element, screen);
+                editor_state.selected = HudElement::all()
                     .iter()
-                    .filter(|c| c.missed)
-                    .count()
-                    == 0,
-            song_name: visualizing_data.state.song_name.clone(),
-            practice_mode: visualizing_data.state.practice_mode,
-            playback_speed: visualizing_data.state.playback_speed,
-            new_best: false,
-            previous_best: 0,
-            game_mode: visualizing_data.state.game_settings.mode,
-            difficulty: visualizing_data.state.game_settings.difficulty,
-            modifiers: visualizing_data.state.game_settings.modifiers.clone(),
+                    .position(|e| *e == element)
+                    .unwrap_or(0);
+                editor_state.dragging = Some((element, grab));
+            }
+        } else if mouse.pressed(MouseButton::Left) {
+            if let Some((element, grab)) = editor_state.dragging {
+                config.hud_layout.move_to(element, cursor - grab, screen);
+            }
+        }
+    }
+    if mouse.just_released(MouseButton::Left) && editor_state.dragging.is_some() {
+        editor_state.dragging = None;
+    }
+}
+
+// ==================== PROFILE PICKER STATE ====================
+
+fn enter_profile_picker(mut picker: ResMut<ProfilePickerState>, config: Res<GameConfig>) {
+    *picker = ProfilePickerState::new(&config.active_profile);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_profile_picker(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut picker: ResMut<ProfilePickerState>,
+    mut config: ResMut<GameConfig>,
+    mut analytics: ResMut<Analytics>,
+    mut key_events: EventReader<KeyboardInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut text_focus: ResMut<TextFocus>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    let keys = pressed_keys(&mut key_events);
+    text_focus.set(FocusId::ProfileName, picker.new_name.is_some());
+
+    // Name for a new profile captures every key
+    if picker.new_name.is_some() {
+        let modifiers = Modifiers::from_keyboard(&keyboard);
+        let state = picker.bypass_change_detection();
+        let Some(input) = state.new_name.as_mut() else {
+            return;
+        };
+        let edited = input.handle_keys(&keys, modifiers, &mut clipboard);
+
+        if keyboard.just_pressed(KeyCode::Escape) {
+            picker.new_name = None;
+            picker.notice = None;
+        } else if keyboard.just_pressed(KeyCode::Enter) {
+            let name = input.text().to_string();
+            match create_profile(&name, &picker.profiles) {
+                Ok(name) => {
+                    picker.profiles = list_profiles();
+                    picker.selected = picker
+                        .profiles
+                        .iter()
+                        .position(|profile| *profile == name)
+                        .unwrap_or(0);
+                    picker.new_name = None;
+                    picker.notice = Some(format!("Created profile {}", name));
+                }
+                // Keep the field open so the name can be fixed
+                Err(e) => picker.notice = Some(e),
+            }
+        } else if edited {
+            picker.set_changed();
+        }
+        return;
+    }
+
+    let count = picker.profiles.len();
+    if keyboard.just_pressed(KeyCode::ArrowUp) && count > 0 {
+        picker.selected = (picker.selected + count - 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) && count > 0 {
+        picker.selected = (picker.selected + 1) % count;
+    }
+    let Some(selected) = picker.selected_profile().map(str::to_string) else {
+        return;
+    };
+
+    // Any key but a second Delete on the same profile cancels a pending deletion
+    if keyboard.get_just_pressed().any(|key| *key != KeyCode::Delete) {
+        picker.pending_delete = None;
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyN) {
+        picker.notice = None;
+        picker.new_name = Some(TextInput::default().with_max_len(MAX_PROFILE_NAME));
+    } else if keyboard.just_pressed(KeyCode::Delete) {
+        if picker.pending_delete.as_ref() == Some(&selected) {
+            picker.pending_delete = None;
+            picker.notice = Some(match delete_profile(&selected, &config.active_profile) {
+                Ok(trash) => format!("Moved {} to {}", selected, trash.display()),
+                Err(e) => e,
+            });
+            picker.profiles = list_profiles();
+            picker.selected = picker.selected.min(picker.profiles.len().saturating_sub(1));
+        } else {
+            picker.pending_delete = Some(selected);
+        }
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        if selected != config.active_profile {
+            switch_profile(&selected, &mut config, &mut analytics);
+        }
+        next_state.set(AppState::Menu);
+    } else if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+    }
+}
+
+// ==================== ACCOUNT STATE ====================
+
+fn enter_account(mut form: ResMut<AccountFormState>) {
+    *form = AccountFormState::new();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_account(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut form: ResMut<AccountFormState>,
+    mut online: ResMut<OnlineServices>,
+    mut key_events: EventReader<KeyboardInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut text_focus: ResMut<TextFocus>,
+    mut clipboard: NonSendMut<Clipboard>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let keys = pressed_keys(&mut key_events);
+    let signed_in = online.user().is_some();
+    text_focus.set(FocusId::AccountForm, !signed_in);
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Online);
+        return;
+    }
+
+    // Signed in there is no form, only signing out
+    if signed_in {
+        if keyboard.just_pressed(KeyCode::KeyL) {
+            online.sign_out();
+            toasts.push("Signed out");
+        } else if keyboard.just_pressed(KeyCode::Enter) {
+            next_state.set(AppState::Online);
+        }
+        return;
+    }
+
+    let modifiers = Modifiers::from_keyboard(&keyboard);
+    if keyboard.just_pressed(KeyCode::F2) {
+        form.toggle_registering();
+    } else if keyboard.just_pressed(KeyCode::Tab) {
+        form.focus_next(modifiers.shift);
+    } else if keyboard.just_pressed(KeyCode::Enter) && form.registering {
+        if !form.can_submit() {
+            return;
+        }
+        let username = form.username.text().trim().to_string();
+        let registered = online.register(
+            &username,
+            form.email.text().trim(),
+            form.password.text(),
+            form.confirm_password.text(),
+        );
+        match registered {
+            Ok(()) => {
+                form.toggle_registering();
+                form.username.set_text(username);
+                form.focused = AccountField::Password;
+                form.notice = Some("Registration successful, sign in to play".to_string());
+            }
+            Err(e) => form.error = Some(e),
+        }
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        if !form.can_submit() {
+            return;
+        }
+        let username = form.username.text().trim().to_string();
+        match online.sign_in(&username, form.password.text()) {
+            Ok(()) => {
+                toasts.push(format!("Signed in as {}", username));
+                next_state.set(AppState::Online);
+            }
+            Err(e) => {
+                form.error = Some(e);
+                form.password.set_text("");
+                form.focused = AccountField::Password;
+            }
+        }
+    } else {
+        let state = form.bypass_change_detection();
+        let focused = state.focused;
+        if state
+            .input_mut(focused)
+            .handle_keys(&keys, modifiers, &mut clipboard)
+        {
+            form.set_changed();
+        }
+    }
+}
+
+// ==================== ONLINE STATE ====================
+
+fn enter_online(
+    mut hub: ResMut<OnlineHubState>,
+    online: Res<OnlineServices>,
+    calendar: Res<SeasonCalendar>,
+    config: Res<GameConfig>,
+) {
+    *hub = OnlineHubState::new(hub.tab, hub.board_scope, hub.board_season.clone());
+    hub.calendar = calendar.clone();
+    hub.set_muted(&config);
+    hub.refresh(&online);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_online(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut hub: ResMut<OnlineHubState>,
+    mut online: ResMut<OnlineServices>,
+    mut config: ResMut<GameConfig>,
+    analytics: Res<Analytics>,
+    mut key_events: EventReader<KeyboardInput>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut text_focus: ResMut<TextFocus>,
+    mut clipboard: NonSendMut<Clipboard>,
+) {
+    let keys = pressed_keys(&mut key_events);
+    let chatting = hub.tab == OnlineTab::Chat && online.user().is_some();
+    text_focus.set(FocusId::FriendName, hub.friend_name.is_some());
+    text_focus.set(FocusId::Chat, chatting);
+
+    // Username for a friend request captures every key
+    if hub.friend_name.is_some() {
+        let modifiers = Modifiers::from_keyboard(&keyboard);
+        let state = hub.bypass_change_detection();
+        let Some(input) = state.friend_name.as_mut() else {
+            return;
         };
+        let edited = input.handle_keys(&keys, modifiers, &mut clipboard);
+
+        if keyboard.just_pressed(KeyCode::Escape) {
+            hub.friend_name = None;
+        } else if keyboard.just_pressed(KeyCode::Enter) {
+            let name = input.text().trim().to_string();
+            let Some(user) = online.user() else {
+                return;
+            };
+            match online
+                .accounts
+                .send_friend_request_sync(user.user_id(), name.clone())
+            {
+                Ok(_) => {
+                    hub.friend_name = None;
+                    hub.message = Some((format!("Friend request sent to {}", name), false));
+                    hub.refresh(&online);
+                }
+                // Keep the field open so the name can be fixed
+                Err(e) => hub.message = Some((e.to_string(), true)),
+            }
+        } else if edited {
+            hub.set_changed();
+        }
+        return;
+    }
 
-        if config.save_analytics {
-            if let Some(session) = active_session {
-                analytics.add_session(session);
+    // An open comparison takes the keys until ESC closes it
+    if hub.comparison.is_some() && !keyboard.just_pressed(KeyCode::Tab) {
+        if keyboard.just_pressed(KeyCode::Escape) {
+            hub.comparison = None;
+            return;
+        }
+        let page = COMPARISON_VISIBLE_ROWS as i32;
+        let moves = [
+            (KeyCode::ArrowUp, -1),
+            (KeyCode::ArrowDown, 1),
+            (KeyCode::PageUp, -page),
+            (KeyCode::PageDown, page),
+        ];
+        let state = hub.bypass_change_detection();
+        let Some(view) = state.comparison.as_mut() else {
+            return;
+        };
+        let mut handled = false;
+        for (key, delta) in moves {
+            if keyboard.just_pressed(key) {
+                view.move_cursor(delta);
+                handled = true;
+            }
+        }
+        if keyboard.just_pressed(KeyCode::ArrowRight) {
+            view.next_tab();
+            handled = true;
+        }
+        if keyboard.just_pressed(KeyCode::ArrowLeft) {
+            // Three lists, so two steps forward is one back
+            view.next_tab();
+            view.next_tab();
+            handled = true;
+        }
+        if keyboard.just_pressed(KeyCode::KeyS) {
+            view.cycle_sort();
+            handled = true;
+        }
+        if handled {
+            hub.set_changed();
+        }
+        return;
+    }
+
+    // Picking a report reason takes the keys until it's sent or ESC cancels it
+    if hub.chat_report.is_some() {
+        file_chat_report(&mut hub, &online, &keyboard);
+        return;
+    }
+
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let tab = hub.tab.cycled(shift);
+        hub.open_tab(tab, &online);
+        return;
+    }
+
+    // The message captures every other key but the room and moderation keys
+    if chatting {
+        let modifiers = Modifiers::from_keyboard(&keyboard);
+        let page_down = keyboard.just_pressed(KeyCode::PageDown);
+        if page_down || keyboard.just_pressed(KeyCode::PageUp) {
+            hub.cycle_chat_room(!page_down, &online);
+            return;
+        }
+        for (key, delta) in [(KeyCode::ArrowUp, -1), (KeyCode::ArrowDown, 1)] {
+            if keyboard.just_pressed(key) {
+                hub.move_chat_cursor(delta);
+                return;
+            }
+        }
+        if modifiers.ctrl && keyboard.just_pressed(KeyCode::KeyM) {
+            hub.message = Some(match hub.toggle_mute_selected(&mut config, &online) {
+                Ok(done) => {
+                    config.save();
+                    (done, false)
+                }
+                Err(e) => (e, true),
+            });
+            return;
+        }
+        if modifiers.ctrl && keyboard.just_pressed(KeyCode::KeyR) {
+            match hub.selected_chat_message() {
+                Some(_) => {
+                    hub.chat_report = Some(ReportReason::Spam);
+                    hub.message = None;
+                }
+                None => hub.message = Some(("Select a message with Up/Down".to_string(), true)),
+            }
+            return;
+        }
+        if hub.chat_host && moderate_chat(&mut hub, &online, &keyboard, modifiers) {
+            return;
+        }
+        if keyboard.just_pressed(KeyCode::Enter) {
+            let room_id = hub.chat_room_id(&online);
+            match online.send_chat(room_id, hub.chat_input.text()) {
+                Ok(()) => {
+                    hub.chat_input.set_text("");
+                    hub.message = None;
+                    hub.refresh(&online);
+                }
+                Err(e) => hub.message = Some((e, true)),
+            }
+        } else {
+            let state = hub.bypass_change_detection();
+            if state
+                .chat_input
+                .handle_keys(&keys, modifiers, &mut clipboard)
+            {
+                hub.set_changed();
             }
         }
+        return;
+    }
 
-        commands.insert_resource(EndData { state: end_state });
-        next_state.set(AppState::End);
+    if keyboard.just_pressed(KeyCode::KeyA) {
+        next_state.set(AppState::Account);
+        return;
     }
-}
 
-fn exit_visualizing(mut commands: Commands) {
-    commands.remove_resource::<VisualizingData>();
-}
+    match hub.tab {
+        OnlineTab::Leaderboard => {
+            let back = keyboard.just_pressed(KeyCode::ArrowLeft);
+            if back || keyboard.just_pressed(KeyCode::ArrowRight) {
+                hub.cycle_board_scope(back, &online);
+            }
+            let back = keyboard.just_pressed(KeyCode::ArrowUp);
+            if back || keyboard.just_pressed(KeyCode::ArrowDown) {
+                hub.cycle_board_season(back, &online);
+            }
+            let back = keyboard.just_pressed(KeyCode::PageUp);
+            if back || keyboard.just_pressed(KeyCode::PageDown) {
+                hub.page_board(back, &online);
+            }
+            if keyboard.just_pressed(KeyCode::KeyM) {
+                hub.jump_to_own_rank(&online);
+            }
+        }
+        OnlineTab::Friends => {
+            let Some(user_id) = online.user().map(|user| user.user_id()) else {
+                return;
+            };
+            let count = hub.friend_rows();
+            if keyboard.just_pressed(KeyCode::ArrowUp) && count > 0 {
+                hub.friend_cursor = (hub.friend_cursor + count - 1) % count;
+            }
+            if keyboard.just_pressed(KeyCode::ArrowDown) && count > 0 {
+                hub.friend_cursor = (hub.friend_cursor + 1) % count;
+            }
 
-// ==================== END STATE ====================
+            if keyboard.just_pressed(KeyCode::KeyN) {
+                hub.message = None;
+                hub.friend_name = Some(TextInput::default().with_max_len(MAX_USERNAME_LEN));
+                return;
+            }
+            if keyboard.just_pressed(KeyCode::KeyC) {
+                if let Some(friend) = hub.selected_friend().cloned() {
+                    hub.message = None;
+                    hub.comparison = Some(online.compare_with(&friend, &analytics));
+                }
+                return;
+            }
+            let Some(request) = hub.selected_request().cloned() else {
+                return;
+            };
+            // Enter accepts, D declines, B declines and blocks
+            let answer = if keyboard.just_pressed(KeyCode::Enter) {
+                online
+                    .accounts
+                    .accept_friend_request_sync(user_id, request.friend_id)
+                    .map(|()| format!("You and {} are now friends", request.username))
+            } else if keyboard.just_pressed(KeyCode::KeyD) {
+                online
+                    .accounts
+                    .decline_friend_request_sync(user_id, request.friend_id, false)
+                    .map(|()| format!("Declined {}'s request", request.username))
+            } else if keyboard.just_pressed(KeyCode::KeyB) {
+                online
+                    .accounts
+                    .decline_friend_request_sync(user_id, request.friend_id, true)
+                    .map(|()| format!("Blocked {}", request.username))
+            } else {
+                return;
+            };
+            hub.message = Some(match answer {
+                Ok(message) => (message, false),
+                Err(e) => (e.to_string(), true),
+            });
+            hub.refresh(&online);
+        }
+        OnlineTab::Tournaments => {
+            let count = hub.tournaments.len();
+            let up = keyboard.just_pressed(KeyCode::ArrowUp);
+            if (up || keyboard.just_pressed(KeyCode::ArrowDown)) && count > 0 {
+                let step = if up { count - 1 } else { 1 };
+                hub.selected_tournament = (hub.selected_tournament + step) % count;
+                hub.message = None;
+                hub.refresh(&online);
+                return;
+            }
 
-fn enter_end() {
-    // Setup end screen
+            // C creates, Enter joins the selected tournament, S starts it
+            let outcome = if keyboard.just_pressed(KeyCode::KeyC) {
+                online.create_tournament().map(|tournament_id| {
+                    hub.refresh(&online);
+                    if let Some(index) = hub
+                        .tournaments
+                        .iter()
+                        .position(|tournament| tournament.tournament_id == tournament_id)
+                    {
+                        hub.selected_tournament = index;
+                    }
+                    "Tournament created, waiting for players".to_string()
+                })
+            } else if keyboard.just_pressed(KeyCode::Enter) {
+                let Some(tournament) = hub.selected_tournament() else {
+                    return;
+                };
+                let name = tournament.name.clone();
+                online
+                    .join_tournament(tournament.tournament_id)
+                    .map(|()| format!("Registered for {}", name))
+            } else if keyboard.just_pressed(KeyCode::KeyS) {
+                let Some(tournament) = hub.selected_tournament() else {
+                    return;
+                };
+                online
+                    .start_tournament(tournament)
+                    .map(|()| format!("{} has started", tournament.name))
+            } else {
+                return;
+            };
+            hub.message = Some(match outcome {
+                Ok(message) => (message, false),
+                Err(e) => (e, true),
+            });
+            hub.refresh(&online);
+        }
+        // Signed out there's nothing to do but read
+        OnlineTab::Chat => {}
+        OnlineTab::Muted => {
+            let count = hub.muted_players.len();
+            if keyboard.just_pressed(KeyCode::ArrowUp) && count > 0 {
+                hub.muted_cursor = (hub.muted_cursor + count - 1) % count;
+            }
+            if keyboard.just_pressed(KeyCode::ArrowDown) && count > 0 {
+                hub.muted_cursor = (hub.muted_cursor + 1) % count;
+            }
+            if keyboard.just_pressed(KeyCode::Enter) {
+                if let Some(done) = hub.unmute_selected(&mut config, &online) {
+                    config.save();
+                    hub.message = Some((done, false));
+                }
+            }
+        }
+    }
 }
 
-fn update_end(
-    mut next_state: ResMut<NextState<AppState>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
+/// Reason picker for reporting the selected chat message: Left/Right pick the reason,
+/// Enter queues the report to send and ESC cancels
+fn file_chat_report(
+    hub: &mut OnlineHubState,
+    online: &OnlineServices,
+    keyboard: &ButtonInput<KeyCode>,
 ) {
-    if keyboard.just_pressed(KeyCode::Escape) || keyboard.just_pressed(KeyCode::Enter) {
-        next_state.set(AppState::Menu);
+    let reasons = ReportReason::all();
+    let index = reasons
+        .iter()
+        .position(|reason| Some(*reason) == hub.chat_report)
+        .unwrap_or(0);
+    if keyboard.just_pressed(KeyCode::Escape) {
+        hub.chat_report = None;
+    } else if keyboard.just_pressed(KeyCode::ArrowLeft) {
+        hub.chat_report = Some(reasons[(index + reasons.len() - 1) % reasons.len()]);
+    } else if keyboard.just_pressed(KeyCode::ArrowRight) {
+        hub.chat_report = Some(reasons[(index + 1) % reasons.len()]);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        let outcome = hub.report_selected(online).and_then(|report| {
+            let done = format!(
+                "Report on {} for {} queued to send",
+                hub.selected_chat_message()
+                    .map_or("them", |message| message.sender_name.as_str()),
+                reasons[index].display_name().to_lowercase()
+            );
+            ReportQueue::load()
+                .submit(report, None)
+                .map(|()| done)
+                .map_err(|e| format!("Couldn't save the report: {}", e))
+        });
+        hub.chat_report = None;
+        hub.message = Some(match outcome {
+            Ok(done) => (done, false),
+            Err(e) => (e, true),
+        });
     }
+}
 
-    if mouse_input.just_pressed(MouseButton::Left) {
-        next_state.set(AppState::Menu);
+/// A room host's moderation keys on the Chat tab, on the message selected with
+/// Up/Down: Ctrl+P pins or unpins it, Ctrl+T times its sender out or lifts the timeout and Ctrl+L
+/// steps slow mode. Returns whether a key was used
+fn moderate_chat(
+    hub: &mut OnlineHubState,
+    online: &OnlineServices,
+    keyboard: &ButtonInput<KeyCode>,
+    modifiers: Modifiers,
+) -> bool {
+    if !modifiers.ctrl {
+        return false;
     }
+    let selected = hub.selected_chat_message().cloned();
+    let settings = &hub.chat_settings;
+    let (action, done) = if keyboard.just_pressed(KeyCode::KeyL) {
+        let next = CHAT_SLOW_MODE_STEPS
+            .iter()
+            .find(|seconds| **seconds > settings.slow_mode_seconds)
+            .copied()
+            .unwrap_or(0);
+        let done = match next {
+            0 => "Slow mode off".to_string(),
+            seconds => format!("Slow mode: one message every {}s", seconds),
+        };
+        (ChatModAction::SlowMode { seconds: next }, done)
+    } else if keyboard.just_pressed(KeyCode::KeyP) {
+        let Some(message) = selected else {
+            hub.message = Some(("Select a message with Up/Down".to_string(), true));
+            return true;
+        };
+        let message_id = message.message_id;
+        if settings
+            .pinned
+            .iter()
+            .any(|pin| pin.message_id == message_id)
+        {
+            (
+                ChatModAction::Unpin { message_id },
+                "Message unpinned".to_string(),
+            )
+        } else {
+            (
+                ChatModAction::Pin { message_id },
+                "Message pinned".to_string(),
+            )
+        }
+    } else if keyboard.just_pressed(KeyCode::KeyT) {
+        let Some(message) = selected else {
+            hub.message = Some(("Select a message with Up/Down".to_string(), true));
+            return true;
+        };
+        let member_id = message.sender_id;
+        let timed_out = settings
+            .timeouts
+            .get(&member_id)
+            .is_some_and(|until| *until > chrono::Utc::now());
+        if timed_out {
+            let done = format!("Lifted {}'s timeout", message.sender_name);
+            (
+                ChatModAction::Timeout {
+                    member_id,
+                    minutes: 0,
+                },
+                done,
+            )
+        } else {
+            let done = format!(
+                "Timed out {} for {} minutes",
+                message.sender_name, CHAT_TIMEOUT_MINUTES
+            );
+            let minutes = CHAT_TIMEOUT_MINUTES;
+            (ChatModAction::Timeout { member_id, minutes }, done)
+        }
+    } else {
+        return false;
+    };
+    hub.message = match online.moderate_chat(hub.chat_room_id(online), action) {
+        Ok(_) => Some((done, false)),
+        Err(e) => Some((e, true)),
+    };
+    hub.refresh(online);
+    true
 }
 
-// ==================== SETTINGS STATE ====================
-
-fn enter_settings(mut settings_state: ResMut<SettingsState>) {
-    *settings_state = SettingsState::new();
+/// Clean up stored account data every `MAINTENANCE_INTERVAL` while the game runs,
+/// and toast the outcome of a cleanup started from Settings
+fn run_account_maintenance(mut online: ResMut<OnlineServices>, mut toasts: ResMut<Toasts>) {
+    let services = online.bypass_change_detection();
+    let started = services.maintenance.is_due(Instant::now());
+    if started {
+        services.start_maintenance(false);
+    }
+    let running = services.maintenance.is_running();
+    if let Some(result) = services.poll_maintenance() {
+        toasts.push(match result {
+            Ok(report) if report.is_empty() => "Account data is already clean".to_string(),
+            Ok(report) => format!(
+                "Cleaned up {} sessions, {} friends, {} friend requests",
+                report.sessions_removed, report.friends_removed, report.requests_removed
+            ),
+            Err(e) => format!("Couldn't clean up account data: {}", e),
+        });
+    }
+    // The storage row redraws when a cleanup starts or finishes
+    if started || (running && !services.maintenance.is_running()) {
+        online.set_changed();
+    }
 }
 
-fn update_settings(
-    mut next_state: ResMut<NextState<AppState>>,
-    settings_state: ResMut<SettingsState>,
-    mut config: ResMut<GameConfig>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+/// Keep the friend request badges current and toast requests that arrive while
+/// signed in
+fn update_friend_inbox(
+    mut online: ResMut<OnlineServices>,
+    mut toasts: ResMut<Toasts>,
+    config: Res<GameConfig>,
 ) {
-    if keyboard.just_pressed(KeyCode::Escape) {
-        config.save();
-        next_state.set(AppState::Menu);
+    if online.user().is_none() {
+        return;
+    }
+    let services = online.bypass_change_detection();
+    let changed = services.sync_inbox(&config.muted_users);
+    if let Some(toast) = services.inbox.take_toast() {
+        toasts.push(toast);
+    }
+    if changed {
+        online.set_changed();
     }
 }
 
@@ -685,12 +3023,256 @@ fn enter_analytics(mut analytics_state: ResMut<AnalyticsState>) {
     *analytics_state = AnalyticsState::new();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_analytics(
     mut next_state: ResMut<NextState<AppState>>,
+    mut analytics_state: ResMut<AnalyticsState>,
+    mut analytics: ResMut<Analytics>,
+    calendar: Res<SeasonCalendar>,
+    archive: Res<SeasonArchive>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    mut cursor_moved: EventReader<CursorMoved>,
 ) {
+    // Read every frame so a move from another view doesn't count as a hover later
+    let mouse_moved = cursor_moved.read().count() > 0;
+
+    // A pending song reset waits for Y (confirm) or N/ESC (cancel)
+    if let Some(song) = analytics_state.pending_song_reset.clone() {
+        if keyboard.just_pressed(KeyCode::KeyY) {
+            analytics.reset_song(&song);
+            analytics_state.pending_song_reset = None;
+            let song_count = analytics.listed_songs(analytics_state.season.as_ref()).len();
+            analytics_state.song_cursor = analytics_state.song_cursor.min(song_count.saturating_sub(1));
+        } else if keyboard.just_pressed(KeyCode::KeyN) || keyboard.just_pressed(KeyCode::Escape) {
+            analytics_state.pending_song_reset = None;
+        }
+        return;
+    }
+
+    // An open song detail panel goes back to the song list first
+    if analytics_state.selected_song.is_some()
+        && keyboard.any_just_pressed([KeyCode::Escape, KeyCode::Backspace])
+    {
+        analytics_state.selected_song = None;
+        return;
+    }
+
     if keyboard.just_pressed(KeyCode::Escape) {
-        next_state.set(AppState::Menu);
+        // Leave a day-filtered session list back to the heatmap first
+        if analytics_state.selected_day.is_some() {
+            analytics_state.selected_day = None;
+            analytics_state.current_view = AnalyticsView::History;
+        } else {
+            next_state.set(AppState::Menu);
+        }
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Tab) {
+        analytics_state.selected_day = None;
+        analytics_state.selected_session = None;
+        analytics_state.selected_song = None;
+        analytics_state.notice = None;
+        analytics_state.current_view = if keyboard.pressed(KeyCode::ShiftLeft) {
+            analytics_state.current_view.previous()
+        } else {
+            analytics_state.current_view.next()
+        };
+    }
+
+    // S steps through lifetime and each played season, newest first
+    if keyboard.just_pressed(KeyCode::KeyS) {
+        let mut choices: Vec<_> = calendar.played(&analytics).into_iter().map(Some).collect();
+        choices.insert(0, None);
+        let index = choices
+            .iter()
+            .position(|season| *season == analytics_state.season)
+            .unwrap_or(0);
+        let step = if keyboard.pressed(KeyCode::ShiftLeft) {
+            choices.len() - 1
+        } else {
+            1
+        };
+        analytics_state.season = choices[(index + step) % choices.len()].clone();
+        analytics_state.song_cursor = 0;
+        analytics_state.selected_session = None;
+        analytics_state.notice = None;
+    }
+
+    // E on the Overview exports everything to CSV and JSON
+    if analytics_state.current_view == AnalyticsView::Overview
+        && keyboard.just_pressed(KeyCode::KeyE)
+    {
+        let notice = match analytics.export() {
+            Ok(path) => format!("Analytics exported to {} (and .json)", path.display()),
+            Err(e) => format!("Couldn't export analytics: {}", e),
+        };
+        analytics_state.notice = Some(notice);
+    }
+
+    if analytics_state.current_view == AnalyticsView::Seasons {
+        let count = archive.summaries.len();
+        if keyboard.just_pressed(KeyCode::ArrowUp) {
+            analytics_state.season_cursor = analytics_state.season_cursor.saturating_sub(1);
+        }
+        if keyboard.just_pressed(KeyCode::ArrowDown) && analytics_state.season_cursor + 1 < count {
+            analytics_state.season_cursor += 1;
+        }
+        return;
+    }
+
+    if analytics_state.current_view == AnalyticsView::Songs {
+        let song_count = analytics.listed_songs(analytics_state.season.as_ref()).len();
+        if keyboard.just_pressed(KeyCode::ArrowUp) {
+            analytics_state.song_cursor = analytics_state.song_cursor.saturating_sub(1);
+        }
+        if keyboard.just_pressed(KeyCode::ArrowDown) && analytics_state.song_cursor + 1 < song_count {
+            analytics_state.song_cursor += 1;
+        }
+        // Enter or a click on a row opens its details, which follow the cursor
+        let clicked = mouse.just_pressed(MouseButton::Left)
+            && analytics_state.selected_song.is_none()
+            && analytics_state.pending_song_reset.is_none();
+        let clicked_row = match windows.get_single() {
+            Ok(window) if clicked => window.cursor_position().and_then(|cursor_pos| {
+                let world = Vec2::new(
+                    cursor_pos.x - window.width() / 2.0,
+                    window.height() / 2.0 - cursor_pos.y,
+                );
+                analytics_song_at(world, analytics_state.song_cursor, song_count)
+            }),
+            _ => None,
+        };
+        if let Some(row) = clicked_row {
+            analytics_state.song_cursor = row;
+        }
+        if clicked_row.is_some()
+            || keyboard.just_pressed(KeyCode::Enter)
+            || analytics_state.selected_song.is_some()
+        {
+            let song = analytics
+                .listed_songs(analytics_state.season.as_ref())
+                .get(analytics_state.song_cursor)
+                .map(|s| (*s).clone());
+            if analytics_state.selected_song != song {
+                analytics_state.selected_song = song;
+            }
+        }
+        if keyboard.just_pressed(KeyCode::KeyR) {
+            let song = analytics
+                .listed_songs(analytics_state.season.as_ref())
+                .get(analytics_state.song_cursor)
+                .map(|s| (*s).clone());
+            analytics_state.pending_song_reset = song;
+        }
+        return;
+    }
+
+    // Up/Down open a session's details; E exports its replay, P pins it
+    if analytics_state.current_view == AnalyticsView::Sessions {
+        let sessions = analytics
+            .listed_sessions(analytics_state.selected_day, analytics_state.season.as_ref());
+        let count = sessions.len().min(MAX_LISTED_SESSIONS);
+        if keyboard.just_pressed(KeyCode::ArrowUp) {
+            analytics_state.selected_session = match analytics_state.selected_session {
+                Some(0) | None => None,
+                Some(index) => Some(index - 1),
+            };
+        }
+        if keyboard.just_pressed(KeyCode::ArrowDown) && count > 0 {
+            analytics_state.selected_session = Some(
+                analytics_state
+                    .selected_session
+                    .map_or(0, |index| (index + 1).min(count - 1)),
+            );
+        }
+        if keyboard.just_pressed(KeyCode::KeyE) {
+            let session = analytics_state
+                .selected_session
+                .and_then(|index| sessions.get(index));
+            if let Some(session) = session {
+                let notice = match &session.replay_file {
+                    Some(file) => Replay::load(std::path::Path::new(file))
+                        .and_then(|replay| export_replay(&replay))
+                        .map(|path| format!("Replay exported to {}", path.display()))
+                        .unwrap_or_else(|e| format!("Couldn't export replay: {}", e)),
+                    None => "No replay was saved for this session".to_string(),
+                };
+                analytics_state.notice = Some(notice);
+            }
+        }
+
+        // The judgment log is read from disk only once its session is opened
+        let selected = analytics_state
+            .selected_session
+            .and_then(|index| sessions.get(index))
+            .map(|session| {
+                (session.session_id, session.local_versus, session.details_file.clone())
+            });
+        analytics_state
+            .follow_session_details(selected.as_ref().and_then(|(_, _, file)| file.as_deref()));
+        if keyboard.just_pressed(KeyCode::KeyP) {
+            if let Some((session_id, local_versus, _)) = selected {
+                let notice = match analytics.toggle_pin(session_id, local_versus) {
+                    Some(true) => "Session pinned: its details are never evicted",
+                    Some(false) => "Session unpinned",
+                    None => "Session not found",
+                };
+                analytics_state.notice = Some(notice.to_string());
+            }
+        }
+        return;
+    }
+
+    if analytics_state.current_view != AnalyticsView::History {
+        return;
+    }
+
+    // Move the day cursor: columns are weeks, rows are weekdays
+    let start_day = history_start_day();
+    let today = yum_osu::analytics::today();
+    let mut cursor = analytics_state.history_cursor as i64;
+    if keyboard.just_pressed(KeyCode::ArrowLeft) {
+        cursor -= 7;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowRight) {
+        cursor += 7;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        cursor -= 1;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        cursor += 1;
+    }
+
+    // Pointing at a cell moves the cursor too, but only when the mouse moves, so a
+    // resting pointer doesn't pin the cursor against the arrow keys
+    if let (true, Ok(window)) = (mouse_moved, windows.get_single()) {
+        if let Some(cursor_pos) = window.cursor_position() {
+            let world = Vec2::new(
+                cursor_pos.x - window.width() / 2.0,
+                window.height() / 2.0 - cursor_pos.y,
+            );
+            if let Some(day) = history_day_at(world) {
+                cursor = day as i64;
+            }
+        }
+    }
+
+    let cursor = cursor.clamp(start_day as i64, today as i64) as u64;
+    if cursor != analytics_state.history_cursor {
+        analytics_state.history_cursor = cursor;
+    }
+
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        analytics_state.history_metric = analytics_state.history_metric.toggle();
+    }
+
+    if keyboard.just_pressed(KeyCode::Enter) {
+        analytics_state.selected_day = Some(analytics_state.history_cursor);
+        analytics_state.current_view = AnalyticsView::Sessions;
     }
 }
 
@@ -699,9 +3281,16 @@ fn update_analytics(
 fn enter_beatmap_editor(
     mut editor_state: ResMut<EditorState>,
     mut editor_ui: ResMut<EditorUIState>,
+    beatmap_assets: Res<BeatmapAssets>,
 ) {
     *editor_state = EditorState::new();
     *editor_ui = EditorUIState::default();
+    // The fresh state keeps the path of the beatmap being opened
+    editor_state.current_beatmap_path = beatmap_assets.current_beatmap.clone();
+    if let Err(e) = editor_state.load_comments() {
+        error!("{}", e);
+        editor_ui.show_status(e, 5);
+    }
 }
 
 // ==================== BEATMAP SELECTION STATE ====================
@@ -710,6 +3299,8 @@ fn enter_beatmap_editor(
 pub struct BeatmapSelectionState {
     pub selected_beatmap: Option<String>,
     pub scroll_pos: f32,
+    /// Beatmap under the cursor, whose background is shown behind the list
+    pub hovered: Option<String>,
 }
 
 fn enter_beatmap_selection(
@@ -718,7 +3309,7 @@ fn enter_beatmap_selection(
 ) {
     // Reload beatmaps to get any new ones
     if let Err(e) = beatmap_assets.load_all() {
-        eprintln!("Failed to reload beatmaps: {}", e);
+        error!("Failed to reload beatmaps: {}", e);
     }
     *selection_state = BeatmapSelectionState::default();
 }
@@ -728,6 +3319,7 @@ fn setup_beatmap_selection_ui(
     assets: Res<GameAssets>,
     windows: Query<&Window>,
     beatmap_assets: Res<BeatmapAssets>,
+    library: Res<LibraryScan>,
 ) {
     if let Ok(window) = windows.get_single() {
         let screen_h = window.height();
@@ -741,7 +3333,7 @@ fn setup_beatmap_selection_ui(
                 font_size: CYBERPUNK_FONT_SIZE,
                 ..default()
             },
-            TextColor(NEON_PINK.into()),
+            TextColor(NEON_PINK),
             Transform::from_xyz(-screen_w / 2.0 + 20.0, screen_h / 2.0 - screen_h * 0.1, 1.0),
             UiElement,
         ));
@@ -753,12 +3345,17 @@ fn setup_beatmap_selection_ui(
                 screen_h / 2.0 - screen_h * 0.2 - (i as f32) * (SONG_ENTRY_HEIGHT + 20.0);
 
             if let Some(beatmap) = beatmap_assets.get(path) {
-                let display_name = format!(
+                let mut display_name = format!(
                     "{} - {} [{}]",
                     beatmap.metadata.artist,
                     beatmap.metadata.title,
                     beatmap.metadata.version
                 );
+                // Broken maps stay listed so they can be opened and fixed
+                let tags = library.health.tags_for(path);
+                if let Some(tags) = &tags {
+                    display_name.push_str(&format!(" ({})", tags));
+                }
 
                 commands.spawn((
                     Text2d::new(display_name),
@@ -767,18 +3364,67 @@ fn setup_beatmap_selection_ui(
                         font_size: CYBERPUNK_FONT_SIZE,
                         ..default()
                     },
-                    TextColor(Color::WHITE.into()),
+                    TextColor(if tags.is_some() {
+                        NEON_ORANGE
+                    } else {
+                        Color::WHITE
+                    }),
                     Transform::from_xyz(-screen_w / 2.0 + 50.0, button_y, 1.0),
                     UiElement,
                     BeatmapButton {
                         path: (*path).clone(),
                     },
                 ));
+
+                // Difficulty: stars and counts, plus a radar chart for hand-made maps
+                let summary = beatmap_assets.difficulty(path);
+                commands.spawn((
+                    Text2d::new(difficulty_summary_text(beatmap, summary)),
+                    TextFont {
+                        font: assets.cyberpunk_font.clone(),
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(NEON_YELLOW),
+                    Transform::from_xyz(screen_w / 2.0 - 300.0, button_y, 1.0),
+                    UiElement,
+                ));
+                if let Some(metrics) = summary.as_ref().and_then(|s| s.metrics()) {
+                    spawn_difficulty_chart(
+                        &mut commands,
+                        &assets,
+                        Vec2::new(screen_w / 2.0 - 80.0, button_y),
+                        DIFFICULTY_CHART_RADIUS,
+                        metrics,
+                    );
+                }
             }
         }
 
+        // Files that failed to parse can't be opened, but are listed as broken
+        let unparsed: Vec<_> = library.health.unparsed().collect();
+        for (i, issue) in unparsed.iter().enumerate() {
+            let row = (paths.len() + i) as f32;
+            commands.spawn((
+                Text2d::new(issue.label()),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(NEON_ORANGE),
+                Transform::from_xyz(
+                    -screen_w / 2.0 + 50.0,
+                    screen_h / 2.0 - screen_h * 0.2 - row * (SONG_ENTRY_HEIGHT + 20.0),
+                    1.0,
+                ),
+                UiElement,
+            ));
+        }
+
         // Create new beatmap button
-        let new_y = screen_h / 2.0 - screen_h * 0.2 - (paths.len() as f32) * (SONG_ENTRY_HEIGHT + 20.0) - 30.0;
+        let rows = (paths.len() + unparsed.len()) as f32;
+        let new_y = screen_h / 2.0 - screen_h * 0.2 - rows * (SONG_ENTRY_HEIGHT + 20.0) - 30.0;
         commands.spawn((
             Text2d::new("+ Create New Beatmap"),
             TextFont {
@@ -786,7 +3432,7 @@ fn setup_beatmap_selection_ui(
                 font_size: CYBERPUNK_FONT_SIZE,
                 ..default()
             },
-            TextColor(NEON_GREEN.into()),
+            TextColor(NEON_GREEN),
             Transform::from_xyz(-screen_w / 2.0 + 50.0, new_y, 1.0),
             UiElement,
             CreateBeatmapButton,
@@ -800,7 +3446,7 @@ fn setup_beatmap_selection_ui(
                 font_size: 16.0,
                 ..default()
             },
-            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5).into()),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
             Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
             UiElement,
         ));
@@ -815,14 +3461,17 @@ pub struct BeatmapButton {
 #[derive(Component)]
 pub struct CreateBeatmapButton;
 
+#[allow(clippy::too_many_arguments)]
 fn handle_beatmap_selection(
     mut next_state: ResMut<NextState<AppState>>,
     mut editor_state: ResMut<EditorState>,
+    mut selection_state: ResMut<BeatmapSelectionState>,
     mut beatmap_assets: ResMut<BeatmapAssets>,
     buttons: Query<(&Transform, &BeatmapButton), With<Text2d>>,
     create_buttons: Query<&Transform, (With<CreateBeatmapButton>, With<Text2d>)>,
     windows: Query<&Window>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    config: Res<GameConfig>,
 ) {
     if let Ok(window) = windows.get_single() {
         if let Some(cursor_pos) = window.cursor_position() {
@@ -830,6 +3479,7 @@ fn handle_beatmap_selection(
             let world_y = window.height() / 2.0 - cursor_pos.y;
 
             // Check beatmap buttons
+            let mut hovered = None;
             for (transform, button) in buttons.iter() {
                 let rect = Rect::from_center_size(
                     transform.translation.truncate(),
@@ -837,6 +3487,7 @@ fn handle_beatmap_selection(
                 );
 
                 if rect.contains(Vec2::new(world_x, world_y)) {
+                    hovered = Some(button.path.clone());
                     if mouse_input.just_pressed(MouseButton::Left) {
                         // Set current beatmap and enter editor
                         beatmap_assets.set_current(Some(button.path.clone()));
@@ -846,6 +3497,10 @@ fn handle_beatmap_selection(
                 }
             }
 
+            if selection_state.hovered != hovered {
+                selection_state.hovered = hovered;
+            }
+
             // Check create new button
             for transform in create_buttons.iter() {
                 let rect = Rect::from_center_size(
@@ -853,25 +3508,26 @@ fn handle_beatmap_selection(
                     Vec2::new(300.0, SONG_ENTRY_HEIGHT),
                 );
 
-                if rect.contains(Vec2::new(world_x, world_y)) {
-                    if mouse_input.just_pressed(MouseButton::Left) {
-                        // Create a new beatmap with default values
-                        use crate::beatmap::Beatmap;
-                        let timestamp = std::time::SystemTime::now()
-                            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs();
-                        let new_path = format!("src/assets/beatmaps/new_beatmap_{}.json", timestamp);
-                        let new_beatmap = Beatmap::new(
-                            "New Song".to_string(),
-                            "Unknown Artist".to_string(),
-                            "src/assets/music/".to_string(),
-                        );
-                        beatmap_assets.add(new_path.clone(), new_beatmap);
-                        beatmap_assets.set_current(Some(new_path.clone()));
-                        editor_state.current_beatmap_path = Some(new_path);
-                        next_state.set(AppState::BeatmapEditor);
-                    }
+                if rect.contains(Vec2::new(world_x, world_y))
+                    && mouse_input.just_pressed(MouseButton::Left)
+                {
+                    // Create a new beatmap with default values
+                    use yum_osu::beatmap::Beatmap;
+                    let timestamp = std::time::SystemTime::now()
+                        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs();
+                    let new_path = format!("src/assets/beatmaps/new_beatmap_{}.json", timestamp);
+                    let mut new_beatmap = Beatmap::new(
+                        "New Song".to_string(),
+                        "Unknown Artist".to_string(),
+                        "src/assets/music/".to_string(),
+                    );
+                    new_beatmap.metadata.creator = config.creator_name();
+                    beatmap_assets.add(new_path.clone(), new_beatmap);
+                    beatmap_assets.set_current(Some(new_path.clone()));
+                    editor_state.current_beatmap_path = Some(new_path);
+                    next_state.set(AppState::BeatmapEditor);
                 }
             }
         }
@@ -887,23 +3543,123 @@ fn update_beatmap_selection(
     }
 }
 
-// ==================== RENDERING SYSTEMS ====================
+/// Marker for the dimmed background behind the beatmap list
+#[derive(Component)]
+struct BeatmapBackdrop;
 
-fn render_game_circles(mut commands: Commands, visualizing_data: Res<VisualizingData>) {
-    let base_elapsed = visualizing_data.start_time.elapsed().as_secs_f64();
-    let elapsed = if visualizing_data.state.playback_speed != 1.0 {
-        base_elapsed * visualizing_data.state.playback_speed as f64
-    } else {
-        base_elapsed
+/// Show the hovered beatmap's background behind the list, the placeholder until it
+/// has loaded
+fn draw_beatmap_backdrop(
+    mut commands: Commands,
+    mut asset_manager: ResMut<AssetManager>,
+    asset_server: Res<AssetServer>,
+    selection_state: Res<BeatmapSelectionState>,
+    beatmap_assets: Res<BeatmapAssets>,
+    windows: Query<&Window>,
+    existing: Query<Entity, With<BeatmapBackdrop>>,
+) {
+    if !selection_state.is_changed() && !asset_manager.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(background) = selection_state
+        .hovered
+        .as_ref()
+        .and_then(|path| beatmap_assets.get(path))
+        .and_then(|beatmap| beatmap.background_path.clone())
+        .filter(|path| !path.is_empty())
+    else {
+        return;
     };
+    // Looking the image up only reorders the cache, which isn't worth a redraw
+    let image = asset_manager
+        .bypass_change_detection()
+        .song_image(&background, &asset_server);
+    commands.spawn((
+        Sprite {
+            image,
+            color: Color::srgba(1.0, 1.0, 1.0, 0.2),
+            custom_size: Some(Vec2::new(window.width(), window.height())),
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, 0.0),
+        UiElement,
+        BeatmapBackdrop,
+    ));
+}
+
+// ==================== RENDERING SYSTEMS ====================
+
+fn render_game_circles(
+    mut commands: Commands,
+    visualizing_data: Res<VisualizingData>,
+    windows: Query<&Window>,
+    assets: Res<GameAssets>,
+    gates: Res<RenderGates>,
+    config: Res<GameConfig>,
+) {
+    let elapsed = visualizing_data
+        .clock
+        .render_seconds(visualizing_data.song_clock.now(), config.audio.offset_ms());
+    let kiai = visualizing_data.state.kiai_intensity(elapsed);
+
+    if gates.kiai_background {
+        if let Ok(window) = windows.get_single() {
+            draw_kiai_background(&mut commands, kiai, Vec2::new(window.width(), window.height()));
+        }
+    }
 
+    let flashlight = visualizing_data.state.flashlight();
+    draw_sliders(
+        &mut commands,
+        &visualizing_data.state.circles,
+        elapsed,
+        SHRINK_TIME,
+        &visualizing_data.state.game_settings,
+        &visualizing_data.state.config.theme,
+        flashlight,
+    );
     draw_circles_bevy(
         &mut commands,
         &visualizing_data.state.circles,
         elapsed,
         SHRINK_TIME,
         &visualizing_data.state.game_settings,
+        &visualizing_data.state.config.theme,
+        kiai,
+        &gates,
+        flashlight,
     );
+
+    // Follow points would show where the next objects are in the dark
+    if gates.follow_points && flashlight.is_none() {
+        draw_follow_points(
+            &mut commands,
+            &visualizing_data.state.follow_points,
+            elapsed,
+            visualizing_data.state.config.theme.reduced_motion,
+        );
+    }
+    if gates.hit_lighting {
+        draw_hit_lights(&mut commands, &visualizing_data.state.hit_lights, elapsed);
+    }
+
+    if let (Some(view), Ok(window)) = (&visualizing_data.analysis, windows.get_single()) {
+        draw_analysis_view(
+            &mut commands,
+            view,
+            &visualizing_data.clock.input_log,
+            elapsed,
+            Vec2::new(window.width(), window.height()),
+            &assets,
+        );
+    }
 }
 
 fn render_game_floating_texts(
@@ -911,12 +3667,7 @@ fn render_game_floating_texts(
     mut visualizing_data: ResMut<VisualizingData>,
     assets: Res<GameAssets>,
 ) {
-    let base_elapsed = visualizing_data.start_time.elapsed().as_secs_f64();
-    let elapsed = if visualizing_data.state.playback_speed != 1.0 {
-        base_elapsed * visualizing_data.state.playback_speed as f64
-    } else {
-        base_elapsed
-    };
+    let elapsed = visualizing_data.song_clock.now();
 
     draw_floating_texts_bevy(
         &mut commands,
@@ -926,73 +3677,91 @@ fn render_game_floating_texts(
     );
 }
 
-fn render_game_score(
+fn render_game_hud(
     mut commands: Commands,
     visualizing_data: Res<VisualizingData>,
     assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    game_state: Res<GameStateResource>,
+    gates: Res<RenderGates>,
+    real_time: Res<Time<Real>>,
 ) {
-    draw_score_bevy(
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    draw_hud(
         &mut commands,
-        visualizing_data.state.score,
-        visualizing_data.state.combo,
-        visualizing_data.state.max_combo,
+        &visualizing_data.state.hud_values(visualizing_data.song_clock.now()),
+        &visualizing_data.state.config.hud_layout,
+        Vec2::new(window.width(), window.height()),
         &assets,
     );
-}
-
-/// Handle key hits with mouse position
-fn handle_key_hits_with_mouse(
-    circles: &mut Vec<structs::GameCircle>,
-    elapsed: f64,
-    vis_state: &mut VisualizingState,
-    shrink_time: f64,
-    config: &GameConfig,
-    mouse_pos: Vec2,
-) {
-    // Find the closest hittable circle
-    let mut best_circle_idx: Option<usize> = None;
-    let mut best_distance = f32::MAX;
-
-    for (idx, circle) in circles.iter().enumerate() {
-        if circle.hit || circle.missed {
-            continue;
-        }
 
-        if let Some(radius) = circle_radius(circle, elapsed, shrink_time) {
-            let distance = mouse_pos.distance(circle.position);
-            if distance < radius && distance < best_distance {
-                best_distance = distance;
-                best_circle_idx = Some(idx);
-            }
-        }
+    let config = &visualizing_data.state.config;
+    if config.theme.show_hit_error_bar && config.hud_layout.get(HudElement::HitErrorBar).visible {
+        // Hits are timed on the judgment clock, which runs the audio offset behind
+        draw_hit_error_bar(
+            &mut commands,
+            &visualizing_data.state.hit_errors,
+            step_seconds(visualizing_data.clock.time_ms),
+            visualizing_data.state.game_settings.timing_window_multiplier(),
+            &config.hud_layout,
+            Vec2::new(window.width(), window.height()),
+            1.0,
+        );
     }
 
-    // Process the hit
-    if let Some(idx) = best_circle_idx {
-        let circle = &mut circles[idx];
-        circle.hit = true;
-
-        let hit_time_diff = (elapsed - circle.hit_time).abs();
-        let points = calculate_score_from_timing(hit_time_diff, &vis_state.game_settings);
+    if !visualizing_data.state.room.is_empty() {
+        let state = &visualizing_data.state;
+        let player = Standing {
+            name: "You".to_string(),
+            score: state.score,
+            accuracy: state.hits.accuracy(),
+            bot: false,
+        };
+        draw_room_scoreboard(
+            &mut commands,
+            &room_standings(player, &state.room),
+            state.config.hud_layout.score_style,
+            Vec2::new(window.width(), window.height()),
+            &assets,
+        );
+    }
 
-        // Record the hit with timing
-        let timing_ms = (hit_time_diff * 1000.0) as f32;
-        vis_state.record_hit(points, timing_ms);
+    if let Some(selected) = visualizing_data.pause_menu {
+        draw_pause_menu(
+            &mut commands,
+            selected,
+            Vec2::new(window.width(), window.height()),
+            &assets,
+        );
+    }
 
-        // Add floating text
-        let (text, color) = match points {
-            300 => ("Perfect!", (0.0, 1.0, 0.5)),
-            100 => ("Good!", (0.0, 0.75, 1.0)),
-            50 => ("Okay", (1.0, 1.0, 0.0)),
-            _ => ("Miss", (1.0, 0.0, 0.0)),
-        };
+    let now = visualizing_data.song_clock.now();
+    if !game_state.multiplayer && visualizing_data.state.intro_skip_target(now).is_some() {
+        draw_intro_skip_prompt(
+            &mut commands,
+            &visualizing_data.state.config.key_bindings.skip_intro,
+            Vec2::new(window.width(), window.height()),
+            &assets,
+        );
+    }
 
-        vis_state.floating_texts.push(FloatingText {
-            text: text.to_string(),
-            position: circle.position,
-            spawn_time: elapsed,
-            duration: 1.0,
-            color,
-        });
+    if visualizing_data.show_debug {
+        let clock = &visualizing_data.song_clock;
+        let lines = [
+            format!("Song time: {:.3} s", clock.now()),
+            format!("Audio drift: {:+.1} ms", clock.drift * 1000.0),
+            format!("Performance: {}", gates.preset.display_name()),
+            visualizing_data.latency.summary_line(),
+            present_mode_line(window, &real_time),
+        ];
+        draw_debug_overlay(
+            &mut commands,
+            &lines,
+            Vec2::new(window.width(), window.height()),
+            &assets,
+        );
     }
 }
@@ -0,0 +1,201 @@
+// src/map_comments.rs
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Extension of the comments sidecar, replacing the beatmap's own: `song.json` keeps its
+/// comments in `song.comments.json`
+pub const COMMENTS_EXTENSION: &str = "comments.json";
+
+/// Longest comment kept, in characters
+pub const MAX_COMMENT_LENGTH: usize = 280;
+
+/// A review note pinned to a song time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapComment {
+    /// Song time the note is about (seconds)
+    pub time: f64,
+    pub author: String,
+    pub text: String,
+    /// Addressed by the mapper; kept listed, dimmed
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+/// Review comments on one beatmap. Stored in a sidecar next to the beatmap rather than
+/// inside it, so they only travel with the map when an export asks for them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapComments {
+    /// Sorted by time
+    pub comments: Vec<MapComment>,
+}
+
+/// Where a beatmap's comments are stored
+pub fn sidecar_path(beatmap_path: &str) -> PathBuf {
+    Path::new(beatmap_path).with_extension(COMMENTS_EXTENSION)
+}
+
+impl MapComments {
+    /// Comments of a beatmap, none when it has no sidecar. A sidecar that can't be
+    /// parsed is renamed to `.corrupt` so saving doesn't overwrite it, and reported
+    pub fn load(beatmap_path: &str) -> Result<Self, String> {
+        let path = sidecar_path(beatmap_path);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Failed to read comments: {}", e)),
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(mut comments) => {
+                comments.comments.sort_by(|a, b| a.time.total_cmp(&b.time));
+                Ok(comments)
+            }
+            Err(e) => {
+                let aside = path.with_extension("json.corrupt");
+                match fs::rename(&path, &aside) {
+                    Ok(()) => Err(format!(
+                        "Comments file is corrupt ({}), moved to {}",
+                        e,
+                        aside.display()
+                    )),
+                    Err(rename) => Err(format!(
+                        "Comments file is corrupt ({}) and could not be moved: {}",
+                        e, rename
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Write the sidecar, or remove it once the last comment is gone
+    pub fn save(&self, beatmap_path: &str) -> Result<(), String> {
+        let path = sidecar_path(beatmap_path);
+        if self.comments.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    Err(format!("Failed to remove comments: {}", e))
+                }
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize comments: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to save comments: {}", e))
+    }
+
+    /// Add a comment in time order, after any at the same time; returns its index
+    pub fn add(&mut self, comment: MapComment) -> usize {
+        let index = self
+            .comments
+            .partition_point(|other| other.time <= comment.time);
+        self.comments.insert(index, comment);
+        index
+    }
+
+    /// Remove a comment
+    pub fn remove(&mut self, index: usize) -> Option<MapComment> {
+        (index < self.comments.len()).then(|| self.comments.remove(index))
+    }
+
+    /// Flip a comment between open and resolved; returns whether it is now resolved
+    pub fn toggle_resolved(&mut self, index: usize) -> Option<bool> {
+        let comment = self.comments.get_mut(index)?;
+        comment.resolved = !comment.resolved;
+        Some(comment.resolved)
+    }
+
+    /// Comments not yet resolved
+    pub fn open_count(&self) -> usize {
+        self.comments
+            .iter()
+            .filter(|comment| !comment.resolved)
+            .count()
+    }
+
+    /// Comment whose marker is closest to `time`, within `tolerance` seconds
+    pub fn marker_at(&self, time: f64, tolerance: f64) -> Option<usize> {
+        self.comments
+            .iter()
+            .enumerate()
+            .map(|(index, comment)| (index, (comment.time - time).abs()))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn comment(time: f64, text: &str) -> MapComment {
+        MapComment {
+            time,
+            author: "reviewer".to_string(),
+            text: text.to_string(),
+            resolved: false,
+        }
+    }
+
+    /// A beatmap path in a folder of its own, with no sidecar yet
+    fn beatmap_path(test: &str) -> String {
+        scratch_dir(&format!("comments-{}", test))
+            .join("song.json")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn comments_round_trip_through_the_sidecar() {
+        let path = beatmap_path("round-trip");
+        assert_eq!(
+            sidecar_path(&path),
+            Path::new(&path).with_file_name("song.comments.json")
+        );
+        assert_eq!(MapComments::load(&path).unwrap(), MapComments::default());
+
+        let mut comments = MapComments::default();
+        assert_eq!(comments.add(comment(102.0, "this jump is brutal")), 0);
+        assert_eq!(comments.add(comment(30.0, "quiet intro")), 0);
+        assert_eq!(comments.add(comment(30.0, "second note")), 1);
+        assert_eq!(comments.toggle_resolved(0), Some(true));
+        assert_eq!(comments.open_count(), 2);
+        comments.save(&path).unwrap();
+        assert_eq!(MapComments::load(&path).unwrap(), comments);
+
+        // Deleting the last comment removes the sidecar
+        while comments.remove(0).is_some() {}
+        comments.save(&path).unwrap();
+        assert!(!sidecar_path(&path).exists());
+        assert!(comments.save(&path).is_ok());
+    }
+
+    #[test]
+    fn a_corrupt_sidecar_is_moved_aside() {
+        let path = beatmap_path("corrupt");
+        fs::write(sidecar_path(&path), "{ not json").unwrap();
+
+        let error = MapComments::load(&path).unwrap_err();
+        assert!(error.starts_with("Comments file is corrupt"), "{}", error);
+        assert!(!sidecar_path(&path).exists());
+        assert!(sidecar_path(&path).with_extension("json.corrupt").exists());
+        // and the next load starts fresh
+        assert_eq!(MapComments::load(&path).unwrap(), MapComments::default());
+    }
+
+    #[test]
+    fn markers_pick_the_closest_comment_in_reach() {
+        let mut comments = MapComments::default();
+        comments.add(comment(10.0, "a"));
+        comments.add(comment(10.5, "b"));
+        comments.add(comment(20.0, "c"));
+        assert_eq!(comments.marker_at(10.2, 0.5), Some(0));
+        assert_eq!(comments.marker_at(10.3, 0.5), Some(1));
+        assert_eq!(comments.marker_at(19.6, 0.5), Some(2));
+        assert_eq!(comments.marker_at(15.0, 0.5), None);
+    }
+}
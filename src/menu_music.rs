@@ -0,0 +1,384 @@
+// src/menu_music.rs
+
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rodio::{Sink, Source};
+
+use crate::audio::{crossfade_gains, song_clip};
+use crate::beatmap::BeatmapAssets;
+use crate::config::GameConfig;
+use crate::structs::{EndData, FailedData, GameStateResource, SongSelectionState};
+use crate::ui::load_songs_from_assets;
+use crate::AppState;
+
+/// Share of the music volume songs play at outside gameplay, so they sit under the menus
+pub const MENU_MUSIC_LEVEL: f32 = 0.35;
+
+/// Seconds a crossfade from one song to the next takes
+pub const CROSSFADE_SECONDS: f32 = 1.5;
+
+/// Length of the sections looped on the results and song selection screens (seconds)
+pub const MUSIC_LOOP_SECONDS: f64 = 20.0;
+
+/// Where songs whose beatmaps set no preview point are previewed from (seconds)
+const DEFAULT_PREVIEW_TIME: f64 = 30.0;
+
+/// Seconds a song has to stay hovered before its preview takes over
+const HOVER_PREVIEW_DELAY: f32 = 0.4;
+
+/// A section of a song played outside gameplay
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicTrack {
+    pub path: String,
+    /// Song time the section starts at (seconds)
+    pub start: f64,
+    /// Section length; None plays to the end of the song
+    pub length: Option<f64>,
+    /// Repeat the section until something else plays
+    pub looped: bool,
+}
+
+impl MusicTrack {
+    /// A song's preview section, looped
+    pub fn preview(path: &str, beatmaps: &BeatmapAssets) -> Self {
+        Self {
+            path: path.to_string(),
+            start: beatmaps
+                .preview_time_for_song(path)
+                .unwrap_or(DEFAULT_PREVIEW_TIME),
+            length: Some(MUSIC_LOOP_SECONDS),
+            looped: true,
+        }
+    }
+
+    /// A song's preview section played once, for the main menu rotation
+    pub fn rotation(path: &str, beatmaps: &BeatmapAssets) -> Self {
+        Self {
+            looped: false,
+            ..Self::preview(path, beatmaps)
+        }
+    }
+
+    /// The rest of a song from `time`, for a play that ended before the song did
+    pub fn tail(path: &str, time: f64) -> Self {
+        Self {
+            path: path.to_string(),
+            start: time,
+            length: None,
+            looped: false,
+        }
+    }
+
+    /// The section leading up to `end`, looped, for a play that reached the end
+    pub fn ending(path: &str, end: f64) -> Self {
+        Self {
+            path: path.to_string(),
+            start: (end - MUSIC_LOOP_SECONDS).max(0.0),
+            length: Some(MUSIC_LOOP_SECONDS.min(end)),
+            looped: true,
+        }
+    }
+}
+
+/// Songs played softly on the screens around gameplay. Two sinks, so one song can fade
+/// out while the next fades in
+#[derive(Resource)]
+pub struct MenuMusic {
+    sinks: [Sink; 2],
+    /// Sink playing `track`; the other is fading out or silent
+    current: usize,
+    track: Option<MusicTrack>,
+    /// Seconds since the crossfade started; `CROSSFADE_SECONDS` or more once settled
+    faded_for: f32,
+    /// Gain the outgoing sink had when the crossfade started
+    outgoing_from: f32,
+    /// Songs left in the main menu rotation, next last
+    rotation: Vec<String>,
+    /// Song hovered in song selection and for how long (seconds)
+    hovered: Option<String>,
+    hovered_for: f32,
+}
+
+impl MenuMusic {
+    pub fn new(sinks: [Sink; 2]) -> Self {
+        Self {
+            sinks,
+            current: 0,
+            track: None,
+            faded_for: CROSSFADE_SECONDS,
+            outgoing_from: 0.0,
+            rotation: Vec::new(),
+            hovered: None,
+            hovered_for: 0.0,
+        }
+    }
+
+    /// Crossfade to a track; nothing changes if it is already playing
+    pub fn play(&mut self, track: MusicTrack) {
+        if self.track.as_ref() != Some(&track) {
+            self.switch(Some(track));
+        }
+    }
+
+    /// Fade out whatever is playing
+    pub fn fade_out(&mut self) {
+        if self.track.is_some() {
+            self.switch(None);
+        }
+    }
+
+    /// Silence both sinks at once
+    pub fn stop(&mut self) {
+        for sink in &self.sinks {
+            sink.stop();
+        }
+        self.track = None;
+        self.faded_for = CROSSFADE_SECONDS;
+        self.outgoing_from = 0.0;
+    }
+
+    /// Whether the current track has played to its end
+    pub fn finished(&self) -> bool {
+        self.track.is_some() && self.sinks[self.current].empty()
+    }
+
+    /// Gains of the outgoing and current sink
+    fn gains(&self) -> (f32, f32) {
+        let (outgoing, incoming) = crossfade_envelope(self.faded_for, self.outgoing_from);
+        let current = if self.track.is_some() { incoming } else { 0.0 };
+        (outgoing, current)
+    }
+
+    fn switch(&mut self, track: Option<MusicTrack>) {
+        // The louder sink fades out from where it is; the quieter one is cut and reused
+        let (outgoing, current) = self.gains();
+        if current >= outgoing {
+            self.current = 1 - self.current;
+            self.outgoing_from = current;
+        } else {
+            self.outgoing_from = outgoing;
+        }
+        self.faded_for = 0.0;
+        self.track = None;
+
+        let sink = &self.sinks[self.current];
+        sink.stop();
+        let Some(track) = track else {
+            return;
+        };
+        match song_clip(&track.path, track.start, track.length) {
+            Ok(clip) => {
+                sink.set_volume(0.0);
+                if track.looped {
+                    sink.append(clip.buffered().repeat_infinite());
+                } else {
+                    sink.append(clip);
+                }
+                sink.play();
+                self.track = Some(track);
+            }
+            Err(e) => error!("Failed to play menu music: {}", e),
+        }
+    }
+
+    /// Move the crossfade on and set both sinks' volume; `volume` is a settled track's
+    fn update(&mut self, delta: f32, volume: f32) {
+        if self.faded_for < CROSSFADE_SECONDS {
+            self.faded_for += delta;
+            if self.faded_for >= CROSSFADE_SECONDS {
+                self.sinks[1 - self.current].stop();
+                self.outgoing_from = 0.0;
+            }
+        }
+        let (outgoing, current) = self.gains();
+        self.sinks[1 - self.current].set_volume(outgoing * volume);
+        self.sinks[self.current].set_volume(current * volume);
+    }
+
+    /// Next song of the main menu rotation, reshuffling the library once it runs out
+    fn next_rotation_song(&mut self) -> Option<String> {
+        if self.rotation.is_empty() {
+            self.rotation = load_songs_from_assets();
+            self.rotation.shuffle(&mut rand::thread_rng());
+            // Don't start the new round on the song that just played
+            if self.rotation.len() > 1
+                && self.rotation.last().map(String::as_str)
+                    == self.track.as_ref().map(|track| track.path.as_str())
+            {
+                let last = self.rotation.len() - 1;
+                self.rotation.swap(0, last);
+            }
+        }
+        self.rotation.pop()
+    }
+}
+
+/// Gains of the outgoing and incoming sink `elapsed` seconds into a crossfade whose
+/// outgoing sink started at `outgoing_from`
+pub fn crossfade_envelope(elapsed: f32, outgoing_from: f32) -> (f32, f32) {
+    let (outgoing, incoming) = crossfade_gains(elapsed / CROSSFADE_SECONDS);
+    (outgoing_from * outgoing, incoming)
+}
+
+/// Volume a settled menu song plays at
+pub fn menu_music_volume(config: &GameConfig) -> f32 {
+    config.effective_music_volume() * MENU_MUSIC_LEVEL
+}
+
+/// Fade menu music at the music volume, and on the main menu move the rotation on when
+/// nothing else is playing
+pub fn update_menu_music(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    state: Res<State<AppState>>,
+    beatmaps: Res<BeatmapAssets>,
+    mut music: ResMut<MenuMusic>,
+) {
+    music.update(time.delta_secs(), menu_music_volume(&config));
+
+    let idle = music.track.is_none() || music.finished();
+    if *state.get() == AppState::Menu && config.audio.menu_music && idle {
+        if let Some(path) = music.next_rotation_song() {
+            music.play(MusicTrack::rotation(&path, &beatmaps));
+        }
+    }
+}
+
+/// Keep the song just played going softly under the results, its last stretch looped
+pub fn play_results_music(end_data: Res<EndData>, mut music: ResMut<MenuMusic>) {
+    let end = &end_data.state;
+    music.play(MusicTrack::ending(&end.song_name, end.ended_at));
+}
+
+/// Keep the rest of a failed song going softly under the failed screen
+pub fn play_failed_music(failed: Res<FailedData>, mut music: ResMut<MenuMusic>) {
+    music.play(MusicTrack::tail(&failed.song_name, failed.ended_at));
+}
+
+/// Loop the last played song's preview on song selection until a hovered song's
+/// preview takes over
+pub fn play_selection_music(
+    game_state: Res<GameStateResource>,
+    beatmaps: Res<BeatmapAssets>,
+    mut music: ResMut<MenuMusic>,
+) {
+    music.hovered = None;
+    if !game_state.selected_song.is_empty() {
+        music.play(MusicTrack::preview(&game_state.selected_song, &beatmaps));
+    }
+}
+
+/// Crossfade to a song's preview once it has been hovered for a moment
+pub fn preview_hovered_song(
+    time: Res<Time>,
+    selection_state: Res<SongSelectionState>,
+    beatmaps: Res<BeatmapAssets>,
+    mut music: ResMut<MenuMusic>,
+) {
+    if music.hovered != selection_state.selected_song {
+        music.hovered = selection_state.selected_song.clone();
+        music.hovered_for = 0.0;
+        return;
+    }
+    let Some(path) = music.hovered.clone() else {
+        return;
+    };
+    music.hovered_for += time.delta_secs();
+    if music.hovered_for >= HOVER_PREVIEW_DELAY {
+        music.play(MusicTrack::preview(&path, &beatmaps));
+    }
+}
+
+/// Fade out while a song loads, so its countdown starts from silence
+pub fn fade_out_menu_music(mut music: ResMut<MenuMusic>) {
+    music.fade_out();
+}
+
+/// Cut menu music entirely, for screens that play audio of their own
+pub fn stop_menu_music(mut music: ResMut<MenuMusic>) {
+    music.stop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f32, expected: f32) -> bool {
+        (actual - expected).abs() < 1e-4
+    }
+
+    /// Menu music on idle sinks, settled on a track that is already playing
+    fn settled_music() -> MenuMusic {
+        let mut music = MenuMusic::new([Sink::new_idle().0, Sink::new_idle().0]);
+        music.track = Some(MusicTrack::tail("song.mp3", 0.0));
+        music
+    }
+
+    fn volumes(music: &MenuMusic) -> (f32, f32) {
+        (
+            music.sinks[1 - music.current].volume(),
+            music.sinks[music.current].volume(),
+        )
+    }
+
+    #[test]
+    fn crossfade_fades_out_from_its_start_gain_and_fades_in_to_full() {
+        assert_eq!(crossfade_envelope(0.0, 0.8), (0.8, 0.0));
+        let (outgoing, incoming) = crossfade_envelope(CROSSFADE_SECONDS / 2.0, 1.0);
+        assert!(close(outgoing, 0.5f32.sqrt()) && close(incoming, 0.5f32.sqrt()));
+        let (outgoing, incoming) = crossfade_envelope(CROSSFADE_SECONDS, 0.8);
+        assert!(close(outgoing, 0.0) && close(incoming, 1.0));
+        assert_eq!(
+            crossfade_envelope(CROSSFADE_SECONDS * 2.0, 0.8),
+            crossfade_envelope(CROSSFADE_SECONDS, 0.8)
+        );
+
+        let steps: Vec<(f32, f32)> = (0..=10)
+            .map(|i| crossfade_envelope(CROSSFADE_SECONDS * i as f32 / 10.0, 1.0))
+            .collect();
+        for pair in steps.windows(2) {
+            assert!(pair[1].0 < pair[0].0 && pair[1].1 > pair[0].1);
+        }
+        // Equal power: loudness holds steady through the fade
+        for (outgoing, incoming) in steps {
+            assert!(close(outgoing * outgoing + incoming * incoming, 1.0));
+        }
+    }
+
+    #[test]
+    fn volume_follows_master_times_music() {
+        let mut config = GameConfig::default();
+        config.audio.master_volume = 0.5;
+        config.audio.music_volume = 0.6;
+        assert!(close(menu_music_volume(&config), 0.3 * MENU_MUSIC_LEVEL));
+
+        let mut music = settled_music();
+        music.update(0.016, menu_music_volume(&config));
+        assert!(close(volumes(&music).1, 0.3 * MENU_MUSIC_LEVEL));
+
+        config.audio.master_volume = 0.0;
+        music.update(0.016, menu_music_volume(&config));
+        assert_eq!(volumes(&music).1, 0.0);
+    }
+
+    #[test]
+    fn stop_before_the_countdown_cuts_a_fade_out() {
+        let mut music = settled_music();
+        music.update(0.016, 1.0);
+        assert!(close(volumes(&music).1, 1.0));
+
+        // Loading fades the song out; the countdown screen stops it partway through
+        music.fade_out();
+        music.update(CROSSFADE_SECONDS / 3.0, 1.0);
+        let (outgoing, current) = volumes(&music);
+        assert!(outgoing > 0.0 && outgoing < 1.0);
+        assert_eq!(current, 0.0);
+
+        music.stop();
+        music.update(0.016, 1.0);
+        assert_eq!(volumes(&music), (0.0, 0.0));
+        music.update(CROSSFADE_SECONDS, 1.0);
+        assert_eq!(volumes(&music), (0.0, 0.0));
+        assert!(music.track.is_none() && !music.finished());
+    }
+}
@@ -0,0 +1,229 @@
+// src/metronome.rs
+
+use bevy::prelude::*;
+
+use crate::beatmap::Hitsound;
+use crate::config::{GameConfig, MetronomeMode};
+use crate::constants::NEON_CYAN;
+use crate::editor_audio::TickScheduler;
+use crate::structs::{GameAudioSink, GameStateResource, VisualizingData};
+use crate::ui::UiElement;
+
+/// Tempo clicked when too few beats were detected to fit one (BPM)
+pub const METRONOME_FALLBACK_BPM: f64 = 120.0;
+
+/// Beats per bar; the first click of each bar is pitched higher
+const BEATS_PER_BAR: i64 = 4;
+
+/// Shortest and longest beat the fitted tempo is folded into (seconds, 60 - 200 BPM)
+const BEAT_RANGE: (f64, f64) = (0.3, 1.0);
+
+/// Song time moving back, or further than this between frames, is a seek such as a
+/// loop wrap or an intro skip
+const SEEK_THRESHOLD: f64 = 0.5;
+
+/// How long the edge pulse takes to fade after a click (seconds)
+const PULSE_SECONDS: f64 = 0.15;
+
+/// Thickness of the edge pulse (pixels)
+const PULSE_WIDTH: f32 = 6.0;
+
+/// Steady beat fitted to a song's detected beats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeatGrid {
+    /// Seconds of song time between clicks
+    pub period: f64,
+    /// Song time of one click; the others are whole periods away
+    pub offset: f64,
+}
+
+impl BeatGrid {
+    /// Fit a steady beat to detected beats: their median gap folded into `BEAT_RANGE`,
+    /// phased by the circular mean of where each beat falls within it. With too few
+    /// beats to go on it clicks `METRONOME_FALLBACK_BPM` from the first one
+    pub fn fit(beats: &[f64]) -> Self {
+        let mut gaps: Vec<f64> = beats
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .filter(|gap| *gap > 0.0)
+            .collect();
+        if gaps.len() < 2 {
+            return Self {
+                period: 60.0 / METRONOME_FALLBACK_BPM,
+                offset: beats.first().copied().unwrap_or(0.0),
+            };
+        }
+        gaps.sort_by(|a, b| a.total_cmp(b));
+        let mut period = gaps[gaps.len() / 2];
+        while period < BEAT_RANGE.0 {
+            period *= 2.0;
+        }
+        while period > BEAT_RANGE.1 {
+            period /= 2.0;
+        }
+
+        let tau = std::f64::consts::TAU;
+        let (sin, cos) = beats.iter().fold((0.0, 0.0), |(sin, cos), beat| {
+            let angle = tau * (beat / period).fract();
+            (sin + angle.sin(), cos + angle.cos())
+        });
+        let offset = f64::atan2(sin, cos).rem_euclid(tau) / tau * period;
+        Self { period, offset }
+    }
+
+    /// Clicks after `from` up to `until`, accenting the first of each bar
+    pub fn clicks_between(&self, from: f64, until: f64) -> impl Iterator<Item = (f64, Hitsound)> {
+        let grid = *self;
+        let first = ((from - grid.offset) / grid.period).floor() as i64 + 1;
+        let last = ((until - grid.offset) / grid.period).floor() as i64;
+        (first..=last).map(move |beat| {
+            let hitsound = if beat.rem_euclid(BEATS_PER_BAR) == 0 {
+                Hitsound::Whistle
+            } else {
+                Hitsound::Normal
+            };
+            (grid.offset + beat as f64 * grid.period, hitsound)
+        })
+    }
+
+    /// Song time since the last click at `time`
+    pub fn since_click(&self, time: f64) -> f64 {
+        (time - self.offset).rem_euclid(self.period)
+    }
+}
+
+/// Practice metronome of the play in progress
+#[derive(Debug, Default, Resource)]
+pub struct Metronome {
+    /// Fitted on the first frame of a play
+    grid: Option<BeatGrid>,
+    scheduler: TickScheduler,
+    /// Song time of the last frame, to spot seeks
+    last_time: Option<f64>,
+}
+
+/// Start every play with a new grid and nothing queued, at the effects volume
+pub fn enter_metronome(
+    mut metronome: ResMut<Metronome>,
+    audio_sink: Res<GameAudioSink>,
+    config: Res<GameConfig>,
+) {
+    *metronome = Metronome::default();
+    audio_sink.effects.stop();
+    audio_sink
+        .effects
+        .set_volume(config.effective_effects_volume());
+}
+
+/// Silence the clicks still queued and hand the effects sink back at full volume
+pub fn exit_metronome(audio_sink: Res<GameAudioSink>) {
+    audio_sink.effects.stop();
+    audio_sink.effects.set_volume(1.0);
+}
+
+/// Keep clicks queued a little ahead of the song clock. Each click's time comes from
+/// the grid and the clock rather than from frame deltas, so the clicks can't drift
+/// from the song, and the queue is stretched by the playback speed
+pub fn update_metronome(
+    mut metronome: ResMut<Metronome>,
+    visualizing_data: Res<VisualizingData>,
+    audio_sink: Res<GameAudioSink>,
+    config: Res<GameConfig>,
+    game_state: Res<GameStateResource>,
+) {
+    let state = &visualizing_data.state;
+    if state.config.practice.metronome == MetronomeMode::Off {
+        return;
+    }
+    let now = visualizing_data.song_clock.now();
+    let metronome = &mut *metronome;
+    let grid = *metronome
+        .grid
+        .get_or_insert_with(|| BeatGrid::fit(&state.beats));
+
+    // Clicks queued for the time before a seek would land off the beat
+    match metronome.last_time {
+        Some(last) if now < last || now - last > SEEK_THRESHOLD => {
+            audio_sink.effects.stop();
+            metronome.scheduler.reset(now);
+        }
+        None => metronome.scheduler.reset(now),
+        Some(_) => {}
+    }
+    metronome.last_time = Some(now);
+
+    let (speed, _) = game_state.playback(&config.game_settings);
+    let (from, until) = metronome.scheduler.window(now);
+    metronome.scheduler.queue(
+        &audio_sink.effects,
+        grid.clicks_between(from, until),
+        now,
+        speed,
+    );
+}
+
+/// Marker for the four strips of the metronome's edge pulse
+#[derive(Component)]
+pub struct MetronomePulse;
+
+/// Flash the screen edge on each click and fade it over `PULSE_SECONDS`. Hidden while
+/// paused and with reduced motion
+pub fn draw_metronome_pulse(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    metronome: Res<Metronome>,
+    visualizing_data: Res<VisualizingData>,
+    config: Res<GameConfig>,
+    game_state: Res<GameStateResource>,
+    mut existing: Query<&mut Sprite, With<MetronomePulse>>,
+) {
+    let practice = &visualizing_data.state.config.practice;
+    let shown = practice.metronome.pulses()
+        && !config.theme.reduced_motion
+        && visualizing_data.pause_menu.is_none();
+    let alpha = match metronome.grid {
+        Some(grid) if shown => {
+            let (speed, _) = game_state.playback(&config.game_settings);
+            let since = grid.since_click(visualizing_data.song_clock.now());
+            let faded = since / speed.max(0.01) as f64 / PULSE_SECONDS;
+            (1.0 - faded).max(0.0) as f32 * 0.6
+        }
+        _ => 0.0,
+    };
+
+    if !existing.is_empty() {
+        for mut sprite in existing.iter_mut() {
+            sprite.color = NEON_CYAN.with_alpha(alpha);
+        }
+        return;
+    }
+    if alpha <= 0.0 {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let (width, height) = (window.width(), window.height());
+    let across = Vec2::new(width, PULSE_WIDTH);
+    let down = Vec2::new(PULSE_WIDTH, height);
+    let x = (width - PULSE_WIDTH) / 2.0;
+    let y = (height - PULSE_WIDTH) / 2.0;
+    let strips = [
+        (Vec2::new(0.0, y), across),
+        (Vec2::new(0.0, -y), across),
+        (Vec2::new(-x, 0.0), down),
+        (Vec2::new(x, 0.0), down),
+    ];
+    for (center, size) in strips {
+        commands.spawn((
+            Sprite {
+                color: NEON_CYAN.with_alpha(alpha),
+                custom_size: Some(size),
+                ..default()
+            },
+            Transform::from_translation(center.extend(0.95)),
+            UiElement,
+            MetronomePulse,
+        ));
+    }
+}
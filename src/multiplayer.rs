@@ -4,12 +4,18 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 use anyhow::Result;
 
-use crate::network::{NetworkMessage, PlayerInfo, Room};
-use crate::game::Circle;
+use crate::bots::{BotPlayer, BotProfile, BOT_TAG};
+use crate::network::{check_settings, ClientSettings, NetworkMessage, Room, RoomRules};
+use crate::structs::GameCircle;
+use crate::community::ScoringType;
+use crate::analytics::Grade;
+
+/// Seconds members have to vote on a rematch
+pub const REMATCH_VOTE_SECONDS: f64 = 20.0;
 
 /// Multiplayer game state for synchronization
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +28,16 @@ pub struct MultiplayerGameState {
     pub players: HashMap<Uuid, PlayerGameState>,
     pub circles: Vec<CircleSync>,
     pub seed: u64,
+    pub rules: RoomRules,
+    /// People who voted to skip the intro
+    #[serde(default)]
+    pub intro_skip_votes: Vec<Uuid>,
+    /// Whether everyone agreed and the intro was skipped
+    #[serde(default)]
+    pub intro_skipped: bool,
+    /// Tournament match being played, copied from the room
+    #[serde(default)]
+    pub match_id: Option<Uuid>,
 }
 
 /// Individual player's game state
@@ -37,10 +53,42 @@ pub struct PlayerGameState {
     pub hits: HitStats,
     pub rank: u32,
     pub is_finished: bool,
+    /// Digest of the settings accepted at song start
+    pub settings_digest: Option<u64>,
+    /// Result invalidated because settings changed mid-song
+    pub result_invalid: bool,
+    /// Profile of a bot opponent; None for people
+    #[serde(default)]
+    pub bot: Option<BotProfile>,
+}
+
+impl PlayerGameState {
+    /// Name for the live scoreboard and results, tagged for bots
+    pub fn display_name(&self) -> String {
+        match self.bot {
+            Some(_) => format!("{} {}", BOT_TAG, self.username),
+            None => self.username.clone(),
+        }
+    }
+}
+
+impl MultiplayerGameState {
+    /// Results that may be written to leaderboards and analytics: bots never are
+    pub fn recordable_results(&self) -> Vec<&PlayerGameState> {
+        self.players.values().filter(|p| p.bot.is_none()).collect()
+    }
+
+    /// (votes, needed) to skip the intro. Only people vote; bots go along with it, and
+    /// votes from players who left no longer count
+    pub fn intro_skip_tally(&self) -> (usize, usize) {
+        let people = self.recordable_results();
+        let votes = people.iter().filter(|p| self.intro_skip_votes.contains(&p.user_id)).count();
+        (votes, people.len())
+    }
 }
 
 /// Hit statistics for a player
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct HitStats {
     pub perfect: u32,
     pub good: u32,
@@ -48,14 +96,16 @@ pub struct HitStats {
     pub miss: u32,
 }
 
-impl Default for HitStats {
-    fn default() -> Self {
-        Self {
-            perfect: 0,
-            good: 0,
-            ok: 0,
-            miss: 0,
-        }
+impl HitStats {
+    /// Grade of the hits under a room's scoring system
+    pub fn grade(&self, scoring_type: ScoringType) -> Grade {
+        let hits = crate::analytics::HitStats {
+            perfect: self.perfect,
+            good: self.good,
+            okay: self.ok,
+            misses: self.miss,
+        };
+        hits.grade(&scoring_type.grade_rules())
     }
 }
 
@@ -72,6 +122,9 @@ impl Default for PlayerGameState {
             hits: HitStats::default(),
             rank: 1,
             is_finished: false,
+            settings_digest: None,
+            result_invalid: false,
+            bot: None,
         }
     }
 }
@@ -113,12 +166,155 @@ pub enum GameEvent {
     },
 }
 
+/// How a rematch vote ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RematchOutcome {
+    Rematch,
+    Declined,
+}
+
+/// Rematch vote held after a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RematchVote {
+    /// When voting opened
+    pub opened_at: f64,
+    /// Members who may vote; members who leave are dropped along with their vote
+    pub voters: Vec<Uuid>,
+    /// Votes cast so far (true = rematch)
+    pub votes: HashMap<Uuid, bool>,
+}
+
+impl RematchVote {
+    /// Open a vote for the members still in the room
+    pub fn open(voters: Vec<Uuid>, now: f64) -> Self {
+        Self {
+            opened_at: now,
+            voters,
+            votes: HashMap::new(),
+        }
+    }
+
+    /// Record or change a member's vote
+    pub fn cast(&mut self, user_id: Uuid, yes: bool) -> Result<()> {
+        if !self.voters.contains(&user_id) {
+            return Err(anyhow::anyhow!("Not part of this vote"));
+        }
+        self.votes.insert(user_id, yes);
+        Ok(())
+    }
+
+    /// Drop a member who left mid-vote
+    pub fn remove_voter(&mut self, user_id: Uuid) {
+        self.voters.retain(|id| *id != user_id);
+        self.votes.remove(&user_id);
+    }
+
+    /// Seconds left to vote
+    pub fn remaining_seconds(&self, now: f64) -> f64 {
+        (REMATCH_VOTE_SECONDS - (now - self.opened_at)).max(0.0)
+    }
+
+    /// Whether everyone has voted or time is up
+    pub fn is_closed(&self, now: f64) -> bool {
+        self.voters.iter().all(|id| self.votes.contains_key(id)) || self.remaining_seconds(now) <= 0.0
+    }
+
+    /// (yes, no) votes cast
+    pub fn tally(&self) -> (usize, usize) {
+        let yes = self.votes.values().filter(|vote| **vote).count();
+        (yes, self.votes.len() - yes)
+    }
+
+    /// Result once voting closes. Members who didn't vote abstain; a tie goes the
+    /// host's way, or is declined if the host didn't vote
+    pub fn outcome(&self, host_id: Uuid, now: f64) -> Option<RematchOutcome> {
+        if !self.is_closed(now) {
+            return None;
+        }
+        let (yes, no) = self.tally();
+        let rematch = if yes != no {
+            yes > no
+        } else {
+            self.votes.get(&host_id).copied().unwrap_or(false)
+        };
+        Some(if rematch {
+            RematchOutcome::Rematch
+        } else {
+            RematchOutcome::Declined
+        })
+    }
+}
+
+/// Who picks the next song when the room rotates picks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickerRotation {
+    /// Members in join order; members who left stay listed and are skipped
+    pub order: Vec<Uuid>,
+    /// Index into `order` of the member whose turn it is
+    pub current: usize,
+}
+
+impl PickerRotation {
+    /// Rotation starting with the first member to join
+    pub fn new(order: Vec<Uuid>) -> Self {
+        Self { order, current: 0 }
+    }
+
+    /// Add members who joined since the rotation started
+    pub fn sync(&mut self, join_order: &[Uuid]) {
+        for id in join_order {
+            if !self.order.contains(id) {
+                self.order.push(*id);
+            }
+        }
+    }
+
+    /// Index of the first connected member at or after `start`, wrapping around
+    fn next_connected(&self, start: usize, connected: &[Uuid]) -> Option<usize> {
+        (0..self.order.len())
+            .map(|offset| (start + offset) % self.order.len())
+            .find(|idx| connected.contains(&self.order[*idx]))
+    }
+
+    /// Member picking now, skipping anyone who has disconnected
+    pub fn picker(&self, connected: &[Uuid]) -> Option<Uuid> {
+        self.next_connected(self.current, connected)
+            .map(|idx| self.order[idx])
+    }
+
+    /// Pass the pick to the next connected member after the current picker
+    pub fn advance(&mut self, connected: &[Uuid]) {
+        if let Some(idx) = self.next_connected(self.current, connected) {
+            self.current = self
+                .next_connected(idx + 1, connected)
+                .unwrap_or(idx);
+        }
+    }
+}
+
+/// Room state between matches, broadcast with `NetworkMessage::PostMatchUpdate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostMatchState {
+    pub room_id: Uuid,
+    /// Song of the match that just ended, replayed on a rematch
+    pub song_name: String,
+    /// Seed of that match, so a rematch plays the same map
+    pub seed: u64,
+    /// Rules of that match, kept for a rematch
+    pub rules: RoomRules,
+    /// Open rematch vote, if any
+    pub vote: Option<RematchVote>,
+    /// Song pick rotation, when the room rotates picks
+    pub rotation: Option<PickerRotation>,
+}
+
 /// Multiplayer game coordinator
 #[derive(Debug, Clone)]
 pub struct GameCoordinator {
     active_games: Arc<RwLock<HashMap<Uuid, MultiplayerGameState>>>,
     game_rooms: Arc<RwLock<HashMap<Uuid, Uuid>>>, // room_id -> game_id
-    event_channels: Arc<RwLock<HashMap<Uuid, mpsc::UnboundedSender<GameEvent>>>>,
+    post_match: Arc<RwLock<HashMap<Uuid, PostMatchState>>>, // room_id -> state
+    bot_runs: Arc<RwLock<HashMap<Uuid, Vec<BotPlayer>>>>, // game_id -> bots
 }
 
 impl GameCoordinator {
@@ -127,7 +323,8 @@ impl GameCoordinator {
         Self {
             active_games: Arc::new(RwLock::new(HashMap::new())),
             game_rooms: Arc::new(RwLock::new(HashMap::new())),
-            event_channels: Arc::new(RwLock::new(HashMap::new())),
+            post_match: Arc::new(RwLock::new(HashMap::new())),
+            bot_runs: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -148,6 +345,9 @@ impl GameCoordinator {
                 hits: HitStats::default(),
                 rank: player_info.rank,
                 is_finished: false,
+                settings_digest: None,
+                result_invalid: false,
+                bot: player_info.bot,
             });
         }
 
@@ -160,6 +360,10 @@ impl GameCoordinator {
             players,
             circles: Vec::new(),
             seed,
+            rules: room.rules.clone(),
+            intro_skip_votes: Vec::new(),
+            intro_skipped: false,
+            match_id: room.match_id,
         };
 
         self.active_games.write().await.insert(game_id, game_state);
@@ -168,12 +372,57 @@ impl GameCoordinator {
         Ok(game_id)
     }
 
-    /// Start a game
+    /// Accept a client's start handshake, rejecting settings that break the room rules
+    pub async fn submit_start_settings(&self, game_id: Uuid, player_id: Uuid, settings: &ClientSettings) -> Result<()> {
+        let mut games = self.active_games.write().await;
+        let game = games.get_mut(&game_id).ok_or_else(|| anyhow::anyhow!("Game not found"))?;
+        check_settings(&game.rules, settings).map_err(|e| anyhow::anyhow!(e))?;
+
+        let player = game.players.get_mut(&player_id).ok_or_else(|| anyhow::anyhow!("Player not in game"))?;
+        player.settings_digest = Some(settings.digest());
+        Ok(())
+    }
+
+    /// Record a score update, invalidating the result if the settings digest changed
+    pub async fn update_score(&self, game_id: Uuid, player_id: Uuid, score: u32, combo: u32, accuracy: f64, settings_digest: u64) -> Result<()> {
+        {
+            let mut games = self.active_games.write().await;
+            let game = games.get_mut(&game_id).ok_or_else(|| anyhow::anyhow!("Game not found"))?;
+            if let Some(player) = game.players.get_mut(&player_id) {
+                if player.settings_digest != Some(settings_digest) {
+                    player.result_invalid = true;
+                }
+                player.score = score;
+                player.combo = combo;
+                player.max_combo = player.max_combo.max(combo);
+                player.accuracy = accuracy;
+            }
+        }
+        self.update_rankings(game_id).await
+    }
+
+    /// Start a game once every player's settings were accepted. Bots plan their runs
+    /// from the game's circles here, so circles must be added first
     pub async fn start_game(&self, game_id: Uuid, start_time: f64) -> Result<()> {
         let mut games = self.active_games.write().await;
         if let Some(game) = games.get_mut(&game_id) {
+            let pending: Vec<&str> = game
+                .players
+                .values()
+                .filter(|p| p.bot.is_none() && p.settings_digest.is_none())
+                .map(|p| p.username.as_str())
+                .collect();
+            if !pending.is_empty() {
+                return Err(anyhow::anyhow!("Waiting for valid settings from: {}", pending.join(", ")));
+            }
             game.is_active = true;
             game.started_at = Some(start_time);
+
+            let beat_times: Vec<f64> = game.circles.iter().map(|c| c.spawn_time).collect();
+            let bots: Vec<BotPlayer> = game.players.values()
+                .filter_map(|p| p.bot.map(|profile| BotPlayer::new(p.user_id, profile, game.seed, &beat_times)))
+                .collect();
+            self.bot_runs.write().await.insert(game_id, bots);
             Ok(())
         } else {
             Err(anyhow::anyhow!("Game not found"))
@@ -181,7 +430,7 @@ impl GameCoordinator {
     }
 
     /// Add circles to game
-    pub async fn add_circles(&self, game_id: Uuid, circles: Vec<Circle>) -> Result<()> {
+    pub async fn add_circles(&self, game_id: Uuid, circles: Vec<GameCircle>) -> Result<()> {
         let mut games = self.active_games.write().await;
         if let Some(game) = games.get_mut(&game_id) {
             game.circles = circles.iter().enumerate()
@@ -199,6 +448,22 @@ impl GameCoordinator {
         }
     }
 
+    /// Feed the bots' hit and miss events up to a song time into the game, as if their
+    /// clients had sent them. Call on the same tick the server handles client events
+    pub async fn tick_bots(&self, game_id: Uuid, song_time: f64) -> Result<()> {
+        let events: Vec<GameEvent> = {
+            let mut runs = self.bot_runs.write().await;
+            let Some(bots) = runs.get_mut(&game_id) else {
+                return Ok(());
+            };
+            bots.iter_mut().flat_map(|bot| bot.events_until(song_time)).collect()
+        };
+        for event in events {
+            self.process_event(event, game_id).await?;
+        }
+        Ok(())
+    }
+
     /// Process a game event
     pub async fn process_event(&self, event: GameEvent, game_id: Uuid) -> Result<()> {
         match event {
@@ -208,10 +473,10 @@ impl GameCoordinator {
             GameEvent::Miss { player_id, circle_id, timestamp } => {
                 self.handle_miss(game_id, player_id, circle_id, timestamp).await?;
             }
-            GameEvent::ComboBreak { player_id, timestamp } => {
+            GameEvent::ComboBreak { player_id, .. } => {
                 self.handle_combo_break(game_id, player_id).await?;
             }
-            GameEvent::GameFinished { player_id, final_score, final_accuracy, timestamp } => {
+            GameEvent::GameFinished { player_id, final_score, final_accuracy, .. } => {
                 self.handle_game_finished(game_id, player_id, final_score, final_accuracy).await?;
             }
         }
@@ -317,11 +582,23 @@ impl GameCoordinator {
     async fn update_rankings(&self, game_id: Uuid) -> Result<()> {
         let mut games = self.active_games.write().await;
         if let Some(game) = games.get_mut(&game_id) {
-            let mut ranked_players: Vec<_> = game.players.values().collect();
-            ranked_players.sort_by(|a, b| b.score.cmp(&a.score));
+            let scoring_type = game.rules.scoring_type;
+            let mut ranked_players: Vec<(Uuid, bool, u32, f64)> = game
+                .players
+                .values()
+                .map(|p| (p.user_id, p.result_invalid, p.score, p.accuracy))
+                .collect();
 
-            for (idx, player_info) in ranked_players.iter().enumerate() {
-                if let Some(player) = game.players.get_mut(&player_info.user_id) {
+            // Invalid results always rank below valid ones
+            ranked_players.sort_by(|a, b| {
+                a.1.cmp(&b.1).then_with(|| match scoring_type {
+                    ScoringType::Accuracy => b.3.partial_cmp(&a.3).unwrap_or(std::cmp::Ordering::Equal),
+                    ScoringType::ScoreV1 | ScoringType::ScoreV2 => b.2.cmp(&a.2),
+                })
+            });
+
+            for (idx, (user_id, _, _, _)) in ranked_players.iter().enumerate() {
+                if let Some(player) = game.players.get_mut(user_id) {
                     player.rank = (idx + 1) as u32;
                 }
             }
@@ -352,6 +629,7 @@ impl GameCoordinator {
 
     /// End a game and return results
     pub async fn end_game(&self, game_id: Uuid) -> Option<MultiplayerGameState> {
+        self.bot_runs.write().await.remove(&game_id);
         let mut games = self.active_games.write().await;
         if let Some(mut game) = games.remove(&game_id) {
             game.is_active = false;
@@ -363,7 +641,138 @@ impl GameCoordinator {
 
     /// Get game ID from room ID
     pub async fn get_game_id_from_room(&self, room_id: Uuid) -> Option<Uuid> {
-        *self.game_rooms.read().await.get(&room_id)?
+        self.game_rooms.read().await.get(&room_id).copied()
+    }
+
+    /// Open the rematch vote for an ended game. The pick rotation carries over between
+    /// matches; when it starts, the first joiner (who picked this song) has had their turn
+    pub async fn begin_post_match(&self, room: &Room, game: &MultiplayerGameState, now: f64) -> NetworkMessage {
+        let mut states = self.post_match.write().await;
+        let previous = states.remove(&room.room_id);
+
+        let rotation = if room.rules.rotate_picker {
+            let mut rotation = match previous.and_then(|state| state.rotation) {
+                Some(rotation) => rotation,
+                None => {
+                    let mut rotation = PickerRotation::new(room.join_order.clone());
+                    rotation.advance(&room.join_order);
+                    rotation
+                }
+            };
+            rotation.sync(&room.join_order);
+            Some(rotation)
+        } else {
+            None
+        };
+
+        let state = PostMatchState {
+            room_id: room.room_id,
+            song_name: game.song_name.clone(),
+            seed: game.seed,
+            rules: game.rules.clone(),
+            vote: Some(RematchVote::open(room.join_order.clone(), now)),
+            rotation,
+        };
+        states.insert(room.room_id, state.clone());
+        NetworkMessage::PostMatchUpdate { state }
+    }
+
+    /// Record a player's vote to skip the intro. Everyone has to agree: once the last
+    /// person votes the game is marked skipped and every client is told to skip
+    pub async fn vote_skip_intro(&self, game_id: Uuid, user_id: Uuid) -> Result<NetworkMessage> {
+        let mut games = self.active_games.write().await;
+        let game = games.get_mut(&game_id).ok_or_else(|| anyhow::anyhow!("Game not found"))?;
+        if game.intro_skipped {
+            return Err(anyhow::anyhow!("Intro already skipped"));
+        }
+        match game.players.get(&user_id) {
+            Some(player) if player.bot.is_none() => {}
+            _ => return Err(anyhow::anyhow!("Player not in game")),
+        }
+        if !game.intro_skip_votes.contains(&user_id) {
+            game.intro_skip_votes.push(user_id);
+        }
+
+        let (votes, needed) = game.intro_skip_tally();
+        if votes >= needed {
+            game.intro_skipped = true;
+            return Ok(NetworkMessage::IntroSkipped { game_id });
+        }
+        Ok(NetworkMessage::SkipIntroUpdate { game_id, votes, needed })
+    }
+
+    /// Record a member's rematch vote
+    pub async fn cast_rematch_vote(&self, room_id: Uuid, user_id: Uuid, yes: bool) -> Result<NetworkMessage> {
+        let mut states = self.post_match.write().await;
+        let state = states.get_mut(&room_id).ok_or_else(|| anyhow::anyhow!("No match to vote on"))?;
+        let vote = state.vote.as_mut().ok_or_else(|| anyhow::anyhow!("Voting has closed"))?;
+        vote.cast(user_id, yes)?;
+        Ok(NetworkMessage::PostMatchUpdate { state: state.clone() })
+    }
+
+    /// Drop a member who left from the vote; their turn to pick is skipped
+    pub async fn member_left(&self, room_id: Uuid, user_id: Uuid) -> Option<NetworkMessage> {
+        let mut states = self.post_match.write().await;
+        let state = states.get_mut(&room_id)?;
+        if let Some(vote) = state.vote.as_mut() {
+            vote.remove_voter(user_id);
+        }
+        Some(NetworkMessage::PostMatchUpdate { state: state.clone() })
+    }
+
+    /// Close the vote once everyone voted or time ran out. A rematch restarts the same
+    /// song with the same seed and rules; the new game is found with `get_game_id_from_room`
+    pub async fn resolve_rematch(&self, room: &Room, now: f64) -> Result<Option<RematchOutcome>> {
+        let state = {
+            let mut states = self.post_match.write().await;
+            let Some(state) = states.get_mut(&room.room_id) else {
+                return Ok(None);
+            };
+            let Some(outcome) = state.vote.as_ref().and_then(|vote| vote.outcome(room.host_id, now)) else {
+                return Ok(None);
+            };
+            state.vote = None;
+            if outcome == RematchOutcome::Declined {
+                return Ok(Some(outcome));
+            }
+            state.clone()
+        };
+
+        let mut rematch_room = room.clone();
+        rematch_room.rules = state.rules;
+        self.create_game(&rematch_room, state.seed, state.song_name).await?;
+        Ok(Some(RematchOutcome::Rematch))
+    }
+
+    /// Member whose turn it is to pick, if the room rotates picks
+    pub async fn current_picker(&self, room: &Room) -> Option<Uuid> {
+        let states = self.post_match.read().await;
+        states.get(&room.room_id)?.rotation.as_ref()?.picker(&room.join_order)
+    }
+
+    /// Accept the current picker's song and pass the pick on. The song must be one every
+    /// member has, unless no such song exists
+    pub async fn pick_song(&self, room: &mut Room, user_id: Uuid, song_name: String) -> Result<NetworkMessage> {
+        let mut states = self.post_match.write().await;
+        let rotation = states
+            .get_mut(&room.room_id)
+            .and_then(|state| state.rotation.as_mut())
+            .ok_or_else(|| anyhow::anyhow!("This room doesn't rotate the song pick"))?;
+        if rotation.picker(&room.join_order) != Some(user_id) {
+            return Err(anyhow::anyhow!("It's not your turn to pick"));
+        }
+
+        let pool = room.song_pool();
+        if !pool.shared.is_empty() && !pool.shared.contains(&song_name) {
+            return Err(match pool.missing.get(&song_name) {
+                Some(names) => anyhow::anyhow!("Not everyone has that song (missing: {})", names.join(", ")),
+                None => anyhow::anyhow!("No one in the room has that song"),
+            });
+        }
+
+        rotation.advance(&room.join_order);
+        room.song_name = song_name.clone();
+        Ok(NetworkMessage::SongPicked { picker_id: user_id, song_name })
     }
 }
 
@@ -444,3 +853,74 @@ impl MultiplayerClient {
         self.current_accuracy = accuracy;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_host_breaks_a_tied_rematch_vote() {
+        let (host, guest) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut vote = RematchVote::open(vec![host, guest], 0.0);
+        vote.cast(guest, true).unwrap();
+        assert_eq!(vote.outcome(host, 5.0), None);
+        vote.cast(host, false).unwrap();
+        assert_eq!(vote.outcome(host, 5.0), Some(RematchOutcome::Declined));
+        vote.cast(host, true).unwrap();
+        vote.cast(guest, false).unwrap();
+        assert_eq!(vote.outcome(host, 5.0), Some(RematchOutcome::Rematch));
+        assert!(vote.cast(Uuid::new_v4(), true).is_err());
+    }
+
+    #[test]
+    fn a_tie_without_the_host_is_declined_when_time_runs_out() {
+        let host = Uuid::new_v4();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut vote = RematchVote::open(vec![host, a, b], 0.0);
+        vote.cast(a, true).unwrap();
+        vote.cast(b, false).unwrap();
+        assert_eq!(vote.outcome(host, REMATCH_VOTE_SECONDS - 1.0), None);
+        assert_eq!(
+            vote.outcome(host, REMATCH_VOTE_SECONDS),
+            Some(RematchOutcome::Declined)
+        );
+    }
+
+    #[test]
+    fn a_member_leaving_mid_vote_takes_their_vote_along() {
+        let (host, leaver) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut vote = RematchVote::open(vec![host, leaver], 0.0);
+        vote.cast(leaver, false).unwrap();
+        vote.remove_voter(leaver);
+        assert_eq!(vote.tally(), (0, 0));
+        vote.cast(host, true).unwrap();
+        assert_eq!(vote.outcome(host, 1.0), Some(RematchOutcome::Rematch));
+    }
+
+    #[test]
+    fn the_pick_skips_members_who_disconnected() {
+        let members: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut rotation = PickerRotation::new(members.clone());
+        assert_eq!(rotation.picker(&members), Some(members[0]));
+
+        let connected = [members[0], members[2]];
+        rotation.advance(&connected);
+        assert_eq!(rotation.picker(&connected), Some(members[2]));
+        rotation.advance(&connected);
+        assert_eq!(rotation.picker(&connected), Some(members[0]));
+
+        // The current picker leaving passes the pick straight on
+        assert_eq!(rotation.picker(&members[1..]), Some(members[1]));
+        assert_eq!(rotation.picker(&[]), None);
+    }
+
+    #[test]
+    fn members_joining_later_pick_last() {
+        let members: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut rotation = PickerRotation::new(members[..2].to_vec());
+        rotation.sync(&members);
+        rotation.advance(&members);
+        rotation.advance(&members);
+        assert_eq!(rotation.picker(&members), Some(members[2]));
+    }
+}
@@ -2,14 +2,21 @@
 //! Provides WebSocket client/server implementation for real-time gameplay
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
 use tokio::sync::{mpsc, RwLock};
-use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
 use futures_util::{SinkExt, StreamExt};
 use uuid::Uuid;
 use anyhow::Result;
 
+use crate::accounts::{Friend, ProfileRecords};
+use crate::bots::{BotProfile, BOT_TAG};
+use crate::community::{ChatModAction, ChatRoomSettings, CommunityManager, MapRating, MapRatingSummary, Match, ScoringType, UserReport};
+use crate::gamemode::Modifier;
+use crate::hashing::Fnv1a;
+use crate::multiplayer::{GameCoordinator, PostMatchState};
+
 /// Represents different network messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
@@ -29,6 +36,8 @@ pub enum NetworkMessage {
         combo: u32,
         accuracy: f64,
         health: f32,
+        /// Digest of the client's effective settings, see `ClientSettings::digest`
+        settings_digest: u64,
     },
     /// Hit event
     HitEvent {
@@ -43,16 +52,68 @@ pub enum NetworkMessage {
         circle_id: u32,
         timestamp: f64,
     },
+    /// Client's effective settings sent during the song-start handshake
+    StartRequest { player_id: Uuid, settings: ClientSettings },
+    /// Start refused because the client's settings break the room rules
+    StartRejected { player_id: Uuid, reason: String },
+    /// Host changed the room rules
+    RoomRulesUpdate { room_id: Uuid, rules: RoomRules },
     /// Game start signal
     GameStart { seed: u64 },
     /// Game end signal
     GameEnd { winner_id: Uuid, final_scores: HashMap<Uuid, u32> },
     /// Chat message
     Chat { user_id: Uuid, username: String, message: String },
+    /// Host moderates a room's chat
+    ChatModerate { room_id: Uuid, user_id: Uuid, action: ChatModAction },
+    /// A room's chat moderation state changed
+    ChatRoomUpdate { room_id: Uuid, host_id: Option<Uuid>, settings: ChatRoomSettings },
+    /// Message refused by slow mode or a timeout, shown to the sender
+    ChatRejected { room_id: Uuid, reason: String },
     /// Lobby update
-    LobbyUpdate { players: Vec<PlayerInfo> },
+    LobbyUpdate { players: Vec<PlayerInfo>, rules: RoomRules },
     /// Error message
     Error { message: String },
+    /// Report a user to moderators
+    Report { report: UserReport },
+    /// Ask for a friend's song records to compare against
+    CompareRequest { friend_id: Uuid },
+    /// A friend's song records, or a notice that their profile is private
+    CompareResponse { friend_id: Uuid, records: ProfileRecords },
+    /// Songs a member has installed, used to scope the song pick
+    LibraryReport { user_id: Uuid, songs: Vec<String> },
+    /// Rematch vote after a match
+    RematchVote { user_id: Uuid, yes: bool },
+    /// Rematch vote and picker rotation after a match
+    PostMatchUpdate { state: PostMatchState },
+    /// The current picker chose the next song
+    PickSong { user_id: Uuid, song_name: String },
+    /// Next song chosen by the picker
+    SongPicked { picker_id: Uuid, song_name: String },
+    /// Host adds a bot opponent
+    AddBot { user_id: Uuid, profile: BotProfile },
+    /// Host removes a bot opponent
+    RemoveBot { user_id: Uuid, bot_id: Uuid },
+    /// Player votes to skip a long intro
+    SkipIntroVote { game_id: Uuid, user_id: Uuid },
+    /// Intro skip votes so far
+    SkipIntroUpdate { game_id: Uuid, votes: usize, needed: usize },
+    /// Everyone voted; clients skip the intro
+    IntroSkipped { game_id: Uuid },
+    /// Send a friend request by username
+    FriendRequest { target_username: String },
+    /// A friend request arrived while the recipient is online
+    FriendRequestReceived { request: Friend },
+    /// Pending friend requests for the connecting user, sent after auth
+    FriendRequests { pending: Vec<Friend> },
+    /// Answer a pending request; `block` only applies when declining
+    FriendRequestAnswer { requester_id: Uuid, accept: bool, block: bool },
+    /// Rate a downloaded community map; a later rating of the same map replaces it
+    RateMap { rating: MapRating },
+    /// A map's aggregate rating changed
+    MapRatingUpdate { summary: MapRatingSummary },
+    /// A tournament's bracket changed: rounds in order, each in slot order
+    BracketUpdate { tournament_id: Uuid, rounds: Vec<Vec<Match>> },
     /// Heartbeat
     Heartbeat,
 }
@@ -67,6 +128,9 @@ pub struct PlayerInfo {
     pub combo: u32,
     pub accuracy: f64,
     pub rank: u32,
+    /// Profile of a bot opponent; None for people
+    #[serde(default)]
+    pub bot: Option<BotProfile>,
 }
 
 impl Default for PlayerInfo {
@@ -79,22 +143,154 @@ impl Default for PlayerInfo {
             combo: 0,
             accuracy: 0.0,
             rank: 0,
+            bot: None,
+        }
+    }
+}
+
+impl PlayerInfo {
+    /// Name for scoreboards and results, tagged for bots
+    pub fn display_name(&self) -> String {
+        match self.bot {
+            Some(_) => format!("{} {}", BOT_TAG, self.username),
+            None => self.username.clone(),
+        }
+    }
+}
+
+/// Slowest playback speed a room may force
+pub const ROOM_MIN_SPEED: f32 = 0.5;
+/// Fastest playback speed a room may force
+pub const ROOM_MAX_SPEED: f32 = 2.0;
+
+/// Rules the host sets for everyone playing in a room
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomRules {
+    /// Modifiers players may enable
+    pub allowed_modifiers: Vec<Modifier>,
+    /// Playback speed every player must use, if any
+    pub forced_playback_speed: Option<f32>,
+    /// How results are ranked
+    pub scoring_type: ScoringType,
+    /// Pass the next song pick to each member in join order after a match
+    #[serde(default)]
+    pub rotate_picker: bool,
+}
+
+impl Default for RoomRules {
+    fn default() -> Self {
+        Self {
+            allowed_modifiers: Modifier::all()
+                .into_iter()
+                .map(|(m, _)| m)
+                .filter(|m| !matches!(m, Modifier::Auto | Modifier::Relaxed))
+                .collect(),
+            forced_playback_speed: None,
+            scoring_type: ScoringType::ScoreV1,
+            rotate_picker: false,
         }
     }
 }
 
+impl RoomRules {
+    /// Check the rules themselves are playable
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(speed) = self.forced_playback_speed {
+            if !(ROOM_MIN_SPEED..=ROOM_MAX_SPEED).contains(&speed) {
+                return Err(format!(
+                    "Forced speed must be between {:.2}x and {:.2}x",
+                    ROOM_MIN_SPEED, ROOM_MAX_SPEED
+                ));
+            }
+        }
+        if self
+            .allowed_modifiers
+            .iter()
+            .any(|m| matches!(m, Modifier::Auto))
+        {
+            return Err("Auto cannot be allowed in multiplayer".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Effective settings a client plays with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientSettings {
+    /// Active modifiers
+    pub modifiers: Vec<Modifier>,
+    /// Final playback speed after modifiers
+    pub playback_speed: f32,
+}
+
+impl ClientSettings {
+    /// Stable digest of the settings, independent of modifier order
+    pub fn digest(&self) -> u64 {
+        // FNV-1a over the sorted modifier ids and the speed bits
+        let mut ids: Vec<u8> = self.modifiers.iter().map(|m| m.wire_id()).collect();
+        ids.sort_unstable();
+
+        let mut hasher = Fnv1a::new();
+        hasher.write(&ids);
+        hasher.write(&[0xff]);
+        hasher.write(&self.playback_speed.to_bits().to_le_bytes());
+        hasher.finish()
+    }
+}
+
+/// Check a client's settings against the room rules.
+/// Shared by the host UI and the game coordinator so both agree on what is allowed.
+pub fn check_settings(rules: &RoomRules, settings: &ClientSettings) -> Result<(), String> {
+    let forbidden: Vec<&str> = settings
+        .modifiers
+        .iter()
+        .filter(|m| !rules.allowed_modifiers.contains(m))
+        .map(|m| m.display_name())
+        .collect();
+    if !forbidden.is_empty() {
+        return Err(format!(
+            "Disable {} - not allowed in this room",
+            forbidden.join(", ")
+        ));
+    }
+
+    if let Some(forced) = rules.forced_playback_speed {
+        if (settings.playback_speed - forced).abs() > 0.001 {
+            return Err(format!(
+                "This room plays at {:.2}x, your speed is {:.2}x",
+                forced, settings.playback_speed
+            ));
+        }
+    }
+
+    Ok(())
+}
+
 /// WebSocket client for connecting to multiplayer server
 pub struct GameClient {
     sender: mpsc::UnboundedSender<NetworkMessage>,
+    /// Messages waiting to go out, handed to the writer task on connect
+    outgoing: Mutex<Option<mpsc::UnboundedReceiver<NetworkMessage>>>,
+    /// Where the reader task puts what the server sends
+    incoming: mpsc::UnboundedSender<NetworkMessage>,
     receiver: Arc<Mutex<mpsc::UnboundedReceiver<NetworkMessage>>>,
 }
 
+impl Default for GameClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GameClient {
     /// Create a new game client
     pub fn new() -> Self {
-        let (tx, rx) = mpsc::unbounded_channel();
+        let (tx, outgoing) = mpsc::unbounded_channel();
+        let (incoming, rx) = mpsc::unbounded_channel();
         Self {
             sender: tx,
+            outgoing: Mutex::new(Some(outgoing)),
+            incoming,
             receiver: Arc::new(Mutex::new(rx)),
         }
     }
@@ -105,11 +301,17 @@ impl GameClient {
         let (ws_stream, _) = tokio_tungstenite::connect_async(url).await?;
 
         let (mut write, mut read) = ws_stream.split();
-        let sender = self.sender.clone();
+        let mut outgoing = self
+            .outgoing
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Client state poisoned"))?
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Already connected"))?;
+        let incoming = self.incoming.clone();
 
         // Task to send messages to server
         tokio::spawn(async move {
-            while let Some(msg) = sender.recv() {
+            while let Some(msg) = outgoing.recv().await {
                 let json = serde_json::to_string(&msg).unwrap();
                 if write.send(Message::Text(json)).await.is_err() {
                     break;
@@ -123,7 +325,9 @@ impl GameClient {
                 match msg {
                     Ok(Message::Text(text)) => {
                         if let Ok(network_msg) = serde_json::from_str::<NetworkMessage>(&text) {
-                            // TODO: Forward to game state
+                            if incoming.send(network_msg).is_err() {
+                                break;
+                            }
                         }
                     }
                     Ok(Message::Close(_)) => break,
@@ -157,6 +361,22 @@ pub struct Room {
     pub is_game_active: bool,
     pub song_name: String,
     pub max_players: usize,
+    pub rules: RoomRules,
+    /// Members in the order they joined
+    pub join_order: Vec<Uuid>,
+    /// Songs each member reported having
+    pub libraries: HashMap<Uuid, Vec<String>>,
+    /// Tournament match the room was opened for
+    pub match_id: Option<Uuid>,
+}
+
+/// Songs the room can play together
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SongPool {
+    /// Songs every member has
+    pub shared: Vec<String>,
+    /// Songs only some members have, with the usernames missing each
+    pub missing: BTreeMap<String, Vec<String>>,
 }
 
 impl Room {
@@ -171,6 +391,7 @@ impl Room {
             combo: 0,
             accuracy: 0.0,
             rank: 1,
+            bot: None,
         });
 
         Self {
@@ -180,7 +401,31 @@ impl Room {
             is_game_active: false,
             song_name: String::new(),
             max_players,
+            rules: RoomRules::default(),
+            join_order: vec![host_id],
+            libraries: HashMap::new(),
+            match_id: None,
+        }
+    }
+
+    /// Create a head-to-head room for a tournament match; its result feeds the bracket
+    pub fn for_match(host_id: Uuid, host_name: String, match_id: Uuid) -> Self {
+        let mut room = Self::new(host_id, host_name, 2);
+        room.match_id = Some(match_id);
+        room
+    }
+
+    /// Replace the room rules (host only)
+    pub fn set_rules(&mut self, user_id: Uuid, rules: RoomRules) -> Result<()> {
+        if user_id != self.host_id {
+            return Err(anyhow::anyhow!("Only the host can change room rules"));
         }
+        if self.is_game_active {
+            return Err(anyhow::anyhow!("Rules cannot change during a game"));
+        }
+        rules.validate().map_err(|e| anyhow::anyhow!(e))?;
+        self.rules = rules;
+        Ok(())
     }
 
     /// Add a player to the room
@@ -197,14 +442,97 @@ impl Room {
             combo: 0,
             accuracy: 0.0,
             rank: (self.players.len() + 1) as u32,
+            bot: None,
         });
+        self.join_order.retain(|id| *id != user_id);
+        self.join_order.push(user_id);
 
         Ok(())
     }
 
+    /// Add a bot opponent (host only). Bots are always ready and stay out of the join
+    /// order, so they never vote on rematches, pick songs or narrow the song pool
+    pub fn add_bot(&mut self, user_id: Uuid, profile: BotProfile) -> Result<Uuid> {
+        if user_id != self.host_id {
+            return Err(anyhow::anyhow!("Only the host can add bots"));
+        }
+        if self.is_game_active {
+            return Err(anyhow::anyhow!("Bots cannot join during a game"));
+        }
+        if self.players.len() >= self.max_players {
+            return Err(anyhow::anyhow!("Room is full"));
+        }
+
+        let bot_id = Uuid::new_v4();
+        let number = self.players.values().filter(|p| p.bot.is_some()).count() + 1;
+        self.players.insert(bot_id, PlayerInfo {
+            user_id: bot_id,
+            username: format!("{} Bot {}", profile.display_name(), number),
+            is_ready: true,
+            score: 0,
+            combo: 0,
+            accuracy: 0.0,
+            rank: (self.players.len() + 1) as u32,
+            bot: Some(profile),
+        });
+        Ok(bot_id)
+    }
+
+    /// Remove a bot opponent (host only)
+    pub fn remove_bot(&mut self, user_id: Uuid, bot_id: Uuid) -> Result<()> {
+        if user_id != self.host_id {
+            return Err(anyhow::anyhow!("Only the host can remove bots"));
+        }
+        match self.players.get(&bot_id) {
+            Some(player) if player.bot.is_some() => {
+                self.players.remove(&bot_id);
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("No such bot in the room")),
+        }
+    }
+
     /// Remove a player from the room
     pub fn remove_player(&mut self, user_id: Uuid) {
         self.players.remove(&user_id);
+        self.join_order.retain(|id| *id != user_id);
+        self.libraries.remove(&user_id);
+    }
+
+    /// Record the songs a member has
+    pub fn set_library(&mut self, user_id: Uuid, songs: Vec<String>) {
+        if self.players.contains_key(&user_id) {
+            self.libraries.insert(user_id, songs);
+        }
+    }
+
+    /// Intersect the members' libraries; members who haven't reported count as having nothing
+    pub fn song_pool(&self) -> SongPool {
+        let mut owners: BTreeMap<&String, Vec<Uuid>> = BTreeMap::new();
+        for (user_id, songs) in &self.libraries {
+            for song in songs {
+                let song_owners = owners.entry(song).or_default();
+                if !song_owners.contains(user_id) {
+                    song_owners.push(*user_id);
+                }
+            }
+        }
+
+        let mut pool = SongPool::default();
+        for (song, song_owners) in owners {
+            let missing: Vec<String> = self
+                .join_order
+                .iter()
+                .filter(|id| !song_owners.contains(id))
+                .filter_map(|id| self.players.get(id).map(|p| p.username.clone()))
+                .collect();
+            if missing.is_empty() {
+                pool.shared.push(song.clone());
+            } else {
+                pool.missing.insert(song.clone(), missing);
+            }
+        }
+        pool
     }
 
     /// Update player readiness
@@ -235,11 +563,12 @@ impl Room {
             player.accuracy = accuracy;
 
             // Update rankings
-            let mut ranked: Vec<_> = self.players.values().collect();
-            ranked.sort_by(|a, b| b.score.cmp(&a.score));
+            let mut ranked: Vec<(Uuid, u32)> =
+                self.players.values().map(|p| (p.user_id, p.score)).collect();
+            ranked.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
 
-            for (idx, p) in ranked.iter().enumerate() {
-                if let Some(player_mut) = self.players.get_mut(&p.user_id) {
+            for (idx, (user_id, _)) in ranked.iter().enumerate() {
+                if let Some(player_mut) = self.players.get_mut(user_id) {
                     player_mut.rank = (idx + 1) as u32;
                 }
             }
@@ -249,7 +578,7 @@ impl Room {
     /// Get all players sorted by rank
     pub fn get_ranked_players(&self) -> Vec<PlayerInfo> {
         let mut players: Vec<_> = self.players.values().cloned().collect();
-        players.sort_by(|a, b| a.rank.cmp(&b.rank));
+        players.sort_by_key(|a| a.rank);
         players
     }
 }
@@ -263,9 +592,18 @@ pub struct ClientConnection {
 }
 
 /// WebSocket server for multiplayer
+#[derive(Clone)]
 pub struct GameServer {
     clients: Arc<RwLock<HashMap<Uuid, ClientConnection>>>,
     rooms: Arc<RwLock<HashMap<Uuid, Room>>>,
+    coordinator: Arc<GameCoordinator>,
+    community: Arc<CommunityManager>,
+}
+
+impl Default for GameServer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GameServer {
@@ -274,9 +612,23 @@ impl GameServer {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
             rooms: Arc::new(RwLock::new(HashMap::new())),
+            coordinator: Arc::new(GameCoordinator::new()),
+            community: Arc::new(CommunityManager::new()),
         }
     }
 
+    /// Run games through a coordinator shared with the rest of the server
+    pub fn with_coordinator(mut self, coordinator: Arc<GameCoordinator>) -> Self {
+        self.coordinator = coordinator;
+        self
+    }
+
+    /// Keep map ratings in a community manager shared with the rest of the server
+    pub fn with_community(mut self, community: Arc<CommunityManager>) -> Self {
+        self.community = community;
+        self
+    }
+
     /// Start the server
     pub async fn start(&self, addr: &str) -> Result<()> {
         let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -284,6 +636,7 @@ impl GameServer {
 
         while let Ok((stream, addr)) = listener.accept().await {
             println!("New connection from: {}", addr);
+            let server = self.clone();
             let clients = self.clients.clone();
             let rooms = self.rooms.clone();
 
@@ -298,7 +651,7 @@ impl GameServer {
                         Ok(Message::Text(text)) => {
                             if let Ok(network_msg) = serde_json::from_str::<NetworkMessage>(&text) {
                                 match network_msg {
-                                    NetworkMessage::Auth { username, password } => {
+                                    NetworkMessage::Auth { username, .. } => {
                                         // TODO: Implement proper authentication
                                         let new_user_id = Uuid::new_v4();
                                         user_id = Some(new_user_id);
@@ -318,10 +671,70 @@ impl GameServer {
                                         let json = serde_json::to_string(&response)?;
                                         write.send(Message::Text(json)).await?;
                                     }
-                                    NetworkMessage::HitEvent { player_id, circle_id, score, timestamp } => {
+                                    NetworkMessage::HitEvent { .. } => {
                                         // Broadcast hit event to all players in room
                                         // TODO: Implement room-specific broadcasting
                                     }
+                                    NetworkMessage::StartRequest { settings, .. } => {
+                                        // Only the connection's own id, never the one in the payload
+                                        let response = match user_id {
+                                            Some(player_id) => server.handle_start_request(player_id, &settings).await,
+                                            None => Some(NetworkMessage::Error { message: "Not signed in".to_string() }),
+                                        };
+                                        if let Some(response) = response {
+                                            let json = serde_json::to_string(&response)?;
+                                            write.send(Message::Text(json)).await?;
+                                        }
+                                    }
+                                    NetworkMessage::GameStateUpdate { score, combo, accuracy, settings_digest, .. } => {
+                                        if let Some(player_id) = user_id {
+                                            if let Err(e) = server.handle_state_update(player_id, score, combo, accuracy, settings_digest).await {
+                                                let json = serde_json::to_string(&NetworkMessage::Error { message: e.to_string() })?;
+                                                write.send(Message::Text(json)).await?;
+                                            }
+                                        }
+                                    }
+                                    NetworkMessage::RateMap { rating } => {
+                                        let response = match user_id {
+                                            Some(rater_id) => server.handle_rate_map(rater_id, rating),
+                                            None => NetworkMessage::Error { message: "Not signed in".to_string() },
+                                        };
+                                        let json = serde_json::to_string(&response)?;
+                                        write.send(Message::Text(json)).await?;
+                                    }
+                                    NetworkMessage::LibraryReport { user_id, songs } => {
+                                        let room_id = clients.read().await.get(&user_id).and_then(|c| c.room_id);
+                                        if let Some(id) = room_id {
+                                            if let Some(room) = rooms.write().await.get_mut(&id) {
+                                                room.set_library(user_id, songs);
+                                            }
+                                        }
+                                    }
+                                    NetworkMessage::AddBot { user_id, profile } => {
+                                        let room_id = clients.read().await.get(&user_id).and_then(|c| c.room_id);
+                                        let result = match room_id {
+                                            Some(id) => match rooms.write().await.get_mut(&id) {
+                                                Some(room) => room.add_bot(user_id, profile).map(|_| ()),
+                                                None => Err(anyhow::anyhow!("Room not found")),
+                                            },
+                                            None => Err(anyhow::anyhow!("Not in a room")),
+                                        };
+                                        if let Err(e) = result {
+                                            let json = serde_json::to_string(&NetworkMessage::Error { message: e.to_string() })?;
+                                            write.send(Message::Text(json)).await?;
+                                        }
+                                    }
+                                    NetworkMessage::RemoveBot { user_id, bot_id } => {
+                                        let room_id = clients.read().await.get(&user_id).and_then(|c| c.room_id);
+                                        if let Some(id) = room_id {
+                                            if let Some(room) = rooms.write().await.get_mut(&id) {
+                                                if let Err(e) = room.remove_bot(user_id, bot_id) {
+                                                    let json = serde_json::to_string(&NetworkMessage::Error { message: e.to_string() })?;
+                                                    write.send(Message::Text(json)).await?;
+                                                }
+                                            }
+                                        }
+                                    }
                                     NetworkMessage::Chat { user_id, username, message } => {
                                         // Broadcast chat message
                                         let response = NetworkMessage::Chat { user_id, username, message };
@@ -350,6 +763,41 @@ impl GameServer {
         Ok(())
     }
 
+    /// Game the player's room is playing
+    async fn current_game(&self, player_id: Uuid) -> Option<Uuid> {
+        let room_id = self.clients.read().await.get(&player_id)?.room_id?;
+        self.coordinator.get_game_id_from_room(room_id).await
+    }
+
+    /// Song-start handshake: settings that break the room rules are rejected, accepted
+    /// ones are kept for the digest check on every score update. Returns the reply, if any
+    pub async fn handle_start_request(&self, player_id: Uuid, settings: &ClientSettings) -> Option<NetworkMessage> {
+        let Some(game_id) = self.current_game(player_id).await else {
+            return Some(NetworkMessage::Error { message: "Not in a game".to_string() });
+        };
+        match self.coordinator.submit_start_settings(game_id, player_id, settings).await {
+            Ok(()) => None,
+            Err(e) => Some(NetworkMessage::StartRejected { player_id, reason: e.to_string() }),
+        }
+    }
+
+    /// Live score from a player; a settings digest other than the one accepted at the
+    /// start invalidates their result
+    pub async fn handle_state_update(&self, player_id: Uuid, score: u32, combo: u32, accuracy: f64, settings_digest: u64) -> Result<()> {
+        let game_id = self.current_game(player_id).await.ok_or_else(|| anyhow::anyhow!("Not in a game"))?;
+        self.coordinator.update_score(game_id, player_id, score, combo, accuracy, settings_digest).await
+    }
+
+    /// Record a map rating under the connection's account, whatever id the payload
+    /// carries. Replies with the map's new aggregate, which also acknowledges the rating
+    pub fn handle_rate_map(&self, rater_id: Uuid, mut rating: MapRating) -> NetworkMessage {
+        rating.user_id = rater_id;
+        match self.community.rate_map(rating) {
+            Ok(summary) => NetworkMessage::MapRatingUpdate { summary },
+            Err(e) => NetworkMessage::Error { message: e.to_string() },
+        }
+    }
+
     /// Create a new room
     pub async fn create_room(&self, host_id: Uuid, host_name: String, max_players: usize) -> Uuid {
         let room = Room::new(host_id, host_name, max_players);
@@ -382,6 +830,16 @@ impl GameServer {
         }
     }
 
+    /// Update a room's rules (host only)
+    pub async fn set_room_rules(&self, room_id: Uuid, user_id: Uuid, rules: RoomRules) -> Result<()> {
+        let mut rooms = self.rooms.write().await;
+        if let Some(room) = rooms.get_mut(&room_id) {
+            room.set_rules(user_id, rules)
+        } else {
+            Err(anyhow::anyhow!("Room not found"))
+        }
+    }
+
     /// Get room info
     pub async fn get_room(&self, room_id: Uuid) -> Option<Room> {
         self.rooms.read().await.get(&room_id).cloned()
@@ -392,3 +850,165 @@ impl GameServer {
         self.rooms.read().await.values().cloned().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(modifiers: &[Modifier], playback_speed: f32) -> ClientSettings {
+        ClientSettings {
+            modifiers: modifiers.to_vec(),
+            playback_speed,
+        }
+    }
+
+    #[test]
+    fn default_rules_allow_every_mod_but_auto_and_relax() {
+        let rules = RoomRules::default();
+        for modifier in [Modifier::DoubleTime, Modifier::Hidden, Modifier::HardRock] {
+            assert_eq!(check_settings(&rules, &settings(&[modifier], 1.5)), Ok(()));
+        }
+        let err = check_settings(&rules, &settings(&[Modifier::Hidden, Modifier::Auto], 1.0))
+            .unwrap_err();
+        assert_eq!(err, "Disable Auto - not allowed in this room");
+        assert!(check_settings(&rules, &settings(&[Modifier::Relaxed], 1.0)).is_err());
+    }
+
+    #[test]
+    fn every_forbidden_mod_is_named() {
+        let rules = RoomRules {
+            allowed_modifiers: vec![Modifier::Hidden],
+            ..RoomRules::default()
+        };
+        let err = check_settings(
+            &rules,
+            &settings(
+                &[Modifier::DoubleTime, Modifier::Hidden, Modifier::Flash],
+                1.5,
+            ),
+        )
+        .unwrap_err();
+        assert!(err.contains(Modifier::DoubleTime.display_name()));
+        assert!(err.contains(Modifier::Flash.display_name()));
+        assert!(!err.contains(Modifier::Hidden.display_name()));
+    }
+
+    #[test]
+    fn a_forced_speed_must_match() {
+        let rules = RoomRules {
+            forced_playback_speed: Some(1.5),
+            ..RoomRules::default()
+        };
+        assert_eq!(check_settings(&rules, &settings(&[], 1.5)), Ok(()));
+        assert_eq!(
+            check_settings(&rules, &settings(&[], 1.0)),
+            Err("This room plays at 1.50x, your speed is 1.00x".to_string())
+        );
+    }
+
+    #[test]
+    fn rules_themselves_are_checked() {
+        let too_fast = RoomRules {
+            forced_playback_speed: Some(ROOM_MAX_SPEED + 0.5),
+            ..RoomRules::default()
+        };
+        assert!(too_fast.validate().is_err());
+        let auto = RoomRules {
+            allowed_modifiers: vec![Modifier::Auto],
+            ..RoomRules::default()
+        };
+        assert!(auto.validate().is_err());
+        assert_eq!(RoomRules::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn only_the_host_changes_rules_between_games() {
+        let host = Uuid::new_v4();
+        let mut room = Room::new(host, "host".to_string(), 4);
+        let rules = RoomRules {
+            forced_playback_speed: Some(1.25),
+            ..RoomRules::default()
+        };
+        assert!(room.set_rules(Uuid::new_v4(), rules.clone()).is_err());
+        room.is_game_active = true;
+        assert!(room.set_rules(host, rules.clone()).is_err());
+        room.is_game_active = false;
+        room.set_rules(host, rules.clone()).unwrap();
+        assert_eq!(room.rules, rules);
+    }
+
+    #[test]
+    fn the_settings_digest_ignores_modifier_order() {
+        let a = settings(&[Modifier::Hidden, Modifier::DoubleTime], 1.5);
+        let b = settings(&[Modifier::DoubleTime, Modifier::Hidden], 1.5);
+        assert_eq!(a.digest(), b.digest());
+        assert_ne!(a.digest(), settings(&[Modifier::Hidden], 1.5).digest());
+    }
+
+    #[test]
+    fn the_settings_digest_is_pinned_to_modifier_ids() {
+        // Clients and servers of different builds compare this value, so it must
+        // only change on purpose
+        let digest = settings(&[Modifier::DoubleTime, Modifier::Hidden], 1.5).digest();
+        assert_eq!(digest, 0x3a7c_57b5_1fba_a155);
+    }
+
+    #[tokio::test]
+    async fn the_start_handshake_is_checked_by_the_game_coordinator() {
+        let server = GameServer::new();
+        let host = Uuid::new_v4();
+        server.clients.write().await.insert(host, ClientConnection {
+            user_id: host,
+            username: "host".to_string(),
+            room_id: None,
+        });
+        let room_id = server.create_room(host, "host".to_string(), 4).await;
+        let room = server.get_room(room_id).await.unwrap();
+        let game_id = server.coordinator.create_game(&room, 1, "Song".to_string()).await.unwrap();
+
+        let rejected = server.handle_start_request(host, &settings(&[Modifier::Auto], 1.0)).await;
+        assert!(matches!(rejected, Some(NetworkMessage::StartRejected { player_id, .. }) if player_id == host));
+        let accepted = settings(&[Modifier::DoubleTime], 1.5);
+        assert!(server.handle_start_request(host, &accepted).await.is_none());
+
+        let invalid = || async { server.coordinator.get_player_state(game_id, host).await.unwrap().result_invalid };
+        server.handle_state_update(host, 100, 1, 100.0, accepted.digest()).await.unwrap();
+        assert!(!invalid().await);
+        // Slowing down mid-song is caught by the digest
+        let slower = settings(&[Modifier::DoubleTime], 1.2).digest();
+        server.handle_state_update(host, 200, 2, 100.0, slower).await.unwrap();
+        assert!(invalid().await);
+
+        let stranger = Uuid::new_v4();
+        assert!(matches!(
+            server.handle_start_request(stranger, &accepted).await,
+            Some(NetworkMessage::Error { .. })
+        ));
+        assert!(server.handle_state_update(stranger, 0, 0, 0.0, 0).await.is_err());
+    }
+
+    #[test]
+    fn map_ratings_are_stored_under_the_connected_account() {
+        let community = Arc::new(CommunityManager::new());
+        let server = GameServer::new().with_community(community.clone());
+        let rater = Uuid::new_v4();
+        let spoofed = Uuid::new_v4();
+
+        let first = MapRating::new("1234".to_string(), spoofed, 5, &[], 99.0, 400, true);
+        assert!(matches!(server.handle_rate_map(rater, first), NetworkMessage::MapRatingUpdate { .. }));
+        // Claiming another id doesn't add a second rating; it replaces the rater's own
+        let second = MapRating::new("1234".to_string(), Uuid::new_v4(), 3, &[], 90.0, 200, true);
+        match server.handle_rate_map(rater, second) {
+            NetworkMessage::MapRatingUpdate { summary } => {
+                assert_eq!(summary.count, 1);
+                assert_eq!(summary.average, 3.0);
+            }
+            other => panic!("unexpected reply {:?}", other),
+        }
+        assert_eq!(community.map_rating_summary("1234").count, 1);
+
+        let mut out_of_range = MapRating::new("1234".to_string(), rater, 3, &[], 90.0, 200, true);
+        out_of_range.stars = 6;
+        assert!(matches!(server.handle_rate_map(rater, out_of_range), NetworkMessage::Error { .. }));
+    }
+}
@@ -0,0 +1,256 @@
+// src/news.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::seasons::{Season, SeasonCalendar};
+
+/// Remote news feed
+pub const NEWS_FEED_URL: &str = "https://raw.githubusercontent.com/Yumshot/yum-osu/main/news.json";
+
+/// Last successfully fetched feed, shown when offline
+pub const NEWS_CACHE_PATH: &str = "news_cache.json";
+
+/// Entries shown on the main menu
+pub const NEWS_SHOWN_ENTRIES: usize = 3;
+
+/// A single news/changelog entry. Unknown fields from newer feeds are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NewsEntry {
+    /// Stable identifier used to track what the player has seen
+    pub id: String,
+    /// Publication date (YYYY-MM-DD), newest entries sort first
+    pub date: String,
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    /// Game version this entry needs (e.g. "0.5.0"), if any
+    #[serde(default)]
+    pub min_version: Option<String>,
+}
+
+impl NewsEntry {
+    /// Whether this entry needs a newer build than the one running
+    pub fn requires_update(&self) -> bool {
+        self.min_version
+            .as_deref()
+            .map(|version| is_newer_version(version, env!("CARGO_PKG_VERSION")))
+            .unwrap_or(false)
+    }
+}
+
+/// News feed as served by the endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct NewsFeed {
+    #[serde(default)]
+    pub entries: Vec<NewsEntry>,
+    /// Seasons announced ahead of time; calendar quarters cover the rest
+    #[serde(default)]
+    pub seasons: Vec<Season>,
+}
+
+impl NewsFeed {
+    /// Parse a feed, sorting entries newest first
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let mut feed: NewsFeed =
+            serde_json::from_str(json).map_err(|e| format!("Failed to parse news feed: {}", e))?;
+        feed.entries.sort_by(|a, b| b.date.cmp(&a.date));
+        Ok(feed)
+    }
+
+    /// Newest entries to show on the menu
+    pub fn latest(&self) -> &[NewsEntry] {
+        &self.entries[..self.entries.len().min(NEWS_SHOWN_ENTRIES)]
+    }
+
+    /// Whether any shown entry is newer than the last one the player saw
+    pub fn has_unread(&self, last_seen_id: Option<&str>) -> bool {
+        match (self.entries.first(), last_seen_id) {
+            (Some(newest), Some(seen)) => newest.id != seen,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Load the cached feed from disk
+    pub fn load_cache() -> Option<Self> {
+        if !Path::new(NEWS_CACHE_PATH).exists() {
+            return None;
+        }
+        fs::read_to_string(NEWS_CACHE_PATH)
+            .ok()
+            .and_then(|json| Self::parse(&json).ok())
+    }
+}
+
+/// Compare dotted numeric versions ("0.10.1" > "0.9.3")
+fn is_newer_version(required: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.trim().parse().unwrap_or(0))
+            .collect()
+    };
+    let (required, current) = (parse(required), parse(current));
+    let len = required.len().max(current.len());
+    for i in 0..len {
+        let r = required.get(i).copied().unwrap_or(0);
+        let c = current.get(i).copied().unwrap_or(0);
+        if r != c {
+            return r > c;
+        }
+    }
+    false
+}
+
+/// Main menu news panel state
+#[derive(Resource, Default)]
+pub struct NewsState {
+    /// Feed currently shown (cached until the fetch succeeds)
+    pub feed: Option<NewsFeed>,
+    /// Whether the panel is expanded
+    pub expanded: bool,
+    /// Pending background fetch
+    receiver: Option<Mutex<Receiver<Result<String, String>>>>,
+}
+
+impl NewsState {
+    /// Show the cached feed and start fetching the latest one on a worker thread
+    pub fn start_fetch() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let result = ureq::get(NEWS_FEED_URL)
+                .timeout(Duration::from_secs(10))
+                .call()
+                .map_err(|e| e.to_string())
+                .and_then(|response| response.into_string().map_err(|e| e.to_string()));
+            let _ = sender.send(result);
+        });
+
+        Self {
+            feed: NewsFeed::load_cache(),
+            expanded: false,
+            receiver: Some(Mutex::new(receiver)),
+        }
+    }
+}
+
+/// Season calendar from the feed shown, announced seasons and all
+pub fn season_calendar(news: &NewsState) -> SeasonCalendar {
+    SeasonCalendar::new(
+        news.feed
+            .as_ref()
+            .map(|feed| feed.seasons.clone())
+            .unwrap_or_default(),
+    )
+}
+
+/// Pick up the fetched feed; malformed feeds and failures keep the cache
+pub fn poll_news_fetch(mut news: ResMut<NewsState>, mut calendar: ResMut<SeasonCalendar>) {
+    let Some(receiver) = news.receiver.as_ref() else {
+        return;
+    };
+    let result = match receiver.lock() {
+        Ok(receiver) => match receiver.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err(String::new()),
+        },
+        Err(_) => Err(String::new()),
+    };
+    news.receiver = None;
+
+    if let Ok(raw) = result {
+        if let Ok(feed) = NewsFeed::parse(&raw) {
+            let _ = fs::write(NEWS_CACHE_PATH, &raw);
+            news.feed = Some(feed);
+            *calendar = season_calendar(&news);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_json(id: &str, date: &str) -> String {
+        format!(
+            r#"{{"id":"{}","date":"{}","title":"Entry {}"}}"#,
+            id, date, id
+        )
+    }
+
+    #[test]
+    fn parse_sorts_entries_newest_first_and_ignores_unknown_fields() {
+        let json = format!(
+            r#"{{"entries":[{},{{"id":"b","date":"2025-03-01","title":"B","body":"text","banner":"x.png","priority":5}},{}],"motd":"ignored"}}"#,
+            entry_json("a", "2025-01-01"),
+            entry_json("c", "2025-02-01")
+        );
+        let feed = NewsFeed::parse(&json).unwrap();
+        let ids: Vec<&str> = feed.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "c", "a"]);
+        assert_eq!(feed.entries[0].body, "text");
+        assert!(feed.seasons.is_empty());
+    }
+
+    #[test]
+    fn a_malformed_feed_is_an_error() {
+        assert!(NewsFeed::parse("{\"entries\": [").is_err());
+        assert!(NewsFeed::parse(r#"{"entries":[{"id":"a"}]}"#).is_err());
+        assert!(NewsFeed::parse("not json").is_err());
+    }
+
+    #[test]
+    fn latest_caps_at_the_shown_entries() {
+        let entries: Vec<String> = (1..=5)
+            .map(|day| entry_json(&day.to_string(), &format!("2025-01-0{}", day)))
+            .collect();
+        let feed = NewsFeed::parse(&format!(r#"{{"entries":[{}]}}"#, entries.join(","))).unwrap();
+        assert_eq!(feed.latest().len(), NEWS_SHOWN_ENTRIES);
+        assert_eq!(feed.latest()[0].id, "5");
+        assert!(NewsFeed::default().latest().is_empty());
+    }
+
+    #[test]
+    fn unread_tracks_the_newest_entry() {
+        let feed = NewsFeed::parse(&format!(
+            r#"{{"entries":[{},{}]}}"#,
+            entry_json("old", "2025-01-01"),
+            entry_json("new", "2025-02-01")
+        ))
+        .unwrap();
+        assert!(feed.has_unread(None));
+        assert!(feed.has_unread(Some("old")));
+        assert!(!feed.has_unread(Some("new")));
+        assert!(!NewsFeed::default().has_unread(None));
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(is_newer_version("0.10.1", "0.9.3"));
+        assert!(is_newer_version("v1.0", "0.99.99"));
+        assert!(is_newer_version("0.5.1", "0.5"));
+        assert!(!is_newer_version("0.5.0", "0.5"));
+        assert!(!is_newer_version("0.4.9", "0.5.0"));
+
+        let mut entry = NewsEntry {
+            id: "a".to_string(),
+            date: "2025-01-01".to_string(),
+            title: "A".to_string(),
+            body: String::new(),
+            min_version: None,
+        };
+        assert!(!entry.requires_update());
+        entry.min_version = Some("999.0.0".to_string());
+        assert!(entry.requires_update());
+        entry.min_version = Some(env!("CARGO_PKG_VERSION").to_string());
+        assert!(!entry.requires_update());
+    }
+}
@@ -0,0 +1,1576 @@
+// src/online.rs
+
+use bevy::prelude::*;
+use chrono::{Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::accounts::{
+    validate_registration, AccountManager, Friend, FriendsSnapshot, GameRecord, LeaderboardEntry,
+    LeaderboardScope, MaintenanceReport, Session, StorageStats, MAINTENANCE_INTERVAL,
+};
+use crate::analytics::{Analytics, GameSession};
+use crate::community::{
+    analytics_records, ChatMessage, ChatModAction, ChatRoomSettings, CommunityManager,
+    ComparisonCache, ComparisonStatus, ComparisonView, EliminationType, FriendInbox, Match,
+    ReportReason, ScoringType, Tournament, TournamentRules, UserReport,
+};
+use crate::config::GameConfig;
+use crate::network::NetworkMessage;
+use crate::seasons::{Season, SeasonCalendar};
+use crate::text_input::TextInput;
+
+/// Folder the game keeps accounts in. The server uses the same one, so a server
+/// started from the game's folder knows the same players
+pub const ONLINE_DATA_DIR: &str = "data";
+
+/// Longest username the account form takes
+pub const MAX_USERNAME_LEN: usize = 24;
+
+/// Longest password the account form takes
+pub const MAX_PASSWORD_LEN: usize = 64;
+
+/// Longest email address the account form takes
+pub const MAX_EMAIL_LEN: usize = 120;
+
+/// Players listed on the online leaderboard
+pub const ONLINE_LEADERBOARD_ROWS: usize = 10;
+
+/// Longest list drawn in one column of the online screen
+pub const ONLINE_LIST_ROWS: usize = 12;
+
+/// Lobby messages the chat tab shows, newest at the bottom
+pub const CHAT_VISIBLE_MESSAGES: usize = 14;
+
+/// Longest chat message the input takes
+pub const MAX_CHAT_MESSAGE_LEN: usize = 80;
+
+/// Slow mode settings a room host steps through, in seconds between messages
+pub const CHAT_SLOW_MODE_STEPS: [u32; 4] = [0, 10, 30, 60];
+
+/// How long a room host's timeout mutes a member
+pub const CHAT_TIMEOUT_MINUTES: u32 = 10;
+
+/// Players a tournament made on the Tournaments tab takes; 8 keep its bracket on screen
+pub const TOURNAMENT_MAX_PLAYERS: u32 = 8;
+
+/// How long after creation a new tournament is scheduled to start
+pub const TOURNAMENT_START_DELAY_MINUTES: i64 = 60;
+
+/// The player signed in on this machine
+#[derive(Debug, Clone)]
+pub struct SignedIn {
+    pub session: Session,
+    pub username: String,
+}
+
+impl SignedIn {
+    pub fn user_id(&self) -> Uuid {
+        self.session.user_id
+    }
+}
+
+/// Size of the stored account data and the cleanup of it running in the background
+#[derive(Default)]
+pub struct AccountMaintenance {
+    /// Read when the accounts load and again after each cleanup
+    pub storage: StorageStats,
+    /// Outcome of the cleanup running on a worker thread
+    receiver: Option<Mutex<Receiver<Result<MaintenanceReport, String>>>>,
+    /// Whether the player asked for the running cleanup
+    requested: bool,
+    /// When the last cleanup started; loading the accounts counts as one
+    last_run: Option<Instant>,
+}
+
+impl AccountMaintenance {
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// Whether the hourly cleanup is due at `now`
+    pub fn is_due(&self, now: Instant) -> bool {
+        !self.is_running()
+            && self
+                .last_run
+                .is_none_or(|last_run| now.duration_since(last_run) >= MAINTENANCE_INTERVAL)
+    }
+}
+
+/// Accounts and community features the online screens work with, and who is
+/// signed in to them
+#[derive(Resource)]
+pub struct OnlineServices {
+    pub accounts: AccountManager,
+    pub community: CommunityManager,
+    /// The public room the chat tab opens on
+    pub lobby: Uuid,
+    /// The signed-in player's incoming friend requests, for the badges and toasts
+    pub inbox: FriendInbox,
+    /// Last comparison with each friend, shown when their records can't be read
+    pub comparisons: ComparisonCache,
+    pub maintenance: AccountMaintenance,
+    signed_in: Option<SignedIn>,
+}
+
+impl OnlineServices {
+    /// The accounts saved in `ONLINE_DATA_DIR`, with nobody signed in
+    pub fn load() -> Self {
+        Self {
+            comparisons: ComparisonCache::load(),
+            ..Self::load_from(PathBuf::from(ONLINE_DATA_DIR))
+        }
+    }
+
+    /// Accounts and chat history kept in `dir` instead of `ONLINE_DATA_DIR`
+    pub fn load_from(dir: PathBuf) -> Self {
+        let accounts = AccountManager::new(dir.clone());
+        if let Err(e) = accounts.load_data() {
+            error!("Failed to load accounts: {}", e);
+        }
+        let community = CommunityManager::new().with_chat_archive(dir.join("chat"));
+        let lobby = community.lobby_room();
+        // Loading cleans up too, so the hourly cleanup counts from now
+        let maintenance = AccountMaintenance {
+            last_run: Some(Instant::now()),
+            ..Default::default()
+        };
+        let mut services = Self {
+            accounts,
+            community,
+            lobby,
+            inbox: FriendInbox::default(),
+            comparisons: ComparisonCache::default(),
+            maintenance,
+            signed_in: None,
+        };
+        services.read_storage();
+        services
+    }
+
+    /// Read the size of the account data and chat history on disk again
+    pub fn read_storage(&mut self) {
+        let mut storage = self.accounts.storage_stats();
+        storage.files.extend(self.community.chat_storage());
+        self.maintenance.storage = storage;
+    }
+
+    /// The signed-in player, unless their session has expired
+    pub fn user(&self) -> Option<&SignedIn> {
+        self.signed_in
+            .as_ref()
+            .filter(|signed_in| !signed_in.session.is_expired())
+    }
+
+    /// Log in with a username and password, signing the player in on success.
+    /// The error is the account manager's, for the form to show
+    pub fn sign_in(&mut self, username: &str, password: &str) -> Result<(), String> {
+        let session = self
+            .accounts
+            .login_sync(username.to_string(), password.to_string(), None)
+            .map_err(|e| e.to_string())?;
+        self.signed_in = Some(SignedIn {
+            session,
+            username: username.to_string(),
+        });
+        // Requests waiting at sign-in only show on the badge
+        self.inbox = FriendInbox::default();
+        self.inbox.handle_message(&NetworkMessage::FriendRequests {
+            pending: self.pending_requests(),
+        });
+        Ok(())
+    }
+
+    pub fn sign_out(&mut self) {
+        self.signed_in = None;
+        self.inbox = FriendInbox::default();
+    }
+
+    /// Clean up stored account data on a worker thread, as the server does, keeping
+    /// the signed-in session. `requested` marks a cleanup the player asked for, whose
+    /// outcome `poll_maintenance` hands back. Does nothing while one is running
+    pub fn start_maintenance(&mut self, requested: bool) {
+        if self.maintenance.is_running() {
+            return;
+        }
+        let accounts = self.accounts.clone();
+        let keep = self.user().map(|user| user.session.token.clone());
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let result = accounts
+                .run_maintenance(keep.as_deref())
+                .map_err(|e| e.to_string());
+            let _ = sender.send(result);
+        });
+        self.maintenance.receiver = Some(Mutex::new(receiver));
+        self.maintenance.requested = requested;
+        self.maintenance.last_run = Some(Instant::now());
+    }
+
+    /// Pick up a finished cleanup and read the storage again. Returns the outcome of
+    /// one the player asked for
+    pub fn poll_maintenance(&mut self) -> Option<Result<MaintenanceReport, String>> {
+        let receiver = self.maintenance.receiver.as_ref()?;
+        let result = match receiver.lock() {
+            Ok(receiver) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    Err("the cleanup stopped unexpectedly".to_string())
+                }
+            },
+            Err(_) => Err("the cleanup stopped unexpectedly".to_string()),
+        };
+        let requested = self.maintenance.requested;
+        self.maintenance.receiver = None;
+        self.read_storage();
+        if let (false, Err(e)) = (requested, &result) {
+            error!("Account cleanup failed: {}", e);
+        }
+        requested.then_some(result)
+    }
+
+    /// Incoming friend requests of the signed-in player, oldest first
+    fn pending_requests(&self) -> Vec<Friend> {
+        self.user()
+            .map(|user| self.accounts.pending_requests_snapshot(user.user_id()))
+            .unwrap_or_default()
+    }
+
+    /// Read the signed-in player's friend requests into the inbox, declining the ones
+    /// from `muted` players first. A request that wasn't there at the last read queues
+    /// a toast. Returns whether anything changed
+    pub fn sync_inbox(&mut self, muted: &HashSet<Uuid>) -> bool {
+        if let Some(user) = self.user() {
+            self.accounts
+                .decline_muted_requests_sync(user.user_id(), muted);
+        }
+        let pending = self.pending_requests();
+        let unchanged = pending.len() == self.inbox.pending.len()
+            && pending
+                .iter()
+                .zip(&self.inbox.pending)
+                .all(|(new, old)| new.friend_id == old.friend_id);
+        if unchanged {
+            return false;
+        }
+        for request in &pending {
+            self.inbox
+                .handle_message(&NetworkMessage::FriendRequestReceived {
+                    request: request.clone(),
+                });
+        }
+        self.inbox
+            .handle_message(&NetworkMessage::FriendRequests { pending });
+        true
+    }
+
+    /// Add a play to the signed-in player's stats and leaderboard row. Returns whether
+    /// it counted: plays that didn't reach the end don't, and practice, assisted and
+    /// local versus runs stay out of the competitive stats. Nothing counts signed out.
+    /// The play also counts toward the season `calendar` has active when it was played
+    pub fn record_play(
+        &self,
+        session: &GameSession,
+        calendar: &SeasonCalendar,
+    ) -> Result<bool, String> {
+        let Some(user) = self.user() else {
+            return Ok(false);
+        };
+        if !session.completed {
+            return Ok(false);
+        }
+        let hits = &session.hits;
+        let record = GameRecord {
+            song_name: session.song_name.clone(),
+            score: session.score,
+            max_combo: session.max_combo,
+            accuracy: session.accuracy as f64,
+            hits: [hits.perfect, hits.good, hits.okay, hits.misses],
+            play_time: session.duration_seconds,
+            practice: !session.counts_for_completion(),
+            season: Some(calendar.season_at(session.session_id as i64).id),
+        };
+        self.accounts
+            .record_game_sync(user.user_id(), record)
+            .map_err(|e| e.to_string())
+    }
+
+    /// The signed-in player's rank on a season's global board
+    pub fn season_rank(&self, season: &str) -> Option<u32> {
+        let user = self.user()?;
+        self.accounts.season_rank(user.user_id(), season)
+    }
+
+    /// Compare this profile's analytics with a friend's account records. A private
+    /// profile shows as such; if the records can't be read the last comparison with
+    /// the friend is shown instead
+    pub fn compare_with(&mut self, friend: &Friend, analytics: &Analytics) -> ComparisonView {
+        let mut view = ComparisonView::open(friend, &self.comparisons, None);
+        view.status =
+            ComparisonStatus::Loading(self.comparisons.comparisons.get(&friend.friend_id).cloned());
+        let records = self
+            .user()
+            .ok_or_else(|| "Sign in to compare".to_string())
+            .and_then(|user| {
+                self.accounts
+                    .profile_records_snapshot(user.user_id(), friend.friend_id)
+                    .map_err(|e| e.to_string())
+            });
+        match records {
+            Ok(records) => {
+                let yours = analytics_records(analytics);
+                if let Err(e) = view.receive(records, &yours, &mut self.comparisons) {
+                    error!("Failed to save the comparison: {}", e);
+                }
+            }
+            Err(e) => view.fetch_failed(e),
+        }
+        view
+    }
+
+    /// Post a message to a chat room as the signed-in player. The error is the
+    /// room's, e.g. a slow mode wait or a timeout
+    pub fn send_chat(&self, room_id: Uuid, content: &str) -> Result<(), String> {
+        let user = self.user().ok_or("Sign in to chat")?;
+        self.community
+            .send_message_sync(
+                room_id,
+                user.user_id(),
+                user.username.clone(),
+                content.to_string(),
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    /// Moderate a chat room the signed-in player hosts, returning its new settings
+    pub fn moderate_chat(
+        &self,
+        room_id: Uuid,
+        action: ChatModAction,
+    ) -> Result<ChatRoomSettings, String> {
+        let user = self.user().ok_or("Sign in to moderate chat")?;
+        self.community
+            .moderate_chat_sync(room_id, user.user_id(), action)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Register the signed-in player for a tournament. The error is the community
+    /// manager's, e.g. "Tournament is full" or "Player already registered"
+    pub fn join_tournament(&self, tournament_id: Uuid) -> Result<(), String> {
+        let user = self.user().ok_or("Sign in to join a tournament")?;
+        self.community
+            .join_tournament_sync(tournament_id, user.user_id())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Open a single elimination tournament for registration with the signed-in
+    /// player as its first entrant
+    pub fn create_tournament(&self) -> Result<Uuid, String> {
+        let user = self.user().ok_or("Sign in to create a tournament")?;
+        let name = format!("{}'s Cup", user.username);
+        let tournament_id = self.community.create_tournament_sync(
+            name.clone(),
+            format!(
+                "Single elimination, up to {} players",
+                TOURNAMENT_MAX_PLAYERS
+            ),
+            TOURNAMENT_MAX_PLAYERS,
+            Utc::now() + Duration::minutes(TOURNAMENT_START_DELAY_MINUTES),
+            TournamentRules {
+                song_pool: Vec::new(),
+                scoring_type: ScoringType::ScoreV1,
+                elimination_type: EliminationType::SingleElimination,
+            },
+        );
+        // The creator organizes, and moderates the tournament's lobby chat
+        self.community
+            .open_tournament_chat(tournament_id, name, user.user_id());
+        self.join_tournament(tournament_id)?;
+        Ok(tournament_id)
+    }
+
+    /// Close registration and draw the bracket. Only a registered player may
+    pub fn start_tournament(&self, tournament: &Tournament) -> Result<(), String> {
+        let user = self.user().ok_or("Sign in to start a tournament")?;
+        if !tournament.players.contains(&user.user_id()) {
+            return Err("Only registered players can start a tournament".to_string());
+        }
+        self.community
+            .start_tournament_sync(tournament.tournament_id)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Create an account from the registration form. Nobody is signed in by it
+    pub fn register(
+        &self,
+        username: &str,
+        email: &str,
+        password: &str,
+        confirm_password: &str,
+    ) -> Result<(), String> {
+        validate_registration(username, email, password, confirm_password)
+            .and_then(|()| {
+                self.accounts.register_sync(
+                    username.to_string(),
+                    password.to_string(),
+                    email.to_string(),
+                )
+            })
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Fields of the account form, in Tab order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccountField {
+    #[default]
+    Username,
+    Email,
+    Password,
+    ConfirmPassword,
+}
+
+impl AccountField {
+    pub const SIGN_IN: [AccountField; 2] = [AccountField::Username, AccountField::Password];
+    pub const REGISTER: [AccountField; 4] = [
+        AccountField::Username,
+        AccountField::Email,
+        AccountField::Password,
+        AccountField::ConfirmPassword,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountField::Username => "Username",
+            AccountField::Email => "Email",
+            AccountField::Password => "Password",
+            AccountField::ConfirmPassword => "Confirm password",
+        }
+    }
+}
+
+/// Sign-in and registration form of the account screen
+#[derive(Resource, Debug, Default)]
+pub struct AccountFormState {
+    /// Creating an account rather than signing in
+    pub registering: bool,
+    pub username: TextInput,
+    pub email: TextInput,
+    pub password: TextInput,
+    pub confirm_password: TextInput,
+    pub focused: AccountField,
+    /// Why the last submit failed
+    pub error: Option<String>,
+    /// Outcome of the last registration, shown over the sign-in form
+    pub notice: Option<String>,
+}
+
+impl AccountFormState {
+    pub fn new() -> Self {
+        Self {
+            username: TextInput::default().with_max_len(MAX_USERNAME_LEN),
+            email: TextInput::default().with_max_len(MAX_EMAIL_LEN),
+            password: TextInput::default().with_max_len(MAX_PASSWORD_LEN).masked(),
+            confirm_password: TextInput::default().with_max_len(MAX_PASSWORD_LEN).masked(),
+            ..default()
+        }
+    }
+
+    /// Fields of the current form, in Tab order
+    pub fn fields(&self) -> &'static [AccountField] {
+        if self.registering {
+            &AccountField::REGISTER
+        } else {
+            &AccountField::SIGN_IN
+        }
+    }
+
+    pub fn input(&self, field: AccountField) -> &TextInput {
+        match field {
+            AccountField::Username => &self.username,
+            AccountField::Email => &self.email,
+            AccountField::Password => &self.password,
+            AccountField::ConfirmPassword => &self.confirm_password,
+        }
+    }
+
+    pub fn input_mut(&mut self, field: AccountField) -> &mut TextInput {
+        match field {
+            AccountField::Username => &mut self.username,
+            AccountField::Email => &mut self.email,
+            AccountField::Password => &mut self.password,
+            AccountField::ConfirmPassword => &mut self.confirm_password,
+        }
+    }
+
+    /// Switch between signing in and registering, keeping the username and
+    /// clearing both passwords
+    pub fn toggle_registering(&mut self) {
+        self.registering = !self.registering;
+        self.password.set_text("");
+        self.confirm_password.set_text("");
+        self.focused = AccountField::Username;
+        self.error = None;
+        self.notice = None;
+    }
+
+    /// Move to the next field, or the previous one going `back`, wrapping around
+    pub fn focus_next(&mut self, back: bool) {
+        let fields = self.fields();
+        let index = fields
+            .iter()
+            .position(|field| *field == self.focused)
+            .unwrap_or(0);
+        let step = if back { fields.len() - 1 } else { 1 };
+        self.focused = fields[(index + step) % fields.len()];
+    }
+
+    /// Whether every field of the current form has something in it; submitting
+    /// is disabled until then
+    pub fn can_submit(&self) -> bool {
+        self.fields()
+            .iter()
+            .all(|field| !self.input(*field).text().trim().is_empty())
+    }
+}
+
+/// Tabs of the online screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnlineTab {
+    #[default]
+    Leaderboard,
+    Friends,
+    Tournaments,
+    Chat,
+    Muted,
+}
+
+impl OnlineTab {
+    pub const ALL: [OnlineTab; 5] = [
+        OnlineTab::Leaderboard,
+        OnlineTab::Friends,
+        OnlineTab::Tournaments,
+        OnlineTab::Chat,
+        OnlineTab::Muted,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OnlineTab::Leaderboard => "Leaderboard",
+            OnlineTab::Friends => "Friends",
+            OnlineTab::Tournaments => "Tournaments",
+            OnlineTab::Chat => "Chat",
+            OnlineTab::Muted => "Muted",
+        }
+    }
+
+    /// Keys of the tab, for the hint line
+    pub fn hint(&self) -> &'static str {
+        match self {
+            OnlineTab::Leaderboard => {
+                "Left/Right: Board | Up/Down: Season | PgUp/PgDn: Page | M: My rank"
+            }
+            OnlineTab::Friends => {
+                "Up/Down: Select | Enter: Accept | D: Decline | B: Block | C: Compare | N: Add friend"
+            }
+            OnlineTab::Tournaments => "Up/Down: Tournament | Enter: Join | C: Create | S: Start",
+            OnlineTab::Chat => {
+                "Enter: Send | PgUp/PgDn: Room | Up/Down: Message | Ctrl+M: Mute | Ctrl+R: Report"
+            }
+            OnlineTab::Muted => "Up/Down: Select | Enter: Unmute",
+        }
+    }
+
+    /// The tab after this one, or before it going `back`, wrapping around
+    pub fn cycled(&self, back: bool) -> OnlineTab {
+        let index = Self::ALL.iter().position(|tab| tab == self).unwrap_or(0);
+        let step = if back { Self::ALL.len() - 1 } else { 1 };
+        Self::ALL[(index + step) % Self::ALL.len()]
+    }
+}
+
+/// Which players the leaderboard tab ranks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoardScope {
+    #[default]
+    Global,
+    Country,
+    Friends,
+}
+
+impl BoardScope {
+    pub const ALL: [BoardScope; 3] = [BoardScope::Global, BoardScope::Country, BoardScope::Friends];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BoardScope::Global => "Global",
+            BoardScope::Country => "Country",
+            BoardScope::Friends => "Friends",
+        }
+    }
+
+    /// The scope after this one, or before it going `back`, wrapping around
+    pub fn cycled(&self, back: bool) -> BoardScope {
+        let index = Self::ALL
+            .iter()
+            .position(|scope| scope == self)
+            .unwrap_or(0);
+        let step = if back { Self::ALL.len() - 1 } else { 1 };
+        Self::ALL[(index + step) % Self::ALL.len()]
+    }
+}
+
+/// Which season the leaderboard tab ranks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BoardSeason {
+    /// The season active now
+    #[default]
+    Current,
+    /// Every game ever played
+    AllTime,
+    /// A season that has ended
+    Past(Season),
+}
+
+impl BoardSeason {
+    pub fn label(&self) -> String {
+        match self {
+            BoardSeason::Current => "This season".to_string(),
+            BoardSeason::AllTime => "All time".to_string(),
+            BoardSeason::Past(season) => season.name.clone(),
+        }
+    }
+}
+
+/// State of the online screen. Each tab's data is read from the services when the
+/// tab is opened or changed, not every frame
+#[derive(Resource, Debug, Default)]
+pub struct OnlineHubState {
+    pub tab: OnlineTab,
+    pub board_scope: BoardScope,
+    /// Season the board ranks
+    pub board_season: BoardSeason,
+    /// Seasons over since which anyone has played, newest first
+    pub past_seasons: Vec<Season>,
+    /// Seasons in play, for the current season and past seasons' names
+    pub calendar: SeasonCalendar,
+    /// The page of the board in `board_scope` and `board_season`, best first
+    pub board: Vec<LeaderboardEntry>,
+    /// Row of the board the page starts at
+    pub board_offset: usize,
+    /// Players on the whole board
+    pub board_total: usize,
+    /// The signed-in player's rank on the board
+    pub own_rank: Option<u32>,
+    /// Country the Country board is for
+    pub country: Option<String>,
+    /// The signed-in player's friends and requests
+    pub friends: FriendsSnapshot,
+    /// Row the Friends tab has selected: the incoming requests, then the friends
+    pub friend_cursor: usize,
+    /// Head-to-head comparison open over the Friends tab
+    pub comparison: Option<ComparisonView>,
+    /// Username being typed for a new friend request
+    pub friend_name: Option<TextInput>,
+    /// Rooms the chat tab switches between: the lobby, then the tournament lobbies the
+    /// player is in
+    pub chat_rooms: Vec<(Uuid, String)>,
+    /// Room the chat tab shows
+    pub chat_room: usize,
+    /// The newest messages of the room, oldest first
+    pub chat: Vec<ChatMessage>,
+    /// Players muted in the config, whose messages are collapsed
+    pub chat_muted: HashSet<Uuid>,
+    /// The muted players and their usernames, for the Muted tab, by name
+    pub muted_players: Vec<(Uuid, String)>,
+    /// Row the Muted tab has selected
+    pub muted_cursor: usize,
+    /// Pinned messages and slow mode or timeout notices shown above the messages
+    pub chat_header: Vec<String>,
+    /// Whether the signed-in player hosts the room, and sees its moderation keys
+    pub chat_host: bool,
+    /// The room's moderation settings
+    pub chat_settings: ChatRoomSettings,
+    /// Message selected to mute or report its sender, or for the host to pin or time out
+    pub chat_cursor: Option<usize>,
+    /// Reason picked while reporting the selected message
+    pub chat_report: Option<ReportReason>,
+    /// Message being typed to the room
+    pub chat_input: TextInput,
+    /// Tournaments open for registration or under way, soonest first
+    pub tournaments: Vec<Tournament>,
+    /// Tournament the Tournaments tab has selected
+    pub selected_tournament: usize,
+    /// The selected tournament's matches, one list per round drawn so far
+    pub bracket: Vec<Vec<Match>>,
+    /// The signed-in player's matches in the selected tournament, soonest first
+    pub own_matches: Vec<Match>,
+    /// Usernames of the selected tournament's players
+    pub player_names: HashMap<Uuid, String>,
+    /// Why the current tab shows nothing, e.g. it needs a signed-in player
+    pub notice: Option<String>,
+    /// Outcome of the last action on the current tab, and whether it failed
+    pub message: Option<(String, bool)>,
+}
+
+impl OnlineHubState {
+    /// A fresh screen on `tab`, keeping the board the player last looked at
+    pub fn new(tab: OnlineTab, board_scope: BoardScope, board_season: BoardSeason) -> Self {
+        Self {
+            tab,
+            board_scope,
+            board_season,
+            chat_input: TextInput::default().with_max_len(MAX_CHAT_MESSAGE_LEN),
+            ..default()
+        }
+    }
+
+    /// Read the open tab's data again
+    pub fn refresh(&mut self, online: &OnlineServices) {
+        self.notice = None;
+        match self.tab {
+            OnlineTab::Leaderboard => self.refresh_board(online),
+            OnlineTab::Friends => self.refresh_friends(online),
+            OnlineTab::Tournaments => self.refresh_tournaments(online),
+            OnlineTab::Chat => self.refresh_chat(online),
+            OnlineTab::Muted => self.refresh_muted(online),
+        }
+    }
+
+    /// Switch tabs, leaving any field and message of the old one behind
+    pub fn open_tab(&mut self, tab: OnlineTab, online: &OnlineServices) {
+        self.tab = tab;
+        self.friend_name = None;
+        self.comparison = None;
+        self.chat_report = None;
+        self.message = None;
+        self.refresh(online);
+    }
+
+    /// Show the next season's board, or the one before going `back`: this season, all
+    /// time, then each past season, wrapping around
+    pub fn cycle_board_season(&mut self, back: bool, online: &OnlineServices) {
+        let choices: Vec<BoardSeason> = [BoardSeason::Current, BoardSeason::AllTime]
+            .into_iter()
+            .chain(self.past_seasons.iter().cloned().map(BoardSeason::Past))
+            .collect();
+        let index = choices
+            .iter()
+            .position(|season| *season == self.board_season)
+            .unwrap_or(0);
+        let step = if back { choices.len() - 1 } else { 1 };
+        self.board_season = choices[(index + step) % choices.len()].clone();
+        self.board_offset = 0;
+        self.refresh(online);
+    }
+
+    /// Show the next board, or the one before going `back`, from its top
+    pub fn cycle_board_scope(&mut self, back: bool, online: &OnlineServices) {
+        self.board_scope = self.board_scope.cycled(back);
+        self.board_offset = 0;
+        self.refresh(online);
+    }
+
+    /// Show the next page of the board, or the one before going `back`
+    pub fn page_board(&mut self, back: bool, online: &OnlineServices) {
+        self.board_offset = if back {
+            self.board_offset.saturating_sub(ONLINE_LEADERBOARD_ROWS)
+        } else {
+            (self.board_offset + ONLINE_LEADERBOARD_ROWS).min(page_offset(self.board_total))
+        };
+        self.refresh(online);
+    }
+
+    /// Show the page with the signed-in player's row
+    pub fn jump_to_own_rank(&mut self, online: &OnlineServices) {
+        match self.own_rank {
+            Some(rank) => {
+                self.board_offset = page_offset(rank as usize);
+                self.message = None;
+                self.refresh(online);
+            }
+            None => self.message = Some(("You're not on this board yet".to_string(), true)),
+        }
+    }
+
+    /// Rows the Friends tab's cursor moves over
+    pub fn friend_rows(&self) -> usize {
+        self.friends.incoming.len() + self.friends.friends.len()
+    }
+
+    /// The incoming request the Friends tab has selected, if the cursor is on one
+    pub fn selected_request(&self) -> Option<&Friend> {
+        self.friends.incoming.get(self.friend_cursor)
+    }
+
+    /// The friend the Friends tab has selected, if the cursor is past the requests
+    pub fn selected_friend(&self) -> Option<&Friend> {
+        let index = self
+            .friend_cursor
+            .checked_sub(self.friends.incoming.len())?;
+        self.friends.friends.get(index)
+    }
+
+    /// The tournament the Tournaments tab has selected
+    pub fn selected_tournament(&self) -> Option<&Tournament> {
+        self.tournaments.get(self.selected_tournament)
+    }
+
+    /// A player's name in the selected tournament
+    pub fn player_name(&self, player_id: Uuid) -> &str {
+        self.player_names
+            .get(&player_id)
+            .map(String::as_str)
+            .unwrap_or("Unknown")
+    }
+
+    fn refresh_tournaments(&mut self, online: &OnlineServices) {
+        self.tournaments = online.community.active_tournaments_snapshot();
+        self.selected_tournament = self
+            .selected_tournament
+            .min(self.tournaments.len().saturating_sub(1));
+        let Some(tournament) = self.tournaments.get(self.selected_tournament) else {
+            self.bracket.clear();
+            self.own_matches.clear();
+            self.player_names.clear();
+            return;
+        };
+        let tournament_id = tournament.tournament_id;
+        self.player_names = tournament
+            .players
+            .iter()
+            .filter_map(|&player_id| {
+                let user = online.accounts.user_snapshot(player_id)?;
+                Some((player_id, user.username))
+            })
+            .collect();
+        self.bracket = online.community.get_bracket(tournament_id);
+        self.own_matches = online
+            .user()
+            .map(|user| online.community.player_matches_snapshot(user.user_id()))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|game_match| game_match.tournament_id == tournament_id)
+            .collect();
+    }
+
+    /// Room the chat tab shows, the lobby until the rooms are read
+    pub fn chat_room_id(&self, online: &OnlineServices) -> Uuid {
+        self.chat_rooms
+            .get(self.chat_room)
+            .map_or(online.lobby, |(room_id, _)| *room_id)
+    }
+
+    /// Show the next chat room, or the one before going `back`, wrapping around
+    pub fn cycle_chat_room(&mut self, back: bool, online: &OnlineServices) {
+        let count = self.chat_rooms.len().max(1);
+        let step = if back { count - 1 } else { 1 };
+        self.chat_room = (self.chat_room + step) % count;
+        self.chat_cursor = None;
+        self.refresh(online);
+    }
+
+    /// Move the message selection over the messages shown, starting from the newest
+    pub fn move_chat_cursor(&mut self, delta: i32) {
+        let shown: Vec<usize> = (0..self.chat.len())
+            .filter(|&index| !self.chat_muted.contains(&self.chat[index].sender_id))
+            .collect();
+        let Some(last) = shown.len().checked_sub(1) else {
+            self.chat_cursor = None;
+            return;
+        };
+        let position = self
+            .chat_cursor
+            .and_then(|cursor| shown.iter().position(|&index| index == cursor))
+            .unwrap_or(last + 1) as i32
+            + delta;
+        self.chat_cursor = Some(shown[position.clamp(0, last as i32) as usize]);
+    }
+
+    /// Message selected on the Chat tab
+    pub fn selected_chat_message(&self) -> Option<&ChatMessage> {
+        self.chat_cursor.and_then(|index| self.chat.get(index))
+    }
+
+    /// Take the muted players from the config, e.g. after muting someone
+    pub fn set_muted(&mut self, config: &GameConfig) {
+        self.chat_muted = config.muted_users.clone();
+        if self
+            .selected_chat_message()
+            .is_some_and(|message| self.chat_muted.contains(&message.sender_id))
+        {
+            self.chat_cursor = None;
+        }
+    }
+
+    /// Mute the sender of the selected message, or unmute them if they are. The
+    /// caller saves the config. Returns what happened, for the message line
+    pub fn toggle_mute_selected(
+        &mut self,
+        config: &mut GameConfig,
+        online: &OnlineServices,
+    ) -> Result<String, String> {
+        let message = self
+            .selected_chat_message()
+            .ok_or("Select a message with Up/Down")?;
+        let (sender_id, sender_name) = (message.sender_id, message.sender_name.clone());
+        if online.user().map(|user| user.user_id()) == Some(sender_id) {
+            return Err("You can't mute yourself".to_string());
+        }
+        let done = if config.unmute_user(sender_id) {
+            format!("Unmuted {}", sender_name)
+        } else {
+            config.mute_user(sender_id);
+            format!("Muted {}. Unmute them from the Muted tab", sender_name)
+        };
+        self.set_muted(config);
+        Ok(done)
+    }
+
+    /// Unmute the player the Muted tab has selected. The caller saves the config
+    pub fn unmute_selected(&mut self, config: &mut GameConfig, online: &OnlineServices) -> Option<String> {
+        let (player_id, name) = self.muted_players.get(self.muted_cursor).cloned()?;
+        config.unmute_user(player_id);
+        self.set_muted(config);
+        self.refresh(online);
+        Some(format!("Unmuted {}", name))
+    }
+
+    /// A report on the selected message's sender, for `chat_report`'s reason
+    pub fn report_selected(&self, online: &OnlineServices) -> Result<UserReport, String> {
+        let reporter = online.user().ok_or("Sign in to report")?;
+        let message = self
+            .selected_chat_message()
+            .ok_or("Select a message with Up/Down")?;
+        if message.sender_id == reporter.user_id() {
+            return Err("You can't report yourself".to_string());
+        }
+        let reason = self.chat_report.unwrap_or(ReportReason::Spam);
+        Ok(UserReport::for_message(reporter.user_id(), message, reason, None))
+    }
+
+    fn refresh_muted(&mut self, online: &OnlineServices) {
+        self.muted_players = self
+            .chat_muted
+            .iter()
+            .map(|&player_id| {
+                let name = online
+                    .accounts
+                    .user_snapshot(player_id)
+                    .map_or_else(|| "Unknown player".to_string(), |user| user.username);
+                (player_id, name)
+            })
+            .collect();
+        self.muted_players
+            .sort_by_key(|(_, name)| name.to_lowercase());
+        self.muted_cursor = self
+            .muted_cursor
+            .min(self.muted_players.len().saturating_sub(1));
+    }
+
+    fn refresh_chat(&mut self, online: &OnlineServices) {
+        let viewer = online.user().map(|user| user.user_id());
+        self.chat_rooms = vec![(online.lobby, "Lobby".to_string())];
+        if let Some(viewer) = viewer {
+            self.chat_rooms
+                .extend(online.community.member_lobby_rooms(viewer));
+        }
+        self.chat_room = self.chat_room.min(self.chat_rooms.len() - 1);
+        let room_id = self.chat_room_id(online);
+        self.chat = online
+            .community
+            .messages_snapshot(room_id, CHAT_VISIBLE_MESSAGES);
+        self.chat_cursor = self
+            .chat_cursor
+            .filter(|_| !self.chat.is_empty())
+            .map(|cursor| cursor.min(self.chat.len() - 1));
+        let room = online.community.chat_room_snapshot(room_id);
+        self.chat_settings = room
+            .as_ref()
+            .map(|room| room.settings.clone())
+            .unwrap_or_default();
+        self.chat_host = room
+            .as_ref()
+            .zip(viewer)
+            .is_some_and(|(room, viewer)| room.is_host(viewer));
+        // Signed out only the pins and slow mode apply
+        self.chat_header = room
+            .map(|room| room.panel_header(viewer.unwrap_or_default(), Utc::now()))
+            .unwrap_or_default();
+    }
+
+    fn refresh_friends(&mut self, online: &OnlineServices) {
+        match online.user() {
+            Some(user) => self.friends = online.accounts.friends_snapshot(user.user_id()),
+            None => {
+                self.friends = FriendsSnapshot::default();
+                self.notice = Some("Sign in to see your friends".to_string());
+            }
+        }
+        self.friend_cursor = self.friend_cursor.min(self.friend_rows().saturating_sub(1));
+    }
+
+    fn refresh_board(&mut self, online: &OnlineServices) {
+        let current = self.calendar.current();
+        let now = Utc::now();
+        self.past_seasons = online
+            .accounts
+            .ranked_seasons()
+            .iter()
+            .filter_map(|id| self.calendar.get(id))
+            .filter(|season| *season != current && season.has_ended(now))
+            .collect();
+        self.past_seasons
+            .sort_by_key(|season| std::cmp::Reverse(season.start));
+
+        let user = online.user();
+        self.country = user
+            .and_then(|user| online.accounts.user_snapshot(user.user_id()))
+            .map(|user| user.profile.country);
+        let scope = match (self.board_scope, user, &self.country) {
+            (BoardScope::Global, _, _) => LeaderboardScope::Global,
+            (BoardScope::Country, Some(_), Some(country)) => {
+                LeaderboardScope::Country(country.clone())
+            }
+            (BoardScope::Friends, Some(user), _) => LeaderboardScope::Friends(user.user_id()),
+            _ => {
+                self.board.clear();
+                self.board_total = 0;
+                self.own_rank = None;
+                self.notice = Some(format!(
+                    "Sign in to see the {} board",
+                    self.board_scope.label().to_lowercase()
+                ));
+                return;
+            }
+        };
+        let season = match &self.board_season {
+            BoardSeason::Current => Some(current.id),
+            BoardSeason::AllTime => None,
+            BoardSeason::Past(season) => Some(season.id.clone()),
+        };
+        let read_page = |offset| {
+            online
+                .accounts
+                .board_page(&scope, season.as_deref(), offset, ONLINE_LEADERBOARD_ROWS)
+        };
+        let mut page = read_page(self.board_offset);
+        if page.entries.is_empty() && page.total_count > 0 {
+            // The board shrank under the page, show its last one
+            page = read_page(page_offset(page.total_count));
+        }
+        self.board = page.entries;
+        self.board_offset = page.offset;
+        self.board_total = page.total_count;
+        self.own_rank = user.and_then(|user| {
+            online
+                .accounts
+                .board_rank(&scope, season.as_deref(), user.user_id())
+        });
+    }
+}
+
+/// Offset of the board page holding the `rank`th row
+fn page_offset(rank: usize) -> usize {
+    rank.saturating_sub(1) / ONLINE_LEADERBOARD_ROWS * ONLINE_LEADERBOARD_ROWS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::community::{visible_chat_lines, ChatLine};
+    use crate::test_support::scratch_dir;
+
+    #[test]
+    fn registering_needs_all_four_fields() {
+        let mut form = AccountFormState::new();
+        form.toggle_registering();
+        form.username.set_text("alice");
+        form.email.set_text("alice@example.com");
+        form.password.set_text("hunter22");
+        assert!(!form.can_submit());
+        form.confirm_password.set_text("hunter22");
+        assert!(form.can_submit());
+
+        // Back on the sign-in form the passwords are cleared
+        form.toggle_registering();
+        assert_eq!(form.username.text(), "alice");
+        assert!(!form.can_submit());
+    }
+
+    #[test]
+    fn tab_wraps_around_the_current_form() {
+        let mut form = AccountFormState::new();
+        form.focus_next(true);
+        assert_eq!(form.focused, AccountField::Password);
+        form.focus_next(false);
+        assert_eq!(form.focused, AccountField::Username);
+
+        form.toggle_registering();
+        form.focus_next(true);
+        assert_eq!(form.focused, AccountField::ConfirmPassword);
+    }
+
+    #[test]
+    fn only_signed_in_players_chat_in_the_lobby() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-chat"));
+        assert_eq!(
+            online.send_chat(online.lobby, "hello"),
+            Err("Sign in to chat".to_string())
+        );
+
+        online
+            .register("alice", "alice@example.com", "hunter22", "hunter22")
+            .unwrap();
+        online.sign_in("alice", "hunter22").unwrap();
+        assert!(online.send_chat(online.lobby, "   ").is_err());
+        online.send_chat(online.lobby, "hello").unwrap();
+
+        let mut hub = OnlineHubState::new(OnlineTab::Chat, BoardScope::Global, BoardSeason::Current);
+        hub.refresh(&online);
+        let lines: Vec<_> = hub
+            .chat
+            .iter()
+            .map(|message| (message.sender_name.as_str(), message.content.as_str()))
+            .collect();
+        assert_eq!(lines, vec![("alice", "hello")]);
+    }
+
+    #[test]
+    fn muting_from_the_chat_tab_collapses_the_sender_until_unmuted() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-mute"));
+        for name in ["alice", "bob"] {
+            let email = format!("{}@example.com", name);
+            online
+                .register(name, &email, "hunter22", "hunter22")
+                .unwrap();
+        }
+        online.sign_in("bob", "hunter22").unwrap();
+        let bob_id = online.user().unwrap().user_id();
+        online.send_chat(online.lobby, "buy gold").unwrap();
+        online.sign_in("alice", "hunter22").unwrap();
+        online.send_chat(online.lobby, "hi all").unwrap();
+
+        let mut config = GameConfig::default();
+        let mut hub = OnlineHubState::new(OnlineTab::Chat, BoardScope::Global, BoardSeason::Current);
+        hub.set_muted(&config);
+        hub.refresh(&online);
+        hub.move_chat_cursor(-1);
+        assert!(hub.toggle_mute_selected(&mut config, &online).is_err());
+        hub.move_chat_cursor(-1);
+        hub.chat_report = Some(ReportReason::Spam);
+        let report = hub.report_selected(&online).unwrap();
+        assert_eq!(report.target_id, bob_id);
+        assert_eq!(report.message_ids, vec![hub.chat[0].message_id]);
+
+        hub.toggle_mute_selected(&mut config, &online).unwrap();
+        assert!(config.is_muted(bob_id));
+        assert_eq!(hub.chat_cursor, None);
+        assert_eq!(
+            visible_chat_lines(&hub.chat, &hub.chat_muted)[0],
+            ChatLine::Hidden(1)
+        );
+        // Bob's message can't be selected while he's muted
+        hub.move_chat_cursor(-1);
+        hub.move_chat_cursor(-1);
+        assert_eq!(hub.selected_chat_message().unwrap().content, "hi all");
+
+        hub.open_tab(OnlineTab::Muted, &online);
+        assert_eq!(hub.muted_players, vec![(bob_id, "bob".to_string())]);
+        assert_eq!(
+            hub.unmute_selected(&mut config, &online).as_deref(),
+            Some("Unmuted bob")
+        );
+        assert!(hub.muted_players.is_empty());
+        assert!(!config.is_muted(bob_id));
+        hub.open_tab(OnlineTab::Chat, &online);
+        assert!(matches!(
+            visible_chat_lines(&hub.chat, &hub.chat_muted)[0],
+            ChatLine::Message(message) if message.sender_id == bob_id
+        ));
+    }
+
+    #[test]
+    fn friend_requests_from_muted_players_are_declined() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-muted-requests"));
+        for name in ["alice", "bob"] {
+            let email = format!("{}@example.com", name);
+            online
+                .register(name, &email, "hunter22", "hunter22")
+                .unwrap();
+        }
+        online.sign_in("bob", "hunter22").unwrap();
+        let bob_id = online.user().unwrap().user_id();
+        online
+            .accounts
+            .send_friend_request_sync(bob_id, "alice".to_string())
+            .unwrap();
+        online.sign_in("alice", "hunter22").unwrap();
+        assert_eq!(online.inbox.badge().as_deref(), Some("1"));
+
+        assert!(online.sync_inbox(&HashSet::from([bob_id])));
+        assert_eq!(online.inbox.badge(), None);
+        assert!(online.pending_requests().is_empty());
+    }
+
+    #[test]
+    fn tournament_organizers_moderate_their_lobby_chat() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-moderation"));
+        for name in ["alice", "bob"] {
+            let email = format!("{}@example.com", name);
+            online
+                .register(name, &email, "hunter22", "hunter22")
+                .unwrap();
+        }
+        online.sign_in("alice", "hunter22").unwrap();
+        let room_id = online.create_tournament().unwrap();
+        online.sign_in("bob", "hunter22").unwrap();
+        online.join_tournament(room_id).unwrap();
+
+        // Bob sees the tournament's room but none of its moderation keys
+        let mut hub = OnlineHubState::new(OnlineTab::Chat, BoardScope::Global, BoardSeason::Current);
+        hub.refresh(&online);
+        assert_eq!(hub.chat_rooms.len(), 2);
+        hub.cycle_chat_room(false, &online);
+        assert_eq!(hub.chat_room_id(&online), room_id);
+        assert!(!hub.chat_host);
+        online.send_chat(room_id, "glhf").unwrap();
+        assert!(online
+            .moderate_chat(room_id, ChatModAction::SlowMode { seconds: 30 })
+            .is_err());
+
+        online.sign_in("alice", "hunter22").unwrap();
+        hub.refresh(&online);
+        assert!(hub.chat_host);
+        hub.move_chat_cursor(-1);
+        let message_id = hub.selected_chat_message().unwrap().message_id;
+        online
+            .moderate_chat(room_id, ChatModAction::Pin { message_id })
+            .unwrap();
+        online
+            .moderate_chat(room_id, ChatModAction::SlowMode { seconds: 30 })
+            .unwrap();
+
+        // Slow mode reaches Bob's panel, and his next message is refused
+        online.sign_in("bob", "hunter22").unwrap();
+        hub.refresh(&online);
+        assert_eq!(
+            hub.chat_header,
+            vec![
+                "📌 bob: glhf".to_string(),
+                "Slow mode: one message every 30s".to_string()
+            ]
+        );
+        let refused = online.send_chat(room_id, "again").unwrap_err();
+        assert!(refused.starts_with("Slow mode is on"), "{}", refused);
+    }
+
+    #[test]
+    fn tournaments_take_each_player_once_and_draw_a_bracket() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-tournaments"));
+        for name in ["alice", "bob"] {
+            let email = format!("{}@example.com", name);
+            online
+                .register(name, &email, "hunter22", "hunter22")
+                .unwrap();
+        }
+        online.sign_in("alice", "hunter22").unwrap();
+        let tournament_id = online.create_tournament().unwrap();
+        assert_eq!(
+            online.join_tournament(tournament_id),
+            Err("Player already registered".to_string())
+        );
+
+        let mut hub = OnlineHubState::new(OnlineTab::Tournaments, BoardScope::Global, BoardSeason::Current);
+        hub.refresh(&online);
+        let tournament = hub.selected_tournament().unwrap().clone();
+        // One player can't make a bracket
+        assert!(online.start_tournament(&tournament).is_err());
+
+        online.sign_in("bob", "hunter22").unwrap();
+        online.join_tournament(tournament_id).unwrap();
+        for _ in 2..TOURNAMENT_MAX_PLAYERS {
+            online
+                .community
+                .join_tournament_sync(tournament_id, Uuid::new_v4())
+                .unwrap();
+        }
+        assert_eq!(
+            online.join_tournament(tournament_id),
+            Err("Tournament is full".to_string())
+        );
+
+        hub.refresh(&online);
+        let tournament = hub.selected_tournament().unwrap().clone();
+        online.start_tournament(&tournament).unwrap();
+        hub.refresh(&online);
+        assert_eq!(hub.bracket.len(), 1);
+        assert_eq!(hub.bracket[0].len(), 4);
+        // Seeds 1 and 2 only meet in the final
+        assert_eq!(hub.own_matches.len(), 1);
+        assert_eq!(hub.player_name(hub.own_matches[0].player1_id), "bob");
+        assert_eq!(hub.player_name(hub.bracket[0][0].player1_id), "alice");
+    }
+
+    #[test]
+    fn finished_plays_count_toward_the_signed_in_account() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-plays"));
+        online
+            .register("alice", "alice@example.com", "hunter22", "hunter22")
+            .unwrap();
+
+        let mut play = GameSession::new("Song".to_string());
+        play.score = 5000;
+        play.max_combo = 40;
+        play.accuracy = 95.0;
+        play.hits.perfect = 38;
+        play.hits.good = 2;
+        play.completed = true;
+        let calendar = SeasonCalendar::default();
+        assert_eq!(online.record_play(&play, &calendar), Ok(false));
+
+        online.sign_in("alice", "hunter22").unwrap();
+        let mut quit = play.clone();
+        quit.completed = false;
+        let mut practice = play.clone();
+        practice.practice_mode = true;
+        let mut versus = play.clone();
+        versus.local_versus = Some(1);
+        for skipped in [&quit, &practice, &versus] {
+            assert_eq!(online.record_play(skipped, &calendar), Ok(false));
+        }
+        assert_eq!(online.record_play(&play, &calendar), Ok(true));
+
+        let user_id = online.user().unwrap().user_id();
+        let stats = online.accounts.user_snapshot(user_id).unwrap().stats;
+        assert_eq!(stats.total_games, 1);
+        assert_eq!(stats.total_score, 5000);
+        assert_eq!(stats.highest_combo, 40);
+        assert_eq!(stats.perfect_hits, 38);
+        let board = online
+            .accounts
+            .board_page(&LeaderboardScope::Global, None, 0, ONLINE_LEADERBOARD_ROWS)
+            .entries;
+        assert_eq!(board.len(), 1);
+        assert_eq!(board[0].total_score, 5000);
+        let season = calendar.season_at(play.session_id as i64);
+        assert_eq!(stats.seasons[&season.id].total_score, 5000);
+        assert_eq!(online.season_rank(&season.id), Some(1));
+    }
+
+    #[test]
+    fn the_board_steps_through_this_season_all_time_and_past_seasons() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-seasons"));
+        online
+            .register("alice", "alice@example.com", "hunter22", "hunter22")
+            .unwrap();
+        online.sign_in("alice", "hunter22").unwrap();
+        let user_id = online.user().unwrap().user_id();
+        let record = |score: i64, season: &str| GameRecord {
+            song_name: "Song".to_string(),
+            score,
+            max_combo: 10,
+            accuracy: 90.0,
+            hits: [10, 0, 0, 0],
+            play_time: 60,
+            practice: false,
+            season: Some(season.to_string()),
+        };
+        let current = SeasonCalendar::default().current();
+        for (score, season) in [(100, "2020-Q1"), (200, current.id.as_str())] {
+            online
+                .accounts
+                .record_game_sync(user_id, record(score, season))
+                .unwrap();
+        }
+
+        let mut hub =
+            OnlineHubState::new(OnlineTab::Leaderboard, BoardScope::Global, BoardSeason::Current);
+        hub.refresh(&online);
+        let past = Season::from_quarter_id("2020-Q1").unwrap();
+        assert_eq!(hub.past_seasons, vec![past.clone()]);
+        assert_eq!(hub.board[0].total_score, 200);
+
+        hub.cycle_board_season(false, &online);
+        assert_eq!(hub.board_season, BoardSeason::AllTime);
+        assert_eq!(hub.board[0].total_score, 300);
+        hub.cycle_board_season(false, &online);
+        assert_eq!(hub.board_season, BoardSeason::Past(past));
+        assert_eq!(hub.board[0].total_score, 100);
+        hub.cycle_board_season(false, &online);
+        assert_eq!(hub.board_season, BoardSeason::Current);
+        hub.cycle_board_season(true, &online);
+        assert_eq!(hub.board_season.label(), "2020 Q1");
+    }
+
+    #[test]
+    fn the_board_pages_and_jumps_to_the_players_rank() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-board-pages"));
+        for name in ["alice", "bob"] {
+            let email = format!("{}@example.com", name);
+            online
+                .register(name, &email, "hunter22", "hunter22")
+                .unwrap();
+        }
+        online.sign_in("bob", "hunter22").unwrap();
+        let bob = online.user().unwrap().user_id();
+        let record = GameRecord {
+            song_name: "Song".to_string(),
+            score: 500,
+            max_combo: 10,
+            accuracy: 90.0,
+            hits: [10, 0, 0, 0],
+            play_time: 60,
+            practice: false,
+            season: Some("2020-Q1".to_string()),
+        };
+        online.accounts.record_game_sync(bob, record).unwrap();
+        online.sign_out();
+        online.sign_in("alice", "hunter22").unwrap();
+
+        let mut hub =
+            OnlineHubState::new(OnlineTab::Leaderboard, BoardScope::Global, BoardSeason::Current);
+        hub.refresh(&online);
+        assert_eq!((hub.board_total, hub.own_rank), (0, None));
+        hub.jump_to_own_rank(&online);
+        assert_eq!(
+            hub.message,
+            Some(("You're not on this board yet".to_string(), true))
+        );
+
+        hub.cycle_board_season(false, &online);
+        assert_eq!((hub.board_total, hub.own_rank), (2, Some(2)));
+        // One page: paging either way stays on it
+        hub.page_board(false, &online);
+        hub.page_board(true, &online);
+        assert_eq!((hub.board_offset, hub.board.len()), (0, 2));
+        hub.jump_to_own_rank(&online);
+        assert_eq!((hub.board_offset, hub.message.clone()), (0, None));
+
+        // A page past the end of a shrunken board falls back to its last page
+        hub.board_offset = 20;
+        hub.refresh(&online);
+        assert_eq!((hub.board_offset, hub.board.len()), (0, 2));
+        assert_eq!(page_offset(ONLINE_LEADERBOARD_ROWS + 1), ONLINE_LEADERBOARD_ROWS);
+        assert_eq!(page_offset(ONLINE_LEADERBOARD_ROWS), 0);
+    }
+
+    #[test]
+    fn cleanup_runs_hourly_in_the_background_and_rereads_storage() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-maintenance"));
+        let now = Instant::now();
+        assert!(!online.maintenance.is_due(now));
+        assert!(online.maintenance.is_due(now + MAINTENANCE_INTERVAL));
+        assert!(online.maintenance.storage.summary().contains("users 0, sessions 0"));
+
+        online
+            .register("alice", "alice@example.com", "hunter22", "hunter22")
+            .unwrap();
+        online.sign_in("alice", "hunter22").unwrap();
+        online.start_maintenance(true);
+        assert!(online.maintenance.is_running());
+        assert!(!online.maintenance.is_due(now + MAINTENANCE_INTERVAL));
+
+        let result = loop {
+            if let Some(result) = online.poll_maintenance() {
+                break result;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        };
+        assert_eq!(result, Ok(MaintenanceReport::default()));
+        assert!(!online.maintenance.is_running());
+        assert!(online.user().is_some());
+        assert!(online.maintenance.storage.summary().contains("users 1, sessions 1"));
+    }
+
+    #[test]
+    fn new_friend_requests_badge_and_toast_once() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-inbox"));
+        for name in ["alice", "bob", "carol"] {
+            let email = format!("{}@example.com", name);
+            online
+                .register(name, &email, "hunter22", "hunter22")
+                .unwrap();
+        }
+        let bob = online
+            .accounts
+            .login_sync("bob".into(), "hunter22".into(), None);
+        let bob_id = bob.unwrap().user_id;
+        online
+            .accounts
+            .send_friend_request_sync(bob_id, "alice".to_string())
+            .unwrap();
+
+        // Waiting at sign-in: a badge, no toast
+        online.sign_in("alice", "hunter22").unwrap();
+        assert_eq!(online.inbox.badge().as_deref(), Some("1"));
+        assert!(!online.sync_inbox(&HashSet::new()));
+        assert_eq!(online.inbox.take_toast(), None);
+
+        let carol = online
+            .accounts
+            .login_sync("carol".into(), "hunter22".into(), None);
+        online
+            .accounts
+            .send_friend_request_sync(carol.unwrap().user_id, "alice".to_string())
+            .unwrap();
+        assert!(online.sync_inbox(&HashSet::new()));
+        assert_eq!(online.inbox.badge().as_deref(), Some("2"));
+        assert_eq!(
+            online.inbox.take_toast().as_deref(),
+            Some("Friend request from carol")
+        );
+        assert!(!online.sync_inbox(&HashSet::new()));
+        assert_eq!(online.inbox.take_toast(), None);
+
+        // Answering clears it from the badge
+        let alice_id = online.user().unwrap().user_id();
+        online
+            .accounts
+            .accept_friend_request_sync(alice_id, bob_id)
+            .unwrap();
+        assert!(online.sync_inbox(&HashSet::new()));
+        assert_eq!(online.inbox.badge().as_deref(), Some("1"));
+        online.sign_out();
+        assert_eq!(online.inbox.badge(), None);
+    }
+
+    #[test]
+    fn the_friends_cursor_runs_over_requests_then_friends() {
+        let mut hub = OnlineHubState::default();
+        let friend = |name: &str| Friend {
+            friend_id: Uuid::new_v4(),
+            username: name.to_string(),
+            status: crate::accounts::FriendStatus::Pending,
+            added_at: Utc::now(),
+        };
+        hub.friends.incoming = vec![friend("carol")];
+        hub.friends.friends = vec![friend("alice"), friend("bob")];
+        assert_eq!(hub.friend_rows(), 3);
+        assert_eq!(hub.selected_request().unwrap().username, "carol");
+        assert!(hub.selected_friend().is_none());
+
+        hub.friend_cursor = 2;
+        assert!(hub.selected_request().is_none());
+        assert_eq!(hub.selected_friend().unwrap().username, "bob");
+    }
+
+    #[test]
+    fn comparing_needs_a_signed_in_player() {
+        let mut online = OnlineServices::load_from(scratch_dir("online-compare"));
+        let bob = Friend {
+            friend_id: Uuid::new_v4(),
+            username: "bob".to_string(),
+            status: crate::accounts::FriendStatus::Accepted,
+            added_at: Utc::now(),
+        };
+        let view = online.compare_with(&bob, &Analytics::default());
+        assert_eq!(view.placeholder().as_deref(), Some("Sign in to compare"));
+    }
+}
@@ -0,0 +1,985 @@
+// src/osu_format.rs
+
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::beatmap::{
+    Beatmap, HitObject, HitObjectId, HitObjectKind, Hitsound, SampleSet, TimingPoint,
+    TICK_AMBIGUITY_EPSILON,
+};
+use crate::editor::PLAYFIELD_WIDTH;
+
+/// Format version written in the header
+const OSU_FORMAT_VERSION: u32 = 14;
+
+/// Size of osu!'s playfield; its origin is the top left corner and y points down
+const OSU_PLAYFIELD: Vec2 = Vec2::new(512.0, 384.0);
+
+/// osu! pixels per playfield pixel. Both playfields are 4:3, so one factor scales
+/// positions, slider lengths and the slider multiplier alike and slider durations
+/// come out the same
+pub const OSU_SCALE: f32 = 512.0 / PLAYFIELD_WIDTH;
+
+// Hit object type bits
+const TYPE_CIRCLE: u32 = 1;
+const TYPE_SLIDER: u32 = 2;
+const TYPE_NEW_COMBO: u32 = 4;
+const TYPE_SPINNER: u32 = 8;
+
+/// Points each curved slider segment is sampled into on import; slider paths here are
+/// straight segments
+const CURVE_SAMPLES: usize = 16;
+
+impl Beatmap {
+    /// The beatmap as the text of an `.osu` file. Per-slider velocities have no .osu
+    /// equivalent, so they are written as extra green lines at the sliders that need
+    /// them. Only the audio file's name is written; osu! looks for it next to the map
+    pub fn to_osu_string(&self) -> String {
+        let mut out = format!("osu file format v{}\n", OSU_FORMAT_VERSION);
+
+        let audio = Path::new(&self.audio_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.audio_path.clone());
+        let preview = if self.preview_time > 0.0 {
+            to_ms(self.preview_time)
+        } else {
+            -1
+        };
+        write_section(
+            &mut out,
+            "General",
+            [
+                format!("AudioFilename: {}", audio),
+                "AudioLeadIn: 0".to_string(),
+                format!("PreviewTime: {}", preview),
+                "Mode: 0".to_string(),
+                format!("StackLeniency: {}", self.settings.stack_leniency),
+            ],
+        );
+
+        let metadata = &self.metadata;
+        write_section(
+            &mut out,
+            "Metadata",
+            [
+                format!("Title:{}", metadata.title),
+                format!("Artist:{}", metadata.artist),
+                format!("Creator:{}", metadata.creator),
+                format!("Version:{}", metadata.version),
+                format!("Source:{}", metadata.source.as_deref().unwrap_or_default()),
+                format!("Tags:{}", self.tags.join(" ")),
+                format!("BeatmapID:{}", metadata.beatmap_id.unwrap_or(0)),
+                format!(
+                    "BeatmapSetID:{}",
+                    metadata.set_id.map_or(-1, |id| id as i64)
+                ),
+            ],
+        );
+
+        let settings = &self.settings;
+        write_section(
+            &mut out,
+            "Difficulty",
+            [
+                format!("HPDrainRate:{}", settings.hp_drain),
+                format!("CircleSize:{}", settings.circle_size),
+                format!("OverallDifficulty:{}", settings.overall_difficulty),
+                format!("ApproachRate:{}", settings.approach_rate),
+                format!(
+                    "SliderMultiplier:{}",
+                    settings.slider_multiplier * OSU_SCALE as f64
+                ),
+                format!("SliderTickRate:{}", settings.slider_tick_rate),
+            ],
+        );
+
+        write_section(
+            &mut out,
+            "TimingPoints",
+            self.osu_timing_points().iter().map(timing_point_line),
+        );
+        write_section(
+            &mut out,
+            "HitObjects",
+            self.hit_objects.iter().map(hit_object_line),
+        );
+        out
+    }
+
+    /// Timing points as written to an .osu file: kiai flags from the kiai sections, and
+    /// a green line wherever a slider's own velocity changes the SV it plays at
+    fn osu_timing_points(&self) -> Vec<TimingPoint> {
+        let mut timeline = Beatmap {
+            timing_points: self.timing_points.clone(),
+            ..Default::default()
+        };
+        timeline.sort_timing_points();
+
+        // Kiai sections start and end on green lines keeping the SV in effect
+        for section in &self.kiai_sections {
+            for time in [section.start, section.end] {
+                let line = green_line(&timeline.timing_points, time, self.slider_velocity_at(time));
+                timeline.timing_points.push(line);
+            }
+        }
+        timeline.sort_timing_points();
+
+        for object in &self.hit_objects {
+            let HitObjectKind::Slider { velocity, .. } = object.kind else {
+                continue;
+            };
+            let wanted = self.slider_velocity_at(object.time) * velocity;
+            if (timeline.slider_velocity_at(object.time) - wanted).abs() < 1e-9 {
+                continue;
+            }
+            // Not clamped like an editor SV point, so the slider keeps its duration
+            let line = green_line(&timeline.timing_points, object.time, wanted);
+            timeline.timing_points.retain(|tp| {
+                !(tp.inherited && (tp.time - object.time).abs() < TICK_AMBIGUITY_EPSILON)
+            });
+            timeline.timing_points.push(line);
+            timeline.sort_timing_points();
+        }
+
+        if !self.kiai_sections.is_empty() {
+            for point in &mut timeline.timing_points {
+                point.kiai = self.kiai_at(point.time).is_some();
+            }
+        }
+        timeline.timing_points
+    }
+
+    /// Read the text of an `.osu` file. Only osu!standard maps are supported. Curved
+    /// slider paths are sampled into straight segments, and `audio_path` is left
+    /// relative to the .osu file's folder
+    pub fn from_osu_str(text: &str) -> Result<Beatmap, String> {
+        let mut beatmap = Beatmap {
+            timing_points: Vec::new(),
+            ..Default::default()
+        };
+        // The same points with the SV files ask for, before clamping
+        let mut unclamped = Beatmap {
+            timing_points: Vec::new(),
+            ..Default::default()
+        };
+        let mut approach_rate_set = false;
+        let mut object_lines = Vec::new();
+
+        let mut section = "";
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+                continue;
+            }
+            let at_line = |e: String| format!("line {}: {}", index + 1, e);
+            match section {
+                "General" | "Metadata" | "Difficulty" => {
+                    let Some((key, value)) = line.split_once(':') else {
+                        continue;
+                    };
+                    approach_rate_set |= key.trim() == "ApproachRate";
+                    apply_key(&mut beatmap, key.trim(), value.trim()).map_err(at_line)?;
+                }
+                "TimingPoints" => {
+                    let (point, raw_velocity) = parse_timing_point(line).map_err(at_line)?;
+                    unclamped.timing_points.push(TimingPoint {
+                        slider_velocity: raw_velocity,
+                        ..point.clone()
+                    });
+                    beatmap.timing_points.push(point);
+                }
+                "HitObjects" => object_lines.push((index, line)),
+                _ => {}
+            }
+        }
+
+        if !beatmap.timing_points.iter().any(|tp| !tp.inherited) {
+            return Err("the map has no uninherited timing points".to_string());
+        }
+        // Files from before ApproachRate existed used OD for both
+        if !approach_rate_set {
+            beatmap.settings.approach_rate = beatmap.settings.overall_difficulty;
+        }
+        beatmap.sort_timing_points();
+        unclamped.sort_timing_points();
+
+        // Whatever SV clamping took off a slider goes into its own velocity
+        let extra_velocity =
+            |time: f64| unclamped.slider_velocity_at(time) / beatmap.slider_velocity_at(time);
+        let objects = object_lines
+            .into_iter()
+            .enumerate()
+            .map(|(n, (index, line))| {
+                parse_hit_object(line, n as HitObjectId + 1, &extra_velocity)
+                    .map_err(|e| format!("line {}: {}", index + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        beatmap.hit_objects = objects;
+        beatmap.sort_hit_objects();
+        beatmap.kiai_sections = beatmap.kiai_from_timing_points();
+        Ok(beatmap)
+    }
+}
+
+/// Append a `[name]` section with one line per entry
+fn write_section(out: &mut String, name: &str, lines: impl IntoIterator<Item = String>) {
+    out.push_str(&format!("\n[{}]\n", name));
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+/// Seconds to whole milliseconds, as .osu files store times
+fn to_ms(seconds: f64) -> i64 {
+    (seconds * 1000.0).round() as i64
+}
+
+/// Playfield position to osu! pixels
+fn to_osu_position(position: Vec2) -> Vec2 {
+    Vec2::new(position.x, -position.y) * OSU_SCALE + OSU_PLAYFIELD / 2.0
+}
+
+/// osu! pixels to a playfield position
+fn from_osu_position(osu: Vec2) -> Vec2 {
+    let centered = (osu - OSU_PLAYFIELD / 2.0) / OSU_SCALE;
+    Vec2::new(centered.x, -centered.y)
+}
+
+/// "x:y" or "x,y" of a position in whole osu! pixels
+fn osu_coordinates(position: Vec2, separator: char) -> String {
+    let osu = to_osu_position(position);
+    format!(
+        "{}{}{}",
+        osu.x.round() as i32,
+        separator,
+        osu.y.round() as i32
+    )
+}
+
+/// Green line at `time` setting `slider_velocity`, keeping the volume and kiai of the
+/// point in effect there
+fn green_line(points: &[TimingPoint], time: f64, slider_velocity: f64) -> TimingPoint {
+    let current = points.iter().rev().find(|tp| tp.time <= time);
+    TimingPoint {
+        time,
+        inherited: true,
+        slider_velocity,
+        volume: current.map_or(100, |tp| tp.volume),
+        kiai: current.is_some_and(|tp| tp.kiai),
+        ..Default::default()
+    }
+}
+
+/// A `[TimingPoints]` line: time, beat length, meter, sample set, sample index, volume,
+/// uninherited, effects
+fn timing_point_line(point: &TimingPoint) -> String {
+    let (beat_length, uninherited) = if point.inherited {
+        (-100.0 / point.slider_velocity, 0)
+    } else {
+        (60_000.0 / point.bpm, 1)
+    };
+    format!(
+        "{},{},{},0,0,{},{},{}",
+        to_ms(point.time),
+        beat_length,
+        point.meter,
+        point.volume,
+        uninherited,
+        point.kiai as u32
+    )
+}
+
+/// osu! hitsound bits of a hitsound
+fn hitsound_bits(hitsound: Hitsound) -> u32 {
+    match hitsound {
+        Hitsound::Normal => 0,
+        Hitsound::Whistle => 2,
+        Hitsound::Finish => 4,
+        Hitsound::Clap => 8,
+    }
+}
+
+/// Hitsound from osu! hitsound bits. osu! allows several additions at once; only the
+/// first is kept
+fn hitsound_from_bits(bits: u32) -> Hitsound {
+    if bits & 2 != 0 {
+        Hitsound::Whistle
+    } else if bits & 4 != 0 {
+        Hitsound::Finish
+    } else if bits & 8 != 0 {
+        Hitsound::Clap
+    } else {
+        Hitsound::Normal
+    }
+}
+
+/// ",normalSet:additionSet:index:volume:filename", or nothing without a sample set
+fn hit_sample(sample_set: &Option<SampleSet>) -> String {
+    sample_set
+        .as_ref()
+        .map(|set| {
+            format!(
+                ",{}:{}:{}:{}:{}",
+                set.normal_set,
+                set.addition_set,
+                set.index,
+                set.volume,
+                set.filename.as_deref().unwrap_or_default()
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// A `[HitObjects]` line. Slider paths are written as linear curves, which is what
+/// they are here; sliders carry no hit sample since edge sounds aren't kept
+fn hit_object_line(object: &HitObject) -> String {
+    let combo = if object.new_combo { TYPE_NEW_COMBO } else { 0 };
+    let hitsound = hitsound_bits(object.hitsound);
+    let time = to_ms(object.time);
+    let position = osu_coordinates(object.position, ',');
+    match &object.kind {
+        HitObjectKind::Circle => format!(
+            "{},{},{},{}{}",
+            position,
+            time,
+            TYPE_CIRCLE | combo,
+            hitsound,
+            hit_sample(&object.sample_set)
+        ),
+        HitObjectKind::Slider {
+            control_points,
+            repeats,
+            pixel_length,
+            ..
+        } => {
+            // The head is the object's position; osu! needs at least one more point
+            let mut points: Vec<String> = control_points
+                .iter()
+                .skip(1)
+                .map(|point| osu_coordinates(*point, ':'))
+                .collect();
+            if points.is_empty() {
+                points.push(osu_coordinates(object.position, ':'));
+            }
+            format!(
+                "{},{},{},{},L|{},{},{}",
+                position,
+                time,
+                TYPE_SLIDER | combo,
+                hitsound,
+                points.join("|"),
+                repeats + 1,
+                pixel_length * OSU_SCALE as f64
+            )
+        }
+        HitObjectKind::Spinner { end_time } => format!(
+            "{},{},{},{},{}{}",
+            osu_coordinates(Vec2::ZERO, ','),
+            time,
+            TYPE_SPINNER | combo,
+            hitsound,
+            to_ms(*end_time),
+            hit_sample(&object.sample_set)
+        ),
+    }
+}
+
+/// Parse a number, naming the field when it isn't one
+fn number<T: FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("{} \"{}\" isn't a number", what, value))
+}
+
+/// Apply a `key: value` line from `[General]`, `[Metadata]` or `[Difficulty]`;
+/// keys this format has no use for are skipped
+fn apply_key(beatmap: &mut Beatmap, key: &str, value: &str) -> Result<(), String> {
+    let online_id = |value: &str| -> Result<Option<u64>, String> {
+        let id: i64 = number(value, key)?;
+        Ok((id > 0).then_some(id as u64))
+    };
+    let metadata = &mut beatmap.metadata;
+    let settings = &mut beatmap.settings;
+    match key {
+        "Mode" if value != "0" => {
+            return Err(format!(
+                "only osu!standard maps can be imported (mode {})",
+                value
+            ))
+        }
+        "AudioFilename" => beatmap.audio_path = value.to_string(),
+        "PreviewTime" => {
+            let ms: f64 = number(value, key)?;
+            beatmap.preview_time = (ms / 1000.0).max(0.0);
+        }
+        "StackLeniency" => settings.stack_leniency = number(value, key)?,
+        "Title" => metadata.title = value.to_string(),
+        "Artist" => metadata.artist = value.to_string(),
+        "Creator" => metadata.creator = value.to_string(),
+        "Version" => metadata.version = value.to_string(),
+        "Source" => metadata.source = (!value.is_empty()).then(|| value.to_string()),
+        "Tags" => beatmap.tags = value.split_whitespace().map(str::to_string).collect(),
+        "BeatmapID" => metadata.beatmap_id = online_id(value)?,
+        "BeatmapSetID" => metadata.set_id = online_id(value)?,
+        "HPDrainRate" => settings.hp_drain = number(value, key)?,
+        "CircleSize" => settings.circle_size = number(value, key)?,
+        "OverallDifficulty" => settings.overall_difficulty = number(value, key)?,
+        "ApproachRate" => settings.approach_rate = number(value, key)?,
+        "SliderMultiplier" => {
+            let multiplier: f64 = number(value, key)?;
+            settings.slider_multiplier = multiplier / OSU_SCALE as f64;
+        }
+        "SliderTickRate" => settings.slider_tick_rate = number(value, key)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A `[TimingPoints]` line as a timing point and the SV it asks for before clamping.
+/// Older files leave out the fields after the beat length
+fn parse_timing_point(line: &str) -> Result<(TimingPoint, f64), String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() < 2 {
+        return Err("timing point needs a time and a beat length".to_string());
+    }
+    let field = |index: usize| fields.get(index).copied().filter(|f| !f.is_empty());
+    let time_ms: f64 = number(fields[0], "time")?;
+    let beat_length: f64 = number(fields[1], "beat length")?;
+    let meter = field(2).map(|f| number(f, "meter")).transpose()?;
+    let volume = field(5).map(|f| number::<u32>(f, "volume")).transpose()?;
+    let uninherited = field(6)
+        .map(|f| number::<u32>(f, "uninherited"))
+        .transpose()?
+        .map_or(beat_length > 0.0, |flag| flag != 0);
+    let effects = field(7).map(|f| number(f, "effects")).transpose()?;
+
+    let point = TimingPoint {
+        volume: volume.unwrap_or(100).min(100),
+        ..TimingPoint::from_osu(
+            time_ms,
+            beat_length,
+            meter.unwrap_or(4),
+            uninherited,
+            effects.unwrap_or(0),
+        )
+    };
+    let raw_velocity = if point.inherited && beat_length < 0.0 {
+        -100.0 / beat_length
+    } else {
+        point.slider_velocity
+    };
+    Ok((point, raw_velocity))
+}
+
+/// Sample set from a hit sample field, None when it only has the defaults
+fn parse_hit_sample(field: Option<&str>) -> Result<Option<SampleSet>, String> {
+    let Some(field) = field.filter(|f| !f.is_empty()) else {
+        return Ok(None);
+    };
+    let parts: Vec<&str> = field.split(':').collect();
+    let part = |index: usize| -> Result<u32, String> {
+        parts
+            .get(index)
+            .filter(|p| !p.is_empty())
+            .map_or(Ok(0), |p| number(p, "hit sample"))
+    };
+    let filename = parts
+        .get(4)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string());
+    let set = SampleSet {
+        normal_set: part(0)?,
+        addition_set: part(1)?,
+        index: part(2)?,
+        volume: part(3)?,
+        filename,
+    };
+    let is_default = set.normal_set == 0
+        && set.addition_set == 0
+        && set.index == 0
+        && set.volume == 0
+        && set.filename.is_none();
+    Ok((!is_default).then_some(set))
+}
+
+/// A `[HitObjects]` line. `extra_velocity` gives the velocity a slider starting at a
+/// time needs on top of the map's SV
+fn parse_hit_object(
+    line: &str,
+    id: HitObjectId,
+    extra_velocity: &dyn Fn(f64) -> f64,
+) -> Result<HitObject, String> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() < 5 {
+        return Err("hit object needs a position, time, type and hitsound".to_string());
+    }
+    let osu_position = Vec2::new(number(fields[0], "x")?, number(fields[1], "y")?);
+    let time = number::<f64>(fields[2], "time")? / 1000.0;
+    let kind_bits: u32 = number(fields[3], "type")?;
+    let hitsound = hitsound_from_bits(number(fields[4], "hitsound")?);
+
+    let (kind, sample_field) = if kind_bits & TYPE_SLIDER != 0 {
+        let curve = fields.get(5).ok_or("slider has no curve")?;
+        let path = slider_path(osu_position, curve)?;
+        let slides: u32 = fields.get(6).map_or(Ok(1), |f| number(f, "slides"))?;
+        let pixel_length = match fields.get(7) {
+            Some(f) => number::<f64>(f, "length")? / OSU_SCALE as f64,
+            None => path.windows(2).map(|p| p[0].distance(p[1]) as f64).sum(),
+        };
+        let kind = HitObjectKind::Slider {
+            control_points: path,
+            repeats: slides.max(1) - 1,
+            pixel_length,
+            velocity: extra_velocity(time),
+        };
+        (kind, fields.get(10))
+    } else if kind_bits & TYPE_SPINNER != 0 {
+        let end_ms: f64 = number(fields.get(5).ok_or("spinner has no end time")?, "end time")?;
+        let kind = HitObjectKind::Spinner {
+            end_time: (end_ms / 1000.0).max(time),
+        };
+        (kind, fields.get(6))
+    } else if kind_bits & TYPE_CIRCLE != 0 {
+        (HitObjectKind::Circle, fields.get(5))
+    } else {
+        return Err(format!("unsupported hit object type {}", kind_bits));
+    };
+
+    Ok(HitObject {
+        id,
+        time,
+        position: from_osu_position(osu_position),
+        kind,
+        new_combo: kind_bits & TYPE_NEW_COMBO != 0,
+        combo_index: 0,
+        hitsound,
+        sample_set: parse_hit_sample(sample_field.copied())?,
+    })
+}
+
+/// Slider path from an .osu curve ("B|x:y|x:y"): the head, then the curve points, with
+/// bezier and perfect circle curves sampled into straight segments. Catmull curves are
+/// kept as drawn through their points
+fn slider_path(head: Vec2, curve: &str) -> Result<Vec<Vec2>, String> {
+    let mut parts = curve.split('|');
+    let curve_type = parts.next().unwrap_or("L");
+    let mut points = vec![head];
+    for part in parts {
+        let (x, y) = part
+            .split_once(':')
+            .ok_or_else(|| format!("bad curve point \"{}\"", part))?;
+        points.push(Vec2::new(number(x, "curve x")?, number(y, "curve y")?));
+    }
+
+    let sampled = match curve_type {
+        "P" if points.len() == 3 => circular_arc(&points).unwrap_or(points),
+        "B" => bezier(&points),
+        _ => points,
+    };
+    Ok(sampled.into_iter().map(from_osu_position).collect())
+}
+
+/// Bezier curve sampled into points; a repeated point (a red anchor) ends one curve and
+/// starts the next
+fn bezier(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sampled = vec![points[0]];
+    let mut segment = vec![points[0]];
+    for (i, &point) in points.iter().enumerate().skip(1) {
+        if point == points[i - 1] {
+            continue;
+        }
+        segment.push(point);
+        if i + 1 < points.len() && points[i + 1] != point {
+            continue;
+        }
+        if segment.len() == 2 {
+            sampled.push(point);
+        } else {
+            sampled.extend(
+                (1..=CURVE_SAMPLES)
+                    .map(|n| de_casteljau(&segment, n as f32 / CURVE_SAMPLES as f32)),
+            );
+        }
+        segment = vec![point];
+    }
+    sampled
+}
+
+/// Point at `t` (0.0 - 1.0) along a bezier curve
+fn de_casteljau(points: &[Vec2], t: f32) -> Vec2 {
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| pair[0].lerp(pair[1], t))
+            .collect();
+    }
+    points[0]
+}
+
+/// Arc from the first point through the second to the third, sampled into points.
+/// None when the points are in a line
+fn circular_arc(points: &[Vec2]) -> Option<Vec<Vec2>> {
+    let (a, b, c) = (points[0], points[1], points[2]);
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < 1e-3 {
+        return None;
+    }
+    let (a2, b2, c2) = (a.length_squared(), b.length_squared(), c.length_squared());
+    let center = Vec2::new(
+        (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+        (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+    );
+    let radius = a.distance(center);
+    let start = (a - center).to_angle();
+    let mut end = (c - center).to_angle();
+    // Go around the side the middle point is on
+    if (b - a).perp_dot(c - b) > 0.0 {
+        while end < start {
+            end += TAU;
+        }
+    } else {
+        while end > start {
+            end -= TAU;
+        }
+    }
+    let samples = CURVE_SAMPLES * 2;
+    Some(
+        (0..=samples)
+            .map(|n| {
+                let angle = start + (end - start) * n as f32 / samples as f32;
+                center + Vec2::from_angle(angle) * radius
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beatmap::{KiaiSection, MAX_SLIDER_VELOCITY};
+
+    /// An .osu file with the given timing point and hit object lines
+    fn osu_file(difficulty: &str, timing_points: &str, hit_objects: &str) -> String {
+        format!(
+            "osu file format v14\n\n[General]\nAudioFilename: audio.mp3\nMode: 0\n\n\
+             [Difficulty]\n{}\nSliderMultiplier:1.12\n\n[TimingPoints]\n{}\n\n\
+             [HitObjects]\n{}\n",
+            difficulty, timing_points, hit_objects
+        )
+    }
+
+    fn parse(timing_points: &str, hit_objects: &str) -> Beatmap {
+        Beatmap::from_osu_str(&osu_file("OverallDifficulty:6", timing_points, hit_objects)).unwrap()
+    }
+
+    fn roundtrip(beatmap: &Beatmap) -> Beatmap {
+        Beatmap::from_osu_str(&beatmap.to_osu_string()).unwrap()
+    }
+
+    fn section<'a>(text: &'a str, name: &str) -> Vec<&'a str> {
+        text.lines()
+            .skip_while(|line| *line != format!("[{}]", name))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .collect()
+    }
+
+    fn object(id: HitObjectId, time: f64, position: Vec2, kind: HitObjectKind) -> HitObject {
+        HitObject {
+            id,
+            time,
+            position,
+            kind,
+            new_combo: false,
+            combo_index: 0,
+            hitsound: Hitsound::Normal,
+            sample_set: None,
+        }
+    }
+
+    fn slider(control_points: Vec<Vec2>, pixel_length: f64, velocity: f64) -> HitObjectKind {
+        HitObjectKind::Slider {
+            control_points,
+            repeats: 0,
+            pixel_length,
+            velocity,
+        }
+    }
+
+    fn slider_parts(object: &HitObject) -> (&[Vec2], f64, f64) {
+        match &object.kind {
+            HitObjectKind::Slider {
+                control_points,
+                pixel_length,
+                velocity,
+                ..
+            } => (control_points, *pixel_length, *velocity),
+            kind => panic!("expected a slider, got {:?}", kind),
+        }
+    }
+
+    fn assert_near(a: Vec2, b: Vec2, tolerance: f32) {
+        assert!(a.distance(b) <= tolerance, "{:?} is not near {:?}", a, b);
+    }
+
+    #[test]
+    fn positions_and_lengths_scale_by_osu_scale() {
+        let mut beatmap = Beatmap::new(
+            "Round trip".to_string(),
+            "tests".to_string(),
+            "songs/audio.mp3".to_string(),
+        );
+        let head = Vec2::new(-100.0, 50.0);
+        beatmap.hit_objects = vec![
+            object(1, 1.0, Vec2::new(100.0, -50.0), HitObjectKind::Circle),
+            object(
+                2,
+                2.0,
+                head,
+                slider(vec![head, head + Vec2::new(200.0, 0.0)], 200.0, 1.0),
+            ),
+        ];
+
+        let text = beatmap.to_osu_string();
+        assert!(text.contains("AudioFilename: audio.mp3\n"));
+        // 100 right of center is 80 osu! pixels right; playfield y points up
+        let objects = section(&text, "HitObjects");
+        assert_eq!(objects[0], "336,232,1000,1,0");
+        assert!(objects[1].starts_with("176,152,2000,2,0,L|336:152,1,"));
+
+        let back = roundtrip(&beatmap);
+        assert_near(back.hit_objects[0].position, Vec2::new(100.0, -50.0), 1e-3);
+        let (points, pixel_length, velocity) = slider_parts(&back.hit_objects[1]);
+        assert_eq!(points.len(), 2);
+        assert_near(points[0], head, 1e-3);
+        assert_near(points[1], head + Vec2::new(200.0, 0.0), 1e-3);
+        assert!((pixel_length - 200.0).abs() < 1e-4);
+        assert_eq!(velocity, 1.0);
+        let multiplier = back.settings.slider_multiplier;
+        assert!((multiplier - beatmap.settings.slider_multiplier).abs() < 1e-6);
+        // Lengths and the multiplier scale alike, so the slider lasts as long
+        let duration = |map: &Beatmap| map.slider_span_duration(2.0, 200.0, 1.0);
+        assert!((duration(&back) - duration(&beatmap)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn negative_beat_lengths_are_green_lines() {
+        let beatmap = parse(
+            "0,500,4,0,0,100,1,0\n1000,-50,4,0,0,80,0,0",
+            "256,192,1500,1,0",
+        );
+        let green = &beatmap.timing_points[1];
+        assert!(green.inherited);
+        assert_eq!(green.slider_velocity, 2.0);
+        assert_eq!(green.volume, 80);
+        assert_eq!(beatmap.slider_velocity_at(0.5), 1.0);
+        assert_eq!(beatmap.slider_velocity_at(1.5), 2.0);
+
+        let lines = section(&beatmap.to_osu_string(), "TimingPoints").join("\n");
+        assert_eq!(lines, "0,500,4,0,0,100,1,0\n1000,-50,4,0,0,80,0,0");
+    }
+
+    #[test]
+    fn clamped_velocity_moves_onto_the_sliders() {
+        // -20 asks for 5x SV, over the editor's limit
+        let beatmap = parse(
+            "0,500,4,0,0,100,1,0\n1000,-20,4,0,0,100,0,0",
+            "256,192,500,2,0,L|356:192,1,100\n256,192,1000,2,0,L|356:192,1,100",
+        );
+        assert_eq!(beatmap.slider_velocity_at(1.0), MAX_SLIDER_VELOCITY);
+        let (_, _, before) = slider_parts(&beatmap.hit_objects[0]);
+        let (_, _, after) = slider_parts(&beatmap.hit_objects[1]);
+        assert_eq!(before, 1.0);
+        assert!((after - 5.0 / MAX_SLIDER_VELOCITY).abs() < 1e-9);
+
+        // Written back as the SV the file asked for, and read the same again
+        let text = beatmap.to_osu_string();
+        assert!(section(&text, "TimingPoints").contains(&"1000,-20,4,0,0,100,0,0"));
+        let back = Beatmap::from_osu_str(&text).unwrap();
+        let (_, _, again) = slider_parts(&back.hit_objects[1]);
+        assert!((again - after).abs() < 1e-9);
+        assert_eq!(back.slider_velocity_at(1.0), MAX_SLIDER_VELOCITY);
+    }
+
+    #[test]
+    fn slider_velocity_gets_its_own_green_line() {
+        let mut beatmap = Beatmap::new(String::new(), String::new(), "audio.mp3".to_string());
+        let points = vec![Vec2::ZERO, Vec2::new(100.0, 0.0)];
+        beatmap.hit_objects = vec![
+            object(1, 1.0, Vec2::ZERO, slider(points.clone(), 100.0, 1.0)),
+            object(2, 2.0, Vec2::ZERO, slider(points, 100.0, 1.5)),
+        ];
+        let lines = section(&beatmap.to_osu_string(), "TimingPoints").join("\n");
+        assert!(
+            lines.ends_with("\n2000,-66.66666666666667,4,0,0,100,0,0"),
+            "{}",
+            lines
+        );
+
+        let back = roundtrip(&beatmap);
+        assert_eq!(slider_parts(&back.hit_objects[0]).2, 1.0);
+        assert!((back.slider_velocity_at(2.0) - 1.5).abs() < 1e-9);
+        assert!((slider_parts(&back.hit_objects[1]).2 - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn timing_points_without_optional_fields_use_defaults() {
+        let beatmap = parse("0,500\n1000,-200\n2000,400,3", "256,192,2500,1,0");
+        let points = &beatmap.timing_points;
+        assert_eq!(points.len(), 3);
+
+        assert!(!points[0].inherited);
+        assert_eq!(points[0].bpm, 120.0);
+        assert_eq!(points[0].meter, 4);
+        assert_eq!(points[0].volume, 100);
+        assert!(!points[0].kiai);
+        // Without the uninherited field the sign of the beat length decides
+        assert!(points[1].inherited);
+        assert_eq!(points[1].slider_velocity, 0.5);
+        assert!(!points[2].inherited);
+        assert_eq!(points[2].bpm, 150.0);
+        assert_eq!(points[2].meter, 3);
+
+        let error = Beatmap::from_osu_str(&osu_file("", "1000", "")).unwrap_err();
+        assert_eq!(
+            error,
+            "line 12: timing point needs a time and a beat length"
+        );
+        let error = Beatmap::from_osu_str(&osu_file("", "1000,-100", "")).unwrap_err();
+        assert_eq!(error, "the map has no uninherited timing points");
+    }
+
+    #[test]
+    fn approach_rate_falls_back_to_overall_difficulty() {
+        let timing = "0,500,4,0,0,100,1,0";
+        let old = Beatmap::from_osu_str(&osu_file("OverallDifficulty:7", timing, "")).unwrap();
+        assert_eq!(old.settings.approach_rate, 7.0);
+        // Order doesn't matter, an ApproachRate anywhere wins
+        let difficulty = "ApproachRate:9.5\nOverallDifficulty:7";
+        let new = Beatmap::from_osu_str(&osu_file(difficulty, timing, "")).unwrap();
+        assert_eq!(new.settings.approach_rate, 9.5);
+        assert_eq!(new.settings.overall_difficulty, 7.0);
+    }
+
+    #[test]
+    fn kiai_flags_become_sections_and_back() {
+        let beatmap = parse(
+            "0,500,4,0,0,100,1,1\n2000,-100,4,0,0,100,0,0\n3000,-100,4,0,0,100,0,1",
+            "256,192,4000,1,0",
+        );
+        assert_eq!(
+            beatmap.kiai_sections,
+            [
+                KiaiSection {
+                    start: 0.0,
+                    end: 2.0
+                },
+                KiaiSection {
+                    start: 3.0,
+                    end: 4.0
+                },
+            ]
+        );
+
+        let mut made = Beatmap::new(String::new(), String::new(), "audio.mp3".to_string());
+        made.hit_objects = vec![object(1, 4.0, Vec2::ZERO, HitObjectKind::Circle)];
+        made.add_kiai_section(KiaiSection {
+            start: 1.0,
+            end: 3.0,
+        })
+        .unwrap();
+        let text = made.to_osu_string();
+        assert_eq!(
+            section(&text, "TimingPoints"),
+            [
+                "0,500,4,0,0,100,1,0",
+                "1000,-100,4,0,0,100,0,1",
+                "3000,-100,4,0,0,100,0,0",
+            ]
+        );
+        assert_eq!(roundtrip(&made).kiai_sections, made.kiai_sections);
+    }
+
+    #[test]
+    fn bezier_sliders_are_sampled_and_split_at_red_anchors() {
+        let beatmap = parse(
+            "0,500,4,0,0,100,1,0",
+            "100,100,1000,2,0,B|200:0|300:100,1,200\n100,100,2000,2,0,B|200:100|200:100|300:200,1,240",
+        );
+        let (curve, _, _) = slider_parts(&beatmap.hit_objects[0]);
+        assert_eq!(curve.len(), CURVE_SAMPLES + 1);
+        assert_eq!(curve[0], from_osu_position(Vec2::new(100.0, 100.0)));
+        // Halfway along a quadratic curve is a quarter of each end and half the middle
+        assert_near(
+            curve[CURVE_SAMPLES / 2],
+            from_osu_position(Vec2::new(200.0, 50.0)),
+            1e-3,
+        );
+        assert_near(
+            curve[CURVE_SAMPLES],
+            from_osu_position(Vec2::new(300.0, 100.0)),
+            1e-3,
+        );
+
+        // A doubled point ends a segment; two points to a segment stay a straight line
+        let (anchored, _, _) = slider_parts(&beatmap.hit_objects[1]);
+        let expected: Vec<Vec2> = [(100.0, 100.0), (200.0, 100.0), (300.0, 200.0)]
+            .into_iter()
+            .map(|(x, y)| from_osu_position(Vec2::new(x, y)))
+            .collect();
+        assert_eq!(anchored, expected);
+
+        // Written back as the sampled line, to within a whole osu! pixel
+        let back = roundtrip(&beatmap);
+        let (again, _, _) = slider_parts(&back.hit_objects[0]);
+        assert_eq!(again.len(), curve.len());
+        for (a, b) in again.iter().zip(curve) {
+            assert_near(*a, *b, 1.0 / OSU_SCALE);
+        }
+        assert!(section(&beatmap.to_osu_string(), "HitObjects")[0].contains(",L|"));
+    }
+
+    #[test]
+    fn perfect_circle_sliders_follow_the_arc() {
+        let beatmap = parse(
+            "0,500,4,0,0,100,1,0",
+            "156,192,1000,2,0,P|256:92|356:192,1,314\n156,192,2000,2,0,P|256:192|356:192,1,200",
+        );
+        let (arc, _, _) = slider_parts(&beatmap.hit_objects[0]);
+        assert_eq!(arc.len(), CURVE_SAMPLES * 2 + 1);
+        // Every point sits on the circle through all three, 100 osu! pixels around
+        // the playfield's center
+        for point in arc {
+            assert!(
+                (point.length() - 100.0 / OSU_SCALE).abs() < 1e-2,
+                "{:?}",
+                point
+            );
+        }
+        assert_near(arc[0], from_osu_position(Vec2::new(156.0, 192.0)), 1e-2);
+        assert_near(
+            arc[CURVE_SAMPLES],
+            from_osu_position(Vec2::new(256.0, 92.0)),
+            1e-2,
+        );
+        assert_near(
+            arc[CURVE_SAMPLES * 2],
+            from_osu_position(Vec2::new(356.0, 192.0)),
+            1e-2,
+        );
+
+        // Points in a line have no circle through them and are kept as they are
+        let (line, _, _) = slider_parts(&beatmap.hit_objects[1]);
+        assert_eq!(line.len(), 3);
+    }
+}
@@ -0,0 +1,215 @@
+// src/performance.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::config::GameConfig;
+use crate::constants::*;
+use crate::structs::GameAssets;
+use crate::toasts::Toasts;
+use crate::ui::UiElement;
+
+/// Seconds the first-run benchmark measures for
+pub const BENCHMARK_SECONDS: f64 = 1.0;
+
+/// Frames skipped before measuring, while the scene's first frames settle
+const BENCHMARK_WARMUP_FRAMES: u32 = 10;
+
+/// Translucent layered sprites drawn every benchmark frame, about what a busy
+/// screen draws at once
+const BENCHMARK_SPRITES: usize = 1500;
+
+/// Mean frame times the benchmark allows for each preset (milliseconds). Above 60 Hz
+/// vsync, so a machine holding its refresh rate still gets Quality
+const QUALITY_FRAME_MS: f64 = 20.0;
+const BALANCED_FRAME_MS: f64 = 33.0;
+
+/// How many cosmetic extras are drawn, traded against frame rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PerformancePreset {
+    /// Everything the theme turns on
+    #[default]
+    Quality,
+    /// No pulsing or kiai background
+    Balanced,
+    /// Flat circles only, for old hardware
+    Potato,
+}
+
+impl PerformancePreset {
+    /// All presets, richest first
+    pub fn all() -> Vec<PerformancePreset> {
+        vec![
+            PerformancePreset::Quality,
+            PerformancePreset::Balanced,
+            PerformancePreset::Potato,
+        ]
+    }
+
+    /// Get display name for the preset
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            PerformancePreset::Quality => "Quality",
+            PerformancePreset::Balanced => "Balanced",
+            PerformancePreset::Potato => "Potato",
+        }
+    }
+
+    /// The preset `direction` steps away, wrapping around; 0 counts as forward
+    pub fn cycled(&self, direction: i32) -> PerformancePreset {
+        let all = Self::all();
+        let index = all.iter().position(|preset| preset == self).unwrap_or(0) as i32;
+        let step = if direction < 0 { -1 } else { 1 };
+        all[(index + step).rem_euclid(all.len() as i32) as usize]
+    }
+
+    /// Preset for a measured mean frame time (milliseconds)
+    pub fn for_frame_time(frame_ms: f64) -> PerformancePreset {
+        if frame_ms <= QUALITY_FRAME_MS {
+            PerformancePreset::Quality
+        } else if frame_ms <= BALANCED_FRAME_MS {
+            PerformancePreset::Balanced
+        } else {
+            PerformancePreset::Potato
+        }
+    }
+}
+
+/// What the active preset lets draw, worked out once when the preset changes so
+/// draw code only checks a flag. Theme toggles still apply on top
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct RenderGates {
+    pub preset: PerformancePreset,
+    /// Circle outline glow and the kiai glow boost
+    pub glow: bool,
+    /// Circle and approach ring pulsing
+    pub pulse: bool,
+    /// Kiai background brightening
+    pub kiai_background: bool,
+    pub follow_points: bool,
+    pub hit_lighting: bool,
+}
+
+impl RenderGates {
+    pub fn new(preset: PerformancePreset) -> Self {
+        let quality = preset == PerformancePreset::Quality;
+        let potato = preset == PerformancePreset::Potato;
+        Self {
+            preset,
+            glow: !potato,
+            pulse: quality,
+            kiai_background: quality,
+            follow_points: !potato,
+            hit_lighting: !potato,
+        }
+    }
+}
+
+impl Default for RenderGates {
+    fn default() -> Self {
+        Self::new(PerformancePreset::default())
+    }
+}
+
+/// Follow the preset picked in settings
+pub fn refresh_render_gates(config: Res<GameConfig>, mut gates: ResMut<RenderGates>) {
+    if !config.is_changed() {
+        return;
+    }
+    let preset = config.display.performance_preset();
+    if gates.preset != preset {
+        *gates = RenderGates::new(preset);
+    }
+}
+
+/// First-run benchmark picking a preset, present until it has run
+#[derive(Resource, Default)]
+pub struct PerformanceBenchmark {
+    frames: u32,
+    started: Option<Instant>,
+    last_frame: Option<Instant>,
+    samples: Vec<f64>,
+}
+
+/// Marker for the benchmark's synthetic scene
+#[derive(Component)]
+pub struct BenchmarkElement;
+
+/// Draw a heavy synthetic scene over the menu and time its frames. After
+/// `BENCHMARK_SECONDS` the preset is picked from the mean frame time and saved.
+/// Leaving the menu early clears the scene; the run restarts on return
+pub fn run_performance_benchmark(
+    mut commands: Commands,
+    mut benchmark: ResMut<PerformanceBenchmark>,
+    mut config: ResMut<GameConfig>,
+    mut toasts: ResMut<Toasts>,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    existing: Query<Entity, With<BenchmarkElement>>,
+) {
+    let now = Instant::now();
+    if existing.is_empty() && benchmark.frames > 0 {
+        // The menu was left and its UI cleaned up; measure from scratch
+        *benchmark = PerformanceBenchmark::default();
+    }
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    benchmark.frames += 1;
+    if benchmark.frames > BENCHMARK_WARMUP_FRAMES {
+        if let Some(last_frame) = benchmark.last_frame {
+            let elapsed = now.duration_since(last_frame).as_secs_f64() * 1000.0;
+            benchmark.samples.push(elapsed);
+        }
+        let started = *benchmark.started.get_or_insert(now);
+        if now.duration_since(started).as_secs_f64() >= BENCHMARK_SECONDS {
+            let mean =
+                benchmark.samples.iter().sum::<f64>() / benchmark.samples.len().max(1) as f64;
+            let preset = PerformancePreset::for_frame_time(mean);
+            config.display.performance_preset = Some(preset);
+            config.save();
+            toasts.push(format!(
+                "Performance preset: {} (Settings → Display)",
+                preset.display_name()
+            ));
+            commands.remove_resource::<PerformanceBenchmark>();
+            return;
+        }
+    }
+    benchmark.last_frame = Some(now);
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let size = Vec2::new(window.width(), window.height());
+    for i in 0..BENCHMARK_SPRITES {
+        // Glow-like stacks of translucent rectangles spread over the screen
+        let t = i as f32 / BENCHMARK_SPRITES as f32;
+        let position = Vec2::new((t * 37.0).fract() - 0.5, (t * 23.0).fract() - 0.5) * size;
+        let layer = (i % 6) as f32;
+        commands.spawn((
+            Sprite {
+                color: NEON_PINK.with_alpha(0.02),
+                custom_size: Some(Vec2::splat(60.0 + layer * 12.0)),
+                ..default()
+            },
+            Transform::from_xyz(position.x, position.y, 30.0 + t),
+            UiElement,
+            BenchmarkElement,
+        ));
+    }
+    commands.spawn((
+        Text2d::new("Checking performance..."),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(NEON_YELLOW),
+        Transform::from_xyz(0.0, 0.0, 32.0),
+        UiElement,
+        BenchmarkElement,
+    ));
+}
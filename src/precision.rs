@@ -0,0 +1,303 @@
+// src/precision.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Bins across each axis of the hit position grid; odd so dead center has its own bin
+pub const POSITION_BINS: usize = 15;
+
+/// Timing offsets binned run from this many ms early to as many late; further out
+/// land in the outermost bins
+pub const TIMING_RANGE_MS: f32 = 150.0;
+
+/// Bins of the timing histogram
+pub const TIMING_BINS: usize = 30;
+
+/// Offsets shorter than this (in radii) or closer than this to on time (ms) count as
+/// centered in summaries
+const CENTERED_OFFSET: f32 = 0.05;
+const ON_TIME_MS: f32 = 3.0;
+
+/// Where hits land relative to circle centers and how early or late they are. Counts
+/// are kept in fixed bins, so the lifetime total stays the same size however much is
+/// played
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrecisionMap {
+    /// Hits per position bin, rows from the top, each row left to right
+    pub positions: Vec<u32>,
+    /// Hits per timing bin, earliest first
+    pub timing: Vec<u32>,
+}
+
+impl Default for PrecisionMap {
+    fn default() -> Self {
+        Self {
+            positions: vec![0; POSITION_BINS * POSITION_BINS],
+            timing: vec![0; TIMING_BINS],
+        }
+    }
+}
+
+/// Position bin for an offset from the circle center in radii. Offsets are clamped to
+/// the circle's bounding square
+pub fn position_bin(offset: Vec2) -> usize {
+    let axis = |value: f32| {
+        let scaled = (value.clamp(-1.0, 1.0) + 1.0) / 2.0 * POSITION_BINS as f32;
+        (scaled as usize).min(POSITION_BINS - 1)
+    };
+    // Row 0 is the top, where y is largest
+    let row = POSITION_BINS - 1 - axis(offset.y);
+    row * POSITION_BINS + axis(offset.x)
+}
+
+/// Center of a position bin as an offset from the circle center in radii
+pub fn position_bin_center(index: usize) -> Vec2 {
+    let size = 2.0 / POSITION_BINS as f32;
+    let (row, column) = (index / POSITION_BINS, index % POSITION_BINS);
+    Vec2::new(
+        -1.0 + (column as f32 + 0.5) * size,
+        1.0 - (row as f32 + 0.5) * size,
+    )
+}
+
+/// Timing bin for an offset in ms, negative when early
+pub fn timing_bin(offset_ms: f32) -> usize {
+    let scaled = (offset_ms.clamp(-TIMING_RANGE_MS, TIMING_RANGE_MS) + TIMING_RANGE_MS)
+        / (2.0 * TIMING_RANGE_MS);
+    ((scaled * TIMING_BINS as f32) as usize).min(TIMING_BINS - 1)
+}
+
+/// Center of a timing bin in ms
+pub fn timing_bin_center(index: usize) -> f32 {
+    let size = 2.0 * TIMING_RANGE_MS / TIMING_BINS as f32;
+    -TIMING_RANGE_MS + (index as f32 + 0.5) * size
+}
+
+/// Each count as a share of the largest, for coloring; all zeros when nothing was
+/// counted
+pub fn normalize(counts: &[u32]) -> Vec<f32> {
+    let max = counts.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return vec![0.0; counts.len()];
+    }
+    counts
+        .iter()
+        .map(|&count| count as f32 / max as f32)
+        .collect()
+}
+
+/// Count-weighted mean of bin centers
+fn weighted_mean<T>(counts: &[u32], center: impl Fn(usize) -> T) -> Option<T>
+where
+    T: std::ops::Mul<f32, Output = T> + std::ops::Add<Output = T> + std::ops::Div<f32, Output = T>,
+{
+    let total: u32 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(index, &count)| center(index) * count as f32)
+        .reduce(|a, b| a + b)
+        .map(|sum| sum / total as f32)
+}
+
+impl PrecisionMap {
+    /// Count a hit landing `offset` radii from the center, `timing_ms` from on time
+    pub fn record(&mut self, offset: Vec2, timing_ms: f32) {
+        self.repair();
+        self.positions[position_bin(offset)] += 1;
+        self.timing[timing_bin(timing_ms)] += 1;
+    }
+
+    /// Add another map's counts to this one
+    pub fn merge(&mut self, other: &PrecisionMap) {
+        self.repair();
+        for (count, added) in self.positions.iter_mut().zip(&other.positions) {
+            *count = count.saturating_add(*added);
+        }
+        for (count, added) in self.timing.iter_mut().zip(&other.timing) {
+            *count = count.saturating_add(*added);
+        }
+    }
+
+    /// Put the bins back to their fixed sizes, for hand-edited files
+    fn repair(&mut self) {
+        self.positions.resize(POSITION_BINS * POSITION_BINS, 0);
+        self.timing.resize(TIMING_BINS, 0);
+    }
+
+    /// Hits counted
+    pub fn total(&self) -> u32 {
+        self.timing.iter().sum()
+    }
+
+    /// Average landing spot in radii from the center
+    pub fn mean_offset(&self) -> Option<Vec2> {
+        weighted_mean(&self.positions, position_bin_center)
+    }
+
+    /// Average timing in ms, negative when early
+    pub fn mean_timing_ms(&self) -> Option<f32> {
+        weighted_mean(&self.timing, timing_bin_center)
+    }
+
+    /// One line such as "aim 12% low-left, 8ms late"; None before any hit
+    pub fn summary(&self) -> Option<String> {
+        let offset = self.mean_offset()?;
+        let timing = self.mean_timing_ms()?;
+
+        let vertical = match offset.y {
+            y if y >= CENTERED_OFFSET => "high",
+            y if y <= -CENTERED_OFFSET => "low",
+            _ => "",
+        };
+        let horizontal = match offset.x {
+            x if x >= CENTERED_OFFSET => "right",
+            x if x <= -CENTERED_OFFSET => "left",
+            _ => "",
+        };
+        let direction = [vertical, horizontal]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let aim = if direction.is_empty() {
+            "aim centered".to_string()
+        } else {
+            format!("aim {:.0}% {}", offset.length() * 100.0, direction)
+        };
+
+        let timing = if timing.abs() < ON_TIME_MS {
+            "on time".to_string()
+        } else {
+            format!(
+                "{:.0}ms {}",
+                timing.abs(),
+                if timing < 0.0 { "early" } else { "late" }
+            )
+        };
+        Some(format!("{}, {}", aim, timing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER_BIN: usize = POSITION_BINS / 2 * POSITION_BINS + POSITION_BINS / 2;
+
+    #[test]
+    fn positions_bin_from_the_top_left() {
+        assert_eq!(position_bin(Vec2::ZERO), CENTER_BIN);
+        assert!(position_bin_center(CENTER_BIN).length() < 1e-6);
+        assert_eq!(position_bin(Vec2::new(-0.99, 0.99)), 0);
+        assert_eq!(
+            position_bin(Vec2::new(0.99, -0.99)),
+            POSITION_BINS * POSITION_BINS - 1
+        );
+        // Right of center is a later column, above it an earlier row
+        assert_eq!(position_bin(Vec2::new(0.1, 0.0)), CENTER_BIN + 1);
+        assert_eq!(
+            position_bin(Vec2::new(0.0, 0.1)),
+            CENTER_BIN - POSITION_BINS
+        );
+    }
+
+    #[test]
+    fn hits_off_the_circle_land_in_the_edge_bins() {
+        assert_eq!(position_bin(Vec2::new(-3.0, 5.0)), 0);
+        assert_eq!(position_bin(Vec2::new(1.0, 1.0)), POSITION_BINS - 1);
+        assert_eq!(
+            position_bin(Vec2::new(2.5, 0.0)),
+            CENTER_BIN + POSITION_BINS / 2
+        );
+        assert_eq!(timing_bin(-1000.0), 0);
+        assert_eq!(timing_bin(-TIMING_RANGE_MS), 0);
+        assert_eq!(timing_bin(TIMING_RANGE_MS), TIMING_BINS - 1);
+        assert_eq!(timing_bin(1000.0), TIMING_BINS - 1);
+    }
+
+    #[test]
+    fn timing_bins_split_early_from_late() {
+        assert_eq!(timing_bin(-0.1), TIMING_BINS / 2 - 1);
+        assert_eq!(timing_bin(0.0), TIMING_BINS / 2);
+        assert_eq!(timing_bin_center(timing_bin(25.0)), 25.0);
+        assert_eq!(timing_bin_center(0), -TIMING_RANGE_MS + 5.0);
+    }
+
+    #[test]
+    fn normalize_scales_to_the_largest_count() {
+        assert_eq!(normalize(&[0, 2, 4]), [0.0, 0.5, 1.0]);
+        assert_eq!(normalize(&[0, 0]), [0.0, 0.0]);
+        assert!(normalize(&[]).is_empty());
+    }
+
+    #[test]
+    fn weighted_mean_weighs_bin_centers_by_count() {
+        assert_eq!(weighted_mean(&[1, 0, 3], |index| index as f32), Some(1.5));
+        assert_eq!(weighted_mean(&[0, 0], |index| index as f32), None);
+    }
+
+    #[test]
+    fn record_counts_a_hit_in_both_histograms() {
+        let mut map = PrecisionMap::default();
+        map.record(Vec2::ZERO, 0.0);
+        map.record(Vec2::new(0.1, 0.0), 40.0);
+        assert_eq!(map.total(), 2);
+        assert_eq!(map.positions[CENTER_BIN], 1);
+        assert_eq!(map.positions[CENTER_BIN + 1], 1);
+        assert_eq!(map.timing[timing_bin(40.0)], 1);
+        assert_eq!(map.positions.iter().sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn merge_sums_the_counts() {
+        let mut a = PrecisionMap::default();
+        a.record(Vec2::ZERO, 0.0);
+        let mut b = PrecisionMap::default();
+        b.record(Vec2::ZERO, 0.0);
+        b.record(Vec2::new(-0.5, 0.5), -30.0);
+
+        a.merge(&b);
+        assert_eq!(a.total(), 3);
+        assert_eq!(a.positions[CENTER_BIN], 2);
+        assert_eq!(a.timing[timing_bin(0.0)], 2);
+        assert_eq!(a.timing[timing_bin(-30.0)], 1);
+
+        // A hand-edited file with short bins is repaired before adding
+        let mut edited = PrecisionMap {
+            positions: vec![1],
+            timing: Vec::new(),
+        };
+        edited.merge(&b);
+        assert_eq!(edited.positions.len(), POSITION_BINS * POSITION_BINS);
+        assert_eq!(edited.positions[0], 1);
+        assert_eq!(edited.total(), 2);
+    }
+
+    #[test]
+    fn an_empty_map_has_no_summary() {
+        let map = PrecisionMap::default();
+        assert_eq!(map.total(), 0);
+        assert_eq!(map.mean_offset(), None);
+        assert_eq!(map.summary(), None);
+    }
+
+    #[test]
+    fn the_summary_names_the_aim_and_timing_tendency() {
+        let mut map = PrecisionMap::default();
+        for _ in 0..3 {
+            map.record(Vec2::new(-0.5, -0.5), 25.0);
+        }
+        assert_eq!(map.summary().unwrap(), "aim 75% low-left, 25ms late");
+
+        let mut centered = PrecisionMap::default();
+        centered.record(Vec2::ZERO, -5.0);
+        centered.record(Vec2::ZERO, 5.0);
+        assert_eq!(centered.summary().unwrap(), "aim centered, on time");
+    }
+}
@@ -0,0 +1,377 @@
+// src/profiles.rs
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::analytics::{Analytics, ANALYTICS_PATH};
+use crate::config::{GameConfig, KeyBindings, PracticeConfig, SongPracticeStore};
+use crate::hud::HudLayout;
+use crate::text_input::TextInput;
+
+/// Directory holding one folder per local profile
+pub const PROFILES_DIR: &str = "profiles";
+
+/// Profile the flat files from before profiles are migrated into. Used whenever the
+/// active profile's folder is missing, and can't be deleted
+pub const DEFAULT_PROFILE: &str = "shared";
+
+/// Folder under `PROFILES_DIR` deleted profiles are moved to
+pub const TRASH_DIR: &str = ".trash";
+
+/// Longest profile name, in characters
+pub const MAX_PROFILE_NAME: usize = 24;
+
+const SETTINGS_FILE: &str = "profile.json";
+const PROFILE_ANALYTICS_FILE: &str = "analytics.json";
+const SEASON_ARCHIVE_FILE: &str = "seasons.json";
+
+/// Names Windows won't create as files or folders, whatever the case
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Settings each local profile keeps to itself. Everything else in the config is
+/// shared by everyone on this machine
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    pub key_bindings: KeyBindings,
+    pub practice: PracticeConfig,
+    pub practice_songs: SongPracticeStore,
+    pub hud_layout: HudLayout,
+}
+
+impl ProfileSettings {
+    /// The profile's share of a config
+    pub fn from_config(config: &GameConfig) -> Self {
+        Self {
+            key_bindings: config.key_bindings.clone(),
+            practice: config.practice.clone(),
+            practice_songs: config.practice_songs.clone(),
+            hud_layout: config.hud_layout.clone(),
+        }
+    }
+
+    /// Put these settings into a config in place of the previous profile's
+    pub fn apply(self, config: &mut GameConfig) {
+        config.key_bindings = self.key_bindings;
+        config.practice = self.practice;
+        config.practice_songs = self.practice_songs;
+        config.hud_layout = self.hud_layout;
+    }
+
+    /// A profile's settings; defaults when it has none or they can't be read
+    pub fn load(profile: &str) -> Self {
+        Self::load_from(&settings_path(profile))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Failed to parse {}: {}, using defaults", path.display(), e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Failed to read {}: {}, using defaults", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, profile: &str) -> Result<(), String> {
+        self.save_to(&settings_path(profile))
+    }
+
+    /// Write the settings to `path`, creating the profile's folder if needed
+    fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)
+                .map_err(|e| format!("Failed to create profile folder: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize profile settings: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to save profile settings: {}", e))
+    }
+}
+
+/// Folder of a profile's files
+pub fn profile_dir(profile: &str) -> PathBuf {
+    Path::new(PROFILES_DIR).join(profile)
+}
+
+/// Where a profile's analytics are stored
+pub fn analytics_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join(PROFILE_ANALYTICS_FILE)
+}
+
+/// Where a profile's archived season summaries are stored
+pub fn season_archive_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join(SEASON_ARCHIVE_FILE)
+}
+
+fn settings_path(profile: &str) -> PathBuf {
+    profile_dir(profile).join(SETTINGS_FILE)
+}
+
+/// The profile to use for `name`: itself if its folder exists, the default otherwise
+pub fn resolve_profile(name: &str) -> String {
+    if profile_dir(name).is_dir() {
+        name.to_string()
+    } else {
+        DEFAULT_PROFILE.to_string()
+    }
+}
+
+/// Local profiles on this machine, sorted by name ignoring case. The default profile
+/// is always listed
+pub fn list_profiles() -> Vec<String> {
+    let mut profiles: Vec<String> = fs::read_dir(PROFILES_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| !name.starts_with('.'))
+                .collect()
+        })
+        .unwrap_or_default();
+    if !profiles.iter().any(|name| name == DEFAULT_PROFILE) {
+        profiles.push(DEFAULT_PROFILE.to_string());
+    }
+    profiles.sort_by_key(|name| name.to_lowercase());
+    profiles
+}
+
+/// Check a new profile's name is safe to use as a folder name on every platform and
+/// not taken (ignoring case). Returns the trimmed name
+pub fn validate_name(name: &str, existing: &[String]) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name can't be empty".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME {
+        return Err(format!(
+            "Profile name is longer than {} characters",
+            MAX_PROFILE_NAME
+        ));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, ' ' | '-' | '_')))
+    {
+        return Err(format!(
+            "Profile names can only use letters, digits, spaces, - and _ (not '{}')",
+            c
+        ));
+    }
+    let lower = name.to_lowercase();
+    if RESERVED_NAMES.contains(&lower.as_str()) {
+        return Err(format!("\"{}\" is reserved by the system", name));
+    }
+    if existing.iter().any(|other| other.to_lowercase() == lower) {
+        return Err(format!("A profile named \"{}\" already exists", name));
+    }
+    Ok(name.to_string())
+}
+
+/// Create an empty profile with default settings; returns its name
+pub fn create_profile(name: &str, existing: &[String]) -> Result<String, String> {
+    let name = validate_name(name, existing)?;
+    ProfileSettings::default().save(&name)?;
+    Ok(name)
+}
+
+/// Move a profile's folder to the trash rather than erasing it; returns where it went.
+/// The default and the active profile can't be deleted
+pub fn delete_profile(name: &str, active: &str) -> Result<PathBuf, String> {
+    if name == DEFAULT_PROFILE {
+        return Err("The shared profile can't be deleted".to_string());
+    }
+    if name == active {
+        return Err("Switch to another profile before deleting this one".to_string());
+    }
+    let trash = Path::new(PROFILES_DIR).join(TRASH_DIR);
+    fs::create_dir_all(&trash).map_err(|e| format!("Failed to create trash folder: {}", e))?;
+    let stamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let destination = trash.join(format!("{}-{}", name, stamp));
+    fs::rename(profile_dir(name), &destination)
+        .map_err(|e| format!("Failed to move profile to the trash: {}", e))?;
+    Ok(destination)
+}
+
+/// Move the flat analytics file from before profiles into the default profile, and
+/// seed its settings from the config. Runs every startup; parts already migrated
+/// are left alone
+pub fn migrate_flat_files(config: &GameConfig) -> Result<(), String> {
+    migrate_flat_files_in(Path::new(""), config)
+}
+
+/// `migrate_flat_files` for the game folder at `root`
+fn migrate_flat_files_in(root: &Path, config: &GameConfig) -> Result<(), String> {
+    fs::create_dir_all(root.join(profile_dir(DEFAULT_PROFILE)))
+        .map_err(|e| format!("Failed to create the shared profile: {}", e))?;
+
+    let flat = root.join(ANALYTICS_PATH);
+    let analytics = root.join(analytics_path(DEFAULT_PROFILE));
+    if flat.exists() && !analytics.exists() {
+        fs::rename(&flat, &analytics)
+            .map_err(|e| format!("Failed to move analytics into the shared profile: {}", e))?;
+    }
+    let settings = root.join(settings_path(DEFAULT_PROFILE));
+    if !settings.exists() {
+        ProfileSettings::from_config(config).save_to(&settings)?;
+    }
+    Ok(())
+}
+
+/// Save the active profile's settings and analytics, then load `name`'s in their place
+pub fn switch_profile(name: &str, config: &mut GameConfig, analytics: &mut Analytics) {
+    config.save();
+    analytics.save();
+    config.active_profile = name.to_string();
+    config.load_active_profile();
+    config.save();
+    *analytics = Analytics::load(analytics_path(&config.active_profile));
+}
+
+/// Profile picker shown at startup when there are several profiles, and from the menu
+#[derive(Resource, Debug, Default)]
+pub struct ProfilePickerState {
+    pub profiles: Vec<String>,
+    pub selected: usize,
+    /// Name being typed for a new profile
+    pub new_name: Option<TextInput>,
+    /// Profile Delete was pressed on once; a second press moves it to the trash
+    pub pending_delete: Option<String>,
+    pub notice: Option<String>,
+}
+
+impl ProfilePickerState {
+    /// Picker listing the profiles on disk with `active` selected
+    pub fn new(active: &str) -> Self {
+        let profiles = list_profiles();
+        let selected = profiles.iter().position(|name| name == active).unwrap_or(0);
+        Self {
+            profiles,
+            selected,
+            ..default()
+        }
+    }
+
+    pub fn selected_profile(&self) -> Option<&str> {
+        self.profiles.get(self.selected).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::GameSession;
+    use crate::test_support::scratch_dir;
+
+    /// An empty game folder of the test's own
+    fn game_folder(test: &str) -> PathBuf {
+        scratch_dir(&format!("profiles-{}", test))
+    }
+
+    fn session(song: &str, score: i64) -> GameSession {
+        GameSession {
+            score,
+            completed: true,
+            ..GameSession::new(song.to_string())
+        }
+    }
+
+    #[test]
+    fn migration_moves_flat_analytics_into_the_shared_profile_once() {
+        let root = game_folder("migrate");
+        let mut flat = Analytics::load(root.join(ANALYTICS_PATH));
+        flat.add_session(session("old song", 1234));
+        flat.save();
+        let mut config = GameConfig::default();
+        config.key_bindings.primary_hit = "J".to_string();
+
+        migrate_flat_files_in(&root, &config).unwrap();
+
+        assert!(!root.join(ANALYTICS_PATH).exists());
+        let migrated = Analytics::load(root.join(analytics_path(DEFAULT_PROFILE)));
+        assert_eq!(migrated.total_games_played, 1);
+        assert_eq!(migrated.recent_sessions[0].song_name, "old song");
+        let settings = ProfileSettings::load_from(&root.join(settings_path(DEFAULT_PROFILE)));
+        assert_eq!(settings.key_bindings.primary_hit, "J");
+
+        // A flat file turning up again doesn't clobber the migrated one, and settings
+        // already seeded aren't overwritten from the config
+        fs::write(root.join(ANALYTICS_PATH), "{}").unwrap();
+        config.key_bindings.primary_hit = "K".to_string();
+        migrate_flat_files_in(&root, &config).unwrap();
+
+        assert!(root.join(ANALYTICS_PATH).exists());
+        let migrated = Analytics::load(root.join(analytics_path(DEFAULT_PROFILE)));
+        assert_eq!(migrated.total_games_played, 1);
+        let settings = ProfileSettings::load_from(&root.join(settings_path(DEFAULT_PROFILE)));
+        assert_eq!(settings.key_bindings.primary_hit, "J");
+    }
+
+    #[test]
+    fn profiles_keep_their_analytics_apart() {
+        let root = game_folder("isolation");
+        for name in ["alice", "bob"] {
+            ProfileSettings::default()
+                .save_to(&root.join(settings_path(name)))
+                .unwrap();
+        }
+
+        let mut alice = Analytics::load(root.join(analytics_path("alice")));
+        alice.add_session(session("a", 500));
+        alice.add_session(session("b", 700));
+        alice.save();
+        let mut bob = Analytics::load(root.join(analytics_path("bob")));
+        assert_eq!(bob.total_games_played, 0);
+        bob.add_session(session("c", 300));
+        bob.save();
+
+        let alice = Analytics::load(root.join(analytics_path("alice")));
+        let bob = Analytics::load(root.join(analytics_path("bob")));
+        assert_eq!(alice.total_games_played, 2);
+        assert!(alice
+            .recent_sessions
+            .iter()
+            .all(|session| session.song_name != "c"));
+        assert_eq!(bob.total_games_played, 1);
+        assert_eq!(bob.recent_sessions[0].song_name, "c");
+    }
+
+    #[test]
+    fn names_must_be_safe_folder_names_and_unique() {
+        let existing = vec!["Alice".to_string(), DEFAULT_PROFILE.to_string()];
+        assert_eq!(validate_name("  Bob  ", &existing), Ok("Bob".to_string()));
+        assert_eq!(
+            validate_name("dj_x-2 b", &existing),
+            Ok("dj_x-2 b".to_string())
+        );
+        for bad in [
+            "",
+            "   ",
+            "alice",
+            "SHARED",
+            "con",
+            "Lpt1",
+            "a/b",
+            "a.b",
+            &"x".repeat(MAX_PROFILE_NAME + 1),
+        ] {
+            assert!(validate_name(bad, &existing).is_err(), "{:?}", bad);
+        }
+        assert!(validate_name(&"x".repeat(MAX_PROFILE_NAME), &existing).is_ok());
+    }
+}
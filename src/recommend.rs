@@ -0,0 +1,355 @@
+// src/recommend.rs
+
+use crate::analytics::Analytics;
+use crate::structs::EndState;
+use bevy::prelude::*;
+use std::path::Path;
+
+/// Accuracy at or above which a run counts as great (percent)
+pub const GREAT_ACCURACY: f32 = 95.0;
+
+/// Accuracy below which a run counts as a struggle, like a fail (percent)
+pub const STRUGGLE_ACCURACY: f32 = 80.0;
+
+/// How far the star target moves from the song just played
+pub const STAR_STEP: f32 = 0.5;
+
+/// Songs within this many stars of the target match it
+pub const STAR_TOLERANCE: f32 = 0.5;
+
+/// Speed taken off a struggling run for the slower retry
+pub const RETRY_SPEED_STEP: f32 = 0.25;
+
+/// Slowest speed a retry is suggested at
+pub const MIN_RETRY_SPEED: f32 = 0.5;
+
+/// Sessions averaged for the recent star rating
+pub const RECENT_SESSIONS: usize = 10;
+
+/// Most recommendations returned
+pub const MAX_RECOMMENDATIONS: usize = 5;
+
+/// The run just finished, as the recommender sees it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionResult {
+    pub song: String,
+    pub star_rating: Option<f32>,
+    /// Accuracy percentage
+    pub accuracy: f32,
+    pub playback_speed: f32,
+}
+
+impl SessionResult {
+    /// Summarize a results screen
+    pub fn from_end_state(state: &EndState) -> Self {
+        Self {
+            song: state.song_name.clone(),
+            star_rating: state.star_rating,
+            accuracy: state.accuracy,
+            playback_speed: state.playback_speed,
+        }
+    }
+}
+
+/// A song in the library with what analytics knows about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibrarySong {
+    pub path: String,
+    /// Star rating of the map last generated for it; unknown until first played
+    pub star_rating: Option<f32>,
+    pub play_count: u32,
+    /// Session id (a timestamp) of the last recent play, if still in the history
+    pub last_played: Option<u64>,
+}
+
+impl LibrarySong {
+    /// Library entries for song paths, filled in from analytics
+    pub fn from_analytics(songs: &[String], analytics: &Analytics) -> Vec<LibrarySong> {
+        songs
+            .iter()
+            .map(|path| {
+                let stats = analytics.song_stats.get(path);
+                LibrarySong {
+                    path: path.clone(),
+                    star_rating: stats.and_then(|stats| stats.star_rating),
+                    play_count: stats.map_or(0, |stats| stats.play_count),
+                    last_played: analytics
+                        .recent_sessions
+                        .iter()
+                        .filter(|session| session.song_name == *path)
+                        .map(|session| session.session_id)
+                        .max(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Player-wide figures the recommender compares a run against
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalyticsSummary {
+    /// Average star rating of the last `RECENT_SESSIONS` rated sessions
+    pub recent_star_rating: Option<f32>,
+}
+
+impl AnalyticsSummary {
+    /// Summarize the recent sessions
+    pub fn from_analytics(analytics: &Analytics) -> Self {
+        let ratings: Vec<f32> = analytics
+            .recent_sessions
+            .iter()
+            .rev()
+            .filter_map(|session| session.star_rating)
+            .take(RECENT_SESSIONS)
+            .collect();
+        let recent_star_rating = if ratings.is_empty() {
+            None
+        } else {
+            Some(ratings.iter().sum::<f32>() / ratings.len() as f32)
+        };
+        Self { recent_star_rating }
+    }
+}
+
+/// Why a song is recommended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecommendReason {
+    /// Great run on a map below the recent average
+    Harder,
+    /// Failed or low accuracy
+    Easier,
+    /// Anything in between
+    Similar,
+    /// The song just played, slowed down
+    SlowerRetry,
+}
+
+/// A suggested next play
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    pub song: String,
+    pub star_rating: Option<f32>,
+    /// Practice speed to start at; 1.0 for a normal play
+    pub playback_speed: f32,
+    pub reason: RecommendReason,
+}
+
+impl Recommendation {
+    /// Card text, e.g. "Try harder: song (4.20*)"
+    pub fn label(&self) -> String {
+        let name = Path::new(&self.song)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.song.clone());
+        let stars = self
+            .star_rating
+            .map(|stars| format!(" ({:.2}*)", stars))
+            .unwrap_or_default();
+        match self.reason {
+            RecommendReason::Harder => format!("Try harder: {}{}", name, stars),
+            RecommendReason::Easier => format!("Try easier: {}{}", name, stars),
+            RecommendReason::Similar => format!("Up next: {}{}", name, stars),
+            RecommendReason::SlowerRetry => {
+                format!("Retry at {:.2}x: {}{}", self.playback_speed, name, stars)
+            }
+        }
+    }
+}
+
+/// Rank what to play after a run. A great run on a map below the recent average
+/// aims harder, low accuracy aims easier and offers the same song slower,
+/// anything else aims at the same difficulty. Songs matching the star target come
+/// first, then songs with no rating yet, then the rest by distance; within each,
+/// unplayed songs lead, then the least recently played. The song just played is
+/// only ever suggested at a lower speed
+pub fn recommend(
+    result: &SessionResult,
+    library: &[LibrarySong],
+    summary: &AnalyticsSummary,
+) -> Vec<Recommendation> {
+    let struggled = result.accuracy < STRUGGLE_ACCURACY;
+    let below_average = match (result.star_rating, summary.recent_star_rating) {
+        (Some(stars), Some(average)) => stars < average,
+        _ => true,
+    };
+    let reason = if struggled {
+        RecommendReason::Easier
+    } else if result.accuracy >= GREAT_ACCURACY && below_average {
+        RecommendReason::Harder
+    } else {
+        RecommendReason::Similar
+    };
+
+    let base = result.star_rating.or(summary.recent_star_rating);
+    let target = base.map(|stars| match reason {
+        RecommendReason::Harder => stars + STAR_STEP,
+        RecommendReason::Easier => (stars - STAR_STEP).max(0.0),
+        _ => stars,
+    });
+
+    // 0: matches the target, 1: not rated yet, 2: rated but off target
+    let tier = |song: &LibrarySong| -> (u8, f32) {
+        let (Some(stars), Some(target), Some(base)) = (song.star_rating, target, base) else {
+            return (1, 0.0);
+        };
+        let distance = (stars - target).abs();
+        let direction_ok = match reason {
+            RecommendReason::Harder => stars > base,
+            RecommendReason::Easier => stars < base,
+            _ => true,
+        };
+        if direction_ok && distance <= STAR_TOLERANCE {
+            (0, distance)
+        } else {
+            (2, distance)
+        }
+    };
+
+    let mut candidates: Vec<(&LibrarySong, (u8, f32))> = library
+        .iter()
+        .filter(|song| song.path != result.song)
+        .map(|song| (song, tier(song)))
+        .collect();
+    candidates.sort_by(|(a, (a_tier, a_distance)), (b, (b_tier, b_distance))| {
+        a_tier
+            .cmp(b_tier)
+            .then((a.play_count > 0).cmp(&(b.play_count > 0)))
+            .then(a.last_played.cmp(&b.last_played))
+            .then(a_distance.total_cmp(b_distance))
+            .then(a.path.cmp(&b.path))
+    });
+
+    let mut recommendations: Vec<Recommendation> = candidates
+        .into_iter()
+        .take(MAX_RECOMMENDATIONS)
+        .map(|(song, _)| Recommendation {
+            song: song.path.clone(),
+            star_rating: song.star_rating,
+            playback_speed: 1.0,
+            reason,
+        })
+        .collect();
+
+    let retry_speed = (result.playback_speed - RETRY_SPEED_STEP).max(MIN_RETRY_SPEED);
+    if struggled && retry_speed < result.playback_speed {
+        let retry = Recommendation {
+            song: result.song.clone(),
+            star_rating: result.star_rating,
+            playback_speed: retry_speed,
+            reason: RecommendReason::SlowerRetry,
+        };
+        // An easier song leads, then practicing the same song
+        recommendations.insert(recommendations.len().min(1), retry);
+        recommendations.truncate(MAX_RECOMMENDATIONS);
+    }
+
+    recommendations
+}
+
+/// Top recommendation shown on the results screen; None when there is nothing to
+/// suggest or the results aren't from a solo play that just ended
+#[derive(Resource, Default)]
+pub struct NextRecommendation(pub Option<Recommendation>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(
+        path: &str,
+        star_rating: f32,
+        play_count: u32,
+        last_played: Option<u64>,
+    ) -> LibrarySong {
+        LibrarySong {
+            path: path.to_string(),
+            star_rating: Some(star_rating),
+            play_count,
+            last_played,
+        }
+    }
+
+    fn run(accuracy: f32, star_rating: f32) -> SessionResult {
+        SessionResult {
+            song: "played".to_string(),
+            star_rating: Some(star_rating),
+            accuracy,
+            playback_speed: 1.0,
+        }
+    }
+
+    fn average(stars: f32) -> AnalyticsSummary {
+        AnalyticsSummary {
+            recent_star_rating: Some(stars),
+        }
+    }
+
+    fn songs(recommendations: &[Recommendation]) -> Vec<&str> {
+        recommendations.iter().map(|r| r.song.as_str()).collect()
+    }
+
+    fn library() -> Vec<LibrarySong> {
+        vec![
+            song("played", 3.0, 4, Some(50)),
+            song("easier", 2.5, 0, None),
+            song("same", 3.0, 0, None),
+            song("harder", 3.5, 0, None),
+            song("harder-played", 3.4, 2, Some(10)),
+            song("far", 6.0, 0, None),
+        ]
+    }
+
+    #[test]
+    fn a_great_run_below_the_average_aims_harder() {
+        let picks = recommend(&run(97.0, 3.0), &library(), &average(3.5));
+        assert!(picks.iter().all(|r| r.reason == RecommendReason::Harder));
+        assert_eq!(songs(&picks)[..2], ["harder", "harder-played"]);
+        assert!(!songs(&picks).contains(&"played"));
+
+        // The same run on a map above the average stays at that level
+        let picks = recommend(&run(97.0, 3.0), &library(), &average(2.0));
+        assert_eq!(picks[0].reason, RecommendReason::Similar);
+        assert_eq!(picks[0].song, "same");
+    }
+
+    #[test]
+    fn a_failed_run_aims_easier_and_offers_a_slower_retry() {
+        let picks = recommend(&run(60.0, 3.0), &library(), &average(3.0));
+        assert_eq!(songs(&picks)[..2], ["easier", "played"]);
+        assert_eq!(picks[0].reason, RecommendReason::Easier);
+        assert_eq!(picks[1].reason, RecommendReason::SlowerRetry);
+        assert_eq!(picks[1].playback_speed, 1.0 - RETRY_SPEED_STEP);
+        assert!(picks.len() <= MAX_RECOMMENDATIONS);
+
+        // No retry once the speed can't go lower
+        let slowest = SessionResult {
+            playback_speed: MIN_RETRY_SPEED,
+            ..run(60.0, 3.0)
+        };
+        let picks = recommend(&slowest, &library(), &average(3.0));
+        assert!(picks.iter().all(|r| r.song != "played"));
+    }
+
+    #[test]
+    fn without_unplayed_songs_the_least_recently_played_comes_first() {
+        let library = vec![
+            song("recent", 3.0, 3, Some(300)),
+            song("older", 3.1, 5, Some(100)),
+            song("oldest", 2.9, 1, None),
+        ];
+        let picks = recommend(&run(90.0, 3.0), &library, &average(3.0));
+        assert_eq!(songs(&picks), ["oldest", "older", "recent"]);
+    }
+
+    #[test]
+    fn a_tiny_library_only_suggests_the_song_just_played_slower() {
+        let library = vec![song("played", 3.0, 1, Some(1))];
+        assert!(recommend(&run(90.0, 3.0), &library, &average(3.0)).is_empty());
+        assert!(recommend(&run(99.0, 3.0), &[], &AnalyticsSummary::default()).is_empty());
+
+        let picks = recommend(&run(50.0, 3.0), &library, &average(3.0));
+        assert_eq!(picks.len(), 1);
+        assert_eq!(picks[0].reason, RecommendReason::SlowerRetry);
+        assert_eq!(picks[0].label(), "Retry at 0.75x: played (3.00*)");
+    }
+}
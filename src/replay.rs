@@ -0,0 +1,711 @@
+// src/replay.rs
+
+use crate::hashing::Fnv1a;
+use crate::simulation::InputEvent;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Watch folder: replay files dropped here show up in the replay browser
+pub const REPLAYS_DIR: &str = "replays";
+
+/// Replays of recorded plays, kept apart from the watch folder so the browser only
+/// lists exported and received ones
+pub const SESSION_REPLAYS_DIR: &str = "replays/sessions";
+
+/// File extension of the compact binary encoding
+pub const REPLAY_EXTENSION: &str = "yrp";
+
+/// Version written by this build. Version 1 was plain JSON with the metadata at the
+/// top level and no integrity hash; version 2 had no key releases
+pub const REPLAY_VERSION: u32 = 3;
+
+/// First bytes of a binary replay
+const BINARY_MAGIC: &[u8; 4] = b"YRPB";
+
+/// How often the watch folder is listed for new or changed files
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Replays listed in the browser, newest first
+pub const MAX_LISTED_REPLAYS: usize = 12;
+
+/// What was played: enough to find the song and show the result without the events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayMeta {
+    /// Song file the play was on
+    pub song: String,
+    /// Layout seed the map was generated from
+    pub seed: Option<u64>,
+    pub score: i64,
+    pub accuracy: f32,
+    pub max_combo: u32,
+    pub playback_speed: f32,
+    /// When the play ended (seconds since the Unix epoch)
+    pub recorded_at: u64,
+}
+
+impl ReplayMeta {
+    /// Song file name without the folder and extension
+    pub fn song_title(&self) -> String {
+        Path::new(&self.song)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.song.clone())
+    }
+}
+
+/// A hit key press or release as stored in a replay
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    /// Song time on the simulation grid (ms)
+    pub time_ms: i64,
+    pub x: f32,
+    pub y: f32,
+    /// Releases ending slider holds; older versions only have presses
+    #[serde(default)]
+    pub release: bool,
+}
+
+impl From<&InputEvent> for ReplayEvent {
+    fn from(input: &InputEvent) -> Self {
+        Self {
+            time_ms: input.time_ms,
+            x: input.position.x,
+            y: input.position.y,
+            release: input.release,
+        }
+    }
+}
+
+impl From<&ReplayEvent> for InputEvent {
+    fn from(event: &ReplayEvent) -> Self {
+        Self {
+            time_ms: event.time_ms,
+            position: Vec2::new(event.x, event.y),
+            release: event.release,
+        }
+    }
+}
+
+/// A recorded play: its metadata, every input, and a hash over both so a truncated
+/// or edited file is caught on load instead of desyncing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub meta: ReplayMeta,
+    pub events: Vec<ReplayEvent>,
+    /// Integrity hash as 16 hex digits
+    pub hash: String,
+}
+
+/// Layout of version 1 files
+#[derive(Deserialize)]
+struct ReplayV1 {
+    song: String,
+    #[serde(default)]
+    seed: Option<u64>,
+    score: i64,
+    accuracy: f32,
+    max_combo: u32,
+    #[serde(default = "default_speed")]
+    playback_speed: f32,
+    #[serde(default)]
+    recorded_at: u64,
+    inputs: Vec<ReplayEvent>,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+impl Replay {
+    /// Record a play's inputs, in the order they were judged
+    pub fn new(meta: ReplayMeta, inputs: &[InputEvent]) -> Self {
+        let events: Vec<ReplayEvent> = inputs.iter().map(ReplayEvent::from).collect();
+        let hash = format_hash(integrity_hash(&meta, &events));
+        Self {
+            version: REPLAY_VERSION,
+            meta,
+            events,
+            hash,
+        }
+    }
+
+    /// The inputs to feed back into a judgment clock
+    pub fn inputs(&self) -> Vec<InputEvent> {
+        self.events.iter().map(InputEvent::from).collect()
+    }
+
+    /// Whether the hash still matches the metadata and events
+    pub fn verify(&self) -> bool {
+        self.hash == format_hash(integrity_hash(&self.meta, &self.events))
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Compact encoding: the metadata as JSON, then each event as the time since the
+    /// previous one (a variable length integer), the cursor position and a release
+    /// flag. Always written as the current version
+    pub fn to_binary(&self) -> Vec<u8> {
+        let meta = serde_json::to_vec(&self.meta).unwrap_or_default();
+        let mut bytes = Vec::with_capacity(meta.len() + self.events.len() * 11 + 32);
+        bytes.extend_from_slice(BINARY_MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&meta);
+        bytes.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+        let mut previous = 0;
+        for event in &self.events {
+            write_varint(&mut bytes, zigzag(event.time_ms - previous));
+            bytes.extend_from_slice(&event.x.to_le_bytes());
+            bytes.extend_from_slice(&event.y.to_le_bytes());
+            bytes.push(event.release as u8);
+            previous = event.time_ms;
+        }
+        bytes.extend_from_slice(&parse_hash(&self.hash).unwrap_or(0).to_le_bytes());
+        bytes
+    }
+
+    /// Read a replay in either encoding and any supported version, migrating older
+    /// versions and checking the hash
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let replay = if bytes.starts_with(BINARY_MAGIC) {
+            decode_binary(bytes)?
+        } else {
+            decode_json(bytes)?
+        };
+        if !replay.verify() {
+            return Err("replay corrupt: integrity check failed".to_string());
+        }
+        Ok(replay)
+    }
+
+    /// Read a replay file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|e| format!("couldn't read {} ({})", path.display(), e))?;
+        Self::decode(&bytes)
+    }
+
+    /// Write the replay, binary for `.yrp` files and JSON otherwise
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("couldn't create {} ({})", parent.display(), e))?;
+        }
+        let bytes = if path.extension().is_some_and(|ext| ext == REPLAY_EXTENSION) {
+            self.to_binary()
+        } else {
+            self.to_json()?.into_bytes()
+        };
+        fs::write(path, bytes).map_err(|e| format!("couldn't write {} ({})", path.display(), e))
+    }
+}
+
+fn decode_json(bytes: &[u8]) -> Result<Replay, String> {
+    let value: serde_json::Value = serde_json::from_slice(bytes)
+        .map_err(|e| format!("replay corrupt: not a replay file ({})", e))?;
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
+    match version {
+        1 => {
+            let old: ReplayV1 =
+                serde_json::from_value(value).map_err(|e| format!("replay corrupt: {}", e))?;
+            Ok(migrate_v1(old))
+        }
+        2 | REPLAY_VERSION => {
+            serde_json::from_value(value).map_err(|e| format!("replay corrupt: {}", e))
+        }
+        newer => Err(unsupported(newer)),
+    }
+}
+
+/// Version 1 files had no hash, so the migrated replay is hashed as it was read
+fn migrate_v1(old: ReplayV1) -> Replay {
+    let meta = ReplayMeta {
+        song: old.song,
+        seed: old.seed,
+        score: old.score,
+        accuracy: old.accuracy,
+        max_combo: old.max_combo,
+        playback_speed: old.playback_speed,
+        recorded_at: old.recorded_at,
+    };
+    let hash = format_hash(integrity_hash(&meta, &old.inputs));
+    Replay {
+        version: REPLAY_VERSION,
+        meta,
+        events: old.inputs,
+        hash,
+    }
+}
+
+fn decode_binary(bytes: &[u8]) -> Result<Replay, String> {
+    let mut reader = ByteReader {
+        bytes,
+        position: BINARY_MAGIC.len(),
+    };
+    let version = reader.u32()?;
+    if !(2..=REPLAY_VERSION).contains(&version) {
+        // Version 1 had no binary encoding
+        return Err(unsupported(version));
+    }
+    // Version 2 events have no release flag
+    let has_release = version >= 3;
+    let event_size = if has_release { 10 } else { 9 };
+    let meta_len = reader.u32()? as usize;
+    let meta: ReplayMeta = serde_json::from_slice(reader.take(meta_len)?)
+        .map_err(|e| format!("replay corrupt: {}", e))?;
+    let count = reader.u32()? as usize;
+    // Each event takes at least `event_size` bytes, so a bad count can't make us
+    // allocate a lot
+    if count > reader.remaining() / event_size {
+        return Err("replay corrupt: file ends early".to_string());
+    }
+    let mut events = Vec::with_capacity(count);
+    let mut time_ms = 0i64;
+    for _ in 0..count {
+        time_ms += unzigzag(reader.varint()?);
+        events.push(ReplayEvent {
+            time_ms,
+            x: f32::from_le_bytes(reader.array()?),
+            y: f32::from_le_bytes(reader.array()?),
+            release: has_release && reader.take(1)?[0] != 0,
+        });
+    }
+    let hash = format_hash(u64::from_le_bytes(reader.array()?));
+    if reader.remaining() > 0 {
+        return Err("replay corrupt: unexpected data after the end".to_string());
+    }
+    Ok(Replay {
+        version,
+        meta,
+        events,
+        hash,
+    })
+}
+
+fn unsupported(version: u32) -> String {
+    format!(
+        "replay version {} isn't supported (this build reads up to {})",
+        version, REPLAY_VERSION
+    )
+}
+
+/// FNV-1a over the metadata as JSON and every event's fields. Presses hash as they
+/// did before releases were recorded, so older replays still verify
+fn integrity_hash(meta: &ReplayMeta, events: &[ReplayEvent]) -> u64 {
+    let mut hasher = Fnv1a::new();
+    if let Ok(json) = serde_json::to_vec(meta) {
+        hasher.write(&json);
+    }
+    for event in events {
+        hasher.write(&event.time_ms.to_le_bytes());
+        hasher.write(&event.x.to_le_bytes());
+        hasher.write(&event.y.to_le_bytes());
+        if event.release {
+            hasher.write(&[1]);
+        }
+    }
+    hasher.finish()
+}
+
+fn format_hash(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+fn parse_hash(text: &str) -> Option<u64> {
+    u64::from_str_radix(text, 16).ok()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads a binary replay front to back; running out of bytes means it was truncated
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.position)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.remaining() {
+            return Err("replay corrupt: file ends early".to_string());
+        }
+        let slice = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("replay corrupt: bad event time".to_string())
+    }
+}
+
+/// Save the replay of a recorded play under its session id. Returns the path to
+/// keep on the session
+pub fn save_session_replay(replay: &Replay, session_id: u64) -> Result<String, String> {
+    let path = Path::new(SESSION_REPLAYS_DIR).join(format!("{}.{}", session_id, REPLAY_EXTENSION));
+    replay.save(&path)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Write a shareable copy into the watch folder, never replacing an existing file
+pub fn export_replay(replay: &Replay) -> Result<PathBuf, String> {
+    let slug: String = replay
+        .meta
+        .song_title()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let base = format!("{}-{}", slug, replay.meta.recorded_at);
+    let path = (1..)
+        .map(|n| {
+            let stem = if n == 1 {
+                base.clone()
+            } else {
+                format!("{}-{}", base, n)
+            };
+            Path::new(REPLAYS_DIR).join(format!("{}.{}", stem, REPLAY_EXTENSION))
+        })
+        .find(|path| !path.exists())
+        .expect("unbounded range always finds a free name");
+    replay.save(&path)?;
+    Ok(path)
+}
+
+/// A file in the watch folder and what loading it gave
+#[derive(Debug, Clone)]
+pub struct ReplayEntry {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    /// The replay's metadata and input count, or why it couldn't be read
+    pub loaded: Result<(ReplayMeta, usize), String>,
+}
+
+impl ReplayEntry {
+    fn load(path: PathBuf, modified: Option<SystemTime>) -> Self {
+        let loaded = Replay::load(&path).map(|replay| (replay.meta, replay.events.len()));
+        Self {
+            path,
+            modified,
+            loaded,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Replays in the watch folder, newest first. Files dropped in, changed or removed
+/// are picked up on the next listing without a restart
+#[derive(Resource, Default)]
+pub struct ReplayLibrary {
+    pub entries: Vec<ReplayEntry>,
+    last_scan: Option<Instant>,
+}
+
+impl ReplayLibrary {
+    /// List the watch folder, loading only new or modified files. Returns whether
+    /// anything changed
+    pub fn refresh(&mut self) -> bool {
+        self.refresh_in(Path::new(REPLAYS_DIR))
+    }
+
+    /// `refresh` for a given folder
+    fn refresh_in(&mut self, dir: &Path) -> bool {
+        let files: Vec<(PathBuf, Option<SystemTime>)> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| {
+                let path = entry.path();
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext == REPLAY_EXTENSION || ext == "json")
+            })
+            .map(|entry| {
+                let modified = entry.metadata().and_then(|meta| meta.modified()).ok();
+                (entry.path(), modified)
+            })
+            .collect();
+
+        let mut changed = files.len() != self.entries.len();
+        let mut entries = Vec::with_capacity(files.len());
+        for (path, modified) in files {
+            let known = self
+                .entries
+                .iter()
+                .position(|entry| entry.path == path && entry.modified == modified);
+            match known {
+                Some(index) => entries.push(self.entries.swap_remove(index)),
+                None => {
+                    changed = true;
+                    entries.push(ReplayEntry::load(path, modified));
+                }
+            }
+        }
+        if !changed {
+            self.entries = entries;
+            return false;
+        }
+        entries.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.path.cmp(&b.path)));
+        self.entries = entries;
+        true
+    }
+}
+
+/// List the watch folder every few seconds while the browser can be open
+pub fn watch_replays(mut library: ResMut<ReplayLibrary>) {
+    let due = library
+        .last_scan
+        .is_none_or(|last| last.elapsed() >= WATCH_INTERVAL);
+    if !due {
+        return;
+    }
+    let tracked = library.bypass_change_detection();
+    tracked.last_scan = Some(Instant::now());
+    if tracked.refresh() {
+        library.set_changed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn meta() -> ReplayMeta {
+        ReplayMeta {
+            song: "songs/neon drive.mp3".to_string(),
+            seed: Some(42),
+            score: 123_456,
+            accuracy: 97.5,
+            max_combo: 300,
+            playback_speed: 1.0,
+            recorded_at: 1_700_000_000,
+        }
+    }
+
+    /// Presses every 150 ms with a release after each, wandering over the playfield
+    fn inputs(count: usize) -> Vec<InputEvent> {
+        (0..count)
+            .map(|i| InputEvent {
+                time_ms: 1_000 + (i as i64 / 2) * 150 + (i as i64 % 2) * 60,
+                position: Vec2::new(
+                    100.0 + (i % 7) as f32 * 40.5,
+                    200.0 - (i % 5) as f32 * 30.25,
+                ),
+                release: i % 2 == 1,
+            })
+            .collect()
+    }
+
+    fn replay(count: usize) -> Replay {
+        Replay::new(meta(), &inputs(count))
+    }
+
+    #[test]
+    fn both_encodings_round_trip() {
+        let replay = replay(500);
+        assert!(replay.verify());
+        assert_eq!(replay.inputs(), inputs(500));
+
+        let json = replay.to_json().unwrap();
+        assert_eq!(Replay::decode(json.as_bytes()).unwrap(), replay);
+        let binary = replay.to_binary();
+        assert_eq!(Replay::decode(&binary).unwrap(), replay);
+        assert!(
+            binary.len() * 3 < json.len(),
+            "{} vs {} bytes",
+            binary.len(),
+            json.len()
+        );
+
+        // Times going backwards still delta-encode
+        let mut events = inputs(4);
+        events[2].time_ms = -5;
+        let backwards = Replay::new(meta(), &events);
+        assert_eq!(Replay::decode(&backwards.to_binary()).unwrap(), backwards);
+    }
+
+    #[test]
+    fn truncated_and_edited_files_are_reported_corrupt() {
+        let replay = replay(20);
+        let binary = replay.to_binary();
+        for len in [binary.len() - 1, binary.len() / 2, 10] {
+            let error = Replay::decode(&binary[..len]).unwrap_err();
+            assert!(error.starts_with("replay corrupt"), "{}", error);
+        }
+        let mut padded = binary.clone();
+        padded.push(0);
+        assert_eq!(
+            Replay::decode(&padded).unwrap_err(),
+            "replay corrupt: unexpected data after the end"
+        );
+
+        // Nudging one cursor coordinate breaks the hash
+        let mut edited = binary.clone();
+        let last_x = binary.len() - 8 - 1 - 8;
+        edited[last_x] ^= 1;
+        assert_eq!(
+            Replay::decode(&edited).unwrap_err(),
+            "replay corrupt: integrity check failed"
+        );
+
+        let json = replay.to_json().unwrap().replace("123456", "999999");
+        assert_eq!(
+            Replay::decode(json.as_bytes()).unwrap_err(),
+            "replay corrupt: integrity check failed"
+        );
+        assert!(Replay::decode(b"{\"version\": 3")
+            .unwrap_err()
+            .starts_with("replay corrupt: not a replay file"));
+    }
+
+    #[test]
+    fn version_one_files_are_migrated() {
+        let events: Vec<ReplayEvent> = inputs(6)
+            .iter()
+            .filter(|input| !input.release)
+            .map(ReplayEvent::from)
+            .collect();
+        let old = serde_json::json!({
+            "song": "songs/neon drive.mp3",
+            "score": 123_456,
+            "accuracy": 97.5,
+            "max_combo": 300,
+            "inputs": events,
+        });
+        let replay = Replay::decode(old.to_string().as_bytes()).unwrap();
+        assert_eq!(replay.version, REPLAY_VERSION);
+        assert_eq!(replay.events, events);
+        assert_eq!(replay.meta.playback_speed, 1.0);
+        assert_eq!(replay.meta.seed, None);
+        assert!(replay.verify());
+    }
+
+    #[test]
+    fn version_two_binaries_load_without_releases() {
+        let presses: Vec<InputEvent> = inputs(10).into_iter().filter(|i| !i.release).collect();
+        let replay = Replay::new(meta(), &presses);
+        // Version 2 wrote the same layout without the release byte after each event
+        let meta = serde_json::to_vec(&replay.meta).unwrap();
+        let mut binary = BINARY_MAGIC.to_vec();
+        binary.extend_from_slice(&2u32.to_le_bytes());
+        binary.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+        binary.extend_from_slice(&meta);
+        binary.extend_from_slice(&(presses.len() as u32).to_le_bytes());
+        let mut previous = 0;
+        for event in &replay.events {
+            write_varint(&mut binary, zigzag(event.time_ms - previous));
+            binary.extend_from_slice(&event.x.to_le_bytes());
+            binary.extend_from_slice(&event.y.to_le_bytes());
+            previous = event.time_ms;
+        }
+        binary.extend_from_slice(&parse_hash(&replay.hash).unwrap().to_le_bytes());
+
+        let loaded = Replay::decode(&binary).unwrap();
+        assert_eq!(loaded.version, 2);
+        assert_eq!(loaded.events, replay.events);
+
+        binary[4..8].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            Replay::decode(&binary).unwrap_err(),
+            unsupported(REPLAY_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn replays_dropped_into_the_watch_folder_are_listed() {
+        let dir = scratch_dir("replays");
+        let mut library = ReplayLibrary::default();
+        assert!(!library.refresh_in(&dir));
+
+        replay(8).save(&dir.join("first.yrp")).unwrap();
+        replay(4).save(&dir.join("second.json")).unwrap();
+        fs::write(dir.join("broken.yrp"), b"YRPB").unwrap();
+        fs::write(dir.join("notes.txt"), "not a replay").unwrap();
+        assert!(library.refresh_in(&dir));
+        assert!(!library.refresh_in(&dir));
+
+        let mut listed: Vec<(String, Result<usize, String>)> = library
+            .entries
+            .iter()
+            .map(|entry| {
+                let loaded = entry
+                    .loaded
+                    .as_ref()
+                    .map(|(_, count)| *count)
+                    .map_err(Clone::clone);
+                (entry.file_name(), loaded)
+            })
+            .collect();
+        listed.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            listed,
+            [
+                (
+                    "broken.yrp".to_string(),
+                    Err("replay corrupt: file ends early".to_string())
+                ),
+                ("first.yrp".to_string(), Ok(8)),
+                ("second.json".to_string(), Ok(4)),
+            ]
+        );
+
+        fs::remove_file(dir.join("broken.yrp")).unwrap();
+        assert!(library.refresh_in(&dir));
+        assert_eq!(library.entries.len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
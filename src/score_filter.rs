@@ -0,0 +1,404 @@
+// src/score_filter.rs
+
+use crate::analytics::{Analytics, GameSession};
+use crate::gamemode::Modifier;
+use serde::{Deserialize, Serialize};
+
+/// Most entries a song's leaderboard panel lists
+pub const LEADERBOARD_ROWS: usize = 8;
+
+/// Modifiers and speed a score was set with. Normalized so plays under the same
+/// conditions compare equal, whatever order the modifiers were turned on in
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PlaySignature {
+    /// In `Modifier::all()` order, without repeats
+    pub modifiers: Vec<Modifier>,
+    /// Playback speed in hundredths, 100 being normal speed
+    pub speed_percent: u32,
+}
+
+impl Default for PlaySignature {
+    fn default() -> Self {
+        Self::new(&[], 1.0)
+    }
+}
+
+impl PlaySignature {
+    /// Signature of a play with these modifiers at this playback speed
+    pub fn new(modifiers: &[Modifier], speed: f32) -> Self {
+        Self {
+            modifiers: normalize_modifiers(modifiers),
+            speed_percent: speed_percent(speed),
+        }
+    }
+
+    /// Speed the song actually played at: the playback speed times Double Time's or
+    /// Half Time's
+    pub fn effective_speed(&self) -> f32 {
+        let modifier_speed: f32 = self
+            .modifiers
+            .iter()
+            .map(Modifier::playback_speed)
+            .product();
+        self.speed_percent as f32 / 100.0 * modifier_speed
+    }
+
+    /// Modifiers and, when not normal, the speed, e.g. "HD+DT 0.75x"
+    pub fn label(&self) -> String {
+        if self.speed_percent == 100 {
+            mods_label(&self.modifiers)
+        } else {
+            format!(
+                "{} {}",
+                mods_label(&self.modifiers),
+                speed_label(self.speed_percent)
+            )
+        }
+    }
+}
+
+/// Modifiers in `Modifier::all()` order without repeats
+pub fn normalize_modifiers(modifiers: &[Modifier]) -> Vec<Modifier> {
+    Modifier::all()
+        .into_iter()
+        .map(|(modifier, _)| modifier)
+        .filter(|modifier| modifiers.contains(modifier))
+        .collect()
+}
+
+/// Playback speed in hundredths
+pub fn speed_percent(speed: f32) -> u32 {
+    (speed.max(0.0) * 100.0).round() as u32
+}
+
+/// Modifier acronyms joined with '+', or "No mod"
+pub fn mods_label(modifiers: &[Modifier]) -> String {
+    if modifiers.is_empty() {
+        return "No mod".to_string();
+    }
+    modifiers
+        .iter()
+        .map(|modifier| modifier.acronym())
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Speed in hundredths as "0.75x"
+pub fn speed_label(speed_percent: u32) -> String {
+    format!("{:.2}x", speed_percent as f32 / 100.0)
+}
+
+/// Which modifier combinations a leaderboard shows
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ModFilter {
+    #[default]
+    All,
+    /// Exactly these normalized modifiers; empty for no-mod scores
+    Exactly(Vec<Modifier>),
+}
+
+/// Which seasons a leaderboard shows
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SeasonFilter {
+    /// The season running now, whichever that is
+    #[default]
+    Current,
+    All,
+    /// A past season, by id
+    Season(String),
+}
+
+impl SeasonFilter {
+    /// Whether a score from this season is shown, given the current season's id
+    pub fn matches(&self, season_id: &str, current: &str) -> bool {
+        match self {
+            SeasonFilter::Current => season_id == current,
+            SeasonFilter::All => true,
+            SeasonFilter::Season(id) => season_id == id,
+        }
+    }
+}
+
+/// Filter chips picked on a leaderboard panel
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ScoreFilter {
+    pub mods: ModFilter,
+    /// Only scores at this speed (hundredths); None for any speed
+    pub speed: Option<u32>,
+    pub season: SeasonFilter,
+}
+
+impl ScoreFilter {
+    /// Whether a score set with this signature is shown
+    pub fn matches(&self, signature: &PlaySignature) -> bool {
+        let mods = match &self.mods {
+            ModFilter::All => true,
+            ModFilter::Exactly(modifiers) => *modifiers == signature.modifiers,
+        };
+        mods && self
+            .speed
+            .is_none_or(|speed| speed == signature.speed_percent)
+    }
+}
+
+/// A chip on a leaderboard panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterChip {
+    Mods(ModFilter),
+    Speed(Option<u32>),
+    Season(SeasonFilter),
+}
+
+impl FilterChip {
+    pub fn label(&self) -> String {
+        match self {
+            FilterChip::Mods(ModFilter::All) => "All".to_string(),
+            FilterChip::Mods(ModFilter::Exactly(modifiers)) => mods_label(modifiers),
+            FilterChip::Speed(None) => "Any speed".to_string(),
+            FilterChip::Speed(Some(speed)) => speed_label(*speed),
+            FilterChip::Season(SeasonFilter::Current) => "This season".to_string(),
+            FilterChip::Season(SeasonFilter::All) => "All time".to_string(),
+            FilterChip::Season(SeasonFilter::Season(id)) => id.clone(),
+        }
+    }
+
+    /// Whether the chip is the filter's current pick
+    pub fn is_selected(&self, filter: &ScoreFilter) -> bool {
+        match self {
+            FilterChip::Mods(mods) => *mods == filter.mods,
+            FilterChip::Speed(speed) => *speed == filter.speed,
+            FilterChip::Season(season) => *season == filter.season,
+        }
+    }
+
+    /// Pick the chip; mod, speed and season chips combine
+    pub fn apply(&self, filter: &mut ScoreFilter) {
+        match self {
+            FilterChip::Mods(mods) => filter.mods = mods.clone(),
+            FilterChip::Speed(speed) => filter.speed = *speed,
+            FilterChip::Season(season) => filter.season = season.clone(),
+        }
+    }
+}
+
+/// Chips for a set of scores: a row of All, No mod and each other modifier
+/// combination present, and a row of Any speed and each speed present
+pub fn filter_chips(signatures: &[&PlaySignature]) -> (Vec<FilterChip>, Vec<FilterChip>) {
+    let mut combinations: Vec<&Vec<Modifier>> = signatures
+        .iter()
+        .map(|signature| &signature.modifiers)
+        .filter(|modifiers| !modifiers.is_empty())
+        .collect();
+    combinations.sort_by_key(|modifiers| (modifiers.len(), mods_label(modifiers)));
+    combinations.dedup();
+    let mut mod_chips = vec![
+        FilterChip::Mods(ModFilter::All),
+        FilterChip::Mods(ModFilter::Exactly(Vec::new())),
+    ];
+    mod_chips.extend(
+        combinations
+            .into_iter()
+            .map(|modifiers| FilterChip::Mods(ModFilter::Exactly(modifiers.clone()))),
+    );
+
+    let mut speeds: Vec<u32> = signatures
+        .iter()
+        .map(|signature| signature.speed_percent)
+        .collect();
+    speeds.sort_unstable();
+    speeds.dedup();
+    let mut speed_chips = vec![FilterChip::Speed(None)];
+    speed_chips.extend(
+        speeds
+            .into_iter()
+            .map(|speed| FilterChip::Speed(Some(speed))),
+    );
+
+    (mod_chips, speed_chips)
+}
+
+/// Season chips for a set of scores: This season, All time and each past season with
+/// scores, most recently played first
+pub fn season_chips(scores: &[&GameSession], current: &str) -> Vec<FilterChip> {
+    let mut newest_first = scores.to_vec();
+    newest_first.sort_by_key(|session| std::cmp::Reverse(session.session_id));
+    let mut seasons: Vec<String> = Vec::new();
+    for id in newest_first.iter().map(|session| session.season_id()) {
+        if id != current && !seasons.contains(&id) {
+            seasons.push(id);
+        }
+    }
+    let mut chips = vec![
+        FilterChip::Season(SeasonFilter::Current),
+        FilterChip::Season(SeasonFilter::All),
+    ];
+    chips.extend(
+        seasons
+            .into_iter()
+            .map(|id| FilterChip::Season(SeasonFilter::Season(id))),
+    );
+    chips
+}
+
+/// A song's scores for its leaderboard: stored sessions of the song, leaving out
+/// unranked plays (auto, no fail) and local versus matches
+pub fn song_scores<'a>(analytics: &'a Analytics, song: &str) -> Vec<&'a GameSession> {
+    analytics
+        .recent_sessions
+        .iter()
+        .filter(|session| {
+            session.song_name == song && !session.unranked && session.counts_for_bests()
+        })
+        .collect()
+}
+
+/// Scores passing the filter, best first; ties go to the more accurate, then the
+/// earlier play. `current` is the id of the season running now
+pub fn filter_scores<'a>(
+    scores: &[&'a GameSession],
+    filter: &ScoreFilter,
+    current: &str,
+) -> Vec<&'a GameSession> {
+    let mut shown: Vec<&GameSession> = scores
+        .iter()
+        .copied()
+        .filter(|session| {
+            filter.matches(&session.signature)
+                && filter.season.matches(&session.season_id(), current)
+        })
+        .collect();
+    shown.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(b.accuracy.total_cmp(&a.accuracy))
+            .then(a.session_id.cmp(&b.session_id))
+    });
+    shown
+}
+
+/// 1-based rank of a session among filtered scores
+pub fn rank_of(scores: &[&GameSession], session_id: u64) -> Option<usize> {
+    scores
+        .iter()
+        .position(|session| session.session_id == session_id)
+        .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Modifier::{DoubleTime, HardRock, Hidden};
+
+    fn score(session_id: u64, score: i64, modifiers: &[Modifier], speed: f32) -> GameSession {
+        let mut session = GameSession::new("song".to_string());
+        session.session_id = session_id;
+        session.score = score;
+        session.accuracy = 90.0;
+        session.signature = PlaySignature::new(modifiers, speed);
+        session.season = Some("S1".to_string());
+        session
+    }
+
+    fn ids(scores: &[&GameSession]) -> Vec<u64> {
+        scores.iter().map(|session| session.session_id).collect()
+    }
+
+    #[test]
+    fn signatures_ignore_modifier_order_and_repeats() {
+        let hd_dt = PlaySignature::new(&[Hidden, DoubleTime], 1.0);
+        assert_eq!(hd_dt, PlaySignature::new(&[DoubleTime, Hidden], 1.0));
+        assert_eq!(
+            hd_dt,
+            PlaySignature::new(&[DoubleTime, Hidden, DoubleTime], 1.0)
+        );
+        assert_ne!(hd_dt, PlaySignature::new(&[Hidden, DoubleTime], 0.75));
+        assert_eq!(hd_dt.label(), "HD+DT");
+        assert_eq!(PlaySignature::new(&[], 0.749).label(), "No mod 0.75x");
+        assert_eq!(
+            PlaySignature::new(&[DoubleTime], 0.5).effective_speed(),
+            0.75
+        );
+    }
+
+    #[test]
+    fn filters_combine_mods_and_speed() {
+        let hd_dt = PlaySignature::new(&[DoubleTime, Hidden], 1.0);
+        let slow = PlaySignature::new(&[], 0.75);
+        let exactly = |modifiers: &[Modifier], speed: Option<u32>| ScoreFilter {
+            mods: ModFilter::Exactly(normalize_modifiers(modifiers)),
+            speed,
+            ..Default::default()
+        };
+
+        assert!(ScoreFilter::default().matches(&hd_dt));
+        assert!(exactly(&[Hidden, DoubleTime], None).matches(&hd_dt));
+        assert!(!exactly(&[Hidden], None).matches(&hd_dt));
+        assert!(!exactly(&[], None).matches(&hd_dt));
+        assert!(exactly(&[], Some(75)).matches(&slow));
+        assert!(!exactly(&[], Some(100)).matches(&slow));
+    }
+
+    #[test]
+    fn chips_list_each_combination_and_speed_once() {
+        let signatures = [
+            PlaySignature::new(&[DoubleTime, Hidden], 1.0),
+            PlaySignature::new(&[Hidden, DoubleTime], 0.75),
+            PlaySignature::new(&[HardRock], 1.0),
+            PlaySignature::new(&[], 1.0),
+        ];
+        let (mods, speeds) = filter_chips(&signatures.iter().collect::<Vec<_>>());
+        let labels = |chips: &[FilterChip]| chips.iter().map(FilterChip::label).collect::<Vec<_>>();
+        assert_eq!(labels(&mods), ["All", "No mod", "HR", "HD+DT"]);
+        assert_eq!(labels(&speeds), ["Any speed", "0.75x", "1.00x"]);
+
+        let mut filter = ScoreFilter::default();
+        mods[3].apply(&mut filter);
+        speeds[1].apply(&mut filter);
+        assert!(mods[3].is_selected(&filter) && speeds[1].is_selected(&filter));
+        assert!(!mods[0].is_selected(&filter));
+        assert!(filter.matches(&signatures[1]));
+        assert!(!filter.matches(&signatures[0]));
+    }
+
+    #[test]
+    fn ranks_are_counted_within_the_filtered_scores() {
+        let sessions = [
+            score(1, 900, &[Hidden, DoubleTime], 1.0),
+            score(2, 700, &[], 1.0),
+            score(3, 800, &[DoubleTime, Hidden], 1.0),
+            score(4, 500, &[], 1.0),
+            score(5, 950, &[], 0.75),
+        ];
+        let scores: Vec<&GameSession> = sessions.iter().collect();
+
+        let all = filter_scores(&scores, &ScoreFilter::default(), "S1");
+        assert_eq!(ids(&all), [5, 1, 3, 2, 4]);
+        assert_eq!(rank_of(&all, 3), Some(3));
+
+        let hd_dt = ScoreFilter {
+            mods: ModFilter::Exactly(vec![Hidden, DoubleTime]),
+            ..Default::default()
+        };
+        let shown = filter_scores(&scores, &hd_dt, "S1");
+        assert_eq!(ids(&shown), [1, 3]);
+        assert_eq!(rank_of(&shown, 3), Some(2));
+        assert_eq!(rank_of(&shown, 2), None);
+
+        let no_mod_normal_speed = ScoreFilter {
+            mods: ModFilter::Exactly(Vec::new()),
+            speed: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(&filter_scores(&scores, &no_mod_normal_speed, "S1")),
+            [2, 4]
+        );
+
+        // Nothing left to show rather than falling back to every score
+        let hard_rock = ScoreFilter {
+            mods: ModFilter::Exactly(vec![HardRock]),
+            ..Default::default()
+        };
+        assert!(filter_scores(&scores, &hard_rock, "S1").is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+// src/score_format.rs
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// How scores are written out on the HUD, results and analytics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScoreStyle {
+    /// Every digit with thousands separators, e.g. "1,234,567"
+    #[default]
+    Separated,
+    /// Three significant digits with a suffix from a thousand up, e.g. "1.23M"
+    Abbreviated,
+}
+
+impl ScoreStyle {
+    /// Name shown in settings
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScoreStyle::Separated => "1,234,567",
+            ScoreStyle::Abbreviated => "1.23M",
+        }
+    }
+
+    /// The other style
+    pub fn toggled(&self) -> ScoreStyle {
+        match self {
+            ScoreStyle::Separated => ScoreStyle::Abbreviated,
+            ScoreStyle::Abbreviated => ScoreStyle::Separated,
+        }
+    }
+}
+
+/// Suffixes for each power of a thousand
+const SUFFIXES: [&str; 7] = ["", "K", "M", "B", "T", "Q", "Qi"];
+
+/// Append a score to `out` without allocating beyond the buffer's own growth, so a
+/// reused buffer costs nothing per frame. Negative values (score deltas) keep
+/// their sign
+pub fn write_score(out: &mut String, score: i64, style: ScoreStyle) {
+    if score < 0 {
+        out.push('-');
+    }
+    let value = score.unsigned_abs();
+    match style {
+        ScoreStyle::Separated => write_separated(out, value),
+        ScoreStyle::Abbreviated => write_abbreviated(out, value),
+    }
+}
+
+/// Score as a new string, for places that format once rather than every frame
+pub fn format_score(score: i64, style: ScoreStyle) -> String {
+    let mut out = String::new();
+    write_score(&mut out, score, style);
+    out
+}
+
+/// Digits of `value` with a comma every three
+fn write_separated(out: &mut String, value: u64) {
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    for i in (0..len).rev() {
+        out.push(digits[i] as char);
+        if i > 0 && i % 3 == 0 {
+            out.push(',');
+        }
+    }
+}
+
+/// Three significant digits and a suffix; below a thousand the value is written
+/// in full. The value is rounded before the suffix and precision are picked, so a
+/// carry into another digit moves them along: 9,999 reads "10.0K" and 999,950
+/// reads "1.00M"
+fn write_abbreviated(out: &mut String, value: u64) {
+    if value < 1000 {
+        write_separated(out, value);
+        return;
+    }
+
+    // Wide enough that rounding up near u64::MAX can't overflow
+    let step = 10u128.pow(value.ilog10() + 1 - 3);
+    let rounded = (value as u128 + step / 2) / step * step;
+    let digits = rounded.ilog10() + 1;
+    let unit = ((digits - 1) / 3).min(SUFFIXES.len() as u32 - 1);
+    let significant = rounded / 10u128.pow(digits - 3);
+
+    // One, two or three of the significant digits come before the point
+    let fraction_digits = 3 - (digits - unit * 3).min(3);
+    let divisor = 10u128.pow(fraction_digits);
+    let _ = write!(out, "{}", significant / divisor);
+    if fraction_digits > 0 {
+        let _ = write!(
+            out,
+            ".{:0width$}",
+            significant % divisor,
+            width = fraction_digits as usize
+        );
+    }
+    out.push_str(SUFFIXES[unit as usize]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abbreviated(score: i64) -> String {
+        format_score(score, ScoreStyle::Abbreviated)
+    }
+
+    #[test]
+    fn separated_groups_thousands() {
+        assert_eq!(format_score(0, ScoreStyle::Separated), "0");
+        assert_eq!(format_score(999, ScoreStyle::Separated), "999");
+        assert_eq!(format_score(1_000, ScoreStyle::Separated), "1,000");
+        assert_eq!(format_score(1_234_567, ScoreStyle::Separated), "1,234,567");
+        assert_eq!(format_score(-12_345, ScoreStyle::Separated), "-12,345");
+    }
+
+    #[test]
+    fn abbreviated_keeps_three_significant_digits() {
+        assert_eq!(abbreviated(999), "999");
+        assert_eq!(abbreviated(1_000), "1.00K");
+        assert_eq!(abbreviated(1_234), "1.23K");
+        assert_eq!(abbreviated(12_345), "12.3K");
+        assert_eq!(abbreviated(123_456), "123K");
+        assert_eq!(abbreviated(1_234_567), "1.23M");
+        assert_eq!(abbreviated(-1_500), "-1.50K");
+    }
+
+    #[test]
+    fn abbreviated_rounds_before_picking_precision() {
+        assert_eq!(abbreviated(9_994), "9.99K");
+        assert_eq!(abbreviated(9_995), "10.0K");
+        assert_eq!(abbreviated(9_999), "10.0K");
+        assert_eq!(abbreviated(99_949), "99.9K");
+        assert_eq!(abbreviated(99_950), "100K");
+    }
+
+    #[test]
+    fn abbreviated_carries_into_the_next_suffix() {
+        assert_eq!(abbreviated(999_499), "999K");
+        assert_eq!(abbreviated(999_500), "1.00M");
+        assert_eq!(abbreviated(999_950), "1.00M");
+        assert_eq!(abbreviated(999_999), "1.00M");
+        assert_eq!(abbreviated(999_999_999), "1.00B");
+    }
+
+    #[test]
+    fn abbreviated_handles_the_extremes() {
+        assert_eq!(abbreviated(i64::MAX), "9.22Qi");
+        assert_eq!(abbreviated(i64::MIN), "-9.22Qi");
+    }
+}
@@ -0,0 +1,559 @@
+// src/seasons.rs
+
+use bevy::prelude::*;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::analytics::{Analytics, GameSession, Grade, HitStats, SECONDS_PER_DAY};
+use crate::config::GameConfig;
+use crate::online::OnlineServices;
+use crate::profiles::season_archive_path;
+use crate::toasts::Toasts;
+
+/// Best scores kept in an archived season summary
+pub const SUMMARY_BEST_SCORES: usize = 5;
+
+/// Grades from best to worst, the order grade counts are listed in
+const GRADES: [Grade; 8] = [
+    Grade::SSPlus,
+    Grade::SS,
+    Grade::S,
+    Grade::A,
+    Grade::B,
+    Grade::C,
+    Grade::D,
+    Grade::F,
+];
+
+/// A stretch of time scores are ranked within. Boundaries are instants in UTC, so a
+/// season ends at the same moment everywhere whatever the local clock says. The start
+/// is included and the end is not: a play exactly on a boundary opens the next season
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Season {
+    pub id: String,
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Season {
+    /// The calendar quarter holding a Unix timestamp, e.g. "2026-Q4" from October 1st
+    pub fn quarter_of(timestamp: i64) -> Season {
+        let time = DateTime::from_timestamp(timestamp, 0).unwrap_or_default();
+        Self::quarter(time.year(), time.month0() / 3)
+    }
+
+    /// A season from a quarter id written by `quarter_of`
+    pub fn from_quarter_id(id: &str) -> Option<Season> {
+        let (year, quarter) = id.split_once("-Q")?;
+        let year: i32 = year.parse().ok()?;
+        let quarter: u32 = quarter.parse().ok()?;
+        (1..=4)
+            .contains(&quarter)
+            .then(|| Self::quarter(year, quarter - 1))
+    }
+
+    /// Quarter `index` (0-3) of a year
+    fn quarter(year: i32, index: u32) -> Season {
+        let start = |year: i32, index: u32| {
+            Utc.with_ymd_and_hms(year, index * 3 + 1, 1, 0, 0, 0)
+                .single()
+                .unwrap_or_default()
+        };
+        let end = if index == 3 {
+            start(year + 1, 0)
+        } else {
+            start(year, index + 1)
+        };
+        Season {
+            id: format!("{}-Q{}", year, index + 1),
+            name: format!("{} Q{}", year, index + 1),
+            start: start(year, index),
+            end,
+        }
+    }
+
+    /// Whether a Unix timestamp falls within the season
+    pub fn contains(&self, timestamp: i64) -> bool {
+        self.start.timestamp() <= timestamp && timestamp < self.end.timestamp()
+    }
+
+    /// Whether the season is over at `now`
+    pub fn has_ended(&self, now: DateTime<Utc>) -> bool {
+        now >= self.end
+    }
+
+    /// Days (since the Unix epoch) the season covers any part of
+    pub fn days(&self) -> Range<u64> {
+        let start = self.start.timestamp().max(0) as u64;
+        let end = self.end.timestamp().max(0) as u64;
+        start / SECONDS_PER_DAY..end.div_ceil(SECONDS_PER_DAY)
+    }
+
+    /// Name with its dates, e.g. "2026 Q4 (2026-10-01 to 2026-12-31 UTC)"
+    pub fn label(&self) -> String {
+        let last_day = self.end - chrono::Duration::seconds(1);
+        format!(
+            "{} ({} to {} UTC)",
+            self.name,
+            self.start.format("%Y-%m-%d"),
+            last_day.format("%Y-%m-%d")
+        )
+    }
+}
+
+/// Seasons in play: those announced with the news feed, and calendar quarters for any
+/// time they don't cover
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SeasonCalendar {
+    announced: Vec<Season>,
+}
+
+impl SeasonCalendar {
+    /// Calendar from announced seasons; ones ending before they start are dropped
+    pub fn new(mut announced: Vec<Season>) -> Self {
+        announced.retain(|season| season.start < season.end);
+        announced.sort_by_key(|season| season.start);
+        Self { announced }
+    }
+
+    /// Season active at a Unix timestamp
+    pub fn season_at(&self, timestamp: i64) -> Season {
+        self.announced
+            .iter()
+            .find(|season| season.contains(timestamp))
+            .cloned()
+            .unwrap_or_else(|| Season::quarter_of(timestamp))
+    }
+
+    /// Season active now
+    pub fn current(&self) -> Season {
+        self.season_at(Utc::now().timestamp())
+    }
+
+    /// Season with this id, announced or a calendar quarter
+    pub fn get(&self, id: &str) -> Option<Season> {
+        self.announced
+            .iter()
+            .find(|season| season.id == id)
+            .cloned()
+            .or_else(|| Season::from_quarter_id(id))
+    }
+
+    /// Seasons the player has plays in, and the current one, newest first
+    pub fn played(&self, analytics: &Analytics) -> Vec<Season> {
+        let mut seasons: Vec<Season> = analytics
+            .seasons
+            .keys()
+            .filter_map(|id| self.get(id))
+            .collect();
+        let current = self.current();
+        if !seasons.contains(&current) {
+            seasons.push(current);
+        }
+        seasons.sort_by_key(|season| std::cmp::Reverse(season.start));
+        seasons
+    }
+}
+
+/// One song's plays within a season
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeasonSongStats {
+    pub plays: u32,
+    pub play_time_seconds: u64,
+    pub hits: HitStats,
+    /// Best score of a play that counts for bests, 0 if none did
+    pub best_score: i64,
+    pub best_accuracy: f32,
+    pub full_combos: u32,
+    /// Plays per grade, keyed by the grade's name
+    pub grades: BTreeMap<String, u32>,
+}
+
+/// What a season's plays added up to, per song so a song's reset can take its share out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeasonStats {
+    pub songs: BTreeMap<String, SeasonSongStats>,
+}
+
+impl SeasonStats {
+    /// Add a finished session
+    pub fn record(&mut self, session: &GameSession) {
+        let song = self.songs.entry(session.song_name.clone()).or_default();
+        song.plays += 1;
+        song.play_time_seconds += session.duration_seconds;
+        song.hits.add_session(&session.hits);
+        if session.counts_for_bests() {
+            song.best_score = song.best_score.max(session.score);
+            song.best_accuracy = song.best_accuracy.max(session.hits.accuracy());
+        }
+        if session.full_combo {
+            song.full_combos += 1;
+        }
+        *song
+            .grades
+            .entry(session.grade.as_str().to_string())
+            .or_default() += 1;
+    }
+
+    pub fn plays(&self) -> u32 {
+        self.songs.values().map(|song| song.plays).sum()
+    }
+
+    pub fn play_time_seconds(&self) -> u64 {
+        self.songs.values().map(|song| song.play_time_seconds).sum()
+    }
+
+    pub fn hits(&self) -> HitStats {
+        let mut hits = HitStats::new();
+        for song in self.songs.values() {
+            hits.add_session(&song.hits);
+        }
+        hits
+    }
+
+    pub fn full_combos(&self) -> u32 {
+        self.songs.values().map(|song| song.full_combos).sum()
+    }
+
+    /// Plays per grade, best grade first, leaving out grades never reached
+    pub fn grade_counts(&self) -> Vec<(String, u32)> {
+        GRADES
+            .iter()
+            .map(|grade| {
+                let count = self
+                    .songs
+                    .values()
+                    .flat_map(|song| song.grades.iter())
+                    .filter(|(name, _)| Grade::from_name(name) == Some(*grade))
+                    .map(|(_, count)| count)
+                    .sum();
+                (grade.as_str().to_string(), count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    /// Songs by their best score this season, best first
+    pub fn best_scores(&self, limit: usize) -> Vec<(String, i64)> {
+        let mut scores: Vec<(String, i64)> = self
+            .songs
+            .iter()
+            .filter(|(_, song)| song.best_score > 0)
+            .map(|(name, song)| (name.clone(), song.best_score))
+            .collect();
+        scores.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        scores.truncate(limit);
+        scores
+    }
+}
+
+/// A finished season as it stood when it was archived
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonSummary {
+    pub season: Season,
+    pub plays: u32,
+    pub play_time_seconds: u64,
+    pub accuracy: f32,
+    pub full_combos: u32,
+    /// Plays per grade, best grade first
+    pub grades: Vec<(String, u32)>,
+    /// Best scores of the season and their songs, best first
+    pub best_scores: Vec<(String, i64)>,
+    /// Place on the season's global online board, filled in once a signed-in player
+    /// has a rank there
+    #[serde(default)]
+    pub rank: Option<u32>,
+    pub archived_at: DateTime<Utc>,
+}
+
+impl SeasonSummary {
+    pub fn new(season: Season, stats: &SeasonStats, archived_at: DateTime<Utc>) -> Self {
+        Self {
+            season,
+            plays: stats.plays(),
+            play_time_seconds: stats.play_time_seconds(),
+            accuracy: stats.hits().accuracy(),
+            full_combos: stats.full_combos(),
+            grades: stats.grade_counts(),
+            best_scores: stats.best_scores(SUMMARY_BEST_SCORES),
+            rank: None,
+            archived_at,
+        }
+    }
+
+    /// Totals on one line, e.g. "42 plays | 3h 5m | 91.2% | 4 FC"
+    pub fn totals_line(&self) -> String {
+        let hours = self.play_time_seconds / 3600;
+        let minutes = (self.play_time_seconds % 3600) / 60;
+        let mut line = format!(
+            "{} plays | {}h {}m | {:.1}% | {} FC",
+            self.plays, hours, minutes, self.accuracy, self.full_combos
+        );
+        if let Some(rank) = self.rank {
+            line.push_str(&format!(" | rank #{}", rank));
+        }
+        line
+    }
+
+    /// Grade counts on one line, e.g. "S x3  A x10  B x2"
+    pub fn grades_line(&self) -> String {
+        self.grades
+            .iter()
+            .map(|(grade, count)| format!("{} x{}", grade, count))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+}
+
+/// Summaries of finished seasons, kept in a file of their own in the profile folder so
+/// wiping analytics leaves them be
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeasonArchive {
+    /// Oldest season first
+    pub summaries: Vec<SeasonSummary>,
+    /// Profile the archive belongs to, None until one is loaded
+    #[serde(skip)]
+    profile: Option<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl SeasonArchive {
+    /// Load a profile's archive, empty if it has none yet
+    pub fn load(profile: &str) -> Self {
+        let path = season_archive_path(profile);
+        let mut archive = if path.exists() {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            {
+                Ok(archive) => archive,
+                Err(e) => {
+                    warn!("Failed to load season archive: {}, starting a new one", e);
+                    Self::default()
+                }
+            }
+        } else {
+            Self::default()
+        };
+        archive.profile = Some(profile.to_string());
+        archive.path = path;
+        archive
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize season archive: {}", e))?;
+        fs::write(&self.path, json).map_err(|e| format!("Failed to save season archive: {}", e))
+    }
+
+    /// Whether a season has been archived
+    pub fn contains(&self, id: &str) -> bool {
+        self.summaries.iter().any(|summary| summary.season.id == id)
+    }
+
+    /// Fill in the rank of summaries that have none from `rank_of`, given a season id.
+    /// Returns whether any rank was filled in
+    pub fn fill_ranks(&mut self, rank_of: impl Fn(&str) -> Option<u32>) -> bool {
+        let mut filled = false;
+        for summary in self.summaries.iter_mut().filter(|summary| summary.rank.is_none()) {
+            summary.rank = rank_of(&summary.season.id);
+            filled |= summary.rank.is_some();
+        }
+        filled
+    }
+
+    /// Archive every season with plays that has ended by `now` and isn't archived yet,
+    /// so running it again adds nothing. Returns the seasons archived
+    pub fn roll_over(
+        &mut self,
+        analytics: &Analytics,
+        calendar: &SeasonCalendar,
+        now: DateTime<Utc>,
+    ) -> Vec<Season> {
+        let mut archived = Vec::new();
+        for (id, stats) in &analytics.seasons {
+            if self.contains(id) || stats.plays() == 0 {
+                continue;
+            }
+            let Some(season) = calendar.get(id) else {
+                continue;
+            };
+            if season.has_ended(now) {
+                self.summaries
+                    .push(SeasonSummary::new(season.clone(), stats, now));
+                archived.push(season);
+            }
+        }
+        self.summaries.sort_by_key(|summary| summary.season.start);
+        archived
+    }
+}
+
+/// Load the active profile's season archive, at launch and after a profile switch,
+/// and archive the seasons that ended since it was last opened. Archived seasons get
+/// the signed-in player's online rank once there is one
+pub fn roll_over_seasons(
+    config: Res<GameConfig>,
+    analytics: Res<Analytics>,
+    calendar: Res<SeasonCalendar>,
+    online: Res<OnlineServices>,
+    mut archive: ResMut<SeasonArchive>,
+    mut toasts: ResMut<Toasts>,
+) {
+    let mut changed = false;
+    if archive.profile.as_deref() != Some(config.active_profile.as_str()) {
+        *archive = SeasonArchive::load(&config.active_profile);
+        for season in archive.roll_over(&analytics, &calendar, Utc::now()) {
+            info!("Archived season {}", season.id);
+            toasts.push(format!(
+                "Season {} is over - see Analytics > Past Seasons",
+                season.name
+            ));
+            changed = true;
+        }
+    }
+    if changed || online.is_changed() {
+        changed |= archive.fill_ranks(|season| online.season_rank(season));
+    }
+    if changed {
+        if let Err(e) = archive.save() {
+            error!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use crate::test_support::scratch_dir;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, second)
+            .unwrap()
+            .timestamp()
+    }
+
+    fn announced(id: &str, start: i64, end: i64) -> Season {
+        Season {
+            id: id.to_string(),
+            name: id.to_string(),
+            start: DateTime::from_timestamp(start, 0).unwrap(),
+            end: DateTime::from_timestamp(end, 0).unwrap(),
+        }
+    }
+
+    /// A finished play at a Unix timestamp, tagged with its season like a recorded one
+    fn play(calendar: &SeasonCalendar, song: &str, score: i64, timestamp: i64) -> GameSession {
+        GameSession {
+            session_id: timestamp as u64,
+            score,
+            season: Some(calendar.season_at(timestamp).id),
+            ..GameSession::new(song.to_string())
+        }
+    }
+
+    #[test]
+    fn a_play_on_a_quarter_boundary_opens_the_new_quarter() {
+        let boundary = utc(2026, 10, 1, 0, 0, 0);
+        assert_eq!(Season::quarter_of(boundary).id, "2026-Q4");
+        assert_eq!(Season::quarter_of(boundary - 1).id, "2026-Q3");
+        assert_eq!(
+            Season::quarter_of(utc(2026, 12, 31, 23, 59, 59)).id,
+            "2026-Q4"
+        );
+        assert_eq!(Season::quarter_of(utc(2027, 1, 1, 0, 0, 0)).id, "2027-Q1");
+
+        let q3 = Season::quarter_of(boundary - 1);
+        assert!(q3.contains(boundary - 1));
+        assert!(!q3.contains(boundary));
+        assert!(!q3.has_ended(q3.end - chrono::Duration::seconds(1)));
+        assert!(q3.has_ended(q3.end));
+        assert_eq!(q3.label(), "2026 Q3 (2026-07-01 to 2026-09-30 UTC)");
+    }
+
+    #[test]
+    fn boundaries_are_the_same_instant_in_every_timezone() {
+        // Evening of September 30th in New York is already October 1st in UTC
+        let new_york = FixedOffset::west_opt(4 * 3600).unwrap();
+        let evening = new_york.with_ymd_and_hms(2026, 9, 30, 20, 0, 0).unwrap();
+        assert_eq!(Season::quarter_of(evening.timestamp()).id, "2026-Q4");
+        // And just before midnight in Tokyo is still September in UTC
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let night = tokyo.with_ymd_and_hms(2026, 10, 1, 8, 59, 59).unwrap();
+        assert_eq!(Season::quarter_of(night.timestamp()).id, "2026-Q3");
+    }
+
+    #[test]
+    fn quarter_ids_round_trip() {
+        let season = Season::quarter_of(utc(2025, 5, 17, 12, 0, 0));
+        assert_eq!(Season::from_quarter_id(&season.id), Some(season));
+        for bad in ["2025-Q0", "2025-Q5", "2025", "Q2-2025", "S1"] {
+            assert_eq!(Season::from_quarter_id(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn announced_seasons_take_over_the_quarters_they_cover() {
+        let start = utc(2026, 8, 15, 0, 0, 0);
+        let end = utc(2026, 11, 15, 0, 0, 0);
+        let calendar = SeasonCalendar::new(vec![
+            announced("summer-cup", start, end),
+            announced("backwards", end, start),
+        ]);
+        assert_eq!(calendar.season_at(start).id, "summer-cup");
+        assert_eq!(calendar.season_at(end - 1).id, "summer-cup");
+        assert_eq!(calendar.season_at(start - 1).id, "2026-Q3");
+        assert_eq!(calendar.season_at(end).id, "2026-Q4");
+        assert_eq!(calendar.get("summer-cup").unwrap().start.timestamp(), start);
+        assert_eq!(calendar.get("backwards"), None);
+        assert_eq!(calendar.get("2026-Q2").unwrap().id, "2026-Q2");
+    }
+
+    #[test]
+    fn rolling_over_twice_archives_each_season_once() {
+        let calendar = SeasonCalendar::default();
+        let boundary = utc(2026, 10, 1, 0, 0, 0);
+        let dir = scratch_dir("seasons");
+        let mut analytics = Analytics::load(dir.join("analytics.json"));
+        analytics.add_session(play(&calendar, "a", 900, boundary - 3600));
+        analytics.add_session(play(&calendar, "b", 400, boundary - 1));
+        analytics.add_session(play(&calendar, "a", 700, boundary));
+
+        let mut archive = SeasonArchive::default();
+        let just_before = DateTime::from_timestamp(boundary - 1, 0).unwrap();
+        assert!(archive
+            .roll_over(&analytics, &calendar, just_before)
+            .is_empty());
+
+        let at_boundary = DateTime::from_timestamp(boundary, 0).unwrap();
+        let archived = archive.roll_over(&analytics, &calendar, at_boundary);
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].id, "2026-Q3");
+        let summary = &archive.summaries[0];
+        assert_eq!(summary.plays, 2);
+        assert_eq!(
+            summary.best_scores,
+            [("a".to_string(), 900), ("b".to_string(), 400)]
+        );
+
+        assert!(archive
+            .roll_over(&analytics, &calendar, at_boundary)
+            .is_empty());
+        let later = DateTime::from_timestamp(boundary + 86400, 0).unwrap();
+        assert!(archive.roll_over(&analytics, &calendar, later).is_empty());
+        assert_eq!(archive.summaries.len(), 1);
+
+        // Ranked once the player signs in, and never ranked over again
+        assert!(!archive.fill_ranks(|_| None));
+        assert!(archive.fill_ranks(|id| (id == "2026-Q3").then_some(4)));
+        assert!(!archive.fill_ranks(|_| Some(1)));
+        assert!(archive.summaries[0].totals_line().ends_with("| rank #4"));
+    }
+}
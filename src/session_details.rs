@@ -0,0 +1,303 @@
+// src/session_details.rs
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::analytics::{GameSession, JudgmentKind, JudgmentLog};
+
+/// Folder next to a profile's analytics.json holding one details file per session
+pub const SESSION_DETAILS_DIR: &str = "sessions";
+
+/// Caps the details folder can be set to (megabytes)
+pub const DETAILS_CAP_STEPS_MB: [u32; 6] = [16, 32, 64, 128, 256, 512];
+
+/// Default cap on the details folder (megabytes)
+pub const DEFAULT_DETAILS_CAP_MB: u32 = 64;
+
+/// Next cap step from `current_mb` in `direction`, wrapping around
+pub fn cycled_cap(current_mb: u32, direction: i32) -> u32 {
+    let index = DETAILS_CAP_STEPS_MB
+        .iter()
+        .position(|&step| step == current_mb)
+        .unwrap_or(2) as i32;
+    let step = if direction < 0 { -1 } else { 1 };
+    DETAILS_CAP_STEPS_MB[(index + step).rem_euclid(DETAILS_CAP_STEPS_MB.len() as i32) as usize]
+}
+
+/// Per-session data too heavy to keep in analytics.json: the judgment log and hit
+/// timings. Written next to the analytics when the session is recorded and read back
+/// only when a detail view opens it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionDetails {
+    pub judgments: JudgmentLog,
+    /// How early (negative) or late each hit was (milliseconds)
+    pub hit_timings: Vec<f32>,
+}
+
+impl SessionDetails {
+    /// Whether there is nothing worth writing
+    pub fn is_empty(&self) -> bool {
+        self.judgments.entries.is_empty() && self.hit_timings.is_empty()
+    }
+
+    /// Read a details file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read session details: {}", e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse session details: {}", e))
+    }
+
+    /// Write the details of `session` into `dir`. Returns the path to keep on the session
+    pub fn save(&self, dir: &Path, session: &GameSession) -> Result<String, String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create session details folder: {}", e))?;
+        // Both players of a local versus match finish in the same second
+        let name = match session.local_versus {
+            Some(player) => format!("{}-p{}.json", session.session_id, player),
+            None => format!("{}.json", session.session_id),
+        };
+        let path = dir.join(name);
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize session details: {}", e))?;
+        fs::write(&path, json).map_err(|e| format!("Failed to save session details: {}", e))?;
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// One line for the session detail view
+    pub fn summary(&self) -> String {
+        let first_miss = self
+            .judgments
+            .entries
+            .iter()
+            .find(|entry| entry.kind == JudgmentKind::Miss)
+            .map(|entry| {
+                let seconds = entry.time.max(0.0);
+                format!(
+                    "first miss {}:{:04.1}",
+                    (seconds / 60.0) as u64,
+                    seconds % 60.0
+                )
+            })
+            .unwrap_or_else(|| "no misses".to_string());
+        let mut line = format!(
+            "{} judgments{}, {}",
+            self.judgments.entries.len(),
+            if self.judgments.truncated { "+" } else { "" },
+            first_miss
+        );
+        if !self.hit_timings.is_empty() {
+            let mean = self.hit_timings.iter().sum::<f32>() / self.hit_timings.len() as f32;
+            line.push_str(&format!(", mean offset {:+.1} ms", mean));
+        }
+        line
+    }
+}
+
+/// What the details folder holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetailsUsage {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl DetailsUsage {
+    /// Files and sizes in a details folder; empty if it doesn't exist yet
+    pub fn of(dir: &Path) -> Self {
+        details_files(dir)
+            .iter()
+            .fold(Self::default(), |usage, (_, bytes)| Self {
+                files: usage.files + 1,
+                bytes: usage.bytes + bytes,
+            })
+    }
+
+    /// e.g. "12 sessions, 1.4 MB of 64 MB"
+    pub fn summary(&self, cap_mb: u32) -> String {
+        format!(
+            "{} sessions, {} of {} MB",
+            self.files,
+            format_bytes(self.bytes),
+            cap_mb
+        )
+    }
+}
+
+/// Details files in a folder with their sizes
+fn details_files(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.len())))
+        .collect()
+}
+
+/// Delete details files until the folder fits in `cap_bytes`. Files no session points
+/// at go first, then the oldest sessions' files; files of pinned sessions are never
+/// deleted. Returns the paths deleted, for clearing them off their sessions
+pub fn evict_details(dir: &Path, cap_bytes: u64, sessions: &[GameSession]) -> Vec<String> {
+    let files = details_files(dir);
+    let mut total: u64 = files.iter().map(|(_, bytes)| bytes).sum();
+    if total <= cap_bytes {
+        return Vec::new();
+    }
+
+    let referenced: HashSet<PathBuf> = sessions
+        .iter()
+        .filter_map(|session| session.details_file.as_deref().map(PathBuf::from))
+        .collect();
+    let mut oldest_first: Vec<&GameSession> =
+        sessions.iter().filter(|session| !session.pinned).collect();
+    oldest_first.sort_by_key(|session| session.session_id);
+
+    let orphans = files
+        .iter()
+        .filter(|(path, _)| !referenced.contains(path))
+        .map(|(path, bytes)| (path.clone(), *bytes));
+    let unpinned = oldest_first.into_iter().filter_map(|session| {
+        let path = PathBuf::from(session.details_file.as_deref()?);
+        let (_, bytes) = files.iter().find(|(file, _)| *file == path)?;
+        Some((path, *bytes))
+    });
+
+    let mut evicted = Vec::new();
+    for (path, bytes) in orphans.chain(unpinned) {
+        if total <= cap_bytes {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                total = total.saturating_sub(bytes);
+                evicted.push(path.to_string_lossy().into_owned());
+            }
+            Err(e) => bevy::log::warn!("Failed to evict {}: {}", path.display(), e),
+        }
+    }
+    evicted
+}
+
+/// Byte count as "512 B", "12.4 KB" or "3.1 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    /// An empty details folder of the test's own
+    fn details_dir(test: &str) -> PathBuf {
+        scratch_dir(&format!("details-{}", test))
+    }
+
+    /// A session recorded at `id` whose details file holds `bytes` bytes
+    fn session(dir: &Path, id: u64, bytes: usize, pinned: bool) -> GameSession {
+        let path = dir.join(format!("{}.json", id));
+        fs::write(&path, vec![b' '; bytes]).unwrap();
+        GameSession {
+            session_id: id,
+            details_file: Some(path.to_string_lossy().into_owned()),
+            pinned,
+            ..GameSession::new("song".to_string())
+        }
+    }
+
+    fn names(paths: &[String]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| {
+                Path::new(path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn eviction_removes_orphans_then_the_oldest_unpinned_sessions() {
+        let dir = details_dir("evict");
+        let sessions = vec![
+            session(&dir, 30, 100, false),
+            session(&dir, 10, 100, true),
+            session(&dir, 20, 100, false),
+            session(&dir, 40, 100, false),
+        ];
+        fs::write(dir.join("orphan.json"), vec![b' '; 100]).unwrap();
+        fs::write(dir.join("notes.txt"), vec![b' '; 1000]).unwrap();
+        assert_eq!(
+            DetailsUsage::of(&dir),
+            DetailsUsage {
+                files: 5,
+                bytes: 500
+            }
+        );
+
+        assert!(evict_details(&dir, 500, &sessions).is_empty());
+        let evicted = evict_details(&dir, 300, &sessions);
+        assert_eq!(names(&evicted), ["orphan.json", "20.json"]);
+        assert_eq!(DetailsUsage::of(&dir).bytes, 300);
+
+        // Pinned details stay even when nothing else fits
+        let evicted = evict_details(&dir, 0, &sessions);
+        assert_eq!(names(&evicted), ["30.json", "40.json"]);
+        assert!(dir.join("10.json").exists());
+        assert_eq!(
+            DetailsUsage::of(&dir),
+            DetailsUsage {
+                files: 1,
+                bytes: 100
+            }
+        );
+    }
+
+    #[test]
+    fn versus_players_get_files_of_their_own() {
+        let dir = details_dir("versus");
+        let details = SessionDetails {
+            hit_timings: vec![-3.0, 5.0],
+            ..Default::default()
+        };
+        let mut session = GameSession {
+            session_id: 77,
+            ..GameSession::new("song".to_string())
+        };
+        session.local_versus = Some(1);
+        let one = details.save(&dir, &session).unwrap();
+        session.local_versus = Some(2);
+        let two = details.save(&dir, &session).unwrap();
+
+        assert_ne!(one, two);
+        assert!(one.ends_with("77-p1.json"));
+        let loaded = SessionDetails::load(Path::new(&two)).unwrap();
+        assert_eq!(loaded.hit_timings, [-3.0, 5.0]);
+        assert_eq!(
+            loaded.summary(),
+            "0 judgments, no misses, mean offset +1.0 ms"
+        );
+    }
+
+    #[test]
+    fn the_cap_cycles_through_its_steps() {
+        assert_eq!(cycled_cap(64, 1), 128);
+        assert_eq!(cycled_cap(512, 1), 16);
+        assert_eq!(cycled_cap(16, -1), 512);
+        assert_eq!(cycled_cap(100, 1), 128);
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(12 * 1024 + 400), "12.4 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 + 100 * 1024), "3.1 MB");
+    }
+}
@@ -0,0 +1,567 @@
+// src/settings_registry.rs
+
+use crate::config::{
+    AudioSetting, DataSetting, DisplaySetting, GameConfig, GameplaySetting, GenerationSetting,
+    KeyBindingType, PracticeSetting, SettingsState, SettingsTab, ThemeSetting,
+};
+use crate::library_scan::LibraryScan;
+use crate::online::AccountMaintenance;
+use crate::text_input::TextInput;
+
+/// Search results listed at once; a longer query narrows the rest down
+pub const MAX_SEARCH_RESULTS: usize = 10;
+
+/// An editable row of the Settings screen, by the tab enum that labels and edits it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingRow {
+    Generation(GenerationSetting),
+    Data(DataSetting),
+    Gameplay(GameplaySetting),
+    KeyBinding(KeyBindingType),
+    Theme(ThemeSetting),
+    Audio(AudioSetting),
+    Display(DisplaySetting),
+    Practice(PracticeSetting),
+}
+
+impl SettingRow {
+    /// Label with the current value
+    pub fn label(
+        &self,
+        config: &GameConfig,
+        state: &SettingsState,
+        library: &LibraryScan,
+        maintenance: &AccountMaintenance,
+    ) -> String {
+        match self {
+            SettingRow::Generation(setting) => setting.label(&config.generation),
+            SettingRow::Data(setting) => setting.label(config, library, maintenance),
+            SettingRow::Gameplay(setting) => setting.label(config),
+            SettingRow::KeyBinding(binding) => binding.label(config, state),
+            SettingRow::Theme(setting) => setting.label(state, &config.theme),
+            SettingRow::Audio(setting) => setting.label(&config.audio),
+            SettingRow::Display(setting) => setting.label(&config.display),
+            SettingRow::Practice(setting) => setting.label(&config.practice),
+        }
+    }
+}
+
+/// A setting as listed in its tab and found by search
+#[derive(Debug)]
+pub struct SettingEntry {
+    /// Stable name, unique across all tabs
+    pub id: &'static str,
+    pub tab: SettingsTab,
+    /// Name without the current value, matched by search
+    pub name: &'static str,
+    /// Other words a player might search for it by
+    pub keywords: &'static [&'static str],
+    pub row: SettingRow,
+}
+
+impl SettingEntry {
+    /// Whether every word of the query appears in the name or a keyword, ignoring case
+    pub fn matches(&self, query: &str) -> bool {
+        let name = self.name.to_lowercase();
+        query.to_lowercase().split_whitespace().all(|word| {
+            name.contains(word)
+                || self
+                    .keywords
+                    .iter()
+                    .any(|keyword| keyword.to_lowercase().contains(word))
+        })
+    }
+}
+
+/// Every setting, grouped by tab in display order
+pub const SETTINGS: &[SettingEntry] = &[
+    SettingEntry {
+        id: "generation.spinner_min_gap",
+        tab: SettingsTab::General,
+        name: "Spinner Min Gap",
+        keywords: &["map generation", "spinner", "pause"],
+        row: SettingRow::Generation(GenerationSetting::SpinnerMinGap),
+    },
+    SettingEntry {
+        id: "generation.spinner_min_energy",
+        tab: SettingsTab::General,
+        name: "Spinner Min Energy",
+        keywords: &["map generation", "spinner", "loudness"],
+        row: SettingRow::Generation(GenerationSetting::SpinnerMinEnergy),
+    },
+    SettingEntry {
+        id: "generation.stream_max_interval",
+        tab: SettingsTab::General,
+        name: "Stream Max Interval",
+        keywords: &["map generation", "stream", "slider"],
+        row: SettingRow::Generation(GenerationSetting::StreamMaxInterval),
+    },
+    SettingEntry {
+        id: "generation.stream_min_beats",
+        tab: SettingsTab::General,
+        name: "Stream Min Beats",
+        keywords: &["map generation", "stream", "slider"],
+        row: SettingRow::Generation(GenerationSetting::StreamMinBeats),
+    },
+    SettingEntry {
+        id: "generation.slider_max_beats",
+        tab: SettingsTab::General,
+        name: "Slider Max Beats",
+        keywords: &["map generation", "slider", "length"],
+        row: SettingRow::Generation(GenerationSetting::SliderMaxBeats),
+    },
+    SettingEntry {
+        id: "data.incognito",
+        tab: SettingsTab::General,
+        name: "Incognito",
+        keywords: &["privacy", "record", "analytics"],
+        row: SettingRow::Data(DataSetting::Incognito),
+    },
+    SettingEntry {
+        id: "data.hit_precision",
+        tab: SettingsTab::General,
+        name: "Record Hit Precision",
+        keywords: &["privacy", "heatmap", "aim", "timing", "accuracy"],
+        row: SettingRow::Data(DataSetting::HitPrecision),
+    },
+    SettingEntry {
+        id: "data.delete_analytics",
+        tab: SettingsTab::General,
+        name: "Delete All Local Analytics",
+        keywords: &["privacy", "wipe", "reset", "statistics"],
+        row: SettingRow::Data(DataSetting::DeleteAnalytics),
+    },
+    SettingEntry {
+        id: "data.library_health",
+        tab: SettingsTab::General,
+        name: "Beatmap Library",
+        keywords: &["scan", "health", "missing audio", "broken"],
+        row: SettingRow::Data(DataSetting::LibraryHealth),
+    },
+    SettingEntry {
+        id: "data.log_verbosity",
+        tab: SettingsTab::General,
+        name: "Log Level",
+        keywords: &["logging", "verbosity", "debug", "errors", "file"],
+        row: SettingRow::Data(DataSetting::LogVerbosity),
+    },
+    SettingEntry {
+        id: "data.session_details",
+        tab: SettingsTab::General,
+        name: "Session Details",
+        keywords: &["storage", "disk", "cap", "judgments", "space"],
+        row: SettingRow::Data(DataSetting::SessionDetails),
+    },
+    SettingEntry {
+        id: "data.account_storage",
+        tab: SettingsTab::General,
+        name: "Account Storage",
+        keywords: &["storage", "disk", "accounts", "sessions", "friends", "size"],
+        row: SettingRow::Data(DataSetting::AccountStorage),
+    },
+    SettingEntry {
+        id: "data.account_cleanup",
+        tab: SettingsTab::General,
+        name: "Clean Up Account Data Now",
+        keywords: &["storage", "maintenance", "sessions", "purge", "compact"],
+        row: SettingRow::Data(DataSetting::AccountCleanup),
+    },
+    SettingEntry {
+        id: "gameplay.hud_layout",
+        tab: SettingsTab::Gameplay,
+        name: "Edit HUD layout",
+        keywords: &["hud", "position", "score", "combo", "lives"],
+        row: SettingRow::Gameplay(GameplaySetting::HudLayout),
+    },
+    SettingEntry {
+        id: "gameplay.reduced_motion",
+        tab: SettingsTab::Gameplay,
+        name: "Reduced Motion",
+        keywords: &["accessibility", "animation", "kiai", "pulse"],
+        row: SettingRow::Gameplay(GameplaySetting::ReducedMotion),
+    },
+    SettingEntry {
+        id: "gameplay.accuracy_readout",
+        tab: SettingsTab::Gameplay,
+        name: "Accuracy Readout",
+        keywords: &["hud", "percent"],
+        row: SettingRow::Gameplay(GameplaySetting::AccuracyReadout),
+    },
+    SettingEntry {
+        id: "gameplay.accuracy_bar",
+        tab: SettingsTab::Gameplay,
+        name: "Accuracy Bar",
+        keywords: &["hud", "grade", "gauge"],
+        row: SettingRow::Gameplay(GameplaySetting::AccuracyBar),
+    },
+    SettingEntry {
+        id: "gameplay.large_scores",
+        tab: SettingsTab::Gameplay,
+        name: "Large Scores",
+        keywords: &["score", "format", "abbreviate", "separators"],
+        row: SettingRow::Gameplay(GameplaySetting::LargeScores),
+    },
+    SettingEntry {
+        id: "keys.primary_hit",
+        tab: SettingsTab::KeyBindings,
+        name: "Primary Hit",
+        keywords: &["keys", "hit", "tap", "z", "x"],
+        row: SettingRow::KeyBinding(KeyBindingType::PrimaryHit),
+    },
+    SettingEntry {
+        id: "keys.secondary_hit",
+        tab: SettingsTab::KeyBindings,
+        name: "Secondary Hit",
+        keywords: &["keys", "hit", "tap", "z", "x"],
+        row: SettingRow::KeyBinding(KeyBindingType::SecondaryHit),
+    },
+    SettingEntry {
+        id: "keys.pause",
+        tab: SettingsTab::KeyBindings,
+        name: "Pause",
+        keywords: &["keys", "escape", "stop"],
+        row: SettingRow::KeyBinding(KeyBindingType::Pause),
+    },
+    SettingEntry {
+        id: "keys.exit",
+        tab: SettingsTab::KeyBindings,
+        name: "Exit Song",
+        keywords: &["keys", "escape", "quit", "leave"],
+        row: SettingRow::KeyBinding(KeyBindingType::Exit),
+    },
+    SettingEntry {
+        id: "keys.navigate_up",
+        tab: SettingsTab::KeyBindings,
+        name: "Navigate Up",
+        keywords: &["keys", "menu", "arrow"],
+        row: SettingRow::KeyBinding(KeyBindingType::NavigateUp),
+    },
+    SettingEntry {
+        id: "keys.navigate_down",
+        tab: SettingsTab::KeyBindings,
+        name: "Navigate Down",
+        keywords: &["keys", "menu", "arrow"],
+        row: SettingRow::KeyBinding(KeyBindingType::NavigateDown),
+    },
+    SettingEntry {
+        id: "keys.select",
+        tab: SettingsTab::KeyBindings,
+        name: "Select / Confirm",
+        keywords: &["keys", "menu", "enter", "confirm"],
+        row: SettingRow::KeyBinding(KeyBindingType::Select),
+    },
+    SettingEntry {
+        id: "keys.quick_retry",
+        tab: SettingsTab::KeyBindings,
+        name: "Quick Retry",
+        keywords: &["keys", "restart", "retry"],
+        row: SettingRow::KeyBinding(KeyBindingType::QuickRetry),
+    },
+    SettingEntry {
+        id: "keys.skip_intro",
+        tab: SettingsTab::KeyBindings,
+        name: "Skip Intro",
+        keywords: &["keys", "skip", "intro", "space"],
+        row: SettingRow::KeyBinding(KeyBindingType::SkipIntro),
+    },
+    SettingEntry {
+        id: "keys.versus_primary_hit",
+        tab: SettingsTab::KeyBindings,
+        name: "Versus P2 Primary Hit",
+        keywords: &["keys", "versus", "player two", "hit"],
+        row: SettingRow::KeyBinding(KeyBindingType::VersusPrimaryHit),
+    },
+    SettingEntry {
+        id: "keys.versus_secondary_hit",
+        tab: SettingsTab::KeyBindings,
+        name: "Versus P2 Secondary Hit",
+        keywords: &["keys", "versus", "player two", "hit"],
+        row: SettingRow::KeyBinding(KeyBindingType::VersusSecondaryHit),
+    },
+    SettingEntry {
+        id: "theme.preset",
+        tab: SettingsTab::Theme,
+        name: "Theme Preset",
+        keywords: &["import", "yumtheme", "colors", "skin"],
+        row: SettingRow::Theme(ThemeSetting::Preset),
+    },
+    SettingEntry {
+        id: "theme.follow_points",
+        tab: SettingsTab::Theme,
+        name: "Follow Points",
+        keywords: &["combo", "dots"],
+        row: SettingRow::Theme(ThemeSetting::FollowPoints),
+    },
+    SettingEntry {
+        id: "theme.hit_lighting",
+        tab: SettingsTab::Theme,
+        name: "Hit Lighting",
+        keywords: &["glow", "flash", "effects"],
+        row: SettingRow::Theme(ThemeSetting::HitLighting),
+    },
+    SettingEntry {
+        id: "theme.hit_error_bar",
+        tab: SettingsTab::Theme,
+        name: "Hit Error Bar",
+        keywords: &["timing", "early", "late", "offset", "unstable rate"],
+        row: SettingRow::Theme(ThemeSetting::HitErrorBar),
+    },
+    SettingEntry {
+        id: "theme.export",
+        tab: SettingsTab::Theme,
+        name: "Export Current Theme",
+        keywords: &["share", "yumtheme", "save"],
+        row: SettingRow::Theme(ThemeSetting::Export),
+    },
+    SettingEntry {
+        id: "audio.master_volume",
+        tab: SettingsTab::Audio,
+        name: "Master Volume",
+        keywords: &["sound", "loudness", "mute"],
+        row: SettingRow::Audio(AudioSetting::MasterVolume),
+    },
+    SettingEntry {
+        id: "audio.music_volume",
+        tab: SettingsTab::Audio,
+        name: "Music Volume",
+        keywords: &["sound", "loudness", "song"],
+        row: SettingRow::Audio(AudioSetting::MusicVolume),
+    },
+    SettingEntry {
+        id: "audio.detection_quality",
+        tab: SettingsTab::Audio,
+        name: "Beat Detection",
+        keywords: &["quality", "analysis", "onsets", "tempo"],
+        row: SettingRow::Audio(AudioSetting::DetectionQuality),
+    },
+    SettingEntry {
+        id: "audio.menu_music",
+        tab: SettingsTab::Audio,
+        name: "Menu Music",
+        keywords: &["preview", "background", "shuffle", "songs"],
+        row: SettingRow::Audio(AudioSetting::MenuMusic),
+    },
+    SettingEntry {
+        id: "audio.offset",
+        tab: SettingsTab::Audio,
+        name: "Audio Offset",
+        keywords: &["latency", "delay", "sync", "early", "late", "timing"],
+        row: SettingRow::Audio(AudioSetting::AudioOffset),
+    },
+    SettingEntry {
+        id: "audio.calibrate_offset",
+        tab: SettingsTab::Audio,
+        name: "Calibrate Offset",
+        keywords: &["latency", "metronome", "sync", "tap", "timing"],
+        row: SettingRow::Audio(AudioSetting::CalibrateOffset),
+    },
+    SettingEntry {
+        id: "audio.analyze_library",
+        tab: SettingsTab::Audio,
+        name: "Analyze Entire Library",
+        keywords: &["beats", "cache", "pre-analyze", "library", "detection"],
+        row: SettingRow::Audio(AudioSetting::AnalyzeLibrary),
+    },
+    SettingEntry {
+        id: "display.performance_preset",
+        tab: SettingsTab::Display,
+        name: "Performance",
+        keywords: &["preset", "quality", "potato", "fps", "low spec", "glow"],
+        row: SettingRow::Display(DisplaySetting::PerformancePreset),
+    },
+    SettingEntry {
+        id: "display.reduced_input_latency",
+        tab: SettingsTab::Display,
+        name: "Reduced Input Latency",
+        keywords: &["vsync", "lag", "delay", "input", "tearing", "judgment"],
+        row: SettingRow::Display(DisplaySetting::ReducedInputLatency),
+    },
+    SettingEntry {
+        id: "practice.playback_speed",
+        tab: SettingsTab::Practice,
+        name: "Default Speed",
+        keywords: &["playback", "rate", "slow"],
+        row: SettingRow::Practice(PracticeSetting::PlaybackSpeed),
+    },
+    SettingEntry {
+        id: "practice.preserve_pitch",
+        tab: SettingsTab::Practice,
+        name: "Preserve Pitch",
+        keywords: &["time stretch", "speed", "chipmunk", "slow down"],
+        row: SettingRow::Practice(PracticeSetting::PreservePitch),
+    },
+    SettingEntry {
+        id: "practice.no_fail",
+        tab: SettingsTab::Practice,
+        name: "Default No Fail",
+        keywords: &["fail", "lives"],
+        row: SettingRow::Practice(PracticeSetting::NoFail),
+    },
+    SettingEntry {
+        id: "practice.autoplay",
+        tab: SettingsTab::Practice,
+        name: "Default Autoplay",
+        keywords: &["auto", "watch"],
+        row: SettingRow::Practice(PracticeSetting::Autoplay),
+    },
+    SettingEntry {
+        id: "practice.hit_sounds",
+        tab: SettingsTab::Practice,
+        name: "Hit Sounds",
+        keywords: &["audio", "hitsound", "volume"],
+        row: SettingRow::Practice(PracticeSetting::HitSounds),
+    },
+    SettingEntry {
+        id: "practice.metronome",
+        tab: SettingsTab::Practice,
+        name: "Metronome",
+        keywords: &["click", "beat", "timing", "pulse"],
+        row: SettingRow::Practice(PracticeSetting::Metronome),
+    },
+    SettingEntry {
+        id: "practice.remember_per_song",
+        tab: SettingsTab::Practice,
+        name: "Remember Settings Per Song",
+        keywords: &["practice", "song"],
+        row: SettingRow::Practice(PracticeSetting::RememberPerSong),
+    },
+    SettingEntry {
+        id: "practice.reset_defaults",
+        tab: SettingsTab::Practice,
+        name: "Reset Practice Defaults",
+        keywords: &["practice", "restore"],
+        row: SettingRow::Practice(PracticeSetting::ResetDefaults),
+    },
+];
+
+/// A tab's settings in display order
+pub fn tab_entries(tab: SettingsTab) -> impl Iterator<Item = &'static SettingEntry> {
+    SETTINGS.iter().filter(move |entry| entry.tab == tab)
+}
+
+/// Where a setting sits within its tab
+pub fn tab_index(entry: &SettingEntry) -> usize {
+    tab_entries(entry.tab)
+        .position(|other| other.id == entry.id)
+        .unwrap_or(0)
+}
+
+/// The search field over every tab and the result picked in it
+#[derive(Debug, Clone, Default)]
+pub struct SettingsSearch {
+    pub input: TextInput,
+    pub selected: usize,
+}
+
+impl SettingsSearch {
+    /// Settings matching the query, in tab order. Nothing until something is typed
+    pub fn results(&self) -> Vec<&'static SettingEntry> {
+        let query = self.input.text().trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        SETTINGS
+            .iter()
+            .filter(|entry| entry.matches(query))
+            .take(MAX_SEARCH_RESULTS)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Tab a row's settings enum is drawn and edited on
+    fn home_tab(row: SettingRow) -> SettingsTab {
+        match row {
+            SettingRow::Generation(_) | SettingRow::Data(_) => SettingsTab::General,
+            SettingRow::Gameplay(_) => SettingsTab::Gameplay,
+            SettingRow::KeyBinding(_) => SettingsTab::KeyBindings,
+            SettingRow::Theme(_) => SettingsTab::Theme,
+            SettingRow::Audio(_) => SettingsTab::Audio,
+            SettingRow::Display(_) => SettingsTab::Display,
+            SettingRow::Practice(_) => SettingsTab::Practice,
+        }
+    }
+
+    fn search(query: &str) -> Vec<&'static str> {
+        let mut search = SettingsSearch::default();
+        search.input.set_text(query);
+        search.results().iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn setting_ids_are_unique() {
+        let mut seen = HashSet::new();
+        for entry in SETTINGS {
+            assert!(seen.insert(entry.id), "{} is registered twice", entry.id);
+        }
+    }
+
+    #[test]
+    fn every_entry_resolves_to_its_row_on_its_tab() {
+        let (config, state, library, maintenance) = (
+            GameConfig::default(),
+            SettingsState::default(),
+            LibraryScan::default(),
+            AccountMaintenance::default(),
+        );
+        for entry in SETTINGS {
+            assert_eq!(
+                home_tab(entry.row),
+                entry.tab,
+                "{} is on the wrong tab",
+                entry.id
+            );
+            let listed = tab_entries(entry.tab).nth(tab_index(entry)).unwrap();
+            assert_eq!(listed.id, entry.id);
+            assert_eq!(listed.row, entry.row);
+            assert!(!entry.row.label(&config, &state, &library, &maintenance).is_empty());
+        }
+        for (tab, name) in SettingsTab::all() {
+            assert!(
+                tab_entries(tab).next().is_some(),
+                "{} has no settings",
+                name
+            );
+        }
+        for binding in KeyBindingType::all() {
+            assert!(SETTINGS
+                .iter()
+                .any(|entry| entry.row == SettingRow::KeyBinding(binding)));
+        }
+    }
+
+    #[test]
+    fn search_finds_settings_by_name_and_keyword() {
+        assert_eq!(
+            search("hud"),
+            [
+                "gameplay.hud_layout",
+                "gameplay.accuracy_readout",
+                "gameplay.accuracy_bar"
+            ]
+        );
+        assert_eq!(
+            search("Privacy"),
+            [
+                "data.incognito",
+                "data.hit_precision",
+                "data.delete_analytics"
+            ]
+        );
+        assert_eq!(
+            search("spinner"),
+            [
+                "generation.spinner_min_gap",
+                "generation.spinner_min_energy"
+            ]
+        );
+        // Every word has to match
+        assert_eq!(search("spinner energy"), ["generation.spinner_min_energy"]);
+        assert!(search("  ").is_empty());
+        assert!(search("no such setting").is_empty());
+    }
+}
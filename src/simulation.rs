@@ -0,0 +1,573 @@
+// src/simulation.rs
+
+use crate::audio::PlaybackPosition;
+use crate::constants::{COMBO_MILESTONES, KIAI_MILESTONE_BOOST};
+use crate::game::{
+    calculate_score_from_timing, circle_radius, handle_missed_circles, hp_change_for_hit,
+    update_sliders,
+};
+use crate::structs::{FloatingText, VisualizingState};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Simulation step in milliseconds. Judgments only ever see song times on this grid,
+/// so identical input timelines give identical results at any frame rate.
+pub const SIM_STEP_MS: i64 = 1;
+
+/// Quantize a song time in seconds to the simulation grid
+pub fn quantize(seconds: f64) -> i64 {
+    ((seconds * 1000.0).round() as i64 / SIM_STEP_MS) * SIM_STEP_MS
+}
+
+/// Judgment time of a song time (ms on the simulation grid): the song time less the
+/// player's audio offset, so someone who hears the music late is judged against
+/// what they heard
+pub fn judgment_ms(seconds: f64, offset_ms: i32) -> i64 {
+    quantize(seconds) - offset_ms as i64
+}
+
+/// How often the song clock is compared against the audio position (seconds)
+pub const SYNC_INTERVAL: f64 = 0.1;
+
+/// Drift below this is treated as measurement noise from the output buffer (seconds)
+pub const SYNC_DEADBAND: f64 = 0.002;
+
+/// Share of the measured drift corrected per second
+pub const SYNC_GAIN: f64 = 0.5;
+
+/// Fastest the correction may speed up or slow down the clock, as a fraction of
+/// real time; well below 1 so the song clock never runs backwards
+pub const SYNC_MAX_SLEW: f64 = 0.05;
+
+/// Song time in seconds of a simulation step
+pub fn step_seconds(time_ms: i64) -> f64 {
+    time_ms as f64 / 1000.0
+}
+
+/// A hit key press or release at a quantized song time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+    /// Song time on the simulation grid (ms)
+    pub time_ms: i64,
+    /// Cursor position when the key was pressed
+    pub position: Vec2,
+    /// Whether the last held hit key was let go rather than a key pressed; releases
+    /// end slider holds and are never judged
+    pub release: bool,
+}
+
+/// Fixed-step judgment clock. Rendering reads the continuous song time; only
+/// judgments and misses are stepped.
+#[derive(Debug, Clone, Default)]
+pub struct JudgmentClock {
+    /// Steps before this time (ms) have been fully simulated
+    pub time_ms: i64,
+    /// Inputs waiting for their step
+    pending: VecDeque<InputEvent>,
+    /// Every input in order, for replays and score verification
+    pub input_log: Vec<InputEvent>,
+    /// Whether a hit key is held as of the current step, for slider bodies
+    holding: bool,
+}
+
+impl JudgmentClock {
+    /// Queue an input; inputs must arrive in time order
+    pub fn push_input(&mut self, input: InputEvent) {
+        // Inputs can never land on an already simulated step
+        let input = InputEvent {
+            time_ms: input.time_ms.max(self.time_ms),
+            ..input
+        };
+        self.input_log.push(input);
+        self.pending.push_back(input);
+    }
+
+    /// Simulate every step before `target_ms`, then judge the inputs already queued on
+    /// `target_ms` so a press is judged in the frame it was read. Within a step inputs
+    /// are judged before misses and slider bodies. Misses on `target_ms` wait for the
+    /// next call so inputs that arrive later in the same millisecond are still applied
+    /// first. Returns true if the game should end.
+    pub fn advance(&mut self, state: &mut VisualizingState, target_ms: i64, shrink_time: f64) -> bool {
+        let mut should_end_game = false;
+
+        while self.time_ms < target_ms {
+            self.judge_due_inputs(state, shrink_time);
+
+            let now = step_seconds(self.time_ms);
+            let mut circles = std::mem::take(&mut state.circles);
+            should_end_game |= handle_missed_circles(&mut circles, now, state, shrink_time);
+            update_sliders(&mut circles, now, state, self.holding);
+            state.circles = circles;
+            should_end_game |= state.hp_depleted();
+
+            self.time_ms += SIM_STEP_MS;
+        }
+        self.judge_due_inputs(state, shrink_time);
+
+        should_end_game || state.hp_depleted()
+    }
+
+    /// Judge the queued inputs up to the current step
+    fn judge_due_inputs(&mut self, state: &mut VisualizingState, shrink_time: f64) {
+        let now = step_seconds(self.time_ms);
+        while let Some(input) = self.pending.front().copied() {
+            if input.time_ms > self.time_ms {
+                break;
+            }
+            self.pending.pop_front();
+            self.holding = !input.release;
+            if !input.release {
+                judge_input(state, now, shrink_time, input.position);
+            }
+        }
+    }
+
+    /// Jump forward to `target_ms` without stepping, for stretches where nothing can
+    /// be judged such as a skipped intro. Never moves backwards
+    pub fn skip_to(&mut self, target_ms: i64) {
+        self.time_ms = self.time_ms.max(target_ms);
+    }
+
+    /// Jump back to `target_ms` when a practice loop wraps. Queued inputs are dropped;
+    /// the input log keeps its order of arrival, so a looped play can't be replayed
+    pub fn rewind_to(&mut self, target_ms: i64) {
+        self.time_ms = target_ms;
+        self.pending.clear();
+    }
+
+    /// Song time to draw at for a song time read after `advance`: the last simulated
+    /// step plus the remainder of the step the song clock is in, so objects move
+    /// smoothly between steps but are never drawn ahead of their judgments
+    pub fn render_seconds(&self, seconds: f64, offset_ms: i32) -> f64 {
+        let judged_ms = seconds * 1000.0 - offset_ms as f64;
+        let previous_ms = (self.time_ms - SIM_STEP_MS) as f64;
+        if judged_ms < previous_ms {
+            return seconds;
+        }
+        let remainder = ((judged_ms - previous_ms) / SIM_STEP_MS as f64).min(1.0);
+        (previous_ms + remainder * SIM_STEP_MS as f64 + offset_ms as f64) / 1000.0
+    }
+}
+
+/// Judge a hit key press at a song time against the closest hittable circle
+pub fn judge_input(state: &mut VisualizingState, elapsed: f64, shrink_time: f64, mouse_pos: Vec2) {
+    // Find the closest hittable circle
+    let mut best_circle_idx: Option<usize> = None;
+    let mut best_distance = f32::MAX;
+
+    for (idx, circle) in state.circles.iter().enumerate() {
+        if circle.hit || circle.missed {
+            continue;
+        }
+
+        if let Some(radius) = circle_radius(circle, elapsed, shrink_time) {
+            let distance = mouse_pos.distance(circle.position);
+            if distance < radius && distance < best_distance {
+                best_distance = distance;
+                best_circle_idx = Some(idx);
+            }
+        }
+    }
+
+    // Process the hit
+    let Some(idx) = best_circle_idx else {
+        return;
+    };
+    let circle = &mut state.circles[idx];
+    circle.hit = true;
+    let (hit_time, position, radius) = (circle.hit_time, circle.position, circle.max_radius);
+    let hitsound = circle.hitsound;
+
+    let hit_time_diff = (elapsed - hit_time).abs();
+    let points = calculate_score_from_timing(hit_time_diff, &state.game_settings);
+
+    // Record the hit with timing
+    let timing_ms = (hit_time_diff * 1000.0) as f32;
+    state.record_hit(points, timing_ms);
+    state.change_hp(hp_change_for_hit(points, state.hp_drain));
+    if points > 0 && state.config.practice.hit_sounds {
+        state.hitsounds.push(hitsound);
+    }
+    // Every hit is aimed with the cursor, so the position offset is always known
+    let timing_offset_ms = ((elapsed - hit_time) * 1000.0) as f32;
+    state.record_precision((mouse_pos - position) / radius, timing_offset_ms);
+    let kind = crate::analytics::JudgmentKind::from_points(points);
+    state.log_judgment(hit_time, position, kind);
+    state.hit_errors.record(timing_offset_ms, kind, elapsed);
+    if state.config.theme.hit_lighting && !state.config.theme.reduced_motion {
+        state.hit_lights.spawn(position, radius, kind, elapsed);
+    }
+
+    // Add floating text
+    let (text, color) = match points {
+        300 => ("Perfect!", (0.0, 1.0, 0.5)),
+        100 => ("Good!", (0.0, 0.75, 1.0)),
+        50 => ("Okay", (1.0, 1.0, 0.0)),
+        _ => ("Miss", (1.0, 0.0, 0.0)),
+    };
+
+    state.floating_texts.push(FloatingText {
+        text: text.to_string(),
+        position,
+        spawn_time: elapsed,
+        duration: 1.0,
+        color,
+        scale: 1.0,
+    });
+
+    // Combo milestones pop bigger during kiai
+    if COMBO_MILESTONES.contains(&state.combo) {
+        let kiai = state.kiai_intensity(elapsed);
+        state.floating_texts.push(FloatingText {
+            text: format!("{} Combo!", state.combo),
+            position: position + Vec2::new(0.0, 40.0),
+            spawn_time: elapsed,
+            duration: 1.5,
+            color: (1.0, 0.84, 0.0),
+            scale: 1.25 * (1.0 + KIAI_MILESTONE_BOOST * kiai),
+        });
+    }
+}
+
+/// Continuous song clock for gameplay and rendering. Runs off the wall clock and is
+/// slewed toward the audio position so long songs don't drift out of sync; it is
+/// never stepped, so circles and judgments never see time jump.
+#[derive(Debug, Clone)]
+pub struct SongClock {
+    start: Instant,
+    speed: f64,
+    /// Correction added to the scaled wall clock (seconds)
+    offset: f64,
+    /// Audio position being followed, if the song is playing
+    audio: Option<PlaybackPosition>,
+    /// Last measured audio time minus song time (seconds)
+    pub drift: f64,
+    /// Wall time of the last measurement
+    last_sync: f64,
+    /// Song time the clock is frozen at while the game is paused
+    paused_at: Option<f64>,
+}
+
+impl SongClock {
+    /// Start a clock at song time zero
+    pub fn new(speed: f32, audio: Option<PlaybackPosition>) -> Self {
+        Self {
+            start: Instant::now(),
+            speed: speed as f64,
+            offset: 0.0,
+            audio,
+            drift: 0.0,
+            last_sync: 0.0,
+            paused_at: None,
+        }
+    }
+
+    /// Current song time in seconds
+    pub fn now(&self) -> f64 {
+        self.now_at(self.start.elapsed().as_secs_f64())
+    }
+
+    /// Song time at `wall` seconds since the clock started
+    fn now_at(&self, wall: f64) -> f64 {
+        if let Some(seconds) = self.paused_at {
+            return seconds;
+        }
+        wall * self.speed + self.offset
+    }
+
+    /// Freeze the clock at the current song time
+    pub fn pause(&mut self) {
+        self.paused_at = Some(self.now());
+    }
+
+    /// Carry on from the song time the clock was frozen at, following the same audio,
+    /// which has to be resumed from the same position
+    pub fn resume(&mut self) {
+        if let Some(seconds) = self.paused_at.take() {
+            let audio = self.audio.take();
+            self.seek(seconds, audio);
+        }
+    }
+
+    /// Jump to a song time after the audio was restarted there; drift measured
+    /// against the old source is discarded
+    pub fn seek(&mut self, seconds: f64, audio: Option<PlaybackPosition>) {
+        let wall = self.start.elapsed().as_secs_f64();
+        self.offset = seconds - wall * self.speed;
+        self.audio = audio;
+        self.drift = 0.0;
+        self.last_sync = wall;
+    }
+
+    /// Measure drift every `SYNC_INTERVAL` and nudge the clock toward the audio by
+    /// a small proportional correction; `dt` is the frame time in seconds
+    pub fn sync(&mut self, dt: f64) {
+        self.sync_at(self.start.elapsed().as_secs_f64(), dt);
+    }
+
+    fn sync_at(&mut self, wall: f64, dt: f64) {
+        let Some(audio) = &self.audio else {
+            return;
+        };
+        if self.paused_at.is_some() {
+            return;
+        }
+        // Before the output starts and after the song ends the position stands still
+        if !audio.started() || audio.finished() {
+            return;
+        }
+
+        if wall - self.last_sync >= SYNC_INTERVAL {
+            self.last_sync = wall;
+            self.drift = audio.seconds() - self.now_at(wall);
+        }
+
+        if self.drift.abs() > SYNC_DEADBAND {
+            let limit = SYNC_MAX_SLEW * self.speed * dt;
+            let correction = (self.drift * SYNC_GAIN * dt).clamp(-limit, limit);
+            self.offset += correction;
+            self.drift -= correction;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::structs::GameCircle;
+
+    const SHRINK_TIME: f64 = 1.5;
+
+    fn circle(x: f32, hit_time: f64) -> GameCircle {
+        GameCircle {
+            position: Vec2::new(x, 0.0),
+            spawn_time: hit_time - SHRINK_TIME,
+            hit_time,
+            max_radius: 60.0,
+            hit: false,
+            missed: false,
+            kind: Default::default(),
+            new_combo: false,
+            hitsound: Default::default(),
+        }
+    }
+
+    fn state() -> VisualizingState {
+        let circles = vec![
+            circle(0.0, 1.0),
+            circle(100.0, 1.4),
+            circle(-100.0, 1.75),
+            circle(200.0, 2.2),
+            circle(0.0, 2.5),
+        ];
+        VisualizingState::new(Vec::new(), circles, GameConfig::default(), "test".into())
+    }
+
+    fn press(time_ms: i64, x: f32) -> InputEvent {
+        InputEvent {
+            time_ms,
+            position: Vec2::new(x, 0.0),
+            release: false,
+        }
+    }
+
+    /// Replay an input log frame by frame, queueing each input in the first frame
+    /// at or after it, and return the judgments and the final score
+    fn replay(inputs: &[InputEvent], fps: f64) -> (String, i64) {
+        let mut state = state();
+        let mut clock = JudgmentClock::default();
+        let mut queued = inputs.iter().peekable();
+        let mut judgments = Vec::new();
+        let mut frame = 0;
+        loop {
+            let now_ms = quantize(frame as f64 / fps);
+            while let Some(input) = queued.next_if(|input| input.time_ms <= now_ms) {
+                clock.push_input(*input);
+            }
+            clock.advance(&mut state, now_ms, SHRINK_TIME);
+            judgments.append(&mut state.bus_events);
+            if now_ms > 4500 {
+                break;
+            }
+            frame += 1;
+        }
+        (format!("{:?}", judgments), state.score)
+    }
+
+    #[test]
+    fn judgments_are_identical_at_any_frame_rate() {
+        // Early presses, a press off every circle, a double tap and circles left to be
+        // missed
+        let inputs = [
+            press(989, 10.0),
+            press(1386, 100.0),
+            press(1600, 300.0),
+            press(2190, 200.0),
+            press(2191, 200.0),
+        ];
+        let (judgments, score) = replay(&inputs, 60.0);
+        assert!(judgments.contains("JudgmentMade"));
+        assert!(score > 0);
+        for fps in [30.0, 240.0] {
+            let replayed = replay(&inputs, fps);
+            assert_eq!(replayed, (judgments.clone(), score), "{} FPS", fps);
+        }
+    }
+
+    #[test]
+    fn rendering_stays_within_the_last_step() {
+        let mut clock = JudgmentClock::default();
+        clock.skip_to(1000);
+        // Between the last two steps the remainder carries over
+        assert!((clock.render_seconds(0.9994, 0) - 0.9994).abs() < 1e-9);
+        // Past the simulation it holds at the last step
+        assert!((clock.render_seconds(1.0123, 0) - 1.0).abs() < 1e-9);
+        // The audio offset shifts the judged time, not the drawn one
+        assert!((clock.render_seconds(1.0304, 30) - 1.03).abs() < 1e-9);
+        // Behind the simulation, e.g. before the first step, the song time is kept
+        assert!((clock.render_seconds(0.5, 0) - 0.5).abs() < 1e-9);
+    }
+
+    /// Position handle of a source nothing reads, moved by hand with `set_seconds`
+    fn audio_position() -> PlaybackPosition {
+        let source = rodio::buffer::SamplesBuffer::new(1, 48_000, vec![0.0f32]);
+        crate::audio::CountingSource::new(source).1
+    }
+
+    /// Play ten minutes at 60 fps against audio running at `rate` times real time.
+    /// Returns the largest error once the clock has caught up and how often the
+    /// error changed sign outside the deadband
+    fn follow_audio(rate: f64) -> (f64, usize) {
+        let position = audio_position();
+        let mut clock = SongClock::new(1.0, Some(position.clone()));
+        let dt = 1.0 / 60.0;
+        let (mut worst, mut flips, mut last_sign) = (0.0f64, 0, 0.0);
+
+        for frame in 1..=36_000 {
+            let wall = frame as f64 * dt;
+            position.set_seconds(wall * rate);
+            clock.sync_at(wall, dt);
+
+            let error = position.seconds() - clock.now_at(wall);
+            if wall < 30.0 {
+                continue;
+            }
+            worst = worst.max(error.abs());
+            if error.abs() > SYNC_DEADBAND {
+                if last_sign != 0.0 && error.signum() != last_sign {
+                    flips += 1;
+                }
+                last_sign = error.signum();
+            }
+        }
+        (worst, flips)
+    }
+
+    #[test]
+    fn the_song_clock_follows_fast_and_slow_audio() {
+        for rate in [1.001, 0.999] {
+            let (worst, flips) = follow_audio(rate);
+            assert!(
+                worst < 0.010,
+                "{}x audio: {:.1} ms off",
+                rate,
+                worst * 1000.0
+            );
+            assert_eq!(flips, 0, "{}x audio oscillates", rate);
+        }
+    }
+
+    #[test]
+    fn practice_speed_reaches_each_beat_at_its_song_time_over_the_speed() {
+        let beats = [0.5, 1.0, 2.25, 30.0];
+        for speed in [0.5f32, 0.75, 1.5] {
+            let clock = SongClock::new(speed, None);
+            for beat in beats {
+                let wall = beat / speed as f64;
+                assert!(
+                    (clock.now_at(wall) - beat).abs() < 1e-9,
+                    "{}x: beat {} at {}",
+                    speed,
+                    beat,
+                    clock.now_at(wall)
+                );
+            }
+        }
+
+        // Following stretched audio the clock still reads song time
+        let position = audio_position();
+        let mut clock = SongClock::new(0.5, Some(position.clone()));
+        let dt = 1.0 / 60.0;
+        for frame in 1..=600 {
+            let wall = frame as f64 * dt;
+            position.set_seconds(wall * 0.5);
+            clock.sync_at(wall, dt);
+        }
+        assert!((clock.now_at(10.0) - 5.0).abs() < 0.010);
+    }
+
+    #[test]
+    fn a_paused_clock_ignores_the_audio() {
+        let position = audio_position();
+        let mut clock = SongClock::new(1.0, Some(position.clone()));
+        position.set_seconds(5.0);
+        clock.pause();
+        let frozen = clock.now();
+        clock.sync_at(1.0, 1.0);
+        assert_eq!(clock.now_at(60.0), frozen);
+    }
+
+    #[test]
+    fn skipping_the_intro_leaves_the_first_circle_one_lead_in_and_approach_away() {
+        use crate::constants::{INTRO_SKIP_LEAD_IN, INTRO_SKIP_THRESHOLD};
+
+        let circles = vec![circle(0.0, 20.0), circle(100.0, 20.5)];
+        let mut state =
+            VisualizingState::new(Vec::new(), circles, GameConfig::default(), "test".into());
+        let target = state.intro_skip_target(0.0).unwrap();
+        assert_eq!(state.circles[0].spawn_time - target, INTRO_SKIP_LEAD_IN);
+        assert_eq!(
+            state.circles[0].hit_time - target,
+            INTRO_SKIP_LEAD_IN + SHRINK_TIME
+        );
+
+        // Skip the way gameplay does: restart the audio there and move both clocks
+        let position = audio_position();
+        let mut song_clock = SongClock::new(1.0, None);
+        position.set_seconds(target);
+        song_clock.seek(target, Some(position.clone()));
+        let mut clock = JudgmentClock::default();
+        clock.skip_to(quantize(target));
+        state.record_intro_skip(target);
+
+        assert!((song_clock.now() - target).abs() < 0.001);
+        assert_eq!(song_clock.drift, 0.0);
+        assert_eq!(
+            state.active_session.as_ref().unwrap().skipped_intro_seconds,
+            target
+        );
+        // Nothing to skip any more, and the judgment clock never winds back
+        assert_eq!(state.intro_skip_target(target), None);
+        assert!(state.circles[0].spawn_time - target <= INTRO_SKIP_THRESHOLD);
+        clock.skip_to(0);
+        assert_eq!(clock.time_ms, quantize(target));
+
+        // The audio carries on from the skip point in step with the song clock
+        let dt = 1.0 / 60.0;
+        let base = song_clock.last_sync;
+        for frame in 1..=120 {
+            let wall = base + frame as f64 * dt;
+            position.set_seconds(target + frame as f64 * dt);
+            song_clock.sync_at(wall, dt);
+            assert!((song_clock.now_at(wall) - position.seconds()).abs() < 0.001);
+        }
+
+        // The first circle is judged on time
+        clock.push_input(press(19_990, 0.0));
+        clock.advance(&mut state, 19_990, SHRINK_TIME);
+        assert_eq!(state.hits.perfect, 1);
+        assert_eq!(state.hits.misses, 0);
+    }
+}
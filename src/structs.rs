@@ -2,11 +2,19 @@
 
 use bevy::prelude::*;
 use std::time::Instant;
-use uuid::Uuid;
 
-use crate::analytics::ActiveSession;
-use crate::config::GameConfig;
-use crate::gamemode::GameSettings;
+use crate::analytics::{ActiveSession, Analytics, CompletionStatus, GradeRules};
+use crate::audio::DetectionQuality;
+use crate::beatmap::{Hitsound, PracticeHint};
+use crate::bots::{BotProfile, RoomBot, Standing};
+use crate::config::{GameConfig, MetronomeMode, PracticeConfig};
+use crate::constants::{INTRO_SKIP_LEAD_IN, INTRO_SKIP_THRESHOLD, NO_FAIL_HP_FLOOR};
+use crate::event_bus::BusEvent;
+use crate::gamemode::{GameSettings, Modifier};
+use crate::multiplayer::PickerRotation;
+use crate::precision::PrecisionMap;
+use crate::score_filter::{PlaySignature, ScoreFilter};
+use crate::text_input::TextInput;
 
 /// UI Assets container
 #[derive(Resource, Clone)]
@@ -17,12 +25,101 @@ pub struct GameAssets {
 /// Song selection state
 #[derive(Debug, Clone, Resource)]
 pub struct SongSelectionState {
-    pub scroll_pos: f32,
+    /// First row of the list shown; the list scrolls to keep the keyboard cursor in view
+    pub scroll_pos: usize,
+    /// Hovered song (or the keyboard cursor's, after moving it); its leaderboard is shown
     pub selected_song: Option<String>,
+    /// Keyboard cursor, an index into the visible list
+    pub selected_index: usize,
     /// Whether practice mode is enabled
     pub practice_mode: bool,
     /// Selected playback speed for practice mode
     pub playback_speed: f32,
+    /// Which songs the list shows
+    pub filter: SongFilter,
+    /// Order of the list
+    pub sort: SongSort,
+    /// Chips picked on the hovered song's leaderboard, kept for the visit
+    pub score_filter: ScoreFilter,
+    /// Result of the last seed paste, shown with the key hints
+    pub seed_notice: Option<String>,
+    /// Search box, open after `/` or Ctrl+F; the list only shows songs whose name
+    /// contains its text
+    pub query: Option<TextInput>,
+}
+
+/// Name a song is listed under: its file name, upper-cased, without the extension
+pub fn song_name(path: &str) -> String {
+    path.split('/')
+        .next_back()
+        .unwrap_or(path)
+        .to_uppercase()
+        .replace(".MP3", "")
+}
+
+/// Song list filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SongFilter {
+    #[default]
+    All,
+    Unplayed,
+    NotFullCombo,
+}
+
+impl SongFilter {
+    /// Get display name for the filter
+    pub fn name(&self) -> &'static str {
+        match self {
+            SongFilter::All => "All",
+            SongFilter::Unplayed => "Unplayed only",
+            SongFilter::NotFullCombo => "Not yet FC'd",
+        }
+    }
+
+    /// Next filter in the cycle
+    pub fn next(&self) -> SongFilter {
+        match self {
+            SongFilter::All => SongFilter::Unplayed,
+            SongFilter::Unplayed => SongFilter::NotFullCombo,
+            SongFilter::NotFullCombo => SongFilter::All,
+        }
+    }
+
+    /// Whether a song with this status is listed
+    pub fn allows(&self, status: CompletionStatus) -> bool {
+        match self {
+            SongFilter::All => true,
+            SongFilter::Unplayed => status == CompletionStatus::Unplayed,
+            SongFilter::NotFullCombo => status < CompletionStatus::FullCombo,
+        }
+    }
+}
+
+/// Song list order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SongSort {
+    #[default]
+    Name,
+    /// Least complete first
+    Status,
+}
+
+impl SongSort {
+    /// Get display name for the sort
+    pub fn name(&self) -> &'static str {
+        match self {
+            SongSort::Name => "Name",
+            SongSort::Status => "Status",
+        }
+    }
+
+    /// Next sort in the cycle
+    pub fn next(&self) -> SongSort {
+        match self {
+            SongSort::Name => SongSort::Status,
+            SongSort::Status => SongSort::Name,
+        }
+    }
 }
 
 impl Default for SongSelectionState {
@@ -35,14 +132,103 @@ impl SongSelectionState {
     /// Create new song selection state
     pub fn new() -> Self {
         Self {
-            scroll_pos: 0.0,
+            scroll_pos: 0,
             selected_song: None,
+            selected_index: 0,
             practice_mode: false,
             playback_speed: 1.0,
+            filter: SongFilter::All,
+            sort: SongSort::Name,
+            score_filter: ScoreFilter::default(),
+            seed_notice: None,
+            query: None,
+        }
+    }
+
+    /// Text typed into the search box, trimmed; empty while it's closed
+    pub fn query_text(&self) -> &str {
+        self.query.as_ref().map_or("", |query| query.text().trim())
+    }
+
+    /// Songs to list with their completion status, filtered, searched and sorted
+    pub fn visible_songs(
+        &self,
+        songs: &[String],
+        analytics: &Analytics,
+    ) -> Vec<(String, CompletionStatus)> {
+        let query = self.query_text().to_uppercase();
+        let mut visible: Vec<(String, CompletionStatus)> = songs
+            .iter()
+            .filter(|song| song_name(song).contains(&query))
+            .map(|song| (song.clone(), analytics.completion(song)))
+            .filter(|(_, status)| self.filter.allows(*status))
+            .collect();
+        if self.sort == SongSort::Status {
+            // Stable, so songs with the same status stay in name order
+            visible.sort_by_key(|(_, status)| *status);
+        }
+        visible
+    }
+
+    /// Keyboard cursor moved by `step` rows through a list of `count`, wrapping at
+    /// the ends. Stays at 0 on an empty list
+    pub fn moved_index(&self, step: i32, count: usize) -> usize {
+        if count == 0 {
+            return 0;
+        }
+        (self.selected_index.min(count - 1) as i32 + step).rem_euclid(count as i32) as usize
+    }
+
+    /// First row to show so the keyboard cursor is one of `rows_shown` rows
+    pub fn scroll_for(&self, index: usize, rows_shown: usize) -> usize {
+        let rows_shown = rows_shown.max(1);
+        if index < self.scroll_pos {
+            index
+        } else if index >= self.scroll_pos + rows_shown {
+            index + 1 - rows_shown
+        } else {
+            self.scroll_pos
         }
     }
 }
 
+/// Completion counts across the song library
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LibraryProgress {
+    pub total: usize,
+    pub full_combo: usize,
+    pub ss: usize,
+}
+
+impl LibraryProgress {
+    /// Count the library's full combos and SSes
+    pub fn of(songs: &[String], analytics: &Analytics) -> Self {
+        let statuses: Vec<CompletionStatus> = songs
+            .iter()
+            .map(|song| analytics.completion(song))
+            .collect();
+        Self {
+            total: songs.len(),
+            full_combo: statuses
+                .iter()
+                .filter(|status| **status >= CompletionStatus::FullCombo)
+                .count(),
+            ss: statuses
+                .iter()
+                .filter(|status| **status == CompletionStatus::SS)
+                .count(),
+        }
+    }
+
+    /// Summary line for the top of song selection
+    pub fn summary(&self) -> String {
+        format!(
+            "FC {}/{} · SS {}/{}",
+            self.full_combo, self.total, self.ss, self.total
+        )
+    }
+}
+
 /// Main game state enum (legacy - used for internal state tracking)
 #[derive(Debug, Clone, Default)]
 pub enum GameState {
@@ -91,6 +277,40 @@ pub struct GameCircle {
     pub max_radius: f32,
     pub hit: bool,
     pub missed: bool,
+    /// Object type; spinners are judged on their head for now
+    pub kind: GameCircleKind,
+    /// Whether this object starts a new combo
+    pub new_combo: bool,
+    /// Sound played when the object is hit
+    pub hitsound: Hitsound,
+}
+
+impl GameCircle {
+    /// Whether this is a slider let go before its end
+    pub fn slider_broken(&self) -> bool {
+        matches!(self.kind, GameCircleKind::Slider { broken: true, .. })
+    }
+}
+
+/// Object type of a gameplay circle
+#[derive(Debug, Clone, Default)]
+pub enum GameCircleKind {
+    #[default]
+    Circle,
+    /// Slider following `path` until `end_time`
+    Slider {
+        path: Vec<Vec2>,
+        end_time: f64,
+        repeats: u32,
+        /// Body ticks, ending with the slider end
+        ticks: Vec<f64>,
+        /// Ticks scored so far
+        ticks_done: usize,
+        /// Whether the hit keys were let go before the end
+        broken: bool,
+    },
+    /// Spinner lasting until `end_time`
+    Spinner { end_time: f64 },
 }
 
 /// Floating text for feedback
@@ -102,6 +322,8 @@ pub struct FloatingText {
     pub duration: f64,
     /// Text color
     pub color: (f32, f32, f32),
+    /// Font size multiplier
+    pub scale: f32,
 }
 
 /// Visualizing/gameplay state
@@ -110,7 +332,7 @@ pub struct VisualizingState {
     pub beats: Vec<f64>,
     pub start_time: Instant,
     pub circles: Vec<GameCircle>,
-    pub score: i32,
+    pub score: i64,
     pub floating_texts: Vec<FloatingText>,
     /// Current game configuration
     pub config: GameConfig,
@@ -130,10 +352,40 @@ pub struct VisualizingState {
     pub combo: u32,
     /// Max combo achieved
     pub max_combo: u32,
+    /// Judgment counts for the HUD accuracy display
+    pub hits: crate::analytics::HitStats,
     /// Lives remaining (for survival mode)
     pub lives: Option<u32>,
     /// Time remaining (for time attack mode)
     pub time_remaining: Option<f64>,
+    /// Health (0.0 - 1.0); the play fails when it runs out
+    pub hp: f32,
+    /// HP drain of the map played (0 - 10), scaling what a miss costs
+    pub hp_drain: f32,
+    /// Kiai sections of the map being played
+    pub kiai_sections: Vec<crate::beatmap::KiaiSection>,
+    /// Live accuracy readout, updated on each judgment
+    pub accuracy_gauge: crate::hud::AccuracyGauge,
+    /// Follow points of the map, empty when turned off in the theme
+    pub follow_points: crate::effects::FollowPoints,
+    /// Glows at recent hit positions
+    pub hit_lights: crate::effects::HitLights,
+    /// Recent hits' timing for the hit-error bar
+    pub hit_errors: crate::effects::HitErrors,
+    /// Where the cursor was last seen on the playfield; the Flashlight mod lights
+    /// around it
+    pub cursor: Vec2,
+    /// Star rating of the generated map
+    pub star_rating: Option<f32>,
+    /// Seed the map was generated from
+    pub seed: u64,
+    /// Judgment and combo events of the current frame, moved to the event bus after
+    /// the simulation step
+    pub bus_events: Vec<BusEvent>,
+    /// Hitsounds of the current frame's hits, played after the simulation step
+    pub hitsounds: Vec<Hitsound>,
+    /// Bots playing the map alongside the player, none outside a local room
+    pub room: Vec<RoomBot>,
 }
 
 impl VisualizingState {
@@ -150,11 +402,16 @@ impl VisualizingState {
         let game_settings = config.game_settings.clone();
 
         let active_session = if config.save_analytics {
-            Some(ActiveSession::new(
-                song_name.clone(),
-                practice_mode,
-                playback_speed,
-            ))
+            let mut session = ActiveSession::new(song_name.clone(), practice_mode, playback_speed);
+            session.unranked = game_settings.is_auto()
+                || game_settings.has_modifier(crate::gamemode::Modifier::NoFail);
+            session.signature = PlaySignature::new(&game_settings.modifiers, playback_speed);
+            session.score_multiplier = game_settings.score_multiplier();
+            // Autoplay always hits dead center on time, which says nothing about the player
+            if config.track_hit_precision && !game_settings.is_auto() {
+                session.precision = Some(PrecisionMap::default());
+            }
+            Some(session)
         } else {
             None
         };
@@ -162,7 +419,7 @@ impl VisualizingState {
         // Initialize lives and time based on game mode
         let lives = match game_settings.mode {
             crate::gamemode::GameMode::Survival { lives } => Some(lives),
-            crate::gamemode::GameMode::SuddenDeath => Some(1),
+            _ if game_settings.has_modifier(Modifier::SuddenDeath) => Some(1),
             _ => None,
         };
 
@@ -173,6 +430,12 @@ impl VisualizingState {
             _ => None,
         };
 
+        let follow_points = if config.theme.follow_points {
+            crate::effects::FollowPoints::generate(&circles)
+        } else {
+            crate::effects::FollowPoints::default()
+        };
+
         Self {
             beats,
             start_time: Instant::now(),
@@ -188,14 +451,51 @@ impl VisualizingState {
             song_name,
             combo: 0,
             max_combo: 0,
+            hits: crate::analytics::HitStats::default(),
             lives,
             time_remaining,
+            hp: 1.0,
+            hp_drain: crate::beatmap::BeatmapSettings::default().hp_drain,
+            kiai_sections: Vec::new(),
+            accuracy_gauge: crate::hud::AccuracyGauge::default(),
+            follow_points,
+            hit_lights: crate::effects::HitLights::default(),
+            hit_errors: crate::effects::HitErrors::default(),
+            cursor: Vec2::ZERO,
+            star_rating: None,
+            seed: 0,
+            bus_events: Vec::new(),
+            hitsounds: Vec::new(),
+            room: Vec::new(),
+        }
+    }
+
+    /// Center of the Flashlight mod's light, None without the mod
+    pub fn flashlight(&self) -> Option<Vec2> {
+        self.game_settings
+            .has_modifier(Modifier::Flash)
+            .then_some(self.cursor)
+    }
+
+    /// Kiai effect strength at a song time; always 0 with reduced motion
+    pub fn kiai_intensity(&self, time: f64) -> f32 {
+        if self.config.theme.reduced_motion {
+            0.0
+        } else {
+            crate::beatmap::kiai_intensity(time, &self.kiai_sections)
         }
     }
 
     /// Record a hit with timing
     pub fn record_hit(&mut self, points: i32, timing_ms: f32) {
-        self.score += points;
+        self.score += (points as f32 * self.game_settings.score_multiplier()) as i64;
+
+        match points {
+            300 => self.hits.perfect += 1,
+            100 => self.hits.good += 1,
+            50 => self.hits.okay += 1,
+            _ => self.hits.misses += 1,
+        }
 
         // Update combo
         if points > 0 {
@@ -204,7 +504,7 @@ impl VisualizingState {
                 self.max_combo = self.combo;
             }
         } else {
-            self.combo = 0;
+            self.break_combo();
         }
 
         // Record in analytics session
@@ -213,18 +513,181 @@ impl VisualizingState {
         }
     }
 
+    /// Score a slider tick or end passed while holding: adds to score and combo but
+    /// not to the judgment counts
+    pub fn record_slider_tick(&mut self, points: i32) {
+        self.score += (points as f32 * self.game_settings.score_multiplier()) as i64;
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+    }
+
+    /// Break the combo on a slider let go early; it isn't counted as a miss
+    pub fn record_slider_break(&mut self) {
+        self.break_combo();
+    }
+
+    /// Whether running out of health fails the play: not with no fail, the No Fail
+    /// modifier or autoplay
+    pub fn can_fail(&self) -> bool {
+        !self.no_fail
+            && !self.game_settings.has_modifier(Modifier::NoFail)
+            && !self.game_settings.is_auto()
+    }
+
+    /// Change health by `delta`. A play that can't fail keeps a sliver of it
+    pub fn change_hp(&mut self, delta: f32) {
+        let floor = if self.can_fail() {
+            0.0
+        } else {
+            NO_FAIL_HP_FLOOR
+        };
+        self.hp = (self.hp + delta).clamp(floor, 1.0);
+    }
+
+    /// Whether health has run out, failing the play
+    pub fn hp_depleted(&self) -> bool {
+        self.hp <= 0.0
+    }
+
+    /// Share of the map's objects judged so far (0.0 - 1.0)
+    pub fn progress(&self) -> f32 {
+        if self.circles.is_empty() {
+            return 1.0;
+        }
+        let judged = self.circles.iter().filter(|c| c.hit || c.missed).count();
+        judged as f32 / self.circles.len() as f32
+    }
+
     /// Record a miss
     pub fn record_miss(&mut self) {
-        self.combo = 0;
+        self.break_combo();
+        self.hits.misses += 1;
 
         if let Some(ref mut session) = self.active_session {
             session.record_miss();
         }
     }
 
-    /// Finish the session and return analytics data
-    pub fn finish_session(self) -> Option<crate::analytics::GameSession> {
-        self.active_session.map(|s| s.finish())
+    /// Record where a hit landed, in radii from the circle center, and how many ms
+    /// late (negative when early)
+    pub fn record_precision(&mut self, offset: Vec2, timing_ms: f32) {
+        if let Some(precision) = self
+            .active_session
+            .as_mut()
+            .and_then(|session| session.precision.as_mut())
+        {
+            precision.record(offset, timing_ms);
+        }
+    }
+
+    /// Hit positions and timing of the play so far, None when not tracked
+    pub fn precision(&self) -> Option<PrecisionMap> {
+        self.active_session
+            .as_ref()
+            .and_then(|session| session.precision.clone())
+    }
+
+    fn break_combo(&mut self) {
+        let combo = std::mem::take(&mut self.combo);
+        if combo > 0 {
+            self.bus_events.push(BusEvent::ComboBroken { combo });
+        }
+    }
+
+    /// Current HUD values at a song time
+    pub fn hud_values(&self, now: f64) -> crate::hud::HudValues {
+        crate::hud::HudValues {
+            score: self.score,
+            combo: self.combo,
+            max_combo: self.max_combo,
+            accuracy: self.accuracy_gauge.displayed(now),
+            lives: self.lives,
+            health: Some(self.hp),
+            grade: self.accuracy_gauge.grade(),
+            hit_error_preview: false,
+        }
+    }
+
+    /// Log a judgment for the results scrubber and move the accuracy readout
+    pub fn log_judgment(&mut self, time: f64, position: Vec2, kind: crate::analytics::JudgmentKind) {
+        // Same accuracy and grade functions as the results screen
+        let (accuracy, grade) = match &self.active_session {
+            Some(session) => (
+                session.current_accuracy(),
+                session.hits.grade(&GradeRules::SCORE_V1),
+            ),
+            None => (self.hits.accuracy(), self.hits.grade(&GradeRules::SCORE_V1)),
+        };
+        if self.hits.total() > 0 {
+            self.accuracy_gauge.set(accuracy, grade, time);
+        }
+        self.bus_events.push(BusEvent::JudgmentMade { kind, time });
+
+        if let Some(ref mut session) = self.active_session {
+            session.judgment_log.push(crate::analytics::JudgmentEntry {
+                time,
+                position,
+                kind,
+            });
+        }
+    }
+
+    /// Copy of the judgment log for the results screen. The session keeps its own for
+    /// the details file written when it's recorded
+    pub fn judgment_log(&self) -> Option<crate::analytics::JudgmentLog> {
+        self.active_session
+            .as_ref()
+            .map(|session| session.judgment_log.clone())
+    }
+
+    /// Song time to jump to if the intro can be skipped at `now`: a lead-in before the
+    /// first circle appears, offered only while no circle has spawned yet and the wait
+    /// is longer than `INTRO_SKIP_THRESHOLD`
+    pub fn intro_skip_target(&self, now: f64) -> Option<f64> {
+        let first_spawn = self
+            .circles
+            .iter()
+            .map(|circle| circle.spawn_time)
+            .min_by(|a, b| a.total_cmp(b))?;
+        if first_spawn <= now || first_spawn - now <= INTRO_SKIP_THRESHOLD {
+            return None;
+        }
+        Some(first_spawn - INTRO_SKIP_LEAD_IN)
+    }
+
+    /// Record a skipped intro on the session
+    pub fn record_intro_skip(&mut self, seconds: f64) {
+        if let Some(ref mut session) = self.active_session {
+            session.skipped_intro_seconds += seconds;
+        }
+    }
+
+    /// Bring back the circles from `loop_start` on after a practice loop wraps, so the
+    /// section can be played again. Circles already on screen at the loop start count
+    pub fn rewind_loop(&mut self, loop_start: f64) {
+        for circle in self
+            .circles
+            .iter_mut()
+            .filter(|circle| circle.hit_time >= loop_start)
+        {
+            circle.hit = false;
+            circle.missed = false;
+        }
+        self.floating_texts.clear();
+        self.hit_errors.clear();
+    }
+
+    /// Mark the run as played to the end without failing
+    pub fn mark_completed(&mut self) {
+        if let Some(ref mut session) = self.active_session {
+            session.completed = true;
+        }
+    }
+
+    /// Finish the session and return analytics data. Leaves the play as it is, so
+    /// the end state can still be read from it
+    pub fn finish_session(&self) -> Option<crate::analytics::GameSession> {
+        self.active_session.clone().map(|s| s.finish())
     }
 }
 
@@ -232,7 +695,7 @@ impl VisualizingState {
 #[derive(Debug, Clone)]
 pub struct EndState {
     /// Final score
-    pub score: i32,
+    pub score: i64,
     /// Max combo
     pub max_combo: u32,
     /// Hit statistics
@@ -252,13 +715,50 @@ pub struct EndState {
     /// New best score
     pub new_best: bool,
     /// Previous best score
-    pub previous_best: i32,
+    pub previous_best: i64,
     /// Game mode played
     pub game_mode: crate::gamemode::GameMode,
     /// Difficulty level
-    pub difficulty: Difficulty,
+    pub difficulty: crate::gamemode::Difficulty,
     /// Active modifiers
     pub modifiers: Vec<Modifier>,
+    /// Score multiplier of the difficulty and modifiers
+    pub score_multiplier: f32,
+    /// Per-judgment log for the scrubber (absent for sessions opened from analytics)
+    pub judgment_log: Option<crate::analytics::JudgmentLog>,
+    /// Played in incognito mode, nothing was recorded
+    pub incognito: bool,
+    /// Star rating of the map played, None for sessions opened from analytics
+    pub star_rating: Option<f32>,
+    /// Song time the play ended at (seconds), where the results music picks up
+    pub ended_at: f64,
+    /// Seed the map was generated from, None for sessions opened from analytics
+    pub seed: Option<u64>,
+    /// Inputs of the play, for exporting
+    pub replay: Option<crate::replay::Replay>,
+    /// Hit positions and timing of the play, None when not tracked
+    pub precision: Option<PrecisionMap>,
+    /// Ranking of the player and the local room's bots, empty without bots
+    pub room: Vec<Standing>,
+}
+
+/// Results screen scrubber position
+#[derive(Resource, Default)]
+pub struct ResultsScrubber {
+    /// Song time under the cursor (seconds)
+    pub cursor_time: f64,
+    /// Whether the cursor is being dragged with the mouse
+    pub dragging: bool,
+}
+
+/// Shortest practice loop the timeline allows (seconds)
+pub const MIN_LOOP_LENGTH: f64 = 1.0;
+
+/// End of the practice loop being dragged on the practice menu timeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopMarker {
+    Start,
+    End,
 }
 
 /// Practice menu state
@@ -268,16 +768,28 @@ pub struct PracticeMenuState {
     pub selected_song: Option<String>,
     /// Playback speed
     pub playback_speed: f32,
+    /// Time-stretch instead of shifting the pitch
+    pub preserve_pitch: bool,
     /// No-fail mode
     pub no_fail: bool,
     /// Autoplay mode
     pub autoplay: bool,
     /// Enable hit sounds
     pub hit_sounds: bool,
+    /// Click track on the song's beats
+    pub metronome: MetronomeMode,
     /// Loop start time
     pub loop_start: Option<f64>,
     /// Loop end time
     pub loop_end: Option<f64>,
+    /// Practice hints the song's beatmaps suggest
+    pub hints: Vec<PracticeHint>,
+    /// Hint whose loop and speed are applied
+    pub hint: Option<usize>,
+    /// Length of the selected song (seconds), for the loop timeline
+    pub song_length: Option<f64>,
+    /// Loop marker held with the mouse
+    pub dragging: Option<LoopMarker>,
     /// Selected menu item
     pub selected_index: usize,
 }
@@ -294,15 +806,56 @@ impl PracticeMenuState {
         Self {
             selected_song: None,
             playback_speed: 1.0,
+            preserve_pitch: false,
             no_fail: false,
             autoplay: false,
             hit_sounds: true,
+            metronome: MetronomeMode::Off,
             loop_start: None,
             loop_end: None,
+            hints: Vec::new(),
+            hint: None,
+            song_length: None,
+            dragging: None,
             selected_index: 0,
         }
     }
 
+    /// Practice menu pre-filled from practice settings
+    pub fn from_config(song: Option<String>, practice: &PracticeConfig) -> Self {
+        Self {
+            selected_song: song,
+            playback_speed: practice.playback_speed,
+            preserve_pitch: practice.preserve_pitch,
+            no_fail: practice.no_fail,
+            autoplay: practice.autoplay,
+            hit_sounds: practice.hit_sounds,
+            metronome: practice.metronome,
+            loop_start: practice.loop_start,
+            loop_end: practice.loop_end,
+            hints: Vec::new(),
+            hint: None,
+            song_length: None,
+            dragging: None,
+            selected_index: 0,
+        }
+    }
+
+    /// Practice settings chosen in the menu
+    pub fn to_config(&self, remember_per_song: bool) -> PracticeConfig {
+        PracticeConfig {
+            playback_speed: self.playback_speed,
+            preserve_pitch: self.preserve_pitch,
+            no_fail: self.no_fail,
+            autoplay: self.autoplay,
+            hit_sounds: self.hit_sounds,
+            metronome: self.metronome,
+            loop_start: self.loop_start,
+            loop_end: self.loop_end,
+            remember_per_song,
+        }
+    }
+
     /// Get playback speed options
     pub fn speed_options() -> Vec<(f32, &'static str)> {
         vec![
@@ -337,6 +890,61 @@ impl PracticeMenuState {
         let prev_idx = current_idx.saturating_sub(1);
         self.playback_speed = options[prev_idx].0;
     }
+
+    /// Move a loop marker to `time`, pushing the other one along to keep the loop at
+    /// least `MIN_LOOP_LENGTH` long and inside the song. A loop set by hand replaces
+    /// the applied hint
+    pub fn move_loop_marker(&mut self, marker: LoopMarker, time: f64) {
+        let length = self.song_length.unwrap_or(f64::MAX).max(MIN_LOOP_LENGTH);
+        match marker {
+            LoopMarker::Start => {
+                let start = time.clamp(0.0, length - MIN_LOOP_LENGTH);
+                let end = self.loop_end.unwrap_or(length);
+                self.loop_start = Some(start);
+                self.loop_end = Some(end.clamp(start + MIN_LOOP_LENGTH, length));
+            }
+            LoopMarker::End => {
+                let end = time.clamp(MIN_LOOP_LENGTH, length);
+                let start = self.loop_start.unwrap_or(0.0);
+                self.loop_end = Some(end);
+                self.loop_start = Some(start.clamp(0.0, end - MIN_LOOP_LENGTH));
+            }
+        }
+        self.hint = None;
+    }
+
+    /// Clear the loop, and the hint that set it
+    pub fn clear_loop(&mut self) {
+        self.loop_start = None;
+        self.loop_end = None;
+        self.hint = None;
+    }
+
+    /// Step through no hint and each hint. Picking a hint loops its section at its
+    /// rate; going back to no hint clears the loop
+    pub fn cycle_hint(&mut self, direction: i32) {
+        if self.hints.is_empty() {
+            return;
+        }
+        // Position 0 is no hint, hint i is position i + 1
+        let count = self.hints.len() as i32 + 1;
+        let current = self.hint.map_or(0, |index| index as i32 + 1);
+        self.hint = match (current + direction).rem_euclid(count) {
+            0 => None,
+            position => Some(position as usize - 1),
+        };
+        match self.hint.and_then(|index| self.hints.get(index)) {
+            Some(hint) => {
+                self.playback_speed = hint.rate;
+                self.loop_start = Some(hint.start);
+                self.loop_end = Some(hint.end);
+            }
+            None => {
+                self.loop_start = None;
+                self.loop_end = None;
+            }
+        }
+    }
 }
 
 /// Resource to hold the current game state
@@ -345,12 +953,40 @@ pub struct GameStateResource {
     pub state: GameState,
     pub selected_song: String,
     pub songs: Vec<String>,
+    /// Practice settings for the next play (None for a normal play)
+    pub practice: Option<PracticeConfig>,
+    /// Whether the current play is in a multiplayer room (disables quick retry)
+    pub multiplayer: bool,
+    /// Layout seed for the next play in place of the song's own, used up when it starts
+    pub seed_override: Option<u64>,
+    /// Whether songs picked in song selection start a two-player local versus match
+    pub local_versus: bool,
+    /// Bot playing player two of a local versus match (None for a second player)
+    pub versus_bot: Option<BotProfile>,
+    /// Bots joining solo plays started from song selection, in the order added
+    pub room_bots: Vec<BotProfile>,
+    /// Whose turn it is to pick the next local versus song, passed on after each
+    /// match that doesn't end in a rematch
+    pub versus_picks: Option<PickerRotation>,
+}
+
+impl GameStateResource {
+    /// Playback speed of the next play, the practice speed times Double Time's or Half
+    /// Time's, and whether it keeps the pitch. Outside practice a speed modifier does
+    pub fn playback(&self, game_settings: &GameSettings) -> (f32, bool) {
+        let (speed, preserve_pitch) = self.practice.as_ref().map_or((1.0, true), |practice| {
+            (practice.playback_speed, practice.preserve_pitch)
+        });
+        (speed * game_settings.playback_speed(), preserve_pitch)
+    }
 }
 
 /// Resource to hold audio sink
 #[derive(Resource)]
 pub struct GameAudioSink {
     pub sink: rodio::Sink,
+    /// Sound effects, kept apart so seeking or stopping the song doesn't cut them off
+    pub effects: rodio::Sink,
 }
 
 /// Resource to hold timing information
@@ -373,16 +1009,28 @@ impl Default for GameTime {
 #[derive(Resource)]
 pub struct LoadingData {
     pub beats: Option<Vec<f64>>,
+    /// Energy curve used by the generator, see `audio::SongAnalysis`
+    pub energy: Vec<f32>,
     pub start_time: Instant,
     pub song_path: String,
+    /// Beat detection mode, from the audio settings unless changed on the loading screen
+    pub quality: DetectionQuality,
+    /// The screen says analysis has started; it runs on the next frame
+    pub analyzing: bool,
+    /// Seed the map will be generated from
+    pub seed: u64,
 }
 
 impl Default for LoadingData {
     fn default() -> Self {
         Self {
             beats: None,
+            energy: Vec::new(),
             start_time: Instant::now(),
             song_path: String::new(),
+            quality: DetectionQuality::default(),
+            analyzing: false,
+            seed: 0,
         }
     }
 }
@@ -391,14 +1039,68 @@ impl Default for LoadingData {
 #[derive(Resource)]
 pub struct ReadyToPlayData {
     pub beats: Vec<f64>,
+    /// Energy curve used by the generator
+    pub energy: Vec<f32>,
     pub ready_time: Instant,
+    /// Attempt number within this sitting (1 for the first play)
+    pub attempt: u32,
+    /// Seed the map is generated from; quick retries keep it
+    pub seed: u64,
 }
 
 /// Resource for visualizing data
 #[derive(Resource)]
 pub struct VisualizingData {
     pub state: VisualizingState,
-    pub start_time: Instant,
+    /// Fixed-step judgment clock and input log
+    pub clock: crate::simulation::JudgmentClock,
+    /// Song time, kept in step with the audio output
+    pub song_clock: crate::simulation::SongClock,
+    /// Whether the debug overlay (F3) is shown
+    pub show_debug: bool,
+    /// Upper bounds on the input-to-judgment latency of recent presses, for the debug overlay
+    pub latency: crate::input_latency::LatencyStats,
+    /// Analysis view (V) while watching; None when off
+    pub analysis: Option<crate::analysis_view::AnalysisView>,
+    /// Energy curve the map was generated from, kept for quick retry
+    pub energy: Vec<f32>,
+    /// Attempt number within this sitting (1 for the first play)
+    pub attempt: u32,
+    /// Times a practice loop wrapped back to its start
+    pub loops: u32,
+    /// Highlighted pause menu option while paused; None while playing
+    pub pause_menu: Option<PauseOption>,
+}
+
+/// Options of the pause menu, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseOption {
+    Resume,
+    Restart,
+    Quit,
+}
+
+impl PauseOption {
+    pub const ALL: [PauseOption; 3] =
+        [PauseOption::Resume, PauseOption::Restart, PauseOption::Quit];
+
+    /// Get display name for the option
+    pub fn label(&self) -> &'static str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::Restart => "Restart",
+            PauseOption::Quit => "Quit to Menu",
+        }
+    }
+
+    /// The option `step` rows down, wrapping at the ends
+    pub fn moved(self, step: i32) -> PauseOption {
+        let index = Self::ALL
+            .iter()
+            .position(|option| *option == self)
+            .unwrap_or(0) as i32;
+        Self::ALL[(index + step).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
 }
 
 /// Resource for end data
@@ -406,3 +1108,56 @@ pub struct VisualizingData {
 pub struct EndData {
     pub state: EndState,
 }
+
+/// A play that ran out of lives or health, for the failed screen
+#[derive(Resource)]
+pub struct FailedData {
+    pub song_name: String,
+    /// Song time of the fail (seconds), where the music picks up
+    pub ended_at: f64,
+    /// Share of the map's objects reached (0.0 - 1.0)
+    pub progress: f32,
+    pub score: i64,
+    /// Session of the failed play, recorded if the player quits; a retry drops it
+    /// like any restarted attempt
+    pub session: Option<ActiveSession>,
+    /// Countdown of another attempt on the same map; None in multiplayer, where the
+    /// room decides what is played
+    pub retry: Option<ReadyToPlayData>,
+    /// Highlighted option
+    pub selected: FailedOption,
+}
+
+impl FailedData {
+    /// Options on offer, top to bottom
+    pub fn options(&self) -> &'static [FailedOption] {
+        if self.retry.is_some() {
+            &FailedOption::ALL
+        } else {
+            &[FailedOption::Quit]
+        }
+    }
+}
+
+/// Options of the failed screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailedOption {
+    Retry,
+    Quit,
+}
+
+impl FailedOption {
+    pub const ALL: [FailedOption; 2] = [FailedOption::Retry, FailedOption::Quit];
+
+    /// Get display name for the option
+    pub fn label(&self) -> &'static str {
+        match self {
+            FailedOption::Retry => "Retry",
+            FailedOption::Quit => "Quit to Menu",
+        }
+    }
+}
+
+/// Result of the last action on the results screen, such as a replay export
+#[derive(Resource, Default)]
+pub struct ResultsNotice(pub Option<String>);
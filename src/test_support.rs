@@ -0,0 +1,17 @@
+// src/test_support.rs
+
+//! Helpers shared by the unit tests
+
+use std::fs;
+use std::path::PathBuf;
+
+/// An empty folder under the system temp dir that belongs to one test
+///
+/// The process id keeps parallel `cargo test` runs apart, and anything a
+/// previous run left behind is removed first.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("yum-osu-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,855 @@
+// src/text_input.rs
+
+use crate::constants::{NEON_CYAN, NEON_PINK, NEON_PURPLE};
+use crate::ui::UiElement;
+use bevy::ecs::system::EntityCommands;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::ops::Range;
+
+/// Seconds the cursor stays shown, then hidden
+pub const CURSOR_BLINK: f32 = 0.5;
+
+/// Character drawn for each character of a masked field
+pub const MASK_CHAR: char = '*';
+
+/// Height of a text box drawn by `draw_text_box`
+pub const TEXT_BOX_HEIGHT: f32 = 32.0;
+
+/// An editable line of text: the string, a cursor and an optional selection, all in
+/// characters. Every text field in the game edits through one of these so cursor
+/// movement, selection and the clipboard behave the same everywhere
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextInput {
+    text: String,
+    cursor: usize,
+    /// Other end of the selection from the cursor, if one is being made
+    anchor: Option<usize>,
+    /// Draw every character as `MASK_CHAR` and refuse to copy (passwords)
+    pub masked: bool,
+    /// Most characters the field holds
+    pub max_len: Option<usize>,
+    /// Only accept digits, '.', '-' and ':' (numbers and m:ss times)
+    pub numeric: bool,
+}
+
+impl TextInput {
+    /// A field holding `text` with the cursor at the end
+    pub fn new(text: impl Into<String>) -> Self {
+        let mut input = Self::default();
+        input.set_text(text);
+        input
+    }
+
+    /// Mask the field, for passwords
+    pub fn masked(mut self) -> Self {
+        self.masked = true;
+        self
+    }
+
+    /// Limit the field to `max_len` characters, cutting what is already there
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        let text = std::mem::take(&mut self.text);
+        self.set_text(text);
+        self
+    }
+
+    /// Only accept the characters of a number or a time
+    pub fn numeric(mut self) -> Self {
+        self.numeric = true;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Cursor position in characters
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replace the text, moving the cursor to the end and dropping the selection
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        let mut text = text.into();
+        if let Some(max_len) = self.max_len {
+            if let Some((index, _)) = text.char_indices().nth(max_len) {
+                text.truncate(index);
+            }
+        }
+        self.text = text;
+        self.cursor = self.len();
+        self.anchor = None;
+    }
+
+    /// Length in characters
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Byte offset of a character position
+    fn byte_index(&self, position: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(position)
+            .map_or(self.text.len(), |(index, _)| index)
+    }
+
+    /// Selected character range, if any characters are selected
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
+        Some(anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    /// The selected text; masked fields never give theirs out
+    pub fn selected_text(&self) -> Option<String> {
+        if self.masked {
+            return None;
+        }
+        let range = self.selection()?;
+        Some(self.text[self.byte_index(range.start)..self.byte_index(range.end)].to_string())
+    }
+
+    /// Text as drawn: the mask for masked fields
+    pub fn display_text(&self) -> String {
+        if self.masked {
+            std::iter::repeat_n(MASK_CHAR, self.len()).collect()
+        } else {
+            self.text.clone()
+        }
+    }
+
+    /// Parse a numeric field
+    pub fn number(&self) -> Result<f64, String> {
+        let text = self.text.trim();
+        text.parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("\"{}\" is not a number", text))
+    }
+
+    /// Whether the field takes a character
+    fn accepts(&self, c: char) -> bool {
+        if c.is_control() {
+            return false;
+        }
+        !self.numeric || c.is_ascii_digit() || matches!(c, '.' | '-' | ':')
+    }
+
+    /// Remove the selection, leaving the cursor where it started. Returns whether
+    /// anything was selected
+    fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        let (start, end) = (self.byte_index(range.start), self.byte_index(range.end));
+        self.text.replace_range(start..end, "");
+        self.cursor = range.start;
+        self.anchor = None;
+        true
+    }
+
+    /// Type or paste text over the selection. Characters the field doesn't take are
+    /// dropped and the rest is cut to fit `max_len`. Returns whether the text changed
+    pub fn insert(&mut self, text: &str) -> bool {
+        let mut typed: Vec<char> = text.chars().filter(|c| self.accepts(*c)).collect();
+        if typed.is_empty() {
+            return false;
+        }
+        let replaced = self.delete_selection();
+        if let Some(max_len) = self.max_len {
+            typed.truncate(max_len.saturating_sub(self.len()));
+        }
+        if typed.is_empty() {
+            return replaced;
+        }
+        let index = self.byte_index(self.cursor);
+        self.text
+            .insert_str(index, &typed.iter().collect::<String>());
+        self.cursor += typed.len();
+        true
+    }
+
+    /// Delete the selection, or the character (or word) before the cursor
+    pub fn backspace(&mut self, word: bool) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        if self.cursor == 0 {
+            return false;
+        }
+        let start = if word {
+            self.word_left(self.cursor)
+        } else {
+            self.cursor - 1
+        };
+        self.remove(start..self.cursor);
+        true
+    }
+
+    /// Delete the selection, or the character (or word) after the cursor
+    pub fn delete(&mut self, word: bool) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        if self.cursor == self.len() {
+            return false;
+        }
+        let end = if word {
+            self.word_right(self.cursor)
+        } else {
+            self.cursor + 1
+        };
+        self.remove(self.cursor..end);
+        true
+    }
+
+    /// Remove a character range and put the cursor at its start
+    fn remove(&mut self, range: Range<usize>) {
+        let (start, end) = (self.byte_index(range.start), self.byte_index(range.end));
+        self.text.replace_range(start..end, "");
+        self.cursor = range.start;
+    }
+
+    /// Move the cursor, extending the selection when `select` is held and dropping
+    /// it otherwise
+    fn move_to(&mut self, position: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = position.min(self.len());
+    }
+
+    /// Cursor left a character or word. Without `select`, a selection collapses to
+    /// its start
+    pub fn move_left(&mut self, word: bool, select: bool) {
+        let position = match self.selection() {
+            Some(range) if !select => range.start,
+            _ if word => self.word_left(self.cursor),
+            _ => self.cursor.saturating_sub(1),
+        };
+        self.move_to(position, select);
+    }
+
+    /// Cursor right a character or word. Without `select`, a selection collapses to
+    /// its end
+    pub fn move_right(&mut self, word: bool, select: bool) {
+        let position = match self.selection() {
+            Some(range) if !select => range.end,
+            _ if word => self.word_right(self.cursor),
+            _ => self.cursor + 1,
+        };
+        self.move_to(position, select);
+    }
+
+    pub fn home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        self.move_to(self.len(), select);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.len();
+    }
+
+    /// Start of the word before `position`, skipping the gap before it. Masked
+    /// fields treat the whole text as one word so jumps don't reveal its shape
+    fn word_left(&self, position: usize) -> usize {
+        if self.masked {
+            return 0;
+        }
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut i = position;
+        while i > 0 && !chars[i - 1].is_alphanumeric() {
+            i -= 1;
+        }
+        while i > 0 && chars[i - 1].is_alphanumeric() {
+            i -= 1;
+        }
+        i
+    }
+
+    /// End of the word after `position`, skipping the gap before it
+    fn word_right(&self, position: usize) -> usize {
+        let chars: Vec<char> = self.text.chars().collect();
+        if self.masked {
+            return chars.len();
+        }
+        let mut i = position;
+        while i < chars.len() && !chars[i].is_alphanumeric() {
+            i += 1;
+        }
+        while i < chars.len() && chars[i].is_alphanumeric() {
+            i += 1;
+        }
+        i
+    }
+
+    /// Apply one key press. Ctrl (or Cmd) jumps and deletes by word and runs the
+    /// clipboard shortcuts, Shift selects. Enter, Tab and Escape are left to the
+    /// field's owner. Returns whether the text, cursor or selection changed
+    pub fn handle_key(
+        &mut self,
+        key: &Key,
+        modifiers: Modifiers,
+        clipboard: &mut Clipboard,
+    ) -> bool {
+        let before = (self.text.len(), self.cursor, self.anchor);
+        let (word, select) = (modifiers.ctrl, modifiers.shift);
+        let edited = match key {
+            Key::Character(text) if modifiers.ctrl => match text.to_lowercase().as_str() {
+                "a" => {
+                    self.select_all();
+                    false
+                }
+                "c" => {
+                    if let Some(selected) = self.selected_text() {
+                        clipboard.set(selected);
+                    }
+                    false
+                }
+                "x" => match self.selected_text() {
+                    Some(selected) => {
+                        clipboard.set(selected);
+                        self.delete_selection()
+                    }
+                    None => false,
+                },
+                "v" => {
+                    let pasted = clipboard.get();
+                    // Only the first line of a multi-line paste
+                    self.insert(pasted.lines().next().unwrap_or_default())
+                }
+                _ => false,
+            },
+            Key::Character(text) => self.insert(text),
+            Key::Space => self.insert(" "),
+            Key::Backspace => self.backspace(word),
+            Key::Delete => self.delete(word),
+            Key::ArrowLeft => {
+                self.move_left(word, select);
+                false
+            }
+            Key::ArrowRight => {
+                self.move_right(word, select);
+                false
+            }
+            Key::Home => {
+                self.home(select);
+                false
+            }
+            Key::End => {
+                self.end(select);
+                false
+            }
+            _ => false,
+        };
+        edited || before != (self.text.len(), self.cursor, self.anchor)
+    }
+
+    /// Apply a frame's key presses in order; see `handle_key`
+    pub fn handle_keys(
+        &mut self,
+        keys: &[Key],
+        modifiers: Modifiers,
+        clipboard: &mut Clipboard,
+    ) -> bool {
+        let mut changed = false;
+        for key in keys {
+            changed |= self.handle_key(key, modifiers, clipboard);
+        }
+        changed
+    }
+}
+
+/// Modifier keys held while typing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    /// Ctrl, or Cmd on macOS
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Modifiers {
+    /// Modifiers held this frame
+    pub fn from_keyboard(keyboard: &ButtonInput<KeyCode>) -> Self {
+        Self {
+            ctrl: keyboard.any_pressed([
+                KeyCode::ControlLeft,
+                KeyCode::ControlRight,
+                KeyCode::SuperLeft,
+                KeyCode::SuperRight,
+            ]),
+            shift: keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+        }
+    }
+}
+
+/// Drain a frame's key presses. Text field systems call this every frame, focused or
+/// not, so keys pressed before a field opens don't land in it
+pub fn pressed_keys(key_events: &mut EventReader<KeyboardInput>) -> Vec<Key> {
+    key_events
+        .read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .map(|event| event.logical_key.clone())
+        .collect()
+}
+
+/// The system clipboard, or a clipboard inside the game when the system's can't be
+/// opened. Held as a non-send resource since some platforms tie it to the main thread
+pub struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    local: String,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        let system = match arboard::Clipboard::new() {
+            Ok(clipboard) => Some(clipboard),
+            Err(e) => {
+                warn!(
+                    "System clipboard unavailable, copy and paste stay in game: {}",
+                    e
+                );
+                None
+            }
+        };
+        Self {
+            system,
+            local: String::new(),
+        }
+    }
+}
+
+impl Clipboard {
+    pub fn get(&mut self) -> String {
+        self.system
+            .as_mut()
+            .and_then(|clipboard| clipboard.get_text().ok())
+            .unwrap_or_else(|| self.local.clone())
+    }
+
+    pub fn set(&mut self, text: String) {
+        if let Some(clipboard) = self.system.as_mut() {
+            if let Err(e) = clipboard.set_text(text.clone()) {
+                error!("Failed to copy to the clipboard: {}", e);
+            }
+        }
+        self.local = text;
+    }
+}
+
+/// Text fields that can take the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusId {
+    EditorProperty,
+    EditorMetadata,
+    EditorPracticeHint,
+    EditorComment,
+    DeleteConfirmation,
+    LocateAudio,
+    SettingsSearch,
+    SongSearch,
+    ProfileName,
+    AccountForm,
+    FriendName,
+    Chat,
+}
+
+/// The text field that has the keyboard; at most one does. Shortcut handlers skip
+/// their keys while a field is focused so typing never triggers them
+#[derive(Resource, Debug, Default)]
+pub struct TextFocus(Option<FocusId>);
+
+impl TextFocus {
+    pub fn focused(&self) -> Option<FocusId> {
+        self.0
+    }
+
+    /// Whether any field has the keyboard
+    pub fn any(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Give a field the keyboard, or take it back. Losing focus only clears it if
+    /// the field had it, so another field opening in the same frame keeps it
+    pub fn set(&mut self, id: FocusId, focused: bool) {
+        if focused {
+            self.0 = Some(id);
+        } else if self.0 == Some(id) {
+            self.0 = None;
+        }
+    }
+}
+
+/// Drop focus on every screen change; the next screen's fields claim it again
+pub fn clear_text_focus(mut focus: ResMut<TextFocus>) {
+    focus.0 = None;
+}
+
+/// Blinking cursor of a drawn field
+#[derive(Component)]
+pub struct TextCursor;
+
+/// Blink every drawn cursor on the same clock
+pub fn blink_text_cursors(time: Res<Time>, mut cursors: Query<&mut TextColor, With<TextCursor>>) {
+    let shown = ((time.elapsed_secs() / CURSOR_BLINK) as u32).is_multiple_of(2);
+    let alpha = if shown { 1.0 } else { 0.0 };
+    for mut color in &mut cursors {
+        if color.0.alpha() != alpha {
+            color.0.set_alpha(alpha);
+        }
+    }
+}
+
+/// Add a field's text to a Text2d as spans: the text before the cursor, the selection
+/// in cyan, the cursor while focused and the rest. The Text2d's own text comes first,
+/// so it can hold a label
+pub fn add_input_spans(
+    text: &mut EntityCommands,
+    input: &TextInput,
+    focused: bool,
+    font: &TextFont,
+    color: Color,
+) {
+    let shown: Vec<char> = input.display_text().chars().collect();
+    let (start, end) = input
+        .selection()
+        .map_or((input.cursor, input.cursor), |range| {
+            (range.start, range.end)
+        });
+    let slice = |range: Range<usize>| shown[range].iter().collect::<String>();
+    let cursor_first = input.cursor == start;
+
+    text.with_children(|spans| {
+        spans.spawn((
+            TextSpan::new(slice(0..start)),
+            font.clone(),
+            TextColor(color),
+            UiElement,
+        ));
+        if focused && cursor_first {
+            spans.spawn((
+                TextSpan::new("|"),
+                font.clone(),
+                TextColor(NEON_PINK),
+                TextCursor,
+                UiElement,
+            ));
+        }
+        spans.spawn((
+            TextSpan::new(slice(start..end)),
+            font.clone(),
+            TextColor(NEON_CYAN),
+            UiElement,
+        ));
+        if focused && !cursor_first {
+            spans.spawn((
+                TextSpan::new("|"),
+                font.clone(),
+                TextColor(NEON_PINK),
+                TextCursor,
+                UiElement,
+            ));
+        }
+        spans.spawn((
+            TextSpan::new(slice(end..shown.len())),
+            font.clone(),
+            TextColor(color),
+            UiElement,
+        ));
+    });
+}
+
+/// Draw a text box: a dark panel with a neon border, pink while focused, holding the
+/// field's text. Returns the panel so callers can tag it
+pub fn draw_text_box<'a>(
+    commands: &'a mut Commands,
+    input: &TextInput,
+    center: Vec2,
+    width: f32,
+    focused: bool,
+    font: Handle<Font>,
+) -> EntityCommands<'a> {
+    let border = if focused { NEON_PINK } else { NEON_PURPLE };
+    let font = TextFont {
+        font,
+        font_size: 16.0,
+        ..default()
+    };
+    let mut panel = commands.spawn((
+        Sprite {
+            color: border,
+            custom_size: Some(Vec2::new(width, TEXT_BOX_HEIGHT)),
+            ..default()
+        },
+        Transform::from_xyz(center.x, center.y, 1.0),
+        UiElement,
+    ));
+    panel.with_children(|parent| {
+        parent.spawn((
+            Sprite {
+                color: Color::srgba(0.05, 0.05, 0.1, 0.95),
+                custom_size: Some(Vec2::new(width - 4.0, TEXT_BOX_HEIGHT - 4.0)),
+                ..default()
+            },
+            Transform::from_xyz(0.0, 0.0, 0.01),
+            UiElement,
+        ));
+        let mut text = parent.spawn((
+            Text2d::new(""),
+            font.clone(),
+            TextColor(Color::WHITE),
+            Transform::from_xyz(0.0, 0.0, 0.02),
+            UiElement,
+        ));
+        add_input_spans(&mut text, input, focused, &font, Color::WHITE);
+    });
+    panel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clipboard that never touches the system's
+    fn clipboard() -> Clipboard {
+        Clipboard {
+            system: None,
+            local: String::new(),
+        }
+    }
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        shift: false,
+    };
+    const SHIFT: Modifiers = Modifiers {
+        ctrl: false,
+        shift: true,
+    };
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        ctrl: true,
+        shift: true,
+    };
+
+    fn press(input: &mut TextInput, key: Key, modifiers: Modifiers) -> bool {
+        input.handle_key(&key, modifiers, &mut clipboard())
+    }
+
+    fn char_key(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[test]
+    fn typing_inserts_at_the_cursor() {
+        let mut input = TextInput::new("hllo");
+        assert_eq!(input.cursor(), 4);
+        input.home(false);
+        input.move_right(false, false);
+        assert!(press(&mut input, char_key("e"), Modifiers::default()));
+        assert_eq!(input.text(), "hello");
+        assert_eq!(input.cursor(), 2);
+        input.end(false);
+        assert!(press(&mut input, Key::Space, Modifiers::default()));
+        input.insert("wörld");
+        assert_eq!(input.text(), "hello wörld");
+        assert_eq!(input.cursor(), 11);
+        // Control characters never land in the text
+        assert!(!input.insert("\u{8}\n"));
+        assert_eq!(input.text(), "hello wörld");
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_characters_or_words() {
+        let mut input = TextInput::new("one two  three");
+        assert!(input.backspace(false));
+        assert_eq!(input.text(), "one two  thre");
+        assert!(input.backspace(true));
+        assert_eq!(input.text(), "one two  ");
+        assert!(input.backspace(true));
+        assert_eq!(input.text(), "one ");
+
+        input.home(false);
+        assert!(!input.backspace(false));
+        assert!(input.delete(true));
+        assert_eq!(input.text(), " ");
+        assert!(input.delete(false));
+        assert!(!input.delete(false));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn ctrl_arrows_jump_by_word() {
+        let mut input = TextInput::new("foo, bar baz");
+        press(&mut input, Key::ArrowLeft, CTRL);
+        assert_eq!(input.cursor(), 9);
+        press(&mut input, Key::ArrowLeft, CTRL);
+        assert_eq!(input.cursor(), 5);
+        press(&mut input, Key::ArrowLeft, CTRL);
+        assert_eq!(input.cursor(), 0);
+        press(&mut input, Key::ArrowLeft, CTRL);
+        assert_eq!(input.cursor(), 0);
+        press(&mut input, Key::ArrowRight, CTRL);
+        assert_eq!(input.cursor(), 3);
+        press(&mut input, Key::ArrowRight, CTRL);
+        assert_eq!(input.cursor(), 8);
+        press(&mut input, Key::End, Modifiers::default());
+        press(&mut input, Key::ArrowRight, Modifiers::default());
+        assert_eq!(input.cursor(), 12);
+    }
+
+    #[test]
+    fn shift_selects_and_typing_replaces_the_selection() {
+        let mut input = TextInput::new("hello world");
+        press(&mut input, Key::ArrowLeft, CTRL_SHIFT);
+        assert_eq!(input.selection(), Some(6..11));
+        assert_eq!(input.selected_text().as_deref(), Some("world"));
+        press(&mut input, Key::ArrowLeft, SHIFT);
+        assert_eq!(input.selection(), Some(5..11));
+
+        assert!(press(&mut input, char_key("!"), Modifiers::default()));
+        assert_eq!(input.text(), "hello!");
+        assert_eq!(input.selection(), None);
+
+        // Shift+Home selects back to the start; an arrow without Shift collapses it
+        press(&mut input, Key::Home, SHIFT);
+        assert_eq!(input.selection(), Some(0..6));
+        press(&mut input, Key::ArrowRight, Modifiers::default());
+        assert_eq!(input.selection(), None);
+        assert_eq!(input.cursor(), 6);
+
+        // Selecting back over the anchor leaves nothing selected
+        press(&mut input, Key::ArrowLeft, SHIFT);
+        press(&mut input, Key::ArrowRight, SHIFT);
+        assert_eq!(input.selection(), None);
+
+        input.select_all();
+        assert!(input.backspace(false));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn clipboard_shortcuts_copy_cut_and_paste() {
+        let mut input = TextInput::new("copy me");
+        let mut clipboard = clipboard();
+        let mut key =
+            |input: &mut TextInput, c: &str| input.handle_key(&char_key(c), CTRL, &mut clipboard);
+
+        key(&mut input, "a");
+        assert_eq!(input.selection(), Some(0..7));
+        key(&mut input, "c");
+        assert_eq!(input.text(), "copy me");
+        assert!(key(&mut input, "x"));
+        assert!(input.is_empty());
+        assert!(key(&mut input, "v"));
+        assert!(key(&mut input, "V"));
+        assert_eq!(input.text(), "copy mecopy me");
+
+        // Only the first line of a multi-line paste goes in
+        clipboard.set("first\nsecond".to_string());
+        let mut input = TextInput::new("");
+        input.handle_key(&char_key("v"), CTRL, &mut clipboard);
+        assert_eq!(input.text(), "first");
+    }
+
+    #[test]
+    fn masked_fields_hide_their_text_and_never_copy_it() {
+        let mut input = TextInput::new("hunter2 pass").masked();
+        assert_eq!(input.display_text(), "************");
+        input.select_all();
+        assert_eq!(input.selected_text(), None);
+
+        let mut clipboard = clipboard();
+        clipboard.set("before".to_string());
+        input.handle_key(&char_key("c"), CTRL, &mut clipboard);
+        input.handle_key(&char_key("x"), CTRL, &mut clipboard);
+        assert_eq!(clipboard.get(), "before");
+        assert_eq!(input.text(), "hunter2 pass");
+
+        // Word jumps and deletes cover the whole field so they don't give away spaces
+        input.end(false);
+        input.move_left(true, false);
+        assert_eq!(input.cursor(), 0);
+        input.end(false);
+        assert!(input.backspace(true));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn max_len_cuts_typing_pasting_and_the_starting_text() {
+        let mut input = TextInput::new("abcdef").with_max_len(4);
+        assert_eq!(input.text(), "abcd");
+        assert!(!input.insert("e"));
+        assert_eq!(input.text(), "abcd");
+
+        input.home(false);
+        input.move_right(false, true);
+        input.move_right(false, true);
+        assert!(input.insert("xyz"));
+        assert_eq!(input.text(), "xycd");
+        assert_eq!(input.cursor(), 2);
+
+        input.set_text("éàüöß");
+        assert_eq!(input.text(), "éàüö");
+    }
+
+    #[test]
+    fn numeric_fields_take_numbers_and_times_only() {
+        let mut input = TextInput::new("").numeric();
+        assert!(!input.insert("abc"));
+        assert!(input.insert("1a2.5x"));
+        assert_eq!(input.text(), "12.5");
+        assert_eq!(input.number(), Ok(12.5));
+
+        input.set_text("-0.25");
+        assert_eq!(input.number(), Ok(-0.25));
+        input.set_text("1:30");
+        assert_eq!(input.number(), Err("\"1:30\" is not a number".to_string()));
+        input.set_text("");
+        assert!(input.number().is_err());
+    }
+
+    #[test]
+    fn keys_that_change_nothing_report_no_change() {
+        let mut input = TextInput::new("abc");
+        assert!(!press(&mut input, Key::ArrowRight, Modifiers::default()));
+        assert!(!press(&mut input, Key::Delete, Modifiers::default()));
+        assert!(!press(&mut input, Key::Enter, Modifiers::default()));
+        assert!(!press(&mut input, char_key("q"), CTRL));
+        assert!(press(&mut input, Key::Home, Modifiers::default()));
+        let keys = [char_key("x"), Key::ArrowRight, Key::Backspace];
+        assert!(input.handle_keys(&keys, Modifiers::default(), &mut clipboard()));
+        assert_eq!(input.text(), "xbc");
+    }
+
+    #[test]
+    fn only_one_field_has_focus() {
+        let mut focus = TextFocus::default();
+        assert!(!focus.any());
+        focus.set(FocusId::Chat, true);
+        focus.set(FocusId::SongSearch, true);
+        assert_eq!(focus.focused(), Some(FocusId::SongSearch));
+
+        // A field that lost focus to another one doesn't take it back from it
+        focus.set(FocusId::Chat, false);
+        assert_eq!(focus.focused(), Some(FocusId::SongSearch));
+        focus.set(FocusId::SongSearch, false);
+        assert!(!focus.any());
+    }
+}
@@ -0,0 +1,304 @@
+// src/theme_presets.rs
+
+use crate::config::{BackgroundStyle, ThemeConfig};
+use crate::constants::hex_to_color;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder exported and imported theme files live in
+pub const THEMES_DIR: &str = "themes";
+
+/// File extension of shareable theme files
+pub const THEME_EXTENSION: &str = "yumtheme";
+
+/// Circle size multipliers a theme file may set
+const CIRCLE_SIZE_RANGE: (f32, f32) = (0.5, 2.0);
+
+/// Hidden fade fractions a theme file may set, matching `FadeSettings::from_theme`
+const HIDDEN_FADE_RANGE: (f32, f32) = (0.01, 1.0);
+
+/// A named theme as stored in a `.yumtheme` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThemePreset {
+    pub name: String,
+    /// Skin the theme was made with, for reference only; skins are not bundled
+    #[serde(default)]
+    pub skin: Option<String>,
+    pub theme: ThemeConfig,
+}
+
+impl ThemePreset {
+    /// Parse and validate a theme file. Colors must be hex; numeric values outside
+    /// their range are clamped rather than rejected
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut preset: ThemePreset =
+            serde_json::from_str(json).map_err(|e| format!("not a valid theme file ({})", e))?;
+
+        let theme = &mut preset.theme;
+        for (label, value) in [
+            ("primary color", &theme.primary_color),
+            ("secondary color", &theme.secondary_color),
+            ("circle color", &theme.circle_color),
+        ] {
+            if hex_to_color(value).is_none() {
+                return Err(format!(
+                    "{} \"{}\" is not a hex color like #FF12B8",
+                    label, value
+                ));
+            }
+        }
+        theme.circle_size = theme
+            .circle_size
+            .clamp(CIRCLE_SIZE_RANGE.0, CIRCLE_SIZE_RANGE.1);
+        theme.hidden_fade_fraction = theme
+            .hidden_fade_fraction
+            .clamp(HIDDEN_FADE_RANGE.0, HIDDEN_FADE_RANGE.1);
+        Ok(preset)
+    }
+
+    /// Read a theme file, naming it after the file if the name is blank
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {} ({})", path.display(), e))?;
+        let mut preset = Self::from_json(&json)?;
+        if preset.name.trim().is_empty() {
+            preset.name = file_stem(path);
+        }
+        Ok(preset)
+    }
+
+    /// Apply to the current theme. Reduced motion is an accessibility choice of the
+    /// player, so a shared theme never changes it
+    pub fn apply(&self, theme: &mut ThemeConfig) {
+        let reduced_motion = theme.reduced_motion;
+        *theme = self.theme.clone();
+        theme.reduced_motion = reduced_motion;
+    }
+
+    /// Presets that ship with the game
+    pub fn bundled() -> Vec<ThemePreset> {
+        let preset = |name: &str, theme: ThemeConfig| ThemePreset {
+            name: name.to_string(),
+            skin: None,
+            theme,
+        };
+        vec![
+            preset("Default", ThemeConfig::default()),
+            preset(
+                "High Contrast",
+                ThemeConfig {
+                    primary_color: "#FFFFFF".to_string(),
+                    secondary_color: "#FFFF00".to_string(),
+                    circle_color: "#FFFFFF".to_string(),
+                    background_style: BackgroundStyle::Dark,
+                    circle_size: 1.1,
+                    particles_enabled: false,
+                    screen_shake: false,
+                    ..ThemeConfig::default()
+                },
+            ),
+            // Okabe-Ito colors, distinguishable with the common color vision deficiencies
+            preset(
+                "Colorblind-safe",
+                ThemeConfig {
+                    primary_color: "#E69F00".to_string(),
+                    secondary_color: "#56B4E9".to_string(),
+                    circle_color: "#0072B2".to_string(),
+                    background_style: BackgroundStyle::Dark,
+                    ..ThemeConfig::default()
+                },
+            ),
+            preset(
+                "Minimal",
+                ThemeConfig {
+                    primary_color: "#DDDDDD".to_string(),
+                    secondary_color: "#888888".to_string(),
+                    circle_color: "#FFFFFF".to_string(),
+                    background_style: BackgroundStyle::Minimal,
+                    particles_enabled: false,
+                    screen_shake: false,
+                    hit_lighting: false,
+                    ..ThemeConfig::default()
+                },
+            ),
+        ]
+    }
+}
+
+/// A preset offered in the Theme tab: bundled, or a file found in `THEMES_DIR`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemePresetEntry {
+    pub name: String,
+    /// File to import from; None for bundled presets
+    pub path: Option<PathBuf>,
+}
+
+impl ThemePresetEntry {
+    /// Load the preset, validating files on the way in
+    pub fn load(&self) -> Result<ThemePreset, String> {
+        match &self.path {
+            Some(path) => ThemePreset::load(path),
+            None => ThemePreset::bundled()
+                .into_iter()
+                .find(|preset| preset.name == self.name)
+                .ok_or_else(|| format!("no bundled theme named \"{}\"", self.name)),
+        }
+    }
+}
+
+/// Bundled presets followed by the theme files in `THEMES_DIR`, sorted by name
+pub fn list_presets() -> Vec<ThemePresetEntry> {
+    let mut entries: Vec<ThemePresetEntry> = ThemePreset::bundled()
+        .into_iter()
+        .map(|preset| ThemePresetEntry {
+            name: preset.name,
+            path: None,
+        })
+        .collect();
+
+    let mut files: Vec<ThemePresetEntry> = fs::read_dir(THEMES_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION))
+        .map(|path| ThemePresetEntry {
+            name: file_stem(&path),
+            path: Some(path),
+        })
+        .collect();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    entries.extend(files);
+    entries
+}
+
+/// Write the theme to a new file in `THEMES_DIR`, never replacing an existing one
+pub fn export_theme(theme: &ThemeConfig, name: &str) -> Result<PathBuf, String> {
+    export_theme_to(Path::new(THEMES_DIR), theme, name)
+}
+
+/// Write the theme to a new file in `dir`
+fn export_theme_to(dir: &Path, theme: &ThemeConfig, name: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("couldn't create {} ({})", dir.display(), e))?;
+
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let path = (1..)
+        .map(|n| {
+            let stem = if n == 1 {
+                slug.clone()
+            } else {
+                format!("{}-{}", slug, n)
+            };
+            dir.join(format!("{}.{}", stem, THEME_EXTENSION))
+        })
+        .find(|path| !path.exists())
+        .expect("unbounded range always finds a free name");
+
+    let preset = ThemePreset {
+        name: name.to_string(),
+        skin: None,
+        theme: theme.clone(),
+    };
+    let json = serde_json::to_string_pretty(&preset).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("couldn't write {} ({})", path.display(), e))?;
+    Ok(path)
+}
+
+/// File name without the extension
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn themes_dir(test: &str) -> PathBuf {
+        scratch_dir(&format!("themes-{}", test))
+    }
+
+    #[test]
+    fn exported_themes_import_identically() {
+        let dir = themes_dir("round-trip");
+        for preset in ThemePreset::bundled() {
+            let theme = ThemeConfig {
+                circle_size: 1.35,
+                hidden_fade_fraction: 0.25,
+                show_hit_error_bar: !preset.theme.show_hit_error_bar,
+                ..preset.theme
+            };
+            let path = export_theme_to(&dir, &theme, &preset.name).unwrap();
+            let imported = ThemePreset::load(&path).unwrap();
+            assert_eq!(imported.name, preset.name);
+            assert_eq!(imported.theme, theme);
+        }
+    }
+
+    #[test]
+    fn exporting_never_replaces_an_existing_file() {
+        let dir = themes_dir("no-replace");
+        let first = export_theme_to(&dir, &ThemeConfig::default(), "My Theme!").unwrap();
+        let second = export_theme_to(&dir, &ThemeConfig::default(), "My Theme!").unwrap();
+        assert_eq!(first.file_name().unwrap(), "my-theme-.yumtheme");
+        assert_eq!(second.file_name().unwrap(), "my-theme--2.yumtheme");
+    }
+
+    #[test]
+    fn imports_check_colors_and_clamp_numbers() {
+        let json = |color: &str, size: f32, fade: f32| {
+            let theme = ThemeConfig {
+                circle_color: color.to_string(),
+                circle_size: size,
+                hidden_fade_fraction: fade,
+                ..ThemeConfig::default()
+            };
+            serde_json::to_string(&ThemePreset {
+                name: "x".to_string(),
+                skin: None,
+                theme,
+            })
+            .unwrap()
+        };
+
+        let clamped = ThemePreset::from_json(&json("#123456", 9.0, 0.0)).unwrap();
+        assert_eq!(clamped.theme.circle_size, CIRCLE_SIZE_RANGE.1);
+        assert_eq!(clamped.theme.hidden_fade_fraction, HIDDEN_FADE_RANGE.0);
+
+        assert_eq!(
+            ThemePreset::from_json(&json("blue", 1.0, 0.4)).unwrap_err(),
+            "circle color \"blue\" is not a hex color like #FF12B8"
+        );
+        assert!(ThemePreset::from_json("{\"name\": 3}")
+            .unwrap_err()
+            .starts_with("not a valid theme file"));
+    }
+
+    #[test]
+    fn applying_a_preset_keeps_reduced_motion() {
+        let mut theme = ThemeConfig {
+            reduced_motion: true,
+            ..ThemeConfig::default()
+        };
+        let minimal = ThemePreset::bundled()
+            .into_iter()
+            .find(|p| p.name == "Minimal")
+            .unwrap();
+        minimal.apply(&mut theme);
+        assert!(theme.reduced_motion);
+        assert_eq!(theme.primary_color, minimal.theme.primary_color);
+        assert!(!theme.particles_enabled);
+    }
+}
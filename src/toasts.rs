@@ -0,0 +1,272 @@
+// src/toasts.rs
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::analytics::AchievementCategory;
+use crate::config::GameConfig;
+use crate::constants::*;
+use crate::event_bus::BusEvent;
+use crate::structs::{GameAssets, GameTime};
+use crate::AppState;
+
+/// Seconds a toast stays on screen
+pub const TOAST_SECONDS: f64 = 4.0;
+
+/// Seconds a toast takes to slide in from the right edge
+const TOAST_SLIDE_SECONDS: f64 = 0.25;
+
+/// Toasts on screen at once; later ones wait for a free spot
+const MAX_VISIBLE_TOASTS: usize = 3;
+
+const TOAST_WIDTH: f32 = 340.0;
+const TOAST_HEIGHT: f32 = 28.0;
+/// Height of a toast with a second line
+const TOAST_DETAIL_HEIGHT: f32 = 44.0;
+
+struct Toast {
+    text: String,
+    /// Smaller second line, e.g. an achievement's category
+    detail: Option<String>,
+    color: Color,
+    /// Game time it came on screen, None while waiting
+    shown_at: Option<f64>,
+}
+
+impl Toast {
+    fn height(&self) -> f32 {
+        if self.detail.is_some() {
+            TOAST_DETAIL_HEIGHT
+        } else {
+            TOAST_HEIGHT
+        }
+    }
+}
+
+/// Short notices stacked in the bottom-right corner of every screen but gameplay
+#[derive(Resource, Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+    /// Held back during a play so nothing covers the circles
+    paused: bool,
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.queue.push_back(Toast {
+            text: text.into(),
+            detail: None,
+            color: NEON_YELLOW,
+            shown_at: None,
+        });
+    }
+
+    /// An unlocked achievement, its category on a second line. Several unlocked at
+    /// once stack like any other toasts
+    pub fn push_achievement(&mut self, name: &str, category: AchievementCategory) {
+        self.queue.push_back(Toast {
+            text: format!("Achievement unlocked: {}", name),
+            detail: Some(format!("{} achievement", category.name())),
+            color: NEON_PINK,
+            shown_at: None,
+        });
+    }
+
+    /// Start the timers of toasts that came on screen and drop expired ones.
+    /// True when what's on screen changed
+    fn tick(&mut self, now: f64) -> bool {
+        if self.paused {
+            return false;
+        }
+        let before = self.queue.len();
+        self.queue
+            .retain(|toast| toast.shown_at.is_none_or(|at| now - at < TOAST_SECONDS));
+        let mut changed = self.queue.len() != before;
+        for toast in self.queue.iter_mut().take(MAX_VISIBLE_TOASTS) {
+            if toast.shown_at.is_none() {
+                toast.shown_at = Some(now);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Event bus subscriber turning events into toasts
+pub fn toast_events(event: &BusEvent, world: &mut World) {
+    match event {
+        BusEvent::AchievementUnlocked { name, category } => {
+            let mut toasts = world.resource_mut::<Toasts>();
+            toasts.push_achievement(name, *category);
+        }
+        BusEvent::ScreenChanged { to, .. } => {
+            world.resource_mut::<Toasts>().paused =
+                matches!(to, AppState::Visualizing | AppState::Versus);
+        }
+        _ => {}
+    }
+}
+
+/// Marker for toast entities, which outlive screen changes
+#[derive(Component)]
+pub struct ToastElement;
+
+/// Slide-in of a toast on screen: where it settles and when it came on screen
+#[derive(Component)]
+pub struct ToastSlide {
+    x: f32,
+    shown_at: f64,
+}
+
+impl ToastSlide {
+    /// Horizontal position at a game time, easing in from past the right edge
+    fn x_at(&self, now: f64) -> f32 {
+        let progress = ((now - self.shown_at) / TOAST_SLIDE_SECONDS).clamp(0.0, 1.0) as f32;
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        self.x + (1.0 - eased) * (TOAST_WIDTH + 40.0)
+    }
+}
+
+/// Draw the toasts on screen, rebuilt when they change and slid in from the right
+/// unless motion is reduced
+#[allow(clippy::too_many_arguments)]
+pub fn draw_toasts(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    game_time: Res<GameTime>,
+    config: Res<GameConfig>,
+    windows: Query<&Window>,
+    mut toasts: ResMut<Toasts>,
+    existing: Query<Entity, With<ToastElement>>,
+    mut slides: Query<(&ToastSlide, &mut Transform)>,
+) {
+    let now = game_time.elapsed;
+    let ticked = toasts.bypass_change_detection().tick(now);
+    if !ticked && !toasts.is_changed() {
+        for (slide, mut transform) in slides.iter_mut() {
+            if now - slide.shown_at <= TOAST_SLIDE_SECONDS {
+                transform.translation.x = slide.x_at(now);
+            }
+        }
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if toasts.paused {
+        return;
+    }
+
+    let x = window.width() / 2.0 - TOAST_WIDTH / 2.0 - 20.0;
+    let visible = toasts
+        .queue
+        .iter()
+        .take_while(|toast| toast.shown_at.is_some());
+    let mut bottom = -window.height() / 2.0 + 26.0;
+    for toast in visible {
+        let height = toast.height();
+        let y = bottom + height / 2.0;
+        bottom += height + 8.0;
+        let slide = ToastSlide {
+            x,
+            // Reduced motion shows toasts in place
+            shown_at: match config.theme.reduced_motion {
+                true => f64::NEG_INFINITY,
+                false => toast.shown_at.unwrap_or(now),
+            },
+        };
+        let text_y = if toast.detail.is_some() { 7.0 } else { 0.0 };
+        commands
+            .spawn((
+                Sprite {
+                    color: Color::srgba(0.05, 0.05, 0.1, 0.9),
+                    custom_size: Some(Vec2::new(TOAST_WIDTH, height)),
+                    ..default()
+                },
+                Transform::from_xyz(slide.x_at(now), y, 20.0),
+                ToastElement,
+                slide,
+            ))
+            .with_children(|parent| {
+                // Neon edge on the left
+                parent.spawn((
+                    Sprite {
+                        color: toast.color,
+                        custom_size: Some(Vec2::new(3.0, height)),
+                        ..default()
+                    },
+                    Transform::from_xyz(-TOAST_WIDTH / 2.0 + 1.5, 0.0, 0.01),
+                ));
+                parent.spawn((
+                    Text2d::new(toast.text.clone()),
+                    TextFont {
+                        font: assets.cyberpunk_font.clone(),
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(toast.color),
+                    Transform::from_xyz(0.0, text_y, 0.01),
+                ));
+                if let Some(detail) = &toast.detail {
+                    parent.spawn((
+                        Text2d::new(detail.clone()),
+                        TextFont {
+                            font: assets.cyberpunk_font.clone(),
+                            font_size: 11.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+                        Transform::from_xyz(0.0, -10.0, 0.01),
+                    ));
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(toasts: &Toasts) -> usize {
+        toasts
+            .queue
+            .iter()
+            .filter(|toast| toast.shown_at.is_some())
+            .count()
+    }
+
+    #[test]
+    fn bus_events_queue_toasts_and_hold_them_during_play() {
+        let mut world = World::new();
+        world.init_resource::<Toasts>();
+        let screen = |from, to| BusEvent::ScreenChanged { from, to };
+
+        toast_events(&screen(AppState::Menu, AppState::Visualizing), &mut world);
+        for name in ["First Steps", "Combo", "Perfect", "Marathon"] {
+            let unlocked = BusEvent::AchievementUnlocked {
+                name: name.to_string(),
+                category: AchievementCategory::Special,
+            };
+            toast_events(&unlocked, &mut world);
+        }
+        let mut toasts = world.resource_mut::<Toasts>();
+        assert_eq!(toasts.queue[0].text, "Achievement unlocked: First Steps");
+        assert!(!toasts.tick(1.0));
+        assert_eq!(shown(&toasts), 0);
+
+        toast_events(&screen(AppState::Visualizing, AppState::End), &mut world);
+        let mut toasts = world.resource_mut::<Toasts>();
+        assert!(toasts.tick(2.0));
+        assert_eq!(shown(&toasts), MAX_VISIBLE_TOASTS);
+
+        // The fourth comes on once the first three have had their time
+        assert!(!toasts.tick(2.0 + TOAST_SECONDS - 0.1));
+        assert!(toasts.tick(2.0 + TOAST_SECONDS));
+        assert_eq!(toasts.queue.len(), 1);
+        assert_eq!(shown(&toasts), 1);
+    }
+}
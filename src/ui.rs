@@ -1,14 +1,54 @@
-use crate::analytics::{Analytics, AnalyticsState, AnalyticsView, Grade};
+use crate::accounts::{Friend, FriendStatus, SongRecord};
+use crate::achievements::AchievementDefinitions;
+use crate::analytics::{
+    today, Analytics, AnalyticsState, AnalyticsView, Grade, GRADE_SS_ACCURACY, GRADE_S_ACCURACY,
+    SECONDS_PER_DAY,
+};
+use crate::asset_manager::AssetManager;
 use crate::config::{
-    get_available_keys, BackgroundStyle, GameConfig, KeyBindingType, SettingsState, SettingsTab,
+    key_label, BackgroundStyle, GameConfig, SettingsState, SettingsTab, ThemeConfig,
+    DELETE_ANALYTICS_CONFIRMATION,
 };
+use crate::beatmap::Beatmap;
+use crate::bots::{Standing, BOT_TAG};
+use crate::community::{visible_chat_lines, ChatLine, ComparisonTab, ComparisonView, Outcome};
 use crate::constants::*;
+use crate::difficulty::{DifficultyMetrics, DifficultySummary, METRIC_SCALE_MAX};
+use crate::editor::format_time;
+use crate::effects::{draw_hit_error_bar, HitErrors};
+use crate::gamemode::Modifier;
+use crate::generator::format_seed;
+use crate::hud::{HudEditorState, HudElement, HudLayout, HudValues};
+use crate::library_scan::{LibraryScan, MAX_LISTED_ISSUES};
+use crate::news::NewsState;
+use crate::online::{
+    AccountFormState, BoardScope, OnlineHubState, OnlineServices, OnlineTab, CHAT_VISIBLE_MESSAGES,
+    ONLINE_LIST_ROWS,
+};
+use crate::precision::{
+    normalize, position_bin_center, PrecisionMap, POSITION_BINS, TIMING_BINS, TIMING_RANGE_MS,
+};
+use crate::profiles::{ProfilePickerState, DEFAULT_PROFILE};
+use crate::recommend::NextRecommendation;
+use crate::replay::{ReplayLibrary, MAX_LISTED_REPLAYS};
+use crate::score_filter::{
+    filter_chips, filter_scores, mods_label, rank_of, season_chips, song_scores, FilterChip,
+    PlaySignature, SeasonFilter, LEADERBOARD_ROWS,
+};
+use crate::score_format::{format_score, write_score, ScoreStyle};
+use crate::seasons::{Season, SeasonArchive, SeasonCalendar, SeasonSummary};
+use crate::settings_registry::tab_entries;
 use crate::structs::{
-    EndData, EndState, FloatingText, GameAssets, GameStateResource, LoadingData, PracticeMenuState,
-    ReadyToPlayData, SongSelectionState, VisualizingData, VisualizingState,
+    song_name, EndData, EndState, FailedData, FloatingText, GameAssets, GameStateResource,
+    LibraryProgress, PauseOption, PracticeMenuState, ReadyToPlayData, ResultsNotice,
+    ResultsScrubber, SongSelectionState,
 };
-use crate::{AppState, MenuData};
+use crate::text_input::draw_text_box;
+use crate::versus::{versus_picker, VersusPlayer};
+use crate::AppState;
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
+use chrono::{DateTime, Datelike, Local, Utc};
 use std::fs;
 
 /// Component marker for UI elements that should be cleaned up between states
@@ -32,11 +72,59 @@ pub enum MenuAction {
 }
 
 /// Setup the main menu UI
-pub fn setup_menu_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+pub fn setup_menu_ui(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    config: Res<GameConfig>,
+    online: Res<OnlineServices>,
+    windows: Query<&Window>,
+) {
     if let Ok(window) = windows.get_single() {
         let scr_width = window.width();
         let scr_height = window.height();
 
+        // Active local profile, top left opposite the news panel
+        commands.spawn((
+            Text2d::new(format!("Profile: {}  [P] switch", config.active_profile)),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NEON_BLUE),
+            Transform::from_xyz(-scr_width / 2.0 + 150.0, scr_height / 2.0 - 40.0, 1.0),
+            UiElement,
+        ));
+        // Account under it
+        let account = match online.user() {
+            Some(user) => format!("Account: {}  [O]", user.username),
+            None => "Not signed in  [O] sign in".to_string(),
+        };
+        commands.spawn((
+            Text2d::new(account),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NEON_BLUE),
+            Transform::from_xyz(-scr_width / 2.0 + 150.0, scr_height / 2.0 - 64.0, 1.0),
+            UiElement,
+        ));
+        if let Some(badge) = online.inbox.badge() {
+            commands.spawn((
+                Text2d::new(format!("Friend requests: {}", badge)),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(NEON_PINK),
+                Transform::from_xyz(-scr_width / 2.0 + 150.0, scr_height / 2.0 - 88.0, 1.0),
+                UiElement,
+            ));
+        }
+
         let button_width = BUTTON_WIDTH;
         let button_height = BUTTON_HEIGHT;
         let button_spacing = BUTTON_SPACING;
@@ -50,7 +138,7 @@ pub fn setup_menu_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Q
                 font_size: 72.0,
                 ..default()
             },
-            TextColor(NEON_PINK.into()),
+            TextColor(NEON_PINK),
             Transform::from_xyz(0.0, scr_height * 0.2, 1.0),
             UiElement,
         ));
@@ -112,7 +200,7 @@ pub fn setup_menu_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Q
                     font_size: CYBERPUNK_FONT_SIZE,
                     ..default()
                 },
-                TextColor(Color::WHITE.into()),
+                TextColor(Color::WHITE),
                 Transform::from_xyz(
                     button_x,
                     y_pos - scr_height / 2.0 + button_height / 2.0,
@@ -131,7 +219,16 @@ pub fn handle_menu_interactions(
     query: Query<(&Transform, &MenuButton), Without<Text2d>>,
     windows: Query<&Window>,
     mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
 ) {
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        next_state.set(AppState::ProfilePicker);
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::KeyO) {
+        next_state.set(AppState::Online);
+        return;
+    }
     if let Ok(window) = windows.get_single() {
         if let Some(cursor_pos) = window.cursor_position() {
             // Convert to world coordinates (center is 0,0 in Bevy)
@@ -144,29 +241,29 @@ pub fn handle_menu_interactions(
                     Vec2::new(BUTTON_WIDTH, BUTTON_HEIGHT),
                 );
 
-                if button_rect.contains(Vec2::new(world_x, world_y)) {
-                    if mouse_input.just_pressed(MouseButton::Left) {
-                        match button.action {
-                            MenuAction::StartGame => {
-                                game_state.songs = load_songs_from_assets();
-                                next_state.set(AppState::SongSelection);
-                            }
-                            MenuAction::Practice => {
-                                game_state.songs = load_songs_from_assets();
-                                next_state.set(AppState::PracticeMenu);
-                            }
-                            MenuAction::BeatmapEditor => {
-                                next_state.set(AppState::BeatmapSelection);
-                            }
-                            MenuAction::Analytics => {
-                                next_state.set(AppState::Analytics);
-                            }
-                            MenuAction::Settings => {
-                                next_state.set(AppState::Settings);
-                            }
-                            MenuAction::Exit => {
-                                // Exit is handled by AppExit event
-                            }
+                if button_rect.contains(Vec2::new(world_x, world_y))
+                    && mouse_input.just_pressed(MouseButton::Left)
+                {
+                    match button.action {
+                        MenuAction::StartGame => {
+                            game_state.songs = load_songs_from_assets();
+                            next_state.set(AppState::SongSelection);
+                        }
+                        MenuAction::Practice => {
+                            game_state.songs = load_songs_from_assets();
+                            next_state.set(AppState::PracticeMenu);
+                        }
+                        MenuAction::BeatmapEditor => {
+                            next_state.set(AppState::BeatmapSelection);
+                        }
+                        MenuAction::Analytics => {
+                            next_state.set(AppState::Analytics);
+                        }
+                        MenuAction::Settings => {
+                            next_state.set(AppState::Settings);
+                        }
+                        MenuAction::Exit => {
+                            // Exit is handled by AppExit event
                         }
                     }
                 }
@@ -175,6 +272,206 @@ pub fn handle_menu_interactions(
     }
 }
 
+/// Marker for news panel entities, rebuilt when the feed or panel state changes
+#[derive(Component)]
+pub struct NewsPanelElement;
+
+/// Top-right anchor and size of the news panel header
+fn news_panel_header(window: &Window) -> Rect {
+    let center = Vec2::new(window.width() / 2.0 - 190.0, window.height() / 2.0 - 40.0);
+    Rect::from_center_size(center, Vec2::new(340.0, 30.0))
+}
+
+/// Toggle the news panel with N or a click on its header; opening it marks news as read
+pub fn handle_news_panel(
+    mut news: ResMut<NewsState>,
+    mut config: ResMut<GameConfig>,
+    windows: Query<&Window>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+) {
+    let mut toggle = keyboard.just_pressed(KeyCode::KeyN);
+    if mouse_input.just_pressed(MouseButton::Left) {
+        if let Ok(window) = windows.get_single() {
+            if let Some(cursor_pos) = window.cursor_position() {
+                let world = Vec2::new(
+                    cursor_pos.x - window.width() / 2.0,
+                    window.height() / 2.0 - cursor_pos.y,
+                );
+                toggle |= news_panel_header(window).contains(world);
+            }
+        }
+    }
+    if !toggle {
+        return;
+    }
+
+    news.expanded = !news.expanded;
+    if news.expanded {
+        let newest = news
+            .feed
+            .as_ref()
+            .and_then(|feed| feed.entries.first())
+            .map(|entry| entry.id.clone());
+        if newest.is_some() && newest != config.last_seen_news_id {
+            config.last_seen_news_id = newest;
+            config.save();
+        }
+    }
+}
+
+/// Draw the news panel on the main menu
+pub fn draw_news_panel(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    news: Res<NewsState>,
+    config: Res<GameConfig>,
+    existing: Query<Entity, With<NewsPanelElement>>,
+) {
+    if !news.is_changed() && !config.is_changed() && !existing.is_empty() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let Some(feed) = news.feed.as_ref().filter(|feed| !feed.entries.is_empty()) else {
+        return;
+    };
+
+    let header = news_panel_header(window);
+    let arrow = if news.expanded { "v" } else { ">" };
+    commands.spawn((
+        Text2d::new(format!("{} News (N)", arrow)),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(NEON_CYAN),
+        Transform::from_xyz(header.center().x, header.center().y, 1.0),
+        UiElement,
+        NewsPanelElement,
+    ));
+
+    // Unread dot until the newest entry has been opened
+    if feed.has_unread(config.last_seen_news_id.as_deref()) {
+        commands.spawn((
+            Sprite {
+                color: NEON_PINK,
+                custom_size: Some(Vec2::new(8.0, 8.0)),
+                ..default()
+            },
+            Transform::from_xyz(header.max.x - 70.0, header.center().y + 6.0, 1.0),
+            UiElement,
+            NewsPanelElement,
+        ));
+    }
+
+    let mut y = header.min.y - 16.0;
+    if feed.latest().iter().any(|entry| entry.requires_update()) {
+        commands.spawn((
+            Text2d::new("Update available"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 12.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 0.0, 0.7)),
+            Transform::from_xyz(header.center().x, y, 1.0),
+            UiElement,
+            NewsPanelElement,
+        ));
+        y -= 20.0;
+    }
+
+    if !news.expanded {
+        return;
+    }
+
+    for entry in feed.latest() {
+        commands.spawn((
+            Text2d::new(format!("{}  {}", entry.date, entry.title)),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(header.center().x, y, 1.0),
+            UiElement,
+            NewsPanelElement,
+        ));
+        y -= 18.0;
+
+        commands.spawn((
+            Text2d::new(entry.body.clone()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 11.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            TextLayout::new_with_linebreak(LineBreak::WordBoundary),
+            bevy::text::TextBounds::new_horizontal(header.width()),
+            Transform::from_xyz(header.center().x, y, 1.0),
+            bevy::sprite::Anchor::TopCenter,
+            UiElement,
+            NewsPanelElement,
+        ));
+        y -= 60.0;
+    }
+}
+
+/// Marker for the asset loading line on the main menu
+#[derive(Component)]
+pub struct AssetStatusElement;
+
+/// Report asset loading progress, or failures and how to retry them, on the main menu
+pub fn draw_asset_status(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    manager: Res<AssetManager>,
+    existing: Query<Entity, With<AssetStatusElement>>,
+) {
+    if !manager.is_changed() && !existing.is_empty() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(line) = manager.status_line() else {
+        return;
+    };
+    let color = if manager.failed().is_empty() {
+        NEON_CYAN
+    } else {
+        NEON_ORANGE
+    };
+    commands.spawn((
+        Text2d::new(line),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(color),
+        Transform::from_xyz(0.0, -window.height() / 2.0 + 30.0, 1.0),
+        UiElement,
+        AssetStatusElement,
+    ));
+}
+
 /// Load all songs from the assets directory
 pub fn load_songs_from_assets() -> Vec<String> {
     let mut songs = Vec::new();
@@ -206,7 +503,6 @@ pub fn setup_song_selection_ui(
     mut commands: Commands,
     assets: Res<GameAssets>,
     windows: Query<&Window>,
-    game_state: Res<GameStateResource>,
 ) {
     if let Ok(window) = windows.get_single() {
         let screen_h = window.height();
@@ -220,40 +516,11 @@ pub fn setup_song_selection_ui(
                 font_size: CYBERPUNK_FONT_SIZE,
                 ..default()
             },
-            TextColor(NEON_PINK.into()),
+            TextColor(NEON_PINK),
             Transform::from_xyz(-screen_w / 2.0 + 20.0, screen_h / 2.0 - screen_h * 0.1, 1.0),
             UiElement,
         ));
 
-        // Song list
-        for (i, song) in game_state.songs.iter().enumerate() {
-            let button_y =
-                screen_h / 2.0 - screen_h * 0.2 - (i as f32) * (SONG_ENTRY_HEIGHT + 20.0);
-
-            let song_name = song
-                .split('/')
-                .last()
-                .unwrap_or(song)
-                .to_uppercase()
-                .replace(".MP3", "")
-                .replace(".mp3", "");
-
-            commands.spawn((
-                Text2d::new(song_name),
-                TextFont {
-                    font: assets.cyberpunk_font.clone(),
-                    font_size: CYBERPUNK_FONT_SIZE,
-                    ..default()
-                },
-                TextColor(Color::WHITE.into()),
-                Transform::from_xyz(-screen_w / 2.0 + 50.0, button_y, 1.0),
-                UiElement,
-                SongButton {
-                    song_path: song.clone(),
-                },
-            ));
-        }
-
         // Back button text
         commands.spawn((
             Text2d::new("Press ESC to go back"),
@@ -262,7 +529,7 @@ pub fn setup_song_selection_ui(
                 font_size: 16.0,
                 ..default()
             },
-            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5).into()),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
             Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
             UiElement,
         ));
@@ -274,301 +541,4362 @@ pub struct SongButton {
     pub song_path: String,
 }
 
-/// Handle song selection interactions
-pub fn handle_song_selection(
-    mut next_state: ResMut<NextState<AppState>>,
-    mut game_state: ResMut<GameStateResource>,
-    query: Query<(&Transform, &SongButton), With<Text2d>>,
-    windows: Query<&Window>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
-) {
-    if let Ok(window) = windows.get_single() {
-        if let Some(cursor_pos) = window.cursor_position() {
-            let world_x = cursor_pos.x - window.width() / 2.0;
-            let world_y = window.height() / 2.0 - cursor_pos.y;
-
-            for (transform, button) in query.iter() {
-                let rect = Rect::from_center_size(
-                    transform.translation.truncate(),
-                    Vec2::new(400.0, SONG_ENTRY_HEIGHT),
-                );
-
-                if rect.contains(Vec2::new(world_x, world_y)) {
-                    if mouse_input.just_pressed(MouseButton::Left) {
-                        game_state.selected_song = button.song_path.clone();
-                        next_state.set(AppState::Playing);
-                    }
-                }
-            }
-        }
-    }
-}
-
-/// Setup loading screen
-pub fn setup_loading_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
-    if let Ok(window) = windows.get_single() {
-        commands.spawn((
-            Text2d::new("Loading..."),
-            TextFont {
-                font: assets.cyberpunk_font.clone(),
-                font_size: CYBERPUNK_FONT_SIZE,
-                ..default()
-            },
-            TextColor(NEON_PINK.into()),
-            Transform::from_xyz(0.0, 0.0, 1.0),
-            UiElement,
-            LoadingText,
-        ));
-    }
-}
-
+/// Marker for the song list, progress summary and list options, rebuilt on change
 #[derive(Component)]
-pub struct LoadingText;
+pub struct SongListElement;
 
-/// Setup ready to play countdown
-pub fn setup_ready_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
-    if let Ok(window) = windows.get_single() {
-        commands.spawn((
-            Text2d::new("Starting in 5"),
-            TextFont {
-                font: assets.cyberpunk_font.clone(),
-                font_size: FONT_SIZE as f32,
-                ..default()
-            },
-            TextColor(NEON_GREEN.into()),
-            Transform::from_xyz(0.0, 0.0, 1.0),
-            UiElement,
-            CountdownText,
-        ));
-    }
+/// Gap between song list rows
+const SONG_ROW_GAP: f32 = 20.0;
+
+/// Song rows that fit between the top of the list and the key hints at the bottom
+pub fn song_rows_shown(screen_h: f32) -> usize {
+    let top = screen_h / 2.0 - screen_h * 0.2;
+    let bottom = -screen_h / 2.0 + 70.0;
+    let gaps = (top - bottom) / (SONG_ENTRY_HEIGHT + SONG_ROW_GAP);
+    gaps.floor().max(0.0) as usize + 1
 }
 
-#[derive(Component)]
-pub struct CountdownText;
+/// Draw the filtered, sorted song list with each song's completion status and the
+/// library progress summary. The keyboard cursor's row is marked and pink, the
+/// hovered row cyan
+/// Keys toggling the speed, difficulty and visibility modifiers on the song
+/// selection screen
+pub const SONG_SELECT_MODS: [(KeyCode, &str, Modifier); 6] = [
+    (KeyCode::KeyD, "D", Modifier::DoubleTime),
+    (KeyCode::KeyH, "H", Modifier::HalfTime),
+    (KeyCode::KeyR, "R", Modifier::HardRock),
+    (KeyCode::KeyE, "E", Modifier::EasyMod),
+    (KeyCode::KeyI, "I", Modifier::Hidden),
+    (KeyCode::KeyL, "L", Modifier::Flash),
+];
 
-/// Update countdown
-pub fn update_countdown(
-    mut query: Query<&mut Text2d, With<CountdownText>>,
-    ready_data: Res<ReadyToPlayData>,
+#[allow(clippy::too_many_arguments)]
+pub fn draw_song_list(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    game_state: Res<GameStateResource>,
+    selection_state: Res<SongSelectionState>,
+    analytics: Res<Analytics>,
+    config: Res<GameConfig>,
+    existing: Query<Entity, With<SongListElement>>,
 ) {
-    let elapsed = ready_data.ready_time.elapsed().as_secs_f32();
-    let remaining = (COUNTDOWN_DURATION - elapsed as f64).max(0.0) as i32;
+    if !selection_state.is_changed()
+        && !analytics.is_changed()
+        && !game_state.is_changed()
+        && !config.is_changed()
+    {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
 
-    for mut text in query.iter_mut() {
-        text.0 = format!("Starting in {}", remaining);
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
     }
-}
 
-/// Draw the score
-pub fn draw_score_bevy(
-    commands: &mut Commands,
-    score: i32,
-    combo: u32,
-    max_combo: u32,
-    assets: &GameAssets,
-) {
-    // Combo display
-    if combo > 0 {
-        let combo_text = format!("{}x", combo);
-        let combo_size = if combo >= 100 {
-            48.0
-        } else if combo >= 50 {
-            40.0
-        } else if combo >= 25 {
-            36.0
-        } else {
-            32.0
-        };
+    let screen_h = window.height();
+    let screen_w = window.width();
 
-        let combo_color = if combo >= 100 {
-            Color::srgba(1.0, 0.84, 0.0, 1.0)
-        } else if combo >= 50 {
-            NEON_PINK
-        } else if combo >= 25 {
-            NEON_PURPLE
-        } else {
-            NEON_BLUE
+    commands.spawn((
+        Text2d::new(LibraryProgress::of(&game_state.songs, &analytics).summary()),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(NEON_GREEN),
+        Transform::from_xyz(screen_w / 2.0 - 180.0, screen_h / 2.0 - screen_h * 0.1, 1.0),
+        UiElement,
+        SongListElement,
+    ));
+
+    // Whether picking a song starts a local versus match
+    let (versus_text, versus_color) = if game_state.local_versus {
+        let keys = VersusPlayer::BOTH.map(|player| player.keys_label(&config.key_bindings));
+        let opponent = match game_state.versus_bot {
+            Some(profile) => format!("{} {}", BOT_TAG, profile.display_name()),
+            None => keys[1].clone(),
         };
+        let mut text = format!(
+            "LOCAL VERSUS  P1: {}  P2: {}  B: opponent",
+            keys[0], opponent
+        );
+        // Once a match has passed the pick on, say whose turn it is
+        if game_state.versus_picks.is_some() {
+            let picker = versus_picker(
+                game_state.versus_picks.as_ref(),
+                game_state.versus_bot.is_some(),
+            );
+            text.push_str(&format!("  |  {} picks", picker.label()));
+        }
+        (text, NEON_PINK)
+    } else if !game_state.room_bots.is_empty() {
+        let bots = game_state
+            .room_bots
+            .iter()
+            .map(|profile| format!("{} {}", BOT_TAG, profile.display_name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let text = format!("ROOM {}  B: add  Shift+B: profile  Ctrl+B: remove", bots);
+        (text, NEON_CYAN)
+    } else {
+        let text = "Tab: local versus  B: add bot".to_string();
+        (text, Color::srgba(1.0, 1.0, 1.0, 0.5))
+    };
+    let versus_y = screen_h / 2.0 - screen_h * 0.1 - 28.0;
+    commands.spawn((
+        Text2d::new(versus_text),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(versus_color),
+        Transform::from_xyz(screen_w / 2.0 - 180.0, versus_y, 1.0),
+        UiElement,
+        SongListElement,
+    ));
 
+    // Modifiers the next play starts with, and the keys toggling them
+    let game_settings = &config.game_settings;
+    let toggles = SONG_SELECT_MODS
+        .iter()
+        .map(|(_, key, modifier)| format!("{}: {}", key, modifier.acronym()))
+        .collect::<Vec<_>>()
+        .join("  ");
+    let (mods_text, mods_color) = if game_settings.modifiers.is_empty() {
+        ("Mods: none".to_string(), Color::srgba(1.0, 1.0, 1.0, 0.5))
+    } else {
+        let text = format!(
+            "Mods: {}  (score x{:.2})",
+            mods_label(&game_settings.modifiers),
+            game_settings.score_multiplier()
+        );
+        (text, NEON_YELLOW)
+    };
+    let mods_lines = [
+        (mods_text, 16.0, mods_color),
+        (toggles, 12.0, Color::srgba(1.0, 1.0, 1.0, 0.5)),
+    ];
+    for (i, (text, size, color)) in mods_lines.into_iter().enumerate() {
+        let y = versus_y - 28.0 - i as f32 * 20.0;
         commands.spawn((
-            Text2d::new(combo_text),
+            Text2d::new(text),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
-                font_size: combo_size,
+                font_size: size,
                 ..default()
             },
-            TextColor(combo_color.into()),
-            Transform::from_xyz(DRAW_SCORE_X, DRAW_SCORE_Y + 50.0, 1.0),
+            TextColor(color),
+            Transform::from_xyz(screen_w / 2.0 - 180.0, y, 1.0),
             UiElement,
+            SongListElement,
         ));
     }
 
-    // Score display
-    let score_text = format!("Score: {}", score);
+    // Pasted layout seed waiting for the next play
+    let seed_color = match game_state.seed_override {
+        Some(_) => NEON_YELLOW,
+        None => Color::srgba(1.0, 1.0, 1.0, 0.5),
+    };
+    let seed_hint = match (game_state.seed_override, &selection_state.seed_notice) {
+        (Some(seed), _) => format!("Next play: seed {} (Backspace clears)", format_seed(seed)),
+        (None, Some(notice)) => notice.clone(),
+        (None, None) => "Ctrl+V: paste a layout seed".to_string(),
+    };
     commands.spawn((
-        Text2d::new(score_text),
+        Text2d::new(seed_hint),
         TextFont {
             font: assets.cyberpunk_font.clone(),
-            font_size: SCORE_FONT_SIZE,
+            font_size: 16.0,
             ..default()
         },
-        TextColor(NEON_BLUE.into()),
-        Transform::from_xyz(DRAW_SCORE_X, DRAW_SCORE_Y, 1.0),
+        TextColor(seed_color),
+        Transform::from_xyz(screen_w / 2.0 - 240.0, -screen_h / 2.0 + 44.0, 1.0),
         UiElement,
+        SongListElement,
     ));
 
-    // Max combo
-    let max_combo_text = format!("Max Combo: {}", max_combo);
     commands.spawn((
-        Text2d::new(max_combo_text),
+        Text2d::new(format!(
+            "{}/{}: move  {}: play  /: search  |  F: {}  |  S: sort by {}",
+            key_label(&config.key_bindings.navigate_up),
+            key_label(&config.key_bindings.navigate_down),
+            key_label(&config.key_bindings.select),
+            selection_state.filter.name(),
+            selection_state.sort.name()
+        )),
         TextFont {
             font: assets.cyberpunk_font.clone(),
-            font_size: 20.0,
+            font_size: 16.0,
             ..default()
         },
-        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6).into()),
-        Transform::from_xyz(DRAW_SCORE_X, DRAW_SCORE_Y - 30.0, 1.0),
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(screen_w / 2.0 - 340.0, -screen_h / 2.0 + 20.0, 1.0),
         UiElement,
+        SongListElement,
     ));
-}
 
-/// Draw floating texts
-pub fn draw_floating_texts_bevy(
-    commands: &mut Commands,
-    floating_texts: &mut Vec<FloatingText>,
-    elapsed: f64,
-    assets: &GameAssets,
+    if let Some(query) = &selection_state.query {
+        let center = Vec2::new(-screen_w / 2.0 + 230.0, screen_h / 2.0 - screen_h * 0.1);
+        draw_text_box(
+            &mut commands,
+            query,
+            center,
+            360.0,
+            true,
+            assets.cyberpunk_font.clone(),
+        )
+        .insert(SongListElement);
+    }
+
+    let songs = selection_state.visible_songs(&game_state.songs, &analytics);
+    if songs.is_empty() {
+        let message = if selection_state.query_text().is_empty() {
+            "No songs match this filter".to_string()
+        } else {
+            format!("No matches for \"{}\"", selection_state.query_text())
+        };
+        commands.spawn((
+            Text2d::new(message),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(0.0, 0.0, 1.0),
+            UiElement,
+            SongListElement,
+        ));
+        return;
+    }
+
+    let first = selection_state.scroll_pos.min(songs.len() - 1);
+    let shown = songs
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(song_rows_shown(screen_h));
+    for (i, (song, status)) in shown {
+        let row = (i - first) as f32;
+        let button_y = screen_h / 2.0 - screen_h * 0.2 - row * (SONG_ENTRY_HEIGHT + SONG_ROW_GAP);
+
+        let name = song_name(song);
+        let cursor = i == selection_state.selected_index;
+        let (name, color) = if cursor {
+            (format!("> {}", name), NEON_PINK)
+        } else if selection_state.selected_song.as_ref() == Some(song) {
+            (name, NEON_CYAN)
+        } else {
+            (name, Color::WHITE)
+        };
+
+        commands.spawn((
+            Text2d::new(name),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: CYBERPUNK_FONT_SIZE,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(-screen_w / 2.0 + 50.0, button_y, 1.0),
+            UiElement,
+            SongListElement,
+            SongButton {
+                song_path: song.clone(),
+            },
+        ));
+
+        let (r, g, b) = status.color();
+        commands.spawn((
+            Text2d::new(status.icon()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 20.0,
+                ..default()
+            },
+            TextColor(Color::srgb(r, g, b)),
+            Transform::from_xyz(screen_w / 2.0 - 60.0, button_y, 1.0),
+            UiElement,
+            SongListElement,
+        ));
+    }
+}
+
+/// Move the keyboard cursor through the song list with the navigate keys, wrapping
+/// at the ends and scrolling to keep it in view, and play its song with the select key
+pub fn handle_song_keyboard(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<GameStateResource>,
+    mut selection_state: ResMut<SongSelectionState>,
+    analytics: Res<Analytics>,
+    config: Res<GameConfig>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let songs = selection_state.visible_songs(&game_state.songs, &analytics);
+    let keys = &config.key_bindings;
+    let step = if keyboard.just_pressed(keys.navigate_up_key()) {
+        -1
+    } else if keyboard.just_pressed(keys.navigate_down_key()) {
+        1
+    } else {
+        0
+    };
+
+    // A filter or sort change can leave the cursor past the end; only write when
+    // something moves so the list isn't redrawn every frame
+    let index = selection_state.moved_index(step, songs.len());
+    let scroll = selection_state.scroll_for(index, song_rows_shown(window.height()));
+    if index != selection_state.selected_index || scroll != selection_state.scroll_pos {
+        selection_state.selected_index = index;
+        selection_state.scroll_pos = scroll;
+    }
+    let Some((song, _)) = songs.get(index) else {
+        return;
+    };
+    if step != 0 && selection_state.selected_song.as_ref() != Some(song) {
+        selection_state.selected_song = Some(song.clone());
+    }
+
+    if keyboard.just_pressed(keys.select_key()) {
+        game_state.selected_song = song.clone();
+        game_state.practice = None;
+        next_state.set(AppState::Playing);
+    }
+}
+
+/// Handle song selection interactions. Hovering a song shows its leaderboard
+pub fn handle_song_selection(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<GameStateResource>,
+    mut selection_state: ResMut<SongSelectionState>,
+    query: Query<(&Transform, &SongButton), With<Text2d>>,
+    windows: Query<&Window>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+) {
+    if let Ok(window) = windows.get_single() {
+        if let Some(cursor_pos) = window.cursor_position() {
+            let world_x = cursor_pos.x - window.width() / 2.0;
+            let world_y = window.height() / 2.0 - cursor_pos.y;
+
+            for (transform, button) in query.iter() {
+                let rect = Rect::from_center_size(
+                    transform.translation.truncate(),
+                    Vec2::new(400.0, SONG_ENTRY_HEIGHT),
+                );
+
+                if rect.contains(Vec2::new(world_x, world_y)) {
+                    if selection_state.selected_song.as_ref() != Some(&button.song_path) {
+                        selection_state.selected_song = Some(button.song_path.clone());
+                    }
+                    if mouse_input.just_pressed(MouseButton::Left) {
+                        game_state.selected_song = button.song_path.clone();
+                        game_state.practice = None;
+                        next_state.set(AppState::Playing);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Marker for the hovered song's leaderboard panel, rebuilt on change
+#[derive(Component)]
+pub struct LeaderboardElement;
+
+/// A filter chip on the song leaderboard
+#[derive(Component)]
+pub struct LeaderboardChip(pub FilterChip);
+
+/// Width of the song leaderboard panel
+const LEADERBOARD_WIDTH: f32 = 400.0;
+
+/// Size of a leaderboard filter chip
+const CHIP_SIZE: Vec2 = Vec2::new(92.0, 22.0);
+
+/// Gap between leaderboard filter chips
+const CHIP_GAP: f32 = 6.0;
+
+/// Center of the leaderboard panel's title; the panel hangs below it
+fn leaderboard_origin(screen_w: f32, screen_h: f32) -> Vec2 {
+    Vec2::new(screen_w * 0.2, screen_h / 2.0 - screen_h * 0.2)
+}
+
+/// Clicking a filter chip narrows the hovered song's leaderboard
+pub fn handle_leaderboard_chips(
+    mut selection_state: ResMut<SongSelectionState>,
+    chips: Query<(&Transform, &LeaderboardChip)>,
+    windows: Query<&Window>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+) {
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let world = Vec2::new(
+        cursor_pos.x - window.width() / 2.0,
+        window.height() / 2.0 - cursor_pos.y,
+    );
+    let clicked = chips.iter().find(|(transform, _)| {
+        Rect::from_center_size(transform.translation.truncate(), CHIP_SIZE).contains(world)
+    });
+    if let Some((_, LeaderboardChip(chip))) = clicked {
+        if !chip.is_selected(&selection_state.score_filter) {
+            chip.apply(&mut selection_state.score_filter);
+        }
+    }
+}
+
+/// Draw the hovered song's local leaderboard: filter chips for the seasons, modifier
+/// combinations and speeds it has scores for, the best scores passing the filter and
+/// where the last play ranks among them
+#[allow(clippy::too_many_arguments)]
+pub fn draw_song_leaderboard(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    selection_state: Res<SongSelectionState>,
+    analytics: Res<Analytics>,
+    config: Res<GameConfig>,
+    calendar: Res<SeasonCalendar>,
+    existing: Query<Entity, With<LeaderboardElement>>,
+) {
+    if !selection_state.is_changed() && !analytics.is_changed() && !calendar.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(song) = &selection_state.selected_song else {
+        return;
+    };
+
+    let origin = leaderboard_origin(window.width(), window.height());
+    let left = origin.x - LEADERBOARD_WIDTH / 2.0;
+    let line = |commands: &mut Commands, text: String, size: f32, color: Color, y: f32| {
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: size,
+                ..default()
+            },
+            TextColor(color),
+            bevy::sprite::Anchor::CenterLeft,
+            Transform::from_xyz(left, y, 1.0),
+            UiElement,
+            LeaderboardElement,
+        ));
+    };
+
+    let name = std::path::Path::new(song)
+        .file_stem()
+        .map_or_else(|| song.clone(), |stem| stem.to_string_lossy().into_owned());
+    line(
+        &mut commands,
+        format!("Local scores: {}", name),
+        18.0,
+        NEON_CYAN,
+        origin.y,
+    );
+
+    let scores = song_scores(&analytics, song);
+    let current = calendar.current().id;
+    let signatures: Vec<&PlaySignature> = scores.iter().map(|session| &session.signature).collect();
+    let (mod_chips, speed_chips) = filter_chips(&signatures);
+    let per_row = ((LEADERBOARD_WIDTH + CHIP_GAP) / (CHIP_SIZE.x + CHIP_GAP)).floor() as usize;
+    let mut y = origin.y - 30.0;
+    for chips in [season_chips(&scores, &current), mod_chips, speed_chips] {
+        for (i, chip) in chips.into_iter().enumerate() {
+            if i > 0 && i % per_row == 0 {
+                y -= CHIP_SIZE.y + CHIP_GAP;
+            }
+            let x = left + CHIP_SIZE.x / 2.0 + (i % per_row) as f32 * (CHIP_SIZE.x + CHIP_GAP);
+            let selected = chip.is_selected(&selection_state.score_filter);
+            let background = if selected {
+                NEON_PINK.with_alpha(0.6)
+            } else {
+                Color::srgba(1.0, 1.0, 1.0, 0.08)
+            };
+            commands
+                .spawn((
+                    Sprite {
+                        color: background,
+                        custom_size: Some(CHIP_SIZE),
+                        ..default()
+                    },
+                    Transform::from_xyz(x, y, 1.0),
+                    UiElement,
+                    LeaderboardElement,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text2d::new(chip.label()),
+                        TextFont {
+                            font: assets.cyberpunk_font.clone(),
+                            font_size: 12.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                        Transform::from_xyz(0.0, 0.0, 0.05),
+                    ));
+                })
+                .insert(LeaderboardChip(chip));
+        }
+        y -= CHIP_SIZE.y + CHIP_GAP;
+    }
+
+    let filter = &selection_state.score_filter;
+    let shown = filter_scores(&scores, filter, &current);
+    y -= 10.0;
+    if shown.is_empty() {
+        let in_season = scores
+            .iter()
+            .any(|session| filter.season.matches(&session.season_id(), &current));
+        let message = if scores.is_empty() {
+            "No scores yet"
+        } else if !in_season && filter.season == SeasonFilter::Current {
+            "No scores this season yet"
+        } else if !in_season {
+            "No scores in this season"
+        } else {
+            "No scores with these mods yet"
+        };
+        line(
+            &mut commands,
+            message.to_string(),
+            16.0,
+            Color::srgba(1.0, 1.0, 1.0, 0.5),
+            y,
+        );
+        return;
+    }
+
+    for (i, session) in shown.iter().take(LEADERBOARD_ROWS).enumerate() {
+        line(
+            &mut commands,
+            format!(
+                "#{}  {}  {:.2}%  {}  {}",
+                i + 1,
+                format_score(session.score, config.hud_layout.score_style),
+                session.accuracy,
+                session.grade.as_str(),
+                session.signature.label()
+            ),
+            14.0,
+            get_grade_color(session.grade.as_str()),
+            y,
+        );
+        y -= 22.0;
+    }
+
+    // Every local score is the player's own, so "your rank" is where the last play
+    // landed within the filter
+    let last = scores.iter().max_by_key(|session| session.session_id);
+    let rank_line = match last.and_then(|last| rank_of(&shown, last.session_id)) {
+        Some(rank) => format!("Your last play: #{} of {}", rank, shown.len()),
+        None => format!(
+            "Your last play isn't in this filter ({} shown)",
+            shown.len()
+        ),
+    };
+    line(&mut commands, rank_line, 14.0, NEON_YELLOW, y - 8.0);
+}
+
+/// Setup loading screen
+pub fn setup_loading_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+    if windows.get_single().is_ok() {
+        commands.spawn((
+            Text2d::new("Loading..."),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: CYBERPUNK_FONT_SIZE,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, 0.0, 1.0),
+            UiElement,
+            LoadingText,
+        ));
+    }
+}
+
+#[derive(Component)]
+pub struct LoadingText;
+
+/// Setup ready to play countdown
+pub fn setup_ready_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+    if windows.get_single().is_ok() {
+        commands.spawn((
+            Text2d::new("Starting in 5"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: FONT_SIZE as f32,
+                ..default()
+            },
+            TextColor(NEON_GREEN),
+            Transform::from_xyz(0.0, 0.0, 1.0),
+            UiElement,
+            CountdownText,
+        ));
+    }
+}
+
+#[derive(Component)]
+pub struct CountdownText;
+
+/// Update countdown
+pub fn update_countdown(
+    mut query: Query<&mut Text2d, With<CountdownText>>,
+    ready_data: Res<ReadyToPlayData>,
+) {
+    let elapsed = ready_data.ready_time.elapsed().as_secs_f32();
+    let remaining = (COUNTDOWN_DURATION - elapsed as f64).max(0.0) as i32;
+
+    for mut text in query.iter_mut() {
+        text.0 = if ready_data.attempt > 1 {
+            format!("Attempt #{}\nStarting in {}", ready_data.attempt, remaining)
+        } else {
+            format!("Starting in {}", remaining)
+        };
+    }
+}
+
+/// Draw the gameplay HUD at the positions in the layout
+pub fn draw_hud(
+    commands: &mut Commands,
+    values: &HudValues,
+    layout: &HudLayout,
+    screen: Vec2,
+    assets: &GameAssets,
+) {
+    for element in HudElement::all() {
+        if layout.get(element).visible {
+            draw_hud_element(commands, element, values, layout, screen, 1.0, assets);
+        }
+    }
+}
+
+/// Draw one HUD element; `alpha` dims it (hidden elements in the layout editor).
+/// Returns the spawned entity, or None if the element has nothing to show
+pub fn draw_hud_element(
+    commands: &mut Commands,
+    element: HudElement,
+    values: &HudValues,
+    layout: &HudLayout,
+    screen: Vec2,
+    alpha: f32,
+    assets: &GameAssets,
+) -> Option<Entity> {
+    let (text, font_size, color) = match element {
+        HudElement::Combo => {
+            if values.combo == 0 {
+                return None;
+            }
+            let combo = values.combo;
+            let combo_size = if combo >= 100 {
+                48.0
+            } else if combo >= 50 {
+                40.0
+            } else if combo >= 25 {
+                36.0
+            } else {
+                32.0
+            };
+
+            let combo_color = if combo >= 100 {
+                Color::srgba(1.0, 0.84, 0.0, 1.0)
+            } else if combo >= 50 {
+                NEON_PINK
+            } else if combo >= 25 {
+                NEON_PURPLE
+            } else {
+                NEON_BLUE
+            };
+            (format!("{}x", combo), combo_size, combo_color)
+        }
+        HudElement::Score => {
+            let mut text = String::from("Score: ");
+            write_score(&mut text, values.score, layout.score_style);
+            (text, SCORE_FONT_SIZE, NEON_BLUE)
+        }
+        HudElement::MaxCombo => (
+            format!("Max Combo: {}", values.max_combo),
+            20.0,
+            Color::srgba(1.0, 1.0, 1.0, 0.6),
+        ),
+        HudElement::Accuracy => match values.accuracy {
+            Some(accuracy) => {
+                let color = match values.grade {
+                    Some(grade) => {
+                        let (r, g, b) = grade.color();
+                        Color::srgb(r, g, b)
+                    }
+                    None => NEON_CYAN,
+                };
+                (format!("{:.2}%", accuracy), 22.0, color)
+            }
+            None => return None,
+        },
+        HudElement::Lives => match values.lives {
+            Some(lives) => (format!("Lives: {}", lives), 22.0, NEON_ORANGE),
+            None => return None,
+        },
+        HudElement::Health => {
+            let health = values.health?;
+            return Some(draw_health_bar(commands, health, layout, screen, alpha));
+        }
+        HudElement::HitErrorBar => {
+            // In gameplay the bar is drawn from the live hits by the gameplay systems
+            if !values.hit_error_preview {
+                return None;
+            }
+            return Some(draw_hit_error_bar(
+                commands,
+                &HitErrors::preview(),
+                0.0,
+                1.0,
+                layout,
+                screen,
+                alpha,
+            ));
+        }
+    };
+
+    let scale = layout.get(element).scale;
+    let position = layout.position(element, screen);
+    let entity = commands
+        .spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: font_size * scale,
+                ..default()
+            },
+            TextColor(color.with_alpha(color.alpha() * alpha)),
+            Transform::from_xyz(position.x, position.y, 1.0),
+            UiElement,
+        ))
+        .id();
+
+    if element == HudElement::Accuracy && layout.accuracy_bar {
+        if let Some(accuracy) = values.accuracy {
+            draw_accuracy_bar(commands, entity, accuracy, color, scale, alpha);
+        }
+    }
+    Some(entity)
+}
+
+/// Health bar, turning from green through yellow to orange as it empties
+fn draw_health_bar(
+    commands: &mut Commands,
+    health: f32,
+    layout: &HudLayout,
+    screen: Vec2,
+    alpha: f32,
+) -> Entity {
+    let size = layout.size(HudElement::Health);
+    let position = layout.position(HudElement::Health, screen);
+    let color = if health > 0.5 {
+        NEON_GREEN
+    } else if health > 0.25 {
+        NEON_YELLOW
+    } else {
+        NEON_ORANGE
+    };
+
+    commands
+        .spawn((
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, 0.15 * alpha),
+                custom_size: Some(size),
+                ..default()
+            },
+            Transform::from_xyz(position.x, position.y, 1.0),
+            UiElement,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Sprite {
+                    color: color.with_alpha(alpha),
+                    custom_size: Some(Vec2::new(size.x * health.clamp(0.0, 1.0), size.y)),
+                    anchor: bevy::sprite::Anchor::CenterLeft,
+                    ..default()
+                },
+                Transform::from_xyz(-size.x / 2.0, 0.0, 0.01),
+                UiElement,
+            ));
+        })
+        .id()
+}
+
+/// Thin bar under the accuracy readout filling toward 100%, with ticks at the S and SS
+/// thresholds so it is clear how much room is left
+fn draw_accuracy_bar(
+    commands: &mut Commands,
+    readout: Entity,
+    accuracy: f32,
+    color: Color,
+    scale: f32,
+    alpha: f32,
+) {
+    let width = 120.0 * scale;
+    let height = 4.0 * scale;
+    let y = -15.0 * scale;
+    let left = -width / 2.0;
+
+    commands.entity(readout).with_children(|parent| {
+        parent.spawn((
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, 0.15 * alpha),
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            Transform::from_xyz(0.0, y, -0.01),
+            UiElement,
+        ));
+        parent.spawn((
+            Sprite {
+                color: color.with_alpha(color.alpha() * alpha),
+                custom_size: Some(Vec2::new(width * (accuracy / 100.0).clamp(0.0, 1.0), height)),
+                anchor: bevy::sprite::Anchor::CenterLeft,
+                ..default()
+            },
+            Transform::from_xyz(left, y, 0.0),
+            UiElement,
+        ));
+        for threshold in [GRADE_S_ACCURACY, GRADE_SS_ACCURACY] {
+            parent.spawn((
+                Sprite {
+                    color: Color::srgba(1.0, 1.0, 1.0, 0.8 * alpha),
+                    custom_size: Some(Vec2::new(1.0, height * 2.0)),
+                    ..default()
+                },
+                Transform::from_xyz(left + width * threshold / 100.0, y, 0.01),
+                UiElement,
+            ));
+        }
+    });
+}
+
+/// Tint the playfield while kiai is active; `kiai` is the effect strength (0.0 - 1.0)
+pub fn draw_kiai_background(commands: &mut Commands, kiai: f32, screen: Vec2) {
+    if kiai <= 0.0 {
+        return;
+    }
+    commands.spawn((
+        Sprite {
+            color: NEON_PURPLE.with_alpha(KIAI_BACKGROUND_ALPHA * kiai),
+            custom_size: Some(screen),
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, 0.05),
+        UiElement,
+    ));
+}
+
+/// Draw the gameplay debug overlay in the bottom-right corner
+pub fn draw_debug_overlay(commands: &mut Commands, lines: &[String], screen: Vec2, assets: &GameAssets) {
+    let corner = Vec2::new(screen.x / 2.0 - 20.0, -screen.y / 2.0 + 20.0);
+    for (i, line) in lines.iter().rev().enumerate() {
+        commands.spawn((
+            Text2d::new(line.clone()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
+            bevy::sprite::Anchor::BottomRight,
+            Transform::from_xyz(corner.x, corner.y + i as f32 * 20.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Draw the skip-intro prompt above the bottom edge of the playfield
+pub fn draw_intro_skip_prompt(
+    commands: &mut Commands,
+    key: &str,
+    screen: Vec2,
+    assets: &GameAssets,
+) {
+    commands.spawn((
+        Text2d::new(format!("Press {} to skip intro", key)),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(NEON_CYAN),
+        Transform::from_xyz(0.0, -screen.y / 2.0 + 80.0, 1.0),
+        UiElement,
+    ));
+}
+
+/// Rows of a local room's ranking from `top_left` down, the player's row in cyan
+fn spawn_standings(
+    commands: &mut Commands,
+    standings: &[Standing],
+    style: ScoreStyle,
+    top_left: Vec2,
+    font_size: f32,
+    assets: &GameAssets,
+) {
+    for (i, standing) in standings.iter().enumerate() {
+        let color = if standing.bot {
+            Color::srgba(1.0, 1.0, 1.0, 0.7)
+        } else {
+            NEON_CYAN
+        };
+        commands.spawn((
+            Text2d::new(format!(
+                "{}. {}  {}  {:.1}%",
+                i + 1,
+                standing.name,
+                format_score(standing.score, style),
+                standing.accuracy
+            )),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size,
+                ..default()
+            },
+            TextColor(color),
+            bevy::sprite::Anchor::CenterLeft,
+            Transform::from_xyz(top_left.x, top_left.y - i as f32 * font_size * 1.4, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Live ranking of the player and the local room's bots, along the left edge
+pub fn draw_room_scoreboard(
+    commands: &mut Commands,
+    standings: &[Standing],
+    style: ScoreStyle,
+    screen: Vec2,
+    assets: &GameAssets,
+) {
+    let top_left = Vec2::new(-screen.x / 2.0 + 20.0, screen.y * 0.15);
+    spawn_standings(commands, standings, style, top_left, 16.0, assets);
+}
+
+/// Dim the paused play and list the pause menu options, the highlighted one in pink
+pub fn draw_pause_menu(
+    commands: &mut Commands,
+    selected: PauseOption,
+    screen: Vec2,
+    assets: &GameAssets,
+) {
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(0.0, 0.0, 0.0, 0.6),
+            custom_size: Some(screen),
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, 5.0),
+        UiElement,
+    ));
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+    commands.spawn((
+        Text2d::new("Paused"),
+        font(36.0),
+        TextColor(NEON_CYAN),
+        Transform::from_xyz(0.0, 120.0, 6.0),
+        UiElement,
+    ));
+    for (i, option) in PauseOption::ALL.iter().enumerate() {
+        let (text, color) = if *option == selected {
+            (format!("> {}", option.label()), NEON_PINK)
+        } else {
+            (option.label().to_string(), Color::WHITE)
+        };
+        commands.spawn((
+            Text2d::new(text),
+            font(24.0),
+            TextColor(color),
+            Transform::from_xyz(0.0, 40.0 - i as f32 * 50.0, 6.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Setup the failed screen: the song, how far the play got and its score
+pub fn setup_failed_ui(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    failed: Res<FailedData>,
+    config: Res<GameConfig>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let scr_height = window.height();
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+
+    commands.spawn((
+        Text2d::new("Failed"),
+        font(48.0),
+        TextColor(NEON_ORANGE),
+        Transform::from_xyz(0.0, scr_height * 0.3, 1.0),
+        UiElement,
+    ));
+    commands.spawn((
+        Text2d::new(format!(
+            "{} - failed at {} ({:.0}% of the map)",
+            song_name(&failed.song_name),
+            format_time(failed.ended_at),
+            failed.progress * 100.0
+        )),
+        font(16.0),
+        TextColor(NEON_ORANGE),
+        Transform::from_xyz(0.0, scr_height * 0.3 - 40.0, 1.0),
+        UiElement,
+    ));
+    commands.spawn((
+        Text2d::new(format!(
+            "Score: {}",
+            format_score(failed.score, config.hud_layout.score_style)
+        )),
+        font(32.0),
+        TextColor(NEON_BLUE),
+        Transform::from_xyz(0.0, scr_height * 0.1, 1.0),
+        UiElement,
+    ));
+}
+
+/// Marker for the failed screen's options, redrawn when the highlight moves
+#[derive(Component)]
+pub struct FailedMenuElement;
+
+/// List the failed screen's options, the highlighted one in pink
+pub fn draw_failed_menu(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    failed: Res<FailedData>,
+    existing: Query<Entity, With<FailedMenuElement>>,
+) {
+    if !failed.is_changed() {
+        return;
+    }
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (i, option) in failed.options().iter().enumerate() {
+        let (text, color) = if *option == failed.selected {
+            (format!("> {}", option.label()), NEON_PINK)
+        } else {
+            (option.label().to_string(), Color::WHITE)
+        };
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(0.0, -60.0 - i as f32 * 50.0, 1.0),
+            UiElement,
+            FailedMenuElement,
+        ));
+    }
+}
+
+/// Marker for HUD layout editor elements that are redrawn on change
+#[derive(Component)]
+pub struct HudEditorElement;
+
+/// Setup the static parts of the HUD layout editor
+pub fn setup_hud_editor_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+
+        // Mock playfield behind the HUD
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(0.6, 0.0, 1.0, 0.08),
+                custom_size: Some(Vec2::new(window.width() * 0.6, screen_h * 0.6)),
+                ..default()
+            },
+            Transform::from_xyz(0.0, 0.0, 0.0),
+            UiElement,
+        ));
+
+        commands.spawn((
+            Text2d::new("HUD Layout"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 28.0,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, screen_h * 0.25, 1.0),
+            UiElement,
+        ));
+
+        commands.spawn((
+            Text2d::new(
+                "Drag: move | Tab: select | V: show/hide | +/-: scale | R: reset layout | ESC: save and back",
+            ),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(0.0, -screen_h * 0.25, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Draw the HUD preview with sample values, outlining the selected element
+pub fn draw_hud_editor(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    config: Res<GameConfig>,
+    editor_state: Res<HudEditorState>,
+    existing: Query<Entity, With<HudEditorElement>>,
+) {
+    if !config.is_changed() && !editor_state.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let screen = Vec2::new(window.width(), window.height());
+    let layout = &config.hud_layout;
+    let values = HudValues::preview();
+    let selected = editor_state.selected_element();
+
+    for element in HudElement::all() {
+        let element_layout = layout.get(element);
+        let position = layout.position(element, screen);
+        let size = layout.size(element);
+
+        // Element box, brighter when selected
+        let box_color = if element == selected {
+            NEON_PINK.with_alpha(0.35)
+        } else {
+            NEON_PURPLE.with_alpha(0.15)
+        };
+        commands.spawn((
+            Sprite {
+                color: box_color,
+                custom_size: Some(size),
+                ..default()
+            },
+            Transform::from_xyz(position.x, position.y, 0.5),
+            UiElement,
+            HudEditorElement,
+        ));
+
+        let alpha = if element_layout.visible { 1.0 } else { 0.25 };
+        if let Some(entity) =
+            draw_hud_element(&mut commands, element, &values, layout, screen, alpha, &assets)
+        {
+            commands.entity(entity).insert(HudEditorElement);
+        }
+    }
+
+    // Selected element details
+    let selected_layout = layout.get(selected);
+    commands.spawn((
+        Text2d::new(format!(
+            "{} | anchor {:?} | offset ({:.0}, {:.0}) | scale {:.1}x | {}",
+            selected.display_name(),
+            selected_layout.anchor,
+            selected_layout.offset.x,
+            selected_layout.offset.y,
+            selected_layout.scale,
+            if selected_layout.visible { "shown" } else { "hidden" }
+        )),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(NEON_CYAN),
+        Transform::from_xyz(0.0, -window.height() * 0.25 - 24.0, 1.0),
+        UiElement,
+        HudEditorElement,
+    ));
+}
+
+/// Draw floating texts
+pub fn draw_floating_texts_bevy(
+    commands: &mut Commands,
+    floating_texts: &mut Vec<FloatingText>,
+    elapsed: f64,
+    assets: &GameAssets,
+) {
+    let mut i = 0;
+    while i < floating_texts.len() {
+        let text = &floating_texts[i];
+        let time_since_spawn = elapsed - text.spawn_time;
+
+        if time_since_spawn >= text.duration {
+            floating_texts.swap_remove(i);
+            continue;
+        }
+
+        let y_offset = (time_since_spawn * 30.0) as f32;
+        let alpha = 1.0 - ((time_since_spawn / text.duration) as f32);
+        let color = Color::srgba(text.color.0, text.color.1, text.color.2, alpha);
+
+        commands.spawn((
+            Text2d::new(text.text.clone()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 24.0 * text.scale,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(text.position.x, text.position.y - y_offset, 1.0),
+            UiElement,
+        ));
+
+        i += 1;
+    }
+}
+
+/// Setup settings UI
+pub fn setup_settings_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+        let screen_w = window.width();
+
+        commands.spawn((
+            Text2d::new("Settings"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            UiElement,
+        ));
+
+        commands.spawn((
+            Text2d::new("Press ESC to go back"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Marker for settings tab bar entities, rebuilt when the tab changes
+#[derive(Component)]
+pub struct SettingsTabElement;
+
+/// Draw the settings tab bar and the current tab's editable rows, or the settings
+/// matching a search; machine-local tabs carry a small marker
+#[allow(clippy::too_many_arguments)]
+pub fn draw_settings_tabs(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    settings_state: Res<SettingsState>,
+    config: Res<GameConfig>,
+    library: Res<LibraryScan>,
+    online: Res<OnlineServices>,
+    existing: Query<Entity, With<SettingsTabElement>>,
+) {
+    if !settings_state.is_changed()
+        && !config.is_changed()
+        && !library.is_changed()
+        && !online.is_changed()
+    {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let tabs = SettingsTab::all();
+    let tab_width = window.width() / tabs.len() as f32;
+    let tab_y = window.height() / 2.0 - 110.0;
+
+    for (i, (tab, name)) in tabs.iter().enumerate() {
+        let x = -window.width() / 2.0 + tab_width * (i as f32 + 0.5);
+        let color = if *tab == settings_state.current_tab {
+            NEON_PINK
+        } else {
+            Color::srgba(1.0, 1.0, 1.0, 0.6)
+        };
+        let label = if tab.is_machine_local() {
+            format!("{} [PC]", name)
+        } else {
+            name.to_string()
+        };
+
+        commands.spawn((
+            Text2d::new(label),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(x, tab_y, 1.0),
+            UiElement,
+            SettingsTabElement,
+        ));
+    }
+
+    commands.spawn((
+        Text2d::new("[PC] = stored on this machine only, not synced with your profile"),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+        Transform::from_xyz(0.0, tab_y - 24.0, 1.0),
+        UiElement,
+        SettingsTabElement,
+    ));
+
+    // While searching, matches from every tab replace the current tab's rows
+    let mut y = tab_y - 80.0;
+    let (title, rows, selected_index) = if let Some(search) = &settings_state.search {
+        draw_text_box(
+            &mut commands,
+            &search.input,
+            Vec2::new(0.0, tab_y - 60.0),
+            360.0,
+            true,
+            assets.cyberpunk_font.clone(),
+        )
+        .insert(SettingsTabElement);
+        y -= 24.0;
+        let results = search.results();
+        let title = if search.input.text().trim().is_empty() {
+            "Type to search every tab"
+        } else if results.is_empty() {
+            "No matching settings"
+        } else {
+            "Search Results"
+        };
+        let rows: Vec<String> = results
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}  [{}]",
+                    entry.row.label(&config, &settings_state, &library, &online.maintenance),
+                    entry.tab.name()
+                )
+            })
+            .collect();
+        (title, rows, search.selected)
+    } else {
+        let title = match settings_state.current_tab {
+            SettingsTab::General => "Map Generation / Data",
+            SettingsTab::Gameplay => "Gameplay",
+            SettingsTab::Theme => "Theme Presets",
+            SettingsTab::Practice => "Practice Defaults",
+            SettingsTab::Audio => "Audio",
+            SettingsTab::Display => "Display",
+            SettingsTab::KeyBindings => "Key Bindings",
+        };
+        let rows: Vec<String> = tab_entries(settings_state.current_tab)
+            .map(|entry| entry.row.label(&config, &settings_state, &library, &online.maintenance))
+            .chain(
+                library
+                    .health
+                    .issues
+                    .iter()
+                    .take(MAX_LISTED_ISSUES)
+                    .filter(|_| settings_state.current_tab == SettingsTab::General)
+                    .map(|issue| format!("  {}", issue.label())),
+            )
+            .collect();
+        (title, rows, settings_state.selected_index)
+    };
+
+    commands.spawn((
+        Text2d::new(title),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(NEON_BLUE),
+        Transform::from_xyz(0.0, y, 1.0),
+        UiElement,
+        SettingsTabElement,
+    ));
+
+    for (i, label) in rows.into_iter().enumerate() {
+        y -= 32.0;
+        let selected = i == selected_index;
+        commands.spawn((
+            Text2d::new(if selected {
+                format!("< {} >", label)
+            } else {
+                label
+            }),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(if selected { NEON_PINK } else { Color::WHITE }),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            SettingsTabElement,
+        ));
+    }
+
+    // Library issues past the listed rows are only counted
+    let unlisted = match settings_state.current_tab {
+        SettingsTab::General if settings_state.search.is_none() => {
+            library.health.issues.len().saturating_sub(MAX_LISTED_ISSUES)
+        }
+        _ => 0,
+    };
+    if unlisted > 0 {
+        y -= 28.0;
+        commands.spawn((
+            Text2d::new(format!("...and {} more", unlisted)),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            SettingsTabElement,
+        ));
+    }
+
+    // Imported theme next to the current one, waiting for confirmation
+    if let Some(preset) = &settings_state.theme_preview {
+        y -= 48.0;
+        let columns = [
+            ("Current", &config.theme, -160.0),
+            (preset.name.as_str(), &preset.theme, 160.0),
+        ];
+        for (label, theme, x) in columns {
+            draw_theme_swatches(&mut commands, &assets, label, theme, Vec2::new(x, y));
+        }
+        y -= THEME_PREVIEW_HEIGHT;
+    }
+
+    // Typed confirmation prompt and data action results
+    let prompt = match &settings_state.delete_confirmation {
+        Some(_) => Some((
+            format!(
+                "Type {} and press Enter to wipe all analytics (ESC cancels)",
+                DELETE_ANALYTICS_CONFIRMATION
+            ),
+            NEON_ORANGE,
+        )),
+        None if settings_state.theme_preview.is_some() => Some((
+            "Press Enter to apply this theme (ESC cancels)".to_string(),
+            NEON_ORANGE,
+        )),
+        None if settings_state.locate_audio.is_some() => Some((
+            "Type the path of the audio file and press Enter (ESC cancels)".to_string(),
+            NEON_ORANGE,
+        )),
+        None if settings_state.waiting_for_key.is_some() => {
+            Some(("Press the key to bind".to_string(), NEON_CYAN))
+        }
+        None => match &settings_state.pending_binding {
+            // Reserved and quick-retry keys get the error color
+            Some(pending) => Some((
+                pending.prompt(&config.key_bindings),
+                if pending.is_strong() {
+                    NEON_ORANGE
+                } else {
+                    NEON_YELLOW
+                },
+            )),
+            None => settings_state
+                .notice
+                .clone()
+                .map(|notice| (notice, NEON_YELLOW)),
+        },
+    };
+    if let Some((text, color)) = prompt {
+        y -= 32.0;
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            SettingsTabElement,
+        ));
+    }
+    if let Some(input) = &settings_state.delete_confirmation {
+        y -= 40.0;
+        draw_text_box(
+            &mut commands,
+            input,
+            Vec2::new(0.0, y),
+            240.0,
+            true,
+            assets.cyberpunk_font.clone(),
+        )
+        .insert(SettingsTabElement);
+    }
+    if let Some((_, input)) = &settings_state.locate_audio {
+        y -= 40.0;
+        draw_text_box(
+            &mut commands,
+            input,
+            Vec2::new(0.0, y),
+            480.0,
+            true,
+            assets.cyberpunk_font.clone(),
+        )
+        .insert(SettingsTabElement);
+    }
+
+    let issue_selected = settings_state.current_tab == SettingsTab::General
+        && settings_state.selected_index >= tab_entries(SettingsTab::General).count();
+    commands.spawn((
+        Text2d::new(if settings_state.search.is_some() {
+            "Up/Down: select | Left/Right: adjust | Enter: toggle | Tab: go to tab | ESC: clear"
+        } else if issue_selected {
+            "Enter: locate audio / reload | R: reload | Delete twice: remove the file"
+        } else if settings_state.current_tab == SettingsTab::KeyBindings {
+            "Up/Down: select | Enter: bind a key | / or Ctrl+F: search"
+        } else {
+            "Up/Down: select | Left/Right: adjust | Enter: toggle | / or Ctrl+F: search"
+        }),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+        Transform::from_xyz(0.0, y - 30.0, 1.0),
+        UiElement,
+        SettingsTabElement,
+    ));
+}
+
+/// Height of a theme preview column below its title
+const THEME_PREVIEW_HEIGHT: f32 = 90.0;
+
+/// Draw a theme's color swatches and settings as a preview column centered on `at`
+fn draw_theme_swatches(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    label: &str,
+    theme: &ThemeConfig,
+    at: Vec2,
+) {
+    commands.spawn((
+        Text2d::new(label.to_string()),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Transform::from_xyz(at.x, at.y, 1.0),
+        UiElement,
+        SettingsTabElement,
+    ));
+
+    let colors = [
+        &theme.primary_color,
+        &theme.secondary_color,
+        &theme.circle_color,
+    ];
+    for (i, hex) in colors.into_iter().enumerate() {
+        commands.spawn((
+            Sprite {
+                color: hex_to_color(hex).unwrap_or(Color::WHITE),
+                custom_size: Some(Vec2::new(36.0, 36.0)),
+                ..default()
+            },
+            Transform::from_xyz(at.x + (i as f32 - 1.0) * 48.0, at.y - 36.0, 1.0),
+            UiElement,
+            SettingsTabElement,
+        ));
+    }
+
+    let background = BackgroundStyle::all()
+        .into_iter()
+        .find(|(style, _)| *style == theme.background_style)
+        .map_or("", |(_, name)| name);
+    let on_off = |value: bool| if value { "ON" } else { "OFF" };
+    commands.spawn((
+        Text2d::new(format!(
+            "{} | Circles {:.1}x | Particles {} | Shake {}",
+            background,
+            theme.circle_size,
+            on_off(theme.particles_enabled),
+            on_off(theme.screen_shake)
+        )),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
+        Transform::from_xyz(at.x, at.y - THEME_PREVIEW_HEIGHT + 16.0, 1.0),
+        UiElement,
+        SettingsTabElement,
+    ));
+}
+
+/// Setup the profile picker UI
+pub fn setup_profile_picker_ui(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+
+        commands.spawn((
+            Text2d::new("Who's Playing?"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            UiElement,
+        ));
+
+        commands.spawn((
+            Text2d::new(
+                "Up/Down: Select | Enter: Play as | N: New profile | Delete: Move to trash | ESC: Back",
+            ),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(0.0, -screen_h / 2.0 + 30.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Marker for profile picker rows, rebuilt when the picker changes
+#[derive(Component)]
+pub struct ProfilePickerElement;
+
+/// Draw the local profiles, the new profile field and any notice
+pub fn draw_profile_picker(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    picker: Res<ProfilePickerState>,
+    config: Res<GameConfig>,
+    existing: Query<Entity, With<ProfilePickerElement>>,
+) {
+    if !picker.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mut y = window.height() / 2.0 - 140.0;
+    for (i, name) in picker.profiles.iter().enumerate() {
+        let selected = i == picker.selected;
+        let mut label = name.clone();
+        if *name == config.active_profile {
+            label.push_str("  (playing)");
+        }
+        if name == DEFAULT_PROFILE {
+            label.push_str("  [shared]");
+        }
+        commands.spawn((
+            Text2d::new(if selected {
+                format!("< {} >", label)
+            } else {
+                label
+            }),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(if selected { NEON_PINK } else { Color::WHITE }),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            ProfilePickerElement,
+        ));
+        y -= 36.0;
+    }
+
+    if let Some(input) = &picker.new_name {
+        y -= 12.0;
+        commands.spawn((
+            Text2d::new("New profile name (Enter to create, ESC to cancel)"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NEON_BLUE),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            ProfilePickerElement,
+        ));
+        y -= 32.0;
+        draw_text_box(
+            &mut commands,
+            input,
+            Vec2::new(0.0, y),
+            300.0,
+            true,
+            assets.cyberpunk_font.clone(),
+        )
+        .insert(ProfilePickerElement);
+        y -= 36.0;
+    }
+
+    let notice = match &picker.pending_delete {
+        Some(name) => Some(format!("Press Delete again to move {} to the trash", name)),
+        None => picker.notice.clone(),
+    };
+    if let Some(notice) = notice {
+        commands.spawn((
+            Text2d::new(notice),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(NEON_YELLOW),
+            Transform::from_xyz(0.0, y - 12.0, 1.0),
+            UiElement,
+            ProfilePickerElement,
+        ));
+    }
+}
+
+/// Setup the account screen UI
+pub fn setup_account_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+
+        commands.spawn((
+            Text2d::new("Account"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Marker for the account form, rebuilt when it or the signed-in player changes
+#[derive(Component)]
+pub struct AccountElement;
+
+/// Draw the sign-in form, or who is signed in
+pub fn draw_account(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    form: Res<AccountFormState>,
+    online: Res<OnlineServices>,
+    existing: Query<Entity, With<AccountElement>>,
+) {
+    if !form.is_changed() && !online.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let font = assets.cyberpunk_font.clone();
+    let text = |commands: &mut Commands, text: String, size: f32, color: Color, y: f32| {
+        commands.spawn((
+            Text2d::new(text),
+            TextFont {
+                font: font.clone(),
+                font_size: size,
+                ..default()
+            },
+            TextColor(color),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            AccountElement,
+        ));
+    };
+    let hint_y = -window.height() / 2.0 + 30.0;
+    let hint_color = Color::srgba(1.0, 1.0, 1.0, 0.5);
+
+    if let Some(user) = online.user() {
+        text(
+            &mut commands,
+            format!("Signed in as {}", user.username),
+            24.0,
+            NEON_GREEN,
+            40.0,
+        );
+        text(
+            &mut commands,
+            "L: Sign out | Enter/ESC: Back".to_string(),
+            14.0,
+            hint_color,
+            hint_y,
+        );
+        return;
+    }
+
+    let title = if form.registering {
+        "Create an account"
+    } else {
+        "Sign in"
+    };
+    text(
+        &mut commands,
+        title.to_string(),
+        24.0,
+        Color::WHITE,
+        window.height() / 2.0 - 110.0,
+    );
+
+    let mut y = window.height() / 2.0 - 160.0;
+    for &field in form.fields() {
+        text(&mut commands, field.label().to_string(), 16.0, NEON_BLUE, y);
+        y -= 32.0;
+        draw_text_box(
+            &mut commands,
+            form.input(field),
+            Vec2::new(0.0, y),
+            320.0,
+            field == form.focused,
+            font.clone(),
+        )
+        .insert(AccountElement);
+        y -= 48.0;
+    }
+
+    if let Some(error) = &form.error {
+        text(&mut commands, error.clone(), 16.0, NEON_ORANGE, y);
+    } else if let Some(notice) = &form.notice {
+        text(&mut commands, notice.clone(), 16.0, NEON_GREEN, y);
+    }
+
+    // Enter does nothing until every field is filled in
+    let (submit, switch) = if form.registering {
+        ("Enter: Create account", "F2: Sign in instead")
+    } else {
+        ("Enter: Sign in", "F2: Create an account")
+    };
+    text(
+        &mut commands,
+        format!("Tab: Next field | {} | {} | ESC: Back", submit, switch),
+        14.0,
+        hint_color,
+        hint_y,
+    );
+    if !form.can_submit() {
+        text(
+            &mut commands,
+            "Fill in every field to continue".to_string(),
+            14.0,
+            hint_color,
+            hint_y + 24.0,
+        );
+    }
+}
+
+/// Setup the online screen UI
+pub fn setup_online_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+
+        commands.spawn((
+            Text2d::new("Online"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Marker for the online screen's tabs and their contents, rebuilt when they change
+#[derive(Component)]
+pub struct OnlineElement;
+
+/// Spawn a left-aligned line of the online screen
+fn spawn_online_text(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    text: impl Into<String>,
+    size: f32,
+    color: Color,
+    at: Vec2,
+) {
+    commands.spawn((
+        Text2d::new(text),
+        TextFont {
+            font: font.clone(),
+            font_size: size,
+            ..default()
+        },
+        TextColor(color),
+        bevy::sprite::Anchor::CenterLeft,
+        Transform::from_xyz(at.x, at.y, 1.0),
+        UiElement,
+        OnlineElement,
+    ));
+}
+
+/// Draw the account line, the tab strip and the open tab
+pub fn draw_online(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    hub: Res<OnlineHubState>,
+    online: Res<OnlineServices>,
+    config: Res<GameConfig>,
+    existing: Query<Entity, With<OnlineElement>>,
+) {
+    if !hub.is_changed() && !online.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let font = &assets.cyberpunk_font;
+    let left = -window.width().min(900.0) / 2.0 + 40.0;
+    let top = window.height() / 2.0 - 110.0;
+    let muted = Color::srgba(1.0, 1.0, 1.0, 0.5);
+
+    let account = match online.user() {
+        Some(user) => format!("Signed in as {}  |  A: Account", user.username),
+        None => "Not signed in  |  A: Sign in or register".to_string(),
+    };
+    spawn_online_text(
+        &mut commands,
+        font,
+        account,
+        16.0,
+        NEON_BLUE,
+        Vec2::new(left, top),
+    );
+
+    let mut x = left;
+    for tab in OnlineTab::ALL {
+        let selected = tab == hub.tab;
+        // Friends carries the badge of pending requests
+        let label = match online.inbox.badge() {
+            Some(badge) if tab == OnlineTab::Friends => format!("{} ({})", tab.label(), badge),
+            _ => tab.label().to_string(),
+        };
+        let label = if selected {
+            format!("[{}]", label)
+        } else {
+            label
+        };
+        let color = if selected { NEON_PINK } else { Color::WHITE };
+        spawn_online_text(
+            &mut commands,
+            font,
+            label,
+            20.0,
+            color,
+            Vec2::new(x, top - 40.0),
+        );
+        x += 170.0;
+    }
+
+    let origin = Vec2::new(left, top - 90.0);
+    match hub.tab {
+        OnlineTab::Leaderboard => {
+            draw_online_leaderboard(&mut commands, font, &hub, &online, &config, origin)
+        }
+        OnlineTab::Friends => match &hub.comparison {
+            Some(view) => draw_online_comparison(&mut commands, font, view, &config, origin),
+            None => draw_online_friends(&mut commands, font, &hub, origin),
+        },
+        OnlineTab::Tournaments => {
+            draw_online_tournaments(&mut commands, font, &hub, &online, origin)
+        }
+        OnlineTab::Chat => draw_online_chat(&mut commands, font, &hub, &online, origin),
+        OnlineTab::Muted => draw_online_muted(&mut commands, font, &hub, origin),
+    }
+
+    let bottom = -window.height() / 2.0 + 30.0;
+    if let Some((message, failed)) = &hub.message {
+        let color = if *failed { NEON_ORANGE } else { NEON_GREEN };
+        spawn_online_text(
+            &mut commands,
+            font,
+            message.clone(),
+            16.0,
+            color,
+            Vec2::new(left, bottom + 30.0),
+        );
+    }
+    let hint = if hub.friend_name.is_some() {
+        "Enter: Send request | ESC: Cancel".to_string()
+    } else if hub.comparison.is_some() {
+        "Up/Down: Scroll | Left/Right: List | S: Sort | ESC: Close".to_string()
+    } else if hub.chat_report.is_some() {
+        "Left/Right: Reason | Enter: Report | ESC: Cancel".to_string()
+    } else if hub.tab == OnlineTab::Chat && hub.chat_host {
+        // Moderation keys only for the room's host
+        format!(
+            "Tab: Next tab | {} | Ctrl+P: Pin | Ctrl+T: Time out | Ctrl+L: Slow mode | ESC: Back",
+            hub.tab.hint()
+        )
+    } else if hub.tab == OnlineTab::Chat && online.user().is_some() {
+        // Every other key types into the message
+        format!("Tab: Next tab | {} | ESC: Back", hub.tab.hint())
+    } else if hub.tab == OnlineTab::Chat {
+        "Tab: Next tab | A: Account | ESC: Back".to_string()
+    } else {
+        format!(
+            "Tab: Next tab | {} | A: Account | ESC: Back",
+            hub.tab.hint()
+        )
+    };
+    spawn_online_text(
+        &mut commands,
+        font,
+        hint,
+        14.0,
+        muted,
+        Vec2::new(left, bottom),
+    );
+}
+
+/// Tournaments tab: the active tournaments on the left, the selected one's
+/// details, the player's matches and its bracket on the right
+fn draw_online_tournaments(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    hub: &OnlineHubState,
+    online: &OnlineServices,
+    origin: Vec2,
+) {
+    let muted = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    let mut y = origin.y;
+    spawn_online_text(
+        commands,
+        font,
+        format!("Tournaments ({})", hub.tournaments.len()),
+        18.0,
+        NEON_PINK,
+        Vec2::new(origin.x, y),
+    );
+    y -= 32.0;
+    if hub.tournaments.is_empty() {
+        spawn_online_text(
+            commands,
+            font,
+            "No tournaments yet. Press C to create one",
+            16.0,
+            muted,
+            Vec2::new(origin.x, y),
+        );
+    }
+    for (i, tournament) in hub.tournaments.iter().take(ONLINE_LIST_ROWS).enumerate() {
+        let selected = i == hub.selected_tournament;
+        let (label, color) = if selected {
+            (format!("> {}", tournament.name), NEON_PINK)
+        } else {
+            (format!("  {}", tournament.name), Color::WHITE)
+        };
+        spawn_online_text(commands, font, label, 16.0, color, Vec2::new(origin.x, y));
+        spawn_online_text(
+            commands,
+            font,
+            format!("{}/{}", tournament.players.len(), tournament.max_players),
+            16.0,
+            muted,
+            Vec2::new(origin.x + 230.0, y),
+        );
+        y -= 26.0;
+    }
+
+    let Some(tournament) = hub.selected_tournament() else {
+        return;
+    };
+    let right = origin.x + 320.0;
+    let mut y = origin.y;
+    spawn_online_text(
+        commands,
+        font,
+        tournament.name.clone(),
+        18.0,
+        NEON_PINK,
+        Vec2::new(right, y),
+    );
+    y -= 28.0;
+    spawn_online_text(
+        commands,
+        font,
+        format!(
+            "Players: {}/{} | {} | Starts {}",
+            tournament.players.len(),
+            tournament.max_players,
+            tournament.status.label(),
+            tournament.starts_at.with_timezone(&Local).format("%H:%M")
+        ),
+        16.0,
+        Color::WHITE,
+        Vec2::new(right, y),
+    );
+    y -= 40.0;
+
+    // The player's own matches, with the result once decided
+    spawn_online_text(
+        commands,
+        font,
+        "Your matches",
+        16.0,
+        NEON_BLUE,
+        Vec2::new(right, y),
+    );
+    y -= 26.0;
+    let own_id = online.user().map(|user| user.user_id());
+    if hub.own_matches.is_empty() {
+        let registered = own_id.is_some_and(|id| tournament.players.contains(&id));
+        let text = if registered {
+            "Scheduled once the tournament starts"
+        } else {
+            "Not registered. Press Enter to join"
+        };
+        spawn_online_text(commands, font, text, 14.0, muted, Vec2::new(right, y));
+        y -= 24.0;
+    }
+    for game_match in &hub.own_matches {
+        let opponent = if Some(game_match.player1_id) == own_id {
+            game_match.player2_id
+        } else {
+            Some(game_match.player1_id)
+        };
+        let (result, color) = match game_match.winner_id {
+            Some(winner) if Some(winner) == own_id => ("Won".to_string(), NEON_GREEN),
+            Some(_) => ("Lost".to_string(), NEON_ORANGE),
+            None => (
+                game_match
+                    .scheduled_at
+                    .with_timezone(&Local)
+                    .format("%H:%M")
+                    .to_string(),
+                NEON_YELLOW,
+            ),
+        };
+        spawn_online_text(
+            commands,
+            font,
+            format!(
+                "Round {} vs {}",
+                game_match.round,
+                opponent.map_or("bye", |id| hub.player_name(id))
+            ),
+            14.0,
+            Color::WHITE,
+            Vec2::new(right, y),
+        );
+        spawn_online_text(
+            commands,
+            font,
+            result,
+            14.0,
+            color,
+            Vec2::new(right + 240.0, y),
+        );
+        y -= 24.0;
+    }
+    y -= 16.0;
+
+    spawn_online_text(
+        commands,
+        font,
+        "Bracket",
+        16.0,
+        NEON_BLUE,
+        Vec2::new(right, y),
+    );
+    y -= 28.0;
+    let Some(first_round) = hub.bracket.first() else {
+        spawn_online_text(
+            commands,
+            font,
+            "Drawn when the tournament starts",
+            14.0,
+            muted,
+            Vec2::new(right, y),
+        );
+        return;
+    };
+    draw_bracket(commands, font, hub, first_round.len(), Vec2::new(right, y));
+}
+
+/// A single elimination bracket as columns of rounds, each match centred between
+/// the two that feed it. Rounds not drawn yet are shown with TBD players
+fn draw_bracket(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    hub: &OnlineHubState,
+    first_round_len: usize,
+    origin: Vec2,
+) {
+    const MATCH_HEIGHT: f32 = 44.0;
+    const ROUND_WIDTH: f32 = 150.0;
+    let muted = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    let rounds = first_round_len.max(1).trailing_zeros() as usize + 1;
+    for round in 0..rounds {
+        let span = (1 << round) as f32;
+        let x = origin.x + round as f32 * ROUND_WIDTH;
+        for slot in 0..(first_round_len >> round).max(1) {
+            let y = origin.y - (slot as f32 * span + (span - 1.0) / 2.0) * MATCH_HEIGHT;
+            let game_match = hub
+                .bracket
+                .get(round)
+                .and_then(|games| games.iter().find(|game| game.slot == slot as u32));
+            let players = match game_match {
+                Some(game) => [Some(game.player1_id), game.player2_id].map(|player| {
+                    let name = player.map_or("bye", |id| hub.player_name(id));
+                    let color = match (player, game.winner_id) {
+                        (Some(id), Some(winner)) if id == winner => NEON_GREEN,
+                        (_, Some(_)) => muted,
+                        _ => Color::WHITE,
+                    };
+                    (name.chars().take(12).collect::<String>(), color)
+                }),
+                None => [("TBD".to_string(), muted), ("TBD".to_string(), muted)],
+            };
+            for (i, (name, color)) in players.into_iter().enumerate() {
+                spawn_online_text(
+                    commands,
+                    font,
+                    name,
+                    14.0,
+                    color,
+                    Vec2::new(x, y - i as f32 * 18.0),
+                );
+            }
+        }
+    }
+}
+
+/// Chat tab: the newest lobby messages above the message field. Messages from
+/// blocked players collapse into a hidden count
+fn draw_online_chat(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    hub: &OnlineHubState,
+    online: &OnlineServices,
+    origin: Vec2,
+) {
+    let muted = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    let own_id = online.user().map(|user| user.user_id());
+    let mut y = origin.y;
+    // Room tabs, then the pins and notices, then as many of the newest messages as fit
+    let rooms = hub
+        .chat_rooms
+        .iter()
+        .enumerate()
+        .map(|(i, (_, name))| {
+            if i == hub.chat_room {
+                format!("[{}]", name)
+            } else {
+                name.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+    spawn_online_text(
+        commands,
+        font,
+        rooms,
+        16.0,
+        NEON_PINK,
+        Vec2::new(origin.x, y),
+    );
+    y -= 26.0;
+    for line in &hub.chat_header {
+        spawn_online_text(
+            commands,
+            font,
+            line,
+            14.0,
+            NEON_YELLOW,
+            Vec2::new(origin.x, y),
+        );
+        y -= 22.0;
+    }
+    let room_rows = CHAT_VISIBLE_MESSAGES.saturating_sub(hub.chat_header.len() + 1);
+    let skipped = hub.chat.len().saturating_sub(room_rows);
+    let selected = hub
+        .selected_chat_message()
+        .map(|message| message.message_id);
+    if hub.chat.is_empty() {
+        spawn_online_text(
+            commands,
+            font,
+            "No messages yet",
+            16.0,
+            muted,
+            Vec2::new(origin.x, y),
+        );
+    }
+    for line in visible_chat_lines(&hub.chat[skipped..], &hub.chat_muted) {
+        let (text, color) = match line {
+            ChatLine::Message(message) => (
+                format!(
+                    "{}{}  {}: {}",
+                    if Some(message.message_id) == selected {
+                        "> "
+                    } else {
+                        ""
+                    },
+                    message.timestamp.with_timezone(&Local).format("%H:%M"),
+                    message.sender_name,
+                    message.content
+                ),
+                if Some(message.message_id) == selected {
+                    NEON_GREEN
+                } else if Some(message.sender_id) == own_id {
+                    NEON_CYAN
+                } else {
+                    Color::WHITE
+                },
+            ),
+            ChatLine::Hidden(count) => (ChatLine::hidden_label(count), muted),
+        };
+        spawn_online_text(commands, font, text, 14.0, color, Vec2::new(origin.x, y));
+        y -= 24.0;
+    }
+
+    let field_y = origin.y - CHAT_VISIBLE_MESSAGES as f32 * 24.0 - 24.0;
+    if let (Some(reason), Some(message)) = (hub.chat_report, hub.selected_chat_message()) {
+        spawn_online_text(
+            commands,
+            font,
+            format!("Report {} for: < {} >", message.sender_name, reason.display_name()),
+            16.0,
+            NEON_ORANGE,
+            Vec2::new(origin.x, field_y + 30.0),
+        );
+    }
+    let signed_in = own_id.is_some();
+    draw_text_box(
+        commands,
+        &hub.chat_input,
+        Vec2::new(origin.x + 360.0, field_y),
+        720.0,
+        signed_in,
+        font.clone(),
+    )
+    .insert(OnlineElement);
+    if !signed_in {
+        spawn_online_text(
+            commands,
+            font,
+            "Sign in to chat",
+            14.0,
+            muted,
+            Vec2::new(origin.x + 12.0, field_y),
+        );
+    }
+}
+
+/// Muted tab: the players whose chat messages are hidden, by name
+fn draw_online_muted(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    hub: &OnlineHubState,
+    origin: Vec2,
+) {
+    let mut y = origin.y;
+    spawn_online_text(
+        commands,
+        font,
+        format!("Muted players ({})", hub.muted_players.len()),
+        18.0,
+        NEON_PINK,
+        Vec2::new(origin.x, y),
+    );
+    y -= 32.0;
+    if hub.muted_players.is_empty() {
+        spawn_online_text(
+            commands,
+            font,
+            "Nobody muted. Select a chat message and press Ctrl+M to mute its sender",
+            16.0,
+            Color::srgba(1.0, 1.0, 1.0, 0.5),
+            Vec2::new(origin.x, y),
+        );
+    }
+    for (i, (_, name)) in hub.muted_players.iter().take(ONLINE_LIST_ROWS).enumerate() {
+        let (label, color) = if i == hub.muted_cursor {
+            (format!("> {}", name), NEON_PINK)
+        } else {
+            (format!("  {}", name), Color::WHITE)
+        };
+        spawn_online_text(commands, font, label, 16.0, color, Vec2::new(origin.x, y));
+        y -= 26.0;
+    }
+}
+
+/// Head-to-head comparison with a friend over the Friends tab: the tally, then the
+/// shared songs side by side or the songs only one of you has played
+fn draw_online_comparison(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    view: &ComparisonView,
+    config: &GameConfig,
+    origin: Vec2,
+) {
+    let muted = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    let mut y = origin.y;
+    spawn_online_text(
+        commands,
+        font,
+        format!("Compare with {}", view.friend_name),
+        18.0,
+        NEON_PINK,
+        Vec2::new(origin.x, y),
+    );
+    y -= 30.0;
+    if let Some(placeholder) = view.placeholder() {
+        spawn_online_text(
+            commands,
+            font,
+            placeholder,
+            16.0,
+            NEON_YELLOW,
+            Vec2::new(origin.x, y),
+        );
+        return;
+    }
+    let Some(comparison) = view.comparison() else {
+        return;
+    };
+    if let Some(tally) = view.tally_label() {
+        spawn_online_text(
+            commands,
+            font,
+            tally,
+            16.0,
+            NEON_YELLOW,
+            Vec2::new(origin.x, y),
+        );
+    }
+    y -= 34.0;
+
+    let mut x = origin.x;
+    for tab in [
+        ComparisonTab::Shared,
+        ComparisonTab::OnlyYou,
+        ComparisonTab::OnlyThem,
+    ] {
+        let (label, color) = if tab == view.tab {
+            (format!("[{}]", tab.display_name()), NEON_CYAN)
+        } else {
+            (tab.display_name().to_string(), Color::WHITE)
+        };
+        spawn_online_text(commands, font, label, 16.0, color, Vec2::new(x, y));
+        x += 180.0;
+    }
+    if view.tab == ComparisonTab::Shared {
+        spawn_online_text(
+            commands,
+            font,
+            format!("Sort: {}", view.sort.display_name()),
+            16.0,
+            muted,
+            Vec2::new(origin.x + 580.0, y),
+        );
+    }
+    y -= 36.0;
+
+    if view.row_count() == 0 {
+        spawn_online_text(
+            commands,
+            font,
+            "No songs here",
+            16.0,
+            muted,
+            Vec2::new(origin.x, y),
+        );
+        return;
+    }
+    let style = config.hud_layout.score_style;
+    let record = |record: &SongRecord| {
+        format!(
+            "{}  {:.2}%  {}",
+            format_score(record.best_score, style),
+            record.best_accuracy,
+            record.grade
+        )
+    };
+    if view.tab == ComparisonTab::Shared {
+        for (text, x) in [
+            ("Song", 0.0),
+            ("You", 340.0),
+            (comparison.friend_name.as_str(), 560.0),
+        ] {
+            spawn_online_text(
+                commands,
+                font,
+                text.to_string(),
+                14.0,
+                NEON_BLUE,
+                Vec2::new(origin.x + x, y),
+            );
+        }
+        y -= 24.0;
+    }
+    for index in view.visible_range() {
+        let marker = if index == view.cursor { ">" } else { " " };
+        let song_color = if index == view.cursor {
+            NEON_PINK
+        } else {
+            Color::WHITE
+        };
+        let song = match view.tab {
+            ComparisonTab::Shared => &comparison.shared[index].song,
+            ComparisonTab::OnlyYou => &comparison.only_you[index],
+            ComparisonTab::OnlyThem => &comparison.only_them[index],
+        };
+        let song: String = song.chars().take(32).collect();
+        spawn_online_text(
+            commands,
+            font,
+            format!("{} {}", marker, song),
+            14.0,
+            song_color,
+            Vec2::new(origin.x, y),
+        );
+        if view.tab == ComparisonTab::Shared {
+            let row = &comparison.shared[index];
+            let color = match row.outcome() {
+                Outcome::Win => NEON_GREEN,
+                Outcome::Loss => NEON_ORANGE,
+                Outcome::Tie => Color::WHITE,
+            };
+            spawn_online_text(
+                commands,
+                font,
+                record(&row.you),
+                14.0,
+                color,
+                Vec2::new(origin.x + 340.0, y),
+            );
+            spawn_online_text(
+                commands,
+                font,
+                record(&row.them),
+                14.0,
+                Color::WHITE,
+                Vec2::new(origin.x + 560.0, y),
+            );
+        }
+        y -= 24.0;
+    }
+}
+
+/// Colour a friend's status is listed in
+fn friend_status_color(status: &FriendStatus) -> Color {
+    match status {
+        FriendStatus::Accepted => NEON_GREEN,
+        FriendStatus::Pending => NEON_YELLOW,
+        FriendStatus::Blocked => NEON_ORANGE,
+    }
+}
+
+/// Friends tab: incoming requests and the new request field on the left, friends,
+/// sent requests and blocked players on the right
+fn draw_online_friends(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    hub: &OnlineHubState,
+    origin: Vec2,
+) {
+    let muted = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    if let Some(notice) = &hub.notice {
+        spawn_online_text(commands, font, notice.clone(), 16.0, NEON_YELLOW, origin);
+        return;
+    }
+
+    let incoming = &hub.friends.incoming;
+    let mut y = origin.y;
+    spawn_online_text(
+        commands,
+        font,
+        format!("Friend requests ({})", incoming.len()),
+        18.0,
+        NEON_PINK,
+        Vec2::new(origin.x, y),
+    );
+    y -= 32.0;
+    if incoming.is_empty() {
+        spawn_online_text(
+            commands,
+            font,
+            "No pending requests",
+            16.0,
+            muted,
+            Vec2::new(origin.x, y),
+        );
+        y -= 26.0;
+    }
+    for (i, request) in incoming.iter().take(ONLINE_LIST_ROWS).enumerate() {
+        let selected = i == hub.friend_cursor;
+        let (label, color) = if selected {
+            (format!("> {}", request.username), NEON_PINK)
+        } else {
+            (format!("  {}", request.username), Color::WHITE)
+        };
+        spawn_online_text(commands, font, label, 16.0, color, Vec2::new(origin.x, y));
+        y -= 26.0;
+    }
+
+    if let Some(input) = &hub.friend_name {
+        y -= 16.0;
+        spawn_online_text(
+            commands,
+            font,
+            "Add a friend by username",
+            16.0,
+            NEON_BLUE,
+            Vec2::new(origin.x, y),
+        );
+        y -= 32.0;
+        draw_text_box(
+            commands,
+            input,
+            Vec2::new(origin.x + 150.0, y),
+            300.0,
+            true,
+            font.clone(),
+        )
+        .insert(OnlineElement);
+    }
+
+    let right = origin.x + 420.0;
+    let mut y = origin.y;
+    spawn_online_text(
+        commands,
+        font,
+        format!("Friends ({})", hub.friends.friends.len()),
+        18.0,
+        NEON_PINK,
+        Vec2::new(right, y),
+    );
+    y -= 32.0;
+    let listed: Vec<&Friend> = hub
+        .friends
+        .friends
+        .iter()
+        .chain(&hub.friends.outgoing)
+        .chain(&hub.friends.blocked)
+        .collect();
+    if listed.is_empty() {
+        spawn_online_text(
+            commands,
+            font,
+            "No friends yet. Press N to add one",
+            16.0,
+            muted,
+            Vec2::new(right, y),
+        );
+    }
+    // Only accepted friends, listed first, can be selected
+    for (i, friend) in listed.iter().take(ONLINE_LIST_ROWS).enumerate() {
+        let selected = i < hub.friends.friends.len() && incoming.len() + i == hub.friend_cursor;
+        let (label, color) = if selected {
+            (format!("> {}", friend.username), NEON_PINK)
+        } else {
+            (format!("  {}", friend.username), Color::WHITE)
+        };
+        spawn_online_text(commands, font, label, 16.0, color, Vec2::new(right, y));
+        spawn_online_text(
+            commands,
+            font,
+            friend.status.label(),
+            16.0,
+            friend_status_color(&friend.status),
+            Vec2::new(right + 240.0, y),
+        );
+        y -= 26.0;
+    }
+}
+
+/// Leaderboard tab: the scope strip, the season and which rows are shown, then rank,
+/// player, score and accuracy per row
+fn draw_online_leaderboard(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    hub: &OnlineHubState,
+    online: &OnlineServices,
+    config: &GameConfig,
+    origin: Vec2,
+) {
+    let mut x = origin.x;
+    for scope in BoardScope::ALL {
+        let mut label = scope.label().to_string();
+        if let (BoardScope::Country, Some(country)) = (scope, &hub.country) {
+            label = format!("{}: {}", label, country);
+        }
+        let (label, color) = if scope == hub.board_scope {
+            (format!("< {} >", label), NEON_CYAN)
+        } else {
+            (label, Color::srgba(1.0, 1.0, 1.0, 0.6))
+        };
+        spawn_online_text(commands, font, label, 16.0, color, Vec2::new(x, origin.y));
+        x += 220.0;
+    }
+    spawn_online_text(
+        commands,
+        font,
+        format!("Season: < {} >", hub.board_season.label()),
+        16.0,
+        NEON_CYAN,
+        Vec2::new(origin.x, origin.y - 30.0),
+    );
+    if !hub.board.is_empty() {
+        let mut position = format!(
+            "#{}-{} of {} players",
+            hub.board_offset + 1,
+            hub.board_offset + hub.board.len(),
+            hub.board_total
+        );
+        if let Some(rank) = hub.own_rank {
+            position.push_str(&format!(" | You: #{}", rank));
+        }
+        spawn_online_text(
+            commands,
+            font,
+            position,
+            16.0,
+            Color::srgba(1.0, 1.0, 1.0, 0.6),
+            Vec2::new(origin.x + 440.0, origin.y - 30.0),
+        );
+    }
+
+    let mut y = origin.y - 70.0;
+    if let Some(notice) = &hub.notice {
+        spawn_online_text(
+            commands,
+            font,
+            notice.clone(),
+            16.0,
+            NEON_YELLOW,
+            Vec2::new(origin.x, y),
+        );
+        return;
+    }
+    if hub.board.is_empty() {
+        spawn_online_text(
+            commands,
+            font,
+            "No scores yet",
+            16.0,
+            Color::srgba(1.0, 1.0, 1.0, 0.5),
+            Vec2::new(origin.x, y),
+        );
+        return;
+    }
+
+    let columns = [0.0, 70.0, 360.0, 560.0];
+    for (column, heading) in columns.iter().zip(["Rank", "Player", "Score", "Accuracy"]) {
+        spawn_online_text(
+            commands,
+            font,
+            heading,
+            14.0,
+            NEON_BLUE,
+            Vec2::new(origin.x + column, y),
+        );
+    }
+    y -= 28.0;
+
+    let own_id = online.user().map(|user| user.user_id());
+    for entry in &hub.board {
+        let color = if Some(entry.user_id) == own_id {
+            NEON_YELLOW
+        } else {
+            Color::WHITE
+        };
+        let cells = [
+            format!("#{}", entry.rank),
+            entry.username.clone(),
+            format_score(entry.total_score as i64, config.hud_layout.score_style),
+            format!("{:.2}%", entry.average_accuracy),
+        ];
+        for (column, cell) in columns.iter().zip(cells) {
+            spawn_online_text(
+                commands,
+                font,
+                cell,
+                16.0,
+                color,
+                Vec2::new(origin.x + column, y),
+            );
+        }
+        y -= 26.0;
+    }
+}
+
+/// Setup practice menu UI
+pub fn setup_practice_menu_ui(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+        let screen_w = window.width();
+
+        commands.spawn((
+            Text2d::new("Practice Mode"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(NEON_YELLOW),
+            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            UiElement,
+        ));
+
+        commands.spawn((
+            Text2d::new("Press ESC to go back"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Rows of the practice menu: song, speed, preserve pitch, no fail, autoplay, hit sounds,
+/// metronome, practice hint, loop, start
+pub const PRACTICE_MENU_ROWS: usize = 10;
+
+/// Practice menu timeline the loop markers are dragged on
+pub fn practice_timeline(scr_width: f32, scr_height: f32) -> Rect {
+    Rect::from_center_size(
+        Vec2::new(0.0, -scr_height / 2.0 + 90.0),
+        Vec2::new((scr_width * 0.7).min(640.0), 14.0),
+    )
+}
+
+/// Marker for practice menu rows, rebuilt when the menu state changes
+#[derive(Component)]
+pub struct PracticeMenuElement;
+
+/// Draw the practice menu rows
+pub fn draw_practice_menu(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    practice_state: Res<PracticeMenuState>,
+    existing: Query<Entity, With<PracticeMenuElement>>,
+) {
+    if !practice_state.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let on_off = |value: bool| if value { "ON" } else { "OFF" };
+    let song_name = practice_state
+        .selected_song
+        .as_ref()
+        .map(|song| {
+            song.split('/')
+                .next_back()
+                .unwrap_or(song)
+                .replace(".mp3", "")
+                .replace(".MP3", "")
+        })
+        .unwrap_or_else(|| "No songs found".to_string());
+    let hint = practice_state
+        .hint
+        .and_then(|index| practice_state.hints.get(index));
+    let hint_name = match hint {
+        Some(hint) => hint.label.clone(),
+        None if practice_state.hints.is_empty() => "None available".to_string(),
+        None => "None".to_string(),
+    };
+    let loop_section = match (practice_state.loop_start, practice_state.loop_end) {
+        (Some(start), Some(end)) if end > start => Some((start, end)),
+        _ => None,
+    };
+    let loop_label = match loop_section {
+        Some((start, end)) => format!("{} - {}", format_time(start), format_time(end)),
+        None => "Off".to_string(),
+    };
+
+    let rows = [
+        format!("Song: {}", song_name),
+        format!("Speed: {:.2}x", practice_state.playback_speed),
+        format!("Preserve Pitch: {}", on_off(practice_state.preserve_pitch)),
+        format!("No Fail: {}", on_off(practice_state.no_fail)),
+        format!("Autoplay: {}", on_off(practice_state.autoplay)),
+        format!("Hit Sounds: {}", on_off(practice_state.hit_sounds)),
+        format!("Metronome: {}", practice_state.metronome.display_name()),
+        format!("Practice Hint: {}", hint_name),
+        format!("Loop: {}", loop_label),
+        "Start Practice".to_string(),
+    ];
+
+    let mut y = window.height() / 2.0 - 150.0;
+    for (i, label) in rows.into_iter().enumerate() {
+        let selected = i == practice_state.selected_index;
+        commands.spawn((
+            Text2d::new(if selected {
+                format!("< {} >", label)
+            } else {
+                label
+            }),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(if selected { NEON_YELLOW } else { Color::WHITE }),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            PracticeMenuElement,
+        ));
+        y -= 40.0;
+    }
+
+    commands.spawn((
+        Text2d::new("Up/Down: select | Left/Right: change | Enter: start / clear loop"),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+        Transform::from_xyz(0.0, y - 10.0, 1.0),
+        UiElement,
+        PracticeMenuElement,
+    ));
+
+    // The mapper's suggested sections, with the applied one highlighted
+    y -= 50.0;
+    for (i, hint) in practice_state.hints.iter().enumerate() {
+        let applied = practice_state.hint == Some(i);
+        commands.spawn((
+            Text2d::new(hint.summary()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(if applied { NEON_YELLOW } else { NEON_BLUE }),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            PracticeMenuElement,
+        ));
+        y -= 20.0;
+    }
+
+    // Loop timeline: the whole song, with the looped section and its two markers
+    let Some(length) = practice_state.song_length.filter(|length| *length > 0.0) else {
+        return;
+    };
+    let timeline = practice_timeline(window.width(), window.height());
+    let x_at = |time: f64| timeline.min.x + (time / length) as f32 * timeline.width();
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(1.0, 1.0, 1.0, 0.15),
+            custom_size: Some(timeline.size()),
+            ..default()
+        },
+        Transform::from_xyz(timeline.center().x, timeline.center().y, 1.0),
+        UiElement,
+        PracticeMenuElement,
+    ));
+    let caption = match loop_section {
+        Some(_) => "Drag the markers to move the loop",
+        None => "Drag across the timeline to loop a section",
+    };
+    commands.spawn((
+        Text2d::new(caption),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.4)),
+        Transform::from_xyz(0.0, timeline.max.y + 16.0, 1.0),
+        UiElement,
+        PracticeMenuElement,
+    ));
+    let Some((start, end)) = loop_section else {
+        return;
+    };
+    let (left, right) = (x_at(start), x_at(end));
+    commands.spawn((
+        Sprite {
+            color: NEON_CYAN.with_alpha(0.4),
+            custom_size: Some(Vec2::new(right - left, timeline.height())),
+            ..default()
+        },
+        Transform::from_xyz((left + right) / 2.0, timeline.center().y, 1.1),
+        UiElement,
+        PracticeMenuElement,
+    ));
+    for (x, time) in [(left, start), (right, end)] {
+        commands.spawn((
+            Sprite {
+                color: NEON_YELLOW,
+                custom_size: Some(Vec2::new(4.0, timeline.height() + 12.0)),
+                ..default()
+            },
+            Transform::from_xyz(x, timeline.center().y, 1.2),
+            UiElement,
+            PracticeMenuElement,
+        ));
+        commands.spawn((
+            Text2d::new(format_time(time)),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 11.0,
+                ..default()
+            },
+            TextColor(NEON_YELLOW),
+            Transform::from_xyz(x, timeline.min.y - 16.0, 1.0),
+            UiElement,
+            PracticeMenuElement,
+        ));
+    }
+}
+
+/// Setup analytics UI
+pub fn setup_analytics_ui(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+) {
+    if let Ok(window) = windows.get_single() {
+        let screen_h = window.height();
+        let screen_w = window.width();
+
+        commands.spawn((
+            Text2d::new("Analytics"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 36.0,
+                ..default()
+            },
+            TextColor(NEON_PINK),
+            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            UiElement,
+        ));
+
+        commands.spawn((
+            Text2d::new("Press ESC to go back"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Marker for analytics view content that is rebuilt when the view changes
+#[derive(Component)]
+pub struct AnalyticsViewElement;
+
+/// Number of week columns shown in the history heatmap
+pub const HISTORY_WEEKS: u64 = 53;
+const HISTORY_CELL_SIZE: f32 = 12.0;
+const HISTORY_CELL_GAP: f32 = 2.0;
+
+/// First day shown in the history heatmap (a Sunday, 52 full weeks before this week)
+pub fn history_start_day() -> u64 {
+    let today = today();
+    // 1970-01-01 was a Thursday, so Sunday-based weekday is (day + 4) % 7
+    let weekday = (today + 4) % 7;
+    today.saturating_sub((HISTORY_WEEKS - 1) * 7 + weekday)
+}
+
+/// Grid position (column, row) of a day in the heatmap
+pub fn history_cell(day: u64, start_day: u64) -> (u64, u64) {
+    let offset = day - start_day;
+    (offset / 7, offset % 7)
+}
+
+/// World-space origin (top-left cell center) of the heatmap grid
+pub fn history_grid_origin() -> Vec2 {
+    let step = HISTORY_CELL_SIZE + HISTORY_CELL_GAP;
+    Vec2::new(-(HISTORY_WEEKS as f32 - 1.0) * step / 2.0, 120.0)
+}
+
+/// Day under a world-space position in the heatmap, if any
+pub fn history_day_at(world_pos: Vec2) -> Option<u64> {
+    let step = HISTORY_CELL_SIZE + HISTORY_CELL_GAP;
+    let local = world_pos - history_grid_origin() + Vec2::new(step / 2.0, -step / 2.0);
+    if local.x < 0.0 || local.y > 0.0 {
+        return None;
+    }
+    let col = (local.x / step) as u64;
+    let row = (-local.y / step) as u64;
+    if col >= HISTORY_WEEKS || row >= 7 {
+        return None;
+    }
+    let day = history_start_day() + col * 7 + row;
+    (day <= today()).then_some(day)
+}
+
+fn day_to_date(day: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp((day * SECONDS_PER_DAY) as i64, 0)
+}
+
+/// Rebuild the current analytics view when its state or data changes
+#[allow(clippy::too_many_arguments)]
+pub fn draw_analytics_view(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    analytics: Res<Analytics>,
+    analytics_state: Res<AnalyticsState>,
+    definitions: Res<AchievementDefinitions>,
+    config: Res<GameConfig>,
+    replays: Res<ReplayLibrary>,
+    archive: Res<SeasonArchive>,
+    existing: Query<Entity, With<AnalyticsViewElement>>,
+) {
+    if !analytics_state.is_changed()
+        && !analytics.is_changed()
+        && !definitions.is_changed()
+        && !replays.is_changed()
+        && !archive.is_changed()
+    {
+        return;
+    }
+    let score_style = config.hud_layout.score_style;
+    let season = analytics_state.season.as_ref();
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let title = AnalyticsView::all()
+        .into_iter()
+        .find(|(view, _)| *view == analytics_state.current_view)
+        .map(|(_, name)| name)
+        .unwrap_or("");
+    commands.spawn((
+        Text2d::new(format!("< {} >  (TAB to switch)", title)),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(NEON_CYAN),
+        Transform::from_xyz(0.0, 200.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+
+    let season_line = match season {
+        Some(season) => format!("Season: {}  (S to change)", season.label()),
+        None => "Season: Lifetime  (S to change)".to_string(),
+    };
+    commands.spawn((
+        Text2d::new(season_line),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(NEON_YELLOW),
+        Transform::from_xyz(0.0, 226.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+
+    match analytics_state.current_view {
+        AnalyticsView::Overview => {
+            draw_analytics_overview(&mut commands, &assets, &analytics, season, score_style)
+        }
+        AnalyticsView::History => {
+            draw_analytics_history(&mut commands, &assets, &analytics, &analytics_state)
+        }
+        AnalyticsView::Sessions => draw_analytics_sessions(
+            &mut commands,
+            &assets,
+            &analytics,
+            &analytics_state,
+            score_style,
+        ),
+        AnalyticsView::Songs => draw_analytics_songs(
+            &mut commands,
+            &assets,
+            &analytics,
+            &analytics_state,
+            score_style,
+        ),
+        AnalyticsView::Achievements => {
+            draw_analytics_achievements(&mut commands, &assets, &analytics, &definitions, season)
+        }
+        AnalyticsView::Replays => draw_analytics_replays(&mut commands, &assets, &replays, season),
+        AnalyticsView::Precision => {
+            draw_analytics_precision(&mut commands, &assets, &analytics, &config, season)
+        }
+        AnalyticsView::Seasons => draw_analytics_seasons(
+            &mut commands,
+            &assets,
+            &archive,
+            analytics_state.season_cursor,
+            score_style,
+        ),
+        AnalyticsView::Trends => draw_analytics_trends(&mut commands, &assets, &analytics, season),
+    }
+
+    if let Some(notice) = &analytics_state.notice {
+        commands.spawn((
+            Text2d::new(notice.clone()),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(NEON_YELLOW),
+            Transform::from_xyz(0.0, -220.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+}
+
+/// Totals, grades and best scores of a season summary, one line each
+fn season_summary_lines(summary: &SeasonSummary, score_style: ScoreStyle) -> Vec<String> {
+    let mut lines = vec![summary.totals_line()];
+    if !summary.grades.is_empty() {
+        lines.push(summary.grades_line());
+    }
+    if !summary.best_scores.is_empty() {
+        lines.push("Best scores:".to_string());
+        lines.extend(
+            summary
+                .best_scores
+                .iter()
+                .map(|(song, score)| format!("{}  {}", song, format_score(*score, score_style))),
+        );
+    }
+    lines
+}
+
+/// Overview: totals of the season picked, or of every recorded play
+fn draw_analytics_overview(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    season: Option<&Season>,
+    score_style: ScoreStyle,
+) {
+    let lines = match season {
+        Some(season) => match analytics.season_stats(season) {
+            Some(stats) => {
+                let summary = SeasonSummary::new(season.clone(), stats, Utc::now());
+                season_summary_lines(&summary, score_style)
+            }
+            None => vec!["No plays this season yet".to_string()],
+        },
+        None => {
+            let stats = analytics.get_overall_stats();
+            let mut lines = vec![format!(
+                "{} plays | {} | {:.1}% | {} FC",
+                stats.total_games,
+                stats.format_play_time(),
+                stats.overall_accuracy,
+                stats.total_full_combos
+            )];
+            if let Some(grade) = stats.best_overall_grade {
+                lines.push(format!("Best recent grade: {}", grade.as_str()));
+            }
+            let most_played = analytics.get_most_played_songs(5);
+            if !most_played.is_empty() {
+                lines.push("Most played:".to_string());
+                lines.extend(
+                    most_played
+                        .iter()
+                        .map(|(song, stats)| format!("{}  {} plays", song, stats.play_count)),
+                );
+            }
+            lines
+        }
+    };
+
+    for (i, line) in lines.into_iter().enumerate() {
+        commands.spawn((
+            Text2d::new(line),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(0.0, 150.0 - i as f32 * 24.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+}
+
+/// Games shown in the accuracy trend chart
+const TREND_GAMES: usize = 50;
+
+/// Games averaged by the trend chart's moving average
+const TREND_AVERAGE_WINDOW: usize = 5;
+
+/// Trends view: accuracy of the latest games as a line chart, with a caption
+fn draw_analytics_trends(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    season: Option<&Season>,
+) {
+    let trend = analytics.get_accuracy_trend(TREND_GAMES);
+    let area = Rect::new(-260.0, -120.0, 280.0, 140.0);
+    spawn_accuracy_chart(commands, assets, &trend, area);
+
+    let caption = match trend.len() {
+        0 => "No games recorded yet".to_string(),
+        1 => "1 game so far: play more to see a trend".to_string(),
+        count => format!(
+            "Last {} games, oldest to newest (average of {} in white)",
+            count, TREND_AVERAGE_WINDOW
+        ),
+    };
+    let caption = match season {
+        Some(_) => format!("{} - accuracy history isn't kept per season", caption),
+        None => caption,
+    };
+    commands.spawn((
+        Text2d::new(caption),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(area.center().x, area.min.y - 36.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+}
+
+/// Line chart of accuracies, oldest first, in `area` on a 0-100% scale: gridlines
+/// every 25%, game numbers below, segments colored by the grade their accuracy
+/// reaches and a moving average on top
+fn spawn_accuracy_chart(commands: &mut Commands, assets: &GameAssets, trend: &[f32], area: Rect) {
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+    let dim = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    let point = |i: usize, accuracy: f32| {
+        let x = match trend.len() {
+            1 => area.center().x,
+            count => area.min.x + area.width() * i as f32 / (count - 1) as f32,
+        };
+        let y = area.min.y + area.height() * accuracy.clamp(0.0, 100.0) / 100.0;
+        Vec2::new(x, y)
+    };
+    let line = |commands: &mut Commands, from: Vec2, to: Vec2, width, color, z| {
+        if let Some(mut entity) = spawn_line(commands, from, to, width, color, z) {
+            entity.insert(AnalyticsViewElement);
+        }
+    };
+
+    // Y axis with gridlines every 25%
+    for percent in [0.0, 25.0, 50.0, 75.0, 100.0] {
+        let y = point(0, percent).y;
+        let alpha = if percent == 0.0 { 0.6 } else { 0.15 };
+        let grid = Color::srgba(1.0, 1.0, 1.0, alpha);
+        let (from, to) = (Vec2::new(area.min.x, y), Vec2::new(area.max.x, y));
+        line(commands, from, to, 1.0, grid, 0.9);
+        commands.spawn((
+            Text2d::new(format!("{:.0}%", percent)),
+            font(11.0),
+            TextColor(dim),
+            Transform::from_xyz(area.min.x - 24.0, y, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+    let axis = Color::srgba(1.0, 1.0, 1.0, 0.6);
+    let (from, to) = (area.min, Vec2::new(area.min.x, area.max.y));
+    line(commands, from, to, 1.0, axis, 0.9);
+    if trend.is_empty() {
+        return;
+    }
+
+    // X axis: game numbers at up to 6 evenly spaced ticks, the latest always labeled
+    let ticks = (trend.len() - 1).clamp(1, 5);
+    let mut labeled: Vec<usize> = (0..=ticks).map(|t| t * (trend.len() - 1) / ticks).collect();
+    labeled.dedup();
+    for i in labeled {
+        let x = point(i, 0.0).x;
+        commands.spawn((
+            Text2d::new(format!("{}", i + 1)),
+            font(11.0),
+            TextColor(dim),
+            Transform::from_xyz(x, area.min.y - 14.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+
+    // Accuracy line and its points
+    for (i, pair) in trend.windows(2).enumerate() {
+        let average = (pair[0] + pair[1]) / 2.0;
+        let color = get_grade_color(Grade::from_accuracy(average).as_str());
+        let (from, to) = (point(i, pair[0]), point(i + 1, pair[1]));
+        line(commands, from, to, 2.0, color, 1.1);
+    }
+    for (i, &accuracy) in trend.iter().enumerate() {
+        let position = point(i, accuracy);
+        commands.spawn((
+            Sprite {
+                color: get_grade_color(Grade::from_accuracy(accuracy).as_str()),
+                custom_size: Some(Vec2::splat(6.0)),
+                ..default()
+            },
+            Transform::from_xyz(position.x, position.y, 1.2),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+
+    // Trailing moving average
+    let averages: Vec<f32> = (0..trend.len())
+        .map(|i| {
+            let window = &trend[i.saturating_sub(TREND_AVERAGE_WINDOW - 1)..=i];
+            window.iter().sum::<f32>() / window.len() as f32
+        })
+        .collect();
+    let average_color = Color::srgba(1.0, 1.0, 1.0, 0.7);
+    for (i, pair) in averages.windows(2).enumerate() {
+        let (from, to) = (point(i, pair[0]), point(i + 1, pair[1]));
+        line(commands, from, to, 1.5, average_color, 1.15);
+    }
+}
+
+/// Past Seasons view: archived seasons newest first, with the highlighted one's summary
+fn draw_analytics_seasons(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    archive: &SeasonArchive,
+    cursor: usize,
+    score_style: ScoreStyle,
 ) {
-    let mut i = 0;
-    while i < floating_texts.len() {
-        let text = &floating_texts[i];
-        let time_since_spawn = elapsed - text.spawn_time;
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+    if archive.summaries.is_empty() {
+        commands.spawn((
+            Text2d::new("No finished seasons yet. A season is archived once it ends"),
+            font(16.0),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(0.0, 120.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+        return;
+    }
 
-        if time_since_spawn >= text.duration {
-            floating_texts.swap_remove(i);
-            continue;
+    for (i, summary) in archive.summaries.iter().rev().take(12).enumerate() {
+        let selected = i == cursor;
+        commands.spawn((
+            Text2d::new(format!(
+                "{}{} | {} plays",
+                if selected { "> " } else { "" },
+                summary.season.name,
+                summary.plays
+            )),
+            font(14.0),
+            TextColor(if selected { NEON_PINK } else { Color::WHITE }),
+            Transform::from_xyz(-300.0, 150.0 - i as f32 * 24.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+
+    let Some(summary) = archive.summaries.iter().rev().nth(cursor) else {
+        return;
+    };
+    let mut lines = vec![summary.season.label()];
+    lines.extend(season_summary_lines(summary, score_style));
+    for (i, line) in lines.into_iter().enumerate() {
+        commands.spawn((
+            Text2d::new(line),
+            font(if i == 0 { 15.0 } else { 13.0 }),
+            TextColor(if i == 0 { NEON_CYAN } else { Color::WHITE }),
+            Transform::from_xyz(150.0, 150.0 - i as f32 * 22.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+
+    commands.spawn((
+        Text2d::new("Up/Down: select a season"),
+        font(14.0),
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(0.0, 150.0 - 12.0 * 24.0 - 10.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+}
+
+/// Lifetime hit precision heatmap and timing histogram
+fn draw_analytics_precision(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    config: &GameConfig,
+    season: Option<&Season>,
+) {
+    spawn_precision_map(
+        commands,
+        assets,
+        &analytics.precision,
+        Vec2::new(-140.0, 10.0),
+        110.0,
+    )
+    .insert(AnalyticsViewElement);
+
+    let note = if config.track_hit_precision {
+        let mut note = format!(
+            "{} hits over all recorded plays",
+            analytics.precision.total()
+        );
+        if season.is_some() {
+            note.push_str(" (precision isn't kept per season)");
         }
+        note
+    } else {
+        "Hit precision recording is off (Settings > General)".to_string()
+    };
+    commands.spawn((
+        Text2d::new(note),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(0.0, -150.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+}
 
-        let y_offset = (time_since_spawn * 30.0) as f32;
-        let alpha = 1.0 - ((time_since_spawn / text.duration) as f32);
-        let color = Color::srgba(text.color.0, text.color.1, text.color.2, alpha);
+/// List the replays in the watch folder, with unreadable files in red and why. A season
+/// leaves out replays recorded outside it
+fn draw_analytics_replays(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    replays: &ReplayLibrary,
+    season: Option<&Season>,
+) {
+    let font = TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: 14.0,
+        ..default()
+    };
+    let entries: Vec<_> = replays
+        .entries
+        .iter()
+        .filter(|entry| match (&entry.loaded, season) {
+            (Ok((meta, _)), Some(season)) => season.contains(meta.recorded_at as i64),
+            _ => true,
+        })
+        .collect();
+    if entries.is_empty() {
+        let message = if replays.entries.is_empty() {
+            format!(
+                "No replays yet. Drop .{} files into the {} folder",
+                crate::replay::REPLAY_EXTENSION,
+                crate::replay::REPLAYS_DIR
+            )
+        } else {
+            "No replays recorded this season".to_string()
+        };
+        commands.spawn((
+            Text2d::new(message),
+            TextFont {
+                font_size: 16.0,
+                ..font
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(0.0, 120.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+        return;
+    }
 
+    for (i, entry) in entries.iter().take(MAX_LISTED_REPLAYS).enumerate() {
+        let (label, color) = match &entry.loaded {
+            Ok((meta, inputs)) => {
+                let date = DateTime::from_timestamp(meta.recorded_at as i64, 0)
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                (
+                    format!(
+                        "{} | {} | {:.1}% | {:.2}x | {} inputs | {}",
+                        meta.song_title(),
+                        meta.score,
+                        meta.accuracy,
+                        meta.playback_speed,
+                        inputs,
+                        date
+                    ),
+                    Color::WHITE,
+                )
+            }
+            Err(error) => (
+                format!("{}: {}", entry.file_name(), error),
+                Color::srgba(1.0, 0.3, 0.3, 1.0),
+            ),
+        };
         commands.spawn((
-            Text2d::new(text.text.clone()),
+            Text2d::new(label),
+            font.clone(),
+            TextColor(color),
+            Transform::from_xyz(0.0, 140.0 - i as f32 * 24.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+    if entries.len() > MAX_LISTED_REPLAYS {
+        commands.spawn((
+            Text2d::new(format!("+{} more", entries.len() - MAX_LISTED_REPLAYS)),
+            font,
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+            Transform::from_xyz(0.0, 140.0 - MAX_LISTED_REPLAYS as f32 * 24.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+}
+
+/// Draw the 52-week play history heatmap
+fn draw_analytics_history(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    analytics_state: &AnalyticsState,
+) {
+    let start_day = history_start_day();
+    let today = today();
+    let metric = analytics_state.history_metric;
+    let origin = history_grid_origin();
+    let step = HISTORY_CELL_SIZE + HISTORY_CELL_GAP;
+    // Days outside the season picked are drawn faded and left out of the scale
+    let season_days = analytics_state.season.as_ref().map(Season::days);
+    let in_season = |day: u64| season_days.as_ref().is_none_or(|days| days.contains(&day));
+
+    // Scale colors to the player's own busiest day
+    let max_value = analytics
+        .daily_activity
+        .range(start_day..=today)
+        .filter(|(day, _)| in_season(**day))
+        .map(|(_, activity)| metric.value(activity))
+        .fold(0.0f32, f32::max);
+
+    let mut last_month = None;
+    for day in start_day..=today {
+        let (col, row) = history_cell(day, start_day);
+        let x = origin.x + col as f32 * step;
+        let y = origin.y - row as f32 * step;
+
+        let value = analytics
+            .daily_activity
+            .get(&day)
+            .map(|activity| metric.value(activity))
+            .unwrap_or(0.0);
+        let color = if !in_season(day) {
+            Color::srgba(1.0, 1.0, 1.0, 0.03)
+        } else if value <= 0.0 || max_value <= 0.0 {
+            Color::srgba(1.0, 1.0, 1.0, 0.08)
+        } else {
+            let intensity = 0.25 + 0.75 * (value / max_value);
+            NEON_GREEN.with_alpha(intensity)
+        };
+
+        commands.spawn((
+            Sprite {
+                color,
+                custom_size: Some(Vec2::splat(HISTORY_CELL_SIZE)),
+                ..default()
+            },
+            Transform::from_xyz(x, y, 0.5),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+
+        if day == analytics_state.history_cursor {
+            commands.spawn((
+                Sprite {
+                    color: NEON_PINK.with_alpha(0.6),
+                    custom_size: Some(Vec2::splat(HISTORY_CELL_SIZE + 4.0)),
+                    ..default()
+                },
+                Transform::from_xyz(x, y, 0.4),
+                UiElement,
+                AnalyticsViewElement,
+            ));
+        }
+
+        // Month label above the first column that enters a new month
+        if row == 0 {
+            if let Some(date) = day_to_date(day) {
+                if last_month != Some(date.month()) {
+                    last_month = Some(date.month());
+                    commands.spawn((
+                        Text2d::new(date.format("%b").to_string()),
+                        TextFont {
+                            font: assets.cyberpunk_font.clone(),
+                            font_size: 10.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+                        Transform::from_xyz(x + step, origin.y + step * 1.2, 0.5),
+                        UiElement,
+                        AnalyticsViewElement,
+                    ));
+                }
+            }
+        }
+    }
+
+    // Details for the highlighted day only
+    let cursor = analytics_state.history_cursor;
+    let date = day_to_date(cursor)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let details = match analytics.daily_activity.get(&cursor) {
+        Some(activity) => format!(
+            "{}: {} sessions, {}m {}s played\n{}",
+            date,
+            activity.sessions,
+            activity.play_time_seconds / 60,
+            activity.play_time_seconds % 60,
+            activity.songs.join(", ")
+        ),
+        None => format!("{}: no plays", date),
+    };
+    commands.spawn((
+        Text2d::new(details),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Transform::from_xyz(0.0, origin.y - step * 9.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+
+    commands.spawn((
+        Text2d::new(format!(
+            "Coloring by {} (M to toggle) | Arrows to move | ENTER to list sessions",
+            metric.name()
+        )),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(0.0, origin.y - step * 12.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+}
+
+/// Songs view: per-song stats with a reset action
+fn draw_analytics_songs(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    analytics_state: &AnalyticsState,
+    score_style: ScoreStyle,
+) {
+    let season = analytics_state.season.as_ref();
+    let season_stats = season.and_then(|season| analytics.season_stats(season));
+    let songs = analytics.listed_songs(season);
+    if songs.is_empty() {
+        commands.spawn((
+            Text2d::new(if season.is_some() {
+                "No songs played this season"
+            } else {
+                "No songs recorded"
+            }),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
-                font_size: 24.0,
+                font_size: 16.0,
                 ..default()
             },
-            TextColor(color.into()),
-            Transform::from_xyz(text.position.x, text.position.y - y_offset, 1.0),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(0.0, 120.0, 1.0),
             UiElement,
+            AnalyticsViewElement,
         ));
+        return;
+    }
+    if let Some(song) = &analytics_state.selected_song {
+        draw_analytics_song_detail(commands, assets, analytics, song, season, score_style);
+        return;
+    }
 
-        i += 1;
+    let first = first_listed_song(analytics_state.song_cursor);
+    let rows = songs.iter().enumerate().skip(first).take(LISTED_SONG_ROWS);
+    for (row, (i, song)) in rows.enumerate() {
+        let (plays, best, accuracy) = match season_stats {
+            Some(season_stats) => {
+                let Some(stats) = season_stats.songs.get(*song) else {
+                    continue;
+                };
+                (stats.plays, stats.best_score, stats.best_accuracy)
+            }
+            None => {
+                let Some(stats) = analytics.song_stats.get(*song) else {
+                    continue;
+                };
+                let best = analytics.best_scores.get(*song).copied().unwrap_or(stats.best_score);
+                (stats.play_count, best, stats.best_accuracy)
+            }
+        };
+        let selected = i == analytics_state.song_cursor;
+        commands.spawn((
+            Text2d::new(format!(
+                "{}{} | {} plays | best {} | {:.1}%",
+                if selected { "> " } else { "" },
+                song,
+                plays,
+                format_score(best, score_style),
+                accuracy
+            )),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(if selected { NEON_PINK } else { Color::WHITE }),
+            Transform::from_xyz(0.0, SONG_ROWS_TOP - row as f32 * SONG_ROW_HEIGHT, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
     }
+
+    let (hint, color) = match &analytics_state.pending_song_reset {
+        Some(song) => (
+            format!("Reset all scores and sessions for {}? Y: confirm | N: cancel", song),
+            NEON_ORANGE,
+        ),
+        None => (
+            "Up/Down: select | Enter/click: details | R: reset scores for this song".to_string(),
+            Color::srgba(1.0, 1.0, 1.0, 0.5),
+        ),
+    };
+    let hint_y = SONG_ROWS_TOP - LISTED_SONG_ROWS as f32 * SONG_ROW_HEIGHT - 10.0;
+    commands.spawn((
+        Text2d::new(hint),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(color),
+        Transform::from_xyz(0.0, hint_y, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
 }
 
-/// Setup settings UI
-pub fn setup_settings_ui(mut commands: Commands, assets: Res<GameAssets>, windows: Query<&Window>) {
-    if let Ok(window) = windows.get_single() {
-        let screen_h = window.height();
-        let screen_w = window.width();
+/// Songs view detail panel: a song's lifetime stats and the accuracy of its latest
+/// plays
+fn draw_analytics_song_detail(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    song: &str,
+    season: Option<&Season>,
+    score_style: ScoreStyle,
+) {
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+    let dim = Color::srgba(1.0, 1.0, 1.0, 0.5);
+    let title = match season {
+        Some(_) => format!("{} (lifetime)", song),
+        None => song.to_string(),
+    };
+    let mut lines = vec![(title, 18.0, NEON_CYAN)];
+    let history: &[f32] = match analytics.song_stats.get(song) {
+        Some(stats) => {
+            let best = analytics
+                .best_scores
+                .get(song)
+                .copied()
+                .unwrap_or(stats.best_score);
+            let time = stats.total_play_time_seconds;
+            lines.push((
+                format!(
+                    "Plays: {} | Time played: {}m {}s",
+                    stats.play_count,
+                    time / 60,
+                    time % 60
+                ),
+                14.0,
+                Color::WHITE,
+            ));
+            lines.push((
+                format!(
+                    "Best score: {} | Average score: {}",
+                    format_score(best, score_style),
+                    format_score(stats.average_score.round() as i64, score_style)
+                ),
+                14.0,
+                Color::WHITE,
+            ));
+            let stars = stats
+                .star_rating
+                .map(|stars| format!(" | {:.2}*", stars))
+                .unwrap_or_default();
+            lines.push((
+                format!("Best accuracy: {:.2}%{}", stats.best_accuracy, stars),
+                14.0,
+                Color::WHITE,
+            ));
+            stats.accuracy_history.as_slice()
+        }
+        None => {
+            lines.push(("No stats recorded for this song".to_string(), 14.0, dim));
+            &[]
+        }
+    };
+    for (i, (text, size, color)) in lines.into_iter().enumerate() {
+        commands.spawn((
+            Text2d::new(text),
+            font(size),
+            TextColor(color),
+            Transform::from_xyz(0.0, 170.0 - i as f32 * 26.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
 
+    let area = Rect::new(-220.0, -100.0, 260.0, 60.0);
+    spawn_accuracy_chart(commands, assets, history, area);
+    let footer = [
+        (
+            format!("Accuracy of the last {} plays", history.len()),
+            area.min.y - 34.0,
+        ),
+        (
+            "Up/Down: previous/next song | ESC/Backspace: back to the list".to_string(),
+            area.min.y - 60.0,
+        ),
+    ];
+    for (text, y) in footer {
         commands.spawn((
-            Text2d::new("Settings"),
+            Text2d::new(text),
+            font(12.0),
+            TextColor(dim),
+            Transform::from_xyz(0.0, y, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+}
+
+/// Song rows shown at once in the Songs view
+const LISTED_SONG_ROWS: usize = 12;
+
+/// Height of the first song row in the Songs view
+const SONG_ROWS_TOP: f32 = 160.0;
+
+const SONG_ROW_HEIGHT: f32 = 24.0;
+
+/// First song row shown, keeping the cursor row on screen
+fn first_listed_song(cursor: usize) -> usize {
+    cursor.saturating_sub(LISTED_SONG_ROWS - 1)
+}
+
+/// Index of the song row under a world-space position in the Songs view, if any
+pub fn analytics_song_at(world_pos: Vec2, cursor: usize, count: usize) -> Option<usize> {
+    let row = ((SONG_ROWS_TOP + SONG_ROW_HEIGHT / 2.0 - world_pos.y) / SONG_ROW_HEIGHT).floor();
+    if row < 0.0 || row >= LISTED_SONG_ROWS as f32 || world_pos.x.abs() > 320.0 {
+        return None;
+    }
+    let index = first_listed_song(cursor) + row as usize;
+    (index < count).then_some(index)
+}
+
+/// Sessions listed in the Sessions view
+pub const MAX_LISTED_SESSIONS: usize = 12;
+
+/// Draw the recent session list, optionally filtered to a single day
+fn draw_analytics_sessions(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    analytics_state: &AnalyticsState,
+    score_style: ScoreStyle,
+) {
+    let sessions = analytics.listed_sessions(
+        analytics_state.selected_day,
+        analytics_state.season.as_ref(),
+    );
+
+    if let Some(date) = analytics_state.selected_day.and_then(day_to_date) {
+        commands.spawn((
+            Text2d::new(format!("Sessions on {}", date.format("%Y-%m-%d"))),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
-                font_size: 36.0,
+                font_size: 16.0,
                 ..default()
             },
-            TextColor(NEON_PINK.into()),
-            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            TextColor(NEON_YELLOW),
+            Transform::from_xyz(0.0, 170.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+
+    if sessions.is_empty() {
+        commands.spawn((
+            Text2d::new("No sessions recorded"),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(0.0, 120.0, 1.0),
             UiElement,
+            AnalyticsViewElement,
         ));
+        return;
+    }
+
+    for (i, session) in sessions.iter().take(MAX_LISTED_SESSIONS).enumerate() {
+        let mut row = format!(
+            "{} | {} | {:.1}% | {}",
+            session.song_name,
+            format_score(session.score, score_style),
+            session.accuracy,
+            session.grade.as_str()
+        );
+        if let Some(player) = session.local_versus {
+            row.push_str(&format!(" | VS P{}", player));
+        }
+        if session.pinned {
+            row.push_str(" | PINNED");
+        }
+        let selected = analytics_state.selected_session == Some(i);
+        commands.spawn((
+            Text2d::new(if selected {
+                format!("> {} <", row)
+            } else {
+                row
+            }),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 14.0,
+                ..default()
+            },
+            TextColor(get_grade_color(session.grade.as_str())),
+            Transform::from_xyz(0.0, 140.0 - i as f32 * 24.0, 1.0),
+            UiElement,
+            AnalyticsViewElement,
+        ));
+    }
+
+    // Details of the selected session
+    let detail = match analytics_state
+        .selected_session
+        .and_then(|index| sessions.get(index))
+    {
+        Some(session) => {
+            let played = DateTime::from_timestamp(session.session_id as i64, 0)
+                .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            format!(
+                "Played {} | {}:{:02} | {:.2}x{} | {}",
+                played,
+                session.duration_seconds / 60,
+                session.duration_seconds % 60,
+                session.playback_speed.unwrap_or(1.0),
+                if session.full_combo { " | FC" } else { "" },
+                if session.replay_file.is_some() {
+                    "E: export replay"
+                } else {
+                    "No replay saved"
+                }
+            )
+        }
+        None => "Up/Down: select a session".to_string(),
+    };
+    commands.spawn((
+        Text2d::new(detail),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 13.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
+        Transform::from_xyz(0.0, 140.0 - MAX_LISTED_SESSIONS as f32 * 24.0 - 10.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+
+    // Judgment log of the selected session, loaded from its details file
+    let Some(session) = analytics_state
+        .selected_session
+        .and_then(|index| sessions.get(index))
+    else {
+        return;
+    };
+    let details = match &analytics_state.session_details {
+        Some((_, Some(details))) if session.details_file.is_some() => details.summary(),
+        Some((_, None)) => "Details file unreadable".to_string(),
+        _ => "Details evicted or not recorded".to_string(),
+    };
+    let pin = if session.pinned { "P: unpin" } else { "P: pin" };
+    commands.spawn((
+        Text2d::new(format!("{} | {}", details, pin)),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 13.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(0.0, 140.0 - MAX_LISTED_SESSIONS as f32 * 24.0 - 32.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+}
+
+/// Width of a locked achievement's progress bar
+const ACHIEVEMENT_BAR_WIDTH: f32 = 220.0;
 
+/// Draw unlocked achievements on the left and locked ones with progress on the right
+fn draw_analytics_achievements(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    analytics: &Analytics,
+    definitions: &AchievementDefinitions,
+    season: Option<&Season>,
+) {
+    let stats = analytics.achievement_stats();
+    let locked = definitions.locked_by_progress(&stats, |id| analytics.has_achievement(id));
+    // A season lists only what was unlocked during it; progress stays lifetime
+    let unlocked: Vec<_> = analytics
+        .achievements
+        .iter()
+        .filter(|achievement| {
+            season.is_none_or(|season| {
+                let unlocked_at = achievement
+                    .unlocked_at
+                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                season.contains(unlocked_at as i64)
+            })
+        })
+        .collect();
+
+    let mut heading = format!(
+        "Unlocked {}/{}",
+        definitions.definitions.len() - locked.len(),
+        definitions.definitions.len()
+    );
+    if season.is_some() {
+        heading.push_str(&format!(" ({} this season)", unlocked.len()));
+    }
+    commands.spawn((
+        Text2d::new(heading),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(NEON_YELLOW),
+        Transform::from_xyz(0.0, 170.0, 1.0),
+        UiElement,
+        AnalyticsViewElement,
+    ));
+
+    for (i, achievement) in unlocked.iter().rev().take(12).enumerate() {
         commands.spawn((
-            Text2d::new("Press ESC to go back"),
+            Text2d::new(format!(
+                "{} ({})",
+                achievement.name,
+                achievement.category.name()
+            )),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
-                font_size: 16.0,
+                font_size: 14.0,
                 ..default()
             },
-            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5).into()),
-            Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
+            TextColor(NEON_GREEN),
+            Transform::from_xyz(-250.0, 140.0 - i as f32 * 24.0, 1.0),
             UiElement,
+            AnalyticsViewElement,
         ));
     }
-}
-
-/// Setup practice menu UI
-pub fn setup_practice_menu_ui(
-    mut commands: Commands,
-    assets: Res<GameAssets>,
-    windows: Query<&Window>,
-) {
-    if let Ok(window) = windows.get_single() {
-        let screen_h = window.height();
-        let screen_w = window.width();
 
+    for (i, (definition, progress)) in locked.iter().take(8).enumerate() {
+        let y = 140.0 - i as f32 * 36.0;
         commands.spawn((
-            Text2d::new("Practice Mode"),
+            Text2d::new(format!("{} - {}", definition.name, definition.description)),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
-                font_size: 36.0,
+                font_size: 12.0,
                 ..default()
             },
-            TextColor(NEON_YELLOW.into()),
-            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(200.0, y, 1.0),
             UiElement,
+            AnalyticsViewElement,
         ));
 
+        let Some(progress) = progress else {
+            continue;
+        };
+        let bar_y = y - 14.0;
+        let filled = ACHIEVEMENT_BAR_WIDTH * progress.fraction();
         commands.spawn((
-            Text2d::new("Press ESC to go back"),
-            TextFont {
-                font: assets.cyberpunk_font.clone(),
-                font_size: 16.0,
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, 0.1),
+                custom_size: Some(Vec2::new(ACHIEVEMENT_BAR_WIDTH, 6.0)),
                 ..default()
             },
-            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5).into()),
-            Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
+            Transform::from_xyz(170.0, bar_y, 0.5),
             UiElement,
+            AnalyticsViewElement,
         ));
-    }
-}
-
-/// Setup analytics UI
-pub fn setup_analytics_ui(
-    mut commands: Commands,
-    assets: Res<GameAssets>,
-    windows: Query<&Window>,
-) {
-    if let Ok(window) = windows.get_single() {
-        let screen_h = window.height();
-        let screen_w = window.width();
-
         commands.spawn((
-            Text2d::new("Analytics"),
-            TextFont {
-                font: assets.cyberpunk_font.clone(),
-                font_size: 36.0,
+            Sprite {
+                color: NEON_CYAN,
+                custom_size: Some(Vec2::new(filled, 6.0)),
                 ..default()
             },
-            TextColor(NEON_PINK.into()),
-            Transform::from_xyz(0.0, screen_h / 2.0 - 60.0, 1.0),
+            Transform::from_xyz(170.0 - (ACHIEVEMENT_BAR_WIDTH - filled) / 2.0, bar_y, 0.6),
             UiElement,
+            AnalyticsViewElement,
         ));
-
         commands.spawn((
-            Text2d::new("Press ESC to go back"),
+            Text2d::new(definition.condition.progress_label(*progress)),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
-                font_size: 16.0,
+                font_size: 11.0,
                 ..default()
             },
-            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5).into()),
-            Transform::from_xyz(-screen_w / 2.0 + 20.0, -screen_h / 2.0 + 20.0, 1.0),
+            TextColor(NEON_CYAN),
+            Transform::from_xyz(170.0 + ACHIEVEMENT_BAR_WIDTH / 2.0 + 45.0, bar_y, 1.0),
             UiElement,
+            AnalyticsViewElement,
         ));
     }
 }
@@ -579,6 +4907,8 @@ pub fn setup_end_ui(
     assets: Res<GameAssets>,
     windows: Query<&Window>,
     end_data: Res<EndData>,
+    config: Res<GameConfig>,
+    recommendation: Res<NextRecommendation>,
 ) {
     if let Ok(window) = windows.get_single() {
         let scr_width = window.width();
@@ -592,24 +4922,61 @@ pub fn setup_end_ui(
                 font_size: 48.0,
                 ..default()
             },
-            TextColor(NEON_PINK.into()),
+            TextColor(NEON_PINK),
             Transform::from_xyz(0.0, scr_height * 0.3, 1.0),
             UiElement,
         ));
 
+        // Incognito indicator
+        if end_data.state.incognito {
+            commands.spawn((
+                Text2d::new("INCOGNITO - not recorded"),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+                Transform::from_xyz(scr_width / 2.0 - 120.0, scr_height / 2.0 - 30.0, 1.0),
+                UiElement,
+            ));
+        }
+
         // Score
         commands.spawn((
-            Text2d::new(format!("Score: {}", end_data.state.score)),
+            Text2d::new(format!(
+                "Score: {}",
+                format_score(end_data.state.score, config.hud_layout.score_style)
+            )),
             TextFont {
                 font: assets.cyberpunk_font.clone(),
                 font_size: 32.0,
                 ..default()
             },
-            TextColor(NEON_BLUE.into()),
+            TextColor(NEON_BLUE),
             Transform::from_xyz(0.0, scr_height * 0.1, 1.0),
             UiElement,
         ));
 
+        // Modifiers the score was set with
+        if !end_data.state.modifiers.is_empty() {
+            commands.spawn((
+                Text2d::new(format!(
+                    "{}  (score x{:.2})",
+                    mods_label(&end_data.state.modifiers),
+                    end_data.state.score_multiplier
+                )),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(NEON_YELLOW),
+                Transform::from_xyz(0.0, scr_height * 0.1 - 32.0, 1.0),
+                UiElement,
+            ));
+        }
+
         // Grade
         commands.spawn((
             Text2d::new(format!("Grade: {}", end_data.state.grade.as_str())),
@@ -618,7 +4985,7 @@ pub fn setup_end_ui(
                 font_size: 40.0,
                 ..default()
             },
-            TextColor(get_grade_color(end_data.state.grade.as_str()).into()),
+            TextColor(get_grade_color(end_data.state.grade.as_str())),
             Transform::from_xyz(0.0, 0.0, 1.0),
             UiElement,
         ));
@@ -631,11 +4998,127 @@ pub fn setup_end_ui(
                 font_size: 24.0,
                 ..default()
             },
-            TextColor(NEON_GREEN.into()),
+            TextColor(NEON_GREEN),
             Transform::from_xyz(0.0, -scr_height * 0.1, 1.0),
             UiElement,
         ));
 
+        // Recommended next song
+        if let Some(next) = &recommendation.0 {
+            let card = recommendation_card(scr_width, scr_height);
+            let center = card.center();
+            commands
+                .spawn((
+                    Sprite {
+                        color: Color::srgba(0.05, 0.05, 0.1, 0.9),
+                        custom_size: Some(card.size()),
+                        ..default()
+                    },
+                    Transform::from_xyz(center.x, center.y, 0.5),
+                    UiElement,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text2d::new("Recommended next"),
+                        TextFont {
+                            font: assets.cyberpunk_font.clone(),
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(NEON_PINK),
+                        Transform::from_xyz(0.0, 20.0, 0.1),
+                    ));
+                    parent.spawn((
+                        Text2d::new(next.label()),
+                        TextFont {
+                            font: assets.cyberpunk_font.clone(),
+                            font_size: 14.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                        Transform::from_xyz(0.0, -2.0, 0.1),
+                    ));
+                    parent.spawn((
+                        Text2d::new("Click to play"),
+                        TextFont {
+                            font: assets.cyberpunk_font.clone(),
+                            font_size: 11.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+                        Transform::from_xyz(0.0, -24.0, 0.1),
+                    ));
+                });
+        }
+
+        // Where this play's hits landed, below the recommendation card
+        if let Some(precision) = &end_data.state.precision {
+            spawn_precision_map(
+                &mut commands,
+                &assets,
+                precision,
+                Vec2::new(-scr_width * 0.32 - 60.0, -scr_height * 0.1),
+                45.0,
+            );
+        }
+
+        // Where the player finished among the local room's bots
+        if !end_data.state.room.is_empty() {
+            let top_left = Vec2::new(scr_width * 0.22, scr_height * 0.1);
+            commands.spawn((
+                Text2d::new("Room"),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(NEON_PINK),
+                bevy::sprite::Anchor::CenterLeft,
+                Transform::from_xyz(top_left.x, top_left.y + 30.0, 1.0),
+                UiElement,
+            ));
+            spawn_standings(
+                &mut commands,
+                &end_data.state.room,
+                config.hud_layout.score_style,
+                top_left,
+                16.0,
+                &assets,
+            );
+        }
+
+        // Layout seed, to replay the same map or share it
+        if let Some(seed) = end_data.state.seed {
+            commands.spawn((
+                Text2d::new(format!(
+                    "Layout seed {}  |  L: replay this layout  |  C: copy seed",
+                    format_seed(seed)
+                )),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+                Transform::from_xyz(0.0, -scr_height * 0.3 - 30.0, 1.0),
+                UiElement,
+            ));
+        }
+
+        if end_data.state.replay.is_some() {
+            commands.spawn((
+                Text2d::new("E: export replay"),
+                TextFont {
+                    font: assets.cyberpunk_font.clone(),
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+                Transform::from_xyz(0.0, -scr_height * 0.3 - 52.0, 1.0),
+                UiElement,
+            ));
+        }
+
         // Continue prompt
         commands.spawn((
             Text2d::new("Click or press ENTER to continue"),
@@ -644,9 +5127,432 @@ pub fn setup_end_ui(
                 font_size: 20.0,
                 ..default()
             },
-            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7).into()),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
             Transform::from_xyz(0.0, -scr_height * 0.3, 1.0),
             UiElement,
         ));
     }
 }
+
+/// Marker for the results screen notice, rebuilt when it changes
+#[derive(Component)]
+pub struct ResultsNoticeElement;
+
+/// Show the outcome of the last results screen action, such as a replay export
+pub fn draw_results_notice(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    notice: Res<ResultsNotice>,
+    existing: Query<Entity, With<ResultsNoticeElement>>,
+) {
+    if !notice.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(text) = &notice.0 else {
+        return;
+    };
+    commands.spawn((
+        Text2d::new(text.clone()),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(NEON_YELLOW),
+        Transform::from_xyz(0.0, -window.height() * 0.3 - 75.0, 1.0),
+        UiElement,
+        ResultsNoticeElement,
+    ));
+}
+
+/// Marker for results scrubber entities, rebuilt as the cursor moves
+#[derive(Component)]
+pub struct ScrubberElement;
+
+/// Seconds of notes shown either side of the scrubber cursor in the inset
+const SCRUBBER_INSET_WINDOW: f64 = 1.5;
+
+/// Area of the results screen's recommended next song card, left of the score
+pub fn recommendation_card(scr_width: f32, scr_height: f32) -> Rect {
+    Rect::from_center_size(
+        Vec2::new(-scr_width * 0.32, scr_height * 0.05),
+        Vec2::new(280.0, 80.0),
+    )
+}
+
+/// Strip geometry for the results scrubber: (center y, width)
+pub fn scrubber_strip(scr_width: f32, scr_height: f32) -> (f32, f32) {
+    (-scr_height * 0.2, scr_width * 0.8)
+}
+
+/// Song length covered by the scrubber strip
+pub fn scrubber_duration(end_state: &EndState) -> f64 {
+    end_state
+        .judgment_log
+        .as_ref()
+        .and_then(|log| log.entries.iter().map(|e| e.time).reduce(f64::max))
+        .map(|t| t + 1.0)
+        .unwrap_or(0.0)
+}
+
+/// Draw the judgment timeline strip and the inset of notes around the cursor
+pub fn draw_results_scrubber(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    windows: Query<&Window>,
+    end_data: Res<EndData>,
+    scrubber: Res<ResultsScrubber>,
+    existing: Query<Entity, With<ScrubberElement>>,
+) {
+    if !scrubber.is_changed() {
+        return;
+    }
+    // Old sessions have no log: the scrubber is skipped entirely
+    let Some(log) = end_data.state.judgment_log.as_ref() else {
+        return;
+    };
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let duration = scrubber_duration(&end_data.state);
+    if duration <= 0.0 {
+        return;
+    }
+
+    let (strip_y, strip_w) = scrubber_strip(window.width(), window.height());
+    let time_to_x = |t: f64| -strip_w / 2.0 + (t / duration) as f32 * strip_w;
+
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(1.0, 1.0, 1.0, 0.1),
+            custom_size: Some(Vec2::new(strip_w, 24.0)),
+            ..default()
+        },
+        Transform::from_xyz(0.0, strip_y, 0.5),
+        UiElement,
+        ScrubberElement,
+    ));
+
+    // One tick per judgment; misses are taller so they stand out
+    for entry in &log.entries {
+        let (r, g, b) = entry.kind.color();
+        let height = if entry.kind == crate::analytics::JudgmentKind::Miss {
+            24.0
+        } else {
+            12.0
+        };
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(r, g, b, 0.8),
+                custom_size: Some(Vec2::new(1.5, height)),
+                ..default()
+            },
+            Transform::from_xyz(time_to_x(entry.time), strip_y, 0.6),
+            UiElement,
+            ScrubberElement,
+        ));
+    }
+
+    // Cursor
+    commands.spawn((
+        Sprite {
+            color: Color::WHITE,
+            custom_size: Some(Vec2::new(2.0, 32.0)),
+            ..default()
+        },
+        Transform::from_xyz(time_to_x(scrubber.cursor_time), strip_y, 0.7),
+        UiElement,
+        ScrubberElement,
+    ));
+
+    let minutes = (scrubber.cursor_time / 60.0) as u32;
+    let seconds = scrubber.cursor_time % 60.0;
+    commands.spawn((
+        Text2d::new(format!("{}:{:04.1}  (drag or LEFT/RIGHT to scrub)", minutes, seconds)),
+        TextFont {
+            font: assets.cyberpunk_font.clone(),
+            font_size: 12.0,
+            ..default()
+        },
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+        Transform::from_xyz(0.0, strip_y - 26.0, 1.0),
+        UiElement,
+        ScrubberElement,
+    ));
+
+    // A truncated log only gets the strip, not the position inset
+    if log.truncated {
+        return;
+    }
+
+    let inset_size = Vec2::new(240.0, 180.0);
+    let inset_center = Vec2::new(window.width() * 0.32, window.height() * 0.05);
+    let scale = inset_size.x / window.width();
+
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(0.05, 0.05, 0.1, 0.9),
+            custom_size: Some(inset_size),
+            ..default()
+        },
+        Transform::from_xyz(inset_center.x, inset_center.y, 0.5),
+        UiElement,
+        ScrubberElement,
+    ));
+
+    for entry in log
+        .entries
+        .iter()
+        .filter(|e| (e.time - scrubber.cursor_time).abs() <= SCRUBBER_INSET_WINDOW)
+    {
+        // Notes further from the cursor fade out
+        let distance = (entry.time - scrubber.cursor_time).abs() / SCRUBBER_INSET_WINDOW;
+        let alpha = 1.0 - distance as f32 * 0.7;
+        let (r, g, b) = entry.kind.color();
+        let pos = inset_center + entry.position * scale;
+        commands.spawn((
+            Sprite {
+                color: Color::srgba(r, g, b, alpha),
+                custom_size: Some(Vec2::splat(8.0)),
+                ..default()
+            },
+            Transform::from_xyz(pos.x, pos.y, 0.6),
+            UiElement,
+            ScrubberElement,
+        ));
+    }
+}
+
+/// Spawn a straight line between two points as a thin rotated sprite, None if the
+/// points are the same
+fn spawn_line<'a>(
+    commands: &'a mut Commands,
+    from: Vec2,
+    to: Vec2,
+    width: f32,
+    color: Color,
+    z: f32,
+) -> Option<EntityCommands<'a>> {
+    let segment = to - from;
+    let length = segment.length();
+    if length <= 0.0 {
+        return None;
+    }
+    let center = from + segment / 2.0;
+    let line = commands.spawn((
+        Sprite {
+            color,
+            custom_size: Some(Vec2::new(length, width)),
+            ..default()
+        },
+        Transform::from_xyz(center.x, center.y, z)
+            .with_rotation(Quat::from_rotation_z(segment.y.atan2(segment.x))),
+        UiElement,
+    ));
+    Some(line)
+}
+
+/// Spawn a hit precision heatmap: a disc standing for a circle, shaded by how many hits
+/// landed on each spot, with the early/late timing histogram to its right. Everything
+/// is a child of the returned entity, centered on the disc
+pub fn spawn_precision_map<'a>(
+    commands: &'a mut Commands,
+    assets: &GameAssets,
+    map: &PrecisionMap,
+    center: Vec2,
+    radius: f32,
+) -> EntityCommands<'a> {
+    let cell = radius * 2.0 / POSITION_BINS as f32;
+    let histogram_width = radius * 2.4;
+    let histogram_height = radius;
+    let histogram_x = radius + 20.0 + histogram_width / 2.0;
+    let baseline = -histogram_height / 2.0;
+    let font = TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: 11.0,
+        ..default()
+    };
+
+    let mut root = commands.spawn((
+        Transform::from_xyz(center.x, center.y, 1.0),
+        Visibility::default(),
+        UiElement,
+    ));
+    root.with_children(|parent| {
+        // Only bins whose centers fall inside the circle make up the disc
+        for (index, density) in normalize(&map.positions).into_iter().enumerate() {
+            let offset = position_bin_center(index);
+            if offset.length() > 1.0 {
+                continue;
+            }
+            let color = if density <= 0.0 {
+                Color::srgba(1.0, 1.0, 1.0, 0.08)
+            } else {
+                NEON_PINK.with_alpha(0.25 + 0.75 * density)
+            };
+            parent.spawn((
+                Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(cell)),
+                    ..default()
+                },
+                Transform::from_xyz(offset.x * radius, offset.y * radius, 0.0),
+                UiElement,
+            ));
+        }
+
+        // Crosshair through the circle center
+        for size in [Vec2::new(radius * 2.0, 1.0), Vec2::new(1.0, radius * 2.0)] {
+            parent.spawn((
+                Sprite {
+                    color: Color::srgba(1.0, 1.0, 1.0, 0.3),
+                    custom_size: Some(size),
+                    ..default()
+                },
+                Transform::from_xyz(0.0, 0.0, 0.1),
+                UiElement,
+            ));
+        }
+
+        // Timing histogram, early on the left, with a line at on time
+        let bar_width = histogram_width / TIMING_BINS as f32;
+        for (index, density) in normalize(&map.timing).into_iter().enumerate() {
+            let height = (density * histogram_height).max(1.0);
+            let x = histogram_x - histogram_width / 2.0 + (index as f32 + 0.5) * bar_width;
+            parent.spawn((
+                Sprite {
+                    color: NEON_CYAN.with_alpha(0.25 + 0.75 * density),
+                    custom_size: Some(Vec2::new((bar_width - 1.0).max(1.0), height)),
+                    ..default()
+                },
+                Transform::from_xyz(x, baseline + height / 2.0, 0.0),
+                UiElement,
+            ));
+        }
+        parent.spawn((
+            Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, 0.5),
+                custom_size: Some(Vec2::new(1.0, histogram_height + 8.0)),
+                ..default()
+            },
+            Transform::from_xyz(histogram_x, 0.0, 0.1),
+            UiElement,
+        ));
+
+        parent.spawn((
+            Text2d::new(format!(
+                "-{0:.0}ms  early | late  +{0:.0}ms",
+                TIMING_RANGE_MS
+            )),
+            font.clone(),
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.6)),
+            Transform::from_xyz(histogram_x, baseline - 12.0, 0.1),
+            UiElement,
+        ));
+        parent.spawn((
+            Text2d::new(
+                map.summary()
+                    .unwrap_or_else(|| "No hits recorded".to_string()),
+            ),
+            font,
+            TextColor(Color::WHITE),
+            Transform::from_xyz(histogram_x, histogram_height / 2.0 + 12.0, 0.1),
+            UiElement,
+        ));
+    });
+    root
+}
+
+/// Spawn a radar chart of difficulty metrics centered on `center`
+pub fn spawn_difficulty_chart(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    center: Vec2,
+    radius: f32,
+    metrics: &DifficultyMetrics,
+) {
+    let axes = metrics.axes();
+    let direction = |i: usize| {
+        // First axis points straight up, the rest go clockwise
+        let angle = std::f32::consts::FRAC_PI_2 - i as f32 * std::f32::consts::TAU / axes.len() as f32;
+        Vec2::new(angle.cos(), angle.sin())
+    };
+
+    // Outer ring and axes
+    for i in 0..axes.len() {
+        let next = (i + 1) % axes.len();
+        spawn_line(
+            commands,
+            center + direction(i) * radius,
+            center + direction(next) * radius,
+            1.0,
+            NEON_PURPLE.with_alpha(0.5),
+            1.0,
+        );
+        spawn_line(
+            commands,
+            center,
+            center + direction(i) * radius,
+            1.0,
+            NEON_PURPLE.with_alpha(0.3),
+            1.0,
+        );
+    }
+
+    // Metric polygon
+    let point = |i: usize| {
+        let value = (axes[i].1 / METRIC_SCALE_MAX).clamp(0.0, 1.0);
+        center + direction(i) * radius * value
+    };
+    for i in 0..axes.len() {
+        let next = (i + 1) % axes.len();
+        spawn_line(commands, point(i), point(next), 2.0, NEON_CYAN, 1.1);
+    }
+
+    // Axis labels
+    for (i, (label, _)) in axes.iter().enumerate() {
+        let position = center + direction(i) * (radius + 8.0);
+        commands.spawn((
+            Text2d::new(*label),
+            TextFont {
+                font: assets.cyberpunk_font.clone(),
+                font_size: 10.0,
+                ..default()
+            },
+            TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
+            Transform::from_xyz(position.x, position.y, 1.2),
+            UiElement,
+        ));
+    }
+}
+
+/// One-line difficulty summary: stars, object counts and length
+pub fn difficulty_summary_text(beatmap: &Beatmap, summary: Option<DifficultySummary>) -> String {
+    let stats = beatmap.get_object_stats();
+    let length = beatmap.get_duration().max(0.0) as u64;
+    let stars = summary
+        .map(|summary| format!("{:.2}*", summary.star_rating()))
+        .unwrap_or_else(|| "-".to_string());
+    format!(
+        "{}  {} obj ({}c/{}s/{}sp)  {}:{:02}",
+        stars,
+        stats.total,
+        stats.circles,
+        stats.sliders,
+        stats.spinners,
+        length / 60,
+        length % 60
+    )
+}
@@ -0,0 +1,1114 @@
+// src/versus.rs
+
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+use crate::analytics::{ActiveSession, Analytics, Grade, GradeRules, HitStats, JudgmentKind};
+use crate::bots::{simulate_run, BotJudgment, BotProfile, BOT_TAG};
+use crate::config::{GameConfig, KeyBindings, ThemeConfig};
+use crate::constants::*;
+use crate::event_bus::{BusEvent, EventBus, FinishedSession};
+use crate::game::{calculate_score_from_timing, draw_circles_bevy};
+use crate::gamemode::{GameSettings, Modifier};
+use crate::multiplayer::{PickerRotation, RematchOutcome, RematchVote};
+use crate::performance::RenderGates;
+use crate::score_filter::PlaySignature;
+use crate::score_format::{format_score, ScoreStyle};
+use crate::simulation::{judgment_ms, step_seconds, SongClock};
+use crate::structs::{FloatingText, GameAssets, GameAudioSink, GameCircle, GameStateResource};
+use crate::ui::{draw_floating_texts_bevy, UiElement};
+use crate::AppState;
+
+/// One of the two players sharing the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersusPlayer {
+    One,
+    Two,
+}
+
+impl VersusPlayer {
+    pub const BOTH: [VersusPlayer; 2] = [VersusPlayer::One, VersusPlayer::Two];
+
+    /// Index of the player's side in `VersusData::sides`
+    pub fn index(self) -> usize {
+        match self {
+            VersusPlayer::One => 0,
+            VersusPlayer::Two => 1,
+        }
+    }
+
+    /// Player number shown on screen and stored with the session
+    pub fn number(self) -> u8 {
+        self.index() as u8 + 1
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VersusPlayer::One => "Player 1",
+            VersusPlayer::Two => "Player 2",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            VersusPlayer::One => NEON_CYAN,
+            VersusPlayer::Two => NEON_PINK,
+        }
+    }
+
+    /// The player's two hit keys as shown on screen, "A/S". Player one uses the normal
+    /// bindings
+    pub fn keys_label(self, bindings: &KeyBindings) -> String {
+        let [primary, secondary] = match self {
+            VersusPlayer::One => [&bindings.primary_hit, &bindings.secondary_hit],
+            VersusPlayer::Two => [&bindings.versus_primary_hit, &bindings.versus_secondary_hit],
+        };
+        format!("{}/{}", key_name(primary), key_name(secondary))
+    }
+
+    /// Stand-in id of the player in a rematch vote or the song pick rotation
+    fn voter_id(self) -> Uuid {
+        Uuid::from_u128(self.number() as u128)
+    }
+
+    fn hit_keys(self, bindings: &KeyBindings) -> [KeyCode; 2] {
+        match self {
+            VersusPlayer::One => [bindings.primary_hit_key(), bindings.secondary_hit_key()],
+            VersusPlayer::Two => [
+                bindings.versus_primary_hit_key(),
+                bindings.versus_secondary_hit_key(),
+            ],
+        }
+    }
+
+    /// Where a point of the shared layout is drawn on this player's half of the screen.
+    /// Player two's half is a mirror image of player one's
+    pub fn to_screen(self, position: Vec2, screen: Vec2) -> Vec2 {
+        match self {
+            VersusPlayer::One => Vec2::new(position.x - screen.x / 4.0, position.y),
+            VersusPlayer::Two => Vec2::new(screen.x / 4.0 - position.x, position.y),
+        }
+    }
+}
+
+/// Player a hit key belongs to. A key bound for both players belongs to neither, so a
+/// press can never count for the other side
+pub fn route_hit_key(key: KeyCode, bindings: &KeyBindings) -> Option<VersusPlayer> {
+    let owners: Vec<VersusPlayer> = VersusPlayer::BOTH
+        .into_iter()
+        .filter(|player| player.hit_keys(bindings).contains(&key))
+        .collect();
+    match owners.as_slice() {
+        [player] => Some(*player),
+        _ => None,
+    }
+}
+
+/// Which players pressed a hit key this frame, indexed like `VersusData::sides`. At
+/// most one press per player per frame, like solo play
+fn pressed_players<'a>(
+    keys: impl IntoIterator<Item = &'a KeyCode>,
+    bindings: &KeyBindings,
+) -> [bool; 2] {
+    let mut pressed = [false; 2];
+    for key in keys {
+        if let Some(player) = route_hit_key(*key, bindings) {
+            pressed[player.index()] = true;
+        }
+    }
+    pressed
+}
+
+/// Binding name as shown to players, "KeyA" as "A"
+fn key_name(binding: &str) -> &str {
+    binding.strip_prefix("Key").unwrap_or(binding)
+}
+
+/// Finished solo plays an adaptive bot opponent is tuned to
+pub const ADAPTIVE_BOT_SESSIONS: usize = 10;
+
+/// Accuracies of the player's latest finished solo plays, for an adaptive bot
+pub fn recent_solo_accuracies(analytics: &Analytics) -> Vec<f32> {
+    analytics
+        .recent_sessions
+        .iter()
+        .rev()
+        .filter(|session| session.completed && session.counts_for_completion())
+        .take(ADAPTIVE_BOT_SESSIONS)
+        .map(|session| session.accuracy)
+        .collect()
+}
+
+/// Opponent after `current` when cycling in song selection: a human player two,
+/// then each bot profile in turn
+pub fn next_versus_opponent(
+    current: Option<BotProfile>,
+    recent_accuracies: &[f32],
+) -> Option<BotProfile> {
+    let choices = BotProfile::choices(recent_accuracies);
+    match current {
+        None => choices.first().copied(),
+        Some(profile) => choices
+            .iter()
+            .position(|choice| choice.display_name() == profile.display_name())
+            .and_then(|index| choices.get(index + 1))
+            .copied(),
+    }
+}
+
+/// One player's half of a match. The object layout is shared; a side only keeps how
+/// far it got through it and its own scoring
+#[derive(Debug, Clone)]
+pub struct VersusSide {
+    pub player: VersusPlayer,
+    /// Objects before this index have been judged. Presses always go to the earliest
+    /// object still open, so the judged objects are always a prefix of the layout
+    pub next: usize,
+    /// Judgment of each object so far, indexed like the shared layout
+    pub judgments: Vec<JudgmentKind>,
+    pub combo: u32,
+    pub max_combo: u32,
+    /// Score, judgment counts and timings, recorded to analytics at the end
+    pub session: ActiveSession,
+    /// Judgment popups, at layout positions
+    pub floating_texts: Vec<FloatingText>,
+    /// Presses waiting to be judged (ms on the simulation grid)
+    pending: VecDeque<i64>,
+    /// Bot playing this side instead of a player at the keyboard
+    pub bot: Option<BotProfile>,
+    /// The bot's judgment of each object, indexed like the shared layout
+    bot_plan: Vec<BotJudgment>,
+}
+
+impl VersusSide {
+    pub fn new(player: VersusPlayer, mut session: ActiveSession) -> Self {
+        session.local_versus = Some(player.number());
+        Self {
+            player,
+            next: 0,
+            judgments: Vec::new(),
+            combo: 0,
+            max_combo: 0,
+            session,
+            floating_texts: Vec::new(),
+            pending: VecDeque::new(),
+            bot: None,
+            bot_plan: Vec::new(),
+        }
+    }
+
+    /// Hand this side to a bot, its whole run decided by the seed and the object times
+    pub fn with_bot(mut self, profile: BotProfile, seed: u64, circles: &[GameCircle]) -> Self {
+        let hit_times: Vec<f64> = circles.iter().map(|circle| circle.hit_time).collect();
+        let mut plan = simulate_run(seed, profile, &hit_times);
+        plan.sort_by_key(|judgment| judgment.circle_id);
+        self.bot = Some(profile);
+        self.bot_plan = plan;
+        self
+    }
+
+    /// Name shown for this side, "[BOT] Hard" for a bot
+    pub fn name(&self) -> String {
+        match self.bot {
+            Some(profile) => format!("{} {}", BOT_TAG, profile.display_name()),
+            None => self.player.label().to_string(),
+        }
+    }
+
+    /// Play the bot's planned judgments of the objects whose time has come. An early
+    /// hit lands when the bot presses, a late hit or a miss at the object's time, so
+    /// the bot's progress never runs behind the objects on screen
+    pub fn advance_bot(&mut self, circles: &[GameCircle], now: f64, settings: &GameSettings) {
+        while let (Some(circle), Some(judgment)) = (
+            circles.get(self.next),
+            self.bot_plan.get(self.next).copied(),
+        ) {
+            let offset = judgment.time - circle.hit_time;
+            if now < circle.hit_time + offset.min(0.0) {
+                break;
+            }
+            let points = judgment.score as i32;
+            if points > 0 {
+                self.session
+                    .record_hit(points, (offset.abs() * 1000.0) as f32);
+                self.combo += 1;
+                self.max_combo = self.max_combo.max(self.combo);
+            } else if !settings.has_modifier(Modifier::NoFail) {
+                self.session.record_miss();
+                self.combo = 0;
+            }
+            self.finish_object(circle, JudgmentKind::from_points(points), now);
+        }
+    }
+
+    /// Queue a hit key press; presses must arrive in time order
+    pub fn push_input(&mut self, time_ms: i64) {
+        self.pending.push_back(time_ms);
+    }
+
+    /// Judge presses up to `target_ms` and misses before it against the shared objects.
+    /// Like the solo judgment clock, a press is judged before objects expiring on its
+    /// step, and misses on `target_ms` wait for the next call
+    pub fn advance(
+        &mut self,
+        circles: &[GameCircle],
+        target_ms: i64,
+        shrink_time: f64,
+        settings: &GameSettings,
+    ) {
+        while let Some(time_ms) = self.pending.front().copied() {
+            if time_ms > target_ms {
+                break;
+            }
+            self.pending.pop_front();
+            self.expire(circles, step_seconds(time_ms - 1), shrink_time, settings);
+            self.judge(circles, step_seconds(time_ms), settings);
+        }
+        self.expire(circles, step_seconds(target_ms - 1), shrink_time, settings);
+    }
+
+    /// Miss every open object whose approach ended before `now`
+    fn expire(
+        &mut self,
+        circles: &[GameCircle],
+        now: f64,
+        shrink_time: f64,
+        settings: &GameSettings,
+    ) {
+        while let Some(circle) = circles.get(self.next) {
+            if now - circle.spawn_time <= shrink_time {
+                break;
+            }
+            // No fail keeps the combo and the judgment counts through misses
+            if !settings.has_modifier(Modifier::NoFail) {
+                self.session.record_miss();
+                self.combo = 0;
+            }
+            self.finish_object(circle, JudgmentKind::Miss, now);
+        }
+    }
+
+    /// Judge a press at `now` against the earliest open object, if it has appeared
+    fn judge(&mut self, circles: &[GameCircle], now: f64, settings: &GameSettings) {
+        let Some(circle) = circles.get(self.next) else {
+            return;
+        };
+        if now < circle.spawn_time {
+            return;
+        }
+        let difference = (now - circle.hit_time).abs();
+        let points = calculate_score_from_timing(difference, settings);
+        self.session
+            .record_hit(points, (difference * 1000.0) as f32);
+        if points > 0 {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        } else {
+            self.combo = 0;
+        }
+        self.finish_object(circle, JudgmentKind::from_points(points), now);
+    }
+
+    fn finish_object(&mut self, circle: &GameCircle, kind: JudgmentKind, now: f64) {
+        self.judgments.push(kind);
+        self.next += 1;
+        let text = match kind {
+            JudgmentKind::Perfect => "Perfect!",
+            JudgmentKind::Good => "Good!",
+            JudgmentKind::Okay => "Okay",
+            JudgmentKind::Miss => "Miss",
+        };
+        self.floating_texts.push(FloatingText {
+            text: text.to_string(),
+            position: circle.position,
+            spawn_time: now,
+            duration: 1.0,
+            color: kind.color(),
+            scale: 1.0,
+        });
+    }
+
+    pub fn score(&self) -> i64 {
+        self.session.score
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        self.session.current_accuracy()
+    }
+
+    /// Final result of this side
+    pub fn result(&self) -> VersusResult {
+        VersusResult {
+            player: self.player,
+            name: self.name(),
+            score: self.session.score,
+            accuracy: self.session.current_accuracy(),
+            grade: self.session.hits.grade(&GradeRules::SCORE_V1),
+            max_combo: self.max_combo,
+            hits: self.session.hits.clone(),
+        }
+    }
+}
+
+/// A local versus match in progress. Both players play the same objects, generated
+/// once for half a screen; only the judgments and scoring are kept per player. Game
+/// modes with lives or a time limit don't apply, a match always runs to the end.
+/// Player two can be a bot for practicing alone
+#[derive(Resource)]
+pub struct VersusData {
+    /// Objects both players play, laid out around the origin. Their hit flags are
+    /// unused; each side keeps its own progress
+    pub circles: Vec<GameCircle>,
+    pub sides: [VersusSide; 2],
+    /// Song time, kept in step with the audio output
+    pub song_clock: SongClock,
+    pub song_name: String,
+    pub game_settings: GameSettings,
+    pub theme: ThemeConfig,
+    pub score_style: ScoreStyle,
+    pub seed: u64,
+}
+
+impl VersusData {
+    pub fn new(
+        circles: Vec<GameCircle>,
+        config: &GameConfig,
+        song_name: &str,
+        star_rating: Option<f32>,
+        seed: u64,
+        song_clock: SongClock,
+        bot: Option<BotProfile>,
+    ) -> Self {
+        let game_settings = config.game_settings.clone();
+        let session = {
+            let mut session = ActiveSession::new(song_name.to_string(), false, 1.0);
+            session.unranked =
+                game_settings.is_auto() || game_settings.has_modifier(Modifier::NoFail);
+            session.signature = PlaySignature::new(&game_settings.modifiers, 1.0);
+            session.score_multiplier = game_settings.score_multiplier();
+            session.star_rating = star_rating;
+            session.seed = Some(seed);
+            session
+        };
+        let mut sides = VersusPlayer::BOTH.map(|player| VersusSide::new(player, session.clone()));
+        if let Some(profile) = bot {
+            sides[1] = sides[1].clone().with_bot(profile, seed, &circles);
+        }
+        Self {
+            circles,
+            sides,
+            song_clock,
+            song_name: song_name.to_string(),
+            game_settings,
+            theme: config.theme.clone(),
+            score_style: config.hud_layout.score_style,
+            seed,
+        }
+    }
+
+    /// End the players' sessions for analytics; a bot's run is never recorded
+    fn finish_sessions(&mut self, completed: bool, bus: &mut EventBus) {
+        for side in self.sides.iter().filter(|side| side.bot.is_none()) {
+            let mut session = side.session.clone();
+            session.completed = completed;
+            bus.emit(BusEvent::SessionFinished(Box::new(FinishedSession {
+                session: session.finish(),
+                replay: None,
+            })));
+        }
+    }
+}
+
+/// How one player did in a match
+#[derive(Debug, Clone)]
+pub struct VersusResult {
+    pub player: VersusPlayer,
+    /// Player or bot name shown on the results
+    pub name: String,
+    pub score: i64,
+    pub accuracy: f32,
+    pub grade: Grade,
+    pub max_combo: u32,
+    pub hits: HitStats,
+}
+
+/// Player who won: the higher score, then the higher accuracy; None for a draw
+pub fn versus_winner(a: &VersusResult, b: &VersusResult) -> Option<VersusPlayer> {
+    match a
+        .score
+        .cmp(&b.score)
+        .then(a.accuracy.total_cmp(&b.accuracy))
+    {
+        std::cmp::Ordering::Greater => Some(a.player),
+        std::cmp::Ordering::Less => Some(b.player),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Players who pick songs in turn: both, unless a bot plays player two
+fn pickers(bot: bool) -> Vec<Uuid> {
+    let players = if bot { 1 } else { 2 };
+    VersusPlayer::BOTH[..players]
+        .iter()
+        .map(|player| player.voter_id())
+        .collect()
+}
+
+/// Player whose turn it is to pick the next versus song
+pub fn versus_picker(rotation: Option<&PickerRotation>, bot: bool) -> VersusPlayer {
+    rotation
+        .and_then(|rotation| rotation.picker(&pickers(bot)))
+        .and_then(|id| {
+            VersusPlayer::BOTH
+                .into_iter()
+                .find(|player| player.voter_id() == id)
+        })
+        .unwrap_or(VersusPlayer::One)
+}
+
+/// Pass the next song pick on after a match, skipping player two when a bot plays them
+pub fn pass_versus_pick(rotation: &mut Option<PickerRotation>, bot: bool) {
+    rotation
+        .get_or_insert_with(|| PickerRotation::new(pickers(false)))
+        .advance(&pickers(bot));
+}
+
+/// Results of the match that just ended, for the side-by-side results screen
+#[derive(Resource)]
+pub struct VersusResults {
+    pub song_name: String,
+    pub seed: u64,
+    pub results: [VersusResult; 2],
+    pub score_style: ScoreStyle,
+    /// Whether a bot played player two
+    pub bot: bool,
+    /// Vote on playing the same map again. Player one breaks ties as the host; a bot
+    /// always votes for a rematch
+    pub vote: RematchVote,
+}
+
+impl VersusResults {
+    /// Results with the rematch vote opened at `now` (seconds)
+    pub fn new(data: &VersusData, now: f64) -> Self {
+        let bot = data.sides[1].bot.is_some();
+        let voters = VersusPlayer::BOTH.map(VersusPlayer::voter_id).to_vec();
+        let mut vote = RematchVote::open(voters, now);
+        if bot {
+            let _ = vote.cast(VersusPlayer::Two.voter_id(), true);
+        }
+        Self {
+            song_name: data.song_name.clone(),
+            seed: data.seed,
+            results: data.sides.each_ref().map(VersusSide::result),
+            score_style: data.score_style,
+            bot,
+            vote,
+        }
+    }
+
+    /// Record a player's rematch vote, or change it
+    pub fn cast_vote(&mut self, player: VersusPlayer, rematch: bool) {
+        let _ = self.vote.cast(player.voter_id(), rematch);
+    }
+
+    /// A player's rematch vote so far
+    pub fn vote_of(&self, player: VersusPlayer) -> Option<bool> {
+        self.vote.votes.get(&player.voter_id()).copied()
+    }
+
+    /// How the rematch vote ended, once both have voted or time is up
+    pub fn rematch_outcome(&self, now: f64) -> Option<RematchOutcome> {
+        self.vote.outcome(VersusPlayer::One.voter_id(), now)
+    }
+
+    pub fn winner(&self) -> Option<VersusPlayer> {
+        versus_winner(&self.results[0], &self.results[1])
+    }
+
+    /// Shown name of a player of this match
+    pub fn name(&self, player: VersusPlayer) -> &str {
+        &self.results[player.index()].name
+    }
+}
+
+/// Route hit keys to their player, play a bot side, judge both sides and end the match with the song
+#[allow(clippy::too_many_arguments)]
+pub fn update_versus(
+    mut commands: Commands,
+    mut data: ResMut<VersusData>,
+    mut next_state: ResMut<NextState<AppState>>,
+    audio_sink: Res<GameAudioSink>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config: Res<GameConfig>,
+    mut bus: ResMut<EventBus>,
+    time: Res<Time>,
+) {
+    data.song_clock.sync(time.delta_secs_f64());
+    let now_ms = judgment_ms(data.song_clock.now(), config.audio.offset_ms());
+
+    let pressed = pressed_players(keyboard.get_just_pressed(), &config.key_bindings);
+    let data = &mut *data;
+    for (side, pressed) in data.sides.iter_mut().zip(pressed) {
+        if side.bot.is_some() {
+            side.advance_bot(&data.circles, step_seconds(now_ms), &data.game_settings);
+            continue;
+        }
+        if pressed {
+            side.push_input(now_ms);
+        }
+        side.advance(&data.circles, now_ms, SHRINK_TIME, &data.game_settings);
+    }
+
+    if keyboard.just_pressed(config.key_bindings.exit_key()) {
+        audio_sink.sink.stop();
+        data.finish_sessions(false, &mut bus);
+        next_state.set(AppState::Menu);
+        return;
+    }
+
+    if audio_sink.sink.empty() {
+        data.finish_sessions(true, &mut bus);
+        commands.insert_resource(VersusResults::new(data, time.elapsed_secs_f64()));
+        next_state.set(AppState::VersusResults);
+    }
+}
+
+pub fn exit_versus(mut commands: Commands) {
+    commands.remove_resource::<VersusData>();
+}
+
+/// Draw both playfields with a divider between them. Everything on this screen is
+/// redrawn each frame
+pub fn render_versus(
+    mut commands: Commands,
+    mut data: ResMut<VersusData>,
+    config: Res<GameConfig>,
+    assets: Res<GameAssets>,
+    gates: Res<RenderGates>,
+    windows: Query<&Window>,
+    existing: Query<Entity, With<UiElement>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    for entity in existing.iter() {
+        commands.entity(entity).despawn();
+    }
+    let screen = Vec2::new(window.width(), window.height());
+    let elapsed = data.song_clock.now();
+
+    commands.spawn((
+        Sprite {
+            color: NEON_PURPLE.with_alpha(0.6),
+            custom_size: Some(Vec2::new(2.0, screen.y)),
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, 0.5),
+        UiElement,
+    ));
+
+    let data = &mut *data;
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+    for side in data.sides.iter_mut() {
+        let player = side.player;
+        // Open objects that could be on screen, moved to this player's half
+        let visible: Vec<GameCircle> = data.circles[side.next..]
+            .iter()
+            .take_while(|circle| circle.spawn_time <= elapsed)
+            .map(|circle| GameCircle {
+                position: player.to_screen(circle.position, screen),
+                ..circle.clone()
+            })
+            .collect();
+        draw_circles_bevy(
+            &mut commands,
+            &visible,
+            elapsed,
+            SHRINK_TIME,
+            &data.game_settings,
+            &data.theme,
+            0.0,
+            &gates,
+            // Both players share the keyboard and have no cursor to light around
+            None,
+        );
+
+        side.floating_texts
+            .retain(|text| elapsed - text.spawn_time < text.duration);
+        let mut texts: Vec<FloatingText> = side
+            .floating_texts
+            .iter()
+            .map(|text| FloatingText {
+                position: player.to_screen(text.position, screen),
+                ..text.clone()
+            })
+            .collect();
+        draw_floating_texts_bevy(&mut commands, &mut texts, elapsed, &assets);
+
+        let x = player.to_screen(Vec2::ZERO, screen).x;
+        let label = match side.bot {
+            Some(_) => side.name(),
+            None => format!(
+                "{}  [{}]",
+                player.label(),
+                player.keys_label(&config.key_bindings)
+            ),
+        };
+        commands.spawn((
+            Text2d::new(label),
+            font(20.0),
+            TextColor(player.color()),
+            Transform::from_xyz(x, screen.y / 2.0 - 30.0, 1.0),
+            UiElement,
+        ));
+        commands.spawn((
+            Text2d::new(format!(
+                "{}  {:.2}%",
+                format_score(side.score(), data.score_style),
+                side.accuracy()
+            )),
+            font(SCORE_FONT_SIZE * 0.75),
+            TextColor(Color::WHITE),
+            Transform::from_xyz(x, screen.y / 2.0 - 65.0, 1.0),
+            UiElement,
+        ));
+        commands.spawn((
+            Text2d::new(format!("{}x", side.combo)),
+            font(SCORE_FONT_SIZE * 0.75),
+            TextColor(player.color()),
+            Transform::from_xyz(x, -screen.y / 2.0 + 40.0, 1.0),
+            UiElement,
+        ));
+    }
+}
+
+/// Side-by-side results with a winner banner
+pub fn setup_versus_results_ui(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    results: Res<VersusResults>,
+    windows: Query<&Window>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let screen = Vec2::new(window.width(), window.height());
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+
+    let winner = results.winner();
+    let (banner, banner_color) = match winner {
+        Some(player) => (format!("{} wins!", results.name(player)), player.color()),
+        None => ("Draw!".to_string(), NEON_YELLOW),
+    };
+    commands.spawn((
+        Text2d::new(banner),
+        font(56.0),
+        TextColor(banner_color),
+        Transform::from_xyz(0.0, screen.y / 2.0 - 80.0, 1.0),
+        UiElement,
+    ));
+    let song = results
+        .song_name
+        .rsplit('/')
+        .next()
+        .unwrap_or(&results.song_name);
+    commands.spawn((
+        Text2d::new(song.to_string()),
+        font(20.0),
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.7)),
+        Transform::from_xyz(0.0, screen.y / 2.0 - 130.0, 1.0),
+        UiElement,
+    ));
+
+    for result in &results.results {
+        let player = result.player;
+        let x = player.to_screen(Vec2::ZERO, screen).x;
+        let won = winner == Some(player);
+        commands.spawn((
+            Sprite {
+                color: player.color().with_alpha(if won { 0.25 } else { 0.1 }),
+                custom_size: Some(Vec2::new(screen.x / 2.0 - 80.0, 330.0)),
+                ..default()
+            },
+            Transform::from_xyz(x, -20.0, 0.5),
+            UiElement,
+        ));
+        let lines = [
+            (result.name.clone(), 28.0, player.color()),
+            (
+                format_score(result.score, results.score_style),
+                SCORE_FONT_SIZE,
+                Color::WHITE,
+            ),
+            (format!("{:.2}%", result.accuracy), 24.0, Color::WHITE),
+            (
+                result.grade.as_str().to_string(),
+                36.0,
+                get_grade_color(result.grade.as_str()),
+            ),
+            (
+                format!("Max combo {}x", result.max_combo),
+                20.0,
+                Color::WHITE,
+            ),
+            (
+                format!(
+                    "300: {}  100: {}  50: {}  Miss: {}",
+                    result.hits.perfect, result.hits.good, result.hits.okay, result.hits.misses
+                ),
+                16.0,
+                Color::srgba(1.0, 1.0, 1.0, 0.7),
+            ),
+        ];
+        for (i, (text, size, color)) in lines.into_iter().enumerate() {
+            commands.spawn((
+                Text2d::new(text),
+                font(size),
+                TextColor(color),
+                Transform::from_xyz(x, 110.0 - i as f32 * 50.0, 1.0),
+                UiElement,
+            ));
+        }
+    }
+
+    commands.spawn((
+        Text2d::new("Enter: menu"),
+        font(16.0),
+        TextColor(Color::srgba(1.0, 1.0, 1.0, 0.5)),
+        Transform::from_xyz(0.0, -screen.y / 2.0 + 30.0, 1.0),
+        UiElement,
+    ));
+}
+
+/// Marks the rematch vote's text, redrawn every frame for its countdown
+#[derive(Component)]
+pub struct VersusVoteElement;
+
+/// Each player's vote under their results, and the keys and time left to vote
+pub fn draw_versus_vote(
+    mut commands: Commands,
+    assets: Res<GameAssets>,
+    results: Res<VersusResults>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+    windows: Query<&Window>,
+    existing: Query<Entity, With<VersusVoteElement>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    for entity in existing.iter() {
+        commands.entity(entity).despawn();
+    }
+    let screen = Vec2::new(window.width(), window.height());
+    let font = |size: f32| TextFont {
+        font: assets.cyberpunk_font.clone(),
+        font_size: size,
+        ..default()
+    };
+
+    for player in VersusPlayer::BOTH {
+        let (text, color) = match results.vote_of(player) {
+            Some(true) => ("Rematch: yes".to_string(), NEON_GREEN),
+            Some(false) => ("Rematch: no".to_string(), NEON_ORANGE),
+            None => {
+                let keys = player.keys_label(&config.key_bindings);
+                let (yes, no) = keys.split_once('/').unwrap_or((&keys, ""));
+                let text = format!("Rematch? {}: yes  {}: no", yes, no);
+                (text, Color::srgba(1.0, 1.0, 1.0, 0.7))
+            }
+        };
+        commands.spawn((
+            Text2d::new(text),
+            font(18.0),
+            TextColor(color),
+            Transform::from_xyz(player.to_screen(Vec2::ZERO, screen).x, -215.0, 1.0),
+            UiElement,
+            VersusVoteElement,
+        ));
+    }
+    let seconds = results
+        .vote
+        .remaining_seconds(time.elapsed_secs_f64())
+        .ceil();
+    commands.spawn((
+        Text2d::new(format!(
+            "Vote closes in {}s, ties go Player 1's way",
+            seconds
+        )),
+        font(16.0),
+        TextColor(NEON_YELLOW),
+        Transform::from_xyz(0.0, -screen.y / 2.0 + 60.0, 1.0),
+        UiElement,
+        VersusVoteElement,
+    ));
+}
+
+/// Vote with the hit keys: a rematch on the same layout, or back to song selection
+/// with the next pick passed on. Enter leaves for the menu
+pub fn update_versus_results(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut game_state: ResMut<GameStateResource>,
+    mut results: ResMut<VersusResults>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    config: Res<GameConfig>,
+    time: Res<Time>,
+) {
+    for key in keyboard.get_just_pressed() {
+        if let Some(player) = route_hit_key(*key, &config.key_bindings) {
+            let rematch = player.hit_keys(&config.key_bindings)[0] == *key;
+            results.cast_vote(player, rematch);
+        }
+    }
+    match results.rematch_outcome(time.elapsed_secs_f64()) {
+        Some(RematchOutcome::Rematch) => {
+            game_state.selected_song = results.song_name.clone();
+            game_state.seed_override = Some(results.seed);
+            next_state.set(AppState::Playing);
+            return;
+        }
+        Some(RematchOutcome::Declined) => {
+            pass_versus_pick(&mut game_state.versus_picks, results.bot);
+            next_state.set(AppState::SongSelection);
+            return;
+        }
+        None => {}
+    }
+    if keyboard.just_pressed(KeyCode::Escape)
+        || keyboard.just_pressed(KeyCode::Enter)
+        || mouse_input.just_pressed(MouseButton::Left)
+    {
+        next_state.set(AppState::Menu);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circles() -> Vec<GameCircle> {
+        (0..40)
+            .map(|i| {
+                let hit_time = 2.0 + i as f64 * 0.5;
+                GameCircle {
+                    position: Vec2::ZERO,
+                    spawn_time: hit_time - SHRINK_TIME,
+                    hit_time,
+                    max_radius: 60.0,
+                    hit: false,
+                    missed: false,
+                    kind: Default::default(),
+                    new_combo: false,
+                    hitsound: Default::default(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_bot_side_plays_its_planned_run() {
+        let circles = circles();
+        let session = ActiveSession::new("song".to_string(), false, 1.0);
+        let mut side =
+            VersusSide::new(VersusPlayer::BOTH[1], session).with_bot(BotProfile::Hard, 3, &circles);
+        let settings = GameSettings::default();
+        side.advance_bot(&circles, 1.0, &settings);
+        assert_eq!(side.next, 0);
+
+        let mut now = 0.0;
+        while now < 30.0 {
+            side.advance_bot(&circles, now, &settings);
+            // Never behind the objects whose time has passed
+            assert!(circles[..side.next]
+                .iter()
+                .all(|circle| circle.hit_time - 0.5 <= now));
+            assert!(circles[side.next..]
+                .iter()
+                .all(|circle| circle.hit_time >= now - 0.02));
+            now += 0.01;
+        }
+        assert_eq!(side.judgments.len(), circles.len());
+        let hit_times: Vec<f64> = circles.iter().map(|circle| circle.hit_time).collect();
+        let planned = crate::bots::run_accuracy(&simulate_run(3, BotProfile::Hard, &hit_times));
+        assert!((side.accuracy() as f64 - planned).abs() < 0.01);
+        assert_eq!(side.name(), "[BOT] Hard");
+    }
+
+    fn results(bot: bool) -> VersusResults {
+        let data = VersusData::new(
+            circles(),
+            &GameConfig::default(),
+            "song",
+            None,
+            3,
+            SongClock::new(1.0, None),
+            bot.then_some(BotProfile::Easy),
+        );
+        VersusResults::new(&data, 0.0)
+    }
+
+    #[test]
+    fn player_one_breaks_a_tied_rematch_vote() {
+        let mut results = results(false);
+        results.cast_vote(VersusPlayer::Two, true);
+        assert_eq!(results.rematch_outcome(1.0), None);
+        results.cast_vote(VersusPlayer::One, false);
+        assert_eq!(results.rematch_outcome(1.0), Some(RematchOutcome::Declined));
+        results.cast_vote(VersusPlayer::One, true);
+        results.cast_vote(VersusPlayer::Two, false);
+        assert_eq!(results.rematch_outcome(1.0), Some(RematchOutcome::Rematch));
+    }
+
+    #[test]
+    fn a_bot_votes_for_the_rematch_and_never_picks() {
+        let results = results(true);
+        assert_eq!(results.vote_of(VersusPlayer::Two), Some(true));
+        assert_eq!(results.rematch_outcome(1.0), None);
+
+        let mut picks = None;
+        assert_eq!(versus_picker(picks.as_ref(), true), VersusPlayer::One);
+        pass_versus_pick(&mut picks, false);
+        assert_eq!(versus_picker(picks.as_ref(), false), VersusPlayer::Two);
+        pass_versus_pick(&mut picks, false);
+        assert_eq!(versus_picker(picks.as_ref(), false), VersusPlayer::One);
+        pass_versus_pick(&mut picks, true);
+        assert_eq!(versus_picker(picks.as_ref(), true), VersusPlayer::One);
+    }
+
+    #[test]
+    fn b_cycles_from_a_second_player_through_the_bots() {
+        let mut opponent = None;
+        let mut names = Vec::new();
+        for _ in 0..5 {
+            opponent = next_versus_opponent(opponent, &[88.0]);
+            names.push(opponent.map(|profile| profile.display_name()));
+        }
+        assert_eq!(
+            names,
+            [
+                Some("Easy"),
+                Some("Medium"),
+                Some("Hard"),
+                Some("Adaptive"),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn hit_keys_route_to_their_own_player_only() {
+        let bindings = KeyBindings::default();
+        assert_eq!(
+            route_hit_key(KeyCode::KeyA, &bindings),
+            Some(VersusPlayer::One)
+        );
+        assert_eq!(
+            route_hit_key(KeyCode::KeyS, &bindings),
+            Some(VersusPlayer::One)
+        );
+        assert_eq!(
+            route_hit_key(KeyCode::KeyK, &bindings),
+            Some(VersusPlayer::Two)
+        );
+        assert_eq!(
+            route_hit_key(KeyCode::KeyL, &bindings),
+            Some(VersusPlayer::Two)
+        );
+        assert_eq!(route_hit_key(KeyCode::KeyD, &bindings), None);
+        assert_eq!(route_hit_key(KeyCode::Escape, &bindings), None);
+
+        assert_eq!(
+            pressed_players(&[KeyCode::KeyA, KeyCode::KeyS], &bindings),
+            [true, false]
+        );
+        assert_eq!(pressed_players(&[KeyCode::KeyL], &bindings), [false, true]);
+        assert_eq!(
+            pressed_players(&[KeyCode::KeyS, KeyCode::KeyK], &bindings),
+            [true, true]
+        );
+        assert_eq!(
+            pressed_players(&[KeyCode::Space], &bindings),
+            [false, false]
+        );
+    }
+
+    #[test]
+    fn a_key_bound_for_both_players_counts_for_neither() {
+        let bindings = KeyBindings {
+            versus_primary_hit: "KeyS".to_string(),
+            ..KeyBindings::default()
+        };
+        assert_eq!(route_hit_key(KeyCode::KeyS, &bindings), None);
+        assert_eq!(
+            route_hit_key(KeyCode::KeyA, &bindings),
+            Some(VersusPlayer::One)
+        );
+        assert_eq!(
+            route_hit_key(KeyCode::KeyL, &bindings),
+            Some(VersusPlayer::Two)
+        );
+        assert_eq!(pressed_players(&[KeyCode::KeyS], &bindings), [false, false]);
+    }
+
+    #[test]
+    fn one_players_presses_never_judge_the_other_side() {
+        let circles = circles();
+        let settings = GameSettings::default();
+        let bindings = KeyBindings::default();
+        let mut sides = VersusPlayer::BOTH.map(|player| {
+            VersusSide::new(player, ActiveSession::new("song".to_string(), false, 1.0))
+        });
+
+        // Player one presses on every object's time; player two never does
+        for circle in &circles {
+            let now_ms = (circle.hit_time * 1000.0).round() as i64;
+            let pressed = pressed_players(&[KeyCode::KeyA], &bindings);
+            for (side, pressed) in sides.iter_mut().zip(pressed) {
+                if pressed {
+                    side.push_input(now_ms);
+                }
+                side.advance(&circles, now_ms, SHRINK_TIME, &settings);
+            }
+        }
+        let end_ms = ((circles.last().unwrap().hit_time + 1.0) * 1000.0) as i64;
+        for side in &mut sides {
+            side.advance(&circles, end_ms, SHRINK_TIME, &settings);
+        }
+
+        let [one, two] = &sides;
+        assert!(one
+            .judgments
+            .iter()
+            .all(|kind| *kind == JudgmentKind::Perfect));
+        assert_eq!(one.max_combo, circles.len() as u32);
+        assert!(two.judgments.iter().all(|kind| *kind == JudgmentKind::Miss));
+        assert_eq!(two.judgments.len(), circles.len());
+        assert_eq!(two.score(), 0);
+        assert_eq!(one.session.local_versus, Some(1));
+        assert_eq!(two.session.local_versus, Some(2));
+    }
+
+    #[test]
+    fn player_two_sees_a_mirror_of_player_ones_layout() {
+        let screen = Vec2::new(1600.0, 900.0);
+        let point = Vec2::new(120.0, -40.0);
+        assert_eq!(
+            VersusPlayer::One.to_screen(point, screen),
+            Vec2::new(-280.0, -40.0)
+        );
+        assert_eq!(
+            VersusPlayer::Two.to_screen(point, screen),
+            Vec2::new(280.0, -40.0)
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! Hit object visibility model used by both normal and Hidden rendering.
+//!
+//! Visibility is a pure function of how far through the approach window an
+//! object is, so draw code never hard-codes fades:
+//! - Circles fade in as they approach. Under Hidden the approach ring is never
+//!   drawn and the body fades out over the last `hidden_fade_fraction` of the
+//!   window, reaching zero at the hit time.
+//! - Slider heads and bodies follow the circle rules. The follow ball is always
+//!   fully visible once the slider starts, so Hidden only removes the guide.
+//! - Spinners are unaffected by Hidden; there is nothing to read ahead. Their
+//!   draw code leaves them lit under Flashlight too.
+//! - Flashlight is applied on top, by where things are rather than when: parts
+//!   of objects within `FLASHLIGHT_RADIUS` of the cursor keep their alpha, which
+//!   falls to zero over `FLASHLIGHT_EDGE` beyond it. Only drawing changes; circles
+//!   in the dark are hit and missed as usual.
+
+use crate::config::ThemeConfig;
+use bevy::math::Vec2;
+
+/// Body alpha when an object first appears
+const BODY_ALPHA_MIN: f32 = 0.1;
+/// Body alpha at the hit time when nothing fades it out
+const BODY_ALPHA_MAX: f32 = 0.6;
+
+/// Distance from the Flashlight center within which objects are fully lit (pixels)
+pub const FLASHLIGHT_RADIUS: f32 = 180.0;
+/// Distance beyond the radius over which Flashlight fades objects out (pixels)
+const FLASHLIGHT_EDGE: f32 = 60.0;
+
+/// Skin-adjustable fade parameters
+#[derive(Debug, Clone, Copy)]
+pub struct FadeParams {
+    /// Fraction of the approach window (at the end) over which Hidden fades the body out
+    pub hidden_fade_fraction: f32,
+}
+
+impl Default for FadeParams {
+    fn default() -> Self {
+        Self {
+            hidden_fade_fraction: 0.4,
+        }
+    }
+}
+
+impl FadeParams {
+    /// Fade parameters from the current theme
+    pub fn from_theme(theme: &ThemeConfig) -> Self {
+        Self {
+            hidden_fade_fraction: theme.hidden_fade_fraction.clamp(0.01, 1.0),
+        }
+    }
+}
+
+/// Kind of object being drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VisibleObject {
+    Circle,
+    Slider,
+    Spinner,
+}
+
+/// How visible each part of an object is (alphas are 0.0 - 1.0 multipliers)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjectVisibility {
+    /// Alpha of the circle body (slider head and body for sliders)
+    pub body_alpha: f32,
+    /// Alpha of the approach ring
+    pub approach_alpha: f32,
+    /// Alpha of a slider's follow ball
+    pub follow_ball_alpha: f32,
+    /// Whether the combo number is drawn
+    pub show_combo_number: bool,
+}
+
+/// Visibility of an object `time_until_hit` seconds before its hit time
+pub fn object_visibility(
+    object: VisibleObject,
+    time_until_hit: f64,
+    approach_time: f64,
+    hidden: bool,
+    fade: &FadeParams,
+) -> ObjectVisibility {
+    if object == VisibleObject::Spinner {
+        return ObjectVisibility {
+            body_alpha: 1.0,
+            approach_alpha: 0.0,
+            follow_ball_alpha: 0.0,
+            show_combo_number: false,
+        };
+    }
+
+    // 0.0 when the object appears, 1.0 at its hit time
+    let progress = if approach_time > 0.0 {
+        (1.0 - time_until_hit / approach_time).clamp(0.0, 1.0) as f32
+    } else {
+        1.0
+    };
+
+    let fade_in = |p: f32| BODY_ALPHA_MIN + (BODY_ALPHA_MAX - BODY_ALPHA_MIN) * p;
+
+    let body_alpha = if hidden {
+        let fade_start = 1.0 - fade.hidden_fade_fraction;
+        if progress < fade_start {
+            fade_in(progress)
+        } else {
+            // Measured from the hit time so it is exactly zero there
+            let remaining = (1.0 - progress) / fade.hidden_fade_fraction;
+            (fade_in(fade_start) * remaining).max(0.0)
+        }
+    } else {
+        fade_in(progress)
+    };
+
+    ObjectVisibility {
+        body_alpha,
+        approach_alpha: if hidden { 0.0 } else { 1.0 },
+        follow_ball_alpha: if object == VisibleObject::Slider { 1.0 } else { 0.0 },
+        show_combo_number: body_alpha > 0.0,
+    }
+}
+
+impl ObjectVisibility {
+    /// The same visibility with every alpha multiplied by `factor`
+    pub fn dimmed(self, factor: f32) -> Self {
+        let body_alpha = self.body_alpha * factor;
+        Self {
+            body_alpha,
+            approach_alpha: self.approach_alpha * factor,
+            follow_ball_alpha: self.follow_ball_alpha * factor,
+            show_combo_number: self.show_combo_number && body_alpha > 0.0,
+        }
+    }
+}
+
+/// Flashlight's alpha multiplier at a position; always 1.0 without Flashlight
+/// (`center` None)
+pub fn flashlight_alpha(position: Vec2, center: Option<Vec2>) -> f32 {
+    let Some(center) = center else {
+        return 1.0;
+    };
+    let beyond = position.distance(center) - FLASHLIGHT_RADIUS;
+    (1.0 - beyond / FLASHLIGHT_EDGE).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APPROACH: f64 = 1.0;
+
+    fn circle(time_until_hit: f64, hidden: bool) -> ObjectVisibility {
+        object_visibility(
+            VisibleObject::Circle,
+            time_until_hit,
+            APPROACH,
+            hidden,
+            &FadeParams::default(),
+        )
+    }
+
+    #[test]
+    fn circles_fade_in_over_the_approach() {
+        assert_eq!(circle(APPROACH, false).body_alpha, BODY_ALPHA_MIN);
+        assert_eq!(circle(0.0, false).body_alpha, BODY_ALPHA_MAX);
+        assert!(circle(0.5, false).body_alpha > circle(0.8, false).body_alpha);
+        assert_eq!(circle(0.5, false).approach_alpha, 1.0);
+        // Before the approach starts and after the hit time the alphas clamp
+        assert_eq!(circle(2.0, false).body_alpha, BODY_ALPHA_MIN);
+        assert_eq!(circle(-0.5, false).body_alpha, BODY_ALPHA_MAX);
+    }
+
+    #[test]
+    fn hidden_shows_circles_early_then_fades_them_out() {
+        // Unchanged before the last 40% of the window, without an approach ring
+        let early = circle(0.7, true);
+        assert_eq!(early.body_alpha, circle(0.7, false).body_alpha);
+        assert_eq!(early.approach_alpha, 0.0);
+        assert!(early.show_combo_number);
+
+        let halfway = circle(0.2, true).body_alpha;
+        let start = circle(0.4, true).body_alpha;
+        assert!(halfway > 0.0 && halfway < start);
+        assert!((halfway - start / 2.0).abs() < 1e-5);
+
+        for time_until_hit in [0.0, -0.5] {
+            let at_hit = circle(time_until_hit, true);
+            assert_eq!(at_hit.body_alpha, 0.0);
+            assert!(!at_hit.show_combo_number);
+        }
+    }
+
+    #[test]
+    fn skins_set_the_hidden_fade_fraction() {
+        let fade = FadeParams {
+            hidden_fade_fraction: 0.8,
+        };
+        let late = object_visibility(VisibleObject::Circle, 0.7, APPROACH, true, &fade);
+        assert!(late.body_alpha < circle(0.7, false).body_alpha);
+
+        let theme = ThemeConfig {
+            hidden_fade_fraction: 5.0,
+            ..ThemeConfig::default()
+        };
+        assert_eq!(FadeParams::from_theme(&theme).hidden_fade_fraction, 1.0);
+    }
+
+    #[test]
+    fn sliders_keep_the_follow_ball_and_spinners_ignore_hidden() {
+        let slider = object_visibility(
+            VisibleObject::Slider,
+            0.0,
+            APPROACH,
+            true,
+            &FadeParams::default(),
+        );
+        assert_eq!(slider.body_alpha, 0.0);
+        assert_eq!(slider.follow_ball_alpha, 1.0);
+
+        let spinner = object_visibility(
+            VisibleObject::Spinner,
+            0.0,
+            APPROACH,
+            true,
+            &FadeParams::default(),
+        );
+        assert_eq!(spinner.body_alpha, 1.0);
+    }
+
+    #[test]
+    fn flashlight_lights_around_its_center() {
+        let center = Some(Vec2::ZERO);
+        assert_eq!(flashlight_alpha(Vec2::new(500.0, 0.0), None), 1.0);
+        assert_eq!(
+            flashlight_alpha(Vec2::new(FLASHLIGHT_RADIUS, 0.0), center),
+            1.0
+        );
+        let edge = FLASHLIGHT_RADIUS + FLASHLIGHT_EDGE / 2.0;
+        assert_eq!(flashlight_alpha(Vec2::new(0.0, edge), center), 0.5);
+        assert_eq!(flashlight_alpha(Vec2::new(1000.0, 0.0), center), 0.0);
+    }
+}
@@ -0,0 +1,198 @@
+// src/waveform.rs
+
+use bevy::prelude::*;
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+
+use crate::beatmap::BeatmapAssets;
+use crate::editor::EditorState;
+
+/// Song time covered by one peak of the finest level (seconds)
+pub const PEAK_SECONDS: f64 = 0.005;
+
+/// Peaks merged into one at each coarser level
+const LEVEL_FACTOR: usize = 4;
+
+/// Lowest and highest sample in a stretch of the song, channels mixed down to mono
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Peak {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Peak {
+    /// Peak of a single sample
+    fn of(sample: f32) -> Self {
+        Self {
+            min: sample,
+            max: sample,
+        }
+    }
+
+    /// Peak covering both stretches
+    fn merge(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+/// Downsampled min/max envelope of a song. The first level holds one peak per
+/// `PEAK_SECONDS` and each level after merges `LEVEL_FACTOR` peaks of the one before,
+/// so a zoomed-out timeline reads a few coarse peaks per column instead of the song
+#[derive(Debug, Clone)]
+pub struct WaveformPeaks {
+    levels: Vec<Vec<Peak>>,
+    /// Largest absolute sample, so quiet songs still fill the timeline
+    loudest: f32,
+}
+
+impl WaveformPeaks {
+    /// Decode a song once, keeping only its peaks
+    pub fn decode(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let decoder = Decoder::new(BufReader::new(file))
+            .map_err(|e| format!("Failed to decode {}: {}", path, e))?;
+        let channels = decoder.channels().max(1) as usize;
+        let frames_per_peak = (decoder.sample_rate() as f64 * PEAK_SECONDS).round() as usize;
+
+        let mut finest = Vec::new();
+        let mut peak: Option<Peak> = None;
+        let (mut frame_sum, mut channel, mut frames) = (0.0, 0, 0);
+        for sample in decoder.convert_samples::<f32>() {
+            frame_sum += sample;
+            channel += 1;
+            if channel < channels {
+                continue;
+            }
+            let mono = Peak::of(frame_sum / channels as f32);
+            (frame_sum, channel) = (0.0, 0);
+            peak = Some(peak.map_or(mono, |peak| peak.merge(mono)));
+            frames += 1;
+            if frames >= frames_per_peak {
+                finest.extend(peak.take());
+                frames = 0;
+            }
+        }
+        finest.extend(peak);
+        Ok(Self::from_finest(finest))
+    }
+
+    /// Build the coarser levels on top of the finest one
+    fn from_finest(finest: Vec<Peak>) -> Self {
+        let loudest = finest
+            .iter()
+            .map(|peak| peak.min.abs().max(peak.max.abs()))
+            .fold(0.0, f32::max);
+        let mut levels = vec![finest];
+        while let Some(last) = levels.last().filter(|level| level.len() > LEVEL_FACTOR) {
+            let coarser = last
+                .chunks(LEVEL_FACTOR)
+                .filter_map(|chunk| chunk.iter().copied().reduce(Peak::merge))
+                .collect();
+            levels.push(coarser);
+        }
+        Self { levels, loudest }
+    }
+
+    /// Peaks of `columns` equal slices of song time from `start` to `end`, None where a
+    /// slice is outside the song. Reads the coarsest level still finer than a slice and
+    /// only the peaks inside the span, so the cost follows the view, not the song length
+    pub fn columns(&self, start: f64, end: f64, columns: usize) -> Vec<Option<Peak>> {
+        if columns == 0 || end <= start {
+            return Vec::new();
+        }
+        let column_seconds = (end - start) / columns as f64;
+        let mut level = 0;
+        let mut peak_seconds = PEAK_SECONDS;
+        while level + 1 < self.levels.len() && peak_seconds * LEVEL_FACTOR as f64 <= column_seconds
+        {
+            level += 1;
+            peak_seconds *= LEVEL_FACTOR as f64;
+        }
+        let peaks = &self.levels[level];
+        let index = |time: f64| ((time / peak_seconds).max(0.0) as usize).min(peaks.len());
+        (0..columns)
+            .map(|column| {
+                let from = start + column as f64 * column_seconds;
+                if from + column_seconds <= 0.0 {
+                    return None;
+                }
+                let first = index(from);
+                let last = index(from + column_seconds).max(first + 1).min(peaks.len());
+                peaks.get(first..last)?.iter().copied().reduce(Peak::merge)
+            })
+            .collect()
+    }
+
+    /// A peak scaled against the loudest sample, both ends within -1.0..=1.0
+    pub fn normalized(&self, peak: Peak) -> (f32, f32) {
+        if self.loudest <= f32::EPSILON {
+            return (0.0, 0.0);
+        }
+        (peak.min / self.loudest, peak.max / self.loudest)
+    }
+}
+
+/// Peaks of the editor's song, decoded on a worker thread when the beatmap's audio
+/// file changes and kept across editor visits
+#[derive(Resource, Default)]
+pub struct EditorWaveform {
+    /// Audio file the peaks are, or are being, decoded from
+    path: Option<String>,
+    pub peaks: Option<WaveformPeaks>,
+    /// Decode in flight. Dropped when the audio file changes again, so an outdated
+    /// waveform never replaces a newer one
+    receiver: Option<Mutex<Receiver<Result<WaveformPeaks, String>>>>,
+}
+
+/// Start decoding when the shown waveform is of another audio file than the beatmap's,
+/// and pick the peaks up once they're ready
+pub fn refresh_waveform_peaks(
+    editor_state: Res<EditorState>,
+    beatmap_assets: Res<BeatmapAssets>,
+    mut waveform: ResMut<EditorWaveform>,
+) {
+    let audio_path = beatmap_assets
+        .current()
+        .map(|beatmap| beatmap.audio_path.as_str());
+    if editor_state.show_waveform && waveform.path.as_deref() != audio_path {
+        waveform.path = audio_path.map(str::to_string);
+        waveform.peaks = None;
+        waveform.receiver = audio_path.map(|path| {
+            let path = path.to_string();
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(WaveformPeaks::decode(&path));
+            });
+            Mutex::new(receiver)
+        });
+    }
+
+    let Some(receiver) = waveform.receiver.as_ref() else {
+        return;
+    };
+    let received = match receiver.lock() {
+        Ok(receiver) => receiver.try_recv(),
+        Err(_) => Err(mpsc::TryRecvError::Disconnected),
+    };
+    match received {
+        Ok(Ok(peaks)) => {
+            waveform.receiver = None;
+            waveform.peaks = Some(peaks);
+        }
+        Ok(Err(e)) => {
+            warn!("No waveform for the editor: {}", e);
+            waveform.receiver = None;
+        }
+        Err(mpsc::TryRecvError::Empty) => {}
+        Err(mpsc::TryRecvError::Disconnected) => {
+            warn!("Waveform decode stopped without peaks");
+            waveform.receiver = None;
+        }
+    }
+}
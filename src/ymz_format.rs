@@ -0,0 +1,120 @@
+// src/ymz_format.rs
+
+use std::fs;
+use std::path::Path;
+
+use crate::beatmap::Beatmap;
+use crate::map_comments::MapComments;
+
+/// First bytes of every .ymz package
+const YMZ_MAGIC: &[u8; 4] = b"YMZ1";
+
+/// Name of the beatmap inside a package
+pub const YMZ_BEATMAP_ENTRY: &str = "beatmap.json";
+
+/// Name of the review comments inside a package that includes them
+pub const YMZ_COMMENTS_ENTRY: &str = "comments.json";
+
+/// A file stored in a .ymz package
+#[derive(Debug, Clone, PartialEq)]
+pub struct YmzEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl Beatmap {
+    /// The beatmap packaged with its audio and background as a .ymz file: the magic,
+    /// then for each file its name's length (u32), the name, the data's length (u64)
+    /// and the data, little endian. The beatmap comes first and refers to the other
+    /// files by their names inside the package. `folder` is where relative paths in
+    /// the beatmap are looked up. Review comments, when given, go last under
+    /// `YMZ_COMMENTS_ENTRY`
+    pub fn to_ymz_bytes(
+        &self,
+        folder: &Path,
+        comments: Option<&MapComments>,
+    ) -> Result<Vec<u8>, String> {
+        let mut beatmap = self.clone();
+        let mut files = Vec::new();
+        for path in [
+            Some(&mut beatmap.audio_path),
+            beatmap.background_path.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|path| !path.is_empty())
+        {
+            let found = folder.join(&*path);
+            let data = fs::read(&found)
+                .map_err(|e| format!("couldn't read {} ({})", found.display(), e))?;
+            let name = Path::new(path.as_str())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| format!("{} is not a file", path))?;
+            *path = name.clone();
+            files.push(YmzEntry { name, data });
+        }
+        if let Some(comments) = comments {
+            let data = serde_json::to_vec_pretty(comments)
+                .map_err(|e| format!("Failed to serialize comments: {}", e))?;
+            files.push(YmzEntry {
+                name: YMZ_COMMENTS_ENTRY.to_string(),
+                data,
+            });
+        }
+        let json = serde_json::to_vec_pretty(&beatmap)
+            .map_err(|e| format!("Failed to serialize beatmap: {}", e))?;
+
+        let mut out = YMZ_MAGIC.to_vec();
+        let beatmap_entry = YmzEntry {
+            name: YMZ_BEATMAP_ENTRY.to_string(),
+            data: json,
+        };
+        for entry in std::iter::once(&beatmap_entry).chain(&files) {
+            out.extend((entry.name.len() as u32).to_le_bytes());
+            out.extend(entry.name.as_bytes());
+            out.extend((entry.data.len() as u64).to_le_bytes());
+            out.extend(&entry.data);
+        }
+        Ok(out)
+    }
+
+    /// Read a package written by `to_ymz_bytes`: the beatmap and the files it refers to
+    pub fn from_ymz_bytes(bytes: &[u8]) -> Result<(Beatmap, Vec<YmzEntry>), String> {
+        let mut rest = bytes.strip_prefix(YMZ_MAGIC).ok_or("not a .ymz package")?;
+        let mut entries = Vec::new();
+        while !rest.is_empty() {
+            let name_len = u32::from_le_bytes(take(&mut rest)?);
+            let name = String::from_utf8(take_slice(&mut rest, name_len as usize)?.to_vec())
+                .map_err(|_| "a file name in the package isn't UTF-8".to_string())?;
+            let data_len = u64::from_le_bytes(take(&mut rest)?);
+            let data = take_slice(&mut rest, data_len as usize)?.to_vec();
+            entries.push(YmzEntry { name, data });
+        }
+
+        if entries.first().map(|entry| entry.name.as_str()) != Some(YMZ_BEATMAP_ENTRY) {
+            return Err("the package doesn't start with a beatmap".to_string());
+        }
+        let first = entries.remove(0);
+        let beatmap = serde_json::from_slice(&first.data)
+            .map_err(|e| format!("Failed to parse beatmap: {}", e))?;
+        Ok((beatmap, entries))
+    }
+}
+
+/// The next `len` bytes of a package, moving past them
+fn take_slice<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if rest.len() < len {
+        return Err("the package is cut short".to_string());
+    }
+    let (taken, after) = rest.split_at(len);
+    *rest = after;
+    Ok(taken)
+}
+
+/// The next `N` bytes of a package as an array, for a length field
+fn take<const N: usize>(rest: &mut &[u8]) -> Result<[u8; N], String> {
+    let mut bytes = [0; N];
+    bytes.copy_from_slice(take_slice(rest, N)?);
+    Ok(bytes)
+}
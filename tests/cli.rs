@@ -0,0 +1,155 @@
+// tests/cli.rs
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use yum_osu::beatmap::{Beatmap, HitObjectKind};
+use yum_osu::cli;
+use yum_osu::map_comments::{MapComment, MapComments};
+use yum_osu::ymz_format::YMZ_COMMENTS_ENTRY;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+fn fixture(name: &str) -> String {
+    format!("{}/{}", FIXTURES, name)
+}
+
+/// An empty folder of its own for each test's output
+fn scratch_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("yum-osu-cli-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn convert_reads_every_object_and_finds_the_audio() {
+    let out = path_string(&scratch_dir("convert").join("simple.json"));
+    let problems = cli::import_osu(&fixture("simple.osu"), &out).unwrap();
+    assert!(problems.is_empty(), "{:?}", problems);
+
+    let beatmap = Beatmap::load_from_file(&out).unwrap();
+    assert_eq!(beatmap.metadata.title, "Fixture");
+    let kinds: Vec<_> = beatmap
+        .hit_objects
+        .iter()
+        .map(|object| &object.kind)
+        .collect();
+    assert!(matches!(
+        kinds[..],
+        [
+            HitObjectKind::Circle,
+            HitObjectKind::Slider { .. },
+            HitObjectKind::Spinner { .. }
+        ]
+    ));
+    assert!(Path::new(&beatmap.audio_path).is_file());
+}
+
+#[test]
+fn validate_passes_a_converted_map_and_lists_errors() {
+    let out = path_string(&scratch_dir("validate").join("simple.json"));
+    cli::import_osu(&fixture("simple.osu"), &out).unwrap();
+    assert_eq!(cli::validate(&out), Ok(Vec::new()));
+
+    let errors = cli::validate(&fixture("broken.json")).unwrap();
+    assert_eq!(errors, vec!["Spinner 2 ends before it starts".to_string()]);
+
+    assert!(cli::validate(&fixture("missing.json")).is_err());
+}
+
+#[test]
+fn export_packages_the_beatmap_with_its_audio() {
+    let dir = scratch_dir("export");
+    let map = path_string(&dir.join("simple.json"));
+    let out = path_string(&dir.join("simple.ymz"));
+    cli::import_osu(&fixture("simple.osu"), &map).unwrap();
+    cli::export_ymz(&map, &out, false).unwrap();
+
+    let (beatmap, files) = Beatmap::from_ymz_bytes(&fs::read(&out).unwrap()).unwrap();
+    assert_eq!(beatmap.metadata.title, "Fixture");
+    assert_eq!(beatmap.hit_objects.len(), 3);
+    assert_eq!(beatmap.audio_path, "audio.wav");
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].name, "audio.wav");
+    assert_eq!(files[0].data, fs::read(fixture("audio.wav")).unwrap());
+}
+
+#[test]
+fn export_fails_without_the_audio_file() {
+    let mut beatmap = Beatmap::load_from_file(&fixture("broken.json")).unwrap();
+    beatmap.audio_path = "missing.mp3".to_string();
+    assert!(beatmap.to_ymz_bytes(Path::new(FIXTURES), None).is_err());
+}
+
+#[test]
+fn export_includes_review_comments_when_asked() {
+    let dir = scratch_dir("comments");
+    let map = path_string(&dir.join("simple.json"));
+    let package = path_string(&dir.join("simple.ymz"));
+    cli::import_osu(&fixture("simple.osu"), &map).unwrap();
+    let mut comments = MapComments::default();
+    comments.add(MapComment {
+        time: 1.5,
+        author: "Reviewer".to_string(),
+        text: "Spacing jumps here".to_string(),
+        resolved: false,
+    });
+    comments.save(&map).unwrap();
+
+    // Left out unless asked for
+    assert_eq!(cli::run(&args(&["export", &map, "-o", &package])), Some(0));
+    let (_, files) = Beatmap::from_ymz_bytes(&fs::read(&package).unwrap()).unwrap();
+    assert!(files.iter().all(|file| file.name != YMZ_COMMENTS_ENTRY));
+
+    assert_eq!(
+        cli::run(&args(&["export", &map, "--with-comments", "-o", &package])),
+        Some(0)
+    );
+    let (_, files) = Beatmap::from_ymz_bytes(&fs::read(&package).unwrap()).unwrap();
+    let packaged = files
+        .iter()
+        .find(|file| file.name == YMZ_COMMENTS_ENTRY)
+        .unwrap();
+    let packaged: MapComments = serde_json::from_slice(&packaged.data).unwrap();
+    assert_eq!(packaged, comments);
+}
+
+#[test]
+fn subcommands_take_an_output_flag() {
+    let dir = scratch_dir("flags");
+    let map = path_string(&dir.join("converted.json"));
+    let package = path_string(&dir.join("converted.ymz"));
+
+    assert_eq!(
+        cli::run(&args(&["convert", &fixture("simple.osu"), "-o", &map])),
+        Some(0)
+    );
+    assert!(Path::new(&map).is_file());
+    assert_eq!(cli::run(&args(&["export", &map, "-o", &package])), Some(0));
+    assert!(Path::new(&package).is_file());
+
+    // Missing or unknown flags are usage errors, failures are plain errors
+    assert_eq!(cli::run(&args(&["export", &map, "-o"])), Some(2));
+    assert_eq!(
+        cli::run(&args(&["convert", &fixture("simple.osu"), "--out", &map])),
+        Some(2)
+    );
+    assert_eq!(
+        cli::run(&args(&["validate", &fixture("broken.json")])),
+        Some(1)
+    );
+    assert_eq!(
+        cli::run(&args(&["convert", &fixture("missing.osu")])),
+        Some(1)
+    );
+    assert_eq!(cli::run(&args(&[])), None);
+}